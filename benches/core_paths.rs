@@ -0,0 +1,6 @@
+//! Benchmarks of the engine's core paths, run with `cargo bench --features bench`
+
+use criterion::{criterion_group, criterion_main};
+
+criterion_group!(benches, agent_graph::bench::core_benches);
+criterion_main!(benches);
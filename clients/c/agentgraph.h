@@ -0,0 +1,83 @@
+/*
+ * C interface of the agent_graph library.
+ *
+ * Build the shared library from the repository root with
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * and link against target/release/libagent_graph.{so,dylib,dll}.
+ * Functions returning a pointer return NULL on failure; agentgraph_last_error
+ * then describes the failure. Strings returned by the library are freed with
+ * agentgraph_string_free.
+ */
+#ifndef AGENTGRAPH_H
+#define AGENTGRAPH_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct AgentGraphRuntime AgentGraphRuntime;
+typedef struct AgentGraphGraph AgentGraphGraph;
+typedef struct AgentGraphExecution AgentGraphExecution;
+
+typedef enum AgentGraphStatus {
+    AGENTGRAPH_INVALID = -1,
+    AGENTGRAPH_RUNNING = 0,
+    AGENTGRAPH_COMPLETED = 1,
+    AGENTGRAPH_FAILED = 2,
+} AgentGraphStatus;
+
+/*
+ * Receives each event of an execution as a JSON event envelope
+ * ({"schema_version", "type", "execution_id", "timestamp", "data"}).
+ * The string is only valid during the call. Called on a runtime thread,
+ * one call at a time per graph; must not free the execution.
+ */
+typedef void (*AgentGraphEventCallback)(const char *event_json, void *user_data);
+
+/* Message of the last failure on the calling thread, or NULL. Owned by the library. */
+const char *agentgraph_last_error(void);
+
+void agentgraph_string_free(char *value);
+
+/* Worker threads executions run on; 0 for one per core. */
+AgentGraphRuntime *agentgraph_runtime_new(uint32_t worker_threads);
+void agentgraph_runtime_free(AgentGraphRuntime *runtime);
+
+/* Load and compile a graph declared in a manifest; environment may be NULL. */
+AgentGraphGraph *agentgraph_graph_load(const AgentGraphRuntime *runtime,
+                                       const char *manifest_path,
+                                       const char *environment,
+                                       const char *graph_name);
+void agentgraph_graph_free(AgentGraphGraph *graph);
+
+/*
+ * Start an execution on a JSON object state ("{}" when input_json is NULL).
+ * callback may be NULL; user_data must stay valid until the execution is freed.
+ */
+AgentGraphExecution *agentgraph_execution_start(const AgentGraphGraph *graph,
+                                                const char *input_json,
+                                                AgentGraphEventCallback callback,
+                                                void *user_data);
+AgentGraphStatus agentgraph_execution_status(const AgentGraphExecution *execution);
+
+/* Block until the execution finishes or timeout_ms passes; negative waits forever. */
+AgentGraphStatus agentgraph_execution_wait(const AgentGraphExecution *execution, int64_t timeout_ms);
+
+/*
+ * {"execution_id", "status"} plus "state" once completed or "error" and
+ * "category" once failed. Free with agentgraph_string_free.
+ */
+char *agentgraph_execution_result(const AgentGraphExecution *execution);
+
+/* Stop the execution's callbacks and free it; a running execution keeps running. */
+void agentgraph_execution_free(AgentGraphExecution *execution);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AGENTGRAPH_H */
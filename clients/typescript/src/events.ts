@@ -0,0 +1,248 @@
+// Generated from the execution event JSON Schemas by
+// `cargo run --example generate_event_client`. Do not edit.
+
+export const EVENT_SCHEMA_VERSION = 1
+
+export interface Duration {
+  nanos: number
+  secs: number
+}
+
+/** LLM usage aggregated over one or more completions, e.g. a single agent task */
+export interface LLMUsage {
+  /** Number of completion calls */
+  calls: number
+  /** Completion tokens */
+  completion_tokens: number
+  /** Estimated cost in USD */
+  cost: number
+  /** Time spent waiting on providers */
+  latency: Duration
+  /** Prompt tokens */
+  prompt_tokens: number
+  /** Total tokens */
+  total_tokens: number
+}
+
+/** Node execution context with timing and metadata */
+export interface NodeExecutionContext {
+  /** Execution duration in milliseconds */
+  duration_ms?: number | null
+  /** End time of execution (if completed) */
+  end_time?: string | null
+  /** Error message if execution failed */
+  error_message?: string | null
+  /** Unique execution ID */
+  execution_id: string
+  /** Custom execution metadata */
+  metadata: Record<string, unknown>
+  /** Node ID being executed */
+  node_id: string
+  /** Start time of execution */
+  start_time: string
+  /** Whether the execution was successful */
+  success?: boolean | null
+}
+
+/** Graph execution started */
+export interface GraphStartedData {
+  /** Entry point node */
+  entry_point: string
+  /** Unique execution ID */
+  execution_id: string
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Graph execution completed */
+export interface GraphCompletedData {
+  /** Total execution time in milliseconds */
+  duration_ms: number
+  /** Execution ID */
+  execution_id: string
+  /** Final node */
+  final_node?: string | null
+  /** Whether execution was successful */
+  success: boolean
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Node execution started */
+export interface NodeStartedData {
+  /** Node execution context */
+  context: NodeExecutionContext
+  /** Execution ID */
+  execution_id: string
+  /** Node ID */
+  node_id: string
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Node execution completed */
+export interface NodeCompletedData {
+  /** Candidates an aggregating node chose from, such as a debate's answers */
+  candidates?: unknown
+  /** Execution duration in milliseconds */
+  duration_ms: number
+  /** Error message if failed */
+  error?: string | null
+  /** Execution ID */
+  execution_id: string
+  /** LLM usage the node reported, if it called a model */
+  llm_usage?: LLMUsage | null
+  /** Node ID */
+  node_id: string
+  /** Whether execution was successful */
+  success: boolean
+  /** Timestamp */
+  timestamp: string
+}
+
+/** State updated */
+export interface StateUpdatedData {
+  /** Execution ID */
+  execution_id: string
+  /** Node that updated the state */
+  node_id: string
+  /** State snapshot ID (if checkpointing is enabled) */
+  snapshot_id?: string | null
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Edge traversed */
+export interface EdgeTraversedData {
+  /** Edge metadata */
+  edge_metadata?: unknown
+  /** Execution ID */
+  execution_id: string
+  /** Source node */
+  from_node: string
+  /** Timestamp */
+  timestamp: string
+  /** Target node */
+  to_node: string
+}
+
+/** Parallel execution started */
+export interface ParallelStartedData {
+  /** Execution ID */
+  execution_id: string
+  /** Nodes being executed in parallel */
+  node_ids: string[]
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Parallel execution completed */
+export interface ParallelCompletedData {
+  /** Total duration in milliseconds */
+  duration_ms: number
+  /** Execution ID */
+  execution_id: string
+  /** Results for each node */
+  results: [string, boolean][]
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Error occurred */
+export interface ErrorData {
+  /** Error category */
+  category: string
+  /** Error message */
+  error: string
+  /** Execution ID */
+  execution_id: string
+  /** Node where error occurred (if applicable) */
+  node_id?: string | null
+  /** Timestamp */
+  timestamp: string
+}
+
+/** Incremental output from a streaming tool */
+export interface ToolOutputChunkData {
+  /** Chunk payload */
+  data: unknown
+  /** Execution ID */
+  execution_id: string
+  /** Node running the tool */
+  node_id: string
+  /** Position of the chunk within the tool's output */
+  sequence: number
+  /** Timestamp */
+  timestamp: string
+  /** Tool that produced the chunk */
+  tool_id: string
+}
+
+/** Custom event */
+export interface CustomData {
+  /** Event data */
+  data: unknown
+  /** Event type */
+  event_type: string
+  /** Execution ID */
+  execution_id: string
+  /** Timestamp */
+  timestamp: string
+}
+
+interface Envelope<T extends string, D> {
+  schema_version: number
+  type: T
+  execution_id: string
+  timestamp: string
+  data: D
+}
+
+export type ExecutionEvent =
+  | Envelope<'graph_started', GraphStartedData>
+  | Envelope<'graph_completed', GraphCompletedData>
+  | Envelope<'node_started', NodeStartedData>
+  | Envelope<'node_completed', NodeCompletedData>
+  | Envelope<'state_updated', StateUpdatedData>
+  | Envelope<'edge_traversed', EdgeTraversedData>
+  | Envelope<'parallel_started', ParallelStartedData>
+  | Envelope<'parallel_completed', ParallelCompletedData>
+  | Envelope<'error', ErrorData>
+  | Envelope<'tool_output_chunk', ToolOutputChunkData>
+  | Envelope<'custom', CustomData>
+
+export type ExecutionEventType = ExecutionEvent['type']
+
+export const EXECUTION_EVENT_TYPES: readonly ExecutionEventType[] = [
+  'graph_started',
+  'graph_completed',
+  'node_started',
+  'node_completed',
+  'state_updated',
+  'edge_traversed',
+  'parallel_started',
+  'parallel_completed',
+  'error',
+  'tool_output_chunk',
+  'custom',
+]
+
+/**
+ * Parse an event received from AgentGraph.
+ *
+ * Returns null for event types this client does not know yet, which newer
+ * servers may send without a version bump. Throws for events written in a
+ * newer schema version, whose fields may have changed.
+ */
+export function parseExecutionEvent(message: string | unknown): ExecutionEvent | null {
+  const event = (typeof message === 'string' ? JSON.parse(message) : message) as ExecutionEvent
+  if (typeof event !== 'object' || event === null || typeof event.schema_version !== 'number') {
+    throw new Error('Not an AgentGraph execution event')
+  }
+  if (event.schema_version > EVENT_SCHEMA_VERSION) {
+    throw new Error(
+      `Event schema version ${event.schema_version} is newer than the supported version ${EVENT_SCHEMA_VERSION}`
+    )
+  }
+  return EXECUTION_EVENT_TYPES.includes(event.type) ? event : null
+}
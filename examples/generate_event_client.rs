@@ -0,0 +1,20 @@
+//! Regenerates the TypeScript event client and the event JSON Schemas.
+//!
+//! Run after changing `ExecutionEvent`:
+//!
+//! ```text
+//! cargo run --example generate_event_client [output dir]
+//! ```
+
+use agent_graph::streaming::schema::write_typescript_client;
+use std::path::PathBuf;
+
+fn main() -> agent_graph::GraphResult<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("clients/typescript"));
+    write_typescript_client(&dir)?;
+    println!("Wrote the event client to {}", dir.display());
+    Ok(())
+}
@@ -0,0 +1,235 @@
+// Importance scoring for agent memory
+// Ranks memories by recency, frequency and salience, and consolidates memory in the background
+
+#![allow(missing_docs)]
+
+use super::memory::{AgentMemory, MemoryEntry, MemoryError};
+use crate::llm::{CompletionRequest, LLMManager, Message};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// How memory entries are scored for retrieval and pruning
+///
+/// A score is the weighted mean of three signals in `0.0..=1.0`:
+/// recency decays exponentially with the time since the entry was last
+/// accessed, frequency grows with the access count, and salience is the
+/// entry's importance (rated by an LLM when a [`SalienceRater`] is set).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportanceWeights {
+    pub recency_weight: f32,
+    pub frequency_weight: f32,
+    pub salience_weight: f32,
+    /// Time after which the recency signal has halved
+    pub half_life: Duration,
+    /// Access count at which the frequency signal is saturated
+    pub frequency_saturation: u32,
+}
+
+impl Default for ImportanceWeights {
+    fn default() -> Self {
+        Self {
+            recency_weight: 0.3,
+            frequency_weight: 0.2,
+            salience_weight: 0.5,
+            half_life: Duration::from_secs(86400), // 1 day
+            frequency_saturation: 10,
+        }
+    }
+}
+
+impl ImportanceWeights {
+    /// Recency signal, halving every `half_life`
+    pub fn recency(&self, entry: &MemoryEntry, now: SystemTime) -> f32 {
+        let age = now.duration_since(entry.last_accessed).unwrap_or(Duration::ZERO);
+        let half_life = self.half_life.as_secs_f32().max(f32::EPSILON);
+        0.5f32.powf(age.as_secs_f32() / half_life)
+    }
+
+    /// Frequency signal, logarithmic in the access count
+    pub fn frequency(&self, entry: &MemoryEntry) -> f32 {
+        let saturation = self.frequency_saturation.max(1) as f32;
+        ((1.0 + entry.access_count as f32).ln() / (1.0 + saturation).ln()).min(1.0)
+    }
+
+    /// Combined score in `0.0..=1.0`
+    pub fn score(&self, entry: &MemoryEntry, now: SystemTime) -> f32 {
+        let total = self.recency_weight + self.frequency_weight + self.salience_weight;
+        if total <= 0.0 {
+            return entry.importance;
+        }
+        (self.recency_weight * self.recency(entry, now)
+            + self.frequency_weight * self.frequency(entry)
+            + self.salience_weight * entry.importance)
+            / total
+    }
+}
+
+/// Rates how worth remembering a piece of content is
+#[async_trait]
+pub trait SalienceRater: Send + Sync + std::fmt::Debug {
+    /// Salience in `0.0..=1.0`
+    async fn rate(&self, content: &str) -> Result<f32, MemoryError>;
+}
+
+/// Salience rater asking an LLM for a 0-10 rating
+#[derive(Debug)]
+pub struct LlmSalienceRater {
+    llm_manager: Arc<LLMManager>,
+    provider: String,
+    model: String,
+}
+
+impl LlmSalienceRater {
+    pub fn new(llm_manager: Arc<LLMManager>, provider: &str, model: &str) -> Self {
+        Self {
+            llm_manager,
+            provider: provider.to_string(),
+            model: model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl SalienceRater for LlmSalienceRater {
+    async fn rate(&self, content: &str) -> Result<f32, MemoryError> {
+        let request = CompletionRequest {
+            model: self.model.clone(),
+            messages: vec![
+                Message::system(
+                    "Rate how important the following memory is for an assistant to recall in future \
+                     conversations, from 0 (trivial) to 10 (essential). Reply with the number only."
+                        .to_string(),
+                ),
+                Message::user(content.to_string()),
+            ],
+            max_tokens: Some(5),
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+
+        let response = self.llm_manager
+            .complete_with_provider(&self.provider, request)
+            .await
+            .map_err(|e| MemoryError::SystemError {
+                message: format!("Salience rating failed: {}", e),
+            })?;
+        let reply = response.choices.first().map(|choice| choice.message.content.as_str()).unwrap_or_default();
+
+        parse_rating(reply).ok_or_else(|| MemoryError::SystemError {
+            message: format!("Salience rating '{}' is not a number", reply.trim()),
+        })
+    }
+}
+
+/// First number in a reply, scaled from 0-10 to 0-1
+fn parse_rating(reply: &str) -> Option<f32> {
+    let start = reply.find(|c: char| c.is_ascii_digit())?;
+    let number: String = reply[start..].chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    number.trim_end_matches('.').parse::<f32>().ok().map(|rating| (rating / 10.0).clamp(0.0, 1.0))
+}
+
+/// Result of one consolidation pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsolidationReport {
+    /// Entries dropped for scoring below the pruning threshold
+    pub below_threshold: usize,
+    /// Entries dropped to fit the memory budget
+    pub over_budget: usize,
+    /// Entries kept
+    pub remaining: usize,
+}
+
+impl ConsolidationReport {
+    pub fn pruned(&self) -> usize {
+        self.below_threshold + self.over_budget
+    }
+}
+
+/// Background job consolidating a shared agent memory on an interval
+#[derive(Debug)]
+pub struct MemoryConsolidator {
+    task: JoinHandle<()>,
+}
+
+impl MemoryConsolidator {
+    /// Consolidate `memory` every `interval`
+    pub fn spawn(memory: Arc<Mutex<AgentMemory>>, interval: Duration) -> Self {
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match memory.lock().await.consolidate().await {
+                    Ok(report) if report.pruned() > 0 => {
+                        tracing::debug!("Memory consolidation pruned {} entries, {} remain", report.pruned(), report.remaining);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Memory consolidation failed: {}", e),
+                }
+            }
+        });
+        Self { task }
+    }
+
+    /// Stop consolidating
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for MemoryConsolidator {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::memory::MemoryEntryType;
+
+    #[test]
+    fn test_recency_decays_exponentially() {
+        let weights = ImportanceWeights::default();
+        let now = SystemTime::now();
+        let mut entry = MemoryEntry::new(MemoryEntryType::Interaction, "hello".to_string());
+        entry.last_accessed = now;
+        assert!((weights.recency(&entry, now) - 1.0).abs() < 1e-6);
+
+        entry.last_accessed = now - weights.half_life;
+        assert!((weights.recency(&entry, now) - 0.5).abs() < 1e-3);
+
+        entry.last_accessed = now - weights.half_life * 2;
+        assert!((weights.recency(&entry, now) - 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_score_combines_signals() {
+        let weights = ImportanceWeights::default();
+        let now = SystemTime::now();
+
+        let mut fresh = MemoryEntry::new(MemoryEntryType::Task, "fresh".to_string()).with_importance(0.5);
+        fresh.last_accessed = now;
+        let mut stale = fresh.clone();
+        stale.last_accessed = now - Duration::from_secs(86400 * 7);
+        let mut frequent = stale.clone();
+        frequent.access_count = 10;
+
+        assert!(weights.score(&fresh, now) > weights.score(&stale, now));
+        assert!(weights.score(&frequent, now) > weights.score(&stale, now));
+        assert!(weights.score(&fresh, now) <= 1.0);
+    }
+
+    #[test]
+    fn test_parse_rating() {
+        assert_eq!(parse_rating("7"), Some(0.7));
+        assert_eq!(parse_rating("Rating: 8.5/10"), Some(0.85));
+        assert_eq!(parse_rating("12"), Some(1.0));
+        assert_eq!(parse_rating("none"), None);
+    }
+}
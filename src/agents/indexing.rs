@@ -0,0 +1,671 @@
+// Incremental knowledge base indexing for AgentGraph
+// Keeps a vector collection in step with a directory, S3 prefix or sitemap by re-embedding only documents that changed
+
+#![allow(missing_docs)]
+
+use super::memory::MemoryError;
+use super::vector::{Embedder, VectorStore};
+use crate::sha256_hex;
+use crate::state::artifacts::{S3ArtifactStore, S3Config};
+use crate::tools::common::document::{parse_document, ChunkOptions, DocumentFormat};
+use crate::tools::common::egress::{HttpGuard, HttpGuardConfig};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// A document a source currently holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceEntry {
+    /// Stable ID: relative path, object key or URL
+    pub id: String,
+    /// Cheap change marker such as an mtime, ETag or `lastmod`; an unchanged
+    /// version skips fetching, a changed one is confirmed by content hash
+    pub version: Option<String>,
+}
+
+/// Where knowledge base documents come from
+#[async_trait]
+pub trait DocumentSource: Send + Sync + std::fmt::Debug {
+    /// Short description for logs, e.g. `dir:/srv/handbook`
+    fn describe(&self) -> String;
+
+    /// Every document the source currently holds
+    async fn list(&self) -> Result<Vec<SourceEntry>, MemoryError>;
+
+    /// Content of one document
+    async fn fetch(&self, id: &str) -> Result<Vec<u8>, MemoryError>;
+}
+
+/// Files under a directory whose format can be parsed
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    root: PathBuf,
+    extensions: Option<Vec<String>>,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), extensions: None }
+    }
+
+    /// Only index files with these extensions
+    pub fn with_extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = Some(extensions.iter().map(|e| e.trim_start_matches('.').to_ascii_lowercase()).collect());
+        self
+    }
+
+    fn wanted(&self, path: &Path) -> bool {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        match &self.extensions {
+            Some(extensions) => extensions.contains(&extension),
+            None => DocumentFormat::from_extension(&extension).is_some(),
+        }
+    }
+
+    fn walk(&self, dir: &Path, entries: &mut Vec<SourceEntry>) -> std::io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                self.walk(&path, entries)?;
+            } else if metadata.is_file() && self.wanted(&path) {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |since| since.as_nanos());
+                let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+                entries.push(SourceEntry {
+                    id: relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"),
+                    version: Some(format!("{}-{}", metadata.len(), modified)),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DocumentSource for DirectorySource {
+    fn describe(&self) -> String {
+        format!("dir:{}", self.root.display())
+    }
+
+    async fn list(&self) -> Result<Vec<SourceEntry>, MemoryError> {
+        let source = self.clone();
+        crate::graph::tuning::spawn_blocking(move || {
+            let mut entries = Vec::new();
+            source.walk(&source.root, &mut entries).map(|_| entries)
+        })
+        .await
+        .map_err(|e| MemoryError::SystemError { message: e.to_string() })?
+        .map_err(|e| MemoryError::RetrievalError {
+            message: format!("Cannot list {}: {}", self.root.display(), e),
+        })
+    }
+
+    async fn fetch(&self, id: &str) -> Result<Vec<u8>, MemoryError> {
+        if id.split('/').any(|segment| segment == "..") {
+            return Err(MemoryError::RetrievalError { message: format!("Invalid document ID '{}'", id) });
+        }
+        tokio::fs::read(self.root.join(id)).await.map_err(|e| MemoryError::RetrievalError {
+            message: format!("Cannot read {}: {}", id, e),
+        })
+    }
+}
+
+/// Objects under the prefix of an S3 bucket or S3-compatible store
+#[derive(Debug, Clone)]
+pub struct S3Source {
+    store: S3ArtifactStore,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Source {
+    /// Index the objects under `config.prefix`
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            bucket: config.bucket.clone(),
+            prefix: config.prefix.trim_start_matches('/').to_string(),
+            store: S3ArtifactStore::new(config),
+        }
+    }
+}
+
+#[async_trait]
+impl DocumentSource for S3Source {
+    fn describe(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.prefix)
+    }
+
+    async fn list(&self) -> Result<Vec<SourceEntry>, MemoryError> {
+        let objects = self.store.list_objects(&self.prefix).await.map_err(|e| MemoryError::RetrievalError {
+            message: e.to_string(),
+        })?;
+        Ok(objects
+            .into_iter()
+            .filter(|(key, _)| !key.ends_with('/'))
+            .map(|(key, etag)| SourceEntry { id: key, version: Some(etag.trim_matches('"').to_string()) })
+            .collect())
+    }
+
+    async fn fetch(&self, id: &str) -> Result<Vec<u8>, MemoryError> {
+        self.store
+            .get_object(id)
+            .await
+            .map_err(|e| MemoryError::RetrievalError { message: e.to_string() })?
+            .ok_or_else(|| MemoryError::RetrievalError { message: format!("Object {} no longer exists", id) })
+    }
+}
+
+/// Deepest chain of nested sitemap indexes followed
+const MAX_SITEMAP_DEPTH: usize = 5;
+
+/// Most sitemaps fetched in one listing
+const MAX_SITEMAPS: usize = 1_000;
+
+/// Most pages one listing may hold; the sitemap protocol allows 50,000 per file
+const MAX_SITEMAP_PAGES: usize = 500_000;
+
+/// Pages listed in a sitemap, following nested sitemap indexes
+///
+/// Sitemaps and pages are fetched through an [`HttpGuard`], so a sitemap
+/// cannot point the indexer at internal hosts.
+#[derive(Debug, Clone)]
+pub struct SitemapSource {
+    url: String,
+    guard: Arc<HttpGuard>,
+}
+
+impl SitemapSource {
+    pub fn new(url: impl Into<String>) -> Self {
+        // Documents are larger than the tool default allows
+        let config = HttpGuardConfig { max_response_bytes: 50 * 1024 * 1024, ..Default::default() };
+        Self { url: url.into(), guard: Arc::new(HttpGuard::new(config)) }
+    }
+
+    /// Fetch through `guard`, e.g. to allow only the documentation host
+    pub fn with_guard(mut self, guard: HttpGuard) -> Self {
+        self.guard = Arc::new(guard);
+        self
+    }
+
+    async fn get(&self, url: &str) -> Result<Vec<u8>, MemoryError> {
+        let failed = |message: String| MemoryError::RetrievalError {
+            message: format!("Cannot fetch {}: {}", url, message),
+        };
+        let (status, body) = self.guard.get_bytes(url, None).await.map_err(|e| failed(e.to_string()))?;
+        if !(200..300).contains(&status) {
+            return Err(failed(format!("HTTP {}", status)));
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl DocumentSource for SitemapSource {
+    fn describe(&self) -> String {
+        format!("sitemap:{}", self.url)
+    }
+
+    async fn list(&self) -> Result<Vec<SourceEntry>, MemoryError> {
+        let too_large = |what: String| MemoryError::RetrievalError {
+            message: format!("Sitemap {} {}", self.url, what),
+        };
+        let mut entries = Vec::new();
+        let mut pending = vec![(self.url.clone(), 0)];
+        let mut seen = std::collections::HashSet::new();
+        while let Some((url, depth)) = pending.pop() {
+            if !seen.insert(url.clone()) {
+                continue;
+            }
+            if seen.len() > MAX_SITEMAPS {
+                return Err(too_large(format!("links more than {} sitemaps", MAX_SITEMAPS)));
+            }
+            let (pages, sitemaps) = parse_sitemap(&String::from_utf8_lossy(&self.get(&url).await?));
+            entries.extend(pages);
+            if entries.len() > MAX_SITEMAP_PAGES {
+                return Err(too_large(format!("lists more than {} pages", MAX_SITEMAP_PAGES)));
+            }
+            if !sitemaps.is_empty() && depth >= MAX_SITEMAP_DEPTH {
+                return Err(too_large(format!("nests sitemap indexes more than {} deep", MAX_SITEMAP_DEPTH)));
+            }
+            pending.extend(sitemaps.into_iter().map(|sitemap| (sitemap, depth + 1)));
+        }
+        Ok(entries)
+    }
+
+    async fn fetch(&self, id: &str) -> Result<Vec<u8>, MemoryError> {
+        self.get(id).await
+    }
+}
+
+/// Pages and nested sitemaps listed in a sitemap or sitemap index
+fn parse_sitemap(xml: &str) -> (Vec<SourceEntry>, Vec<String>) {
+    let text = |block: &str, tag: &str| -> Option<String> {
+        let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+        let start = block.find(&open)? + open.len();
+        let end = start + block[start..].find(&close)?;
+        let value = block[start..end].trim().trim_start_matches("<![CDATA[").trim_end_matches("]]>").replace("&amp;", "&");
+        (!value.is_empty()).then_some(value)
+    };
+    let blocks = |tag: &str| -> Vec<String> {
+        xml.split(&format!("<{}>", tag)).skip(1).filter_map(|rest| rest.split(&format!("</{}>", tag)).next().map(str::to_string)).collect()
+    };
+    let pages = blocks("url")
+        .iter()
+        .filter_map(|block| Some(SourceEntry { id: text(block, "loc")?, version: text(block, "lastmod") }))
+        .collect();
+    let sitemaps = blocks("sitemap").iter().filter_map(|block| text(block, "loc")).collect();
+    (pages, sitemaps)
+}
+
+/// What the index knows about one indexed document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedDocument {
+    /// Source version seen at the last sync
+    pub version: Option<String>,
+    /// Hex SHA-256 of the content that was embedded
+    pub content_hash: String,
+    /// IDs of the document's records in the collection
+    pub chunk_ids: Vec<String>,
+    pub indexed_at: DateTime<Utc>,
+}
+
+/// Outcome of one sync
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Documents whose version or content did not change
+    pub unchanged: usize,
+    /// Documents that could not be indexed, with the reason; their previous chunks stay
+    pub failed: Vec<(String, String)>,
+    pub chunks_embedded: usize,
+    pub duration_ms: u64,
+}
+
+impl SyncReport {
+    pub fn changed(&self) -> bool {
+        !(self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty())
+    }
+}
+
+/// How current an index is
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexFreshness {
+    pub collection: String,
+    pub source: String,
+    pub documents: usize,
+    pub chunks: usize,
+    /// When the last sync finished
+    pub last_sync_at: Option<DateTime<Utc>>,
+    pub last_sync_ms: u64,
+    /// When a sync last added, updated or removed a document
+    pub last_change_at: Option<DateTime<Utc>>,
+    /// Documents the last sync failed to index
+    pub failing_documents: usize,
+    /// Error that stopped the last sync, if it did not complete
+    pub last_error: Option<String>,
+    pub syncs: u64,
+}
+
+impl IndexFreshness {
+    /// Time since the last completed sync
+    pub fn age(&self) -> Option<Duration> {
+        self.last_sync_at.map(|at| (Utc::now() - at).to_std().unwrap_or_default())
+    }
+
+    /// Gauges named `index.<collection>.<metric>`, for
+    /// [`PerformanceMetrics::add_custom_metric`](crate::enterprise::monitoring::PerformanceMetrics::add_custom_metric)
+    pub fn metrics(&self) -> HashMap<String, f64> {
+        let name = |metric: &str| format!("index.{}.{}", self.collection, metric);
+        let mut metrics = HashMap::from([
+            (name("documents"), self.documents as f64),
+            (name("chunks"), self.chunks as f64),
+            (name("failing_documents"), self.failing_documents as f64),
+            (name("last_sync_ms"), self.last_sync_ms as f64),
+            (name("sync_errors"), if self.last_error.is_some() { 1.0 } else { 0.0 }),
+        ]);
+        if let Some(age) = self.age() {
+            metrics.insert(name("age_seconds"), age.as_secs_f64());
+        }
+        if let Some(at) = self.last_change_at {
+            metrics.insert(name("seconds_since_change"), (Utc::now() - at).num_milliseconds() as f64 / 1000.0);
+        }
+        metrics
+    }
+}
+
+/// Keeps a vector collection in step with a document source
+///
+/// Each [`sync`](Self::sync) lists the source and compares every document
+/// with what was indexed before: an unchanged version is skipped without
+/// fetching, and fetched content whose hash matches is not re-embedded. New
+/// and changed documents are parsed, chunked and embedded; documents gone
+/// from the source have their chunks deleted. With a manifest file the index
+/// survives restarts without a full reindex.
+#[derive(Debug)]
+pub struct IndexingService {
+    source: Arc<dyn DocumentSource>,
+    store: Arc<dyn VectorStore>,
+    embedder: Arc<dyn Embedder>,
+    collection: String,
+    chunking: ChunkOptions,
+    manifest_path: Option<PathBuf>,
+    documents: RwLock<HashMap<String, IndexedDocument>>,
+    freshness: RwLock<IndexFreshness>,
+    /// Keeps syncs from overlapping
+    sync_lock: tokio::sync::Mutex<()>,
+}
+
+impl IndexingService {
+    pub fn new(
+        source: Arc<dyn DocumentSource>,
+        store: Arc<dyn VectorStore>,
+        embedder: Arc<dyn Embedder>,
+        collection: impl Into<String>,
+    ) -> Self {
+        let collection = collection.into();
+        Self {
+            freshness: RwLock::new(IndexFreshness {
+                collection: collection.clone(),
+                source: source.describe(),
+                documents: 0,
+                chunks: 0,
+                last_sync_at: None,
+                last_sync_ms: 0,
+                last_change_at: None,
+                failing_documents: 0,
+                last_error: None,
+                syncs: 0,
+            }),
+            source,
+            store,
+            embedder,
+            collection,
+            chunking: ChunkOptions::default(),
+            manifest_path: None,
+            documents: RwLock::new(HashMap::new()),
+            sync_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn with_chunking(mut self, chunking: ChunkOptions) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// Persist what was indexed to `path`, loading it if it exists
+    ///
+    /// The manifest must belong to the same collection: it records which
+    /// chunks are already in the store.
+    pub fn with_manifest(mut self, path: impl Into<PathBuf>) -> Result<Self, MemoryError> {
+        let path = path.into();
+        if path.exists() {
+            let manifest = std::fs::read(&path).map_err(|e| MemoryError::StorageError {
+                message: format!("Cannot read index manifest {}: {}", path.display(), e),
+            })?;
+            let documents: HashMap<String, IndexedDocument> = serde_json::from_slice(&manifest).map_err(|e| MemoryError::StorageError {
+                message: format!("Invalid index manifest {}: {}", path.display(), e),
+            })?;
+            self.set_counts(&documents);
+            *self.documents.get_mut().unwrap_or_else(|e| e.into_inner()) = documents;
+        }
+        self.manifest_path = Some(path);
+        Ok(self)
+    }
+
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// What is currently indexed, by document ID
+    pub fn documents(&self) -> HashMap<String, IndexedDocument> {
+        self.documents.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn freshness(&self) -> IndexFreshness {
+        self.freshness.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn set_counts(&self, documents: &HashMap<String, IndexedDocument>) {
+        let mut freshness = self.freshness.write().unwrap_or_else(|e| e.into_inner());
+        freshness.documents = documents.len();
+        freshness.chunks = documents.values().map(|document| document.chunk_ids.len()).sum();
+    }
+
+    /// Bring the collection up to date with the source
+    ///
+    /// Failing to list the source fails the sync; a document that cannot be
+    /// fetched, parsed or embedded is reported and keeps its previous chunks.
+    pub async fn sync(&self) -> Result<SyncReport, MemoryError> {
+        let _guard = self.sync_lock.lock().await;
+        let started = Instant::now();
+        let result = self.sync_changes().await;
+        let finished_at = Utc::now();
+
+        let mut freshness = self.freshness.write().unwrap_or_else(|e| e.into_inner());
+        freshness.syncs += 1;
+        match result {
+            Ok(mut report) => {
+                report.duration_ms = started.elapsed().as_millis() as u64;
+                freshness.last_sync_at = Some(finished_at);
+                freshness.last_sync_ms = report.duration_ms;
+                freshness.failing_documents = report.failed.len();
+                freshness.last_error = None;
+                if report.changed() {
+                    freshness.last_change_at = Some(finished_at);
+                }
+                tracing::info!(
+                    collection = %self.collection,
+                    added = report.added.len(),
+                    updated = report.updated.len(),
+                    removed = report.removed.len(),
+                    failed = report.failed.len(),
+                    "Index sync finished"
+                );
+                Ok(report)
+            }
+            Err(e) => {
+                freshness.last_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    async fn sync_changes(&self) -> Result<SyncReport, MemoryError> {
+        let entries = self.source.list().await?;
+        let mut documents = self.documents();
+        let mut report = SyncReport::default();
+
+        let listed: std::collections::HashSet<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        let gone: Vec<String> = documents.keys().filter(|id| !listed.contains(id.as_str())).cloned().collect();
+        for id in gone {
+            if let Some(document) = documents.get(&id) {
+                self.store.delete(&self.collection, &document.chunk_ids).await?;
+            }
+            documents.remove(&id);
+            report.removed.push(id);
+        }
+
+        for entry in &entries {
+            let known = documents.get(&entry.id);
+            if entry.version.is_some() && known.is_some_and(|known| known.version == entry.version) {
+                report.unchanged += 1;
+                continue;
+            }
+            let indexed = match self.index_document(entry, known).await {
+                Ok(indexed) => indexed,
+                Err(e) => {
+                    tracing::warn!(collection = %self.collection, document = %entry.id, "Failed to index document: {}", e);
+                    report.failed.push((entry.id.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            match indexed {
+                Indexed::Unchanged(document) => {
+                    report.unchanged += 1;
+                    documents.insert(entry.id.clone(), document);
+                }
+                Indexed::Embedded(document) => {
+                    report.chunks_embedded += document.chunk_ids.len();
+                    if known.is_some() {
+                        report.updated.push(entry.id.clone());
+                    } else {
+                        report.added.push(entry.id.clone());
+                    }
+                    documents.insert(entry.id.clone(), document);
+                }
+            }
+        }
+
+        self.set_counts(&documents);
+        *self.documents.write().unwrap_or_else(|e| e.into_inner()) = documents;
+        self.save_manifest().await?;
+        Ok(report)
+    }
+
+    async fn index_document(&self, entry: &SourceEntry, known: Option<&IndexedDocument>) -> Result<Indexed, MemoryError> {
+        let bytes = self.source.fetch(&entry.id).await?;
+        let content_hash = sha256_hex(&bytes);
+        if let Some(known) = known.filter(|known| known.content_hash == content_hash) {
+            return Ok(Indexed::Unchanged(IndexedDocument { version: entry.version.clone(), ..known.clone() }));
+        }
+
+        let format = Path::new(entry.id.split(['?', '#']).next().unwrap_or_default())
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(DocumentFormat::from_extension)
+            .or_else(|| DocumentFormat::sniff(&bytes))
+            .ok_or_else(|| MemoryError::ConfigurationError { message: "unknown document format".to_string() })?;
+        let (id, chunking) = (entry.id.clone(), self.chunking.clone());
+        let chunks = crate::graph::tuning::spawn_blocking(move || parse_document(&bytes, format).map(|document| document.chunks(&id, &chunking)))
+            .await
+            .map_err(|e| MemoryError::SystemError { message: e.to_string() })?
+            .map_err(|e| MemoryError::StorageError { message: e.to_string() })?;
+
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+        let embeddings = if texts.is_empty() { Vec::new() } else { self.embedder.embed(&texts).await? };
+        if embeddings.len() != chunks.len() {
+            return Err(MemoryError::StorageError {
+                message: format!("Embedder returned {} vectors for {} chunks", embeddings.len(), chunks.len()),
+            });
+        }
+        let indexed_at = Utc::now();
+        let chunk_ids: Vec<String> = chunks.iter().map(|chunk| chunk.id.clone()).collect();
+        let records = chunks
+            .into_iter()
+            .zip(embeddings)
+            .map(|(chunk, embedding)| {
+                chunk
+                    .into_record(embedding)
+                    .with_metadata("content_hash", &content_hash)
+                    .with_metadata("indexed_at", indexed_at)
+            })
+            .collect();
+        self.store.upsert(&self.collection, records).await?;
+
+        // Chunk IDs are positional, so only the tail of a shrunken document is left over
+        if let Some(known) = known {
+            let stale: Vec<String> = known.chunk_ids.iter().filter(|id| !chunk_ids.contains(id)).cloned().collect();
+            if !stale.is_empty() {
+                self.store.delete(&self.collection, &stale).await?;
+            }
+        }
+        Ok(Indexed::Embedded(IndexedDocument { version: entry.version.clone(), content_hash, chunk_ids, indexed_at }))
+    }
+
+    async fn save_manifest(&self) -> Result<(), MemoryError> {
+        let Some(path) = &self.manifest_path else {
+            return Ok(());
+        };
+        let manifest = serde_json::to_vec_pretty(&self.documents()).map_err(|e| MemoryError::StorageError { message: e.to_string() })?;
+        tokio::fs::write(path, manifest).await.map_err(|e| MemoryError::StorageError {
+            message: format!("Cannot write index manifest {}: {}", path.display(), e),
+        })
+    }
+
+    /// Sync every `interval` until the returned handle is aborted
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.sync().await {
+                    tracing::warn!(collection = %self.collection, source = %self.source.describe(), "Index sync failed: {}", e);
+                }
+            }
+        })
+    }
+}
+
+enum Indexed {
+    /// Content matched the indexed hash
+    Unchanged(IndexedDocument),
+    Embedded(IndexedDocument),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::vector::{HashingEmbedder, InMemoryVectorStore};
+
+    #[tokio::test]
+    async fn test_sync_reindexes_only_changes() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("leave.md"), "# Leave\n\nTwenty days of paid leave.").unwrap();
+        std::fs::write(root.path().join("travel.txt"), "Book travel through the portal.").unwrap();
+        std::fs::write(root.path().join("logo.svg"), "<svg/>").unwrap();
+        let manifest = root.path().join(".index.json");
+        let store = Arc::new(InMemoryVectorStore::new());
+        let service = |store: Arc<InMemoryVectorStore>| {
+            IndexingService::new(Arc::new(DirectorySource::new(root.path())), store, Arc::new(HashingEmbedder::new(64)), "handbook")
+                .with_manifest(&manifest)
+                .unwrap()
+        };
+
+        let indexer = service(store.clone());
+        let report = indexer.sync().await.unwrap();
+        assert_eq!(report.added.len(), 2);
+        assert_eq!(store.count("handbook"), 2);
+
+        std::fs::write(root.path().join("travel.txt"), "Book travel through the portal.\n\nEconomy class only.").unwrap();
+        std::fs::remove_file(root.path().join("leave.md")).unwrap();
+        // A fresh service picks up where the manifest left off
+        let indexer = service(store.clone());
+        let report = indexer.sync().await.unwrap();
+        assert_eq!((report.updated.clone(), report.removed.clone()), (vec!["travel.txt".to_string()], vec!["leave.md".to_string()]));
+        assert_eq!(store.count("handbook"), 1);
+
+        let report = indexer.sync().await.unwrap();
+        assert!(!report.changed());
+        assert_eq!(report.unchanged, 1);
+        let freshness = indexer.freshness();
+        assert_eq!((freshness.documents, freshness.syncs), (1, 2));
+        assert_eq!(freshness.metrics()["index.handbook.documents"], 1.0);
+    }
+
+    #[test]
+    fn test_parse_sitemap() {
+        let xml = r#"<urlset><url><loc>https://docs.example.com/a?x=1&amp;y=2</loc><lastmod>2024-05-01</lastmod></url><url><loc>https://docs.example.com/b</loc></url></urlset>
+            <sitemapindex><sitemap><loc>https://docs.example.com/more.xml</loc></sitemap></sitemapindex>"#;
+        let (pages, sitemaps) = parse_sitemap(xml);
+        assert_eq!(pages[0], SourceEntry { id: "https://docs.example.com/a?x=1&y=2".to_string(), version: Some("2024-05-01".to_string()) });
+        assert_eq!(pages[1].version, None);
+        assert_eq!(sitemaps, ["https://docs.example.com/more.xml"]);
+    }
+}
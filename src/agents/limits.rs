@@ -0,0 +1,190 @@
+// Conversation limits for agents
+// Bounds the turns and tokens an agent's conversation history carries into each prompt
+
+use crate::llm::{Message, MessageRole, ModelProfile};
+use serde::{Deserialize, Serialize};
+
+/// What happens when a conversation outgrows its limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowStrategy {
+    /// Drop the oldest turns
+    #[default]
+    Truncate,
+    /// Replace the oldest turns with a summary written by the agent's model
+    Summarize,
+    /// Refuse the task
+    Error,
+}
+
+/// Limits on an agent's conversation history
+///
+/// A turn starts with a user message and includes the replies and tool
+/// results that follow it. Tokens are estimated from message length, and an
+/// agent also caps them to its model's context window when the model has a profile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConversationLimits {
+    /// Most turns kept, including the current one
+    pub max_turns: Option<u32>,
+    /// Most estimated tokens kept
+    pub max_tokens: Option<u32>,
+    /// What to do when either limit is exceeded
+    pub overflow: OverflowStrategy,
+    /// Turns kept verbatim when summarizing
+    pub keep_recent_turns: Option<u32>,
+}
+
+/// Size of a conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversationUsage {
+    pub turns: u32,
+    pub tokens: u32,
+}
+
+/// Rough token count of a message, about four characters per token plus framing
+pub fn estimate_tokens(message: &Message) -> u32 {
+    (message.content.chars().count() as u32).div_ceil(4) + 4
+}
+
+/// Turns and estimated tokens of `conversation`
+pub fn conversation_usage(conversation: &[Message]) -> ConversationUsage {
+    ConversationUsage {
+        turns: conversation.iter().filter(|m| m.role == MessageRole::User).count() as u32,
+        tokens: conversation.iter().map(estimate_tokens).sum(),
+    }
+}
+
+impl ConversationLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_turns(mut self, turns: u32) -> Self {
+        self.max_turns = Some(turns.max(1));
+        self
+    }
+
+    pub fn with_max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: OverflowStrategy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Keep the last `turns` turns verbatim when summarizing, 2 by default
+    pub fn with_keep_recent_turns(mut self, turns: u32) -> Self {
+        self.keep_recent_turns = Some(turns.max(1));
+        self
+    }
+
+    /// These limits with the token limit capped to what fits in `profile`'s context window
+    ///
+    /// `reserved` tokens are left free for the system prompt and the reply.
+    pub fn within_context(&self, profile: &ModelProfile, reserved: u32) -> Self {
+        let window = profile.context_window.saturating_sub(reserved);
+        Self {
+            max_tokens: Some(self.max_tokens.map_or(window, |max| max.min(window))),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.max_turns.is_none() && self.max_tokens.is_none()
+    }
+
+    /// The limit `usage` exceeds, described, if any
+    pub fn exceeded(&self, usage: ConversationUsage) -> Option<String> {
+        match (self.max_turns, self.max_tokens) {
+            (Some(max), _) if usage.turns > max => Some(format!("{} turns > {}", usage.turns, max)),
+            (_, Some(max)) if usage.tokens > max => Some(format!("~{} tokens > {}", usage.tokens, max)),
+            _ => None,
+        }
+    }
+
+    /// Drop the oldest turns until `conversation` is within limits, returning how many were dropped
+    ///
+    /// Leading system messages, such as an earlier summary, and the current
+    /// turn are kept, so the result may still exceed a token limit.
+    pub fn truncate(&self, conversation: &mut Vec<Message>) -> u32 {
+        let mut dropped = 0;
+        while self.exceeded(conversation_usage(conversation)).is_some() {
+            let starts = turn_starts(conversation);
+            if starts.len() < 2 {
+                break;
+            }
+            conversation.drain(starts[0]..starts[1]);
+            dropped += 1;
+        }
+        dropped
+    }
+
+    /// Index where the turns to summarize end, if summarizing would leave any turn out
+    pub fn summary_cutoff(&self, conversation: &[Message]) -> Option<usize> {
+        let starts = turn_starts(conversation);
+        let keep = self.keep_recent_turns.unwrap_or(2).max(1) as usize;
+        (starts.len() > keep).then(|| starts[starts.len() - keep])
+    }
+}
+
+/// Indices of the user messages that start each turn
+fn turn_starts(conversation: &[Message]) -> Vec<usize> {
+    conversation
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.role == MessageRole::User)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(turns: usize) -> Vec<Message> {
+        let mut messages = vec![Message::system("Summary: earlier chat".to_string())];
+        for turn in 0..turns {
+            messages.push(Message::user(format!("question {}", turn)));
+            messages.push(Message::assistant("x".repeat(40)));
+        }
+        messages
+    }
+
+    #[test]
+    fn test_conversation_limits_truncate_oldest_turns() {
+        let limits = ConversationLimits::new().with_max_turns(3);
+        let mut messages = conversation(5);
+        assert_eq!(limits.exceeded(conversation_usage(&messages)).as_deref(), Some("5 turns > 3"));
+        assert_eq!(limits.truncate(&mut messages), 2);
+        assert_eq!(messages[0].role, MessageRole::System);
+        assert_eq!(messages[1].content, "question 2");
+        assert_eq!(conversation_usage(&messages).turns, 3);
+
+        // Each turn is ~21 tokens; the current turn is never dropped
+        let limits = ConversationLimits::new().with_max_tokens(30);
+        let mut messages = conversation(3);
+        assert_eq!(limits.truncate(&mut messages), 2);
+        assert_eq!(messages.len(), 3);
+        assert!(limits.exceeded(conversation_usage(&messages)).is_some());
+
+        let limits = ConversationLimits::new().with_keep_recent_turns(2);
+        assert_eq!(limits.summary_cutoff(&conversation(4)), Some(5));
+        assert_eq!(limits.summary_cutoff(&conversation(2)), None);
+        assert!(ConversationLimits::new().is_unbounded());
+    }
+
+    #[test]
+    fn test_conversation_limits_within_context_window() {
+        let profile = ModelProfile::new("small", "mock", 4096);
+        let limits = ConversationLimits::new().within_context(&profile, 1096);
+        assert_eq!(limits.max_tokens, Some(3000));
+
+        // A tighter configured limit is kept
+        let limits = ConversationLimits::new().with_max_tokens(500).within_context(&profile, 1096);
+        assert_eq!(limits.max_tokens, Some(500));
+    }
+}
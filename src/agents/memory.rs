@@ -3,9 +3,13 @@
 
 #![allow(missing_docs)]
 
+use super::failures::{ToolFailureConfig, ToolFailureMemory};
+use super::importance::{ConsolidationReport, ImportanceWeights, SalienceRater};
+use super::persistence::{MemoryBackend, MemoryKey, MemoryRecord, MemoryTier, MemoryWrite};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
-use std::time::{Duration, SystemTime};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 
 /// Memory configuration for agents
@@ -21,6 +25,40 @@ pub struct MemoryConfig {
     pub semantic_search: bool,
     /// Memory compression threshold
     pub compression_threshold: usize,
+    /// Pending changes that trigger a flush to the persistent backend
+    #[serde(default = "default_flush_threshold")]
+    pub flush_threshold: usize,
+    /// Longest time changes wait before being flushed
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval: Duration,
+    /// How entries are ranked for retrieval and pruning
+    #[serde(default)]
+    pub importance: ImportanceWeights,
+    /// Most episodic and semantic entries kept by consolidation
+    #[serde(default = "default_memory_budget")]
+    pub memory_budget: usize,
+    /// Entries scoring below this are dropped by consolidation
+    #[serde(default = "default_prune_threshold")]
+    pub prune_threshold: f32,
+    /// How failed tool calls are remembered and shown in prompts
+    #[serde(default)]
+    pub tool_failures: ToolFailureConfig,
+}
+
+fn default_flush_threshold() -> usize {
+    16
+}
+
+fn default_flush_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_memory_budget() -> usize {
+    500
+}
+
+fn default_prune_threshold() -> f32 {
+    0.1
 }
 
 impl Default for MemoryConfig {
@@ -31,6 +69,12 @@ impl Default for MemoryConfig {
             retention_period: Duration::from_secs(86400 * 30), // 30 days
             semantic_search: false, // Disabled for now, would require embeddings
             compression_threshold: 100,
+            flush_threshold: default_flush_threshold(),
+            flush_interval: default_flush_interval(),
+            importance: ImportanceWeights::default(),
+            memory_budget: default_memory_budget(),
+            prune_threshold: default_prune_threshold(),
+            tool_failures: ToolFailureConfig::default(),
         }
     }
 }
@@ -129,54 +173,339 @@ pub struct AgentMemory {
     short_term: VecDeque<MemoryEntry>,
     /// Long-term memory (important/frequent interactions)
     long_term: Vec<MemoryEntry>,
+    /// Semantic memory (facts keyed by topic)
+    semantic: HashMap<String, MemoryEntry>,
     /// Working memory (current session)
     working_memory: HashMap<String, serde_json::Value>,
+    /// Recently failed tool calls (current session)
+    tool_failures: ToolFailureMemory,
+    /// Durable storage, when configured
+    persistence: Option<Persistence>,
+    /// Rates the salience of new entries
+    salience_rater: Option<Arc<dyn SalienceRater>>,
+}
+
+/// Write-behind state for a persistent backend
+#[derive(Debug)]
+struct Persistence {
+    backend: Arc<dyn MemoryBackend>,
+    key: MemoryKey,
+    episodic_loaded: bool,
+    semantic_loaded: bool,
+    /// Entries changed since the last flush
+    dirty: HashSet<String>,
+    /// Entries removed since the last flush
+    deleted: HashSet<String>,
+    /// Stored memory must be wiped before the next writes
+    clear_pending: bool,
+    last_flush: Instant,
+}
+
+impl Persistence {
+    fn pending(&self) -> usize {
+        self.dirty.len() + self.deleted.len() + usize::from(self.clear_pending)
+    }
+}
+
+/// Pending changes of a memory, ready to be written to its backend
+struct FlushBatch {
+    backend: Arc<dyn MemoryBackend>,
+    key: MemoryKey,
+    clear: bool,
+    writes: Vec<MemoryWrite>,
 }
 
 impl AgentMemory {
     /// Create a new agent memory system
     pub fn new(config: MemoryConfig) -> Result<Self, MemoryError> {
         Ok(Self {
+            tool_failures: ToolFailureMemory::new(config.tool_failures.clone()),
             config,
             short_term: VecDeque::new(),
             long_term: Vec::new(),
+            semantic: HashMap::new(),
             working_memory: HashMap::new(),
+            persistence: None,
+            salience_rater: None,
+        })
+    }
+
+    /// Rate the importance of new episodic entries with a salience rater
+    /// instead of keyword heuristics
+    pub fn with_salience_rater(mut self, rater: Arc<dyn SalienceRater>) -> Self {
+        self.salience_rater = Some(rater);
+        self
+    }
+
+    /// Set the importance of a new entry from the salience rater, if any
+    async fn rate_salience(&self, entry: &mut MemoryEntry) {
+        let Some(rater) = &self.salience_rater else {
+            return;
+        };
+        match rater.rate(&entry.content).await {
+            Ok(salience) => {
+                entry.importance = salience.clamp(0.0, 1.0);
+                entry.metadata.insert("salience_rated".to_string(), serde_json::Value::Bool(true));
+            }
+            Err(e) => tracing::warn!("Keeping heuristic importance for memory entry: {}", e),
+        }
+    }
+
+    /// Retrieval score of an entry
+    pub fn score(&self, entry: &MemoryEntry) -> f32 {
+        self.config.importance.score(entry, SystemTime::now())
+    }
+
+    /// Drop low-scoring entries and fit memory into `memory_budget`
+    ///
+    /// Entries scoring below `prune_threshold` are dropped first, then the
+    /// lowest-scoring entries until the budget is met. Working memory is not
+    /// affected.
+    pub async fn consolidate(&mut self) -> Result<ConsolidationReport, MemoryError> {
+        self.ensure_loaded(true, true).await?;
+
+        let now = SystemTime::now();
+        let weights = &self.config.importance;
+        let mut scored: Vec<(f32, String)> = self.short_term.iter()
+            .chain(self.long_term.iter())
+            .chain(self.semantic.values())
+            .map(|entry| (weights.score(entry, now), entry.id.clone()))
+            .collect();
+        scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let below_threshold = scored.iter().take_while(|(score, _)| *score < self.config.prune_threshold).count();
+        let over_budget = scored.len().saturating_sub(below_threshold).saturating_sub(self.config.memory_budget);
+        let pruned: HashSet<String> = scored.into_iter()
+            .take(below_threshold + over_budget)
+            .map(|(_, id)| id)
+            .collect();
+
+        if !pruned.is_empty() {
+            self.short_term.retain(|entry| !pruned.contains(&entry.id));
+            self.long_term.retain(|entry| !pruned.contains(&entry.id));
+            self.semantic.retain(|_, entry| !pruned.contains(&entry.id));
+            self.mark_deleted(pruned);
+            self.flush_if_due().await;
+        }
+
+        Ok(ConsolidationReport {
+            below_threshold,
+            over_budget,
+            remaining: self.short_term.len() + self.long_term.len() + self.semantic.len(),
         })
     }
+
+    /// Persist episodic and semantic memory in a backend
+    ///
+    /// Stored memory is loaded on first use and changes are written behind:
+    /// they are batched and flushed once `flush_threshold` changes are pending
+    /// or `flush_interval` has passed, when [`AgentMemory::flush`] is called,
+    /// and when the memory is dropped. Working memory is never persisted.
+    pub fn with_backend(mut self, backend: Arc<dyn MemoryBackend>, key: MemoryKey) -> Self {
+        self.set_backend(backend, key);
+        self
+    }
+
+    /// Attach a persistent backend, replacing any previous one
+    ///
+    /// Entries already in memory are kept and written on the next flush.
+    pub fn set_backend(&mut self, backend: Arc<dyn MemoryBackend>, key: MemoryKey) {
+        let dirty = self.short_term.iter()
+            .chain(self.long_term.iter())
+            .chain(self.semantic.values())
+            .map(|entry| entry.id.clone())
+            .collect();
+        self.persistence = Some(Persistence {
+            backend,
+            key,
+            episodic_loaded: false,
+            semantic_loaded: false,
+            dirty,
+            deleted: HashSet::new(),
+            clear_pending: false,
+            last_flush: Instant::now(),
+        });
+    }
+
+    /// Key the memory is persisted under
+    pub fn persistence_key(&self) -> Option<&MemoryKey> {
+        self.persistence.as_ref().map(|persistence| &persistence.key)
+    }
+
+    /// Changes not yet flushed to the backend
+    pub fn pending_writes(&self) -> usize {
+        self.persistence.as_ref().map_or(0, Persistence::pending)
+    }
+
+    /// Load all persisted memory now instead of on first use
+    pub async fn load(&mut self) -> Result<(), MemoryError> {
+        self.ensure_loaded(true, true).await
+    }
+
+    async fn ensure_loaded(&mut self, episodic: bool, semantic: bool) -> Result<(), MemoryError> {
+        let Some(persistence) = &self.persistence else {
+            return Ok(());
+        };
+
+        let mut tiers = Vec::new();
+        if episodic && !persistence.episodic_loaded {
+            tiers.extend(MemoryTier::EPISODIC);
+        }
+        if semantic && !persistence.semantic_loaded {
+            tiers.push(MemoryTier::Semantic);
+        }
+        if tiers.is_empty() {
+            return Ok(());
+        }
+
+        let records = persistence.backend.load(&persistence.key, &tiers).await?;
+
+        let mut short_term = Vec::new();
+        for record in records {
+            match record.tier {
+                MemoryTier::ShortTerm => short_term.push(record.entry),
+                MemoryTier::LongTerm => self.long_term.push(record.entry),
+                MemoryTier::Semantic => {
+                    let topic = record.entry.metadata.get("topic")
+                        .and_then(|topic| topic.as_str())
+                        .unwrap_or(&record.entry.id)
+                        .to_string();
+                    self.semantic.insert(topic, record.entry);
+                }
+            }
+        }
+
+        short_term.sort_by_key(|entry| entry.created_at);
+        short_term.extend(self.short_term.drain(..));
+        self.short_term = short_term.into();
+        rank_entries(&self.config.importance, &mut self.long_term);
+
+        if let Some(persistence) = &mut self.persistence {
+            persistence.episodic_loaded |= episodic;
+            persistence.semantic_loaded |= semantic;
+        }
+        Ok(())
+    }
+
+    /// Write pending changes to the backend
+    ///
+    /// On failure the changes stay pending and are retried on the next flush.
+    /// Changes still pending when the memory is dropped are written by a
+    /// background task, whose failures are only logged; flush before dropping
+    /// the memory to know they were written.
+    pub async fn flush(&mut self) -> Result<(), MemoryError> {
+        let Some(FlushBatch { backend, key, clear, writes }) = self.pending_batch() else {
+            return Ok(());
+        };
+
+        if clear {
+            backend.clear(&key).await?;
+            if let Some(persistence) = &mut self.persistence {
+                persistence.clear_pending = false;
+            }
+        }
+        if !writes.is_empty() {
+            backend.write(&key, &writes).await?;
+        }
+
+        if let Some(persistence) = &mut self.persistence {
+            persistence.dirty.clear();
+            persistence.deleted.clear();
+            persistence.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Changes not yet written to the backend, if there is one
+    fn pending_batch(&self) -> Option<FlushBatch> {
+        let persistence = self.persistence.as_ref()?;
+        let tiers = self.short_term.iter().map(|entry| (MemoryTier::ShortTerm, entry))
+            .chain(self.long_term.iter().map(|entry| (MemoryTier::LongTerm, entry)))
+            .chain(self.semantic.values().map(|entry| (MemoryTier::Semantic, entry)));
+        let mut writes: Vec<MemoryWrite> = tiers
+            .filter(|(_, entry)| persistence.dirty.contains(&entry.id))
+            .map(|(tier, entry)| MemoryWrite::Upsert(MemoryRecord::new(tier, entry.clone())))
+            .collect();
+        writes.extend(persistence.deleted.iter().map(|id| MemoryWrite::Delete { entry_id: id.clone() }));
+
+        Some(FlushBatch {
+            backend: persistence.backend.clone(),
+            key: persistence.key.clone(),
+            clear: persistence.clear_pending,
+            writes,
+        })
+    }
+
+    /// Flush when enough changes are pending or they have waited long enough
+    async fn flush_if_due(&mut self) {
+        let due = self.persistence.as_ref().is_some_and(|persistence| {
+            let pending = persistence.pending();
+            pending >= self.config.flush_threshold
+                || (pending > 0 && persistence.last_flush.elapsed() >= self.config.flush_interval)
+        });
+        if due {
+            if let Err(e) = self.flush().await {
+                tracing::warn!("Deferred memory flush failed: {}", e);
+            }
+        }
+    }
+
+    fn mark_dirty(&mut self, id: &str) {
+        if let Some(persistence) = &mut self.persistence {
+            persistence.deleted.remove(id);
+            persistence.dirty.insert(id.to_string());
+        }
+    }
+
+    fn mark_deleted<I: IntoIterator<Item = String>>(&mut self, ids: I) {
+        if let Some(persistence) = &mut self.persistence {
+            for id in ids {
+                persistence.dirty.remove(&id);
+                persistence.deleted.insert(id);
+            }
+        }
+    }
     
     /// Store an interaction in memory
     pub async fn store_interaction(&mut self, input: &str, output: &str) -> Result<(), MemoryError> {
+        self.ensure_loaded(true, false).await?;
         let content = format!("Input: {}\nOutput: {}", input, output);
-        let entry = MemoryEntry::new(MemoryEntryType::Interaction, content)
+        let mut entry = MemoryEntry::new(MemoryEntryType::Interaction, content)
             .with_importance(self.calculate_importance(input, output))
             .with_metadata("input_length".to_string(), input.len())
             .with_metadata("output_length".to_string(), output.len());
+        self.rate_salience(&mut entry).await;
         
         self.add_to_short_term(entry);
         self.manage_memory_limits();
+        self.flush_if_due().await;
         
         Ok(())
     }
     
     /// Store a task execution in memory
     pub async fn store_task(&mut self, task: &str, result: &str, success: bool) -> Result<(), MemoryError> {
+        self.ensure_loaded(true, false).await?;
         let content = format!("Task: {}\nResult: {}\nSuccess: {}", task, result, success);
         let entry_type = if success { MemoryEntryType::Success } else { MemoryEntryType::Error };
         let importance = if success { 0.7 } else { 0.8 }; // Errors are slightly more important for learning
         
-        let entry = MemoryEntry::new(entry_type, content)
+        let mut entry = MemoryEntry::new(entry_type, content)
             .with_importance(importance)
             .with_metadata("task_length".to_string(), task.len())
             .with_metadata("success".to_string(), success);
+        self.rate_salience(&mut entry).await;
         
         self.add_to_short_term(entry);
         self.manage_memory_limits();
+        self.flush_if_due().await;
         
         Ok(())
     }
     
     /// Store tool usage in memory
     pub async fn store_tool_usage(&mut self, tool_name: &str, args: &str, result: &str) -> Result<(), MemoryError> {
+        self.ensure_loaded(true, false).await?;
         let content = format!("Tool: {}\nArgs: {}\nResult: {}", tool_name, args, result);
         let entry = MemoryEntry::new(MemoryEntryType::Tool, content)
             .with_importance(0.6)
@@ -185,13 +514,16 @@ impl AgentMemory {
         
         self.add_to_short_term(entry);
         self.manage_memory_limits();
+        self.flush_if_due().await;
         
         Ok(())
     }
     
     /// Get relevant context for a query
     pub async fn get_relevant_context(&mut self, query: &str) -> Result<String, MemoryError> {
+        self.ensure_loaded(true, true).await?;
         let mut relevant_entries = Vec::new();
+        let mut accessed = Vec::new();
 
         // Search short-term memory
         let mut indices_to_update = Vec::new();
@@ -206,6 +538,7 @@ impl AgentMemory {
         for i in indices_to_update {
             if let Some(entry) = self.short_term.get_mut(i) {
                 entry.access();
+                accessed.push(entry.id.clone());
             }
         }
 
@@ -222,15 +555,25 @@ impl AgentMemory {
         for i in indices_to_update {
             if let Some(entry) = self.long_term.get_mut(i) {
                 entry.access();
+                accessed.push(entry.id.clone());
+            }
+        }
+
+        // Search semantic memory
+        for entry in self.semantic.values_mut() {
+            if query_matches(query, &entry.content) {
+                relevant_entries.push(entry.clone());
+                entry.access();
+                accessed.push(entry.id.clone());
             }
         }
+
+        for id in &accessed {
+            self.mark_dirty(id);
+        }
         
-        // Sort by relevance and importance
-        relevant_entries.sort_by(|a, b| {
-            let score_a = a.importance + (a.access_count as f32 * 0.1);
-            let score_b = b.importance + (b.access_count as f32 * 0.1);
-            score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        // Rank by recency, frequency and salience
+        rank_entries(&self.config.importance, &mut relevant_entries);
         
         // Take top 5 most relevant entries
         let context = relevant_entries
@@ -239,12 +582,59 @@ impl AgentMemory {
             .map(|entry| entry.content)
             .collect::<Vec<_>>()
             .join("\n---\n");
+
+        self.flush_if_due().await;
         
         Ok(context)
     }
+
+    /// Store a fact in semantic memory, replacing any earlier fact on the topic
+    pub async fn store_fact(&mut self, topic: &str, fact: &str) -> Result<(), MemoryError> {
+        self.ensure_loaded(false, true).await?;
+
+        let id = match self.semantic.get_mut(topic) {
+            Some(entry) => {
+                entry.content = fact.to_string();
+                entry.access();
+                entry.id.clone()
+            }
+            None => {
+                let entry = MemoryEntry::new(MemoryEntryType::Learning, fact.to_string())
+                    .with_importance(0.7)
+                    .with_metadata("topic".to_string(), topic);
+                let id = entry.id.clone();
+                self.semantic.insert(topic.to_string(), entry);
+                id
+            }
+        };
+        self.mark_dirty(&id);
+        self.flush_if_due().await;
+
+        Ok(())
+    }
+
+    /// Recall the fact stored on a topic
+    pub async fn recall_fact(&mut self, topic: &str) -> Result<Option<String>, MemoryError> {
+        self.ensure_loaded(false, true).await?;
+        Ok(self.semantic.get(topic).map(|entry| entry.content.clone()))
+    }
+
+    /// Forget the fact stored on a topic
+    pub async fn forget_fact(&mut self, topic: &str) -> Result<bool, MemoryError> {
+        self.ensure_loaded(false, true).await?;
+        match self.semantic.remove(topic) {
+            Some(entry) => {
+                self.mark_deleted([entry.id]);
+                self.flush_if_due().await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
     
     /// Add entry to short-term memory
     fn add_to_short_term(&mut self, entry: MemoryEntry) {
+        self.mark_dirty(&entry.id);
         self.short_term.push_back(entry);
         
         // Remove oldest if exceeding limit
@@ -253,6 +643,8 @@ impl AgentMemory {
                 // Consider moving to long-term if important
                 if old_entry.importance > 0.7 || old_entry.access_count > 3 {
                     self.add_to_long_term(old_entry);
+                } else {
+                    self.mark_deleted([old_entry.id]);
                 }
             }
         }
@@ -260,18 +652,17 @@ impl AgentMemory {
     
     /// Add entry to long-term memory
     fn add_to_long_term(&mut self, entry: MemoryEntry) {
+        self.mark_dirty(&entry.id);
         self.long_term.push(entry);
         
-        // Sort by importance and access count
-        self.long_term.sort_by(|a, b| {
-            let score_a = a.importance + (a.access_count as f32 * 0.1);
-            let score_b = b.importance + (b.access_count as f32 * 0.1);
-            score_b.partial_cmp(&score_a).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        // Keep the highest-scoring entries first
+        rank_entries(&self.config.importance, &mut self.long_term);
         
         // Remove least important if exceeding limit
         while self.long_term.len() > self.config.max_long_term_entries {
-            self.long_term.pop();
+            if let Some(dropped) = self.long_term.pop() {
+                self.mark_deleted([dropped.id]);
+            }
         }
     }
     
@@ -279,11 +670,26 @@ impl AgentMemory {
     fn manage_memory_limits(&mut self) {
         let now = SystemTime::now();
         
+        let mut removed = Vec::new();
+        
         // Remove expired entries from short-term memory
-        self.short_term.retain(|entry| !entry.is_expired(self.config.retention_period));
+        self.short_term.retain(|entry| {
+            let keep = !entry.is_expired(self.config.retention_period);
+            if !keep {
+                removed.push(entry.id.clone());
+            }
+            keep
+        });
         
         // Remove expired entries from long-term memory
-        self.long_term.retain(|entry| !entry.is_expired(self.config.retention_period));
+        self.long_term.retain(|entry| {
+            let keep = !entry.is_expired(self.config.retention_period);
+            if !keep {
+                removed.push(entry.id.clone());
+            }
+            keep
+        });
+        self.mark_deleted(removed);
         
         // Compress memory if needed
         if self.short_term.len() + self.long_term.len() > self.config.compression_threshold {
@@ -293,24 +699,26 @@ impl AgentMemory {
     
     /// Compress memory by removing less important entries
     fn compress_memory(&mut self) {
+        let mut removed = Vec::new();
+
         // Remove entries with low importance and access count from short-term
         self.short_term.retain(|entry| {
-            entry.importance > 0.3 || entry.access_count > 1
+            let keep = entry.importance > 0.3 || entry.access_count > 1;
+            if !keep {
+                removed.push(entry.id.clone());
+            }
+            keep
         });
         
         // Keep only top 80% of long-term memory
         let keep_count = (self.long_term.len() as f32 * 0.8) as usize;
-        self.long_term.truncate(keep_count);
+        removed.extend(self.long_term.drain(keep_count..).map(|entry| entry.id));
+        self.mark_deleted(removed);
     }
     
     /// Check if content is relevant to query (simple keyword matching)
     fn is_relevant(&self, query: &str, content: &str) -> bool {
-        let query_lower = query.to_lowercase();
-        let query_words: Vec<&str> = query_lower.split_whitespace().collect();
-        let content_lower = content.to_lowercase();
-
-        // Check if any query words appear in content
-        query_words.iter().any(|word| content_lower.contains(word))
+        query_matches(query, content)
     }
     
     /// Calculate importance score for an interaction
@@ -356,12 +764,46 @@ impl AgentMemory {
     pub fn clear_working_memory(&mut self) {
         self.working_memory.clear();
     }
+
+    /// Failed tool calls the agent should not repeat
+    pub fn tool_failures(&self) -> &ToolFailureMemory {
+        &self.tool_failures
+    }
+
+    /// Record failed and successful tool calls
+    pub fn tool_failures_mut(&mut self) -> &mut ToolFailureMemory {
+        &mut self.tool_failures
+    }
     
     /// Clear all memory
+    ///
+    /// Persisted memory is deleted from the backend on the next flush.
     pub fn clear(&mut self) {
         self.short_term.clear();
         self.long_term.clear();
+        self.semantic.clear();
         self.working_memory.clear();
+        self.tool_failures.clear();
+
+        if let Some(persistence) = &mut self.persistence {
+            persistence.dirty.clear();
+            persistence.deleted.clear();
+            persistence.clear_pending = true;
+            persistence.episodic_loaded = true;
+            persistence.semantic_loaded = true;
+        }
+    }
+
+    /// Start over for a new task
+    ///
+    /// Working memory is always dropped. Episodic and semantic memory are kept
+    /// when they are persisted, and dropped along with everything else otherwise.
+    pub fn reset(&mut self) {
+        if self.persistence.is_some() {
+            self.working_memory.clear();
+        } else {
+            self.clear();
+        }
     }
     
     /// Get memory statistics
@@ -369,8 +811,9 @@ impl AgentMemory {
         MemoryStats {
             short_term_entries: self.short_term.len(),
             long_term_entries: self.long_term.len(),
+            semantic_entries: self.semantic.len(),
             working_memory_entries: self.working_memory.len(),
-            total_entries: self.short_term.len() + self.long_term.len(),
+            total_entries: self.short_term.len() + self.long_term.len() + self.semantic.len(),
             average_importance: self.calculate_average_importance(),
         }
     }
@@ -379,6 +822,7 @@ impl AgentMemory {
     fn calculate_average_importance(&self) -> f32 {
         let all_entries: Vec<&MemoryEntry> = self.short_term.iter()
             .chain(self.long_term.iter())
+            .chain(self.semantic.values())
             .collect();
         
         if all_entries.is_empty() {
@@ -398,6 +842,50 @@ impl AgentMemory {
     }
 }
 
+impl Drop for AgentMemory {
+    /// Write what is still pending in the background, so the last batch is not lost
+    fn drop(&mut self) {
+        if self.pending_writes() == 0 {
+            return;
+        }
+        let Some(FlushBatch { backend, key, clear, writes }) = self.pending_batch() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!("Dropped {} unflushed memory changes of {:?} outside a runtime", writes.len(), key);
+            return;
+        };
+        runtime.spawn(async move {
+            if clear {
+                if let Err(e) = backend.clear(&key).await {
+                    tracing::warn!("Final memory flush of {:?} failed: {}", key, e);
+                    return;
+                }
+            }
+            if !writes.is_empty() {
+                if let Err(e) = backend.write(&key, &writes).await {
+                    tracing::warn!("Final memory flush of {:?} failed: {}", key, e);
+                }
+            }
+        });
+    }
+}
+
+/// Sort entries from highest to lowest score
+fn rank_entries(weights: &ImportanceWeights, entries: &mut [MemoryEntry]) {
+    let now = SystemTime::now();
+    entries.sort_by(|a, b| {
+        weights.score(b, now).partial_cmp(&weights.score(a, now)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// Check if any query word appears in content
+fn query_matches(query: &str, content: &str) -> bool {
+    let query_lower = query.to_lowercase();
+    let content_lower = content.to_lowercase();
+    query_lower.split_whitespace().any(|word| content_lower.contains(word))
+}
+
 /// Memory statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryStats {
@@ -405,6 +893,8 @@ pub struct MemoryStats {
     pub short_term_entries: usize,
     /// Number of long-term memory entries
     pub long_term_entries: usize,
+    /// Number of semantic memory entries
+    pub semantic_entries: usize,
     /// Number of working memory entries
     pub working_memory_entries: usize,
     /// Total memory entries
@@ -436,6 +926,7 @@ pub enum MemoryError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::persistence::InMemoryMemoryBackend;
 
     #[test]
     fn test_memory_config_default() {
@@ -527,4 +1018,95 @@ mod tests {
         let importance2 = memory.calculate_importance(&long_input, &long_output);
         assert!(importance2 > importance1);
     }
+
+    #[tokio::test]
+    async fn test_persistent_memory_write_behind() {
+        let backend = Arc::new(InMemoryMemoryBackend::new());
+        let key = MemoryKey::new("support-bot", "session-1");
+        let config = MemoryConfig {
+            flush_threshold: 3,
+            flush_interval: Duration::from_secs(3600),
+            ..Default::default()
+        };
+
+        let mut memory = AgentMemory::new(config.clone()).unwrap().with_backend(backend.clone(), key.clone());
+        memory.store_interaction("Where is my invoice?", "It was emailed on Monday").await.unwrap();
+        memory.store_fact("billing_email", "billing@example.com").await.unwrap();
+        assert_eq!(backend.count(&key), 0);
+        assert_eq!(memory.pending_writes(), 2);
+
+        memory.store_task("Resend invoice", "Sent", true).await.unwrap();
+        assert_eq!(backend.count(&key), 3);
+        assert_eq!(memory.pending_writes(), 0);
+
+        // Resetting keeps persisted memory
+        memory.reset();
+        assert_eq!(memory.get_stats().total_entries, 3);
+
+        // A new memory for the same agent and session loads lazily
+        let mut restored = AgentMemory::new(config.clone()).unwrap().with_backend(backend.clone(), key.clone());
+        assert_eq!(restored.get_stats().total_entries, 0);
+        assert_eq!(restored.recall_fact("billing_email").await.unwrap().as_deref(), Some("billing@example.com"));
+        assert!(restored.get_relevant_context("invoice").await.unwrap().contains("emailed on Monday"));
+        assert_eq!(restored.get_stats().short_term_entries, 2);
+
+        // Other sessions are isolated
+        let mut other = AgentMemory::new(config).unwrap().with_backend(backend.clone(), MemoryKey::new("support-bot", "session-2"));
+        assert!(other.get_relevant_context("invoice").await.unwrap().is_empty());
+
+        restored.clear();
+        restored.flush().await.unwrap();
+        assert_eq!(backend.count(&key), 0);
+
+        // What is pending when the memory is dropped is still written
+        restored.store_fact("billing_email", "billing@example.com").await.unwrap();
+        assert_eq!(restored.pending_writes(), 1);
+        drop(restored);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(backend.count(&key), 1);
+    }
+
+    #[derive(Debug)]
+    struct FixedRater(f32);
+
+    #[async_trait::async_trait]
+    impl SalienceRater for FixedRater {
+        async fn rate(&self, _content: &str) -> Result<f32, MemoryError> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_salience_rater_sets_importance() {
+        let mut memory = AgentMemory::new(MemoryConfig::default()).unwrap()
+            .with_salience_rater(Arc::new(FixedRater(0.9)));
+        memory.store_interaction("Hi", "Hello").await.unwrap();
+
+        assert!((memory.get_stats().average_importance - 0.9).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_consolidation_prunes_to_budget() {
+        let config = MemoryConfig {
+            memory_budget: 3,
+            prune_threshold: 0.2,
+            ..Default::default()
+        };
+        let mut memory = AgentMemory::new(config).unwrap();
+        for i in 0..5 {
+            memory.store_task(&format!("task {}", i), "done", true).await.unwrap();
+        }
+
+        // A forgotten, unimportant entry falls below the pruning threshold
+        let stale = memory.short_term.front_mut().unwrap();
+        stale.importance = 0.0;
+        stale.last_accessed = SystemTime::now() - Duration::from_secs(86400 * 30);
+        let stale_id = stale.id.clone();
+
+        let report = memory.consolidate().await.unwrap();
+        assert_eq!(report.below_threshold, 1);
+        assert_eq!(report.over_budget, 1);
+        assert_eq!(report.remaining, 3);
+        assert!(memory.short_term.iter().all(|entry| entry.id != stale_id));
+    }
 }
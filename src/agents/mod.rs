@@ -3,17 +3,26 @@
 
 #![allow(missing_docs)]
 
-use crate::llm::{LLMManager, CompletionRequest, Message, FunctionDefinition};
-use crate::tools::{ToolRegistry, ToolExecutor};
+use crate::llm::{LLMManager, LLMUsage, CompletionRequest, Message, FunctionDefinition};
+use crate::tools::{ToolRegistry, ToolExecutor, ToolPolicy, ResolvedTool};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::SystemTime;
 use thiserror::Error;
 
+pub mod limits;
+pub mod prompts;
 pub mod memory;
+pub mod persistence;
+pub mod importance;
+pub mod runtime;
 pub mod roles;
 pub mod collaboration;
+pub mod vector;
+pub mod rerank;
+pub mod failures;
+pub mod indexing;
 
 /// Agent configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +47,15 @@ pub struct AgentConfig {
     pub memory_config: memory::MemoryConfig,
     /// Collaboration settings
     pub collaboration_config: collaboration::CollaborationConfig,
+    /// Request token logprobs so responses carry a confidence score
+    #[serde(default)]
+    pub request_logprobs: bool,
+    /// Agent-level policy over which tools may be exposed
+    #[serde(default)]
+    pub tool_policy: ToolPolicy,
+    /// Limits on the conversation history sent with each task
+    #[serde(default)]
+    pub conversation_limits: limits::ConversationLimits,
 }
 
 impl Default for AgentConfig {
@@ -53,6 +71,9 @@ impl Default for AgentConfig {
             available_tools: Vec::new(),
             memory_config: memory::MemoryConfig::default(),
             collaboration_config: collaboration::CollaborationConfig::default(),
+            request_logprobs: false,
+            tool_policy: ToolPolicy::default(),
+            conversation_limits: limits::ConversationLimits::default(),
         }
     }
 }
@@ -104,6 +125,9 @@ impl AgentRole {
                 "file_read".to_string(),
                 "text_search".to_string(),
                 "calculator".to_string(),
+                "email_read".to_string(),
+                "email_send".to_string(),
+                "calendar_create_event".to_string(),
             ],
             AgentRole::Researcher => vec![
                 "http_get".to_string(),
@@ -146,6 +170,8 @@ impl AgentRole {
                 "text_search".to_string(),
                 "http_get".to_string(),
                 "file_read".to_string(),
+                "email_read".to_string(),
+                "email_send".to_string(),
             ],
             AgentRole::Custom(_) => Vec::new(),
         }
@@ -171,6 +197,10 @@ pub struct AgentState {
     pub total_cost: f64,
     /// Number of tool calls made
     pub tool_calls_count: u64,
+    /// Model confidence of the last response, when logprobs were available
+    pub last_confidence: Option<f64>,
+    /// LLM usage of the most recent task
+    pub last_task_usage: LLMUsage,
 }
 
 impl Default for AgentState {
@@ -184,6 +214,8 @@ impl Default for AgentState {
             total_tokens_used: 0,
             total_cost: 0.0,
             tool_calls_count: 0,
+            last_confidence: None,
+            last_task_usage: LLMUsage::default(),
         }
     }
 }
@@ -222,6 +254,8 @@ pub struct Agent {
     tool_executor: Arc<ToolExecutor>,
     /// Agent memory system
     memory: memory::AgentMemory,
+    /// Policy imposed by the tenant the agent runs for
+    tenant_tool_policy: Option<ToolPolicy>,
 }
 
 impl Agent {
@@ -241,24 +275,60 @@ impl Agent {
             tool_registry,
             tool_executor,
             memory,
+            tenant_tool_policy: None,
         })
     }
     
+    /// Persist the agent's memory for a session, keyed by the agent name
+    pub fn with_memory_backend(mut self, backend: Arc<dyn persistence::MemoryBackend>, session_id: &str) -> Self {
+        let key = persistence::MemoryKey::new(self.config.name.clone(), session_id);
+        self.memory.set_backend(backend, key);
+        self
+    }
+    
+    /// Restrict the agent's tools with a tenant policy
+    pub fn with_tenant_tool_policy(mut self, policy: ToolPolicy) -> Self {
+        self.tenant_tool_policy = Some(policy);
+        self
+    }
+    
+    /// Tools this agent may use: role recommendations plus configured tools,
+    /// filtered by the agent and tenant policies
+    pub fn resolved_tools(&self) -> Vec<ResolvedTool> {
+        let mut policies = vec![&self.config.tool_policy];
+        if let Some(tenant_policy) = &self.tenant_tool_policy {
+            policies.push(tenant_policy);
+        }
+        self.tool_registry.resolve_tools(
+            &self.config.role.recommended_tools(),
+            &self.config.available_tools,
+            &policies,
+        )
+    }
+    
     /// Execute a task
     pub async fn execute_task(&mut self, task: String) -> Result<String, AgentError> {
         self.state.status = AgentStatus::Thinking;
         self.state.current_task = Some(task.clone());
         self.state.last_activity = SystemTime::now();
+        self.state.last_task_usage = LLMUsage::default();
         
         // Add task to conversation
         let user_message = Message::user(task.clone());
         self.state.conversation.push(user_message);
+        if let Err(e) = self.enforce_conversation_limits().await {
+            self.state.conversation.pop();
+            self.state.status = AgentStatus::Error;
+            self.state.current_task = None;
+            return Err(e);
+        }
         
         // Build system message with role context
+        let tool_names: Vec<String> = self.resolved_tools().into_iter().map(|t| t.id).collect();
         let system_message = Message::system(format!(
             "{}\n\nYou have access to the following tools: {}",
             self.config.system_prompt,
-            self.config.available_tools.join(", ")
+            tool_names.join(", ")
         ));
         
         // Prepare messages for LLM
@@ -273,6 +343,12 @@ impl Agent {
             ));
             messages.push(context_message);
         }
+
+        // Remind the model of tool calls that already failed; their errors are
+        // tool output, so they do not get a system message's authority
+        if let Some(failures) = self.memory.tool_failures().summary() {
+            messages.push(Message::user(failures));
+        }
         
         // Add conversation history
         messages.extend(self.state.conversation.clone());
@@ -288,20 +364,24 @@ impl Agent {
             temperature: self.config.temperature,
             functions: if functions.is_empty() { None } else { Some(functions) },
             function_call: Some(crate::llm::FunctionCallBehavior::Auto),
+            logprobs: self.config.request_logprobs,
             ..Default::default()
         };
         
         // Execute LLM request
+        let started = std::time::Instant::now();
         let response = self.llm_manager
             .complete_with_provider(&self.config.provider, request)
             .await
             .map_err(|e| AgentError::LLMError { message: e.to_string() })?;
+        self.state.last_task_usage.record(&response, started.elapsed());
         
         // Update usage statistics
         self.state.total_tokens_used += response.usage.total_tokens as u64;
         if let Some(cost) = response.usage.estimated_cost {
             self.state.total_cost += cost;
         }
+        self.state.last_confidence = response.confidence();
         
         let choice = &response.choices[0];
         let mut final_response = choice.message.content.clone();
@@ -326,15 +406,19 @@ impl Agent {
                 messages: self.state.conversation.clone(),
                 max_tokens: self.config.max_tokens,
                 temperature: self.config.temperature,
+                logprobs: self.config.request_logprobs,
                 ..Default::default()
             };
             
+            let started = std::time::Instant::now();
             let follow_up_response = self.llm_manager
                 .complete_with_provider(&self.config.provider, follow_up_request)
                 .await
                 .map_err(|e| AgentError::LLMError { message: e.to_string() })?;
+            self.state.last_task_usage.record(&follow_up_response, started.elapsed());
             
             final_response = follow_up_response.choices[0].message.content.clone();
+            self.state.last_confidence = follow_up_response.confidence();
             
             // Update usage statistics
             self.state.total_tokens_used += follow_up_response.usage.total_tokens as u64;
@@ -358,12 +442,119 @@ impl Agent {
         Ok(final_response)
     }
     
-    /// Execute a tool function call
+    /// Bring the conversation within the configured limits and the model's context window before it is sent
+    async fn enforce_conversation_limits(&mut self) -> Result<(), AgentError> {
+        let mut limits = self.config.conversation_limits.clone();
+        if let Some(profile) = self.llm_manager.profiles().get(&self.config.model) {
+            let reserved = limits::estimate_tokens(&Message::system(self.config.system_prompt.clone()))
+                + self.config.max_tokens.unwrap_or_default();
+            limits = limits.within_context(profile, reserved);
+        }
+        let usage = limits::conversation_usage(&self.state.conversation);
+        let Some(reason) = limits.exceeded(usage) else {
+            return Ok(());
+        };
+
+        match limits.overflow {
+            limits::OverflowStrategy::Error => {
+                return Err(AgentError::ConversationLimitExceeded { agent: self.config.name.clone(), reason });
+            }
+            limits::OverflowStrategy::Summarize => {
+                if let Some(cutoff) = limits.summary_cutoff(&self.state.conversation) {
+                    match self.summarize_turns(cutoff).await {
+                        Ok(summary) => {
+                            tracing::info!(agent = %self.config.name, %reason, "Summarized earlier conversation turns");
+                            self.state.conversation.splice(..cutoff, [summary]);
+                        }
+                        Err(e) => tracing::warn!(agent = %self.config.name, error = %e, "Failed to summarize conversation, truncating instead"),
+                    }
+                }
+            }
+            limits::OverflowStrategy::Truncate => {}
+        }
+
+        let dropped = limits.truncate(&mut self.state.conversation);
+        if dropped > 0 {
+            tracing::info!(agent = %self.config.name, dropped, %reason, "Dropped oldest conversation turns");
+        }
+        match limits.exceeded(limits::conversation_usage(&self.state.conversation)) {
+            // Only the current turn is left and it is still too long
+            Some(reason) => Err(AgentError::ConversationLimitExceeded { agent: self.config.name.clone(), reason }),
+            None => Ok(()),
+        }
+    }
+
+    /// Ask the agent's model to summarize the conversation before `cutoff`
+    async fn summarize_turns(&mut self, cutoff: usize) -> Result<Message, AgentError> {
+        let transcript = self.state.conversation[..cutoff]
+            .iter()
+            .map(|m| format!("{:?}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = CompletionRequest {
+            model: self.config.model.clone(),
+            messages: vec![
+                Message::system("Summarize this conversation in a few sentences, keeping facts, decisions and open questions.".to_string()),
+                Message::user(transcript),
+            ],
+            max_tokens: Some(self.config.conversation_limits.max_tokens.map_or(400, |max| (max / 4).clamp(64, 400))),
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let response = self.llm_manager
+            .complete_with_provider(&self.config.provider, request)
+            .await
+            .map_err(|e| AgentError::LLMError { message: e.to_string() })?;
+        self.state.last_task_usage.record(&response, started.elapsed());
+        self.state.total_tokens_used += response.usage.total_tokens as u64;
+        if let Some(cost) = response.usage.estimated_cost {
+            self.state.total_cost += cost;
+        }
+        let summary = response.choices.first().map(|c| c.message.content.clone()).unwrap_or_default();
+        Ok(Message::system(format!("Summary of the earlier conversation: {}", summary)))
+    }
+
+    /// Execute a tool function call, remembering failures for later prompts
+    ///
+    /// A call identical to one that already failed the configured number of
+    /// times is refused without running.
     async fn execute_tool(&mut self, function_call: &crate::llm::FunctionCall) -> Result<serde_json::Value, AgentError> {
         let tool_name = &function_call.name;
+        let arguments = &function_call.arguments;
+        if let Some(failure) = self.memory.tool_failures().blocked(tool_name, arguments) {
+            let error = format!(
+                "this exact call already failed {} times ({}); change the arguments or use another tool",
+                failure.occurrences, failure.error
+            );
+            self.memory.tool_failures_mut().record(tool_name, arguments, failures::ToolFailureKind::Repeated, &error);
+            return Err(AgentError::ToolExecutionError { tool_name: tool_name.clone(), error });
+        }
+
+        let result = self.call_tool(function_call).await;
+        let failure = match &result {
+            Ok(_) => {
+                self.memory.tool_failures_mut().record_success(tool_name);
+                None
+            }
+            Err(e @ AgentError::ToolNotAvailable { .. }) => Some((failures::ToolFailureKind::NotAvailable, e.to_string())),
+            Err(AgentError::InvalidToolArguments { error, .. }) => Some((failures::ToolFailureKind::InvalidArguments, error.clone())),
+            Err(AgentError::ToolExecutionError { error, .. }) => Some((failures::ToolFailureKind::ExecutionError, error.clone())),
+            Err(_) => None,
+        };
+        if let Some((kind, error)) = failure {
+            let occurrences = self.memory.tool_failures_mut().record(tool_name, arguments, kind, &error);
+            tracing::debug!(agent = %self.config.name, tool = %tool_name, occurrences, "Tool call failed: {}", error);
+        }
+        result
+    }
+
+    /// Check and run a tool function call
+    async fn call_tool(&mut self, function_call: &crate::llm::FunctionCall) -> Result<serde_json::Value, AgentError> {
+        let tool_name = &function_call.name;
         
-        // Check if tool is available
-        if !self.config.available_tools.contains(tool_name) {
+        // Check if tool is available to this agent
+        if !self.resolved_tools().iter().any(|t| &t.id == tool_name) {
             return Err(AgentError::ToolNotAvailable {
                 tool_name: tool_name.clone(),
             });
@@ -384,8 +575,13 @@ impl Agent {
 
         // Create tool input
         let tool_input = crate::tools::ToolInput::new(serde_json::to_value(args).unwrap_or_default());
+        tool.validate_input(&tool_input).await.map_err(|e| AgentError::InvalidToolArguments {
+            tool_name: tool_name.clone(),
+            error: e.to_string(),
+        })?;
         let tool_config = crate::tools::ToolConfig::default();
-        let tool_context = crate::tools::ToolExecutionContext::new(uuid::Uuid::new_v4().to_string());
+        let tool_context = crate::tools::ToolExecutionContext::new(uuid::Uuid::new_v4().to_string())
+            .with_context_vars(&crate::graph::context_vars::current());
 
         // For now, return a mock result since we need to fix the tool executor integration
         let result = serde_json::json!({
@@ -401,19 +597,18 @@ impl Agent {
     async fn get_available_functions(&self) -> Result<Vec<FunctionDefinition>, AgentError> {
         let mut functions = Vec::new();
 
-        for tool_name in &self.config.available_tools {
-            if let Some(_tool) = self.tool_registry.get(tool_name) {
-                let function_def = FunctionDefinition::new(
-                    tool_name.clone(),
-                    format!("Execute {} tool", tool_name), // Use tool name as description
-                    serde_json::json!({
-                        "type": "object",
-                        "properties": {},
-                        "required": []
-                    }), // Basic schema for now
-                );
-                functions.push(function_def);
-            }
+        for resolved in self.resolved_tools() {
+            let metadata = resolved.tool.metadata();
+            let function_def = FunctionDefinition::new(
+                resolved.id.clone(),
+                metadata.description.clone(),
+                metadata.input_schema.clone().unwrap_or_else(|| serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                })), // Basic schema for tools without one
+            );
+            functions.push(function_def);
         }
 
         Ok(functions)
@@ -442,7 +637,7 @@ impl Agent {
     /// Reset agent state
     pub fn reset(&mut self) {
         self.state = AgentState::default();
-        self.memory.clear();
+        self.memory.reset();
     }
     
     /// Update agent configuration
@@ -508,6 +703,10 @@ pub enum AgentError {
     /// System error
     #[error("System error: {message}")]
     SystemError { message: String },
+
+    /// Conversation outgrew the agent's limits
+    #[error("Conversation limit exceeded for agent {agent}: {reason}")]
+    ConversationLimitExceeded { agent: String, reason: String },
 }
 
 impl From<memory::MemoryError> for AgentError {
@@ -557,4 +756,32 @@ mod tests {
         assert!(state.current_task.is_none());
         assert!(state.conversation.is_empty());
     }
+
+    #[test]
+    fn test_resolved_tools_respect_policies() {
+        let registry = Arc::new(crate::tools::common::create_common_tools_registry().unwrap());
+        let config = AgentConfig {
+            role: AgentRole::Developer,
+            available_tools: vec!["math:*".to_string()],
+            tool_policy: ToolPolicy::allow_all().with_deny("fs:file_write"),
+            ..Default::default()
+        };
+        let agent = Agent::new(
+            config,
+            Arc::new(LLMManager::new(crate::llm::LLMConfig::default())),
+            registry,
+            Arc::new(ToolExecutor::new()),
+        ).unwrap();
+
+        let ids: Vec<String> = agent.resolved_tools().into_iter().map(|t| t.id).collect();
+        assert!(ids.contains(&"file_read".to_string()));
+        assert!(ids.contains(&"calculator".to_string()));
+        assert!(ids.contains(&"statistics".to_string()));
+        assert!(!ids.contains(&"file_write".to_string()));
+
+        // Tenant only allows network tools
+        let agent = agent.with_tenant_tool_policy(ToolPolicy::deny_all().with_allow("#network"));
+        let ids: Vec<String> = agent.resolved_tools().into_iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec!["http_get".to_string()]);
+    }
 }
@@ -0,0 +1,585 @@
+// Persistent backends for agent memory
+// Stores episodic and semantic memory per agent and session so it outlives the process
+
+#![allow(missing_docs)]
+
+use super::memory::{MemoryEntry, MemoryError};
+use crate::state::tenant::TenantScope;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Identifies whose memory is stored
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MemoryKey {
+    /// Agent the memory belongs to
+    pub agent_id: String,
+    /// Session within the agent
+    pub session_id: String,
+}
+
+impl MemoryKey {
+    pub fn new<A: Into<String>, S: Into<String>>(agent_id: A, session_id: S) -> Self {
+        Self {
+            agent_id: agent_id.into(),
+            session_id: session_id.into(),
+        }
+    }
+}
+
+impl fmt::Display for MemoryKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.agent_id, self.session_id)
+    }
+}
+
+/// Where an entry lives inside an agent's memory
+///
+/// Short- and long-term entries make up episodic memory; semantic entries
+/// are facts keyed by topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryTier {
+    ShortTerm,
+    LongTerm,
+    Semantic,
+}
+
+impl MemoryTier {
+    /// Tiers holding episodic memory
+    pub const EPISODIC: [MemoryTier; 2] = [MemoryTier::ShortTerm, MemoryTier::LongTerm];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryTier::ShortTerm => "short_term",
+            MemoryTier::LongTerm => "long_term",
+            MemoryTier::Semantic => "semantic",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, MemoryError> {
+        match value {
+            "short_term" => Ok(MemoryTier::ShortTerm),
+            "long_term" => Ok(MemoryTier::LongTerm),
+            "semantic" => Ok(MemoryTier::Semantic),
+            other => Err(MemoryError::RetrievalError {
+                message: format!("Unknown memory tier '{}'", other),
+            }),
+        }
+    }
+}
+
+/// A persisted memory entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRecord {
+    pub tier: MemoryTier,
+    pub entry: MemoryEntry,
+}
+
+impl MemoryRecord {
+    pub fn new(tier: MemoryTier, entry: MemoryEntry) -> Self {
+        Self { tier, entry }
+    }
+
+    fn to_json(&self) -> Result<String, MemoryError> {
+        serde_json::to_string(self).map_err(|e| MemoryError::StorageError {
+            message: format!("Failed to serialize memory entry {}: {}", self.entry.id, e),
+        })
+    }
+
+    fn from_json(json: &str) -> Result<Self, MemoryError> {
+        serde_json::from_str(json).map_err(|e| MemoryError::RetrievalError {
+            message: format!("Corrupt memory entry: {}", e),
+        })
+    }
+}
+
+/// A change flushed to a backend
+#[derive(Debug, Clone)]
+pub enum MemoryWrite {
+    /// Insert or replace an entry
+    Upsert(MemoryRecord),
+    /// Remove an entry
+    Delete { entry_id: String },
+}
+
+/// Durable storage for agent memory
+///
+/// Writes arrive in batches; a backend should apply each batch atomically
+/// where the underlying store allows it.
+#[async_trait]
+pub trait MemoryBackend: Send + Sync + fmt::Debug {
+    /// Load the entries of the given tiers
+    async fn load(&self, key: &MemoryKey, tiers: &[MemoryTier]) -> Result<Vec<MemoryRecord>, MemoryError>;
+
+    /// Apply a batch of writes
+    async fn write(&self, key: &MemoryKey, writes: &[MemoryWrite]) -> Result<(), MemoryError>;
+
+    /// Remove everything stored for a key
+    async fn clear(&self, key: &MemoryKey) -> Result<(), MemoryError>;
+}
+
+/// Process-local backend
+///
+/// Survives agent resets and re-creation within one process, which makes it
+/// useful for tests and single-node development.
+#[derive(Debug, Default)]
+pub struct InMemoryMemoryBackend {
+    records: RwLock<HashMap<MemoryKey, HashMap<String, MemoryRecord>>>,
+}
+
+impl InMemoryMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of entries stored for a key
+    pub fn count(&self, key: &MemoryKey) -> usize {
+        self.records.read().unwrap().get(key).map_or(0, |records| records.len())
+    }
+}
+
+#[async_trait]
+impl MemoryBackend for InMemoryMemoryBackend {
+    async fn load(&self, key: &MemoryKey, tiers: &[MemoryTier]) -> Result<Vec<MemoryRecord>, MemoryError> {
+        let records = self.records.read().unwrap();
+        Ok(records
+            .get(key)
+            .map(|records| {
+                records.values()
+                    .filter(|record| tiers.contains(&record.tier))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn write(&self, key: &MemoryKey, writes: &[MemoryWrite]) -> Result<(), MemoryError> {
+        let mut records = self.records.write().unwrap();
+        let records = records.entry(key.clone()).or_default();
+        for write in writes {
+            match write {
+                MemoryWrite::Upsert(record) => {
+                    records.insert(record.entry.id.clone(), record.clone());
+                }
+                MemoryWrite::Delete { entry_id } => {
+                    records.remove(entry_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn clear(&self, key: &MemoryKey) -> Result<(), MemoryError> {
+        self.records.write().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Memory of one tenant's agents, stored under tenant-prefixed agent IDs
+///
+/// Agents of different tenants may share IDs and sessions without seeing
+/// each other's memory.
+#[derive(Debug, Clone)]
+pub struct TenantMemoryBackend {
+    inner: Arc<dyn MemoryBackend>,
+    scope: TenantScope,
+}
+
+impl TenantMemoryBackend {
+    pub fn new(inner: Arc<dyn MemoryBackend>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    fn scoped(&self, key: &MemoryKey) -> MemoryKey {
+        MemoryKey::new(self.scope.prefix(&key.agent_id), key.session_id.clone())
+    }
+}
+
+#[async_trait]
+impl MemoryBackend for TenantMemoryBackend {
+    async fn load(&self, key: &MemoryKey, tiers: &[MemoryTier]) -> Result<Vec<MemoryRecord>, MemoryError> {
+        self.inner.load(&self.scoped(key), tiers).await
+    }
+
+    async fn write(&self, key: &MemoryKey, writes: &[MemoryWrite]) -> Result<(), MemoryError> {
+        self.inner.write(&self.scoped(key), writes).await
+    }
+
+    async fn clear(&self, key: &MemoryKey) -> Result<(), MemoryError> {
+        self.inner.clear(&self.scoped(key)).await
+    }
+}
+
+fn storage_error(backend: &str, error: impl fmt::Display) -> MemoryError {
+    MemoryError::StorageError {
+        message: format!("{} memory backend: {}", backend, error),
+    }
+}
+
+#[cfg(feature = "memory-sqlite")]
+pub use self::sqlite::SqliteMemoryBackend;
+
+#[cfg(feature = "memory-sqlite")]
+mod sqlite {
+    use super::*;
+    use rusqlite::{params, Connection};
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS agent_memory (
+        agent_id TEXT NOT NULL,
+        session_id TEXT NOT NULL,
+        entry_id TEXT NOT NULL,
+        tier TEXT NOT NULL,
+        record TEXT NOT NULL,
+        PRIMARY KEY (agent_id, session_id, entry_id)
+    )";
+
+    /// Backend storing memory in a SQLite database
+    #[derive(Debug, Clone)]
+    pub struct SqliteMemoryBackend {
+        connection: Arc<Mutex<Connection>>,
+    }
+
+    impl SqliteMemoryBackend {
+        /// Open or create a database file
+        pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MemoryError> {
+            let connection = Connection::open(path).map_err(|e| storage_error("SQLite", e))?;
+            Self::with_connection(connection)
+        }
+
+        /// Database that lives as long as the backend
+        pub fn in_memory() -> Result<Self, MemoryError> {
+            let connection = Connection::open_in_memory().map_err(|e| storage_error("SQLite", e))?;
+            Self::with_connection(connection)
+        }
+
+        fn with_connection(connection: Connection) -> Result<Self, MemoryError> {
+            connection.execute_batch(SCHEMA).map_err(|e| storage_error("SQLite", e))?;
+            Ok(Self {
+                connection: Arc::new(Mutex::new(connection)),
+            })
+        }
+
+        async fn run<T, F>(&self, operation: F) -> Result<T, MemoryError>
+        where
+            T: Send + 'static,
+            F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+        {
+            let connection = self.connection.clone();
+            tokio::task::spawn_blocking(move || operation(&mut connection.lock().unwrap()))
+                .await
+                .map_err(|e| storage_error("SQLite", e))?
+                .map_err(|e| storage_error("SQLite", e))
+        }
+    }
+
+    #[async_trait]
+    impl MemoryBackend for SqliteMemoryBackend {
+        async fn load(&self, key: &MemoryKey, tiers: &[MemoryTier]) -> Result<Vec<MemoryRecord>, MemoryError> {
+            let key = key.clone();
+            let tiers: Vec<&'static str> = tiers.iter().map(MemoryTier::as_str).collect();
+            let rows = self.run(move |connection| {
+                let mut statement = connection.prepare(
+                    "SELECT tier, record FROM agent_memory WHERE agent_id = ?1 AND session_id = ?2",
+                )?;
+                let rows = statement.query_map(params![key.agent_id, key.session_id], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?;
+                rows.filter(|row| row.as_ref().map_or(true, |(tier, _)| tiers.contains(&tier.as_str())))
+                    .map(|row| row.map(|(_, record)| record))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            }).await?;
+
+            rows.iter().map(|json| MemoryRecord::from_json(json)).collect()
+        }
+
+        async fn write(&self, key: &MemoryKey, writes: &[MemoryWrite]) -> Result<(), MemoryError> {
+            let key = key.clone();
+            let writes = writes
+                .iter()
+                .map(|write| match write {
+                    MemoryWrite::Upsert(record) => Ok((record.entry.id.clone(), Some((record.tier.as_str(), record.to_json()?)))),
+                    MemoryWrite::Delete { entry_id } => Ok((entry_id.clone(), None)),
+                })
+                .collect::<Result<Vec<_>, MemoryError>>()?;
+
+            self.run(move |connection| {
+                let transaction = connection.transaction()?;
+                for (entry_id, upsert) in &writes {
+                    match upsert {
+                        Some((tier, record)) => transaction.execute(
+                            "INSERT INTO agent_memory (agent_id, session_id, entry_id, tier, record)
+                             VALUES (?1, ?2, ?3, ?4, ?5)
+                             ON CONFLICT (agent_id, session_id, entry_id)
+                             DO UPDATE SET tier = excluded.tier, record = excluded.record",
+                            params![key.agent_id, key.session_id, entry_id, tier, record],
+                        )?,
+                        None => transaction.execute(
+                            "DELETE FROM agent_memory WHERE agent_id = ?1 AND session_id = ?2 AND entry_id = ?3",
+                            params![key.agent_id, key.session_id, entry_id],
+                        )?,
+                    };
+                }
+                transaction.commit()
+            }).await
+        }
+
+        async fn clear(&self, key: &MemoryKey) -> Result<(), MemoryError> {
+            let key = key.clone();
+            self.run(move |connection| {
+                connection.execute(
+                    "DELETE FROM agent_memory WHERE agent_id = ?1 AND session_id = ?2",
+                    params![key.agent_id, key.session_id],
+                ).map(|_| ())
+            }).await
+        }
+    }
+}
+
+#[cfg(feature = "memory-redis")]
+pub use self::redis_backend::RedisMemoryBackend;
+
+#[cfg(feature = "memory-redis")]
+mod redis_backend {
+    use super::*;
+    use redis::aio::MultiplexedConnection;
+    use redis::AsyncCommands;
+
+    /// Backend storing each agent session's memory in a Redis hash
+    #[derive(Clone)]
+    pub struct RedisMemoryBackend {
+        connection: MultiplexedConnection,
+        prefix: String,
+    }
+
+    impl fmt::Debug for RedisMemoryBackend {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RedisMemoryBackend")
+                .field("prefix", &self.prefix)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl RedisMemoryBackend {
+        /// Connect to a server, e.g. `redis://127.0.0.1/`
+        pub async fn connect(url: &str) -> Result<Self, MemoryError> {
+            let client = redis::Client::open(url).map_err(|e| storage_error("Redis", e))?;
+            let connection = client.get_multiplexed_tokio_connection().await
+                .map_err(|e| storage_error("Redis", e))?;
+            Ok(Self {
+                connection,
+                prefix: "agent_graph:memory".to_string(),
+            })
+        }
+
+        /// Namespace for the keys this backend creates
+        pub fn with_prefix(mut self, prefix: &str) -> Self {
+            self.prefix = prefix.to_string();
+            self
+        }
+
+        fn redis_key(&self, key: &MemoryKey) -> String {
+            format!("{}:{}:{}", self.prefix, key.agent_id, key.session_id)
+        }
+    }
+
+    #[async_trait]
+    impl MemoryBackend for RedisMemoryBackend {
+        async fn load(&self, key: &MemoryKey, tiers: &[MemoryTier]) -> Result<Vec<MemoryRecord>, MemoryError> {
+            let mut connection = self.connection.clone();
+            let stored: HashMap<String, String> = connection.hgetall(self.redis_key(key)).await
+                .map_err(|e| storage_error("Redis", e))?;
+
+            let mut records = Vec::new();
+            for json in stored.values() {
+                let record = MemoryRecord::from_json(json)?;
+                if tiers.contains(&record.tier) {
+                    records.push(record);
+                }
+            }
+            Ok(records)
+        }
+
+        async fn write(&self, key: &MemoryKey, writes: &[MemoryWrite]) -> Result<(), MemoryError> {
+            let redis_key = self.redis_key(key);
+            let mut pipeline = redis::pipe();
+            pipeline.atomic();
+            for write in writes {
+                match write {
+                    MemoryWrite::Upsert(record) => pipeline.hset(&redis_key, &record.entry.id, record.to_json()?).ignore(),
+                    MemoryWrite::Delete { entry_id } => pipeline.hdel(&redis_key, entry_id).ignore(),
+                };
+            }
+
+            let mut connection = self.connection.clone();
+            pipeline.query_async::<_, ()>(&mut connection).await
+                .map_err(|e| storage_error("Redis", e))
+        }
+
+        async fn clear(&self, key: &MemoryKey) -> Result<(), MemoryError> {
+            let mut connection = self.connection.clone();
+            connection.del::<_, ()>(self.redis_key(key)).await
+                .map_err(|e| storage_error("Redis", e))
+        }
+    }
+}
+
+#[cfg(feature = "memory-postgres")]
+pub use self::postgres::PostgresMemoryBackend;
+
+#[cfg(feature = "memory-postgres")]
+mod postgres {
+    use super::*;
+    use tokio::sync::Mutex;
+    use tokio_postgres::{Client, NoTls};
+
+    const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS agent_memory (
+        agent_id TEXT NOT NULL,
+        session_id TEXT NOT NULL,
+        entry_id TEXT NOT NULL,
+        tier TEXT NOT NULL,
+        record TEXT NOT NULL,
+        PRIMARY KEY (agent_id, session_id, entry_id)
+    )";
+
+    /// Backend storing memory in a PostgreSQL table
+    pub struct PostgresMemoryBackend {
+        client: Mutex<Client>,
+    }
+
+    impl fmt::Debug for PostgresMemoryBackend {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("PostgresMemoryBackend").finish_non_exhaustive()
+        }
+    }
+
+    impl PostgresMemoryBackend {
+        /// Connect with a connection string and create the table if needed
+        pub async fn connect(config: &str) -> Result<Self, MemoryError> {
+            let (client, connection) = tokio_postgres::connect(config, NoTls).await
+                .map_err(|e| storage_error("PostgreSQL", e))?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    tracing::warn!("PostgreSQL memory backend connection closed: {}", e);
+                }
+            });
+
+            client.batch_execute(SCHEMA).await.map_err(|e| storage_error("PostgreSQL", e))?;
+            Ok(Self {
+                client: Mutex::new(client),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl MemoryBackend for PostgresMemoryBackend {
+        async fn load(&self, key: &MemoryKey, tiers: &[MemoryTier]) -> Result<Vec<MemoryRecord>, MemoryError> {
+            let tiers: Vec<&str> = tiers.iter().map(MemoryTier::as_str).collect();
+            let client = self.client.lock().await;
+            let rows = client.query(
+                "SELECT record FROM agent_memory WHERE agent_id = $1 AND session_id = $2 AND tier = ANY($3)",
+                &[&key.agent_id, &key.session_id, &tiers],
+            ).await.map_err(|e| storage_error("PostgreSQL", e))?;
+
+            rows.iter().map(|row| MemoryRecord::from_json(row.get(0))).collect()
+        }
+
+        async fn write(&self, key: &MemoryKey, writes: &[MemoryWrite]) -> Result<(), MemoryError> {
+            let mut client = self.client.lock().await;
+            let transaction = client.transaction().await.map_err(|e| storage_error("PostgreSQL", e))?;
+            for write in writes {
+                match write {
+                    MemoryWrite::Upsert(record) => transaction.execute(
+                        "INSERT INTO agent_memory (agent_id, session_id, entry_id, tier, record)
+                         VALUES ($1, $2, $3, $4, $5)
+                         ON CONFLICT (agent_id, session_id, entry_id)
+                         DO UPDATE SET tier = EXCLUDED.tier, record = EXCLUDED.record",
+                        &[&key.agent_id, &key.session_id, &record.entry.id, &record.tier.as_str(), &record.to_json()?],
+                    ).await,
+                    MemoryWrite::Delete { entry_id } => transaction.execute(
+                        "DELETE FROM agent_memory WHERE agent_id = $1 AND session_id = $2 AND entry_id = $3",
+                        &[&key.agent_id, &key.session_id, entry_id],
+                    ).await,
+                }.map_err(|e| storage_error("PostgreSQL", e))?;
+            }
+            transaction.commit().await.map_err(|e| storage_error("PostgreSQL", e))
+        }
+
+        async fn clear(&self, key: &MemoryKey) -> Result<(), MemoryError> {
+            let client = self.client.lock().await;
+            client.execute(
+                "DELETE FROM agent_memory WHERE agent_id = $1 AND session_id = $2",
+                &[&key.agent_id, &key.session_id],
+            ).await.map(|_| ()).map_err(|e| storage_error("PostgreSQL", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::memory::MemoryEntryType;
+
+    fn record(tier: MemoryTier, content: &str) -> MemoryRecord {
+        MemoryRecord::new(tier, MemoryEntry::new(MemoryEntryType::Context, content.to_string()))
+    }
+
+    async fn exercise_backend(backend: &dyn MemoryBackend) {
+        let key = MemoryKey::new("agent", "session-1");
+        let other = MemoryKey::new("agent", "session-2");
+        let episode = record(MemoryTier::ShortTerm, "asked about invoices");
+        let fact = record(MemoryTier::Semantic, "customer prefers email");
+
+        backend.write(&key, &[MemoryWrite::Upsert(episode.clone()), MemoryWrite::Upsert(fact.clone())]).await.unwrap();
+        backend.write(&other, &[MemoryWrite::Upsert(record(MemoryTier::LongTerm, "other session"))]).await.unwrap();
+
+        let episodic = backend.load(&key, &MemoryTier::EPISODIC).await.unwrap();
+        assert_eq!(episodic.len(), 1);
+        assert_eq!(episodic[0].entry.content, "asked about invoices");
+
+        let mut promoted = episode.clone();
+        promoted.tier = MemoryTier::LongTerm;
+        backend.write(&key, &[
+            MemoryWrite::Upsert(promoted),
+            MemoryWrite::Delete { entry_id: fact.entry.id.clone() },
+        ]).await.unwrap();
+
+        let all = backend.load(&key, &[MemoryTier::ShortTerm, MemoryTier::LongTerm, MemoryTier::Semantic]).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].tier, MemoryTier::LongTerm);
+
+        backend.clear(&key).await.unwrap();
+        assert!(backend.load(&key, &MemoryTier::EPISODIC).await.unwrap().is_empty());
+        assert_eq!(backend.load(&other, &MemoryTier::EPISODIC).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_backend() {
+        exercise_backend(&InMemoryMemoryBackend::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_tenant_backends_share_storage_not_memory() {
+        let shared: Arc<dyn MemoryBackend> = Arc::new(InMemoryMemoryBackend::new());
+        let acme = TenantMemoryBackend::new(shared.clone(), TenantScope::new("acme").unwrap());
+        let globex = TenantMemoryBackend::new(shared.clone(), TenantScope::new("globex").unwrap());
+        exercise_backend(&acme).await;
+
+        // Same agent and session under another tenant
+        let key = MemoryKey::new("agent", "session-2");
+        assert!(globex.load(&key, &MemoryTier::EPISODIC).await.unwrap().is_empty());
+        assert!(shared.load(&key, &MemoryTier::EPISODIC).await.unwrap().is_empty());
+        globex.clear(&key).await.unwrap();
+        assert_eq!(acme.load(&key, &MemoryTier::EPISODIC).await.unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "memory-sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_backend() {
+        exercise_backend(&SqliteMemoryBackend::in_memory().unwrap()).await;
+    }
+}
@@ -0,0 +1,531 @@
+// Prompt registry for agents
+// Versioned prompts and personas loaded from a directory or a store, reloaded on change while developing
+
+use super::AgentConfig;
+use crate::error::{GraphError, GraphResult};
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// One version of a named prompt
+///
+/// A prompt with model settings is a persona: applied to an agent, it
+/// replaces the system prompt and the settings it names.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptVersion {
+    pub name: String,
+    pub version: Version,
+    /// Prompt text, with `{variable}` placeholders
+    pub template: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+}
+
+impl PromptVersion {
+    pub fn new(name: impl Into<String>, version: Version, template: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            template: template.into(),
+            description: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            tools: None,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Whether the prompt carries model settings
+    pub fn is_persona(&self) -> bool {
+        self.model.is_some() || self.temperature.is_some() || self.max_tokens.is_some() || self.tools.is_some()
+    }
+
+    /// Short hash of the prompt's content, to tell apart versions edited in place
+    pub fn fingerprint(&self) -> String {
+        let content = serde_json::to_string(&(&self.template, &self.model, &self.temperature, &self.max_tokens, &self.tools))
+            .unwrap_or_default();
+        Sha256::digest(content.as_bytes())[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// The template with `{variable}` placeholders replaced
+    pub fn render(&self, variables: &HashMap<String, String>) -> String {
+        variables.iter().fold(self.template.clone(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+    }
+
+    /// Use the prompt as an agent's system prompt, along with the persona settings it has
+    pub fn apply_to(&self, config: &mut AgentConfig) {
+        config.system_prompt = self.template.clone();
+        if let Some(model) = &self.model {
+            config.model = model.clone();
+        }
+        if self.temperature.is_some() {
+            config.temperature = self.temperature;
+        }
+        if self.max_tokens.is_some() {
+            config.max_tokens = self.max_tokens;
+        }
+        if let Some(tools) = &self.tools {
+            config.available_tools = tools.clone();
+        }
+    }
+}
+
+/// Where a registry loads prompts from
+///
+/// Implement it over a database table to manage prompts outside the code.
+#[async_trait]
+pub trait PromptSource: Send + Sync + std::fmt::Debug {
+    /// Every version of every prompt
+    async fn load(&self) -> GraphResult<Vec<PromptVersion>>;
+
+    /// Token that changes whenever the prompts change, or `None` to reload on every check
+    async fn revision(&self) -> GraphResult<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Prompts in a directory, one file per version: `<root>/<name>/<version>.<ext>`
+///
+/// `.md` and `.txt` files hold the prompt text. `.toml`, `.yaml` and `.yml`
+/// files hold a `template` and optionally a `description` and the persona
+/// settings `model`, `temperature`, `max_tokens` and `tools`.
+#[derive(Debug, Clone)]
+pub struct DirectoryPromptSource {
+    root: PathBuf,
+}
+
+/// Prompt file without the name and version its path gives
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PromptFile {
+    template: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    max_tokens: Option<u32>,
+    #[serde(default)]
+    tools: Option<Vec<String>>,
+}
+
+impl DirectoryPromptSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Prompt files with their names and versions
+    fn files(&self) -> GraphResult<Vec<(String, Version, PathBuf)>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let dir = entry?.path();
+            let Some(name) = dir.file_name().and_then(|n| n.to_str()).map(str::to_string) else {
+                continue;
+            };
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                    continue;
+                };
+                if !path.is_file() || stem.starts_with('.') {
+                    continue;
+                }
+                let version = Version::parse(stem).map_err(|e| GraphError::ConfigurationError(format!(
+                    "Prompt file {} is not named after a semantic version: {}",
+                    path.display(),
+                    e
+                )))?;
+                files.push((name.clone(), version, path));
+            }
+        }
+        Ok(files)
+    }
+
+    fn parse(name: String, version: Version, path: &Path) -> GraphResult<PromptVersion> {
+        let text = std::fs::read_to_string(path)?;
+        let invalid = |e: String| GraphError::ConfigurationError(format!("Invalid prompt file {}: {}", path.display(), e));
+        let file: PromptFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("md") | Some("txt") => return Ok(PromptVersion::new(name, version, text.trim_end())),
+            Some("toml") => toml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+            _ => return Err(invalid("expected .md, .txt, .toml, .yaml or .yml".to_string())),
+        };
+        Ok(PromptVersion {
+            name,
+            version,
+            template: file.template,
+            description: file.description,
+            model: file.model,
+            temperature: file.temperature,
+            max_tokens: file.max_tokens,
+            tools: file.tools,
+        })
+    }
+}
+
+#[async_trait]
+impl PromptSource for DirectoryPromptSource {
+    async fn load(&self) -> GraphResult<Vec<PromptVersion>> {
+        self.files()?
+            .into_iter()
+            .map(|(name, version, path)| Self::parse(name, version, &path))
+            .collect()
+    }
+
+    async fn revision(&self) -> GraphResult<Option<String>> {
+        let mut stamps = Vec::new();
+        for (_, _, path) in self.files()? {
+            let modified = std::fs::metadata(&path)?.modified()?;
+            stamps.push((path, modified));
+        }
+        stamps.sort();
+        let digest = Sha256::digest(format!("{:?}", stamps).as_bytes());
+        Ok(Some(digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect()))
+    }
+}
+
+/// Prompts published at runtime, e.g. from an admin API
+#[derive(Debug, Default)]
+pub struct InMemoryPromptSource {
+    versions: RwLock<Vec<PromptVersion>>,
+    revision: AtomicU64,
+}
+
+impl InMemoryPromptSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a version; published versions cannot be changed
+    pub fn publish(&self, prompt: PromptVersion) -> GraphResult<()> {
+        let mut versions = self.versions.write();
+        if versions.iter().any(|p| p.name == prompt.name && p.version == prompt.version) {
+            return Err(GraphError::validation_error(format!(
+                "Prompt '{}' version {} is already published",
+                prompt.name, prompt.version
+            )));
+        }
+        versions.push(prompt);
+        self.revision.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PromptSource for InMemoryPromptSource {
+    async fn load(&self) -> GraphResult<Vec<PromptVersion>> {
+        Ok(self.versions.read().clone())
+    }
+
+    async fn revision(&self) -> GraphResult<Option<String>> {
+        Ok(Some(self.revision.load(Ordering::SeqCst).to_string()))
+    }
+}
+
+/// Versions of every prompt, and the versions rolled back to
+#[derive(Debug, Default)]
+struct Catalog {
+    prompts: BTreeMap<String, BTreeMap<Version, Arc<PromptVersion>>>,
+    pinned: HashMap<String, Version>,
+    revisions: Vec<Option<String>>,
+}
+
+impl Catalog {
+    /// Pinned version, else the latest release, else the latest pre-release
+    fn active(&self, name: &str) -> Option<&Arc<PromptVersion>> {
+        let versions = self.prompts.get(name)?;
+        if let Some(pinned) = self.pinned.get(name) {
+            return versions.get(pinned);
+        }
+        versions.values().rev().find(|p| p.version.pre.is_empty()).or_else(|| versions.values().next_back())
+    }
+}
+
+/// Versioned prompts and personas, resolved by name at run time
+///
+/// The latest release of a prompt is active unless it was rolled back to
+/// an earlier version. Every resolution is logged and, inside a node,
+/// emitted as a `prompt_resolved` event with the version and fingerprint,
+/// so the trace of an execution records the exact prompts it ran with.
+#[derive(Debug, Default)]
+pub struct PromptRegistry {
+    sources: Vec<Arc<dyn PromptSource>>,
+    hot_reload: Option<Duration>,
+    catalog: RwLock<Catalog>,
+    last_check: Mutex<Option<Instant>>,
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load prompts from `source`; a later source's version replaces an earlier source's
+    pub fn with_source(mut self, source: impl PromptSource + 'static) -> Self {
+        self.sources.push(Arc::new(source));
+        self
+    }
+
+    /// Check the sources for changes at most every `interval` when a prompt is resolved, for development
+    pub fn with_hot_reload(mut self, interval: Duration) -> Self {
+        self.hot_reload = Some(interval);
+        self
+    }
+
+    /// Load every source again, keeping rollbacks to versions that still exist
+    pub async fn load(&self) -> GraphResult<()> {
+        let mut prompts: BTreeMap<String, BTreeMap<Version, Arc<PromptVersion>>> = BTreeMap::new();
+        let mut revisions = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            revisions.push(source.revision().await?);
+            for prompt in source.load().await? {
+                prompts.entry(prompt.name.clone()).or_default().insert(prompt.version.clone(), Arc::new(prompt));
+            }
+        }
+
+        let mut catalog = self.catalog.write();
+        catalog.pinned.retain(|name, version| {
+            let kept = prompts.get(name).is_some_and(|versions| versions.contains_key(version));
+            if !kept {
+                tracing::warn!(prompt = %name, version = %version, "Pinned prompt version is gone, using the latest");
+            }
+            kept
+        });
+        catalog.prompts = prompts;
+        catalog.revisions = revisions;
+        Ok(())
+    }
+
+    /// Load the sources again if any of them changed, returning whether they did
+    pub async fn reload_if_changed(&self) -> GraphResult<bool> {
+        let mut revisions = Vec::with_capacity(self.sources.len());
+        for source in &self.sources {
+            revisions.push(source.revision().await?);
+        }
+        let unchanged = revisions.iter().all(Option::is_some) && self.catalog.read().revisions == revisions;
+        if unchanged {
+            return Ok(false);
+        }
+        self.load().await?;
+        tracing::info!("Reloaded prompt registry");
+        Ok(true)
+    }
+
+    /// Active version of a prompt
+    pub async fn get(&self, name: &str) -> GraphResult<Arc<PromptVersion>> {
+        self.check_for_changes().await;
+        let prompt = self.catalog.read().active(name).cloned().ok_or_else(|| self.unknown(name))?;
+        stamp(&prompt);
+        Ok(prompt)
+    }
+
+    /// Latest version of a prompt matching a requirement such as `^1.2`
+    pub async fn get_matching(&self, name: &str, requirement: &str) -> GraphResult<Arc<PromptVersion>> {
+        let requirement = VersionReq::parse(requirement).map_err(|e| {
+            GraphError::validation_error(format!("Invalid version requirement '{}': {}", requirement, e))
+        })?;
+        self.check_for_changes().await;
+        let prompt = {
+            let catalog = self.catalog.read();
+            let versions = catalog.prompts.get(name).ok_or_else(|| self.unknown(name))?;
+            versions.values().rev().find(|p| requirement.matches(&p.version)).cloned().ok_or_else(|| {
+                GraphError::ConfigurationError(format!("Prompt '{}' has no version matching {}", name, requirement))
+            })?
+        };
+        stamp(&prompt);
+        Ok(prompt)
+    }
+
+    /// Names of the loaded prompts
+    pub fn names(&self) -> Vec<String> {
+        self.catalog.read().prompts.keys().cloned().collect()
+    }
+
+    /// Versions of a prompt, oldest first
+    pub fn versions(&self, name: &str) -> Vec<Version> {
+        self.catalog.read().prompts.get(name).map(|v| v.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Version `get` returns, without recording a resolution
+    pub fn active_version(&self, name: &str) -> Option<Version> {
+        self.catalog.read().active(name).map(|p| p.version.clone())
+    }
+
+    /// Make `version` the active version of a prompt until it is unpinned
+    pub fn pin(&self, name: &str, version: &Version) -> GraphResult<()> {
+        let mut catalog = self.catalog.write();
+        let exists = catalog.prompts.get(name).ok_or_else(|| self.unknown(name))?.contains_key(version);
+        if !exists {
+            return Err(GraphError::ConfigurationError(format!("Prompt '{}' has no version {}", name, version)));
+        }
+        catalog.pinned.insert(name.to_string(), version.clone());
+        tracing::info!(prompt = %name, version = %version, "Pinned prompt version");
+        Ok(())
+    }
+
+    /// Roll a prompt back to the version before the active one, returning it
+    pub fn rollback(&self, name: &str) -> GraphResult<Version> {
+        let previous = {
+            let catalog = self.catalog.read();
+            let active = catalog.active(name).ok_or_else(|| self.unknown(name))?;
+            catalog.prompts[name].range(..active.version.clone()).next_back().map(|(version, _)| version.clone())
+        };
+        let previous = previous.ok_or_else(|| {
+            GraphError::ConfigurationError(format!("Prompt '{}' has no earlier version to roll back to", name))
+        })?;
+        self.pin(name, &previous)?;
+        Ok(previous)
+    }
+
+    /// Go back to the latest version of a prompt
+    pub fn unpin(&self, name: &str) {
+        self.catalog.write().pinned.remove(name);
+    }
+
+    async fn check_for_changes(&self) {
+        let Some(interval) = self.hot_reload else {
+            return;
+        };
+        {
+            let mut last_check = self.last_check.lock();
+            if last_check.is_some_and(|at| at.elapsed() < interval) {
+                return;
+            }
+            *last_check = Some(Instant::now());
+        }
+        // Keep serving the last good prompts while a file is being edited
+        if let Err(error) = self.reload_if_changed().await {
+            tracing::warn!(error = %error, "Failed to reload prompt registry");
+        }
+    }
+
+    fn unknown(&self, name: &str) -> GraphError {
+        GraphError::ConfigurationError(format!("Unknown prompt '{}'", name))
+    }
+}
+
+/// A prompt version resolved while a node ran, emitted as a `prompt_resolved` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptResolved {
+    pub node_id: String,
+    pub prompt: String,
+    pub version: Version,
+    pub fingerprint: String,
+}
+
+#[cfg(feature = "streaming")]
+impl crate::streaming::CustomEvent for PromptResolved {
+    const EVENT_TYPE: &'static str = "prompt_resolved";
+}
+
+/// Record which version of a prompt was used
+fn stamp(prompt: &PromptVersion) {
+    let fingerprint = prompt.fingerprint();
+    tracing::debug!(prompt = %prompt.name, version = %prompt.version, fingerprint = %fingerprint, "Resolved prompt");
+
+    #[cfg(feature = "streaming")]
+    crate::streaming::emit_node_custom(|node_id| PromptResolved {
+        node_id,
+        prompt: prompt.name.clone(),
+        version: prompt.version.clone(),
+        fingerprint,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_registry_versions_and_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("triage")).unwrap();
+        std::fs::write(dir.path().join("triage/1.0.0.md"), "Sort {ticket} by urgency.\n").unwrap();
+        std::fs::write(dir.path().join("triage/1.1.0.yaml"), "template: Sort {ticket} by urgency and product.\nmodel: small\ntemperature: 0.1\n").unwrap();
+        std::fs::write(dir.path().join("triage/2.0.0-rc.1.md"), "Experimental").unwrap();
+
+        let published = InMemoryPromptSource::new();
+        published.publish(PromptVersion::new("greeting", version("1.0.0"), "Hello {name}")).unwrap();
+        assert!(published.publish(PromptVersion::new("greeting", version("1.0.0"), "Hi")).is_err());
+
+        let registry = PromptRegistry::new()
+            .with_source(DirectoryPromptSource::new(dir.path()))
+            .with_source(published);
+        registry.load().await.unwrap();
+        assert_eq!(registry.names(), vec!["greeting", "triage"]);
+
+        // Pre-releases are only used when asked for
+        let triage = registry.get("triage").await.unwrap();
+        assert_eq!(triage.version, version("1.1.0"));
+        assert!(triage.is_persona());
+        let mut config = AgentConfig::default();
+        triage.apply_to(&mut config);
+        assert_eq!((config.model.as_str(), config.temperature), ("small", Some(0.1)));
+        assert_eq!(registry.get_matching("triage", ">=2.0.0-rc.1").await.unwrap().template, "Experimental");
+        assert_eq!(registry.get_matching("triage", "~1.0").await.unwrap().version, version("1.0.0"));
+
+        let variables = HashMap::from([("ticket".to_string(), "#42".to_string())]);
+        assert_eq!(registry.rollback("triage").unwrap(), version("1.0.0"));
+        assert_eq!(registry.get("triage").await.unwrap().render(&variables), "Sort #42 by urgency.");
+        assert!(registry.rollback("triage").is_err());
+        registry.unpin("triage");
+        assert_eq!(registry.active_version("triage"), Some(version("1.1.0")));
+        assert!(registry.get("missing").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registry_reloads_changed_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("summary")).unwrap();
+        std::fs::write(dir.path().join("summary/1.0.0.txt"), "Summarize.").unwrap();
+
+        let registry = PromptRegistry::new()
+            .with_source(DirectoryPromptSource::new(dir.path()))
+            .with_hot_reload(Duration::ZERO);
+        registry.load().await.unwrap();
+        assert!(!registry.reload_if_changed().await.unwrap());
+
+        std::fs::write(dir.path().join("summary/1.0.1.txt"), "Summarize briefly.").unwrap();
+        assert_eq!(registry.get("summary").await.unwrap().template, "Summarize briefly.");
+
+        // A broken file keeps the last good prompts
+        std::fs::write(dir.path().join("summary/not-a-version.txt"), "?").unwrap();
+        assert_eq!(registry.get("summary").await.unwrap().version, version("1.0.1"));
+    }
+}
@@ -0,0 +1,177 @@
+// Reranking of retrieved chunks for AgentGraph
+// Rescores vector search hits against the query with a cross-encoder or a rerank API before they reach the prompt
+
+#![allow(missing_docs)]
+
+use super::memory::MemoryError;
+use super::vector::VectorMatch;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Scores how relevant documents are to a query
+///
+/// Rerankers read the query and each document together, so they rank more
+/// precisely than embedding similarity but are too slow to run over a whole
+/// collection. They are run over a vector search's candidates instead.
+#[async_trait]
+pub trait Reranker: Send + Sync + std::fmt::Debug {
+    /// Relevance of each of `documents` to `query`, in the same order; higher is more relevant
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, MemoryError>;
+}
+
+/// Order `matches` by the reranker's scores and keep the best `top_n`
+///
+/// Each kept match carries its score in `rerank_score`; `score` stays the
+/// vector similarity.
+pub async fn rerank(
+    reranker: &dyn Reranker,
+    query: &str,
+    mut matches: Vec<VectorMatch>,
+    top_n: usize,
+) -> Result<Vec<VectorMatch>, MemoryError> {
+    if matches.is_empty() {
+        return Ok(matches);
+    }
+    let documents: Vec<String> = matches.iter().map(|m| m.content.clone()).collect();
+    let scores = reranker.score(query, &documents).await?;
+    if scores.len() != matches.len() {
+        return Err(MemoryError::RetrievalError {
+            message: format!("Reranker returned {} scores for {} documents", scores.len(), matches.len()),
+        });
+    }
+    for (m, score) in matches.iter_mut().zip(scores) {
+        m.rerank_score = Some(score);
+    }
+    matches.sort_by(|a, b| b.rerank_score.partial_cmp(&a.rerank_score).unwrap_or(std::cmp::Ordering::Equal));
+    matches.truncate(top_n);
+    Ok(matches)
+}
+
+/// Reranker backed by the Cohere Rerank API
+#[derive(Debug, Clone)]
+pub struct CohereReranker {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+impl CohereReranker {
+    /// Rerank with `rerank-v3.5`
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(15))
+                .build()
+                .unwrap_or_default(),
+            api_key: api_key.into(),
+            base_url: "https://api.cohere.com/v2".to_string(),
+            model: "rerank-v3.5".to_string(),
+        }
+    }
+
+    /// Reranker using `COHERE_API_KEY`, if it is set
+    pub fn from_env() -> Option<Self> {
+        std::env::var("COHERE_API_KEY").ok().filter(|key| !key.is_empty()).map(Self::new)
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+}
+
+#[async_trait]
+impl Reranker for CohereReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, MemoryError> {
+        let failed = |message: String| MemoryError::RetrievalError {
+            message: format!("Cohere rerank failed: {}", message),
+        };
+        let response = self
+            .client
+            .post(format!("{}/rerank", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "model": self.model,
+                "query": query,
+                "documents": documents,
+                "top_n": documents.len(),
+            }))
+            .send()
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.map_err(|e| failed(format!("invalid response: {}", e)))?;
+        if !status.is_success() {
+            return Err(failed(format!("HTTP {}: {}", status, body["message"].as_str().unwrap_or("unknown error"))));
+        }
+        parse_cohere_results(&body, documents.len()).ok_or_else(|| failed("response has no results".to_string()))
+    }
+}
+
+/// Scores in document order from a rerank response's `results`
+///
+/// Documents the response leaves out score 0.0.
+fn parse_cohere_results(body: &serde_json::Value, documents: usize) -> Option<Vec<f32>> {
+    let mut scores = vec![0.0; documents];
+    for result in body["results"].as_array()? {
+        let index = result["index"].as_u64()? as usize;
+        if let Some(score) = scores.get_mut(index) {
+            *score = result["relevance_score"].as_f64()? as f32;
+        }
+    }
+    Some(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Scores by how many query words a document contains
+    #[derive(Debug)]
+    struct OverlapReranker;
+
+    #[async_trait]
+    impl Reranker for OverlapReranker {
+        async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, MemoryError> {
+            Ok(documents
+                .iter()
+                .map(|document| query.split_whitespace().filter(|word| document.contains(word)).count() as f32)
+                .collect())
+        }
+    }
+
+    fn hit(id: &str, content: &str, score: f32) -> VectorMatch {
+        VectorMatch {
+            id: id.to_string(),
+            collection: "docs".to_string(),
+            content: content.to_string(),
+            metadata: HashMap::new(),
+            score,
+            rerank_score: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerank_reorders_and_truncates() {
+        let matches = vec![
+            hit("a", "remote work policy", 0.9),
+            hit("b", "expense approval for travel", 0.8),
+            hit("c", "travel expense limits", 0.7),
+        ];
+        let reranked = rerank(&OverlapReranker, "travel expense", matches, 2).await.unwrap();
+        let ids: Vec<&str> = reranked.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["b", "c"]);
+        assert_eq!(reranked[0].rerank_score, Some(2.0));
+        assert_eq!(reranked[0].score, 0.8);
+
+        let body = serde_json::json!({"results": [{"index": 1, "relevance_score": 0.9}, {"index": 0, "relevance_score": 0.2}]});
+        assert_eq!(parse_cohere_results(&body, 3), Some(vec![0.2, 0.9, 0.0]));
+    }
+}
@@ -46,6 +46,9 @@ impl RoleTemplate {
             available_tools: self.tools.clone(),
             memory_config: self.memory_config.clone(),
             collaboration_config: self.collaboration_config.clone(),
+            request_logprobs: false,
+            tool_policy: crate::tools::ToolPolicy::default(),
+            conversation_limits: Default::default(),
         }
     }
 }
@@ -335,6 +338,8 @@ You have access to research, file operations, and knowledge base tools. Always:
                 "text_search".to_string(),
                 "http_get".to_string(),
                 "file_read".to_string(),
+                "email_read".to_string(),
+                "email_send".to_string(),
             ],
             model: "gpt-3.5-turbo".to_string(), // Faster model for real-time support
             temperature: 0.6,
@@ -0,0 +1,527 @@
+// Agent runtime for AgentGraph
+// Keeps a pool of warm agents, dispatches tasks through per-agent mailboxes and evicts idle agents
+
+#![allow(missing_docs)]
+
+use super::{Agent, AgentConfig, AgentError};
+use crate::llm::budget::{current_priority, with_priority, ExecutionPriority};
+use crate::llm::LLMManager;
+use crate::tools::{ToolExecutor, ToolRegistry};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Runtime configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRuntimeConfig {
+    /// Tasks that can wait in an agent's mailbox
+    pub mailbox_capacity: usize,
+    /// Idle time after which an agent is evicted
+    pub idle_timeout: Duration,
+    /// How often idle agents are looked for
+    pub eviction_interval: Duration,
+    /// Most agents kept instantiated at once
+    pub max_agents: usize,
+}
+
+impl Default for AgentRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            mailbox_capacity: 32,
+            idle_timeout: Duration::from_secs(600),
+            eviction_interval: Duration::from_secs(30),
+            max_agents: 100,
+        }
+    }
+}
+
+/// What an agent is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgentRunState {
+    /// Waiting for tasks
+    Idle,
+    /// Executing a task
+    Busy,
+    /// Worker exited unexpectedly
+    Failed,
+    /// Worker shut down
+    Stopped,
+}
+
+/// Status of one pooled agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStatusReport {
+    pub agent_id: String,
+    pub state: AgentRunState,
+    /// Tasks waiting in the mailbox
+    pub queued: usize,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    pub started_at: SystemTime,
+    pub last_active: SystemTime,
+    pub last_error: Option<String>,
+}
+
+/// Health of the whole runtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeHealth {
+    /// Agents instantiated
+    pub agents: usize,
+    pub busy: usize,
+    pub idle: usize,
+    /// Tasks waiting across all mailboxes
+    pub queued: usize,
+    /// Agents whose worker exited unexpectedly
+    pub failed: Vec<String>,
+    /// Agents that can be instantiated on demand
+    pub registered: usize,
+}
+
+impl RuntimeHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A task waiting in a mailbox
+struct Envelope {
+    task: String,
+    /// Priority of the dispatching execution, restored in the worker
+    priority: ExecutionPriority,
+    reply: oneshot::Sender<Result<String, AgentError>>,
+}
+
+/// Handle to a running agent worker
+struct AgentHandle {
+    mailbox: mpsc::Sender<Envelope>,
+    queued: Arc<AtomicUsize>,
+    status: Arc<RwLock<AgentStatusReport>>,
+    worker: JoinHandle<()>,
+}
+
+impl AgentHandle {
+    fn report(&self) -> AgentStatusReport {
+        let mut report = self.status.read().unwrap().clone();
+        report.queued = self.queued.load(Ordering::SeqCst);
+        if self.worker.is_finished() && report.state != AgentRunState::Stopped {
+            report.state = AgentRunState::Failed;
+        }
+        report
+    }
+
+    fn is_evictable(&self, idle_timeout: Duration, now: SystemTime) -> bool {
+        let report = self.report();
+        match report.state {
+            AgentRunState::Failed | AgentRunState::Stopped => true,
+            AgentRunState::Busy => false,
+            AgentRunState::Idle => {
+                report.queued == 0
+                    && now.duration_since(report.last_active).unwrap_or(Duration::ZERO) >= idle_timeout
+            }
+        }
+    }
+}
+
+/// Pool of long-lived agents
+///
+/// Agents are registered by configuration and instantiated on first use, then
+/// kept warm with their LLM clients, conversation and memory. Each agent has a
+/// mailbox and works through its tasks one at a time, while different agents
+/// run concurrently. Agents idle for longer than `idle_timeout` are evicted and
+/// their memory flushed; they are re-created on the next task.
+pub struct AgentRuntime {
+    config: AgentRuntimeConfig,
+    llm_manager: Arc<LLMManager>,
+    tool_registry: Arc<ToolRegistry>,
+    tool_executor: Arc<ToolExecutor>,
+    registered: RwLock<HashMap<String, AgentConfig>>,
+    agents: RwLock<HashMap<String, AgentHandle>>,
+    eviction_task: RwLock<Option<JoinHandle<()>>>,
+}
+
+impl std::fmt::Debug for AgentRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentRuntime")
+            .field("config", &self.config)
+            .field("registered", &self.registered.read().unwrap().keys().collect::<Vec<_>>())
+            .field("agents", &self.agents.read().unwrap().keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl AgentRuntime {
+    /// Create a runtime sharing LLM and tool infrastructure across its agents
+    pub fn new(
+        config: AgentRuntimeConfig,
+        llm_manager: Arc<LLMManager>,
+        tool_registry: Arc<ToolRegistry>,
+        tool_executor: Arc<ToolExecutor>,
+    ) -> Self {
+        Self {
+            config,
+            llm_manager,
+            tool_registry,
+            tool_executor,
+            registered: RwLock::new(HashMap::new()),
+            agents: RwLock::new(HashMap::new()),
+            eviction_task: RwLock::new(None),
+        }
+    }
+
+    /// Register an agent to be instantiated on first use
+    pub fn register(&self, agent_id: &str, config: AgentConfig) {
+        self.registered.write().unwrap().insert(agent_id.to_string(), config);
+    }
+
+    /// Add an already constructed agent to the pool
+    ///
+    /// Agents added this way are not re-created after eviction unless a
+    /// configuration is also registered for them.
+    pub fn insert(&self, agent_id: &str, agent: Agent) -> Result<(), RuntimeError> {
+        let mut agents = self.agents.write().unwrap();
+        self.make_room(&mut agents)?;
+        agents.insert(agent_id.to_string(), self.spawn_worker(agent_id, agent));
+        Ok(())
+    }
+
+    /// Run a task on an agent, waiting for mailbox space if it is full
+    pub async fn dispatch(&self, agent_id: &str, task: String) -> Result<String, RuntimeError> {
+        let (mailbox, queued) = self.mailbox(agent_id)?;
+        let (reply, response) = oneshot::channel();
+
+        queued.fetch_add(1, Ordering::SeqCst);
+        if mailbox.send(Envelope { task, priority: current_priority(), reply }).await.is_err() {
+            queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(RuntimeError::AgentStopped { agent_id: agent_id.to_string() });
+        }
+        Self::await_reply(agent_id, response).await
+    }
+
+    /// Run a task on an agent, failing at once if its mailbox is full
+    pub async fn try_dispatch(&self, agent_id: &str, task: String) -> Result<String, RuntimeError> {
+        let (mailbox, queued) = self.mailbox(agent_id)?;
+        let (reply, response) = oneshot::channel();
+
+        queued.fetch_add(1, Ordering::SeqCst);
+        if let Err(error) = mailbox.try_send(Envelope { task, priority: current_priority(), reply }) {
+            queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(match error {
+                mpsc::error::TrySendError::Full(_) => RuntimeError::MailboxFull { agent_id: agent_id.to_string() },
+                mpsc::error::TrySendError::Closed(_) => RuntimeError::AgentStopped { agent_id: agent_id.to_string() },
+            });
+        }
+        Self::await_reply(agent_id, response).await
+    }
+
+    async fn await_reply(
+        agent_id: &str,
+        response: oneshot::Receiver<Result<String, AgentError>>,
+    ) -> Result<String, RuntimeError> {
+        match response.await {
+            Ok(result) => result.map_err(|source| RuntimeError::TaskFailed {
+                agent_id: agent_id.to_string(),
+                source,
+            }),
+            // The worker dropped the task, which only happens when it dies mid-task
+            Err(_) => Err(RuntimeError::AgentStopped { agent_id: agent_id.to_string() }),
+        }
+    }
+
+    /// Mailbox of a live agent, instantiating the agent if needed
+    fn mailbox(&self, agent_id: &str) -> Result<(mpsc::Sender<Envelope>, Arc<AtomicUsize>), RuntimeError> {
+        if let Some(handle) = self.agents.read().unwrap().get(agent_id) {
+            if !handle.worker.is_finished() {
+                return Ok((handle.mailbox.clone(), handle.queued.clone()));
+            }
+        }
+
+        let config = self.registered.read().unwrap().get(agent_id).cloned();
+        let mut agents = self.agents.write().unwrap();
+        // Another caller may have started the agent meanwhile
+        if let Some(handle) = agents.get(agent_id) {
+            if !handle.worker.is_finished() {
+                return Ok((handle.mailbox.clone(), handle.queued.clone()));
+            }
+            tracing::warn!("Agent '{}' worker exited unexpectedly, restarting", agent_id);
+            agents.remove(agent_id);
+        }
+
+        let config = config.ok_or_else(|| RuntimeError::UnknownAgent { agent_id: agent_id.to_string() })?;
+        let agent = Agent::new(
+            config,
+            self.llm_manager.clone(),
+            self.tool_registry.clone(),
+            self.tool_executor.clone(),
+        ).map_err(|source| RuntimeError::TaskFailed { agent_id: agent_id.to_string(), source })?;
+
+        self.make_room(&mut agents)?;
+        let handle = self.spawn_worker(agent_id, agent);
+        let mailbox = (handle.mailbox.clone(), handle.queued.clone());
+        agents.insert(agent_id.to_string(), handle);
+        tracing::info!("Instantiated agent '{}'", agent_id);
+        Ok(mailbox)
+    }
+
+    /// Evict the least recently active idle agent when the pool is full
+    fn make_room(&self, agents: &mut HashMap<String, AgentHandle>) -> Result<(), RuntimeError> {
+        if agents.len() < self.config.max_agents {
+            return Ok(());
+        }
+
+        let victim = agents
+            .iter()
+            .filter(|(_, handle)| handle.is_evictable(Duration::ZERO, SystemTime::now()))
+            .min_by_key(|(_, handle)| handle.report().last_active)
+            .map(|(agent_id, _)| agent_id.clone());
+
+        match victim {
+            Some(agent_id) => {
+                agents.remove(&agent_id);
+                tracing::info!("Evicted agent '{}' to make room", agent_id);
+                Ok(())
+            }
+            None => Err(RuntimeError::CapacityExceeded { max_agents: self.config.max_agents }),
+        }
+    }
+
+    fn spawn_worker(&self, agent_id: &str, agent: Agent) -> AgentHandle {
+        let (mailbox, inbox) = mpsc::channel(self.config.mailbox_capacity.max(1));
+        let queued = Arc::new(AtomicUsize::new(0));
+        let now = SystemTime::now();
+        let status = Arc::new(RwLock::new(AgentStatusReport {
+            agent_id: agent_id.to_string(),
+            state: AgentRunState::Idle,
+            queued: 0,
+            tasks_completed: 0,
+            tasks_failed: 0,
+            started_at: now,
+            last_active: now,
+            last_error: None,
+        }));
+
+        let worker = tokio::spawn(run_worker(agent, inbox, queued.clone(), status.clone()));
+        AgentHandle { mailbox, queued, status, worker }
+    }
+
+    /// Status of one agent, if instantiated
+    pub fn status(&self, agent_id: &str) -> Option<AgentStatusReport> {
+        self.agents.read().unwrap().get(agent_id).map(AgentHandle::report)
+    }
+
+    /// Status of every instantiated agent
+    pub fn statuses(&self) -> Vec<AgentStatusReport> {
+        let mut reports: Vec<_> = self.agents.read().unwrap().values().map(AgentHandle::report).collect();
+        reports.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        reports
+    }
+
+    /// Summary of the runtime's health
+    pub fn health(&self) -> RuntimeHealth {
+        let reports = self.statuses();
+        RuntimeHealth {
+            agents: reports.len(),
+            busy: reports.iter().filter(|r| r.state == AgentRunState::Busy).count(),
+            idle: reports.iter().filter(|r| r.state == AgentRunState::Idle).count(),
+            queued: reports.iter().map(|r| r.queued).sum(),
+            failed: reports.iter()
+                .filter(|r| r.state == AgentRunState::Failed)
+                .map(|r| r.agent_id.clone())
+                .collect(),
+            registered: self.registered.read().unwrap().len(),
+        }
+    }
+
+    /// Evict agents idle for longer than `idle_timeout`, returning their IDs
+    ///
+    /// Dropping an agent's mailbox lets its worker finish and flush memory.
+    pub fn evict_idle(&self) -> Vec<String> {
+        let now = SystemTime::now();
+        let mut agents = self.agents.write().unwrap();
+        let evicted: Vec<String> = agents
+            .iter()
+            .filter(|(_, handle)| handle.is_evictable(self.config.idle_timeout, now))
+            .map(|(agent_id, _)| agent_id.clone())
+            .collect();
+        for agent_id in &evicted {
+            agents.remove(agent_id);
+        }
+        if !evicted.is_empty() {
+            tracing::info!("Evicted {} idle agents", evicted.len());
+        }
+        evicted
+    }
+
+    /// Evict idle agents every `eviction_interval` until the runtime is dropped
+    pub fn start_idle_eviction(self: &Arc<Self>) {
+        let runtime: Weak<Self> = Arc::downgrade(self);
+        let interval = self.config.eviction_interval;
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match runtime.upgrade() {
+                    Some(runtime) => {
+                        runtime.evict_idle();
+                    }
+                    None => break,
+                }
+            }
+        });
+
+        if let Some(previous) = self.eviction_task.write().unwrap().replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// Stop all agents after they finish their queued tasks
+    pub async fn shutdown(&self) {
+        if let Some(task) = self.eviction_task.write().unwrap().take() {
+            task.abort();
+        }
+        let handles: Vec<AgentHandle> = self.agents.write().unwrap().drain().map(|(_, handle)| handle).collect();
+        for handle in handles {
+            drop(handle.mailbox);
+            let _ = handle.worker.await;
+        }
+    }
+}
+
+impl Drop for AgentRuntime {
+    fn drop(&mut self) {
+        if let Some(task) = self.eviction_task.write().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+/// Work through an agent's mailbox until it is closed
+async fn run_worker(
+    mut agent: Agent,
+    mut inbox: mpsc::Receiver<Envelope>,
+    queued: Arc<AtomicUsize>,
+    status: Arc<RwLock<AgentStatusReport>>,
+) {
+    while let Some(envelope) = inbox.recv().await {
+        queued.fetch_sub(1, Ordering::SeqCst);
+        status.write().unwrap().state = AgentRunState::Busy;
+
+        let result = with_priority(envelope.priority, agent.execute_task(envelope.task)).await;
+
+        {
+            let mut status = status.write().unwrap();
+            status.state = AgentRunState::Idle;
+            status.last_active = SystemTime::now();
+            match &result {
+                Ok(_) => status.tasks_completed += 1,
+                Err(e) => {
+                    status.tasks_failed += 1;
+                    status.last_error = Some(e.to_string());
+                }
+            }
+        }
+        // The caller may have stopped waiting
+        let _ = envelope.reply.send(result);
+    }
+
+    if let Err(e) = agent.memory_mut().flush().await {
+        tracing::warn!("Failed to flush memory of agent '{}': {}", agent.config().name, e);
+    }
+    status.write().unwrap().state = AgentRunState::Stopped;
+}
+
+/// Errors from the agent runtime
+#[derive(Debug, Error, Clone)]
+pub enum RuntimeError {
+    #[error("Agent '{agent_id}' is not registered")]
+    UnknownAgent { agent_id: String },
+
+    #[error("Mailbox of agent '{agent_id}' is full")]
+    MailboxFull { agent_id: String },
+
+    #[error("Agent pool is full ({max_agents} agents busy)")]
+    CapacityExceeded { max_agents: usize },
+
+    #[error("Agent '{agent_id}' stopped before finishing the task")]
+    AgentStopped { agent_id: String },
+
+    #[error("Agent '{agent_id}' failed: {source}")]
+    TaskFailed { agent_id: String, source: AgentError },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::roles::RoleTemplates;
+    use crate::llm::{LLMConfig, providers::MockProvider};
+
+    fn runtime(config: AgentRuntimeConfig) -> AgentRuntime {
+        let mut llm_manager = LLMManager::new(LLMConfig::default());
+        llm_manager.register_provider("mock".to_string(), Arc::new(MockProvider::new()));
+        let runtime = AgentRuntime::new(
+            config,
+            Arc::new(llm_manager),
+            Arc::new(ToolRegistry::new()),
+            Arc::new(ToolExecutor::new()),
+        );
+        for name in ["researcher", "writer"] {
+            let agent_config = RoleTemplates::research_analyst().to_agent_config(name.to_string(), "mock".to_string());
+            runtime.register(name, agent_config);
+        }
+        runtime
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_keeps_agents_warm() {
+        let runtime = runtime(AgentRuntimeConfig::default());
+        assert!(runtime.status("researcher").is_none());
+
+        let (first, second) = tokio::join!(
+            runtime.dispatch("researcher", "Find sources".to_string()),
+            runtime.dispatch("writer", "Draft intro".to_string()),
+        );
+        assert!(!first.unwrap().is_empty());
+        assert!(!second.unwrap().is_empty());
+
+        runtime.dispatch("researcher", "Summarize sources".to_string()).await.unwrap();
+        let status = runtime.status("researcher").unwrap();
+        assert_eq!(status.state, AgentRunState::Idle);
+        assert_eq!(status.tasks_completed, 2);
+
+        let health = runtime.health();
+        assert_eq!(health.agents, 2);
+        assert!(health.is_healthy());
+
+        assert!(matches!(
+            runtime.dispatch("editor", "Edit".to_string()).await,
+            Err(RuntimeError::UnknownAgent { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_idle_eviction_and_capacity() {
+        let runtime = runtime(AgentRuntimeConfig {
+            idle_timeout: Duration::ZERO,
+            max_agents: 1,
+            ..Default::default()
+        });
+
+        runtime.dispatch("researcher", "Find sources".to_string()).await.unwrap();
+        // The idle researcher makes room for the writer
+        runtime.dispatch("writer", "Draft intro".to_string()).await.unwrap();
+        assert!(runtime.status("researcher").is_none());
+
+        assert_eq!(runtime.evict_idle(), vec!["writer".to_string()]);
+        assert_eq!(runtime.health().agents, 0);
+
+        // Evicted agents come back on demand
+        runtime.dispatch("writer", "Draft outro".to_string()).await.unwrap();
+        assert_eq!(runtime.status("writer").unwrap().tasks_completed, 1);
+        runtime.shutdown().await;
+        assert_eq!(runtime.health().agents, 0);
+    }
+}
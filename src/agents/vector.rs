@@ -0,0 +1,414 @@
+// Vector store layer for AgentGraph memory
+// Stores embedded chunks in named collections and ranks them by cosine similarity
+
+#![allow(missing_docs)]
+
+use super::memory::MemoryError;
+use crate::state::tenant::TenantScope;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+/// Turns text into embedding vectors
+#[async_trait]
+pub trait Embedder: Send + Sync + std::fmt::Debug {
+    /// Embed a batch of texts
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, MemoryError>;
+
+    /// Dimension of produced vectors
+    fn dimensions(&self) -> usize;
+}
+
+/// Embedder based on hashed word features
+///
+/// Needs no model or network access, which makes it suitable for tests and
+/// keyword-heavy corpora. Use a model-backed embedder for semantic recall.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            let mut hasher = DefaultHasher::new();
+            word.to_lowercase().hash(&mut hasher);
+            let hash = hasher.finish();
+            let sign = if hash & 1 == 0 { 1.0 } else { -1.0 };
+            vector[(hash >> 1) as usize % self.dimensions] += sign;
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[async_trait]
+impl Embedder for HashingEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, MemoryError> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
+
+/// A chunk of text stored in a collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorRecord {
+    /// Unique ID within the collection
+    pub id: String,
+    /// Chunk text
+    pub content: String,
+    /// Metadata used for filtering
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Embedding vector
+    pub embedding: Vec<f32>,
+}
+
+impl VectorRecord {
+    pub fn new<I: Into<String>, C: Into<String>>(id: I, content: C, embedding: Vec<f32>) -> Self {
+        Self {
+            id: id.into(),
+            content: content.into(),
+            metadata: HashMap::new(),
+            embedding,
+        }
+    }
+
+    /// Add metadata
+    pub fn with_metadata<T: Serialize>(mut self, key: &str, value: T) -> Self {
+        self.metadata.insert(
+            key.to_string(),
+            serde_json::to_value(value).unwrap_or(serde_json::Value::Null),
+        );
+        self
+    }
+}
+
+/// A ranked search hit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorMatch {
+    /// Record ID
+    pub id: String,
+    /// Collection the record came from
+    pub collection: String,
+    /// Chunk text
+    pub content: String,
+    /// Record metadata
+    pub metadata: HashMap<String, serde_json::Value>,
+    /// Cosine similarity to the query
+    pub score: f32,
+    /// Relevance assigned by a [`Reranker`](super::rerank::Reranker), if the match was reranked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+}
+
+/// Metadata filter applied before ranking
+///
+/// Every key must be present on a record. A scalar value must be equal to
+/// the record's value; an array value matches if the record's value is any
+/// of its elements.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetadataFilter {
+    pub conditions: HashMap<String, serde_json::Value>,
+}
+
+impl MetadataFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require a metadata key to match a value
+    pub fn with_condition<T: Serialize>(mut self, key: &str, value: T) -> Self {
+        self.conditions.insert(
+            key.to_string(),
+            serde_json::to_value(value).unwrap_or(serde_json::Value::Null),
+        );
+        self
+    }
+
+    /// Check whether a record satisfies the filter
+    pub fn matches(&self, metadata: &HashMap<String, serde_json::Value>) -> bool {
+        self.conditions.iter().all(|(key, expected)| match (metadata.get(key), expected) {
+            (Some(actual), serde_json::Value::Array(options)) => options.contains(actual),
+            (Some(actual), expected) => actual == expected,
+            (None, _) => false,
+        })
+    }
+}
+
+/// Storage for embedded chunks organised into collections
+#[async_trait]
+pub trait VectorStore: Send + Sync + std::fmt::Debug {
+    /// Insert or replace records in a collection, creating it if needed
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<(), MemoryError>;
+
+    /// Rank records in a collection by similarity to a query vector
+    async fn search(
+        &self,
+        collection: &str,
+        query: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<VectorMatch>, MemoryError>;
+
+    /// Remove records by ID, returning how many were removed
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<usize, MemoryError>;
+
+    /// Names of existing collections
+    async fn collections(&self) -> Result<Vec<String>, MemoryError>;
+}
+
+/// Vector store kept in process memory
+#[derive(Debug, Default)]
+pub struct InMemoryVectorStore {
+    collections: RwLock<HashMap<String, HashMap<String, VectorRecord>>>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of records in a collection
+    pub fn count(&self, collection: &str) -> usize {
+        self.collections.read().unwrap()
+            .get(collection)
+            .map_or(0, |records| records.len())
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<(), MemoryError> {
+        let mut collections = self.collections.write().map_err(|_| MemoryError::StorageError {
+            message: "Vector store lock poisoned".to_string(),
+        })?;
+        let entries = collections.entry(collection.to_string()).or_default();
+
+        if let Some(expected) = entries.values().next().map(|r| r.embedding.len()) {
+            if let Some(record) = records.iter().find(|r| r.embedding.len() != expected) {
+                return Err(MemoryError::StorageError {
+                    message: format!(
+                        "Record '{}' has {} dimensions, collection '{}' uses {}",
+                        record.id, record.embedding.len(), collection, expected
+                    ),
+                });
+            }
+        }
+
+        for record in records {
+            entries.insert(record.id.clone(), record);
+        }
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        collection: &str,
+        query: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<VectorMatch>, MemoryError> {
+        let collections = self.collections.read().map_err(|_| MemoryError::RetrievalError {
+            message: "Vector store lock poisoned".to_string(),
+        })?;
+        let records = collections.get(collection)
+            .ok_or_else(|| MemoryError::RetrievalError {
+                message: format!("Collection '{}' does not exist", collection),
+            })?;
+
+        let mut matches: Vec<VectorMatch> = records.values()
+            .filter(|record| filter.is_none_or(|f| f.matches(&record.metadata)))
+            .map(|record| VectorMatch {
+                id: record.id.clone(),
+                collection: collection.to_string(),
+                content: record.content.clone(),
+                metadata: record.metadata.clone(),
+                score: cosine_similarity(query, &record.embedding),
+                rerank_score: None,
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        matches.truncate(top_k);
+        Ok(matches)
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<usize, MemoryError> {
+        let mut collections = self.collections.write().map_err(|_| MemoryError::StorageError {
+            message: "Vector store lock poisoned".to_string(),
+        })?;
+        Ok(collections.get_mut(collection)
+            .map_or(0, |records| ids.iter().filter(|id| records.remove(*id).is_some()).count()))
+    }
+
+    async fn collections(&self) -> Result<Vec<String>, MemoryError> {
+        let collections = self.collections.read().map_err(|_| MemoryError::RetrievalError {
+            message: "Vector store lock poisoned".to_string(),
+        })?;
+        let mut names: Vec<String> = collections.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// Collections of one tenant, stored under tenant-prefixed names
+///
+/// Tenants may use the same collection names without seeing each other's
+/// records; collections are listed and matched under the names the tenant
+/// gave them.
+#[derive(Debug, Clone)]
+pub struct TenantVectorStore {
+    inner: Arc<dyn VectorStore>,
+    scope: TenantScope,
+}
+
+impl TenantVectorStore {
+    pub fn new(inner: Arc<dyn VectorStore>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+}
+
+#[async_trait]
+impl VectorStore for TenantVectorStore {
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<(), MemoryError> {
+        self.inner.upsert(&self.scope.prefix(collection), records).await
+    }
+
+    async fn search(
+        &self,
+        collection: &str,
+        query: &[f32],
+        top_k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<VectorMatch>, MemoryError> {
+        let mut matches = self.inner.search(&self.scope.prefix(collection), query, top_k, filter).await?;
+        for found in &mut matches {
+            found.collection = collection.to_string();
+        }
+        Ok(matches)
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<usize, MemoryError> {
+        self.inner.delete(&self.scope.prefix(collection), ids).await
+    }
+
+    async fn collections(&self) -> Result<Vec<String>, MemoryError> {
+        let names = self.inner.collections().await?;
+        Ok(names.iter().filter_map(|name| self.scope.strip(name)).map(str::to_string).collect())
+    }
+}
+
+/// Cosine similarity of two vectors, 0.0 when either is zero or lengths differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn seeded_store(embedder: &HashingEmbedder) -> InMemoryVectorStore {
+        let store = InMemoryVectorStore::new();
+        let texts = vec![
+            "Refunds are processed within five business days".to_string(),
+            "Shipping to Europe takes two weeks".to_string(),
+            "Refunds for digital goods are not available".to_string(),
+        ];
+        let embeddings = embedder.embed(&texts).await.unwrap();
+        let records = texts.into_iter().zip(embeddings).enumerate()
+            .map(|(i, (text, embedding))| {
+                VectorRecord::new(format!("doc-{}", i), text, embedding)
+                    .with_metadata("category", if i == 1 { "shipping" } else { "billing" })
+            })
+            .collect();
+        store.upsert("faq", records).await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_by_similarity() {
+        let embedder = HashingEmbedder::default();
+        let store = seeded_store(&embedder).await;
+
+        let query = embedder.embed(&["how long do refunds take".to_string()]).await.unwrap().remove(0);
+        let matches = store.search("faq", &query, 2, None).await.unwrap();
+
+        assert_eq!(matches.len(), 2);
+        assert!(matches[0].content.contains("Refunds"));
+        assert!(matches[0].score >= matches[1].score);
+        assert!(store.search("missing", &query, 2, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_filter_and_delete() {
+        let embedder = HashingEmbedder::default();
+        let store = seeded_store(&embedder).await;
+        let query = embedder.embed(&["refunds".to_string()]).await.unwrap().remove(0);
+
+        let filter = MetadataFilter::new().with_condition("category", "shipping");
+        let matches = store.search("faq", &query, 5, Some(&filter)).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].id, "doc-1");
+
+        let any_of = MetadataFilter::new().with_condition("category", vec!["shipping", "billing"]);
+        assert_eq!(store.search("faq", &query, 5, Some(&any_of)).await.unwrap().len(), 3);
+
+        assert_eq!(store.delete("faq", &["doc-0".to_string()]).await.unwrap(), 1);
+        assert_eq!(store.count("faq"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_stores_share_storage_not_collections() {
+        let embedder = HashingEmbedder::default();
+        let shared: Arc<dyn VectorStore> = Arc::new(InMemoryVectorStore::new());
+        let acme = TenantVectorStore::new(shared.clone(), TenantScope::new("acme").unwrap());
+        let globex = TenantVectorStore::new(shared.clone(), TenantScope::new("globex").unwrap());
+        let text = "Refunds are processed within five business days".to_string();
+        let embedding = embedder.embed(&[text.clone()]).await.unwrap().remove(0);
+        acme.upsert("faq", vec![VectorRecord::new("doc-0", text, embedding.clone())]).await.unwrap();
+
+        let matches = acme.search("faq", &embedding, 1, None).await.unwrap();
+        assert_eq!((matches[0].id.as_str(), matches[0].collection.as_str()), ("doc-0", "faq"));
+        assert_eq!(acme.collections().await.unwrap(), ["faq"]);
+
+        assert!(globex.search("faq", &embedding, 1, None).await.is_err());
+        assert!(globex.collections().await.unwrap().is_empty());
+        assert_eq!(globex.delete("faq", &["doc-0".to_string()]).await.unwrap(), 0);
+        assert!(shared.search("faq", &embedding, 1, None).await.is_err());
+        assert_eq!(acme.search("faq", &embedding, 1, None).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,251 @@
+//! Benchmark helpers for the engine's core paths.
+//!
+//! Each helper registers a criterion benchmark for one path the engine takes
+//! on every execution: cloning and serializing the state, compiling a graph
+//! into its plan, emitting events, and scheduling parallel branches. They are
+//! generic over the state and graph where that matters, so an application
+//! can measure its own state types and graphs, and [`core_benches`] runs them
+//! all on the fixtures here, as the crate's `core_paths` bench does.
+//!
+//! ```ignore
+//! use agent_graph::bench;
+//! use criterion::{criterion_group, criterion_main, Criterion};
+//!
+//! fn benches(c: &mut Criterion) {
+//!     bench::bench_state_serialization(c, "ticket", &Ticket::large());
+//!     bench::bench_plan_creation(c, "triage", build_triage_graph);
+//! }
+//!
+//! criterion_group!(group, benches);
+//! criterion_main!(group);
+//! ```
+
+use crate::edge::Edge;
+use crate::error::GraphResult;
+use crate::graph::{ExecutionConfig, Graph, GraphBuilder};
+use crate::node::Node;
+use crate::state::State;
+use async_trait::async_trait;
+use criterion::{black_box, BatchSize, BenchmarkId, Criterion, Throughput};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tokio::runtime::Runtime;
+
+/// State with a configurable number of fields and payload size
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchState {
+    /// Incremented by every [`WorkNode`]
+    pub counter: u64,
+    /// Named fields, as a typical state carries
+    pub fields: BTreeMap<String, Value>,
+    /// Opaque payload, e.g. a document being processed
+    pub payload: String,
+}
+
+impl BenchState {
+    /// A state with `fields` text fields and a payload of `payload_bytes` bytes
+    pub fn sized(fields: usize, payload_bytes: usize) -> Self {
+        Self {
+            counter: 0,
+            fields: (0..fields).map(|i| (format!("field_{}", i), Value::from(format!("value {}", i)))).collect(),
+            payload: "x".repeat(payload_bytes),
+        }
+    }
+}
+
+/// Node doing a fixed amount of arithmetic on a [`BenchState`]
+#[derive(Debug, Clone)]
+pub struct WorkNode {
+    /// Iterations of work per invocation
+    pub iterations: u32,
+}
+
+#[async_trait]
+impl Node<BenchState> for WorkNode {
+    async fn invoke(&self, state: &mut BenchState) -> GraphResult<()> {
+        let mut value = state.counter;
+        for i in 0..self.iterations {
+            value = value.wrapping_mul(31).wrapping_add(i as u64);
+        }
+        state.counter = black_box(value).wrapping_add(1);
+        Ok(())
+    }
+}
+
+/// Graph of `length` work nodes run one after another
+pub fn chain(length: usize) -> GraphResult<Graph<BenchState>> {
+    let length = length.max(1);
+    let mut builder = GraphBuilder::new();
+    for i in 0..length {
+        builder = builder.add_node(format!("node_{}", i), WorkNode { iterations: 100 })?;
+        if i > 0 {
+            builder = builder.add_edge(Edge::simple(format!("node_{}", i - 1), format!("node_{}", i)))?;
+        }
+    }
+    builder
+        .with_entry_point("node_0".to_string())?
+        .add_finish_point(format!("node_{}", length - 1))?
+        .build()
+}
+
+/// Graph fanning out from one work node to `width` work nodes run in parallel
+pub fn fan_out(width: usize) -> GraphResult<Graph<BenchState>> {
+    let config = ExecutionConfig { enable_parallel: true, ..Default::default() };
+    let branches: Vec<String> = (0..width.max(1)).map(|i| format!("branch_{}", i)).collect();
+    let mut builder = GraphBuilder::new()
+        .with_config(config)
+        .add_node("start".to_string(), WorkNode { iterations: 100 })?;
+    for branch in &branches {
+        builder = builder.add_node(branch.clone(), WorkNode { iterations: 100 })?.add_finish_point(branch.clone())?;
+    }
+    builder
+        .add_edge(Edge::parallel("start", branches))?
+        .with_entry_point("start".to_string())?
+        .build()
+}
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build the benchmark runtime")
+}
+
+/// Benchmark cloning `state`, as the engine does for retries and parallel branches
+pub fn bench_state_clone<S: State>(c: &mut Criterion, name: &str, state: &S) {
+    c.bench_function(&format!("state_clone/{}", name), |b| b.iter(|| black_box(state.clone())));
+}
+
+/// Benchmark a JSON round trip of `state`, as checkpoints and events make
+pub fn bench_state_serialization<S>(c: &mut Criterion, name: &str, state: &S)
+where
+    S: State + Serialize + DeserializeOwned,
+{
+    let mut group = c.benchmark_group("state_serialization");
+    let encoded = serde_json::to_vec(state).expect("benchmark state does not serialize");
+    group.throughput(Throughput::Bytes(encoded.len() as u64));
+    group.bench_function(BenchmarkId::new("serialize", name), |b| {
+        b.iter(|| black_box(serde_json::to_vec(state).expect("benchmark state does not serialize")))
+    });
+    group.bench_function(BenchmarkId::new("deserialize", name), |b| {
+        b.iter(|| black_box(serde_json::from_slice::<S>(&encoded).expect("benchmark state does not deserialize")))
+    });
+    group.finish();
+}
+
+/// Benchmark compiling the graphs `build` returns into their execution plan
+///
+/// Building the graph is not measured.
+pub fn bench_plan_creation<S, F>(c: &mut Criterion, name: &str, build: F)
+where
+    S: State,
+    F: Fn() -> GraphResult<Graph<S>>,
+{
+    c.bench_function(&format!("plan_creation/{}", name), |b| {
+        b.iter_batched(
+            || build().expect("benchmark graph does not build"),
+            |graph| black_box(graph.compile().expect("benchmark graph does not compile")),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Benchmark emitting `batch` node events and receiving them on the execution stream
+#[cfg(feature = "streaming")]
+pub fn bench_event_emission(c: &mut Criterion, batch: usize) {
+    use crate::node::NodeExecutionContext;
+    use crate::streaming::EventEmitter;
+
+    let mut group = c.benchmark_group("event_emission");
+    group.throughput(Throughput::Elements(batch as u64));
+    group.bench_function(BenchmarkId::from_parameter(batch), |b| {
+        let (emitter, mut receiver) = EventEmitter::new();
+        let execution_id = uuid::Uuid::new_v4();
+        let node_id = "node".to_string();
+        b.iter(|| {
+            for _ in 0..batch {
+                emitter
+                    .emit_node_started(execution_id, node_id.clone(), NodeExecutionContext::new(node_id.clone()))
+                    .expect("event receiver dropped");
+            }
+            while let Ok(event) = receiver.try_recv() {
+                black_box(event);
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Benchmark running `graph` from `state` to completion
+pub fn bench_execution<S>(c: &mut Criterion, name: &str, graph: &Graph<S>, state: &S)
+where
+    S: State + Serialize + DeserializeOwned,
+{
+    let runtime = runtime();
+    c.bench_function(&format!("execution/{}", name), |b| {
+        b.iter(|| {
+            let mut state = state.clone();
+            runtime.block_on(graph.run(&mut state)).expect("benchmark graph failed");
+            black_box(state)
+        })
+    });
+}
+
+/// Benchmark the cost of scheduling branches in parallel
+///
+/// For each width, a [`fan_out`] graph runs against a [`chain`] of the same
+/// number of nodes; the difference is the engine's scheduling overhead.
+pub fn bench_parallel_overhead(c: &mut Criterion, widths: &[usize]) {
+    let runtime = runtime();
+    let mut group = c.benchmark_group("parallel_scheduling");
+    for &width in widths {
+        let parallel = fan_out(width).expect("fan-out graph does not build");
+        let sequential = chain(width + 1).expect("chain graph does not build");
+        for (label, graph) in [("parallel", &parallel), ("sequential", &sequential)] {
+            group.bench_with_input(BenchmarkId::new(label, width), graph, |b, graph| {
+                b.iter(|| {
+                    let mut state = BenchState::default();
+                    runtime.block_on(graph.run(&mut state)).expect("benchmark graph failed");
+                    black_box(state)
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Run every core-path benchmark on the fixtures in this module
+pub fn core_benches(c: &mut Criterion) {
+    for (name, fields, payload) in [("small", 4, 64), ("large", 64, 64 * 1024)] {
+        let state = BenchState::sized(fields, payload);
+        bench_state_clone(c, name, &state);
+        bench_state_serialization(c, name, &state);
+    }
+    for length in [10, 100] {
+        bench_plan_creation(c, &format!("chain_{}", length), || chain(length));
+    }
+    bench_plan_creation(c, "fan_out_16", || fan_out(16));
+    #[cfg(feature = "streaming")]
+    bench_event_emission(c, 1000);
+    bench_parallel_overhead(c, &[2, 8, 32]);
+    bench_execution(c, "chain_10", &chain(10).expect("chain graph does not build"), &BenchState::sized(4, 64));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixtures_run() {
+        let mut state = BenchState::sized(3, 16);
+        chain(5).unwrap().compile().unwrap().run(&mut state).await.unwrap();
+        assert_eq!(state.fields.len(), 3);
+        assert_ne!(state.counter, 0);
+
+        let mut state = BenchState::default();
+        let context = fan_out(4).unwrap().run(&mut state).await.unwrap();
+        assert_eq!(context.execution_path.first().map(String::as_str), Some("start"));
+    }
+}
@@ -0,0 +1,967 @@
+//! Command-line tools for AgentGraph
+//! Built with the `cli` feature
+
+use agent_graph::config::ConfigLoader;
+use agent_graph::enterprise::api_keys::ApiKeyManager;
+use agent_graph::enterprise::audit::{self, AuditLogger, AuditStorageBackend, ComplianceStandard};
+use agent_graph::enterprise::audit_chain::{self, FileAnchor};
+use agent_graph::enterprise::compliance::{self, ComplianceEvidence, EvidenceFormat};
+use agent_graph::enterprise::resources::QuotaPeriod;
+use agent_graph::enterprise::tenancy::{TenancyConfig, TenantError, TenantManager, TenantSpec, DEFAULT_TENANTS_FILE};
+use agent_graph::graph::cost::{CostEstimate, TokenCost, TokenHistory};
+use agent_graph::manifest::lint::{self, LintReport};
+use agent_graph::manifest::{AppManifest, MANIFEST_FILE};
+use agent_graph::schemas::{self, SchemaKind};
+use agent_graph::state::dead_letter::{DeadLetterQueue, FileDeadLetterQueue, DEFAULT_DEAD_LETTER_DIR};
+use agent_graph::visualization::chrome_trace::TraceExportFormat;
+use agent_graph::visualization::dataset::{DatasetExporter, DatasetFilter};
+use agent_graph::visualization::ExecutionTrace;
+use agent_graph::{GraphError, GraphResult};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "agentgraph", version, about = "AgentGraph command-line tools")]
+struct Cli {
+    /// Bearer token for Studio URLs [default: $AGENTGRAPH_STUDIO_TOKEN]
+    #[arg(long, global = true)]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Environment variable holding the bearer token for Studio URLs
+const STUDIO_TOKEN_ENV: &str = "AGENTGRAPH_STUDIO_TOKEN";
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Work with execution traces
+    Trace {
+        #[command(subcommand)]
+        command: TraceCommand,
+    },
+    /// Build eval datasets from production traces
+    Dataset {
+        #[command(subcommand)]
+        command: DatasetCommand,
+    },
+    /// Work with the application manifest
+    Manifest {
+        #[command(subcommand)]
+        command: ManifestCommand,
+    },
+    /// Work with layered configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Inspect and retry executions that failed for good
+    Dlq {
+        /// Dead-letter directory
+        #[arg(long, global = true, default_value = DEFAULT_DEAD_LETTER_DIR)]
+        dir: PathBuf,
+        #[command(subcommand)]
+        command: DlqCommand,
+    },
+    /// Administer enterprise features
+    Enterprise {
+        #[command(subcommand)]
+        command: EnterpriseCommand,
+    },
+    /// Check manifests, graph templates and tool files and report problems with their locations
+    Validate {
+        /// Manifests or graph templates (TOML) and tool files (YAML) [default: agentgraph.toml in this or a parent directory]
+        paths: Vec<PathBuf>,
+        /// Only check this environment of manifests [default: every environment]
+        #[arg(long)]
+        env: Option<String>,
+        /// Print diagnostics as JSON
+        #[arg(long)]
+        json: bool,
+        /// Fail on warnings too
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Work with the JSON Schemas of the declarative formats
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommand,
+    },
+    /// Estimate the tokens and dollars a declared graph will cost, before running it
+    Estimate {
+        /// Graph declared in the manifest
+        graph: String,
+        /// Manifest file [default: agentgraph.toml in this or a parent directory]
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        /// Environment to apply [default: $AGENTGRAPH_ENV, then app.default_environment]
+        #[arg(long)]
+        env: Option<String>,
+        /// Input state as a JSON file [default: {}]
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+        /// Trace JSON files or Studio URLs to take per-node token counts from
+        #[arg(long = "trace")]
+        traces: Vec<String>,
+        /// Print the estimate as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TraceCommand {
+    /// Export an execution trace for a profiling UI
+    Export {
+        /// Trace JSON file, or a Studio URL such as http://localhost:8080/api/traces/<execution_id>
+        source: String,
+        /// Output format: chrome or perfetto
+        #[arg(long, default_value = "perfetto")]
+        format: String,
+        /// Output file, or `-` for stdout [default: <execution_id>.trace.json]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DatasetCommand {
+    /// Export scrubbed node input/output pairs as JSONL
+    Export {
+        /// Trace JSON files, or Studio URLs of one trace or of /api/traces
+        #[arg(required = true)]
+        sources: Vec<String>,
+        /// Only pairs of this node; repeat for more [default: every node]
+        #[arg(long = "node")]
+        nodes: Vec<String>,
+        /// Only executions of this workflow; repeat for more
+        #[arg(long = "workflow")]
+        workflows: Vec<String>,
+        /// Also export from failed and cancelled executions
+        #[arg(long)]
+        include_failed: bool,
+        /// Output file, or `-` for stdout
+        #[arg(short, long, default_value = "-")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ManifestCommand {
+    /// Resolve the manifest for an environment and check its references
+    Check {
+        /// Manifest file [default: agentgraph.toml in this or a parent directory]
+        path: Option<PathBuf>,
+        /// Environment to apply [default: $AGENTGRAPH_ENV, then app.default_environment]
+        #[arg(long)]
+        env: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SchemaCommand {
+    /// Write JSON Schemas for editor autocomplete and validation
+    Dump {
+        /// Schema to write: manifest, graph, persona, tools, dataset or config [default: all of them]
+        name: Option<String>,
+        /// Directory to write <name>.schema.json files to, or `-` for stdout
+        #[arg(short, long, default_value = "-")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DlqCommand {
+    /// List dead-lettered executions, oldest first
+    List {
+        /// Only letters of this graph
+        #[arg(long)]
+        graph: Option<String>,
+        /// Print full letters, including state, as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Mark letters for retry; the application reruns them on its next redrive
+    Retry {
+        /// Letter IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Delete letters without retrying them
+    Discard {
+        /// Letter IDs
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum EnterpriseCommand {
+    /// Provision and manage tenants
+    Tenant {
+        /// File the tenants are kept in
+        #[arg(long, global = true, default_value = DEFAULT_TENANTS_FILE)]
+        file: PathBuf,
+        #[command(subcommand)]
+        command: TenantCommand,
+    },
+    /// Produce and check signed compliance evidence
+    Compliance {
+        #[command(subcommand)]
+        command: ComplianceCommand,
+    },
+    /// Work with the audit log
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AuditCommand {
+    /// Check a hash-chained audit log for edited, deleted or inserted events
+    Verify {
+        /// Audit log written by the file backend
+        #[arg(long, default_value = "./audit.log")]
+        audit_log: PathBuf,
+        /// Anchor file the chain head was recorded in
+        #[arg(long)]
+        anchors: Option<PathBuf>,
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ComplianceCommand {
+    /// Build a signed evidence bundle from the audit log for a period
+    Report {
+        /// Standard to assess against: soc2, gdpr, hipaa, pcidss or iso27001
+        #[arg(long, default_value = "soc2")]
+        standard: String,
+        /// Only this tenant's events [default: every tenant]
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Start of the period, as a date or RFC 3339 time
+        #[arg(long)]
+        from: String,
+        /// End of the period, as a date or RFC 3339 time [default: now]
+        #[arg(long)]
+        to: Option<String>,
+        /// Config files (TOML or JSON) whose `enterprise.audit` section the
+        /// retention, archiving and encryption evidence is taken from
+        #[arg(short, long = "config")]
+        config_files: Vec<PathBuf>,
+        /// Ignore AGENTGRAPH_* environment variables when loading the config
+        #[arg(long)]
+        no_env: bool,
+        /// Audit log to read [default: the configured file backend's location]
+        #[arg(long)]
+        audit_log: Option<PathBuf>,
+        /// API key file to list the keys reaching the tenant from
+        #[arg(long)]
+        api_keys: Option<PathBuf>,
+        /// Formats to write: json, pdf or both
+        #[arg(long, value_delimiter = ',', default_value = "json,pdf")]
+        format: Vec<String>,
+        /// Directory to write the bundle to
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Environment variable holding the signing key
+        #[arg(long, default_value = "AGENTGRAPH_COMPLIANCE_KEY")]
+        key_env: String,
+    },
+    /// Check a bundle's signature and that none of its files changed
+    Verify {
+        /// Bundle directory
+        dir: PathBuf,
+        /// Environment variable holding the signing key
+        #[arg(long, default_value = "AGENTGRAPH_COMPLIANCE_KEY")]
+        key_env: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TenantCommand {
+    /// List tenants
+    List {
+        /// Print full tenants as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a tenant as JSON
+    Show {
+        id: String,
+    },
+    /// Provision a tenant
+    Create {
+        /// Tenant ID: letters, digits, `-` and `_`
+        id: String,
+        /// Display name [default: the ID]
+        #[arg(long)]
+        name: Option<String>,
+        /// Start from a configuration exported with `tenant export`
+        #[arg(long)]
+        from: Option<PathBuf>,
+        #[command(flatten)]
+        settings: TenantSettings,
+    },
+    /// Change a tenant's quotas, default provider or allowed tools
+    Update {
+        id: String,
+        /// New display name
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        settings: TenantSettings,
+    },
+    /// Suspend a tenant until it is reactivated
+    Suspend {
+        id: String,
+        /// Why, kept in the tenant's metadata
+        #[arg(long, default_value = "Suspended by an operator")]
+        reason: String,
+    },
+    /// Reactivate a suspended tenant
+    Reactivate {
+        id: String,
+    },
+    /// Delete a tenant
+    Delete {
+        id: String,
+    },
+    /// Export a tenant's configuration, to provision it elsewhere with `create --from`
+    Export {
+        id: String,
+        /// Output file, or `-` for stdout
+        #[arg(short, long, default_value = "-")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Args)]
+struct TenantSettings {
+    /// LLM provider the tenant's agents use by default
+    #[arg(long)]
+    provider: Option<String>,
+    /// Model the tenant's agents use by default
+    #[arg(long)]
+    model: Option<String>,
+    /// Allow a tool by name or pattern; repeat for more
+    #[arg(long = "tool")]
+    tools: Vec<String>,
+    /// Allow a tool category; repeat for more
+    #[arg(long = "tool-category")]
+    tool_categories: Vec<String>,
+    /// Most executions per quota period
+    #[arg(long)]
+    max_executions: Option<u64>,
+    /// Most executions at once
+    #[arg(long)]
+    max_concurrent: Option<u32>,
+    /// Quota period: minute, hourly, daily, weekly or monthly
+    #[arg(long)]
+    quota_period: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Merge defaults, files, AGENTGRAPH_* variables and overrides, and report problems
+    Validate {
+        /// Config files (TOML or JSON), later ones overriding earlier ones
+        #[arg(short, long = "file")]
+        files: Vec<PathBuf>,
+        /// Overrides applied last, as dotted.key=value
+        #[arg(long = "set")]
+        overrides: Vec<String>,
+        /// Ignore AGENTGRAPH_* environment variables
+        #[arg(long)]
+        no_env: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let token = cli.token.or_else(|| std::env::var(STUDIO_TOKEN_ENV).ok().filter(|token| !token.is_empty()));
+    let token = token.as_deref();
+    let result = match cli.command {
+        Command::Trace { command: TraceCommand::Export { source, format, output } } => {
+            export_trace(&source, &format, output, token).await
+        }
+        Command::Dataset { command: DatasetCommand::Export { sources, nodes, workflows, include_failed, output } } => {
+            let filter = DatasetFilter { successful_only: !include_failed, nodes, workflows };
+            export_dataset(&sources, filter, &output, token).await
+        }
+        Command::Manifest { command: ManifestCommand::Check { path, env } } => {
+            check_manifest(path, env.as_deref())
+        }
+        Command::Config { command: ConfigCommand::Validate { files, overrides, no_env } } => {
+            validate_config(files, overrides, no_env)
+        }
+        Command::Dlq { dir, command } => dead_letters(FileDeadLetterQueue::new(dir), command).await,
+        Command::Enterprise { command: EnterpriseCommand::Tenant { file, command } } => {
+            tenants(&file, command).await
+        }
+        Command::Enterprise { command: EnterpriseCommand::Compliance { command } } => compliance(command).await,
+        Command::Enterprise { command: EnterpriseCommand::Audit { command: AuditCommand::Verify { audit_log, anchors, json } } } => {
+            verify_audit_log(&audit_log, anchors.as_deref(), json)
+        }
+        Command::Validate { paths, env, json, strict } => validate(paths, env.as_deref(), json, strict),
+        Command::Schema { command: SchemaCommand::Dump { name, output } } => dump_schemas(name.as_deref(), &output),
+        Command::Estimate { graph, manifest, env, input, traces, json } => {
+            estimate_cost(&graph, manifest, env.as_deref(), input, &traces, json, token).await
+        }
+    };
+
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+async fn export_trace(source: &str, format: &str, output: Option<PathBuf>, token: Option<&str>) -> GraphResult<()> {
+    // Both formats share the trace_event JSON layout
+    let _format: TraceExportFormat = format.parse()?;
+    let trace = load_trace(source, token).await?;
+    let chrome = trace.to_chrome_trace();
+
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.trace.json", trace.execution_id)));
+    if output.as_os_str() == "-" {
+        println!("{}", chrome.to_json()?);
+    } else {
+        chrome.write_to(&output)?;
+        eprintln!(
+            "Wrote {} events for execution {} to {}",
+            chrome.trace_events.len(),
+            trace.execution_id,
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+async fn export_dataset(
+    sources: &[String],
+    filter: DatasetFilter,
+    output: &std::path::Path,
+    token: Option<&str>,
+) -> GraphResult<()> {
+    let mut traces = Vec::new();
+    for source in sources {
+        traces.extend(load_traces(source, token).await?);
+    }
+    let exporter = DatasetExporter::new(filter);
+    let examples = if output.as_os_str() == "-" {
+        exporter.write_jsonl(&traces, std::io::stdout().lock())?
+    } else {
+        exporter.write_jsonl(&traces, std::io::BufWriter::new(std::fs::File::create(output)?))?
+    };
+    eprintln!("Exported {} example(s) from {} trace(s)", examples, traces.len());
+    Ok(())
+}
+
+fn manifest_path(path: Option<PathBuf>) -> GraphResult<PathBuf> {
+    match path {
+        Some(path) => Ok(path),
+        None => AppManifest::discover(&std::env::current_dir()?).ok_or_else(|| {
+            GraphError::ConfigurationError(format!("No {} found in this or any parent directory", MANIFEST_FILE))
+        }),
+    }
+}
+
+fn check_manifest(path: Option<PathBuf>, env: Option<&str>) -> GraphResult<()> {
+    let path = manifest_path(path)?;
+    let manifest = AppManifest::load(&path, env)?;
+    manifest.validate()?;
+
+    println!("{} ({})", manifest.app.name, path.display());
+    println!("  environment: {}", manifest.environment().unwrap_or("none"));
+    println!("  providers:   {}", manifest.providers.keys().cloned().collect::<Vec<_>>().join(", "));
+    println!("  personas:    {}", manifest.personas.keys().cloned().collect::<Vec<_>>().join(", "));
+    for name in manifest.graph_names() {
+        let template = manifest.graph_template(name)?;
+        println!("  graph {}: {}", name, template.description.unwrap_or_default());
+    }
+    Ok(())
+}
+
+fn validate_config(files: Vec<PathBuf>, overrides: Vec<String>, no_env: bool) -> GraphResult<()> {
+    let mut loader = ConfigLoader::new();
+    for file in &files {
+        loader = loader.with_file(file)?;
+    }
+    if !no_env {
+        loader = loader.with_env()?;
+    }
+    for assignment in &overrides {
+        loader = loader.with_override(assignment)?;
+    }
+
+    let report = loader.validate()?;
+    for unknown in &report.unknown_keys {
+        println!("unknown key {} (from {})", unknown.key, unknown.source);
+    }
+    for secret in &report.missing_secrets {
+        println!("missing secret {}: {} (set {})", secret.key, secret.reason, secret.env_var);
+    }
+    if !report.is_ok() {
+        return Err(GraphError::ConfigurationError(format!(
+            "{} unknown key(s), {} missing secret(s)",
+            report.unknown_keys.len(),
+            report.missing_secrets.len()
+        )));
+    }
+    println!("configuration is valid");
+    Ok(())
+}
+
+fn validate(paths: Vec<PathBuf>, env: Option<&str>, json: bool, strict: bool) -> GraphResult<()> {
+    let paths = if paths.is_empty() { vec![manifest_path(None)?] } else { paths };
+    let mut report = LintReport::default();
+    for path in &paths {
+        report.merge(lint::lint_path(path, env));
+    }
+
+    let (errors, warnings) = (report.error_count(), report.warning_count());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+            "files": report.files,
+            "errors": errors,
+            "warnings": warnings,
+            "diagnostics": report.diagnostics,
+        }))?);
+    } else {
+        for diagnostic in &report.diagnostics {
+            println!("{}", diagnostic);
+        }
+        println!("{} file(s) checked: {} error(s), {} warning(s)", report.files.len(), errors, warnings);
+    }
+    if errors > 0 || (strict && warnings > 0) {
+        return Err(GraphError::validation_error(format!("{} error(s), {} warning(s)", errors, warnings)));
+    }
+    Ok(())
+}
+
+fn dump_schemas(name: Option<&str>, output: &std::path::Path) -> GraphResult<()> {
+    let kinds = match name {
+        Some(name) => vec![name.parse::<SchemaKind>()?],
+        None => SchemaKind::ALL.to_vec(),
+    };
+    if output.as_os_str() == "-" {
+        let json = match kinds.as_slice() {
+            [kind] => kind.schema(),
+            _ => kinds.iter().map(|kind| (kind.name().to_string(), kind.schema())).collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+    for path in schemas::write_schemas(output, &kinds)? {
+        eprintln!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+async fn dead_letters(queue: FileDeadLetterQueue, command: DlqCommand) -> GraphResult<()> {
+    match command {
+        DlqCommand::List { graph, json } => {
+            let mut letters = queue.list().await?;
+            letters.retain(|letter| graph.as_ref().is_none_or(|graph| &letter.graph == graph));
+            if json {
+                println!("{}", serde_json::to_string_pretty(&letters)?);
+                return Ok(());
+            }
+            for letter in &letters {
+                println!(
+                    "{}  {}  {}  node={}  attempts={}  {:?}",
+                    letter.id,
+                    letter.failed_at.format("%Y-%m-%d %H:%M:%S"),
+                    letter.graph,
+                    letter.node_id.as_deref().unwrap_or("-"),
+                    letter.attempts,
+                    letter.status
+                );
+                for (depth, error) in letter.errors.iter().enumerate() {
+                    println!("    {}{}", if depth == 0 { "" } else { "caused by: " }, error);
+                }
+            }
+            eprintln!("{} dead letter(s)", letters.len());
+        }
+        DlqCommand::Retry { ids } => {
+            for id in &ids {
+                queue.request_retry(id).await?;
+                println!("{} marked for retry", id);
+            }
+        }
+        DlqCommand::Discard { ids } => {
+            for id in &ids {
+                if !queue.remove(id).await? {
+                    return Err(GraphError::validation_error(format!("No dead letter '{}'", id)));
+                }
+                println!("{} discarded", id);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn tenant_error(error: TenantError) -> GraphError {
+    GraphError::ConfigurationError(error.to_string())
+}
+
+fn apply_tenant_settings(spec: &mut TenantSpec, settings: TenantSettings) -> GraphResult<()> {
+    if let Some(provider) = settings.provider {
+        spec.config.default_provider = Some(provider);
+    }
+    if let Some(model) = settings.model {
+        spec.config.default_model = Some(model);
+    }
+    if !settings.tools.is_empty() {
+        spec.config.allowed_tools = Some(settings.tools);
+    }
+    if !settings.tool_categories.is_empty() {
+        spec.config.allowed_tool_categories = Some(settings.tool_categories);
+    }
+    if let Some(max) = settings.max_executions {
+        spec.resource_limits.max_executions = Some(max);
+    }
+    if let Some(max) = settings.max_concurrent {
+        spec.resource_limits.max_concurrent_executions = Some(max);
+        spec.config.max_concurrent_executions = max;
+    }
+    if let Some(period) = settings.quota_period {
+        spec.resource_limits.quota_period = match period.as_str() {
+            "minute" => QuotaPeriod::Minute,
+            "hourly" => QuotaPeriod::Hourly,
+            "daily" => QuotaPeriod::Daily,
+            "weekly" => QuotaPeriod::Weekly,
+            "monthly" => QuotaPeriod::Monthly,
+            other => return Err(GraphError::validation_error(format!("Unknown quota period '{}'", other))),
+        };
+    }
+    Ok(())
+}
+
+async fn tenants(file: &std::path::Path, command: TenantCommand) -> GraphResult<()> {
+    let manager = TenantManager::from_file(TenancyConfig::default(), file).map_err(tenant_error)?;
+    match command {
+        TenantCommand::List { json } => {
+            let mut tenants = manager.list_tenants().await.map_err(tenant_error)?;
+            tenants.sort_by(|a, b| a.id.cmp(&b.id));
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tenants)?);
+                return Ok(());
+            }
+            for tenant in &tenants {
+                println!(
+                    "{}  {:?}  {}  provider={}  executions={}",
+                    tenant.id,
+                    tenant.status,
+                    tenant.name,
+                    tenant.config.default_provider.as_deref().unwrap_or("-"),
+                    tenant.resource_limits.max_executions.map_or_else(|| "unlimited".to_string(), |max| max.to_string())
+                );
+            }
+            eprintln!("{} tenant(s)", tenants.len());
+            return Ok(());
+        }
+        TenantCommand::Show { id } => {
+            let tenant = manager.get_tenant(&id).await.map_err(tenant_error)?;
+            println!("{}", serde_json::to_string_pretty(&tenant)?);
+            return Ok(());
+        }
+        TenantCommand::Export { id, output } => {
+            let spec = manager.export_tenant(&id).await.map_err(tenant_error)?;
+            let json = serde_json::to_string_pretty(&spec)?;
+            if output.as_os_str() == "-" {
+                println!("{}", json);
+            } else {
+                std::fs::write(&output, json)?;
+                eprintln!("Exported tenant {} to {}", id, output.display());
+            }
+            return Ok(());
+        }
+        TenantCommand::Create { id, name, from, settings } => {
+            let mut spec = match from {
+                Some(path) => {
+                    let mut spec: TenantSpec = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+                    spec.id = id;
+                    spec
+                }
+                None => TenantSpec::new(id.clone(), id),
+            };
+            if let Some(name) = name {
+                spec.name = name;
+            }
+            apply_tenant_settings(&mut spec, settings)?;
+            let tenant = manager.provision_tenant(spec).await.map_err(tenant_error)?;
+            println!("{} created", tenant.id);
+        }
+        TenantCommand::Update { id, name, settings } => {
+            let mut tenant = manager.get_tenant(&id).await.map_err(tenant_error)?;
+            let mut spec = TenantSpec::from(&tenant);
+            if let Some(name) = name {
+                spec.name = name;
+            }
+            apply_tenant_settings(&mut spec, settings)?;
+            tenant.name = spec.name;
+            tenant.update_config(spec.config);
+            tenant.update_resource_limits(spec.resource_limits);
+            manager.update_tenant(tenant).await.map_err(tenant_error)?;
+            println!("{} updated", id);
+        }
+        TenantCommand::Suspend { id, reason } => {
+            manager.suspend_tenant(&id, reason).await.map_err(tenant_error)?;
+            println!("{} suspended", id);
+        }
+        TenantCommand::Reactivate { id } => {
+            manager.reactivate_tenant(&id).await.map_err(tenant_error)?;
+            println!("{} reactivated", id);
+        }
+        TenantCommand::Delete { id } => {
+            manager.delete_tenant(&id).await.map_err(tenant_error)?;
+            println!("{} deleted", id);
+        }
+    }
+    manager.save_to_file(file).map_err(tenant_error)
+}
+
+fn signing_key(env: &str) -> GraphResult<Vec<u8>> {
+    match std::env::var(env) {
+        Ok(key) if !key.is_empty() => Ok(key.into_bytes()),
+        _ => Err(GraphError::ConfigurationError(format!("Set {} to the bundle signing key", env))),
+    }
+}
+
+/// A date (midnight UTC) or RFC 3339 time
+fn parse_time(value: &str) -> GraphResult<std::time::SystemTime> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.into());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc().into())
+        .map_err(|_| GraphError::validation_error(format!("'{}' is neither a date nor an RFC 3339 time", value)))
+}
+
+async fn compliance(command: ComplianceCommand) -> GraphResult<()> {
+    let audit_error = |e: audit::AuditError| GraphError::ExternalServiceError(e.to_string());
+    match command {
+        ComplianceCommand::Report {
+            standard,
+            tenant,
+            from,
+            to,
+            config_files,
+            no_env,
+            audit_log,
+            api_keys,
+            format,
+            output,
+            key_env,
+        } => {
+            let key = signing_key(&key_env)?;
+            let standard = match standard.to_ascii_lowercase().as_str() {
+                "soc2" => ComplianceStandard::SOC2,
+                "gdpr" => ComplianceStandard::GDPR,
+                "hipaa" => ComplianceStandard::HIPAA,
+                "pcidss" => ComplianceStandard::PCIDSS,
+                "iso27001" => ComplianceStandard::ISO27001,
+                other => return Err(GraphError::validation_error(format!("Unknown compliance standard '{}'", other))),
+            };
+            let formats = format
+                .iter()
+                .map(|format| match format.as_str() {
+                    "json" => Ok(EvidenceFormat::Json),
+                    "pdf" => Ok(EvidenceFormat::Pdf),
+                    other => Err(GraphError::validation_error(format!("Unknown evidence format '{}'", other))),
+                })
+                .collect::<GraphResult<Vec<_>>>()?;
+            let start = parse_time(&from)?;
+            let end = to.as_deref().map(parse_time).transpose()?.unwrap_or_else(std::time::SystemTime::now);
+
+            // The evidence describes how the audit log is actually kept
+            let mut loader = ConfigLoader::new();
+            for file in &config_files {
+                loader = loader.with_file(file)?;
+            }
+            if !no_env {
+                loader = loader.with_env()?;
+            }
+            let mut config = loader.load()?.enterprise.audit;
+            if let Some(audit_log) = audit_log {
+                config.storage.backend = AuditStorageBackend::File;
+                config.storage.location = audit_log.to_string_lossy().into_owned();
+            }
+            let logger = AuditLogger::new(config).map_err(audit_error)?;
+            let report = logger
+                .generate_tenant_compliance_report(standard, start, end, tenant.as_deref())
+                .await
+                .map_err(audit_error)?;
+
+            let mut evidence = ComplianceEvidence::new(report);
+            if let Some(path) = api_keys {
+                let keys = ApiKeyManager::from_file(&path).map_err(|e| GraphError::ConfigurationError(e.to_string()))?;
+                evidence = evidence.with_api_keys(&keys.list(None));
+            }
+            let manifest = compliance::write_bundle(&evidence, &output, &formats, &key).map_err(audit_error)?;
+            println!(
+                "{:?} report for {}: score {:.1}, {} event(s), {} finding(s)",
+                evidence.report.standard,
+                tenant.as_deref().unwrap_or("all tenants"),
+                evidence.report.compliance_score,
+                evidence.report.total_events,
+                evidence.report.findings.len()
+            );
+            for finding in &evidence.report.findings {
+                println!("  [{:?}] {}", finding.severity, finding.description);
+            }
+            eprintln!("Wrote {} file(s) and a signed manifest to {}", manifest.files.len(), output.display());
+        }
+        ComplianceCommand::Verify { dir, key_env } => {
+            let key = signing_key(&key_env)?;
+            let manifest = compliance::verify_bundle(&dir, &key).map_err(audit_error)?;
+            println!(
+                "{} verified: {:?} evidence for {}, {} file(s)",
+                dir.display(),
+                manifest.standard,
+                manifest.tenant_id.as_deref().unwrap_or("all tenants"),
+                manifest.files.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn verify_audit_log(path: &std::path::Path, anchors: Option<&std::path::Path>, json: bool) -> GraphResult<()> {
+    let audit_error = |e: audit::AuditError| GraphError::ExternalServiceError(e.to_string());
+    let events = audit::read_log_file(path).map_err(audit_error)?;
+    let anchors = anchors.map(FileAnchor::load).transpose().map_err(audit_error)?.unwrap_or_default();
+    let verification = audit_chain::verify_chain(&events, &anchors);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&verification)?);
+    } else {
+        println!(
+            "{}: {} event(s), {} anchor(s) checked, head {}",
+            path.display(),
+            verification.events,
+            verification.anchors_checked,
+            verification.head_hash.as_deref().unwrap_or("-")
+        );
+        if let Some(first) = verification.first_sequence.filter(|first| *first > 0) {
+            println!("  log starts at event {}; earlier events were cleaned up or removed", first);
+        }
+        for problem in &verification.problems {
+            println!("  {}", serde_json::to_string(problem)?);
+        }
+    }
+    if verification.is_intact() {
+        Ok(())
+    } else {
+        Err(GraphError::validation_error(format!(
+            "{} failed verification with {} problem(s)",
+            path.display(),
+            verification.problems.len()
+        )))
+    }
+}
+
+async fn estimate_cost(
+    graph: &str,
+    manifest: Option<PathBuf>,
+    env: Option<&str>,
+    input: Option<PathBuf>,
+    traces: &[String],
+    json: bool,
+    token: Option<&str>,
+) -> GraphResult<()> {
+    let manifest = AppManifest::load(&manifest_path(manifest)?, env)?;
+    let input = match input {
+        Some(path) => serde_json::from_str(&std::fs::read_to_string(&path)?)?,
+        None => serde_json::json!({}),
+    };
+    let mut loaded = Vec::new();
+    for source in traces {
+        loaded.push(load_trace(source, token).await?);
+    }
+    let history = TokenHistory::from_traces(&loaded);
+
+    let estimate = manifest.estimate_cost(graph, &input, &history)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&estimate)?);
+    } else {
+        print_estimate(graph, &estimate);
+    }
+    Ok(())
+}
+
+fn print_estimate(graph: &str, estimate: &CostEstimate) {
+    let line = |label: &str, cost: &TokenCost| {
+        println!("  {:<9}{:>10} tokens  ${:.4}", label, cost.total_tokens(), cost.cost_usd);
+    };
+    println!("{}", graph);
+    line("min", &estimate.total.min);
+    line("expected", &estimate.total.expected);
+    line("max", &estimate.total.max);
+    for node in &estimate.nodes {
+        println!(
+            "  node {}: {} ({:?}, {} sample(s)), expected {} tokens per visit",
+            node.node_id,
+            node.model.as_deref().unwrap_or("no model"),
+            node.basis,
+            node.samples,
+            node.per_visit.expected.total_tokens()
+        );
+    }
+    if estimate.has_cycles {
+        println!("  warning: graph loops; totals count one pass through each loop");
+    }
+    if !estimate.unpriced_models.is_empty() {
+        println!("  warning: no price for {}", estimate.unpriced_models.join(", "));
+    }
+}
+
+async fn load_trace(source: &str, token: Option<&str>) -> GraphResult<ExecutionTrace> {
+    let body = read_source(source, token).await?;
+    serde_json::from_str(&body).map_err(|e| {
+        GraphError::validation_error(format!("{} is not an execution trace: {}", source, e))
+    })
+}
+
+/// One trace, or the list of traces served at /api/traces
+async fn load_traces(source: &str, token: Option<&str>) -> GraphResult<Vec<ExecutionTrace>> {
+    let body = read_source(source, token).await?;
+    if body.trim_start().starts_with('[') {
+        return serde_json::from_str(&body).map_err(|e| {
+            GraphError::validation_error(format!("{} is not a list of execution traces: {}", source, e))
+        });
+    }
+    serde_json::from_str(&body).map(|trace| vec![trace]).map_err(|e| {
+        GraphError::validation_error(format!("{} is not an execution trace: {}", source, e))
+    })
+}
+
+/// Read a file, or fetch a URL with `token` as its bearer token
+///
+/// A URL answering with an error status is an error, not a body to parse.
+async fn read_source(source: &str, token: Option<&str>) -> GraphResult<String> {
+    let body = if source.starts_with("http://") || source.starts_with("https://") {
+        let mut request = reqwest::Client::new().get(source);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| GraphError::ExternalServiceError(format!("Failed to fetch {}: {}", source, e)))?;
+        response
+            .text()
+            .await
+            .map_err(|e| GraphError::ExternalServiceError(format!("Failed to read {}: {}", source, e)))?
+    } else {
+        std::fs::read_to_string(source)?
+    };
+    Ok(body)
+}
@@ -0,0 +1,420 @@
+//! Layered configuration: defaults, files, environment variables and command-line overrides
+//! merged into the typed configs of each subsystem.
+
+use crate::enterprise::security::AuthMethod;
+use crate::enterprise::EnterpriseConfig;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::ExecutionConfig;
+use crate::llm::LLMConfig;
+use crate::visualization::VisualizationConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Prefix of environment variables read by [`ConfigLoader::with_env`]
+pub const ENV_PREFIX: &str = "AGENTGRAPH_";
+
+/// Separator between key segments in environment variable names
+pub const ENV_SEPARATOR: &str = "__";
+
+/// Provider that needs no API key
+const KEYLESS_PROVIDERS: &[&str] = &["mock"];
+
+/// Configuration of every subsystem
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct AppConfig {
+    /// LLM providers and request defaults
+    pub llm: LLMConfig,
+    /// Graph execution limits
+    pub execution: ExecutionConfig,
+    /// Tenancy, security, audit and monitoring
+    pub enterprise: EnterpriseConfig,
+    /// Tracing and Studio
+    pub visualization: VisualizationConfig,
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "name", rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// Built-in default
+    Default,
+    /// Configuration file
+    File(PathBuf),
+    /// Environment variable
+    Env(String),
+    /// Command-line override
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(var) => write!(f, "${}", var),
+            Self::Cli => f.write_str("command line"),
+        }
+    }
+}
+
+/// A key no config type knows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnknownKey {
+    /// Dotted key, e.g. `llm.default_provder`
+    pub key: String,
+    /// Layer that set it
+    pub source: ConfigSource,
+}
+
+/// A secret the configuration needs but does not have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissingSecret {
+    /// Dotted key the secret belongs under
+    pub key: String,
+    /// Why it is needed
+    pub reason: String,
+    /// Environment variable that would provide it
+    pub env_var: String,
+}
+
+/// Findings of [`ConfigLoader::validate`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigReport {
+    /// Keys that would be ignored
+    pub unknown_keys: Vec<UnknownKey>,
+    /// Secrets that have to be provided
+    pub missing_secrets: Vec<MissingSecret>,
+}
+
+impl ConfigReport {
+    /// Whether nothing was found
+    pub fn is_ok(&self) -> bool {
+        self.unknown_keys.is_empty() && self.missing_secrets.is_empty()
+    }
+}
+
+/// Merges configuration layers, later layers overriding earlier ones
+///
+/// Layers apply in the order they are added, on top of the defaults of
+/// [`AppConfig`]. Tables merge key by key; any other value replaces the one
+/// below it.
+///
+/// ```no_run
+/// # use agent_graph::config::ConfigLoader;
+/// let config = ConfigLoader::new()
+///     .with_file("agentgraph.config.toml")?
+///     .with_env()?
+///     .with_override("llm.default_provider=anthropic")?
+///     .load()?;
+/// # Ok::<(), agent_graph::GraphError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    merged: Map<String, Value>,
+    defaults: Value,
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    /// Start from the defaults
+    pub fn new() -> Self {
+        let defaults = serde_json::to_value(AppConfig::default()).expect("default config serializes");
+        let merged = defaults.as_object().cloned().unwrap_or_default();
+        Self {
+            merged,
+            defaults,
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Add a TOML or JSON file, chosen by extension
+    pub fn with_file<P: AsRef<Path>>(self, path: P) -> GraphResult<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| GraphError::ConfigurationError(format!(
+            "Failed to read config file {}: {}",
+            path.display(),
+            e
+        )))?;
+        let layer: Value = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content)?
+        } else {
+            let table: toml::Table = toml::from_str(&content).map_err(|e| {
+                GraphError::ConfigurationError(format!("Invalid config file {}: {}", path.display(), e))
+            })?;
+            serde_json::to_value(table)?
+        };
+        self.with_layer(layer, ConfigSource::File(path.to_path_buf()))
+    }
+
+    /// Add the file if it exists
+    pub fn with_optional_file<P: AsRef<Path>>(self, path: P) -> GraphResult<Self> {
+        if path.as_ref().is_file() {
+            self.with_file(path)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Add environment variables such as `AGENTGRAPH_LLM__DEFAULT_PROVIDER=anthropic`
+    ///
+    /// Only variables whose first segment names a section of [`AppConfig`] are
+    /// read, so unrelated `AGENTGRAPH_*` variables are left alone.
+    pub fn with_env(self) -> GraphResult<Self> {
+        self.with_vars(std::env::vars())
+    }
+
+    /// Add variables as [`with_env`](Self::with_env) would read them from the environment
+    pub fn with_vars<I>(mut self, vars: I) -> GraphResult<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut vars: Vec<(String, String)> = vars.into_iter().collect();
+        vars.sort();
+        for (name, raw) in vars {
+            let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let path: Vec<String> = rest.split(ENV_SEPARATOR).map(str::to_lowercase).collect();
+            if path.iter().any(String::is_empty) || !self.defaults.get(&path[0]).is_some_and(Value::is_object) {
+                continue;
+            }
+            self = self.with_value(&path, parse_value(&raw), ConfigSource::Env(name))?;
+        }
+        Ok(self)
+    }
+
+    /// Add a `dotted.key=value` override, as given on the command line
+    pub fn with_override(self, assignment: &str) -> GraphResult<Self> {
+        let (key, raw) = assignment.split_once('=').ok_or_else(|| GraphError::ConfigurationError(format!(
+            "Override '{}' is not of the form key=value",
+            assignment
+        )))?;
+        let path: Vec<String> = key.trim().split('.').map(str::to_string).collect();
+        if path.iter().any(String::is_empty) {
+            return Err(GraphError::ConfigurationError(format!("Invalid config key '{}'", key)));
+        }
+        self.with_value(&path, parse_value(raw.trim()), ConfigSource::Cli)
+    }
+
+    fn with_value(self, path: &[String], value: Value, source: ConfigSource) -> GraphResult<Self> {
+        let layer = path.iter().rev().fold(value, |value, key| {
+            let mut table = Map::new();
+            table.insert(key.clone(), value);
+            Value::Object(table)
+        });
+        self.with_layer(layer, source)
+    }
+
+    fn with_layer(mut self, layer: Value, source: ConfigSource) -> GraphResult<Self> {
+        let Value::Object(layer) = layer else {
+            return Err(GraphError::ConfigurationError(format!("Config from {} is not a table", source)));
+        };
+        merge(&mut self.merged, layer, "", &source, &mut self.sources);
+        Ok(self)
+    }
+
+    /// Layer that set `key` last
+    pub fn source_of(&self, key: &str) -> ConfigSource {
+        self.sources.get(key).cloned().unwrap_or(ConfigSource::Default)
+    }
+
+    /// Merged configuration as a JSON value
+    pub fn merged(&self) -> Value {
+        Value::Object(self.merged.clone())
+    }
+
+    /// Deserialize the merged layers
+    pub fn load(&self) -> GraphResult<AppConfig> {
+        serde_json::from_value(self.merged()).map_err(|e| {
+            GraphError::ConfigurationError(format!("Invalid configuration: {}", e))
+        })
+    }
+
+    /// Report unknown keys and missing secrets without failing on them
+    pub fn validate(&self) -> GraphResult<ConfigReport> {
+        let config = self.load()?;
+        let mut report = ConfigReport::default();
+
+        let mut unknown = Vec::new();
+        unknown_keys(&self.merged, &self.defaults, "", &mut unknown);
+        report.unknown_keys = unknown
+            .into_iter()
+            .map(|key| UnknownKey {
+                source: self.source_of(&key),
+                key,
+            })
+            .collect();
+
+        report.missing_secrets = missing_secrets(&config);
+        Ok(report)
+    }
+}
+
+/// Interpret a raw string as JSON when it parses, otherwise as a string
+fn parse_value(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn merge(
+    base: &mut Map<String, Value>,
+    layer: Map<String, Value>,
+    prefix: &str,
+    source: &ConfigSource,
+    sources: &mut BTreeMap<String, ConfigSource>,
+) {
+    for (key, value) in layer {
+        let path = join_key(prefix, &key);
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(nested)) => {
+                merge(existing, nested, &path, source, sources);
+            }
+            (_, value) => {
+                sources.insert(path, source.clone());
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Keys of `merged` absent from `defaults`
+///
+/// Tables that are empty by default are maps (providers, headers, ...) and
+/// accept any key.
+fn unknown_keys(merged: &Map<String, Value>, defaults: &Value, prefix: &str, out: &mut Vec<String>) {
+    let Some(known) = defaults.as_object() else {
+        return;
+    };
+    if known.is_empty() {
+        return;
+    }
+    for (key, value) in merged {
+        let path = join_key(prefix, key);
+        match (known.get(key), value) {
+            (None, _) => out.push(path),
+            (Some(default), Value::Object(nested)) => unknown_keys(nested, default, &path, out),
+            _ => {}
+        }
+    }
+}
+
+fn env_var_for(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.replace('.', ENV_SEPARATOR).to_uppercase())
+}
+
+fn missing_secrets(config: &AppConfig) -> Vec<MissingSecret> {
+    let mut missing = Vec::new();
+
+    let mut providers: Vec<&str> = config.llm.providers.keys().map(String::as_str).collect();
+    if !providers.contains(&config.llm.default_provider.as_str()) {
+        providers.push(&config.llm.default_provider);
+    }
+    providers.sort_unstable();
+    for provider in providers {
+        if KEYLESS_PROVIDERS.contains(&provider) {
+            continue;
+        }
+        let has_key = config.llm.providers
+            .get(provider)
+            .and_then(|settings| settings.api_key.as_deref())
+            .is_some_and(|key| !key.is_empty());
+        if !has_key {
+            let key = format!("llm.providers.{}.api_key", provider);
+            missing.push(MissingSecret {
+                reason: if provider == config.llm.default_provider {
+                    format!("API key for the default provider '{}'", provider)
+                } else {
+                    format!("API key for provider '{}'", provider)
+                },
+                env_var: env_var_for(&key),
+                key,
+            });
+        }
+    }
+
+    let security = &config.enterprise.security;
+    let needs_jwt = security.authentication_enabled && security.auth_methods.contains(&AuthMethod::JWT);
+    if needs_jwt && security.jwt_secret.as_deref().is_none_or(str::is_empty) {
+        let key = "enterprise.security.jwt_secret".to_string();
+        missing.push(MissingSecret {
+            reason: "JWT authentication is enabled".to_string(),
+            env_var: env_var_for(&key),
+            key,
+        });
+    }
+
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let loader = ConfigLoader::new()
+            .with_vars([
+                ("AGENTGRAPH_EXECUTION__MAX_STEPS".to_string(), "50".to_string()),
+                ("AGENTGRAPH_LLM__DEFAULT_PROVIDER".to_string(), "anthropic".to_string()),
+                ("AGENTGRAPH_ENV".to_string(), "prod".to_string()),
+            ])
+            .unwrap()
+            .with_override("llm.default_provider=mock")
+            .unwrap();
+
+        let config = loader.load().unwrap();
+        assert_eq!(config.execution.max_steps, Some(50));
+        assert_eq!(config.llm.default_provider, "mock");
+        // Untouched values keep their defaults
+        assert_eq!(config.visualization.web_port, 8080);
+
+        assert_eq!(loader.source_of("llm.default_provider"), ConfigSource::Cli);
+        assert_eq!(
+            loader.source_of("execution.max_steps"),
+            ConfigSource::Env("AGENTGRAPH_EXECUTION__MAX_STEPS".to_string())
+        );
+        assert_eq!(loader.source_of("visualization.web_port"), ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_keys_and_missing_secrets() {
+        let report = ConfigLoader::new()
+            .with_override("llm.default_provder=mock")
+            .unwrap()
+            .with_override("llm.providers.anthropic.api_key=\"\"")
+            .unwrap()
+            .with_override("enterprise.security.authentication_enabled=true")
+            .unwrap()
+            .validate()
+            .unwrap();
+
+        assert_eq!(report.unknown_keys, vec![UnknownKey {
+            key: "llm.default_provder".to_string(),
+            source: ConfigSource::Cli,
+        }]);
+        let missing: Vec<&str> = report.missing_secrets.iter().map(|secret| secret.key.as_str()).collect();
+        assert_eq!(missing, vec![
+            "llm.providers.anthropic.api_key",
+            "llm.providers.openai.api_key",
+            "enterprise.security.jwt_secret",
+        ]);
+        assert_eq!(report.missing_secrets[1].env_var, "AGENTGRAPH_LLM__PROVIDERS__OPENAI__API_KEY");
+        assert!(!report.is_ok());
+    }
+}
@@ -1,6 +1,8 @@
 //! Edge definitions and routing logic for the AgentGraph framework.
 
 pub mod routing;
+pub mod speculation;
+pub mod throttle;
 
 use crate::error::GraphResult;
 use crate::node::NodeId;
@@ -9,6 +11,8 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
+use speculation::SpeculationConfig;
+use throttle::EdgeRateLimit;
 
 /// Represents different types of edges in the graph
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,6 +112,12 @@ pub struct EdgeMetadata {
     pub parallel_safe: bool,
     /// Priority for edge selection (higher = more priority)
     pub priority: i32,
+    /// Limit on how often the edge may be traversed
+    #[serde(default)]
+    pub rate_limit: Option<EdgeRateLimit>,
+    /// Run the likely branch of a conditional edge before its condition resolves
+    #[serde(default)]
+    pub speculation: Option<SpeculationConfig>,
 }
 
 impl Default for EdgeMetadata {
@@ -119,6 +129,8 @@ impl Default for EdgeMetadata {
             custom: HashMap::new(),
             parallel_safe: true,
             priority: 0,
+            rate_limit: None,
+            speculation: None,
         }
     }
 }
@@ -231,6 +243,30 @@ impl Edge {
         self
     }
 
+    /// Throttle traversals of this edge
+    pub fn with_rate_limit(mut self, rate_limit: EdgeRateLimit) -> Self {
+        self.metadata.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Speculatively run the likely branch of this conditional edge
+    pub fn with_speculation(mut self, speculation: SpeculationConfig) -> Self {
+        self.metadata.speculation = Some(speculation);
+        self
+    }
+
+    /// Edge name, or its source and targets if it has none
+    pub fn label(&self) -> String {
+        self.metadata.name.clone().unwrap_or_else(|| {
+            let targets: Vec<&str> = self
+                .possible_targets()
+                .into_iter()
+                .map(String::as_str)
+                .collect();
+            format!("{} -> {}", self.from, targets.join("|"))
+        })
+    }
+
     /// Get all possible target nodes for this edge
     pub fn possible_targets(&self) -> Vec<&NodeId> {
         match &self.edge_type {
@@ -442,18 +478,51 @@ pub mod routers {
                 ));
             }
 
-            use rand::seq::SliceRandom;
-            let mut rng = rand::thread_rng();
-            Ok(possible_targets
-                .choose(&mut rng)
-                .unwrap()
-                .clone())
+            use rand::Rng;
+            let index = crate::graph::determinism::with_rng(|rng| rng.gen_range(0..possible_targets.len()));
+            Ok(possible_targets[index].clone())
         }
 
         fn router_id(&self) -> String {
             "random".to_string()
         }
     }
+
+    /// Router following the target named in a state value
+    ///
+    /// Names are matched case-insensitively against the possible targets;
+    /// anything else routes to the fallback target.
+    #[derive(Debug)]
+    pub struct StateKeyRouter {
+        key: String,
+        fallback: NodeId,
+    }
+
+    impl StateKeyRouter {
+        /// Route on the value under `key`, falling back to `fallback`
+        pub fn new<K: Into<String>, F: Into<NodeId>>(key: K, fallback: F) -> Self {
+            Self {
+                key: key.into(),
+                fallback: fallback.into(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<S: State> DynamicRouter<S> for StateKeyRouter {
+        async fn route(&self, state: &S, possible_targets: &[NodeId]) -> GraphResult<NodeId> {
+            let requested = state.get_value(&self.key)
+                .and_then(|value| value.as_str().map(|s| s.trim().to_lowercase()));
+            Ok(requested
+                .and_then(|name| possible_targets.iter().find(|target| target.to_lowercase() == name))
+                .cloned()
+                .unwrap_or_else(|| self.fallback.clone()))
+        }
+
+        fn router_id(&self) -> String {
+            format!("state_key:{}", self.key)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -116,9 +116,7 @@ where
                     ));
                 }
 
-                use rand::Rng;
-                let mut rng = rand::thread_rng();
-                let random_value = rng.gen::<f64>() * total_weight;
+                let random_value = crate::graph::determinism::random_f64() * total_weight;
 
                 let mut cumulative_weight = 0.0;
                 for (node_id, weight) in targets {
@@ -0,0 +1,158 @@
+//! Branch statistics and speculative execution settings for conditional edges.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Opt-in for running the likely branch of a conditional edge while its condition evaluates
+///
+/// The speculative run works on a copy of the state and is discarded if the
+/// condition picks the other branch, so side effects of the target node (LLM
+/// calls, tool calls) may happen twice or for nothing. Enable it only where
+/// that extra work is an acceptable price for lower latency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeculationConfig {
+    /// Evaluations of the edge observed before speculating
+    pub min_samples: u64,
+    /// Share of evaluations the likely branch must have won
+    pub min_probability: f64,
+}
+
+impl Default for SpeculationConfig {
+    fn default() -> Self {
+        Self {
+            min_samples: 20,
+            min_probability: 0.8,
+        }
+    }
+}
+
+/// How often each side of a conditional edge was taken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchCounts {
+    /// Times the condition was true
+    pub true_count: u64,
+    /// Times the condition was false
+    pub false_count: u64,
+}
+
+impl BranchCounts {
+    /// Total evaluations
+    pub fn total(&self) -> u64 {
+        self.true_count + self.false_count
+    }
+
+    /// The more frequent outcome and its observed probability
+    pub fn likely(&self) -> Option<(bool, f64)> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let outcome = self.true_count >= self.false_count;
+        let wins = if outcome { self.true_count } else { self.false_count };
+        Some((outcome, wins as f64 / total as f64))
+    }
+}
+
+/// Outcomes of conditional edges, shared by every execution of a graph
+///
+/// Counts can be exported with [`snapshot`](Self::snapshot) and loaded into a
+/// new process with [`load`](Self::load) so speculation does not start cold.
+#[derive(Debug, Default)]
+pub struct BranchStatistics {
+    counts: parking_lot::RwLock<HashMap<String, BranchCounts>>,
+}
+
+impl BranchStatistics {
+    /// Create empty statistics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an evaluation of `edge`
+    pub fn record(&self, edge: &str, outcome: bool) {
+        let mut counts = self.counts.write();
+        let entry = counts.entry(edge.to_string()).or_default();
+        if outcome {
+            entry.true_count += 1;
+        } else {
+            entry.false_count += 1;
+        }
+    }
+
+    /// Counts for `edge`
+    pub fn get(&self, edge: &str) -> BranchCounts {
+        self.counts.read().get(edge).copied().unwrap_or_default()
+    }
+
+    /// Outcome worth speculating on, if the history is long and lopsided enough
+    pub fn prediction(&self, edge: &str, config: &SpeculationConfig) -> Option<bool> {
+        let counts = self.get(edge);
+        if counts.total() < config.min_samples.max(1) {
+            return None;
+        }
+        counts
+            .likely()
+            .filter(|(_, probability)| *probability >= config.min_probability)
+            .map(|(outcome, _)| outcome)
+    }
+
+    /// Copy of all counts
+    pub fn snapshot(&self) -> HashMap<String, BranchCounts> {
+        self.counts.read().clone()
+    }
+
+    /// Add previously exported counts to the current ones
+    pub fn load(&self, history: HashMap<String, BranchCounts>) {
+        let mut counts = self.counts.write();
+        for (edge, loaded) in history {
+            let entry = counts.entry(edge).or_default();
+            entry.true_count += loaded.true_count;
+            entry.false_count += loaded.false_count;
+        }
+    }
+}
+
+/// Result of a speculative run, recorded in the execution context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeculationOutcome {
+    /// Conditional edge speculated on
+    pub edge: String,
+    /// Node run speculatively
+    pub predicted: String,
+    /// Node the condition chose
+    pub actual: String,
+    /// Whether the speculative result was kept
+    pub hit: bool,
+    /// Step after which the speculation happened
+    pub step: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prediction_needs_history_and_confidence() {
+        let stats = BranchStatistics::new();
+        let config = SpeculationConfig {
+            min_samples: 4,
+            min_probability: 0.75,
+        };
+
+        for outcome in [true, true, true] {
+            stats.record("review", outcome);
+        }
+        assert_eq!(stats.prediction("review", &config), None);
+
+        stats.record("review", false);
+        // 3 of 4 meets the threshold
+        assert_eq!(stats.prediction("review", &config), Some(true));
+
+        stats.record("review", false);
+        assert_eq!(stats.prediction("review", &config), None);
+
+        let restored = BranchStatistics::new();
+        restored.load(stats.snapshot());
+        assert_eq!(restored.get("review"), BranchCounts { true_count: 3, false_count: 2 });
+    }
+}
@@ -0,0 +1,163 @@
+//! Rate limiting for edge traversal.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Limits on how often an edge may be traversed
+///
+/// Both limits apply when set: traversals are spaced at least `min_interval`
+/// apart, and draw one token each from a bucket refilled at
+/// `tokens_per_minute` that holds up to `burst` tokens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeRateLimit {
+    /// Minimum time between traversals
+    pub min_interval: Option<Duration>,
+    /// Sustained traversals per minute
+    pub tokens_per_minute: Option<u32>,
+    /// Traversals allowed in a burst before `tokens_per_minute` applies
+    pub burst: u32,
+}
+
+impl EdgeRateLimit {
+    /// Space traversals at least `interval` apart
+    pub fn min_interval(interval: Duration) -> Self {
+        Self {
+            min_interval: Some(interval),
+            tokens_per_minute: None,
+            burst: 1,
+        }
+    }
+
+    /// Allow `tokens_per_minute` traversals per minute with bursts of `burst`
+    pub fn per_minute(tokens_per_minute: u32, burst: u32) -> Self {
+        Self {
+            min_interval: None,
+            tokens_per_minute: Some(tokens_per_minute),
+            burst: burst.max(1),
+        }
+    }
+
+    /// Also space traversals at least `interval` apart
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = Some(interval);
+        self
+    }
+}
+
+/// Reservation state of one edge
+#[derive(Debug)]
+struct EdgeBucket {
+    /// When the last reserved traversal happens
+    last_traversal: Option<Instant>,
+    /// Tokens available at `refilled_at`
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Tracks traversals of rate-limited edges
+///
+/// Waits are reserved under a lock and slept outside it, so concurrent
+/// branches crossing the same edge queue up rather than all waking together.
+#[derive(Debug, Default)]
+pub struct EdgeThrottle {
+    buckets: parking_lot::Mutex<HashMap<String, EdgeBucket>>,
+}
+
+impl EdgeThrottle {
+    /// Create an empty throttle
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserve a traversal of `edge_key` and return how long to wait for it
+    pub fn reserve(&self, edge_key: &str, limit: &EdgeRateLimit) -> Duration {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets.entry(edge_key.to_string()).or_insert_with(|| EdgeBucket {
+            last_traversal: None,
+            tokens: limit.burst.max(1) as f64,
+            refilled_at: now,
+        });
+
+        let mut ready_at = now;
+        if let (Some(interval), Some(last)) = (limit.min_interval, bucket.last_traversal) {
+            ready_at = ready_at.max(last + interval);
+        }
+
+        if let Some(per_minute) = limit.tokens_per_minute.filter(|rate| *rate > 0) {
+            let rate = per_minute as f64 / 60.0;
+            let capacity = limit.burst.max(1) as f64;
+            // Earlier reservations may already have drawn tokens up to a future time
+            let start = ready_at.max(bucket.refilled_at);
+            let elapsed = start.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+            bucket.refilled_at = start;
+
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+            } else {
+                ready_at = start + Duration::from_secs_f64((1.0 - bucket.tokens) / rate);
+                bucket.tokens = 0.0;
+                bucket.refilled_at = ready_at;
+            }
+        }
+
+        bucket.last_traversal = Some(ready_at);
+        ready_at.saturating_duration_since(now)
+    }
+
+    /// Wait until `edge_key` may be traversed, returning the time waited
+    pub async fn acquire(&self, edge_key: &str, limit: &EdgeRateLimit) -> Duration {
+        let wait = self.reserve(edge_key, limit);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+}
+
+/// A wait imposed by a rate-limited edge, recorded in the execution context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeWait {
+    /// Edge that was throttled
+    pub edge: String,
+    /// Step after which the wait happened
+    pub step: u64,
+    /// Time waited
+    pub waited: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_interval_spacing() {
+        let throttle = EdgeThrottle::new();
+        let limit = EdgeRateLimit::min_interval(Duration::from_secs(10));
+
+        assert_eq!(throttle.reserve("poll", &limit), Duration::ZERO);
+        let second = throttle.reserve("poll", &limit);
+        assert!(second > Duration::from_secs(9) && second <= Duration::from_secs(10));
+        // Queued behind the second traversal
+        assert!(throttle.reserve("poll", &limit) > Duration::from_secs(19));
+        // Other edges are independent
+        assert_eq!(throttle.reserve("other", &limit), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_token_bucket_burst() {
+        let throttle = EdgeThrottle::new();
+        let limit = EdgeRateLimit::per_minute(6, 2);
+
+        assert_eq!(throttle.reserve("retry", &limit), Duration::ZERO);
+        assert_eq!(throttle.reserve("retry", &limit), Duration::ZERO);
+        // Bucket empty: one token every 10 seconds
+        let third = throttle.reserve("retry", &limit);
+        assert!(third > Duration::from_secs(9) && third <= Duration::from_secs(10));
+        let fourth = throttle.reserve("retry", &limit);
+        assert!(fourth > Duration::from_secs(19) && fourth <= Duration::from_secs(20));
+    }
+}
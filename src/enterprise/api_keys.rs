@@ -0,0 +1,396 @@
+// API keys for programmatic access
+// Issues, rotates and revokes tenant- and role-scoped keys, stored only as hashes
+
+#![allow(missing_docs)]
+
+use crate::enterprise::audit::{AuditEvent, AuditLevel, AuditLogger};
+use crate::sha256_hex;
+use crate::visualization::auth::StudioRole;
+use chrono::{DateTime, Duration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Prefix of every issued secret, so leaked keys are easy to spot
+pub const API_KEY_PREFIX: &str = "agk_";
+
+/// A programmatic access key; the secret itself is never stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    /// Tenant whose data the key reaches; `None` reaches every tenant
+    pub tenant: Option<String>,
+    pub role: StudioRole,
+    /// SHA-256 of the current secret
+    secret_hash: String,
+    /// SHA-256 of the secret replaced by the last rotation, and until when it still works
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_secret: Option<(String, DateTime<Utc>)>,
+    /// Requests allowed per minute; `None` is unlimited
+    pub rate_limit: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn is_revoked(&self) -> bool {
+        self.revoked_at.is_some()
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    fn matches(&self, hash: &str, now: DateTime<Utc>) -> bool {
+        self.secret_hash == hash
+            || self
+                .previous_secret
+                .as_ref()
+                .is_some_and(|(previous, until)| previous == hash && *until > now)
+    }
+}
+
+/// What a new key may do
+#[derive(Debug, Clone)]
+pub struct ApiKeySpec {
+    pub name: String,
+    pub tenant: Option<String>,
+    pub role: StudioRole,
+    pub rate_limit: Option<u32>,
+    pub expires_in: Option<Duration>,
+}
+
+impl ApiKeySpec {
+    /// A key confined to one tenant
+    pub fn new(name: impl Into<String>, tenant: impl Into<String>, role: StudioRole) -> Self {
+        Self {
+            name: name.into(),
+            tenant: Some(tenant.into()),
+            role,
+            rate_limit: None,
+            expires_in: None,
+        }
+    }
+
+    /// A key reaching every tenant
+    pub fn unscoped(name: impl Into<String>, role: StudioRole) -> Self {
+        Self {
+            name: name.into(),
+            tenant: None,
+            role,
+            rate_limit: None,
+            expires_in: None,
+        }
+    }
+
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limit = Some(requests_per_minute);
+        self
+    }
+
+    pub fn with_expiry(mut self, expires_in: Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+}
+
+/// A key as handed to its owner; `secret` is shown once and cannot be recovered
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedApiKey {
+    pub key: ApiKey,
+    pub secret: String,
+}
+
+/// Issues API keys and checks the ones presented by clients
+#[derive(Debug, Default)]
+pub struct ApiKeyManager {
+    keys: parking_lot::RwLock<HashMap<String, ApiKey>>,
+    /// Start of each key's current minute and the requests made in it
+    windows: parking_lot::Mutex<HashMap<String, (DateTime<Utc>, u32)>>,
+    audit_logger: Option<Arc<AuditLogger>>,
+}
+
+impl ApiKeyManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record issuance, rotation, revocation and refused keys
+    pub fn with_audit_logger(mut self, audit_logger: Arc<AuditLogger>) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Load keys saved with [`save_to_file`](Self::save_to_file); a missing file holds no keys
+    pub fn from_file(path: &std::path::Path) -> Result<Self, ApiKeyError> {
+        let manager = Self::new();
+        if !path.exists() {
+            return Ok(manager);
+        }
+        let storage_error = |e: &dyn std::fmt::Display| ApiKeyError::Storage(format!("{}: {}", path.display(), e));
+        let json = std::fs::read_to_string(path).map_err(|e| storage_error(&e))?;
+        let keys: Vec<ApiKey> = serde_json::from_str(&json).map_err(|e| storage_error(&e))?;
+        *manager.keys.write() = keys.into_iter().map(|key| (key.id.clone(), key)).collect();
+        Ok(manager)
+    }
+
+    /// Write every key, hashes included, to `path`
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), ApiKeyError> {
+        let storage_error = |e: &dyn std::fmt::Display| ApiKeyError::Storage(format!("{}: {}", path.display(), e));
+        let json = serde_json::to_string_pretty(&self.list(None)).map_err(|e| storage_error(&e))?;
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| storage_error(&e))?;
+        }
+        // Only the owner may read the key hashes
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path).map_err(|e| storage_error(&e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600)).map_err(|e| storage_error(&e))?;
+        }
+        std::io::Write::write_all(&mut file, json.as_bytes()).map_err(|e| storage_error(&e))
+    }
+
+    /// Create a key and return it with its secret
+    pub async fn issue(&self, spec: ApiKeySpec) -> IssuedApiKey {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..16].to_string();
+        let secret = new_secret(&id);
+        let now = crate::graph::determinism::now();
+        let key = ApiKey {
+            id: id.clone(),
+            name: spec.name,
+            tenant: spec.tenant,
+            role: spec.role,
+            secret_hash: sha256_hex(&secret),
+            previous_secret: None,
+            rate_limit: spec.rate_limit,
+            created_at: now,
+            expires_at: spec.expires_in.map(|expires_in| now + expires_in),
+            rotated_at: None,
+            revoked_at: None,
+            last_used_at: None,
+        };
+        self.keys.write().insert(id, key.clone());
+        self.audit(&key, "api_key_issued", format!("API key '{}' issued", key.name), AuditLevel::Info).await;
+        IssuedApiKey { key, secret }
+    }
+
+    /// Give a key a new secret, keeping the old one working for `grace`
+    /// so clients can be switched over without downtime
+    pub async fn rotate(&self, id: &str, grace: Duration) -> Result<IssuedApiKey, ApiKeyError> {
+        let secret = new_secret(id);
+        let key = {
+            let mut keys = self.keys.write();
+            let key = keys.get_mut(id).ok_or_else(|| ApiKeyError::NotFound(id.to_string()))?;
+            if key.is_revoked() {
+                return Err(ApiKeyError::Revoked(id.to_string()));
+            }
+            let now = crate::graph::determinism::now();
+            let old_hash = std::mem::replace(&mut key.secret_hash, sha256_hex(&secret));
+            key.previous_secret = (grace > Duration::zero()).then(|| (old_hash, now + grace));
+            key.rotated_at = Some(now);
+            key.clone()
+        };
+        self.audit(&key, "api_key_rotated", format!("API key '{}' rotated", key.name), AuditLevel::Info).await;
+        Ok(IssuedApiKey { key, secret })
+    }
+
+    /// Stop a key working, old secrets included; the record is kept for auditing
+    pub async fn revoke(&self, id: &str) -> Result<ApiKey, ApiKeyError> {
+        let key = {
+            let mut keys = self.keys.write();
+            let key = keys.get_mut(id).ok_or_else(|| ApiKeyError::NotFound(id.to_string()))?;
+            key.revoked_at.get_or_insert_with(crate::graph::determinism::now);
+            key.previous_secret = None;
+            key.clone()
+        };
+        self.windows.lock().remove(id);
+        self.audit(&key, "api_key_revoked", format!("API key '{}' revoked", key.name), AuditLevel::Warning).await;
+        Ok(key)
+    }
+
+    pub fn get(&self, id: &str) -> Option<ApiKey> {
+        self.keys.read().get(id).cloned()
+    }
+
+    /// Keys of `tenant`, or every key for `None`, oldest first
+    pub fn list(&self, tenant: Option<&str>) -> Vec<ApiKey> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .read()
+            .values()
+            .filter(|key| tenant.is_none() || key.tenant.as_deref() == tenant)
+            .cloned()
+            .collect();
+        keys.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        keys
+    }
+
+    /// Key a client presented, counted against its rate limit
+    ///
+    /// Secrets not issued by this manager are `Err(ApiKeyError::Unknown)`,
+    /// so callers can try other credentials.
+    pub async fn authenticate(&self, secret: &str) -> Result<ApiKey, ApiKeyError> {
+        let id = secret
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|rest| rest.split_once('_'))
+            .map(|(id, _)| id)
+            .ok_or(ApiKeyError::Unknown)?;
+        let now = crate::graph::determinism::now();
+        let hash = sha256_hex(secret);
+
+        let key = {
+            let mut keys = self.keys.write();
+            let key = keys.get_mut(id).filter(|key| key.matches(&hash, now)).ok_or(ApiKeyError::Unknown)?;
+            if key.is_revoked() {
+                Err(ApiKeyError::Revoked(key.id.clone()))
+            } else if key.is_expired(now) {
+                Err(ApiKeyError::Expired(key.id.clone()))
+            } else {
+                self.check_rate_limit(key, now).map(|()| {
+                    key.last_used_at = Some(now);
+                    key.clone()
+                })
+            }
+        };
+        if let Err(e) = &key {
+            if let Some(refused) = self.get(id) {
+                self.audit(&refused, "api_key_refused", e.to_string(), AuditLevel::Warning).await;
+            }
+        }
+        key
+    }
+
+    fn check_rate_limit(&self, key: &ApiKey, now: DateTime<Utc>) -> Result<(), ApiKeyError> {
+        let Some(limit) = key.rate_limit else {
+            return Ok(());
+        };
+        let mut windows = self.windows.lock();
+        let (started, requests) = windows.entry(key.id.clone()).or_insert((now, 0));
+        if now - *started >= Duration::minutes(1) {
+            *started = now;
+            *requests = 0;
+        }
+        if *requests >= limit {
+            let retry_after = (*started + Duration::minutes(1) - now).num_seconds().max(1) as u64;
+            return Err(ApiKeyError::RateLimited { id: key.id.clone(), retry_after_secs: retry_after });
+        }
+        *requests += 1;
+        Ok(())
+    }
+
+    async fn audit(&self, key: &ApiKey, event_type: &str, description: String, level: AuditLevel) {
+        let Some(logger) = &self.audit_logger else {
+            return;
+        };
+        let mut event = AuditEvent::security_event(event_type.to_string(), description)
+            .with_level(level)
+            .with_resource(format!("api_key:{}", key.id))
+            .with_data("role".to_string(), key.role);
+        if let Some(tenant) = &key.tenant {
+            event = event.with_tenant(tenant.clone());
+        }
+        if let Err(e) = logger.log_event(event).await {
+            tracing::warn!("Failed to record API key audit event: {}", e);
+        }
+    }
+}
+
+fn new_secret(id: &str) -> String {
+    let random: String = rand::thread_rng().sample_iter(&Alphanumeric).take(40).map(char::from).collect();
+    format!("{}{}_{}", API_KEY_PREFIX, id, random)
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("Unknown API key")]
+    Unknown,
+
+    #[error("API key {0} not found")]
+    NotFound(String),
+
+    #[error("API key {0} has been revoked")]
+    Revoked(String),
+
+    #[error("API key {0} has expired")]
+    Expired(String),
+
+    #[error("API key {id} exceeded its rate limit; retry in {retry_after_secs}s")]
+    RateLimited { id: String, retry_after_secs: u64 },
+
+    #[error("API key storage error: {0}")]
+    Storage(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let manager = ApiKeyManager::new();
+        let issued = manager
+            .issue(ApiKeySpec::new("ci", "acme", StudioRole::Operator).with_rate_limit(2))
+            .await;
+        assert!(issued.secret.starts_with(API_KEY_PREFIX));
+        assert!(!serde_json::to_string(&manager.list(None)).unwrap().contains(&issued.secret));
+
+        let key = manager.authenticate(&issued.secret).await.unwrap();
+        assert_eq!(key.tenant.as_deref(), Some("acme"));
+        assert!(key.last_used_at.is_some());
+        manager.authenticate(&issued.secret).await.unwrap();
+        assert!(matches!(manager.authenticate(&issued.secret).await, Err(ApiKeyError::RateLimited { .. })));
+        assert!(matches!(manager.authenticate("agk_nope_secret").await, Err(ApiKeyError::Unknown)));
+
+        let other = manager.issue(ApiKeySpec::unscoped("ops", StudioRole::Viewer)).await;
+        let rotated = manager.rotate(&other.key.id, Duration::minutes(5)).await.unwrap();
+        manager.authenticate(&other.secret).await.unwrap();
+        manager.authenticate(&rotated.secret).await.unwrap();
+        let rotated_again = manager.rotate(&other.key.id, Duration::zero()).await.unwrap();
+        assert!(matches!(manager.authenticate(&rotated.secret).await, Err(ApiKeyError::Unknown)));
+
+        manager.revoke(&other.key.id).await.unwrap();
+        assert!(matches!(manager.authenticate(&rotated_again.secret).await, Err(ApiKeyError::Revoked(_))));
+        assert_eq!(manager.list(Some("acme")).len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        manager.save_to_file(&path).unwrap();
+        let reloaded = ApiKeyManager::from_file(&path).unwrap();
+        assert!(matches!(reloaded.authenticate(&rotated_again.secret).await, Err(ApiKeyError::Revoked(_))));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_key_expiry_follows_the_execution_clock() {
+        use crate::graph::determinism::{self, Determinism, FrozenClock};
+
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let determinism = Determinism::new().with_clock(clock.clone());
+        let manager = ApiKeyManager::new();
+        determinism::scope(&determinism, async {
+            let issued = manager
+                .issue(ApiKeySpec::unscoped("batch", StudioRole::Viewer).with_expiry(Duration::hours(1)))
+                .await;
+            manager.authenticate(&issued.secret).await.unwrap();
+            clock.advance(std::time::Duration::from_secs(2 * 60 * 60));
+            assert!(matches!(manager.authenticate(&issued.secret).await, Err(ApiKeyError::Expired(_))));
+        })
+        .await;
+    }
+}
@@ -3,6 +3,7 @@
 
 #![allow(missing_docs)]
 
+use crate::enterprise::audit_chain::{AnchorPoint, AuditAnchor, ChainHead, ChainVerification};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -40,6 +41,15 @@ pub struct AuditEvent {
     pub user_agent: Option<String>,
     /// Request ID for correlation
     pub request_id: Option<String>,
+    /// Position in the hash chain, when the log is chained
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    /// Hash of the event before this one in the chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_hash: Option<String>,
+    /// Hash of this event, covering `previous_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
 }
 
 impl AuditEvent {
@@ -60,6 +70,9 @@ impl AuditEvent {
             source_ip: None,
             user_agent: None,
             request_id: None,
+            sequence: None,
+            previous_hash: None,
+            hash: None,
         }
     }
     
@@ -117,7 +130,7 @@ impl AuditEvent {
 }
 
 /// Types of audit events
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum AuditEventType {
     /// Authentication events
     Authentication,
@@ -142,7 +155,7 @@ pub enum AuditEventType {
 }
 
 /// Audit event severity levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum AuditLevel {
     /// Debug information
     Debug,
@@ -157,7 +170,7 @@ pub enum AuditLevel {
 }
 
 /// Audit configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct AuditConfig {
     /// Enable audit logging
     pub enabled: bool,
@@ -173,6 +186,19 @@ pub struct AuditConfig {
     pub alerts_enabled: bool,
     /// Compliance standards to follow
     pub compliance_standards: Vec<ComplianceStandard>,
+    /// Append-only mode with tamper-evident hash chaining
+    #[serde(default)]
+    pub hash_chain: HashChainConfig,
+}
+
+/// Append-only audit mode, where each event carries the hash of the one
+/// before it so edits, deletions and insertions can be detected
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HashChainConfig {
+    /// Chain events
+    pub enabled: bool,
+    /// Record the chain head with the logger's anchor every this many events
+    pub anchor_every: Option<u64>,
 }
 
 impl Default for AuditConfig {
@@ -190,12 +216,13 @@ impl Default for AuditConfig {
             retention: RetentionPolicy::default(),
             alerts_enabled: true,
             compliance_standards: vec![ComplianceStandard::SOC2],
+            hash_chain: HashChainConfig::default(),
         }
     }
 }
 
 /// Audit storage configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct AuditStorageConfig {
     /// Storage backend type
     pub backend: AuditStorageBackend,
@@ -225,7 +252,7 @@ impl Default for AuditStorageConfig {
 }
 
 /// Audit storage backend types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum AuditStorageBackend {
     /// File-based storage
     File,
@@ -240,7 +267,7 @@ pub enum AuditStorageBackend {
 }
 
 /// Retention policy for audit logs
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct RetentionPolicy {
     /// Retention period in days
     pub retention_days: u32,
@@ -264,7 +291,7 @@ impl Default for RetentionPolicy {
 }
 
 /// Compliance standards
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum ComplianceStandard {
     /// SOC 2 compliance
     SOC2,
@@ -285,6 +312,14 @@ pub struct AuditLogger {
     config: AuditConfig,
     /// Event buffer for batching
     event_buffer: Arc<Mutex<Vec<AuditEvent>>>,
+    /// Flushed events, for the memory backend
+    stored_events: Arc<Mutex<Vec<AuditEvent>>>,
+    /// Held while a batch is taken out of the buffer and written, so batches land in order
+    write_lock: tokio::sync::Mutex<()>,
+    /// Last link of the hash chain, in append-only mode
+    chain: Option<Mutex<ChainHead>>,
+    /// Where the chain head is recorded outside the log
+    anchor: Option<Arc<dyn AuditAnchor>>,
     /// Statistics
     stats: Arc<Mutex<AuditStats>>,
 }
@@ -292,15 +327,44 @@ pub struct AuditLogger {
 impl AuditLogger {
     /// Create a new audit logger
     pub fn new(config: AuditConfig) -> Result<Self, AuditError> {
+        // A chained file log carries on from its last event
+        let chain = match (config.hash_chain.enabled, config.storage.backend) {
+            (false, _) => None,
+            (true, AuditStorageBackend::File) => {
+                let last = last_logged_event(std::path::Path::new(&config.storage.location))?;
+                Some(Mutex::new(ChainHead::resume(last.as_ref())))
+            }
+            (true, _) => Some(Mutex::new(ChainHead::resume(None))),
+        };
         Ok(Self {
             config,
             event_buffer: Arc::new(Mutex::new(Vec::new())),
+            stored_events: Arc::new(Mutex::new(Vec::new())),
+            write_lock: tokio::sync::Mutex::new(()),
+            chain,
+            anchor: None,
             stats: Arc::new(Mutex::new(AuditStats::default())),
         })
     }
     
+    /// Logger keeping every event in memory, writing each as it is logged
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> Self {
+        Self::new(AuditConfig {
+            storage: AuditStorageConfig { backend: AuditStorageBackend::Memory, batch_size: 1, ..Default::default() },
+            ..Default::default()
+        })
+        .expect("in-memory audit loggers need no storage")
+    }
+
+    /// Record the chain head with `anchor` every `hash_chain.anchor_every` events
+    pub fn with_anchor(mut self, anchor: Arc<dyn AuditAnchor>) -> Self {
+        self.anchor = Some(anchor);
+        self
+    }
+    
     /// Log an audit event
-    pub async fn log_event(&self, event: AuditEvent) -> Result<(), AuditError> {
+    pub async fn log_event(&self, mut event: AuditEvent) -> Result<(), AuditError> {
         if !self.config.enabled {
             return Ok(());
         }
@@ -310,17 +374,19 @@ impl AuditLogger {
             return Ok(());
         }
         
-        // Add to buffer
-        {
+        // Chain and buffer under one lock, so buffer order is chain order
+        let full = {
             let mut buffer = self.event_buffer.lock().unwrap();
-            buffer.push(event.clone());
-            
-            // Flush if buffer is full
-            if buffer.len() >= self.config.storage.batch_size as usize {
-                let events = buffer.drain(..).collect();
-                drop(buffer); // Release lock before async operation
-                self.flush_events(events).await?;
+            if let Some(chain) = &self.chain {
+                chain.lock().unwrap().link(&mut event);
             }
+            buffer.push(event.clone());
+            buffer.len() >= self.config.storage.batch_size as usize
+        };
+        
+        // Flush outside the lock so the future stays Send
+        if full {
+            self.flush().await?;
         }
         
         // Update statistics
@@ -355,51 +421,83 @@ impl AuditLogger {
         true
     }
     
-    /// Flush events to storage
-    async fn flush_events(&self, events: Vec<AuditEvent>) -> Result<(), AuditError> {
-        match self.config.storage.backend {
-            AuditStorageBackend::File => self.flush_to_file(events).await,
-            AuditStorageBackend::Memory => Ok(()), // No-op for memory backend
+    /// Flush events to storage, putting them back in the buffer if they
+    /// could not be written
+    async fn flush_events(&self, mut events: Vec<AuditEvent>) -> Result<(), AuditError> {
+        let anchor_points: Vec<AnchorPoint> = match self.config.hash_chain.anchor_every {
+            Some(every) if every > 0 && self.anchor.is_some() => events
+                .iter()
+                .filter(|event| event.sequence.is_some_and(|sequence| (sequence + 1) % every == 0))
+                .filter_map(AnchorPoint::of)
+                .collect(),
+            _ => Vec::new(),
+        };
+        
+        let written = match self.config.storage.backend {
+            AuditStorageBackend::File => self.flush_to_file(&events).await,
+            AuditStorageBackend::Memory => {
+                self.stored_events.lock().unwrap().append(&mut events);
+                Ok(())
+            }
             _ => Err(AuditError::StorageError {
                 message: "Storage backend not implemented".to_string(),
             }),
+        };
+        if let Err(e) = written {
+            // Already chained, so they go back ahead of anything logged since
+            self.event_buffer.lock().unwrap().splice(0..0, events);
+            return Err(e);
+        }
+        
+        // Only anchor what has been written
+        if let Some(anchor) = &self.anchor {
+            for point in &anchor_points {
+                anchor.anchor(point).await?;
+            }
         }
+        Ok(())
     }
     
-    /// Flush events to file
-    async fn flush_to_file(&self, events: Vec<AuditEvent>) -> Result<(), AuditError> {
+    /// Flush events to file, serializing all of them before writing any
+    async fn flush_to_file(&self, events: &[AuditEvent]) -> Result<(), AuditError> {
         use std::io::Write;
         
-        let mut file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.config.storage.location)
-            .map_err(|e| AuditError::StorageError {
-                message: format!("Failed to open audit log file: {}", e),
-            })?;
-        
+        let mut lines = String::new();
         for event in events {
-            let json_line = serde_json::to_string(&event)
+            let json_line = serde_json::to_string(event)
                 .map_err(|e| AuditError::SerializationError {
                     message: format!("Failed to serialize audit event: {}", e),
                 })?;
-            
-            writeln!(file, "{}", json_line)
-                .map_err(|e| AuditError::StorageError {
-                    message: format!("Failed to write audit event: {}", e),
-                })?;
+            lines.push_str(&json_line);
+            lines.push('\n');
         }
         
-        file.flush()
-            .map_err(|e| AuditError::StorageError {
-                message: format!("Failed to flush audit log: {}", e),
-            })?;
+        let storage_error = |action: &str, e: std::io::Error| AuditError::StorageError {
+            message: format!("Failed to {} audit log: {}", action, e),
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.config.storage.location)
+            .map_err(|e| storage_error("open", e))?;
+        // A batch that failed half-way may have left part of a line behind
+        let start = repair_torn_tail(&mut file).map_err(|e| storage_error("repair", e))?;
+        
+        if let Err(e) = file.write_all(lines.as_bytes()).and_then(|_| file.flush()) {
+            // The batch goes back in the buffer, so take back what was written of it
+            if let Err(truncate_error) = file.set_len(start) {
+                tracing::warn!("Failed to take back a partly written audit batch: {}", truncate_error);
+            }
+            return Err(storage_error("write events to", e));
+        }
         
         Ok(())
     }
     
     /// Force flush all buffered events
     pub async fn flush(&self) -> Result<(), AuditError> {
+        let _writing = self.write_lock.lock().await;
         let events: Vec<AuditEvent> = {
             let mut buffer = self.event_buffer.lock().unwrap();
             buffer.drain(..).collect()
@@ -417,6 +515,43 @@ impl AuditLogger {
         self.stats.lock().unwrap().clone()
     }
     
+    /// Events logged between `start_time` and `end_time`, stored or still
+    /// buffered, optionally only those of one tenant, oldest first
+    pub async fn events(
+        &self,
+        start_time: SystemTime,
+        end_time: SystemTime,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<AuditEvent>, AuditError> {
+        let mut events = self.logged_events()?;
+        events.retain(|event| {
+            event.timestamp >= start_time
+                && event.timestamp <= end_time
+                && (tenant_id.is_none() || event.tenant_id.as_deref() == tenant_id)
+        });
+        events.sort_by_key(|event| event.timestamp);
+        Ok(events)
+    }
+    
+    /// Every stored or still buffered event, in the order logged
+    fn logged_events(&self) -> Result<Vec<AuditEvent>, AuditError> {
+        let mut events = match self.config.storage.backend {
+            AuditStorageBackend::File => read_log_file(std::path::Path::new(&self.config.storage.location))?,
+            AuditStorageBackend::Memory => self.stored_events.lock().unwrap().clone(),
+            _ => return Err(AuditError::StorageError {
+                message: "Storage backend not implemented".to_string(),
+            }),
+        };
+        events.extend(self.event_buffer.lock().unwrap().iter().cloned());
+        Ok(events)
+    }
+    
+    /// Check the hash chain of every logged event, and that it still holds
+    /// the chain heads recorded in `anchors`
+    pub fn verify_chain(&self, anchors: &[AnchorPoint]) -> Result<ChainVerification, AuditError> {
+        Ok(crate::enterprise::audit_chain::verify_chain(&self.logged_events()?, anchors))
+    }
+    
     /// Generate compliance report
     pub async fn generate_compliance_report(
         &self,
@@ -424,18 +559,26 @@ impl AuditLogger {
         start_time: SystemTime,
         end_time: SystemTime,
     ) -> Result<ComplianceReport, AuditError> {
-        // This would read from storage and generate a compliance report
-        // For now, return a basic report
-        Ok(ComplianceReport {
+        self.generate_tenant_compliance_report(standard, start_time, end_time, None).await
+    }
+    
+    /// Generate a compliance report covering one tenant's events, or every
+    /// tenant's for `None`
+    pub async fn generate_tenant_compliance_report(
+        &self,
+        standard: ComplianceStandard,
+        start_time: SystemTime,
+        end_time: SystemTime,
+        tenant_id: Option<&str>,
+    ) -> Result<ComplianceReport, AuditError> {
+        let events = self.events(start_time, end_time, tenant_id).await?;
+        Ok(ComplianceReport::from_events(
             standard,
-            period_start: start_time,
-            period_end: end_time,
-            total_events: 0,
-            compliance_score: 100.0,
-            findings: Vec::new(),
-            recommendations: Vec::new(),
-            generated_at: SystemTime::now(),
-        })
+            (start_time, end_time),
+            tenant_id,
+            &events,
+            &self.config,
+        ))
     }
     
     /// Get configuration
@@ -444,6 +587,101 @@ impl AuditLogger {
     }
 }
 
+/// Read the events of an audit log written by the file backend, in order
+pub fn read_log_file(path: &std::path::Path) -> Result<Vec<AuditEvent>, AuditError> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AuditError::StorageError {
+            message: format!("Failed to read audit log file: {}", e),
+        }),
+    };
+    
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| AuditError::SerializationError {
+                message: format!("Invalid audit event on line {} of {}: {}", index + 1, path.display(), e),
+            })
+        })
+        .collect()
+}
+
+/// Position of the last newline in `file` before `end`
+fn last_newline_before(file: &mut std::fs::File, end: u64) -> std::io::Result<Option<u64>> {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut chunk = [0u8; 8192];
+    let mut end = end;
+    while end > 0 {
+        let start = end.saturating_sub(chunk.len() as u64);
+        let size = (end - start) as usize;
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk[..size])?;
+        if let Some(index) = chunk[..size].iter().rposition(|&byte| byte == b'\n') {
+            return Ok(Some(start + index as u64));
+        }
+        end = start;
+    }
+    Ok(None)
+}
+
+/// Make sure a log file ends with a complete line, returning its length
+///
+/// A last line without its newline is completed if it holds a whole event,
+/// and cut off otherwise: it was left by a write that failed or a process
+/// that died part-way, and its events were never acknowledged.
+fn repair_torn_tail(file: &mut std::fs::File) -> std::io::Result<u64> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+    let len = file.metadata()?.len();
+    let complete = match last_newline_before(file, len)? {
+        Some(newline) => newline + 1,
+        None => 0,
+    };
+    if complete == len {
+        return Ok(len);
+    }
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(complete))?;
+    file.read_to_end(&mut tail)?;
+    if serde_json::from_slice::<AuditEvent>(&tail).is_ok() {
+        file.write_all(b"\n")?;
+        return Ok(len + 1);
+    }
+    tracing::warn!(bytes = len - complete, "Cutting a partly written line off the audit log");
+    file.set_len(complete)?;
+    Ok(complete)
+}
+
+/// Last event of an audit log file, repairing a torn last line, without reading the rest
+fn last_logged_event(path: &std::path::Path) -> Result<Option<AuditEvent>, AuditError> {
+    use std::io::{Read, Seek, SeekFrom};
+    let storage_error = |e: std::io::Error| AuditError::StorageError {
+        message: format!("Failed to read audit log file: {}", e),
+    };
+    let mut file = match std::fs::OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(storage_error(e)),
+    };
+    let mut end = repair_torn_tail(&mut file).map_err(storage_error)?;
+    // Step back over blank lines to the last one holding an event
+    while end > 0 {
+        let start = last_newline_before(&mut file, end - 1).map_err(storage_error)?.map_or(0, |newline| newline + 1);
+        let mut line = vec![0u8; (end - 1 - start) as usize];
+        file.seek(SeekFrom::Start(start)).map_err(storage_error)?;
+        file.read_exact(&mut line).map_err(storage_error)?;
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            return serde_json::from_slice(&line).map(Some).map_err(|e| AuditError::SerializationError {
+                message: format!("Invalid last audit event in {}: {}", path.display(), e),
+            });
+        }
+        end = start;
+    }
+    Ok(None)
+}
+
 /// Audit statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditStats {
@@ -480,8 +718,20 @@ pub struct ComplianceReport {
     pub period_start: SystemTime,
     /// Report period end
     pub period_end: SystemTime,
+    /// Tenant the report covers; `None` covers every tenant
+    #[serde(default)]
+    pub tenant_id: Option<String>,
     /// Total events in period
     pub total_events: u64,
+    /// Events in period by type
+    #[serde(default)]
+    pub events_by_type: HashMap<AuditEventType, u64>,
+    /// Who authenticated or was refused access in the period
+    #[serde(default)]
+    pub access_records: Vec<AccessRecord>,
+    /// Retention settings the audit log was kept under
+    #[serde(default)]
+    pub retention: RetentionPolicy,
     /// Compliance score (0-100)
     pub compliance_score: f64,
     /// Compliance findings
@@ -492,6 +742,23 @@ pub struct ComplianceReport {
     pub generated_at: SystemTime,
 }
 
+/// Authentication and authorization activity of one principal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessRecord {
+    /// User ID, or the resource acted on when no user was recorded
+    pub principal: String,
+    /// Successful logins
+    pub successful_logins: u64,
+    /// Failed logins
+    pub failed_logins: u64,
+    /// Requests refused for lack of permission
+    pub access_denied: u64,
+    /// First access in the period
+    pub first_seen: SystemTime,
+    /// Last access in the period
+    pub last_seen: SystemTime,
+}
+
 /// Compliance finding
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComplianceFinding {
@@ -554,6 +821,7 @@ impl AuditEvent {
             format!("User {} login {}", user_id, if success { "successful" } else { "failed" }),
         )
         .with_user(user_id)
+        .with_data("success".to_string(), success)
         .with_level(if success { AuditLevel::Info } else { AuditLevel::Warning })
     }
     
@@ -581,6 +849,18 @@ impl AuditEvent {
         .with_level(AuditLevel::Warning)
     }
     
+    /// Tool execution event
+    pub fn tool_execution(tool_id: String, resource: String, allowed: bool) -> Self {
+        Self::new(
+            AuditEventType::ToolExecution,
+            tool_id.clone(),
+            format!("Tool {} {} for {}", tool_id, if allowed { "executed" } else { "blocked" }, resource),
+        )
+        .with_resource(resource)
+        .with_data("allowed".to_string(), allowed)
+        .with_level(if allowed { AuditLevel::Info } else { AuditLevel::Warning })
+    }
+    
     /// Security event
     pub fn security_event(event_type: String, description: String) -> Self {
         Self::new(
@@ -612,15 +892,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_audit_logger() {
-        let config = AuditConfig {
-            storage: AuditStorageConfig {
-                backend: AuditStorageBackend::Memory,
-                ..Default::default()
-            },
-            ..Default::default()
-        };
-        
-        let logger = AuditLogger::new(config).unwrap();
+        let logger = AuditLogger::in_memory();
         
         let event = AuditEvent::user_login("test_user".to_string(), true);
         logger.log_event(event).await.unwrap();
@@ -630,6 +902,59 @@ mod tests {
         assert_eq!(stats.events_by_type.get(&AuditEventType::Authentication), Some(&1));
     }
 
+    #[tokio::test]
+    async fn test_failed_flush_keeps_chained_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("logs").join("audit.log");
+        let logger = AuditLogger::new(AuditConfig {
+            storage: AuditStorageConfig { location: log.to_string_lossy().into_owned(), ..Default::default() },
+            hash_chain: HashChainConfig { enabled: true, anchor_every: None },
+            ..Default::default()
+        })
+        .unwrap();
+
+        for user in ["alice", "bob"] {
+            logger.log_event(AuditEvent::user_login(user.to_string(), true)).await.unwrap();
+        }
+        // The log directory does not exist yet
+        assert!(logger.flush().await.is_err());
+        logger.log_event(AuditEvent::user_login("carol".to_string(), true)).await.unwrap();
+
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        logger.flush().await.unwrap();
+        let events = read_log_file(&log).unwrap();
+        assert_eq!(events.iter().map(|event| event.sequence).collect::<Vec<_>>(), vec![Some(0), Some(1), Some(2)]);
+        let verification = logger.verify_chain(&[]).unwrap();
+        assert!(verification.is_intact(), "{:?}", verification.problems);
+    }
+
+    #[tokio::test]
+    async fn test_chained_log_resumes_after_a_torn_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("audit.log");
+        let config = AuditConfig {
+            storage: AuditStorageConfig { location: log.to_string_lossy().into_owned(), ..Default::default() },
+            hash_chain: HashChainConfig { enabled: true, anchor_every: None },
+            ..Default::default()
+        };
+        let logger = AuditLogger::new(config.clone()).unwrap();
+        for user in ["alice", "bob"] {
+            logger.log_event(AuditEvent::user_login(user.to_string(), true)).await.unwrap();
+        }
+        logger.flush().await.unwrap();
+
+        // The process died half-way through writing the next batch
+        let mut torn = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+        std::io::Write::write_all(&mut torn, br#"{"id":"half-writ"#).unwrap();
+
+        let logger = AuditLogger::new(config).unwrap();
+        logger.log_event(AuditEvent::user_login("carol".to_string(), true)).await.unwrap();
+        logger.flush().await.unwrap();
+        let events = read_log_file(&log).unwrap();
+        assert_eq!(events.iter().map(|event| event.sequence).collect::<Vec<_>>(), vec![Some(0), Some(1), Some(2)]);
+        assert!(logger.verify_chain(&[]).unwrap().is_intact());
+    }
+
     #[test]
     fn test_audit_config_serialization() {
         let config = AuditConfig::default();
@@ -0,0 +1,350 @@
+// Tamper-evident audit log chaining for AgentGraph
+// Links audit events by hash, anchors the chain head externally and verifies logs
+
+#![allow(missing_docs)]
+
+use crate::enterprise::audit::{AuditError, AuditEvent};
+use crate::sha256_hex;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// `previous_hash` of the first event of a chain
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// SHA-256 of an event with its `hash` left out, over JSON with sorted keys
+/// so the hash survives a round trip through the log file
+pub fn event_hash(event: &AuditEvent) -> String {
+    let mut unhashed = event.clone();
+    unhashed.hash = None;
+    let value = serde_json::to_value(&unhashed).expect("audit events serialize");
+    let mut canonical = String::new();
+    write_canonical(&value, &mut canonical);
+    sha256_hex(canonical)
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// The last link of a chain, which the next event is attached to
+#[derive(Debug, Clone)]
+pub(crate) struct ChainHead {
+    next_sequence: u64,
+    last_hash: String,
+}
+
+impl ChainHead {
+    /// Carry on after `last`, or start a new chain
+    pub(crate) fn resume(last: Option<&AuditEvent>) -> Self {
+        match last.and_then(|event| Some((event.sequence?, event.hash.clone()?))) {
+            Some((sequence, hash)) => Self { next_sequence: sequence + 1, last_hash: hash },
+            None => Self { next_sequence: 0, last_hash: GENESIS_HASH.to_string() },
+        }
+    }
+
+    pub(crate) fn link(&mut self, event: &mut AuditEvent) {
+        event.sequence = Some(self.next_sequence);
+        event.previous_hash = Some(self.last_hash.clone());
+        let hash = event_hash(event);
+        event.hash = Some(hash.clone());
+        self.next_sequence += 1;
+        self.last_hash = hash;
+    }
+}
+
+/// A chain head recorded outside the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorPoint {
+    pub sequence: u64,
+    pub hash: String,
+    pub anchored_at: SystemTime,
+}
+
+impl AnchorPoint {
+    /// Anchor for a chained event
+    pub fn of(event: &AuditEvent) -> Option<Self> {
+        Some(Self {
+            sequence: event.sequence?,
+            hash: event.hash.clone()?,
+            anchored_at: SystemTime::now(),
+        })
+    }
+}
+
+/// Somewhere the chain head is recorded out of reach of whoever can edit
+/// the log, so truncating or rewriting the whole log can be detected
+#[async_trait]
+pub trait AuditAnchor: Send + Sync + std::fmt::Debug {
+    async fn anchor(&self, point: &AnchorPoint) -> Result<(), AuditError>;
+}
+
+/// Appends anchors as JSON lines to a file, e.g. on write-once storage
+#[derive(Debug, Clone)]
+pub struct FileAnchor {
+    path: PathBuf,
+}
+
+impl FileAnchor {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Anchors recorded in `path`, oldest first
+    pub fn load(path: &Path) -> Result<Vec<AnchorPoint>, AuditError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| AuditError::StorageError {
+            message: format!("Failed to read anchors from {}: {}", path.display(), e),
+        })?;
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| AuditError::SerializationError {
+                    message: format!("Invalid anchor in {}: {}", path.display(), e),
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl AuditAnchor for FileAnchor {
+    async fn anchor(&self, point: &AnchorPoint) -> Result<(), AuditError> {
+        use std::io::Write;
+
+        let line = serde_json::to_string(point).map_err(|e| AuditError::SerializationError {
+            message: format!("Failed to serialize anchor: {}", e),
+        })?;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| AuditError::StorageError {
+                message: format!("Failed to write anchor to {}: {}", self.path.display(), e),
+            })
+    }
+}
+
+/// POSTs anchors as JSON to a notary or timestamping service
+#[derive(Debug, Clone)]
+pub struct HttpAnchor {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpAnchor {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), client: reqwest::Client::new() }
+    }
+}
+
+#[async_trait]
+impl AuditAnchor for HttpAnchor {
+    async fn anchor(&self, point: &AnchorPoint) -> Result<(), AuditError> {
+        self.client
+            .post(&self.url)
+            .json(point)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| AuditError::StorageError {
+                message: format!("Failed to anchor audit chain at {}: {}", self.url, e),
+            })
+    }
+}
+
+/// How a chained log was found to differ from what was logged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChainProblem {
+    /// An event without chain fields, e.g. logged before chaining was enabled
+    Unchained { index: usize, event_id: String },
+    /// An event whose content no longer matches its hash
+    Modified { index: usize, sequence: u64, event_id: String },
+    /// Events missing or inserted before this one
+    SequenceGap { index: usize, expected: u64, found: u64 },
+    /// An event not linked to the one before it
+    BrokenLink { index: usize, sequence: u64 },
+    /// An anchored event whose hash differs from the anchor's
+    AnchorMismatch { sequence: u64 },
+    /// An anchored event no longer in the log
+    AnchorMissing { sequence: u64 },
+    /// Events before the first one missing, though anchored, and the log does
+    /// not start right after an anchored event as it does when cleaned up
+    PrefixMissing { anchor_sequence: u64, first_sequence: u64 },
+}
+
+/// Outcome of checking a chained log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainVerification {
+    /// Events checked
+    pub events: u64,
+    /// Sequence of the first event; above 0 when older events were cleaned up,
+    /// which keeps the log intact only when it was cut right after an anchor
+    pub first_sequence: Option<u64>,
+    /// Hash of the last event
+    pub head_hash: Option<String>,
+    /// Anchors that fell within the log
+    pub anchors_checked: usize,
+    pub problems: Vec<ChainProblem>,
+}
+
+impl ChainVerification {
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check that `events`, in log order, form an unbroken chain holding every anchor
+pub fn verify_chain(events: &[AuditEvent], anchors: &[AnchorPoint]) -> ChainVerification {
+    let mut verification = ChainVerification { events: events.len() as u64, ..Default::default() };
+    let mut hashes: HashMap<u64, &str> = HashMap::new();
+    let mut previous: Option<(u64, &str)> = None;
+    let mut first_link: Option<&str> = None;
+
+    for (index, event) in events.iter().enumerate() {
+        let (Some(sequence), Some(previous_hash), Some(hash)) = (event.sequence, &event.previous_hash, &event.hash) else {
+            verification.problems.push(ChainProblem::Unchained { index, event_id: event.event_id.clone() });
+            continue;
+        };
+        if verification.first_sequence.is_none() {
+            verification.first_sequence = Some(sequence);
+            first_link = Some(previous_hash);
+        }
+        if event_hash(event) != *hash {
+            verification.problems.push(ChainProblem::Modified { index, sequence, event_id: event.event_id.clone() });
+        }
+        match previous {
+            Some((last, _)) if sequence != last + 1 => {
+                verification.problems.push(ChainProblem::SequenceGap { index, expected: last + 1, found: sequence });
+            }
+            Some((_, last_hash)) if previous_hash != last_hash => {
+                verification.problems.push(ChainProblem::BrokenLink { index, sequence });
+            }
+            None if sequence == 0 && previous_hash != GENESIS_HASH => {
+                verification.problems.push(ChainProblem::BrokenLink { index, sequence });
+            }
+            _ => {}
+        }
+        hashes.insert(sequence, hash);
+        previous = Some((sequence, hash));
+    }
+    verification.head_hash = previous.map(|(_, hash)| hash.to_string());
+
+    let first = verification.first_sequence.unwrap_or(0);
+    // A log cleaned up at an anchor starts with the event linked to it, which
+    // accounts for every older anchor
+    let cut_at_anchor = first > 0
+        && anchors.iter().any(|anchor| anchor.sequence == first - 1 && first_link == Some(anchor.hash.as_str()));
+    for anchor in anchors {
+        if anchor.sequence < first {
+            if !cut_at_anchor {
+                verification.problems.push(ChainProblem::PrefixMissing { anchor_sequence: anchor.sequence, first_sequence: first });
+            }
+            continue;
+        }
+        verification.anchors_checked += 1;
+        match hashes.get(&anchor.sequence) {
+            Some(hash) if *hash == anchor.hash => {}
+            Some(_) => verification.problems.push(ChainProblem::AnchorMismatch { sequence: anchor.sequence }),
+            None => verification.problems.push(ChainProblem::AnchorMissing { sequence: anchor.sequence }),
+        }
+    }
+    verification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enterprise::audit::{AuditConfig, AuditLogger, AuditStorageConfig, HashChainConfig};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_chained_log_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("audit.log");
+        let anchors = dir.path().join("anchors.jsonl");
+        let config = AuditConfig {
+            storage: AuditStorageConfig { location: log.to_string_lossy().into_owned(), batch_size: 2, ..Default::default() },
+            hash_chain: HashChainConfig { enabled: true, anchor_every: Some(2) },
+            ..Default::default()
+        };
+
+        let logger = AuditLogger::new(config.clone()).unwrap().with_anchor(Arc::new(FileAnchor::new(&anchors)));
+        for user in ["alice", "bob", "carol"] {
+            logger.log_event(AuditEvent::user_login(user.to_string(), true)).await.unwrap();
+        }
+        logger.flush().await.unwrap();
+        // A restarted logger carries the chain on
+        let logger = AuditLogger::new(config).unwrap().with_anchor(Arc::new(FileAnchor::new(&anchors)));
+        logger.log_event(AuditEvent::user_login("dave".to_string(), true)).await.unwrap();
+        logger.flush().await.unwrap();
+
+        let anchored = FileAnchor::load(&anchors).unwrap();
+        assert_eq!(anchored.iter().map(|a| a.sequence).collect::<Vec<_>>(), vec![1, 3]);
+        let verification = logger.verify_chain(&anchored).unwrap();
+        assert!(verification.is_intact(), "{:?}", verification.problems);
+        assert_eq!((verification.events, verification.anchors_checked), (4, 2));
+
+        let lines: Vec<String> = std::fs::read_to_string(&log).unwrap().lines().map(String::from).collect();
+        let edited = lines[1].replace("bob", "eve");
+        std::fs::write(&log, [lines[0].as_str(), &edited, &lines[2], &lines[3]].join("\n")).unwrap();
+        let problems = logger.verify_chain(&[]).unwrap().problems;
+        assert!(matches!(problems.as_slice(), [ChainProblem::Modified { index: 1, sequence: 1, .. }]));
+
+        // Rewriting the rest of the chain to match hides the edit from everything but the anchors
+        let mut events: Vec<AuditEvent> = lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+        events[1].user_id = Some("eve".to_string());
+        let mut head = ChainHead::resume(Some(&events[0]));
+        for event in &mut events[1..] {
+            head.link(event);
+        }
+        assert!(verify_chain(&events, &[]).is_intact());
+        let problems = verify_chain(&events, &anchored).problems;
+        assert_eq!(problems, vec![ChainProblem::AnchorMismatch { sequence: 1 }, ChainProblem::AnchorMismatch { sequence: 3 }]);
+
+        std::fs::write(&log, [lines[0].as_str(), &lines[2]].join("\n")).unwrap();
+        let problems = logger.verify_chain(&anchored).unwrap().problems;
+        assert!(problems.contains(&ChainProblem::SequenceGap { index: 1, expected: 1, found: 2 }));
+        assert!(problems.contains(&ChainProblem::AnchorMissing { sequence: 3 }));
+
+        // Cleaning up the log right after an anchor keeps it intact, cutting anywhere else does not
+        std::fs::write(&log, [lines[2].as_str(), &lines[3]].join("\n")).unwrap();
+        let verification = logger.verify_chain(&anchored).unwrap();
+        assert!(verification.is_intact(), "{:?}", verification.problems);
+        std::fs::write(&log, &lines[3]).unwrap();
+        let problems = logger.verify_chain(&anchored).unwrap().problems;
+        assert_eq!(problems, vec![ChainProblem::PrefixMissing { anchor_sequence: 1, first_sequence: 3 }]);
+    }
+}
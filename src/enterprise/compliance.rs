@@ -0,0 +1,626 @@
+// Compliance evidence for AgentGraph
+// Builds compliance reports from audit logs and writes them as signed JSON/PDF bundles
+
+#![allow(missing_docs)]
+
+use crate::enterprise::api_keys::ApiKey;
+use crate::enterprise::audit::{
+    AccessRecord, AuditConfig, AuditError, AuditEvent, AuditEventType, AuditLevel, ComplianceFinding,
+    ComplianceReport, ComplianceStandard, FindingSeverity,
+};
+use crate::visualization::auth::StudioRole;
+use crate::{hex, sha256_hex};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Name of the signed manifest in an evidence bundle
+pub const BUNDLE_MANIFEST_FILE: &str = "manifest.json";
+
+/// Failed logins by one principal in a period before they are reported
+const FAILED_LOGIN_THRESHOLD: u64 = 5;
+
+/// Shortest audit log retention a standard accepts, where it sets one
+pub fn minimum_retention_days(standard: ComplianceStandard) -> Option<u32> {
+    match standard {
+        ComplianceStandard::SOC2 | ComplianceStandard::PCIDSS | ComplianceStandard::ISO27001 => Some(365),
+        ComplianceStandard::HIPAA => Some(6 * 365),
+        ComplianceStandard::GDPR => None,
+    }
+}
+
+impl ComplianceReport {
+    /// Assess the audit events of a period against `standard`
+    pub fn from_events(
+        standard: ComplianceStandard,
+        (period_start, period_end): (SystemTime, SystemTime),
+        tenant_id: Option<&str>,
+        events: &[AuditEvent],
+        config: &AuditConfig,
+    ) -> Self {
+        let mut events_by_type = HashMap::new();
+        for event in events {
+            *events_by_type.entry(event.event_type).or_insert(0) += 1;
+        }
+        let access_records = access_records(events);
+        let findings = findings(standard, events, &access_records, config);
+
+        let mut recommendations: Vec<String> = Vec::new();
+        for step in findings.iter().flat_map(|finding| &finding.remediation) {
+            if !recommendations.contains(step) {
+                recommendations.push(step.clone());
+            }
+        }
+        let penalty: f64 = findings
+            .iter()
+            .map(|finding| match finding.severity {
+                FindingSeverity::Low => 1.0,
+                FindingSeverity::Medium => 5.0,
+                FindingSeverity::High => 15.0,
+                FindingSeverity::Critical => 25.0,
+            })
+            .sum();
+
+        Self {
+            standard,
+            period_start,
+            period_end,
+            tenant_id: tenant_id.map(String::from),
+            total_events: events.len() as u64,
+            events_by_type,
+            access_records,
+            retention: config.retention.clone(),
+            compliance_score: (100.0 - penalty).max(0.0),
+            findings,
+            recommendations,
+            generated_at: SystemTime::now(),
+        }
+    }
+}
+
+fn access_records(events: &[AuditEvent]) -> Vec<AccessRecord> {
+    let mut records: BTreeMap<String, AccessRecord> = BTreeMap::new();
+    for event in events {
+        if !matches!(event.event_type, AuditEventType::Authentication | AuditEventType::Authorization) {
+            continue;
+        }
+        let principal = event
+            .user_id
+            .clone()
+            .or_else(|| event.resource.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+        let record = records.entry(principal.clone()).or_insert_with(|| AccessRecord {
+            principal,
+            successful_logins: 0,
+            failed_logins: 0,
+            access_denied: 0,
+            first_seen: event.timestamp,
+            last_seen: event.timestamp,
+        });
+        record.first_seen = record.first_seen.min(event.timestamp);
+        record.last_seen = record.last_seen.max(event.timestamp);
+
+        match (event.event_type, event.action.as_str()) {
+            (AuditEventType::Authentication, "login") => {
+                let success = match event.data.get("success") {
+                    Some(success) => success.as_bool() == Some(true),
+                    None => event.level <= AuditLevel::Info,
+                };
+                if success {
+                    record.successful_logins += 1;
+                } else {
+                    record.failed_logins += 1;
+                }
+            }
+            (AuditEventType::Authorization, "access_denied") => record.access_denied += 1,
+            _ => {}
+        }
+    }
+    records.into_values().collect()
+}
+
+fn findings(
+    standard: ComplianceStandard,
+    events: &[AuditEvent],
+    access_records: &[AccessRecord],
+    config: &AuditConfig,
+) -> Vec<ComplianceFinding> {
+    let finding = |severity, category: &str, description: String, affected_events, remediation: &[&str]| ComplianceFinding {
+        severity,
+        category: category.to_string(),
+        description,
+        affected_events,
+        remediation: remediation.iter().map(|step| step.to_string()).collect(),
+    };
+    let mut findings = Vec::new();
+
+    if !config.enabled {
+        findings.push(finding(
+            FindingSeverity::Critical,
+            "audit_coverage",
+            "Audit logging is disabled".to_string(),
+            0,
+            &["Enable audit logging"],
+        ));
+    } else {
+        let missing: Vec<String> = [AuditEventType::Authentication, AuditEventType::Authorization]
+            .into_iter()
+            .filter(|event_type| !config.event_types.is_empty() && !config.event_types.contains(event_type))
+            .map(|event_type| format!("{:?}", event_type))
+            .collect();
+        if !missing.is_empty() {
+            findings.push(finding(
+                FindingSeverity::High,
+                "audit_coverage",
+                format!("{} events are not audited", missing.join(" and ")),
+                0,
+                &["Audit authentication and authorization events"],
+            ));
+        }
+        if events.is_empty() {
+            findings.push(finding(
+                FindingSeverity::Medium,
+                "audit_coverage",
+                "No audit events were recorded in the period".to_string(),
+                0,
+                &["Check that every service of the tenant writes to the audit log"],
+            ));
+        }
+    }
+
+    if let Some(minimum) = minimum_retention_days(standard) {
+        if config.retention.retention_days < minimum {
+            findings.push(finding(
+                FindingSeverity::High,
+                "retention",
+                format!(
+                    "Audit logs are kept for {} days; {:?} expects at least {}",
+                    config.retention.retention_days, standard, minimum
+                ),
+                0,
+                &["Raise the audit log retention period"],
+            ));
+        }
+    }
+    if config.retention.auto_cleanup && !config.retention.archive_enabled {
+        findings.push(finding(
+            FindingSeverity::Medium,
+            "retention",
+            format!("Audit logs are deleted after {} days without being archived", config.retention.retention_days),
+            0,
+            &["Archive audit logs before cleanup"],
+        ));
+    }
+    if matches!(standard, ComplianceStandard::HIPAA | ComplianceStandard::PCIDSS) && !config.storage.encryption_enabled {
+        findings.push(finding(
+            FindingSeverity::High,
+            "encryption",
+            format!("Audit logs are stored unencrypted, which {:?} does not allow", standard),
+            0,
+            &["Enable audit log encryption"],
+        ));
+    }
+
+    let repeated_failures: Vec<&AccessRecord> = access_records
+        .iter()
+        .filter(|record| record.failed_logins >= FAILED_LOGIN_THRESHOLD)
+        .collect();
+    if !repeated_failures.is_empty() {
+        let principals: Vec<&str> = repeated_failures.iter().map(|record| record.principal.as_str()).collect();
+        findings.push(finding(
+            FindingSeverity::Medium,
+            "authentication",
+            format!("Repeated failed logins by {}", principals.join(", ")),
+            repeated_failures.iter().map(|record| record.failed_logins).sum(),
+            &["Investigate repeated failed logins", "Lock out accounts after repeated failures"],
+        ));
+    }
+    let denied: u64 = access_records.iter().map(|record| record.access_denied).sum();
+    if denied > 0 {
+        findings.push(finding(
+            FindingSeverity::Low,
+            "authorization",
+            format!("{} request(s) were refused for lack of permission", denied),
+            denied,
+            &["Review refused requests for misconfigured roles or probing"],
+        ));
+    }
+    let critical = events.iter().filter(|event| event.level == AuditLevel::Critical).count() as u64;
+    if critical > 0 {
+        findings.push(finding(
+            FindingSeverity::High,
+            "security",
+            format!("{} critical security event(s) were recorded", critical),
+            critical,
+            &["Confirm every critical security event was triaged"],
+        ));
+    }
+    findings
+}
+
+/// An API key as listed in evidence, without its secret hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyAccess {
+    pub id: String,
+    pub name: String,
+    pub tenant: Option<String>,
+    pub role: StudioRole,
+    pub rate_limit: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<&ApiKey> for ApiKeyAccess {
+    fn from(key: &ApiKey) -> Self {
+        Self {
+            id: key.id.clone(),
+            name: key.name.clone(),
+            tenant: key.tenant.clone(),
+            role: key.role,
+            rate_limit: key.rate_limit,
+            created_at: key.created_at,
+            expires_at: key.expires_at,
+            rotated_at: key.rotated_at,
+            revoked_at: key.revoked_at,
+            last_used_at: key.last_used_at,
+        }
+    }
+}
+
+/// What an evidence bundle attests to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceEvidence {
+    pub report: ComplianceReport,
+    /// API keys that could reach the report's tenant
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyAccess>,
+}
+
+impl ComplianceEvidence {
+    pub fn new(report: ComplianceReport) -> Self {
+        Self { report, api_keys: Vec::new() }
+    }
+
+    /// List the keys able to reach the report's tenant, unscoped ones included
+    pub fn with_api_keys<'a>(mut self, keys: impl IntoIterator<Item = &'a ApiKey>) -> Self {
+        let tenant = self.report.tenant_id.as_deref();
+        self.api_keys = keys
+            .into_iter()
+            .filter(|key| tenant.is_none() || key.tenant.is_none() || key.tenant.as_deref() == tenant)
+            .map(ApiKeyAccess::from)
+            .collect();
+        self
+    }
+
+    /// The evidence as a printable PDF document
+    pub fn to_pdf(&self) -> Vec<u8> {
+        render_pdf(&self.summary_lines())
+    }
+
+    fn summary_lines(&self) -> Vec<String> {
+        let report = &self.report;
+        let mut lines = vec![
+            format!("{:?} compliance report", report.standard),
+            String::new(),
+            format!("Tenant: {}", report.tenant_id.as_deref().unwrap_or("all tenants")),
+            format!("Period: {} to {}", format_time(report.period_start), format_time(report.period_end)),
+            format!("Generated: {}", format_time(report.generated_at)),
+            format!("Compliance score: {:.1}", report.compliance_score),
+            format!("Audit events: {}", report.total_events),
+        ];
+        let mut by_type: Vec<String> = report
+            .events_by_type
+            .iter()
+            .map(|(event_type, count)| format!("  {:?}: {}", event_type, count))
+            .collect();
+        by_type.sort();
+        lines.extend(by_type);
+
+        lines.push(String::new());
+        lines.push("Retention".to_string());
+        lines.push(format!(
+            "  Kept for {} days, archived: {}, cleaned up automatically: {}",
+            report.retention.retention_days, report.retention.archive_enabled, report.retention.auto_cleanup
+        ));
+
+        lines.push(String::new());
+        lines.push(format!("Findings ({})", report.findings.len()));
+        for finding in &report.findings {
+            lines.push(format!("  [{:?}] {}: {}", finding.severity, finding.category, finding.description));
+        }
+        if !report.recommendations.is_empty() {
+            lines.push(String::new());
+            lines.push("Recommendations".to_string());
+            lines.extend(report.recommendations.iter().map(|step| format!("  - {}", step)));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("Access records ({})", report.access_records.len()));
+        for record in &report.access_records {
+            lines.push(format!(
+                "  {}: {} login(s), {} failed, {} denied, last seen {}",
+                record.principal,
+                record.successful_logins,
+                record.failed_logins,
+                record.access_denied,
+                format_time(record.last_seen)
+            ));
+        }
+        if !self.api_keys.is_empty() {
+            lines.push(String::new());
+            lines.push(format!("API keys ({})", self.api_keys.len()));
+            for key in &self.api_keys {
+                let status = match (key.revoked_at, key.expires_at) {
+                    (Some(revoked_at), _) => format!("revoked {}", revoked_at.format("%Y-%m-%d")),
+                    (None, Some(expires_at)) => format!("expires {}", expires_at.format("%Y-%m-%d")),
+                    (None, None) => "active".to_string(),
+                };
+                lines.push(format!(
+                    "  {} ({}): {:?}, tenant {}, {}, last used {}",
+                    key.name,
+                    key.id,
+                    key.role,
+                    key.tenant.as_deref().unwrap_or("all"),
+                    status,
+                    key.last_used_at.map_or_else(|| "never".to_string(), |used| used.format("%Y-%m-%d").to_string())
+                ));
+            }
+        }
+        lines
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+/// Lines of text laid out on US Letter pages in Helvetica
+fn render_pdf(lines: &[String]) -> Vec<u8> {
+    const LINES_PER_PAGE: usize = 50;
+    const WRAP_AT: usize = 100;
+
+    let mut wrapped = Vec::new();
+    for line in lines {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            wrapped.push(String::new());
+        }
+        for chunk in chars.chunks(WRAP_AT) {
+            wrapped.push(chunk.iter().collect::<String>());
+        }
+    }
+    let pages: Vec<&[String]> = if wrapped.is_empty() { vec![&[]] } else { wrapped.chunks(LINES_PER_PAGE).collect() };
+
+    // Objects 1-3 are the catalog, page tree and font; each page adds itself and its content stream
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len()).map(|page| format!("{} 0 R", 4 + 2 * page)).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    for (page, page_lines) in pages.iter().enumerate() {
+        let mut content = String::from("BT /F1 10 Tf 14 TL 50 742 Td\n");
+        for line in page_lines.iter() {
+            content.push_str(&format!("({}) Tj T*\n", pdf_escape(line)));
+        }
+        content.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            5 + 2 * page
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", index + 1, object).as_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes(),
+    );
+    pdf
+}
+
+/// Text safe inside a PDF string; the standard fonts only cover ASCII here
+fn pdf_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+/// Formats an evidence bundle can hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvidenceFormat {
+    Json,
+    Pdf,
+}
+
+impl EvidenceFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            EvidenceFormat::Json => "report.json",
+            EvidenceFormat::Pdf => "report.pdf",
+        }
+    }
+}
+
+/// Signed list of the files of an evidence bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub standard: ComplianceStandard,
+    pub tenant_id: Option<String>,
+    pub period_start: SystemTime,
+    pub period_end: SystemTime,
+    pub generated_at: SystemTime,
+    /// SHA-256 of each file, by file name
+    pub files: BTreeMap<String, String>,
+    pub algorithm: String,
+    /// HMAC-SHA256 of the manifest with this field empty, hex encoded
+    pub signature: String,
+}
+
+impl BundleManifest {
+    fn signature(&self, key: &[u8]) -> Hmac<Sha256> {
+        let unsigned = BundleManifest { signature: String::new(), ..self.clone() };
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(&serde_json::to_vec(&unsigned).expect("manifests serialize"));
+        mac
+    }
+}
+
+/// Write `evidence` to `dir` in each of `formats`, with a manifest signed by `signing_key`
+pub fn write_bundle(
+    evidence: &ComplianceEvidence,
+    dir: &Path,
+    formats: &[EvidenceFormat],
+    signing_key: &[u8],
+) -> Result<BundleManifest, AuditError> {
+    let storage_error = |e: &dyn std::fmt::Display| AuditError::StorageError {
+        message: format!("Failed to write evidence bundle {}: {}", dir.display(), e),
+    };
+    std::fs::create_dir_all(dir).map_err(|e| storage_error(&e))?;
+
+    let mut files = BTreeMap::new();
+    for format in formats {
+        let contents = match format {
+            EvidenceFormat::Json => serde_json::to_vec_pretty(evidence).map_err(|e| AuditError::SerializationError {
+                message: format!("Failed to serialize compliance evidence: {}", e),
+            })?,
+            EvidenceFormat::Pdf => evidence.to_pdf(),
+        };
+        std::fs::write(dir.join(format.file_name()), &contents).map_err(|e| storage_error(&e))?;
+        files.insert(format.file_name().to_string(), sha256_hex(&contents));
+    }
+
+    let report = &evidence.report;
+    let mut manifest = BundleManifest {
+        standard: report.standard,
+        tenant_id: report.tenant_id.clone(),
+        period_start: report.period_start,
+        period_end: report.period_end,
+        generated_at: report.generated_at,
+        files,
+        algorithm: "HMAC-SHA256".to_string(),
+        signature: String::new(),
+    };
+    manifest.signature = hex(&manifest.signature(signing_key).finalize().into_bytes());
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| AuditError::SerializationError {
+        message: format!("Failed to serialize bundle manifest: {}", e),
+    })?;
+    std::fs::write(dir.join(BUNDLE_MANIFEST_FILE), json).map_err(|e| storage_error(&e))?;
+    Ok(manifest)
+}
+
+/// Check that the bundle in `dir` was signed with `signing_key` and that
+/// none of its files changed since
+pub fn verify_bundle(dir: &Path, signing_key: &[u8]) -> Result<BundleManifest, AuditError> {
+    let invalid = |message: String| AuditError::ComplianceError { message };
+    let manifest_path = dir.join(BUNDLE_MANIFEST_FILE);
+    let json = std::fs::read_to_string(&manifest_path).map_err(|e| AuditError::StorageError {
+        message: format!("Failed to read {}: {}", manifest_path.display(), e),
+    })?;
+    let manifest: BundleManifest = serde_json::from_str(&json).map_err(|e| AuditError::SerializationError {
+        message: format!("Invalid bundle manifest: {}", e),
+    })?;
+
+    let signature = unhex(&manifest.signature).ok_or_else(|| invalid("Bundle signature is not hex".to_string()))?;
+    manifest
+        .signature(signing_key)
+        .verify_slice(&signature)
+        .map_err(|_| invalid("Bundle signature does not match".to_string()))?;
+
+    for (name, expected) in &manifest.files {
+        if name.contains(['/', '\\']) || name == ".." {
+            return Err(invalid(format!("Bundle lists a file outside it: {}", name)));
+        }
+        let contents = std::fs::read(dir.join(name)).map_err(|e| invalid(format!("Bundle file {} unreadable: {}", name, e)))?;
+        if sha256_hex(&contents) != *expected {
+            return Err(invalid(format!("Bundle file {} was modified", name)));
+        }
+    }
+    Ok(manifest)
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enterprise::api_keys::{ApiKeyManager, ApiKeySpec};
+    use crate::enterprise::audit::AuditLogger;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_signed_tenant_evidence_bundle() {
+        let logger = AuditLogger::in_memory();
+        let start = SystemTime::now() - Duration::from_secs(60);
+        logger.log_event(AuditEvent::user_login("alice".to_string(), true).with_tenant("acme".to_string())).await.unwrap();
+        for _ in 0..FAILED_LOGIN_THRESHOLD {
+            logger.log_event(AuditEvent::user_login("mallory".to_string(), false).with_tenant("acme".to_string())).await.unwrap();
+        }
+        logger
+            .log_event(AuditEvent::permission_denied("alice".to_string(), "billing".to_string(), "write".to_string()).with_tenant("acme".to_string()))
+            .await
+            .unwrap();
+        logger.log_event(AuditEvent::user_login("bob".to_string(), true).with_tenant("globex".to_string())).await.unwrap();
+
+        let report = logger
+            .generate_tenant_compliance_report(ComplianceStandard::HIPAA, start, SystemTime::now(), Some("acme"))
+            .await
+            .unwrap();
+        assert_eq!(report.total_events, 7);
+        let principals: Vec<(&str, u64, u64, u64)> = report
+            .access_records
+            .iter()
+            .map(|r| (r.principal.as_str(), r.successful_logins, r.failed_logins, r.access_denied))
+            .collect();
+        assert_eq!(principals, vec![("alice", 1, 0, 1), ("mallory", 0, 5, 0)]);
+        let categories: Vec<&str> = report.findings.iter().map(|f| f.category.as_str()).collect();
+        assert_eq!(categories, vec!["retention", "encryption", "authentication", "authorization"]);
+        assert!(report.compliance_score < 100.0);
+
+        let keys = ApiKeyManager::new();
+        keys.issue(ApiKeySpec::new("acme-ci", "acme", StudioRole::Operator)).await;
+        keys.issue(ApiKeySpec::new("globex-ci", "globex", StudioRole::Operator)).await;
+        let evidence = ComplianceEvidence::new(report).with_api_keys(&keys.list(None));
+        assert_eq!(evidence.api_keys.len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = write_bundle(&evidence, dir.path(), &[EvidenceFormat::Json, EvidenceFormat::Pdf], b"secret").unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert!(std::fs::read(dir.path().join("report.pdf")).unwrap().starts_with(b"%PDF-1.4"));
+        assert!(!std::fs::read_to_string(dir.path().join("report.json")).unwrap().contains("secret_hash"));
+
+        verify_bundle(dir.path(), b"secret").unwrap();
+        assert!(verify_bundle(dir.path(), b"other key").is_err());
+        std::fs::write(dir.path().join("report.json"), "{}").unwrap();
+        assert!(verify_bundle(dir.path(), b"secret").is_err());
+    }
+}
@@ -11,21 +11,45 @@ pub mod resources;
 pub mod security;
 /// Audit logging and compliance
 pub mod audit;
+/// Signed compliance evidence bundles built from audit logs
+pub mod compliance;
+/// Hash-chained, externally anchored audit logs
+pub mod audit_chain;
 /// Monitoring and observability
 pub mod monitoring;
+/// Per-tenant secrets and tool credential injection
+pub mod secrets;
+/// Service level objectives and error budgets of graphs
+pub mod slo;
+/// Prompt-injection screening of agent inputs
+pub mod guardrails;
+/// Moderation of agent outputs
+pub mod moderation;
+/// Tenant- and role-scoped API keys for programmatic access
+pub mod api_keys;
+/// Sandbox profiles limiting the tools, hosts, models and spend of executions
+pub mod sandbox;
 
-pub use tenancy::{Tenant, TenantManager, TenantConfig, TenantContext, TenantError};
+pub use tenancy::{Tenant, TenantManager, TenantConfig, TenantContext, TenantError, TenantSpec};
 pub use resources::{ResourceManager, ResourceQuota, ResourceUsage, ResourceLimits};
-pub use security::{SecurityManager, Role, Permission, AuthContext, SecurityError};
+pub use security::{SecurityManager, Role, Permission, AuthContext, SecurityError, RedactionPolicy, PiiScrubber};
 pub use audit::{AuditLogger, AuditEvent, AuditLevel, ComplianceReport};
+pub use audit_chain::{AuditAnchor, AnchorPoint, FileAnchor, HttpAnchor, ChainVerification, ChainProblem};
+pub use compliance::{ComplianceEvidence, EvidenceFormat, BundleManifest};
 pub use monitoring::{MetricsCollector, PerformanceMetrics, HealthCheck, AlertManager};
+pub use slo::{Slo, SloObjective, SloReport, SloTracker, RunSample, BurnRateAlert};
+pub use guardrails::{PromptGuard, GuardAction, InputOrigin, InjectionScan, GuardrailError};
+pub use moderation::{Moderator, ModerationPolicy, ModerationAction, ModerationClassifier, KeywordClassifier, OpenAIModeration, ModerationError, ContentModerated};
+pub use api_keys::{ApiKeyManager, ApiKey, ApiKeySpec, IssuedApiKey, ApiKeyError};
+pub use sandbox::{SandboxRegistry, SandboxProfile, EgressRules, ExecutionSandbox, SandboxViolation, ViolationKind, SandboxError};
+pub use secrets::{CredentialVault, Credential, CredentialBinding, SecretStore, InMemorySecretStore, EncryptedSecretStore, SecretsError};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{Duration, SystemTime};
 
 /// Enterprise configuration for AgentGraph
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct EnterpriseConfig {
     /// Multi-tenancy configuration
     pub tenancy: tenancy::TenancyConfig,
@@ -55,7 +79,7 @@ impl Default for EnterpriseConfig {
 }
 
 /// Feature flags for enterprise capabilities
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct FeatureFlags {
     /// Enable multi-tenancy
     pub multi_tenancy: bool,
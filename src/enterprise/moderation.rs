@@ -0,0 +1,525 @@
+// Output moderation for AgentGraph
+// Classifies agent outputs and blocks, redacts or warns on them according to each tenant's policy
+
+#![allow(missing_docs)]
+
+use crate::enterprise::audit::{AuditEvent, AuditLevel, AuditLogger};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+/// What a classifier found in a text
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModerationVerdict {
+    /// Classifier that produced the verdict
+    pub classifier: String,
+    /// Score per category, 0.0 to 1.0
+    pub scores: BTreeMap<String, f32>,
+    /// Byte ranges of offending passages, when the classifier can locate them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<(usize, usize)>,
+}
+
+impl ModerationVerdict {
+    /// Categories scoring at least `threshold`
+    pub fn flagged(&self, threshold: f32) -> Vec<&str> {
+        self.scores
+            .iter()
+            .filter(|(_, score)| **score >= threshold)
+            .map(|(category, _)| category.as_str())
+            .collect()
+    }
+}
+
+/// Scores texts by moderation category
+#[async_trait]
+pub trait ModerationClassifier: Send + Sync + std::fmt::Debug {
+    /// Name recorded in verdicts and audit events
+    fn name(&self) -> &str;
+
+    /// Score `text`
+    async fn classify(&self, text: &str) -> Result<ModerationVerdict, ModerationError>;
+}
+
+/// Classifier backed by the OpenAI moderation endpoint
+#[derive(Debug, Clone)]
+pub struct OpenAIModeration {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+impl OpenAIModeration {
+    /// Classify with `omni-moderation-latest`
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            api_key: api_key.into(),
+            base_url: "https://api.openai.com/v1".to_string(),
+            model: "omni-moderation-latest".to_string(),
+        }
+    }
+
+    /// Classifier using `OPENAI_API_KEY`, if it is set
+    pub fn from_env() -> Option<Self> {
+        std::env::var("OPENAI_API_KEY").ok().filter(|key| !key.is_empty()).map(Self::new)
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+}
+
+#[async_trait]
+impl ModerationClassifier for OpenAIModeration {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    async fn classify(&self, text: &str) -> Result<ModerationVerdict, ModerationError> {
+        let response = self
+            .client
+            .post(format!("{}/moderations", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({ "model": self.model, "input": text }))
+            .send()
+            .await
+            .map_err(|e| ModerationError::ClassifierFailed {
+                classifier: self.name().to_string(),
+                message: e.to_string(),
+            })?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.map_err(|e| ModerationError::ClassifierFailed {
+            classifier: self.name().to_string(),
+            message: format!("Invalid response: {}", e),
+        })?;
+        if !status.is_success() {
+            return Err(ModerationError::ClassifierFailed {
+                classifier: self.name().to_string(),
+                message: format!("HTTP {}: {}", status, body["error"]["message"].as_str().unwrap_or("unknown error")),
+            });
+        }
+        Ok(parse_openai_result(&body["results"][0]))
+    }
+}
+
+/// Scores from one entry of a moderation response's `results`
+///
+/// Categories the endpoint flagged score at least 1.0, so they are acted on
+/// whatever the policy's threshold.
+fn parse_openai_result(result: &serde_json::Value) -> ModerationVerdict {
+    let mut scores = BTreeMap::new();
+    if let Some(category_scores) = result["category_scores"].as_object() {
+        for (category, score) in category_scores {
+            scores.insert(normalize_category(category), score.as_f64().unwrap_or(0.0) as f32);
+        }
+    }
+    if let Some(categories) = result["categories"].as_object() {
+        for (category, flagged) in categories {
+            if flagged.as_bool() == Some(true) {
+                scores.insert(normalize_category(category), 1.0);
+            }
+        }
+    }
+    ModerationVerdict { classifier: "openai".to_string(), scores, spans: Vec::new() }
+}
+
+/// `self-harm/intent` becomes `self_harm/intent`
+fn normalize_category(category: &str) -> String {
+    category.replace('-', "_")
+}
+
+/// Local classifier matching patterns per category
+///
+/// Works offline, and is the fallback when a provider classifier fails. A
+/// match scores its category 1.0 and its passage can be redacted.
+#[derive(Debug, Clone, Default)]
+pub struct KeywordClassifier {
+    rules: Vec<(String, regex::Regex)>,
+}
+
+impl KeywordClassifier {
+    /// A classifier with no patterns
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Score `category` when `pattern` matches, ignoring case
+    pub fn with_pattern(mut self, category: impl Into<String>, pattern: &str) -> Result<Self, ModerationError> {
+        let regex = regex::RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| ModerationError::InvalidPattern {
+                pattern: pattern.to_string(),
+                message: e.to_string(),
+            })?;
+        self.rules.push((category.into(), regex));
+        Ok(self)
+    }
+
+    /// Score `category` when any of `terms` appears as a whole word
+    pub fn with_terms(self, category: impl Into<String>, terms: &[&str]) -> Result<Self, ModerationError> {
+        let alternatives: Vec<String> = terms.iter().map(|term| regex::escape(term)).collect();
+        self.with_pattern(category, &format!(r"\b(?:{})\b", alternatives.join("|")))
+    }
+
+    fn verdict(&self, text: &str) -> ModerationVerdict {
+        let mut verdict = ModerationVerdict { classifier: "keyword".to_string(), ..Default::default() };
+        for (category, regex) in &self.rules {
+            for found in regex.find_iter(text) {
+                verdict.scores.insert(category.clone(), 1.0);
+                verdict.spans.push((found.start(), found.end()));
+            }
+        }
+        verdict
+    }
+}
+
+#[async_trait]
+impl ModerationClassifier for KeywordClassifier {
+    fn name(&self) -> &str {
+        "keyword"
+    }
+
+    async fn classify(&self, text: &str) -> Result<ModerationVerdict, ModerationError> {
+        Ok(self.verdict(text))
+    }
+}
+
+/// A flagged output, emitted as a `content_moderated` event from the running node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentModerated {
+    pub node_id: String,
+    pub action: ModerationAction,
+    pub categories: Vec<String>,
+    pub classifier: String,
+    pub tenant_id: Option<String>,
+}
+
+#[cfg(feature = "streaming")]
+impl crate::streaming::CustomEvent for ContentModerated {
+    const EVENT_TYPE: &'static str = "content_moderated";
+}
+
+/// What happens to an output in a flagged category, from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Pass it on, recording the flag
+    Warn,
+    /// Replace the offending passages, or the whole output if they cannot be located
+    Redact,
+    /// Refuse it
+    Block,
+}
+
+/// How one tenant's outputs are moderated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModerationPolicy {
+    /// Scores at or above this flag their category
+    pub threshold: f32,
+    /// Action for flagged categories not listed in `actions`
+    pub default_action: ModerationAction,
+    /// Action per category
+    pub actions: HashMap<String, ModerationAction>,
+    /// Categories never acted on
+    pub allowed: Vec<String>,
+    /// Text redacted passages are replaced with
+    pub replacement: String,
+}
+
+impl Default for ModerationPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            default_action: ModerationAction::Block,
+            actions: HashMap::new(),
+            allowed: Vec::new(),
+            replacement: "[removed]".to_string(),
+        }
+    }
+}
+
+impl ModerationPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_default_action(mut self, action: ModerationAction) -> Self {
+        self.default_action = action;
+        self
+    }
+
+    /// Take `action` on outputs flagged for `category`
+    ///
+    /// A category also covers its subcategories, e.g. `violence` covers
+    /// `violence/graphic` unless that has an action of its own.
+    pub fn with_action(mut self, category: impl Into<String>, action: ModerationAction) -> Self {
+        self.actions.insert(category.into(), action);
+        self
+    }
+
+    /// Never act on `category` or its subcategories
+    pub fn allow(mut self, category: impl Into<String>) -> Self {
+        self.allowed.push(category.into());
+        self
+    }
+
+    /// Action for a flagged category, if it is acted on
+    pub fn action_for(&self, category: &str) -> Option<ModerationAction> {
+        let parent = category.split('/').next().unwrap_or(category);
+        if self.allowed.iter().any(|allowed| allowed == category || allowed == parent) {
+            return None;
+        }
+        Some(
+            self.actions
+                .get(category)
+                .or_else(|| self.actions.get(parent))
+                .copied()
+                .unwrap_or(self.default_action),
+        )
+    }
+}
+
+/// An output after moderation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModeratedOutput {
+    /// Text to pass on
+    pub text: String,
+    pub verdict: ModerationVerdict,
+    /// Categories the policy acted on
+    pub flagged: Vec<String>,
+    /// Most severe action taken, if any
+    pub action: Option<ModerationAction>,
+}
+
+/// Moderates agent outputs with a provider classifier, falling back to keywords
+///
+/// The policy applied is the tenant's, if one was set, else the default.
+#[derive(Debug, Clone, Default)]
+pub struct Moderator {
+    classifier: Option<Arc<dyn ModerationClassifier>>,
+    fallback: KeywordClassifier,
+    policy: ModerationPolicy,
+    tenant_policies: HashMap<String, ModerationPolicy>,
+    audit_logger: Option<Arc<AuditLogger>>,
+}
+
+impl Moderator {
+    /// A moderator classifying with `fallback` only
+    pub fn new(fallback: KeywordClassifier) -> Self {
+        Self { fallback, ..Self::default() }
+    }
+
+    /// Classify with `classifier` first, using the keyword fallback when it fails
+    pub fn with_classifier<C: ModerationClassifier + 'static>(mut self, classifier: C) -> Self {
+        self.classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// Policy for tenants without one of their own
+    pub fn with_policy(mut self, policy: ModerationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_tenant_policy(mut self, tenant_id: impl Into<String>, policy: ModerationPolicy) -> Self {
+        self.tenant_policies.insert(tenant_id.into(), policy);
+        self
+    }
+
+    /// Record flagged outputs in an audit log
+    pub fn with_audit_logger(mut self, audit_logger: Arc<AuditLogger>) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Policy applied to `tenant_id`'s outputs
+    pub fn policy_for(&self, tenant_id: Option<&str>) -> &ModerationPolicy {
+        tenant_id
+            .and_then(|tenant_id| self.tenant_policies.get(tenant_id))
+            .unwrap_or(&self.policy)
+    }
+
+    /// Classify `text`, using the fallback if the provider classifier fails
+    pub async fn classify(&self, text: &str) -> ModerationVerdict {
+        if let Some(classifier) = &self.classifier {
+            match classifier.classify(text).await {
+                Ok(verdict) => return verdict,
+                Err(e) => tracing::warn!(classifier = classifier.name(), error = %e, "Moderation classifier failed, using keyword fallback"),
+            }
+        }
+        self.fallback.verdict(text)
+    }
+
+    /// Moderate `text` from `source`, e.g. a node ID, under `tenant_id`'s policy
+    ///
+    /// Returns [`ModerationError::Blocked`] when a flagged category's action is
+    /// to block.
+    pub async fn moderate(&self, text: &str, source: &str, tenant_id: Option<&str>) -> Result<ModeratedOutput, ModerationError> {
+        let verdict = self.classify(text).await;
+        let policy = self.policy_for(tenant_id);
+        let mut flagged = Vec::new();
+        let mut action = None;
+        for category in verdict.flagged(policy.threshold) {
+            if let Some(category_action) = policy.action_for(category) {
+                flagged.push(category.to_string());
+                action = action.max(Some(category_action));
+            }
+        }
+        let Some(action) = action else {
+            return Ok(ModeratedOutput { text: text.to_string(), verdict, flagged, action: None });
+        };
+
+        tracing::warn!(source, tenant_id, ?action, categories = ?flagged, classifier = %verdict.classifier, "Output flagged by moderation");
+        self.record(source, tenant_id, action, &flagged, &verdict).await;
+
+        match action {
+            ModerationAction::Warn => Ok(ModeratedOutput { text: text.to_string(), verdict, flagged, action: Some(action) }),
+            ModerationAction::Redact => Ok(ModeratedOutput {
+                text: redact(text, &verdict.spans, &policy.replacement),
+                verdict,
+                flagged,
+                action: Some(action),
+            }),
+            ModerationAction::Block => Err(ModerationError::Blocked {
+                from: source.to_string(),
+                categories: flagged.join(", "),
+            }),
+        }
+    }
+
+    /// Audit the flag and emit it as a [`ContentModerated`] event from the running node
+    async fn record(&self, source: &str, tenant_id: Option<&str>, action: ModerationAction, flagged: &[String], verdict: &ModerationVerdict) {
+        #[cfg(feature = "streaming")]
+        crate::streaming::emit_node_custom(|node_id| ContentModerated {
+            node_id,
+            action,
+            categories: flagged.to_vec(),
+            classifier: verdict.classifier.clone(),
+            tenant_id: tenant_id.map(str::to_string),
+        });
+
+        let Some(logger) = &self.audit_logger else {
+            return;
+        };
+        let mut event = AuditEvent::security_event(
+            "content_moderated".to_string(),
+            format!("Output of {} flagged for {}", source, flagged.join(", ")),
+        )
+        .with_level(if action == ModerationAction::Block { AuditLevel::Critical } else { AuditLevel::Warning })
+        .with_resource(source.to_string())
+        .with_data("action".to_string(), action)
+        .with_data("categories".to_string(), flagged)
+        .with_data("scores".to_string(), &verdict.scores)
+        .with_data("classifier".to_string(), &verdict.classifier);
+        if let Some(tenant_id) = tenant_id {
+            event = event.with_tenant(tenant_id.to_string());
+        }
+        if let Err(e) = logger.log_event(event).await {
+            tracing::warn!("Failed to record moderation audit event: {}", e);
+        }
+    }
+}
+
+/// `text` with `spans` replaced, or entirely replaced when there are none
+fn redact(text: &str, spans: &[(usize, usize)], replacement: &str) -> String {
+    if spans.is_empty() {
+        return replacement.to_string();
+    }
+    let mut spans = spans.to_vec();
+    spans.sort_unstable();
+    let mut redacted = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end) in spans {
+        if start >= copied {
+            redacted.push_str(&text[copied..start]);
+            redacted.push_str(replacement);
+        }
+        copied = copied.max(end);
+    }
+    redacted.push_str(&text[copied..]);
+    redacted
+}
+
+/// Moderation errors
+#[derive(Debug, Error)]
+pub enum ModerationError {
+    #[error("Output of {from} blocked by moderation: {categories}")]
+    Blocked { from: String, categories: String },
+
+    #[error("Moderation classifier {classifier} failed: {message}")]
+    ClassifierFailed { classifier: String, message: String },
+
+    #[error("Invalid moderation pattern '{pattern}': {message}")]
+    InvalidPattern { pattern: String, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Unreachable;
+
+    #[async_trait]
+    impl ModerationClassifier for Unreachable {
+        fn name(&self) -> &str {
+            "unreachable"
+        }
+
+        async fn classify(&self, _text: &str) -> Result<ModerationVerdict, ModerationError> {
+            Err(ModerationError::ClassifierFailed { classifier: "unreachable".to_string(), message: "timeout".to_string() })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_moderation_policies_per_tenant() {
+        let keywords = KeywordClassifier::new()
+            .with_terms("profanity", &["darn", "heck"]).unwrap()
+            .with_terms("violence/threat", &["hurt you"]).unwrap();
+        let moderator = Moderator::new(keywords)
+            .with_classifier(Unreachable)
+            .with_policy(ModerationPolicy::new().with_action("profanity", ModerationAction::Redact))
+            .with_tenant_policy("kids", ModerationPolicy::new())
+            .with_tenant_policy("games", ModerationPolicy::new().with_action("violence", ModerationAction::Warn).allow("profanity"));
+
+        let clean = moderator.moderate("Happy to help!", "reply", None).await.unwrap();
+        assert_eq!(clean.action, None);
+
+        let redacted = moderator.moderate("Well, darn it.", "reply", None).await.unwrap();
+        assert_eq!(redacted.text, "Well, [removed] it.");
+        assert_eq!(redacted.verdict.classifier, "keyword");
+        assert!(matches!(moderator.moderate("Well, darn it.", "reply", Some("kids")).await, Err(ModerationError::Blocked { .. })));
+
+        let warned = moderator.moderate("Heck, the boss will hurt you.", "reply", Some("games")).await.unwrap();
+        assert_eq!((warned.action, warned.flagged), (Some(ModerationAction::Warn), vec!["violence/threat".to_string()]));
+        assert!(matches!(moderator.moderate("It will hurt you.", "reply", None).await, Err(ModerationError::Blocked { .. })));
+
+        let verdict = parse_openai_result(&serde_json::json!({
+            "flagged": true,
+            "categories": { "self-harm/intent": true, "hate": false },
+            "category_scores": { "self-harm/intent": 0.91, "hate": 0.02 },
+        }));
+        assert_eq!(verdict.flagged(0.5), vec!["self_harm/intent"]);
+    }
+}
@@ -3,6 +3,7 @@
 
 #![allow(missing_docs)]
 
+use super::slo::{RunSample, Slo, SloReport, SloTracker};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
@@ -145,7 +146,7 @@ impl HealthCheck {
 }
 
 /// Alert severity levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum AlertSeverity {
     /// Informational alert
     Info,
@@ -272,7 +273,7 @@ impl ComparisonOperator {
 }
 
 /// Monitoring configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct MonitoringConfig {
     /// Enable monitoring
     pub enabled: bool,
@@ -290,6 +291,9 @@ pub struct MonitoringConfig {
     pub detailed_metrics: bool,
     /// Custom health check endpoints
     pub health_check_endpoints: Vec<String>,
+    /// Service level objectives of graphs
+    #[serde(default)]
+    pub slos: Vec<Slo>,
 }
 
 impl Default for MonitoringConfig {
@@ -303,10 +307,21 @@ impl Default for MonitoringConfig {
             metrics_retention: Duration::from_secs(86400 * 7), // 7 days
             detailed_metrics: false,
             health_check_endpoints: Vec::new(),
+            slos: Vec::new(),
         }
     }
 }
 
+/// Refuse an SLO whose target is not a fraction or whose window is empty
+fn validate_slo(slo: &Slo) -> Result<(), MonitoringError> {
+    if !(0.0..=1.0).contains(&slo.target) || slo.window.is_zero() {
+        return Err(MonitoringError::ConfigurationError {
+            message: format!("SLO {} needs a target between 0 and 1 and a window", slo.name),
+        });
+    }
+    Ok(())
+}
+
 /// Metrics collector for system monitoring
 #[derive(Debug)]
 pub struct MetricsCollector {
@@ -322,12 +337,16 @@ pub struct MetricsCollector {
     alert_rules: Arc<RwLock<Vec<AlertRule>>>,
     /// Active alerts
     active_alerts: Arc<RwLock<HashMap<String, Alert>>>,
+    /// Graph runs counted towards SLOs
+    slos: Arc<RwLock<SloTracker>>,
 }
 
 impl MetricsCollector {
     /// Create a new metrics collector
     pub fn new(config: MonitoringConfig) -> Result<Self, MonitoringError> {
+        config.slos.iter().try_for_each(validate_slo)?;
         Ok(Self {
+            slos: Arc::new(RwLock::new(SloTracker::new(config.slos.clone()))),
             config,
             metrics_history: Arc::new(RwLock::new(Vec::new())),
             current_metrics: Arc::new(RwLock::new(PerformanceMetrics::default())),
@@ -453,6 +472,8 @@ impl MetricsCollector {
             }
         }
         
+        new_alerts.extend(self.slos.write().unwrap().evaluate(SystemTime::now()));
+
         // Update active alerts
         {
             let mut active = self.active_alerts.write().unwrap();
@@ -470,6 +491,28 @@ impl MetricsCollector {
         rules.push(rule);
     }
     
+    /// Add an SLO, replacing one with the same name
+    pub fn define_slo(&self, slo: Slo) -> Result<(), MonitoringError> {
+        validate_slo(&slo)?;
+        self.slos.write().unwrap().define(slo);
+        Ok(())
+    }
+
+    /// Count a finished graph run towards its graph's SLOs
+    pub fn record_run(&self, run: RunSample) {
+        if self.config.enabled {
+            self.slos.write().unwrap().record(run);
+        }
+    }
+
+    /// Compliance and burn rates of every SLO
+    ///
+    /// Alerts for exhausted budgets and fast burns are raised by
+    /// [`evaluate_alerts`](Self::evaluate_alerts).
+    pub fn slo_reports(&self) -> Vec<SloReport> {
+        self.slos.read().unwrap().reports(SystemTime::now())
+    }
+
     /// Get current metrics
     pub fn get_current_metrics(&self) -> PerformanceMetrics {
         self.current_metrics.read().unwrap().clone()
@@ -93,7 +93,7 @@ impl ResourceUsage {
 }
 
 /// Resource limits and quotas
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
 pub struct ResourceLimits {
     /// Maximum CPU time per period in milliseconds
     pub max_cpu_time_ms: Option<u64>,
@@ -238,7 +238,7 @@ impl ResourceLimits {
 }
 
 /// Quota period for resource limits
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum QuotaPeriod {
     /// Per minute quotas
     Minute,
@@ -372,7 +372,7 @@ pub enum ResourceViolation {
 }
 
 /// Resource configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ResourceConfig {
     /// Enable resource quotas
     pub quotas_enabled: bool,
@@ -0,0 +1,502 @@
+// Execution sandbox profiles for AgentGraph
+// Limits which tools, hosts and models a tenant's or graph's executions may use, and how much they may spend
+
+#![allow(missing_docs)]
+
+use crate::enterprise::audit::{AuditEvent, AuditLevel, AuditLogger};
+use crate::tools::common::egress::host_matches;
+use crate::tools::traits::ToolMetadata;
+use crate::tools::ToolPolicy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Hosts outbound requests may reach
+///
+/// Patterns are exact hostnames or `*.example.com` wildcards, as for
+/// [`HttpGuard`](crate::tools::common::HttpGuard). Denied hosts win.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EgressRules {
+    /// Hosts requests must match; `None` allows any host not denied
+    #[serde(default)]
+    pub allowed_hosts: Option<Vec<String>>,
+    #[serde(default)]
+    pub denied_hosts: Vec<String>,
+}
+
+impl EgressRules {
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        if self.denied_hosts.iter().any(|pattern| host_matches(pattern, &host)) {
+            return false;
+        }
+        self.allowed_hosts.as_ref().is_none_or(|allowed| allowed.iter().any(|pattern| host_matches(pattern, &host)))
+    }
+}
+
+/// Limits an admin places on the executions of tenants or graphs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxProfile {
+    pub name: String,
+    /// Tools the executions may call
+    #[serde(default)]
+    pub tools: ToolPolicy,
+    /// Hosts HTTP tools may reach
+    #[serde(default)]
+    pub egress: EgressRules,
+    /// Models the executions may call, as `model`, `provider/model` or
+    /// `provider/*`, with a trailing `*` matching any suffix; `None` allows any
+    #[serde(default)]
+    pub allowed_models: Option<Vec<String>>,
+    /// Most an execution may spend on LLM calls, in USD
+    #[serde(default)]
+    pub max_cost_usd: Option<f64>,
+}
+
+impl SandboxProfile {
+    /// A profile allowing everything until restricted
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            tools: ToolPolicy::allow_all(),
+            egress: EgressRules::default(),
+            allowed_models: None,
+            max_cost_usd: None,
+        }
+    }
+
+    pub fn with_tools(mut self, tools: ToolPolicy) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Only let requests reach these hosts
+    pub fn with_allowed_hosts<I, H>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = H>,
+        H: Into<String>,
+    {
+        self.egress.allowed_hosts = Some(hosts.into_iter().map(|host| host.into().to_lowercase()).collect());
+        self
+    }
+
+    pub fn with_denied_host(mut self, host: impl Into<String>) -> Self {
+        self.egress.denied_hosts.push(host.into().to_lowercase());
+        self
+    }
+
+    /// Only let these models be called
+    pub fn with_allowed_models<I, M>(mut self, models: I) -> Self
+    where
+        I: IntoIterator<Item = M>,
+        M: Into<String>,
+    {
+        self.allowed_models = Some(models.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_max_cost(mut self, max_cost_usd: f64) -> Self {
+        self.max_cost_usd = Some(max_cost_usd);
+        self
+    }
+
+    pub fn allows_model(&self, provider: &str, model: &str) -> bool {
+        self.allowed_models.as_ref().is_none_or(|allowed| allowed.iter().any(|pattern| model_matches(pattern, provider, model)))
+    }
+}
+
+fn model_matches(pattern: &str, provider: &str, model: &str) -> bool {
+    let pattern = match pattern.split_once('/') {
+        Some((pattern_provider, rest)) if pattern_provider == provider => rest,
+        Some(_) => return false,
+        None => pattern,
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => model.starts_with(prefix),
+        None => pattern == model,
+    }
+}
+
+/// What a sandbox refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    Tool,
+    Egress,
+    Model,
+    Cost,
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ViolationKind::Tool => "tool",
+            ViolationKind::Egress => "egress",
+            ViolationKind::Model => "model",
+            ViolationKind::Cost => "cost",
+        })
+    }
+}
+
+/// A call refused by a sandbox profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxViolation {
+    pub profile: String,
+    pub kind: ViolationKind,
+    /// Tool, host or model refused, or the cost that would have been spent
+    pub subject: String,
+    pub message: String,
+}
+
+impl fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sandbox profile '{}' refused {}: {}", self.profile, self.kind, self.message)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SandboxError {
+    #[error("Unknown sandbox profile: {0}")]
+    UnknownProfile(String),
+    #[error("Sandbox profile storage error: {0}")]
+    Storage(String),
+}
+
+/// Profiles and what they are attached to, as stored on disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SandboxDefinitions {
+    #[serde(default)]
+    profiles: Vec<SandboxProfile>,
+    /// Profile names by tenant ID
+    #[serde(default)]
+    tenants: HashMap<String, String>,
+    /// Profile names by graph name
+    #[serde(default)]
+    graphs: HashMap<String, String>,
+}
+
+/// Sandbox profiles defined by admins and the tenants and graphs they apply to
+///
+/// An execution is held to the profile of its tenant and the profile of its
+/// graph, both at once.
+#[derive(Debug, Default)]
+pub struct SandboxRegistry {
+    definitions: RwLock<SandboxDefinitions>,
+    audit_logger: Option<Arc<AuditLogger>>,
+}
+
+impl SandboxRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record every violation in an audit log
+    pub fn with_audit_logger(mut self, logger: Arc<AuditLogger>) -> Self {
+        self.audit_logger = Some(logger);
+        self
+    }
+
+    /// Load profiles and attachments saved with [`save_to_file`](Self::save_to_file); none if `path` is missing
+    pub fn from_file(path: &std::path::Path) -> Result<Self, SandboxError> {
+        let registry = Self::new();
+        if !path.exists() {
+            return Ok(registry);
+        }
+        let storage_error = |e: &dyn fmt::Display| SandboxError::Storage(format!("{}: {}", path.display(), e));
+        let json = std::fs::read_to_string(path).map_err(|e| storage_error(&e))?;
+        let definitions: SandboxDefinitions = serde_json::from_str(&json).map_err(|e| storage_error(&e))?;
+        let known = |name: &String| definitions.profiles.iter().any(|profile| &profile.name == name);
+        if let Some(name) = definitions.tenants.values().chain(definitions.graphs.values()).find(|name| !known(name)) {
+            return Err(SandboxError::UnknownProfile(name.clone()));
+        }
+        *registry.definitions.write() = definitions;
+        Ok(registry)
+    }
+
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), SandboxError> {
+        let storage_error = |e: &dyn fmt::Display| SandboxError::Storage(format!("{}: {}", path.display(), e));
+        let json = serde_json::to_string_pretty(&*self.definitions.read()).map_err(|e| storage_error(&e))?;
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| storage_error(&e))?;
+        }
+        std::fs::write(path, json).map_err(|e| storage_error(&e))
+    }
+
+    /// Add a profile, replacing the one of the same name
+    pub fn define(&self, profile: SandboxProfile) {
+        let mut definitions = self.definitions.write();
+        definitions.profiles.retain(|existing| existing.name != profile.name);
+        definitions.profiles.push(profile);
+    }
+
+    /// Remove a profile and detach it from every tenant and graph
+    pub fn remove(&self, name: &str) -> Option<SandboxProfile> {
+        let mut definitions = self.definitions.write();
+        let index = definitions.profiles.iter().position(|profile| profile.name == name)?;
+        definitions.tenants.retain(|_, profile| profile != name);
+        definitions.graphs.retain(|_, profile| profile != name);
+        Some(definitions.profiles.remove(index))
+    }
+
+    pub fn profile(&self, name: &str) -> Option<SandboxProfile> {
+        self.definitions.read().profiles.iter().find(|profile| profile.name == name).cloned()
+    }
+
+    pub fn profiles(&self) -> Vec<SandboxProfile> {
+        self.definitions.read().profiles.clone()
+    }
+
+    /// Hold every execution of `tenant_id` to `profile`
+    pub fn attach_to_tenant(&self, tenant_id: impl Into<String>, profile: &str) -> Result<(), SandboxError> {
+        let mut definitions = self.definitions.write();
+        if !definitions.profiles.iter().any(|existing| existing.name == profile) {
+            return Err(SandboxError::UnknownProfile(profile.to_string()));
+        }
+        definitions.tenants.insert(tenant_id.into(), profile.to_string());
+        Ok(())
+    }
+
+    /// Hold every execution of the graph named `graph` to `profile`
+    pub fn attach_to_graph(&self, graph: impl Into<String>, profile: &str) -> Result<(), SandboxError> {
+        let mut definitions = self.definitions.write();
+        if !definitions.profiles.iter().any(|existing| existing.name == profile) {
+            return Err(SandboxError::UnknownProfile(profile.to_string()));
+        }
+        definitions.graphs.insert(graph.into(), profile.to_string());
+        Ok(())
+    }
+
+    pub fn detach_tenant(&self, tenant_id: &str) {
+        self.definitions.write().tenants.remove(tenant_id);
+    }
+
+    pub fn detach_graph(&self, graph: &str) {
+        self.definitions.write().graphs.remove(graph);
+    }
+
+    /// Sandbox for one execution of `graph` on behalf of `tenant_id`; `None` when no profile applies
+    pub fn sandbox_for(&self, tenant_id: Option<&str>, graph: &str) -> Option<ExecutionSandbox> {
+        let definitions = self.definitions.read();
+        let attached = [
+            tenant_id.and_then(|tenant_id| definitions.tenants.get(tenant_id)),
+            definitions.graphs.get(graph),
+        ];
+        let profiles: Vec<SandboxProfile> = attached
+            .into_iter()
+            .flatten()
+            .filter_map(|name| definitions.profiles.iter().find(|profile| &profile.name == name))
+            .cloned()
+            .collect();
+        if profiles.is_empty() {
+            return None;
+        }
+        Some(ExecutionSandbox {
+            inner: Arc::new(SandboxInner {
+                profiles,
+                tenant_id: tenant_id.map(String::from),
+                graph: graph.to_string(),
+                spent_usd: Mutex::new(0.0),
+                audit_logger: self.audit_logger.clone(),
+            }),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct SandboxInner {
+    profiles: Vec<SandboxProfile>,
+    tenant_id: Option<String>,
+    graph: String,
+    /// Spent on LLM calls so far, across the execution's retries
+    spent_usd: Mutex<f64>,
+    audit_logger: Option<Arc<AuditLogger>>,
+}
+
+/// The profiles one execution is held to, and what it has spent
+#[derive(Debug, Clone)]
+pub struct ExecutionSandbox {
+    inner: Arc<SandboxInner>,
+}
+
+impl ExecutionSandbox {
+    pub fn profiles(&self) -> &[SandboxProfile] {
+        &self.inner.profiles
+    }
+
+    pub fn spent_usd(&self) -> f64 {
+        *self.inner.spent_usd.lock()
+    }
+
+    /// Refuse a tool no profile allows, auditing the refusal
+    pub async fn check_tool(&self, tool: &ToolMetadata) -> Result<(), SandboxViolation> {
+        let refused = self.inner.profiles.iter().find(|profile| !profile.tools.allows(tool));
+        self.enforce(refused.map(|profile| SandboxViolation {
+            profile: profile.name.clone(),
+            kind: ViolationKind::Tool,
+            subject: tool.qualified_name(),
+            message: format!("tool '{}' is not allowed", tool.qualified_name()),
+        }))
+        .await
+    }
+
+    pub async fn check_host(&self, host: &str) -> Result<(), SandboxViolation> {
+        let refused = self.inner.profiles.iter().find(|profile| !profile.egress.allows(host));
+        self.enforce(refused.map(|profile| SandboxViolation {
+            profile: profile.name.clone(),
+            kind: ViolationKind::Egress,
+            subject: host.to_string(),
+            message: format!("host '{}' may not be reached", host),
+        }))
+        .await
+    }
+
+    pub async fn check_model(&self, provider: &str, model: &str) -> Result<(), SandboxViolation> {
+        let refused = self.inner.profiles.iter().find(|profile| !profile.allows_model(provider, model));
+        self.enforce(refused.map(|profile| SandboxViolation {
+            profile: profile.name.clone(),
+            kind: ViolationKind::Model,
+            subject: format!("{}/{}", provider, model),
+            message: format!("model '{}' of provider '{}' is not allowed", model, provider),
+        }))
+        .await
+    }
+
+    /// Refuse a call whose estimated cost would take the execution past a profile's maximum
+    pub async fn check_cost(&self, estimated_usd: f64) -> Result<(), SandboxViolation> {
+        let total = self.spent_usd() + estimated_usd;
+        let refused = self
+            .inner
+            .profiles
+            .iter()
+            .find(|profile| profile.max_cost_usd.is_some_and(|max| total > max));
+        self.enforce(refused.map(|profile| SandboxViolation {
+            profile: profile.name.clone(),
+            kind: ViolationKind::Cost,
+            subject: format!("{:.4}", total),
+            message: format!(
+                "spending ${:.4} would exceed the maximum of ${:.4}",
+                total,
+                profile.max_cost_usd.unwrap_or_default()
+            ),
+        }))
+        .await
+    }
+
+    /// Add the cost of a completed call to what the execution has spent
+    pub fn record_cost(&self, cost_usd: f64) {
+        *self.inner.spent_usd.lock() += cost_usd;
+    }
+
+    async fn enforce(&self, violation: Option<SandboxViolation>) -> Result<(), SandboxViolation> {
+        let Some(violation) = violation else {
+            return Ok(());
+        };
+        tracing::warn!(
+            profile = %violation.profile,
+            kind = %violation.kind,
+            subject = %violation.subject,
+            graph = %self.inner.graph,
+            "Sandbox violation"
+        );
+        if let Some(logger) = &self.inner.audit_logger {
+            let mut event = AuditEvent::security_event("sandbox_violation".to_string(), violation.to_string())
+                .with_level(AuditLevel::Warning)
+                .with_resource(violation.subject.clone())
+                .with_data("profile".to_string(), &violation.profile)
+                .with_data("kind".to_string(), violation.kind)
+                .with_data("graph".to_string(), &self.inner.graph);
+            if let Some(execution_id) = crate::graph::engine::current_execution_id() {
+                event = event.with_data("execution_id".to_string(), execution_id.to_string());
+            }
+            if let Some(tenant_id) = &self.inner.tenant_id {
+                event = event.with_tenant(tenant_id.clone());
+            }
+            if let Err(e) = logger.log_event(event).await {
+                tracing::warn!("Failed to record sandbox violation audit event: {}", e);
+            }
+        }
+        Err(violation)
+    }
+}
+
+tokio::task_local! {
+    static SANDBOX: ExecutionSandbox;
+}
+
+/// Run `future` with its tool and LLM calls held to `sandbox`
+pub async fn with_sandbox<F: Future>(sandbox: Option<ExecutionSandbox>, future: F) -> F::Output {
+    match sandbox {
+        Some(sandbox) => SANDBOX.scope(sandbox, future).await,
+        None => future.await,
+    }
+}
+
+/// Carry the current sandbox into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = SANDBOX.try_with(ExecutionSandbox::clone).ok();
+    async move {
+        match value {
+            Some(value) => SANDBOX.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Sandbox of the execution the current task belongs to, if it has one
+pub fn current() -> Option<ExecutionSandbox> {
+    SANDBOX.try_with(ExecutionSandbox::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[tokio::test]
+    async fn test_tenant_and_graph_profiles_both_apply_and_violations_are_audited() {
+        let logger = Arc::new(AuditLogger::in_memory());
+        let registry = SandboxRegistry::new().with_audit_logger(logger.clone());
+        registry.define(
+            SandboxProfile::new("restricted")
+                .with_tools(ToolPolicy::deny_all().with_allow("web:*"))
+                .with_allowed_hosts(["*.example.com"])
+                .with_allowed_models(["openai/gpt-4o*", "claude-3-haiku"])
+                .with_max_cost(0.10),
+        );
+        registry.define(SandboxProfile::new("no-shell").with_tools(ToolPolicy::allow_all().with_deny("shell")));
+        registry.attach_to_tenant("acme", "restricted").unwrap();
+        registry.attach_to_graph("support", "no-shell").unwrap();
+        assert!(registry.attach_to_graph("billing", "missing").is_err());
+        assert!(registry.sandbox_for(Some("globex"), "billing").is_none());
+
+        let sandbox = registry.sandbox_for(Some("acme"), "support").unwrap();
+        assert_eq!(sandbox.profiles().len(), 2);
+        let fetch = ToolMetadata::new("fetch", "Fetch", "Fetch a page").with_namespace("web");
+        let shell = ToolMetadata::new("shell", "Shell", "Run commands");
+        assert!(sandbox.check_tool(&fetch).await.is_ok());
+        assert_eq!(sandbox.check_tool(&shell).await.unwrap_err().kind, ViolationKind::Tool);
+        assert!(sandbox.check_host("api.example.com").await.is_ok());
+        assert!(sandbox.check_host("evil.com").await.is_err());
+        assert!(sandbox.check_model("openai", "gpt-4o-mini").await.is_ok());
+        assert!(sandbox.check_model("anthropic", "claude-3-haiku").await.is_ok());
+        assert!(sandbox.check_model("anthropic", "gpt-4o").await.is_err());
+
+        sandbox.check_cost(0.06).await.unwrap();
+        sandbox.record_cost(0.06);
+        let violation = sandbox.check_cost(0.06).await.unwrap_err();
+        assert_eq!((violation.profile.as_str(), violation.kind), ("restricted", ViolationKind::Cost));
+
+        // A graph alone only gets its own profile
+        let sandbox = registry.sandbox_for(None, "support").unwrap();
+        assert!(sandbox.check_model("anthropic", "gpt-4o").await.is_ok());
+
+        let events = logger.events(SystemTime::UNIX_EPOCH, SystemTime::now(), Some("acme")).await.unwrap();
+        let kinds: Vec<_> = events.iter().map(|event| event.data["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["tool", "egress", "model", "cost"]);
+        assert!(events.iter().all(|event| event.action == "sandbox_violation"));
+    }
+}
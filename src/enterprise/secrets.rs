@@ -0,0 +1,531 @@
+// Per-tenant secrets for AgentGraph
+// Stores tool credentials by tenant and injects them into tool calls at execution time
+
+#![allow(missing_docs)]
+
+use crate::tools::{ToolError, ToolInput, ToolResult};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+
+/// Context key tool inputs carry the tenant ID under
+pub const TENANT_CONTEXT_KEY: &str = "tenant_id";
+
+/// Secret string that never appears in debug output or serialized data
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wrap a secret value
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Access the underlying value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+/// Kind of credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CredentialKind {
+    /// Static API key
+    ApiKey,
+    /// Bearer token such as an OAuth access token
+    BearerToken,
+    /// Password for basic authentication
+    Password,
+}
+
+/// Credential stored for a tenant
+#[derive(Debug, Clone)]
+pub struct Credential {
+    /// Name tools refer to the credential by, e.g. `github_token`
+    pub name: String,
+    /// Kind of credential
+    pub kind: CredentialKind,
+    /// Secret value
+    pub secret: SecretString,
+    /// Username for password credentials
+    pub username: Option<String>,
+    /// When the credential stops being valid
+    pub expires_at: Option<DateTime<Utc>>,
+    /// When the credential was stored
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Credential {
+    /// Create a credential
+    pub fn new(name: &str, kind: CredentialKind, secret: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            secret: SecretString::new(secret),
+            username: None,
+            expires_at: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Create an API key credential
+    pub fn api_key(name: &str, key: impl Into<String>) -> Self {
+        Self::new(name, CredentialKind::ApiKey, key)
+    }
+
+    /// Create a bearer token credential
+    pub fn bearer_token(name: &str, token: impl Into<String>) -> Self {
+        Self::new(name, CredentialKind::BearerToken, token)
+    }
+
+    /// Set the username
+    pub fn with_username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_string());
+        self
+    }
+
+    /// Set the expiry time
+    pub fn with_expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Check if the credential has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+}
+
+/// Storage backend for tenant secrets
+///
+/// Implement this to keep credentials in an external secret manager; the
+/// vault only ever asks for one tenant's secrets at a time.
+#[async_trait]
+pub trait SecretStore: Send + Sync + fmt::Debug {
+    /// Store or replace a credential for a tenant
+    async fn put(&self, tenant_id: &str, credential: Credential) -> Result<(), SecretsError>;
+
+    /// Get a tenant's credential by name
+    async fn get(&self, tenant_id: &str, name: &str) -> Result<Option<Credential>, SecretsError>;
+
+    /// Delete a tenant's credential, returning whether it existed
+    async fn delete(&self, tenant_id: &str, name: &str) -> Result<bool, SecretsError>;
+
+    /// Names of a tenant's credentials
+    async fn list(&self, tenant_id: &str) -> Result<Vec<String>, SecretsError>;
+}
+
+/// In-process secret store
+#[derive(Debug, Default)]
+pub struct InMemorySecretStore {
+    /// Credentials by tenant, then by name
+    secrets: RwLock<HashMap<String, HashMap<String, Credential>>>,
+}
+
+impl InMemorySecretStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SecretStore for InMemorySecretStore {
+    async fn put(&self, tenant_id: &str, credential: Credential) -> Result<(), SecretsError> {
+        let mut secrets = self.secrets.write().unwrap();
+        secrets.entry(tenant_id.to_string())
+            .or_default()
+            .insert(credential.name.clone(), credential);
+        Ok(())
+    }
+
+    async fn get(&self, tenant_id: &str, name: &str) -> Result<Option<Credential>, SecretsError> {
+        let secrets = self.secrets.read().unwrap();
+        Ok(secrets.get(tenant_id).and_then(|tenant| tenant.get(name)).cloned())
+    }
+
+    async fn delete(&self, tenant_id: &str, name: &str) -> Result<bool, SecretsError> {
+        let mut secrets = self.secrets.write().unwrap();
+        Ok(secrets.get_mut(tenant_id).is_some_and(|tenant| tenant.remove(name).is_some()))
+    }
+
+    async fn list(&self, tenant_id: &str) -> Result<Vec<String>, SecretsError> {
+        let secrets = self.secrets.read().unwrap();
+        let mut names: Vec<String> = secrets.get(tenant_id)
+            .map(|tenant| tenant.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// Secret store wrapper that encrypts secret values with AES-256-GCM
+///
+/// Only ciphertext reaches the wrapped backend. Each value is bound to its
+/// tenant and credential name, so a ciphertext copied to another tenant's
+/// entry fails to decrypt.
+pub struct EncryptedSecretStore {
+    inner: Arc<dyn SecretStore>,
+    cipher: Aes256Gcm,
+}
+
+impl fmt::Debug for EncryptedSecretStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedSecretStore")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedSecretStore {
+    /// Wrap a store using a 256-bit key
+    pub fn new(inner: Arc<dyn SecretStore>, key: &[u8; 32]) -> Self {
+        Self {
+            inner,
+            cipher: Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// Wrap a store using a base64-encoded 256-bit key
+    pub fn from_base64_key(inner: Arc<dyn SecretStore>, key: &str) -> Result<Self, SecretsError> {
+        let bytes = base64::engine::general_purpose::STANDARD.decode(key.trim())
+            .map_err(|e| SecretsError::EncryptionError { message: format!("Invalid key encoding: {}", e) })?;
+        let key: [u8; 32] = bytes.try_into().map_err(|_| SecretsError::EncryptionError {
+            message: "Encryption key must be 32 bytes".to_string(),
+        })?;
+        Ok(Self::new(inner, &key))
+    }
+
+    fn associated_data(tenant_id: &str, name: &str) -> Vec<u8> {
+        format!("{}\0{}", tenant_id, name).into_bytes()
+    }
+
+    fn encrypt(&self, tenant_id: &str, name: &str, plaintext: &str) -> Result<String, SecretsError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = Self::associated_data(tenant_id, name);
+        let ciphertext = self.cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: &aad })
+            .map_err(|_| SecretsError::EncryptionError { message: "Encryption failed".to_string() })?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(base64::engine::general_purpose::STANDARD.encode(sealed))
+    }
+
+    fn decrypt(&self, tenant_id: &str, name: &str, sealed: &str) -> Result<String, SecretsError> {
+        let failed = || SecretsError::EncryptionError {
+            message: format!("Failed to decrypt credential '{}' for tenant {}", name, tenant_id),
+        };
+        let sealed = base64::engine::general_purpose::STANDARD.decode(sealed).map_err(|_| failed())?;
+        if sealed.len() < 12 {
+            return Err(failed());
+        }
+        let (nonce, ciphertext) = sealed.split_at(12);
+        let aad = Self::associated_data(tenant_id, name);
+        let plaintext = self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| failed())?;
+        String::from_utf8(plaintext).map_err(|_| failed())
+    }
+}
+
+#[async_trait]
+impl SecretStore for EncryptedSecretStore {
+    async fn put(&self, tenant_id: &str, mut credential: Credential) -> Result<(), SecretsError> {
+        let sealed = self.encrypt(tenant_id, &credential.name, credential.secret.expose())?;
+        credential.secret = SecretString::new(sealed);
+        self.inner.put(tenant_id, credential).await
+    }
+
+    async fn get(&self, tenant_id: &str, name: &str) -> Result<Option<Credential>, SecretsError> {
+        match self.inner.get(tenant_id, name).await? {
+            Some(mut credential) => {
+                credential.secret = SecretString::new(self.decrypt(tenant_id, name, credential.secret.expose())?);
+                Ok(Some(credential))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, tenant_id: &str, name: &str) -> Result<bool, SecretsError> {
+        self.inner.delete(tenant_id, name).await
+    }
+
+    async fn list(&self, tenant_id: &str) -> Result<Vec<String>, SecretsError> {
+        self.inner.list(tenant_id).await
+    }
+}
+
+/// Where a credential is placed in the tool input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CredentialInjection {
+    /// Set an input parameter to the secret
+    Parameter { key: String },
+    /// Add an HTTP header to the `headers` parameter, e.g. `Authorization: Bearer <secret>`
+    Header { name: String, prefix: Option<String> },
+}
+
+/// Binding of a tenant credential to a tool
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CredentialBinding {
+    /// Credential name
+    pub credential: String,
+    /// Where the credential goes
+    pub injection: CredentialInjection,
+    /// Fail the call when the tenant has no such credential
+    pub required: bool,
+}
+
+impl CredentialBinding {
+    /// Inject a credential as an input parameter
+    pub fn parameter(credential: &str, key: &str) -> Self {
+        Self {
+            credential: credential.to_string(),
+            injection: CredentialInjection::Parameter { key: key.to_string() },
+            required: true,
+        }
+    }
+
+    /// Inject a credential as an HTTP header
+    pub fn header(credential: &str, name: &str) -> Self {
+        Self {
+            credential: credential.to_string(),
+            injection: CredentialInjection::Header { name: name.to_string(), prefix: None },
+            required: true,
+        }
+    }
+
+    /// Inject a credential as `Authorization: Bearer <secret>`
+    pub fn bearer(credential: &str) -> Self {
+        Self {
+            credential: credential.to_string(),
+            injection: CredentialInjection::Header {
+                name: "Authorization".to_string(),
+                prefix: Some("Bearer ".to_string()),
+            },
+            required: true,
+        }
+    }
+
+    /// Skip the binding instead of failing when the tenant lacks the credential
+    pub fn optional(mut self) -> Self {
+        self.required = false;
+        self
+    }
+}
+
+/// Vault resolving tool credentials from the calling tenant's secrets
+#[derive(Debug, Clone)]
+pub struct CredentialVault {
+    store: Arc<dyn SecretStore>,
+    /// Bindings by tool ID
+    bindings: HashMap<String, Vec<CredentialBinding>>,
+}
+
+impl CredentialVault {
+    /// Create a vault over a secret store
+    pub fn new(store: Arc<dyn SecretStore>) -> Self {
+        Self {
+            store,
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Bind a credential to a tool
+    pub fn with_binding(mut self, tool_id: &str, binding: CredentialBinding) -> Self {
+        self.bindings.entry(tool_id.to_string()).or_default().push(binding);
+        self
+    }
+
+    /// Underlying secret store
+    pub fn store(&self) -> &Arc<dyn SecretStore> {
+        &self.store
+    }
+
+    /// Credentials bound to a tool
+    pub fn bindings(&self, tool_id: &str) -> &[CredentialBinding] {
+        self.bindings.get(tool_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Store a credential for a tenant
+    pub async fn store_credential(&self, tenant_id: &str, credential: Credential) -> Result<(), SecretsError> {
+        self.store.put(tenant_id, credential).await
+    }
+
+    /// Inject the calling tenant's credentials into a tool input
+    ///
+    /// The tenant comes from the input's `tenant_id` context entry. Tools
+    /// without bindings pass through untouched; a tool with bindings called
+    /// without a tenant is rejected so it never runs with another tenant's
+    /// or the process's credentials. Returns the number injected.
+    pub async fn inject(&self, tool_id: &str, input: &mut ToolInput) -> ToolResult<usize> {
+        let bindings = self.bindings(tool_id);
+        if bindings.is_empty() {
+            return Ok(0);
+        }
+
+        let tenant_id = input.get_context(TENANT_CONTEXT_KEY).cloned().ok_or_else(|| {
+            ToolError::PermissionDenied {
+                message: format!("Tool '{}' requires credentials but no tenant context was provided", tool_id),
+            }
+        })?;
+
+        let mut injected = 0;
+        for binding in bindings {
+            let credential = self.store.get(&tenant_id, &binding.credential).await
+                .map_err(|e| ToolError::ConfigurationError { message: e.to_string() })?;
+            let credential = match credential {
+                Some(credential) if credential.is_expired() => {
+                    return Err(ToolError::AuthenticationError {
+                        message: SecretsError::CredentialExpired {
+                            tenant_id: tenant_id.clone(),
+                            name: binding.credential.clone(),
+                        }.to_string(),
+                    });
+                }
+                Some(credential) => credential,
+                None if binding.required => {
+                    return Err(ToolError::AuthenticationError {
+                        message: SecretsError::CredentialNotFound {
+                            tenant_id: tenant_id.clone(),
+                            name: binding.credential.clone(),
+                        }.to_string(),
+                    });
+                }
+                None => continue,
+            };
+
+            let secret = credential.secret.expose();
+            match &binding.injection {
+                CredentialInjection::Parameter { key } => {
+                    input.parameters.insert(key.clone(), serde_json::Value::String(secret.to_string()));
+                }
+                CredentialInjection::Header { name, prefix } => {
+                    let headers = input.parameters.entry("headers".to_string())
+                        .or_insert_with(|| serde_json::json!({}));
+                    if !headers.is_object() {
+                        *headers = serde_json::json!({});
+                    }
+                    headers[name.as_str()] = serde_json::Value::String(
+                        format!("{}{}", prefix.as_deref().unwrap_or_default(), secret),
+                    );
+                }
+            }
+            injected += 1;
+        }
+
+        tracing::debug!("Injected {} credentials for tool '{}' (tenant {})", injected, tool_id, tenant_id);
+        Ok(injected)
+    }
+}
+
+/// Errors that can occur in secret operations
+#[derive(Debug, Error, Clone, Serialize, Deserialize)]
+pub enum SecretsError {
+    /// Tenant has no credential with this name
+    #[error("Credential '{name}' not found for tenant {tenant_id}")]
+    CredentialNotFound { tenant_id: String, name: String },
+
+    /// Credential is past its expiry time
+    #[error("Credential '{name}' for tenant {tenant_id} has expired")]
+    CredentialExpired { tenant_id: String, name: String },
+
+    /// Storage backend failure
+    #[error("Secret storage error: {message}")]
+    StorageError { message: String },
+
+    /// Secret could not be encrypted or decrypted
+    #[error("Secret encryption error: {message}")]
+    EncryptionError { message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn vault() -> CredentialVault {
+        let vault = CredentialVault::new(Arc::new(InMemorySecretStore::new()))
+            .with_binding("http_get", CredentialBinding::bearer("api_token"))
+            .with_binding("search", CredentialBinding::parameter("search_key", "api_key"))
+            .with_binding("search", CredentialBinding::header("org_id", "X-Org").optional());
+        vault.store_credential("acme", Credential::bearer_token("api_token", "acme-token")).await.unwrap();
+        vault.store_credential("globex", Credential::bearer_token("api_token", "globex-token")).await.unwrap();
+        vault.store_credential("acme", Credential::api_key("search_key", "acme-search")).await.unwrap();
+        vault
+    }
+
+    #[tokio::test]
+    async fn test_injects_calling_tenants_credentials() {
+        let vault = vault().await;
+
+        let mut acme = ToolInput::new(json!("https://api.test")).with_context(TENANT_CONTEXT_KEY, "acme");
+        let mut globex = ToolInput::new(json!("https://api.test")).with_context(TENANT_CONTEXT_KEY, "globex");
+        assert_eq!(vault.inject("http_get", &mut acme).await.unwrap(), 1);
+        assert_eq!(vault.inject("http_get", &mut globex).await.unwrap(), 1);
+        assert_eq!(acme.parameters["headers"]["Authorization"], "Bearer acme-token");
+        assert_eq!(globex.parameters["headers"]["Authorization"], "Bearer globex-token");
+
+        // Optional binding is skipped when the tenant lacks it
+        let mut search = ToolInput::new(json!({"q": "rust"})).with_context(TENANT_CONTEXT_KEY, "acme");
+        assert_eq!(vault.inject("search", &mut search).await.unwrap(), 1);
+        assert_eq!(search.parameters["api_key"], "acme-search");
+        assert!(search.parameters.get("headers").is_none());
+
+        // Unbound tools need no tenant
+        let mut other = ToolInput::new(json!(null));
+        assert_eq!(vault.inject("calculator", &mut other).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_missing_tenant_or_credential() {
+        let vault = vault().await;
+
+        let mut anonymous = ToolInput::new(json!({}));
+        assert!(matches!(vault.inject("http_get", &mut anonymous).await, Err(ToolError::PermissionDenied { .. })));
+
+        let mut globex = ToolInput::new(json!({})).with_context(TENANT_CONTEXT_KEY, "globex");
+        assert!(matches!(vault.inject("search", &mut globex).await, Err(ToolError::AuthenticationError { .. })));
+
+        vault.store_credential(
+            "globex",
+            Credential::api_key("search_key", "old").with_expires_at(Utc::now() - chrono::Duration::minutes(1)),
+        ).await.unwrap();
+        let error = vault.inject("search", &mut globex).await.unwrap_err();
+        assert!(error.to_string().contains("expired"));
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_round_trip() {
+        let backend = Arc::new(InMemorySecretStore::new());
+        let store = EncryptedSecretStore::new(backend.clone(), &[7u8; 32]);
+
+        store.put("acme", Credential::api_key("key", "plain-value")).await.unwrap();
+        let raw = backend.get("acme", "key").await.unwrap().unwrap();
+        assert_ne!(raw.secret.expose(), "plain-value");
+        assert_eq!(store.get("acme", "key").await.unwrap().unwrap().secret.expose(), "plain-value");
+
+        // Ciphertext is bound to its tenant
+        backend.put("globex", raw).await.unwrap();
+        assert!(matches!(store.get("globex", "key").await, Err(SecretsError::EncryptionError { .. })));
+    }
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let credential = Credential::api_key("key", "super-secret");
+        assert!(!format!("{:?}", credential).contains("super-secret"));
+    }
+}
@@ -249,7 +249,7 @@ impl Role {
 }
 
 /// Security configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct SecurityConfig {
     /// Enable authentication
     pub authentication_enabled: bool,
@@ -265,6 +265,9 @@ pub struct SecurityConfig {
     pub password_policy: PasswordPolicy,
     /// Rate limiting configuration
     pub rate_limiting: RateLimitConfig,
+    /// Fields hidden when state is shown to users
+    #[serde(default)]
+    pub redaction: RedactionPolicy,
 }
 
 impl Default for SecurityConfig {
@@ -277,12 +280,13 @@ impl Default for SecurityConfig {
             auth_methods: vec![AuthMethod::ApiKey, AuthMethod::JWT],
             password_policy: PasswordPolicy::default(),
             rate_limiting: RateLimitConfig::default(),
+            redaction: RedactionPolicy::default(),
         }
     }
 }
 
 /// Authentication methods
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum AuthMethod {
     /// API key authentication
     ApiKey,
@@ -295,7 +299,7 @@ pub enum AuthMethod {
 }
 
 /// Password policy configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct PasswordPolicy {
     /// Minimum password length
     pub min_length: u32,
@@ -325,7 +329,7 @@ impl Default for PasswordPolicy {
 }
 
 /// Rate limiting configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct RateLimitConfig {
     /// Enable rate limiting
     pub enabled: bool,
@@ -348,6 +352,165 @@ impl Default for RateLimitConfig {
     }
 }
 
+/// Sensitive fields to hide when state leaves the process, e.g. in the Studio
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct RedactionPolicy {
+    /// Object keys redacted wherever they appear, matched ignoring case,
+    /// `-` and `_`; `api_key` covers `apiKey` and `API-KEY`
+    pub fields: Vec<String>,
+    /// JSON pointers redacted exactly, e.g. `/customer/address`
+    pub paths: Vec<String>,
+    /// What redacted values are replaced with
+    pub replacement: String,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            fields: ["password", "secret", "token", "api_key", "access_token", "refresh_token", "authorization", "credentials", "private_key", "ssn", "credit_card"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            paths: Vec::new(),
+            replacement: "[REDACTED]".to_string(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// A policy that redacts nothing
+    pub fn none() -> Self {
+        Self { fields: Vec::new(), paths: Vec::new(), replacement: "[REDACTED]".to_string() }
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.fields.push(field.into());
+        self
+    }
+
+    pub fn with_path(mut self, pointer: impl Into<String>) -> Self {
+        self.paths.push(pointer.into());
+        self
+    }
+
+    fn normalize(key: &str) -> String {
+        key.chars().filter(|c| *c != '_' && *c != '-').flat_map(char::to_lowercase).collect()
+    }
+
+    /// Whether values under `key` are redacted wherever they appear
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        let key = Self::normalize(key);
+        self.fields.iter().any(|field| Self::normalize(field) == key)
+    }
+
+    /// Replace the sensitive values in `value`
+    pub fn redact(&self, value: &mut serde_json::Value) {
+        self.redact_at(value, &mut String::new());
+    }
+
+    /// A redacted copy of `value`
+    pub fn redacted(&self, value: &serde_json::Value) -> serde_json::Value {
+        let mut value = value.clone();
+        self.redact(&mut value);
+        value
+    }
+
+    fn redact_at(&self, value: &mut serde_json::Value, pointer: &mut String) {
+        if self.paths.iter().any(|path| path == pointer) {
+            *value = serde_json::Value::String(self.replacement.clone());
+            return;
+        }
+        let len = pointer.len();
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    if self.is_sensitive(key) {
+                        *child = serde_json::Value::String(self.replacement.clone());
+                        continue;
+                    }
+                    pointer.push('/');
+                    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                    self.redact_at(child, pointer);
+                    pointer.truncate(len);
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for (index, child) in items.iter_mut().enumerate() {
+                    pointer.push('/');
+                    pointer.push_str(&index.to_string());
+                    self.redact_at(child, pointer);
+                    pointer.truncate(len);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Personal data found by pattern inside free text, which field-based
+/// redaction cannot see, e.g. an email address in an agent's reply
+#[derive(Debug, Clone)]
+pub struct PiiScrubber {
+    /// Label and pattern, applied in order; matches become `[LABEL]`
+    patterns: Vec<(String, regex::Regex)>,
+}
+
+impl Default for PiiScrubber {
+    fn default() -> Self {
+        let builtin = [
+            ("EMAIL", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+            ("CREDIT_CARD", r"\b(?:\d[ -]?){12,18}\d\b"),
+            ("SSN", r"\b\d{3}-\d{2}-\d{4}\b"),
+            ("PHONE", r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)|\b\d{3})[ .-]?\d{3}[ .-]?\d{4}\b"),
+            ("IP_ADDRESS", r"\b(?:\d{1,3}\.){3}\d{1,3}\b"),
+        ];
+        Self {
+            patterns: builtin
+                .into_iter()
+                .map(|(label, pattern)| (label.to_string(), regex::Regex::new(pattern).expect("built-in PII pattern")))
+                .collect(),
+        }
+    }
+}
+
+impl PiiScrubber {
+    /// A scrubber that finds nothing
+    pub fn none() -> Self {
+        Self { patterns: Vec::new() }
+    }
+
+    /// Also replace matches of `pattern` with `[LABEL]`
+    pub fn with_pattern(mut self, label: impl Into<String>, pattern: &str) -> Result<Self, SecurityError> {
+        let regex = regex::Regex::new(pattern).map_err(|e| SecurityError::ConfigurationError {
+            message: format!("Invalid PII pattern '{}': {}", pattern, e),
+        })?;
+        self.patterns.push((label.into().to_uppercase(), regex));
+        Ok(self)
+    }
+
+    /// `text` with personal data replaced
+    pub fn scrub_text(&self, text: &str) -> String {
+        let mut scrubbed = text.to_string();
+        for (label, regex) in &self.patterns {
+            if regex.is_match(&scrubbed) {
+                scrubbed = regex.replace_all(&scrubbed, format!("[{}]", label).as_str()).into_owned();
+            }
+        }
+        scrubbed
+    }
+
+    /// Replace personal data in every string inside `value`
+    pub fn scrub(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.scrub_text(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| self.scrub(item)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|child| self.scrub(child)),
+            _ => {}
+        }
+    }
+}
+
 /// Security manager for handling authentication and authorization
 #[derive(Debug)]
 pub struct SecurityManager {
@@ -492,7 +655,7 @@ impl SecurityManager {
         }
         
         let mut rate_limits = self.rate_limits.write().unwrap();
-        let now = SystemTime::now();
+        let now = crate::graph::determinism::system_now();
         
         let state = rate_limits.entry(user_id.to_string())
             .or_insert_with(|| RateLimitState::new(now));
@@ -641,6 +804,42 @@ mod tests {
         assert!(!auth.is_expired());
     }
 
+    #[test]
+    fn test_redaction_policy() {
+        let policy = RedactionPolicy::default().with_path("/customer/address");
+        let state = serde_json::json!({
+            "apiKey": "sk-123",
+            "customer": { "name": "Ada", "address": "1 Main St", "cards": [{ "credit-card": "4111" }] },
+            "tokens_used": 42,
+        });
+
+        let redacted = policy.redacted(&state);
+        assert_eq!(redacted, serde_json::json!({
+            "apiKey": "[REDACTED]",
+            "customer": { "name": "Ada", "address": "[REDACTED]", "cards": [{ "credit-card": "[REDACTED]" }] },
+            "tokens_used": 42,
+        }));
+        assert_eq!(RedactionPolicy::none().redacted(&state), state);
+    }
+
+    #[test]
+    fn test_pii_scrubber() {
+        let scrubber = PiiScrubber::default().with_pattern("order", r"ORD-\d+").unwrap();
+        let mut value = serde_json::json!({
+            "reply": "Mail ada@example.com or call +1 (555) 123-4567 about ORD-991",
+            "notes": ["card 4111 1111 1111 1111, ssn 078-05-1120", "from 10.0.0.12"],
+            "attempts": 2,
+        });
+        scrubber.scrub(&mut value);
+        assert_eq!(value, serde_json::json!({
+            "reply": "Mail [EMAIL] or call [PHONE] about [ORDER]",
+            "notes": ["card [CREDIT_CARD], ssn [SSN]", "from [IP_ADDRESS]"],
+            "attempts": 2,
+        }));
+        assert_eq!(PiiScrubber::none().scrub_text("ada@example.com"), "ada@example.com");
+        assert!(PiiScrubber::none().with_pattern("broken", "(").is_err());
+    }
+
     #[tokio::test]
     async fn test_security_manager() {
         let config = SecurityConfig::default();
@@ -0,0 +1,420 @@
+// Service level objectives for graphs
+// Tracks compliance and error-budget burn rates over rolling windows
+
+#![allow(missing_docs)]
+
+use super::monitoring::{Alert, AlertSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, SystemTime};
+
+/// What a run of a graph has to achieve to count as good
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SloObjective {
+    /// Run finishes within `threshold_ms`
+    Latency { threshold_ms: u64 },
+    /// Run succeeds
+    SuccessRate,
+    /// Run costs at most `max_usd`; runs without a cost are not counted
+    CostPerRun { max_usd: f64 },
+}
+
+impl SloObjective {
+    /// Whether `run` meets the objective; `None` when it does not apply
+    fn is_good(&self, run: &RunSample) -> Option<bool> {
+        match self {
+            Self::Latency { threshold_ms } => Some(run.duration_ms <= *threshold_ms),
+            Self::SuccessRate => Some(run.succeeded),
+            Self::CostPerRun { max_usd } => run.cost_usd.map(|cost| cost <= *max_usd),
+        }
+    }
+}
+
+/// Fire when the error budget burns faster than `threshold` times the
+/// sustainable rate over both windows
+///
+/// The long window makes the alert significant, the short one makes it stop
+/// soon after the burn does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BurnRateAlert {
+    pub long_window: Duration,
+    pub short_window: Duration,
+    pub threshold: f64,
+    pub severity: AlertSeverity,
+}
+
+impl BurnRateAlert {
+    /// Pages: 2% of a 30-day budget gone in an hour
+    pub fn fast() -> Self {
+        Self {
+            long_window: Duration::from_secs(3600),
+            short_window: Duration::from_secs(300),
+            threshold: 14.4,
+            severity: AlertSeverity::Critical,
+        }
+    }
+
+    /// Tickets: 5% of a 30-day budget gone in six hours
+    pub fn slow() -> Self {
+        Self {
+            long_window: Duration::from_secs(6 * 3600),
+            short_window: Duration::from_secs(1800),
+            threshold: 6.0,
+            severity: AlertSeverity::Warning,
+        }
+    }
+}
+
+/// A service level objective for one graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Slo {
+    /// Unique name, e.g. `support-bot-latency`
+    pub name: String,
+    /// Graph whose runs count
+    pub graph: String,
+    pub objective: SloObjective,
+    /// Share of runs that must be good, e.g. 0.99
+    pub target: f64,
+    /// Rolling window compliance is measured over
+    pub window: Duration,
+    /// Burn rates that raise alerts
+    #[serde(default)]
+    pub alerts: Vec<BurnRateAlert>,
+}
+
+impl Slo {
+    /// An SLO over 30 days with the fast and slow burn-rate alerts
+    pub fn new(name: impl Into<String>, graph: impl Into<String>, objective: SloObjective, target: f64) -> Self {
+        Self {
+            name: name.into(),
+            graph: graph.into(),
+            objective,
+            target: target.clamp(0.0, 1.0),
+            window: Duration::from_secs(30 * 86400),
+            alerts: vec![BurnRateAlert::fast(), BurnRateAlert::slow()],
+        }
+    }
+
+    /// 95% of runs finish within `threshold_ms`
+    pub fn p95_latency(name: impl Into<String>, graph: impl Into<String>, threshold_ms: u64) -> Self {
+        Self::new(name, graph, SloObjective::Latency { threshold_ms }, 0.95)
+    }
+
+    /// `target` of runs succeed
+    pub fn success_rate(name: impl Into<String>, graph: impl Into<String>, target: f64) -> Self {
+        Self::new(name, graph, SloObjective::SuccessRate, target)
+    }
+
+    /// `target` of runs cost at most `max_usd`
+    pub fn cost_per_run(name: impl Into<String>, graph: impl Into<String>, max_usd: f64, target: f64) -> Self {
+        Self::new(name, graph, SloObjective::CostPerRun { max_usd }, target)
+    }
+
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn with_alerts(mut self, alerts: Vec<BurnRateAlert>) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Share of runs allowed to be bad
+    pub fn error_budget(&self) -> f64 {
+        1.0 - self.target
+    }
+
+    /// Longest window any of the SLO's figures look back over
+    fn horizon(&self) -> Duration {
+        self.alerts.iter().map(|alert| alert.long_window).fold(self.window, Duration::max)
+    }
+}
+
+/// One finished run of a graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSample {
+    pub graph: String,
+    pub finished_at: SystemTime,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    pub cost_usd: Option<f64>,
+}
+
+impl RunSample {
+    pub fn new(graph: impl Into<String>, duration_ms: u64, succeeded: bool) -> Self {
+        Self { graph: graph.into(), finished_at: SystemTime::now(), duration_ms, succeeded, cost_usd: None }
+    }
+
+    pub fn with_cost(mut self, cost_usd: f64) -> Self {
+        self.cost_usd = Some(cost_usd);
+        self
+    }
+
+    pub fn at(mut self, finished_at: SystemTime) -> Self {
+        self.finished_at = finished_at;
+        self
+    }
+}
+
+/// Burn rate over one window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BurnRate {
+    pub window: Duration,
+    /// Bad share of runs divided by the error budget; 1 spends the budget
+    /// exactly over the SLO window
+    pub rate: f64,
+    pub runs: usize,
+}
+
+/// Where an SLO stands
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SloReport {
+    pub slo: String,
+    pub graph: String,
+    pub objective: SloObjective,
+    pub target: f64,
+    /// Runs counted in the SLO window
+    pub runs: usize,
+    pub good: usize,
+    /// Share of good runs; 1 without runs
+    pub compliance: f64,
+    /// Share of the error budget left; negative once overspent
+    pub budget_remaining: f64,
+    pub budget_exhausted: bool,
+    /// Burn rate over each alert window, long windows first
+    pub burn_rates: Vec<BurnRate>,
+    /// Observed 95th percentile duration in the SLO window
+    pub p95_latency_ms: Option<u64>,
+    /// Observed mean cost of runs with a cost in the SLO window
+    pub mean_cost_usd: Option<f64>,
+}
+
+impl SloReport {
+    pub fn is_met(&self) -> bool {
+        self.compliance >= self.target
+    }
+}
+
+/// Runs of each graph and the SLOs they count towards
+#[derive(Debug, Default)]
+pub struct SloTracker {
+    slos: Vec<Slo>,
+    runs: HashMap<String, VecDeque<RunSample>>,
+    /// Alerts firing, by SLO name and alert key, so each fires once until it clears
+    firing: HashSet<(String, String)>,
+}
+
+impl SloTracker {
+    pub fn new(slos: Vec<Slo>) -> Self {
+        Self { slos, ..Self::default() }
+    }
+
+    /// Add an SLO, replacing one with the same name
+    pub fn define(&mut self, slo: Slo) {
+        self.slos.retain(|existing| existing.name != slo.name);
+        self.slos.push(slo);
+    }
+
+    pub fn slos(&self) -> &[Slo] {
+        &self.slos
+    }
+
+    /// Count a run, dropping runs older than every SLO of its graph looks at
+    ///
+    /// Runs of graphs without an SLO are ignored.
+    pub fn record(&mut self, run: RunSample) {
+        let horizon = self.slos.iter().filter(|slo| slo.graph == run.graph).map(Slo::horizon).max();
+        let Some(horizon) = horizon else {
+            return;
+        };
+        let runs = self.runs.entry(run.graph.clone()).or_default();
+        let cutoff = run.finished_at.checked_sub(horizon);
+        runs.push_back(run);
+        while runs.front().is_some_and(|oldest| Some(oldest.finished_at) < cutoff) {
+            runs.pop_front();
+        }
+    }
+
+    /// Reports for every SLO as of `now`
+    pub fn reports(&self, now: SystemTime) -> Vec<SloReport> {
+        self.slos.iter().map(|slo| self.report(slo, now)).collect()
+    }
+
+    fn runs_within<'a>(&'a self, graph: &str, now: SystemTime, window: Duration) -> impl Iterator<Item = &'a RunSample> {
+        let cutoff = now.checked_sub(window);
+        self.runs
+            .get(graph)
+            .into_iter()
+            .flatten()
+            .filter(move |run| run.finished_at <= now && Some(run.finished_at) >= cutoff)
+    }
+
+    /// Good and counted runs of `slo` in a window
+    fn count(&self, slo: &Slo, now: SystemTime, window: Duration) -> (usize, usize) {
+        self.runs_within(&slo.graph, now, window)
+            .filter_map(|run| slo.objective.is_good(run))
+            .fold((0, 0), |(good, runs), is_good| (good + is_good as usize, runs + 1))
+    }
+
+    fn burn_rate(&self, slo: &Slo, now: SystemTime, window: Duration) -> BurnRate {
+        let (good, runs) = self.count(slo, now, window);
+        let bad_share = if runs == 0 { 0.0 } else { (runs - good) as f64 / runs as f64 };
+        let rate = if slo.error_budget() > 0.0 {
+            bad_share / slo.error_budget()
+        } else if bad_share > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        };
+        BurnRate { window, rate, runs }
+    }
+
+    fn report(&self, slo: &Slo, now: SystemTime) -> SloReport {
+        let (good, runs) = self.count(slo, now, slo.window);
+        let compliance = if runs == 0 { 1.0 } else { good as f64 / runs as f64 };
+        let budget = slo.error_budget();
+        let budget_remaining = if budget > 0.0 {
+            1.0 - (1.0 - compliance) / budget
+        } else if compliance < 1.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        let mut windows: Vec<Duration> = slo.alerts.iter().flat_map(|alert| [alert.long_window, alert.short_window]).collect();
+        windows.sort_by(|a, b| b.cmp(a));
+        windows.dedup();
+        let burn_rates = windows.into_iter().map(|window| self.burn_rate(slo, now, window)).collect();
+
+        let mut durations: Vec<u64> = self.runs_within(&slo.graph, now, slo.window).map(|run| run.duration_ms).collect();
+        durations.sort_unstable();
+        let p95_latency_ms = (!durations.is_empty()).then(|| {
+            let rank = ((0.95 * durations.len() as f64).ceil() as usize).max(1);
+            durations[rank - 1]
+        });
+        let costs: Vec<f64> = self.runs_within(&slo.graph, now, slo.window).filter_map(|run| run.cost_usd).collect();
+        let mean_cost_usd = (!costs.is_empty()).then(|| costs.iter().sum::<f64>() / costs.len() as f64);
+
+        SloReport {
+            slo: slo.name.clone(),
+            graph: slo.graph.clone(),
+            objective: slo.objective.clone(),
+            target: slo.target,
+            runs,
+            good,
+            compliance,
+            budget_remaining,
+            budget_exhausted: budget_remaining <= 0.0,
+            burn_rates,
+            p95_latency_ms,
+            mean_cost_usd,
+        }
+    }
+
+    /// Alerts that started firing since the last evaluation
+    ///
+    /// An SLO alerts when its budget is exhausted and when it burns faster
+    /// than one of its burn-rate alerts allows. Each alert fires once and
+    /// again only after it has cleared.
+    pub fn evaluate(&mut self, now: SystemTime) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let mut firing = HashSet::new();
+        for slo in &self.slos {
+            let report = self.report(slo, now);
+            if report.budget_exhausted {
+                let key = (slo.name.clone(), "budget".to_string());
+                if !self.firing.contains(&key) {
+                    alerts.push(
+                        Alert::new(
+                            AlertSeverity::Critical,
+                            format!("SLO {} error budget exhausted", slo.name),
+                            format!("{} of {} runs of graph {} were good, below the {:.2}% target", report.good, report.runs, slo.graph, slo.target * 100.0),
+                            slo.graph.clone(),
+                        )
+                        .with_metric("slo_compliance".to_string(), report.compliance, slo.target)
+                        .with_metadata("slo".to_string(), slo.name.clone()),
+                    );
+                }
+                firing.insert(key);
+            }
+
+            for alert in &slo.alerts {
+                let long = self.burn_rate(slo, now, alert.long_window);
+                let short = self.burn_rate(slo, now, alert.short_window);
+                if long.rate < alert.threshold || short.rate < alert.threshold {
+                    continue;
+                }
+                let key = (slo.name.clone(), format!("burn:{}s", alert.long_window.as_secs()));
+                if !self.firing.contains(&key) {
+                    alerts.push(
+                        Alert::new(
+                            alert.severity,
+                            format!("SLO {} burning error budget", slo.name),
+                            format!(
+                                "Graph {} burns its error budget {:.1}x too fast over {}s ({} runs)",
+                                slo.graph,
+                                long.rate,
+                                alert.long_window.as_secs(),
+                                long.runs
+                            ),
+                            slo.graph.clone(),
+                        )
+                        .with_metric("slo_burn_rate".to_string(), long.rate, alert.threshold)
+                        .with_metadata("slo".to_string(), slo.name.clone())
+                        .with_metadata("window_secs".to_string(), alert.long_window.as_secs().to_string()),
+                    );
+                }
+                firing.insert(key);
+            }
+        }
+        self.firing = firing;
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compliance_burn_rate_and_alerts() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let slo = Slo::success_rate("bot-success", "bot", 0.99).with_window(Duration::from_secs(86400));
+        let mut tracker = SloTracker::new(vec![slo]);
+
+        // A healthy day: one failure in 200 runs, one every five minutes
+        for i in 0..200 {
+            let at = start + Duration::from_secs(i * 300);
+            tracker.record(RunSample::new("bot", 100, i != 3).at(at));
+        }
+        tracker.record(RunSample::new("other", 100, false).at(start));
+        let now = start + Duration::from_secs(199 * 300);
+        let report = &tracker.reports(now)[0];
+        assert_eq!((report.good, report.runs), (199, 200));
+        assert!(report.is_met());
+        assert!((report.budget_remaining - 0.5).abs() < 1e-9);
+        assert!(tracker.evaluate(now).is_empty());
+
+        // Then everything fails for ten minutes
+        for i in 1..=10 {
+            tracker.record(RunSample::new("bot", 100, false).at(now + Duration::from_secs(i * 60)));
+        }
+        let now = now + Duration::from_secs(600);
+        let report = &tracker.reports(now)[0];
+        assert!(report.budget_exhausted);
+        assert_eq!(report.burn_rates[0].window, Duration::from_secs(6 * 3600));
+
+        let alerts = tracker.evaluate(now);
+        let titles: Vec<_> = alerts.iter().map(|alert| alert.title.as_str()).collect();
+        assert_eq!(titles, vec![
+            "SLO bot-success error budget exhausted",
+            "SLO bot-success burning error budget",
+            "SLO bot-success burning error budget",
+        ]);
+        assert_eq!(alerts[1].severity, AlertSeverity::Critical);
+        // Already firing
+        assert!(tracker.evaluate(now).is_empty());
+    }
+}
@@ -112,6 +112,44 @@ pub struct TenantConfig {
     pub allowed_node_types: Option<Vec<String>>,
     /// Allowed tool categories for this tenant
     pub allowed_tool_categories: Option<Vec<String>>,
+    /// Hosts HTTP tools may reach for this tenant
+    #[serde(default)]
+    pub allowed_http_hosts: Option<Vec<String>>,
+    /// Tools this tenant may use, by name or pattern, on top of the allowed categories
+    #[serde(default)]
+    pub allowed_tools: Option<Vec<String>>,
+    /// LLM provider of the tenant's template agents and of calls that name none
+    #[serde(default)]
+    pub default_provider: Option<String>,
+    /// Model of the tenant's template agents, in place of the one their role names
+    #[serde(default)]
+    pub default_model: Option<String>,
+}
+
+impl TenantConfig {
+    /// `base` with the tenant's default provider and model in place of its own
+    ///
+    /// Build the tenant's `LLMManager` from it; template agents pick the
+    /// defaults up through `TemplateContext::with_tenant`.
+    pub fn llm_config(&self, mut base: crate::llm::LLMConfig) -> crate::llm::LLMConfig {
+        if let Some(provider) = &self.default_provider {
+            base.default_provider = provider.clone();
+        }
+        if let Some(model) = &self.default_model {
+            base.default_models.insert(base.default_provider.clone(), model.clone());
+        }
+        base
+    }
+
+    /// Tool policy derived from the allowed tool categories and tools
+    pub fn tool_policy(&self) -> crate::tools::ToolPolicy {
+        if self.allowed_tool_categories.is_none() && self.allowed_tools.is_none() {
+            return crate::tools::ToolPolicy::allow_all();
+        }
+        let categories = self.allowed_tool_categories.iter().flatten().map(|category| format!("#{}", category));
+        let tools = self.allowed_tools.iter().flatten().cloned();
+        categories.chain(tools).fold(crate::tools::ToolPolicy::deny_all(), |policy, pattern| policy.with_allow(pattern))
+    }
 }
 
 impl Default for TenantConfig {
@@ -124,12 +162,93 @@ impl Default for TenantConfig {
             custom_config: HashMap::new(),
             allowed_node_types: None, // None means all allowed
             allowed_tool_categories: None, // None means all allowed
+            allowed_http_hosts: None, // None means any public host
+            allowed_tools: None,
+            default_provider: None,
+            default_model: None,
+        }
+    }
+}
+
+/// Everything a tenant is provisioned with, as exported and imported
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TenantSpec {
+    /// Unique tenant identifier; letters, digits, `-` and `_`
+    pub id: String,
+    /// Human-readable tenant name
+    pub name: String,
+    /// Tenant configuration
+    #[serde(default)]
+    pub config: TenantConfig,
+    /// Quotas
+    #[serde(default)]
+    pub resource_limits: super::resources::ResourceLimits,
+    /// Tenant metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl TenantSpec {
+    /// A tenant with the default configuration and quotas
+    pub fn new(id: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            config: TenantConfig::default(),
+            resource_limits: super::resources::ResourceLimits::default(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    /// Set the quotas
+    pub fn with_resource_limits(mut self, limits: super::resources::ResourceLimits) -> Self {
+        self.resource_limits = limits;
+        self
+    }
+    
+    /// Use `provider`, and `model` if given, for agents that name neither
+    pub fn with_default_provider(mut self, provider: impl Into<String>, model: Option<String>) -> Self {
+        self.config.default_provider = Some(provider.into());
+        self.config.default_model = model;
+        self
+    }
+    
+    /// Only allow these tools, by name or pattern, and the allowed categories
+    pub fn with_allowed_tools<I, T>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.config.allowed_tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+    
+    /// Check the ID is safe to use in storage keys
+    pub fn validate(&self) -> Result<(), TenantError> {
+        crate::state::tenant::TenantScope::new(self.id.clone())
+            .map(|_| ())
+            .map_err(|e| TenantError::ConfigurationError { message: e.to_string() })?;
+        if self.name.trim().is_empty() {
+            return Err(TenantError::ConfigurationError { message: format!("Tenant '{}' needs a name", self.id) });
+        }
+        Ok(())
+    }
+}
+
+impl From<&Tenant> for TenantSpec {
+    fn from(tenant: &Tenant) -> Self {
+        Self {
+            id: tenant.id.clone(),
+            name: tenant.name.clone(),
+            config: tenant.config.clone(),
+            resource_limits: tenant.resource_limits.clone(),
+            metadata: tenant.metadata.clone(),
         }
     }
 }
 
 /// Global tenancy configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct TenancyConfig {
     /// Enable multi-tenancy
     pub enabled: bool,
@@ -156,7 +275,7 @@ impl Default for TenancyConfig {
 }
 
 /// Tenant isolation levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum IsolationLevel {
     /// No isolation (single tenant mode)
     None,
@@ -169,7 +288,7 @@ pub enum IsolationLevel {
 }
 
 /// Tenant storage configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct TenantStorageConfig {
     /// Storage backend type
     pub backend: StorageBackend,
@@ -190,7 +309,7 @@ impl Default for TenantStorageConfig {
 }
 
 /// Storage backend types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum StorageBackend {
     /// In-memory storage (for testing)
     Memory,
@@ -232,6 +351,12 @@ impl TenantContext {
         &self.tenant.id
     }
     
+    /// Scope confining checkpointers, stores and memory backends to this tenant's data
+    pub fn storage_scope(&self) -> Result<crate::state::tenant::TenantScope, TenantError> {
+        crate::state::tenant::TenantScope::new(self.tenant.id.clone())
+            .map_err(|e| TenantError::ConfigurationError { message: e.to_string() })
+    }
+    
     /// Check if tenant can execute
     pub fn can_execute(&self) -> bool {
         self.tenant.is_active()
@@ -288,16 +413,24 @@ impl TenantManager {
         })
     }
     
-    /// Create a new tenant
+    /// Create a new tenant with the default configuration and quotas
     pub async fn create_tenant(&self, id: String, name: String) -> Result<Tenant, TenantError> {
+        self.provision_tenant(TenantSpec::new(id, name)).await
+    }
+    
+    /// Create a new tenant with its quotas, default provider and allowed tools
+    pub async fn provision_tenant(&self, spec: TenantSpec) -> Result<Tenant, TenantError> {
         // Check if tenancy is enabled
         if !self.config.enabled {
             return Err(TenantError::TenancyDisabled);
         }
+        spec.validate()?;
+        
+        let mut tenants = self.tenants.write().unwrap();
         
         // Check tenant limits
         if let Some(max_tenants) = self.config.max_tenants {
-            let current_count = self.tenants.read().unwrap().len() as u32;
+            let current_count = tenants.len() as u32;
             if current_count >= max_tenants {
                 return Err(TenantError::TenantLimitExceeded { 
                     limit: max_tenants,
@@ -306,20 +439,22 @@ impl TenantManager {
             }
         }
         
-        let mut tenants = self.tenants.write().unwrap();
-        
         // Check if tenant already exists
-        if tenants.contains_key(&id) {
-            return Err(TenantError::TenantAlreadyExists { tenant_id: id });
+        if tenants.contains_key(&spec.id) {
+            return Err(TenantError::TenantAlreadyExists { tenant_id: spec.id });
         }
         
-        let tenant = Tenant::new(id.clone(), name);
-        tenants.insert(id, tenant.clone());
+        let mut tenant = Tenant::new(spec.id.clone(), spec.name);
+        tenant.config = spec.config;
+        tenant.resource_limits = spec.resource_limits;
+        tenant.metadata = spec.metadata;
+        tenants.insert(spec.id, tenant.clone());
         
         // Update statistics
         let mut stats = self.stats.write().unwrap();
         stats.total_tenants += 1;
         stats.active_tenants += 1;
+        stats.last_updated = SystemTime::now();
         
         Ok(tenant)
     }
@@ -360,6 +495,100 @@ impl TenantManager {
         }
     }
     
+    /// Suspend a tenant; its executions are refused until it is reactivated
+    pub async fn suspend_tenant(&self, tenant_id: &str, reason: String) -> Result<Tenant, TenantError> {
+        self.change_status(tenant_id, |tenant| {
+            tenant.suspend(reason);
+            Ok(())
+        })
+    }
+    
+    /// Reactivate a suspended tenant
+    pub async fn reactivate_tenant(&self, tenant_id: &str) -> Result<Tenant, TenantError> {
+        self.change_status(tenant_id, |tenant| {
+            if tenant.status == TenantStatus::Archived {
+                return Err(TenantError::TenantInactive {
+                    tenant_id: tenant.id.clone(),
+                    status: tenant.status,
+                });
+            }
+            tenant.activate();
+            Ok(())
+        })
+    }
+    
+    /// Change a tenant's status and keep the statistics in step
+    fn change_status<F>(&self, tenant_id: &str, change: F) -> Result<Tenant, TenantError>
+    where
+        F: FnOnce(&mut Tenant) -> Result<(), TenantError>,
+    {
+        let mut tenants = self.tenants.write().unwrap();
+        let tenant = tenants.get_mut(tenant_id).ok_or_else(|| TenantError::TenantNotFound {
+            tenant_id: tenant_id.to_string(),
+        })?;
+        let before = tenant.status;
+        change(tenant)?;
+        
+        let mut stats = self.stats.write().unwrap();
+        for (status, delta) in [(before, -1i64), (tenant.status, 1)] {
+            let counter = match status {
+                TenantStatus::Active => &mut stats.active_tenants,
+                TenantStatus::Suspended => &mut stats.suspended_tenants,
+                TenantStatus::Archived => &mut stats.archived_tenants,
+                TenantStatus::Provisioning => continue,
+            };
+            *counter = counter.saturating_add_signed(delta);
+        }
+        stats.last_updated = SystemTime::now();
+        Ok(tenant.clone())
+    }
+    
+    /// Configuration a tenant was provisioned with, to provision it elsewhere
+    pub async fn export_tenant(&self, tenant_id: &str) -> Result<TenantSpec, TenantError> {
+        self.get_tenant(tenant_id).await.map(|tenant| TenantSpec::from(&tenant))
+    }
+    
+    /// Load tenants saved with [`save_to_file`](Self::save_to_file); a missing file holds none
+    pub fn from_file(config: TenancyConfig, path: &std::path::Path) -> Result<Self, TenantError> {
+        let manager = Self::new(config)?;
+        if !path.exists() {
+            return Ok(manager);
+        }
+        let storage_error = |e: &dyn std::fmt::Display| TenantError::StorageError {
+            message: format!("{}: {}", path.display(), e),
+        };
+        let json = std::fs::read_to_string(path).map_err(|e| storage_error(&e))?;
+        let loaded: Vec<Tenant> = serde_json::from_str(&json).map_err(|e| storage_error(&e))?;
+        
+        let mut stats = TenantStats::default();
+        for tenant in &loaded {
+            stats.total_tenants += 1;
+            match tenant.status {
+                TenantStatus::Active => stats.active_tenants += 1,
+                TenantStatus::Suspended => stats.suspended_tenants += 1,
+                TenantStatus::Archived => stats.archived_tenants += 1,
+                TenantStatus::Provisioning => {}
+            }
+        }
+        *manager.tenants.write().unwrap() = loaded.into_iter().map(|tenant| (tenant.id.clone(), tenant)).collect();
+        *manager.stats.write().unwrap() = stats;
+        Ok(manager)
+    }
+    
+    /// Save every tenant as JSON, creating the parent directory
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<(), TenantError> {
+        let storage_error = |e: &dyn std::fmt::Display| TenantError::StorageError {
+            message: format!("{}: {}", path.display(), e),
+        };
+        let mut tenants: Vec<Tenant> = self.tenants.read().unwrap().values().cloned().collect();
+        tenants.sort_by(|a, b| a.id.cmp(&b.id));
+        let json = serde_json::to_string_pretty(&tenants).map_err(|e| storage_error(&e))?;
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| storage_error(&e))?;
+        }
+        std::fs::write(path, json).map_err(|e| storage_error(&e))
+    }
+    
     /// List all tenants
     pub async fn list_tenants(&self) -> Result<Vec<Tenant>, TenantError> {
         let tenants = self.tenants.read().unwrap();
@@ -395,6 +624,9 @@ impl TenantManager {
     }
 }
 
+/// Where the CLI keeps provisioned tenants
+pub const DEFAULT_TENANTS_FILE: &str = ".agentgraph/tenants.json";
+
 /// Tenant statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TenantStats {
@@ -481,6 +713,18 @@ mod tests {
         assert!(tenant.is_active());
     }
 
+    #[test]
+    fn test_tenant_tool_policy() {
+        let mut config = TenantConfig::default();
+        let tool = crate::tools::ToolMetadata::new("http_get", "HTTP GET", "GET requests")
+            .with_namespace("web")
+            .with_tag("network");
+        assert!(config.tool_policy().allows(&tool));
+
+        config.allowed_tool_categories = Some(vec!["file".to_string()]);
+        assert!(!config.tool_policy().allows(&tool));
+    }
+
     #[tokio::test]
     async fn test_tenant_manager() {
         let config = TenancyConfig::default();
@@ -521,6 +765,43 @@ mod tests {
         assert!(matches!(result, Err(TenantError::TenantLimitExceeded { .. })));
     }
 
+    #[tokio::test]
+    async fn test_tenant_provisioning_lifecycle() {
+        let manager = TenantManager::new(TenancyConfig::default()).unwrap();
+        let mut limits = crate::enterprise::resources::ResourceLimits::default();
+        limits.max_executions = Some(50);
+        let spec = TenantSpec::new("acme", "ACME Corporation")
+            .with_resource_limits(limits)
+            .with_default_provider("anthropic", Some("claude-sonnet".to_string()))
+            .with_allowed_tools(["http_get"]);
+        
+        let tenant = manager.provision_tenant(spec.clone()).await.unwrap();
+        assert_eq!(tenant.resource_limits.max_executions, Some(50));
+        assert_eq!(tenant.config.default_provider.as_deref(), Some("anthropic"));
+        let llm = tenant.config.llm_config(crate::llm::LLMConfig::default());
+        assert_eq!((llm.default_provider.as_str(), llm.default_models["anthropic"].as_str()), ("anthropic", "claude-sonnet"));
+        let http = crate::tools::ToolMetadata::new("http_get", "HTTP GET", "GET requests");
+        let shell = crate::tools::ToolMetadata::new("shell", "Shell", "Run commands");
+        assert!(tenant.config.tool_policy().allows(&http));
+        assert!(!tenant.config.tool_policy().allows(&shell));
+        assert!(manager.provision_tenant(TenantSpec::new("../etc", "Bad")).await.is_err());
+        
+        let suspended = manager.suspend_tenant("acme", "Unpaid invoice".to_string()).await.unwrap();
+        assert_eq!(suspended.status, TenantStatus::Suspended);
+        assert_eq!((manager.get_stats().active_tenants, manager.get_stats().suspended_tenants), (0, 1));
+        manager.reactivate_tenant("acme").await.unwrap();
+        assert!(manager.get_tenant("acme").await.unwrap().is_active());
+        assert_eq!(manager.export_tenant("acme").await.unwrap(), spec);
+        
+        // Tenants survive a round trip through the file the CLI uses
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("tenants.json");
+        manager.save_to_file(&path).unwrap();
+        let reloaded = TenantManager::from_file(TenancyConfig::default(), &path).unwrap();
+        assert_eq!(reloaded.export_tenant("acme").await.unwrap(), spec);
+        assert_eq!(reloaded.get_stats().active_tenants, 1);
+    }
+
     #[test]
     fn test_tenant_config_serialization() {
         let config = TenantConfig::default();
@@ -71,6 +71,32 @@ pub enum GraphError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    /// Execution suspended while a node waits on an external operation
+    #[error("Execution suspended at node '{node_id}' waiting on operation {operation_id}")]
+    Suspended {
+        /// Node that suspended
+        node_id: String,
+        /// Operation the node is waiting on
+        operation_id: String,
+        /// Checkpoint saved when the branch suspended
+        checkpoint_id: Option<String>,
+    },
+
+    /// Execution cancelled from outside, e.g. by an operator
+    #[error("Execution cancelled: {0}")]
+    Cancelled(String),
+
+    /// Execution refused because a session or user is over a rate limit
+    #[error("Rate limit '{limit}' exceeded for '{key}'; retry after {retry_after_ms} ms")]
+    Throttled {
+        /// Limit that was hit, e.g. `messages_per_minute`
+        limit: String,
+        /// Session or user the limit applies to
+        key: String,
+        /// Milliseconds until an execution would be admitted again
+        retry_after_ms: u64,
+    },
+
     /// Generic internal errors
     #[error("Internal error: {0}")]
     Internal(String),
@@ -115,6 +141,42 @@ impl GraphError {
         Self::ValidationError(message.into())
     }
 
+    /// Create a new suspension for a node waiting on an operation
+    pub fn suspended<S: Into<String>>(node_id: S, operation_id: S) -> Self {
+        Self::Suspended {
+            node_id: node_id.into(),
+            operation_id: operation_id.into(),
+            checkpoint_id: None,
+        }
+    }
+
+    /// Create a new throttle error for `key` over `limit`
+    pub fn throttled<S: Into<String>>(limit: S, key: S, retry_after: std::time::Duration) -> Self {
+        Self::Throttled {
+            limit: limit.into(),
+            key: key.into(),
+            retry_after_ms: retry_after.as_millis().min(u64::MAX as u128) as u64,
+        }
+    }
+
+    /// How long to wait before trying again, if this error is a throttle
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            GraphError::Throttled { retry_after_ms, .. } => Some(std::time::Duration::from_millis(*retry_after_ms)),
+            _ => None,
+        }
+    }
+
+    /// Check if this error is a suspension rather than a failure
+    pub fn is_suspended(&self) -> bool {
+        matches!(self, GraphError::Suspended { .. })
+    }
+
+    /// Check if this error is a cancellation rather than a failure
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, GraphError::Cancelled(_))
+    }
+
     /// Check if this error is recoverable
     pub fn is_recoverable(&self) -> bool {
         matches!(
@@ -142,6 +204,9 @@ impl GraphError {
             GraphError::ResourceError(_) => "resource",
             GraphError::ExternalServiceError(_) => "external_service",
             GraphError::ValidationError(_) => "validation",
+            GraphError::Suspended { .. } => "suspended",
+            GraphError::Cancelled(_) => "cancelled",
+            GraphError::Throttled { .. } => "throttled",
             GraphError::Internal(_) => "internal",
         }
     }
@@ -173,6 +238,8 @@ mod tests {
             GraphError::graph_structure("test"),
             GraphError::state_error("test"),
             GraphError::timeout(30),
+            GraphError::suspended("render", "op-1"),
+            GraphError::throttled("messages_per_minute", "session-1", std::time::Duration::from_secs(5)),
         ];
 
         for error in errors {
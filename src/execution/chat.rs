@@ -0,0 +1,429 @@
+// Slack and Discord notifications for execution events
+// Posts execution summaries and failures to chat channels chosen by per-graph rules
+
+#![allow(missing_docs)]
+
+use super::webhooks::{WebhookEvent, WebhookPayload};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const GREEN: u32 = 0x2eb67d;
+const RED: u32 = 0xe01e5a;
+const AMBER: u32 = 0xecb22e;
+const GREY: u32 = 0x868686;
+
+/// Chat service a channel lives on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatPlatform {
+    Slack,
+    Discord,
+}
+
+/// A channel messages are posted to through its incoming webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatChannel {
+    /// Name rules refer to the channel by
+    pub name: String,
+    pub platform: ChatPlatform,
+    /// Incoming webhook URL of the channel
+    pub webhook_url: String,
+}
+
+impl ChatChannel {
+    pub fn slack(name: impl Into<String>, webhook_url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            platform: ChatPlatform::Slack,
+            webhook_url: webhook_url.into(),
+        }
+    }
+
+    pub fn discord(name: impl Into<String>, webhook_url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            platform: ChatPlatform::Discord,
+            webhook_url: webhook_url.into(),
+        }
+    }
+}
+
+/// Which channels hear about which graphs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationRule {
+    /// Graph name; `*` matches every graph and a trailing `*` matches a prefix
+    pub graph: String,
+    /// Events to post
+    pub events: Vec<WebhookEvent>,
+    /// Names of the channels to post to
+    pub channels: Vec<String>,
+}
+
+impl Default for NotificationRule {
+    fn default() -> Self {
+        Self {
+            graph: "*".to_string(),
+            events: vec![WebhookEvent::ExecutionCompleted, WebhookEvent::ExecutionFailed],
+            channels: Vec::new(),
+        }
+    }
+}
+
+impl NotificationRule {
+    /// Post summaries and failures of the matching graphs to `channels`
+    pub fn new<I, C>(graph: impl Into<String>, channels: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<String>,
+    {
+        Self {
+            graph: graph.into(),
+            channels: channels.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_events(mut self, events: impl IntoIterator<Item = WebhookEvent>) -> Self {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    pub fn matches(&self, graph: &str) -> bool {
+        match self.graph.strip_suffix('*') {
+            Some(prefix) => graph.starts_with(prefix),
+            None => self.graph == graph,
+        }
+    }
+}
+
+/// Chat notification configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatNotifierConfig {
+    pub channels: Vec<ChatChannel>,
+    /// Checked in order; the first rule matching a graph decides where its events go
+    pub rules: Vec<NotificationRule>,
+    /// Failure messages per graph and channel allowed in one window; the rest
+    /// are counted and mentioned in the next message that gets through
+    pub max_failures_per_window: u32,
+    pub failure_window_secs: u64,
+    /// Time to wait for the chat service to answer
+    pub timeout_ms: u64,
+}
+
+impl Default for ChatNotifierConfig {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            rules: Vec::new(),
+            max_failures_per_window: 5,
+            failure_window_secs: 600,
+            timeout_ms: 10_000,
+        }
+    }
+}
+
+impl ChatNotifierConfig {
+    pub fn with_channel(mut self, channel: ChatChannel) -> Self {
+        self.channels.push(channel);
+        self
+    }
+
+    pub fn with_rule(mut self, rule: NotificationRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_failure_limit(mut self, max_failures: u32, window: Duration) -> Self {
+        self.max_failures_per_window = max_failures;
+        self.failure_window_secs = window.as_secs();
+        self
+    }
+}
+
+/// Outcome of posting one event to one channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatDelivery {
+    pub channel: String,
+    /// Whether the message was held back by the failure rate limit
+    pub suppressed: bool,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct FailureWindow {
+    started: Instant,
+    sent: u32,
+    suppressed: u32,
+}
+
+/// Posts execution events to Slack and Discord channels
+///
+/// Takes the payloads sent to webhooks, so it reports the same events with
+/// the same details, rendered as chat messages.
+#[derive(Debug)]
+pub struct ChatNotifier {
+    config: ChatNotifierConfig,
+    client: reqwest::Client,
+    /// Failure rate limit state, by graph and channel
+    windows: Mutex<HashMap<(String, String), FailureWindow>>,
+}
+
+impl ChatNotifier {
+    pub fn new(config: ChatNotifierConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ChatNotifierConfig {
+        &self.config
+    }
+
+    /// Post in the background, so executions never wait on chat services
+    pub fn notify(self: &Arc<Self>, payload: WebhookPayload) {
+        if self.channels_for(&payload).is_empty() {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            for delivery in notifier.deliver(&payload).await {
+                if let Some(error) = &delivery.error {
+                    tracing::warn!(channel = %delivery.channel, error = %error, "Chat notification failed");
+                }
+            }
+        });
+    }
+
+    /// Post an event to the channels its graph is routed to and wait for the outcome
+    pub async fn deliver(&self, payload: &WebhookPayload) -> Vec<ChatDelivery> {
+        let graph = payload.graph.as_deref().unwrap_or_default();
+        let mut deliveries = Vec::new();
+        for channel in self.channels_for(payload) {
+            let mut delivery = ChatDelivery {
+                channel: channel.name.clone(),
+                suppressed: false,
+                delivered: false,
+                error: None,
+            };
+            let suppressed_before = if payload.event == WebhookEvent::ExecutionFailed {
+                match self.admit_failure(graph, &channel.name) {
+                    Some(count) => count,
+                    None => {
+                        delivery.suppressed = true;
+                        deliveries.push(delivery);
+                        continue;
+                    }
+                }
+            } else {
+                0
+            };
+
+            let message = render(channel.platform, payload, suppressed_before);
+            let response = self.client
+                .post(&channel.webhook_url)
+                .timeout(Duration::from_millis(self.config.timeout_ms))
+                .json(&message)
+                .send()
+                .await;
+            match response {
+                Ok(response) if response.status().is_success() => delivery.delivered = true,
+                Ok(response) => delivery.error = Some(format!("HTTP {}", response.status())),
+                Err(error) => delivery.error = Some(error.to_string()),
+            }
+            deliveries.push(delivery);
+        }
+        deliveries
+    }
+
+    /// Channels the first rule matching the payload's graph routes its event to
+    fn channels_for(&self, payload: &WebhookPayload) -> Vec<&ChatChannel> {
+        let graph = payload.graph.as_deref().unwrap_or_default();
+        let Some(rule) = self.config.rules.iter().find(|rule| rule.matches(graph)) else {
+            return Vec::new();
+        };
+        if !rule.events.contains(&payload.event) {
+            return Vec::new();
+        }
+        rule.channels
+            .iter()
+            .filter_map(|name| self.config.channels.iter().find(|channel| &channel.name == name))
+            .collect()
+    }
+
+    /// Let a failure message through, returning how many were held back since
+    /// the last one, or `None` if this one is held back too
+    fn admit_failure(&self, graph: &str, channel: &str) -> Option<u32> {
+        let window_length = Duration::from_secs(self.config.failure_window_secs);
+        let mut windows = self.windows.lock();
+        let window = windows
+            .entry((graph.to_string(), channel.to_string()))
+            .or_insert_with(|| FailureWindow { started: Instant::now(), sent: 0, suppressed: 0 });
+        if window.started.elapsed() >= window_length {
+            window.started = Instant::now();
+            window.sent = 0;
+        }
+        if window.sent >= self.config.max_failures_per_window {
+            window.suppressed += 1;
+            return None;
+        }
+        window.sent += 1;
+        Some(std::mem::take(&mut window.suppressed))
+    }
+}
+
+/// Chat message for an event, in the platform's incoming webhook format
+pub fn render(platform: ChatPlatform, payload: &WebhookPayload, suppressed_before: u32) -> serde_json::Value {
+    let graph = payload.graph.as_deref().unwrap_or("graph");
+    let data = &payload.data;
+    let (headline, color) = match payload.event {
+        WebhookEvent::ExecutionStarted => (format!("Execution of {} started", graph), GREY),
+        WebhookEvent::ExecutionCompleted if data["status"] == "partial_success" => {
+            (format!("Execution of {} partially succeeded", graph), AMBER)
+        }
+        WebhookEvent::ExecutionCompleted => (format!("Execution of {} completed", graph), GREEN),
+        WebhookEvent::ExecutionFailed => (format!("Execution of {} failed", graph), RED),
+        WebhookEvent::ExecutionPaused => (format!("Execution of {} paused", graph), AMBER),
+        WebhookEvent::HumanInteractionRequested => (format!("{} is waiting for a human", graph), AMBER),
+    };
+
+    let mut fields = Vec::new();
+    if let Some(execution_id) = &payload.execution_id {
+        fields.push(("Execution", execution_id.clone(), true));
+    }
+    if let Some(duration_ms) = data["duration_ms"].as_i64() {
+        fields.push(("Duration", format_duration(duration_ms), true));
+    }
+    if let Some(cost) = data["cost_usd"].as_f64() {
+        fields.push(("Cost", format!("${:.4}", cost), true));
+    }
+    if let Some(attempts) = data["attempts"].as_u64().filter(|&attempts| attempts > 1) {
+        fields.push(("Attempts", attempts.to_string(), true));
+    }
+    if let Some(node_id) = data["node_id"].as_str() {
+        fields.push(("Node", node_id.to_string(), true));
+    }
+    if let Some(error) = data["errors"].get(0).and_then(|error| error.as_str()) {
+        fields.push(("Error", truncate(error, 1000), false));
+    }
+    if let Some(prompt) = data["prompt"].as_str() {
+        fields.push(("Prompt", truncate(prompt, 1000), false));
+    }
+    if suppressed_before > 0 {
+        fields.push(("Suppressed", format!("{} earlier failures not posted", suppressed_before), false));
+    }
+
+    match platform {
+        ChatPlatform::Slack => serde_json::json!({
+            "text": headline,
+            "attachments": [{
+                "color": format!("#{:06x}", color),
+                "fields": fields.iter().map(|(title, value, short)| serde_json::json!({
+                    "title": title,
+                    "value": value,
+                    "short": short,
+                })).collect::<Vec<_>>(),
+                "ts": payload.timestamp.timestamp(),
+            }],
+        }),
+        ChatPlatform::Discord => serde_json::json!({
+            "embeds": [{
+                "title": headline,
+                "color": color,
+                "fields": fields.iter().map(|(name, value, inline)| serde_json::json!({
+                    "name": name,
+                    "value": value,
+                    "inline": inline,
+                })).collect::<Vec<_>>(),
+                "timestamp": payload.timestamp.to_rfc3339(),
+            }],
+        }),
+    }
+}
+
+fn format_duration(millis: i64) -> String {
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if millis < 60_000 {
+        format!("{:.1}s", millis as f64 / 1000.0)
+    } else {
+        format!("{}m {}s", millis / 60_000, (millis % 60_000) / 1000)
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    #[tokio::test]
+    async fn test_failures_are_routed_and_rate_limited() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let route = warp::post()
+            .and(warp::path::param::<String>())
+            .and(warp::body::json())
+            .map({
+                let received = received.clone();
+                move |channel: String, body: serde_json::Value| {
+                    received.lock().push((channel, body));
+                    warp::reply()
+                }
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let config = ChatNotifierConfig::default()
+            .with_channel(ChatChannel::slack("payments", format!("http://{}/payments", address)))
+            .with_channel(ChatChannel::discord("ops", format!("http://{}/ops", address)))
+            .with_rule(NotificationRule::new("billing-*", ["payments", "ops"]))
+            .with_rule(NotificationRule::new("*", ["ops"]).with_events([WebhookEvent::ExecutionFailed]))
+            .with_failure_limit(2, Duration::from_secs(3600));
+        let notifier = ChatNotifier::new(config);
+
+        let failed = |graph: &str| {
+            WebhookPayload::new(WebhookEvent::ExecutionFailed, serde_json::json!({
+                "errors": ["Node error: card declined"],
+                "duration_ms": 1500,
+                "cost_usd": 0.0123,
+            }))
+            .with_execution("exec-1", graph)
+        };
+        for _ in 0..3 {
+            notifier.deliver(&failed("billing-invoices")).await;
+        }
+        let deliveries = notifier.deliver(&failed("billing-invoices")).await;
+        assert!(deliveries.iter().all(|delivery| delivery.suppressed));
+
+        // Summaries only go where a rule asks for them
+        let completed = WebhookPayload::new(WebhookEvent::ExecutionCompleted, serde_json::json!({"status": "succeeded"}))
+            .with_execution("exec-2", "search");
+        assert!(notifier.deliver(&completed).await.is_empty());
+
+        let received = received.lock();
+        assert_eq!(received.iter().filter(|(channel, _)| channel == "payments").count(), 2);
+        assert_eq!(received.iter().filter(|(channel, _)| channel == "ops").count(), 2);
+
+        let (_, slack) = received.iter().find(|(channel, _)| channel == "payments").unwrap();
+        assert_eq!(slack["text"], "Execution of billing-invoices failed");
+        let fields = slack["attachments"][0]["fields"].as_array().unwrap();
+        assert!(fields.iter().any(|field| field["title"] == "Cost" && field["value"] == "$0.0123"));
+        assert!(fields.iter().any(|field| field["title"] == "Duration" && field["value"] == "1.5s"));
+        let (_, discord) = received.iter().find(|(channel, _)| channel == "ops").unwrap();
+        assert_eq!(discord["embeds"][0]["color"], RED);
+    }
+}
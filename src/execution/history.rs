@@ -0,0 +1,258 @@
+// Node execution history levels and content-addressed state capture
+// Records hold diffs, or hashes into a shared deduplicating pool, instead of full state copies
+
+#![allow(missing_docs)]
+
+use super::{ExecutionState, NodeExecution, NodeExecutionStatus};
+use crate::llm::LLMUsage;
+use crate::sha256_hex;
+use crate::visualization::state_inspector::{diff, FieldChange};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How much of each node execution the history keeps
+///
+/// By default only the fields each node changed are kept. Production runs can
+/// keep memory smaller still with `metadata` or `none`, while debugging runs
+/// capture every state with `references` or `full`. The state pool behind
+/// `references` lives in memory for the whole execution and holds each
+/// distinct state in full, so it saves little over `full` when every node
+/// changes the state. Totals (node counts and LLM usage) are kept at every
+/// level.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryLevel {
+    /// No per-node records
+    None,
+    /// Status, timing, retries and metadata, without states
+    Metadata,
+    /// The fields each node changed in its input state
+    #[default]
+    Diffs,
+    /// A hash of each state in the execution's state pool
+    References,
+    /// A full copy of each state in the record
+    Full,
+}
+
+impl HistoryLevel {
+    /// Check if node execution records are kept at all
+    pub fn keeps_records(&self) -> bool {
+        *self != HistoryLevel::None
+    }
+}
+
+/// Content hash of a state held in a [`StatePool`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StateRef {
+    /// SHA-256 of the state's JSON, hex encoded
+    pub hash: String,
+    /// Size of the state's JSON in bytes
+    pub size_bytes: u64,
+}
+
+/// A state as recorded in a node execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedState {
+    /// Reference into the execution's state pool
+    Ref(StateRef),
+    /// Full copy of the state
+    Full(ExecutionState),
+    /// Fields the node changed in its input state
+    Diff(Vec<FieldChange>),
+    /// Not captured at the history level the execution ran with
+    Omitted,
+}
+
+impl RecordedState {
+    /// The recorded state, looked up in `pool` if it is a reference
+    ///
+    /// Returns `None` for a reference the pool does not hold, e.g. one read
+    /// back from a serialized history, and for states recorded as a diff or
+    /// not at all.
+    pub fn resolve(&self, pool: &StatePool) -> Option<ExecutionState> {
+        match self {
+            RecordedState::Ref(state_ref) => pool.get(state_ref).map(|state| (*state).clone()),
+            RecordedState::Full(state) => Some(state.clone()),
+            RecordedState::Diff(_) | RecordedState::Omitted => None,
+        }
+    }
+
+    /// The reference, if the state was recorded as one
+    pub fn state_ref(&self) -> Option<&StateRef> {
+        match self {
+            RecordedState::Ref(state_ref) => Some(state_ref),
+            _ => None,
+        }
+    }
+
+    /// The changed fields, if the state was recorded as a diff
+    pub fn changes(&self) -> Option<&[FieldChange]> {
+        match self {
+            RecordedState::Diff(changes) => Some(changes),
+            _ => None,
+        }
+    }
+}
+
+impl From<ExecutionState> for RecordedState {
+    fn from(state: ExecutionState) -> Self {
+        RecordedState::Full(state)
+    }
+}
+
+impl From<StateRef> for RecordedState {
+    fn from(state_ref: StateRef) -> Self {
+        RecordedState::Ref(state_ref)
+    }
+}
+
+/// Deduplicating store of the states an execution's nodes read and wrote
+///
+/// Each distinct state is kept once however many records refer to it, so a
+/// node's output and the next node's input, or a state a node left unchanged,
+/// cost one entry. Cloning shares the pool.
+#[derive(Debug, Clone, Default)]
+pub struct StatePool {
+    states: Arc<Mutex<HashMap<String, Arc<ExecutionState>>>>,
+}
+
+impl StatePool {
+    /// An empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `state` to the pool unless it already holds it, and return its reference
+    pub fn intern(&self, state: &ExecutionState) -> StateRef {
+        let bytes = serde_json::to_vec(state).unwrap_or_default();
+        let state_ref = StateRef {
+            hash: sha256_hex(&bytes),
+            size_bytes: bytes.len() as u64,
+        };
+        self.states
+            .lock()
+            .entry(state_ref.hash.clone())
+            .or_insert_with(|| Arc::new(state.clone()));
+        state_ref
+    }
+
+    /// Record a node's input state the way `level` asks for
+    pub fn record(&self, state: &ExecutionState, level: HistoryLevel) -> RecordedState {
+        match level {
+            HistoryLevel::References => RecordedState::Ref(self.intern(state)),
+            HistoryLevel::Full => RecordedState::Full(state.clone()),
+            HistoryLevel::None | HistoryLevel::Metadata | HistoryLevel::Diffs => RecordedState::Omitted,
+        }
+    }
+
+    /// Record the state a node produced from `input` the way `level` asks for
+    pub fn record_output(&self, input: &ExecutionState, output: &ExecutionState, level: HistoryLevel) -> RecordedState {
+        match level {
+            HistoryLevel::Diffs => RecordedState::Diff(diff(input, output)),
+            level => self.record(output, level),
+        }
+    }
+
+    /// The state `state_ref` refers to
+    pub fn get(&self, state_ref: &StateRef) -> Option<Arc<ExecutionState>> {
+        self.states.lock().get(&state_ref.hash).cloned()
+    }
+
+    /// Number of distinct states held
+    pub fn len(&self) -> usize {
+        self.states.lock().len()
+    }
+
+    /// Check if the pool holds no states
+    pub fn is_empty(&self) -> bool {
+        self.states.lock().is_empty()
+    }
+
+    /// Drop every state, e.g. once an execution's history is no longer inspected
+    pub fn clear(&self) {
+        self.states.lock().clear();
+    }
+}
+
+/// Counts kept for every node execution, whatever the history level
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryTotals {
+    /// Node executions finished
+    pub executions: usize,
+    /// Node executions that completed
+    pub successful: usize,
+    /// Node executions that failed
+    pub failed: usize,
+    /// LLM usage reported by the nodes
+    pub llm_usage: LLMUsage,
+}
+
+impl HistoryTotals {
+    /// Count a finished node execution
+    pub fn add(&mut self, execution: &NodeExecution) {
+        self.executions += 1;
+        match execution.status {
+            NodeExecutionStatus::Completed => self.successful += 1,
+            NodeExecutionStatus::Failed => self.failed += 1,
+            _ => {}
+        }
+        if let Some(usage) = execution.llm_usage() {
+            self.llm_usage.merge(&usage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_recorded_states_are_deduplicated() {
+        let pool = StatePool::new();
+        let document = json!({"text": "x".repeat(4096), "step": 1});
+
+        let input = pool.record(&document, HistoryLevel::References);
+        let output = pool.record(&document.clone(), HistoryLevel::References);
+        assert_eq!(input, output);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(output.resolve(&pool), Some(document.clone()));
+        assert!(serde_json::to_vec(&output).unwrap().len() < 200);
+
+        let full = pool.record(&json!({"step": 2}), HistoryLevel::Full);
+        assert!(full.state_ref().is_none());
+        assert_eq!(full.resolve(&pool), Some(json!({"step": 2})));
+        assert_eq!(pool.len(), 1);
+
+        assert_eq!(output.resolve(&StatePool::new()), None);
+    }
+
+    #[test]
+    fn test_history_levels() {
+        let pool = StatePool::new();
+        let input = json!({"text": "x".repeat(4096), "step": 1});
+        let output = json!({"text": "x".repeat(4096), "step": 2});
+
+        let diff = pool.record_output(&input, &output, HistoryLevel::Diffs);
+        let changes = diff.changes().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "/step");
+        assert_eq!(pool.record(&input, HistoryLevel::Diffs), RecordedState::Omitted);
+        assert_eq!(pool.record_output(&input, &output, HistoryLevel::Metadata), RecordedState::Omitted);
+        assert!(pool.is_empty());
+
+        let mut execution = NodeExecution::new("node".to_string(), RecordedState::Omitted);
+        execution.complete(RecordedState::Omitted);
+        let mut totals = HistoryTotals::default();
+        totals.add(&execution);
+        assert_eq!((totals.executions, totals.successful, totals.failed), (1, 1, 0));
+
+        assert_eq!(HistoryLevel::default(), HistoryLevel::Diffs);
+        assert!(!HistoryLevel::None.keeps_records());
+        assert_eq!(serde_json::from_str::<HistoryLevel>("\"metadata\"").unwrap(), HistoryLevel::Metadata);
+    }
+}
@@ -3,7 +3,9 @@
 
 #![allow(missing_docs)]
 
+use crate::graph::report::NodeReports;
 use crate::graph::Graph;
+use crate::llm::LLMUsage;
 use crate::node::{Node, NodeId};
 use crate::state::StateManager;
 use serde_json::Value as JsonValue;
@@ -11,24 +13,36 @@ use serde_json::Value as JsonValue;
 // Type alias for execution state
 pub type ExecutionState = JsonValue;
 use crate::edge::{Edge, EdgeCondition};
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
-use tokio::sync::{mpsc, RwLock, Semaphore};
+use tokio::sync::{mpsc, RwLock};
 use tokio::time::timeout;
 
 pub mod parallel;
+pub mod pools;
 pub mod scheduler;
+pub mod chat;
 pub mod checkpoint;
+pub mod history;
+pub mod sticky;
 pub mod streaming;
+pub mod webhooks;
+
+pub use history::{HistoryLevel, HistoryTotals, RecordedState, StatePool, StateRef};
+pub use pools::{ConcurrencyPools, PoolLimit, PoolPermit};
 
 /// Execution configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionConfig {
     /// Maximum concurrent node executions
     pub max_concurrency: usize,
+    /// Named pools within `max_concurrency` that nodes declare membership in
+    #[serde(default)]
+    pub concurrency_pools: Vec<PoolLimit>,
     /// Execution timeout per node
     pub node_timeout: Duration,
     /// Overall execution timeout
@@ -45,12 +59,16 @@ pub struct ExecutionConfig {
     pub retry_config: RetryConfig,
     /// Resource limits
     pub resource_limits: ResourceLimits,
+    /// How much of each node execution the history keeps; a graph's own level takes precedence
+    #[serde(default)]
+    pub history_level: HistoryLevel,
 }
 
 impl Default for ExecutionConfig {
     fn default() -> Self {
         Self {
             max_concurrency: 10,
+            concurrency_pools: Vec::new(),
             node_timeout: Duration::from_secs(300), // 5 minutes
             total_timeout: Duration::from_secs(3600), // 1 hour
             parallel_execution: true,
@@ -59,6 +77,7 @@ impl Default for ExecutionConfig {
             streaming_enabled: false,
             retry_config: RetryConfig::default(),
             resource_limits: ResourceLimits::default(),
+            history_level: HistoryLevel::default(),
         }
     }
 }
@@ -131,8 +150,14 @@ pub struct ExecutionContext {
     pub current_state: ExecutionState,
     /// Execution metadata
     pub metadata: HashMap<String, serde_json::Value>,
-    /// Node execution history
+    /// Node execution history, as much as the history level keeps
     pub execution_history: Vec<NodeExecution>,
+    /// Counts over every node execution, kept at any history level
+    #[serde(default)]
+    pub totals: HistoryTotals,
+    /// States the history refers to; not serialized with the context
+    #[serde(skip)]
+    pub state_pool: StatePool,
     /// Error information
     pub error: Option<ExecutionError>,
 }
@@ -149,6 +174,8 @@ impl ExecutionContext {
             current_state: input_state,
             metadata: HashMap::new(),
             execution_history: Vec::new(),
+            totals: HistoryTotals::default(),
+            state_pool: StatePool::new(),
             error: None,
         }
     }
@@ -162,7 +189,10 @@ impl ExecutionContext {
     
     /// Add node execution to history
     pub fn add_execution(&mut self, execution: NodeExecution) {
-        self.execution_history.push(execution);
+        self.totals.add(&execution);
+        if self.config.history_level.keeps_records() {
+            self.execution_history.push(execution);
+        }
     }
     
     /// Get successful executions
@@ -180,6 +210,11 @@ impl ExecutionContext {
             .filter(|e| e.status == NodeExecutionStatus::Failed)
             .collect()
     }
+    
+    /// Total LLM usage across all node executions
+    pub fn llm_usage(&self) -> LLMUsage {
+        self.totals.llm_usage.clone()
+    }
 }
 
 /// Execution status
@@ -213,9 +248,9 @@ pub struct NodeExecution {
     /// End time
     pub ended_at: Option<SystemTime>,
     /// Input state
-    pub input_state: ExecutionState,
+    pub input_state: RecordedState,
     /// Output state
-    pub output_state: Option<ExecutionState>,
+    pub output_state: Option<RecordedState>,
     /// Error information
     pub error: Option<String>,
     /// Retry attempts
@@ -226,13 +261,13 @@ pub struct NodeExecution {
 
 impl NodeExecution {
     /// Create a new node execution
-    pub fn new(node_id: NodeId, input_state: ExecutionState) -> Self {
+    pub fn new(node_id: NodeId, input_state: impl Into<RecordedState>) -> Self {
         Self {
             node_id,
             status: NodeExecutionStatus::Pending,
             started_at: SystemTime::now(),
             ended_at: None,
-            input_state,
+            input_state: input_state.into(),
             output_state: None,
             error: None,
             retry_attempts: 0,
@@ -247,10 +282,10 @@ impl NodeExecution {
     }
     
     /// Mark execution as completed
-    pub fn complete(&mut self, output_state: ExecutionState) {
+    pub fn complete(&mut self, output_state: impl Into<RecordedState>) {
         self.status = NodeExecutionStatus::Completed;
         self.ended_at = Some(SystemTime::now());
-        self.output_state = Some(output_state);
+        self.output_state = Some(output_state.into());
     }
     
     /// Mark execution as failed
@@ -260,6 +295,23 @@ impl NodeExecution {
         self.error = Some(error);
     }
     
+    /// Record LLM usage for this node into its metadata
+    pub fn record_llm_usage(&mut self, usage: &LLMUsage) {
+        let mut total = self.llm_usage().unwrap_or_default();
+        total.merge(usage);
+        self.metadata.extend(total.to_metadata());
+    }
+    
+    /// LLM usage recorded for this node, if any
+    pub fn llm_usage(&self) -> Option<LLMUsage> {
+        LLMUsage::from_metadata(&self.metadata)
+    }
+    
+    /// Record the candidates this node chose from into its metadata
+    pub fn record_candidates(&mut self, candidates: JsonValue) {
+        self.metadata.insert("candidates".to_string(), candidates);
+    }
+    
     /// Get execution duration
     pub fn duration(&self) -> Duration {
         let end_time = self.ended_at.unwrap_or_else(SystemTime::now);
@@ -293,8 +345,8 @@ pub struct ExecutionEngine {
     config: ExecutionConfig,
     /// State manager
     state_manager: Arc<StateManager>,
-    /// Concurrency semaphore
-    semaphore: Arc<Semaphore>,
+    /// Concurrency pools
+    pools: Arc<ConcurrencyPools>,
     /// Active executions
     active_executions: Arc<RwLock<HashMap<String, ExecutionContext>>>,
     /// Execution scheduler
@@ -308,7 +360,7 @@ pub struct ExecutionEngine {
 impl ExecutionEngine {
     /// Create a new execution engine
     pub fn new(config: ExecutionConfig, state_manager: Arc<StateManager>) -> Self {
-        let semaphore = Arc::new(Semaphore::new(config.max_concurrency));
+        let pools = Arc::new(ConcurrencyPools::from_limits(config.max_concurrency, &config.concurrency_pools));
         let scheduler = scheduler::ExecutionScheduler::new(config.clone());
         let checkpoint_manager = checkpoint::CheckpointManager::new(config.clone());
         let streaming_manager = streaming::StreamingManager::new(config.clone());
@@ -316,7 +368,7 @@ impl ExecutionEngine {
         Self {
             config,
             state_manager,
-            semaphore,
+            pools,
             active_executions: Arc::new(RwLock::new(HashMap::new())),
             scheduler,
             checkpoint_manager,
@@ -333,7 +385,11 @@ impl ExecutionEngine {
     where
         S: crate::state::State,
     {
-        let mut context = ExecutionContext::new(self.config.clone(), input_state);
+        let mut config = self.config.clone();
+        if let Some(level) = graph.config().history {
+            config.history_level = level;
+        }
+        let mut context = ExecutionContext::new(config, input_state);
         context.status = ExecutionStatus::Running;
         
         // Register execution
@@ -455,9 +511,13 @@ impl ExecutionEngine {
         
         for node_id in sorted_nodes {
             let node = graph.get_node(&node_id).unwrap();
+            let incoming = graph.incoming_edges(&node_id);
+            for edge in &incoming {
+                plan.add_dependency(edge.from().clone(), node_id.clone());
+            }
             
             // Check if all dependencies are satisfied
-            let dependencies_satisfied = graph.incoming_edges(&node_id)
+            let dependencies_satisfied = incoming
                 .iter()
                 .all(|edge| visited.contains(&edge.from()));
             
@@ -484,6 +544,10 @@ impl ExecutionEngine {
     }
     
     /// Execute graph in parallel
+    ///
+    /// Each node starts as soon as the nodes it depends on have completed,
+    /// rather than when its whole level has, so a slow branch only delays
+    /// the nodes downstream of it.
     async fn execute_parallel<S>(
         &self,
         graph: &Graph<S>,
@@ -494,58 +558,67 @@ impl ExecutionEngine {
         S: crate::state::State,
     {
         let mut current_state = context.current_state.clone();
+        let mut pending = plan.dependency_counts();
+        let mut ready: VecDeque<NodeId> = plan
+            .nodes()
+            .filter(|node_id| pending.get(*node_id) == Some(&0))
+            .cloned()
+            .collect();
+        let mut running = FuturesUnordered::new();
         
-        for level in &plan.execution_levels {
-            // Execute all nodes in this level in parallel
-            let mut tasks = Vec::new();
-            
-            for node_id in level {
-                let node = graph.get_node(node_id).unwrap();
+        loop {
+            // Start every node whose dependencies have all completed
+            while let Some(node_id) = ready.pop_front() {
+                let node = graph.get_node(&node_id).unwrap();
                 let node_state = current_state.clone();
-                let semaphore = Arc::clone(&self.semaphore);
-                let config = self.config.clone();
+                let pools = Arc::clone(&self.pools);
+                let config = context.config.clone();
+                let pool = context.state_pool.clone();
                 
-                let task = tokio::spawn(async move {
-                    let _permit = semaphore.acquire().await.unwrap();
-                    Self::execute_node_with_retry(node, node_state, &config).await
+                running.push(async move {
+                    let _permit = pools.acquire(&node.metadata().concurrency_pools).await;
+                    let result = Self::execute_node_with_retry(node, node_state, &config, &pool).await;
+                    (node_id, result)
                 });
-                
-                tasks.push((node_id.clone(), task));
             }
             
-            // Wait for all tasks to complete
-            for (node_id, task) in tasks {
-                let result = task.await
-                    .map_err(|e| ExecutionError::NodeExecution {
-                        node_id: node_id.clone(),
-                        error: e.to_string(),
-                    })?;
-                
-                match result {
-                    Ok(node_execution) => {
-                        current_state = node_execution.output_state.clone().unwrap_or(current_state);
-                        context.add_execution(node_execution);
-                    }
-                    Err(error) => {
-                        return Err(ExecutionError::NodeExecution {
-                            node_id,
-                            error: error.to_string(),
-                        });
+            let Some((node_id, result)) = running.next().await else {
+                break;
+            };
+            
+            match result {
+                Ok((node_execution, output_state)) => {
+                    current_state = output_state.unwrap_or(current_state);
+                    context.add_execution(node_execution);
+                    for dependent in plan.dependents(&node_id) {
+                        if let Some(count) = pending.get_mut(dependent) {
+                            *count -= 1;
+                            if *count == 0 {
+                                ready.push_back(dependent.clone());
+                            }
+                        }
                     }
                 }
+                Err(error) => {
+                    return Err(ExecutionError::NodeExecution {
+                        node_id,
+                        error: error.to_string(),
+                    });
+                }
             }
         }
         
         context.current_state = current_state.clone();
+        context.metadata.extend(context.llm_usage().to_metadata());
         
         Ok(ExecutionResult {
             execution_id: context.execution_id.clone(),
             status: ExecutionStatus::Completed,
             final_state: current_state,
             execution_time: context.duration(),
-            node_executions: context.execution_history.len(),
-            successful_nodes: context.successful_executions().len(),
-            failed_nodes: context.failed_executions().len(),
+            node_executions: context.totals.executions,
+            successful_nodes: context.totals.successful,
+            failed_nodes: context.totals.failed,
             metadata: context.metadata.clone(),
         })
     }
@@ -566,11 +639,11 @@ impl ExecutionEngine {
             for node_id in level {
                 let node = graph.get_node(node_id).unwrap();
                 
-                let result = Self::execute_node_with_retry(node, current_state.clone(), &self.config).await;
+                let result = Self::execute_node_with_retry(node, current_state.clone(), &context.config, &context.state_pool).await;
                 
                 match result {
-                    Ok(node_execution) => {
-                        current_state = node_execution.output_state.clone().unwrap_or(current_state);
+                    Ok((node_execution, output_state)) => {
+                        current_state = output_state.unwrap_or(current_state);
                         context.add_execution(node_execution);
                     }
                     Err(error) => {
@@ -584,30 +657,37 @@ impl ExecutionEngine {
         }
         
         context.current_state = current_state.clone();
+        context.metadata.extend(context.llm_usage().to_metadata());
         
         Ok(ExecutionResult {
             execution_id: context.execution_id.clone(),
             status: ExecutionStatus::Completed,
             final_state: current_state,
             execution_time: context.duration(),
-            node_executions: context.execution_history.len(),
-            successful_nodes: context.successful_executions().len(),
-            failed_nodes: context.failed_executions().len(),
+            node_executions: context.totals.executions,
+            successful_nodes: context.totals.successful,
+            failed_nodes: context.totals.failed,
             metadata: context.metadata.clone(),
         })
     }
     
     /// Execute a single node with retry logic
+    ///
+    /// Returns the node's record, with its states captured in `pool` at the
+    /// config's history level, and its output state if it completed.
     async fn execute_node_with_retry<S>(
         node: &dyn Node<S>,
         input_state: ExecutionState,
         config: &ExecutionConfig,
-    ) -> Result<NodeExecution, ExecutionError>
+        pool: &StatePool,
+    ) -> Result<(NodeExecution, Option<ExecutionState>), ExecutionError>
     where
         S: crate::state::State,
     {
-        let mut execution = NodeExecution::new(node.id().clone(), input_state.clone());
+        let mut execution = NodeExecution::new(node.id().clone(), pool.record(&input_state, config.history_level));
         execution.start();
+        // Usage of every attempt, reported by the node while it runs
+        let reports = NodeReports::default();
         
         for attempt in 0..config.retry_config.max_attempts {
             execution.retry_attempts = attempt;
@@ -615,19 +695,26 @@ impl ExecutionEngine {
             // Execute node with timeout
             let result = timeout(
                 config.node_timeout,
-                node.execute(execution.input_state.clone()),
+                reports.scope(node.execute(input_state.clone())),
             )
             .await;
             
             match result {
                 Ok(Ok(output_state)) => {
-                    execution.complete(output_state);
-                    return Ok(execution);
+                    let report = reports.take();
+                    if let Some(usage) = report.llm_usage {
+                        execution.record_llm_usage(&usage);
+                    }
+                    if let Some(candidates) = report.candidates {
+                        execution.record_candidates(candidates);
+                    }
+                    execution.complete(pool.record_output(&input_state, &output_state, config.history_level));
+                    return Ok((execution, Some(output_state)));
                 }
                 Ok(Err(error)) => {
                     if attempt == config.retry_config.max_attempts - 1 {
                         execution.fail(error.to_string());
-                        return Ok(execution);
+                        return Ok((execution, None));
                     }
                     
                     // Calculate retry delay
@@ -636,13 +723,13 @@ impl ExecutionEngine {
                 }
                 Err(_) => {
                     execution.fail("Node execution timed out".to_string());
-                    return Ok(execution);
+                    return Ok((execution, None));
                 }
             }
         }
         
         execution.fail("Maximum retry attempts exceeded".to_string());
-        Ok(execution)
+        Ok((execution, None))
     }
     
     /// Calculate retry delay with exponential backoff and jitter
@@ -654,7 +741,7 @@ impl ExecutionEngine {
         let delay_ms = delay_ms.min(max_delay_ms);
         
         // Add jitter
-        let jitter = delay_ms * config.jitter_factor * (rand::random::<f64>() - 0.5);
+        let jitter = delay_ms * config.jitter_factor * (crate::graph::determinism::random_f64() - 0.5);
         let final_delay_ms = (delay_ms + jitter).max(0.0) as u64;
         
         Duration::from_millis(final_delay_ms)
@@ -690,6 +777,10 @@ impl ExecutionEngine {
 pub struct ExecutionPlan {
     /// Execution levels (for parallel execution)
     pub execution_levels: Vec<Vec<NodeId>>,
+    /// Nodes each node depends on
+    pub dependencies: HashMap<NodeId, Vec<NodeId>>,
+    /// Nodes depending on each node
+    pub dependents: HashMap<NodeId, Vec<NodeId>>,
 }
 
 impl ExecutionPlan {
@@ -697,8 +788,36 @@ impl ExecutionPlan {
     pub fn new() -> Self {
         Self {
             execution_levels: Vec::new(),
+            dependencies: HashMap::new(),
+            dependents: HashMap::new(),
         }
     }
+    
+    /// Record that `to` may only start once `from` has completed
+    pub fn add_dependency(&mut self, from: NodeId, to: NodeId) {
+        let dependencies = self.dependencies.entry(to.clone()).or_default();
+        if !dependencies.contains(&from) {
+            dependencies.push(from.clone());
+            self.dependents.entry(from).or_default().push(to);
+        }
+    }
+    
+    /// Nodes in the plan, level by level
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeId> {
+        self.execution_levels.iter().flatten()
+    }
+    
+    /// Nodes depending on `node_id`
+    pub fn dependents(&self, node_id: &NodeId) -> &[NodeId] {
+        self.dependents.get(node_id).map(Vec::as_slice).unwrap_or_default()
+    }
+    
+    /// Number of dependencies each node in the plan is waiting for
+    pub fn dependency_counts(&self) -> HashMap<NodeId, usize> {
+        self.nodes()
+            .map(|node_id| (node_id.clone(), self.dependencies.get(node_id).map_or(0, Vec::len)))
+            .collect()
+    }
 }
 
 /// Execution result
@@ -801,9 +920,62 @@ mod tests {
         assert!(execution.output_state.is_some());
     }
 
+    #[test]
+    fn test_node_llm_usage_metadata() {
+        let usage = LLMUsage {
+            calls: 1,
+            prompt_tokens: 120,
+            completion_tokens: 30,
+            total_tokens: 150,
+            cost: 0.042,
+            latency: Duration::from_millis(3100),
+        };
+        let mut execution = NodeExecution::new("agent".to_string(), serde_json::json!({}));
+        execution.record_llm_usage(&usage);
+        execution.complete(serde_json::json!({"output": "done"}));
+
+        assert_eq!(execution.metadata["llm_cost_usd"], serde_json::json!(0.042));
+        assert_eq!(execution.metadata["llm_latency_ms"], serde_json::json!(3100));
+
+        let mut context = ExecutionContext::new(ExecutionConfig::default(), serde_json::json!({}));
+        context.add_execution(execution.clone());
+        context.add_execution(execution);
+        assert_eq!(context.llm_usage().total_tokens, 300);
+    }
+
+    #[test]
+    fn test_node_candidates_metadata() {
+        let mut execution = NodeExecution::new("vote".to_string(), serde_json::json!({}));
+        execution.record_candidates(serde_json::json!([{"source": "a", "output": "42"}, {"source": "b", "output": "41"}]));
+
+        assert_eq!(execution.metadata["candidates"].as_array().unwrap().len(), 2);
+    }
+
     #[test]
     fn test_execution_plan_creation() {
         let plan = ExecutionPlan::new();
         assert!(plan.execution_levels.is_empty());
     }
+
+    #[test]
+    fn test_execution_plan_dependencies() {
+        // `fast` and `slow` start together; `after_fast` only waits for `fast`
+        let mut plan = ExecutionPlan::new();
+        plan.execution_levels = vec![
+            vec!["fast".to_string(), "slow".to_string()],
+            vec!["after_fast".to_string(), "join".to_string()],
+        ];
+        plan.add_dependency("fast".to_string(), "after_fast".to_string());
+        plan.add_dependency("fast".to_string(), "join".to_string());
+        plan.add_dependency("slow".to_string(), "join".to_string());
+        plan.add_dependency("slow".to_string(), "join".to_string());
+
+        let counts = plan.dependency_counts();
+        assert_eq!(counts["fast"], 0);
+        assert_eq!(counts["slow"], 0);
+        assert_eq!(counts["after_fast"], 1);
+        assert_eq!(counts["join"], 2);
+        assert_eq!(plan.dependents(&"fast".to_string()), ["after_fast".to_string(), "join".to_string()]);
+        assert!(plan.dependents(&"join".to_string()).is_empty());
+    }
 }
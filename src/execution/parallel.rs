@@ -3,14 +3,14 @@
 
 #![allow(missing_docs)]
 
-use super::{ExecutionConfig, NodeExecution, ExecutionError};
+use super::{ConcurrencyPools, ExecutionConfig, NodeExecution, ExecutionError, StatePool};
 use crate::node::{Node, NodeId};
 use crate::state::State;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock, Semaphore};
+use tokio::sync::{mpsc, RwLock};
 use tokio::task::JoinHandle;
 use thiserror::Error;
 
@@ -271,8 +271,10 @@ pub struct ParallelExecutor {
     work_queue: Arc<RwLock<WorkQueue>>,
     /// Workers
     workers: Arc<RwLock<Vec<Worker>>>,
-    /// Semaphore for concurrency control
-    semaphore: Arc<Semaphore>,
+    /// Concurrency pools
+    pools: Arc<ConcurrencyPools>,
+    /// States recorded in the executions' history
+    state_pool: StatePool,
     /// Active tasks
     active_tasks: Arc<RwLock<HashMap<usize, JoinHandle<()>>>>,
 }
@@ -280,7 +282,7 @@ pub struct ParallelExecutor {
 impl ParallelExecutor {
     /// Create a new parallel executor
     pub fn new(config: ExecutionConfig, strategy: ParallelStrategy) -> Self {
-        let semaphore = Arc::new(Semaphore::new(config.max_concurrency));
+        let pools = Arc::new(ConcurrencyPools::from_limits(config.max_concurrency, &config.concurrency_pools));
         let work_queue = Arc::new(RwLock::new(WorkQueue::new()));
         let workers = Arc::new(RwLock::new(Vec::<Worker>::new()));
         
@@ -295,7 +297,8 @@ impl ParallelExecutor {
             strategy,
             work_queue,
             workers: Arc::new(RwLock::new(worker_vec)),
-            semaphore,
+            pools,
+            state_pool: StatePool::new(),
             active_tasks: Arc::new(RwLock::new(HashMap::new())),
         }
     }
@@ -330,7 +333,8 @@ impl ParallelExecutor {
         for worker_id in 0..self.config.max_concurrency {
             let queue = Arc::clone(&self.work_queue);
             let workers = Arc::clone(&self.workers);
-            let semaphore = Arc::clone(&self.semaphore);
+            let pools = Arc::clone(&self.pools);
+            let state_pool = self.state_pool.clone();
             let config = self.config.clone();
             
             let task = tokio::spawn(async move {
@@ -342,8 +346,8 @@ impl ParallelExecutor {
                     };
                     
                     if let Some(item) = work_item {
-                        // Acquire semaphore permit
-                        let _permit = semaphore.acquire().await.unwrap();
+                        // Acquire permits for the node's pools
+                        let _permit = pools.acquire(&item.node.metadata().concurrency_pools).await;
                         
                         // Update worker status
                         {
@@ -355,7 +359,7 @@ impl ParallelExecutor {
                         
                         // Execute work item
                         let start_time = Instant::now();
-                        let result = Self::execute_work_item(&item, &config).await;
+                        let result = Self::execute_work_item(&item, &config, &state_pool).await;
                         let execution_time = start_time.elapsed();
                         
                         // Update queue and worker
@@ -427,11 +431,19 @@ impl ParallelExecutor {
     }
     
     /// Execute a single work item
+    ///
+    /// Its states are recorded in `state_pool` at the config's history level.
     async fn execute_work_item<S: State>(
         item: &WorkItem<S>,
         config: &ExecutionConfig,
+        state_pool: &StatePool,
     ) -> Result<NodeExecution, ExecutionError> {
-        let mut execution = NodeExecution::new(item.node.id().clone(), item.input_state.clone());
+        let node_id = item.node.id().clone();
+        let input = item.input_state.to_json().map_err(|e| ExecutionError::NodeExecution {
+            node_id: node_id.clone(),
+            error: e.to_string(),
+        })?;
+        let mut execution = NodeExecution::new(node_id, state_pool.record(&input, config.history_level));
         execution.start();
         
         // Execute with timeout
@@ -443,7 +455,10 @@ impl ParallelExecutor {
         
         match result {
             Ok(Ok(output_state)) => {
-                execution.complete(output_state);
+                match output_state.to_json() {
+                    Ok(output) => execution.complete(state_pool.record_output(&input, &output, config.history_level)),
+                    Err(error) => execution.fail(error.to_string()),
+                }
                 Ok(execution)
             }
             Ok(Err(error)) => {
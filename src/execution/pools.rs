@@ -0,0 +1,112 @@
+// Named concurrency pools for node executions
+// Each resource class has its own permit budget inside the engine's global limit
+
+#![allow(missing_docs)]
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A named pool and the permits it holds, e.g. `llm` with 8
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolLimit {
+    pub name: String,
+    pub permits: usize,
+}
+
+/// Global permit budget split into named pools
+///
+/// A node takes a permit from every pool it declares and then one from the
+/// global budget. Pool permits are taken first, so nodes queued behind a
+/// saturated class hold no global permit and other classes keep running.
+/// Permits are granted in the order they were asked for. Pools a node names
+/// that were never configured do not limit it.
+#[derive(Debug)]
+pub struct ConcurrencyPools {
+    global: Arc<Semaphore>,
+    pools: HashMap<String, Arc<Semaphore>>,
+}
+
+/// Permits held by a running node; released on drop
+#[derive(Debug)]
+pub struct PoolPermit {
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+impl ConcurrencyPools {
+    /// Pools sharing a global budget of `global_limit` permits
+    pub fn new(global_limit: usize) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(global_limit.max(1))),
+            pools: HashMap::new(),
+        }
+    }
+
+    /// Add a pool of `permits` permits
+    pub fn with_pool(mut self, name: impl Into<String>, permits: usize) -> Self {
+        self.pools.insert(name.into(), Arc::new(Semaphore::new(permits.max(1))));
+        self
+    }
+
+    /// Pools configured as `limits` within `global_limit`
+    pub fn from_limits(global_limit: usize, limits: &[PoolLimit]) -> Self {
+        limits
+            .iter()
+            .fold(Self::new(global_limit), |pools, limit| pools.with_pool(limit.name.clone(), limit.permits))
+    }
+
+    /// Wait for a permit from each of `pools` and from the global budget
+    pub async fn acquire(&self, pools: &[String]) -> PoolPermit {
+        // A fixed order, so nodes in several pools cannot deadlock each other
+        let names: BTreeSet<&String> = pools.iter().collect();
+        let mut permits = Vec::with_capacity(names.len() + 1);
+        for name in names {
+            match self.pools.get(name) {
+                Some(pool) => permits.push(pool.clone().acquire_owned().await.expect("pools are never closed")),
+                None => tracing::debug!(pool = %name, "Node names a concurrency pool that is not configured"),
+            }
+        }
+        permits.push(self.global.clone().acquire_owned().await.expect("pools are never closed"));
+        PoolPermit { _permits: permits }
+    }
+
+    /// Permits left in the global budget
+    pub fn available_global(&self) -> usize {
+        self.global.available_permits()
+    }
+
+    /// Permits left in pool `name`, if it is configured
+    pub fn available(&self, name: &str) -> Option<usize> {
+        self.pools.get(name).map(|pool| pool.available_permits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_saturated_pool_leaves_global_permits() {
+        let pools = ConcurrencyPools::from_limits(
+            3,
+            &[PoolLimit { name: "llm".to_string(), permits: 1 }, PoolLimit { name: "db".to_string(), permits: 2 }],
+        );
+        let llm = vec!["llm".to_string()];
+
+        let first = pools.acquire(&llm).await;
+        assert_eq!(pools.available("llm"), Some(0));
+        // A second LLM node waits on its pool without taking a global permit
+        let waiting = tokio::time::timeout(std::time::Duration::from_millis(20), pools.acquire(&llm)).await;
+        assert!(waiting.is_err());
+        assert_eq!(pools.available_global(), 2);
+
+        let db = pools.acquire(&["db".to_string(), "db".to_string(), "unknown".to_string()]).await;
+        assert_eq!(pools.available("db"), Some(1));
+        assert_eq!(pools.available_global(), 1);
+
+        drop((first, db));
+        assert_eq!(pools.available_global(), 3);
+        assert_eq!(pools.available("llm"), Some(1));
+    }
+}
@@ -55,6 +55,8 @@ pub struct ScheduledExecution {
     pub deadline: Option<SystemTime>,
     /// User/tenant ID
     pub user_id: Option<String>,
+    /// Conversation or session the execution belongs to
+    pub session_id: Option<String>,
 }
 
 impl ScheduledExecution {
@@ -75,6 +77,7 @@ impl ScheduledExecution {
             scheduled_at: SystemTime::now(),
             deadline: None,
             user_id: None,
+            session_id: None,
         }
     }
     
@@ -108,6 +111,21 @@ impl ScheduledExecution {
         self
     }
     
+    /// Set session ID
+    pub fn with_session_id(mut self, session_id: String) -> Self {
+        self.session_id = Some(session_id);
+        self
+    }
+    
+    /// Key that keeps related executions on one worker: tenant and session, else whichever is set
+    pub fn routing_key(&self) -> String {
+        match (&self.user_id, &self.session_id) {
+            (Some(user_id), Some(session_id)) => format!("{}/{}", user_id, session_id),
+            (Some(key), None) | (None, Some(key)) => key.clone(),
+            (None, None) => self.execution_id.clone(),
+        }
+    }
+    
     /// Check if execution is overdue
     pub fn is_overdue(&self) -> bool {
         if let Some(deadline) = self.deadline {
@@ -0,0 +1,237 @@
+// Sticky session routing for distributed execution
+// Consistent hashing keeps a session's executions on one worker while workers come and go
+
+#![allow(missing_docs)]
+
+use super::scheduler::ScheduledExecution;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Sticky routing configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StickyRoutingConfig {
+    /// Points each worker gets on the hash ring, per unit of weight
+    pub virtual_nodes: u32,
+    /// Workers not heard from for this long are routed around
+    pub heartbeat_timeout: Duration,
+}
+
+impl Default for StickyRoutingConfig {
+    fn default() -> Self {
+        Self {
+            virtual_nodes: 128,
+            heartbeat_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A worker taking executions
+#[derive(Debug, Clone)]
+pub struct WorkerNode {
+    /// Worker ID
+    pub id: String,
+    /// Where to send executions
+    pub address: String,
+    /// Relative share of sessions
+    pub weight: u32,
+    /// False once the worker is reported down, until its next heartbeat
+    pub healthy: bool,
+    /// Last registration or heartbeat
+    pub last_heartbeat: Instant,
+}
+
+/// Worker chosen for a routing key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteDecision {
+    /// Worker to send the execution to
+    pub worker_id: String,
+    /// Its address
+    pub address: String,
+    /// Worker the key belongs to when every worker is up
+    pub owner: String,
+}
+
+impl RouteDecision {
+    /// Whether the owner was unavailable and another worker took the key
+    pub fn is_failover(&self) -> bool {
+        self.worker_id != self.owner
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ring {
+    points: BTreeMap<u64, String>,
+    workers: HashMap<String, WorkerNode>,
+}
+
+/// Routes executions of the same session or tenant to the same worker
+///
+/// Workers own arcs of a consistent hash ring, so adding or removing a worker
+/// only moves the keys on its arcs: warm caches, memory stores and rate-limit
+/// buckets stay where they are for everyone else. Keys of a worker that is
+/// down or has missed its heartbeat go to the next worker on the ring and
+/// return once it is back.
+#[derive(Debug, Default)]
+pub struct StickyRouter {
+    config: StickyRoutingConfig,
+    ring: RwLock<Ring>,
+}
+
+impl StickyRouter {
+    /// Create a router with no workers
+    pub fn new(config: StickyRoutingConfig) -> Self {
+        Self {
+            config,
+            ring: RwLock::new(Ring::default()),
+        }
+    }
+
+    /// Add a worker, or update its address and weight if it is already known
+    pub fn register_worker(&self, id: impl Into<String>, address: impl Into<String>, weight: u32) {
+        let id = id.into();
+        let mut ring = self.ring.write();
+        ring.points.retain(|_, worker| *worker != id);
+        for replica in 0..self.config.virtual_nodes.saturating_mul(weight.max(1)) {
+            ring.points.insert(ring_hash(&format!("{}#{}", id, replica)), id.clone());
+        }
+        ring.workers.insert(id.clone(), WorkerNode {
+            id,
+            address: address.into(),
+            weight: weight.max(1),
+            healthy: true,
+            last_heartbeat: Instant::now(),
+        });
+    }
+
+    /// Remove a worker; its keys move to the next workers on the ring
+    pub fn remove_worker(&self, id: &str) -> bool {
+        let mut ring = self.ring.write();
+        ring.points.retain(|_, worker| worker != id);
+        ring.workers.remove(id).is_some()
+    }
+
+    /// Record that a worker is alive, bringing it back if it was down
+    pub fn heartbeat(&self, id: &str) -> bool {
+        match self.ring.write().workers.get_mut(id) {
+            Some(worker) => {
+                worker.healthy = true;
+                worker.last_heartbeat = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Route around a worker until its next heartbeat
+    pub fn mark_unhealthy(&self, id: &str) {
+        if let Some(worker) = self.ring.write().workers.get_mut(id) {
+            worker.healthy = false;
+        }
+    }
+
+    /// Registered workers
+    pub fn workers(&self) -> Vec<WorkerNode> {
+        self.ring.read().workers.values().cloned().collect()
+    }
+
+    /// Worker for a routing key, or `None` if no worker is available
+    pub fn route(&self, key: &str) -> Option<RouteDecision> {
+        let ring = self.ring.read();
+        let hash = ring_hash(key);
+        let mut seen = HashSet::new();
+        let mut owner = None;
+
+        for (_, id) in ring.points.range(hash..).chain(ring.points.range(..hash)) {
+            if !seen.insert(id) {
+                continue;
+            }
+            let owner = owner.get_or_insert_with(|| id.clone());
+            let worker = &ring.workers[id];
+            if self.is_available(worker) {
+                return Some(RouteDecision {
+                    worker_id: worker.id.clone(),
+                    address: worker.address.clone(),
+                    owner: owner.clone(),
+                });
+            }
+            if seen.len() == ring.workers.len() {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Worker for a scheduled execution, keyed by its tenant and session
+    pub fn route_execution(&self, execution: &ScheduledExecution) -> Option<RouteDecision> {
+        self.route(&execution.routing_key())
+    }
+
+    fn is_available(&self, worker: &WorkerNode) -> bool {
+        worker.healthy && worker.last_heartbeat.elapsed() <= self.config.heartbeat_timeout
+    }
+}
+
+/// Position on the ring; stable across processes so every router agrees
+fn ring_hash(key: &str) -> u64 {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(workers: &[&str]) -> StickyRouter {
+        let router = StickyRouter::new(StickyRoutingConfig::default());
+        for worker in workers {
+            router.register_worker(*worker, format!("http://{}:9000", worker), 1);
+        }
+        router
+    }
+
+    #[test]
+    fn test_removing_a_worker_only_moves_its_sessions() {
+        let router = router(&["w1", "w2", "w3"]);
+        let keys: Vec<String> = (0..300).map(|i| format!("tenant-{}/session-{}", i % 7, i)).collect();
+        let before: Vec<String> = keys.iter().map(|key| router.route(key).unwrap().worker_id).collect();
+        for worker in ["w1", "w2", "w3"] {
+            assert!(before.iter().any(|id| id == worker));
+        }
+
+        assert!(router.remove_worker("w2"));
+        for (key, previous) in keys.iter().zip(&before) {
+            let decision = router.route(key).unwrap();
+            assert!(!decision.is_failover());
+            if previous != "w2" {
+                assert_eq!(&decision.worker_id, previous);
+            } else {
+                assert_ne!(decision.worker_id, "w2");
+            }
+        }
+    }
+
+    #[test]
+    fn test_failover_and_return() {
+        let router = router(&["w1", "w2"]);
+        let owner = router.route("acme/chat-1").unwrap().worker_id;
+
+        router.mark_unhealthy(&owner);
+        let decision = router.route("acme/chat-1").unwrap();
+        assert!(decision.is_failover());
+        assert_eq!(decision.owner, owner);
+
+        // Back on its next heartbeat
+        assert!(router.heartbeat(&owner));
+        assert_eq!(router.route("acme/chat-1").unwrap().worker_id, owner);
+
+        router.mark_unhealthy("w1");
+        router.mark_unhealthy("w2");
+        assert!(router.route("acme/chat-1").is_none());
+    }
+}
@@ -0,0 +1,438 @@
+// Webhook notifications on execution lifecycle events
+// Signed HTTP callbacks so external systems can react without polling the REST API
+
+#![allow(missing_docs)]
+
+use crate::error::{GraphError, GraphResult};
+use crate::human::traits::HumanResult;
+use crate::human::{HumanConfig, HumanContext, HumanInput, HumanInteraction, HumanResponse};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header carrying the signature of a delivery
+pub const SIGNATURE_HEADER: &str = "x-agentgraph-signature";
+/// Header carrying the event type of a delivery
+pub const EVENT_HEADER: &str = "x-agentgraph-event";
+/// Header carrying the delivery ID, the same on every retry
+pub const DELIVERY_HEADER: &str = "x-agentgraph-delivery";
+
+/// Lifecycle events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    ExecutionStarted,
+    ExecutionCompleted,
+    ExecutionFailed,
+    /// The execution suspended, e.g. waiting for a long-running operation
+    ExecutionPaused,
+    /// A node asked a human for input or approval
+    HumanInteractionRequested,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ExecutionStarted => "execution_started",
+            WebhookEvent::ExecutionCompleted => "execution_completed",
+            WebhookEvent::ExecutionFailed => "execution_failed",
+            WebhookEvent::ExecutionPaused => "execution_paused",
+            WebhookEvent::HumanInteractionRequested => "human_interaction_requested",
+        }
+    }
+}
+
+/// Body POSTed to webhooks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    /// Delivery ID; receivers can use it to drop duplicates
+    pub id: String,
+    pub event: WebhookEvent,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub execution_id: Option<String>,
+    /// Name of the graph
+    pub graph: Option<String>,
+    /// Event details
+    pub data: serde_json::Value,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent, data: serde_json::Value) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            event,
+            timestamp: chrono::Utc::now(),
+            execution_id: None,
+            graph: None,
+            data,
+        }
+    }
+
+    pub fn with_execution(mut self, execution_id: impl ToString, graph: impl Into<String>) -> Self {
+        self.execution_id = Some(execution_id.to_string());
+        self.graph = Some(graph.into());
+        self
+    }
+}
+
+/// A webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key for the HMAC-SHA256 signature; deliveries are unsigned without one
+    pub secret: Option<String>,
+    /// Events to deliver; all of them when empty
+    pub events: Vec<WebhookEvent>,
+    /// Deliveries in total, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubles after each one
+    pub initial_backoff_ms: u64,
+    /// Time to wait for the endpoint to answer
+    pub timeout_ms: u64,
+    /// Extra headers, e.g. for authentication
+    pub headers: HashMap<String, String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            secret: None,
+            events: Vec::new(),
+            max_attempts: 5,
+            initial_backoff_ms: 500,
+            timeout_ms: 10_000,
+            headers: HashMap::new(),
+        }
+    }
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_events(mut self, events: impl IntoIterator<Item = WebhookEvent>) -> Self {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    pub fn with_retries(mut self, max_attempts: u32, initial_backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.initial_backoff_ms = initial_backoff.as_millis() as u64;
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Outcome of delivering one payload to one webhook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub url: String,
+    pub delivery_id: String,
+    pub attempts: u32,
+    /// Last HTTP status received, if the endpoint answered
+    pub status: Option<u16>,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+/// Sends lifecycle events to the configured webhooks
+///
+/// Deliveries are signed, retried with exponential backoff on network errors,
+/// 429 and 5xx answers, and given up on other 4xx answers, which retrying
+/// would not fix.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    hooks: Vec<WebhookConfig>,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(hooks: Vec<WebhookConfig>) -> Self {
+        Self {
+            hooks,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn hooks(&self) -> &[WebhookConfig] {
+        &self.hooks
+    }
+
+    /// Deliver in the background, so executions never wait on webhooks
+    pub fn notify(self: &Arc<Self>, payload: WebhookPayload) {
+        if !self.hooks.iter().any(|hook| hook.wants(payload.event)) {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            for delivery in notifier.deliver(&payload).await {
+                if !delivery.delivered {
+                    tracing::warn!(
+                        url = %delivery.url,
+                        delivery_id = %delivery.delivery_id,
+                        attempts = delivery.attempts,
+                        error = ?delivery.error,
+                        "Webhook delivery failed"
+                    );
+                }
+            }
+        });
+    }
+
+    /// Deliver to every webhook subscribed to the payload's event and wait for the outcome
+    pub async fn deliver(&self, payload: &WebhookPayload) -> Vec<WebhookDelivery> {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(error) => {
+                tracing::error!(error = %error, "Failed to serialize webhook payload");
+                return Vec::new();
+            }
+        };
+        let deliveries = self.hooks
+            .iter()
+            .filter(|hook| hook.wants(payload.event))
+            .map(|hook| self.deliver_to(hook, payload, &body));
+        futures::future::join_all(deliveries).await
+    }
+
+    async fn deliver_to(&self, hook: &WebhookConfig, payload: &WebhookPayload, body: &[u8]) -> WebhookDelivery {
+        let mut delivery = WebhookDelivery {
+            url: hook.url.clone(),
+            delivery_id: payload.id.clone(),
+            attempts: 0,
+            status: None,
+            delivered: false,
+            error: None,
+        };
+        let mut backoff = Duration::from_millis(hook.initial_backoff_ms);
+
+        while delivery.attempts < hook.max_attempts.max(1) {
+            if delivery.attempts > 0 {
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+            }
+            delivery.attempts += 1;
+
+            let mut request = self.client
+                .post(&hook.url)
+                .timeout(Duration::from_millis(hook.timeout_ms))
+                .header("content-type", "application/json")
+                .header(EVENT_HEADER, payload.event.as_str())
+                .header(DELIVERY_HEADER, &payload.id);
+            if let Some(secret) = &hook.secret {
+                let timestamp = chrono::Utc::now().timestamp();
+                request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, body));
+            }
+            for (name, value) in &hook.headers {
+                request = request.header(name, value);
+            }
+
+            match request.body(body.to_vec()).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    delivery.status = Some(status.as_u16());
+                    if status.is_success() {
+                        delivery.delivered = true;
+                        delivery.error = None;
+                        return delivery;
+                    }
+                    delivery.error = Some(format!("HTTP {}", status));
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                        return delivery;
+                    }
+                }
+                Err(error) => delivery.error = Some(error.to_string()),
+            }
+        }
+        delivery
+    }
+}
+
+/// Signature header value: `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`
+///
+/// The timestamp is signed with the body so receivers can reject replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!("t={},v1={}", timestamp, signature(secret, timestamp, body))
+}
+
+fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let digest = signing_mac(secret, timestamp, body).finalize().into_bytes();
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn signing_mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Check a signature header on the receiving side
+///
+/// Fails if the signature does not match or is older than `tolerance`.
+pub fn verify_signature(secret: &str, header: &str, body: &[u8], tolerance: Duration) -> GraphResult<()> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| GraphError::validation_error("Webhook signature has no timestamp"))?;
+    let age = chrono::Utc::now().timestamp() - timestamp;
+    if age.unsigned_abs() > tolerance.as_secs() {
+        return Err(GraphError::validation_error("Webhook signature is too old"));
+    }
+
+    // verify_slice compares in constant time
+    let matches = signatures.iter().any(|signature| {
+        decode_hex(signature)
+            .is_some_and(|bytes| signing_mac(secret, timestamp, body).verify_slice(&bytes).is_ok())
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(GraphError::validation_error("Webhook signature does not match"))
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Human interaction provider that announces every request to the webhooks
+/// before handing it to the wrapped provider
+#[derive(Debug)]
+pub struct NotifyingInteraction {
+    inner: Arc<dyn HumanInteraction>,
+    notifier: Arc<WebhookNotifier>,
+}
+
+impl NotifyingInteraction {
+    pub fn new(inner: Arc<dyn HumanInteraction>, notifier: Arc<WebhookNotifier>) -> Self {
+        Self { inner, notifier }
+    }
+}
+
+#[async_trait]
+impl HumanInteraction for NotifyingInteraction {
+    async fn request_input(
+        &self,
+        input: HumanInput,
+        context: &HumanContext,
+        config: &HumanConfig,
+    ) -> HumanResult<HumanResponse> {
+        let mut payload = WebhookPayload::new(WebhookEvent::HumanInteractionRequested, serde_json::json!({
+            "interaction_id": context.interaction_id,
+            "interaction_type": input.interaction_type,
+            "prompt": input.prompt,
+            "context": input.context,
+            "options": input.options,
+            "user_id": context.user_id,
+            "session_id": context.session_id,
+        }));
+        payload.execution_id = crate::graph::engine::current_execution_id().map(|id| id.to_string());
+        self.notifier.notify(payload);
+        self.inner.request_input(input, context, config).await
+    }
+
+    async fn is_available(&self) -> bool {
+        self.inner.is_available().await
+    }
+
+    async fn cancel_interaction(&self, interaction_id: &str) -> HumanResult<()> {
+        self.inner.cancel_interaction(interaction_id).await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use warp::Filter;
+
+    #[tokio::test]
+    async fn test_signed_delivery_is_retried_until_accepted() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let received = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let route = warp::post()
+            .and(warp::header::<String>(SIGNATURE_HEADER))
+            .and(warp::body::bytes())
+            .map({
+                let calls = calls.clone();
+                let received = received.clone();
+                move |signature: String, body: warp::hyper::body::Bytes| {
+                    received.lock().push((signature, body.to_vec()));
+                    let status = if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        warp::http::StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        warp::http::StatusCode::NO_CONTENT
+                    };
+                    warp::reply::with_status(warp::reply(), status)
+                }
+            });
+        let (address, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let hook = WebhookConfig::new(format!("http://{}/hooks", address))
+            .with_secret("s3cret")
+            .with_events([WebhookEvent::ExecutionFailed])
+            .with_retries(3, Duration::from_millis(5));
+        let notifier = WebhookNotifier::new(vec![hook]);
+
+        // Not subscribed
+        let started = WebhookPayload::new(WebhookEvent::ExecutionStarted, serde_json::json!({}));
+        assert!(notifier.deliver(&started).await.is_empty());
+
+        let failed = WebhookPayload::new(WebhookEvent::ExecutionFailed, serde_json::json!({"error": "boom"}))
+            .with_execution("exec-1", "billing");
+        let deliveries = notifier.deliver(&failed).await;
+        assert_eq!(deliveries.len(), 1);
+        assert!(deliveries[0].delivered);
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[0].status, Some(204));
+
+        let (signature, body) = received.lock().last().cloned().unwrap();
+        verify_signature("s3cret", &signature, &body, Duration::from_secs(60)).unwrap();
+        assert!(verify_signature("other", &signature, &body, Duration::from_secs(60)).is_err());
+        assert!(verify_signature("s3cret", &signature, b"{}", Duration::from_secs(60)).is_err());
+        let payload: WebhookPayload = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload, failed);
+    }
+}
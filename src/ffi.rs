@@ -0,0 +1,578 @@
+//! C ABI for embedding the engine in services written in other languages.
+//!
+//! Go, Java or C++ services can run graphs in-process instead of calling a
+//! server over gRPC. A runtime owns the worker threads; a graph is loaded from
+//! an application manifest, compiled once and shared by its executions, which
+//! run over a JSON state. Each execution reports its events to an optional
+//! callback, as JSON [`EventEnvelope`]s, and is polled or waited on for its
+//! result. Build the shared library with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`; the
+//! declarations are in `clients/c/agentgraph.h`.
+//!
+//! Functions returning a pointer return null on failure, and
+//! [`agentgraph_last_error`] then describes the failure. Strings returned by
+//! the library are freed with [`agentgraph_string_free`].
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::compiled::CompiledGraph;
+use crate::graph::engine::GraphEngine;
+use crate::graph::services::Services;
+use crate::graph::Graph;
+use crate::manifest::AppManifest;
+use crate::streaming::schema::EventEnvelope;
+use crate::streaming::{EventEmitter, ExecutionEvent};
+use crate::tools::ToolExecutor;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Callback receiving an execution's events as JSON, with the `user_data` it was registered with
+///
+/// The JSON is only valid during the call. Callbacks run on a runtime
+/// thread, one at a time per graph, and must not free the execution.
+pub type AgentGraphEventCallback = Option<extern "C" fn(event_json: *const c_char, user_data: *mut c_void)>;
+
+/// Status of an execution
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentGraphStatus {
+    /// The handle was null
+    Invalid = -1,
+    /// Still running
+    Running = 0,
+    /// Finished successfully
+    Completed = 1,
+    /// Finished with an error
+    Failed = 2,
+}
+
+/// Worker threads executions run on
+pub struct AgentGraphRuntime {
+    runtime: tokio::runtime::Runtime,
+}
+
+/// A compiled graph over a JSON state
+pub struct AgentGraphGraph {
+    graph: Arc<CompiledGraph<Value>>,
+    /// Engine whose siblings run the executions, so nodes are set up once per graph
+    engine: GraphEngine<Value>,
+    runtime: tokio::runtime::Handle,
+    subscribers: Subscribers,
+}
+
+/// A started execution
+pub struct AgentGraphExecution {
+    execution_id: Uuid,
+    outcome: Arc<(Mutex<Outcome>, Condvar)>,
+    subscribers: Subscribers,
+}
+
+type Subscribers = Arc<Mutex<HashMap<Uuid, Subscription>>>;
+
+/// Callback of one execution, emptied when the execution is freed
+type Subscription = Arc<Mutex<Option<Subscriber>>>;
+
+/// Callback of one execution
+#[derive(Clone, Copy)]
+struct Subscriber {
+    callback: extern "C" fn(*const c_char, *mut c_void),
+    user_data: *mut c_void,
+}
+
+// The caller promises `user_data` may be used from the runtime's threads
+unsafe impl Send for Subscriber {}
+
+enum Outcome {
+    Running,
+    Completed(Value),
+    Failed(GraphError),
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run an entry point, turning errors and panics into the last error
+fn guard<T>(f: impl FnOnce() -> GraphResult<T>) -> Option<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(error)) => {
+            set_last_error(error.to_string());
+            None
+        }
+        Err(_) => {
+            set_last_error("agentgraph panicked");
+            None
+        }
+    }
+}
+
+/// Borrowed C string argument, `None` if null
+unsafe fn optional_str<'a>(value: *const c_char, name: &str) -> GraphResult<Option<&'a str>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map(Some)
+        .map_err(|_| GraphError::validation_error(format!("{} is not valid UTF-8", name)))
+}
+
+unsafe fn required_str<'a>(value: *const c_char, name: &str) -> GraphResult<&'a str> {
+    optional_str(value, name)?.ok_or_else(|| GraphError::validation_error(format!("{} is null", name)))
+}
+
+fn into_c_string(value: String) -> *mut c_char {
+    CString::new(value.replace('\0', " ")).unwrap_or_default().into_raw()
+}
+
+impl AgentGraphGraph {
+    /// Compile `graph`, to run on siblings of `engine`, and forward its events to the callbacks of its executions
+    fn new(runtime: tokio::runtime::Handle, mut graph: Graph<Value>, engine: GraphEngine<Value>) -> GraphResult<Self> {
+        let (emitter, receiver) = EventEmitter::new();
+        graph.set_event_emitter(emitter);
+        let subscribers = Subscribers::default();
+        runtime.spawn(dispatch_events(receiver, subscribers.clone()));
+        Ok(Self {
+            graph: Arc::new(graph.compile()?),
+            engine,
+            runtime,
+            subscribers,
+        })
+    }
+
+    fn start(&self, input: Value, subscriber: Option<Subscriber>) -> AgentGraphExecution {
+        let execution_id = Uuid::new_v4();
+        if let Some(subscriber) = subscriber {
+            self.subscribers.lock().unwrap().insert(execution_id, Arc::new(Mutex::new(Some(subscriber))));
+        }
+        let outcome = Arc::new((Mutex::new(Outcome::Running), Condvar::new()));
+
+        let graph = self.graph.clone();
+        let mut engine = self.engine.sibling();
+        let finished = outcome.clone();
+        self.runtime.spawn(async move {
+            let mut state = input;
+            let result = engine.execute_with_id(&graph, &mut state, execution_id).await;
+            let (lock, done) = &*finished;
+            *lock.lock().unwrap() = match result {
+                Ok(_) => Outcome::Completed(state),
+                Err(error) => Outcome::Failed(error),
+            };
+            done.notify_all();
+        });
+
+        AgentGraphExecution {
+            execution_id,
+            outcome,
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+/// Hand each event to the callback of its execution, until the graph is dropped
+async fn dispatch_events(mut receiver: mpsc::UnboundedReceiver<ExecutionEvent>, subscribers: Subscribers) {
+    while let Some(event) = receiver.recv().await {
+        // Not held during the call, so callbacks may start and free other executions
+        let Some(subscription) = subscribers.lock().unwrap().get(&event.execution_id()).cloned() else {
+            continue;
+        };
+        let json = match EventEnvelope::new(&event).and_then(|envelope| Ok(serde_json::to_string(&envelope)?)) {
+            Ok(json) => json,
+            Err(error) => {
+                tracing::warn!(error = %error, "Failed to serialize event for FFI callback");
+                continue;
+            }
+        };
+        let json = CString::new(json).unwrap_or_default();
+        // Held during the call, so a freed execution gets no more callbacks
+        let subscriber = subscription.lock().unwrap();
+        if let Some(subscriber) = &*subscriber {
+            (subscriber.callback)(json.as_ptr(), subscriber.user_data);
+        }
+    }
+}
+
+impl AgentGraphExecution {
+    fn status(&self) -> AgentGraphStatus {
+        match &*self.outcome.0.lock().unwrap() {
+            Outcome::Running => AgentGraphStatus::Running,
+            Outcome::Completed(_) => AgentGraphStatus::Completed,
+            Outcome::Failed(_) => AgentGraphStatus::Failed,
+        }
+    }
+
+    fn wait(&self, timeout: Option<Duration>) -> AgentGraphStatus {
+        let (lock, done) = &*self.outcome;
+        let running = |outcome: &mut Outcome| matches!(outcome, Outcome::Running);
+        let outcome = lock.lock().unwrap();
+        drop(match timeout {
+            Some(timeout) => done.wait_timeout_while(outcome, timeout, running).unwrap().0,
+            None => done.wait_while(outcome, running).unwrap(),
+        });
+        self.status()
+    }
+
+    fn result(&self) -> Value {
+        let mut result = serde_json::json!({ "execution_id": self.execution_id.to_string() });
+        match &*self.outcome.0.lock().unwrap() {
+            Outcome::Running => {
+                result["status"] = "running".into();
+            }
+            Outcome::Completed(state) => {
+                result["status"] = "completed".into();
+                result["state"] = state.clone();
+            }
+            Outcome::Failed(error) => {
+                result["status"] = "failed".into();
+                result["error"] = error.to_string().into();
+                result["category"] = error.category().into();
+            }
+        }
+        result
+    }
+}
+
+impl Drop for AgentGraphExecution {
+    fn drop(&mut self) {
+        let subscription = self.subscribers.lock().unwrap().remove(&self.execution_id);
+        // Waits for a callback under way to return
+        if let Some(subscription) = subscription {
+            subscription.lock().unwrap().take();
+        }
+    }
+}
+
+/// Message of the last failure on the calling thread, or null
+///
+/// The string belongs to the library and is valid until the next call that
+/// fails on the same thread.
+#[no_mangle]
+pub extern "C" fn agentgraph_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |message| message.as_ptr()))
+}
+
+/// Free a string returned by the library
+///
+/// # Safety
+///
+/// `value` must be null or a string returned by the library, not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Create a runtime with `worker_threads` threads, or one per core when 0
+#[no_mangle]
+pub extern "C" fn agentgraph_runtime_new(worker_threads: u32) -> *mut AgentGraphRuntime {
+    guard(|| {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        if worker_threads > 0 {
+            builder.worker_threads(worker_threads as usize);
+        }
+        let runtime = builder
+            .thread_name("agentgraph-ffi")
+            .enable_all()
+            .build()
+            .map_err(|e| GraphError::Internal(format!("Failed to start runtime: {}", e)))?;
+        Ok(Box::into_raw(Box::new(AgentGraphRuntime { runtime })))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Shut a runtime down, cancelling the executions still running on it
+///
+/// # Safety
+///
+/// `runtime` must be null or a runtime from [`agentgraph_runtime_new`] not
+/// freed yet. Graphs loaded on it must not start executions afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_runtime_free(runtime: *mut AgentGraphRuntime) {
+    if !runtime.is_null() {
+        let runtime = Box::from_raw(runtime);
+        runtime.runtime.shutdown_background();
+    }
+}
+
+/// Load a graph declared in an application manifest and compile it
+///
+/// `environment` selects the manifest's environment overrides and may be null.
+///
+/// # Safety
+///
+/// `runtime` must be a live runtime and the strings null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_graph_load(
+    runtime: *const AgentGraphRuntime,
+    manifest_path: *const c_char,
+    environment: *const c_char,
+    graph_name: *const c_char,
+) -> *mut AgentGraphGraph {
+    guard(|| {
+        let runtime = runtime.as_ref().ok_or_else(|| GraphError::validation_error("runtime is null"))?;
+        let path = required_str(manifest_path, "manifest_path")?;
+        let environment = optional_str(environment, "environment")?;
+        let name = required_str(graph_name, "graph_name")?;
+
+        // Providers and tools may spawn tasks while they are created
+        let _entered = runtime.runtime.enter();
+        let manifest = AppManifest::load(Path::new(path), environment)?;
+        let llm_manager = Arc::new(manifest.llm_manager()?);
+        let tool_registry = Arc::new(manifest.tool_registry()?);
+        let tool_executor = Arc::new(ToolExecutor::new());
+        let context = manifest.graph_context(name, llm_manager.clone(), tool_registry.clone(), tool_executor.clone())?;
+        let graph = manifest.build_graph(name, &context)?;
+        // Nodes resolve the manifest's clients instead of building their own
+        let services = Services::new()
+            .with_arc(llm_manager)
+            .with_arc(tool_registry)
+            .with_arc(tool_executor);
+        let engine = GraphEngine::new().with_services(services);
+        Ok(Box::into_raw(Box::new(AgentGraphGraph::new(runtime.runtime.handle().clone(), graph, engine)?)))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Free a graph; executions already started keep running
+///
+/// # Safety
+///
+/// `graph` must be null or a graph from [`agentgraph_graph_load`] not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_graph_free(graph: *mut AgentGraphGraph) {
+    if !graph.is_null() {
+        drop(Box::from_raw(graph));
+    }
+}
+
+/// Start an execution on a JSON object state, `{}` when `input_json` is null
+///
+/// `callback`, if not null, is called with each of the execution's events
+/// and `user_data` until the execution is freed.
+///
+/// # Safety
+///
+/// `graph` must be a live graph and `input_json` null or NUL-terminated.
+/// `user_data` must stay valid, and usable from other threads, until the
+/// execution is freed.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_execution_start(
+    graph: *const AgentGraphGraph,
+    input_json: *const c_char,
+    callback: AgentGraphEventCallback,
+    user_data: *mut c_void,
+) -> *mut AgentGraphExecution {
+    guard(|| {
+        let graph = graph.as_ref().ok_or_else(|| GraphError::validation_error("graph is null"))?;
+        let input = match optional_str(input_json, "input_json")? {
+            Some(json) => serde_json::from_str(json)?,
+            None => Value::Object(Default::default()),
+        };
+        if !input.is_object() {
+            return Err(GraphError::validation_error("input_json must be a JSON object"));
+        }
+        let subscriber = callback.map(|callback| Subscriber { callback, user_data });
+        Ok(Box::into_raw(Box::new(graph.start(input, subscriber))))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Status of an execution, without blocking
+///
+/// # Safety
+///
+/// `execution` must be null or a live execution.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_execution_status(execution: *const AgentGraphExecution) -> AgentGraphStatus {
+    guard(|| Ok(execution.as_ref().map_or(AgentGraphStatus::Invalid, AgentGraphExecution::status)))
+        .unwrap_or(AgentGraphStatus::Invalid)
+}
+
+/// Block until an execution finishes or `timeout_ms` passes, negative to wait forever
+///
+/// # Safety
+///
+/// `execution` must be null or a live execution.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_execution_wait(
+    execution: *const AgentGraphExecution,
+    timeout_ms: i64,
+) -> AgentGraphStatus {
+    let timeout = u64::try_from(timeout_ms).ok().map(Duration::from_millis);
+    guard(|| Ok(execution.as_ref().map_or(AgentGraphStatus::Invalid, |execution| execution.wait(timeout))))
+        .unwrap_or(AgentGraphStatus::Invalid)
+}
+
+/// Execution ID and status as JSON, with the final state or the error once finished
+///
+/// Free the string with [`agentgraph_string_free`].
+///
+/// # Safety
+///
+/// `execution` must be null or a live execution.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_execution_result(execution: *const AgentGraphExecution) -> *mut c_char {
+    guard(|| {
+        let execution = execution.as_ref().ok_or_else(|| GraphError::validation_error("execution is null"))?;
+        Ok(into_c_string(execution.result().to_string()))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Free an execution, stopping its callbacks; a running execution keeps running
+///
+/// # Safety
+///
+/// `execution` must be null or a live execution, and not be freed from its
+/// own callback.
+#[no_mangle]
+pub unsafe extern "C" fn agentgraph_execution_free(execution: *mut AgentGraphExecution) {
+    if !execution.is_null() {
+        guard(|| {
+            drop(Box::from_raw(execution));
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphBuilder;
+    use crate::node::Node;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug)]
+    struct GreetNode;
+
+    #[async_trait]
+    impl Node<Value> for GreetNode {
+        async fn invoke(&self, state: &mut Value) -> GraphResult<()> {
+            let name = state["name"].as_str().unwrap_or("world").to_string();
+            state["greeting"] = format!("Hello, {}", name).into();
+            Ok(())
+        }
+    }
+
+    extern "C" fn count_event(event_json: *const c_char, user_data: *mut c_void) {
+        let event: Value = serde_json::from_str(unsafe { CStr::from_ptr(event_json) }.to_str().unwrap()).unwrap();
+        assert!(event["type"].is_string());
+        unsafe { &*(user_data as *const AtomicUsize) }.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// A graph greeting `name`, loaded on `runtime`
+    fn greet_graph(runtime: *mut AgentGraphRuntime) -> *mut AgentGraphGraph {
+        let graph = GraphBuilder::new()
+            .add_node("greet".to_string(), GreetNode).unwrap()
+            .with_entry_point("greet".to_string()).unwrap()
+            .add_finish_point("greet".to_string()).unwrap()
+            .build().unwrap();
+        let handle = unsafe { &*runtime }.runtime.handle().clone();
+        Box::into_raw(Box::new(AgentGraphGraph::new(handle, graph, GraphEngine::new()).unwrap()))
+    }
+
+    #[test]
+    fn test_ffi_execution_reports_events_and_result() {
+        let runtime = agentgraph_runtime_new(2);
+        assert!(!runtime.is_null());
+        let graph = greet_graph(runtime);
+
+        unsafe {
+            let events = AtomicUsize::new(0);
+            let input = CString::new(r#"{"name": "Ada"}"#).unwrap();
+            let execution = agentgraph_execution_start(
+                graph,
+                input.as_ptr(),
+                Some(count_event),
+                &events as *const AtomicUsize as *mut c_void,
+            );
+            assert!(!execution.is_null());
+            assert_eq!(agentgraph_execution_wait(execution, 5_000), AgentGraphStatus::Completed);
+
+            let result = agentgraph_execution_result(execution);
+            let json: Value = serde_json::from_str(CStr::from_ptr(result).to_str().unwrap()).unwrap();
+            agentgraph_string_free(result);
+            assert_eq!(json["status"], "completed");
+            assert_eq!(json["state"]["greeting"], "Hello, Ada");
+
+            // Events are delivered after the result is set; give the dispatcher a moment
+            for _ in 0..50 {
+                if events.load(Ordering::SeqCst) >= 2 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert!(events.load(Ordering::SeqCst) >= 2);
+            agentgraph_execution_free(execution);
+
+            let input = CString::new("[1, 2]").unwrap();
+            assert!(agentgraph_execution_start(graph, input.as_ptr(), None, std::ptr::null_mut()).is_null());
+            let error = CStr::from_ptr(agentgraph_last_error()).to_str().unwrap();
+            assert!(error.contains("JSON object"), "{}", error);
+
+            agentgraph_graph_free(graph);
+            agentgraph_runtime_free(runtime);
+        }
+    }
+
+    struct Reentrant {
+        graph: *const AgentGraphGraph,
+        calls: AtomicUsize,
+    }
+
+    extern "C" fn start_another(_event_json: *const c_char, user_data: *mut c_void) {
+        let reentrant = unsafe { &*(user_data as *const Reentrant) };
+        // Starting and freeing an execution both take the subscribers lock
+        unsafe {
+            let execution = agentgraph_execution_start(reentrant.graph, std::ptr::null(), None, std::ptr::null_mut());
+            agentgraph_execution_free(execution);
+        }
+        reentrant.calls.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_ffi_callback_may_start_and_free_executions() {
+        let runtime = agentgraph_runtime_new(2);
+        let graph = greet_graph(runtime);
+
+        unsafe {
+            let reentrant = Reentrant { graph, calls: AtomicUsize::new(0) };
+            let execution = agentgraph_execution_start(
+                graph,
+                std::ptr::null(),
+                Some(start_another),
+                &reentrant as *const Reentrant as *mut c_void,
+            );
+            assert_eq!(agentgraph_execution_wait(execution, 5_000), AgentGraphStatus::Completed);
+            for _ in 0..50 {
+                if reentrant.calls.load(Ordering::SeqCst) >= 2 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert!(reentrant.calls.load(Ordering::SeqCst) >= 2);
+            agentgraph_execution_free(execution);
+
+            assert_eq!(agentgraph_execution_status(std::ptr::null()), AgentGraphStatus::Invalid);
+            assert!(agentgraph_execution_result(std::ptr::null()).is_null());
+
+            agentgraph_graph_free(graph);
+            agentgraph_runtime_free(runtime);
+        }
+    }
+}
@@ -1,9 +1,15 @@
 //! Agent nodes for integrating AI agents into graph workflows
 //! This module bridges the gap between the graph workflow system and the AI agent system
 
+use crate::agents::prompts::PromptRegistry;
 use crate::agents::Agent;
+use crate::enterprise::guardrails::{InputOrigin, PromptGuard, ScreeningSubject};
+use crate::enterprise::moderation::Moderator;
 use crate::error::{GraphError, GraphResult};
+use crate::llm::LLMUsage;
 use crate::graph::command::{Command, CommandParser, CommandContext};
+use crate::graph::flags::FlagContext;
+use crate::graph::validate_node::{OnInvalid, ValidateNode};
 use crate::node::{Node, NodeMetadata};
 use crate::state::State;
 use async_trait::async_trait;
@@ -27,6 +33,14 @@ pub struct AgentNode {
     command_parser: CommandParser,
     /// Whether this node supports command-based routing
     supports_routing: bool,
+    /// Screens state values for prompt injection before they enter the task
+    prompt_guard: Option<Arc<PromptGuard>>,
+    /// Moderates the agent's response before it is written to the state
+    moderator: Option<Arc<Moderator>>,
+    /// Registry and name of the prompt used as the agent's system prompt
+    system_prompt: Option<(Arc<PromptRegistry>, String)>,
+    /// Validates the response, which is asked for again with the errors while invalid
+    validator: Option<Arc<ValidateNode>>,
     /// Node metadata
     metadata: NodeMetadata,
 }
@@ -37,7 +51,8 @@ impl AgentNode {
         let metadata = NodeMetadata::new("AgentNode")
             .with_description("AI agent execution node")
             .with_tag("agent")
-            .with_parallel_safe(true);
+            .with_parallel_safe(true)
+            .with_llm(agent.config().model.clone(), agent.config().max_tokens);
 
         Self {
             agent: Arc::new(Mutex::new(agent)),
@@ -46,6 +61,10 @@ impl AgentNode {
             output_mapping: HashMap::new(),
             command_parser: CommandParser::new(),
             supports_routing: false,
+            prompt_guard: None,
+            moderator: None,
+            system_prompt: None,
+            validator: None,
             metadata,
         }
     }
@@ -56,7 +75,8 @@ impl AgentNode {
             .with_description("AI agent execution node with command routing")
             .with_tag("agent")
             .with_tag("routing")
-            .with_parallel_safe(false); // Routing nodes should be sequential
+            .with_parallel_safe(false) // Routing nodes should be sequential
+            .with_llm(agent.config().model.clone(), agent.config().max_tokens);
 
         Self {
             agent: Arc::new(Mutex::new(agent)),
@@ -65,6 +85,10 @@ impl AgentNode {
             output_mapping: HashMap::new(),
             command_parser: CommandParser::new(),
             supports_routing: true,
+            prompt_guard: None,
+            moderator: None,
+            system_prompt: None,
+            validator: None,
             metadata,
         }
     }
@@ -79,13 +103,18 @@ impl AgentNode {
         let metadata = NodeMetadata::new("AgentNode")
             .with_description("AI agent execution node with mapping")
             .with_tag("agent")
-            .with_parallel_safe(true);
+            .with_parallel_safe(true)
+            .with_llm(agent.config().model.clone(), agent.config().max_tokens);
 
         Self {
             agent: Arc::new(Mutex::new(agent)),
             task_template,
             input_mapping,
             output_mapping,
+            prompt_guard: None,
+            moderator: None,
+            system_prompt: None,
+            validator: None,
             metadata,
         }
     }
@@ -114,8 +143,59 @@ impl AgentNode {
         self
     }
 
+    /// Screen the state values placed in the task for prompt injection
+    ///
+    /// Values are treated as user input; screen retrieved documents where
+    /// they are retrieved, e.g. with [`RetrievalNode::with_prompt_guard`](crate::graph::retrieval_node::RetrievalNode::with_prompt_guard).
+    pub fn with_prompt_guard(mut self, guard: PromptGuard) -> Self {
+        self.prompt_guard = Some(Arc::new(guard));
+        self
+    }
+
+    /// Moderate the agent's response under the tenant's policy before it reaches the state
+    ///
+    /// A blocked response fails the node with a validation error.
+    pub fn with_moderator(mut self, moderator: Moderator) -> Self {
+        self.moderator = Some(Arc::new(moderator));
+        self
+    }
+
+    /// Take the agent's system prompt, and persona settings, from the active version of a registry prompt
+    ///
+    /// The prompt is resolved on every run, so rollbacks and reloaded
+    /// prompts apply from the next run on.
+    pub fn with_system_prompt_from(mut self, registry: Arc<PromptRegistry>, name: impl Into<String>) -> Self {
+        self.system_prompt = Some((registry, name.into()));
+        self
+    }
+
+    /// Validate the response in the node, re-asking the agent with the errors while it is invalid
+    ///
+    /// The response is written to the state and checked by `validator` after
+    /// every attempt. An invalid one is sent back to the agent with the
+    /// validator's feedback appended to the task, until the validator's
+    /// `max_attempts` have failed and the node fails with the report. The
+    /// validator's report and feedback keys are written as when it runs as a
+    /// node of its own; its [`OnInvalid`] target is not used.
+    pub fn with_output_validation(mut self, validator: ValidateNode) -> Self {
+        self.validator = Some(Arc::new(validator));
+        self
+    }
+
+    /// Apply the registry prompt to the agent, if the node has one
+    async fn apply_system_prompt(&self, agent: &mut Agent) -> GraphResult<()> {
+        let Some((registry, name)) = &self.system_prompt else {
+            return Ok(());
+        };
+        let prompt = registry.get(name).await?;
+        let mut config = agent.config().clone();
+        prompt.apply_to(&mut config);
+        agent.update_config(config)
+            .map_err(|e| GraphError::node_error("agent_node".to_string(), e.to_string(), None))
+    }
+
     /// Build task from template and state
-    fn build_task<S: State>(&self, state: &S) -> GraphResult<String> {
+    async fn build_task<S: State>(&self, state: &S) -> GraphResult<String> {
         let mut task = self.task_template.clone();
         
         // Replace placeholders with state values
@@ -126,6 +206,7 @@ impl AgentNode {
                     serde_json::Value::String(s) => s,
                     other => other.to_string(),
                 };
+                let value_str = self.screen(state, state_key, value_str).await?;
                 task = task.replace(&placeholder, &value_str);
             }
         }
@@ -137,6 +218,7 @@ impl AgentNode {
                     serde_json::Value::String(s) => s,
                     other => other.to_string(),
                 };
+                let input_str = self.screen(state, "input", input_str).await?;
                 task = task.replace("{input}", &input_str);
             }
         }
@@ -147,6 +229,7 @@ impl AgentNode {
                     serde_json::Value::String(s) => s,
                     other => other.to_string(),
                 };
+                let query_str = self.screen(state, "query", query_str).await?;
                 task = task.replace("{query}", &query_str);
             }
         }
@@ -154,6 +237,85 @@ impl AgentNode {
         Ok(task)
     }
 
+    /// Pass a state value through the prompt guard, if the node has one
+    async fn screen<S: State>(&self, state: &S, key: &str, value: String) -> GraphResult<String> {
+        let Some(guard) = &self.prompt_guard else {
+            return Ok(value);
+        };
+        let subject = FlagContext::from_state(state);
+        let screened = guard.screen(&value, InputOrigin::User, key, ScreeningSubject {
+            tenant_id: subject.tenant.as_deref(),
+            user_id: subject.user.as_deref(),
+        }).await.map_err(|e| GraphError::validation_error(e.to_string()))?;
+        Ok(screened.text)
+    }
+
+    /// Pass the agent's response through the moderator, if the node has one
+    async fn moderate<S: State>(&self, state: &S, agent: &Agent, response: String) -> GraphResult<String> {
+        let Some(moderator) = &self.moderator else {
+            return Ok(response);
+        };
+        let tenant = FlagContext::from_state(state).tenant;
+        let moderated = moderator.moderate(&response, &agent.config().name, tenant.as_deref()).await
+            .map_err(|e| GraphError::validation_error(e.to_string()))?;
+        Ok(moderated.text)
+    }
+
+    /// Run the agent on the task built from the state and return its moderated response
+    ///
+    /// With output validation, the response is also written to the state and
+    /// the agent is asked again, with the errors, while it is invalid.
+    async fn respond<S: State>(&self, state: &mut S) -> GraphResult<String> {
+        // Build task from template and state
+        let task = self.build_task(state).await?;
+
+        tracing::debug!("Built task: {}", task);
+
+        let mut agent = self.agent.lock().await;
+        self.apply_system_prompt(&mut agent).await?;
+        // Every attempt's usage is reported, whether or not the output is kept
+        let mut usage = LLMUsage::default();
+        let result = self.attempt(state, &mut agent, &task, &mut usage).await;
+        report_llm_usage(&usage);
+        result
+    }
+
+    async fn attempt<S: State>(&self, state: &mut S, agent: &mut Agent, task: &str, usage: &mut LLMUsage) -> GraphResult<String> {
+        let mut prompt = task.to_string();
+        let mut attempt = 1;
+        loop {
+            let response = agent.execute_task(prompt).await
+                .map_err(|e| GraphError::node_error(
+                    "agent_node".to_string(),
+                    format!("Agent execution failed: {}", e),
+                    Some(Box::new(e)),
+                ))?;
+
+            tracing::info!("Agent response received: {} characters", response.len());
+            usage.merge(&agent.state().last_task_usage);
+            let response = self.moderate(state, agent, response).await?;
+
+            let Some(validator) = &self.validator else {
+                return Ok(response);
+            };
+            self.update_state(state, &response)?;
+            let report = validator.check(state, attempt, &OnInvalid::Fail)?;
+            if report.valid {
+                return Ok(response);
+            }
+            if attempt >= validator.max_attempts() {
+                return Err(GraphError::node_error(
+                    "agent_node".to_string(),
+                    format!("Agent output failed validation: {}", report),
+                    Some(Box::new(report)),
+                ));
+            }
+            tracing::info!("Agent output failed validation attempt {}, asking again", attempt);
+            prompt = format!("{}\n\nYour previous answer was rejected. {}\nAnswer again, fixing these errors.", task, report.feedback());
+            attempt += 1;
+        }
+    }
+
     /// Update state with agent response
     fn update_state<S: State>(&self, state: &mut S, response: &str) -> GraphResult<()> {
         // Default output mapping
@@ -193,21 +355,7 @@ impl AgentNode {
     pub async fn invoke_with_command<S: State>(&self, state: &mut S, context: &CommandContext) -> GraphResult<Command> {
         tracing::info!("Executing agent node with command routing: {}", self.task_template);
 
-        // Build task from template and state
-        let task = self.build_task(state)?;
-
-        tracing::debug!("Built task: {}", task);
-
-        // Execute agent task
-        let mut agent = self.agent.lock().await;
-        let response = agent.execute_task(task).await
-            .map_err(|e| GraphError::node_error(
-                "agent_node".to_string(),
-                format!("Agent execution failed: {}", e),
-                Some(Box::new(e)),
-            ))?;
-
-        tracing::info!("Agent response received: {} characters", response.len());
+        let response = self.respond(state).await?;
 
         // Parse command from response if routing is supported
         let command = if self.supports_routing {
@@ -259,6 +407,15 @@ impl AgentNode {
     }
 }
 
+/// Report an agent task's LLM usage so the engine can attach it to the node execution
+pub(crate) fn report_llm_usage(usage: &LLMUsage) {
+    if usage.is_empty() {
+        return;
+    }
+    crate::graph::session_limits::charge_tokens(usage.total_tokens);
+    crate::graph::report::llm_usage(usage);
+}
+
 /// Statistics for agent node performance
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentStats {
@@ -276,21 +433,7 @@ where
     async fn invoke(&self, state: &mut S) -> GraphResult<()> {
         tracing::info!("Executing agent node with task template: {}", self.task_template);
 
-        // Build task from template and state
-        let task = self.build_task(state)?;
-        
-        tracing::debug!("Built task: {}", task);
-
-        // Execute agent task
-        let mut agent = self.agent.lock().await;
-        let response = agent.execute_task(task).await
-            .map_err(|e| GraphError::node_error(
-                "agent_node".to_string(),
-                format!("Agent execution failed: {}", e),
-                Some(Box::new(e)),
-            ))?;
-
-        tracing::info!("Agent response received: {} characters", response.len());
+        let response = self.respond(state).await?;
 
         // Update state with response
         self.update_state(state, &response)?;
@@ -375,6 +518,7 @@ impl Default for AgentNodeBuilder {
 mod tests {
     use super::*;
     use crate::agents::roles::RoleTemplates;
+    use crate::graph::report::NodeReports;
     use crate::llm::{LLMManager, LLMConfig, providers::MockProvider};
     use crate::tools::{ToolRegistry, ToolExecutor};
     use serde_json::json;
@@ -416,9 +560,12 @@ mod tests {
     }
 
     async fn create_test_agent() -> Agent {
+        create_agent_with(MockProvider::new()).await
+    }
+
+    async fn create_agent_with(mock_provider: MockProvider) -> Agent {
         let llm_config = LLMConfig::default();
         let mut llm_manager = LLMManager::new(llm_config);
-        let mock_provider = MockProvider::new();
         llm_manager.register_provider("mock".to_string(), Arc::new(mock_provider));
         
         let tool_registry = Arc::new(ToolRegistry::new());
@@ -451,9 +598,43 @@ mod tests {
             metadata: HashMap::new(),
         };
         
-        agent_node.invoke(&mut state).await.unwrap();
+        let reports = NodeReports::default();
+        reports.scope(agent_node.invoke(&mut state)).await.unwrap();
         
         assert!(!state.output.is_empty());
+
+        let usage = reports.take().llm_usage.unwrap();
+        assert_eq!(usage.calls, 1);
+        assert!(usage.total_tokens > 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_output_is_retried_with_feedback() {
+        let schema = json!({"type": "object", "required": ["total"]});
+        let replies = vec!["The total is 12".to_string(), r#"{"total": 12}"#.to_string()];
+        let agent = create_agent_with(MockProvider::with_responses(replies)).await;
+        let agent_node = AgentNode::new(agent, "Extract the total: {input}".to_string())
+            .with_output_validation(ValidateNode::new().with_schema("output", &schema).unwrap());
+        let mut state = TestState {
+            input: "Invoice INV-7, total 12 EUR".to_string(),
+            output: String::new(),
+            metadata: HashMap::new(),
+        };
+
+        let reports = NodeReports::default();
+        reports.scope(agent_node.invoke(&mut state)).await.unwrap();
+        assert_eq!(state.output, r#"{"total": 12}"#);
+        assert_eq!(state.metadata["validation"]["attempt"], json!(2));
+        assert_eq!(state.metadata["validation"]["valid"], json!(true));
+        assert!(state.metadata["validation_feedback"].is_null());
+        let usage = reports.take().llm_usage.unwrap();
+        assert_eq!(usage.calls, 2);
+
+        let agent = create_agent_with(MockProvider::with_responses(vec!["no idea".to_string()])).await;
+        let stubborn = AgentNode::new(agent, "Extract the total: {input}".to_string())
+            .with_output_validation(ValidateNode::new().with_schema("output", &schema).unwrap().with_max_attempts(2));
+        assert!(stubborn.invoke(&mut state).await.is_err());
+        assert!(state.metadata["validation_feedback"].as_str().unwrap().contains("output"));
     }
 
     #[tokio::test]
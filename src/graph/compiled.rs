@@ -0,0 +1,313 @@
+//! Graph compilation into an immutable, pre-planned form.
+
+use crate::edge::{Edge, EdgeType};
+use crate::error::{GraphError, GraphResult};
+use crate::graph::Graph;
+use crate::node::NodeId;
+use crate::state::State;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// How the engine leaves a node of a compiled graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompiledRoute {
+    /// Plain simple edge, followed without looking at the edge
+    Next(NodeId),
+    /// Edge that must be resolved on each traversal, by index into [`Graph::edges`]
+    Edge(usize),
+}
+
+/// Routing decisions made once when a graph is compiled
+///
+/// The engine consults the plan instead of scanning the edge list after every
+/// node, and follows plain simple edges directly. Edges with a condition,
+/// router, rate limit or speculation are still resolved per traversal, but
+/// their conditions and routers are known to be registered.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionPlan {
+    routes: HashMap<NodeId, CompiledRoute>,
+    chains: Vec<Vec<NodeId>>,
+    levels: Vec<Vec<NodeId>>,
+}
+
+impl ExecutionPlan {
+    /// Build the plan for a graph, checking that every condition and router is registered
+    pub(crate) fn build<S: State>(graph: &Graph<S>) -> GraphResult<Self> {
+        let registry = graph.edge_registry();
+        for edge in graph.edges() {
+            match &edge.edge_type {
+                EdgeType::Conditional { condition_id, .. } if registry.get_condition(condition_id).is_none() => {
+                    return Err(GraphError::graph_structure(format!(
+                        "Edge '{}' uses unregistered condition '{}'",
+                        edge.label(),
+                        condition_id
+                    )));
+                }
+                EdgeType::Dynamic { router_id, .. } if registry.get_router(router_id).is_none() => {
+                    return Err(GraphError::graph_structure(format!(
+                        "Edge '{}' uses unregistered router '{}'",
+                        edge.label(),
+                        router_id
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        // The engine follows the first outgoing edge of a node
+        let mut routes = HashMap::new();
+        for (index, edge) in graph.edges().iter().enumerate() {
+            if routes.contains_key(&edge.from) {
+                continue;
+            }
+            let route = match &edge.edge_type {
+                EdgeType::Simple { target } if Self::is_plain(edge) => CompiledRoute::Next(target.clone()),
+                _ => CompiledRoute::Edge(index),
+            };
+            routes.insert(edge.from.clone(), route);
+        }
+
+        let mut plan = Self {
+            routes,
+            chains: Vec::new(),
+            levels: Vec::new(),
+        };
+        plan.chains = plan.find_chains(graph);
+        plan.levels = plan.find_levels(graph);
+        Ok(plan)
+    }
+
+    fn is_plain(edge: &Edge) -> bool {
+        edge.metadata.rate_limit.is_none() && edge.metadata.speculation.is_none()
+    }
+
+    /// Maximal runs of nodes joined by plain edges, each entered only from its predecessor
+    fn find_chains<S: State>(&self, graph: &Graph<S>) -> Vec<Vec<NodeId>> {
+        let mut incoming: HashMap<&NodeId, usize> = HashMap::new();
+        for edge in graph.edges() {
+            for target in edge.possible_targets() {
+                *incoming.entry(target).or_default() += 1;
+            }
+        }
+        let is_link = |from: &NodeId| -> Option<&NodeId> {
+            match self.routes.get(from)? {
+                CompiledRoute::Next(to) if incoming.get(to) == Some(&1) && !graph.finish_points().contains(from) => Some(to),
+                _ => None,
+            }
+        };
+        let linked: HashSet<&NodeId> = self.routes.keys().filter_map(|from| is_link(from)).collect();
+
+        let mut chains = Vec::new();
+        for head in graph.node_ids() {
+            // Chains start at a node nothing links into; cycles of links are left alone
+            if linked.contains(head) || is_link(head).is_none() {
+                continue;
+            }
+            let mut chain = vec![head.clone()];
+            let mut current = head;
+            while let Some(next) = is_link(current) {
+                if chain.contains(next) {
+                    break;
+                }
+                chain.push(next.clone());
+                current = next;
+            }
+            chains.push(chain);
+        }
+        chains.sort();
+        chains
+    }
+
+    /// Breadth-first levels over the routes the engine will take
+    fn find_levels<S: State>(&self, graph: &Graph<S>) -> Vec<Vec<NodeId>> {
+        let Some(entry) = graph.entry_point() else {
+            return Vec::new();
+        };
+        let mut levels: Vec<Vec<NodeId>> = Vec::new();
+        let mut seen = HashSet::from([entry.clone()]);
+        let mut queue = VecDeque::from([(entry.clone(), 0usize)]);
+
+        while let Some((node, depth)) = queue.pop_front() {
+            if levels.len() <= depth {
+                levels.push(Vec::new());
+            }
+            levels[depth].push(node.clone());
+            if graph.finish_points().contains(&node) {
+                continue;
+            }
+            let targets: Vec<&NodeId> = match self.routes.get(&node) {
+                Some(CompiledRoute::Next(target)) => vec![target],
+                Some(CompiledRoute::Edge(index)) => graph.edges()[*index].possible_targets(),
+                None => Vec::new(),
+            };
+            for target in targets {
+                if seen.insert(target.clone()) {
+                    queue.push_back((target.clone(), depth + 1));
+                }
+            }
+        }
+
+        for level in &mut levels {
+            level.sort();
+        }
+        levels
+    }
+
+    /// Route out of `node_id`, or `None` if the node has no outgoing edge
+    pub fn route(&self, node_id: &NodeId) -> Option<&CompiledRoute> {
+        self.routes.get(node_id)
+    }
+
+    /// Node reached from `node_id` over a plain simple edge
+    pub fn next(&self, node_id: &NodeId) -> Option<&NodeId> {
+        match self.routes.get(node_id)? {
+            CompiledRoute::Next(target) => Some(target),
+            CompiledRoute::Edge(_) => None,
+        }
+    }
+
+    /// Sequences of nodes run back to back without routing
+    pub fn chains(&self) -> &[Vec<NodeId>] {
+        &self.chains
+    }
+
+    /// Reachable nodes grouped by their shortest distance from the entry point
+    pub fn levels(&self) -> &[Vec<NodeId>] {
+        &self.levels
+    }
+
+    /// Level of a node, if it is reachable from the entry point
+    pub fn level_of(&self, node_id: &NodeId) -> Option<usize> {
+        self.levels.iter().position(|level| level.contains(node_id))
+    }
+}
+
+/// A validated graph with its execution plan, ready to be run many times
+///
+/// Created with [`Graph::compile`]. The graph can no longer be modified; pass
+/// it to [`GraphEngine::execute`](crate::graph::engine::GraphEngine::execute)
+/// like any other graph, which then skips validation and per-step edge lookups.
+/// Use [`into_graph`](Self::into_graph) to get an editable graph back.
+pub struct CompiledGraph<S>
+where
+    S: State,
+{
+    graph: Graph<S>,
+}
+
+impl<S> CompiledGraph<S>
+where
+    S: State,
+{
+    pub(crate) fn new(mut graph: Graph<S>) -> GraphResult<Self> {
+        graph.validate()?;
+        graph.plan = Some(Arc::new(ExecutionPlan::build(&graph)?));
+        Ok(Self { graph })
+    }
+
+    /// The precomputed plan
+    pub fn plan(&self) -> &ExecutionPlan {
+        self.graph.plan.as_deref().expect("compiled graph always has a plan")
+    }
+
+    /// Discard the plan and return the editable graph
+    pub fn into_graph(mut self) -> Graph<S> {
+        self.graph.plan = None;
+        self.graph
+    }
+}
+
+impl<S> Deref for CompiledGraph<S>
+where
+    S: State,
+{
+    type Target = Graph<S>;
+
+    fn deref(&self) -> &Graph<S> {
+        &self.graph
+    }
+}
+
+impl<S> std::fmt::Debug for CompiledGraph<S>
+where
+    S: State,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledGraph")
+            .field("name", &self.graph.metadata().name)
+            .field("plan", self.plan())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::node::Node;
+    use async_trait::async_trait;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    struct TestState {
+        value: i32,
+    }
+
+    #[derive(Debug)]
+    struct NoopNode;
+
+    #[async_trait]
+    impl Node<TestState> for NoopNode {
+        async fn invoke(&self, _state: &mut TestState) -> GraphResult<()> {
+            Ok(())
+        }
+    }
+
+    fn graph(names: &[&str]) -> Graph<TestState> {
+        let mut graph = Graph::new();
+        for name in names {
+            graph.add_node(name.to_string(), NoopNode).unwrap();
+        }
+        graph.set_entry_point(names[0].to_string()).unwrap();
+        graph
+    }
+
+    #[test]
+    fn test_compile_plans_chains_and_levels() {
+        // a -> b -> c -> {d, e} in parallel, e -> f
+        let mut graph = graph(&["a", "b", "c", "d", "e", "f"]);
+        graph.add_edge(Edge::simple("a", "b")).unwrap();
+        graph.add_edge(Edge::simple("b", "c")).unwrap();
+        graph.add_edge(Edge::parallel("c", vec!["d".to_string(), "e".to_string()])).unwrap();
+        graph.add_edge(Edge::simple("e", "f")).unwrap();
+        graph.add_finish_point("d".to_string()).unwrap();
+        graph.add_finish_point("f".to_string()).unwrap();
+
+        let compiled = graph.compile().unwrap();
+        let plan = compiled.plan();
+        assert_eq!(plan.next(&"a".to_string()), Some(&"b".to_string()));
+        assert_eq!(plan.route(&"c".to_string()), Some(&CompiledRoute::Edge(2)));
+        assert_eq!(plan.route(&"f".to_string()), None);
+        assert_eq!(plan.chains(), &[
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            vec!["e".to_string(), "f".to_string()],
+        ]);
+        assert_eq!(plan.levels().len(), 5);
+        assert_eq!(plan.levels()[3], vec!["d".to_string(), "e".to_string()]);
+        assert_eq!(plan.level_of(&"f".to_string()), Some(4));
+
+        // The plan goes away with the graph's immutability
+        assert!(compiled.into_graph().plan.is_none());
+    }
+
+    #[test]
+    fn test_compile_rejects_unregistered_condition() {
+        let mut graph = graph(&["a", "b", "c"]);
+        graph.add_edge(Edge::conditional("a", "missing".to_string(), "b", "c")).unwrap();
+        graph.add_finish_point("b".to_string()).unwrap();
+        graph.add_finish_point("c".to_string()).unwrap();
+
+        let error = graph.compile().unwrap_err();
+        assert!(error.to_string().contains("missing"));
+    }
+}
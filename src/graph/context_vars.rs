@@ -0,0 +1,254 @@
+//! Execution context variables, kept apart from business state.
+//!
+//! Who a graph runs for and how — the user and tenant IDs, the locale, the
+//! experiment arms the request was assigned to — is known to the caller but
+//! is not part of the work the graph does. Putting it in the [`State`] struct
+//! would make every state type carry it and would copy it into the results
+//! and checkpoints of every run. Instead the caller runs the graph with
+//! [`ContextVars`] through
+//! [`GraphEngine::execute_with_context`](crate::graph::engine::GraphEngine::execute_with_context)
+//! or [`scope`], and nodes, edge conditions and tools read them with
+//! [`current`] or [`get`] while the execution runs. They are never written
+//! to the state.
+
+use crate::edge::EdgeCondition;
+use crate::error::GraphResult;
+use crate::state::State;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Variable holding the ID of the user the execution runs for
+pub const USER_ID: &str = "user_id";
+/// Variable holding the ID of the tenant the execution runs for
+pub const TENANT_ID: &str = "tenant_id";
+/// Variable holding the ID of the session the execution belongs to
+pub const SESSION_ID: &str = "session_id";
+/// Variable holding the locale, e.g. `fr-CA`
+pub const LOCALE: &str = "locale";
+
+/// Key-value context of an execution
+///
+/// Immutable once built; cloning is cheap.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextVars {
+    values: Arc<BTreeMap<String, Value>>,
+}
+
+impl ContextVars {
+    /// Create an empty context
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a variable, replacing any of the same name
+    ///
+    /// A value that does not serialize is left out.
+    pub fn with(mut self, key: impl Into<String>, value: impl Serialize) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            Arc::make_mut(&mut self.values).insert(key.into(), value);
+        }
+        self
+    }
+
+    /// Set the user ID
+    pub fn with_user(self, user: impl Into<String>) -> Self {
+        self.with(USER_ID, user.into())
+    }
+
+    /// Set the tenant ID
+    pub fn with_tenant(self, tenant: impl Into<String>) -> Self {
+        self.with(TENANT_ID, tenant.into())
+    }
+
+    /// Set the session ID
+    pub fn with_session(self, session: impl Into<String>) -> Self {
+        self.with(SESSION_ID, session.into())
+    }
+
+    /// Set the locale
+    pub fn with_locale(self, locale: impl Into<String>) -> Self {
+        self.with(LOCALE, locale.into())
+    }
+
+    /// Raw value of a variable
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// Value of a variable, if it is set and deserializes as `T`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.value(key).and_then(|value| T::deserialize(value).ok())
+    }
+
+    /// Value of a variable as text; strings are taken as they are
+    pub fn text(&self, key: &str) -> Option<String> {
+        match self.value(key)? {
+            Value::String(text) => Some(text.clone()),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// The user ID, if set
+    pub fn user_id(&self) -> Option<String> {
+        self.text(USER_ID)
+    }
+
+    /// The tenant ID, if set
+    pub fn tenant_id(&self) -> Option<String> {
+        self.text(TENANT_ID)
+    }
+
+    /// The session ID, if set
+    pub fn session_id(&self) -> Option<String> {
+        self.text(SESSION_ID)
+    }
+
+    /// The locale, if set
+    pub fn locale(&self) -> Option<String> {
+        self.text(LOCALE)
+    }
+
+    /// Check if a variable is set
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// Variables, by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Number of variables
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check if no variables are set
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// These variables with those of `inner` added, `inner` winning on conflicts
+    pub fn merged(&self, inner: &ContextVars) -> ContextVars {
+        if self.is_empty() {
+            return inner.clone();
+        }
+        let mut merged = self.clone();
+        if !inner.is_empty() {
+            let values = Arc::make_mut(&mut merged.values);
+            values.extend(inner.values.iter().map(|(key, value)| (key.clone(), value.clone())));
+        }
+        merged
+    }
+}
+
+tokio::task_local! {
+    static VARS: ContextVars;
+}
+
+/// Context variables of the execution the current task is running, empty outside of one
+pub fn current() -> ContextVars {
+    VARS.try_with(ContextVars::clone).unwrap_or_default()
+}
+
+/// Value of a context variable of the current execution
+pub fn get<T: DeserializeOwned>(key: &str) -> Option<T> {
+    VARS.try_with(|vars| vars.get(key)).ok().flatten()
+}
+
+/// Run `future` with `vars` added to the [`current`] variables
+///
+/// Variables of an enclosing scope stay visible unless `vars` replaces them,
+/// so a node can run a nested graph with one more variable set.
+pub async fn scope<F: Future>(vars: ContextVars, future: F) -> F::Output {
+    let vars = current().merged(&vars);
+    if vars.is_empty() {
+        future.await
+    } else {
+        VARS.scope(vars, future).await
+    }
+}
+
+/// Carry the current variables into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = VARS.try_with(ContextVars::clone).ok();
+    async move {
+        match value {
+            Some(value) => VARS.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Edge condition on a context variable of the running execution
+#[derive(Debug, Clone)]
+pub struct ContextCondition {
+    key: String,
+    expected: Option<Value>,
+}
+
+impl ContextCondition {
+    /// True while `key` is set
+    pub fn is_set(key: impl Into<String>) -> Self {
+        Self { key: key.into(), expected: None }
+    }
+
+    /// True while `key` has `value`
+    pub fn equals(key: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self { key: key.into(), expected: Some(value.into()) }
+    }
+}
+
+#[async_trait]
+impl<S: State> EdgeCondition<S> for ContextCondition {
+    async fn evaluate(&self, _state: &S) -> GraphResult<bool> {
+        let value = get::<Value>(&self.key);
+        Ok(match &self.expected {
+            Some(expected) => value.as_ref() == Some(expected),
+            None => value.is_some(),
+        })
+    }
+
+    fn condition_id(&self) -> String {
+        match &self.expected {
+            Some(expected) => format!("context:{}={}", self.key, expected),
+            None => format!("context:{}", self.key),
+        }
+    }
+
+    fn description(&self) -> String {
+        match &self.expected {
+            Some(expected) => format!("Context variable '{}' is {}", self.key, expected),
+            None => format!("Context variable '{}' is set", self.key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_context_vars_scope_and_nest() {
+        let vars = ContextVars::new().with_user("u-42").with_locale("fr-CA").with("experiments", json!({"ranker": "b"}));
+        assert_eq!(vars.user_id().as_deref(), Some("u-42"));
+        assert!(current().is_empty());
+
+        let (locale, arm, nested) = scope(vars, async {
+            let nested = scope(ContextVars::new().with_locale("en-US"), async { (current().locale(), current().user_id()) }).await;
+            (get::<String>(LOCALE), current().get::<Value>("experiments").map(|e| e["ranker"].clone()), nested)
+        })
+        .await;
+        assert_eq!(locale.as_deref(), Some("fr-CA"));
+        assert_eq!(arm, Some(json!("b")));
+        assert_eq!(nested, (Some("en-US".to_string()), Some("u-42".to_string())));
+        assert!(get::<String>(LOCALE).is_none());
+    }
+}
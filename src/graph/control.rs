@@ -0,0 +1,533 @@
+//! Live control of running executions: cancel, pause, resume and retry.
+//!
+//! Executions of a graph given [`ExecutionControls`] can be driven from
+//! outside, e.g. by an operator in the Studio. The engine looks for requests
+//! between steps, so a running node always finishes first. A paused execution
+//! keeps its state in memory and waits until it is resumed or cancelled.
+//!
+//! Executions that fail are kept with the state they failed in, so the failed
+//! node can be retried, optionally after editing that state.
+//!
+//! Before a deploy, [`ExecutionControls::shutdown`] drains the instance: every
+//! execution suspends before its next node and is checkpointed under a lease
+//! held by the instance. On startup, [`ExecutionControls::resume_drained`]
+//! picks those executions up again, along with any whose lease has expired
+//! because the instance that drained them never came back.
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::{ExecutionContext, Graph};
+use crate::node::NodeId;
+use crate::state::State;
+use futures::future::BoxFuture;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Failed executions kept for retries
+const MAX_FAILED_EXECUTIONS: usize = 100;
+
+/// How long a drained execution is reserved for the instance that drained it
+const DEFAULT_DRAIN_LEASE: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Operation a drained execution is suspended on
+pub const DRAIN_OPERATION: &str = "drain";
+
+/// Tag of checkpoints saved for drained executions
+pub const DRAINED_TAG: &str = "drained";
+
+/// Variable naming this instance, used as the owner of executions it drains
+pub const INSTANCE_ID_VAR: &str = "AGENTGRAPH_INSTANCE_ID";
+
+/// ID of this instance from `$AGENTGRAPH_INSTANCE_ID`, then `$HOSTNAME`, then one made up for this process
+///
+/// Without either variable, instances sharing a store must not all claim to
+/// be the same owner, so each process gets an ID of its own.
+pub(crate) fn default_instance_id() -> String {
+    static PROCESS_ID: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    [INSTANCE_ID_VAR, "HOSTNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|id| !id.is_empty()))
+        .unwrap_or_else(|| PROCESS_ID.get_or_init(|| format!("local-{}", Uuid::new_v4())).clone())
+}
+
+/// What an execution has been asked to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Signal {
+    Run,
+    Pause,
+    Cancel,
+    Drain,
+}
+
+/// Where a controlled execution stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveStatus {
+    /// Running its next step
+    Running,
+    /// Asked to pause, finishing its current node
+    Pausing,
+    /// Waiting between steps to be resumed
+    Paused,
+    /// Asked to cancel, finishing its current node
+    Cancelling,
+    /// Suspending for a shutdown, finishing its current node
+    Draining,
+}
+
+/// An execution in progress
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveExecution {
+    /// Execution ID
+    pub execution_id: Uuid,
+    /// Name of the graph
+    pub graph: String,
+    /// Whether it runs, is paused or is stopping
+    pub status: LiveStatus,
+    /// Node run by the latest step
+    pub current_node: Option<NodeId>,
+    /// Steps run so far
+    pub step: u64,
+    /// When the execution started
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// An execution that failed, with what is needed to retry it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedExecution {
+    /// Execution ID
+    pub execution_id: Uuid,
+    /// Name of the graph
+    pub graph: String,
+    /// Node that failed
+    pub node_id: Option<NodeId>,
+    /// State the node failed in
+    pub state: serde_json::Value,
+    /// Why it failed
+    pub error: String,
+    /// When it failed
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Request to run a failed execution again from one of its nodes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryRequest {
+    /// Node to start from; the failed node when unset
+    pub node_id: Option<NodeId>,
+    /// State to start with; the state the execution failed in when unset
+    pub state: Option<serde_json::Value>,
+}
+
+type RetryHandler =
+    Arc<dyn Fn(&FailedExecution, NodeId, serde_json::Value, ExecutionContext) -> GraphResult<BoxFuture<'static, ()>> + Send + Sync>;
+
+#[derive(Debug)]
+struct Entry {
+    signal: watch::Sender<Signal>,
+    info: LiveExecution,
+}
+
+#[derive(Default)]
+struct Inner {
+    running: Mutex<HashMap<Uuid, Entry>>,
+    failed: Mutex<VecDeque<FailedExecution>>,
+    /// Starts retries; graph-specific, since it has to rebuild the state
+    retry: RwLock<Option<(RetryHandler, String)>>,
+    /// Set once the instance is shutting down; executions then suspend at their next step
+    draining: std::sync::atomic::AtomicBool,
+    /// Owner recorded on drained executions, when set explicitly
+    instance_id: RwLock<Option<String>>,
+    /// How long drained executions are reserved for this instance, when set explicitly
+    drain_lease: RwLock<Option<std::time::Duration>>,
+    /// Drained checkpoints whose executions are being resumed by this instance
+    resuming: Mutex<HashSet<Uuid>>,
+}
+
+/// Handles on the executions of one or more graphs
+///
+/// Cloning shares the handles. Give a graph one with
+/// [`Graph::set_controls`](crate::graph::Graph::set_controls).
+#[derive(Clone, Default)]
+pub struct ExecutionControls {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for ExecutionControls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecutionControls")
+            .field("running", &self.inner.running.lock().len())
+            .field("failed", &self.inner.failed.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExecutionControls {
+    /// Create controls with no executions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `instance_id` as the owner of executions drained by these controls
+    ///
+    /// Defaults to `$AGENTGRAPH_INSTANCE_ID`, then `$HOSTNAME`, then an ID
+    /// made up for this process, which does not survive a restart. Give each
+    /// instance sharing a checkpoint store an ID that survives its restarts,
+    /// e.g. a StatefulSet pod name.
+    pub fn with_instance_id(self, instance_id: impl Into<String>) -> Self {
+        *self.inner.instance_id.write() = Some(instance_id.into());
+        self
+    }
+
+    /// Reserve drained executions for this instance for `lease` [default: 5 minutes]
+    pub fn with_drain_lease(self, lease: std::time::Duration) -> Self {
+        *self.inner.drain_lease.write() = Some(lease);
+        self
+    }
+
+    /// Owner recorded on drained executions
+    pub fn instance_id(&self) -> String {
+        self.inner.instance_id.read().clone().unwrap_or_else(default_instance_id)
+    }
+
+    /// How long drained executions are reserved for this instance
+    pub fn drain_lease(&self) -> std::time::Duration {
+        self.inner.drain_lease.read().unwrap_or(DEFAULT_DRAIN_LEASE)
+    }
+
+    /// Whether the instance is shutting down
+    pub fn is_draining(&self) -> bool {
+        self.inner.draining.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Suspend every execution in progress, and every one started from now on, before its next node
+    ///
+    /// Returns how many executions were asked to suspend. Executions being
+    /// cancelled are left to finish cancelling.
+    pub fn drain(&self) -> usize {
+        self.inner.draining.store(true, std::sync::atomic::Ordering::SeqCst);
+        let mut running = self.inner.running.lock();
+        let mut drained = 0;
+        for entry in running.values_mut() {
+            if *entry.signal.borrow() == Signal::Cancel {
+                continue;
+            }
+            entry.signal.send_replace(Signal::Drain);
+            entry.info.status = LiveStatus::Draining;
+            drained += 1;
+        }
+        drained
+    }
+
+    /// Drain, then wait up to `grace` for executions to suspend
+    ///
+    /// Call it on SIGTERM before the process exits. Returns how many
+    /// executions were still running when `grace` ran out; a node that
+    /// outlives the grace period is lost with the process.
+    pub async fn shutdown(&self, grace: std::time::Duration) -> usize {
+        let drained = self.drain();
+        tracing::info!(executions = drained, instance_id = %self.instance_id(), "Draining executions for shutdown");
+        let deadline = tokio::time::Instant::now() + grace;
+        loop {
+            let running = self.inner.running.lock().len();
+            if running == 0 || tokio::time::Instant::now() >= deadline {
+                if running > 0 {
+                    tracing::warn!(executions = running, "Executions still running at shutdown");
+                }
+                return running;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    }
+
+    /// Executions in progress
+    pub fn list(&self) -> Vec<LiveExecution> {
+        let mut live: Vec<_> = self.inner.running.lock().values().map(|entry| entry.info.clone()).collect();
+        live.sort_by_key(|execution| execution.started_at);
+        live
+    }
+
+    /// An execution in progress
+    pub fn get(&self, execution_id: Uuid) -> Option<LiveExecution> {
+        self.inner.running.lock().get(&execution_id).map(|entry| entry.info.clone())
+    }
+
+    /// Cancel an execution after its current node
+    pub fn cancel(&self, execution_id: Uuid) -> GraphResult<LiveExecution> {
+        self.send(execution_id, Signal::Cancel, LiveStatus::Cancelling)
+    }
+
+    /// Pause an execution after its current node
+    pub fn pause(&self, execution_id: Uuid) -> GraphResult<LiveExecution> {
+        self.send(execution_id, Signal::Pause, LiveStatus::Pausing)
+    }
+
+    /// Let a paused execution carry on
+    pub fn resume(&self, execution_id: Uuid) -> GraphResult<LiveExecution> {
+        self.send(execution_id, Signal::Run, LiveStatus::Running)
+    }
+
+    fn send(&self, execution_id: Uuid, signal: Signal, status: LiveStatus) -> GraphResult<LiveExecution> {
+        let mut running = self.inner.running.lock();
+        let entry = running
+            .get_mut(&execution_id)
+            .ok_or_else(|| GraphError::validation_error(format!("No execution {} in progress", execution_id)))?;
+        match *entry.signal.borrow() {
+            Signal::Cancel => {
+                return Err(GraphError::validation_error(format!("Execution {} is being cancelled", execution_id)));
+            }
+            Signal::Drain => {
+                return Err(GraphError::validation_error(format!("Execution {} is suspending for a shutdown", execution_id)));
+            }
+            Signal::Run | Signal::Pause => {}
+        }
+        let paused = entry.info.status == LiveStatus::Paused;
+        entry.signal.send_replace(signal);
+        entry.info.status = match (signal, paused) {
+            // Pausing twice does not wake it up
+            (Signal::Pause, true) => LiveStatus::Paused,
+            _ => status,
+        };
+        Ok(entry.info.clone())
+    }
+
+    /// Executions that failed, most recent first
+    pub fn failed(&self) -> Vec<FailedExecution> {
+        self.inner.failed.lock().iter().rev().cloned().collect()
+    }
+
+    /// A failed execution
+    pub fn failed_execution(&self, execution_id: Uuid) -> Option<FailedExecution> {
+        self.inner.failed.lock().iter().find(|failed| failed.execution_id == execution_id).cloned()
+    }
+
+    /// Retry failed executions of `graph` on request
+    ///
+    /// Retries run in the background on a sibling of `engine`, so with its
+    /// services, determinism and pools, under a new execution ID and these
+    /// controls.
+    pub fn serve_retries<S>(&self, graph: Arc<Graph<S>>, engine: crate::graph::engine::GraphEngine<S>)
+    where
+        S: State + Clone + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        let name = graph.metadata().name.clone();
+        let engine = Arc::new(engine);
+        let handler: RetryHandler = Arc::new(move |failed, node_id, state, context| {
+            let mut state: S = serde_json::from_value(state)?;
+            if graph.node_registry().get(&node_id).is_none() {
+                return Err(GraphError::validation_error(format!("Graph '{}' has no node '{}'", failed.graph, node_id)));
+            }
+            let graph = graph.clone();
+            let mut engine = engine.sibling();
+            let retried = failed.execution_id;
+            Ok(Box::pin(async move {
+                tracing::info!(execution_id = %context.execution_id, retried = %retried, node_id = %node_id, "Retrying failed execution");
+                if let Err(error) = engine.execute_from_node_in(&graph, &mut state, context, node_id).await {
+                    tracing::warn!(retried = %retried, error = %error, "Retried execution failed");
+                }
+            }))
+        });
+        *self.inner.retry.write() = Some((handler, name));
+    }
+
+    /// Run a failed execution again from a node, returning the new execution's ID
+    ///
+    /// The failed execution is forgotten once the retry starts.
+    pub fn retry(&self, execution_id: Uuid, request: RetryRequest) -> GraphResult<Uuid> {
+        let failed = self.failed_execution(execution_id)
+            .ok_or_else(|| GraphError::validation_error(format!("No failed execution {}", execution_id)))?;
+        let (handler, graph) = self.inner.retry.read().clone()
+            .ok_or_else(|| GraphError::ConfigurationError("Retries are not served for any graph".to_string()))?;
+        if failed.graph != graph {
+            return Err(GraphError::ConfigurationError(format!("Retries are not served for graph '{}'", failed.graph)));
+        }
+        let node_id = request.node_id.or_else(|| failed.node_id.clone())
+            .ok_or_else(|| GraphError::validation_error("Execution failed before its first node; name one to retry from"))?;
+        let state = request.state.unwrap_or_else(|| failed.state.clone());
+
+        let context = ExecutionContext::new();
+        let new_id = context.execution_id;
+        tokio::spawn(handler(&failed, node_id, state, context)?);
+        self.inner.failed.lock().retain(|f| f.execution_id != execution_id);
+        Ok(new_id)
+    }
+
+    #[cfg(feature = "checkpointing")]
+    /// Resume executions of `graph` drained by this instance, or whose lease has expired
+    ///
+    /// Call it on startup, after giving the graph these controls. Each drained
+    /// execution carries on in the background, on a sibling of `engine`, under
+    /// its old execution ID, from the node it suspended before. Its checkpoint
+    /// is deleted once the resumed run has finished or been checkpointed
+    /// again, so an instance that dies meanwhile leaves it to be resumed by
+    /// the next. Checkpoints that cannot be read are logged and skipped.
+    /// Returns the IDs of the executions resumed. Executions suspended on any
+    /// other operation wait for it, as before.
+    pub async fn resume_drained<S>(&self, graph: Arc<Graph<S>>, engine: &crate::graph::engine::GraphEngine<S>) -> GraphResult<Vec<Uuid>>
+    where
+        S: State + Clone + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        let checkpointer = graph.checkpointer.as_ref()
+            .ok_or_else(|| GraphError::CheckpointError("Graph has no checkpointer".to_string()))?;
+        let instance_id = self.instance_id();
+        let now = chrono::Utc::now();
+        let mut resumed = Vec::new();
+
+        for checkpoint_id in checkpointer.list_snapshots().await? {
+            let metadata = match checkpointer.get_metadata(checkpoint_id).await {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    tracing::warn!(checkpoint_id = %checkpoint_id, error = %error, "Skipping unreadable checkpoint");
+                    continue;
+                }
+            };
+            let custom = |key: &str| metadata.custom.get(key).and_then(serde_json::Value::as_str);
+            if !metadata.tags.iter().any(|tag| tag == DRAINED_TAG) || custom("graph") != Some(graph.metadata().name.as_str()) {
+                continue;
+            }
+            if self.inner.resuming.lock().contains(&checkpoint_id) {
+                continue;
+            }
+            let lease_expired = custom("lease_expires_at")
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                .map_or(true, |at| at <= now);
+            if custom("owner").is_some_and(|owner| owner != instance_id) && !lease_expired {
+                continue;
+            }
+            let Some(node_id) = metadata.current_node.clone() else {
+                continue;
+            };
+            let mut context = ExecutionContext::new();
+            if let Some(execution_id) = custom("execution_id").and_then(|id| Uuid::parse_str(id).ok()) {
+                context.execution_id = execution_id;
+            }
+            // With shared leases, only the instance that wins the lease resumes the execution
+            if let Some(leases) = graph.leases() {
+                if leases.claim(context.execution_id).await.is_err() {
+                    continue;
+                }
+            }
+
+            // Gone if another instance resumed it first
+            let snapshot = match checkpointer.load(checkpoint_id).await {
+                Ok(snapshot) => snapshot,
+                Err(error) => {
+                    tracing::warn!(checkpoint_id = %checkpoint_id, error = %error, "Skipping drained checkpoint that could not be loaded");
+                    continue;
+                }
+            };
+            context.current_step = metadata.step;
+            let execution_id = context.execution_id;
+            tracing::info!(
+                execution_id = %execution_id,
+                checkpoint_id = %checkpoint_id,
+                node_id = %node_id,
+                owner = custom("owner").unwrap_or("none"),
+                "Resuming drained execution"
+            );
+
+            self.inner.resuming.lock().insert(checkpoint_id);
+            let inner = self.inner.clone();
+            let graph = graph.clone();
+            let mut engine = engine.sibling();
+            let mut state = snapshot.state;
+            tokio::spawn(async move {
+                if let Err(error) = engine.execute_from_node_in(&graph, &mut state, context, node_id).await {
+                    tracing::warn!(execution_id = %execution_id, error = %error, "Resumed execution did not complete");
+                }
+                // The run has completed, failed or been checkpointed again
+                if let Some(checkpointer) = graph.checkpointer.as_ref() {
+                    if let Err(error) = checkpointer.delete(checkpoint_id).await {
+                        tracing::warn!(checkpoint_id = %checkpoint_id, error = %error, "Could not delete resumed checkpoint");
+                    }
+                }
+                inner.resuming.lock().remove(&checkpoint_id);
+            });
+            resumed.push(execution_id);
+        }
+        Ok(resumed)
+    }
+
+    /// Start controlling an execution
+    pub(crate) fn register(&self, context: &ExecutionContext, graph: &str) {
+        let draining = self.is_draining();
+        let (signal, _) = watch::channel(if draining { Signal::Drain } else { Signal::Run });
+        let info = LiveExecution {
+            execution_id: context.execution_id,
+            graph: graph.to_string(),
+            status: if draining { LiveStatus::Draining } else { LiveStatus::Running },
+            current_node: None,
+            step: 0,
+            started_at: context.start_time,
+        };
+        self.inner.running.lock().insert(context.execution_id, Entry { signal, info });
+    }
+
+    /// Between steps: report progress, wait while paused, stop if cancelled and suspend if draining
+    ///
+    /// `next_node` is the node the execution is about to run, where a
+    /// drained execution suspends.
+    pub(crate) async fn checkpoint(&self, context: &ExecutionContext, next_node: &NodeId) -> GraphResult<()> {
+        let mut receiver = {
+            let mut running = self.inner.running.lock();
+            let Some(entry) = running.get_mut(&context.execution_id) else {
+                return Ok(());
+            };
+            entry.info.current_node = context.current_node.clone();
+            entry.info.step = context.current_step;
+            entry.signal.subscribe()
+        };
+        loop {
+            let signal = *receiver.borrow_and_update();
+            match signal {
+                Signal::Run => return Ok(()),
+                Signal::Cancel => {
+                    return Err(GraphError::Cancelled(format!("Execution {} cancelled at step {}", context.execution_id, context.current_step)));
+                }
+                Signal::Drain => {
+                    return Err(GraphError::suspended(next_node.as_str(), DRAIN_OPERATION));
+                }
+                Signal::Pause => {
+                    self.set_status(context.execution_id, LiveStatus::Paused);
+                    tracing::info!(execution_id = %context.execution_id, step = context.current_step, "Execution paused");
+                    if receiver.changed().await.is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_status(&self, execution_id: Uuid, status: LiveStatus) {
+        if let Some(entry) = self.inner.running.lock().get_mut(&execution_id) {
+            entry.info.status = status;
+        }
+    }
+
+    /// Stop controlling an execution, keeping it for retries if it failed
+    pub(crate) fn finish<S: Serialize>(&self, context: &ExecutionContext, graph: &str, state: &S, result: Result<(), &GraphError>) {
+        self.inner.running.lock().remove(&context.execution_id);
+        let mut failed = self.inner.failed.lock();
+        failed.retain(|f| f.execution_id != context.execution_id);
+        let Err(error) = result else {
+            return;
+        };
+        if error.is_suspended() || error.is_cancelled() {
+            return;
+        }
+        if failed.len() >= MAX_FAILED_EXECUTIONS {
+            failed.pop_front();
+        }
+        failed.push_back(FailedExecution {
+            execution_id: context.execution_id,
+            graph: graph.to_string(),
+            node_id: context.current_node.clone(),
+            state: serde_json::to_value(state).unwrap_or(serde_json::Value::Null),
+            error: error.to_string(),
+            failed_at: chrono::Utc::now(),
+        });
+    }
+}
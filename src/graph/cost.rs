@@ -0,0 +1,584 @@
+//! Pre-flight cost estimates for a graph run.
+//!
+//! The execution plan is walked from the entry point. Each node is priced from
+//! the tokens it used in earlier runs when there are any, otherwise from the
+//! model it declares in its metadata and that model's profile.
+
+use crate::edge::EdgeType;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::compiled::{CompiledRoute, ExecutionPlan};
+use crate::graph::{ExecutionContext, Graph};
+use crate::llm::{LLMUsage, ModelProfile, ModelProfileRegistry};
+use crate::node::{NodeId, NodeMetadata};
+use crate::state::State;
+use crate::visualization::{ExecutionTrace, VisualEventType};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+#[cfg(feature = "streaming")]
+use crate::streaming::ExecutionEvent;
+
+/// Completion tokens assumed per call for nodes that declare no limit
+pub const DEFAULT_COMPLETION_TOKENS: u32 = 1024;
+
+/// Rough characters per token, used to size the input state as a prompt
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens and their price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenCost {
+    /// Prompt tokens
+    pub prompt_tokens: u64,
+    /// Completion tokens
+    pub completion_tokens: u64,
+    /// Price in USD
+    pub cost_usd: f64,
+}
+
+impl TokenCost {
+    /// Prompt plus completion tokens
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn priced(prompt_tokens: u64, completion_tokens: u64, profile: Option<&ModelProfile>) -> Self {
+        let cost_usd = profile.map_or(0.0, |profile| {
+            (prompt_tokens as f64 / 1000.0) * profile.prompt_cost_per_1k
+                + (completion_tokens as f64 / 1000.0) * profile.completion_cost_per_1k
+        });
+        Self { prompt_tokens, completion_tokens, cost_usd }
+    }
+
+    fn plus(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            cost_usd: self.cost_usd + other.cost_usd,
+        }
+    }
+
+    fn scaled(self, factor: f64) -> Self {
+        Self {
+            prompt_tokens: (self.prompt_tokens as f64 * factor).round() as u64,
+            completion_tokens: (self.completion_tokens as f64 * factor).round() as u64,
+            cost_usd: self.cost_usd * factor,
+        }
+    }
+
+    fn is_cheaper_than(&self, other: &Self) -> bool {
+        (self.cost_usd, self.total_tokens()) < (other.cost_usd, other.total_tokens())
+    }
+}
+
+/// Cheapest, expected and most expensive cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostRange {
+    /// Lower bound
+    pub min: TokenCost,
+    /// Probability-weighted cost
+    pub expected: TokenCost,
+    /// Upper bound
+    pub max: TokenCost,
+}
+
+impl CostRange {
+    fn plus(self, other: Self) -> Self {
+        Self {
+            min: self.min.plus(other.min),
+            expected: self.expected.plus(other.expected),
+            max: self.max.plus(other.max),
+        }
+    }
+
+    /// Exactly one of `options` runs, with the given probabilities
+    fn either(options: &[(CostRange, f64)]) -> Self {
+        let Some((first, _)) = options.first() else {
+            return Self::default();
+        };
+        let mut range = Self { expected: TokenCost::default(), ..*first };
+        for (option, probability) in options {
+            if option.min.is_cheaper_than(&range.min) {
+                range.min = option.min;
+            }
+            if range.max.is_cheaper_than(&option.max) {
+                range.max = option.max;
+            }
+            range.expected = range.expected.plus(option.expected.scaled(*probability));
+        }
+        range
+    }
+}
+
+/// Where a node's per-visit cost came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateBasis {
+    /// Tokens recorded for the node in earlier runs
+    History,
+    /// The declared model's profile and completion limit
+    Profile,
+    /// A declared model with no profile; tokens are estimated but not priced
+    Unpriced,
+    /// The node declares no model and has no history
+    NoLlm,
+}
+
+/// Cost of one visit to a node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeCostEstimate {
+    /// Node ID
+    pub node_id: NodeId,
+    /// Model the node declares
+    pub model: Option<String>,
+    /// Where the estimate came from
+    pub basis: EstimateBasis,
+    /// Runs of the node in the history
+    pub samples: usize,
+    /// Cost of a single visit
+    pub per_visit: CostRange,
+}
+
+/// Estimated cost of running a graph once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    /// Cost over the whole run
+    pub total: CostRange,
+    /// Per-visit cost of every node reachable from the entry point, by node ID
+    pub nodes: Vec<NodeCostEstimate>,
+    /// Whether the graph loops; the total counts one pass through each loop
+    pub has_cycles: bool,
+    /// Declared models missing from the profile registry
+    pub unpriced_models: Vec<String>,
+}
+
+/// LLM usage recorded per node across earlier runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenHistory {
+    nodes: HashMap<NodeId, Vec<LLMUsage>>,
+}
+
+impl TokenHistory {
+    /// Create an empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the usage reported by completed nodes in `traces`
+    pub fn from_traces<'a, I>(traces: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ExecutionTrace>,
+    {
+        let mut history = Self::new();
+        for trace in traces {
+            for event in &trace.events {
+                if !matches!(event.event_type, VisualEventType::NodeCompleted) {
+                    continue;
+                }
+                let (Some(node_id), Some(usage)) = (&event.node_id, event.data.get("llm_usage")) else {
+                    continue;
+                };
+                if let Ok(usage) = serde_json::from_value::<LLMUsage>(usage.clone()) {
+                    history.record(node_id.clone(), usage);
+                }
+            }
+        }
+        history
+    }
+
+    /// Record the usage every node reported during an execution the engine ran
+    pub fn record_execution(&mut self, context: &ExecutionContext) {
+        for (node_id, usage) in &context.llm_usage {
+            self.record(node_id.clone(), usage.clone());
+        }
+    }
+
+    /// Record the usage reported by a completed node, as streamed by the engine
+    #[cfg(feature = "streaming")]
+    pub fn record_event(&mut self, event: &ExecutionEvent) {
+        if let ExecutionEvent::NodeCompleted { node_id, llm_usage: Some(usage), .. } = event {
+            self.record(node_id.clone(), usage.clone());
+        }
+    }
+
+    /// Collect the usage reported by completed nodes in a stream of `events`
+    #[cfg(feature = "streaming")]
+    pub fn from_events<'a, I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ExecutionEvent>,
+    {
+        let mut history = Self::new();
+        for event in events {
+            history.record_event(event);
+        }
+        history
+    }
+
+    /// Record one run of a node
+    pub fn record(&mut self, node_id: NodeId, usage: LLMUsage) {
+        if !usage.is_empty() {
+            self.nodes.entry(node_id).or_default().push(usage);
+        }
+    }
+
+    /// Runs recorded for a node
+    pub fn samples(&self, node_id: &str) -> &[LLMUsage] {
+        self.nodes.get(node_id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Check whether any runs were recorded
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<S> Graph<S>
+where
+    S: State + Serialize,
+{
+    /// Estimate the tokens and dollars one run starting from `input_state` will cost
+    ///
+    /// Nodes without history are assumed to send the serialized input state as
+    /// their prompt and to use between none and all of their completion limit.
+    /// Conditional edges are weighted by the graph's branch statistics, or
+    /// evenly before any have been recorded.
+    pub fn estimate_cost(
+        &self,
+        input_state: &S,
+        profiles: &ModelProfileRegistry,
+        history: &TokenHistory,
+    ) -> GraphResult<CostEstimate> {
+        let entry = self.entry_point()
+            .ok_or_else(|| GraphError::graph_structure("No entry point set".to_string()))?;
+        let plan = match self.plan() {
+            Some(plan) => Cow::Borrowed(plan),
+            None => Cow::Owned(ExecutionPlan::build(self)?),
+        };
+        let input_tokens = serde_json::to_string(input_state)?.len().div_ceil(CHARS_PER_TOKEN) as u64;
+
+        let mut nodes = Vec::new();
+        let mut unpriced_models = BTreeSet::new();
+        for node_id in plan.levels().iter().flatten() {
+            let node = estimate_node(node_id, self.node_registry().get_metadata(node_id), profiles, history, input_tokens);
+            if node.basis == EstimateBasis::Unpriced {
+                unpriced_models.extend(node.model.clone());
+            }
+            nodes.push(node);
+        }
+        nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+
+        let mut walk = Walk {
+            graph: self,
+            plan: &plan,
+            per_visit: nodes.iter().map(|node| (node.node_id.clone(), node.per_visit)).collect(),
+            memo: HashMap::new(),
+            stack: Vec::new(),
+            has_cycles: false,
+        };
+        let total = walk.from(entry);
+
+        Ok(CostEstimate {
+            total,
+            nodes,
+            has_cycles: walk.has_cycles,
+            unpriced_models: unpriced_models.into_iter().collect(),
+        })
+    }
+}
+
+fn estimate_node(
+    node_id: &NodeId,
+    metadata: Option<&NodeMetadata>,
+    profiles: &ModelProfileRegistry,
+    history: &TokenHistory,
+    input_tokens: u64,
+) -> NodeCostEstimate {
+    let llm = metadata.and_then(|metadata| metadata.llm.as_ref());
+    let model = llm.map(|llm| llm.model.clone());
+    let profile = model.as_deref().and_then(|model| profiles.get(model));
+    let samples = history.samples(node_id);
+
+    let (basis, per_visit) = if !samples.is_empty() {
+        let costs: Vec<TokenCost> = samples.iter()
+            .map(|usage| {
+                let priced = TokenCost::priced(usage.prompt_tokens, usage.completion_tokens, profile);
+                // Providers that report a cost know their prices better than the profile
+                TokenCost { cost_usd: if usage.cost > 0.0 { usage.cost } else { priced.cost_usd }, ..priced }
+            })
+            .collect();
+        (EstimateBasis::History, sample_range(&costs))
+    } else if let Some(llm) = llm {
+        let limit = u64::from(llm.max_tokens.unwrap_or(DEFAULT_COMPLETION_TOKENS));
+        let range = CostRange {
+            min: TokenCost::priced(input_tokens, 0, profile),
+            expected: TokenCost::priced(input_tokens, limit / 2, profile),
+            max: TokenCost::priced(input_tokens, limit, profile),
+        };
+        let basis = if profile.is_some() { EstimateBasis::Profile } else { EstimateBasis::Unpriced };
+        (basis, range)
+    } else {
+        (EstimateBasis::NoLlm, CostRange::default())
+    };
+
+    NodeCostEstimate {
+        node_id: node_id.clone(),
+        model,
+        basis,
+        samples: samples.len(),
+        per_visit,
+    }
+}
+
+/// Smallest, mean and largest value of each field across recorded runs
+fn sample_range(costs: &[TokenCost]) -> CostRange {
+    let field = |pick: fn(&TokenCost) -> f64| {
+        let values = costs.iter().map(pick);
+        let min = values.clone().fold(f64::INFINITY, f64::min);
+        let max = values.clone().fold(0.0, f64::max);
+        (min, values.sum::<f64>() / costs.len() as f64, max)
+    };
+    let prompt = field(|cost| cost.prompt_tokens as f64);
+    let completion = field(|cost| cost.completion_tokens as f64);
+    let dollars = field(|cost| cost.cost_usd);
+    let at = |prompt: f64, completion: f64, cost_usd: f64| TokenCost {
+        prompt_tokens: prompt.round() as u64,
+        completion_tokens: completion.round() as u64,
+        cost_usd,
+    };
+    CostRange {
+        min: at(prompt.0, completion.0, dollars.0),
+        expected: at(prompt.1, completion.1, dollars.1),
+        max: at(prompt.2, completion.2, dollars.2),
+    }
+}
+
+/// Depth-first walk of the plan summing node costs along every route
+struct Walk<'a, S: State> {
+    graph: &'a Graph<S>,
+    plan: &'a ExecutionPlan,
+    per_visit: HashMap<NodeId, CostRange>,
+    memo: HashMap<NodeId, CostRange>,
+    stack: Vec<NodeId>,
+    has_cycles: bool,
+}
+
+impl<S: State> Walk<'_, S> {
+    /// Cost of running `node` and everything after it
+    fn from(&mut self, node: &NodeId) -> CostRange {
+        if let Some(range) = self.memo.get(node) {
+            return *range;
+        }
+        if self.stack.contains(node) {
+            // Back edge: the loop body is already counted once
+            self.has_cycles = true;
+            return CostRange::default();
+        }
+
+        self.stack.push(node.clone());
+        let own = self.visit(node);
+        let rest = if self.graph.finish_points().contains(node) {
+            CostRange::default()
+        } else {
+            self.after(node)
+        };
+        self.stack.pop();
+
+        let range = own.plus(rest);
+        self.memo.insert(node.clone(), range);
+        range
+    }
+
+    fn visit(&self, node: &NodeId) -> CostRange {
+        self.per_visit.get(node).copied().unwrap_or_default()
+    }
+
+    fn after(&mut self, node: &NodeId) -> CostRange {
+        let graph = self.graph;
+        let edge = match self.plan.route(node) {
+            None => return CostRange::default(),
+            Some(CompiledRoute::Next(target)) => return self.from(&target.clone()),
+            Some(CompiledRoute::Edge(index)) => &graph.edges()[*index],
+        };
+
+        match &edge.edge_type {
+            EdgeType::Simple { target } => self.from(target),
+            EdgeType::Conditional { true_target, false_target, .. } => {
+                let counts = graph.branch_statistics().get(&edge.label());
+                let p_true = match counts.total() {
+                    0 => 0.5,
+                    total => counts.true_count as f64 / total as f64,
+                };
+                let options = [(self.from(true_target), p_true), (self.from(false_target), 1.0 - p_true)];
+                CostRange::either(&options)
+            }
+            EdgeType::Dynamic { possible_targets, .. } => {
+                let p = 1.0 / possible_targets.len().max(1) as f64;
+                let options: Vec<_> = possible_targets.iter().map(|target| (self.from(target), p)).collect();
+                CostRange::either(&options)
+            }
+            EdgeType::Weighted { targets } => {
+                let total: f64 = targets.iter().map(|(_, weight)| weight.max(0.0)).sum();
+                let options: Vec<_> = targets.iter()
+                    .map(|(target, weight)| {
+                        let p = if total > 0.0 { weight.max(0.0) / total } else { 1.0 / targets.len() as f64 };
+                        (self.from(target), p)
+                    })
+                    .collect();
+                CostRange::either(&options)
+            }
+            // Parallel branches each run once and end the execution
+            EdgeType::Parallel { targets } => targets.iter()
+                .fold(CostRange::default(), |range, target| range.plus(self.visit(target))),
+        }
+    }
+}
+
+/// Cost estimator for a registered graph, taking its input state as JSON
+#[derive(Clone)]
+pub struct CostEstimator {
+    estimate: Arc<dyn Fn(serde_json::Value, &TokenHistory) -> GraphResult<CostEstimate> + Send + Sync>,
+}
+
+impl CostEstimator {
+    /// Estimator for `graph`, priced with `profiles`
+    pub fn new<S>(graph: Arc<Graph<S>>, profiles: ModelProfileRegistry) -> Self
+    where
+        S: State + Serialize + DeserializeOwned,
+    {
+        Self {
+            estimate: Arc::new(move |input, history| {
+                let input: S = serde_json::from_value(input).map_err(|e| {
+                    GraphError::validation_error(format!("Input does not match the graph's state: {}", e))
+                })?;
+                graph.estimate_cost(&input, &profiles, history)
+            }),
+        }
+    }
+
+    /// Estimate a run starting from `input`
+    pub fn estimate(&self, input: serde_json::Value, history: &TokenHistory) -> GraphResult<CostEstimate> {
+        (self.estimate)(input, history)
+    }
+}
+
+impl std::fmt::Debug for CostEstimator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CostEstimator").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::conditions::AlwaysTrue;
+    use crate::edge::Edge;
+    use crate::node::Node;
+    use async_trait::async_trait;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct TestState {
+        question: String,
+    }
+
+    #[derive(Debug)]
+    struct LlmNode(Option<&'static str>);
+
+    #[async_trait]
+    impl Node<TestState> for LlmNode {
+        async fn invoke(&self, _state: &mut TestState) -> GraphResult<()> {
+            Ok(())
+        }
+
+        fn metadata(&self) -> NodeMetadata {
+            match self.0 {
+                Some(model) => NodeMetadata::new("LlmNode").with_llm(model, Some(100)),
+                None => NodeMetadata::new("LlmNode"),
+            }
+        }
+    }
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> LLMUsage {
+        LLMUsage {
+            calls: 1,
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_cost_over_branches() {
+        // plan -> {cheap, costly} on a condition, costly -> unknown
+        let mut graph: Graph<TestState> = Graph::new();
+        graph.add_node("plan".to_string(), LlmNode(Some("priced"))).unwrap();
+        graph.add_node("cheap".to_string(), LlmNode(None)).unwrap();
+        graph.add_node("costly".to_string(), LlmNode(Some("priced"))).unwrap();
+        graph.add_node("unknown".to_string(), LlmNode(Some("homegrown"))).unwrap();
+        graph.set_entry_point("plan".to_string()).unwrap();
+        graph.add_edge(Edge::conditional("plan", "always_true".to_string(), "costly", "cheap")).unwrap();
+        graph.add_edge(Edge::simple("costly", "unknown")).unwrap();
+        graph.add_finish_point("cheap".to_string()).unwrap();
+        graph.add_finish_point("unknown".to_string()).unwrap();
+        graph.edge_registry_mut().register_condition(AlwaysTrue);
+
+        let mut profiles = ModelProfileRegistry::new();
+        profiles.register(ModelProfile::new("priced", "test", 8000).with_cost(1.0, 2.0));
+        let mut history = TokenHistory::new();
+        history.record("costly".to_string(), usage(1000, 500));
+        history.record("costly".to_string(), usage(3000, 1500));
+
+        let input = TestState { question: "x".repeat(385) };
+        let estimate = graph.estimate_cost(&input, &profiles, &history).unwrap();
+        assert!(!estimate.has_cycles);
+        assert_eq!(estimate.unpriced_models, vec!["homegrown".to_string()]);
+
+        // plan: profile-based on a 100-token input, up to its 100-token limit
+        let plan = &estimate.nodes[2];
+        assert_eq!(plan.basis, EstimateBasis::Profile);
+        assert_eq!(plan.per_visit.max.total_tokens(), 200);
+        assert!((plan.per_visit.max.cost_usd - 0.3).abs() < 1e-9);
+
+        // costly: from the two recorded runs
+        let costly = &estimate.nodes[1];
+        assert_eq!((costly.basis, costly.samples), (EstimateBasis::History, 2));
+        assert_eq!(costly.per_visit.expected.prompt_tokens, 2000);
+        assert!((costly.per_visit.max.cost_usd - 6.0).abs() < 1e-9);
+
+        // min skips the costly branch, max takes it, expected weighs them evenly
+        assert!((estimate.total.min.cost_usd - 0.1).abs() < 1e-9);
+        assert_eq!(estimate.total.max.total_tokens(), 200 + 4500 + 200);
+        assert!((estimate.total.expected.cost_usd - (0.2 + 0.5 * 4.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_history_from_engine_runs() {
+        let mut context = ExecutionContext::new();
+        context.record_llm_usage("draft".to_string(), usage(1000, 500));
+        context.record_llm_usage("review".to_string(), LLMUsage::default());
+        let mut history = TokenHistory::new();
+        history.record_execution(&context);
+        assert_eq!(history.samples("draft"), &[usage(1000, 500)]);
+        assert!(history.samples("review").is_empty());
+
+        #[cfg(feature = "streaming")]
+        {
+            let completed = |node_id: &str, llm_usage: Option<LLMUsage>| ExecutionEvent::NodeCompleted {
+                execution_id: context.execution_id,
+                node_id: node_id.to_string(),
+                timestamp: chrono::Utc::now(),
+                duration_ms: 10,
+                success: true,
+                error: None,
+                llm_usage,
+                candidates: None,
+            };
+            let events = [completed("draft", Some(usage(3000, 1500))), completed("route", None)];
+            let history = TokenHistory::from_events(&events);
+            assert_eq!(history.samples("draft")[0].prompt_tokens, 3000);
+            assert!(history.samples("route").is_empty());
+        }
+    }
+}
@@ -0,0 +1,494 @@
+//! Debate and voting nodes for multi-agent workflows
+//! Runs several agents on the same task in parallel and aggregates their answers into one result
+
+use crate::agents::{Agent, AgentConfig};
+use crate::error::{GraphError, GraphResult};
+use crate::llm::{LLMManager, LLMUsage};
+use crate::node::{Node, NodeMetadata};
+use crate::state::State;
+use crate::tools::{ToolExecutor, ToolRegistry};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Scores a candidate answer; higher is better
+pub trait CandidateScorer: Send + Sync + Debug {
+    /// Score `output` produced for `task`
+    fn score(&self, task: &str, output: &str) -> f64;
+}
+
+/// How candidate answers are combined into one result
+#[derive(Debug, Clone)]
+pub enum Aggregation {
+    /// Pick the most common answer, comparing answers case- and whitespace-insensitively
+    MajorityVote,
+    /// Have a judge agent pick the best answer
+    Judge {
+        /// Judge agent
+        judge: Arc<Mutex<Agent>>,
+        /// What makes an answer best
+        criteria: String,
+    },
+    /// Pick the answer with the highest score
+    Score(Arc<dyn CandidateScorer>),
+}
+
+impl Aggregation {
+    /// Judge answers with an LLM agent
+    pub fn judge<C: Into<String>>(judge: Agent, criteria: C) -> Self {
+        Aggregation::Judge {
+            judge: Arc::new(Mutex::new(judge)),
+            criteria: criteria.into(),
+        }
+    }
+
+    /// Strategy name recorded in results
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregation::MajorityVote => "majority_vote",
+            Aggregation::Judge { .. } => "llm_judge",
+            Aggregation::Score(_) => "score",
+        }
+    }
+}
+
+/// One participant's answer in one round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    /// Participant that produced the answer
+    pub source: String,
+    /// Debate round, starting at 1
+    pub round: usize,
+    /// The answer, empty if the participant failed
+    pub output: String,
+    /// Why the participant failed
+    pub error: Option<String>,
+    /// Score given by a scorer
+    pub score: Option<f64>,
+    /// Whether this answer was chosen
+    pub selected: bool,
+}
+
+/// Outcome of the aggregation written to state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebateResult {
+    /// Aggregation strategy used
+    pub strategy: String,
+    /// Participant whose answer was chosen
+    pub winner: String,
+    /// Share of final answers matching the chosen one
+    pub agreement: f64,
+    /// Rounds run
+    pub rounds: usize,
+    /// Final-round answers that were considered
+    pub candidates: usize,
+    /// Judge's explanation, when judged
+    pub rationale: Option<String>,
+}
+
+/// Node running K agents on the same task and aggregating their answers
+///
+/// With one round every participant answers independently and the answers are
+/// voted on. With more rounds each participant sees the others' previous answers
+/// and may revise its own before the final round is aggregated. Every answer
+/// from every round is reported as the node's candidates in the execution trace.
+#[derive(Debug)]
+pub struct DebateNode {
+    participants: Vec<(String, Arc<Mutex<Agent>>)>,
+    task_template: String,
+    aggregation: Aggregation,
+    rounds: usize,
+    output_key: String,
+    result_key: String,
+    metadata: NodeMetadata,
+}
+
+/// Single-round debate: independent answers decided by vote
+pub type VoteNode = DebateNode;
+
+impl DebateNode {
+    /// Run each agent on the task
+    pub fn new(agents: Vec<Agent>, task_template: String) -> GraphResult<Self> {
+        if agents.is_empty() {
+            return Err(GraphError::validation_error("A debate needs at least one participant".to_string()));
+        }
+
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let participants = agents
+            .into_iter()
+            .map(|agent| {
+                let name = agent.config().name.clone();
+                let count = seen.entry(name.clone()).or_insert(0);
+                *count += 1;
+                let name = if *count > 1 { format!("{}#{}", name, count) } else { name };
+                (name, Arc::new(Mutex::new(agent)))
+            })
+            .collect();
+
+        let metadata = NodeMetadata::new("DebateNode")
+            .with_description("Runs several agents on one task and aggregates their answers")
+            .with_tag("agent")
+            .with_tag("debate")
+            .with_parallel_safe(false);
+
+        Ok(Self {
+            participants,
+            task_template,
+            aggregation: Aggregation::MajorityVote,
+            rounds: 1,
+            output_key: "output".to_string(),
+            result_key: "debate".to_string(),
+            metadata,
+        })
+    }
+
+    /// Take `samples` independent samples from agents sharing one configuration
+    ///
+    /// Sampling only yields different answers with a non-zero temperature.
+    pub fn sampled(
+        config: AgentConfig,
+        samples: usize,
+        llm_manager: Arc<LLMManager>,
+        tool_registry: Arc<ToolRegistry>,
+        tool_executor: Arc<ToolExecutor>,
+        task_template: String,
+    ) -> GraphResult<Self> {
+        let agents = (0..samples)
+            .map(|_| Agent::new(config.clone(), llm_manager.clone(), tool_registry.clone(), tool_executor.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| GraphError::validation_error(format!("Failed to create sampling agent: {}", e)))?;
+        Self::new(agents, task_template)
+    }
+
+    /// Set how answers are aggregated
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Let participants see each other's answers for `rounds` rounds
+    pub fn with_rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds.max(1);
+        self
+    }
+
+    /// Write the chosen answer under `key`
+    pub fn with_output_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.output_key = key.into();
+        self
+    }
+
+    /// Write the [`DebateResult`] under `key`
+    pub fn with_result_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.result_key = key.into();
+        self
+    }
+
+    /// Names of the participants
+    pub fn participants(&self) -> Vec<&str> {
+        self.participants.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Build task from template and state
+    fn build_task<S: State>(&self, state: &S) -> String {
+        let mut task = self.task_template.clone();
+        for key in ["input", "context", "query"] {
+            let placeholder = format!("{{{}}}", key);
+            if task.contains(&placeholder) {
+                if let Some(value) = state.get_value(key) {
+                    let value = match value {
+                        serde_json::Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    task = task.replace(&placeholder, &value);
+                }
+            }
+        }
+        task
+    }
+
+    /// Prompt for a later round, showing the other participants' answers
+    fn debate_prompt(task: &str, own: &str, previous: &[Candidate]) -> String {
+        let mut prompt = format!("{}\n\nOther agents answered this task as follows:\n", task);
+        for candidate in previous.iter().filter(|c| c.source != own && c.error.is_none()) {
+            prompt.push_str(&format!("\n[{}]\n{}\n", candidate.source, candidate.output));
+        }
+        prompt.push_str("\nConsider their reasoning, point out any mistakes, and give your final answer.");
+        prompt
+    }
+
+    /// Run one round with every participant in parallel
+    async fn run_round(&self, task: &str, round: usize, previous: &[Candidate], usage: &mut LLMUsage) -> Vec<Candidate> {
+        let runs = self.participants.iter().map(|(name, agent)| {
+            let prompt = if previous.is_empty() {
+                task.to_string()
+            } else {
+                Self::debate_prompt(task, name, previous)
+            };
+            async move {
+                let mut agent = agent.lock().await;
+                let result = agent.execute_task(prompt).await;
+                (name.clone(), result, agent.state().last_task_usage.clone())
+            }
+        });
+
+        futures::future::join_all(runs)
+            .await
+            .into_iter()
+            .map(|(source, result, run_usage)| {
+                usage.merge(&run_usage);
+                let (output, error) = match result {
+                    Ok(output) => (output, None),
+                    Err(e) => {
+                        tracing::warn!("Debate participant '{}' failed in round {}: {}", source, round, e);
+                        (String::new(), Some(e.to_string()))
+                    }
+                };
+                Candidate { source, round, output, error, score: None, selected: false }
+            })
+            .collect()
+    }
+
+    /// Pick the winning answer among the final round's candidates
+    async fn aggregate(&self, task: &str, candidates: &mut [Candidate], usage: &mut LLMUsage) -> GraphResult<(usize, Option<String>)> {
+        let answered: Vec<usize> = (0..candidates.len()).filter(|&i| candidates[i].error.is_none()).collect();
+
+        match &self.aggregation {
+            Aggregation::MajorityVote => Ok((majority(candidates, &answered), None)),
+            Aggregation::Score(scorer) => {
+                let mut best = answered[0];
+                for &i in &answered {
+                    let score = scorer.score(task, &candidates[i].output);
+                    candidates[i].score = Some(score);
+                    if score > candidates[best].score.unwrap_or(f64::NEG_INFINITY) {
+                        best = i;
+                    }
+                }
+                Ok((best, None))
+            }
+            Aggregation::Judge { judge, criteria } => {
+                let mut prompt = format!(
+                    "Several agents answered the task below. Pick the best answer by these criteria: {}\n\nTask:\n{}\n",
+                    criteria, task
+                );
+                for (number, &i) in answered.iter().enumerate() {
+                    prompt.push_str(&format!("\nAnswer {}:\n{}\n", number + 1, candidates[i].output));
+                }
+                prompt.push_str("\nReply with the number of the best answer on the first line, then a one-sentence reason.");
+
+                let mut judge = judge.lock().await;
+                let verdict = judge.execute_task(prompt).await.map_err(|e| GraphError::node_error(
+                    "debate_node".to_string(),
+                    format!("Judge agent failed: {}", e),
+                    Some(Box::new(e)),
+                ))?;
+                usage.merge(&judge.state().last_task_usage);
+
+                let choice = parse_choice(&verdict, answered.len()).ok_or_else(|| GraphError::node_error(
+                    "debate_node".to_string(),
+                    format!("Judge did not pick one of {} answers: {}", answered.len(), verdict.trim()),
+                    None,
+                ))?;
+                Ok((answered[choice - 1], Some(verdict.trim().to_string())))
+            }
+        }
+    }
+
+    async fn debate<S: State>(&self, state: &mut S) -> GraphResult<DebateResult> {
+        let task = self.build_task(state);
+        let mut usage = LLMUsage::default();
+        let mut history: Vec<Candidate> = Vec::new();
+        let mut final_round: Vec<Candidate> = Vec::new();
+
+        for round in 1..=self.rounds {
+            final_round = self.run_round(&task, round, &final_round, &mut usage).await;
+            if final_round.iter().all(|candidate| candidate.error.is_some()) {
+                return Err(GraphError::node_error(
+                    "debate_node".to_string(),
+                    format!("All {} participants failed in round {}", final_round.len(), round),
+                    None,
+                ));
+            }
+            if round < self.rounds {
+                history.extend(final_round.iter().cloned());
+            }
+        }
+
+        let (winner, rationale) = self.aggregate(&task, &mut final_round, &mut usage).await?;
+        final_round[winner].selected = true;
+        let output = final_round[winner].output.clone();
+        let answered = final_round.iter().filter(|c| c.error.is_none()).count();
+        let agreeing = final_round.iter()
+            .filter(|c| c.error.is_none() && normalize(&c.output) == normalize(&output))
+            .count();
+
+        let result = DebateResult {
+            strategy: self.aggregation.name().to_string(),
+            winner: final_round[winner].source.clone(),
+            agreement: agreeing as f64 / answered as f64,
+            rounds: self.rounds,
+            candidates: answered,
+            rationale,
+        };
+        tracing::info!(
+            "Debate chose the answer from '{}' ({:.0}% agreement)",
+            result.winner,
+            result.agreement * 100.0
+        );
+
+        history.extend(final_round);
+        state.set_value(&self.output_key, serde_json::Value::String(output))?;
+        state.set_value(&self.result_key, to_state_value(&result)?)?;
+        super::report::candidates(to_state_value(&history)?);
+        super::agent_node::report_llm_usage(&usage);
+        Ok(result)
+    }
+}
+
+fn to_state_value<T: Serialize>(value: &T) -> GraphResult<serde_json::Value> {
+    serde_json::to_value(value)
+        .map_err(|e| GraphError::state_error(format!("Failed to serialize debate result: {}", e)))
+}
+
+/// Answer compared for voting: lowercase, collapsed whitespace, no trailing punctuation
+fn normalize(output: &str) -> String {
+    output
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_string()
+}
+
+/// Index of the most common answer, breaking ties by earliest answer
+fn majority(candidates: &[Candidate], answered: &[usize]) -> usize {
+    let mut counts: Vec<(String, usize, usize)> = Vec::new();
+    for &i in answered {
+        let key = normalize(&candidates[i].output);
+        match counts.iter_mut().find(|(k, _, _)| *k == key) {
+            Some(entry) => entry.1 += 1,
+            None => counts.push((key, 1, i)),
+        }
+    }
+    // max_by_key returns the last maximum, so compare in reverse to keep the earliest
+    counts.iter().rev().max_by_key(|(_, count, _)| *count).map_or(answered[0], |(_, _, first)| *first)
+}
+
+/// Answer number (1-based) the judge picked
+fn parse_choice(verdict: &str, answers: usize) -> Option<usize> {
+    let start = verdict.find(|c: char| c.is_ascii_digit())?;
+    let number: String = verdict[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    number.parse().ok().filter(|choice| (1..=answers).contains(choice))
+}
+
+#[async_trait]
+impl<S> Node<S> for DebateNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        self.debate(state).await.map(|_| ())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::roles::RoleTemplates;
+    use crate::llm::{LLMConfig, providers::MockProvider};
+
+    fn create_agent(name: &str, responses: Vec<&str>) -> Agent {
+        let mut llm_manager = LLMManager::new(LLMConfig::default());
+        let provider = MockProvider::with_responses(responses.into_iter().map(str::to_string).collect());
+        llm_manager.register_provider("mock".to_string(), Arc::new(provider));
+
+        let config = RoleTemplates::research_analyst().to_agent_config(name.to_string(), "mock".to_string());
+        Agent::new(config, Arc::new(llm_manager), Arc::new(ToolRegistry::new()), Arc::new(ToolExecutor::new())).unwrap()
+    }
+
+    fn candidate(source: &str, output: &str) -> Candidate {
+        Candidate {
+            source: source.to_string(),
+            round: 1,
+            output: output.to_string(),
+            error: None,
+            score: None,
+            selected: false,
+        }
+    }
+
+    #[derive(Debug)]
+    struct LengthScorer;
+
+    impl CandidateScorer for LengthScorer {
+        fn score(&self, _task: &str, output: &str) -> f64 {
+            output.len() as f64
+        }
+    }
+
+    #[test]
+    fn test_majority_vote() {
+        let candidates = vec![
+            candidate("a", "Paris"),
+            candidate("b", "Lyon"),
+            candidate("c", "paris."),
+            candidate("d", "Lyon"),
+            candidate("e", "  Paris "),
+        ];
+        assert_eq!(majority(&candidates, &[0, 1, 2, 3, 4]), 0);
+
+        // Ties go to the earliest answer
+        assert_eq!(majority(&candidates, &[1, 0]), 1);
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("2\nIt cites sources.", 3), Some(2));
+        assert_eq!(parse_choice("Answer 3 is best", 3), Some(3));
+        assert_eq!(parse_choice("Answer 4 is best", 3), None);
+        assert_eq!(parse_choice("None of them", 3), None);
+    }
+
+    #[test]
+    fn test_participant_names_are_unique() {
+        let node = DebateNode::new(
+            vec![create_agent("analyst", vec![]), create_agent("analyst", vec![]), create_agent("critic", vec![])],
+            "{input}".to_string(),
+        ).unwrap();
+        assert_eq!(node.participants(), vec!["analyst", "analyst#2", "critic"]);
+        assert!(DebateNode::new(Vec::new(), "{input}".to_string()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_score_aggregation() {
+        let node = DebateNode::new(
+            vec![create_agent("brief", vec!["Short"]), create_agent("thorough", vec!["A longer answer"])],
+            "Explain".to_string(),
+        ).unwrap()
+        .with_aggregation(Aggregation::Score(Arc::new(LengthScorer)));
+
+        let mut usage = LLMUsage::default();
+        let mut candidates = node.run_round("Explain", 1, &[], &mut usage).await;
+        let (winner, rationale) = node.aggregate("Explain", &mut candidates, &mut usage).await.unwrap();
+
+        assert_eq!(candidates[winner].source, "thorough");
+        assert_eq!(candidates[0].score, Some(5.0));
+        assert!(rationale.is_none());
+    }
+
+    #[test]
+    fn test_later_rounds_show_other_answers() {
+        let previous = vec![candidate("a", "Paris"), candidate("b", "Lyon")];
+        let prompt = DebateNode::debate_prompt("Capital of France?", "a", &previous);
+        assert!(prompt.contains("[b]\nLyon"));
+        assert!(!prompt.contains("[a]"));
+    }
+}
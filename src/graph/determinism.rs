@@ -0,0 +1,307 @@
+//! Deterministic mode: an injectable clock and seeded randomness.
+//!
+//! The engine reads the time and draws random numbers through this module:
+//! execution IDs and start times, event and node timestamps and durations,
+//! retry jitter, and weighted and random routing. Outside of deterministic
+//! mode that means the system clock and the thread's RNG. An engine given a
+//! [`Determinism`] with
+//! [`GraphEngine::with_determinism`](crate::graph::engine::GraphEngine::with_determinism)
+//! uses its [`Clock`] and [`SeededRng`] instead, so a test or a replay run
+//! with the same seed and a [`FrozenClock`] takes the same routes and
+//! produces the same IDs, timestamps and events every time.
+//!
+//! Nodes that want the same guarantee read [`now`] and draw from [`with_rng`]
+//! rather than calling `chrono::Utc::now` or `rand` directly.
+//!
+//! Timeouts are measured on tokio's timer, so tests of timeout behavior can
+//! fast-forward them: under `#[tokio::test(start_paused = true)]` (tokio's
+//! `test-util` feature) node timeouts, retry delays, edge throttles and
+//! session limits elapse as soon as every task is idle. [`SimulatedClock`]
+//! makes the timestamps, the execution time limit, rate-limit windows and the
+//! expiry of approval requests and resume tokens follow the same time.
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Source of the current time
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// With a step, every reading advances the clock by the step afterwards, so
+/// successive timestamps stay ordered and durations are non-zero while still
+/// being the same on every run.
+#[derive(Debug)]
+pub struct FrozenClock {
+    now: Mutex<DateTime<Utc>>,
+    step: chrono::Duration,
+}
+
+impl FrozenClock {
+    /// A clock stopped at `at`
+    pub fn new(at: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(at), step: chrono::Duration::zero() }
+    }
+
+    /// A clock stopped at the Unix epoch
+    pub fn at_epoch() -> Self {
+        Self::new(DateTime::<Utc>::UNIX_EPOCH)
+    }
+
+    /// Advance by `step` after every reading
+    pub fn with_step(mut self, step: Duration) -> Self {
+        self.step = chrono::Duration::from_std(step).unwrap_or_else(|_| chrono::Duration::zero());
+        self
+    }
+
+    /// Set the time
+    pub fn set(&self, at: DateTime<Utc>) {
+        *self.now.lock() = at;
+    }
+
+    /// Move the time forward
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock() += chrono::Duration::from_std(by).unwrap_or_else(|_| chrono::Duration::zero());
+    }
+}
+
+impl Clock for FrozenClock {
+    fn now(&self) -> DateTime<Utc> {
+        let mut now = self.now.lock();
+        let reading = *now;
+        *now += self.step;
+        reading
+    }
+}
+
+/// A clock that follows tokio's timer
+///
+/// Reads `origin` plus the time the timer has moved since the clock was
+/// created, so when tokio's time is paused, sleeping or advancing it moves
+/// this clock too.
+#[derive(Debug)]
+pub struct SimulatedClock {
+    origin: DateTime<Utc>,
+    started: tokio::time::Instant,
+}
+
+impl SimulatedClock {
+    /// A clock starting at `origin` now
+    pub fn new(origin: DateTime<Utc>) -> Self {
+        Self { origin, started: tokio::time::Instant::now() }
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = chrono::Duration::from_std(self.started.elapsed()).unwrap_or_else(|_| chrono::Duration::zero());
+        self.origin + elapsed
+    }
+}
+
+/// Random number generator seeded for reproducible runs
+///
+/// Clones share one generator, so the sequence of draws across an execution
+/// depends only on the seed and the order of the draws.
+#[derive(Clone)]
+pub struct SeededRng {
+    seed: u64,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl SeededRng {
+    /// A generator starting from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { seed, rng: Arc::new(Mutex::new(StdRng::seed_from_u64(seed))) }
+    }
+
+    /// Seed the generator started from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Run `f` with the generator
+    pub fn with<R>(&self, f: impl FnOnce(&mut dyn RngCore) -> R) -> R {
+        f(&mut *self.rng.lock())
+    }
+}
+
+impl fmt::Debug for SeededRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeededRng").field("seed", &self.seed).finish()
+    }
+}
+
+/// Clock and random source an engine runs with
+#[derive(Debug, Clone, Default)]
+pub struct Determinism {
+    clock: Option<Arc<dyn Clock>>,
+    rng: Option<SeededRng>,
+}
+
+impl Determinism {
+    /// The system clock and unseeded randomness
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A frozen clock at the Unix epoch, stepping a millisecond per reading, and randomness seeded with `seed`
+    pub fn seeded(seed: u64) -> Self {
+        Self::new()
+            .with_clock(Arc::new(FrozenClock::at_epoch().with_step(Duration::from_millis(1))))
+            .with_seed(seed)
+    }
+
+    /// A clock following tokio's timer from the Unix epoch, and randomness seeded with `seed`
+    ///
+    /// Create it inside the runtime whose time the test pauses.
+    pub fn simulated(seed: u64) -> Self {
+        Self::new().with_clock(Arc::new(SimulatedClock::new(DateTime::<Utc>::UNIX_EPOCH))).with_seed(seed)
+    }
+
+    /// Read the time from `clock`
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Draw random numbers from a generator seeded with `seed`
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Some(SeededRng::new(seed));
+        self
+    }
+
+    /// The clock, if one was injected
+    pub fn clock(&self) -> Option<&Arc<dyn Clock>> {
+        self.clock.as_ref()
+    }
+
+    /// The seeded generator, if there is one
+    pub fn rng(&self) -> Option<&SeededRng> {
+        self.rng.as_ref()
+    }
+
+    /// Check if neither a clock nor a seed was injected
+    pub fn is_default(&self) -> bool {
+        self.clock.is_none() && self.rng.is_none()
+    }
+}
+
+tokio::task_local! {
+    static DETERMINISM: Determinism;
+}
+
+/// The current time, from the running engine's clock
+pub fn now() -> DateTime<Utc> {
+    DETERMINISM
+        .try_with(|determinism| determinism.clock.as_ref().map(|clock| clock.now()))
+        .ok()
+        .flatten()
+        .unwrap_or_else(Utc::now)
+}
+
+/// The current time as a [`SystemTime`](std::time::SystemTime), from the running engine's clock
+pub fn system_now() -> std::time::SystemTime {
+    now().into()
+}
+
+/// Run `f` with the running engine's seeded generator, or the thread's generator
+pub fn with_rng<R>(f: impl FnOnce(&mut dyn RngCore) -> R) -> R {
+    let seeded = DETERMINISM.try_with(|determinism| determinism.rng.clone()).ok().flatten();
+    match seeded {
+        Some(rng) => rng.with(f),
+        None => f(&mut rand::thread_rng()),
+    }
+}
+
+/// A random number in `[0, 1)`
+pub fn random_f64() -> f64 {
+    with_rng(|rng| rng.gen::<f64>())
+}
+
+/// A new random (version 4) UUID
+pub fn new_uuid() -> Uuid {
+    let mut bytes = [0u8; 16];
+    with_rng(|rng| rng.fill_bytes(&mut bytes));
+    uuid::Builder::from_random_bytes(bytes).into_uuid()
+}
+
+/// Run `future` with `determinism` as the running engine's
+///
+/// Engines enter it themselves; tests enter it to put code that runs outside
+/// of an execution, e.g. an approval sweep, on the same clock. The default
+/// leaves any enclosing clock and seed in place.
+pub async fn scope<F: Future>(determinism: &Determinism, future: F) -> F::Output {
+    if determinism.is_default() {
+        future.await
+    } else {
+        DETERMINISM.scope(determinism.clone(), future).await
+    }
+}
+
+/// Carry the running engine's clock and seed into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = DETERMINISM.try_with(Determinism::clone).ok();
+    async move {
+        match value {
+            Some(value) => DETERMINISM.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Call `f` with `determinism` as the running engine's
+pub(crate) fn sync_scope<R>(determinism: &Determinism, f: impl FnOnce() -> R) -> R {
+    if determinism.is_default() {
+        f()
+    } else {
+        DETERMINISM.sync_scope(determinism.clone(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn draws() -> (Uuid, f64, DateTime<Utc>, DateTime<Utc>) {
+        (new_uuid(), random_f64(), now(), now())
+    }
+
+    #[tokio::test]
+    async fn test_seeded_runs_repeat() {
+        let first = scope(&Determinism::seeded(7), draws()).await;
+        let second = scope(&Determinism::seeded(7), draws()).await;
+        let other = scope(&Determinism::seeded(8), draws()).await;
+        assert_eq!(first, second);
+        assert_ne!(first.0, other.0);
+        assert_eq!(first.0.get_version_num(), 4);
+        assert_eq!(first.2, DateTime::<Utc>::UNIX_EPOCH);
+        assert_eq!(first.3 - first.2, chrono::Duration::milliseconds(1));
+
+        let clock = Arc::new(FrozenClock::at_epoch());
+        let determinism = Determinism::new().with_clock(clock.clone());
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(scope(&determinism, async { now() }).await.timestamp(), 60);
+        assert!(now() > DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::days(365));
+    }
+}
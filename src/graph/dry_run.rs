@@ -0,0 +1,162 @@
+//! Dry-run execution: LLM calls answered by the mock provider, tools by their declared
+//! sample outputs, and writes skipped, with every simulated effect recorded.
+
+use crate::node::NodeId;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+
+/// What a dry run did instead of a real side effect
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimulatedEffectKind {
+    /// LLM call answered by the mock provider
+    LlmCall {
+        /// Provider the call was meant for
+        provider: String,
+        /// Model the call asked for
+        model: String,
+    },
+    /// Tool call answered with the tool's declared sample output
+    ToolSampled {
+        /// Tool ID
+        tool_id: String,
+    },
+    /// Tool without side effects or a sample output, run for real
+    ToolExecuted {
+        /// Tool ID
+        tool_id: String,
+    },
+    /// Call to a tool with side effects and no sample output, skipped
+    WriteSkipped {
+        /// Tool ID
+        tool_id: String,
+    },
+    /// Checkpoint that was not saved
+    CheckpointSkipped,
+    /// Outbox message that was not performed
+    OutboxSkipped {
+        /// Effect the message asked for
+        effect: String,
+    },
+}
+
+/// A simulated effect and where in the execution it happened
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SimulatedEffect {
+    /// Node running when the effect happened
+    pub node_id: Option<NodeId>,
+    /// Step of that node
+    pub step: Option<u64>,
+    /// The effect
+    #[serde(flatten)]
+    pub kind: SimulatedEffectKind,
+}
+
+/// Effects recorded by a dry run, shared by everything running inside it
+#[derive(Debug, Clone, Default)]
+pub struct DryRunLog {
+    effects: Arc<parking_lot::Mutex<Vec<SimulatedEffect>>>,
+}
+
+impl DryRunLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Effects recorded so far
+    pub fn effects(&self) -> Vec<SimulatedEffect> {
+        self.effects.lock().clone()
+    }
+
+    /// Remove and return the recorded effects
+    pub fn take(&self) -> Vec<SimulatedEffect> {
+        std::mem::take(&mut *self.effects.lock())
+    }
+}
+
+tokio::task_local! {
+    static DRY_RUN: DryRunScope;
+}
+
+#[derive(Debug, Clone)]
+struct DryRunScope {
+    log: DryRunLog,
+    node_id: Option<NodeId>,
+    step: Option<u64>,
+}
+
+/// Run `future` as a dry run, recording its simulated effects in `log`
+pub async fn with_dry_run<F: Future>(log: DryRunLog, future: F) -> F::Output {
+    DRY_RUN.scope(DryRunScope { log, node_id: None, step: None }, future).await
+}
+
+/// Attribute effects recorded by `future` to a node, if a dry run is active
+pub(crate) async fn in_node<F: Future>(node_id: &NodeId, step: u64, future: F) -> F::Output {
+    let Ok(log) = DRY_RUN.try_with(|scope| scope.log.clone()) else {
+        return future.await;
+    };
+    let scope = DryRunScope {
+        log,
+        node_id: Some(node_id.clone()),
+        step: Some(step),
+    };
+    DRY_RUN.scope(scope, future).await
+}
+
+/// Carry the current dry run into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = DRY_RUN.try_with(DryRunScope::clone).ok();
+    async move {
+        match value {
+            Some(value) => DRY_RUN.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Whether the current task is part of a dry run
+pub fn is_active() -> bool {
+    DRY_RUN.try_with(|_| ()).is_ok()
+}
+
+/// Record a simulated effect; does nothing outside a dry run
+pub fn record(kind: SimulatedEffectKind) {
+    let _ = DRY_RUN.try_with(|scope| {
+        scope.log.effects.lock().push(SimulatedEffect {
+            node_id: scope.node_id.clone(),
+            step: scope.step,
+            kind,
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_effects_attributed_to_nodes() {
+        assert!(!is_active());
+        record(SimulatedEffectKind::CheckpointSkipped);
+
+        let log = DryRunLog::new();
+        with_dry_run(log.clone(), async {
+            assert!(is_active());
+            record(SimulatedEffectKind::CheckpointSkipped);
+            in_node(&"write".to_string(), 2, async {
+                record(SimulatedEffectKind::WriteSkipped { tool_id: "http_post".to_string() });
+            })
+            .await;
+        })
+        .await;
+
+        let effects = log.take();
+        assert_eq!(effects.len(), 2);
+        assert_eq!(effects[0].node_id, None);
+        assert_eq!(effects[1].node_id.as_deref(), Some("write"));
+        assert_eq!(effects[1].step, Some(2));
+        assert!(log.effects().is_empty());
+    }
+}
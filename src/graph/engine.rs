@@ -1,18 +1,61 @@
 //! Core graph execution engine.
 
 use crate::edge::routing::{EdgeResolver, RouteResolution};
+use crate::edge::speculation::SpeculationOutcome;
+use crate::edge::throttle::EdgeWait;
+use crate::edge::Edge;
+use crate::enterprise::sandbox;
+use crate::enterprise::slo::RunSample;
 use crate::error::{GraphError, GraphResult};
+use crate::execution::webhooks::{WebhookEvent, WebhookPayload};
+use crate::execution::ConcurrencyPools;
+use crate::graph::compiled::CompiledRoute;
+use crate::graph::context_vars::{self, ContextVars};
+use crate::graph::control::DRAIN_OPERATION;
+use crate::graph::determinism::{self, Determinism};
+use crate::graph::dry_run::{self, DryRunLog};
+use crate::graph::flags::{self, FlagContext};
+use crate::graph::outbox::OutboxState;
+use crate::graph::outcome::NodeOutcomeStatus;
+use crate::graph::report::{NodeReport, NodeReports};
+use crate::graph::retry;
+use crate::graph::saga;
+use crate::graph::services::{self, Services};
+use crate::graph::watchdog::{self, WatchdogAction};
+use crate::graph::session_limits::{self, SessionLimiter};
+use crate::graph::tuning::{NodePools, NodeWorkload};
 use crate::graph::{ExecutionContext, Graph};
-use crate::node::{NodeExecutionContext, NodeId};
+use crate::node::concurrency::{self, ConcurrencyKeys};
+use crate::node::lifecycle::{NodeLifecycle, ResourcePool};
+use crate::node::{Node, NodeExecutionContext, NodeId, SharedNode};
+use crate::state::dead_letter::{DeadLetter, DeadLetterStatus};
 use crate::state::State;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::task::JoinSet;
 use tokio::time::timeout;
 
 
 #[cfg(feature = "streaming")]
 use crate::streaming::ExecutionEvent;
 
+#[cfg(feature = "checkpointing")]
+use crate::graph::{ExecutionLineage, fork::{BranchOutcome, ForkBranch}};
+
+tokio::task_local! {
+    static EXECUTION_ID: uuid::Uuid;
+}
+
+/// ID of the execution the current task is running, if any
+///
+/// Lets nodes and tools file what they produce, e.g. artifacts, under the
+/// execution without it being threaded through their inputs.
+pub fn current_execution_id() -> Option<uuid::Uuid> {
+    EXECUTION_ID.try_with(|id| *id).ok()
+}
+
 /// Graph execution engine
 #[derive(Debug)]
 pub struct GraphEngine<S>
@@ -21,6 +64,14 @@ where
 {
     /// Edge resolver for routing decisions
     edge_resolver: EdgeResolver<S>,
+    /// Nodes set up by this engine and the resources they share
+    lifecycle: Arc<NodeLifecycle>,
+    /// Shared clients nodes resolve while they run
+    services: Services,
+    /// Clock and random source executions run with
+    determinism: Determinism,
+    /// Per-class limits on the nodes running at once, across executions
+    pools: Option<Arc<ConcurrencyPools>>,
 }
 
 impl<S> GraphEngine<S>
@@ -31,17 +82,273 @@ where
     pub fn new() -> Self {
         Self {
             edge_resolver: EdgeResolver::new(),
+            lifecycle: Arc::new(NodeLifecycle::new()),
+            services: Services::new(),
+            determinism: Determinism::new(),
+            pools: None,
+        }
+    }
+
+    /// Let nodes resolve `services` through [`services::resolve`] while they run
+    pub fn with_services(mut self, services: Services) -> Self {
+        self.services = services;
+        self
+    }
+
+    /// Shared clients nodes resolve while they run
+    pub fn services(&self) -> &Services {
+        &self.services
+    }
+
+    /// Read the time and draw random numbers through `determinism`
+    ///
+    /// With a frozen clock and a seed, re-running an execution reproduces its
+    /// IDs, timestamps, retry delays and weighted or random routes.
+    pub fn with_determinism(mut self, determinism: Determinism) -> Self {
+        self.determinism = determinism;
+        self
+    }
+
+    /// Clock and random source executions run with
+    pub fn determinism(&self) -> &Determinism {
+        &self.determinism
+    }
+
+    /// Limit the nodes running at once through `pools`
+    ///
+    /// Each node takes a permit from every pool its metadata names and one
+    /// from the global budget before it runs, in every execution of this
+    /// engine, parallel branches included.
+    pub fn with_concurrency_pools(mut self, pools: ConcurrencyPools) -> Self {
+        self.pools = Some(Arc::new(pools));
+        self
+    }
+
+    /// Pools limiting the nodes running at once, if any
+    pub fn concurrency_pools(&self) -> Option<&Arc<ConcurrencyPools>> {
+        self.pools.as_ref()
+    }
+
+    /// Resources shared by the nodes this engine sets up
+    pub fn resources(&self) -> &ResourcePool {
+        self.lifecycle.resources()
+    }
+
+    /// An engine configured like this one, for an execution run on a task of its own
+    ///
+    /// It runs with the same services, determinism and concurrency pools, and
+    /// shares the nodes this engine has set up and their resources.
+    pub fn sibling(&self) -> Self {
+        Self {
+            edge_resolver: EdgeResolver::new(),
+            lifecycle: self.lifecycle.clone(),
+            services: self.services.clone(),
+            determinism: self.determinism.clone(),
+            pools: self.pools.clone(),
+        }
+    }
+
+    /// Scopes an execution of `graph` over `state` runs its nodes in
+    ///
+    /// With `admit` the execution counts as a new message from its session,
+    /// and is refused if the session is over a limit. Resumes, retries and
+    /// forks continue a message that was already admitted.
+    fn scope_for(&self, graph: &Graph<S>, state: &S, execution_id: uuid::Uuid, admit: bool) -> GraphResult<ExecutionScope> {
+        let session = match (graph.session_limiter(), SessionLimiter::session_of(state)) {
+            (Some(limiter), Some(session)) => {
+                if admit {
+                    limiter.admit(&session)?;
+                }
+                Some((limiter.clone(), session))
+            }
+            _ => None,
+        };
+        let sandbox = graph.sandbox_registry().and_then(|registry| {
+            registry.sandbox_for(FlagContext::from_state(state).tenant.as_deref(), &graph.metadata().name)
+        });
+        Ok(ExecutionScope {
+            execution_id,
+            services: self.services.clone(),
+            determinism: self.determinism.clone(),
+            session,
+            sandbox,
+        })
+    }
+
+    /// Set up every node of a graph ahead of its first execution
+    ///
+    /// Nodes are otherwise set up lazily before they first run. Either way
+    /// each node is set up once per engine, so reusing the engine across
+    /// executions keeps clients and warmed-up models alive between runs.
+    pub async fn warm_up(&self, graph: &Graph<S>) -> GraphResult<()> {
+        let setups = graph.node_registry()
+            .list_nodes()
+            .into_iter()
+            .filter_map(|node_id| {
+                let node = graph.node_registry().get(node_id)?;
+                Some(self.lifecycle.ensure_setup(graph.id(), node_id, node))
+            });
+        futures::future::try_join_all(setups).await?;
+        Ok(())
+    }
+
+    /// Clean up the nodes of a graph that this engine set up
+    ///
+    /// Every node is cleaned up even if some fail; the first error is returned.
+    /// Shared resources stay pooled for other graphs run by the engine.
+    pub async fn teardown(&self, graph: &Graph<S>) -> GraphResult<()> {
+        let mut first_error = None;
+        for node_id in self.lifecycle.take_ready(graph.id()) {
+            let Some(node) = graph.node_registry().get(&node_id) else { continue };
+            if let Err(error) = node.cleanup().await {
+                tracing::warn!(node_id = %node_id, error = %error, "Node cleanup failed");
+                first_error.get_or_insert(error);
+            }
         }
+        first_error.map_or(Ok(()), Err)
     }
 
     /// Execute a graph with the given state
+    ///
+    /// A [`CompiledGraph`](crate::graph::compiled::CompiledGraph) is accepted
+    /// too, through deref, and was validated when it was compiled.
     pub async fn execute(&mut self, graph: &Graph<S>, state: &mut S) -> GraphResult<ExecutionContext> {
-        // Validate the graph
-        graph.validate()?;
+        self.execute_attempts(graph, state, None, None).await
+    }
+
+    /// Execute a graph with context variables kept out of its state
+    ///
+    /// Nodes, edge conditions and tools read `vars` through
+    /// [`context_vars::current`] while the execution runs. To resume or fork
+    /// an execution with them, run it inside [`context_vars::scope`].
+    pub async fn execute_with_context(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        vars: ContextVars,
+    ) -> GraphResult<ExecutionContext> {
+        context_vars::scope(vars, self.execute_attempts(graph, state, None, None)).await
+    }
+
+    /// Execute a graph under a caller-chosen idempotency key
+    ///
+    /// Side effects recorded under `key` in the graph's idempotency ledger are
+    /// not performed again, so a trigger delivered twice with the same key,
+    /// e.g. a webhook's event ID, runs its side effects once.
+    pub async fn execute_with_idempotency_key(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        key: impl Into<String>,
+    ) -> GraphResult<ExecutionContext> {
+        self.execute_attempts(graph, state, Some(key.into()), None).await
+    }
+
+    /// Execute a graph under a caller-chosen execution ID
+    ///
+    /// Lets a caller that shares the graph's event emitter between executions
+    /// pick out the events of this one before it starts.
+    pub async fn execute_with_id(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        execution_id: uuid::Uuid,
+    ) -> GraphResult<ExecutionContext> {
+        self.execute_attempts(graph, state, None, Some(execution_id)).await
+    }
+
+    /// Run an execution with the engine's clock and random source
+    async fn execute_attempts(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        key: Option<String>,
+        execution_id: Option<uuid::Uuid>,
+    ) -> GraphResult<ExecutionContext> {
+        let determinism = self.determinism.clone();
+        determinism::scope(&determinism, self.attempts(graph, state, key, execution_id)).await
+    }
 
-        // Create execution context
+    /// Run an execution, again from its input state while its retry policy allows
+    async fn attempts(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        key: Option<String>,
+        execution_id: Option<uuid::Uuid>,
+    ) -> GraphResult<ExecutionContext> {
         let mut context = ExecutionContext::new();
-        
+        if let Some(execution_id) = execution_id {
+            context.execution_id = execution_id;
+        }
+        // Admitted once per execution and one sandbox for all its attempts,
+        // so retries are not new messages and the sandbox's spend carries over
+        let scope = self.scope_for(graph, state, context.execution_id, true)?;
+        let policy = graph.config().retry.clone().unwrap_or_default();
+        let input = (graph.dead_letter_queue().is_some() || policy.max_attempts > 1).then(|| state.clone());
+        let ledger = graph.idempotency_ledger().cloned().unwrap_or_default();
+        let own_key = key.is_none();
+        let key = key.unwrap_or_else(|| context.execution_id.to_string());
+        notify_webhooks(graph, &context, WebhookEvent::ExecutionStarted, serde_json::json!({
+            "idempotency_key": key,
+        }));
+
+        loop {
+            context.idempotency_key = Some(key.clone());
+            let attempt = retry::with_idempotency(key.clone(), ledger.clone(), self.run(graph, state, &mut context));
+            let result = scope.run(graph, attempt).await;
+            let error = match result {
+                Ok(()) => {
+                    if own_key {
+                        ledger.forget(&key);
+                    }
+                    notify_result(graph, &context, Ok(()));
+                    return Ok(context);
+                }
+                Err(error) => error,
+            };
+
+            let delay = if graph.config().dry_run { None } else { policy.next_delay(&error, context.attempt) };
+            let (Some(delay), Some(input)) = (delay, &input) else {
+                Self::compensate(graph, state, &mut context, &error).await;
+                let error = match &input {
+                    Some(input) => Self::dead_letter(graph, input, state, &context, error, None).await,
+                    None => error,
+                };
+                notify_result(graph, &context, Err(&error));
+                return Err(error);
+            };
+            tracing::warn!(
+                execution_id = %context.execution_id,
+                attempt = context.attempt,
+                delay_ms = delay.as_millis() as u64,
+                error = %error,
+                "Execution failed, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            let mut next = input.clone();
+            // What the failed attempt did is still to be undone if the execution fails for good
+            if graph.saga().is_some() {
+                if let Err(error) = saga::carry_over(state, &mut next) {
+                    tracing::warn!(execution_id = %context.execution_id, error = %error, "Failed to carry compensations over to the next attempt");
+                }
+            }
+            *state = next;
+            context = ExecutionContext {
+                execution_id: context.execution_id,
+                attempt: context.attempt + 1,
+                ..ExecutionContext::new()
+            };
+        }
+    }
+
+    /// Run a graph from its entry point in an existing context
+    async fn run(&mut self, graph: &Graph<S>, state: &mut S, context: &mut ExecutionContext) -> GraphResult<()> {
+        // Validate the graph
+        if graph.plan().is_none() {
+            graph.validate()?;
+        }
+
         // Get entry point
         let entry_point = graph.entry_point()
             .ok_or_else(|| GraphError::graph_structure("No entry point defined".to_string()))?
@@ -53,9 +360,26 @@ where
         }
 
         // Start execution from entry point
-        let start_time = std::time::Instant::now();
-        let result = self.execute_from_node(graph, state, &mut context, entry_point).await;
-        let duration_ms = start_time.elapsed().as_millis() as u64;
+        let start_time = determinism::now();
+        if let Some(controls) = graph.controls() {
+            controls.register(context, &graph.metadata().name);
+        }
+        let result = if graph.config().dry_run {
+            let log = DryRunLog::new();
+            let result = dry_run::with_dry_run(
+                log.clone(),
+                self.execute_from_node(graph, state, context, entry_point),
+            ).await;
+            context.simulated_effects = log.take();
+            result
+        } else {
+            self.execute_from_node(graph, state, context, entry_point).await
+        };
+        let duration_ms = (determinism::now() - start_time).num_milliseconds().max(0) as u64;
+        release_lease(graph, context, result.as_ref().map(|_| ())).await;
+        if let Some(controls) = graph.controls() {
+            controls.finish(context, &graph.metadata().name, state, result.as_ref().map(|_| ()));
+        }
 
         #[cfg(feature = "streaming")]
         if let Some(ref emitter) = graph.event_emitter {
@@ -67,6 +391,178 @@ where
             )?;
         }
 
+        result
+    }
+
+    /// Undo the work of an execution that failed for good with the graph's saga
+    ///
+    /// Suspensions, cancellations and dry runs are not compensated. Each
+    /// compensation is recorded in `context` and emitted as a `compensation`
+    /// event.
+    async fn compensate(graph: &Graph<S>, state: &mut S, context: &mut ExecutionContext, error: &GraphError) {
+        let Some(saga) = graph.saga() else {
+            return;
+        };
+        if error.is_suspended() || error.is_cancelled() || graph.config().dry_run {
+            return;
+        }
+        let records = saga.compensate(state, error).await;
+        if records.is_empty() {
+            return;
+        }
+        tracing::warn!(
+            execution_id = %context.execution_id,
+            compensations = records.len(),
+            failed = records.iter().filter(|record| record.status == saga::CompensationStatus::Failed).count(),
+            error = %error,
+            "Execution failed and was compensated"
+        );
+
+        #[cfg(feature = "streaming")]
+        if let Some(ref emitter) = graph.event_emitter {
+            for record in &records {
+                let data = serde_json::to_value(record).unwrap_or(serde_json::Value::Null);
+                if let Err(emit_error) = emitter.emit_custom(context.execution_id, "compensation".to_string(), data) {
+                    tracing::debug!(error = %emit_error, "Failed to emit compensation event");
+                }
+            }
+        }
+
+        context.compensations.extend(records);
+    }
+
+    /// Keep a failed execution in the graph's dead-letter queue and hand back its error
+    ///
+    /// `previous` is the letter being retried, which is updated instead of
+    /// adding another. Suspensions, dry runs and new executions that failed
+    /// before their first node are not dead-lettered.
+    async fn dead_letter(
+        graph: &Graph<S>,
+        input: &S,
+        state: &S,
+        context: &ExecutionContext,
+        error: GraphError,
+        previous: Option<DeadLetter>,
+    ) -> GraphError {
+        let Some(queue) = graph.dead_letter_queue() else {
+            return error;
+        };
+        if error.is_suspended() || error.is_cancelled() || graph.config().dry_run || (previous.is_none() && context.current_step == 0) {
+            return error;
+        }
+
+        let to_json = |state: &S| serde_json::to_value(state).unwrap_or(serde_json::Value::Null);
+        let letter = DeadLetter {
+            id: previous.as_ref().map_or_else(|| context.execution_id.to_string(), |letter| letter.id.clone()),
+            graph: graph.metadata().name.clone(),
+            failed_at: determinism::now(),
+            node_id: context.current_node.clone(),
+            step: context.current_step,
+            execution_path: context.execution_path.clone(),
+            errors: DeadLetter::error_chain(&error),
+            input_state: to_json(input),
+            failed_state: to_json(state),
+            attempts: previous.map_or(context.attempt, |letter| letter.attempts + 1),
+            status: DeadLetterStatus::Pending,
+        };
+        match queue.put(&letter).await {
+            Ok(()) => tracing::warn!(
+                execution_id = %context.execution_id,
+                dead_letter = %letter.id,
+                attempts = letter.attempts,
+                error = %error,
+                "Execution failed and was dead-lettered"
+            ),
+            Err(put_error) => tracing::error!(
+                execution_id = %context.execution_id,
+                error = %put_error,
+                "Failed to dead-letter execution"
+            ),
+        }
+        error
+    }
+
+    /// Run a dead-lettered execution again from its input state
+    ///
+    /// The letter is removed when the run succeeds. Otherwise it stays in the
+    /// queue with the new error and one more attempt. The run uses the letter's
+    /// ID as its idempotency key, so with a graph-wide idempotency ledger the
+    /// side effects of the failed run are not repeated.
+    pub async fn retry_dead_letter(&mut self, graph: &Graph<S>, id: &str) -> GraphResult<(S, ExecutionContext)> {
+        let queue = graph.dead_letter_queue()
+            .cloned()
+            .ok_or_else(|| GraphError::ConfigurationError("Graph has no dead-letter queue".to_string()))?;
+        let letter = queue.get(id).await?
+            .ok_or_else(|| GraphError::validation_error(format!("No dead letter '{}'", id)))?;
+        let input: S = serde_json::from_value(letter.input_state.clone())?;
+
+        let mut state = input.clone();
+        let mut context = determinism::sync_scope(&self.determinism, ExecutionContext::new);
+        context.idempotency_key = Some(letter.id.clone());
+        let scope = self.scope_for(graph, &state, context.execution_id, false)?;
+        let ledger = graph.idempotency_ledger().cloned().unwrap_or_default();
+        let run = retry::with_idempotency(letter.id.clone(), ledger.clone(), self.run(graph, &mut state, &mut context));
+        let result = scope.run(graph, run).await;
+        match result {
+            Ok(()) => {
+                queue.remove(id).await?;
+                ledger.forget(&letter.id);
+                Ok((state, context))
+            }
+            Err(error) => {
+                Self::compensate(graph, &mut state, &mut context, &error).await;
+                Err(Self::dead_letter(graph, &input, &state, &context, error, Some(letter)).await)
+            }
+        }
+    }
+
+    /// Retry every letter of this graph marked for retry, e.g. with `agentgraph dlq retry`
+    ///
+    /// Returns the IDs of the letters whose retry succeeded.
+    pub async fn redrive(&mut self, graph: &Graph<S>) -> GraphResult<Vec<String>> {
+        let queue = graph.dead_letter_queue()
+            .cloned()
+            .ok_or_else(|| GraphError::ConfigurationError("Graph has no dead-letter queue".to_string()))?;
+        let mut succeeded = Vec::new();
+        for letter in queue.list().await? {
+            if letter.graph != graph.metadata().name || letter.status != DeadLetterStatus::RetryRequested {
+                continue;
+            }
+            if self.retry_dead_letter(graph, &letter.id).await.is_ok() {
+                succeeded.push(letter.id);
+            }
+        }
+        Ok(succeeded)
+    }
+
+    /// Run a graph from `node_id` in a new execution, e.g. to retry a failed node
+    pub(crate) async fn execute_from_node_in(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        mut context: ExecutionContext,
+        node_id: NodeId,
+    ) -> GraphResult<ExecutionContext> {
+        if graph.plan().is_none() {
+            graph.validate()?;
+        }
+
+        if let Some(controls) = graph.controls() {
+            controls.register(&context, &graph.metadata().name);
+        }
+        notify_webhooks(graph, &context, WebhookEvent::ExecutionStarted, serde_json::json!({
+            "node_id": node_id,
+        }));
+        let scope = self.scope_for(graph, state, context.execution_id, false)?;
+        let result = scope.run(graph, self.execute_from_node(graph, state, &mut context, node_id)).await;
+        if let Err(error) = &result {
+            Self::compensate(graph, state, &mut context, error).await;
+        }
+        release_lease(graph, &context, result.as_ref().map(|_| ())).await;
+        if let Some(controls) = graph.controls() {
+            controls.finish(&context, &graph.metadata().name, state, result.as_ref().map(|_| ()));
+        }
+        notify_result(graph, &context, result.as_ref().map(|_| ()));
         result?;
         Ok(context)
     }
@@ -82,8 +578,22 @@ where
         let mut current_node = start_node;
         let mut visited_nodes = HashSet::new();
         let config = graph.config();
+        // Set when the current node already ran speculatively and its result was kept
+        let mut speculated = false;
+        let mut watch = graph.watchdog().map(|watchdog| (watchdog, watchdog.tracker()));
 
         loop {
+            // Wait here while paused, stop here if cancelled and suspend here if draining
+            if let Some(controls) = graph.controls() {
+                if let Err(error) = controls.checkpoint(context, &current_node).await {
+                    return Err(self.checkpoint_drain(graph, state, context, error).await?);
+                }
+            }
+
+            // Send output fields the previous step changed to clients streaming them
+            #[cfg(feature = "streaming")]
+            crate::streaming::output::observe(context.current_node.as_ref(), state);
+
             // Check execution limits
             if let Some(max_steps) = config.max_steps {
                 if context.current_step >= max_steps {
@@ -114,8 +624,41 @@ where
             context.add_to_path(current_node.clone());
             context.increment_step();
 
-            // Execute the current node
-            self.execute_node(graph, state, context, &current_node).await?;
+            // Execute the current node, checkpointing if it suspends
+            if std::mem::take(&mut speculated) {
+                tracing::debug!(node_id = %current_node, "Keeping speculative result");
+            } else if let Err(error) = self.execute_node_once(graph, state, context, &current_node).await {
+                if error.is_suspended() {
+                    return Err(self.checkpoint_suspension(graph, state, context, error).await?);
+                }
+                if config.stop_on_error {
+                    return Err(error);
+                }
+                record_failure(graph, context, &current_node, &error);
+                break;
+            }
+            context.record_outcome(current_node.clone(), NodeOutcomeStatus::Succeeded);
+            if graph.saga().is_some() {
+                saga::stamp(state, &current_node, context.current_step)?;
+            }
+
+            #[cfg(feature = "checkpointing")]
+            self.checkpoint_step(graph, state, context, &current_node).await?;
+
+            // Perform side effects the node queued, now that its step is committed
+            if let Some(outbox) = graph.outbox() {
+                let ledger = retry::current_ledger().or_else(|| graph.idempotency_ledger().cloned());
+                outbox.dispatch(state, &current_node, ledger.as_ref()).await?;
+            }
+
+            // Break out of loops that make no progress
+            if let Some((watchdog, tracker)) = watch.as_mut() {
+                if let Some(found) = tracker.observe(watchdog, &context.execution_path, &current_node, state) {
+                    tracker.reset(&context.execution_path);
+                    current_node = self.trip_watchdog(graph, state, context, &current_node, watchdog, found).await?;
+                    continue;
+                }
+            }
 
             // Check if we've reached a finish point AFTER executing the node
             if graph.finish_points().contains(&current_node) {
@@ -127,7 +670,19 @@ where
                 break;
             }
 
+            // Compiled graphs follow plain edges without resolving them
+            if let Some(next_node) = graph.plan().and_then(|plan| plan.next(&current_node)) {
+                current_node = next_node.clone();
+                continue;
+            }
+
             // Find next node(s)
+            self.throttle_edge(graph, context, &current_node).await?;
+            if let Some((next_node, hit)) = self.speculate(graph, state, context, &current_node).await? {
+                current_node = next_node;
+                speculated = hit;
+                continue;
+            }
             let next_nodes = self.find_next_nodes(graph, state, &current_node).await?;
 
             match next_nodes {
@@ -135,15 +690,7 @@ where
                     current_node = next_node;
                 }
                 RouteResolution::Multiple(nodes) => {
-                    // Execute nodes in parallel
-                    if config.enable_parallel {
-                        self.execute_parallel_nodes(graph, state, context, nodes).await?;
-                    } else {
-                        // Execute sequentially if parallel is disabled
-                        for node in nodes {
-                            self.execute_node(graph, state, context, &node).await?;
-                        }
-                    }
+                    self.execute_branches(graph, state, context, nodes).await?;
                     // For parallel execution, we need to determine the next step
                     // This is a simplified approach - in practice, you might want
                     // more sophisticated merging logic
@@ -162,299 +709,2923 @@ where
         Ok(())
     }
 
-    /// Execute a single node
-    async fn execute_node(
-        &self,
+    /// Resume an execution that suspended at `node_id`
+    ///
+    /// `output` is the result of the operation the node was waiting on. The
+    /// node finishes via [`Node::resume`](crate::node::Node::resume) and
+    /// execution continues along its outgoing edges. Nothing here names the
+    /// suspended execution, so the rest of it runs under a new execution ID;
+    /// [`Self::resume_from_checkpoint`] and [`Self::resume_execution`] keep it.
+    pub async fn resume(
+        &mut self,
         graph: &Graph<S>,
         state: &mut S,
-        context: &ExecutionContext,
-        node_id: &NodeId,
-    ) -> GraphResult<()> {
+        node_id: NodeId,
+        output: serde_json::Value,
+    ) -> GraphResult<ExecutionContext> {
+        let mut context = determinism::sync_scope(&self.determinism, ExecutionContext::new);
+        context.increment_step();
+        self.resume_execution(graph, state, context, node_id, output).await
+    }
+
+    /// Resume an execution that suspended at `node_id` in the context it suspended in
+    ///
+    /// `context` carries the execution's ID and the step the node suspended
+    /// at, so traces, audit events and leases of the resumed run line up with
+    /// those from before the suspension.
+    pub async fn resume_execution(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        mut context: ExecutionContext,
+        node_id: NodeId,
+        output: serde_json::Value,
+    ) -> GraphResult<ExecutionContext> {
+        if graph.plan().is_none() {
+            graph.validate()?;
+        }
+
         let node = graph.node_registry()
-            .get(node_id)
+            .get(&node_id)
             .ok_or_else(|| GraphError::node_error(
                 node_id.clone(),
                 "Node not found in registry".to_string(),
                 None,
             ))?;
 
-        let mut node_context = NodeExecutionContext::new(node_id.clone());
-        
-        #[cfg(feature = "streaming")]
-        if let Some(ref emitter) = graph.event_emitter {
-            emitter.emit_node_started(
-                context.execution_id,
-                node_id.clone(),
-                node_context.clone(),
-            )?;
-        }
+        context.current_node = Some(node_id.clone());
+        context.add_to_path(node_id.clone());
 
         tracing::info!(
             node_id = %node_id,
             execution_id = %context.execution_id,
-            step = context.current_step,
-            "Executing node"
+            "Resuming suspended node"
         );
-
-        // Execute with timeout if configured
-        let result = if let Some(timeout_seconds) = graph.config().max_execution_time_seconds {
-            let timeout_duration = Duration::from_secs(timeout_seconds);
-            match timeout(timeout_duration, node.invoke(state)).await {
-                Ok(result) => result,
-                Err(_) => {
-                    let error = GraphError::timeout(timeout_seconds);
-                    node_context.mark_failure(error.to_string());
-                    return Err(error);
-                }
-            }
-        } else {
-            node.invoke(state).await
-        };
-
-        // Handle result
-        match result {
-            Ok(()) => {
-                node_context.mark_success();
-                
-                #[cfg(feature = "streaming")]
-                if let Some(ref emitter) = graph.event_emitter {
-                    emitter.emit_node_completed(
-                        context.execution_id,
-                        node_id.clone(),
-                        node_context.duration_ms.unwrap_or(0),
-                        true,
-                        None,
-                    )?;
-                    
-                    emitter.emit_state_updated(
-                        context.execution_id,
-                        node_id.clone(),
-                        None, // TODO: Add snapshot ID if checkpointing is enabled
-                    )?;
-                }
-
-                tracing::info!(
-                    node_id = %node_id,
-                    duration_ms = node_context.duration_ms.unwrap_or(0),
-                    "Node executed successfully"
-                );
-            }
-            Err(error) => {
-                node_context.mark_failure(error.to_string());
-                
-                #[cfg(feature = "streaming")]
-                if let Some(ref emitter) = graph.event_emitter {
-                    emitter.emit_node_completed(
-                        context.execution_id,
-                        node_id.clone(),
-                        node_context.duration_ms.unwrap_or(0),
-                        false,
-                        Some(error.to_string()),
-                    )?;
-                    
-                    emitter.emit_error(
-                        context.execution_id,
-                        Some(node_id.clone()),
-                        error.to_string(),
-                        error.category().to_string(),
-                    )?;
-                }
-
-                tracing::error!(
-                    node_id = %node_id,
-                    error = %error,
-                    "Node execution failed"
-                );
-
-                if graph.config().stop_on_error {
-                    return Err(error);
-                }
-            }
+        self.lifecycle.ensure_setup(graph.id(), &node_id, node).await?;
+        let scope = self.scope_for(graph, state, context.execution_id, false)?;
+        let result = scope.run(graph, self.resume_node(graph, state, &mut context, node, node_id, output)).await;
+        if let Err(error) = &result {
+            Self::compensate(graph, state, &mut context, error).await;
         }
-
-        Ok(())
+        release_lease(graph, &context, result.as_ref().map(|_| ())).await;
+        notify_result(graph, &context, result.as_ref().map(|_| ()));
+        result?;
+        Ok(context)
     }
 
-    /// Execute multiple nodes in parallel
-    async fn execute_parallel_nodes(
-        &self,
+    /// Finish a suspended node and continue after it
+    async fn resume_node(
+        &mut self,
         graph: &Graph<S>,
         state: &mut S,
+        context: &mut ExecutionContext,
+        node: &SharedNode<S>,
+        node_id: NodeId,
+        output: serde_json::Value,
+    ) -> GraphResult<()> {
+        if graph.config().dry_run {
+            let log = DryRunLog::new();
+            let result = dry_run::with_dry_run(log.clone(), async {
+                dry_run::in_node(&node_id, context.current_step, node.resume(state, output)).await?;
+                self.continue_after(graph, state, context, &node_id).await
+            }).await;
+            context.simulated_effects = log.take();
+            return result;
+        }
+        node.resume(state, output).await?;
+
+        self.continue_after(graph, state, context, &node_id).await
+    }
+
+    /// Continue execution along the outgoing edges of a node that has completed
+    async fn continue_after(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        context: &mut ExecutionContext,
+        node_id: &NodeId,
+    ) -> GraphResult<()> {
+        if graph.finish_points().contains(node_id) {
+            return Ok(());
+        }
+
+        self.throttle_edge(graph, context, node_id).await?;
+        match self.find_next_nodes(graph, state, node_id).await? {
+            RouteResolution::Single(next_node) => {
+                self.execute_from_node(graph, state, context, next_node).await?;
+            }
+            RouteResolution::Multiple(nodes) => {
+                self.execute_branches(graph, state, context, nodes).await?;
+            }
+            RouteResolution::None => {}
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "checkpointing")]
+    /// Resume a suspended execution from the checkpoint saved when it suspended
+    pub async fn resume_from_checkpoint(
+        &mut self,
+        graph: &Graph<S>,
+        checkpoint_id: uuid::Uuid,
+        output: serde_json::Value,
+    ) -> GraphResult<(S, ExecutionContext)> {
+        let checkpointer = graph.checkpointer.as_ref()
+            .ok_or_else(|| GraphError::CheckpointError("Graph has no checkpointer".to_string()))?;
+        let snapshot = checkpointer.load(checkpoint_id).await?;
+        let node_id = snapshot.metadata.current_node.clone()
+            .ok_or_else(|| GraphError::CheckpointError(format!(
+                "Checkpoint {} does not record a suspended node",
+                checkpoint_id
+            )))?;
+
+        // Carry on as the execution that suspended, at the step it suspended at
+        let mut context = determinism::sync_scope(&self.determinism, ExecutionContext::new);
+        if let Some(execution_id) = snapshot.get_custom_metadata::<uuid::Uuid>("execution_id") {
+            context.execution_id = execution_id;
+        }
+        context.lineage = snapshot.get_custom_metadata::<ExecutionLineage>("lineage");
+        context.current_step = snapshot.metadata.step.max(1);
+        context.checkpoint_ids.push(checkpoint_id);
+
+        let mut state = snapshot.state;
+        let context = self.resume_execution(graph, &mut state, context, node_id, output).await?;
+        Ok((state, context))
+    }
+
+    #[cfg(feature = "checkpointing")]
+    /// Fork a checkpointed execution into branches with their own state edits
+    ///
+    /// Each branch starts from a copy of the checkpointed state, applies its
+    /// edits and continues where the parent execution left off: after the
+    /// checkpointed node, or at it if the execution was suspended there.
+    /// Branches run one after another and record the parent execution and
+    /// checkpoint in their context's lineage, so their traces can be compared
+    /// side by side.
+    pub async fn fork(
+        &mut self,
+        graph: &Graph<S>,
+        checkpoint_id: uuid::Uuid,
+        branches: Vec<ForkBranch<S>>,
+    ) -> GraphResult<Vec<BranchOutcome<S>>> {
+        graph.validate()?;
+        let checkpointer = graph.checkpointer.as_ref()
+            .ok_or_else(|| GraphError::CheckpointError("Graph has no checkpointer".to_string()))?;
+        let snapshot = checkpointer.load(checkpoint_id).await?;
+        let node_id = snapshot.metadata.current_node.clone()
+            .ok_or_else(|| GraphError::CheckpointError(format!(
+                "Checkpoint {} does not record a node to fork from",
+                checkpoint_id
+            )))?;
+        let parent_execution_id = snapshot.get_custom_metadata::<uuid::Uuid>("execution_id")
+            .ok_or_else(|| GraphError::CheckpointError(format!(
+                "Checkpoint {} does not record its execution",
+                checkpoint_id
+            )))?;
+        let suspended = snapshot.metadata.tags.iter().any(|tag| tag == "suspended");
+
+        let mut outcomes = Vec::with_capacity(branches.len());
+        for branch in branches {
+            let mut state = snapshot.state.clone();
+            let lineage = ExecutionLineage {
+                parent_execution_id,
+                checkpoint_id,
+                branch: branch.name().to_string(),
+            };
+            let mut context = determinism::sync_scope(&self.determinism, || ExecutionContext::forked(lineage));
+            context.current_step = snapshot.metadata.step;
+
+            tracing::info!(
+                branch = %branch.name(),
+                checkpoint_id = %checkpoint_id,
+                execution_id = %context.execution_id,
+                "Forking execution"
+            );
+            let result = match branch.apply(&mut state).and_then(|()| self.scope_for(graph, &state, context.execution_id, false)) {
+                Ok(scope) if suspended => {
+                    scope.run(graph, self.execute_from_node(graph, &mut state, &mut context, node_id.clone())).await
+                }
+                Ok(scope) => scope.run(graph, self.continue_after(graph, &mut state, &mut context, &node_id)).await,
+                Err(e) => Err(e),
+            };
+
+            outcomes.push(BranchOutcome {
+                branch: branch.name().to_string(),
+                state,
+                context,
+                error: result.err(),
+            });
+        }
+        Ok(outcomes)
+    }
+
+    #[cfg(feature = "checkpointing")]
+    /// Checkpoint after a node when the graph checkpoints every `checkpoint_interval` steps
+    async fn checkpoint_step(
+        &self,
+        graph: &Graph<S>,
+        state: &S,
+        context: &mut ExecutionContext,
+        node_id: &NodeId,
+    ) -> GraphResult<()> {
+        let config = graph.config();
+        let Some(checkpointer) = graph.checkpointer.as_ref() else {
+            return Ok(());
+        };
+        let interval = config.checkpoint_interval.unwrap_or(1).max(1);
+        if !config.enable_checkpointing || context.current_step % interval != 0 {
+            return Ok(());
+        }
+        if config.dry_run {
+            dry_run::record(dry_run::SimulatedEffectKind::CheckpointSkipped);
+            return Ok(());
+        }
+
+        let mut custom: std::collections::HashMap<String, serde_json::Value> =
+            [("execution_id".to_string(), serde_json::json!(context.execution_id))].into_iter().collect();
+        if let Some(lineage) = &context.lineage {
+            custom.insert("lineage".to_string(), serde_json::json!(lineage));
+        }
+        let metadata = crate::state::SnapshotMetadata {
+            current_node: Some(node_id.clone()),
+            step: context.current_step,
+            tags: vec!["step".to_string()],
+            custom,
+        };
+        let snapshot = crate::state::StateSnapshot::with_metadata(state.clone(), metadata);
+        checkpointer.save(&snapshot).await?;
+        context.checkpoint_ids.push(snapshot.id);
+
+        tracing::debug!(
+            node_id = %node_id,
+            checkpoint_id = %snapshot.id,
+            step = context.current_step,
+            "Checkpointed execution"
+        );
+        Ok(())
+    }
+
+    /// Execute a node unless another run of this execution already completed it at this step
+    ///
+    /// Without leases this is [`Self::execute_node`]. With them, the node runs
+    /// under the execution's lease, and its completion is recorded with the
+    /// state it left behind; a completion already recorded is taken instead.
+    async fn execute_node_once(
+        &self,
+        graph: &Graph<S>,
+        state: &mut S,
+        context: &mut ExecutionContext,
+        node_id: &NodeId,
+    ) -> GraphResult<()> {
+        let Some(leases) = graph.leases() else {
+            return self.execute_node(graph, state, context, node_id).await;
+        };
+        let execution_id = context.execution_id;
+        let step = context.current_step;
+        if let Some(completion) = leases.completed(execution_id, step, node_id).await? {
+            tracing::info!(
+                node_id = %node_id,
+                execution_id = %execution_id,
+                step,
+                completed_by = %completion.owner,
+                "Node already completed; taking its recorded state"
+            );
+            *state = serde_json::from_value(completion.state)?;
+            return Ok(());
+        }
+
+        leases.hold(execution_id, self.execute_node(graph, state, context, node_id)).await?;
+        if let Some(kept) = leases.record(execution_id, step, node_id, serde_json::to_value(&*state)?).await? {
+            // Another instance got there first; carry on from what it recorded
+            *state = serde_json::from_value(kept)?;
+        }
+        Ok(())
+    }
+
+    /// Act on a loop the watchdog found after `node_id` ran
+    ///
+    /// Returns the fallback node to continue at; failing and escalating are
+    /// returned as errors.
+    async fn trip_watchdog(
+        &self,
+        graph: &Graph<S>,
+        state: &S,
+        context: &mut ExecutionContext,
+        node_id: &NodeId,
+        watchdog: &watchdog::Watchdog,
+        found: watchdog::LoopKind,
+    ) -> GraphResult<NodeId> {
+        tracing::warn!(
+            execution_id = %context.execution_id,
+            node_id = %node_id,
+            step = context.current_step,
+            action = ?watchdog.action,
+            "Watchdog tripped: {}",
+            found
+        );
+
+        #[cfg(feature = "streaming")]
+        if let Some(ref emitter) = graph.event_emitter {
+            emitter.emit_custom(
+                context.execution_id,
+                "watchdog_tripped".to_string(),
+                serde_json::json!({
+                    "node_id": node_id,
+                    "step": context.current_step,
+                    "loop": found,
+                    "action": watchdog.action,
+                }),
+            )?;
+        }
+
+        let message = format!("Watchdog stopped a loop: {}", found);
+        context.watchdog_trips.push(watchdog::WatchdogTrip {
+            kind: found,
+            step: context.current_step,
+            action: watchdog.action.clone(),
+        });
+        match &watchdog.action {
+            WatchdogAction::Fail => Err(GraphError::execution_error(message)),
+            WatchdogAction::Fallback { node_id: fallback } => {
+                if graph.node_registry().get(fallback).is_none() {
+                    return Err(GraphError::ConfigurationError(format!(
+                        "Watchdog fallback node '{}' does not exist",
+                        fallback
+                    )));
+                }
+                Ok(fallback.clone())
+            }
+            WatchdogAction::Escalate => {
+                let suspended = GraphError::suspended(node_id.as_str(), watchdog::WATCHDOG_OPERATION);
+                Err(self.checkpoint_suspension(graph, state, context, suspended).await?)
+            }
+        }
+    }
+
+    /// Save a checkpoint for a suspended node and attach its ID to the error
+    async fn checkpoint_suspension(
+        &self,
+        graph: &Graph<S>,
+        state: &S,
         context: &ExecutionContext,
-        node_ids: Vec<NodeId>,
+        error: GraphError,
+    ) -> GraphResult<GraphError> {
+        let GraphError::Suspended { node_id, operation_id, .. } = error else {
+            return Ok(error);
+        };
+
+        #[cfg(feature = "checkpointing")]
+        if let Some(checkpointer) = &graph.checkpointer {
+            let mut custom: std::collections::HashMap<String, serde_json::Value> = [
+                ("operation_id".to_string(), serde_json::json!(operation_id)),
+                ("execution_id".to_string(), serde_json::json!(context.execution_id)),
+            ].into_iter().collect();
+            if let Some(lineage) = &context.lineage {
+                custom.insert("lineage".to_string(), serde_json::json!(lineage));
+            }
+            let metadata = crate::state::SnapshotMetadata {
+                current_node: Some(node_id.clone()),
+                step: context.current_step,
+                tags: vec!["suspended".to_string()],
+                custom,
+            };
+            let snapshot = crate::state::StateSnapshot::with_metadata(state.clone(), metadata);
+            checkpointer.save(&snapshot).await?;
+
+            tracing::info!(
+                node_id = %node_id,
+                operation_id = %operation_id,
+                checkpoint_id = %snapshot.id,
+                "Execution suspended and checkpointed"
+            );
+            return Ok(GraphError::Suspended {
+                node_id,
+                operation_id,
+                checkpoint_id: Some(snapshot.id.to_string()),
+            });
+        }
+
+        let _ = (graph, state);
+        tracing::info!(
+            node_id = %node_id,
+            operation_id = %operation_id,
+            execution_id = %context.execution_id,
+            "Execution suspended without checkpointer"
+        );
+        Ok(GraphError::Suspended { node_id, operation_id, checkpoint_id: None })
+    }
+
+    /// Save a checkpoint for an execution drained before `node_id` and attach its ID to the error
+    ///
+    /// The checkpoint names the instance that drained the execution and when
+    /// its lease on it runs out, for [`ExecutionControls::resume_drained`](crate::graph::control::ExecutionControls::resume_drained).
+    async fn checkpoint_drain(
+        &self,
+        graph: &Graph<S>,
+        state: &S,
+        context: &ExecutionContext,
+        error: GraphError,
+    ) -> GraphResult<GraphError> {
+        let (GraphError::Suspended { node_id, operation_id, .. }, Some(controls)) = (&error, graph.controls()) else {
+            return Ok(error);
+        };
+        if operation_id != DRAIN_OPERATION {
+            return Ok(error);
+        }
+
+        #[cfg(feature = "checkpointing")]
+        if let Some(checkpointer) = &graph.checkpointer {
+            let lease = chrono::Duration::from_std(controls.drain_lease()).unwrap_or_else(|_| chrono::Duration::zero());
+            let metadata = crate::state::SnapshotMetadata {
+                current_node: Some(node_id.clone()),
+                step: context.current_step,
+                tags: vec![crate::graph::control::DRAINED_TAG.to_string()],
+                custom: [
+                    ("execution_id".to_string(), serde_json::json!(context.execution_id)),
+                    ("graph".to_string(), serde_json::json!(graph.metadata().name)),
+                    ("owner".to_string(), serde_json::json!(controls.instance_id())),
+                    ("lease_expires_at".to_string(), serde_json::json!((determinism::now() + lease).to_rfc3339())),
+                ].into_iter().collect(),
+            };
+            let snapshot = crate::state::StateSnapshot::with_metadata(state.clone(), metadata);
+            checkpointer.save(&snapshot).await?;
+
+            tracing::info!(
+                node_id = %node_id,
+                execution_id = %context.execution_id,
+                checkpoint_id = %snapshot.id,
+                "Execution drained and checkpointed"
+            );
+            return Ok(GraphError::Suspended {
+                node_id: node_id.clone(),
+                operation_id: operation_id.clone(),
+                checkpoint_id: Some(snapshot.id.to_string()),
+            });
+        }
+
+        let _ = (state, controls);
+        tracing::warn!(
+            node_id = %node_id,
+            execution_id = %context.execution_id,
+            "Execution drained without checkpointer; it cannot be resumed"
+        );
+        Ok(error)
+    }
+
+    /// Execute the targets of a fan-out edge
+    async fn execute_branches(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        context: &mut ExecutionContext,
+        nodes: Vec<NodeId>,
+    ) -> GraphResult<()> {
+        let parallel = graph.config().enable_parallel;
+        let (results, continue_at) = match self.execute_fan_out(graph, state, context, nodes, parallel).await {
+            Err(error) if error.is_suspended() => {
+                return Err(self.checkpoint_suspension(graph, state, context, error).await?);
+            }
+            results => results?,
+        };
+
+        // Failed branches leave the others be
+        for (node_id, error) in results {
+            match error {
+                Some(error) => record_failure(graph, context, &node_id, &error),
+                None => context.record_outcome(node_id, NodeOutcomeStatus::Succeeded),
+            }
+        }
+
+        // Joins and loops after the branches run one node at a time, as outside a fan-out
+        for node_id in continue_at {
+            Box::pin(self.execute_from_node(graph, state, context, node_id)).await?;
+        }
+        Ok(())
+    }
+
+    /// Execute a single node
+    async fn execute_node(
+        &self,
+        graph: &Graph<S>,
+        state: &mut S,
+        context: &mut ExecutionContext,
+        node_id: &NodeId,
     ) -> GraphResult<()> {
+        let node = graph.node_registry()
+            .get(node_id)
+            .ok_or_else(|| GraphError::node_error(
+                node_id.clone(),
+                "Node not found in registry".to_string(),
+                None,
+            ))?;
+
+        self.lifecycle.ensure_setup(graph.id(), node_id, node).await?;
+        let mut node_context = NodeExecutionContext::new(node_id.clone());
+        
         #[cfg(feature = "streaming")]
         if let Some(ref emitter) = graph.event_emitter {
-            emitter.emit(ExecutionEvent::ParallelStarted {
-                execution_id: context.execution_id,
-                node_ids: node_ids.clone(),
-                timestamp: chrono::Utc::now(),
-            })?;
+            emitter.emit_node_started(
+                context.execution_id,
+                node_id.clone(),
+                node_context.clone(),
+            )?;
+        }
+
+        tracing::info!(
+            node_id = %node_id,
+            execution_id = %context.execution_id,
+            step = context.current_step,
+            "Executing node"
+        );
+
+        // Execute with the node's own timeout, or the graph's
+        let reports = NodeReports::default();
+        let result = if let Some(timeout_duration) = node_timeout(graph, node_id) {
+            match timeout(timeout_duration, reports.scope(self.invoke_node(graph, context, node_id, node, state))).await {
+                Ok(result) => result,
+                Err(_) => {
+                    let error = GraphError::timeout(timeout_duration.as_secs_f64().ceil() as u64);
+                    node_context.mark_failure(error.to_string());
+                    record_node_report(context, node_id, reports.take());
+                    return Err(error);
+                }
+            }
+        } else {
+            reports.scope(self.invoke_node(graph, context, node_id, node, state)).await
+        };
+        // Recorded whether or not the graph streams it
+        #[cfg_attr(not(feature = "streaming"), allow(unused_variables))]
+        let report = record_node_report(context, node_id, reports.take());
+
+        // Handle result
+        match result {
+            Ok(()) => {
+                node_context.mark_success();
+                
+                #[cfg(feature = "streaming")]
+                if let Some(ref emitter) = graph.event_emitter {
+                    emitter.emit_node_completed(
+                        context.execution_id,
+                        node_id.clone(),
+                        node_context.duration_ms.unwrap_or(0),
+                        true,
+                        None,
+                        report.llm_usage,
+                        report.candidates,
+                    )?;
+                    
+                    emitter.emit_state_updated(
+                        context.execution_id,
+                        node_id.clone(),
+                        None, // TODO: Add snapshot ID if checkpointing is enabled
+                    )?;
+                }
+
+                tracing::info!(
+                    node_id = %node_id,
+                    duration_ms = node_context.duration_ms.unwrap_or(0),
+                    "Node executed successfully"
+                );
+            }
+            Err(error) if error.is_suspended() => {
+                tracing::info!(
+                    node_id = %node_id,
+                    error = %error,
+                    "Node suspended"
+                );
+                return Err(with_suspended_node(error, node_id));
+            }
+            Err(error) => {
+                node_context.mark_failure(error.to_string());
+                
+                #[cfg(feature = "streaming")]
+                if let Some(ref emitter) = graph.event_emitter {
+                    emitter.emit_node_completed(
+                        context.execution_id,
+                        node_id.clone(),
+                        node_context.duration_ms.unwrap_or(0),
+                        false,
+                        Some(error.to_string()),
+                        report.llm_usage,
+                        report.candidates,
+                    )?;
+                    
+                    emitter.emit_error(
+                        context.execution_id,
+                        Some(node_id.clone()),
+                        error.to_string(),
+                        error.category().to_string(),
+                    )?;
+                }
+
+                tracing::error!(
+                    node_id = %node_id,
+                    error = %error,
+                    "Node execution failed"
+                );
+
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Invoke a node, letting it emit its own events when the graph is streaming
+    async fn invoke_node(
+        &self,
+        graph: &Graph<S>,
+        context: &ExecutionContext,
+        node_id: &NodeId,
+        node: &SharedNode<S>,
+        state: &mut S,
+    ) -> GraphResult<()> {
+        NodeInvocation::new(graph, context, node_id, state, self.pools.clone())?.run(node, state).await
+    }
+
+    /// Execute the targets of a fan-out edge, and the nodes after them
+    ///
+    /// In parallel, each node runs on a task of its own and starts as soon as
+    /// the nodes it depends on have completed, rather than once every target
+    /// has. Otherwise the same nodes run one at a time, in plan order. The
+    /// fields a node changed are merged into the state in plan order, so the
+    /// result does not depend on which branch finished first, nor on whether
+    /// the branches ran in parallel. Nodes past a finish point, joins and
+    /// nodes on a cycle are left to the caller.
+    ///
+    /// Returns each node that ran with the error it failed with, if any, and
+    /// the joins and nodes on a cycle to continue at, unless the graph stops
+    /// on errors, in which case the first failure is returned.
+    ///
+    /// A node that suspends holds back the nodes after it, while the others
+    /// run to completion; the state then holds their changes and the first
+    /// suspension, in the order nodes finished, is returned to be resumed.
+    async fn execute_fan_out(
+        &mut self,
+        graph: &Graph<S>,
+        state: &mut S,
+        context: &mut ExecutionContext,
+        node_ids: Vec<NodeId>,
+        parallel: bool,
+    ) -> GraphResult<(Vec<(NodeId, Option<GraphError>)>, Vec<NodeId>)> {
+        #[cfg(feature = "streaming")]
+        if let (Some(emitter), true) = (&graph.event_emitter, parallel) {
+            emitter.emit(ExecutionEvent::ParallelStarted {
+                execution_id: context.execution_id,
+                node_ids: node_ids.clone(),
+                timestamp: determinism::now(),
+            })?;
+        }
+
+        let start_time = determinism::now();
+        let mut schedule = ParallelSchedule::new(graph, &node_ids);
+        let base = serde_json::to_value(&*state)?;
+        let mut inputs: HashMap<NodeId, serde_json::Value> = HashMap::new();
+        let mut changes: HashMap<NodeId, StateChanges> = HashMap::new();
+        let mut outcomes: HashMap<NodeId, Option<GraphError>> = HashMap::new();
+        let mut tasks = JoinSet::new();
+        let mut running: HashMap<tokio::task::Id, NodeId> = HashMap::new();
+        let mut ready = schedule.start();
+        let mut suspended: Option<GraphError> = None;
+
+        loop {
+            let (node_id, result, node_state) = if parallel {
+                // Start every node whose dependencies have all completed
+                for node_id in ready.drain(..) {
+                    let node = graph.node_registry()
+                        .get(&node_id)
+                        .ok_or_else(|| GraphError::node_error(
+                            node_id.clone(),
+                            "Node not found in registry".to_string(),
+                            None,
+                        ))?
+                        .clone();
+                    self.lifecycle.ensure_setup(graph.id(), &node_id, &node).await?;
+
+                    let input = schedule.merge(&base, &changes);
+                    let mut node_state: S = serde_json::from_value(input.clone())?;
+                    let invocation = NodeInvocation::new(graph, context, &node_id, &node_state, self.pools.clone());
+                    let limit = node_timeout(graph, &node_id);
+                    let leases = graph.leases().cloned();
+                    let (execution_id, step, task_node_id) = (context.execution_id, context.current_step, node_id.clone());
+                    let reports = NodeReports::default();
+                    let task = tasks.spawn(inherit_scopes(async move {
+                        // Run once across instances, as execute_node_once does for a single node
+                        let result = async {
+                            if let Some(leases) = &leases {
+                                if let Some(completion) = leases.completed(execution_id, step, &task_node_id).await? {
+                                    node_state = serde_json::from_value(completion.state)?;
+                                    return Ok(());
+                                }
+                            }
+                            let invoke = async {
+                                let invoke = reports.scope(invocation?.run(&node, &mut node_state));
+                                match limit {
+                                    Some(limit) => timeout(limit, invoke).await
+                                        .unwrap_or_else(|_| Err(GraphError::timeout(limit.as_secs_f64().ceil() as u64))),
+                                    None => invoke.await,
+                                }
+                            };
+                            match &leases {
+                                Some(leases) => leases.hold(execution_id, invoke).await?,
+                                None => invoke.await?,
+                            }
+                            if let Some(leases) = &leases {
+                                if let Some(kept) = leases.record(execution_id, step, &task_node_id, serde_json::to_value(&node_state)?).await? {
+                                    node_state = serde_json::from_value(kept)?;
+                                }
+                            }
+                            Ok::<(), GraphError>(())
+                        }
+                        .await;
+                        (result, node_state, reports.take())
+                    }));
+                    running.insert(task.id(), node_id.clone());
+                    inputs.insert(node_id, input);
+                }
+
+                let Some(joined) = tasks.join_next_with_id().await else {
+                    break;
+                };
+                let (node_id, result, node_state, report) = match joined {
+                    Ok((task_id, (result, node_state, report))) => (running.remove(&task_id).unwrap_or_default(), result, node_state, report),
+                    Err(error) => {
+                        let node_id = running.remove(&error.id()).unwrap_or_default();
+                        return Err(GraphError::node_error(node_id, format!("Node task failed: {}", error), None));
+                    }
+                };
+                record_node_report(context, &node_id, report);
+                (node_id, result, node_state)
+            } else {
+                // One node at a time, the first in plan order whose dependencies have completed
+                let Some(node_id) = schedule.take_first(&mut ready) else {
+                    break;
+                };
+                let input = schedule.merge(&base, &changes);
+                let mut node_state: S = serde_json::from_value(input.clone())?;
+                let result = self.execute_node_once(graph, &mut node_state, context, &node_id).await;
+                inputs.insert(node_id.clone(), input);
+                (node_id, result, node_state)
+            };
+
+            match result {
+                Ok(()) => {
+                    let routed = match graph.finish_points().contains(&node_id) {
+                        true => Vec::new(),
+                        false => match self.find_next_nodes(graph, &node_state, &node_id).await? {
+                            RouteResolution::Single(next_node) => vec![next_node],
+                            RouteResolution::Multiple(next_nodes) => next_nodes,
+                            RouteResolution::None => Vec::new(),
+                        },
+                    };
+                    let input = inputs.remove(&node_id).unwrap_or_default();
+                    changes.insert(node_id.clone(), StateChanges::between(&input, serde_json::to_value(&node_state)?));
+                    ready.extend(schedule.settle(&node_id, &routed, false));
+                    outcomes.insert(node_id, None);
+                }
+                Err(error) => {
+                    if error.is_suspended() {
+                        tracing::info!(node_id = %node_id, "Fan-out node suspended; finishing the nodes not waiting on it");
+                        suspended.get_or_insert(with_suspended_node(error, &node_id));
+                        continue;
+                    }
+                    if graph.config().stop_on_error {
+                        return Err(error);
+                    }
+                    tracing::error!(node_id = %node_id, error = %error, "Fan-out node failed");
+                    ready.extend(schedule.settle(&node_id, &[], true));
+                    outcomes.insert(node_id, Some(error));
+                }
+            }
+        }
+
+        if let Some(suspended) = suspended {
+            *state = serde_json::from_value(schedule.merge(&base, &changes))?;
+            return Err(suspended);
+        }
+        *state = serde_json::from_value(schedule.merge(&base, &changes))?;
+
+        // Report nodes in plan order, not in the order they happened to finish
+        let outcomes: Vec<(NodeId, Option<GraphError>)> = schedule.order
+            .iter()
+            .filter_map(|node_id| outcomes.remove(node_id).map(|error| (node_id.clone(), error)))
+            .collect();
+        let success_count = outcomes.iter().filter(|(_, error)| error.is_none()).count();
+        let duration_ms = (determinism::now() - start_time).num_milliseconds().max(0) as u64;
+
+        #[cfg(feature = "streaming")]
+        if let (Some(emitter), true) = (&graph.event_emitter, parallel) {
+            emitter.emit(ExecutionEvent::ParallelCompleted {
+                execution_id: context.execution_id,
+                results: outcomes.iter().map(|(node_id, error)| (node_id.clone(), error.is_none())).collect(),
+                timestamp: determinism::now(),
+                duration_ms,
+            })?;
+        }
+
+        tracing::info!(
+            fan_out_nodes = outcomes.len(),
+            successful_nodes = success_count,
+            parallel,
+            duration_ms = duration_ms,
+            "Fan-out completed"
+        );
+
+        Ok((outcomes, schedule.continue_at()))
+    }
+
+    /// Wait for the rate limit, if any, on the edge leaving `current_node`
+    async fn throttle_edge(
+        &self,
+        graph: &Graph<S>,
+        context: &mut ExecutionContext,
+        current_node: &NodeId,
+    ) -> GraphResult<()> {
+        // Same edge find_next_nodes will take
+        let Some(edge) = outgoing_edge(graph, current_node) else {
+            return Ok(());
+        };
+        let Some(limit) = edge.metadata.rate_limit.as_ref() else {
+            return Ok(());
+        };
+
+        let label = edge.label();
+        let waited = graph.edge_throttle().acquire(&label, limit).await;
+        if waited.is_zero() {
+            return Ok(());
+        }
+
+        tracing::info!(
+            edge = %label,
+            step = context.current_step,
+            waited_ms = waited.as_millis() as u64,
+            "Edge rate limit delayed traversal"
+        );
+
+        #[cfg(feature = "streaming")]
+        if let Some(ref emitter) = graph.event_emitter {
+            emitter.emit_custom(
+                context.execution_id,
+                "edge_throttled".to_string(),
+                serde_json::json!({
+                    "edge": label,
+                    "step": context.current_step,
+                    "waited_ms": waited.as_millis() as u64,
+                }),
+            )?;
+        }
+
+        context.edge_waits.push(EdgeWait {
+            edge: label,
+            step: context.current_step,
+            waited,
+        });
+        Ok(())
+    }
+
+    /// Resolve a speculation-enabled conditional edge leaving `current_node`
+    ///
+    /// Returns `None` if the edge is not eligible, leaving routing to
+    /// [`find_next_nodes`](Self::find_next_nodes). Otherwise the condition is
+    /// evaluated and its outcome recorded; when the branch statistics predict
+    /// an outcome, the predicted target runs on a copy of the state at the
+    /// same time. The returned flag is true when that run matched the
+    /// condition and succeeded, in which case its state has been kept.
+    ///
+    /// A run that queued outbox messages is never kept, so the node runs
+    /// again as a normal step and its side effects are performed only after
+    /// that step is checkpointed. Graphs with execution leases do not
+    /// speculate, since a node may only run while its lease is held.
+    async fn speculate(
+        &self,
+        graph: &Graph<S>,
+        state: &mut S,
+        context: &mut ExecutionContext,
+        current_node: &NodeId,
+    ) -> GraphResult<Option<(NodeId, bool)>> {
+        let Some(edge) = outgoing_edge(graph, current_node) else {
+            return Ok(None);
+        };
+        let (Some(config), crate::edge::EdgeType::Conditional { condition_id, true_target, false_target }) =
+            (edge.metadata.speculation.as_ref(), &edge.edge_type)
+        else {
+            return Ok(None);
+        };
+        let Some(condition) = graph.edge_registry().get_condition(condition_id) else {
+            return Ok(None);
+        };
+
+        let label = edge.label();
+        let stats = graph.branch_statistics();
+        let target_of = |outcome: bool| if outcome { true_target } else { false_target };
+
+        let prediction = match graph.leases() {
+            Some(_) => None,
+            None => stats.prediction(&label, config),
+        };
+        let Some(predicted) = prediction else {
+            let outcome = condition.evaluate(state).await?;
+            stats.record(&label, outcome);
+            return Ok(Some((target_of(outcome).clone(), false)));
+        };
+
+        // Run the predicted node as the next step would, without touching the real context
+        let predicted_node = target_of(predicted).clone();
+        let mut speculative_state = state.clone();
+        let mut speculative_context = context.clone();
+        speculative_context.current_node = Some(predicted_node.clone());
+        speculative_context.add_to_path(predicted_node.clone());
+        speculative_context.increment_step();
+
+        let (outcome, speculative_result) = tokio::join!(
+            condition.evaluate(state),
+            self.execute_node(graph, &mut speculative_state, &mut speculative_context, &predicted_node),
+        );
+        let outcome = outcome?;
+        stats.record(&label, outcome);
+        // The tokens were spent whether or not the branch is kept
+        let reported = speculative_context.llm_usage.split_off(context.llm_usage.len());
+        context.llm_usage.extend(reported);
+
+        let actual_node = target_of(outcome).clone();
+        let queued_effects = speculative_result.is_ok()
+            && OutboxState::from_state(&speculative_state)?.enqueued > OutboxState::from_state(state)?.enqueued;
+        let hit = outcome == predicted && speculative_result.is_ok() && !queued_effects;
+        if hit {
+            *state = speculative_state;
+            let candidates = speculative_context.candidates.split_off(context.candidates.len());
+            context.candidates.extend(candidates);
+        }
+
+        tracing::info!(
+            edge = %label,
+            predicted = %predicted_node,
+            actual = %actual_node,
+            hit,
+            queued_effects,
+            "Speculative branch resolved"
+        );
+
+        #[cfg(feature = "streaming")]
+        if let Some(ref emitter) = graph.event_emitter {
+            emitter.emit_custom(
+                context.execution_id,
+                "speculation_resolved".to_string(),
+                serde_json::json!({
+                    "edge": label,
+                    "predicted": predicted_node,
+                    "actual": actual_node,
+                    "hit": hit,
+                }),
+            )?;
+        }
+
+        context.speculations.push(SpeculationOutcome {
+            edge: label,
+            predicted: predicted_node,
+            actual: actual_node.clone(),
+            hit,
+            step: context.current_step,
+        });
+        Ok(Some((actual_node, hit)))
+    }
+
+    /// Find the next nodes to execute
+    async fn find_next_nodes(
+        &mut self,
+        graph: &Graph<S>,
+        state: &S,
+        current_node: &NodeId,
+    ) -> GraphResult<RouteResolution> {
+        let Some(edge) = outgoing_edge(graph, current_node) else {
+            return Ok(RouteResolution::None);
+        };
+
+        // For simple edges, just return the target directly
+        match &edge.edge_type {
+            crate::edge::EdgeType::Simple { target } => {
+                Ok(RouteResolution::Single(target.clone()))
+            }
+            crate::edge::EdgeType::Parallel { targets } => {
+                if targets.is_empty() {
+                    Ok(RouteResolution::None)
+                } else {
+                    Ok(RouteResolution::Multiple(targets.clone()))
+                }
+            }
+            crate::edge::EdgeType::Conditional { condition_id, true_target, false_target } => {
+                // Conditions registered on the graph take precedence over the engine's own
+                match graph.edge_registry().get_condition(condition_id) {
+                    Some(condition) => {
+                        let target = if condition.evaluate(state).await? { true_target } else { false_target };
+                        Ok(RouteResolution::Single(target.clone()))
+                    }
+                    None => self.edge_resolver.resolve_edge(edge, state).await,
+                }
+            }
+            crate::edge::EdgeType::Dynamic { router_id, possible_targets } => {
+                match graph.edge_registry().get_router(router_id) {
+                    Some(router) => {
+                        let target = router.route(state, possible_targets).await?;
+                        Ok(RouteResolution::Single(target))
+                    }
+                    None => self.edge_resolver.resolve_edge(edge, state).await,
+                }
+            }
+            _ => {
+                // For complex edges, use the resolver
+                self.edge_resolver.resolve_edge(edge, state).await
+            }
+        }
+    }
+}
+
+/// The edge taken out of a node, looked up in the plan of a compiled graph
+///
+/// For now this is the first edge (in practice, you might want priority-based selection).
+fn outgoing_edge<'g, S: State>(graph: &'g Graph<S>, node_id: &NodeId) -> Option<&'g Edge> {
+    match graph.plan() {
+        Some(plan) => match plan.route(node_id)? {
+            CompiledRoute::Edge(index) => graph.edges().get(*index),
+            CompiledRoute::Next(_) => graph.edges().iter().find(|edge| edge.from == *node_id),
+        },
+        None => graph.edges().iter().find(|edge| edge.from == *node_id),
+    }
+}
+
+/// Whether more than one node has an edge to `node_id`
+fn is_join<S: State>(graph: &Graph<S>, node_id: &NodeId) -> bool {
+    graph.node_ids()
+        .into_iter()
+        .filter(|from| !graph.finish_points().contains(*from))
+        .filter(|from| outgoing_edge(graph, from).is_some_and(|edge| edge.possible_targets().contains(&node_id)))
+        .nth(1)
+        .is_some()
+}
+
+/// Whether the edges leaving `node_id` can lead back to it
+fn is_on_cycle<S: State>(graph: &Graph<S>, node_id: &NodeId) -> bool {
+    let mut seen: HashSet<&NodeId> = HashSet::new();
+    let mut queue: VecDeque<&NodeId> = VecDeque::from([node_id]);
+    while let Some(current) = queue.pop_front() {
+        if graph.finish_points().contains(current) {
+            continue;
+        }
+        for target in outgoing_edge(graph, current).map(Edge::possible_targets).unwrap_or_default() {
+            if target == node_id {
+                return true;
+            }
+            if seen.insert(target) {
+                queue.push_back(target);
+            }
+        }
+    }
+    false
+}
+
+/// Time a node may run: its own timeout if it declares one, otherwise the graph's
+fn node_timeout<S: State>(graph: &Graph<S>, node_id: &NodeId) -> Option<Duration> {
+    let config = graph.config();
+    graph.node_registry()
+        .get_metadata(node_id)
+        .and_then(|metadata| metadata.timeout_ms)
+        .map(Duration::from_millis)
+        .or_else(|| config.node_timeout_seconds.or(config.max_execution_time_seconds).map(Duration::from_secs))
+}
+
+/// Record a node that failed without failing the execution, and skip the nodes after it
+///
+/// Every node reachable from the failed one that has not run yet is skipped,
+/// since its input would have come, directly or not, from the failed node.
+fn record_failure<S: State>(graph: &Graph<S>, context: &mut ExecutionContext, node_id: &NodeId, error: &GraphError) {
+    tracing::warn!(node_id = %node_id, error = %error, "Node failed, continuing without its dependents");
+    context.record_outcome(node_id.clone(), NodeOutcomeStatus::Failed {
+        error: error.to_string(),
+        category: error.category().to_string(),
+    });
+
+    let mut seen: HashSet<&NodeId> = context.node_outcomes.iter().map(|outcome| &outcome.node_id).collect();
+    let mut queue = std::collections::VecDeque::from([node_id]);
+    let mut skipped = Vec::new();
+    while let Some(current) = queue.pop_front() {
+        for edge in graph.edges().iter().filter(|edge| edge.from == *current) {
+            for target in edge.possible_targets() {
+                if seen.insert(target) {
+                    skipped.push(target.clone());
+                    queue.push_back(target);
+                }
+            }
+        }
+    }
+    for target in skipped {
+        context.record_outcome(target, NodeOutcomeStatus::Skipped {
+            reason: format!("Depends on failed node '{}'", node_id),
+        });
+    }
+}
+
+/// Tell the graph's webhooks and chat channels about a lifecycle event of an execution
+///
+/// Dry runs notify nobody: a notification is a side effect.
+fn notify_webhooks<S: State>(graph: &Graph<S>, context: &ExecutionContext, event: WebhookEvent, data: serde_json::Value) {
+    if graph.config().dry_run || (graph.webhooks().is_none() && graph.chat_notifier().is_none()) {
+        return;
+    }
+    let payload = WebhookPayload::new(event, data).with_execution(context.execution_id, graph.metadata().name.clone());
+    if let Some(chat) = graph.chat_notifier() {
+        chat.notify(payload.clone());
+    }
+    if let Some(webhooks) = graph.webhooks() {
+        webhooks.notify(payload);
+    }
+}
+
+/// Notify the webhooks of how an execution ended: completed, paused waiting on
+/// an operation, or failed
+fn notify_result<S: State>(graph: &Graph<S>, context: &ExecutionContext, result: Result<(), &GraphError>) {
+    let duration_ms = (determinism::now() - context.start_time).num_milliseconds();
+    let (event, data) = match result {
+        Ok(()) => (WebhookEvent::ExecutionCompleted, serde_json::json!({
+            "status": context.completion_status(),
+            "failed_nodes": context.failed_nodes(),
+            "skipped_nodes": context.skipped_nodes(),
+            "steps": context.current_step,
+            "attempts": context.attempt,
+            "duration_ms": duration_ms,
+        })),
+        Err(GraphError::Suspended { node_id, operation_id, checkpoint_id }) => (WebhookEvent::ExecutionPaused, serde_json::json!({
+            "node_id": node_id,
+            "operation_id": operation_id,
+            "checkpoint_id": checkpoint_id,
+            "steps": context.current_step,
+        })),
+        Err(error) => (WebhookEvent::ExecutionFailed, serde_json::json!({
+            "node_id": context.current_node,
+            "category": error.category(),
+            "errors": DeadLetter::error_chain(error),
+            "steps": context.current_step,
+            "attempts": context.attempt,
+            "duration_ms": duration_ms,
+            "compensations": context.compensations,
+        })),
+    };
+    notify_webhooks(graph, context, event, data);
+    record_slo_run(graph, context, result);
+}
+
+/// Count a finished execution towards the graph's SLOs
+fn record_slo_run<S: State>(graph: &Graph<S>, context: &ExecutionContext, result: Result<(), &GraphError>) {
+    let Some(metrics) = graph.slo_metrics() else {
+        return;
+    };
+    if result.is_err_and(|error| error.is_suspended() || error.is_cancelled()) {
+        return;
+    }
+    let duration_ms = (determinism::now() - context.start_time).num_milliseconds().max(0) as u64;
+    let mut run = RunSample::new(&graph.metadata().name, duration_ms, result.is_ok());
+    if !context.llm_usage.is_empty() {
+        run = run.with_cost(context.total_llm_usage().cost);
+    }
+    metrics.record_run(run);
+}
+
+/// Release the execution's lease, if the graph shares executions with other instances
+async fn release_lease<S: State>(graph: &Graph<S>, context: &ExecutionContext, result: Result<(), &GraphError>) {
+    if let Some(leases) = graph.leases() {
+        leases.finish(context.execution_id, result.is_ok()).await;
+    }
+}
+
+/// Record what a node reported while it ran into the context
+fn record_node_report(context: &mut ExecutionContext, node_id: &NodeId, report: NodeReport) -> NodeReport {
+    if let Some(usage) = &report.llm_usage {
+        context.record_llm_usage(node_id.clone(), usage.clone());
+    }
+    if let Some(candidates) = &report.candidates {
+        context.candidates.push((node_id.clone(), candidates.clone()));
+    }
+    report
+}
+
+/// What invoking a node needs from its graph and execution
+///
+/// Owned, so that a node can run on a task of its own.
+#[derive(Clone)]
+struct NodeInvocation {
+    node_id: NodeId,
+    step: u64,
+    /// Key the node holds while it runs, resolved from the state
+    concurrency_key: Option<String>,
+    workload: NodeWorkload,
+    affinity: Option<Vec<usize>>,
+    /// Pools the node takes permits from, with the pool names it declares
+    pools: Option<(Arc<ConcurrencyPools>, Vec<String>)>,
+    /// Emitter the node's own events go to, with the execution they belong to
+    #[cfg(feature = "streaming")]
+    events: Option<(crate::streaming::EventEmitter, uuid::Uuid)>,
+}
+
+impl NodeInvocation {
+    fn new<S: State>(
+        graph: &Graph<S>,
+        context: &ExecutionContext,
+        node_id: &NodeId,
+        state: &S,
+        pools: Option<Arc<ConcurrencyPools>>,
+    ) -> GraphResult<Self> {
+        let metadata = graph.node_registry().get_metadata(node_id);
+        let concurrency_key = match metadata.and_then(|metadata| metadata.concurrency_key.as_deref()) {
+            Some(template) => Some(
+                concurrency::resolve_key(template, |name| {
+                    state.get_value(name).or_else(|| context.get_custom_data(name))
+                })
+                .map_err(|e| GraphError::node_error(node_id.clone(), e.to_string(), None))?,
+            ),
+            None => None,
+        };
+        let (workload, affinity) = metadata
+            .map_or((NodeWorkload::Io, None), |metadata| (metadata.workload, metadata.cpu_affinity.clone()));
+
+        Ok(Self {
+            node_id: node_id.clone(),
+            step: context.current_step,
+            concurrency_key,
+            workload,
+            affinity,
+            pools: pools.map(|pools| (pools, metadata.map(|metadata| metadata.concurrency_pools.clone()).unwrap_or_default())),
+            #[cfg(feature = "streaming")]
+            events: graph.event_emitter.clone().map(|emitter| (emitter, context.execution_id)),
+        })
+    }
+
+    async fn run<S: State>(self, node: &SharedNode<S>, state: &mut S) -> GraphResult<()> {
+        let _permit = match &self.concurrency_key {
+            Some(key) => Some(ConcurrencyKeys::global().acquire(key).await),
+            None => None,
+        };
+        let _pool_permit = match &self.pools {
+            Some((pools, names)) => Some(pools.acquire(names).await),
+            None => None,
+        };
+
+        if self.workload == NodeWorkload::Io {
+            return self.invoke(node.as_ref(), state).await;
+        }
+        // A compute node runs on the compute pool with a copy of the state, so
+        // that a timeout can give up on it without waiting for it to return
+        let (invocation, node, mut node_state) = (self.clone(), node.clone(), state.clone());
+        let affinity = self.affinity.clone();
+        let (result, node_state) = NodePools::current()
+            .run(self.workload, affinity.as_deref(), async move {
+                let result = invocation.invoke(node.as_ref(), &mut node_state).await;
+                (result, node_state)
+            })
+            .await;
+        *state = node_state;
+        result
+    }
+
+    async fn invoke<S: State>(&self, node: &dyn Node<S>, state: &mut S) -> GraphResult<()> {
+        let invoke = async {
+            #[cfg(feature = "streaming")]
+            if let Some((emitter, execution_id)) = self.events.clone() {
+                return crate::streaming::with_node_events(emitter, execution_id, self.node_id.clone(), node.invoke(state)).await;
+            }
+
+            node.invoke(state).await
+        };
+        dry_run::in_node(&self.node_id, self.step, invoke).await
+    }
+}
+
+/// What the nodes of one execution run under, whichever entry point started it
+struct ExecutionScope {
+    execution_id: uuid::Uuid,
+    services: Services,
+    determinism: Determinism,
+    session: Option<(SessionLimiter, String)>,
+    sandbox: Option<sandbox::ExecutionSandbox>,
+}
+
+impl ExecutionScope {
+    /// Run `future` with the execution's ID, services, clock, flags, session limits and sandbox
+    async fn run<S: State, F: Future>(&self, graph: &Graph<S>, future: F) -> F::Output {
+        let future = session_limits::scope(self.session.clone(), sandbox::with_sandbox(self.sandbox.clone(), future));
+        let future = determinism::scope(&self.determinism, services::scope(&self.services, flags::scope(graph.flags(), future)));
+        EXECUTION_ID.scope(self.execution_id, future).await
+    }
+}
+
+/// Carry the execution's task-local scopes into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit_scopes<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let future = services::inherit(flags::inherit(context_vars::inherit(determinism::inherit(future))));
+    let future = dry_run::inherit(retry::inherit(session_limits::inherit(sandbox::inherit(future))));
+    let future = crate::llm::budget::inherit(crate::graph::report::inherit(future));
+    #[cfg(feature = "streaming")]
+    let future = crate::streaming::output::inherit(future);
+    let execution_id = current_execution_id();
+    async move {
+        match execution_id {
+            Some(execution_id) => EXECUTION_ID.scope(execution_id, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Dependency counters of the nodes a fan-out leads to
+///
+/// The nodes are those reachable from the fan-out's targets along the edges
+/// the engine routes by, up to the graph's finish points and up to the first
+/// join or node on a cycle, such as a review loop after the branches meet.
+/// Those are left to run one after another once the fan-out completes. A node
+/// depends on each of the others with an edge to it, and runs once all of
+/// those have settled if at least one routed to it and none failed.
+/// Otherwise it settles without running, so that the nodes after it are not
+/// left waiting.
+struct ParallelSchedule {
+    /// Each node after the nodes it depends on
+    order: Vec<NodeId>,
+    /// Nodes depending on each node
+    dependents: HashMap<NodeId, Vec<NodeId>>,
+    /// Dependencies each node is still waiting for
+    pending: HashMap<NodeId, usize>,
+    /// Nodes a settled dependency routed to, starting with the fan-out's targets
+    routed: HashSet<NodeId>,
+    /// Nodes after a failed node
+    blocked: HashSet<NodeId>,
+    /// Nodes that ran or settled without running
+    settled: HashSet<NodeId>,
+    /// Joins and nodes on a cycle the schedule stops at, in the order they were found
+    exits: Vec<NodeId>,
+    /// Exits each node of the schedule leads to
+    exits_of: HashMap<NodeId, Vec<NodeId>>,
+}
+
+impl ParallelSchedule {
+    fn new<S: State>(graph: &Graph<S>, targets: &[NodeId]) -> Self {
+        let mut discovered: Vec<NodeId> = Vec::new();
+        let mut dependents: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut pending: HashMap<NodeId, usize> = HashMap::new();
+        let mut exits: Vec<NodeId> = Vec::new();
+        let mut exits_of: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut queue: VecDeque<NodeId> = VecDeque::new();
+        for target in targets {
+            if !pending.contains_key(target) {
+                pending.insert(target.clone(), 0);
+                discovered.push(target.clone());
+                queue.push_back(target.clone());
+            }
+        }
+        while let Some(node_id) = queue.pop_front() {
+            if graph.finish_points().contains(&node_id) {
+                continue;
+            }
+            let Some(edge) = outgoing_edge(graph, &node_id) else { continue };
+            for target in edge.possible_targets() {
+                // Edges back to a target, into a join or around a loop leave the schedule
+                if targets.contains(target) || is_join(graph, target) || is_on_cycle(graph, target) {
+                    let node_exits = exits_of.entry(node_id.clone()).or_default();
+                    if !node_exits.contains(target) {
+                        node_exits.push(target.clone());
+                    }
+                    if !exits.contains(target) {
+                        exits.push(target.clone());
+                    }
+                    continue;
+                }
+                let node_dependents = dependents.entry(node_id.clone()).or_default();
+                if node_dependents.contains(target) {
+                    continue;
+                }
+                node_dependents.push(target.clone());
+                if !pending.contains_key(target) {
+                    discovered.push(target.clone());
+                    queue.push_back(target.clone());
+                }
+                *pending.entry(target.clone()).or_default() += 1;
+            }
+        }
+
+        // Kahn's algorithm, taking nodes in the order they were discovered
+        let mut remaining = pending.clone();
+        let mut order = Vec::new();
+        let mut next: VecDeque<&NodeId> = discovered.iter().filter(|node_id| remaining[*node_id] == 0).collect();
+        while let Some(node_id) = next.pop_front() {
+            order.push(node_id.clone());
+            for dependent in dependents.get(node_id).into_iter().flatten() {
+                let count = remaining.get_mut(dependent).expect("dependents are discovered");
+                *count -= 1;
+                if *count == 0 {
+                    next.push_back(dependent);
+                }
+            }
+        }
+
+        Self {
+            order,
+            dependents,
+            pending,
+            routed: targets.iter().cloned().collect(),
+            blocked: HashSet::new(),
+            settled: HashSet::new(),
+            exits,
+            exits_of,
+        }
+    }
+
+    /// Nodes to start with: the targets that depend on no other node of the fan-out
+    fn start(&self) -> Vec<NodeId> {
+        self.order.iter().filter(|node_id| self.pending[*node_id] == 0).cloned().collect()
+    }
+
+    /// Record that a node finished, routing to `routed`, and return the nodes that may start now
+    fn settle(&mut self, node_id: &NodeId, routed: &[NodeId], failed: bool) -> Vec<NodeId> {
+        let mut ready = Vec::new();
+        let mut queue = VecDeque::from([(node_id.clone(), routed.to_vec(), failed)]);
+        while let Some((node_id, routed, failed)) = queue.pop_front() {
+            self.settled.insert(node_id.clone());
+            for exit in self.exits_of.get(&node_id).cloned().unwrap_or_default() {
+                if failed {
+                    self.blocked.insert(exit);
+                } else if routed.contains(&exit) {
+                    self.routed.insert(exit);
+                }
+            }
+            for dependent in self.dependents.get(&node_id).cloned().unwrap_or_default() {
+                if failed {
+                    self.blocked.insert(dependent.clone());
+                } else if routed.contains(&dependent) {
+                    self.routed.insert(dependent.clone());
+                }
+                let count = self.pending.get_mut(&dependent).expect("dependents are discovered");
+                *count -= 1;
+                if *count == 0 {
+                    if let Some(skipped) = self.release(dependent, &mut ready) {
+                        queue.push_back(skipped);
+                    }
+                }
+            }
+        }
+        ready
+    }
+
+    /// Queue a node whose dependencies have settled, or settle it without running it
+    fn release(&mut self, node_id: NodeId, ready: &mut Vec<NodeId>) -> Option<(NodeId, Vec<NodeId>, bool)> {
+        let blocked = self.blocked.contains(&node_id);
+        if self.routed.contains(&node_id) && !blocked {
+            ready.push(node_id);
+            return None;
+        }
+        Some((node_id, Vec::new(), blocked))
+    }
+
+    /// Take the node of `ready` that comes first in plan order
+    fn take_first(&self, ready: &mut Vec<NodeId>) -> Option<NodeId> {
+        let position = self.order.iter().find_map(|node_id| ready.iter().position(|ready| ready == node_id))?;
+        Some(ready.remove(position))
+    }
+
+    /// Exits to continue at once every node settled: those routed to and not after a failed node
+    fn continue_at(&self) -> Vec<NodeId> {
+        self.exits
+            .iter()
+            .filter(|node_id| self.routed.contains(*node_id) && !self.blocked.contains(*node_id))
+            .cloned()
+            .collect()
+    }
+
+    /// The state `base` with the changes of the nodes that ran applied in plan order
+    fn merge(&self, base: &serde_json::Value, changes: &HashMap<NodeId, StateChanges>) -> serde_json::Value {
+        let mut state = base.clone();
+        for node_changes in self.order.iter().filter_map(|node_id| changes.get(node_id)) {
+            node_changes.apply(&mut state);
+        }
+        state
+    }
+}
+
+/// Changes a node made to a serialized state
+enum StateChanges {
+    /// Top-level fields set, or removed when `None`
+    Fields(Vec<(String, Option<serde_json::Value>)>),
+    /// A replacement for a state that is not an object
+    Whole(serde_json::Value),
+}
+
+impl StateChanges {
+    fn between(before: &serde_json::Value, after: serde_json::Value) -> Self {
+        match (before, after) {
+            (serde_json::Value::Object(before), serde_json::Value::Object(after)) => {
+                let mut fields: Vec<(String, Option<serde_json::Value>)> = before
+                    .keys()
+                    .filter(|key| !after.contains_key(*key))
+                    .map(|key| (key.clone(), None))
+                    .collect();
+                fields.extend(after.into_iter().filter(|(key, value)| before.get(key) != Some(value)).map(|(key, value)| (key, Some(value))));
+                Self::Fields(fields)
+            }
+            (_, after) => Self::Whole(after),
+        }
+    }
+
+    fn apply(&self, state: &mut serde_json::Value) {
+        match (self, state) {
+            (Self::Fields(fields), serde_json::Value::Object(object)) => {
+                for (key, value) in fields {
+                    match value {
+                        Some(value) => object.insert(key.clone(), value.clone()),
+                        None => object.remove(key),
+                    };
+                }
+            }
+            (Self::Whole(value), state) => *state = value.clone(),
+            (Self::Fields(_), _) => {}
+        }
+    }
+}
+
+/// Record the graph node ID on a suspension raised by a node
+fn with_suspended_node(error: GraphError, node_id: &NodeId) -> GraphError {
+    match error {
+        GraphError::Suspended { operation_id, checkpoint_id, .. } => GraphError::Suspended {
+            node_id: node_id.clone(),
+            operation_id,
+            checkpoint_id,
+        },
+        other => other,
+    }
+}
+
+impl<S> Default for GraphEngine<S>
+where
+    S: State + Clone + serde::Serialize + for<'de> serde::Deserialize<'de>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::graph::GraphBuilder;
+    use crate::node::Node;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    struct TestState {
+        value: i32,
+    }
+
+    #[derive(Debug)]
+    struct IncrementNode {
+        amount: i32,
+    }
+
+    #[async_trait]
+    impl Node<TestState> for IncrementNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            state.value += self.amount;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graph_execution() {
+        let node1 = IncrementNode { amount: 5 };
+        let node2 = IncrementNode { amount: 3 };
+
+        let graph = GraphBuilder::new()
+            .add_node("node1".to_string(), node1).unwrap()
+            .add_node("node2".to_string(), node2).unwrap()
+            .with_entry_point("node1".to_string()).unwrap()
+            .add_finish_point("node2".to_string()).unwrap()
+            .add_edge(Edge::simple("node1", "node2")).unwrap()
+            .build().unwrap();
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        
+        assert_eq!(state.value, 8); // 0 + 5 + 3
+        assert_eq!(context.current_step, 2);
+        assert_eq!(context.execution_path.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_compiled_graph_execution() {
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("middle".to_string(), IncrementNode { amount: 2 }).unwrap()
+            .add_node("small".to_string(), IncrementNode { amount: 10 }).unwrap()
+            .add_node("large".to_string(), IncrementNode { amount: 100 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_finish_point("small".to_string()).unwrap()
+            .add_finish_point("large".to_string()).unwrap()
+            .add_edge(Edge::simple("start", "middle")).unwrap()
+            .add_edge(Edge::conditional("middle", "below".to_string(), "small", "large")).unwrap()
+            .build().unwrap();
+        graph.edge_registry_mut().register_condition(BelowCondition(5));
+        let compiled = graph.compile().unwrap();
+
+        let mut engine = GraphEngine::new();
+        for (initial, expected) in [(0, 13), (10, 113)] {
+            let mut state = TestState { value: initial };
+            let context = engine.execute(&compiled, &mut state).await.unwrap();
+            assert_eq!(state.value, expected);
+            assert_eq!(context.current_step, 3);
+        }
+    }
+
+    /// Resolves its step size when invoked instead of capturing it
+    #[derive(Debug)]
+    struct ServiceNode;
+
+    #[derive(Debug)]
+    struct Step(i32);
+
+    #[async_trait]
+    impl Node<TestState> for ServiceNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            state.value += services::resolve::<Step>()?.0;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nodes_resolve_engine_services() {
+        let graph = GraphBuilder::new()
+            .add_node("step".to_string(), ServiceNode).unwrap()
+            .with_entry_point("step".to_string()).unwrap()
+            .add_finish_point("step".to_string()).unwrap()
+            .build().unwrap();
+
+        let mut state = TestState { value: 0 };
+        assert!(GraphEngine::new().execute(&graph, &mut state).await.is_err());
+
+        let mut engine = GraphEngine::new().with_services(Services::new().with(Step(4)));
+        engine.execute(&graph, &mut state).await.unwrap();
+        engine.services().provide(Step(10));
+        engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 14);
+    }
+
+    #[tokio::test]
+    async fn test_context_vars_route_without_entering_state() {
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("french".to_string(), IncrementNode { amount: 10 }).unwrap()
+            .add_node("other".to_string(), IncrementNode { amount: 100 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_finish_point("french".to_string()).unwrap()
+            .add_finish_point("other".to_string()).unwrap()
+            .add_edge(Edge::conditional("start", "context:locale=\"fr\"".to_string(), "french", "other")).unwrap()
+            .build().unwrap();
+        graph.edge_registry_mut().register_condition(context_vars::ContextCondition::equals(context_vars::LOCALE, "fr"));
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+        let vars = ContextVars::new().with_locale("fr").with_user("u-1");
+        let context = engine.execute_with_context(&graph, &mut state, vars).await.unwrap();
+        assert_eq!(state.value, 11);
+        assert_eq!(context.vars.user_id().as_deref(), Some("u-1"));
+
+        let mut state = TestState { value: 0 };
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 101);
+        assert!(context.vars.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_seeded_executions_repeat() {
+        let graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("a".to_string(), IncrementNode { amount: 10 }).unwrap()
+            .add_node("b".to_string(), IncrementNode { amount: 100 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_finish_point("a".to_string()).unwrap()
+            .add_finish_point("b".to_string()).unwrap()
+            .add_edge(Edge::weighted("start", vec![("a".to_string(), 1.0), ("b".to_string(), 1.0)])).unwrap()
+            .build().unwrap();
+
+        let run = |seed| {
+            let graph = &graph;
+            async move {
+                let mut engine = GraphEngine::new().with_determinism(Determinism::seeded(seed));
+                let mut state = TestState { value: 0 };
+                let context = engine.execute(graph, &mut state).await.unwrap();
+                (context.execution_id, context.start_time, context.execution_path, state.value)
+            }
+        };
+        let first = run(42).await;
+        assert_eq!(first, run(42).await);
+        assert_eq!(first.1, chrono::DateTime::<chrono::Utc>::UNIX_EPOCH);
+        let paths: HashSet<_> = futures::future::join_all((0..16).map(run)).await.into_iter().map(|run| run.2).collect();
+        assert_eq!(paths.len(), 2);
+    }
+
+    #[derive(Debug)]
+    struct AskNode(Arc<crate::llm::LLMManager>);
+
+    #[async_trait]
+    impl Node<TestState> for AskNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            // No provider is registered, so only a dry run can answer
+            let response = self.0
+                .complete_with_provider("openai", crate::llm::CompletionRequest::default())
+                .await
+                .map_err(|e| GraphError::execution_error(e.to_string()))?;
+            state.value += response.choices.len() as i32;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_simulates_llm_calls() {
+        use crate::graph::dry_run::SimulatedEffectKind;
+        use crate::graph::ExecutionConfig;
+
+        let llm = Arc::new(crate::llm::LLMManager::new(Default::default()));
+        let build = |dry_run| {
+            GraphBuilder::new()
+                .with_config(ExecutionConfig { dry_run, ..Default::default() })
+                .add_node("ask".to_string(), AskNode(llm.clone())).unwrap()
+                .with_entry_point("ask".to_string()).unwrap()
+                .add_finish_point("ask".to_string()).unwrap()
+                .build().unwrap()
+        };
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+        let context = engine.execute(&build(true), &mut state).await.unwrap();
+        assert_eq!(state.value, 1);
+        assert_eq!(context.simulated_effects.len(), 1);
+        assert_eq!(context.simulated_effects[0].node_id.as_deref(), Some("ask"));
+        assert!(matches!(
+            &context.simulated_effects[0].kind,
+            SimulatedEffectKind::LlmCall { provider, .. } if provider == "openai"
+        ));
+
+        assert!(engine.execute(&build(false), &mut state).await.is_err());
+    }
+
+    #[derive(Debug)]
+    struct BelowCondition(i32);
+
+    #[async_trait]
+    impl crate::edge::EdgeCondition<TestState> for BelowCondition {
+        async fn evaluate(&self, state: &TestState) -> GraphResult<bool> {
+            Ok(state.value < self.0)
+        }
+
+        fn condition_id(&self) -> String {
+            "below".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_speculative_branch_kept_or_discarded() {
+        use crate::edge::speculation::{BranchCounts, SpeculationConfig};
+
+        let mut graph = GraphBuilder::new()
+            .add_node("check".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("small".to_string(), IncrementNode { amount: 10 }).unwrap()
+            .add_node("large".to_string(), IncrementNode { amount: 100 }).unwrap()
+            .with_entry_point("check".to_string()).unwrap()
+            .add_finish_point("small".to_string()).unwrap()
+            .add_finish_point("large".to_string()).unwrap()
+            .add_edge(
+                Edge::conditional("check", "below".to_string(), "small", "large")
+                    .with_name("size")
+                    .with_speculation(SpeculationConfig { min_samples: 1, min_probability: 0.5 }),
+            ).unwrap()
+            .build().unwrap();
+        graph.edge_registry_mut().register_condition(BelowCondition(5));
+        graph.branch_statistics().load(
+            [("size".to_string(), BranchCounts { true_count: 9, false_count: 1 })].into(),
+        );
+
+        let mut engine = GraphEngine::new();
+
+        // Predicted "small" and the condition agrees
+        let mut state = TestState { value: 0 };
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 11);
+        assert_eq!(context.execution_path, vec!["check".to_string(), "small".to_string()]);
+        assert_eq!(context.current_step, 2);
+        assert!(context.speculations[0].hit);
+
+        // Predicted "small" but the condition picks "large": the speculative +10 is dropped
+        let mut state = TestState { value: 10 };
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 111);
+        assert_eq!(context.execution_path, vec!["check".to_string(), "large".to_string()]);
+        assert!(!context.speculations[0].hit);
+        assert_eq!(context.speculations[0].actual, "large");
+
+        assert_eq!(graph.branch_statistics().get("size").total(), 12);
+    }
+
+    #[derive(Debug, Default)]
+    struct WarmNode {
+        setups: std::sync::atomic::AtomicUsize,
+        cleanups: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Node<TestState> for WarmNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            state.value = self.setups.load(std::sync::atomic::Ordering::SeqCst) as i32;
+            Ok(())
+        }
+
+        async fn setup(&self) -> GraphResult<()> {
+            self.setups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn cleanup(&self) -> GraphResult<()> {
+            self.cleanups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nodes_set_up_once_per_engine() {
+        let cleanups = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let node = WarmNode { cleanups: cleanups.clone(), ..Default::default() };
+        let graph = GraphBuilder::new()
+            .add_node("warm".to_string(), node).unwrap()
+            .with_entry_point("warm".to_string()).unwrap()
+            .add_finish_point("warm".to_string()).unwrap()
+            .build().unwrap();
+
+        let mut engine = GraphEngine::new();
+        engine.warm_up(&graph).await.unwrap();
+        for _ in 0..3 {
+            let mut state = TestState { value: 0 };
+            engine.execute(&graph, &mut state).await.unwrap();
+            assert_eq!(state.value, 1);
+        }
+
+        engine.teardown(&graph).await.unwrap();
+        assert_eq!(cleanups.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Torn down nodes are set up again on their next run
+        let mut state = TestState { value: 0 };
+        engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 2);
+    }
+
+    #[derive(Debug, Default)]
+    struct LedgerNode {
+        active: Arc<std::sync::atomic::AtomicUsize>,
+        peak: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Node<TestState> for LedgerNode {
+        async fn invoke(&self, _state: &mut TestState) -> GraphResult<()> {
+            use std::sync::atomic::Ordering;
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn metadata(&self) -> crate::node::NodeMetadata {
+            crate::node::NodeMetadata::new("LedgerNode").with_concurrency_key("engine-test-ledger")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_key_serializes_parallel_branches() {
+        let ledger = LedgerNode::default();
+        let node = || LedgerNode { active: ledger.active.clone(), peak: ledger.peak.clone() };
+        let graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("debit".to_string(), node()).unwrap()
+            .add_node("credit".to_string(), node()).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["debit".to_string(), "credit".to_string()])).unwrap()
+            .add_finish_point("debit".to_string()).unwrap()
+            .add_finish_point("credit".to_string()).unwrap()
+            .build().unwrap();
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+        engine.execute(&graph, &mut state).await.unwrap();
+
+        assert_eq!(ledger.peak.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(!ConcurrencyKeys::global().is_held("engine-test-ledger"));
+    }
+
+    #[derive(Debug, Default)]
+    struct PooledNode {
+        active: Arc<std::sync::atomic::AtomicUsize>,
+        peak: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Node<TestState> for PooledNode {
+        async fn invoke(&self, _state: &mut TestState) -> GraphResult<()> {
+            use std::sync::atomic::Ordering;
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn metadata(&self) -> crate::node::NodeMetadata {
+            crate::node::NodeMetadata::new("PooledNode").with_concurrency_pool("llm")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_pools_limit_parallel_branches() {
+        let pooled = PooledNode::default();
+        let node = || PooledNode { active: pooled.active.clone(), peak: pooled.peak.clone() };
+        let targets = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut builder = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", targets.clone())).unwrap();
+        for target in &targets {
+            builder = builder
+                .add_node(target.clone(), node()).unwrap()
+                .add_finish_point(target.clone()).unwrap();
+        }
+        let graph = builder.build().unwrap();
+
+        let mut engine = GraphEngine::new().with_concurrency_pools(ConcurrencyPools::new(8).with_pool("llm", 2));
+        let mut state = TestState { value: 0 };
+        engine.execute(&graph, &mut state).await.unwrap();
+
+        assert_eq!(pooled.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+        let pools = engine.concurrency_pools().unwrap();
+        assert_eq!(pools.available("llm"), Some(2));
+        assert_eq!(pools.available_global(), 8);
+    }
+
+    #[derive(Debug, Default)]
+    struct FlakyNode {
+        failing: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Node<TestState> for FlakyNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(GraphError::node_error("flaky".to_string(), "upstream unavailable".to_string(), None));
+            }
+            state.value *= 10;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_execution_dead_lettered_and_redriven() {
+        use crate::state::dead_letter::MemoryDeadLetterQueue;
+
+        let failing = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("flaky".to_string(), FlakyNode { failing: failing.clone() }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_finish_point("flaky".to_string()).unwrap()
+            .add_edge(Edge::simple("start", "flaky")).unwrap()
+            .build().unwrap();
+        graph.set_dead_letter_queue(MemoryDeadLetterQueue::new());
+        let queue = graph.dead_letter_queue().unwrap().clone();
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 4 };
+        assert!(engine.execute(&graph, &mut state).await.is_err());
+
+        let letter = queue.list().await.unwrap().remove(0);
+        assert_eq!(letter.node_id.as_deref(), Some("flaky"));
+        assert_eq!(letter.execution_path, vec!["start".to_string(), "flaky".to_string()]);
+        assert_eq!(letter.input_state, serde_json::json!({ "value": 4 }));
+        assert_eq!(letter.failed_state, serde_json::json!({ "value": 5 }));
+        assert!(letter.errors[0].contains("upstream unavailable"));
+
+        // A failed retry updates the same letter
+        assert!(engine.retry_dead_letter(&graph, &letter.id).await.is_err());
+        let retried = queue.get(&letter.id).await.unwrap().unwrap();
+        assert_eq!(retried.attempts, 2);
+
+        // Only letters marked for retry are redriven
+        assert!(engine.redrive(&graph).await.unwrap().is_empty());
+        queue.request_retry(&letter.id).await.unwrap();
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(engine.redrive(&graph).await.unwrap(), vec![letter.id.clone()]);
+        assert!(queue.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finished_executions_count_towards_slos() {
+        use crate::enterprise::monitoring::{MetricsCollector, MonitoringConfig};
+        use crate::enterprise::slo::Slo;
+
+        let failing = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let mut graph = GraphBuilder::new()
+            .add_node("flaky".to_string(), FlakyNode { failing: failing.clone() }).unwrap()
+            .with_entry_point("flaky".to_string()).unwrap()
+            .add_finish_point("flaky".to_string()).unwrap()
+            .build().unwrap();
+        let metrics = Arc::new(MetricsCollector::new(MonitoringConfig::default()).unwrap());
+        metrics.define_slo(Slo::success_rate("availability", graph.metadata().name.clone(), 0.99)).unwrap();
+        assert!(metrics.define_slo(Slo::success_rate("impossible", "other", 1.5)).is_err());
+        graph.set_slo_metrics(metrics.clone());
+
+        let mut engine = GraphEngine::new();
+        assert!(engine.execute(&graph, &mut TestState { value: 0 }).await.is_err());
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        engine.execute(&graph, &mut TestState { value: 0 }).await.unwrap();
+
+        let report = metrics.slo_reports().remove(0);
+        assert_eq!((report.runs, report.good), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_continue_on_error_skips_dependents_only() {
+        use crate::graph::outcome::{CompletionStatus, NodeOutcomeStatus};
+
+        let failing = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("flaky".to_string(), FlakyNode { failing: failing.clone() }).unwrap()
+            .add_node("report".to_string(), IncrementNode { amount: 100 }).unwrap()
+            .add_node("audit".to_string(), IncrementNode { amount: 2 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["flaky".to_string(), "audit".to_string()])).unwrap()
+            .add_edge(Edge::simple("flaky", "report")).unwrap()
+            .add_finish_point("report".to_string()).unwrap()
+            .add_finish_point("audit".to_string()).unwrap()
+            .build().unwrap();
+        let mut config = graph.config().clone();
+        config.stop_on_error = false;
+        graph.set_config(config.clone());
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 4 };
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 7);
+        assert_eq!(context.completion_status(), CompletionStatus::PartialSuccess);
+        assert_eq!(context.failed_nodes(), vec!["flaky"]);
+        assert_eq!(context.skipped_nodes(), vec!["report"]);
+        let audit = context.node_outcomes.iter().find(|outcome| outcome.node_id == "audit").unwrap();
+        assert_eq!(audit.status, NodeOutcomeStatus::Succeeded);
+
+        // Sequential branches behave the same
+        config.enable_parallel = false;
+        graph.set_config(config);
+        let mut state = TestState { value: 4 };
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 7);
+        assert_eq!(context.failed_nodes(), vec!["flaky"]);
+
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        let context = engine.execute(&graph, &mut TestState { value: 4 }).await.unwrap();
+        assert_eq!(context.completion_status(), CompletionStatus::Succeeded);
+    }
+
+    /// Records its name once it has waited `delay_ms`
+    #[derive(Debug)]
+    struct StepNode {
+        name: &'static str,
+        delay_ms: u64,
+        log: Arc<parking_lot::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl Node<TestState> for StepNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            self.log.lock().push(self.name);
+            state.value += 1;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_starts_each_node_after_its_own_dependencies() {
+        let log = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let step = |name, delay_ms| StepNode { name, delay_ms, log: log.clone() };
+        let graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("slow".to_string(), step("slow", 50)).unwrap()
+            .add_node("fast".to_string(), step("fast", 0)).unwrap()
+            .add_node("after_fast".to_string(), step("after_fast", 0)).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["slow".to_string(), "fast".to_string()])).unwrap()
+            .add_edge(Edge::simple("fast", "after_fast")).unwrap()
+            .add_finish_point("slow".to_string()).unwrap()
+            .add_finish_point("after_fast".to_string()).unwrap()
+            .build().unwrap();
+
+        let mut state = TestState { value: 0 };
+        let context = GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+        assert_eq!(*log.lock(), ["fast", "after_fast", "slow"]);
+        let succeeded: Vec<&str> = context.node_outcomes.iter().map(|outcome| outcome.node_id.as_str()).collect();
+        assert_eq!(succeeded, ["start", "slow", "fast", "after_fast"]);
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct PairState {
+        left: i32,
+        right: i32,
+        total: i32,
+    }
+
+    /// Sets one field of a [`PairState`] after `delay_ms`
+    #[derive(Debug)]
+    struct FieldNode {
+        field: &'static str,
+        delay_ms: u64,
+    }
+
+    #[async_trait]
+    impl Node<PairState> for FieldNode {
+        async fn invoke(&self, state: &mut PairState) -> GraphResult<()> {
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            match self.field {
+                "left" => state.left = 1,
+                "right" => state.right = 2,
+                "total" => state.total = state.left + state.right,
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_merges_every_branch_into_the_state() {
+        let graph = GraphBuilder::new()
+            .add_node("start".to_string(), FieldNode { field: "none", delay_ms: 0 }).unwrap()
+            .add_node("left".to_string(), FieldNode { field: "left", delay_ms: 20 }).unwrap()
+            .add_node("right".to_string(), FieldNode { field: "right", delay_ms: 0 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["left".to_string(), "right".to_string()])).unwrap()
+            .add_finish_point("left".to_string()).unwrap()
+            .add_finish_point("right".to_string()).unwrap()
+            .build().unwrap();
+
+        // Neither branch's copy of the state overwrites the other's changes
+        let mut state = PairState::default();
+        GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+        assert_eq!((state.left, state.right), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_runs_the_same_nodes_with_or_without_parallelism() {
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), FieldNode { field: "none", delay_ms: 0 }).unwrap()
+            .add_node("left".to_string(), FieldNode { field: "left", delay_ms: 10 }).unwrap()
+            .add_node("right".to_string(), FieldNode { field: "right", delay_ms: 0 }).unwrap()
+            .add_node("join".to_string(), FieldNode { field: "total", delay_ms: 0 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["left".to_string(), "right".to_string()])).unwrap()
+            .add_edge(Edge::simple("left", "join")).unwrap()
+            .add_edge(Edge::simple("right", "join")).unwrap()
+            .add_finish_point("join".to_string()).unwrap()
+            .build().unwrap();
+
+        let mut states = Vec::new();
+        for enable_parallel in [true, false] {
+            let mut config = graph.config().clone();
+            config.enable_parallel = enable_parallel;
+            graph.set_config(config);
+            let mut state = PairState::default();
+            let context = GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+            let ran: HashSet<&str> = context.node_outcomes.iter().map(|outcome| outcome.node_id.as_str()).collect();
+            assert_eq!(ran, HashSet::from(["start", "left", "right", "join"]));
+            states.push(state);
         }
+        assert_eq!(states[0], PairState { left: 1, right: 2, total: 3 });
+        assert_eq!(states[0], states[1]);
+    }
 
-        let start_time = std::time::Instant::now();
-        
-        // Clone state for each parallel execution
-        let mut tasks = Vec::new();
-        
-        for node_id in &node_ids {
-            let mut state_clone = state.clone();
-            let node = graph.node_registry()
-                .get(node_id)
-                .ok_or_else(|| GraphError::node_error(
-                    node_id.clone(),
-                    "Node not found in registry".to_string(),
-                    None,
-                ))?;
-
-            // Create a task for each node
-            let node_id_clone = node_id.clone();
-            let task = async move {
-                let result = node.invoke(&mut state_clone).await;
-                (node_id_clone, result, state_clone)
+    /// Waits for a sign-off that sets the right field
+    #[derive(Debug)]
+    struct SignOffNode;
+
+    #[async_trait]
+    impl Node<PairState> for SignOffNode {
+        async fn invoke(&self, _state: &mut PairState) -> GraphResult<()> {
+            Err(GraphError::suspended("sign_off", "sign-off-request"))
+        }
+
+        async fn resume(&self, state: &mut PairState, output: serde_json::Value) -> GraphResult<()> {
+            state.right = output.as_i64().unwrap_or_default() as i32;
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "checkpointing")]
+    #[tokio::test]
+    async fn test_fan_out_node_suspends_and_resumes() {
+        use crate::state::checkpointing::MemoryCheckpointer;
+
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), FieldNode { field: "none", delay_ms: 0 }).unwrap()
+            .add_node("left".to_string(), FieldNode { field: "left", delay_ms: 10 }).unwrap()
+            .add_node("sign_off".to_string(), SignOffNode).unwrap()
+            .add_node("join".to_string(), FieldNode { field: "total", delay_ms: 0 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["left".to_string(), "sign_off".to_string()])).unwrap()
+            .add_edge(Edge::simple("left", "join")).unwrap()
+            .add_edge(Edge::simple("sign_off", "join")).unwrap()
+            .add_finish_point("join".to_string()).unwrap()
+            .build().unwrap();
+        graph.set_checkpointer(MemoryCheckpointer::new());
+
+        for enable_parallel in [true, false] {
+            let mut config = graph.config().clone();
+            config.enable_parallel = enable_parallel;
+            graph.set_config(config);
+
+            let mut engine = GraphEngine::new();
+            let execution_id = uuid::Uuid::new_v4();
+            let error = engine.execute_with_id(&graph, &mut PairState::default(), execution_id).await.unwrap_err();
+            let GraphError::Suspended { node_id, checkpoint_id: Some(checkpoint_id), .. } = error else {
+                panic!("expected a checkpointed suspension, got {}", error);
             };
-            
-            tasks.push(task);
+            assert_eq!(node_id, "sign_off");
+
+            // The branch that did not wait on the sign-off ran before the checkpoint
+            let checkpoint_id = uuid::Uuid::parse_str(&checkpoint_id).unwrap();
+            let (state, context) = engine.resume_from_checkpoint(&graph, checkpoint_id, serde_json::json!(2)).await.unwrap();
+            assert_eq!(state, PairState { left: 1, right: 2, total: 3 });
+            // The resumed run carries on as the execution that suspended
+            assert_eq!(context.execution_id, execution_id);
+            assert_eq!(context.current_step, 2);
         }
+    }
 
-        // Execute all tasks concurrently
-        let results = futures::future::join_all(tasks).await;
-        
-        // Process results
-        let mut success_count = 0;
-        let mut node_results = Vec::new();
-        
-        for (node_id, result, updated_state) in results {
-            let success = result.is_ok();
-            node_results.push((node_id.clone(), success));
-            
-            if success {
-                success_count += 1;
-                // For now, we'll use the last successful state update
-                // In practice, you might want a more sophisticated merging strategy
-                *state = updated_state;
-            } else if graph.config().stop_on_error {
-                return result;
+    /// Waits for a go-ahead, then tries to reach an internal host
+    #[derive(Debug)]
+    struct EgressNode;
+
+    #[async_trait]
+    impl Node<TestState> for EgressNode {
+        async fn invoke(&self, _state: &mut TestState) -> GraphResult<()> {
+            Err(GraphError::suspended("egress", "go-ahead"))
+        }
+
+        async fn resume(&self, state: &mut TestState, _output: serde_json::Value) -> GraphResult<()> {
+            let sandbox = sandbox::current().ok_or_else(|| GraphError::execution_error("no sandbox"))?;
+            if sandbox.check_host("metadata.internal").await.is_err() {
+                state.value = -1;
             }
+            Ok(())
         }
+    }
 
-        let duration_ms = start_time.elapsed().as_millis() as u64;
+    #[tokio::test]
+    async fn test_resumed_node_stays_sandboxed() {
+        use crate::enterprise::sandbox::{SandboxProfile, SandboxRegistry};
 
-        #[cfg(feature = "streaming")]
-        if let Some(ref emitter) = graph.event_emitter {
-            emitter.emit(ExecutionEvent::ParallelCompleted {
-                execution_id: context.execution_id,
-                results: node_results,
-                timestamp: chrono::Utc::now(),
-                duration_ms,
-            })?;
-        }
+        let mut graph = GraphBuilder::new()
+            .add_node("egress".to_string(), EgressNode).unwrap()
+            .with_entry_point("egress".to_string()).unwrap()
+            .add_finish_point("egress".to_string()).unwrap()
+            .build().unwrap();
+        let registry = SandboxRegistry::new();
+        registry.define(SandboxProfile::new("locked").with_denied_host("*.internal"));
+        registry.attach_to_graph(graph.metadata().name.clone(), "locked").unwrap();
+        graph.set_sandbox_registry(Arc::new(registry));
 
-        tracing::info!(
-            parallel_nodes = node_ids.len(),
-            successful_nodes = success_count,
-            duration_ms = duration_ms,
-            "Parallel execution completed"
-        );
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+        let error = engine.execute(&graph, &mut state).await.unwrap_err();
+        assert!(error.is_suspended(), "{}", error);
 
-        Ok(())
+        engine.resume(&graph, &mut state, "egress".to_string(), serde_json::json!(true)).await.unwrap();
+        assert_eq!(state.value, -1);
     }
 
-    /// Find the next nodes to execute
-    async fn find_next_nodes(
-        &mut self,
-        graph: &Graph<S>,
-        state: &S,
-        current_node: &NodeId,
-    ) -> GraphResult<RouteResolution> {
-        // Find edges from the current node
-        let outgoing_edges: Vec<_> = graph.edges()
-            .iter()
-            .filter(|edge| edge.from == *current_node)
-            .collect();
+    #[tokio::test]
+    async fn test_fan_out_continues_into_a_loop_after_the_join() {
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("draft".to_string(), IncrementNode { amount: 0 }).unwrap()
+            .add_node("research".to_string(), IncrementNode { amount: 0 }).unwrap()
+            .add_node("review".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("revise".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("done".to_string(), IncrementNode { amount: 100 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["draft".to_string(), "research".to_string()])).unwrap()
+            .add_edge(Edge::simple("draft", "review")).unwrap()
+            .add_edge(Edge::simple("research", "review")).unwrap()
+            .add_edge(Edge::conditional("review", "below".to_string(), "revise", "done")).unwrap()
+            .add_edge(Edge::simple("revise", "review")).unwrap()
+            .add_finish_point("done".to_string()).unwrap()
+            .build().unwrap();
+        graph.edge_registry_mut().register_condition(BelowCondition(5));
 
-        if outgoing_edges.is_empty() {
-            return Ok(RouteResolution::None);
+        for enable_parallel in [true, false] {
+            let mut config = graph.config().clone();
+            config.enable_parallel = enable_parallel;
+            graph.set_config(config);
+
+            // The review loop runs once the branches met, until the value reaches 5
+            let mut state = TestState { value: 0 };
+            let context = GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+            assert_eq!(state.value, 106);
+            let reviews = context.execution_path.iter().filter(|node_id| *node_id == "review").count();
+            assert_eq!(reviews, 3);
         }
+    }
 
-        // For now, take the first edge (in practice, you might want priority-based selection)
-        let edge = outgoing_edges[0];
+    #[derive(Debug)]
+    struct SleepNode {
+        duration: Duration,
+        timeout: Option<Duration>,
+    }
 
-        // For simple edges, just return the target directly
-        match &edge.edge_type {
-            crate::edge::EdgeType::Simple { target } => {
-                Ok(RouteResolution::Single(target.clone()))
-            }
-            crate::edge::EdgeType::Parallel { targets } => {
-                if targets.is_empty() {
-                    Ok(RouteResolution::None)
-                } else {
-                    Ok(RouteResolution::Multiple(targets.clone()))
-                }
-            }
-            _ => {
-                // For complex edges, use the resolver
-                self.edge_resolver.resolve_edge(edge, state).await
+    #[async_trait]
+    impl Node<TestState> for SleepNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            tokio::time::sleep(self.duration).await;
+            state.value += 1;
+            Ok(())
+        }
+
+        fn metadata(&self) -> crate::node::NodeMetadata {
+            let metadata = crate::node::NodeMetadata::new("SleepNode");
+            match self.timeout {
+                Some(timeout) => metadata.with_timeout(timeout),
+                None => metadata,
             }
         }
     }
-}
 
-impl<S> Default for GraphEngine<S>
-where
-    S: State + Clone + serde::Serialize + for<'de> serde::Deserialize<'de>,
-{
-    fn default() -> Self {
-        Self::new()
-    }
-}
+    #[tokio::test]
+    async fn test_node_timeout_overrides_graph_timeout() {
+        let sleep = |millis, timeout| SleepNode { duration: Duration::from_millis(millis), timeout };
+        let mut graph = GraphBuilder::new()
+            .add_node("lookup".to_string(), sleep(10, Some(Duration::from_millis(500)))).unwrap()
+            .add_node("batch".to_string(), sleep(1200, Some(Duration::from_secs(5)))).unwrap()
+            .with_entry_point("lookup".to_string()).unwrap()
+            .add_edge(Edge::simple("lookup", "batch")).unwrap()
+            .add_finish_point("batch".to_string()).unwrap()
+            .build().unwrap();
+        let mut config = graph.config().clone();
+        config.node_timeout_seconds = Some(1);
+        graph.set_config(config);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::edge::Edge;
-    use crate::graph::GraphBuilder;
-    use crate::node::Node;
-    use async_trait::async_trait;
+        // "batch" outlives the graph's node timeout but stays within its own
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+        engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 2);
 
-    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-    struct TestState {
-        value: i32,
+        // A node's own timeout can be shorter than the graph's, too
+        graph.node_registry_mut().get_metadata_mut(&"lookup".to_string()).unwrap().timeout_ms = Some(1);
+        let error = engine.execute(&graph, &mut TestState { value: 0 }).await.unwrap_err();
+        assert!(matches!(error, GraphError::Timeout { .. }));
     }
 
-    #[derive(Debug)]
-    struct IncrementNode {
-        amount: i32,
+    #[derive(Debug, Default)]
+    struct OutageNode {
+        failures_left: Arc<std::sync::atomic::AtomicU32>,
     }
 
     #[async_trait]
-    impl Node<TestState> for IncrementNode {
+    impl Node<TestState> for OutageNode {
         async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
-            state.value += self.amount;
+            let ordering = std::sync::atomic::Ordering::SeqCst;
+            if self.failures_left.fetch_update(ordering, ordering, |left| left.checked_sub(1)).is_ok() {
+                return Err(GraphError::ExternalServiceError("503 from upstream".to_string()));
+            }
+            state.value *= 10;
             Ok(())
         }
     }
 
     #[tokio::test]
-    async fn test_graph_execution() {
-        let node1 = IncrementNode { amount: 5 };
-        let node2 = IncrementNode { amount: 3 };
+    async fn test_execution_retried_from_input_state() {
+        use crate::graph::retry::ExecutionRetryPolicy;
+
+        let failures_left = Arc::new(std::sync::atomic::AtomicU32::new(2));
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("call".to_string(), OutageNode { failures_left: failures_left.clone() }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_finish_point("call".to_string()).unwrap()
+            .add_edge(Edge::simple("start", "call")).unwrap()
+            .build().unwrap();
+        let mut config = graph.config().clone();
+        config.retry = Some(
+            ExecutionRetryPolicy::new(3)
+                .with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+                .with_jitter(false),
+        );
+        graph.set_config(config.clone());
+
+        // Each attempt starts again from the input, so "start" is not applied twice
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 4 };
+        let context = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.value, 50);
+        assert_eq!(context.attempt, 3);
+        assert_eq!(context.execution_path, vec!["start".to_string(), "call".to_string()]);
+
+        // Out of attempts
+        failures_left.store(3, std::sync::atomic::Ordering::SeqCst);
+        let mut state = TestState { value: 4 };
+        assert!(engine.execute(&graph, &mut state).await.is_err());
+
+        // Only the listed error classes are retried
+        config.retry = config.retry.map(|policy| policy.with_retry_on(["timeout"]));
+        graph.set_config(config);
+        failures_left.store(1, std::sync::atomic::Ordering::SeqCst);
+        let mut state = TestState { value: 4 };
+        assert!(engine.execute(&graph, &mut state).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paused_time_fast_forwards_timeouts() {
+        use crate::graph::retry::ExecutionRetryPolicy;
+        use crate::human::interrupt::ResumeToken;
+
+        let minutes = |count: u64| Duration::from_secs(60 * count);
+        let mut graph = GraphBuilder::new()
+            .add_node("call".to_string(), SleepNode { duration: minutes(10), timeout: Some(minutes(5)) }).unwrap()
+            .with_entry_point("call".to_string()).unwrap()
+            .add_finish_point("call".to_string()).unwrap()
+            .build().unwrap();
+        let mut config = graph.config().clone();
+        config.retry = Some(ExecutionRetryPolicy::new(2).with_backoff(minutes(30), minutes(30)).with_jitter(false));
+        graph.set_config(config);
+
+        let started = std::time::Instant::now();
+        let determinism = Determinism::simulated(1);
+        let mut engine = GraphEngine::new().with_determinism(determinism.clone());
+        let error = engine.execute(&graph, &mut TestState { value: 0 }).await.unwrap_err();
+        assert!(matches!(error, GraphError::Timeout { .. }));
+        // Two five-minute timeouts and the half-hour retry delay between them
+        assert!(determinism::scope(&determinism, async { determinism::now() }).await.timestamp() >= 40 * 60);
+
+        let token = determinism::scope(&determinism, async { ResumeToken::new("run".to_string(), "review".to_string()).with_expiration(minutes(60)) }).await;
+        tokio::time::sleep(minutes(61)).await;
+        assert!(determinism::scope(&determinism, async { token.is_expired() }).await);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_edge_records_wait() {
+        use crate::edge::throttle::EdgeRateLimit;
 
+        let limit = EdgeRateLimit::min_interval(Duration::from_millis(50));
         let graph = GraphBuilder::new()
-            .add_node("node1".to_string(), node1).unwrap()
-            .add_node("node2".to_string(), node2).unwrap()
+            .add_node("poll".to_string(), IncrementNode { amount: 1 }).unwrap()
+            .add_node("done".to_string(), IncrementNode { amount: 0 }).unwrap()
+            .with_entry_point("poll".to_string()).unwrap()
+            .add_finish_point("done".to_string()).unwrap()
+            .add_edge(Edge::simple("poll", "done").with_name("poll_loop").with_rate_limit(limit)).unwrap()
+            .build().unwrap();
+
+        let mut engine = GraphEngine::new();
+        let mut state = TestState { value: 0 };
+
+        let first = engine.execute(&graph, &mut state).await.unwrap();
+        assert!(first.edge_waits.is_empty());
+
+        // The throttle lives on the graph, so a second run is spaced from the first
+        let second = engine.execute(&graph, &mut state).await.unwrap();
+        assert_eq!(second.edge_waits.len(), 1);
+        assert_eq!(second.edge_waits[0].edge, "poll_loop");
+        assert_eq!(second.edge_waits[0].step, 1);
+        assert!(second.edge_waits[0].waited > Duration::ZERO);
+    }
+
+    #[cfg(feature = "checkpointing")]
+    #[tokio::test]
+    async fn test_fork_from_checkpoint() {
+        use crate::graph::ExecutionConfig;
+        use crate::state::checkpointing::MemoryCheckpointer;
+
+        let config = ExecutionConfig {
+            enable_checkpointing: true,
+            checkpoint_interval: Some(1),
+            ..Default::default()
+        };
+        let mut graph = GraphBuilder::new()
+            .with_config(config)
+            .add_node("node1".to_string(), IncrementNode { amount: 5 }).unwrap()
+            .add_node("node2".to_string(), IncrementNode { amount: 3 }).unwrap()
             .with_entry_point("node1".to_string()).unwrap()
             .add_finish_point("node2".to_string()).unwrap()
             .add_edge(Edge::simple("node1", "node2")).unwrap()
             .build().unwrap();
+        graph.set_checkpointer(MemoryCheckpointer::new());
 
         let mut engine = GraphEngine::new();
         let mut state = TestState { value: 0 };
-
         let context = engine.execute(&graph, &mut state).await.unwrap();
-        
-        assert_eq!(state.value, 8); // 0 + 5 + 3
-        assert_eq!(context.current_step, 2);
-        assert_eq!(context.execution_path.len(), 2);
+        assert_eq!(context.checkpoint_ids.len(), 2);
+
+        // Fork after node1, where the state is 5
+        let midpoint = context.checkpoint_ids[0];
+        let branches = vec![
+            ForkBranch::new("baseline"),
+            ForkBranch::new("boosted").with_edit(|state: &mut TestState| {
+                state.value *= 10;
+                Ok(())
+            }),
+        ];
+        let outcomes = engine.fork(&graph, midpoint, branches).await.unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|outcome| outcome.is_success()));
+        assert_eq!(outcomes[0].state.value, 8);
+        assert_eq!(outcomes[1].state.value, 53);
+
+        let lineage = outcomes[1].context.lineage.as_ref().unwrap();
+        assert_eq!(lineage.parent_execution_id, context.execution_id);
+        assert_eq!(lineage.checkpoint_id, midpoint);
+        assert_eq!(lineage.branch, "boosted");
+        assert_eq!(outcomes[1].context.execution_path, vec!["node2".to_string()]);
+        assert_ne!(outcomes[0].context.execution_id, outcomes[1].context.execution_id);
+    }
+
+    #[tokio::test]
+    async fn test_live_control_pause_cancel_and_retry() {
+        use crate::graph::control::{ExecutionControls, LiveStatus, RetryRequest};
+
+        let failing = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let mut graph = GraphBuilder::new()
+            .add_node("slow".to_string(), SleepNode { duration: Duration::from_millis(50), timeout: None }).unwrap()
+            .add_node("flaky".to_string(), FlakyNode { failing: failing.clone() }).unwrap()
+            .with_entry_point("slow".to_string()).unwrap()
+            .add_finish_point("flaky".to_string()).unwrap()
+            .add_edge(Edge::simple("slow", "flaky")).unwrap()
+            .build().unwrap();
+        let controls = ExecutionControls::new();
+        graph.set_controls(controls.clone());
+        let graph = Arc::new(graph);
+        controls.serve_retries(graph.clone(), GraphEngine::new());
+
+        let run = |graph: Arc<Graph<TestState>>| tokio::spawn(async move {
+            let mut state = TestState { value: 1 };
+            GraphEngine::new().execute(&graph, &mut state).await.map(|_| state)
+        });
+        let started = |controls: &ExecutionControls| {
+            let controls = controls.clone();
+            async move {
+                loop {
+                    if let Some(live) = controls.list().pop() {
+                        return live.execution_id;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            }
+        };
+
+        // Paused after the slow node, then resumed
+        let handle = run(graph.clone());
+        let id = started(&controls).await;
+        assert_eq!(controls.pause(id).unwrap().status, LiveStatus::Pausing);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let live = controls.get(id).unwrap();
+        assert_eq!(live.status, LiveStatus::Paused);
+        assert_eq!(live.current_node.as_deref(), Some("slow"));
+        controls.resume(id).unwrap();
+        assert_eq!(handle.await.unwrap().unwrap().value, 20);
+        assert!(controls.list().is_empty());
+
+        // Cancelled after the slow node
+        let handle = run(graph.clone());
+        let id = started(&controls).await;
+        controls.cancel(id).unwrap();
+        assert!(controls.pause(id).is_err());
+        assert!(handle.await.unwrap().unwrap_err().is_cancelled());
+        assert!(controls.failed().is_empty());
+
+        // Failed at the flaky node, then retried there with an edited state
+        failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        let handle = run(graph.clone());
+        assert!(handle.await.unwrap().is_err());
+        let failed = controls.failed().remove(0);
+        assert_eq!(failed.node_id.as_deref(), Some("flaky"));
+        assert_eq!(failed.state, serde_json::json!({ "value": 2 }));
+
+        failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        let request = RetryRequest { node_id: None, state: Some(serde_json::json!({ "value": 7 })) };
+        let retry_id = controls.retry(failed.execution_id, request).unwrap();
+        assert_ne!(retry_id, failed.execution_id);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(controls.get(retry_id).is_none());
+        assert!(controls.failed().is_empty());
+        assert!(controls.retry(failed.execution_id, RetryRequest::default()).is_err());
+    }
+
+    #[cfg(feature = "checkpointing")]
+    #[tokio::test]
+    async fn test_drain_on_shutdown_and_resume_on_startup() {
+        use crate::graph::control::{ExecutionControls, DRAINED_TAG};
+        use crate::graph::ExecutionConfig;
+        use crate::state::checkpointing::MemoryCheckpointer;
+
+        let mut graph = GraphBuilder::new()
+            .with_config(ExecutionConfig { enable_checkpointing: true, ..Default::default() })
+            .add_node("slow".to_string(), SleepNode { duration: Duration::from_millis(50), timeout: None }).unwrap()
+            .add_node("bump".to_string(), IncrementNode { amount: 3 }).unwrap()
+            .with_entry_point("slow".to_string()).unwrap()
+            .add_finish_point("bump".to_string()).unwrap()
+            .add_edge(Edge::simple("slow", "bump")).unwrap()
+            .build().unwrap();
+        graph.set_checkpointer(MemoryCheckpointer::new());
+        let controls = ExecutionControls::new().with_instance_id("worker-0").with_drain_lease(Duration::from_secs(3600));
+        graph.set_controls(controls.clone());
+        let graph = Arc::new(graph);
+
+        // Drained after the slow node, suspending before bump
+        let handle = tokio::spawn({
+            let graph = graph.clone();
+            async move { GraphEngine::new().execute(&graph, &mut TestState { value: 1 }).await }
+        });
+        let id = loop {
+            if let Some(live) = controls.list().pop() {
+                break live.execution_id;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(controls.shutdown(Duration::from_secs(2)).await, 0);
+        let error = handle.await.unwrap().unwrap_err();
+        let GraphError::Suspended { node_id, checkpoint_id: Some(checkpoint_id), .. } = error else {
+            panic!("expected a checkpointed suspension, got {}", error);
+        };
+        assert_eq!(node_id, "bump");
+        let checkpoint_id = uuid::Uuid::parse_str(&checkpoint_id).unwrap();
+        let checkpointer = graph.checkpointer.as_ref().unwrap();
+        let metadata = checkpointer.get_metadata(checkpoint_id).await.unwrap();
+        assert_eq!(metadata.tags, vec![DRAINED_TAG.to_string()]);
+        assert_eq!(metadata.custom["owner"], serde_json::json!("worker-0"));
+
+        // After the deploy: another instance waits out the lease, the same one resumes at once
+        let mut graph = Arc::try_unwrap(graph).ok().unwrap();
+        let restarted = ExecutionControls::new().with_instance_id("worker-0");
+        graph.set_controls(restarted.clone());
+        let graph = Arc::new(graph);
+        let other = ExecutionControls::new().with_instance_id("worker-1");
+        let engine = GraphEngine::new();
+        assert!(other.resume_drained(graph.clone(), &engine).await.unwrap().is_empty());
+        assert_eq!(restarted.resume_drained(graph.clone(), &engine).await.unwrap(), vec![id]);
+        // Kept until the resumed run finishes, but not resumed twice
+        assert!(graph.checkpointer.as_ref().unwrap().exists(checkpoint_id).await.unwrap());
+        assert!(restarted.resume_drained(graph.clone(), &engine).await.unwrap().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(restarted.list().is_empty());
+        let checkpointer = graph.checkpointer.as_ref().unwrap();
+        assert!(!checkpointer.exists(checkpoint_id).await.unwrap());
+        let mut finished = false;
+        for snapshot_id in checkpointer.list_snapshots().await.unwrap() {
+            let snapshot = checkpointer.load(snapshot_id).await.unwrap();
+            finished |= snapshot.metadata.current_node.as_deref() == Some("bump") && snapshot.state.value == 5;
+        }
+        assert!(finished);
+    }
+
+    #[tokio::test]
+    async fn test_leased_execution_runs_recorded_nodes_once() {
+        use crate::state::lease::{ExecutionLeases, LeaseStore, MemoryLeaseStore, NodeCompletion};
+
+        let store: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::new());
+        let mut graph = GraphBuilder::new()
+            .add_node("node1".to_string(), IncrementNode { amount: 5 }).unwrap()
+            .add_node("node2".to_string(), IncrementNode { amount: 3 }).unwrap()
+            .with_entry_point("node1".to_string()).unwrap()
+            .add_finish_point("node2".to_string()).unwrap()
+            .add_edge(Edge::simple("node1", "node2")).unwrap()
+            .build().unwrap();
+        graph.set_leases(ExecutionLeases::shared(store.clone()).with_owner("worker-1"));
+        let context = ExecutionContext::new();
+        let id = context.execution_id;
+
+        // worker-0 completed node1, then failed over while still holding the lease
+        store.complete(NodeCompletion {
+            execution_id: id,
+            step: 1,
+            node_id: "node1".to_string(),
+            owner: "worker-0".to_string(),
+            state: serde_json::json!({ "value": 100 }),
+            completed_at: chrono::Utc::now(),
+        }).await.unwrap();
+        store.acquire(id, "worker-0", Duration::from_secs(60)).await.unwrap();
+
+        let mut engine = GraphEngine::new();
+        let error = engine
+            .execute_from_node_in(&graph, &mut TestState { value: 0 }, context.clone(), "node1".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(error.category(), "concurrency");
+        assert_eq!(store.lease(id).await.unwrap().unwrap().owner, "worker-0");
+
+        // Once the lease is free, node1 is not run again
+        store.release(id, "worker-0").await.unwrap();
+        let mut state = TestState { value: 0 };
+        engine.execute_from_node_in(&graph, &mut state, context, "node1".to_string()).await.unwrap();
+        assert_eq!(state.value, 103);
+        assert!(store.completion(id, 1, "node1").await.unwrap().is_none());
+        assert!(store.lease(id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_leased_fan_out_runs_recorded_nodes_once() {
+        use crate::state::lease::{ExecutionLeases, LeaseStore, MemoryLeaseStore, NodeCompletion};
+
+        let store: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::new());
+        let mut graph = GraphBuilder::new()
+            .add_node("start".to_string(), FieldNode { field: "none", delay_ms: 0 }).unwrap()
+            .add_node("left".to_string(), FieldNode { field: "left", delay_ms: 0 }).unwrap()
+            .add_node("right".to_string(), FieldNode { field: "right", delay_ms: 0 }).unwrap()
+            .with_entry_point("start".to_string()).unwrap()
+            .add_edge(Edge::parallel("start", vec!["left".to_string(), "right".to_string()])).unwrap()
+            .add_finish_point("left".to_string()).unwrap()
+            .add_finish_point("right".to_string()).unwrap()
+            .build().unwrap();
+        graph.set_leases(ExecutionLeases::shared(store.clone()).with_owner("worker-1"));
+
+        for enable_parallel in [true, false] {
+            let mut config = graph.config().clone();
+            config.enable_parallel = enable_parallel;
+            graph.set_config(config);
+            let context = ExecutionContext::new();
+
+            // worker-0 completed the left branch before failing over
+            store.complete(NodeCompletion {
+                execution_id: context.execution_id,
+                step: 1,
+                node_id: "left".to_string(),
+                owner: "worker-0".to_string(),
+                state: serde_json::json!({ "left": 5, "right": 0, "total": 0 }),
+                completed_at: chrono::Utc::now(),
+            }).await.unwrap();
+
+            let mut state = PairState::default();
+            GraphEngine::new().execute_from_node_in(&graph, &mut state, context, "start".to_string()).await.unwrap();
+            assert_eq!(state, PairState { left: 5, right: 2, total: 0 });
+        }
+    }
+
+    /// State backed by a map, so nodes can report through reserved keys
+    #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+    struct MapState {
+        values: std::collections::BTreeMap<String, serde_json::Value>,
+    }
+
+    impl State for MapState {
+        fn get_value(&self, key: &str) -> Option<serde_json::Value> {
+            self.values.get(key).cloned()
+        }
+
+        fn set_value(&mut self, key: &str, value: serde_json::Value) -> GraphResult<()> {
+            self.values.insert(key.to_string(), value);
+            Ok(())
+        }
+
+        fn remove_value(&mut self, key: &str) -> GraphResult<Option<serde_json::Value>> {
+            Ok(self.values.remove(key))
+        }
+    }
+
+    /// Reports a completion of `tokens` tokens, as an LLM node does
+    #[derive(Debug)]
+    struct UsageNode {
+        tokens: u64,
+    }
+
+    #[async_trait]
+    impl Node<MapState> for UsageNode {
+        async fn invoke(&self, _state: &mut MapState) -> GraphResult<()> {
+            let usage = crate::llm::LLMUsage { calls: 1, total_tokens: self.tokens, cost: 0.01, ..Default::default() };
+            crate::graph::agent_node::report_llm_usage(&usage);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_llm_usage_is_summed_per_node() {
+        let graph = GraphBuilder::new()
+            .add_node("draft".to_string(), UsageNode { tokens: 120 }).unwrap()
+            .add_node("review".to_string(), UsageNode { tokens: 80 }).unwrap()
+            .with_entry_point("draft".to_string()).unwrap()
+            .add_finish_point("review".to_string()).unwrap()
+            .add_edge(Edge::simple("draft", "review")).unwrap()
+            .build().unwrap();
+
+        let mut state = MapState::default();
+        let context = GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+        assert!(state.values.is_empty());
+
+        let nodes: Vec<&str> = context.llm_usage.iter().map(|(node_id, _)| node_id.as_str()).collect();
+        assert_eq!(nodes, ["draft", "review"]);
+        let total = context.total_llm_usage();
+        assert_eq!(total.calls, 2);
+        assert_eq!(total.total_tokens, 200);
+        assert!((total.cost - 0.02).abs() < 1e-9);
+    }
+
+    /// Reports the answers it chose from, as a debate node does
+    #[derive(Debug)]
+    struct VoteNode;
+
+    #[async_trait]
+    impl Node<MapState> for VoteNode {
+        async fn invoke(&self, state: &mut MapState) -> GraphResult<()> {
+            crate::graph::report::candidates(serde_json::json!([
+                { "source": "a", "output": "42", "selected": true },
+                { "source": "b", "output": "41", "selected": false },
+            ]));
+            state.set_value("answer", serde_json::json!("42"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_candidates_are_kept_out_of_the_state() {
+        let graph = GraphBuilder::new()
+            .add_node("vote".to_string(), VoteNode).unwrap()
+            .with_entry_point("vote".to_string()).unwrap()
+            .add_finish_point("vote".to_string()).unwrap()
+            .build().unwrap();
+
+        let mut state = MapState::default();
+        let context = GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+        assert_eq!(state.get_value("answer"), Some(serde_json::json!("42")));
+        assert_eq!(state.values.len(), 1);
+
+        assert_eq!(context.candidates.len(), 1);
+        let (node_id, candidates) = &context.candidates[0];
+        assert_eq!(node_id, "vote");
+        assert_eq!(candidates[1]["source"], "b");
+    }
+
+    #[derive(Debug)]
+    struct AlwaysCondition;
+
+    #[async_trait]
+    impl crate::edge::EdgeCondition<MapState> for AlwaysCondition {
+        async fn evaluate(&self, _state: &MapState) -> GraphResult<bool> {
+            Ok(true)
+        }
+
+        fn condition_id(&self) -> String {
+            "always".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_speculated_outbox_message_performed_once() {
+        use crate::edge::speculation::{BranchCounts, SpeculationConfig};
+        use crate::graph::outbox::{Outbox, OutboxMessage, OutboxNode};
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let mut graph = GraphBuilder::new()
+            .add_node("vote".to_string(), VoteNode).unwrap()
+            .add_node("notify".to_string(), OutboxNode::new("email", "answer")).unwrap()
+            .add_node("skip".to_string(), VoteNode).unwrap()
+            .with_entry_point("vote".to_string()).unwrap()
+            .add_finish_point("notify".to_string()).unwrap()
+            .add_finish_point("skip".to_string()).unwrap()
+            .add_edge(
+                Edge::conditional("vote", "always".to_string(), "notify", "skip")
+                    .with_name("send")
+                    .with_speculation(SpeculationConfig { min_samples: 1, min_probability: 0.5 }),
+            ).unwrap()
+            .build().unwrap();
+        graph.edge_registry_mut().register_condition(AlwaysCondition);
+        graph.branch_statistics().load(
+            [("send".to_string(), BranchCounts { true_count: 9, false_count: 1 })].into(),
+        );
+        let calls = Arc::new(AtomicU32::new(0));
+        let sent = calls.clone();
+        graph.set_outbox(Outbox::new().with_handler("email", move |message: OutboxMessage| {
+            let sent = sent.clone();
+            async move {
+                sent.fetch_add(1, Ordering::SeqCst);
+                Ok(serde_json::json!({ "message_id": message.id }))
+            }
+        }));
+
+        let mut state = MapState::default();
+        let context = GraphEngine::new().execute(&graph, &mut state).await.unwrap();
+        assert_eq!(context.execution_path, vec!["vote".to_string(), "notify".to_string()]);
+        // The prediction was right, but the queued email is left to the real step
+        assert_eq!(context.speculations[0].actual, "notify");
+        assert!(!context.speculations[0].hit);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let outbox = OutboxState::from_state(&state).unwrap();
+        assert!(outbox.messages.is_empty());
+        assert_eq!(outbox.enqueued, 1);
     }
 }
@@ -21,6 +21,17 @@ where
         engine.execute(self, state).await
     }
 
+    /// Resume an execution that suspended at `node_id` with the awaited operation's output
+    pub async fn resume(
+        &self,
+        state: &mut S,
+        node_id: impl Into<String>,
+        output: serde_json::Value,
+    ) -> GraphResult<ExecutionContext> {
+        let mut engine = GraphEngine::new();
+        engine.resume(self, state, node_id.into(), output).await
+    }
+
     /// Execute the graph and return both the final state and execution context
     pub async fn run_with_context(&self, mut state: S) -> GraphResult<(S, ExecutionContext)> {
         let context = self.run(&mut state).await?;
@@ -0,0 +1,733 @@
+//! Feature flags that toggle graph behavior at runtime, per tenant or user.
+//!
+//! A [`Flags`] set asks its [`FlagProvider`]s in order: environment variables,
+//! a flags file that is re-read when it changes, LaunchDarkly, Unleash, or
+//! anything else implementing the trait. Conditions branch on a flag with
+//! [`FlagCondition`]; nodes of a graph given flags with
+//! [`Graph::set_flags`](crate::graph::Graph::set_flags) read them through
+//! [`current`] while they run.
+
+use crate::edge::EdgeCondition;
+use crate::error::{GraphError, GraphResult};
+use crate::state::State;
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// State key holding the tenant flags are evaluated for
+pub const TENANT_STATE_KEY: &str = "tenant_id";
+/// State key holding the user flags are evaluated for
+pub const USER_STATE_KEY: &str = "user_id";
+
+/// Who a flag is evaluated for
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagContext {
+    /// Tenant ID
+    pub tenant: Option<String>,
+    /// User ID
+    pub user: Option<String>,
+    /// Further attributes for providers that target on them
+    pub attributes: HashMap<String, Value>,
+}
+
+impl FlagContext {
+    /// A context with no tenant or user
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluate for `tenant`
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// Evaluate for `user`
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Add an attribute
+    pub fn with_attribute(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.attributes.insert(name.into(), value);
+        self
+    }
+
+    /// Tenant and user from the state's `tenant_id` and `user_id`
+    ///
+    /// Either one missing from the state is taken from the execution's
+    /// [context variables](crate::graph::context_vars).
+    pub fn from_state<S: State>(state: &S) -> Self {
+        let vars = crate::graph::context_vars::current();
+        let text = |key| state.get_value(key).and_then(|value| match value {
+            Value::String(s) => Some(s),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        });
+        Self {
+            tenant: text(TENANT_STATE_KEY).or_else(|| vars.tenant_id()),
+            user: text(USER_STATE_KEY).or_else(|| vars.user_id()),
+            attributes: HashMap::new(),
+        }
+    }
+}
+
+/// Source of flag values
+#[async_trait]
+pub trait FlagProvider: Send + Sync + std::fmt::Debug {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Value of `flag` for `context`, or `None` if this provider does not know the flag
+    async fn value(&self, flag: &str, context: &FlagContext) -> GraphResult<Option<Value>>;
+}
+
+/// Whether a flag value turns a feature on: `true`, or any value other than `false` and null
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(enabled) => *enabled,
+        Value::Null => false,
+        _ => true,
+    }
+}
+
+/// A flag's value, with overrides for some tenants and users
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlagRule {
+    /// Value for everyone not overridden
+    pub value: Value,
+    /// Values by tenant ID
+    pub tenants: HashMap<String, Value>,
+    /// Values by user ID, taking precedence over tenants
+    pub users: HashMap<String, Value>,
+}
+
+impl FlagRule {
+    /// A rule giving everyone `value`
+    pub fn new(value: Value) -> Self {
+        Self { value, ..Default::default() }
+    }
+
+    /// Give `tenant` its own value
+    pub fn with_tenant(mut self, tenant: impl Into<String>, value: Value) -> Self {
+        self.tenants.insert(tenant.into(), value);
+        self
+    }
+
+    /// Give `user` its own value
+    pub fn with_user(mut self, user: impl Into<String>, value: Value) -> Self {
+        self.users.insert(user.into(), value);
+        self
+    }
+
+    /// Value for `context`
+    pub fn resolve(&self, context: &FlagContext) -> Value {
+        context.user.as_ref().and_then(|user| self.users.get(user))
+            .or_else(|| context.tenant.as_ref().and_then(|tenant| self.tenants.get(tenant)))
+            .unwrap_or(&self.value)
+            .clone()
+    }
+}
+
+/// Flags held in memory and changed through code, e.g. from an admin endpoint
+#[derive(Debug, Default)]
+pub struct StaticFlagProvider {
+    rules: RwLock<HashMap<String, FlagRule>>,
+}
+
+impl StaticFlagProvider {
+    /// Create a provider with no flags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a flag
+    pub fn with_flag(self, flag: impl Into<String>, rule: FlagRule) -> Self {
+        self.set(flag, rule);
+        self
+    }
+
+    /// Add or replace a flag
+    pub fn set(&self, flag: impl Into<String>, rule: FlagRule) {
+        self.rules.write().insert(flag.into(), rule);
+    }
+
+    /// Remove a flag
+    pub fn remove(&self, flag: &str) -> Option<FlagRule> {
+        self.rules.write().remove(flag)
+    }
+}
+
+#[async_trait]
+impl FlagProvider for StaticFlagProvider {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    async fn value(&self, flag: &str, context: &FlagContext) -> GraphResult<Option<Value>> {
+        Ok(self.rules.read().get(flag).map(|rule| rule.resolve(context)))
+    }
+}
+
+/// Flags from environment variables, read on every evaluation
+///
+/// Flag `new-router` is read from `AGENTGRAPH_FLAG_NEW_ROUTER`, overridden
+/// for tenant `acme` by `AGENTGRAPH_FLAG_NEW_ROUTER__TENANT_ACME` and for
+/// user `u1` by `AGENTGRAPH_FLAG_NEW_ROUTER__USER_U1`. Values are parsed as
+/// JSON, falling back to plain strings.
+#[derive(Debug, Clone)]
+pub struct EnvFlagProvider {
+    prefix: String,
+}
+
+impl Default for EnvFlagProvider {
+    fn default() -> Self {
+        Self::new("AGENTGRAPH_FLAG_")
+    }
+}
+
+impl EnvFlagProvider {
+    /// Read variables starting with `prefix`
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self { prefix: prefix.into() }
+    }
+
+    fn variable(&self, parts: &[&str]) -> String {
+        let name: String = parts.join("__")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+        format!("{}{}", self.prefix, name)
+    }
+}
+
+#[async_trait]
+impl FlagProvider for EnvFlagProvider {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    async fn value(&self, flag: &str, context: &FlagContext) -> GraphResult<Option<Value>> {
+        let mut candidates = Vec::new();
+        if let Some(user) = &context.user {
+            candidates.push(self.variable(&[flag, &format!("user_{}", user)]));
+        }
+        if let Some(tenant) = &context.tenant {
+            candidates.push(self.variable(&[flag, &format!("tenant_{}", tenant)]));
+        }
+        candidates.push(self.variable(&[flag]));
+
+        Ok(candidates.iter().find_map(|name| std::env::var(name).ok()).map(|raw| {
+            serde_json::from_str(raw.trim()).unwrap_or(Value::String(raw))
+        }))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FlagFile {
+    flags: HashMap<String, FlagRule>,
+}
+
+/// Flags from a TOML or JSON file, re-read whenever it changes
+///
+/// ```toml
+/// [flags.new-router]
+/// value = false
+/// tenants = { acme = true }
+/// ```
+#[derive(Debug)]
+pub struct FileFlagProvider {
+    path: PathBuf,
+    loaded: Mutex<(Option<SystemTime>, HashMap<String, FlagRule>)>,
+}
+
+impl FileFlagProvider {
+    /// Load flags from `path`, as JSON if it ends in `.json` and TOML otherwise
+    pub fn new(path: impl Into<PathBuf>) -> GraphResult<Self> {
+        let provider = Self {
+            path: path.into(),
+            loaded: Mutex::new((None, HashMap::new())),
+        };
+        provider.reload()?;
+        Ok(provider)
+    }
+
+    /// Re-read the file if it changed since it was last read
+    pub fn reload(&self) -> GraphResult<()> {
+        let modified = std::fs::metadata(&self.path)?.modified().ok();
+        if modified.is_some() && self.loaded.lock().0 == modified {
+            return Ok(());
+        }
+        let rules = Self::parse(&self.path)?;
+        *self.loaded.lock() = (modified, rules);
+        Ok(())
+    }
+
+    fn parse(path: &Path) -> GraphResult<HashMap<String, FlagRule>> {
+        let text = std::fs::read_to_string(path)?;
+        let file: FlagFile = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)
+                .map_err(|e| GraphError::ConfigurationError(format!("Invalid flags file {}: {}", path.display(), e)))?
+        } else {
+            toml::from_str(&text)
+                .map_err(|e| GraphError::ConfigurationError(format!("Invalid flags file {}: {}", path.display(), e)))?
+        };
+        Ok(file.flags)
+    }
+}
+
+#[async_trait]
+impl FlagProvider for FileFlagProvider {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn value(&self, flag: &str, context: &FlagContext) -> GraphResult<Option<Value>> {
+        // Keep serving the last good flags while the file is being rewritten
+        if let Err(error) = self.reload() {
+            tracing::warn!(path = %self.path.display(), error = %error, "Failed to reload flags file");
+        }
+        Ok(self.loaded.lock().1.get(flag).map(|rule| rule.resolve(context)))
+    }
+}
+
+/// Flag values fetched per context and kept for a while
+#[derive(Debug)]
+struct FlagCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, (Instant, Arc<HashMap<String, Value>>)>>,
+}
+
+/// Tenant, user and a hash of the other attributes a context's flags were evaluated for
+type CacheKey = (Option<String>, Option<String>, String);
+
+impl FlagCache {
+    fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// Contexts differing in any attribute may target differently, so they are cached apart
+    fn key(context: &FlagContext) -> CacheKey {
+        // Sorted, so the same attributes always hash the same
+        let attributes: std::collections::BTreeMap<_, _> = context.attributes.iter().collect();
+        let attributes = serde_json::to_string(&attributes).unwrap_or_default();
+        (context.tenant.clone(), context.user.clone(), crate::sha256_hex(attributes))
+    }
+
+    fn get(&self, context: &FlagContext) -> Option<Arc<HashMap<String, Value>>> {
+        let entries = self.entries.lock();
+        entries.get(&Self::key(context))
+            .filter(|(fetched, _)| fetched.elapsed() < self.ttl)
+            .map(|(_, values)| values.clone())
+    }
+
+    fn put(&self, context: &FlagContext, values: HashMap<String, Value>) -> Arc<HashMap<String, Value>> {
+        let values = Arc::new(values);
+        let mut entries = self.entries.lock();
+        entries.retain(|_, (fetched, _)| fetched.elapsed() < self.ttl);
+        entries.insert(Self::key(context), (Instant::now(), values.clone()));
+        values
+    }
+}
+
+fn service_error(service: &str, error: impl std::fmt::Display) -> GraphError {
+    GraphError::ExternalServiceError(format!("{} flag evaluation failed: {}", service, error))
+}
+
+/// Flags evaluated by LaunchDarkly
+///
+/// Uses the client-side evaluation endpoint, so flags must be made available
+/// to client-side SDKs. The tenant is sent as a `tenant` context kind next to
+/// the `user` kind, and each context's flags are cached for `ttl`.
+#[derive(Debug)]
+pub struct LaunchDarklyFlagProvider {
+    client: reqwest::Client,
+    base_url: String,
+    client_side_id: String,
+    cache: FlagCache,
+}
+
+impl LaunchDarklyFlagProvider {
+    /// Evaluate flags of the environment with `client_side_id`
+    pub fn new(client_side_id: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: "https://clientsdk.launchdarkly.com".to_string(),
+            client_side_id: client_side_id.into(),
+            cache: FlagCache::new(Duration::from_secs(30)),
+        }
+    }
+
+    /// Evaluate through a Relay Proxy or another base URL
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Keep each context's flags for `ttl`
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache = FlagCache::new(ttl);
+        self
+    }
+
+    fn ld_context(context: &FlagContext) -> Value {
+        let mut kinds = serde_json::Map::new();
+        kinds.insert("kind".to_string(), Value::String("multi".to_string()));
+        let user = context.user.clone().unwrap_or_else(|| "anonymous".to_string());
+        let mut user_context = serde_json::json!({ "key": user, "anonymous": context.user.is_none() });
+        for (name, value) in &context.attributes {
+            user_context[name] = value.clone();
+        }
+        kinds.insert("user".to_string(), user_context);
+        if let Some(tenant) = &context.tenant {
+            kinds.insert("tenant".to_string(), serde_json::json!({ "key": tenant }));
+        }
+        Value::Object(kinds)
+    }
+
+    async fn fetch(&self, context: &FlagContext) -> GraphResult<HashMap<String, Value>> {
+        use base64::Engine;
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Self::ld_context(context).to_string());
+        let url = format!("{}/sdk/evalx/{}/contexts/{}", self.base_url, self.client_side_id, encoded);
+        let response = self.client.get(&url).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| service_error("LaunchDarkly", e))?;
+        let flags: HashMap<String, Value> = response.json().await.map_err(|e| service_error("LaunchDarkly", e))?;
+        Ok(flags.into_iter()
+            .map(|(flag, evaluation)| (flag, evaluation.get("value").cloned().unwrap_or(Value::Null)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl FlagProvider for LaunchDarklyFlagProvider {
+    fn name(&self) -> &str {
+        "launchdarkly"
+    }
+
+    async fn value(&self, flag: &str, context: &FlagContext) -> GraphResult<Option<Value>> {
+        let flags = match self.cache.get(context) {
+            Some(flags) => flags,
+            None => self.cache.put(context, self.fetch(context).await?),
+        };
+        Ok(flags.get(flag).cloned())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UnleashToggles {
+    toggles: Vec<UnleashToggle>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnleashToggle {
+    name: String,
+    enabled: bool,
+    #[serde(default)]
+    variant: Option<UnleashVariant>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnleashVariant {
+    name: String,
+    enabled: bool,
+    #[serde(default)]
+    payload: Option<UnleashPayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnleashPayload {
+    value: String,
+}
+
+/// Flags evaluated by Unleash through its frontend API or Unleash Edge
+///
+/// A toggle's value is `true` or `false`, or the variant's payload (its name
+/// without one) when a variant is enabled. Unleash only lists enabled
+/// toggles, so toggles it does not list are `false` rather than unknown.
+/// The tenant is sent as the `tenantId` property.
+#[derive(Debug)]
+pub struct UnleashFlagProvider {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+    cache: FlagCache,
+}
+
+impl UnleashFlagProvider {
+    /// Evaluate through the frontend API at `url`, e.g. `https://unleash.example.com/api/frontend`
+    pub fn new(url: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            token: token.into(),
+            cache: FlagCache::new(Duration::from_secs(30)),
+        }
+    }
+
+    /// Keep each context's toggles for `ttl`
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache = FlagCache::new(ttl);
+        self
+    }
+
+    async fn fetch(&self, context: &FlagContext) -> GraphResult<HashMap<String, Value>> {
+        let mut query = Vec::new();
+        if let Some(user) = &context.user {
+            query.push(("userId".to_string(), user.clone()));
+        }
+        if let Some(tenant) = &context.tenant {
+            query.push(("properties[tenantId]".to_string(), tenant.clone()));
+        }
+        for (name, value) in &context.attributes {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            query.push((format!("properties[{}]", name), value));
+        }
+        let response = self.client.get(&self.url)
+            .header("Authorization", &self.token)
+            .query(&query)
+            .send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| service_error("Unleash", e))?;
+        let toggles: UnleashToggles = response.json().await.map_err(|e| service_error("Unleash", e))?;
+        Ok(toggles.toggles.into_iter()
+            .map(|toggle| {
+                let value = match toggle.variant {
+                    Some(variant) if toggle.enabled && variant.enabled => match variant.payload {
+                        Some(payload) => serde_json::from_str(&payload.value).unwrap_or(Value::String(payload.value)),
+                        None => Value::String(variant.name),
+                    },
+                    _ => Value::Bool(toggle.enabled),
+                };
+                (toggle.name, value)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl FlagProvider for UnleashFlagProvider {
+    fn name(&self) -> &str {
+        "unleash"
+    }
+
+    async fn value(&self, flag: &str, context: &FlagContext) -> GraphResult<Option<Value>> {
+        let toggles = match self.cache.get(context) {
+            Some(toggles) => toggles,
+            None => self.cache.put(context, self.fetch(context).await?),
+        };
+        Ok(Some(toggles.get(flag).cloned().unwrap_or(Value::Bool(false))))
+    }
+}
+
+/// Providers asked in order; the first that knows a flag decides it
+///
+/// A provider that fails is logged and skipped, so an unreachable flag
+/// service falls back to later providers and then to the caller's default
+/// instead of failing executions.
+#[derive(Debug, Clone, Default)]
+pub struct Flags {
+    providers: Vec<Arc<dyn FlagProvider>>,
+}
+
+impl Flags {
+    /// Create a set with no providers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask `provider` after the providers added before it
+    pub fn with_provider<P: FlagProvider + 'static>(mut self, provider: P) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    /// Ask a shared provider after the providers added before it
+    pub fn with_shared_provider(mut self, provider: Arc<dyn FlagProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// Value of `flag` for `context`, if any provider knows it
+    pub async fn value(&self, flag: &str, context: &FlagContext) -> Option<Value> {
+        for provider in &self.providers {
+            match provider.value(flag, context).await {
+                Ok(Some(value)) => return Some(value),
+                Ok(None) => {}
+                Err(error) => {
+                    tracing::warn!(flag = %flag, provider = %provider.name(), error = %error, "Flag provider failed");
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether `flag` is on for `context`, `default` if no provider knows it
+    pub async fn is_enabled(&self, flag: &str, context: &FlagContext, default: bool) -> bool {
+        self.value(flag, context).await.map_or(default, |value| is_truthy(&value))
+    }
+}
+
+tokio::task_local! {
+    static FLAGS: Flags;
+}
+
+/// Flags of the graph whose node the current task is running, if it has any
+pub fn current() -> Option<Flags> {
+    FLAGS.try_with(Flags::clone).ok()
+}
+
+/// Run `future` with `flags` as the [`current`] flags
+pub(crate) async fn scope<F: Future>(flags: Option<&Flags>, future: F) -> F::Output {
+    match flags {
+        Some(flags) => FLAGS.scope(flags.clone(), future).await,
+        None => future.await,
+    }
+}
+
+/// Carry the current flags into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = FLAGS.try_with(Flags::clone).ok();
+    async move {
+        match value {
+            Some(value) => FLAGS.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Edge condition on a feature flag, evaluated for the state's tenant and user
+#[derive(Debug, Clone)]
+pub struct FlagCondition {
+    flags: Flags,
+    flag: String,
+    expected: Option<Value>,
+    default: bool,
+}
+
+impl FlagCondition {
+    /// True while `flag` is on; false if no provider knows it
+    pub fn enabled(flags: Flags, flag: impl Into<String>) -> Self {
+        Self { flags, flag: flag.into(), expected: None, default: false }
+    }
+
+    /// True while `flag` has `value`, e.g. a variant name
+    pub fn equals(flags: Flags, flag: impl Into<String>, value: Value) -> Self {
+        Self { flags, flag: flag.into(), expected: Some(value), default: false }
+    }
+
+    /// Hold when no provider knows the flag
+    pub fn default_on(mut self) -> Self {
+        self.default = true;
+        self
+    }
+}
+
+#[async_trait]
+impl<S: State> EdgeCondition<S> for FlagCondition {
+    async fn evaluate(&self, state: &S) -> GraphResult<bool> {
+        let context = FlagContext::from_state(state);
+        Ok(match self.flags.value(&self.flag, &context).await {
+            None => self.default,
+            Some(value) => match &self.expected {
+                Some(expected) => &value == expected,
+                None => is_truthy(&value),
+            },
+        })
+    }
+
+    fn condition_id(&self) -> String {
+        match &self.expected {
+            Some(expected) => format!("flag:{}={}", self.flag, expected),
+            None => format!("flag:{}", self.flag),
+        }
+    }
+
+    fn description(&self) -> String {
+        match &self.expected {
+            Some(expected) => format!("Flag '{}' is {}", self.flag, expected),
+            None => format!("Flag '{}' is on", self.flag),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, Default)]
+    struct TestState {
+        values: HashMap<String, Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<Value> {
+            self.values.get(key).cloned()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_providers_in_order_with_overrides() {
+        std::env::set_var("AGENTGRAPH_FLAG_TEST_REPLY_MODEL__TENANT_ACME", "\"large\"");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flags.toml");
+        std::fs::write(&path, "[flags.new-router]\nvalue = false\ntenants = { acme = true }\n").unwrap();
+
+        let overrides = Arc::new(StaticFlagProvider::new()
+            .with_flag("new-router", FlagRule::new(json!(false)).with_user("u1", json!(true))));
+        let flags = Flags::new()
+            .with_shared_provider(overrides.clone())
+            .with_provider(EnvFlagProvider::default())
+            .with_provider(FileFlagProvider::new(&path).unwrap());
+        overrides.remove("new-router");
+        overrides.set("test-reply-model", FlagRule::new(json!("small")).with_user("u1", json!("medium")));
+
+        let acme = FlagContext::new().with_tenant("acme");
+        assert!(flags.is_enabled("new-router", &acme, false).await);
+        assert!(!flags.is_enabled("new-router", &FlagContext::new().with_tenant("globex"), true).await);
+        assert!(flags.is_enabled("unknown", &acme, true).await);
+        assert_eq!(flags.value("test-reply-model", &acme.clone().with_user("u1")).await, Some(json!("medium")));
+        assert_eq!(flags.value("test-reply-model", &acme).await, Some(json!("small")));
+
+        // Rewritten files are picked up without a restart
+        std::fs::write(&path, "[flags.new-router]\nvalue = true\n").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        assert!(flags.is_enabled("new-router", &FlagContext::new().with_tenant("globex"), false).await);
+
+        let mut state = TestState::default();
+        state.values.insert(TENANT_STATE_KEY.to_string(), json!("acme"));
+        let env_only = Flags::new().with_provider(EnvFlagProvider::default());
+        let condition = FlagCondition::equals(env_only.clone(), "test-reply-model", json!("large"));
+        assert!(condition.evaluate(&state).await.unwrap());
+        assert!(!FlagCondition::enabled(env_only.clone(), "new-router").evaluate(&state).await.unwrap());
+        assert!(FlagCondition::enabled(env_only.clone(), "new-router").default_on().evaluate(&state).await.unwrap());
+
+        assert!(current().is_none());
+        let seen = scope(Some(&env_only), async { current().map(|flags| flags.providers.len()) }).await;
+        assert_eq!(seen, Some(1));
+    }
+
+    #[test]
+    fn test_flag_cache_keeps_attributes_apart() {
+        let cache = FlagCache::new(Duration::from_secs(60));
+        let free = FlagContext::new().with_tenant("acme").with_attribute("plan", json!("free")).with_attribute("region", json!("eu"));
+        cache.put(&free, HashMap::from([("beta".to_string(), json!(false))]));
+
+        let pro = FlagContext::new().with_tenant("acme").with_attribute("plan", json!("pro")).with_attribute("region", json!("eu"));
+        assert!(cache.get(&pro).is_none());
+        let same = FlagContext::new().with_tenant("acme").with_attribute("region", json!("eu")).with_attribute("plan", json!("free"));
+        assert_eq!(cache.get(&same).unwrap()["beta"], json!(false));
+    }
+}
@@ -0,0 +1,88 @@
+//! Forked executions for comparing alternatives from a shared checkpoint
+//! A branch applies its own state edits to the checkpointed state before execution continues
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::ExecutionContext;
+use crate::state::State;
+use std::fmt;
+
+type StateEdit<S> = Box<dyn Fn(&mut S) -> GraphResult<()> + Send + Sync>;
+
+/// One branch of a fork and the state edits that make it diverge
+pub struct ForkBranch<S> {
+    name: String,
+    edits: Vec<StateEdit<S>>,
+}
+
+impl<S: State> ForkBranch<S> {
+    /// Branch continuing from the checkpointed state unchanged
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            edits: Vec::new(),
+        }
+    }
+
+    /// Edit the state before the branch continues
+    pub fn with_edit<F>(mut self, edit: F) -> Self
+    where
+        F: Fn(&mut S) -> GraphResult<()> + Send + Sync + 'static,
+    {
+        self.edits.push(Box::new(edit));
+        self
+    }
+
+    /// Set a state value before the branch continues
+    pub fn with_value<K: Into<String>>(self, key: K, value: serde_json::Value) -> Self {
+        let key = key.into();
+        self.with_edit(move |state: &mut S| state.set_value(&key, value.clone()))
+    }
+
+    /// Branch name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Apply the branch's edits in order
+    pub(crate) fn apply(&self, state: &mut S) -> GraphResult<()> {
+        for edit in &self.edits {
+            edit(state).map_err(|e| GraphError::state_error(format!(
+                "Failed to apply edits for branch '{}': {}",
+                self.name, e
+            )))?;
+        }
+        Ok(())
+    }
+}
+
+impl<S> fmt::Debug for ForkBranch<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForkBranch")
+            .field("name", &self.name)
+            .field("edits", &self.edits.len())
+            .finish()
+    }
+}
+
+/// How one branch of a fork ended
+///
+/// A failed branch does not stop the others; its error is kept here along
+/// with the state it reached.
+#[derive(Debug)]
+pub struct BranchOutcome<S> {
+    /// Branch name
+    pub branch: String,
+    /// State the branch finished with
+    pub state: S,
+    /// Execution context, with the branch's lineage
+    pub context: ExecutionContext,
+    /// Error the branch stopped with
+    pub error: Option<GraphError>,
+}
+
+impl<S> BranchOutcome<S> {
+    /// Whether the branch ran to completion
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
@@ -0,0 +1,309 @@
+//! Typed inputs and outputs for graphs whose state is an implementation detail.
+//!
+//! A graph's state carries everything its nodes need: retrieved documents,
+//! scratch fields, outboxes. Callers should not have to build one, and API
+//! clients should not see one. A [`TypedGraph`] puts a mapping layer around
+//! a graph: an input mapper builds the initial state from a typed `Input`,
+//! and an output mapper extracts a typed `Output` from the final state, so
+//! the state can change shape without changing the API.
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::engine::GraphEngine;
+use crate::graph::{ExecutionContext, Graph};
+use crate::state::State;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+use warp::{Filter, Reply};
+
+/// Largest input [`TypedGraph::route`] accepts
+const MAX_INPUT_BYTES: u64 = 1024 * 1024;
+
+type InputMapper<I, S> = dyn Fn(I) -> GraphResult<S> + Send + Sync;
+type OutputMapper<S, O> = dyn Fn(&S) -> GraphResult<O> + Send + Sync;
+
+/// A graph run with a typed input and output instead of its state
+pub struct TypedGraph<S: State + Clone + Serialize + DeserializeOwned, I, O> {
+    graph: Arc<Graph<S>>,
+    input: Arc<InputMapper<I, S>>,
+    output: Arc<OutputMapper<S, O>>,
+    engine: Arc<GraphEngine<S>>,
+}
+
+impl<S: State + Clone + Serialize + DeserializeOwned, I, O> Clone for TypedGraph<S, I, O> {
+    fn clone(&self) -> Self {
+        Self {
+            graph: self.graph.clone(),
+            input: self.input.clone(),
+            output: self.output.clone(),
+            engine: self.engine.clone(),
+        }
+    }
+}
+
+impl<S: State + Clone + Serialize + DeserializeOwned, I, O> std::fmt::Debug for TypedGraph<S, I, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedGraph")
+            .field("graph", &self.graph.metadata().name)
+            .field("input", &std::any::type_name::<I>())
+            .field("output", &std::any::type_name::<O>())
+            .finish()
+    }
+}
+
+impl<S, I, O> TypedGraph<S, I, O>
+where
+    S: State + Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    I: Send + 'static,
+    O: Send + 'static,
+{
+    /// Wrap `graph`, building its state with `input` and reading the result with `output`
+    pub fn new<FI, FO>(graph: Arc<Graph<S>>, input: FI, output: FO) -> Self
+    where
+        FI: Fn(I) -> GraphResult<S> + Send + Sync + 'static,
+        FO: Fn(&S) -> GraphResult<O> + Send + Sync + 'static,
+    {
+        Self {
+            graph,
+            input: Arc::new(input),
+            output: Arc::new(output),
+            engine: Arc::new(GraphEngine::new()),
+        }
+    }
+
+    /// Run the graph on siblings of `engine`, so with its services, determinism and pools
+    pub fn with_engine(mut self, engine: GraphEngine<S>) -> Self {
+        self.engine = Arc::new(engine);
+        self
+    }
+
+    /// Wrap `graph`, mapping fields by name
+    ///
+    /// The input's fields are set on a default state, and the output is read
+    /// from the fields of the final state that it names; other state fields
+    /// are left out.
+    pub fn by_field_names(graph: Arc<Graph<S>>) -> Self
+    where
+        S: Default,
+        I: Serialize,
+        O: DeserializeOwned,
+    {
+        Self::new(graph, state_from_fields, fields_from_state)
+    }
+
+    /// The wrapped graph
+    pub fn graph(&self) -> &Arc<Graph<S>> {
+        &self.graph
+    }
+
+    /// Run the graph on `input` and return its output
+    pub async fn run(&self, input: I) -> GraphResult<O> {
+        self.run_with_context(input).await.map(|(output, _)| output)
+    }
+
+    /// Run the graph on `input` and return its output with the execution's context
+    pub async fn run_with_context(&self, input: I) -> GraphResult<(O, ExecutionContext)> {
+        let mut state = (self.input)(input)?;
+        let mut engine = self.engine.sibling();
+        let context = engine.execute(&self.graph, &mut state).await?;
+        let output = (self.output)(&state)?;
+        Ok((output, context))
+    }
+
+    /// POST handler running the graph on a JSON `Input` and answering with its JSON `Output`
+    ///
+    /// Failed executions answer with `{"error", "category"}`: 400 for
+    /// invalid input, 429 with `Retry-After` when throttled and 500
+    /// otherwise. A 500 says no more than that the request failed, with an
+    /// `error_id` to find the error by in the logs. Mount it under a path of your choosing, e.g.
+    /// `warp::path!("graphs" / "support").and(typed.route())`.
+    pub fn route(self) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone
+    where
+        I: DeserializeOwned,
+        O: Serialize,
+    {
+        warp::post()
+            .and(warp::body::content_length_limit(MAX_INPUT_BYTES))
+            .and(warp::body::json::<I>())
+            .and_then(move |input: I| {
+                let typed = self.clone();
+                async move {
+                    let reply = match typed.run(input).await {
+                        Ok(output) => warp::reply::json(&output).into_response(),
+                        Err(error) => error_response(&error),
+                    };
+                    Ok::<_, std::convert::Infallible>(reply)
+                }
+            })
+    }
+}
+
+/// Default state with the fields of `input` set on it
+fn state_from_fields<S, I>(input: I) -> GraphResult<S>
+where
+    S: Default + Serialize + DeserializeOwned,
+    I: Serialize,
+{
+    let mut state = serde_json::to_value(S::default())?;
+    match (state.as_object_mut(), serde_json::to_value(input)?) {
+        (Some(fields), Value::Object(input)) => fields.extend(input),
+        _ => {
+            return Err(GraphError::validation_error(
+                "Mapping by field names needs a state and an input that serialize to objects",
+            ))
+        }
+    }
+    Ok(serde_json::from_value(state)?)
+}
+
+/// Output read from the state fields it names
+fn fields_from_state<S, O>(state: &S) -> GraphResult<O>
+where
+    S: Serialize,
+    O: DeserializeOwned,
+{
+    Ok(serde_json::from_value(serde_json::to_value(state)?)?)
+}
+
+fn error_response(error: &GraphError) -> warp::reply::Response {
+    let status = match error.category() {
+        "validation" | "serialization" => warp::http::StatusCode::BAD_REQUEST,
+        "throttled" => warp::http::StatusCode::TOO_MANY_REQUESTS,
+        _ => warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let body = if status == warp::http::StatusCode::INTERNAL_SERVER_ERROR {
+        // Internal errors can name files, hosts and state; they stay in the logs
+        let error_id = Uuid::new_v4();
+        tracing::error!(%error_id, category = error.category(), error = %error, "Typed graph request failed");
+        serde_json::json!({
+            "error": "Internal error",
+            "category": error.category(),
+            "error_id": error_id,
+        })
+    } else {
+        serde_json::json!({
+            "error": error.to_string(),
+            "category": error.category(),
+        })
+    };
+    let mut response = warp::reply::with_status(warp::reply::json(&body), status).into_response();
+    if let Some(wait) = error.retry_after() {
+        let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        response.headers_mut().insert(warp::http::header::RETRY_AFTER, seconds.into());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphBuilder;
+    use crate::node::Node;
+    use async_trait::async_trait;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct SupportState {
+        question: String,
+        documents: Vec<String>,
+        answer: String,
+    }
+
+    impl State for SupportState {}
+
+    #[derive(Debug)]
+    struct AnswerNode;
+
+    #[async_trait]
+    impl Node<SupportState> for AnswerNode {
+        async fn invoke(&self, state: &mut SupportState) -> GraphResult<()> {
+            state.documents.push("faq.md".to_string());
+            state.answer = format!("Re: {}", state.question);
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Question {
+        question: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Answer {
+        answer: String,
+    }
+
+    #[tokio::test]
+    async fn test_typed_graph_hides_state() {
+        let graph = Arc::new(GraphBuilder::new()
+            .add_node("answer".to_string(), AnswerNode).unwrap()
+            .with_entry_point("answer".to_string()).unwrap()
+            .add_finish_point("answer".to_string()).unwrap()
+            .build().unwrap());
+
+        let typed: TypedGraph<SupportState, Question, Answer> = TypedGraph::by_field_names(graph.clone());
+        let answer = typed.run(Question { question: "refunds?".to_string() }).await.unwrap();
+        assert_eq!(answer, Answer { answer: "Re: refunds?".to_string() });
+
+        let response = warp::test::request()
+            .method("POST")
+            .json(&serde_json::json!({ "question": "hours?" }))
+            .reply(&typed.route())
+            .await;
+        assert_eq!(response.status(), 200);
+        let body: Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "answer": "Re: hours?" }));
+
+        let rejecting = TypedGraph::new(
+            graph,
+            |question: Question| {
+                if question.question.is_empty() {
+                    return Err(GraphError::validation_error("Ask a question"));
+                }
+                Ok(SupportState { question: question.question, ..Default::default() })
+            },
+            |state: &SupportState| Ok(state.documents.len()),
+        );
+        let error = rejecting.run(Question { question: String::new() }).await.unwrap_err();
+        assert_eq!(error_response(&error).status(), 400);
+
+        let internal = error_response(&GraphError::ExternalServiceError("db at /var/lib/app.db unreachable".to_string()));
+        assert_eq!(internal.status(), 500);
+        let body = warp::hyper::body::to_bytes(internal.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "Internal error");
+        assert!(body["error_id"].as_str().is_some_and(|id| Uuid::parse_str(id).is_ok()));
+        assert_eq!(rejecting.run(Question { question: "x".to_string() }).await.unwrap(), 1);
+    }
+
+    #[derive(Debug)]
+    struct SignedAnswerNode;
+
+    #[async_trait]
+    impl Node<SupportState> for SignedAnswerNode {
+        async fn invoke(&self, state: &mut SupportState) -> GraphResult<()> {
+            let signature = crate::graph::services::resolve::<String>()?;
+            state.answer = format!("Re: {} -- {}", state.question, signature);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_graph_runs_on_its_engine() {
+        let graph = Arc::new(GraphBuilder::new()
+            .add_node("answer".to_string(), SignedAnswerNode).unwrap()
+            .with_entry_point("answer".to_string()).unwrap()
+            .add_finish_point("answer".to_string()).unwrap()
+            .build().unwrap());
+
+        let typed: TypedGraph<SupportState, Question, Answer> = TypedGraph::by_field_names(graph.clone());
+        assert!(typed.run(Question { question: "refunds?".to_string() }).await.is_err());
+
+        let engine = GraphEngine::new().with_services(crate::graph::services::Services::new().with("Support".to_string()));
+        let typed = typed.with_engine(engine);
+        let answer = typed.run(Question { question: "refunds?".to_string() }).await.unwrap();
+        assert_eq!(answer, Answer { answer: "Re: refunds? -- Support".to_string() });
+    }
+}
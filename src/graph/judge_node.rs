@@ -0,0 +1,409 @@
+//! LLM-as-judge evaluation node for agent workflows
+//! Scores state content against a rubric with a separate judge model and writes scores and rationale to state
+
+use crate::agents::Agent;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::value_text;
+use crate::llm::LLMUsage;
+use crate::node::{Node, NodeMetadata};
+use crate::state::State;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// One criterion of a [`Rubric`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RubricCriterion {
+    /// Short name, used as the key in the judge's scores
+    pub name: String,
+    /// What earns a high score
+    pub description: String,
+    /// Relative weight in the overall score
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl RubricCriterion {
+    /// Create a criterion with weight 1
+    pub fn new<N: Into<String>, D: Into<String>>(name: N, description: D) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            weight: default_weight(),
+        }
+    }
+
+    /// Set the relative weight
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight.max(0.0);
+        self
+    }
+}
+
+/// Criteria a judge model scores content against
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rubric {
+    /// Name of the rubric
+    pub name: String,
+    /// Criteria scored from 0 to 10
+    pub criteria: Vec<RubricCriterion>,
+}
+
+impl Rubric {
+    /// Create an empty rubric
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Self {
+            name: name.into(),
+            criteria: Vec::new(),
+        }
+    }
+
+    /// Add a criterion
+    pub fn with_criterion<N: Into<String>, D: Into<String>>(mut self, name: N, description: D) -> Self {
+        self.criteria.push(RubricCriterion::new(name, description));
+        self
+    }
+
+    /// Add a weighted criterion
+    pub fn with_weighted_criterion(mut self, criterion: RubricCriterion) -> Self {
+        self.criteria.push(criterion);
+        self
+    }
+
+    /// Whether claims are supported by the context, e.g. retrieved sources
+    pub fn faithfulness() -> Self {
+        Self::new("faithfulness")
+            .with_criterion("grounded", "Every claim is supported by the context; nothing is invented")
+            .with_criterion("consistent", "Nothing contradicts the context")
+            .with_weighted_criterion(
+                RubricCriterion::new("attributed", "Claims drawn from the context are clearly attributed").with_weight(0.5),
+            )
+    }
+
+    /// Whether the content answers the question or task in the context
+    pub fn relevance() -> Self {
+        Self::new("relevance")
+            .with_criterion("on_topic", "Addresses the question or task in the context")
+            .with_criterion("complete", "Covers every part of the request")
+            .with_weighted_criterion(RubricCriterion::new("focused", "Leaves out unrelated material").with_weight(0.5))
+    }
+
+    /// Whether the content is free of toxicity; high scores mean safe content
+    pub fn toxicity() -> Self {
+        Self::new("toxicity")
+            .with_criterion("respectful", "Free of insults, harassment and demeaning language")
+            .with_criterion("harmless", "Free of threats, hate speech and encouragement of harm")
+            .with_weighted_criterion(
+                RubricCriterion::new("appropriate", "Free of profanity and sexual content unsuited to a general audience")
+                    .with_weight(0.5),
+            )
+    }
+
+    /// Built-in rubric by name: `faithfulness`, `relevance` or `toxicity`
+    pub fn template(name: &str) -> Option<Self> {
+        match name {
+            "faithfulness" => Some(Self::faithfulness()),
+            "relevance" => Some(Self::relevance()),
+            "toxicity" => Some(Self::toxicity()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn validate(&self) -> GraphResult<()> {
+        if self.criteria.is_empty() {
+            return Err(GraphError::validation_error(format!("Rubric '{}' has no criteria", self.name)));
+        }
+        if self.criteria.iter().all(|criterion| criterion.weight <= 0.0) {
+            return Err(GraphError::validation_error(format!("Rubric '{}' weights must not all be zero", self.name)));
+        }
+        Ok(())
+    }
+
+    /// Prompt asking a judge to score `content` on each criterion
+    pub(crate) fn prompt(&self, content: &str, context: Option<&str>) -> String {
+        let mut prompt = String::from(
+            "You are an impartial judge. Score the content on each criterion from 0 (fails) to 10 (excellent).\n\nCriteria:\n",
+        );
+        for criterion in &self.criteria {
+            prompt.push_str(&format!("- {}: {}\n", criterion.name, criterion.description));
+        }
+        if let Some(context) = context {
+            prompt.push_str(&format!("\nContext:\n{}\n", context));
+        }
+        prompt.push_str(&format!("\nContent to judge:\n{}\n", content));
+        prompt.push_str(
+            "\nRespond with JSON only, in the form \
+             {\"scores\": {\"<criterion>\": <0-10>}, \"rationale\": \"...\"}",
+        );
+        prompt
+    }
+
+    /// Weighted overall score (0.0 - 1.0) and per-criterion scores of a verdict
+    ///
+    /// Criteria the judge left unscored count as failed.
+    pub(crate) fn weigh(&self, verdict: &Verdict) -> (f32, HashMap<String, f32>) {
+        let mut scores = HashMap::new();
+        let mut weighted = 0.0;
+        let mut total_weight = 0.0;
+        for criterion in &self.criteria {
+            let score = verdict.scores.get(&criterion.name).map_or(0.0, |score| (score / 10.0).clamp(0.0, 1.0));
+            scores.insert(criterion.name.clone(), score);
+            weighted += score * criterion.weight;
+            total_weight += criterion.weight;
+        }
+        let score = if total_weight > 0.0 { weighted / total_weight } else { 0.0 };
+        (score, scores)
+    }
+
+    /// Have `judge` score `content`, returning its verdict and the LLM usage spent
+    pub(crate) async fn judge(
+        &self,
+        judge: &Mutex<Agent>,
+        node: &str,
+        content: &str,
+        context: Option<&str>,
+    ) -> GraphResult<(Verdict, LLMUsage)> {
+        let prompt = self.prompt(content, context);
+        let mut judge = judge.lock().await;
+        let response = judge.execute_task(prompt).await
+            .map_err(|e| GraphError::node_error(
+                node.to_string(),
+                format!("Judge agent failed: {}", e),
+                Some(Box::new(e)),
+            ))?;
+        let usage = judge.state().last_task_usage.clone();
+        drop(judge);
+        Ok((parse_verdict(node, &response)?, usage))
+    }
+}
+
+/// Verdict as returned by a judge model
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct Verdict {
+    pub(crate) scores: HashMap<String, f32>,
+    pub(crate) rationale: String,
+}
+
+/// Parse a judge's JSON reply, tolerating surrounding prose and code fences
+pub(crate) fn parse_verdict(node: &str, response: &str) -> GraphResult<Verdict> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => {
+            return Err(GraphError::node_error(
+                node.to_string(),
+                "Judge reply contains no JSON verdict".to_string(),
+                None,
+            ))
+        }
+    };
+    serde_json::from_str(json).map_err(|e| GraphError::node_error(
+        node.to_string(),
+        format!("Invalid verdict from judge: {}", e),
+        None,
+    ))
+}
+
+/// Scores written to state by a [`JudgeNode`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Judgement {
+    /// Rubric the content was judged against
+    pub rubric: String,
+    /// Weighted overall score (0.0 - 1.0)
+    pub score: f32,
+    /// Score per criterion (0.0 - 1.0)
+    pub scores: HashMap<String, f32>,
+    /// The judge's reasoning
+    pub rationale: String,
+    /// Whether the score met the threshold
+    pub passed: bool,
+}
+
+/// Node that has a separate judge model score state content against a rubric
+///
+/// The judge sees the content and, when configured, context such as the
+/// question or retrieved sources, which rubrics like [`Rubric::faithfulness`]
+/// and [`Rubric::relevance`] rely on. The node writes a [`Judgement`] to
+/// state; it does not route, so branch on it with a conditional edge or gate
+/// on it with a [`QualityGateNode`](crate::graph::quality_gate_node::QualityGateNode).
+#[derive(Debug)]
+pub struct JudgeNode {
+    judge: Arc<Mutex<Agent>>,
+    rubric: Rubric,
+    content_key: String,
+    context_key: Option<String>,
+    output_key: String,
+    pass_threshold: f32,
+    metadata: NodeMetadata,
+}
+
+impl JudgeNode {
+    /// Create a node judging `output` against `rubric`, writing to `judgement`
+    pub fn new(judge: Agent, rubric: Rubric) -> GraphResult<Self> {
+        rubric.validate()?;
+        let metadata = NodeMetadata::new("JudgeNode")
+            .with_description("Scores content against a rubric with a judge model")
+            .with_tag("agent")
+            .with_tag("evaluation")
+            .with_parallel_safe(false);
+
+        Ok(Self {
+            judge: Arc::new(Mutex::new(judge)),
+            rubric,
+            content_key: "output".to_string(),
+            context_key: Some("input".to_string()),
+            output_key: "judgement".to_string(),
+            pass_threshold: 0.7,
+            metadata,
+        })
+    }
+
+    /// Judge the content stored under `key`
+    pub fn with_content_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.content_key = key.into();
+        self
+    }
+
+    /// Show the judge the context stored under `key`, or no context with `None`
+    pub fn with_context_key(mut self, key: Option<&str>) -> Self {
+        self.context_key = key.map(str::to_string);
+        self
+    }
+
+    /// Write the judgement under `key`
+    pub fn with_output_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.output_key = key.into();
+        self
+    }
+
+    /// Set the score (0.0 - 1.0) a judgement needs to pass
+    pub fn with_pass_threshold(mut self, threshold: f32) -> Self {
+        self.pass_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Rubric the node judges against
+    pub fn rubric(&self) -> &Rubric {
+        &self.rubric
+    }
+
+    fn evaluate(&self, verdict: Verdict) -> Judgement {
+        let (score, scores) = self.rubric.weigh(&verdict);
+        Judgement {
+            rubric: self.rubric.name.clone(),
+            score,
+            scores,
+            rationale: verdict.rationale.trim().to_string(),
+            passed: score >= self.pass_threshold,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for JudgeNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        let content = state.get_value(&self.content_key).ok_or_else(|| {
+            GraphError::state_error(format!("No content to judge under '{}'", self.content_key))
+        })?;
+        let context = self.context_key.as_ref().and_then(|key| state.get_value(key));
+
+        let (verdict, usage) = self.rubric.judge(
+            &self.judge,
+            "judge_node",
+            &value_text(&content),
+            context.as_ref().map(value_text).as_deref(),
+        ).await?;
+        super::agent_node::report_llm_usage(&usage);
+
+        let judgement = self.evaluate(verdict);
+        tracing::info!("Judged '{}' on {}: {:.2}", self.content_key, judgement.rubric, judgement.score);
+        let value = serde_json::to_value(&judgement)
+            .map_err(|e| GraphError::state_error(format!("Failed to serialize judgement: {}", e)))?;
+        state.set_value(&self.output_key, value)
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::roles::RoleTemplates;
+    use crate::llm::{LLMManager, LLMConfig, providers::MockProvider};
+    use crate::tools::{ToolRegistry, ToolExecutor};
+    use serde_json::json;
+
+    fn create_judge(responses: Vec<String>) -> Agent {
+        let mut llm_manager = LLMManager::new(LLMConfig::default());
+        llm_manager.register_provider("mock".to_string(), Arc::new(MockProvider::with_responses(responses)));
+
+        let template = RoleTemplates::quality_assurance();
+        let config = template.to_agent_config("Judge".to_string(), "mock".to_string());
+        Agent::new(config, Arc::new(llm_manager), Arc::new(ToolRegistry::new()), Arc::new(ToolExecutor::new())).unwrap()
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct TestState {
+        values: HashMap<String, serde_json::Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<serde_json::Value> {
+            self.values.get(key).cloned()
+        }
+
+        fn set_value(&mut self, key: &str, value: serde_json::Value) -> GraphResult<()> {
+            self.values.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rubric_templates() {
+        for name in ["faithfulness", "relevance", "toxicity"] {
+            let rubric = Rubric::template(name).unwrap();
+            assert_eq!(rubric.name, name);
+            assert!(rubric.validate().is_ok());
+        }
+        assert!(Rubric::template("style").is_none());
+        assert!(Rubric::new("empty").validate().is_err());
+
+        let prompt = Rubric::relevance().prompt("Paris", Some("What is the capital of France?"));
+        assert!(prompt.contains("- on_topic: Addresses the question or task in the context"));
+        assert!(prompt.contains("Context:\nWhat is the capital of France?"));
+        assert!(parse_verdict("judge_node", "Looks good").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_judge_writes_scores_and_rationale() {
+        let reply = "Verdict:\n{\"scores\": {\"grounded\": 4, \"consistent\": 8, \"attributed\": 10}, \"rationale\": \"The date is not in the sources\"}";
+        let node = JudgeNode::new(create_judge(vec![reply.to_string()]), Rubric::faithfulness())
+            .unwrap()
+            .with_context_key(Some("sources"))
+            .with_pass_threshold(0.6);
+
+        let mut state = TestState::default();
+        state.set_value("output", json!("Rust 1.0 shipped in May 2015")).unwrap();
+        state.set_value("sources", json!(["Rust 1.0 was released in 2015"])).unwrap();
+        node.invoke(&mut state).await.unwrap();
+
+        let judgement: Judgement = serde_json::from_value(state.get_value("judgement").unwrap()).unwrap();
+        // (0.4 + 0.8 + 1.0 * 0.5) / 2.5 = 0.68
+        assert!((judgement.score - 0.68).abs() < 1e-6);
+        assert_eq!(judgement.scores.get("grounded"), Some(&0.4));
+        assert_eq!(judgement.rationale, "The date is not in the sources");
+        assert!(judgement.passed);
+        assert!(node.invoke(&mut TestState::default()).await.is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Map nodes for fanning a task out over a list of items
+//! Runs an agent task once per item, spreading items across a pool of agents that work in parallel
+
+use crate::agents::{Agent, AgentConfig};
+use crate::error::{GraphError, GraphResult};
+use crate::llm::{LLMManager, LLMUsage};
+use crate::node::{Node, NodeMetadata};
+use crate::state::State;
+use crate::tools::{ToolExecutor, ToolRegistry};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Node applying an agent task to every item of a state array
+///
+/// Items are read from `items_key`, and each one replaces `{item}` in the
+/// task template. Items are distributed round-robin over the worker agents;
+/// workers run concurrently and each handles its items in order. Results are
+/// written as an array under `output_key` in item order.
+#[derive(Debug)]
+pub struct MapNode {
+    workers: Vec<Arc<Mutex<Agent>>>,
+    task_template: String,
+    items_key: String,
+    output_key: String,
+    metadata: NodeMetadata,
+}
+
+impl MapNode {
+    /// Create a map node over `workers`
+    pub fn new(workers: Vec<Agent>, task_template: String) -> GraphResult<Self> {
+        if workers.is_empty() {
+            return Err(GraphError::validation_error("Map node needs at least one worker agent".to_string()));
+        }
+
+        let metadata = NodeMetadata::new("MapNode")
+            .with_description("Applies an agent task to each item of a list")
+            .with_tag("agent")
+            .with_tag("map")
+            .with_parallel_safe(true);
+
+        Ok(Self {
+            workers: workers.into_iter().map(|agent| Arc::new(Mutex::new(agent))).collect(),
+            task_template,
+            items_key: "items".to_string(),
+            output_key: "results".to_string(),
+            metadata,
+        })
+    }
+
+    /// Create `parallelism` workers sharing one configuration
+    pub fn with_workers(
+        config: AgentConfig,
+        parallelism: usize,
+        llm_manager: Arc<LLMManager>,
+        tool_registry: Arc<ToolRegistry>,
+        tool_executor: Arc<ToolExecutor>,
+        task_template: String,
+    ) -> GraphResult<Self> {
+        let workers = (0..parallelism.max(1))
+            .map(|_| Agent::new(config.clone(), llm_manager.clone(), tool_registry.clone(), tool_executor.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| GraphError::validation_error(format!("Failed to create map worker: {}", e)))?;
+        Self::new(workers, task_template)
+    }
+
+    /// Read items from `key`
+    pub fn with_items_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.items_key = key.into();
+        self
+    }
+
+    /// Write results under `key`
+    pub fn with_output_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.output_key = key.into();
+        self
+    }
+
+    /// Number of worker agents
+    pub fn parallelism(&self) -> usize {
+        self.workers.len()
+    }
+
+    async fn map<S: State>(&self, state: &mut S) -> GraphResult<Vec<String>> {
+        let items = match state.get_value(&self.items_key) {
+            Some(serde_json::Value::Array(items)) => items,
+            Some(_) => {
+                return Err(GraphError::state_error(format!("'{}' is not a list of items", self.items_key)));
+            }
+            None => Vec::new(),
+        };
+        let tasks: Vec<String> = items.iter()
+            .map(|item| {
+                let item = match item {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                self.task_template.replace("{item}", &item)
+            })
+            .collect();
+
+        let runs = self.workers.iter().enumerate().map(|(worker, agent)| {
+            let assigned: Vec<(usize, String)> = tasks.iter()
+                .enumerate()
+                .skip(worker)
+                .step_by(self.workers.len())
+                .map(|(index, task)| (index, task.clone()))
+                .collect();
+            async move {
+                let mut agent = agent.lock().await;
+                let mut results = Vec::with_capacity(assigned.len());
+                let mut usage = LLMUsage::default();
+                for (index, task) in assigned {
+                    let result = agent.execute_task(task).await;
+                    usage.merge(&agent.state().last_task_usage);
+                    results.push((index, result));
+                }
+                (results, usage)
+            }
+        });
+
+        let mut outputs = vec![String::new(); tasks.len()];
+        let mut usage = LLMUsage::default();
+        for (results, worker_usage) in futures::future::join_all(runs).await {
+            usage.merge(&worker_usage);
+            for (index, result) in results {
+                outputs[index] = result.map_err(|e| GraphError::node_error(
+                    "map_node".to_string(),
+                    format!("Item {} failed: {}", index, e),
+                    Some(Box::new(e)),
+                ))?;
+            }
+        }
+
+        tracing::info!("Mapped {} items over {} workers", outputs.len(), self.workers.len());
+        state.set_value(&self.output_key, serde_json::json!(outputs))?;
+        super::agent_node::report_llm_usage(&usage);
+        Ok(outputs)
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for MapNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        self.map(state).await.map(|_| ())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
@@ -2,16 +2,55 @@
 
 pub mod agent_node;
 pub mod command;
+pub mod compiled;
+pub mod context_vars;
+pub mod control;
+pub mod cost;
+pub mod debate_node;
+pub mod determinism;
+pub mod dry_run;
 pub mod engine;
 pub mod executor;
+pub mod flags;
+pub mod fork;
+pub mod io;
+pub mod judge_node;
+pub mod map_node;
+pub mod outbox;
+pub mod outcome;
+pub mod quality_gate_node;
+pub mod registry;
+pub mod reflection_node;
+pub(crate) mod report;
+pub mod retrieval_node;
+pub mod retry;
 pub mod routing_node;
+pub mod saga;
+pub mod services;
+pub mod session_limits;
+pub mod templates;
 pub mod tool_node;
+pub mod tuning;
+pub mod validate_node;
+pub mod watchdog;
 
+use crate::edge::speculation::{BranchStatistics, SpeculationOutcome};
+use crate::edge::throttle::{EdgeThrottle, EdgeWait};
 use crate::edge::{Edge, EdgeRegistry};
+use crate::enterprise::monitoring::MetricsCollector;
+use crate::enterprise::sandbox::SandboxRegistry;
 use crate::error::{GraphError, GraphResult};
+use crate::execution::chat::ChatNotifier;
+use crate::execution::webhooks::WebhookNotifier;
+use crate::graph::compiled::{CompiledGraph, ExecutionPlan};
+use crate::graph::dry_run::SimulatedEffect;
+use crate::llm::LLMUsage;
 use crate::node::{Node, NodeId, NodeRegistry};
+use crate::state::dead_letter::DeadLetterQueue;
+use crate::state::lease::ExecutionLeases;
 use crate::state::State;
 use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[cfg(feature = "streaming")]
@@ -25,6 +64,8 @@ pub struct Graph<S>
 where
     S: State,
 {
+    /// Identity of this graph, distinguishing its nodes from other graphs' in engines
+    id: Uuid,
     /// Node registry
     nodes: NodeRegistry<S>,
     /// Edge registry for conditions and routers
@@ -39,6 +80,38 @@ where
     metadata: GraphMetadata,
     /// Execution configuration
     config: ExecutionConfig,
+    /// Traversal state of rate-limited edges
+    throttle: EdgeThrottle,
+    /// Outcomes of conditional edges, used to pick branches to speculate on
+    branch_stats: BranchStatistics,
+    /// Routing plan, set only on graphs wrapped in a [`CompiledGraph`]
+    plan: Option<Arc<ExecutionPlan>>,
+    /// Where executions that fail for good are kept
+    dead_letter_queue: Option<Arc<dyn DeadLetterQueue>>,
+    /// Side effects performed, kept across executions when set
+    idempotency_ledger: Option<retry::IdempotencyLedger>,
+    /// Webhooks told about execution lifecycle events
+    webhooks: Option<Arc<WebhookNotifier>>,
+    /// Chat channels told about execution events
+    chat_notifier: Option<Arc<ChatNotifier>>,
+    /// Handles for cancelling, pausing and retrying executions
+    controls: Option<control::ExecutionControls>,
+    /// Feature flags nodes read while they run
+    flags: Option<flags::Flags>,
+    /// Per-session message and token limits checked before executions start
+    session_limiter: Option<session_limits::SessionLimiter>,
+    /// Sandbox profiles the tool and LLM calls of executions are held to
+    sandboxes: Option<Arc<SandboxRegistry>>,
+    /// Ownership of executions shared with other instances
+    leases: Option<ExecutionLeases>,
+    /// Handlers for side effects nodes queue in the outbox
+    outbox: Option<outbox::Outbox>,
+    /// Compensators run when an execution fails for good
+    saga: Option<saga::Saga>,
+    /// Loop detection for executions
+    watchdog: Option<watchdog::Watchdog>,
+    /// Collector whose SLOs executions count towards
+    slo_metrics: Option<Arc<MetricsCollector>>,
 
     #[cfg(feature = "streaming")]
     /// Event emitter for streaming
@@ -77,10 +150,14 @@ impl Default for GraphMetadata {
 }
 
 /// Execution configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(default)]
 pub struct ExecutionConfig {
     /// Maximum execution time in seconds
     pub max_execution_time_seconds: Option<u64>,
+    /// Time a node may run unless it sets its own timeout; defaults to the
+    /// maximum execution time
+    pub node_timeout_seconds: Option<u64>,
     /// Maximum number of steps
     pub max_steps: Option<u64>,
     /// Whether to enable parallel execution
@@ -93,14 +170,24 @@ pub struct ExecutionConfig {
     pub checkpoint_interval: Option<u64>,
     /// Maximum number of retries for failed nodes
     pub max_retries: u32,
-    /// Whether to stop on first error
+    /// Whether to stop on first error; otherwise failed nodes are recorded,
+    /// the nodes after them skipped and independent branches run to completion
     pub stop_on_error: bool,
+    /// Simulate the execution: LLM calls go to the mock provider, tools return
+    /// their sample outputs and writes and checkpoints are skipped
+    pub dry_run: bool,
+    /// Run failed executions again from their input state
+    pub retry: Option<retry::ExecutionRetryPolicy>,
+    /// Node execution history kept when the execution engine runs this graph,
+    /// instead of the engine's own level; set per environment in a manifest
+    pub history: Option<crate::execution::HistoryLevel>,
 }
 
 impl Default for ExecutionConfig {
     fn default() -> Self {
         Self {
             max_execution_time_seconds: Some(300), // 5 minutes
+            node_timeout_seconds: None,
             max_steps: Some(1000),
             enable_parallel: true,
             enable_checkpointing: false,
@@ -108,6 +195,9 @@ impl Default for ExecutionConfig {
             checkpoint_interval: Some(10),
             max_retries: 3,
             stop_on_error: true,
+            dry_run: false,
+            retry: None,
+            history: None,
         }
     }
 }
@@ -127,18 +217,76 @@ pub struct ExecutionContext {
     pub execution_path: Vec<NodeId>,
     /// Custom context data
     pub custom_data: HashMap<String, serde_json::Value>,
+    /// Checkpoints saved during this execution, oldest first
+    pub checkpoint_ids: Vec<Uuid>,
+    /// Origin of this execution if it was forked from a checkpoint
+    pub lineage: Option<ExecutionLineage>,
+    /// Waits imposed by rate-limited edges
+    pub edge_waits: Vec<EdgeWait>,
+    /// Branches run speculatively and whether they were kept
+    pub speculations: Vec<SpeculationOutcome>,
+    /// Side effects simulated instead of performed, when dry-running
+    pub simulated_effects: Vec<SimulatedEffect>,
+    /// Run of the execution this context belongs to, counting from 1
+    pub attempt: u32,
+    /// Key shared by every attempt, under which side effects are deduplicated
+    pub idempotency_key: Option<String>,
+    /// Outcome of every node run or skipped, in order
+    pub node_outcomes: Vec<outcome::NodeOutcome>,
+    /// Compensations run after the execution failed, in the order they ran
+    pub compensations: Vec<saga::CompensationRecord>,
+    /// Loops the watchdog broke
+    pub watchdog_trips: Vec<watchdog::WatchdogTrip>,
+    /// LLM usage reported by each node run, in order
+    pub llm_usage: Vec<(NodeId, LLMUsage)>,
+    /// Candidates aggregating nodes chose from, by node run
+    pub candidates: Vec<(NodeId, serde_json::Value)>,
+    /// Context variables the execution was started with
+    pub vars: context_vars::ContextVars,
+}
+
+/// Parent of an execution forked from a checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExecutionLineage {
+    /// Execution the checkpoint was saved by
+    pub parent_execution_id: Uuid,
+    /// Checkpoint the branch started from
+    pub checkpoint_id: Uuid,
+    /// Branch name
+    pub branch: String,
 }
 
 impl ExecutionContext {
     /// Create a new execution context
     pub fn new() -> Self {
         Self {
-            execution_id: Uuid::new_v4(),
-            start_time: chrono::Utc::now(),
+            execution_id: determinism::new_uuid(),
+            start_time: determinism::now(),
             current_step: 0,
             current_node: None,
             execution_path: Vec::new(),
             custom_data: HashMap::new(),
+            checkpoint_ids: Vec::new(),
+            lineage: None,
+            edge_waits: Vec::new(),
+            speculations: Vec::new(),
+            simulated_effects: Vec::new(),
+            attempt: 1,
+            idempotency_key: None,
+            node_outcomes: Vec::new(),
+            compensations: Vec::new(),
+            watchdog_trips: Vec::new(),
+            llm_usage: Vec::new(),
+            candidates: Vec::new(),
+            vars: context_vars::current(),
+        }
+    }
+
+    /// Create a context for a branch forked from a checkpoint
+    pub fn forked(lineage: ExecutionLineage) -> Self {
+        Self {
+            lineage: Some(lineage),
+            ..Self::new()
         }
     }
 
@@ -152,6 +300,50 @@ impl ExecutionContext {
         self.current_step += 1;
     }
 
+    /// Record what happened to a node
+    pub fn record_outcome(&mut self, node_id: NodeId, status: outcome::NodeOutcomeStatus) {
+        self.node_outcomes.push(outcome::NodeOutcome {
+            node_id,
+            step: self.current_step,
+            status,
+        });
+    }
+
+    /// Whether every node succeeded or some failed or were skipped
+    pub fn completion_status(&self) -> outcome::CompletionStatus {
+        if self.node_outcomes.iter().all(|o| o.status == outcome::NodeOutcomeStatus::Succeeded) {
+            outcome::CompletionStatus::Succeeded
+        } else {
+            outcome::CompletionStatus::PartialSuccess
+        }
+    }
+
+    /// Nodes that failed without failing the execution
+    pub fn failed_nodes(&self) -> Vec<&NodeId> {
+        self.node_outcomes.iter().filter(|o| o.is_failed()).map(|o| &o.node_id).collect()
+    }
+
+    /// Record the LLM usage a node run reported
+    pub fn record_llm_usage(&mut self, node_id: NodeId, usage: LLMUsage) {
+        if !usage.is_empty() {
+            self.llm_usage.push((node_id, usage));
+        }
+    }
+
+    /// LLM usage of every node run so far, summed
+    pub fn total_llm_usage(&self) -> LLMUsage {
+        let mut total = LLMUsage::default();
+        for (_, usage) in &self.llm_usage {
+            total.merge(usage);
+        }
+        total
+    }
+
+    /// Nodes skipped because a node before them failed
+    pub fn skipped_nodes(&self) -> Vec<&NodeId> {
+        self.node_outcomes.iter().filter(|o| o.is_skipped()).map(|o| &o.node_id).collect()
+    }
+
     /// Set custom data
     pub fn set_custom_data<K, V>(&mut self, key: K, value: V)
     where
@@ -175,7 +367,7 @@ impl ExecutionContext {
 
     /// Get execution duration
     pub fn duration(&self) -> chrono::Duration {
-        chrono::Utc::now() - self.start_time
+        determinism::now() - self.start_time
     }
 
     /// Get execution duration in milliseconds
@@ -197,6 +389,7 @@ where
     /// Create a new graph
     pub fn new() -> Self {
         Self {
+            id: Uuid::new_v4(),
             nodes: NodeRegistry::new(),
             edge_registry: EdgeRegistry::new(),
             edges: Vec::new(),
@@ -204,6 +397,22 @@ where
             finish_points: Vec::new(),
             metadata: GraphMetadata::default(),
             config: ExecutionConfig::default(),
+            throttle: EdgeThrottle::new(),
+            branch_stats: BranchStatistics::new(),
+            plan: None,
+            dead_letter_queue: None,
+            idempotency_ledger: None,
+            webhooks: None,
+            chat_notifier: None,
+            controls: None,
+            flags: None,
+            session_limiter: None,
+            sandboxes: None,
+            leases: None,
+            outbox: None,
+            saga: None,
+            watchdog: None,
+            slo_metrics: None,
 
             #[cfg(feature = "streaming")]
             event_emitter: None,
@@ -371,11 +580,34 @@ where
         Ok(())
     }
 
+    /// Validate the graph and precompute how it is traversed
+    ///
+    /// Conditions and routers must be registered on the graph. The result
+    /// is immutable and meant to be executed many times.
+    pub fn compile(self) -> GraphResult<CompiledGraph<S>> {
+        CompiledGraph::new(self)
+    }
+
+    /// Routing plan if this graph has been compiled
+    pub fn plan(&self) -> Option<&ExecutionPlan> {
+        self.plan.as_deref()
+    }
+
+    /// Unique ID of this graph instance
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
     /// Get node registry (for advanced usage)
     pub fn node_registry(&self) -> &NodeRegistry<S> {
         &self.nodes
     }
 
+    /// Get mutable node registry (for advanced usage)
+    pub fn node_registry_mut(&mut self) -> &mut NodeRegistry<S> {
+        &mut self.nodes
+    }
+
     /// Get edge registry (for advanced usage)
     pub fn edge_registry(&self) -> &EdgeRegistry<S> {
         &self.edge_registry
@@ -386,12 +618,158 @@ where
         &mut self.edge_registry
     }
 
+    /// Get the traversal state of rate-limited edges
+    pub fn edge_throttle(&self) -> &EdgeThrottle {
+        &self.throttle
+    }
+
+    /// Get the recorded outcomes of conditional edges
+    pub fn branch_statistics(&self) -> &BranchStatistics {
+        &self.branch_stats
+    }
+
     #[cfg(feature = "streaming")]
     /// Set event emitter for streaming
     pub fn set_event_emitter(&mut self, emitter: EventEmitter) {
         self.event_emitter = Some(emitter);
     }
 
+    /// Keep executions that fail in `queue`, with their input state, for retrying
+    pub fn set_dead_letter_queue<Q>(&mut self, queue: Q)
+    where
+        Q: DeadLetterQueue + 'static,
+    {
+        self.dead_letter_queue = Some(Arc::new(queue));
+    }
+
+    /// Queue failed executions are kept in
+    pub fn dead_letter_queue(&self) -> Option<&Arc<dyn DeadLetterQueue>> {
+        self.dead_letter_queue.as_ref()
+    }
+
+    /// Record side effects in `ledger` rather than in one per execution
+    pub fn set_idempotency_ledger(&mut self, ledger: retry::IdempotencyLedger) {
+        self.idempotency_ledger = Some(ledger);
+    }
+
+    /// Ledger side effects are recorded in across executions
+    pub fn idempotency_ledger(&self) -> Option<&retry::IdempotencyLedger> {
+        self.idempotency_ledger.as_ref()
+    }
+
+    /// Notify `webhooks` when executions start, complete, fail or pause
+    pub fn set_webhooks(&mut self, webhooks: Arc<WebhookNotifier>) {
+        self.webhooks = Some(webhooks);
+    }
+
+    /// Webhooks told about execution lifecycle events
+    pub fn webhooks(&self) -> Option<&Arc<WebhookNotifier>> {
+        self.webhooks.as_ref()
+    }
+
+    /// Post execution summaries and failures to Slack or Discord through `notifier`
+    pub fn set_chat_notifier(&mut self, notifier: Arc<ChatNotifier>) {
+        self.chat_notifier = Some(notifier);
+    }
+
+    /// Chat channels told about execution events
+    pub fn chat_notifier(&self) -> Option<&Arc<ChatNotifier>> {
+        self.chat_notifier.as_ref()
+    }
+
+    /// Let executions be cancelled, paused, resumed and retried through `controls`
+    pub fn set_controls(&mut self, controls: control::ExecutionControls) {
+        self.controls = Some(controls);
+    }
+
+    /// Handles for cancelling, pausing and retrying executions
+    pub fn controls(&self) -> Option<&control::ExecutionControls> {
+        self.controls.as_ref()
+    }
+
+    /// Let nodes read `flags` through [`flags::current`] while they run
+    pub fn set_flags(&mut self, flags: flags::Flags) {
+        self.flags = Some(flags);
+    }
+
+    /// Feature flags nodes read while they run
+    pub fn flags(&self) -> Option<&flags::Flags> {
+        self.flags.as_ref()
+    }
+
+    /// Refuse executions from sessions over the limits of `limiter`
+    pub fn set_session_limiter(&mut self, limiter: session_limits::SessionLimiter) {
+        self.session_limiter = Some(limiter);
+    }
+
+    /// Per-session message and token limits checked before executions start
+    pub fn session_limiter(&self) -> Option<&session_limits::SessionLimiter> {
+        self.session_limiter.as_ref()
+    }
+
+    /// Hold each execution to the sandbox profiles `registry` attaches to its
+    /// tenant, read from the state's `tenant_id`, and to this graph's name
+    pub fn set_sandbox_registry(&mut self, registry: Arc<SandboxRegistry>) {
+        self.sandboxes = Some(registry);
+    }
+
+    /// Sandbox profiles the tool and LLM calls of executions are held to
+    pub fn sandbox_registry(&self) -> Option<&Arc<SandboxRegistry>> {
+        self.sandboxes.as_ref()
+    }
+
+    /// Run each execution on one instance at a time and each of its nodes once, through `leases`
+    pub fn set_leases(&mut self, leases: ExecutionLeases) {
+        self.leases = Some(leases);
+    }
+
+    /// Ownership of executions shared with other instances
+    pub fn leases(&self) -> Option<&ExecutionLeases> {
+        self.leases.as_ref()
+    }
+
+    /// Perform side effects nodes queue in the outbox through `outbox`, after each checkpoint
+    pub fn set_outbox(&mut self, outbox: outbox::Outbox) {
+        self.outbox = Some(outbox);
+    }
+
+    /// Handlers for side effects nodes queue in the outbox
+    pub fn outbox(&self) -> Option<&outbox::Outbox> {
+        self.outbox.as_ref()
+    }
+
+    /// Undo the work of executions that fail for good with `saga`'s compensators
+    pub fn set_saga(&mut self, saga: saga::Saga) {
+        self.saga = Some(saga);
+    }
+
+    /// Compensators run when an execution fails for good
+    pub fn saga(&self) -> Option<&saga::Saga> {
+        self.saga.as_ref()
+    }
+
+    /// Break executions out of loops with `watchdog`
+    pub fn set_watchdog(&mut self, watchdog: watchdog::Watchdog) {
+        self.watchdog = Some(watchdog);
+    }
+
+    /// Loop detection for executions
+    pub fn watchdog(&self) -> Option<&watchdog::Watchdog> {
+        self.watchdog.as_ref()
+    }
+
+    /// Count each finished execution towards the SLOs `metrics` defines for this graph's name
+    ///
+    /// Suspended and cancelled executions are not counted.
+    pub fn set_slo_metrics(&mut self, metrics: Arc<MetricsCollector>) {
+        self.slo_metrics = Some(metrics);
+    }
+
+    /// Collector whose SLOs executions count towards
+    pub fn slo_metrics(&self) -> Option<&Arc<MetricsCollector>> {
+        self.slo_metrics.as_ref()
+    }
+
     #[cfg(feature = "checkpointing")]
     /// Set checkpointer for state persistence
     pub fn set_checkpointer<C>(&mut self, checkpointer: C)
@@ -493,6 +871,11 @@ where
         self.graph.validate()?;
         Ok(self.graph)
     }
+
+    /// Build and compile the graph
+    pub fn compile(self) -> GraphResult<CompiledGraph<S>> {
+        self.graph.compile()
+    }
 }
 
 impl<S> Default for GraphBuilder<S>
@@ -504,6 +887,14 @@ where
     }
 }
 
+/// Text of a state value, without quotes for strings
+pub(crate) fn value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,366 @@
+//! Outbox for external side effects: recorded with the checkpoint, performed after it.
+//!
+//! A node that wants an email sent or an API written to does not do it
+//! itself. It adds a message to the outbox kept in its state, with
+//! [`enqueue`] or as an [`OutboxNode`], so the intent is saved in the same
+//! checkpoint as the rest of the node's work. Once that checkpoint is
+//! committed, the engine hands each pending message to the graph's
+//! [`Outbox`], which performs it through the handler registered for its
+//! effect, retrying with backoff.
+//!
+//! Message IDs derive from the execution's idempotency key, so an execution
+//! retried from a checkpoint enqueues the same IDs again. Delivered IDs are
+//! recorded in the idempotency ledger and skipped, and handlers receive the
+//! ID to pass on to APIs that deduplicate requests themselves.
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::dry_run;
+use crate::graph::retry::{self, ExecutionRetryPolicy, IdempotencyLedger};
+use crate::node::{Node, NodeId, NodeMetadata};
+use crate::state::State;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// State key the outbox is kept under
+pub const OUTBOX_STATE_KEY: &str = "__outbox";
+
+/// Whether a message still has to be performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Waiting for the checkpoint to commit
+    Pending,
+    /// Every attempt failed; performed again when the execution is retried
+    Failed,
+}
+
+/// An intended side effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    /// Stable ID, also the idempotency key handed to the handler
+    pub id: String,
+    /// Handler to perform it, e.g. `email`
+    pub effect: String,
+    /// What to send
+    pub payload: Value,
+    /// Attempts made so far
+    #[serde(default)]
+    pub attempts: u32,
+    /// Error of the last attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Whether it is waiting or has given up for now
+    pub status: OutboxStatus,
+}
+
+/// Messages of one execution, as kept in its state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxState {
+    /// Messages not yet performed, in the order they were enqueued
+    pub messages: Vec<OutboxMessage>,
+    /// Messages enqueued so far, delivered or not; numbers the next ID
+    pub enqueued: u64,
+}
+
+impl OutboxState {
+    /// The outbox kept in `state`, empty if it has none
+    pub fn from_state<S: State>(state: &S) -> GraphResult<Self> {
+        match state.get_value(OUTBOX_STATE_KEY) {
+            Some(value) if !value.is_null() => Ok(serde_json::from_value(value)?),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Keep the outbox in `state`
+    pub fn save<S: State>(&self, state: &mut S) -> GraphResult<()> {
+        state.set_value(OUTBOX_STATE_KEY, serde_json::to_value(self)?)?;
+        if state.get_value(OUTBOX_STATE_KEY).is_none() {
+            return Err(GraphError::state_error(
+                "The outbox needs a state that stores values with set_value",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Record an intended side effect in `state`'s outbox, returning its ID
+pub fn enqueue<S: State>(state: &mut S, effect: impl Into<String>, payload: Value) -> GraphResult<String> {
+    let mut outbox = OutboxState::from_state(state)?;
+    let key = retry::current_key().unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let id = format!("{}/outbox/{}", key, outbox.enqueued + 1);
+    outbox.enqueued += 1;
+    outbox.messages.push(OutboxMessage {
+        id: id.clone(),
+        effect: effect.into(),
+        payload,
+        attempts: 0,
+        last_error: None,
+        status: OutboxStatus::Pending,
+    });
+    outbox.save(state)?;
+    Ok(id)
+}
+
+/// Performs one kind of side effect
+#[async_trait]
+pub trait EffectHandler: Send + Sync {
+    /// Perform `message`, returning what the external system answered
+    ///
+    /// Pass `message.id` on as the request's idempotency key where the
+    /// system supports one.
+    async fn perform(&self, message: &OutboxMessage) -> GraphResult<Value>;
+}
+
+#[async_trait]
+impl<F, Fut> EffectHandler for F
+where
+    F: Fn(OutboxMessage) -> Fut + Send + Sync,
+    Fut: std::future::Future<Output = GraphResult<Value>> + Send,
+{
+    async fn perform(&self, message: &OutboxMessage) -> GraphResult<Value> {
+        self(message.clone()).await
+    }
+}
+
+/// Handlers that perform a graph's outbox messages after each checkpoint
+///
+/// Give a graph one with [`Graph::set_outbox`](crate::graph::Graph::set_outbox).
+#[derive(Clone)]
+pub struct Outbox {
+    handlers: HashMap<String, Arc<dyn EffectHandler>>,
+    retry: ExecutionRetryPolicy,
+}
+
+impl std::fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Outbox")
+            .field("effects", &self.handlers.keys().collect::<Vec<_>>())
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            retry: ExecutionRetryPolicy::new(3).with_backoff(std::time::Duration::from_millis(200), std::time::Duration::from_secs(5)),
+        }
+    }
+}
+
+impl Outbox {
+    /// An outbox with no handlers, trying each message up to 3 times on transient errors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Perform messages for `effect` with `handler`
+    pub fn with_handler<H: EffectHandler + 'static>(mut self, effect: impl Into<String>, handler: H) -> Self {
+        self.handlers.insert(effect.into(), Arc::new(handler));
+        self
+    }
+
+    /// Try each message, and back off between attempts, as `policy` says
+    pub fn with_retry(mut self, policy: ExecutionRetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Perform the pending messages in `state`'s outbox, in order
+    ///
+    /// Delivered messages leave the outbox. A message that fails every
+    /// attempt stays in it, marked failed, and fails the execution, so that
+    /// retrying it from its checkpoint performs the message again.
+    pub(crate) async fn dispatch<S: State>(&self, state: &mut S, node_id: &NodeId, ledger: Option<&IdempotencyLedger>) -> GraphResult<()> {
+        let mut outbox = OutboxState::from_state(state)?;
+        if outbox.messages.is_empty() {
+            return Ok(());
+        }
+        let mut failure = None;
+        let mut kept = Vec::new();
+        for mut message in std::mem::take(&mut outbox.messages) {
+            if failure.is_some() {
+                kept.push(message);
+                continue;
+            }
+            if dry_run::is_active() {
+                dry_run::record(dry_run::SimulatedEffectKind::OutboxSkipped { effect: message.effect.clone() });
+                continue;
+            }
+            if ledger.is_some_and(|ledger| ledger.get(&message.id).is_some()) {
+                tracing::debug!(message_id = %message.id, "Outbox message already delivered");
+                continue;
+            }
+            match self.perform(&mut message).await {
+                Ok(output) => {
+                    tracing::info!(message_id = %message.id, effect = %message.effect, node_id = %node_id, attempts = message.attempts, "Outbox message delivered");
+                    if let Some(ledger) = ledger {
+                        ledger.record(message.id.clone(), output);
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(message_id = %message.id, effect = %message.effect, error = %error, "Outbox message failed");
+                    message.status = OutboxStatus::Failed;
+                    message.last_error = Some(error.to_string());
+                    failure = Some(GraphError::node_error(
+                        node_id.clone(),
+                        format!("Outbox message {} ({}) failed after {} attempts", message.id, message.effect, message.attempts),
+                        Some(Box::new(error)),
+                    ));
+                    kept.push(message);
+                }
+            }
+        }
+        outbox.messages = kept;
+        outbox.save(state)?;
+        failure.map_or(Ok(()), Err)
+    }
+
+    async fn perform(&self, message: &mut OutboxMessage) -> GraphResult<Value> {
+        let handler = self.handlers.get(&message.effect).ok_or_else(|| {
+            GraphError::ConfigurationError(format!("No outbox handler for effect '{}'", message.effect))
+        })?;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            message.attempts += 1;
+            let error = match handler.perform(message).await {
+                Ok(output) => return Ok(output),
+                Err(error) => error,
+            };
+            let Some(delay) = self.retry.next_delay(&error, attempt) else {
+                return Err(error);
+            };
+            tracing::debug!(message_id = %message.id, attempt, error = %error, "Retrying outbox message");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Node that adds one message to the outbox, with its payload taken from the state
+///
+/// The payload is the value under `payload_key`, or the whole state when no
+/// key is given.
+#[derive(Debug, Clone)]
+pub struct OutboxNode {
+    effect: String,
+    payload_key: Option<String>,
+}
+
+impl OutboxNode {
+    /// Enqueue an `effect` message with the value under `payload_key` as its payload
+    pub fn new(effect: impl Into<String>, payload_key: impl Into<String>) -> Self {
+        Self {
+            effect: effect.into(),
+            payload_key: Some(payload_key.into()),
+        }
+    }
+
+    /// Enqueue an `effect` message with the whole state as its payload
+    pub fn with_state_payload(effect: impl Into<String>) -> Self {
+        Self {
+            effect: effect.into(),
+            payload_key: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for OutboxNode
+where
+    S: State + Serialize,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        let payload = match &self.payload_key {
+            Some(key) => state.get_value(key).ok_or_else(|| {
+                GraphError::state_error(format!("No '{}' in state to send as '{}'", key, self.effect))
+            })?,
+            None => {
+                let mut payload = serde_json::to_value(&*state)?;
+                if let Some(fields) = payload.as_object_mut() {
+                    fields.remove(OUTBOX_STATE_KEY);
+                }
+                payload
+            }
+        };
+        enqueue(state, self.effect.clone(), payload)?;
+        Ok(())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        NodeMetadata::new("OutboxNode")
+            .with_description(format!("Sends '{}' once the step is checkpointed", self.effect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug, Clone, Default, Serialize)]
+    struct TestState {
+        values: HashMap<String, Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<Value> {
+            self.values.get(key).cloned()
+        }
+
+        fn set_value(&mut self, key: &str, value: Value) -> GraphResult<()> {
+            self.values.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbox_delivers_once_with_retries() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let sent = calls.clone();
+        let outbox = Outbox::new()
+            .with_retry(ExecutionRetryPolicy::new(3).with_backoff(std::time::Duration::from_millis(1), std::time::Duration::from_millis(1)).with_jitter(false))
+            .with_handler("email", move |message: OutboxMessage| {
+                let sent = sent.clone();
+                async move {
+                    // The mail server is down for the first attempt
+                    if sent.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err(GraphError::ExternalServiceError("421 try again".to_string()));
+                    }
+                    Ok(json!({ "message_id": message.id }))
+                }
+            });
+        let ledger = IdempotencyLedger::new();
+        let node = "notify".to_string();
+
+        let mut state = TestState::default();
+        state.values.insert("receipt".to_string(), json!({ "to": "ada@example.com" }));
+        let checkpointed = retry::with_idempotency("exec-1".to_string(), ledger.clone(), async {
+            Node::<TestState>::invoke(&OutboxNode::new("email", "receipt"), &mut state).await.unwrap();
+            state.clone()
+        }).await;
+        assert_eq!(OutboxState::from_state(&state).unwrap().messages[0].id, "exec-1/outbox/1");
+
+        outbox.dispatch(&mut state, &node, Some(&ledger)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        let outbox_state = OutboxState::from_state(&state).unwrap();
+        assert!(outbox_state.messages.is_empty());
+        assert_eq!(outbox_state.enqueued, 1);
+
+        // Retried from the checkpoint taken before delivery: not sent again
+        let mut retried = checkpointed;
+        outbox.dispatch(&mut retried, &node, Some(&ledger)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        enqueue(&mut state, "sms", json!("hi")).unwrap();
+        let error = outbox.dispatch(&mut state, &node, Some(&ledger)).await.unwrap_err();
+        assert!(error.to_string().contains("sms"));
+        assert_eq!(OutboxState::from_state(&state).unwrap().messages[0].status, OutboxStatus::Failed);
+    }
+}
@@ -0,0 +1,62 @@
+//! Per-node outcomes of executions that continue past failed nodes.
+//!
+//! With `stop_on_error` disabled a failed node does not fail the execution:
+//! the nodes that depend on it are skipped, independent branches carry on and
+//! the execution finishes with a partial-success status.
+
+use crate::node::NodeId;
+use serde::{Deserialize, Serialize};
+
+/// What happened to a node during an execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum NodeOutcomeStatus {
+    /// The node ran and succeeded
+    Succeeded,
+    /// The node ran and failed
+    Failed {
+        /// Error the node failed with
+        error: String,
+        /// Category of the error, as reported by [`GraphError::category`](crate::error::GraphError::category)
+        category: String,
+    },
+    /// The node did not run
+    Skipped {
+        /// Why it did not run
+        reason: String,
+    },
+}
+
+/// Outcome of one node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeOutcome {
+    /// Node ID
+    pub node_id: NodeId,
+    /// Step the node ran at, or would have run after, if skipped
+    pub step: u64,
+    /// What happened
+    #[serde(flatten)]
+    pub status: NodeOutcomeStatus,
+}
+
+impl NodeOutcome {
+    /// Whether the node ran and failed
+    pub fn is_failed(&self) -> bool {
+        matches!(self.status, NodeOutcomeStatus::Failed { .. })
+    }
+
+    /// Whether the node was skipped
+    pub fn is_skipped(&self) -> bool {
+        matches!(self.status, NodeOutcomeStatus::Skipped { .. })
+    }
+}
+
+/// How an execution that returned normally went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionStatus {
+    /// Every node that ran succeeded
+    Succeeded,
+    /// Some nodes failed or were skipped, and the rest completed
+    PartialSuccess,
+}
@@ -0,0 +1,801 @@
+//! Quality gate node for agent workflows
+//! Scores output with pluggable scorers and routes it on to pass, revision or human review
+
+use crate::agents::Agent;
+use crate::edge::EdgeCondition;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::command::Command;
+use crate::graph::judge_node::Rubric;
+use crate::graph::value_text;
+use crate::llm::LLMUsage;
+use crate::node::{Node, NodeId, NodeMetadata};
+use crate::state::State;
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Score given by one scorer
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Score {
+    /// Score from 0.0 (fails) to 1.0 (excellent)
+    pub value: f32,
+    /// Why points were lost
+    pub notes: Vec<String>,
+    /// LLM usage spent scoring
+    pub usage: LLMUsage,
+}
+
+impl Score {
+    /// A score without notes
+    pub fn new(value: f32) -> Self {
+        Self {
+            value: value.clamp(0.0, 1.0),
+            ..Default::default()
+        }
+    }
+
+    /// Add a note on why points were lost
+    pub fn with_note<N: Into<String>>(mut self, note: N) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Score the fraction of `passed` checks out of `total`, full marks with no checks
+    fn fraction(passed: usize, total: usize, notes: Vec<String>) -> Self {
+        let value = if total == 0 { 1.0 } else { passed as f32 / total as f32 };
+        Self { notes, ..Self::new(value) }
+    }
+}
+
+/// Scores content for a [`QualityGateNode`]
+#[async_trait]
+pub trait Scorer: Send + Sync + fmt::Debug {
+    /// Name used as the key in the gate's scores
+    fn name(&self) -> &str;
+
+    /// Score the content
+    async fn score(&self, content: &serde_json::Value) -> GraphResult<Score>;
+}
+
+/// Scores text on length and required or forbidden terms
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeuristicScorer {
+    /// Fewest characters expected
+    pub min_chars: Option<usize>,
+    /// Most characters expected
+    pub max_chars: Option<usize>,
+    /// Terms the text should mention, matched case-insensitively
+    pub required_terms: Vec<String>,
+    /// Terms the text must not mention, matched case-insensitively
+    pub forbidden_terms: Vec<String>,
+}
+
+impl HeuristicScorer {
+    /// Create a scorer that only checks the text is not empty
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect between `min` and `max` characters
+    pub fn with_length(mut self, min: usize, max: Option<usize>) -> Self {
+        self.min_chars = Some(min);
+        self.max_chars = max;
+        self
+    }
+
+    /// Expect the text to mention `term`
+    pub fn with_required_term<T: Into<String>>(mut self, term: T) -> Self {
+        self.required_terms.push(term.into());
+        self
+    }
+
+    /// Expect the text not to mention `term`
+    pub fn with_forbidden_term<T: Into<String>>(mut self, term: T) -> Self {
+        self.forbidden_terms.push(term.into());
+        self
+    }
+}
+
+#[async_trait]
+impl Scorer for HeuristicScorer {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    async fn score(&self, content: &serde_json::Value) -> GraphResult<Score> {
+        let text = value_text(content);
+        let lower = text.to_lowercase();
+        let chars = text.trim().chars().count();
+        let mut notes = Vec::new();
+        let mut total = 1;
+
+        let mut passed = usize::from(chars > 0);
+        if chars == 0 {
+            notes.push("Output is empty".to_string());
+        }
+        if let Some(min) = self.min_chars {
+            total += 1;
+            if chars >= min {
+                passed += 1;
+            } else {
+                notes.push(format!("Output has {} characters, expected at least {}", chars, min));
+            }
+        }
+        if let Some(max) = self.max_chars {
+            total += 1;
+            if chars <= max {
+                passed += 1;
+            } else {
+                notes.push(format!("Output has {} characters, expected at most {}", chars, max));
+            }
+        }
+        for term in &self.required_terms {
+            total += 1;
+            if lower.contains(&term.to_lowercase()) {
+                passed += 1;
+            } else {
+                notes.push(format!("Output does not mention '{}'", term));
+            }
+        }
+        for term in &self.forbidden_terms {
+            total += 1;
+            if lower.contains(&term.to_lowercase()) {
+                notes.push(format!("Output mentions '{}'", term));
+            } else {
+                passed += 1;
+            }
+        }
+        Ok(Score::fraction(passed, total, notes))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PatternCheck {
+    regex: Regex,
+    required: bool,
+    description: String,
+}
+
+/// Scores text on the fraction of regex checks it satisfies
+#[derive(Debug, Clone, Default)]
+pub struct RegexScorer {
+    checks: Vec<PatternCheck>,
+}
+
+impl RegexScorer {
+    /// Create a scorer with no checks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect the text to match `pattern`
+    pub fn require<D: Into<String>>(self, pattern: &str, description: D) -> GraphResult<Self> {
+        self.check(pattern, true, description.into())
+    }
+
+    /// Expect the text not to match `pattern`
+    pub fn forbid<D: Into<String>>(self, pattern: &str, description: D) -> GraphResult<Self> {
+        self.check(pattern, false, description.into())
+    }
+
+    fn check(mut self, pattern: &str, required: bool, description: String) -> GraphResult<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| GraphError::validation_error(format!("Invalid pattern '{}': {}", pattern, e)))?;
+        self.checks.push(PatternCheck { regex, required, description });
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl Scorer for RegexScorer {
+    fn name(&self) -> &str {
+        "regex"
+    }
+
+    async fn score(&self, content: &serde_json::Value) -> GraphResult<Score> {
+        let text = value_text(content);
+        let mut notes = Vec::new();
+        let mut passed = 0;
+        for check in &self.checks {
+            if check.regex.is_match(&text) == check.required {
+                passed += 1;
+            } else if check.required {
+                notes.push(format!("Missing: {}", check.description));
+            } else {
+                notes.push(format!("Found: {}", check.description));
+            }
+        }
+        Ok(Score::fraction(passed, self.checks.len(), notes))
+    }
+}
+
+/// Scores structured output on how many of a JSON Schema's properties it fills
+///
+/// Strings are parsed as JSON first. Output that does not validate against
+/// the schema keeps at most half its completeness score.
+pub struct SchemaCompletenessScorer {
+    schema: JSONSchema,
+    properties: Vec<String>,
+}
+
+impl fmt::Debug for SchemaCompletenessScorer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaCompletenessScorer")
+            .field("properties", &self.properties)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SchemaCompletenessScorer {
+    /// Create a scorer from a JSON Schema declaring top-level `properties`
+    pub fn new(schema: &serde_json::Value) -> GraphResult<Self> {
+        let compiled = JSONSchema::compile(schema)
+            .map_err(|e| GraphError::validation_error(format!("Invalid JSON Schema: {}", e)))?;
+        let mut properties: Vec<String> = schema.get("properties")
+            .and_then(|properties| properties.as_object())
+            .map(|properties| properties.keys().cloned().collect())
+            .unwrap_or_default();
+        properties.sort();
+        Ok(Self { schema: compiled, properties })
+    }
+}
+
+#[async_trait]
+impl Scorer for SchemaCompletenessScorer {
+    fn name(&self) -> &str {
+        "schema_completeness"
+    }
+
+    async fn score(&self, content: &serde_json::Value) -> GraphResult<Score> {
+        let parsed;
+        let value = match content {
+            serde_json::Value::String(text) => match serde_json::from_str(text.trim()) {
+                Ok(value) => {
+                    parsed = value;
+                    &parsed
+                }
+                Err(_) => return Ok(Score::new(0.0).with_note("Output is not JSON")),
+            },
+            other => other,
+        };
+
+        let mut notes = Vec::new();
+        let filled = self.properties.iter()
+            .filter(|property| {
+                let present = value.get(property.as_str()).is_some_and(|field| !is_empty(field));
+                if !present {
+                    notes.push(format!("'{}' is missing or empty", property));
+                }
+                present
+            })
+            .count();
+        let mut score = Score::fraction(filled, self.properties.len(), notes);
+        if let Err(errors) = self.schema.validate(value) {
+            score.notes.extend(errors.map(|error| format!("Schema violation: {}", error)));
+            score.value /= 2.0;
+        }
+        Ok(score)
+    }
+}
+
+fn is_empty(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::String(s) => s.trim().is_empty(),
+        serde_json::Value::Array(items) => items.is_empty(),
+        serde_json::Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+/// Has a judge agent score content against a rubric
+#[derive(Debug)]
+pub struct LlmJudgeScorer {
+    judge: Arc<Mutex<Agent>>,
+    rubric: Rubric,
+}
+
+impl LlmJudgeScorer {
+    /// Create a scorer judging with `judge` against `rubric`
+    pub fn new(judge: Agent, rubric: Rubric) -> GraphResult<Self> {
+        rubric.validate()?;
+        Ok(Self {
+            judge: Arc::new(Mutex::new(judge)),
+            rubric,
+        })
+    }
+}
+
+#[async_trait]
+impl Scorer for LlmJudgeScorer {
+    fn name(&self) -> &str {
+        &self.rubric.name
+    }
+
+    async fn score(&self, content: &serde_json::Value) -> GraphResult<Score> {
+        let (verdict, usage) = self.rubric.judge(&self.judge, "quality_gate", &value_text(content), None).await?;
+        let (value, _) = self.rubric.weigh(&verdict);
+        let mut score = Score::new(value);
+        if !verdict.rationale.trim().is_empty() {
+            score.notes.push(verdict.rationale.trim().to_string());
+        }
+        score.usage = usage;
+        Ok(score)
+    }
+}
+
+/// Where gated output goes next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateOutcome {
+    /// Good enough to continue
+    Pass,
+    /// Sent back for another attempt
+    Revise,
+    /// Handed to a human reviewer
+    Escalate,
+}
+
+/// Configuration for a quality gate node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityGateConfig {
+    /// State key holding the output to score
+    pub content_key: String,
+    /// State key the [`GateReport`] is written to
+    pub report_key: String,
+    /// State key the score and scorer notes are written to, as instructions for the reviser
+    pub feedback_key: String,
+    /// Overall score (0.0 - 1.0) needed to pass
+    pub pass_threshold: f32,
+    /// Overall score needed to be revised rather than escalated
+    pub revise_threshold: f32,
+    /// Most revisions requested before failing output is escalated
+    pub max_revisions: u32,
+    /// Node that revises the output
+    pub revise_node: Option<NodeId>,
+    /// Node human review is routed to; the execution suspends for review when unset
+    pub escalate_node: Option<NodeId>,
+}
+
+impl Default for QualityGateConfig {
+    fn default() -> Self {
+        Self {
+            content_key: "output".to_string(),
+            report_key: "quality".to_string(),
+            feedback_key: "revision_feedback".to_string(),
+            pass_threshold: 0.7,
+            revise_threshold: 0.4,
+            max_revisions: 2,
+            revise_node: None,
+            escalate_node: None,
+        }
+    }
+}
+
+impl QualityGateConfig {
+    /// Create a configuration routing revisions to `revise_node`
+    pub fn new<N: Into<NodeId>>(revise_node: N) -> Self {
+        Self {
+            revise_node: Some(revise_node.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Score the output stored under `key`
+    pub fn with_content_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.content_key = key.into();
+        self
+    }
+
+    /// Write the report under `key`
+    pub fn with_report_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.report_key = key.into();
+        self
+    }
+
+    /// Write revision instructions under `key`
+    pub fn with_feedback_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.feedback_key = key.into();
+        self
+    }
+
+    /// Pass at `pass` or above, revise at `revise` or above and escalate below
+    pub fn with_thresholds(mut self, pass: f32, revise: f32) -> Self {
+        self.pass_threshold = pass.clamp(0.0, 1.0);
+        self.revise_threshold = revise.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the revision limit
+    pub fn with_max_revisions(mut self, max_revisions: u32) -> Self {
+        self.max_revisions = max_revisions;
+        self
+    }
+
+    /// Route escalations to `node` instead of suspending for review
+    pub fn with_escalate_node<N: Into<NodeId>>(mut self, node: N) -> Self {
+        self.escalate_node = Some(node.into());
+        self
+    }
+
+    fn validate(&self) -> GraphResult<()> {
+        if self.revise_threshold > self.pass_threshold {
+            return Err(GraphError::validation_error(format!(
+                "Quality gate revise threshold {} is above its pass threshold {}",
+                self.revise_threshold, self.pass_threshold
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a quality gate, written to state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GateReport {
+    /// Weighted overall score (0.0 - 1.0)
+    pub score: f32,
+    /// Score per scorer
+    pub scores: HashMap<String, f32>,
+    /// Why points were lost, per scorer
+    pub notes: HashMap<String, Vec<String>>,
+    /// Where the output goes next
+    pub outcome: GateOutcome,
+    /// Gates performed so far, including this one
+    pub attempt: u32,
+    /// Decision of the human reviewer, once an escalation is resumed
+    #[serde(default)]
+    pub review: Option<serde_json::Value>,
+}
+
+impl GateReport {
+    /// Instructions for the revising node
+    pub fn revision_instructions(&self) -> String {
+        let mut instructions = format!("Quality score: {:.2}.", self.score);
+        let mut scorers: Vec<_> = self.notes.iter().filter(|(_, notes)| !notes.is_empty()).collect();
+        scorers.sort_by_key(|(name, _)| name.as_str());
+        for (scorer, notes) in scorers {
+            instructions.push_str(&format!("\n{}:", scorer));
+            for note in notes {
+                instructions.push_str(&format!("\n- {}", note));
+            }
+        }
+        instructions
+    }
+}
+
+/// Node that scores output and routes it on by score
+///
+/// Each scorer rates the output from 0 to 1 and the weighted mean decides
+/// the [`GateOutcome`]: pass at the pass threshold, revise at the revise
+/// threshold while revisions remain, otherwise escalate. Escalations go to
+/// the escalate node, or suspend the execution until a reviewer's decision
+/// is passed to [`GraphEngine::resume`](crate::graph::engine::GraphEngine::resume).
+/// Route with [`QualityGateNode::invoke_with_command`] or conditional edges on
+/// [`QualityGateNode::outcome_condition`].
+#[derive(Debug)]
+pub struct QualityGateNode {
+    scorers: Vec<(Arc<dyn Scorer>, f32)>,
+    config: QualityGateConfig,
+    metadata: NodeMetadata,
+}
+
+impl QualityGateNode {
+    /// Create a gate with no scorers
+    pub fn new(config: QualityGateConfig) -> GraphResult<Self> {
+        config.validate()?;
+        let metadata = NodeMetadata::new("QualityGateNode")
+            .with_description("Scores output and routes it to pass, revision or review")
+            .with_tag("quality")
+            .with_parallel_safe(false);
+
+        Ok(Self {
+            scorers: Vec::new(),
+            config,
+            metadata,
+        })
+    }
+
+    /// Add a scorer with weight 1
+    pub fn with_scorer<T: Scorer + 'static>(self, scorer: T) -> Self {
+        self.with_weighted_scorer(scorer, 1.0)
+    }
+
+    /// Add a scorer with a relative weight
+    pub fn with_weighted_scorer<T: Scorer + 'static>(mut self, scorer: T, weight: f32) -> Self {
+        self.scorers.push((Arc::new(scorer), weight.max(0.0)));
+        self
+    }
+
+    /// Node configuration
+    pub fn config(&self) -> &QualityGateConfig {
+        &self.config
+    }
+
+    /// Edge condition that is true when the gate decided `outcome`
+    pub fn outcome_condition(&self, outcome: GateOutcome) -> GateCondition {
+        GateCondition::new(self.config.report_key.clone(), outcome)
+    }
+
+    /// Gate the output and return the routing decision
+    pub async fn invoke_with_command<S: State>(&self, state: &mut S) -> GraphResult<Command> {
+        let report = self.gate(state).await?;
+        Ok(match report.outcome {
+            GateOutcome::Pass => Command::continue_(),
+            GateOutcome::Revise => match &self.config.revise_node {
+                Some(node) => Command::goto(node.clone()),
+                None => Command::continue_(),
+            },
+            GateOutcome::Escalate => match &self.config.escalate_node {
+                Some(node) => Command::goto(node.clone()),
+                None => return Err(self.suspend(&report)),
+            },
+        })
+    }
+
+    async fn gate<S: State>(&self, state: &mut S) -> GraphResult<GateReport> {
+        if self.scorers.is_empty() {
+            return Err(GraphError::validation_error("Quality gate has no scorers".to_string()));
+        }
+        let content = state.get_value(&self.config.content_key).ok_or_else(|| {
+            GraphError::state_error(format!("No output to gate under '{}'", self.config.content_key))
+        })?;
+        let previous_attempts = state.get_value(&self.config.report_key)
+            .and_then(|report| serde_json::from_value::<GateReport>(report).ok())
+            .map_or(0, |report| report.attempt);
+
+        let mut results = Vec::with_capacity(self.scorers.len());
+        let mut usage = LLMUsage::default();
+        for (scorer, weight) in &self.scorers {
+            let score = scorer.score(&content).await?;
+            usage.merge(&score.usage);
+            results.push((scorer.name().to_string(), *weight, score));
+        }
+        super::agent_node::report_llm_usage(&usage);
+
+        let report = self.evaluate(results, previous_attempts + 1);
+        tracing::info!(
+            "Quality gate attempt {} scored {:.2} ({:?})",
+            report.attempt,
+            report.score,
+            report.outcome
+        );
+
+        let value = serde_json::to_value(&report)
+            .map_err(|e| GraphError::state_error(format!("Failed to serialize quality report: {}", e)))?;
+        state.set_value(&self.config.report_key, value)?;
+        state.set_value(&self.config.feedback_key, serde_json::Value::String(report.revision_instructions()))?;
+        Ok(report)
+    }
+
+    /// Turn scores into the report for attempt number `attempt`
+    fn evaluate(&self, results: Vec<(String, f32, Score)>, attempt: u32) -> GateReport {
+        let mut scores = HashMap::new();
+        let mut notes = HashMap::new();
+        let mut weighted = 0.0;
+        let mut total_weight = 0.0;
+        for (name, weight, score) in results {
+            weighted += score.value * weight;
+            total_weight += weight;
+            scores.insert(name.clone(), score.value);
+            notes.insert(name, score.notes);
+        }
+        let score = if total_weight > 0.0 { weighted / total_weight } else { 0.0 };
+
+        let outcome = if score >= self.config.pass_threshold {
+            GateOutcome::Pass
+        } else if score >= self.config.revise_threshold
+            && attempt <= self.config.max_revisions
+            && self.config.revise_node.is_some()
+        {
+            GateOutcome::Revise
+        } else {
+            GateOutcome::Escalate
+        };
+
+        GateReport {
+            score,
+            scores,
+            notes,
+            outcome,
+            attempt,
+            review: None,
+        }
+    }
+
+    fn suspend(&self, report: &GateReport) -> GraphError {
+        GraphError::suspended(
+            self.metadata.name.clone(),
+            format!("review:{}:{}", self.config.report_key, report.attempt),
+        )
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for QualityGateNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        let report = self.gate(state).await?;
+        if report.outcome == GateOutcome::Escalate && self.config.escalate_node.is_none() {
+            return Err(self.suspend(&report));
+        }
+        Ok(())
+    }
+
+    /// Record the reviewer's decision on an escalated output
+    async fn resume(&self, state: &mut S, output: serde_json::Value) -> GraphResult<()> {
+        let mut report: GateReport = state.get_value(&self.config.report_key)
+            .and_then(|report| serde_json::from_value(report).ok())
+            .ok_or_else(|| GraphError::state_error(format!("No quality report under '{}'", self.config.report_key)))?;
+        report.review = Some(output);
+        let value = serde_json::to_value(&report)
+            .map_err(|e| GraphError::state_error(format!("Failed to serialize quality report: {}", e)))?;
+        state.set_value(&self.config.report_key, value)
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+/// Edge condition reading the outcome written by a [`QualityGateNode`]
+#[derive(Debug, Clone)]
+pub struct GateCondition {
+    report_key: String,
+    outcome: GateOutcome,
+}
+
+impl GateCondition {
+    /// Condition on the report stored under `report_key` having `outcome`
+    pub fn new<K: Into<String>>(report_key: K, outcome: GateOutcome) -> Self {
+        Self {
+            report_key: report_key.into(),
+            outcome,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: State> EdgeCondition<S> for GateCondition {
+    async fn evaluate(&self, state: &S) -> GraphResult<bool> {
+        Ok(state.get_value(&self.report_key)
+            .and_then(|report| report.get("outcome").cloned())
+            .and_then(|outcome| serde_json::from_value::<GateOutcome>(outcome).ok())
+            == Some(self.outcome))
+    }
+
+    fn condition_id(&self) -> String {
+        format!("quality_gate:{}:{:?}", self.report_key, self.outcome)
+    }
+
+    fn description(&self) -> String {
+        format!("Output gated under '{}' has outcome {:?}", self.report_key, self.outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::roles::RoleTemplates;
+    use crate::graph::judge_node::RubricCriterion;
+    use crate::llm::{LLMManager, LLMConfig, providers::MockProvider};
+    use crate::tools::{ToolRegistry, ToolExecutor};
+    use serde_json::json;
+
+    fn create_judge(responses: Vec<String>) -> Agent {
+        let mut llm_manager = LLMManager::new(LLMConfig::default());
+        llm_manager.register_provider("mock".to_string(), Arc::new(MockProvider::with_responses(responses)));
+
+        let template = RoleTemplates::quality_assurance();
+        let config = template.to_agent_config("Judge".to_string(), "mock".to_string());
+        Agent::new(config, Arc::new(llm_manager), Arc::new(ToolRegistry::new()), Arc::new(ToolExecutor::new())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_builtin_scorers() {
+        let heuristic = HeuristicScorer::new()
+            .with_length(10, Some(100))
+            .with_required_term("Rust")
+            .with_forbidden_term("lorem ipsum");
+        let score = heuristic.score(&json!("Short rust note, lorem ipsum")).await.unwrap();
+        // Non-empty, long enough, short enough, mentions rust; fails the forbidden term
+        assert!((score.value - 0.8).abs() < 1e-6);
+        assert_eq!(score.notes, vec!["Output mentions 'lorem ipsum'".to_string()]);
+
+        let regex = RegexScorer::new()
+            .require(r"https?://", "a source link").unwrap()
+            .forbid(r"(?i)\bTODO\b", "a TODO marker").unwrap();
+        assert_eq!(regex.score(&json!("See https://example.com")).await.unwrap().value, 1.0);
+        assert_eq!(regex.score(&json!("TODO: find a source")).await.unwrap().value, 0.0);
+        assert!(RegexScorer::new().require("(", "broken").is_err());
+
+        let schema = SchemaCompletenessScorer::new(&json!({
+            "type": "object",
+            "required": ["title"],
+            "properties": {"title": {"type": "string"}, "summary": {"type": "string"}}
+        })).unwrap();
+        assert_eq!(schema.score(&json!(r#"{"title": "A", "summary": "B"}"#)).await.unwrap().value, 1.0);
+        assert_eq!(schema.score(&json!({"title": "A", "summary": ""})).await.unwrap().value, 0.5);
+        // Incomplete and invalid: half of half
+        assert_eq!(schema.score(&json!({"summary": "B"})).await.unwrap().value, 0.25);
+        assert_eq!(schema.score(&json!("not json")).await.unwrap().value, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_llm_judge_scorer() {
+        let rubric = Rubric::new("judge")
+            .with_criterion("accuracy", "Claims are correct")
+            .with_weighted_criterion(RubricCriterion::new("clarity", "Easy to follow").with_weight(0.5));
+        assert!(LlmJudgeScorer::new(create_judge(Vec::new()), Rubric::new("empty")).is_err());
+
+        let reply = "```json\n{\"scores\": {\"accuracy\": 6, \"clarity\": 9}, \"rationale\": \"One wrong date\"}\n```";
+        let scorer = LlmJudgeScorer::new(create_judge(vec![reply.to_string()]), rubric).unwrap();
+        let score = scorer.score(&json!("Rust 1.0 shipped in 2016")).await.unwrap();
+        // (0.6 * 1.0 + 0.9 * 0.5) / 1.5 = 0.7
+        assert!((score.value - 0.7).abs() < 1e-6);
+        assert_eq!(score.notes, vec!["One wrong date".to_string()]);
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct TestState {
+        values: HashMap<String, serde_json::Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<serde_json::Value> {
+            self.values.get(key).cloned()
+        }
+
+        fn set_value(&mut self, key: &str, value: serde_json::Value) -> GraphResult<()> {
+            self.values.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routing_outcomes() {
+        let gate = QualityGateNode::new(
+            QualityGateConfig::new("writer").with_thresholds(0.9, 0.5).with_max_revisions(1),
+        )
+        .unwrap()
+        .with_scorer(HeuristicScorer::new().with_required_term("rust").with_required_term("safety").with_required_term("ownership"))
+        .with_scorer(RegexScorer::new().require(r"\d{4}", "a year").unwrap());
+        assert!(QualityGateNode::new(QualityGateConfig::default().with_thresholds(0.5, 0.8)).is_err());
+
+        // (2/4 + 0) / 2 = 0.25: too low to revise
+        let mut state = TestState::default();
+        state.set_value("output", json!("Rust is fast")).unwrap();
+        assert!(gate.invoke_with_command(&mut state).await.unwrap_err().is_suspended());
+        assert!(gate.outcome_condition(GateOutcome::Escalate).evaluate(&state).await.unwrap());
+
+        // (2/4 + 1) / 2 = 0.75
+        let mut state = TestState::default();
+        state.set_value("output", json!("Rust is fast, since 2015")).unwrap();
+        let command = gate.invoke_with_command(&mut state).await.unwrap();
+        assert_eq!(command.target_node(), Some("writer"));
+        let report: GateReport = serde_json::from_value(state.get_value("quality").unwrap()).unwrap();
+        assert_eq!(report.outcome, GateOutcome::Revise);
+        assert_eq!(report.attempt, 1);
+        let feedback = state.get_value("revision_feedback").unwrap();
+        assert!(feedback.as_str().unwrap().contains("- Output does not mention 'safety'"));
+
+        // Revisions exhausted: suspend for review and record the decision on resume
+        assert!(gate.invoke_with_command(&mut state).await.unwrap_err().is_suspended());
+        Node::<TestState>::resume(&gate, &mut state, json!({"approved": true})).await.unwrap();
+        let report: GateReport = serde_json::from_value(state.get_value("quality").unwrap()).unwrap();
+        assert_eq!(report.outcome, GateOutcome::Escalate);
+        assert_eq!(report.review, Some(json!({"approved": true})));
+
+        state.set_value("output", json!("Rust safety through ownership, since 2015")).unwrap();
+        assert!(gate.invoke_with_command(&mut state).await.unwrap().is_continue());
+        assert!(gate.outcome_condition(GateOutcome::Pass).evaluate(&state).await.unwrap());
+    }
+}
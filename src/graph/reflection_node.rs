@@ -0,0 +1,479 @@
+//! Self-reflection node for agent workflows
+//! Critiques the previous agent output against configured criteria and routes back for revision
+
+use crate::agents::Agent;
+use crate::edge::EdgeCondition;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::command::Command;
+use crate::graph::value_text;
+use crate::node::{Node, NodeId, NodeMetadata};
+use crate::state::State;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A quality criterion the critic scores the output against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReflectionCriterion {
+    /// Short name, used as the key in the feedback scores
+    pub name: String,
+    /// What the criterion checks
+    pub description: String,
+    /// Relative weight in the overall score
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl ReflectionCriterion {
+    /// Create a criterion with weight 1
+    pub fn new<N: Into<String>, D: Into<String>>(name: N, description: D) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            weight: default_weight(),
+        }
+    }
+
+    /// Set the relative weight
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight.max(0.0);
+        self
+    }
+}
+
+/// Configuration for a reflection node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReflectionConfig {
+    /// Criteria the output is scored against
+    pub criteria: Vec<ReflectionCriterion>,
+    /// Overall score (0.0 - 1.0) needed to pass
+    pub pass_threshold: f32,
+    /// Most revisions requested before the output is accepted as is
+    pub max_revisions: u32,
+    /// State key holding the output to critique
+    pub output_key: String,
+    /// State key holding the original task, included for context
+    pub task_key: Option<String>,
+    /// State key the structured feedback is written to
+    pub feedback_key: String,
+    /// State key the plain-text revision instructions are written to
+    pub instructions_key: String,
+    /// Node that revises the output
+    pub revise_node: NodeId,
+}
+
+impl Default for ReflectionConfig {
+    fn default() -> Self {
+        Self {
+            criteria: Vec::new(),
+            pass_threshold: 0.7,
+            max_revisions: 2,
+            output_key: "output".to_string(),
+            task_key: Some("input".to_string()),
+            feedback_key: "reflection".to_string(),
+            instructions_key: "revision_feedback".to_string(),
+            revise_node: String::new(),
+        }
+    }
+}
+
+impl ReflectionConfig {
+    /// Create a configuration routing failed critiques to `revise_node`
+    pub fn new<N: Into<NodeId>>(revise_node: N) -> Self {
+        Self {
+            revise_node: revise_node.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a criterion
+    pub fn with_criterion<N: Into<String>, D: Into<String>>(mut self, name: N, description: D) -> Self {
+        self.criteria.push(ReflectionCriterion::new(name, description));
+        self
+    }
+
+    /// Add a weighted criterion
+    pub fn with_weighted_criterion(mut self, criterion: ReflectionCriterion) -> Self {
+        self.criteria.push(criterion);
+        self
+    }
+
+    /// Set the passing score
+    pub fn with_pass_threshold(mut self, threshold: f32) -> Self {
+        self.pass_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the revision limit
+    pub fn with_max_revisions(mut self, max_revisions: u32) -> Self {
+        self.max_revisions = max_revisions;
+        self
+    }
+
+    /// Critique the output stored under `key`
+    pub fn with_output_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.output_key = key.into();
+        self
+    }
+
+    /// Include the task stored under `key`, or no task with `None`
+    pub fn with_task_key(mut self, key: Option<&str>) -> Self {
+        self.task_key = key.map(str::to_string);
+        self
+    }
+
+    /// Write feedback under `key`
+    pub fn with_feedback_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.feedback_key = key.into();
+        self
+    }
+
+    /// Write revision instructions under `key`
+    pub fn with_instructions_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.instructions_key = key.into();
+        self
+    }
+
+    fn validate(&self) -> GraphResult<()> {
+        if self.criteria.is_empty() {
+            return Err(GraphError::validation_error("Reflection needs at least one criterion".to_string()));
+        }
+        if self.revise_node.is_empty() {
+            return Err(GraphError::validation_error("Reflection needs a node to route revisions to".to_string()));
+        }
+        if self.criteria.iter().all(|criterion| criterion.weight <= 0.0) {
+            return Err(GraphError::validation_error("Reflection criteria weights must not all be zero".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Structured critique written to state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReflectionFeedback {
+    /// Score per criterion (0.0 - 1.0)
+    pub scores: HashMap<String, f32>,
+    /// Weighted overall score (0.0 - 1.0)
+    pub score: f32,
+    /// Whether the score met the threshold
+    pub passed: bool,
+    /// Problems found
+    pub issues: Vec<String>,
+    /// Suggested improvements
+    pub suggestions: Vec<String>,
+    /// One-paragraph summary of the critique
+    pub summary: String,
+    /// Critiques performed so far, including this one
+    pub attempt: u32,
+    /// Whether the output goes back for revision
+    pub needs_revision: bool,
+}
+
+impl ReflectionFeedback {
+    /// Instructions for the revising agent
+    pub fn revision_instructions(&self) -> String {
+        let mut instructions = format!("Reviewer score: {:.2}. {}", self.score, self.summary.trim());
+        if !self.issues.is_empty() {
+            instructions.push_str("\nIssues:");
+            for issue in &self.issues {
+                instructions.push_str(&format!("\n- {}", issue));
+            }
+        }
+        if !self.suggestions.is_empty() {
+            instructions.push_str("\nSuggestions:");
+            for suggestion in &self.suggestions {
+                instructions.push_str(&format!("\n- {}", suggestion));
+            }
+        }
+        instructions
+    }
+}
+
+/// Critique as returned by the critic agent
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Critique {
+    scores: HashMap<String, f32>,
+    issues: Vec<String>,
+    suggestions: Vec<String>,
+    summary: String,
+}
+
+/// Node that has a critic agent review the previous output
+///
+/// The critic scores the output on each configured criterion. The node writes
+/// a [`ReflectionFeedback`] to state and, while the output fails and revisions
+/// remain, routes to the revise node. Route with [`ReflectionNode::invoke_with_command`]
+/// or a conditional edge on [`ReflectionNode::revision_condition`].
+#[derive(Debug)]
+pub struct ReflectionNode {
+    critic: Arc<Mutex<Agent>>,
+    config: ReflectionConfig,
+    metadata: NodeMetadata,
+}
+
+impl ReflectionNode {
+    /// Create a reflection node with a critic agent
+    pub fn new(critic: Agent, config: ReflectionConfig) -> GraphResult<Self> {
+        config.validate()?;
+        let metadata = NodeMetadata::new("ReflectionNode")
+            .with_description("Critiques agent output and requests revisions")
+            .with_tag("agent")
+            .with_tag("reflection")
+            .with_parallel_safe(false);
+
+        Ok(Self {
+            critic: Arc::new(Mutex::new(critic)),
+            config,
+            metadata,
+        })
+    }
+
+    /// Node configuration
+    pub fn config(&self) -> &ReflectionConfig {
+        &self.config
+    }
+
+    /// Edge condition that is true while the output needs revision
+    pub fn revision_condition(&self) -> ReflectionCondition {
+        ReflectionCondition::new(self.config.feedback_key.clone())
+    }
+
+    /// Critique the output and return the routing decision
+    pub async fn invoke_with_command<S: State>(&self, state: &mut S) -> GraphResult<Command> {
+        let feedback = self.reflect(state).await?;
+        if feedback.needs_revision {
+            Ok(Command::goto(self.config.revise_node.clone()))
+        } else {
+            Ok(Command::continue_())
+        }
+    }
+
+    async fn reflect<S: State>(&self, state: &mut S) -> GraphResult<ReflectionFeedback> {
+        let output = state.get_value(&self.config.output_key).ok_or_else(|| {
+            GraphError::state_error(format!("No output to reflect on under '{}'", self.config.output_key))
+        })?;
+        let task = self.config.task_key.as_ref().and_then(|key| state.get_value(key));
+        let previous_attempts = state.get_value(&self.config.feedback_key)
+            .and_then(|feedback| serde_json::from_value::<ReflectionFeedback>(feedback).ok())
+            .map_or(0, |feedback| feedback.attempt);
+
+        let prompt = self.build_prompt(&value_text(&output), task.as_ref().map(value_text).as_deref());
+        let mut critic = self.critic.lock().await;
+        let response = critic.execute_task(prompt).await
+            .map_err(|e| GraphError::node_error(
+                "reflection_node".to_string(),
+                format!("Critic agent failed: {}", e),
+                Some(Box::new(e)),
+            ))?;
+        super::agent_node::report_llm_usage(&critic.state().last_task_usage);
+        drop(critic);
+
+        let critique = parse_critique(&response)?;
+        let feedback = self.evaluate(critique, previous_attempts + 1);
+        tracing::info!(
+            "Reflection attempt {} scored {:.2} ({})",
+            feedback.attempt,
+            feedback.score,
+            if feedback.passed { "passed" } else if feedback.needs_revision { "revising" } else { "revisions exhausted" }
+        );
+
+        let value = serde_json::to_value(&feedback)
+            .map_err(|e| GraphError::state_error(format!("Failed to serialize reflection feedback: {}", e)))?;
+        state.set_value(&self.config.feedback_key, value)?;
+        state.set_value(&self.config.instructions_key, serde_json::Value::String(feedback.revision_instructions()))?;
+        Ok(feedback)
+    }
+
+    fn build_prompt(&self, output: &str, task: Option<&str>) -> String {
+        let mut prompt = String::from(
+            "You are reviewing another agent's work. Score it on each criterion from 0 (fails) to 10 (excellent).\n\nCriteria:\n",
+        );
+        for criterion in &self.config.criteria {
+            prompt.push_str(&format!("- {}: {}\n", criterion.name, criterion.description));
+        }
+        if let Some(task) = task {
+            prompt.push_str(&format!("\nTask:\n{}\n", task));
+        }
+        prompt.push_str(&format!("\nOutput to review:\n{}\n", output));
+        prompt.push_str(
+            "\nRespond with JSON only, in the form \
+             {\"scores\": {\"<criterion>\": <0-10>}, \"issues\": [\"...\"], \"suggestions\": [\"...\"], \"summary\": \"...\"}",
+        );
+        prompt
+    }
+
+    /// Turn a critique into feedback for attempt number `attempt`
+    fn evaluate(&self, critique: Critique, attempt: u32) -> ReflectionFeedback {
+        let mut scores = HashMap::new();
+        let mut weighted = 0.0;
+        let mut total_weight = 0.0;
+        for criterion in &self.config.criteria {
+            // Unscored criteria count as failed
+            let score = critique.scores.get(&criterion.name).map_or(0.0, |score| (score / 10.0).clamp(0.0, 1.0));
+            scores.insert(criterion.name.clone(), score);
+            weighted += score * criterion.weight;
+            total_weight += criterion.weight;
+        }
+        let score = if total_weight > 0.0 { weighted / total_weight } else { 0.0 };
+        let passed = score >= self.config.pass_threshold;
+
+        ReflectionFeedback {
+            scores,
+            score,
+            passed,
+            issues: critique.issues,
+            suggestions: critique.suggestions,
+            summary: critique.summary,
+            attempt,
+            needs_revision: !passed && attempt <= self.config.max_revisions,
+        }
+    }
+}
+
+/// Parse the critic's JSON reply, tolerating surrounding prose and code fences
+fn parse_critique(response: &str) -> GraphResult<Critique> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => {
+            return Err(GraphError::node_error(
+                "reflection_node".to_string(),
+                "Critic reply contains no JSON critique".to_string(),
+                None,
+            ))
+        }
+    };
+    serde_json::from_str(json).map_err(|e| GraphError::node_error(
+        "reflection_node".to_string(),
+        format!("Invalid critique from critic agent: {}", e),
+        None,
+    ))
+}
+
+#[async_trait]
+impl<S> Node<S> for ReflectionNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        self.reflect(state).await.map(|_| ())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+/// Edge condition reading the feedback written by a [`ReflectionNode`]
+#[derive(Debug, Clone)]
+pub struct ReflectionCondition {
+    feedback_key: String,
+}
+
+impl ReflectionCondition {
+    /// Condition on the feedback stored under `feedback_key`
+    pub fn new<K: Into<String>>(feedback_key: K) -> Self {
+        Self {
+            feedback_key: feedback_key.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: State> EdgeCondition<S> for ReflectionCondition {
+    async fn evaluate(&self, state: &S) -> GraphResult<bool> {
+        Ok(state.get_value(&self.feedback_key)
+            .and_then(|feedback| feedback.get("needs_revision").and_then(|v| v.as_bool()))
+            .unwrap_or(false))
+    }
+
+    fn condition_id(&self) -> String {
+        format!("reflection:{}", self.feedback_key)
+    }
+
+    fn description(&self) -> String {
+        format!("Output reviewed under '{}' needs revision", self.feedback_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::roles::RoleTemplates;
+    use crate::llm::{LLMManager, LLMConfig, providers::MockProvider};
+    use crate::tools::{ToolRegistry, ToolExecutor};
+
+    fn create_critic(responses: Vec<String>) -> Agent {
+        let mut llm_manager = LLMManager::new(LLMConfig::default());
+        llm_manager.register_provider("mock".to_string(), Arc::new(MockProvider::with_responses(responses)));
+
+        let template = RoleTemplates::quality_assurance();
+        let config = template.to_agent_config("Critic".to_string(), "mock".to_string());
+        Agent::new(config, Arc::new(llm_manager), Arc::new(ToolRegistry::new()), Arc::new(ToolExecutor::new())).unwrap()
+    }
+
+    fn reflection_node() -> ReflectionNode {
+        let config = ReflectionConfig::new("writer")
+            .with_criterion("accuracy", "Claims are correct")
+            .with_weighted_criterion(ReflectionCriterion::new("clarity", "Easy to follow").with_weight(0.5))
+            .with_pass_threshold(0.75)
+            .with_max_revisions(1);
+        ReflectionNode::new(create_critic(Vec::new()), config).unwrap()
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(ReflectionNode::new(create_critic(Vec::new()), ReflectionConfig::new("writer")).is_err());
+        assert!(ReflectionNode::new(
+            create_critic(Vec::new()),
+            ReflectionConfig::default().with_criterion("accuracy", "Claims are correct"),
+        ).is_err());
+    }
+
+    #[test]
+    fn test_parse_critique_from_fenced_reply() {
+        let reply = "Here is my review:\n```json\n{\"scores\": {\"accuracy\": 6}, \"issues\": [\"Wrong date\"], \"summary\": \"Mostly fine\"}\n```";
+        let critique = parse_critique(reply).unwrap();
+        assert_eq!(critique.scores.get("accuracy"), Some(&6.0));
+        assert_eq!(critique.issues, vec!["Wrong date".to_string()]);
+        assert!(critique.suggestions.is_empty());
+        assert!(parse_critique("Looks good to me").is_err());
+    }
+
+    #[test]
+    fn test_revisions_are_bounded() {
+        let node = reflection_node();
+        let critique = || parse_critique(r#"{"scores": {"accuracy": 6, "clarity": 9}, "issues": ["Wrong date"]}"#).unwrap();
+
+        // (0.6 * 1.0 + 0.9 * 0.5) / 1.5 = 0.7
+        let first = node.evaluate(critique(), 1);
+        assert!((first.score - 0.7).abs() < 1e-6);
+        assert!(!first.passed);
+        assert!(first.needs_revision);
+        assert!(first.revision_instructions().contains("- Wrong date"));
+
+        let second = node.evaluate(critique(), 2);
+        assert!(!second.passed);
+        assert!(!second.needs_revision);
+
+        let passing = node.evaluate(parse_critique(r#"{"scores": {"accuracy": 9, "clarity": 8}}"#).unwrap(), 1);
+        assert!(passing.passed);
+        assert!(!passing.needs_revision);
+    }
+
+    #[test]
+    fn test_unscored_criteria_fail() {
+        let node = reflection_node();
+        let feedback = node.evaluate(parse_critique(r#"{"scores": {"clarity": 10}}"#).unwrap(), 1);
+        assert_eq!(feedback.scores.get("accuracy"), Some(&0.0));
+        assert!(!feedback.passed);
+        assert_eq!(node.revision_condition().feedback_key, "reflection");
+    }
+}
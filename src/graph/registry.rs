@@ -0,0 +1,466 @@
+//! Registry of the graphs an application serves.
+//!
+//! A [`GraphRegistry`] keeps the definition of every registered graph and
+//! each version of it seen, renders them as Mermaid flowcharts, tracks their
+//! recent runs and can run them on a one-off input, e.g. from the Studio.
+
+use crate::edge::EdgeType;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::engine::GraphEngine;
+use crate::graph::Graph;
+use crate::node::NodeId;
+use crate::state::State;
+use futures::future::BoxFuture;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Runs kept per graph for statistics
+const DEFAULT_MAX_RUNS: usize = 200;
+
+/// An edge of a registered graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeDefinition {
+    /// Source node
+    pub from: NodeId,
+    /// Target node
+    pub to: NodeId,
+    /// `simple`, `conditional`, `dynamic`, `parallel` or `weighted`
+    pub kind: String,
+    /// Condition outcome, router or weight the edge is taken on
+    pub label: Option<String>,
+}
+
+/// The structure of a registered graph version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphDefinition {
+    /// Graph name, the key it is registered under
+    pub name: String,
+    /// Graph version from its metadata
+    pub version: String,
+    /// Graph description
+    pub description: Option<String>,
+    /// Graph tags
+    pub tags: Vec<String>,
+    /// Node executions start at
+    pub entry_point: Option<NodeId>,
+    /// Nodes executions finish at
+    pub finish_points: Vec<NodeId>,
+    /// Node IDs, sorted
+    pub nodes: Vec<NodeId>,
+    /// Edges, one per target
+    pub edges: Vec<EdgeDefinition>,
+    /// When this version was first registered
+    pub registered_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl GraphDefinition {
+    /// Describe `graph`
+    pub fn of<S: State>(graph: &Graph<S>) -> Self {
+        let metadata = graph.metadata();
+        let mut nodes: Vec<NodeId> = graph.node_ids().into_iter().cloned().collect();
+        nodes.sort();
+        let mut edges = Vec::new();
+        for edge in graph.edges() {
+            let mut add = |to: &NodeId, kind: &str, label: Option<String>| {
+                edges.push(EdgeDefinition { from: edge.from.clone(), to: to.clone(), kind: kind.to_string(), label });
+            };
+            match &edge.edge_type {
+                EdgeType::Simple { target } => add(target, "simple", edge.metadata.name.clone()),
+                EdgeType::Conditional { condition_id, true_target, false_target } => {
+                    add(true_target, "conditional", Some(format!("{}: true", condition_id)));
+                    add(false_target, "conditional", Some(format!("{}: false", condition_id)));
+                }
+                EdgeType::Dynamic { router_id, possible_targets } => {
+                    for target in possible_targets {
+                        add(target, "dynamic", Some(router_id.clone()));
+                    }
+                }
+                EdgeType::Parallel { targets } => {
+                    for target in targets {
+                        add(target, "parallel", None);
+                    }
+                }
+                EdgeType::Weighted { targets } => {
+                    for (target, weight) in targets {
+                        add(target, "weighted", Some(weight.to_string()));
+                    }
+                }
+            }
+        }
+        Self {
+            name: metadata.name.clone(),
+            version: metadata.version.clone(),
+            description: metadata.description.clone(),
+            tags: metadata.tags.clone(),
+            entry_point: graph.entry_point().cloned(),
+            finish_points: graph.finish_points().to_vec(),
+            nodes,
+            edges,
+            registered_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Whether two definitions have the same structure, ignoring when they
+    /// were registered
+    fn same_structure(&self, other: &Self) -> bool {
+        Self { registered_at: other.registered_at, ..self.clone() } == *other
+    }
+
+    /// Render as a Mermaid flowchart
+    ///
+    /// Conditional and dynamic edges are dotted, parallel ones thick.
+    pub fn to_mermaid(&self) -> String {
+        let ids: HashMap<&str, String> = self.nodes.iter().enumerate().map(|(index, node)| (node.as_str(), format!("n{}", index))).collect();
+        let id = |node: &str| ids.get(node).cloned().unwrap_or_else(|| mermaid_text(node));
+        let mut out = String::from("flowchart TD\n");
+        for node in &self.nodes {
+            out.push_str(&format!("    {}[\"{}\"]\n", id(node), mermaid_text(node)));
+        }
+        if let Some(entry) = &self.entry_point {
+            out.push_str(&format!("    __start__([start]) --> {}\n", id(entry)));
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind.as_str() {
+                "conditional" | "dynamic" => "-.->",
+                "parallel" => "==>",
+                _ => "-->",
+            };
+            match &edge.label {
+                Some(label) => out.push_str(&format!("    {} {}|\"{}\"| {}\n", id(&edge.from), arrow, mermaid_text(label), id(&edge.to))),
+                None => out.push_str(&format!("    {} {} {}\n", id(&edge.from), arrow, id(&edge.to))),
+            }
+        }
+        for finish in &self.finish_points {
+            out.push_str(&format!("    {} --> __end__([end])\n", id(finish)));
+        }
+        out
+    }
+}
+
+/// Escape text for a quoted Mermaid label
+fn mermaid_text(text: &str) -> String {
+    text.replace('"', "#quot;").replace('\n', " ")
+}
+
+/// One run of a registered graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Unknown for runs that failed before returning their context
+    pub execution_id: Option<Uuid>,
+    /// Graph version that ran
+    pub version: String,
+    /// Whether the run succeeded
+    pub succeeded: bool,
+    /// Wall-clock duration
+    pub duration_ms: u64,
+    /// When the run ended
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    /// Why the run failed
+    pub error: Option<String>,
+    /// Started by hand with [`GraphRegistry::run`]
+    #[serde(default)]
+    pub manual: bool,
+}
+
+/// Statistics over a graph's recent runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Runs counted
+    pub runs: usize,
+    /// Runs that succeeded
+    pub succeeded: usize,
+    /// Runs that failed
+    pub failed: usize,
+    /// Share of runs that succeeded, 0 to 1; 0 without runs
+    pub success_rate: f64,
+    /// Median duration
+    pub p50_duration_ms: Option<u64>,
+    /// 95th percentile duration
+    pub p95_duration_ms: Option<u64>,
+    /// When the latest run ended
+    pub last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl RunStats {
+    fn of<'a>(runs: impl Iterator<Item = &'a RunRecord>) -> Self {
+        let mut stats = Self::default();
+        let mut durations = Vec::new();
+        for run in runs {
+            stats.runs += 1;
+            if run.succeeded {
+                stats.succeeded += 1;
+            } else {
+                stats.failed += 1;
+            }
+            durations.push(run.duration_ms);
+            stats.last_run_at = stats.last_run_at.max(Some(run.finished_at));
+        }
+        if stats.runs > 0 {
+            stats.success_rate = stats.succeeded as f64 / stats.runs as f64;
+        }
+        durations.sort_unstable();
+        let percentile = |p: f64| {
+            let rank = ((p * durations.len() as f64).ceil() as usize).max(1);
+            durations.get(rank - 1).copied()
+        };
+        stats.p50_duration_ms = percentile(0.5);
+        stats.p95_duration_ms = percentile(0.95);
+        stats
+    }
+}
+
+/// Result of running a graph on a one-off input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManualRun {
+    /// Unknown when the run failed
+    pub execution_id: Option<Uuid>,
+    /// Whether the run succeeded
+    pub succeeded: bool,
+    /// State the run ended with
+    pub state: serde_json::Value,
+    /// Why the run failed
+    pub error: Option<String>,
+    /// Wall-clock duration
+    pub duration_ms: u64,
+}
+
+type Runner = Arc<dyn Fn(serde_json::Value) -> BoxFuture<'static, GraphResult<ManualRun>> + Send + Sync>;
+
+struct Entry {
+    /// Versions seen, oldest first; the last is the one that runs
+    versions: Vec<GraphDefinition>,
+    runner: Runner,
+    runs: VecDeque<RunRecord>,
+}
+
+/// Graphs an application serves, by name
+pub struct GraphRegistry {
+    entries: RwLock<HashMap<String, Entry>>,
+    max_runs: usize,
+}
+
+impl std::fmt::Debug for GraphRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GraphRegistry")
+            .field("graphs", &self.entries.read().keys().collect::<Vec<_>>())
+            .field("max_runs", &self.max_runs)
+            .finish()
+    }
+}
+
+impl Default for GraphRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GraphRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self { entries: RwLock::new(HashMap::new()), max_runs: DEFAULT_MAX_RUNS }
+    }
+
+    /// Keep at most `max_runs` runs per graph for statistics
+    pub fn with_max_runs(mut self, max_runs: usize) -> Self {
+        self.max_runs = max_runs.max(1);
+        self
+    }
+
+    /// Register `graph` under its name, or a new version of it
+    ///
+    /// A graph registered again with a different structure or version is
+    /// added to its version history; the latest registration is the one run.
+    /// Manual runs execute on a sibling of `engine`, so with its services,
+    /// determinism and pools.
+    pub fn register<S>(&self, graph: Arc<Graph<S>>, engine: GraphEngine<S>)
+    where
+        S: State + Clone + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        let definition = GraphDefinition::of(&graph);
+        let name = definition.name.clone();
+        let engine = Arc::new(engine);
+        let runner: Runner = Arc::new(move |input| {
+            let graph = graph.clone();
+            let mut engine = engine.sibling();
+            Box::pin(async move {
+                let mut state: S = serde_json::from_value(input)?;
+                let started = std::time::Instant::now();
+                let result = engine.execute(&graph, &mut state).await;
+                let (execution_id, error) = match result {
+                    Ok(context) => (Some(context.execution_id), None),
+                    Err(error) => (None, Some(error.to_string())),
+                };
+                Ok(ManualRun {
+                    execution_id,
+                    succeeded: error.is_none(),
+                    state: serde_json::to_value(&state)?,
+                    error,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                })
+            })
+        });
+
+        let mut entries = self.entries.write();
+        match entries.get_mut(&name) {
+            Some(entry) => {
+                if !entry.versions.last().is_some_and(|latest| latest.same_structure(&definition)) {
+                    entry.versions.push(definition);
+                }
+                entry.runner = runner;
+            }
+            None => {
+                entries.insert(name, Entry { versions: vec![definition], runner, runs: VecDeque::new() });
+            }
+        }
+    }
+
+    /// Names of the registered graphs, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.entries.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Latest definition of every graph, sorted by name
+    pub fn list(&self) -> Vec<GraphDefinition> {
+        let mut definitions: Vec<_> = self.entries.read().values().filter_map(|entry| entry.versions.last().cloned()).collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// Latest definition of a graph
+    pub fn get(&self, name: &str) -> Option<GraphDefinition> {
+        self.entries.read().get(name).and_then(|entry| entry.versions.last().cloned())
+    }
+
+    /// Every version of a graph registered, oldest first
+    pub fn versions(&self, name: &str) -> Vec<GraphDefinition> {
+        self.entries.read().get(name).map(|entry| entry.versions.clone()).unwrap_or_default()
+    }
+
+    /// Record a run of a graph made outside the registry
+    pub fn record_run(&self, name: &str, run: RunRecord) {
+        if let Some(entry) = self.entries.write().get_mut(name) {
+            if entry.runs.len() >= self.max_runs {
+                entry.runs.pop_front();
+            }
+            entry.runs.push_back(run);
+        }
+    }
+
+    /// A graph's recent runs, most recent first
+    pub fn recent_runs(&self, name: &str, limit: usize) -> Vec<RunRecord> {
+        self.entries.read().get(name).map(|entry| entry.runs.iter().rev().take(limit).cloned().collect()).unwrap_or_default()
+    }
+
+    /// Statistics over a graph's recent runs
+    pub fn stats(&self, name: &str) -> RunStats {
+        self.entries.read().get(name).map(|entry| RunStats::of(entry.runs.iter())).unwrap_or_default()
+    }
+
+    /// Run the latest version of a graph once on `input`, its initial state as JSON
+    ///
+    /// A run that fails still returns, with the error and the state it
+    /// stopped in. Errors are for unknown graphs and input that is not a
+    /// valid state.
+    pub async fn run(&self, name: &str, input: serde_json::Value) -> GraphResult<ManualRun> {
+        let (runner, version) = {
+            let entries = self.entries.read();
+            let entry = entries.get(name)
+                .ok_or_else(|| GraphError::validation_error(format!("No graph '{}' registered", name)))?;
+            let version = entry.versions.last().map(|definition| definition.version.clone()).unwrap_or_default();
+            (entry.runner.clone(), version)
+        };
+        let run = runner(input).await?;
+        self.record_run(name, RunRecord {
+            execution_id: run.execution_id,
+            version,
+            succeeded: run.succeeded,
+            duration_ms: run.duration_ms,
+            finished_at: chrono::Utc::now(),
+            error: run.error.clone(),
+            manual: true,
+        });
+        Ok(run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::graph::GraphMetadata;
+    use crate::node::Node;
+    use async_trait::async_trait;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    struct TestState {
+        value: i32,
+    }
+
+    #[derive(Debug)]
+    struct DoubleNode;
+
+    #[async_trait]
+    impl Node<TestState> for DoubleNode {
+        async fn invoke(&self, state: &mut TestState) -> GraphResult<()> {
+            if state.value < 0 {
+                return Err(GraphError::validation_error("negative input"));
+            }
+            state.value *= 2;
+            Ok(())
+        }
+    }
+
+    fn graph(version: &str, nodes: &[&str]) -> Arc<Graph<TestState>> {
+        let mut graph = Graph::with_metadata(GraphMetadata {
+            name: "doubler".to_string(),
+            version: version.to_string(),
+            ..GraphMetadata::default()
+        });
+        for node in nodes {
+            graph.add_node(node.to_string(), DoubleNode).unwrap();
+        }
+        graph.set_entry_point(nodes[0].to_string()).unwrap();
+        for pair in nodes.windows(2) {
+            graph.add_edge(Edge::simple(pair[0], pair[1])).unwrap();
+        }
+        graph.add_finish_point(nodes[nodes.len() - 1].to_string()).unwrap();
+        Arc::new(graph)
+    }
+
+    #[tokio::test]
+    async fn test_registry_versions_mermaid_and_manual_runs() {
+        let registry = GraphRegistry::new();
+        registry.register(graph("1.0.0", &["a"]), GraphEngine::new());
+        registry.register(graph("1.0.0", &["a"]), GraphEngine::new());
+        registry.register(graph("1.1.0", &["a", "b"]), GraphEngine::new());
+
+        assert_eq!(registry.names(), vec!["doubler".to_string()]);
+        let versions: Vec<_> = registry.versions("doubler").into_iter().map(|definition| definition.version).collect();
+        assert_eq!(versions, vec!["1.0.0", "1.1.0"]);
+        assert_eq!(
+            registry.get("doubler").unwrap().to_mermaid(),
+            "flowchart TD\n    n0[\"a\"]\n    n1[\"b\"]\n    __start__([start]) --> n0\n    n0 --> n1\n    n1 --> __end__([end])\n"
+        );
+
+        let run = registry.run("doubler", serde_json::json!({ "value": 3 })).await.unwrap();
+        assert!(run.succeeded);
+        assert_eq!(run.state, serde_json::json!({ "value": 12 }));
+        let failed = registry.run("doubler", serde_json::json!({ "value": -1 })).await.unwrap();
+        assert!(!failed.succeeded);
+        assert!(registry.run("doubler", serde_json::json!({ "other": 1 })).await.is_err());
+        assert!(registry.run("missing", serde_json::json!({})).await.is_err());
+
+        let stats = registry.stats("doubler");
+        assert_eq!((stats.runs, stats.succeeded, stats.failed), (2, 1, 1));
+        assert_eq!(stats.success_rate, 0.5);
+        let recent = registry.recent_runs("doubler", 10);
+        assert_eq!(recent[0].error, failed.error);
+        assert_eq!(recent[1].execution_id, run.execution_id);
+        assert!(recent.iter().all(|run| run.manual && run.version == "1.1.0"));
+    }
+}
@@ -0,0 +1,92 @@
+//! What a node reports to the engine besides the state it leaves behind.
+//!
+//! LLM usage and the candidates an aggregating node chose from belong to the
+//! execution, not to the graph's state: kept in the state they would be
+//! checkpointed, carried into later nodes and returned with the output. A
+//! node reports them while it runs, and the engine collects them around each
+//! invocation with [`NodeReports`].
+
+use crate::llm::LLMUsage;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::future::Future;
+use std::sync::Arc;
+
+/// What one invocation of a node reported
+#[derive(Debug, Default, Clone)]
+pub(crate) struct NodeReport {
+    /// LLM usage of every completion the node made
+    pub(crate) llm_usage: Option<LLMUsage>,
+    /// Candidates an aggregating node chose from
+    pub(crate) candidates: Option<Value>,
+}
+
+/// Collects what a node reports while it runs
+///
+/// Shared, so that what a node reported before it timed out is still there
+/// once it has been given up on.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct NodeReports(Arc<Mutex<NodeReport>>);
+
+tokio::task_local! {
+    static REPORTS: NodeReports;
+}
+
+impl NodeReports {
+    /// Run `future` with what it reports collected here
+    pub(crate) async fn scope<F: Future>(&self, future: F) -> F::Output {
+        REPORTS.scope(self.clone(), future).await
+    }
+
+    /// Take what was reported so far
+    pub(crate) fn take(&self) -> NodeReport {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+/// Report LLM usage of the running node, adding to what it already reported
+///
+/// Outside a node run by the engine there is no one to report to, and the
+/// usage is dropped.
+pub(crate) fn llm_usage(usage: &LLMUsage) {
+    let _ = REPORTS.try_with(|reports| {
+        reports.0.lock().llm_usage.get_or_insert_with(LLMUsage::default).merge(usage);
+    });
+}
+
+/// Report the candidates the running node chose from, replacing any it already reported
+pub(crate) fn candidates(candidates: Value) {
+    let _ = REPORTS.try_with(|reports| reports.0.lock().candidates = Some(candidates));
+}
+
+/// Carry the current node's reports into `future`, for work it spawns on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let reports = REPORTS.try_with(NodeReports::clone).ok();
+    async move {
+        match reports {
+            Some(reports) => REPORTS.scope(reports, future).await,
+            None => future.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reports_are_collected_per_scope() {
+        let usage = LLMUsage { total_tokens: 150, cost: 0.04, ..Default::default() };
+        llm_usage(&usage);
+
+        let reports = NodeReports::default();
+        reports.scope(async {
+            llm_usage(&usage);
+            inherit(async { llm_usage(&usage) }).await;
+        }).await;
+
+        let report = reports.take();
+        assert_eq!(report.llm_usage.unwrap().total_tokens, 300);
+        assert!(reports.take().llm_usage.is_none());
+    }
+}
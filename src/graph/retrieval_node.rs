@@ -0,0 +1,171 @@
+//! Retrieval nodes for retrieval-augmented generation
+//! Looks up the chunks most similar to a query in a vector store and writes them to the state as context
+
+use crate::agents::rerank::{self, Reranker};
+use crate::agents::vector::{Embedder, MetadataFilter, VectorMatch, VectorStore};
+use crate::enterprise::guardrails::{InputOrigin, PromptGuard, ScreeningSubject};
+use crate::error::{GraphError, GraphResult};
+use crate::graph::flags::FlagContext;
+use crate::node::{Node, NodeMetadata};
+use crate::state::State;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Node retrieving context for the query in the state
+///
+/// The query is read from `query_key` and the matching chunks are written
+/// to `context_key` as numbered passages, ready to be placed in a prompt,
+/// and to `sources_key` as the full matches.
+#[derive(Debug)]
+pub struct RetrievalNode {
+    store: Arc<dyn VectorStore>,
+    embedder: Arc<dyn Embedder>,
+    collection: String,
+    top_k: usize,
+    filter: Option<MetadataFilter>,
+    /// Reranker and how many candidates it chooses the `top_k` from
+    reranker: Option<(Arc<dyn Reranker>, usize)>,
+    query_key: String,
+    context_key: String,
+    sources_key: String,
+    prompt_guard: Option<Arc<PromptGuard>>,
+    metadata: NodeMetadata,
+}
+
+impl RetrievalNode {
+    /// Retrieve from `collection` in `store`
+    pub fn new<C: Into<String>>(store: Arc<dyn VectorStore>, embedder: Arc<dyn Embedder>, collection: C) -> Self {
+        let metadata = NodeMetadata::new("RetrievalNode")
+            .with_description("Retrieves context from a vector store")
+            .with_tag("retrieval")
+            .with_parallel_safe(true);
+
+        Self {
+            store,
+            embedder,
+            collection: collection.into(),
+            top_k: 4,
+            filter: None,
+            reranker: None,
+            query_key: "query".to_string(),
+            context_key: "context".to_string(),
+            sources_key: "sources".to_string(),
+            prompt_guard: None,
+            metadata,
+        }
+    }
+
+    /// Retrieve at most `top_k` chunks
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+
+    /// Only retrieve chunks matching `filter`
+    pub fn with_filter(mut self, filter: MetadataFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Retrieve `candidates` chunks and keep the `top_k` the reranker rates most relevant
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>, candidates: usize) -> Self {
+        self.reranker = Some((reranker, candidates));
+        self
+    }
+
+    /// Read the query from `key`
+    pub fn with_query_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.query_key = key.into();
+        self
+    }
+
+    /// Write the context passages under `key`
+    pub fn with_context_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.context_key = key.into();
+        self
+    }
+
+    /// Write the full matches under `key`
+    pub fn with_sources_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.sources_key = key.into();
+        self
+    }
+
+    /// Screen retrieved chunks for prompt injection before they are written to the context
+    ///
+    /// Chunks are treated as documents, so by default injected instructions
+    /// are stripped from them; a guard that blocks documents fails the node.
+    pub fn with_prompt_guard(mut self, guard: PromptGuard) -> Self {
+        self.prompt_guard = Some(Arc::new(guard));
+        self
+    }
+
+    /// Pass retrieved chunks through the prompt guard, if the node has one
+    async fn screen<S: State>(&self, state: &S, mut matches: Vec<VectorMatch>) -> GraphResult<Vec<VectorMatch>> {
+        let Some(guard) = &self.prompt_guard else {
+            return Ok(matches);
+        };
+        let subject = FlagContext::from_state(state);
+        let subject = ScreeningSubject {
+            tenant_id: subject.tenant.as_deref(),
+            user_id: subject.user.as_deref(),
+        };
+        for chunk in &mut matches {
+            let screened = guard.screen(&chunk.content, InputOrigin::Document, &chunk.id, subject).await
+                .map_err(|e| GraphError::validation_error(e.to_string()))?;
+            chunk.content = screened.text;
+        }
+        Ok(matches)
+    }
+
+    async fn retrieve<S: State>(&self, state: &mut S) -> GraphResult<Vec<VectorMatch>> {
+        let query = match state.get_value(&self.query_key) {
+            Some(serde_json::Value::String(query)) => query,
+            Some(other) => other.to_string(),
+            None => return Err(GraphError::state_error(format!("No query under '{}'", self.query_key))),
+        };
+
+        let embedding = self.embedder.embed(std::slice::from_ref(&query)).await
+            .map_err(|e| GraphError::node_error("retrieval_node".to_string(), format!("Failed to embed query: {}", e), Some(Box::new(e))))?
+            .pop()
+            .unwrap_or_default();
+        let candidates = self.reranker.as_ref().map_or(self.top_k, |(_, candidates)| (*candidates).max(self.top_k));
+        let mut matches = self.store.search(&self.collection, &embedding, candidates, self.filter.as_ref()).await
+            .map_err(|e| GraphError::node_error("retrieval_node".to_string(), format!("Vector search failed: {}", e), Some(Box::new(e))))?;
+        if let Some((reranker, _)) = &self.reranker {
+            matches = rerank::rerank(reranker.as_ref(), &query, matches, self.top_k).await
+                .map_err(|e| GraphError::node_error("retrieval_node".to_string(), format!("Reranking failed: {}", e), Some(Box::new(e))))?;
+        }
+
+        tracing::debug!("Retrieved {} chunks from '{}'", matches.len(), self.collection);
+        let matches = self.screen(state, matches).await?;
+        state.set_value(&self.context_key, serde_json::Value::String(format_context(&matches)))?;
+        let sources = serde_json::to_value(&matches)
+            .map_err(|e| GraphError::state_error(format!("Failed to serialize retrieved sources: {}", e)))?;
+        state.set_value(&self.sources_key, sources)?;
+        Ok(matches)
+    }
+}
+
+/// Number the passages so an answer can cite them
+fn format_context(matches: &[VectorMatch]) -> String {
+    matches.iter()
+        .enumerate()
+        .map(|(i, m)| format!("[{}] {}", i + 1, m.content.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[async_trait]
+impl<S> Node<S> for RetrievalNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        self.retrieve(state).await.map(|_| ())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
@@ -0,0 +1,295 @@
+//! Execution-level retries: rerun a whole failed execution from its input state
+//! with exponential backoff, without repeating side effects that already happened.
+//!
+//! Every attempt runs under the same idempotency key. Calls to tools with side
+//! effects are recorded in an [`IdempotencyLedger`] under a key derived from it,
+//! so an attempt that reaches a call an earlier attempt already made gets the
+//! recorded output back instead of calling the tool again. The derived key is
+//! also passed to the tool as `idempotency_key` in its input context, for APIs
+//! that deduplicate requests themselves.
+
+use crate::error::GraphError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tool input context entry carrying the idempotency key of a call
+pub const IDEMPOTENCY_KEY_CONTEXT_KEY: &str = "idempotency_key";
+
+/// When and how often a failed execution is run again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct ExecutionRetryPolicy {
+    /// Runs in total, including the first; 1 disables retries
+    pub max_attempts: u32,
+    /// Wait before the second attempt
+    pub initial_backoff_ms: u64,
+    /// Longest wait between attempts
+    pub max_backoff_ms: u64,
+    /// Factor the wait grows by after each attempt
+    pub multiplier: f64,
+    /// Wait a random time between half and all of the backoff, so executions
+    /// that failed together do not retry together
+    pub jitter: bool,
+    /// Error categories worth retrying, as reported by [`GraphError::category`]
+    pub retry_on: Vec<String>,
+}
+
+impl Default for ExecutionRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60_000,
+            multiplier: 2.0,
+            jitter: true,
+            retry_on: ["timeout", "external_service", "resource", "concurrency"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+impl ExecutionRetryPolicy {
+    /// Retry up to `max_attempts` runs in total with the default backoff
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    /// Set the first wait and the longest wait between attempts
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff_ms = initial.as_millis() as u64;
+        self.max_backoff_ms = max.as_millis() as u64;
+        self
+    }
+
+    /// Set the factor the wait grows by
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Enable or disable jitter
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the error categories worth retrying
+    pub fn with_retry_on<I, C>(mut self, categories: I) -> Self
+    where
+        I: IntoIterator<Item = C>,
+        C: Into<String>,
+    {
+        self.retry_on = categories.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Wait after the given failed attempt, counting from 1, before jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(63) as i32;
+        let millis = self.initial_backoff_ms as f64 * self.multiplier.max(1.0).powi(exponent);
+        Duration::from_millis(millis.min(self.max_backoff_ms as f64) as u64)
+    }
+
+    /// Whether an execution that failed with `error` on `attempt` should run again
+    ///
+    /// Node errors are judged by the error they wrap. Suspensions are never
+    /// retried: the execution is waiting, not failing.
+    pub fn should_retry(&self, error: &GraphError, attempt: u32) -> bool {
+        if attempt >= self.max_attempts || error.is_suspended() {
+            return false;
+        }
+        let category = retry_category(error);
+        self.retry_on.iter().any(|retryable| retryable == category)
+    }
+
+    /// Wait before the next attempt, or `None` if the execution should fail now
+    pub fn next_delay(&self, error: &GraphError, attempt: u32) -> Option<Duration> {
+        if !self.should_retry(error, attempt) {
+            return None;
+        }
+        let backoff = self.backoff(attempt);
+        if !self.jitter {
+            return Some(backoff);
+        }
+        let factor = 0.5 + crate::graph::determinism::random_f64() * 0.5;
+        Some(backoff.mul_f64(factor))
+    }
+}
+
+/// Category of the innermost graph error behind `error`
+fn retry_category(error: &GraphError) -> &'static str {
+    if let GraphError::NodeError { source: Some(source), .. } = error {
+        if let Some(inner) = source.downcast_ref::<GraphError>() {
+            return retry_category(inner);
+        }
+    }
+    error.category()
+}
+
+/// Outputs of side-effecting tool calls, by idempotency key
+///
+/// Cloning shares the ledger. Give a graph one with
+/// [`Graph::set_idempotency_ledger`](crate::graph::Graph::set_idempotency_ledger)
+/// to keep it across executions, e.g. so a redelivered trigger or a
+/// dead-letter retry run under the same key skips effects already performed.
+#[derive(Debug, Clone, Default)]
+pub struct IdempotencyLedger {
+    entries: Arc<parking_lot::Mutex<HashMap<String, serde_json::Value>>>,
+}
+
+impl IdempotencyLedger {
+    /// Create an empty ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recorded output of a call
+    pub fn get(&self, effect_key: &str) -> Option<serde_json::Value> {
+        self.entries.lock().get(effect_key).cloned()
+    }
+
+    /// Record the output of a call
+    pub fn record(&self, effect_key: impl Into<String>, output: serde_json::Value) {
+        self.entries.lock().insert(effect_key.into(), output);
+    }
+
+    /// Drop everything recorded under an execution's idempotency key
+    pub fn forget(&self, key: &str) {
+        let prefix = format!("{}/", key);
+        self.entries.lock().retain(|effect_key, _| !effect_key.starts_with(&prefix));
+    }
+
+    /// Number of recorded calls
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    /// Whether nothing is recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+}
+
+tokio::task_local! {
+    static IDEMPOTENCY: IdempotencyScope;
+}
+
+#[derive(Debug, Clone)]
+struct IdempotencyScope {
+    key: String,
+    ledger: IdempotencyLedger,
+    /// Calls made so far in this attempt, per tool and input
+    calls: Arc<parking_lot::Mutex<HashMap<String, u32>>>,
+}
+
+/// Run one attempt of an execution under an idempotency key
+pub async fn with_idempotency<F: Future>(key: String, ledger: IdempotencyLedger, future: F) -> F::Output {
+    let scope = IdempotencyScope {
+        key,
+        ledger,
+        calls: Arc::default(),
+    };
+    IDEMPOTENCY.scope(scope, future).await
+}
+
+/// Carry the current attempt's key, ledger and call counts into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = IDEMPOTENCY.try_with(IdempotencyScope::clone).ok();
+    async move {
+        match value {
+            Some(value) => IDEMPOTENCY.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Idempotency key of the current execution, if it runs under one
+pub fn current_key() -> Option<String> {
+    IDEMPOTENCY.try_with(|scope| scope.key.clone()).ok()
+}
+
+/// Ledger of the current execution, if it runs under an idempotency key
+pub(crate) fn current_ledger() -> Option<IdempotencyLedger> {
+    IDEMPOTENCY.try_with(|scope| scope.ledger.clone()).ok()
+}
+
+/// Claim the next call of `tool_id` with `input` in the current attempt
+///
+/// Returns the call's effect key and the ledger it is recorded in, or `None`
+/// outside an execution. Identical calls are numbered in the order they are
+/// made, so a node calling the same tool twice with the same input performs
+/// two effects, and a retry replays both.
+pub(crate) fn next_call(tool_id: &str, input: &serde_json::Value) -> Option<(String, IdempotencyLedger)> {
+    IDEMPOTENCY
+        .try_with(|scope| {
+            let digest = Sha256::digest(serde_json::to_string(input).unwrap_or_default().as_bytes());
+            let hash: String = digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+            let call = format!("{}/{}", tool_id, hash);
+            let mut calls = scope.calls.lock();
+            let count = calls.entry(call.clone()).or_insert(0);
+            *count += 1;
+            (format!("{}/{}/{}", scope.key, call, count), scope.ledger.clone())
+        })
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_retryable_errors() {
+        let policy = ExecutionRetryPolicy::new(4)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .with_jitter(false);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(300));
+
+        let outage = GraphError::ExternalServiceError("503".to_string());
+        assert_eq!(policy.next_delay(&outage, 1), Some(Duration::from_millis(100)));
+        assert_eq!(policy.next_delay(&outage, 4), None);
+        assert!(!policy.should_retry(&GraphError::validation_error("bad input"), 1));
+
+        let wrapped = GraphError::NodeError {
+            node_id: "charge".to_string(),
+            message: "call failed".to_string(),
+            source: Some(Box::new(GraphError::Timeout { seconds: 5 })),
+        };
+        assert!(policy.should_retry(&wrapped, 1));
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_numbered_per_attempt() {
+        let ledger = IdempotencyLedger::new();
+        assert!(next_call("charge", &serde_json::json!({"amount": 10})).is_none());
+
+        let attempt = || async {
+            let input = serde_json::json!({"amount": 10});
+            let (first, _) = next_call("charge", &input).unwrap();
+            let (second, ledger) = next_call("charge", &input).unwrap();
+            ledger.record(first.clone(), serde_json::json!("ch_1"));
+            (first, second)
+        };
+        let (first, second) = with_idempotency("exec-1".to_string(), ledger.clone(), attempt()).await;
+        assert_ne!(first, second);
+        assert!(first.starts_with("exec-1/charge/"));
+
+        // A retry makes the same calls under the same keys
+        let (retried, _) = with_idempotency("exec-1".to_string(), ledger.clone(), attempt()).await;
+        assert_eq!(retried, first);
+        assert_eq!(ledger.get(&first), Some(serde_json::json!("ch_1")));
+
+        ledger.forget("exec-1");
+        assert!(ledger.is_empty());
+    }
+}
@@ -25,6 +25,8 @@ pub struct RoutingAgentNode {
     command_parser: CommandParser,
     /// Fallback node if no routing rules match
     fallback_node: Option<String>,
+    /// Minimum model confidence and the node to route to below it
+    confidence_route: Option<(f64, String)>,
     /// Node metadata
     metadata: NodeMetadata,
 }
@@ -44,6 +46,7 @@ impl RoutingAgentNode {
             routing_rules: HashMap::new(),
             command_parser: CommandParser::new(),
             fallback_node: None,
+            confidence_route: None,
             metadata,
         }
     }
@@ -66,6 +69,16 @@ impl RoutingAgentNode {
         self
     }
 
+    /// Route to `low_confidence_node` when the agent's response confidence is below `threshold`.
+    ///
+    /// Confidence comes from token logprobs, so the agent should be configured with
+    /// `request_logprobs` and use a provider that supports them. Responses without
+    /// logprobs are routed by the regular rules.
+    pub fn with_confidence_threshold<S: Into<String>>(mut self, threshold: f64, low_confidence_node: S) -> Self {
+        self.confidence_route = Some((threshold, low_confidence_node.into()));
+        self
+    }
+
     /// Execute agent and determine routing
     pub async fn execute_with_routing<S: State>(
         &self, 
@@ -86,14 +99,22 @@ impl RoutingAgentNode {
                 Some(Box::new(e)),
             ))?;
 
+        let confidence = agent.state().last_confidence;
+        let usage = agent.state().last_task_usage.clone();
+        drop(agent);
+        super::agent_node::report_llm_usage(&usage);
+
         tracing::info!("Agent response: {} characters", response.len());
 
         // Analyze response for routing decisions
-        let command = self.analyze_routing_decision(&response, context)?;
+        let command = self.analyze_routing_decision(&response, confidence, context)?;
 
         // Update state with response if not ending
         if !command.is_end() {
             self.update_state(state, &response)?;
+            if let Some(confidence) = confidence {
+                state.set_value("agent_confidence", serde_json::json!(confidence))?;
+            }
         }
 
         Ok(command)
@@ -131,7 +152,12 @@ impl RoutingAgentNode {
     }
 
     /// Analyze agent response for routing decisions
-    fn analyze_routing_decision(&self, response: &str, context: &CommandContext) -> GraphResult<Command> {
+    fn analyze_routing_decision(
+        &self,
+        response: &str,
+        confidence: Option<f64>,
+        context: &CommandContext,
+    ) -> GraphResult<Command> {
         // First, try to parse explicit commands
         let explicit_command = self.command_parser.parse_command(response)?;
         if !explicit_command.is_continue() {
@@ -139,6 +165,12 @@ impl RoutingAgentNode {
             return Ok(explicit_command);
         }
 
+        // Low model confidence takes precedence over keyword heuristics
+        if let Some(target_node) = self.low_confidence_target(confidence) {
+            tracing::info!("Response confidence {:?} below threshold, going to '{}'", confidence, target_node);
+            return Ok(Command::goto(target_node.to_string()));
+        }
+
         // Check routing rules based on response content
         for (condition, target_node) in &self.routing_rules {
             if self.matches_condition(response, condition) {
@@ -157,6 +189,16 @@ impl RoutingAgentNode {
         Ok(Command::continue_())
     }
 
+    /// Target node when confidence falls below the configured threshold
+    fn low_confidence_target(&self, confidence: Option<f64>) -> Option<&str> {
+        match (&self.confidence_route, confidence) {
+            (Some((threshold, target_node)), Some(confidence)) if confidence < *threshold => {
+                Some(target_node.as_str())
+            }
+            _ => None,
+        }
+    }
+
     /// Check if response matches a routing condition
     fn matches_condition(&self, response: &str, condition: &str) -> bool {
         let response_lower = response.to_lowercase();
@@ -203,6 +245,7 @@ impl RoutingAgentNode {
         RoutingInfo {
             rules: self.routing_rules.clone(),
             fallback_node: self.fallback_node.clone(),
+            confidence_threshold: self.confidence_route.as_ref().map(|(threshold, _)| *threshold),
             supports_commands: true,
         }
     }
@@ -226,6 +269,7 @@ where
             ))?;
 
         self.update_state(state, &response)?;
+        super::agent_node::report_llm_usage(&agent.state().last_task_usage);
         Ok(())
     }
 
@@ -241,6 +285,8 @@ pub struct RoutingInfo {
     pub rules: HashMap<String, String>,
     /// Fallback node
     pub fallback_node: Option<String>,
+    /// Confidence threshold below which the node reroutes
+    pub confidence_threshold: Option<f64>,
     /// Whether the node supports command parsing
     pub supports_commands: bool,
 }
@@ -415,6 +461,18 @@ mod tests {
         assert!(info.supports_commands);
     }
 
+    #[tokio::test]
+    async fn test_confidence_threshold_routing() {
+        let agent = create_test_agent("TestAgent").await;
+        let routing_node = RoutingAgentNode::new(agent, "Process: {input}".to_string())
+            .with_confidence_threshold(0.7, "human_review");
+
+        assert_eq!(routing_node.routing_info().confidence_threshold, Some(0.7));
+        assert_eq!(routing_node.low_confidence_target(Some(0.5)), Some("human_review"));
+        assert_eq!(routing_node.low_confidence_target(Some(0.9)), None);
+        assert_eq!(routing_node.low_confidence_target(None), None);
+    }
+
     #[tokio::test]
     async fn test_multi_agent_coordinator() {
         let agent1 = create_test_agent("Agent1").await;
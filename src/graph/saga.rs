@@ -0,0 +1,381 @@
+//! Compensating actions for executions that span several external transactions.
+//!
+//! A node that books a flight cannot roll it back when a later node fails to
+//! book the hotel. Instead it registers a compensation, e.g. `cancel_flight`
+//! with the booking reference, with [`register`]. Compensations are kept in
+//! the state, so they are checkpointed with the work they undo. When an
+//! execution fails for good, after its retry policy gave up, the graph's
+//! [`Saga`] runs the compensations it registered in reverse order, each with
+//! its own retries, and records how each went in the execution context.
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::retry::ExecutionRetryPolicy;
+use crate::node::NodeId;
+use crate::state::State;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// State key the registered compensations are kept under
+pub const SAGA_STATE_KEY: &str = "__saga";
+
+/// Whether a compensation has run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompensationStatus {
+    /// Registered; runs if the execution fails
+    Pending,
+    /// Ran and succeeded
+    Compensated,
+    /// Every attempt failed; needs attention
+    Failed,
+}
+
+/// A compensating action registered by a node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Compensation {
+    /// Compensator to run, e.g. `cancel_flight`
+    pub action: String,
+    /// What the compensator needs to undo the node's work
+    pub payload: Value,
+    /// Node that registered it, once the node finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<NodeId>,
+    /// Step the node ran at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<u64>,
+    /// Attempts made so far
+    #[serde(default)]
+    pub attempts: u32,
+    /// Error of the last attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Whether it has run
+    pub status: CompensationStatus,
+}
+
+/// Compensations of one execution, as kept in its state
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SagaState {
+    /// Compensations in the order they were registered
+    pub compensations: Vec<Compensation>,
+}
+
+impl SagaState {
+    /// The compensations kept in `state`, none if it has none
+    pub fn from_state<S: State>(state: &S) -> GraphResult<Self> {
+        match state.get_value(SAGA_STATE_KEY) {
+            Some(value) if !value.is_null() => Ok(serde_json::from_value(value)?),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Keep the compensations in `state`
+    pub fn save<S: State>(&self, state: &mut S) -> GraphResult<()> {
+        state.set_value(SAGA_STATE_KEY, serde_json::to_value(self)?)?;
+        if state.get_value(SAGA_STATE_KEY).is_none() {
+            return Err(GraphError::state_error(
+                "Compensations need a state that stores values with set_value",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Register a compensation that undoes the current node's work if the execution fails
+///
+/// Register it once the work is done, so a node that fails half-way does
+/// not leave a compensation for something that never happened. Registering
+/// the same action and payload again, e.g. from a retried attempt, keeps the
+/// pending compensation already registered.
+pub fn register<S: State>(state: &mut S, action: impl Into<String>, payload: Value) -> GraphResult<()> {
+    let action = action.into();
+    let mut saga = SagaState::from_state(state)?;
+    if saga.compensations.iter().any(|c| c.status == CompensationStatus::Pending && c.action == action && c.payload == payload) {
+        return Ok(());
+    }
+    saga.compensations.push(Compensation {
+        action,
+        payload,
+        node_id: None,
+        step: None,
+        attempts: 0,
+        last_error: None,
+        status: CompensationStatus::Pending,
+    });
+    saga.save(state)
+}
+
+/// Attribute compensations registered by the node that just finished to it
+pub(crate) fn stamp<S: State>(state: &mut S, node_id: &NodeId, step: u64) -> GraphResult<()> {
+    let mut saga = SagaState::from_state(state)?;
+    let mut stamped = false;
+    for compensation in saga.compensations.iter_mut().filter(|c| c.node_id.is_none()) {
+        compensation.node_id = Some(node_id.clone());
+        compensation.step = Some(step);
+        stamped = true;
+    }
+    if stamped {
+        saga.save(state)?;
+    }
+    Ok(())
+}
+
+/// Keep the pending compensations of a failed attempt in the state the next attempt starts from
+///
+/// A retried execution starts again from its input state, but the work the
+/// failed attempt did is still done, so it must still be undone if the
+/// execution fails for good.
+pub(crate) fn carry_over<S: State>(attempt: &S, next: &mut S) -> GraphResult<()> {
+    let failed = SagaState::from_state(attempt)?;
+    let mut saga = SagaState::from_state(next)?;
+    let kept = saga.compensations.len();
+    for compensation in failed.compensations {
+        if compensation.status == CompensationStatus::Pending && !saga.compensations.contains(&compensation) {
+            saga.compensations.push(compensation);
+        }
+    }
+    if saga.compensations.len() > kept {
+        saga.save(next)?;
+    }
+    Ok(())
+}
+
+/// Runs one kind of compensation
+#[async_trait]
+pub trait Compensator: Send + Sync {
+    /// Undo what `compensation` describes
+    ///
+    /// Compensations may run more than once, e.g. when an attempt timed out
+    /// after it took effect, so undoing something already undone should
+    /// succeed.
+    async fn compensate(&self, compensation: &Compensation) -> GraphResult<()>;
+}
+
+#[async_trait]
+impl<F, Fut> Compensator for F
+where
+    F: Fn(Compensation) -> Fut + Send + Sync,
+    Fut: std::future::Future<Output = GraphResult<()>> + Send,
+{
+    async fn compensate(&self, compensation: &Compensation) -> GraphResult<()> {
+        self(compensation.clone()).await
+    }
+}
+
+/// How one compensation went, as recorded in the execution context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompensationRecord {
+    /// Compensator that ran
+    pub action: String,
+    /// Node whose work it undid
+    pub node_id: Option<NodeId>,
+    /// Step the node ran at
+    pub step: Option<u64>,
+    /// Attempts it took
+    pub attempts: u32,
+    /// Whether it succeeded
+    pub status: CompensationStatus,
+    /// Error of the last attempt, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Compensators that undo a failed execution's work
+///
+/// Give a graph one with [`Graph::set_saga`](crate::graph::Graph::set_saga).
+#[derive(Clone)]
+pub struct Saga {
+    compensators: HashMap<String, Arc<dyn Compensator>>,
+    retry: ExecutionRetryPolicy,
+}
+
+impl std::fmt::Debug for Saga {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Saga")
+            .field("actions", &self.compensators.keys().collect::<Vec<_>>())
+            .field("retry", &self.retry)
+            .finish()
+    }
+}
+
+impl Default for Saga {
+    fn default() -> Self {
+        Self {
+            compensators: HashMap::new(),
+            retry: ExecutionRetryPolicy::new(5).with_backoff(std::time::Duration::from_millis(500), std::time::Duration::from_secs(30)),
+        }
+    }
+}
+
+impl Saga {
+    /// A saga with no compensators, trying each compensation up to 5 times on transient errors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run compensations for `action` with `compensator`
+    pub fn with_compensator<C: Compensator + 'static>(mut self, action: impl Into<String>, compensator: C) -> Self {
+        self.compensators.insert(action.into(), Arc::new(compensator));
+        self
+    }
+
+    /// Try each compensation, and back off between attempts, as `policy` says
+    pub fn with_retry(mut self, policy: ExecutionRetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Run the pending compensations in `state`, most recent first
+    ///
+    /// A compensation that fails every attempt does not stop the others; it
+    /// is marked failed in the state and in the returned records.
+    pub(crate) async fn compensate<S: State>(&self, state: &mut S, failure: &GraphError) -> Vec<CompensationRecord> {
+        let mut saga = match SagaState::from_state(state) {
+            Ok(saga) => saga,
+            Err(error) => {
+                tracing::error!(error = %error, "Compensations in state are unreadable");
+                return Vec::new();
+            }
+        };
+        let mut records = Vec::new();
+        for compensation in saga.compensations.iter_mut().rev() {
+            if compensation.status != CompensationStatus::Pending {
+                continue;
+            }
+            match self.run(compensation).await {
+                Ok(()) => {
+                    tracing::info!(action = %compensation.action, node_id = ?compensation.node_id, attempts = compensation.attempts, failure = %failure, "Compensation ran");
+                    compensation.status = CompensationStatus::Compensated;
+                    compensation.last_error = None;
+                }
+                Err(error) => {
+                    tracing::error!(action = %compensation.action, node_id = ?compensation.node_id, attempts = compensation.attempts, error = %error, "Compensation failed");
+                    compensation.status = CompensationStatus::Failed;
+                    compensation.last_error = Some(error.to_string());
+                }
+            }
+            records.push(CompensationRecord {
+                action: compensation.action.clone(),
+                node_id: compensation.node_id.clone(),
+                step: compensation.step,
+                attempts: compensation.attempts,
+                status: compensation.status,
+                error: compensation.last_error.clone(),
+            });
+        }
+        if let Err(error) = saga.save(state) {
+            tracing::warn!(error = %error, "Failed to record compensations in state");
+        }
+        records
+    }
+
+    async fn run(&self, compensation: &mut Compensation) -> GraphResult<()> {
+        let compensator = self.compensators.get(&compensation.action).ok_or_else(|| {
+            GraphError::ConfigurationError(format!("No compensator for action '{}'", compensation.action))
+        })?;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            compensation.attempts += 1;
+            let error = match compensator.compensate(compensation).await {
+                Ok(()) => return Ok(()),
+                Err(error) => error,
+            };
+            let Some(delay) = self.retry.next_delay(&error, attempt) else {
+                return Err(error);
+            };
+            tracing::debug!(action = %compensation.action, attempt, error = %error, "Retrying compensation");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, Default)]
+    struct TestState {
+        values: HashMap<String, Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<Value> {
+            self.values.get(key).cloned()
+        }
+
+        fn set_value(&mut self, key: &str, value: Value) -> GraphResult<()> {
+            self.values.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compensations_run_in_reverse_with_retries() {
+        let undone = Arc::new(Mutex::new(Vec::new()));
+        let log = undone.clone();
+        let flaky = Arc::new(Mutex::new(true));
+        let saga = Saga::new()
+            .with_retry(ExecutionRetryPolicy::new(2).with_backoff(std::time::Duration::from_millis(1), std::time::Duration::from_millis(1)).with_jitter(false))
+            .with_compensator("cancel", move |compensation: Compensation| {
+                let log = log.clone();
+                let flaky = flaky.clone();
+                async move {
+                    // The airline times out once
+                    if compensation.payload == json!("flight") && std::mem::take(&mut *flaky.lock()) {
+                        return Err(GraphError::ExternalServiceError("timeout".to_string()));
+                    }
+                    log.lock().push(compensation.payload);
+                    Ok(())
+                }
+            });
+
+        let mut state = TestState::default();
+        register(&mut state, "cancel", json!("flight")).unwrap();
+        stamp(&mut state, &"book_flight".to_string(), 1).unwrap();
+        register(&mut state, "cancel", json!("hotel")).unwrap();
+        register(&mut state, "refund", json!({ "amount": 120 })).unwrap();
+        stamp(&mut state, &"book_hotel".to_string(), 2).unwrap();
+
+        let failure = GraphError::execution_error("car rental failed".to_string());
+        let records = saga.compensate(&mut state, &failure).await;
+        assert_eq!(*undone.lock(), vec![json!("hotel"), json!("flight")]);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].action, "refund");
+        assert_eq!(records[0].status, CompensationStatus::Failed);
+        assert_eq!(records[1].node_id.as_deref(), Some("book_hotel"));
+        assert_eq!((records[2].step, records[2].attempts, records[2].status), (Some(1), 2, CompensationStatus::Compensated));
+
+        // Already compensated ones do not run again
+        let records = saga.compensate(&mut state, &failure).await;
+        assert!(records.is_empty());
+        let kept = SagaState::from_state(&state).unwrap();
+        assert!(kept.compensations[2].last_error.as_deref().unwrap().contains("refund"));
+    }
+
+    #[test]
+    fn test_failed_attempt_compensations_carry_over() {
+        let input = TestState::default();
+        let mut attempt = input.clone();
+        register(&mut attempt, "cancel", json!("flight")).unwrap();
+        stamp(&mut attempt, &"book_flight".to_string(), 1).unwrap();
+
+        let mut next = input.clone();
+        carry_over(&attempt, &mut next).unwrap();
+        // The retried node registering it again keeps one
+        register(&mut next, "cancel", json!("flight")).unwrap();
+        stamp(&mut next, &"book_flight".to_string(), 1).unwrap();
+        register(&mut next, "cancel", json!("hotel")).unwrap();
+
+        let saga = SagaState::from_state(&next).unwrap();
+        assert_eq!(saga.compensations.len(), 2);
+        assert_eq!(saga.compensations[0].node_id.as_deref(), Some("book_flight"));
+        assert_eq!(saga.compensations[1].payload, json!("hotel"));
+    }
+}
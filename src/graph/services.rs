@@ -0,0 +1,222 @@
+//! Shared clients resolved by type while nodes run.
+//!
+//! Register the [`LLMManager`](crate::llm::LLMManager), database pools, HTTP
+//! clients and the like once in a [`Services`] container, give it to the
+//! engine with [`GraphEngine::with_services`](crate::graph::engine::GraphEngine::with_services),
+//! and nodes [`resolve`] them when invoked instead of capturing an `Arc` of
+//! each in their constructor. Nodes built from declarative definitions can
+//! then be created from their configuration alone.
+//!
+//! Services are keyed by type, so register a newtype to keep two clients of
+//! the same type apart. Trait objects are registered with
+//! [`Services::provide_arc`] and resolved under their `dyn` type.
+
+use crate::error::{GraphError, GraphResult};
+use parking_lot::RwLock;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+/// An `Arc<T>`, type-erased
+type Erased = Arc<dyn Any + Send + Sync>;
+
+type Factory = Arc<dyn Fn(&Services) -> GraphResult<Erased> + Send + Sync>;
+
+enum Entry {
+    Ready(Erased),
+    /// Built on first resolution and kept from then on
+    Lazy { factory: Factory, built: Arc<OnceLock<Erased>> },
+}
+
+/// Registry of shared services keyed by type
+///
+/// Cloning is cheap and clones share their services, so a service added
+/// after the container was handed to an engine is visible to its nodes.
+#[derive(Clone, Default)]
+pub struct Services {
+    entries: Arc<RwLock<HashMap<TypeId, (&'static str, Entry)>>>,
+}
+
+impl Services {
+    /// Create an empty container
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a service, replacing any of the same type
+    pub fn with<T: Send + Sync + 'static>(self, service: T) -> Self {
+        self.provide(service);
+        self
+    }
+
+    /// Add a shared or trait-object service, replacing any of the same type
+    pub fn with_arc<T: ?Sized + Send + Sync + 'static>(self, service: Arc<T>) -> Self {
+        self.provide_arc(service);
+        self
+    }
+
+    /// Register a service, replacing any of the same type
+    pub fn provide<T: Send + Sync + 'static>(&self, service: T) {
+        self.provide_arc(Arc::new(service));
+    }
+
+    /// Register a shared service, e.g. `Arc<dyn VectorStore>` under `dyn VectorStore`
+    pub fn provide_arc<T: ?Sized + Send + Sync + 'static>(&self, service: Arc<T>) {
+        self.insert::<T>(Entry::Ready(Arc::new(service)));
+    }
+
+    /// Register a service built by `factory` when it is first resolved
+    ///
+    /// The factory can resolve the services it depends on from the container
+    /// it is given. It runs once; if it fails, the next resolution tries again.
+    pub fn provide_lazy<T, F>(&self, factory: F)
+    where
+        T: ?Sized + Send + Sync + 'static,
+        F: Fn(&Services) -> GraphResult<Arc<T>> + Send + Sync + 'static,
+    {
+        let factory: Factory = Arc::new(move |services| factory(services).map(|service| Arc::new(service) as Erased));
+        self.insert::<T>(Entry::Lazy { factory, built: Arc::new(OnceLock::new()) });
+    }
+
+    fn insert<T: ?Sized + 'static>(&self, entry: Entry) {
+        self.entries.write().insert(TypeId::of::<T>(), (std::any::type_name::<T>(), entry));
+    }
+
+    /// The service of type `T`, if one is registered
+    ///
+    /// Fails only if a lazy service's factory fails.
+    pub fn get<T: ?Sized + Send + Sync + 'static>(&self) -> GraphResult<Option<Arc<T>>> {
+        // The lock is released before a factory runs, so factories can resolve other services
+        let (factory, built) = match self.entries.read().get(&TypeId::of::<T>()) {
+            None => return Ok(None),
+            Some((_, Entry::Ready(service))) => return Ok(Self::downcast::<T>(service)),
+            Some((_, Entry::Lazy { factory, built })) => match built.get() {
+                Some(service) => return Ok(Self::downcast::<T>(service)),
+                None => (factory.clone(), built.clone()),
+            },
+        };
+        let service = factory(self)?;
+        Ok(Self::downcast::<T>(built.get_or_init(|| service)))
+    }
+
+    /// The service of type `T`, failing if none is registered
+    pub fn resolve<T: ?Sized + Send + Sync + 'static>(&self) -> GraphResult<Arc<T>> {
+        self.get::<T>()?.ok_or_else(|| {
+            GraphError::ConfigurationError(format!("No service of type {} is registered", std::any::type_name::<T>()))
+        })
+    }
+
+    /// Whether a service of type `T` is registered
+    pub fn contains<T: ?Sized + 'static>(&self) -> bool {
+        self.entries.read().contains_key(&TypeId::of::<T>())
+    }
+
+    /// Remove the service of type `T`, returning whether there was one
+    pub fn remove<T: ?Sized + 'static>(&self) -> bool {
+        self.entries.write().remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// Number of registered services
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// Check if no services are registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn downcast<T: ?Sized + 'static>(service: &Erased) -> Option<Arc<T>> {
+        service.downcast_ref::<Arc<T>>().cloned()
+    }
+}
+
+impl std::fmt::Debug for Services {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<&str> = self.entries.read().values().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        f.debug_struct("Services").field("services", &names).finish()
+    }
+}
+
+tokio::task_local! {
+    static SERVICES: Services;
+}
+
+/// Services of the engine running the current task's node, if any
+pub fn current() -> Option<Services> {
+    SERVICES.try_with(Services::clone).ok()
+}
+
+/// Resolve a service of the engine running the current task's node
+pub fn resolve<T: ?Sized + Send + Sync + 'static>() -> GraphResult<Arc<T>> {
+    current()
+        .ok_or_else(|| GraphError::ConfigurationError("No services are available outside of a graph execution".to_string()))?
+        .resolve::<T>()
+}
+
+/// Run `future` with `services` as the [`current`] services
+///
+/// An empty container leaves the enclosing services in place, so a graph run
+/// from inside a node by an engine of its own still sees them.
+pub(crate) async fn scope<F: Future>(services: &Services, future: F) -> F::Output {
+    if services.is_empty() {
+        future.await
+    } else {
+        SERVICES.scope(services.clone(), future).await
+    }
+}
+
+/// Carry the current services into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = SERVICES.try_with(Services::clone).ok();
+    async move {
+        match value {
+            Some(value) => SERVICES.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    trait Greeter: Send + Sync {
+        fn greet(&self) -> String;
+    }
+
+    struct English;
+
+    impl Greeter for English {
+        fn greet(&self) -> String {
+            "hello".to_string()
+        }
+    }
+
+    #[derive(Debug)]
+    struct BaseUrl(String);
+
+    #[tokio::test]
+    async fn test_services_resolve_by_type() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let services = Services::new().with(BaseUrl("https://api.example.com".to_string())).with_arc::<dyn Greeter>(Arc::new(English));
+        let counter = builds.clone();
+        services.provide_lazy(move |services| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(Arc::new(format!("{}/v1", services.resolve::<BaseUrl>()?.0)))
+        });
+
+        assert_eq!(services.resolve::<dyn Greeter>().unwrap().greet(), "hello");
+        assert_eq!(*services.resolve::<String>().unwrap(), "https://api.example.com/v1");
+        assert_eq!(*services.resolve::<String>().unwrap(), "https://api.example.com/v1");
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+        assert!(services.resolve::<u32>().is_err());
+
+        assert!(resolve::<BaseUrl>().is_err());
+        let seen = scope(&services, async { resolve::<BaseUrl>().map(|url| url.0.clone()) }).await;
+        assert_eq!(seen.unwrap(), "https://api.example.com");
+    }
+}
@@ -0,0 +1,302 @@
+//! Per-session rate limits for chat applications.
+//!
+//! A [`SessionLimiter`] given to a graph with
+//! [`Graph::set_session_limiter`](crate::graph::Graph::set_session_limiter)
+//! admits each execution as one message from the user named in the state, or
+//! the session for anonymous chats, and charges it the LLM tokens its agent nodes report. A session over
+//! its messages per minute or tokens per hour is refused before the execution
+//! starts with [`GraphError::Throttled`], which carries how long to wait, so a
+//! chat frontend can tell the user when to try again.
+
+use crate::error::{GraphError, GraphResult};
+use crate::graph::flags::USER_STATE_KEY;
+use crate::state::State;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// State key holding the chat session an execution answers
+pub const SESSION_STATE_KEY: &str = "session_id";
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// How often sessions with nothing left in their windows are forgotten
+const PURGE_EVERY: Duration = MINUTE;
+
+/// Limits applied to each session separately
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLimits {
+    /// Executions admitted per rolling minute
+    pub messages_per_minute: Option<u32>,
+    /// LLM tokens used per rolling hour
+    pub tokens_per_hour: Option<u64>,
+}
+
+impl SessionLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Admit at most `messages` executions per minute
+    pub fn with_messages_per_minute(mut self, messages: u32) -> Self {
+        self.messages_per_minute = Some(messages);
+        self
+    }
+
+    /// Refuse executions once `tokens` were used in the past hour
+    pub fn with_tokens_per_hour(mut self, tokens: u64) -> Self {
+        self.tokens_per_hour = Some(tokens);
+        self
+    }
+}
+
+/// What a session used within its limits' windows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionUsage {
+    /// Executions admitted in the past minute
+    pub messages_last_minute: u32,
+    /// LLM tokens used in the past hour
+    pub tokens_last_hour: u64,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    messages: VecDeque<Instant>,
+    tokens: VecDeque<(Instant, u64)>,
+    token_total: u64,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant) {
+        while self.messages.front().is_some_and(|at| now.duration_since(*at) >= MINUTE) {
+            self.messages.pop_front();
+        }
+        while let Some((at, tokens)) = self.tokens.front().copied() {
+            if now.duration_since(at) < HOUR {
+                break;
+            }
+            self.token_total -= tokens;
+            self.tokens.pop_front();
+        }
+    }
+
+    /// Time until the window's tokens drop below `limit`
+    fn tokens_free_in(&self, limit: u64, now: Instant) -> Duration {
+        let mut total = self.token_total;
+        for (at, tokens) in &self.tokens {
+            total -= tokens;
+            if total < limit {
+                return HOUR.saturating_sub(now.duration_since(*at));
+            }
+        }
+        Duration::ZERO
+    }
+
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.tokens.is_empty()
+    }
+}
+
+/// Windows of the sessions seen recently
+#[derive(Debug, Default)]
+struct Sessions {
+    windows: HashMap<String, Window>,
+    purged_at: Option<Instant>,
+}
+
+impl Sessions {
+    /// Window of `session`, forgetting idle sessions first when it is time to
+    fn window(&mut self, session: &str, now: Instant) -> &mut Window {
+        if self.purged_at.map_or(true, |at| now.duration_since(at) >= PURGE_EVERY) {
+            self.purge(now);
+        }
+        let window = self.windows.entry(session.to_string()).or_default();
+        window.prune(now);
+        window
+    }
+
+    fn purge(&mut self, now: Instant) {
+        self.windows.retain(|_, window| {
+            window.prune(now);
+            !window.is_empty()
+        });
+        self.purged_at = Some(now);
+    }
+}
+
+/// Rate limits shared by every execution of one or more graphs
+///
+/// Sessions are told apart by the state's `user_id`, so a caller cannot
+/// escape its limits by starting new chats; anonymous executions fall back
+/// to their `session_id`, and executions with neither are not limited.
+/// Sessions are forgotten once nothing is left in their windows. Cloning
+/// shares the sessions' usage.
+#[derive(Debug, Clone, Default)]
+pub struct SessionLimiter {
+    limits: SessionLimits,
+    sessions: Arc<Mutex<Sessions>>,
+}
+
+impl SessionLimiter {
+    /// Create a limiter applying `limits` to each session
+    pub fn new(limits: SessionLimits) -> Self {
+        Self {
+            limits,
+            sessions: Arc::default(),
+        }
+    }
+
+    /// Limits applied to each session
+    pub fn limits(&self) -> &SessionLimits {
+        &self.limits
+    }
+
+    /// Session an execution of `state` is limited as, if any
+    pub fn session_of<S: State>(state: &S) -> Option<String> {
+        [USER_STATE_KEY, SESSION_STATE_KEY].iter().find_map(|key| match state.get_value(key)? {
+            serde_json::Value::String(id) if !id.is_empty() => Some(id),
+            serde_json::Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        })
+    }
+
+    /// Count one message from `session`, or refuse it if the session is over a limit
+    pub fn admit(&self, session: &str) -> GraphResult<()> {
+        self.admit_at(session, Instant::now())
+    }
+
+    fn admit_at(&self, session: &str, now: Instant) -> GraphResult<()> {
+        let mut sessions = self.sessions.lock();
+        let window = sessions.window(session, now);
+        if let Some(limit) = self.limits.messages_per_minute {
+            if window.messages.len() >= limit as usize {
+                let wait = match window.messages.len().checked_sub(limit as usize).and_then(|i| window.messages.get(i)) {
+                    Some(at) => MINUTE.saturating_sub(now.duration_since(*at)),
+                    None => MINUTE,
+                };
+                return Err(GraphError::throttled("messages_per_minute", session, wait));
+            }
+        }
+        if let Some(limit) = self.limits.tokens_per_hour {
+            if window.token_total >= limit {
+                let wait = window.tokens_free_in(limit, now);
+                return Err(GraphError::throttled("tokens_per_hour", session, wait));
+            }
+        }
+        window.messages.push_back(now);
+        Ok(())
+    }
+
+    /// Charge `session` for `tokens` used answering it
+    pub fn record_tokens(&self, session: &str, tokens: u64) {
+        self.record_tokens_at(session, tokens, Instant::now());
+    }
+
+    fn record_tokens_at(&self, session: &str, tokens: u64, now: Instant) {
+        if tokens == 0 {
+            return;
+        }
+        let mut sessions = self.sessions.lock();
+        let window = sessions.window(session, now);
+        window.tokens.push_back((now, tokens));
+        window.token_total += tokens;
+    }
+
+    /// What `session` used within its limits' windows
+    pub fn usage(&self, session: &str) -> SessionUsage {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock();
+        let Some(window) = sessions.windows.get_mut(session) else {
+            return SessionUsage::default();
+        };
+        window.prune(now);
+        SessionUsage {
+            messages_last_minute: window.messages.len() as u32,
+            tokens_last_hour: window.token_total,
+        }
+    }
+
+    /// Forget sessions with nothing left in their windows
+    pub fn purge_idle(&self) {
+        self.sessions.lock().purge(Instant::now());
+    }
+}
+
+tokio::task_local! {
+    static SESSION: (SessionLimiter, String);
+}
+
+/// Run `future` with tokens reported by its agent nodes charged to `session`
+pub(crate) async fn scope<F: Future>(session: Option<(SessionLimiter, String)>, future: F) -> F::Output {
+    match session {
+        Some(session) => SESSION.scope(session, future).await,
+        None => future.await,
+    }
+}
+
+/// Carry the current session into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let session = SESSION.try_with(Clone::clone).ok();
+    scope(session, future)
+}
+
+/// Charge the session the current task is answering, if it is limited
+pub(crate) fn charge_tokens(tokens: u64) {
+    let _ = SESSION.try_with(|(limiter, session)| limiter.record_tokens(session, tokens));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[derive(Debug, Clone, Default)]
+    struct TestState {
+        values: HashMap<String, Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<Value> {
+            self.values.get(key).cloned()
+        }
+    }
+
+    #[test]
+    fn test_session_limits_refuse_with_retry_after() {
+        let limiter = SessionLimiter::new(SessionLimits::new().with_messages_per_minute(2).with_tokens_per_hour(1000));
+        let start = Instant::now();
+
+        limiter.admit_at("s1", start).unwrap();
+        limiter.admit_at("s1", start + Duration::from_secs(20)).unwrap();
+        let error = limiter.admit_at("s1", start + Duration::from_secs(30)).unwrap_err();
+        assert_eq!(error.category(), "throttled");
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(30)));
+        // Other sessions have their own budget
+        limiter.admit_at("s2", start + Duration::from_secs(30)).unwrap();
+        limiter.admit_at("s1", start + Duration::from_secs(60)).unwrap();
+
+        limiter.record_tokens_at("s2", 600, start + Duration::from_secs(40));
+        limiter.record_tokens_at("s2", 500, start + Duration::from_secs(100));
+        let error = limiter.admit_at("s2", start + Duration::from_secs(200)).unwrap_err();
+        assert!(matches!(&error, GraphError::Throttled { limit, key, .. } if limit == "tokens_per_hour" && key == "s2"));
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(3440)));
+        limiter.admit_at("s2", start + Duration::from_secs(3640)).unwrap();
+        // s1 has been idle for an hour and is forgotten
+        assert_eq!(limiter.sessions.lock().windows.keys().collect::<Vec<_>>(), ["s2"]);
+
+        let mut state = TestState::default();
+        assert_eq!(SessionLimiter::session_of(&state), None);
+        state.values.insert(SESSION_STATE_KEY.to_string(), json!("chat-7"));
+        assert_eq!(SessionLimiter::session_of(&state).as_deref(), Some("chat-7"));
+        // A new chat does not reset the user's limits
+        state.values.insert(USER_STATE_KEY.to_string(), json!("ada"));
+        assert_eq!(SessionLimiter::session_of(&state).as_deref(), Some("ada"));
+    }
+}
@@ -0,0 +1,627 @@
+//! Prebuilt graph templates for common multi-agent topologies
+//! Each template is a constructor function and can also be described declaratively in TOML
+
+use super::agent_node::AgentNode;
+use super::map_node::MapNode;
+use super::reflection_node::{ReflectionConfig, ReflectionNode};
+use super::retrieval_node::RetrievalNode;
+use super::{Graph, GraphMetadata};
+use crate::agents::roles::{RoleTemplate, RoleTemplates};
+use crate::agents::vector::{Embedder, VectorStore};
+use crate::agents::{Agent, AgentConfig};
+use crate::edge::routers::StateKeyRouter;
+use crate::edge::Edge;
+use crate::enterprise::TenantConfig;
+use crate::error::{GraphError, GraphResult};
+use crate::llm::LLMManager;
+use crate::node::{Node, NodeMetadata};
+use crate::state::State;
+use crate::tools::{ToolExecutor, ToolRegistry};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Templates shipped with the crate
+const BUNDLED_TEMPLATES: &str = include_str!("templates.toml");
+
+/// Node every template ends in
+const DONE_NODE: &str = "done";
+
+/// Shared infrastructure the agents of a template are built on
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    llm_manager: Arc<LLMManager>,
+    tool_registry: Arc<ToolRegistry>,
+    tool_executor: Arc<ToolExecutor>,
+    provider: String,
+    model: Option<String>,
+    vector_store: Option<(Arc<dyn VectorStore>, Arc<dyn Embedder>)>,
+    personas: HashMap<String, RoleTemplate>,
+}
+
+impl TemplateContext {
+    /// Build template agents on `provider`
+    pub fn new<P: Into<String>>(
+        llm_manager: Arc<LLMManager>,
+        tool_registry: Arc<ToolRegistry>,
+        tool_executor: Arc<ToolExecutor>,
+        provider: P,
+    ) -> Self {
+        Self {
+            llm_manager,
+            tool_registry,
+            tool_executor,
+            provider: provider.into(),
+            model: None,
+            vector_store: None,
+            personas: HashMap::new(),
+        }
+    }
+
+    /// Vector store used by retrieval templates
+    pub fn with_vector_store(mut self, store: Arc<dyn VectorStore>, embedder: Arc<dyn Embedder>) -> Self {
+        self.vector_store = Some((store, embedder));
+        self
+    }
+
+    /// Build agents on a tenant's default provider and model, where it has them
+    ///
+    /// The model replaces the one each role template names.
+    pub fn with_tenant(mut self, config: &TenantConfig) -> Self {
+        if let Some(provider) = &config.default_provider {
+            self.provider = provider.clone();
+        }
+        if let Some(model) = &config.default_model {
+            self.model = Some(model.clone());
+        }
+        self
+    }
+
+    /// Custom personas, usable as roles and taking precedence over built-in roles of the same name
+    pub fn with_personas(mut self, personas: HashMap<String, RoleTemplate>) -> Self {
+        self.personas = personas;
+        self
+    }
+
+    /// Agent configuration for a named persona or role template
+    fn agent_config(&self, name: &str, role: &str) -> GraphResult<AgentConfig> {
+        let template = self.personas.get(role).cloned().or_else(|| RoleTemplates::get_template(role));
+        let template = template.ok_or_else(|| GraphError::validation_error(format!(
+            "Unknown role '{}' for agent '{}' (expected one of: {})",
+            role,
+            name,
+            self.personas.keys().cloned().chain(RoleTemplates::template_names()).collect::<Vec<_>>().join(", ")
+        )))?;
+        let mut config = template.to_agent_config(name.to_string(), self.provider.clone());
+        if let Some(model) = &self.model {
+            config.model = model.clone();
+        }
+        Ok(config)
+    }
+
+    fn agent(&self, name: &str, role: &str) -> GraphResult<Agent> {
+        Agent::new(
+            self.agent_config(name, role)?,
+            self.llm_manager.clone(),
+            self.tool_registry.clone(),
+            self.tool_executor.clone(),
+        ).map_err(|e| GraphError::validation_error(format!("Failed to create agent '{}': {}", name, e)))
+    }
+}
+
+/// Parameters of the researcher → writer → reviewer pipeline
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct PipelineParams {
+    /// Role template of the researcher
+    pub researcher_role: String,
+    /// Role template of the writer
+    pub writer_role: String,
+    /// Role template of the reviewer
+    pub reviewer_role: String,
+    /// Times the reviewer may send the draft back; 0 reviews once without revising
+    pub max_revisions: u32,
+    /// Review score a draft needs to pass
+    pub pass_threshold: f32,
+}
+
+impl Default for PipelineParams {
+    fn default() -> Self {
+        Self {
+            researcher_role: "research_analyst".to_string(),
+            writer_role: "content_writer".to_string(),
+            reviewer_role: "quality_assurance".to_string(),
+            max_revisions: 2,
+            pass_threshold: 0.7,
+        }
+    }
+}
+
+/// A worker managed by a supervisor
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct WorkerSpec {
+    /// Node ID, used by the supervisor to pick the worker
+    pub name: String,
+    /// Role template name
+    pub role: String,
+    /// What the worker is good at, shown to the supervisor
+    #[serde(default)]
+    pub description: String,
+}
+
+impl WorkerSpec {
+    /// Create a worker named `name` playing `role`
+    pub fn new<N: Into<String>, R: Into<String>, D: Into<String>>(name: N, role: R, description: D) -> Self {
+        Self {
+            name: name.into(),
+            role: role.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Parameters of the supervisor + workers topology
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct SupervisorParams {
+    /// Role template of the supervisor
+    pub supervisor_role: String,
+    /// Workers the supervisor delegates to
+    pub workers: Vec<WorkerSpec>,
+}
+
+impl Default for SupervisorParams {
+    fn default() -> Self {
+        Self {
+            supervisor_role: "project_manager".to_string(),
+            workers: vec![
+                WorkerSpec::new("researcher", "research_analyst", "finds and analyses information"),
+                WorkerSpec::new("writer", "content_writer", "writes and edits text"),
+                WorkerSpec::new("developer", "software_developer", "writes and reviews code"),
+            ],
+        }
+    }
+}
+
+/// Parameters of the map-reduce over documents topology
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct MapReduceParams {
+    /// Role template of the mapper agents
+    pub mapper_role: String,
+    /// Role template of the reducer agent
+    pub reducer_role: String,
+    /// Mapper agents working in parallel
+    pub parallelism: usize,
+    /// State key holding the documents
+    pub documents_key: String,
+    /// Task for each document; `{item}` is replaced by the document
+    pub map_instructions: String,
+    /// Task for the combined results; `{summaries}` is replaced by the mapped results
+    pub reduce_instructions: String,
+}
+
+impl Default for MapReduceParams {
+    fn default() -> Self {
+        Self {
+            mapper_role: "research_analyst".to_string(),
+            reducer_role: "content_writer".to_string(),
+            parallelism: 4,
+            documents_key: "documents".to_string(),
+            map_instructions: "Summarize the key points of this document:\n\n{item}".to_string(),
+            reduce_instructions: "Combine these document summaries into one coherent summary:\n\n{summaries}".to_string(),
+        }
+    }
+}
+
+/// Parameters of the retrieval-augmented chat topology
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct RagChatParams {
+    /// Role template of the assistant
+    pub assistant_role: String,
+    /// Vector store collection to retrieve from
+    pub collection: String,
+    /// Chunks retrieved per question
+    pub top_k: usize,
+}
+
+impl Default for RagChatParams {
+    fn default() -> Self {
+        Self {
+            assistant_role: "customer_support".to_string(),
+            collection: "documents".to_string(),
+            top_k: 4,
+        }
+    }
+}
+
+/// Researcher → writer → reviewer pipeline on the topic under `input`
+///
+/// The research is written to `research` and the draft to `draft`. With
+/// `max_revisions > 0` the reviewer is a [`ReflectionNode`] that sends weak
+/// drafts back to the writer with its feedback under `revision_feedback`.
+pub fn research_pipeline<S: State>(context: &TemplateContext, params: &PipelineParams) -> GraphResult<Graph<S>> {
+    let mut graph = Graph::with_metadata(template_metadata("research_pipeline", "Researcher, writer and reviewer pipeline"));
+
+    let researcher = AgentNode::new(
+        context.agent("researcher", &params.researcher_role)?,
+        "Research the following topic and list the key facts with their sources:\n\n{input}".to_string(),
+    ).map_output("response".to_string(), "research".to_string());
+    let writer = AgentNode::new(
+        context.agent("writer", &params.writer_role)?,
+        "Write a clear, well-structured piece on {input} based on this research:\n\n{research}\n\n\
+         Reviewer feedback to address, if any:\n{feedback}".to_string(),
+    )
+    .map_input("research".to_string(), "research".to_string())
+    .map_input("revision_feedback".to_string(), "feedback".to_string())
+    .map_output("response".to_string(), "draft".to_string());
+
+    graph.add_node("researcher".to_string(), researcher)?;
+    graph.add_node("writer".to_string(), writer)?;
+    graph.add_node(DONE_NODE.to_string(), DoneNode::new())?;
+    graph.add_edge(Edge::simple("researcher", "writer"))?;
+    graph.add_edge(Edge::simple("writer", "reviewer"))?;
+
+    if params.max_revisions > 0 {
+        let config = ReflectionConfig::new("writer")
+            .with_criterion("accuracy", "Claims are supported by the research")
+            .with_criterion("clarity", "The piece is easy to follow")
+            .with_criterion("completeness", "The topic is covered without major gaps")
+            .with_pass_threshold(params.pass_threshold)
+            .with_max_revisions(params.max_revisions)
+            .with_output_key("draft");
+        let reviewer = ReflectionNode::new(context.agent("reviewer", &params.reviewer_role)?, config)?;
+        let condition = reviewer.revision_condition();
+        let condition_id = crate::edge::EdgeCondition::<S>::condition_id(&condition);
+        graph.edge_registry_mut().register_condition(condition);
+        graph.add_node("reviewer".to_string(), reviewer)?;
+        graph.add_edge(Edge::conditional("reviewer", condition_id, "writer", DONE_NODE))?;
+    } else {
+        let reviewer = AgentNode::new(
+            context.agent("reviewer", &params.reviewer_role)?,
+            "Review this draft for accuracy and clarity and list any problems:\n\n{draft}".to_string(),
+        )
+        .map_input("draft".to_string(), "draft".to_string())
+        .map_output("response".to_string(), "review".to_string());
+        graph.add_node("reviewer".to_string(), reviewer)?;
+        graph.add_edge(Edge::simple("reviewer", DONE_NODE))?;
+    }
+
+    graph.set_entry_point("researcher".to_string())?;
+    graph.set_finish_point(DONE_NODE.to_string())?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+/// Supervisor delegating the request under `input` to workers until it is done
+///
+/// The supervisor replies with the worker to run next and its task; each
+/// worker reports back to the supervisor, which finishes by answering
+/// `FINISH`. The latest worker result is under `worker_output`.
+pub fn supervisor_workers<S: State>(context: &TemplateContext, params: &SupervisorParams) -> GraphResult<Graph<S>> {
+    if params.workers.is_empty() {
+        return Err(GraphError::validation_error("Supervisor template needs at least one worker".to_string()));
+    }
+    let mut graph = Graph::with_metadata(template_metadata("supervisor_workers", "Supervisor delegating to workers"));
+
+    let roster = params.workers.iter()
+        .map(|worker| format!("- {}: {}", worker.name, worker.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let supervisor = AgentNode::new(
+        context.agent("supervisor", &params.supervisor_role)?,
+        format!(
+            "You coordinate a team to handle this request:\n{{input}}\n\nTeam members:\n{}\n\n\
+             Latest result from the team, if any:\n{{worker_output}}\n\n\
+             Decide who should act next. Reply with JSON only: \
+             {{\"next\": \"<member name or FINISH>\", \"task\": \"<instructions for that member>\"}}",
+            roster
+        ),
+    )
+    .map_input("worker_output".to_string(), "worker_output".to_string())
+    .map_output("next".to_string(), "next".to_string())
+    .map_output("task".to_string(), "task".to_string());
+    graph.add_node("supervisor".to_string(), supervisor)?;
+    graph.add_node(DONE_NODE.to_string(), DoneNode::new())?;
+
+    let mut targets = Vec::with_capacity(params.workers.len() + 1);
+    for worker in &params.workers {
+        if worker.name == "supervisor" || worker.name == DONE_NODE {
+            return Err(GraphError::validation_error(format!("Worker name '{}' is reserved", worker.name)));
+        }
+        let node = AgentNode::new(context.agent(&worker.name, &worker.role)?, "{task}".to_string())
+            .map_input("task".to_string(), "task".to_string())
+            .map_output("response".to_string(), "worker_output".to_string());
+        graph.add_node(worker.name.clone(), node)?;
+        graph.add_edge(Edge::simple(worker.name.clone(), "supervisor"))?;
+        targets.push(worker.name.clone());
+    }
+    targets.push(DONE_NODE.to_string());
+
+    // Anything other than a worker's name, including FINISH, ends the run
+    let router = StateKeyRouter::new("next", DONE_NODE);
+    let router_id = crate::edge::DynamicRouter::<S>::router_id(&router);
+    graph.edge_registry_mut().register_router(router);
+    graph.add_edge(Edge::dynamic("supervisor", router_id, targets))?;
+
+    graph.set_entry_point("supervisor".to_string())?;
+    graph.set_finish_point(DONE_NODE.to_string())?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+/// Summarize each document under `documents_key` in parallel, then combine the summaries
+///
+/// Per-document results are written to `summaries` and the combined result to `output`.
+pub fn map_reduce<S: State>(context: &TemplateContext, params: &MapReduceParams) -> GraphResult<Graph<S>> {
+    let mut graph = Graph::with_metadata(template_metadata("map_reduce", "Map-reduce over documents"));
+
+    let mapper = MapNode::with_workers(
+        context.agent_config("mapper", &params.mapper_role)?,
+        params.parallelism,
+        context.llm_manager.clone(),
+        context.tool_registry.clone(),
+        context.tool_executor.clone(),
+        params.map_instructions.clone(),
+    )?
+    .with_items_key(params.documents_key.clone())
+    .with_output_key("summaries");
+    let reducer = AgentNode::new(context.agent("reducer", &params.reducer_role)?, params.reduce_instructions.clone())
+        .map_input("summaries".to_string(), "summaries".to_string());
+
+    graph.add_node("map".to_string(), mapper)?;
+    graph.add_node("reduce".to_string(), reducer)?;
+    graph.add_edge(Edge::simple("map", "reduce"))?;
+    graph.set_entry_point("map".to_string())?;
+    graph.set_finish_point("reduce".to_string())?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+/// Answer the question under `query` from documents retrieved from the context's vector store
+///
+/// The assistant agent is kept by the graph, so its memory carries the
+/// conversation across runs. The answer is written to `output`.
+pub fn rag_chat<S: State>(context: &TemplateContext, params: &RagChatParams) -> GraphResult<Graph<S>> {
+    let (store, embedder) = context.vector_store.clone().ok_or_else(|| {
+        GraphError::validation_error("RAG chat template needs a vector store in the template context".to_string())
+    })?;
+    let mut graph = Graph::with_metadata(template_metadata("rag_chat", "Retrieval-augmented chat"));
+
+    let retriever = RetrievalNode::new(store, embedder, params.collection.clone()).with_top_k(params.top_k);
+    let assistant = AgentNode::new(
+        context.agent("assistant", &params.assistant_role)?,
+        "Answer the question using the numbered passages below, citing them like [1]. \
+         If they do not contain the answer, say so.\n\nPassages:\n{context}\n\nQuestion: {query}".to_string(),
+    )
+    .map_input("context".to_string(), "context".to_string());
+
+    graph.add_node("retrieve".to_string(), retriever)?;
+    graph.add_node("answer".to_string(), assistant)?;
+    graph.add_edge(Edge::simple("retrieve", "answer"))?;
+    graph.set_entry_point("retrieve".to_string())?;
+    graph.set_finish_point("answer".to_string())?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+fn template_metadata(name: &str, description: &str) -> GraphMetadata {
+    GraphMetadata {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        tags: vec!["template".to_string()],
+        ..Default::default()
+    }
+}
+
+/// Topology of a declarative template and its parameters
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Topology {
+    /// See [`research_pipeline`]
+    Pipeline(PipelineParams),
+    /// See [`supervisor_workers`]
+    Supervisor(SupervisorParams),
+    /// See [`map_reduce`]
+    MapReduce(MapReduceParams),
+    /// See [`rag_chat`]
+    RagChat(RagChatParams),
+}
+
+/// Declarative graph template
+///
+/// ```toml
+/// name = "blog_post"
+/// description = "Research and write a blog post"
+///
+/// [topology]
+/// kind = "pipeline"
+/// writer_role = "content_writer"
+/// max_revisions = 1
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GraphTemplate {
+    /// Template name, used as the graph name
+    pub name: String,
+    /// What graphs built from the template do
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Topology and its parameters
+    pub topology: Topology,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateFile {
+    templates: Vec<GraphTemplate>,
+}
+
+impl GraphTemplate {
+    /// Parse a template from TOML
+    pub fn from_toml_str(content: &str) -> GraphResult<Self> {
+        toml::from_str(content)
+            .map_err(|e| GraphError::ConfigurationError(format!("Invalid graph template: {}", e)))
+    }
+
+    /// Load a template from a TOML file
+    pub fn load(path: &Path) -> GraphResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| GraphError::ConfigurationError(format!(
+            "Failed to read graph template {}: {}",
+            path.display(),
+            e
+        )))?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Templates shipped with the crate
+    pub fn bundled() -> Vec<Self> {
+        toml::from_str::<TemplateFile>(BUNDLED_TEMPLATES)
+            .expect("bundled graph templates must parse")
+            .templates
+    }
+
+    /// Bundled template by name
+    pub fn bundled_template(name: &str) -> Option<Self> {
+        Self::bundled().into_iter().find(|template| template.name == name)
+    }
+
+    /// Build the graph, keeping the template's name and description
+    pub fn build<S: State>(&self, context: &TemplateContext) -> GraphResult<Graph<S>> {
+        let mut graph = match &self.topology {
+            Topology::Pipeline(params) => research_pipeline(context, params)?,
+            Topology::Supervisor(params) => supervisor_workers(context, params)?,
+            Topology::MapReduce(params) => map_reduce(context, params)?,
+            Topology::RagChat(params) => rag_chat(context, params)?,
+        };
+        graph.metadata.name = self.name.clone();
+        if self.description.is_some() {
+            graph.metadata.description = self.description.clone();
+        }
+        Ok(graph)
+    }
+}
+
+/// Terminal node that leaves the state unchanged
+#[derive(Debug)]
+struct DoneNode {
+    metadata: NodeMetadata,
+}
+
+impl DoneNode {
+    fn new() -> Self {
+        Self {
+            metadata: NodeMetadata::new("DoneNode").with_description("End of a template graph"),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for DoneNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, _state: &mut S) -> GraphResult<()> {
+        Ok(())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::vector::{HashingEmbedder, InMemoryVectorStore};
+    use crate::llm::{LLMConfig, providers::MockProvider};
+
+    #[derive(Debug, Clone)]
+    struct TestState;
+
+    fn context() -> TemplateContext {
+        let mut llm_manager = LLMManager::new(LLMConfig::default());
+        llm_manager.register_provider("mock".to_string(), Arc::new(MockProvider::new()));
+        TemplateContext::new(
+            Arc::new(llm_manager),
+            Arc::new(ToolRegistry::new()),
+            Arc::new(ToolExecutor::new()),
+            "mock",
+        )
+    }
+
+    #[test]
+    fn test_bundled_templates_build() {
+        let context = context().with_vector_store(
+            Arc::new(InMemoryVectorStore::new()),
+            Arc::new(HashingEmbedder::new(64)),
+        );
+        let templates = GraphTemplate::bundled();
+        assert!(templates.len() >= 4);
+
+        for template in templates {
+            let graph: Graph<TestState> = template.build(&context).unwrap();
+            assert_eq!(graph.metadata().name, template.name);
+        }
+    }
+
+    #[test]
+    fn test_tenant_defaults_apply_to_template_agents() {
+        let role = RoleTemplates::template_names().remove(0);
+        assert_eq!(context().agent_config("writer", &role).unwrap().provider, "mock");
+
+        let config = TenantConfig {
+            default_provider: Some("tenant-mock".to_string()),
+            default_model: Some("tenant-model".to_string()),
+            ..TenantConfig::default()
+        };
+        let agent = context().with_tenant(&config).agent_config("writer", &role).unwrap();
+        assert_eq!((agent.provider.as_str(), agent.model.as_str()), ("tenant-mock", "tenant-model"));
+    }
+
+    #[test]
+    fn test_template_topologies() {
+        let context = context();
+
+        let pipeline: Graph<TestState> = research_pipeline(&context, &PipelineParams::default()).unwrap();
+        assert_eq!(pipeline.entry_point(), Some(&"researcher".to_string()));
+        assert_eq!(pipeline.node_ids().len(), 4);
+
+        let supervisor: Graph<TestState> = supervisor_workers(&context, &SupervisorParams::default()).unwrap();
+        assert_eq!(supervisor.node_ids().len(), 5);
+        assert_eq!(supervisor.edge_registry().list_routers().len(), 1);
+
+        // RAG chat needs a vector store
+        assert!(rag_chat::<TestState>(&context, &RagChatParams::default()).is_err());
+
+        let bad_role = PipelineParams { writer_role: "astronaut".to_string(), ..Default::default() };
+        assert!(research_pipeline::<TestState>(&context, &bad_role).is_err());
+    }
+
+    #[test]
+    fn test_declarative_template() {
+        let template = GraphTemplate::from_toml_str(
+            r#"
+            name = "digest"
+
+            [topology]
+            kind = "map_reduce"
+            parallelism = 2
+            documents_key = "articles"
+            "#,
+        ).unwrap();
+
+        match &template.topology {
+            Topology::MapReduce(params) => {
+                assert_eq!(params.parallelism, 2);
+                assert_eq!(params.documents_key, "articles");
+                assert_eq!(params.reducer_role, MapReduceParams::default().reducer_role);
+            }
+            other => panic!("unexpected topology {:?}", other),
+        }
+
+        let graph: Graph<TestState> = template.build(&context()).unwrap();
+        assert_eq!(graph.metadata().name, "digest");
+    }
+}
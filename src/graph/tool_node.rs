@@ -5,10 +5,14 @@ use crate::error::{GraphError, GraphResult};
 use crate::node::{Node, NodeMetadata};
 use crate::state::State;
 use crate::tools::{ToolRegistry, ToolExecutor, ToolInput, ToolConfig, ToolExecutionContext};
+use crate::tools::long_running::{LongRunningTool, OperationHandle, OperationTracker};
+use crate::tools::stream::{StreamingTool, ToolOutputChunk, ToolOutputStream};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
 
 /// Tool node that executes a tool as part of a workflow
 #[derive(Debug)]
@@ -36,11 +40,16 @@ impl ToolNode {
         tool_executor: Arc<ToolExecutor>,
         tool_registry: Arc<ToolRegistry>,
     ) -> Self {
-        let metadata = NodeMetadata::new("ToolNode")
+        let mut metadata = NodeMetadata::new("ToolNode")
             .with_description(&format!("Tool execution node for {}", tool_name))
             .with_tag("tool")
             .with_tag(&tool_name)
             .with_parallel_safe(true);
+        if let Some(tool) = tool_registry.get(&tool_name) {
+            for pool in &tool.metadata().concurrency_pools {
+                metadata = metadata.with_concurrency_pool(pool.clone());
+            }
+        }
 
         Self {
             tool_name,
@@ -85,64 +94,12 @@ impl ToolNode {
 
     /// Build tool input from state
     fn build_tool_input<S: State>(&self, state: &S) -> GraphResult<ToolInput> {
-        let mut tool_params = HashMap::new();
-
-        // Map state values to tool parameters
-        for (state_key, tool_param) in &self.input_mapping {
-            if let Some(value) = state.get_value(state_key) {
-                tool_params.insert(tool_param.clone(), value);
-            }
-        }
-
-        // Handle common parameter patterns
-        if tool_params.is_empty() {
-            // If no explicit mapping, try common patterns
-            if let Some(input) = state.get_value("input") {
-                tool_params.insert("input".to_string(), input);
-            }
-            if let Some(query) = state.get_value("query") {
-                tool_params.insert("query".to_string(), query);
-            }
-            if let Some(text) = state.get_value("text") {
-                tool_params.insert("text".to_string(), text);
-            }
-        }
-
-        Ok(ToolInput::new(serde_json::Value::Object(
-            tool_params.into_iter()
-                .map(|(k, v)| (k, v))
-                .collect()
-        )))
+        build_tool_input(&self.input_mapping, state)
     }
 
     /// Update state with tool results
     fn update_state_with_result<S: State>(&self, state: &mut S, result: serde_json::Value) -> GraphResult<()> {
-        // Default output mapping
-        if self.output_mapping.is_empty() {
-            state.set_value("tool_output", result)?;
-            return Ok(());
-        }
-
-        // Custom output mapping
-        for (result_key, state_key) in &self.output_mapping {
-            let value = match result_key.as_str() {
-                "result" | "output" => result.clone(),
-                "success" => serde_json::Value::Bool(true), // Tool executed successfully
-                "tool_name" => serde_json::Value::String(self.tool_name.clone()),
-                _ => {
-                    // Try to extract from result object
-                    if let serde_json::Value::Object(ref obj) = result {
-                        obj.get(result_key).cloned().unwrap_or(serde_json::Value::Null)
-                    } else {
-                        serde_json::Value::Null
-                    }
-                }
-            };
-            
-            state.set_value(state_key, value)?;
-        }
-
-        Ok(())
+        apply_tool_result(&self.tool_name, &self.output_mapping, state, result)
     }
 
     /// Get tool information
@@ -179,7 +136,8 @@ where
         tracing::debug!("Tool input: {:?}", tool_input);
 
         // Create execution context
-        let context = ToolExecutionContext::new(uuid::Uuid::new_v4().to_string());
+        let context = ToolExecutionContext::new(uuid::Uuid::new_v4().to_string())
+            .with_context_vars(&crate::graph::context_vars::current());
 
         // Execute tool
         let result = self.tool_executor.execute_tool(
@@ -206,6 +164,349 @@ where
     }
 }
 
+/// Node that runs a long-running tool and suspends until it completes
+///
+/// On invoke the tool's operation is started and tracked. If it has not
+/// finished within the inline wait, the node returns `GraphError::Suspended`
+/// and the engine checkpoints the branch. Once the tracker reports the
+/// operation finished, the host resumes the graph with its output.
+#[derive(Debug)]
+pub struct LongRunningToolNode {
+    /// Tool to run
+    tool: Arc<dyn LongRunningTool>,
+    /// Tracker that follows the operation after the node suspends
+    tracker: Arc<OperationTracker>,
+    /// Input mapping from state to tool parameters
+    input_mapping: HashMap<String, String>,
+    /// Output mapping from tool results to state
+    output_mapping: HashMap<String, String>,
+    /// How long to wait for completion before suspending
+    inline_wait: Option<Duration>,
+    /// Node metadata
+    metadata: NodeMetadata,
+}
+
+impl LongRunningToolNode {
+    /// Create a new long-running tool node
+    pub fn new(tool: Arc<dyn LongRunningTool>, tracker: Arc<OperationTracker>) -> Self {
+        let tool_name = tool.metadata().id.clone();
+        let metadata = NodeMetadata::new("LongRunningToolNode")
+            .with_description(format!("Long-running tool node for {}", tool_name))
+            .with_tag("tool")
+            .with_tag("long_running")
+            .with_tag(&tool_name)
+            .with_parallel_safe(true);
+
+        Self {
+            tool,
+            tracker,
+            input_mapping: HashMap::new(),
+            output_mapping: HashMap::new(),
+            inline_wait: None,
+            metadata,
+        }
+    }
+
+    /// Add input mapping
+    pub fn map_input<K: Into<String>, V: Into<String>>(mut self, state_key: K, tool_param: V) -> Self {
+        self.input_mapping.insert(state_key.into(), tool_param.into());
+        self
+    }
+
+    /// Add output mapping
+    pub fn map_output<K: Into<String>, V: Into<String>>(mut self, result_key: K, state_key: V) -> Self {
+        self.output_mapping.insert(result_key.into(), state_key.into());
+        self
+    }
+
+    /// Poll for up to `wait` before suspending, so quick operations finish inline
+    pub fn with_inline_wait(mut self, wait: Duration) -> Self {
+        self.inline_wait = Some(wait);
+        self
+    }
+
+    fn tool_name(&self) -> &str {
+        &self.tool.metadata().id
+    }
+
+    /// Poll until the operation finishes or the inline wait runs out
+    async fn wait_inline(&self, handle: &OperationHandle, wait: Duration) -> GraphResult<Option<serde_json::Value>> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let status = self.tracker.poll_operation(&handle.operation_id).await
+                .map_err(|e| GraphError::node_error(
+                    self.tool_name().to_string(),
+                    format!("Polling operation failed: {}", e),
+                    Some(Box::new(e)),
+                ))?;
+            if status.is_terminal() {
+                return match self.tracker.take_result(&handle.operation_id).await {
+                    Some(Ok(output)) => Ok(Some(output.data)),
+                    Some(Err(e)) => Err(GraphError::node_error(
+                        self.tool_name().to_string(),
+                        format!("Operation failed: {}", e),
+                        Some(Box::new(e)),
+                    )),
+                    None => Ok(None),
+                };
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(handle.poll_interval.min(deadline - now)).await;
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for LongRunningToolNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        let tool_input = build_tool_input(&self.input_mapping, state)?;
+        let handle = self.tool.start(tool_input).await.map_err(|e| GraphError::node_error(
+            self.tool_name().to_string(),
+            format!("Failed to start operation: {}", e),
+            Some(Box::new(e)),
+        ))?;
+        let operation_id = handle.operation_id.clone();
+        self.tracker.track(handle.clone()).await;
+
+        tracing::info!(
+            tool = %self.tool_name(),
+            operation_id = %operation_id,
+            "Started long-running operation"
+        );
+
+        if let Some(wait) = self.inline_wait {
+            if let Some(output) = self.wait_inline(&handle, wait).await? {
+                return apply_tool_result(self.tool_name(), &self.output_mapping, state, output);
+            }
+        }
+
+        Err(GraphError::suspended(self.tool_name().to_string(), operation_id))
+    }
+
+    async fn resume(&self, state: &mut S, output: serde_json::Value) -> GraphResult<()> {
+        apply_tool_result(self.tool_name(), &self.output_mapping, state, output)
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+/// Receives a streaming tool's chunks while the tool is still running
+#[async_trait]
+pub trait ToolChunkConsumer<S>: Send + Sync + std::fmt::Debug
+where
+    S: State,
+{
+    /// Handle one chunk, optionally updating state
+    async fn on_chunk(&self, state: &mut S, chunk: &ToolOutputChunk) -> GraphResult<()>;
+}
+
+/// Node that runs a streaming tool and surfaces its chunks as they arrive
+///
+/// Each chunk is emitted as a `ToolOutputChunk` event when the graph is
+/// streaming, and handed to the node's consumers before the tool finishes.
+/// The final tool output is mapped into state like a regular tool node.
+#[derive(Debug)]
+pub struct StreamingToolNode<S>
+where
+    S: State,
+{
+    /// Tool to run
+    tool: Arc<dyn StreamingTool>,
+    /// Input mapping from state to tool parameters
+    input_mapping: HashMap<String, String>,
+    /// Output mapping from tool results to state
+    output_mapping: HashMap<String, String>,
+    /// Consumers of incremental output
+    consumers: Vec<Arc<dyn ToolChunkConsumer<S>>>,
+    /// Node metadata
+    metadata: NodeMetadata,
+}
+
+impl<S> StreamingToolNode<S>
+where
+    S: State,
+{
+    /// Create a new streaming tool node
+    pub fn new(tool: Arc<dyn StreamingTool>) -> Self {
+        let tool_name = tool.metadata().id.clone();
+        let metadata = NodeMetadata::new("StreamingToolNode")
+            .with_description(format!("Streaming tool node for {}", tool_name))
+            .with_tag("tool")
+            .with_tag("streaming")
+            .with_tag(&tool_name)
+            .with_parallel_safe(true);
+
+        Self {
+            tool,
+            input_mapping: HashMap::new(),
+            output_mapping: HashMap::new(),
+            consumers: Vec::new(),
+            metadata,
+        }
+    }
+
+    /// Add input mapping
+    pub fn map_input<K: Into<String>, V: Into<String>>(mut self, state_key: K, tool_param: V) -> Self {
+        self.input_mapping.insert(state_key.into(), tool_param.into());
+        self
+    }
+
+    /// Add output mapping
+    pub fn map_output<K: Into<String>, V: Into<String>>(mut self, result_key: K, state_key: V) -> Self {
+        self.output_mapping.insert(result_key.into(), state_key.into());
+        self
+    }
+
+    /// Add a consumer for incremental output
+    pub fn with_consumer(mut self, consumer: Arc<dyn ToolChunkConsumer<S>>) -> Self {
+        self.consumers.push(consumer);
+        self
+    }
+
+    /// Emit a chunk event and pass the chunk to consumers
+    async fn handle_chunk(&self, state: &mut S, chunk: &ToolOutputChunk) -> GraphResult<()> {
+        #[cfg(feature = "streaming")]
+        crate::streaming::emit_node_event(|execution_id, node_id| {
+            crate::streaming::ExecutionEvent::ToolOutputChunk {
+                execution_id,
+                node_id,
+                tool_id: chunk.tool_id.clone(),
+                sequence: chunk.sequence,
+                data: chunk.data.clone(),
+                timestamp: chunk.timestamp,
+            }
+        });
+
+        for consumer in &self.consumers {
+            consumer.on_chunk(state, chunk).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S> Node<S> for StreamingToolNode<S>
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        let tool_name = self.tool.metadata().id.clone();
+        let tool_input = build_tool_input(&self.input_mapping, state)?;
+
+        let output_stream = ToolOutputStream::new(&tool_name);
+        let mut chunks = output_stream.subscribe();
+        let run = self.tool.execute_streaming(tool_input, output_stream);
+        tokio::pin!(run);
+
+        let result = loop {
+            tokio::select! {
+                biased;
+                chunk = chunks.recv() => match chunk {
+                    Ok(chunk) => self.handle_chunk(state, &chunk).await?,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(tool = %tool_name, skipped, "Streaming tool consumer lagged");
+                    }
+                    // The tool dropped its stream; just wait for the result
+                    Err(broadcast::error::RecvError::Closed) => break (&mut run).await,
+                },
+                result = &mut run => break result,
+            }
+        };
+
+        // Deliver chunks sent just before the tool returned
+        while let Ok(chunk) = chunks.try_recv() {
+            self.handle_chunk(state, &chunk).await?;
+        }
+
+        let output = result.map_err(|e| GraphError::node_error(
+            tool_name.clone(),
+            format!("Tool execution failed: {}", e),
+            Some(Box::new(e)),
+        ))?;
+        apply_tool_result(&tool_name, &self.output_mapping, state, output.data)
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+/// Build tool input from state using a state-key to parameter mapping
+fn build_tool_input<S: State>(input_mapping: &HashMap<String, String>, state: &S) -> GraphResult<ToolInput> {
+    let mut tool_params = HashMap::new();
+
+    // Map state values to tool parameters
+    for (state_key, tool_param) in input_mapping {
+        if let Some(value) = state.get_value(state_key) {
+            tool_params.insert(tool_param.clone(), value);
+        }
+    }
+
+    // Handle common parameter patterns
+    if tool_params.is_empty() {
+        // If no explicit mapping, try common patterns
+        if let Some(input) = state.get_value("input") {
+            tool_params.insert("input".to_string(), input);
+        }
+        if let Some(query) = state.get_value("query") {
+            tool_params.insert("query".to_string(), query);
+        }
+        if let Some(text) = state.get_value("text") {
+            tool_params.insert("text".to_string(), text);
+        }
+    }
+
+    Ok(ToolInput::new(serde_json::Value::Object(
+        tool_params.into_iter()
+            .map(|(k, v)| (k, v))
+            .collect()
+    )))
+}
+
+/// Update state with tool results using a result-key to state-key mapping
+fn apply_tool_result<S: State>(
+    tool_name: &str,
+    output_mapping: &HashMap<String, String>,
+    state: &mut S,
+    result: serde_json::Value,
+) -> GraphResult<()> {
+    // Default output mapping
+    if output_mapping.is_empty() {
+        state.set_value("tool_output", result)?;
+        return Ok(());
+    }
+
+    // Custom output mapping
+    for (result_key, state_key) in output_mapping {
+        let value = match result_key.as_str() {
+            "result" | "output" => result.clone(),
+            "success" => serde_json::Value::Bool(true), // Tool executed successfully
+            "tool_name" => serde_json::Value::String(tool_name.to_string()),
+            _ => {
+                // Try to extract from result object
+                if let serde_json::Value::Object(ref obj) = result {
+                    obj.get(result_key).cloned().unwrap_or(serde_json::Value::Null)
+                } else {
+                    serde_json::Value::Null
+                }
+            }
+        };
+
+        state.set_value(state_key, value)?;
+    }
+
+    Ok(())
+}
+
 /// Tool information for debugging and monitoring
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolInfo {
@@ -349,4 +650,185 @@ mod tests {
         assert!(!info.input_mapping.is_empty());
         assert!(!info.output_mapping.is_empty());
     }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct RenderState {
+        published: u32,
+    }
+
+    #[derive(Debug)]
+    struct PublishNode;
+
+    #[async_trait]
+    impl Node<RenderState> for PublishNode {
+        async fn invoke(&self, state: &mut RenderState) -> GraphResult<()> {
+            state.published += 1;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct RenderTool {
+        metadata: crate::tools::ToolMetadata,
+        polls_until_done: u32,
+        polls: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait]
+    impl LongRunningTool for RenderTool {
+        fn metadata(&self) -> &crate::tools::ToolMetadata {
+            &self.metadata
+        }
+
+        async fn start(&self, input: ToolInput) -> crate::tools::ToolResult<OperationHandle> {
+            Ok(OperationHandle::new(&self.metadata.id, input.data).with_poll_interval(Duration::ZERO))
+        }
+
+        async fn poll(&self, _handle: &OperationHandle) -> crate::tools::ToolResult<crate::tools::OperationStatus> {
+            let polls = self.polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(if polls >= self.polls_until_done {
+                crate::tools::OperationStatus::Completed {
+                    output: crate::tools::ToolOutput::new(serde_json::json!({"url": "render.mp4"})),
+                }
+            } else {
+                crate::tools::OperationStatus::Pending { progress: None }
+            })
+        }
+    }
+
+    async fn render_graph(polls_until_done: u32, inline_wait: Option<Duration>) -> (crate::graph::Graph<RenderState>, Arc<OperationTracker>) {
+        let tool = Arc::new(RenderTool {
+            metadata: crate::tools::ToolMetadata::new("render_video", "Render Video", "Render a video"),
+            polls_until_done,
+            polls: std::sync::atomic::AtomicU32::new(0),
+        });
+        let tracker = Arc::new(OperationTracker::new());
+        tracker.register_tool(tool.clone()).await;
+
+        let mut node = LongRunningToolNode::new(tool, tracker.clone());
+        if let Some(wait) = inline_wait {
+            node = node.with_inline_wait(wait);
+        }
+
+        let graph = crate::graph::GraphBuilder::new()
+            .add_node("render".to_string(), node).unwrap()
+            .add_node("publish".to_string(), PublishNode).unwrap()
+            .with_entry_point("render".to_string()).unwrap()
+            .add_finish_point("publish".to_string()).unwrap()
+            .add_edge(crate::edge::Edge::simple("render", "publish")).unwrap()
+            .build().unwrap();
+        (graph, tracker)
+    }
+
+    #[tokio::test]
+    async fn test_long_running_node_suspends_and_resumes() {
+        let (mut graph, tracker) = render_graph(2, None).await;
+        graph.set_checkpointer(crate::state::checkpointing::MemoryCheckpointer::new());
+        let mut state = RenderState::default();
+
+        let error = graph.run(&mut state).await.unwrap_err();
+        let (node_id, operation_id, checkpoint_id) = match error {
+            GraphError::Suspended { node_id, operation_id, checkpoint_id } => (node_id, operation_id, checkpoint_id),
+            other => panic!("Expected suspension, got {}", other),
+        };
+        assert_eq!(node_id, "render");
+        assert_eq!(state.published, 0);
+
+        assert!(tracker.poll_due().await.is_empty());
+        assert_eq!(tracker.poll_due().await.len(), 1);
+        let output = tracker.take_result(&operation_id).await.unwrap().unwrap();
+
+        let checkpoint_id = uuid::Uuid::parse_str(&checkpoint_id.unwrap()).unwrap();
+        let mut engine = crate::graph::engine::GraphEngine::new();
+        let (state, context) = engine.resume_from_checkpoint(&graph, checkpoint_id, output.data).await.unwrap();
+        assert_eq!(state.published, 1);
+        assert_eq!(context.execution_path, vec!["render".to_string(), "publish".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_long_running_node_completes_inline() {
+        let (graph, tracker) = render_graph(1, Some(Duration::from_secs(1))).await;
+        let mut state = RenderState::default();
+
+        graph.run(&mut state).await.unwrap();
+        assert_eq!(state.published, 1);
+        assert!(tracker.pending().await.is_empty());
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct CrawlState {
+        pages_seen: Vec<u64>,
+    }
+
+    #[derive(Debug)]
+    struct CrawlerTool {
+        metadata: crate::tools::ToolMetadata,
+    }
+
+    #[async_trait]
+    impl crate::tools::Tool for CrawlerTool {
+        fn metadata(&self) -> &crate::tools::ToolMetadata {
+            &self.metadata
+        }
+
+        async fn execute(&self, input: ToolInput) -> crate::tools::ToolResult<crate::tools::ToolOutput> {
+            self.execute_streaming(input, ToolOutputStream::new(&self.metadata.id)).await
+        }
+    }
+
+    #[async_trait]
+    impl StreamingTool for CrawlerTool {
+        async fn execute_streaming(
+            &self,
+            _input: ToolInput,
+            output: ToolOutputStream,
+        ) -> crate::tools::ToolResult<crate::tools::ToolOutput> {
+            for page in 0..3u64 {
+                output.send(serde_json::json!({"page": page}));
+                tokio::task::yield_now().await;
+            }
+            Ok(crate::tools::ToolOutput::new(serde_json::json!({"pages": 3})))
+        }
+    }
+
+    #[derive(Debug)]
+    struct PageCollector;
+
+    #[async_trait]
+    impl ToolChunkConsumer<CrawlState> for PageCollector {
+        async fn on_chunk(&self, state: &mut CrawlState, chunk: &ToolOutputChunk) -> GraphResult<()> {
+            state.pages_seen.push(chunk.data["page"].as_u64().unwrap());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_tool_node_surfaces_chunks() {
+        let tool = Arc::new(CrawlerTool {
+            metadata: crate::tools::ToolMetadata::new("crawler", "Crawler", "Crawl pages"),
+        });
+        let node = StreamingToolNode::new(tool).with_consumer(Arc::new(PageCollector));
+
+        let mut graph = crate::graph::GraphBuilder::new()
+            .add_node("crawl".to_string(), node).unwrap()
+            .with_entry_point("crawl".to_string()).unwrap()
+            .add_finish_point("crawl".to_string()).unwrap()
+            .build().unwrap();
+        let (emitter, mut events) = crate::streaming::EventEmitter::new();
+        graph.set_event_emitter(emitter);
+
+        let mut state = CrawlState::default();
+        graph.run(&mut state).await.unwrap();
+        assert_eq!(state.pages_seen, vec![0, 1, 2]);
+
+        let mut chunk_events = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let crate::streaming::ExecutionEvent::ToolOutputChunk { node_id, sequence, .. } = event {
+                assert_eq!(node_id, "crawl");
+                chunk_events.push(sequence);
+            }
+        }
+        assert_eq!(chunk_events, vec![0, 1, 2]);
+    }
 }
+
@@ -0,0 +1,366 @@
+//! Executor tuning: separate pools for I/O-bound and compute-bound work.
+//!
+//! Nodes declare their workload with
+//! [`NodeMetadata::with_workload`](crate::node::NodeMetadata::with_workload).
+//! I/O-bound nodes, the default, run as ordinary tasks: they spend their time
+//! waiting on LLM providers and HTTP tools. A compute-bound node instead runs
+//! as a task of a runtime dedicated to compute, with one thread per
+//! [`ExecutorTuning::compute_slots`] and at most that many such nodes at once,
+//! so heavy local compute never leaves the I/O tasks without workers. The
+//! caller only awaits the node's task, so node timeouts, cancellation and
+//! other branches keep running; a node given up on is aborted at its next
+//! await and keeps its slot until it stops. While it is polled, the node is
+//! pinned to its CPU affinity hint, if any; pinning is a hint and only honored
+//! on Linux.
+//!
+//! Tools doing blocking work call [`spawn_blocking`], which uses a runtime
+//! dedicated to blocking work when [`ExecutorTuning::dedicated_blocking_threads`]
+//! is set, and the caller's `spawn_blocking` pool otherwise. The pools take
+//! effect process-wide once [`NodePools::install`]ed:
+//!
+//! ```ignore
+//! let tuning = ExecutorTuning::new()
+//!     .with_worker_threads(8)
+//!     .with_compute_slots(2)
+//!     .with_compute_cores(vec![6, 7])
+//!     .with_dedicated_blocking_threads(4);
+//! let runtime = tuning.runtime()?;
+//! NodePools::new(tuning)?.install();
+//! runtime.block_on(graph.run(&mut state))?;
+//! ```
+
+use crate::error::{GraphError, GraphResult};
+use arc_swap::ArcSwapOption;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::{JoinError, JoinHandle};
+
+/// What a node spends its time on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeWorkload {
+    /// Waiting on LLM providers, tools and other I/O
+    #[default]
+    Io,
+    /// Computing locally, e.g. running a local model or parsing large documents
+    Compute,
+}
+
+/// Sizing of the runtime and of the node and blocking pools
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutorTuning {
+    /// Worker threads of the runtime built by [`ExecutorTuning::runtime`]; one per core by default
+    pub worker_threads: Option<usize>,
+    /// Size of that runtime's `spawn_blocking` pool; tokio's default when unset
+    pub max_blocking_threads: Option<usize>,
+    /// Compute-bound nodes running at once; one per core by default
+    pub compute_slots: Option<usize>,
+    /// Cores compute-bound nodes are pinned to unless they give their own hint
+    pub compute_cores: Vec<usize>,
+    /// Threads of a runtime dedicated to blocking tool work
+    pub dedicated_blocking_threads: Option<usize>,
+}
+
+impl ExecutorTuning {
+    /// Tokio's defaults, one compute slot per core and no dedicated blocking pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the runtime on `threads` worker threads
+    pub fn with_worker_threads(mut self, threads: usize) -> Self {
+        self.worker_threads = Some(threads);
+        self
+    }
+
+    /// Let the runtime's `spawn_blocking` pool grow to `threads`
+    pub fn with_max_blocking_threads(mut self, threads: usize) -> Self {
+        self.max_blocking_threads = Some(threads);
+        self
+    }
+
+    /// Run at most `slots` compute-bound nodes at once
+    pub fn with_compute_slots(mut self, slots: usize) -> Self {
+        self.compute_slots = Some(slots);
+        self
+    }
+
+    /// Pin compute-bound nodes without an affinity hint to `cores`
+    pub fn with_compute_cores(mut self, cores: Vec<usize>) -> Self {
+        self.compute_cores = cores;
+        self
+    }
+
+    /// Run blocking tool work on a dedicated pool of `threads` threads
+    pub fn with_dedicated_blocking_threads(mut self, threads: usize) -> Self {
+        self.dedicated_blocking_threads = Some(threads);
+        self
+    }
+
+    /// Build a multi-thread runtime sized by these settings
+    pub fn runtime(&self) -> GraphResult<Runtime> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        if let Some(threads) = self.worker_threads {
+            builder.worker_threads(threads.max(1));
+        }
+        if let Some(threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(threads.max(1));
+        }
+        builder
+            .thread_name("agentgraph-worker")
+            .enable_all()
+            .build()
+            .map_err(|e| GraphError::ConfigurationError(format!("Failed to build the runtime: {}", e)))
+    }
+}
+
+static INSTALLED: ArcSwapOption<NodePools> = ArcSwapOption::const_empty();
+
+/// Pools compute-bound nodes and blocking tool work run on
+#[derive(Debug)]
+pub struct NodePools {
+    tuning: ExecutorTuning,
+    compute: Arc<Semaphore>,
+    compute_runtime: Option<Runtime>,
+    blocking: Option<Runtime>,
+}
+
+impl NodePools {
+    /// Create the pools `tuning` describes
+    pub fn new(tuning: ExecutorTuning) -> GraphResult<Self> {
+        let slots = tuning
+            .compute_slots
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, usize::from))
+            .max(1);
+        let compute_runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(slots)
+            .thread_name("agentgraph-compute")
+            .enable_all()
+            .build()
+            .map_err(|e| GraphError::ConfigurationError(format!("Failed to build the compute pool: {}", e)))?;
+        let blocking = match tuning.dedicated_blocking_threads {
+            Some(threads) => Some(
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .max_blocking_threads(threads.max(1))
+                    .thread_name("agentgraph-blocking")
+                    .enable_all()
+                    .build()
+                    .map_err(|e| GraphError::ConfigurationError(format!("Failed to build the blocking pool: {}", e)))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            tuning,
+            compute: Arc::new(Semaphore::new(slots)),
+            compute_runtime: Some(compute_runtime),
+            blocking,
+        })
+    }
+
+    /// Use these pools for every execution in the process, returning the pools they replace
+    pub fn install(self) -> Option<Arc<NodePools>> {
+        INSTALLED.swap(Some(Arc::new(self)))
+    }
+
+    /// The installed pools, or pools with the default tuning
+    pub fn current() -> Arc<NodePools> {
+        static DEFAULT: OnceLock<Arc<NodePools>> = OnceLock::new();
+        INSTALLED.load_full().unwrap_or_else(|| {
+            DEFAULT
+                .get_or_init(|| Arc::new(NodePools::new(ExecutorTuning::default()).expect("default pools build")))
+                .clone()
+        })
+    }
+
+    /// Settings the pools were created with
+    pub fn tuning(&self) -> &ExecutorTuning {
+        &self.tuning
+    }
+
+    /// Compute slots not taken by a running node
+    pub fn available_compute_slots(&self) -> usize {
+        self.compute.available_permits()
+    }
+
+    /// Run a node's future the way its workload asks for
+    ///
+    /// A compute-bound node runs on the compute pool with the caller's
+    /// task-local scopes. Dropping the returned future, e.g. when a timeout
+    /// expires, aborts it. `affinity` lists the cores a compute-bound node
+    /// prefers; it falls back to [`ExecutorTuning::compute_cores`].
+    pub async fn run<F>(&self, workload: NodeWorkload, affinity: Option<&[usize]>, node: F) -> F::Output
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let Some(runtime) = self.compute_runtime.as_ref().filter(|_| workload == NodeWorkload::Compute) else {
+            return node.await;
+        };
+        // The slot moves into the task, so a node given up on holds it until it stops
+        let slot = self.compute.clone().acquire_owned().await.expect("compute slots are never closed");
+        let cores = affinity.unwrap_or(self.tuning.compute_cores.as_slice()).to_vec();
+        let node = crate::graph::engine::inherit_scopes(OnCores { cores, future: Box::pin(node) });
+        let mut task = AbortOnDrop(runtime.spawn(async move {
+            let _slot = slot;
+            node.await
+        }));
+        match (&mut task.0).await {
+            Ok(output) => output,
+            Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+            Err(error) => panic!("Compute node task failed: {}", error),
+        }
+    }
+
+    /// Run blocking work on the dedicated blocking pool, or the caller's
+    pub async fn spawn_blocking<F, R>(&self, work: F) -> Result<R, JoinError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        match &self.blocking {
+            Some(runtime) => runtime.spawn_blocking(work).await,
+            None => tokio::task::spawn_blocking(work).await,
+        }
+    }
+}
+
+impl Drop for NodePools {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which is not allowed on an async thread
+        for runtime in [self.compute_runtime.take(), self.blocking.take()].into_iter().flatten() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// A compute node's future, pinned to its cores whenever it is polled
+struct OnCores<F> {
+    cores: Vec<usize>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for OnCores<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        let _pinned = affinity::pin(&this.cores);
+        this.future.as_mut().poll(cx)
+    }
+}
+
+/// A spawned node task, aborted when whoever awaits it gives up
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Run blocking tool work on the installed pools
+pub async fn spawn_blocking<F, R>(work: F) -> Result<R, JoinError>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    NodePools::current().spawn_blocking(work).await
+}
+
+#[cfg(target_os = "linux")]
+mod affinity {
+    use std::mem;
+
+    /// The calling thread's previous affinity, restored on drop
+    pub(super) struct Pinned(Option<libc::cpu_set_t>);
+
+    pub(super) fn pin(cores: &[usize]) -> Pinned {
+        if cores.is_empty() {
+            return Pinned(None);
+        }
+        // SAFETY: cpu_set_t is plain data and both calls only touch the calling thread
+        unsafe {
+            let mut previous: libc::cpu_set_t = mem::zeroed();
+            if libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut previous) != 0 {
+                return Pinned(None);
+            }
+            let mut set: libc::cpu_set_t = mem::zeroed();
+            for &core in cores.iter().filter(|core| **core < libc::CPU_SETSIZE as usize) {
+                libc::CPU_SET(core, &mut set);
+            }
+            if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                tracing::debug!(?cores, "Could not pin compute node to its cores");
+                return Pinned(None);
+            }
+            Pinned(Some(previous))
+        }
+    }
+
+    impl Drop for Pinned {
+        fn drop(&mut self) {
+            if let Some(previous) = &self.0 {
+                // SAFETY: restores a mask read from this thread
+                unsafe {
+                    libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), previous);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod affinity {
+    pub(super) struct Pinned;
+
+    pub(super) fn pin(_cores: &[usize]) -> Pinned {
+        Pinned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::determinism::{self, Determinism};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_compute_nodes_and_blocking_work_use_their_pools() {
+        let pools = NodePools::new(ExecutorTuning::new().with_compute_slots(1).with_dedicated_blocking_threads(1)).unwrap();
+        assert_eq!(pools.available_compute_slots(), 1);
+
+        // Task-local scopes, here the seeded clock, reach compute nodes
+        let at = determinism::scope(
+            &Determinism::seeded(1),
+            pools.run(NodeWorkload::Compute, Some(&[0]), async {
+                tokio::task::yield_now().await;
+                determinism::now()
+            }),
+        )
+        .await;
+        assert_eq!(at, chrono::DateTime::<chrono::Utc>::UNIX_EPOCH);
+        assert_eq!(pools.available_compute_slots(), 1);
+
+        let thread = pools.spawn_blocking(|| std::thread::current().name().map(str::to_string)).await.unwrap();
+        assert_eq!(thread.as_deref(), Some("agentgraph-blocking"));
+        assert_eq!(NodePools::current().tuning(), &ExecutorTuning::default());
+    }
+
+    #[tokio::test]
+    async fn test_hung_compute_node_does_not_block_its_timeout() {
+        let pools = NodePools::new(ExecutorTuning::new().with_compute_slots(1)).unwrap();
+        let hung = pools.run(NodeWorkload::Compute, None, async {
+            std::thread::sleep(std::time::Duration::from_secs(2));
+        });
+
+        let timed_out = tokio::time::timeout(std::time::Duration::from_millis(50), hung).await;
+        assert!(timed_out.is_err());
+        // The node still holds its slot until its thread is free again
+        assert_eq!(pools.available_compute_slots(), 0);
+    }
+}
@@ -0,0 +1,493 @@
+//! Output validation node for agent workflows
+//! Checks state fields against JSON Schemas or serde types and routes invalid output for repair or retry
+
+use crate::edge::EdgeCondition;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::command::Command;
+use crate::node::{Node, NodeId, NodeMetadata};
+use crate::state::State;
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What happens when a field fails validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnInvalid {
+    /// Fail the execution with the validation report
+    Fail,
+    /// Route to a node that repairs the output
+    Repair(NodeId),
+    /// Route back to the node that produced the output, with the errors as feedback
+    Retry(NodeId),
+}
+
+impl OnInvalid {
+    fn target(&self) -> Option<&NodeId> {
+        match self {
+            OnInvalid::Fail => None,
+            OnInvalid::Repair(node) | OnInvalid::Retry(node) => Some(node),
+        }
+    }
+}
+
+/// One problem found in a field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldViolation {
+    /// State key of the field
+    pub key: String,
+    /// JSON pointer to the offending value within the field
+    pub path: String,
+    /// What is wrong
+    pub message: String,
+}
+
+/// Outcome of a validation, written to state
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Whether every field passed
+    pub valid: bool,
+    /// Problems found
+    pub violations: Vec<FieldViolation>,
+    /// Validations performed so far, including this one
+    pub attempt: u32,
+    /// Node the output is routed to for another attempt
+    pub rerun_node: Option<NodeId>,
+}
+
+impl ValidationReport {
+    /// Whether the output goes back for repair or retry
+    pub fn needs_rerun(&self) -> bool {
+        self.rerun_node.is_some()
+    }
+
+    /// Error feedback for the node repairing or regenerating the output
+    pub fn feedback(&self) -> String {
+        let mut feedback = String::from("The output did not match the required format:");
+        for violation in &self.violations {
+            if violation.path.is_empty() {
+                feedback.push_str(&format!("\n- {}: {}", violation.key, violation.message));
+            } else {
+                feedback.push_str(&format!("\n- {} at {}: {}", violation.key, violation.path, violation.message));
+            }
+        }
+        feedback
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} violation(s) after {} attempt(s)", self.violations.len(), self.attempt)?;
+        for violation in &self.violations {
+            write!(f, "; {}{}: {}", violation.key, violation.path, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationReport {}
+
+enum Rule {
+    Schema(JSONSchema),
+    Type {
+        name: &'static str,
+        check: fn(&serde_json::Value) -> Result<(), String>,
+    },
+}
+
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Schema(_) => f.write_str("Schema"),
+            Rule::Type { name, .. } => write!(f, "Type({})", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FieldRule {
+    key: String,
+    rule: Rule,
+}
+
+impl FieldRule {
+    fn check(&self, value: &serde_json::Value) -> Vec<FieldViolation> {
+        match &self.rule {
+            Rule::Schema(schema) => match schema.validate(value) {
+                Ok(()) => Vec::new(),
+                Err(errors) => errors
+                    .map(|error| FieldViolation {
+                        key: self.key.clone(),
+                        path: error.instance_path.to_string(),
+                        message: error.to_string(),
+                    })
+                    .collect(),
+            },
+            Rule::Type { name, check } => match check(value) {
+                Ok(()) => Vec::new(),
+                Err(message) => vec![FieldViolation {
+                    key: self.key.clone(),
+                    path: String::new(),
+                    message: format!("not a valid {}: {}", name, message),
+                }],
+            },
+        }
+    }
+}
+
+fn check_type<T: DeserializeOwned>(value: &serde_json::Value) -> Result<(), String> {
+    T::deserialize(value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Node validating state fields produced by earlier nodes
+///
+/// Each field is checked against a JSON Schema or a serde type. Fields holding
+/// a string are parsed as JSON first, since agents return text, and the parsed
+/// value replaces the string once it validates. The node writes a
+/// [`ValidationReport`] to state; on failure it routes per [`OnInvalid`] until
+/// `max_attempts` validations have failed, then fails the execution with the
+/// report as the error source. Route with [`ValidateNode::invoke_with_command`]
+/// or a conditional edge on [`ValidateNode::rerun_condition`].
+#[derive(Debug)]
+pub struct ValidateNode {
+    fields: Vec<FieldRule>,
+    on_invalid: OnInvalid,
+    max_attempts: u32,
+    parse_strings: bool,
+    report_key: String,
+    feedback_key: String,
+    metadata: NodeMetadata,
+}
+
+impl ValidateNode {
+    /// Create a node that fails the execution on invalid output
+    pub fn new() -> Self {
+        let metadata = NodeMetadata::new("ValidateNode")
+            .with_description("Validates state fields against schemas")
+            .with_tag("validation")
+            .with_parallel_safe(false);
+
+        Self {
+            fields: Vec::new(),
+            on_invalid: OnInvalid::Fail,
+            max_attempts: 3,
+            parse_strings: true,
+            report_key: "validation".to_string(),
+            feedback_key: "validation_feedback".to_string(),
+            metadata,
+        }
+    }
+
+    /// Check the field under `key` against a JSON Schema
+    pub fn with_schema<K: Into<String>>(mut self, key: K, schema: &serde_json::Value) -> GraphResult<Self> {
+        let key = key.into();
+        let compiled = JSONSchema::compile(schema)
+            .map_err(|e| GraphError::validation_error(format!("Invalid JSON Schema for '{}': {}", key, e)))?;
+        self.fields.push(FieldRule { key, rule: Rule::Schema(compiled) });
+        Ok(self)
+    }
+
+    /// Check that the field under `key` deserializes into `T`
+    pub fn with_type<T: DeserializeOwned, K: Into<String>>(mut self, key: K) -> Self {
+        self.fields.push(FieldRule {
+            key: key.into(),
+            rule: Rule::Type {
+                name: std::any::type_name::<T>(),
+                check: check_type::<T>,
+            },
+        });
+        self
+    }
+
+    /// Set what happens to invalid output
+    pub fn on_invalid(mut self, on_invalid: OnInvalid) -> Self {
+        self.on_invalid = on_invalid;
+        self
+    }
+
+    /// Fail after `max_attempts` failed validations
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Validate string fields as they are instead of parsing them as JSON
+    pub fn without_string_parsing(mut self) -> Self {
+        self.parse_strings = false;
+        self
+    }
+
+    /// Write the report under `key`
+    pub fn with_report_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.report_key = key.into();
+        self
+    }
+
+    /// Write error feedback for the repair or retry node under `key`
+    pub fn with_feedback_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.feedback_key = key.into();
+        self
+    }
+
+    /// Edge condition that is true while invalid output goes back for another attempt
+    pub fn rerun_condition(&self) -> ValidationCondition {
+        ValidationCondition::new(self.report_key.clone())
+    }
+
+    /// Validate and return the routing decision
+    pub async fn invoke_with_command<S: State>(&self, state: &mut S) -> GraphResult<Command> {
+        let report = self.validate(state)?;
+        Ok(match report.rerun_node {
+            Some(node) => Command::goto(node),
+            None => Command::continue_(),
+        })
+    }
+
+    /// Failed validations allowed before the output is rejected for good
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    fn validate<S: State>(&self, state: &mut S) -> GraphResult<ValidationReport> {
+        let previous_attempts = state.get_value(&self.report_key)
+            .and_then(|report| serde_json::from_value::<ValidationReport>(report).ok())
+            .filter(|report| !report.valid)
+            .map_or(0, |report| report.attempt);
+
+        let report = self.check(state, previous_attempts + 1, &self.on_invalid)?;
+        if report.valid {
+            return Ok(report);
+        }
+        match &report.rerun_node {
+            Some(node) => {
+                tracing::info!("Validation attempt {} failed, routing to '{}'", report.attempt, node);
+                Ok(report)
+            }
+            None => Err(GraphError::node_error(
+                "validate_node".to_string(),
+                format!("Output failed validation: {}", report),
+                Some(Box::new(report)),
+            )),
+        }
+    }
+
+    /// Validate the state as attempt `attempt`, writing the report, the parsed
+    /// values and, on failure, the feedback to the state
+    ///
+    /// On success, feedback an earlier attempt left is removed.
+    pub(crate) fn check<S: State>(&self, state: &mut S, attempt: u32, on_invalid: &OnInvalid) -> GraphResult<ValidationReport> {
+        if self.fields.is_empty() {
+            return Err(GraphError::validation_error("Validate node has no fields to check".to_string()));
+        }
+        let (violations, parsed) = self.check_fields(|key| state.get_value(key));
+        for (key, value) in parsed {
+            state.set_value(&key, value)?;
+        }
+        let report = self.evaluate_for(violations, attempt, on_invalid);
+
+        let value = serde_json::to_value(&report)
+            .map_err(|e| GraphError::state_error(format!("Failed to serialize validation report: {}", e)))?;
+        state.set_value(&self.report_key, value)?;
+        if report.valid {
+            state.remove_value(&self.feedback_key)?;
+        } else {
+            state.set_value(&self.feedback_key, serde_json::Value::String(report.feedback()))?;
+        }
+        Ok(report)
+    }
+
+    /// Check every field, returning the violations and the parsed values of valid string fields
+    fn check_fields<F>(&self, get: F) -> (Vec<FieldViolation>, Vec<(String, serde_json::Value)>)
+    where
+        F: Fn(&str) -> Option<serde_json::Value>,
+    {
+        let mut violations = Vec::new();
+        let mut parsed = Vec::new();
+        for field in &self.fields {
+            let Some(value) = get(&field.key) else {
+                violations.push(FieldViolation {
+                    key: field.key.clone(),
+                    path: String::new(),
+                    message: "field is missing".to_string(),
+                });
+                continue;
+            };
+
+            let candidate = match &value {
+                serde_json::Value::String(text) if self.parse_strings => extract_json(text),
+                _ => None,
+            };
+            match candidate {
+                Some(json) => {
+                    let field_violations = field.check(&json);
+                    if field_violations.is_empty() {
+                        parsed.push((field.key.clone(), json));
+                    }
+                    violations.extend(field_violations);
+                }
+                None => violations.extend(field.check(&value)),
+            }
+        }
+        (violations, parsed)
+    }
+
+    fn evaluate(&self, violations: Vec<FieldViolation>, attempt: u32) -> ValidationReport {
+        self.evaluate_for(violations, attempt, &self.on_invalid)
+    }
+
+    fn evaluate_for(&self, violations: Vec<FieldViolation>, attempt: u32, on_invalid: &OnInvalid) -> ValidationReport {
+        let valid = violations.is_empty();
+        let rerun_node = if !valid && attempt < self.max_attempts {
+            on_invalid.target().cloned()
+        } else {
+            None
+        };
+        ValidationReport { valid, violations, attempt, rerun_node }
+    }
+}
+
+impl Default for ValidateNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// JSON in an agent reply, allowing for surrounding prose or a code fence
+fn extract_json(text: &str) -> Option<serde_json::Value> {
+    if let Ok(value) = serde_json::from_str(text.trim()) {
+        return Some(value);
+    }
+    let start = text.find(['{', '['])?;
+    let end = text.rfind(['}', ']'])?;
+    if end <= start {
+        return None;
+    }
+    serde_json::from_str(&text[start..=end]).ok()
+}
+
+#[async_trait]
+impl<S> Node<S> for ValidateNode
+where
+    S: State + Send + Sync,
+{
+    async fn invoke(&self, state: &mut S) -> GraphResult<()> {
+        self.validate(state).map(|_| ())
+    }
+
+    fn metadata(&self) -> NodeMetadata {
+        self.metadata.clone()
+    }
+}
+
+/// Edge condition reading the report written by a [`ValidateNode`]
+#[derive(Debug, Clone)]
+pub struct ValidationCondition {
+    report_key: String,
+}
+
+impl ValidationCondition {
+    /// Condition on the report stored under `report_key`
+    pub fn new<K: Into<String>>(report_key: K) -> Self {
+        Self {
+            report_key: report_key.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: State> EdgeCondition<S> for ValidationCondition {
+    async fn evaluate(&self, state: &S) -> GraphResult<bool> {
+        Ok(state.get_value(&self.report_key)
+            .and_then(|report| serde_json::from_value::<ValidationReport>(report).ok())
+            .is_some_and(|report| report.needs_rerun()))
+    }
+
+    fn condition_id(&self) -> String {
+        format!("validation:{}", self.report_key)
+    }
+
+    fn description(&self) -> String {
+        format!("Output validated under '{}' needs another attempt", self.report_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Invoice {
+        number: String,
+        total: f64,
+    }
+
+    fn invoice_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "required": ["number", "total"],
+            "properties": {
+                "number": {"type": "string"},
+                "total": {"type": "number", "minimum": 0}
+            }
+        })
+    }
+
+    #[test]
+    fn test_schema_violations_and_string_parsing() {
+        let node = ValidateNode::new().with_schema("invoice", &invoice_schema()).unwrap();
+        let state: HashMap<&str, serde_json::Value> = [
+            ("invoice", json!("Here you go:\n```json\n{\"number\": \"INV-1\", \"total\": 12.5}\n```")),
+        ].into_iter().collect();
+
+        let (violations, parsed) = node.check_fields(|key| state.get(key).cloned());
+        assert!(violations.is_empty());
+        assert_eq!(parsed, vec![("invoice".to_string(), json!({"number": "INV-1", "total": 12.5}))]);
+
+        let state: HashMap<&str, serde_json::Value> = [("invoice", json!({"number": "INV-2", "total": -3}))].into_iter().collect();
+        let (violations, _) = node.check_fields(|key| state.get(key).cloned());
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "/total");
+
+        let (violations, _) = node.check_fields(|_| None);
+        assert_eq!(violations[0].message, "field is missing");
+    }
+
+    #[test]
+    fn test_serde_type_check() {
+        let node = ValidateNode::new().with_type::<Invoice, _>("invoice");
+        let (violations, _) = node.check_fields(|_| Some(json!({"number": "INV-3"})));
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].message.contains("total"));
+
+        let (violations, _) = node.check_fields(|_| Some(json!({"number": "INV-3", "total": 1})));
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn test_rerun_until_attempts_exhausted() {
+        let node = ValidateNode::new()
+            .with_type::<Invoice, _>("invoice")
+            .on_invalid(OnInvalid::Retry("extract".to_string()))
+            .with_max_attempts(2);
+        let violation = || vec![FieldViolation {
+            key: "invoice".to_string(),
+            path: String::new(),
+            message: "bad".to_string(),
+        }];
+
+        let first = node.evaluate(violation(), 1);
+        assert_eq!(first.rerun_node.as_deref(), Some("extract"));
+        assert!(first.feedback().contains("invoice: bad"));
+
+        let second = node.evaluate(violation(), 2);
+        assert!(!second.valid);
+        assert!(!second.needs_rerun());
+
+        assert!(node.evaluate(Vec::new(), 1).valid);
+        assert!(ValidateNode::new().with_schema("x", &json!({"type": 5})).is_err());
+    }
+}
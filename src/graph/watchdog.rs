@@ -0,0 +1,296 @@
+//! Watchdog for executions stuck in a loop.
+//!
+//! An agent that keeps producing the same answer, or two nodes that keep
+//! handing work back and forth, burn tokens without making progress. A
+//! [`Watchdog`] given to a graph with
+//! [`Graph::set_watchdog`](crate::graph::Graph::set_watchdog) compares each
+//! node's output with its previous one and looks for the same sequence of
+//! nodes repeating at the end of the execution path. When either goes on
+//! past its threshold the watchdog trips: the execution fails, moves to a
+//! fallback node or suspends for a human, and the trip is recorded in the
+//! execution context.
+
+use crate::node::NodeId;
+use crate::state::State;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Operation ID of executions a watchdog suspended for a human
+pub const WATCHDOG_OPERATION: &str = "watchdog";
+
+/// What to do when the watchdog trips
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WatchdogAction {
+    /// Fail the execution
+    #[default]
+    Fail,
+    /// Continue at another node, e.g. one that answers with what it has
+    Fallback {
+        /// Node to continue at
+        node_id: NodeId,
+    },
+    /// Suspend the execution so a human can inspect it, and continue it with
+    /// [`GraphEngine::fork`](crate::graph::engine::GraphEngine::fork)
+    Escalate,
+}
+
+/// Loop the watchdog found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LoopKind {
+    /// A node produced near-identical outputs in a row
+    RepeatedOutput {
+        /// Node producing them
+        node_id: NodeId,
+        /// Outputs in a row, counting the first
+        repeats: usize,
+    },
+    /// The same sequence of nodes ran again and again
+    Oscillation {
+        /// Nodes of one cycle, in order
+        cycle: Vec<NodeId>,
+        /// Cycles in a row
+        repeats: usize,
+    },
+}
+
+impl std::fmt::Display for LoopKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoopKind::RepeatedOutput { node_id, repeats } => {
+                write!(f, "node '{}' repeated its output {} times", node_id, repeats)
+            }
+            LoopKind::Oscillation { cycle, repeats } => {
+                write!(f, "nodes {} repeated {} times", cycle.join(" -> "), repeats)
+            }
+        }
+    }
+}
+
+/// A trip of the watchdog, as recorded in the execution context
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogTrip {
+    /// Loop found
+    #[serde(flatten)]
+    pub kind: LoopKind,
+    /// Step the loop was found at
+    pub step: u64,
+    /// Action taken
+    pub action: WatchdogAction,
+}
+
+/// Loop detection settings of a graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watchdog {
+    /// State fields compared between runs of a node
+    pub output_keys: Vec<String>,
+    /// Similarity, 0.0 to 1.0, at which two outputs count as the same
+    pub similarity: f64,
+    /// Near-identical outputs in a row that trip the watchdog
+    pub max_repeated_outputs: usize,
+    /// Repeats of a cycle of nodes that trip the watchdog
+    pub max_cycle_repeats: usize,
+    /// Longest cycle looked for, in nodes
+    pub max_cycle_len: usize,
+    /// What to do when it trips
+    pub action: WatchdogAction,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            output_keys: vec!["output".to_string()],
+            similarity: 0.9,
+            max_repeated_outputs: 3,
+            max_cycle_repeats: 5,
+            max_cycle_len: 4,
+            action: WatchdogAction::Fail,
+        }
+    }
+}
+
+impl Watchdog {
+    /// A watchdog comparing the `output` field, tripping on 3 repeated outputs or 5 repeated cycles
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare `keys` between runs of a node
+    pub fn with_output_keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.output_keys = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Count outputs at least `similarity` alike as repeats
+    pub fn with_similarity(mut self, similarity: f64) -> Self {
+        self.similarity = similarity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Trip after `repeats` near-identical outputs in a row
+    pub fn with_max_repeated_outputs(mut self, repeats: usize) -> Self {
+        self.max_repeated_outputs = repeats.max(2);
+        self
+    }
+
+    /// Trip after a cycle of at most `max_len` nodes ran `repeats` times in a row
+    pub fn with_max_cycle_repeats(mut self, repeats: usize, max_len: usize) -> Self {
+        self.max_cycle_repeats = repeats.max(2);
+        self.max_cycle_len = max_len;
+        self
+    }
+
+    /// Take `action` when it trips
+    pub fn with_action(mut self, action: WatchdogAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Watch one execution
+    pub(crate) fn tracker(&self) -> WatchdogTracker {
+        WatchdogTracker::default()
+    }
+
+    /// Text compared between runs of a node, if the state has any of the output fields
+    fn output_of<S: State>(&self, state: &S) -> Option<String> {
+        let values: Vec<String> = self.output_keys.iter()
+            .filter_map(|key| state.get_value(key))
+            .map(|value| match value {
+                serde_json::Value::String(text) => text,
+                other => other.to_string(),
+            })
+            .collect();
+        (!values.is_empty()).then(|| values.join("\n"))
+    }
+
+    /// Cycle at the end of `path` repeated at least `max_cycle_repeats` times, shortest first
+    fn oscillation(&self, path: &[NodeId]) -> Option<LoopKind> {
+        let repeats = self.max_cycle_repeats;
+        for len in 1..=self.max_cycle_len {
+            if path.len() < len * repeats {
+                break;
+            }
+            let cycle = &path[path.len() - len..];
+            // A cycle of one node repeating a shorter cycle was already checked
+            if len > 1 && cycle.iter().all(|node| node == &cycle[0]) {
+                continue;
+            }
+            let repeated = (2..=repeats).all(|k| &path[path.len() - k * len..path.len() - (k - 1) * len] == cycle);
+            if repeated {
+                return Some(LoopKind::Oscillation { cycle: cycle.to_vec(), repeats });
+            }
+        }
+        None
+    }
+}
+
+/// What the watchdog remembers about one execution
+#[derive(Debug, Default)]
+pub(crate) struct WatchdogTracker {
+    /// Last output of each node and how many times in a row it was repeated
+    outputs: HashMap<NodeId, (String, usize)>,
+    /// Start of the path still considered, moved past loops already handled
+    path_start: usize,
+}
+
+impl WatchdogTracker {
+    /// Look for a loop after `node_id` ran, with `path` the execution path so far
+    pub(crate) fn observe<S: State>(&mut self, watchdog: &Watchdog, path: &[NodeId], node_id: &NodeId, state: &S) -> Option<LoopKind> {
+        if let Some(output) = watchdog.output_of(state) {
+            let repeats = match self.outputs.get(node_id) {
+                Some((previous, repeats)) if similarity(previous, &output) >= watchdog.similarity => repeats + 1,
+                _ => 1,
+            };
+            self.outputs.insert(node_id.clone(), (output, repeats));
+            if repeats >= watchdog.max_repeated_outputs {
+                return Some(LoopKind::RepeatedOutput { node_id: node_id.clone(), repeats });
+            }
+        }
+        watchdog.oscillation(&path[self.path_start.min(path.len())..])
+    }
+
+    /// Forget everything before the end of `path`, after the loop was dealt with
+    pub(crate) fn reset(&mut self, path: &[NodeId]) {
+        self.outputs.clear();
+        self.path_start = path.len();
+    }
+}
+
+/// Similarity of two texts, 0.0 to 1.0, by the words they share
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[derive(Debug, Clone, Default)]
+    struct TestState {
+        values: HashMap<String, Value>,
+    }
+
+    impl State for TestState {
+        fn get_value(&self, key: &str) -> Option<Value> {
+            self.values.get(key).cloned()
+        }
+    }
+
+    #[test]
+    fn test_watchdog_detects_repeats_and_oscillation() {
+        let watchdog = Watchdog::new().with_max_cycle_repeats(3, 3);
+        let mut tracker = watchdog.tracker();
+        let mut state = TestState::default();
+        let agent = "agent".to_string();
+        let mut path = Vec::new();
+
+        for (i, output) in ["Let me check the docs.", "Let me check the docs!", "let me check the DOCS"].iter().enumerate() {
+            state.values.insert("output".to_string(), json!(output));
+            path.push(agent.clone());
+            // A self-loop trips on the third repeated output first
+            let found = tracker.observe(&watchdog, &path, &agent, &state);
+            if i < 2 {
+                assert_eq!(found, None);
+            } else {
+                assert_eq!(found, Some(LoopKind::RepeatedOutput { node_id: agent.clone(), repeats: 3 }));
+            }
+        }
+
+        tracker.reset(&path);
+        let mut found = None;
+        for step in 0..6 {
+            let node = if step % 2 == 0 { "plan" } else { "critique" }.to_string();
+            state.values.insert("output".to_string(), json!(format!("draft {}", step)));
+            path.push(node.clone());
+            found = tracker.observe(&watchdog, &path, &node, &state);
+            if found.is_some() {
+                break;
+            }
+        }
+        assert_eq!(found, Some(LoopKind::Oscillation { cycle: vec!["plan".to_string(), "critique".to_string()], repeats: 3 }));
+
+        assert!(similarity("The refund is approved", "the refund is approved.") > 0.99);
+        assert!(similarity("The refund is approved", "The refund is denied") < 0.9);
+    }
+}
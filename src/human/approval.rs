@@ -2,6 +2,7 @@
 
 use super::traits::{HumanResult, InteractionError, HumanInteraction};
 use super::{HumanContext, HumanStats};
+use crate::graph::determinism;
 // use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -72,7 +73,7 @@ impl ApprovalRequest {
     
     /// Set expiration time
     pub fn with_expiration(mut self, duration: Duration) -> Self {
-        self.expires_at = Some(SystemTime::now() + duration);
+        self.expires_at = Some(determinism::system_now() + duration);
         self
     }
     
@@ -85,7 +86,7 @@ impl ApprovalRequest {
     /// Check if the request has expired
     pub fn is_expired(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
-            SystemTime::now() > expires_at
+            determinism::system_now() > expires_at
         } else {
             false
         }
@@ -411,7 +412,7 @@ impl ApprovalManager {
                 request.min_approvals = policy.min_approvals;
             }
             if request.expires_at.is_none() {
-                request.expires_at = Some(SystemTime::now() + policy.timeout);
+                request.expires_at = Some(determinism::system_now() + policy.timeout);
             }
         }
         
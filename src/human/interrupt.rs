@@ -30,7 +30,7 @@ impl ResumeToken {
             interrupt_id: Uuid::new_v4().to_string(),
             execution_id,
             node_id,
-            interrupted_at: SystemTime::now(),
+            interrupted_at: crate::graph::determinism::system_now(),
             expires_at: None,
         }
     }
@@ -44,7 +44,7 @@ impl ResumeToken {
     /// Check if the token has expired
     pub fn is_expired(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
-            SystemTime::now() > expires_at
+            crate::graph::determinism::system_now() > expires_at
         } else {
             false
         }
@@ -53,7 +53,7 @@ impl ResumeToken {
     /// Get time remaining before expiration
     pub fn time_remaining(&self) -> Option<Duration> {
         self.expires_at.and_then(|expires_at| {
-            expires_at.duration_since(SystemTime::now()).ok()
+            expires_at.duration_since(crate::graph::determinism::system_now()).ok()
         })
     }
 }
@@ -95,6 +95,27 @@ pub mod execution;
 /// Visual debugging and monitoring interface (LangSmith/LangGraph Studio equivalent)
 pub mod visualization;
 
+/// Application manifests declaring graphs and the resources they share
+pub mod manifest;
+
+/// Layered configuration for every subsystem
+pub mod config;
+
+/// JSON Schemas of the declarative formats, for editors and CI
+pub mod schemas;
+
+#[cfg(feature = "ffi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ffi")))]
+pub mod ffi;
+
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
+
+#[cfg(feature = "bench")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bench")))]
+pub mod bench;
+
 // Re-export core types for convenience
 pub use error::{GraphError, GraphResult};
 pub use graph::{Graph, GraphBuilder, ExecutionContext, ExecutionConfig};
@@ -108,6 +129,18 @@ pub use streaming::{ExecutionEvent, ExecutionStream};
 /// Version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Lowercase hex encoding of `bytes`
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Lowercase hex SHA-256 digest of `data`
+pub(crate) fn sha256_hex(data: impl AsRef<[u8]>) -> String {
+    use sha2::{Digest, Sha256};
+
+    hex(&Sha256::digest(data.as_ref()))
+}
+
 /// Initialize tracing for the framework
 pub fn init_tracing() {
     tracing_subscriber::fmt()
@@ -0,0 +1,357 @@
+// Budget-aware scheduling of LLM calls for AgentGraph
+// Batch and low-priority executions move to cheaper models and share fewer slots during peak hours
+
+#![allow(missing_docs)]
+
+use super::ModelProfileRegistry;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// How urgently an execution's LLM calls need to be served
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionPriority {
+    /// A user is waiting on the result
+    #[default]
+    Interactive,
+    /// Offline or bulk work
+    Batch,
+    /// Work that may be delayed or degraded freely
+    LowPriority,
+}
+
+impl ExecutionPriority {
+    /// Whether calls at this priority may be moved to cheaper models or throttled
+    pub fn is_deferrable(&self) -> bool {
+        !matches!(self, Self::Interactive)
+    }
+}
+
+tokio::task_local! {
+    static EXECUTION_PRIORITY: ExecutionPriority;
+}
+
+/// Run `future` with every LLM call inside it tagged with `priority`
+///
+/// Calls outside such a scope are treated as interactive.
+pub async fn with_priority<F: Future>(priority: ExecutionPriority, future: F) -> F::Output {
+    EXECUTION_PRIORITY.scope(priority, future).await
+}
+
+/// Carry the current priority into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let value = EXECUTION_PRIORITY.try_with(ExecutionPriority::clone).ok();
+    async move {
+        match value {
+            Some(value) => EXECUTION_PRIORITY.scope(value, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Priority of the execution the current task belongs to
+pub fn current_priority() -> ExecutionPriority {
+    EXECUTION_PRIORITY.try_with(|priority| *priority).unwrap_or_default()
+}
+
+/// A recurring window of peak demand, in UTC
+///
+/// The window covers `start_hour` up to but excluding `end_hour` and may
+/// wrap past midnight. An empty `weekdays` list means every day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeakWindow {
+    pub start_hour: u32,
+    pub end_hour: u32,
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+}
+
+impl PeakWindow {
+    pub fn new(start_hour: u32, end_hour: u32) -> Self {
+        Self {
+            start_hour,
+            end_hour,
+            weekdays: Vec::new(),
+        }
+    }
+
+    /// Restrict the window to certain days
+    pub fn on(mut self, weekdays: Vec<Weekday>) -> Self {
+        self.weekdays = weekdays;
+        self
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let hour = at.hour();
+        let in_hours = if self.start_hour <= self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        };
+        if !in_hours {
+            return false;
+        }
+        if self.weekdays.is_empty() {
+            return true;
+        }
+
+        // A window wrapping past midnight belongs to the day it started on
+        let day = if self.start_hour > self.end_hour && hour < self.end_hour {
+            at.weekday().pred()
+        } else {
+            at.weekday()
+        };
+        self.weekdays.contains(&day)
+    }
+}
+
+/// Configuration for [`BudgetScheduler`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetPolicyConfig {
+    /// Windows during which deferrable calls are degraded
+    pub peak_windows: Vec<PeakWindow>,
+    /// Explicit cheaper model for a model, used before profile lookup
+    #[serde(default)]
+    pub model_substitutions: HashMap<String, String>,
+    /// Deferrable calls allowed in flight at once during peak windows
+    pub peak_concurrency: usize,
+}
+
+impl Default for BudgetPolicyConfig {
+    fn default() -> Self {
+        Self {
+            peak_windows: Vec::new(),
+            model_substitutions: HashMap::new(),
+            peak_concurrency: 2,
+        }
+    }
+}
+
+/// An LLM call about to be made
+#[derive(Debug, Clone)]
+pub struct ScheduledCall<'a> {
+    pub priority: ExecutionPriority,
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub needs_tools: bool,
+    /// Estimated tokens of the prompt
+    pub prompt_tokens: u32,
+    /// Most tokens the completion may take
+    pub max_completion_tokens: u32,
+    pub at: DateTime<Utc>,
+}
+
+/// A model swapped for a cheaper one, recorded in events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSubstitution {
+    pub provider: String,
+    pub from: String,
+    pub to: String,
+    pub priority: ExecutionPriority,
+    pub reason: String,
+}
+
+/// How a call should be made
+#[derive(Debug, Default)]
+pub struct CallPlan {
+    /// Model to use instead of the requested one
+    pub substitution: Option<ModelSubstitution>,
+    /// Slot held for the duration of the call
+    pub permit: Option<OwnedSemaphorePermit>,
+}
+
+/// Hook consulted by [`LLMManager`](super::LLMManager) before each call of a deferrable execution
+#[async_trait]
+pub trait SchedulingPolicy: Send + Sync + Debug {
+    /// Decide the model and admission of a call, waiting if it must be throttled
+    async fn schedule(&self, call: &ScheduledCall<'_>) -> CallPlan;
+}
+
+/// Moves deferrable calls to cheaper models and limits their concurrency during peak windows
+#[derive(Debug)]
+pub struct BudgetScheduler {
+    config: BudgetPolicyConfig,
+    profiles: ModelProfileRegistry,
+    peak_slots: Arc<Semaphore>,
+}
+
+impl BudgetScheduler {
+    pub fn new(config: BudgetPolicyConfig, profiles: ModelProfileRegistry) -> Self {
+        let peak_slots = Arc::new(Semaphore::new(config.peak_concurrency.max(1)));
+        Self {
+            config,
+            profiles,
+            peak_slots,
+        }
+    }
+
+    pub fn is_peak(&self, at: DateTime<Utc>) -> bool {
+        self.config.peak_windows.iter().any(|window| window.contains(at))
+    }
+
+    /// Cheaper model on the same provider whose context holds the call, explicit substitutions first
+    ///
+    /// An explicit substitute that cannot hold the call, or lacks tools it needs, is skipped.
+    pub fn cheaper_model(&self, call: &ScheduledCall<'_>) -> Option<String> {
+        let explicit = self
+            .config
+            .model_substitutions
+            .get(call.model)
+            .and_then(|substitute| self.profiles.get(substitute))
+            .filter(|p| !call.needs_tools || p.supports_tools)
+            .filter(|p| p.fits_context(call.prompt_tokens, call.max_completion_tokens));
+        if let Some(profile) = explicit {
+            return Some(profile.model.clone());
+        }
+
+        let cost = |model: &str| {
+            self.profiles
+                .get(model)
+                .map(|p| p.prompt_cost_per_1k + p.completion_cost_per_1k)
+        };
+        let current = cost(call.model)?;
+        self.profiles
+            .for_provider(call.provider)
+            .into_iter()
+            .filter(|p| !call.needs_tools || p.supports_tools)
+            .filter(|p| p.fits_context(call.prompt_tokens, call.max_completion_tokens))
+            .filter(|p| p.prompt_cost_per_1k + p.completion_cost_per_1k < current)
+            .min_by(|a, b| {
+                let cost_a = a.prompt_cost_per_1k + a.completion_cost_per_1k;
+                let cost_b = b.prompt_cost_per_1k + b.completion_cost_per_1k;
+                cost_a.total_cmp(&cost_b)
+            })
+            .map(|p| p.model.clone())
+    }
+}
+
+#[async_trait]
+impl SchedulingPolicy for BudgetScheduler {
+    async fn schedule(&self, call: &ScheduledCall<'_>) -> CallPlan {
+        if !call.priority.is_deferrable() || !self.is_peak(call.at) {
+            return CallPlan::default();
+        }
+
+        let substitution = self
+            .cheaper_model(call)
+            .filter(|model| model != call.model)
+            .map(|to| ModelSubstitution {
+                provider: call.provider.to_string(),
+                from: call.model.to_string(),
+                to,
+                priority: call.priority,
+                reason: "peak_window".to_string(),
+            });
+        let permit = self.peak_slots.clone().acquire_owned().await.ok();
+
+        CallPlan {
+            substitution,
+            permit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ModelProfile;
+    use chrono::TimeZone;
+
+    fn profiles() -> ModelProfileRegistry {
+        let mut profiles = ModelProfileRegistry::new();
+        profiles.register(ModelProfile::new("big", "openai", 8192).with_tools(true).with_cost(0.03, 0.06));
+        profiles.register(ModelProfile::new("small", "openai", 4096).with_tools(true).with_cost(0.001, 0.002));
+        profiles.register(ModelProfile::new("tiny", "openai", 2048).with_cost(0.0001, 0.0002));
+        profiles.register(ModelProfile::new("other", "anthropic", 8192).with_tools(true).with_cost(0.0, 0.0));
+        profiles
+    }
+
+    #[test]
+    fn test_peak_window_wraps_midnight() {
+        // Friday 23:00 through Saturday 02:00 UTC
+        let window = PeakWindow::new(23, 2).on(vec![Weekday::Fri]);
+        let friday_late = Utc.with_ymd_and_hms(2024, 5, 3, 23, 30, 0).unwrap();
+        let saturday_early = Utc.with_ymd_and_hms(2024, 5, 4, 1, 0, 0).unwrap();
+        let saturday_late = Utc.with_ymd_and_hms(2024, 5, 4, 23, 30, 0).unwrap();
+
+        assert!(window.contains(friday_late));
+        assert!(window.contains(saturday_early));
+        assert!(!window.contains(saturday_late));
+        assert!(!PeakWindow::new(9, 17).contains(saturday_late));
+    }
+
+    #[tokio::test]
+    async fn test_batch_calls_substituted_during_peak() {
+        let config = BudgetPolicyConfig {
+            peak_windows: vec![PeakWindow::new(9, 17)],
+            peak_concurrency: 1,
+            ..Default::default()
+        };
+        let scheduler = BudgetScheduler::new(config, profiles());
+        let peak = Utc.with_ymd_and_hms(2024, 5, 3, 10, 0, 0).unwrap();
+        let call = |priority, at| ScheduledCall {
+            priority,
+            provider: "openai",
+            model: "big",
+            needs_tools: true,
+            prompt_tokens: 1500,
+            max_completion_tokens: 500,
+            at,
+        };
+
+        let plan = scheduler.schedule(&call(ExecutionPriority::Batch, peak)).await;
+        // Cheapest same-provider model that still supports tools
+        assert_eq!(plan.substitution.as_ref().unwrap().to, "small");
+        assert!(plan.permit.is_some());
+        // The only peak slot is held until the plan is dropped
+        assert_eq!(scheduler.peak_slots.available_permits(), 0);
+        drop(plan);
+
+        let interactive = scheduler.schedule(&call(ExecutionPriority::Interactive, peak)).await;
+        assert!(interactive.substitution.is_none() && interactive.permit.is_none());
+
+        let off_peak = Utc.with_ymd_and_hms(2024, 5, 3, 20, 0, 0).unwrap();
+        let batch_off_peak = scheduler.schedule(&call(ExecutionPriority::Batch, off_peak)).await;
+        assert!(batch_off_peak.substitution.is_none());
+
+        // Nothing cheaper with tools has room for a long prompt
+        let long = ScheduledCall { prompt_tokens: 6000, ..call(ExecutionPriority::Batch, peak) };
+        assert!(scheduler.schedule(&long).await.substitution.is_none());
+    }
+
+    #[test]
+    fn test_substitution_skipped_when_too_small() {
+        let config = BudgetPolicyConfig {
+            model_substitutions: HashMap::from([("big".to_string(), "tiny".to_string())]),
+            ..Default::default()
+        };
+        let scheduler = BudgetScheduler::new(config, profiles());
+        let call = ScheduledCall {
+            priority: ExecutionPriority::Batch,
+            provider: "openai",
+            model: "big",
+            needs_tools: false,
+            prompt_tokens: 500,
+            max_completion_tokens: 500,
+            at: Utc::now(),
+        };
+        assert_eq!(scheduler.cheaper_model(&call).as_deref(), Some("tiny"));
+
+        // "tiny" cannot hold the prompt, so the profile search picks the next cheapest
+        let long = ScheduledCall { prompt_tokens: 3000, ..call };
+        assert_eq!(scheduler.cheaper_model(&long).as_deref(), Some("small"));
+    }
+
+    #[tokio::test]
+    async fn test_priority_scope() {
+        assert_eq!(current_priority(), ExecutionPriority::Interactive);
+        let inside = with_priority(ExecutionPriority::Batch, async { current_priority() }).await;
+        assert_eq!(inside, ExecutionPriority::Batch);
+    }
+}
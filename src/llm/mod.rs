@@ -9,7 +9,11 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+pub mod budget;
 pub mod providers;
+pub mod profiles;
+
+pub use profiles::{LatencyClass, ModelProfile, ModelProfileRegistry};
 
 /// LLM message role
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -125,6 +129,12 @@ pub struct CompletionRequest {
     pub functions: Option<Vec<FunctionDefinition>>,
     /// Function call behavior
     pub function_call: Option<FunctionCallBehavior>,
+    /// Return log probabilities of the generated tokens
+    #[serde(default)]
+    pub logprobs: bool,
+    /// Number of most likely alternatives to return per token (requires `logprobs`)
+    #[serde(default)]
+    pub top_logprobs: Option<u32>,
     /// Request metadata
     pub metadata: HashMap<String, String>,
 }
@@ -141,11 +151,27 @@ impl Default for CompletionRequest {
             stream: false,
             functions: None,
             function_call: None,
+            logprobs: false,
+            top_logprobs: None,
             metadata: HashMap::new(),
         }
     }
 }
 
+impl CompletionRequest {
+    /// Request token log probabilities, optionally with the top-N alternatives per token
+    pub fn with_logprobs(mut self, top_logprobs: Option<u32>) -> Self {
+        self.logprobs = true;
+        self.top_logprobs = top_logprobs;
+        self
+    }
+
+    /// Rough token count of the messages, estimated from their length
+    pub fn estimated_prompt_tokens(&self) -> u32 {
+        self.messages.iter().map(crate::agents::limits::estimate_tokens).sum()
+    }
+}
+
 /// Function call behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FunctionCallBehavior {
@@ -205,6 +231,13 @@ pub struct CompletionResponse {
     pub timestamp: SystemTime,
 }
 
+impl CompletionResponse {
+    /// Confidence of the first choice, if the provider returned log probabilities
+    pub fn confidence(&self) -> Option<f64> {
+        self.choices.first().and_then(|choice| choice.confidence())
+    }
+}
+
 /// Response choice
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Choice {
@@ -214,6 +247,96 @@ pub struct Choice {
     pub message: Message,
     /// Finish reason
     pub finish_reason: FinishReason,
+    /// Token log probabilities (only present when requested and supported)
+    #[serde(default)]
+    pub logprobs: Option<ChoiceLogprobs>,
+}
+
+impl Choice {
+    /// Model confidence for this choice in the range 0.0-1.0.
+    ///
+    /// Computed as the geometric mean of the token probabilities, i.e.
+    /// `exp(mean(logprob))`. Returns `None` when no log probabilities are available.
+    pub fn confidence(&self) -> Option<f64> {
+        self.logprobs.as_ref().and_then(|logprobs| logprobs.confidence())
+    }
+}
+
+/// Log probability information for a generated choice
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChoiceLogprobs {
+    /// Per-token log probabilities in generation order
+    pub content: Vec<TokenLogprob>,
+}
+
+impl ChoiceLogprobs {
+    /// Create log probabilities from a list of tokens
+    pub fn new(content: Vec<TokenLogprob>) -> Self {
+        Self { content }
+    }
+
+    /// Sum of the token log probabilities (log probability of the whole sequence)
+    pub fn total_logprob(&self) -> f64 {
+        self.content.iter().map(|t| t.logprob).sum()
+    }
+
+    /// Mean token log probability
+    pub fn mean_logprob(&self) -> Option<f64> {
+        if self.content.is_empty() {
+            None
+        } else {
+            Some(self.total_logprob() / self.content.len() as f64)
+        }
+    }
+
+    /// Geometric-mean token probability in the range 0.0-1.0
+    pub fn confidence(&self) -> Option<f64> {
+        self.mean_logprob().map(|mean| mean.exp().clamp(0.0, 1.0))
+    }
+
+    /// Lowest-probability token, useful for spotting where the model was unsure
+    pub fn least_likely_token(&self) -> Option<&TokenLogprob> {
+        self.content
+            .iter()
+            .min_by(|a, b| a.logprob.partial_cmp(&b.logprob).unwrap_or(std::cmp::Ordering::Equal))
+    }
+}
+
+/// Log probability of a single generated token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    /// Token text
+    pub token: String,
+    /// Natural log probability of the token
+    pub logprob: f64,
+    /// Most likely alternatives at this position
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+impl TokenLogprob {
+    /// Create a new token log probability
+    pub fn new(token: String, logprob: f64) -> Self {
+        Self {
+            token,
+            logprob,
+            top_logprobs: Vec::new(),
+        }
+    }
+
+    /// Probability of the token in the range 0.0-1.0
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+/// Alternative token candidate with its log probability
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopLogprob {
+    /// Token text
+    pub token: String,
+    /// Natural log probability of the token
+    pub logprob: f64,
 }
 
 /// Reason why generation finished
@@ -262,6 +385,130 @@ impl TokenUsage {
     }
 }
 
+/// LLM usage aggregated over one or more completions, e.g. a single agent task
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct LLMUsage {
+    /// Number of completion calls
+    pub calls: u32,
+    /// Prompt tokens
+    pub prompt_tokens: u64,
+    /// Completion tokens
+    pub completion_tokens: u64,
+    /// Total tokens
+    pub total_tokens: u64,
+    /// Estimated cost in USD
+    pub cost: f64,
+    /// Time spent waiting on providers
+    pub latency: Duration,
+}
+
+impl LLMUsage {
+    /// Record a completion and the time it took
+    pub fn record(&mut self, response: &CompletionResponse, latency: Duration) {
+        self.calls += 1;
+        self.prompt_tokens += response.usage.prompt_tokens as u64;
+        self.completion_tokens += response.usage.completion_tokens as u64;
+        self.total_tokens += response.usage.total_tokens as u64;
+        self.cost += response.usage.estimated_cost.unwrap_or(0.0);
+        self.latency += latency;
+    }
+
+    /// Add another usage record to this one
+    pub fn merge(&mut self, other: &LLMUsage) {
+        self.calls += other.calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cost += other.cost;
+        self.latency += other.latency;
+    }
+
+    /// Check whether any calls were recorded
+    pub fn is_empty(&self) -> bool {
+        self.calls == 0
+    }
+
+    /// Provider latency in milliseconds
+    pub fn latency_ms(&self) -> u64 {
+        self.latency.as_millis() as u64
+    }
+
+    /// Flat metadata entries, as stored on node executions and trace events
+    pub fn to_metadata(&self) -> HashMap<String, serde_json::Value> {
+        let mut metadata = HashMap::new();
+        metadata.insert("llm_calls".to_string(), serde_json::json!(self.calls));
+        metadata.insert("llm_prompt_tokens".to_string(), serde_json::json!(self.prompt_tokens));
+        metadata.insert("llm_completion_tokens".to_string(), serde_json::json!(self.completion_tokens));
+        metadata.insert("llm_total_tokens".to_string(), serde_json::json!(self.total_tokens));
+        metadata.insert("llm_cost_usd".to_string(), serde_json::json!(self.cost));
+        metadata.insert("llm_latency_ms".to_string(), serde_json::json!(self.latency_ms()));
+        metadata
+    }
+
+    /// Read usage back from metadata written by `to_metadata`
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let calls = metadata.get("llm_calls")?.as_u64()? as u32;
+        let field = |key: &str| metadata.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        Some(Self {
+            calls,
+            prompt_tokens: field("llm_prompt_tokens"),
+            completion_tokens: field("llm_completion_tokens"),
+            total_tokens: field("llm_total_tokens"),
+            cost: metadata.get("llm_cost_usd").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            latency: Duration::from_millis(field("llm_latency_ms")),
+        })
+    }
+}
+
+/// One call to a provider as it happened, kept in traces so it can be replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmCallRecord {
+    /// Name the provider is registered under
+    pub provider: String,
+    /// Request sent, after profile defaults and scheduling were applied
+    pub request: CompletionRequest,
+    /// Response received
+    pub response: CompletionResponse,
+    /// Time the provider took to answer
+    pub duration_ms: u64,
+}
+
+impl LlmCallRecord {
+    /// Text of the first choice
+    pub fn content(&self) -> &str {
+        self.response.choices.first().map(|choice| choice.message.content.as_str()).unwrap_or("")
+    }
+}
+
+/// A provider call made while a node ran, emitted as an `llm_call` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmCallEvent {
+    /// Node that made the call
+    pub node_id: String,
+    /// The call
+    pub call: LlmCallRecord,
+}
+
+#[cfg(feature = "streaming")]
+impl crate::streaming::CustomEvent for LlmCallEvent {
+    const EVENT_TYPE: &'static str = "llm_call";
+}
+
+/// A model swapped while a node ran, emitted as a `model_substituted` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSubstituted {
+    /// Node whose call was rescheduled
+    pub node_id: String,
+    /// The swap
+    pub substitution: budget::ModelSubstitution,
+}
+
+#[cfg(feature = "streaming")]
+impl crate::streaming::CustomEvent for ModelSubstituted {
+    const EVENT_TYPE: &'static str = "model_substituted";
+}
+
 /// LLM provider trait
 #[async_trait::async_trait]
 pub trait LLMProvider: Send + Sync + std::fmt::Debug {
@@ -286,6 +533,24 @@ pub trait LLMProvider: Send + Sync + std::fmt::Debug {
         false
     }
     
+    /// Check if provider can return token log probabilities
+    fn supports_logprobs(&self) -> bool {
+        false
+    }
+    
+    /// Check if provider accepts image inputs
+    fn supports_vision(&self) -> bool {
+        false
+    }
+    
+    /// List models available to the configured credentials.
+    ///
+    /// Remote providers query their model listing endpoint, which doubles as an
+    /// authentication check. The default returns the static model list.
+    async fn list_models(&self) -> Result<Vec<String>, LLMError> {
+        Ok(self.supported_models())
+    }
+    
     /// Complete a request
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, LLMError>;
     
@@ -328,7 +593,7 @@ impl ModelPricing {
 }
 
 /// LLM configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct LLMConfig {
     /// Default provider
     pub default_provider: String,
@@ -361,7 +626,7 @@ impl Default for LLMConfig {
 }
 
 /// Provider-specific configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ProviderConfig {
     /// API key
     pub api_key: Option<String>,
@@ -370,13 +635,15 @@ pub struct ProviderConfig {
     /// Organization ID
     pub organization: Option<String>,
     /// Additional headers
+    #[serde(default)]
     pub headers: HashMap<String, String>,
     /// Provider-specific settings
+    #[serde(default)]
     pub settings: HashMap<String, serde_json::Value>,
 }
 
 /// Retry configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct RetryConfig {
     /// Maximum retry attempts
     pub max_attempts: u32,
@@ -408,18 +675,47 @@ pub struct LLMManager {
     providers: HashMap<String, Arc<dyn LLMProvider>>,
     /// Request statistics
     stats: Arc<std::sync::Mutex<LLMStats>>,
+    /// Per-model configuration profiles
+    profiles: ModelProfileRegistry,
+    /// Policy applied to calls of batch and low-priority executions
+    scheduling_policy: Option<Arc<dyn budget::SchedulingPolicy>>,
 }
 
 impl LLMManager {
-    /// Create a new LLM manager
+    /// Create a new LLM manager using the bundled model profiles
     pub fn new(config: LLMConfig) -> Self {
         Self {
             config,
             providers: HashMap::new(),
             stats: Arc::new(std::sync::Mutex::new(LLMStats::default())),
+            profiles: ModelProfileRegistry::bundled(),
+            scheduling_policy: None,
         }
     }
     
+    /// Replace the model profile registry
+    pub fn with_profiles(mut self, profiles: ModelProfileRegistry) -> Self {
+        self.profiles = profiles;
+        self
+    }
+    
+    /// Get the model profile registry
+    pub fn profiles(&self) -> &ModelProfileRegistry {
+        &self.profiles
+    }
+    
+    /// Route calls of deferrable executions through a scheduling policy
+    pub fn with_scheduling_policy(mut self, policy: Arc<dyn budget::SchedulingPolicy>) -> Self {
+        self.scheduling_policy = Some(policy);
+        self
+    }
+    
+    /// Pricing for a model, preferring the provider's table over the profile
+    fn pricing_for(&self, provider: &dyn LLMProvider, model: &str) -> Option<ModelPricing> {
+        provider.get_pricing(model)
+            .or_else(|| self.profiles.get(model).map(|profile| profile.pricing()))
+    }
+    
     /// Register a provider
     pub fn register_provider(&mut self, name: String, provider: Arc<dyn LLMProvider>) {
         self.providers.insert(name, provider);
@@ -435,17 +731,69 @@ impl LLMManager {
         self.complete_with_provider(&self.config.default_provider, request).await
     }
     
+    /// Answer a call of a dry run from the mock provider, registered or built-in
+    async fn complete_dry_run(
+        &self,
+        provider_name: &str,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, LLMError> {
+        crate::graph::dry_run::record(crate::graph::dry_run::SimulatedEffectKind::LlmCall {
+            provider: provider_name.to_string(),
+            model: request.model.clone(),
+        });
+        match self.get_provider("mock") {
+            Some(mock) => mock.complete(request).await,
+            None => providers::MockProvider::new()
+                .with_delay(Duration::ZERO)
+                .complete(request)
+                .await,
+        }
+    }
+    
     /// Complete using specific provider
     pub async fn complete_with_provider(
         &self,
         provider_name: &str,
-        request: CompletionRequest,
+        mut request: CompletionRequest,
     ) -> Result<CompletionResponse, LLMError> {
+        let sandbox = crate::enterprise::sandbox::current();
+        if let Some(sandbox) = &sandbox {
+            sandbox.check_model(provider_name, &request.model).await
+                .map_err(|violation| LLMError::SandboxViolation { violation })?;
+        }
+        if crate::graph::dry_run::is_active() {
+            return self.complete_dry_run(provider_name, request).await;
+        }
+        
         let provider = self.get_provider(provider_name)
             .ok_or_else(|| LLMError::ProviderNotFound {
                 provider: provider_name.to_string(),
             })?;
         
+        // Let the scheduling policy swap the model or hold the call until a slot frees up
+        let _slot = self.apply_scheduling_policy(provider_name, &mut request).await;
+        if let Some(sandbox) = &sandbox {
+            // A substituted model has to be allowed too
+            sandbox.check_model(provider_name, &request.model).await
+                .map_err(|violation| LLMError::SandboxViolation { violation })?;
+        }
+        
+        // Apply model profile defaults and context window limits
+        if let Some(profile) = self.profiles.get(&request.model) {
+            if request.temperature.is_none() {
+                request.temperature = profile.default_temperature;
+            }
+            let prompt_tokens = request.estimated_prompt_tokens();
+            let max_tokens = request.max_tokens.unwrap_or_default();
+            if !profile.fits_context(prompt_tokens, max_tokens) {
+                return Err(LLMError::TokenLimitExceeded {
+                    prompt_tokens,
+                    max_tokens,
+                    limit: profile.context_window,
+                });
+            }
+        }
+        
         // Check cost limits
         if let Some(max_cost) = self.config.max_cost_per_request {
             if let Some(estimated_cost) = self.estimate_cost(&request, provider_name).await? {
@@ -458,6 +806,14 @@ impl LLMManager {
             }
         }
         
+        // Calls the sandbox cannot price are let through and counted once priced
+        if let Some(sandbox) = &sandbox {
+            if let Some(estimated_cost) = self.estimate_cost(&request, provider_name).await? {
+                sandbox.check_cost(estimated_cost).await
+                    .map_err(|violation| LLMError::SandboxViolation { violation })?;
+            }
+        }
+        
         // Execute with retry logic
         let mut attempts = 0;
         let mut delay = self.config.retry_config.base_delay;
@@ -465,18 +821,39 @@ impl LLMManager {
         loop {
             attempts += 1;
             
+            #[cfg(feature = "streaming")]
+            let started = tokio::time::Instant::now();
             match provider.complete(request.clone()).await {
                 Ok(mut response) => {
                     // Add cost information if tracking enabled
                     if self.config.cost_tracking {
-                        if let Some(pricing) = provider.get_pricing(&request.model) {
+                        if let Some(pricing) = self.pricing_for(provider.as_ref(), &request.model) {
                             let cost = pricing.calculate_cost(&response.usage);
                             response.usage.estimated_cost = Some(cost);
                         }
                     }
                     
+                    if let Some(sandbox) = &sandbox {
+                        let cost = response.usage.estimated_cost.or_else(|| {
+                            self.pricing_for(provider.as_ref(), &request.model)
+                                .map(|pricing| pricing.calculate_cost(&response.usage))
+                        });
+                        sandbox.record_cost(cost.unwrap_or_default());
+                    }
+                    
                     // Update statistics
                     self.update_stats(&response, provider_name);
+
+                    #[cfg(feature = "streaming")]
+                    crate::streaming::emit_node_custom(|node_id| LlmCallEvent {
+                        node_id,
+                        call: LlmCallRecord {
+                            provider: provider_name.to_string(),
+                            request: request.clone(),
+                            response: response.clone(),
+                            duration_ms: started.elapsed().as_millis() as u64,
+                        },
+                    });
                     
                     return Ok(response);
                 }
@@ -503,6 +880,126 @@ impl LLMManager {
         }
     }
     
+    /// Consult the scheduling policy for calls made under a deferrable priority
+    async fn apply_scheduling_policy(
+        &self,
+        provider_name: &str,
+        request: &mut CompletionRequest,
+    ) -> Option<tokio::sync::OwnedSemaphorePermit> {
+        let policy = self.scheduling_policy.as_ref()?;
+        let priority = budget::current_priority();
+        if !priority.is_deferrable() {
+            return None;
+        }
+
+        let plan = policy.schedule(&budget::ScheduledCall {
+            priority,
+            provider: provider_name,
+            model: &request.model,
+            needs_tools: request.functions.as_ref().is_some_and(|f| !f.is_empty()),
+            prompt_tokens: request.estimated_prompt_tokens(),
+            max_completion_tokens: request.max_tokens.unwrap_or_default(),
+            at: crate::graph::determinism::now(),
+        }).await;
+
+        if let Some(substitution) = plan.substitution {
+            tracing::info!(
+                provider = %substitution.provider,
+                from = %substitution.from,
+                to = %substitution.to,
+                priority = ?substitution.priority,
+                "Substituted model for deferrable execution"
+            );
+            request.model = substitution.to.clone();
+
+            #[cfg(feature = "streaming")]
+            crate::streaming::emit_node_custom(|node_id| ModelSubstituted {
+                node_id,
+                substitution: substitution.clone(),
+            });
+        }
+        plan.permit
+    }
+    
+    /// Probe every registered provider and collect a capability report.
+    ///
+    /// Each provider's model listing is queried (bounded by the configured request
+    /// timeout) and the configured default model is checked against it. Failures
+    /// are recorded in the report rather than returned.
+    pub async fn probe_providers(&self) -> providers::CapabilityReport {
+        let mut names: Vec<&String> = self.providers.keys().collect();
+        names.sort();
+
+        let mut results = Vec::with_capacity(names.len());
+        for name in names {
+            let provider = &self.providers[name];
+            let default_model = self.config.default_models.get(name).cloned();
+            let start = std::time::Instant::now();
+
+            let listing = match tokio::time::timeout(self.config.timeout, provider.list_models()).await {
+                Ok(result) => result,
+                Err(_) => Err(LLMError::NetworkError {
+                    message: format!("Timed out listing models after {:?}", self.config.timeout),
+                }),
+            };
+
+            let (available_models, error) = match listing {
+                Ok(models) => {
+                    let error = default_model.as_ref()
+                        .filter(|model| !models.contains(model) && !provider.supports_model(model))
+                        .map(|model| LLMError::ModelNotSupported {
+                            model: model.clone(),
+                            provider: name.clone(),
+                        });
+                    (models, error)
+                }
+                Err(e) => (Vec::new(), Some(e)),
+            };
+
+            results.push(providers::ProviderVerification {
+                provider: name.clone(),
+                capabilities: providers::get_provider_capabilities(provider.as_ref()),
+                available_models,
+                default_model,
+                latency: start.elapsed(),
+                error,
+            });
+        }
+
+        providers::CapabilityReport {
+            providers: results,
+            generated_at: SystemTime::now(),
+        }
+    }
+
+    /// Verify provider configuration at startup.
+    ///
+    /// Fails if the default provider is not registered or any provider fails its
+    /// probe, so misconfiguration surfaces before a graph starts executing.
+    pub async fn verify(&self) -> Result<providers::CapabilityReport, LLMError> {
+        if !self.providers.contains_key(&self.config.default_provider) {
+            return Err(LLMError::ConfigurationError {
+                message: format!(
+                    "Default provider '{}' is not registered",
+                    self.config.default_provider
+                ),
+            });
+        }
+
+        let report = self.probe_providers().await;
+        if !report.is_healthy() {
+            let failures = report.failures().iter()
+                .map(|p| format!("{}: {}", p.provider, p.error.as_ref().map(|e| e.to_string()).unwrap_or_default()))
+                .collect::<Vec<_>>()
+                .join("; ");
+            return Err(LLMError::ConfigurationError {
+                message: format!("Provider verification failed: {}", failures),
+            });
+        }
+
+        Ok(report)
+    }
+    
     /// Estimate cost for a request
     pub async fn estimate_cost(
         &self,
@@ -514,7 +1011,7 @@ impl LLMManager {
                 provider: provider_name.to_string(),
             })?;
         
-        if let Some(pricing) = provider.get_pricing(&request.model) {
+        if let Some(pricing) = self.pricing_for(provider.as_ref(), &request.model) {
             // Estimate prompt tokens
             let prompt_text = request.messages.iter()
                 .map(|m| m.content.as_str())
@@ -622,13 +1119,17 @@ pub enum LLMError {
     FunctionCallError { message: String },
     
     /// Token limit exceeded
-    #[error("Token limit exceeded: {tokens} > {limit}")]
-    TokenLimitExceeded { tokens: u32, limit: u32 },
+    #[error("Token limit exceeded: ~{prompt_tokens} prompt + {max_tokens} completion tokens > {limit}")]
+    TokenLimitExceeded { prompt_tokens: u32, max_tokens: u32, limit: u32 },
     
     /// Configuration error
     #[error("Configuration error: {message}")]
     ConfigurationError { message: String },
     
+    /// Call refused by the execution's sandbox profile
+    #[error("{violation}")]
+    SandboxViolation { violation: crate::enterprise::sandbox::SandboxViolation },
+    
     /// System error
     #[error("System error: {message}")]
     SystemError { message: String },
@@ -690,5 +1191,126 @@ mod tests {
         assert_eq!(request.max_tokens, Some(1000));
         assert_eq!(request.temperature, Some(0.7));
         assert!(!request.stream);
+        assert!(!request.logprobs);
+        assert!(request.top_logprobs.is_none());
+    }
+
+    #[test]
+    fn test_choice_confidence() {
+        let logprobs = ChoiceLogprobs::new(vec![
+            TokenLogprob::new("yes".to_string(), 0.9f64.ln()),
+            TokenLogprob::new("!".to_string(), 0.4f64.ln()),
+        ]);
+        let choice = Choice {
+            index: 0,
+            message: Message::assistant("yes!".to_string()),
+            finish_reason: FinishReason::Stop,
+            logprobs: Some(logprobs),
+        };
+
+        let confidence = choice.confidence().unwrap();
+        assert!((confidence - 0.6).abs() < 1e-9); // sqrt(0.9 * 0.4)
+        assert_eq!(choice.logprobs.as_ref().unwrap().least_likely_token().unwrap().token, "!");
+
+        let without = Choice { logprobs: None, ..choice };
+        assert!(without.confidence().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_providers() {
+        let mut config = LLMConfig::default();
+        config.default_provider = "mock".to_string();
+        config.default_models.insert("mock".to_string(), "mock-gpt-4".to_string());
+
+        let mut manager = LLMManager::new(config.clone());
+        manager.register_provider("mock".to_string(), Arc::new(providers::MockProvider::new()));
+
+        let report = manager.verify().await.unwrap();
+        let mock = report.provider("mock").unwrap();
+        assert!(report.is_healthy());
+        assert!(mock.capabilities.streaming);
+        assert!(mock.available_models.contains(&"mock-gpt-4".to_string()));
+
+        // Unknown default model fails fast
+        config.default_models.insert("mock".to_string(), "missing-model".to_string());
+        let mut manager = LLMManager::new(config);
+        manager.register_provider("mock".to_string(), Arc::new(providers::MockProvider::new()));
+        assert!(!manager.probe_providers().await.is_healthy());
+        assert!(matches!(manager.verify().await, Err(LLMError::ConfigurationError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_verify_missing_default_provider() {
+        let manager = LLMManager::new(LLMConfig::default());
+        assert!(manager.verify().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_profile_pricing_fallback() {
+        let mut profiles = ModelProfileRegistry::new();
+        profiles.register(ModelProfile::new("mock-llama-2", "mock", 2048).with_cost(1.0, 1.0));
+        profiles.register(ModelProfile::new("unpriced", "mock", 2048).with_cost(1.0, 2.0));
+
+        let mut manager = LLMManager::new(LLMConfig::default()).with_profiles(profiles);
+        manager.register_provider("mock".to_string(), Arc::new(providers::MockProvider::new()));
+
+        let request = CompletionRequest {
+            model: "unpriced".to_string(),
+            messages: vec![Message::user("one two".to_string())],
+            max_tokens: Some(1000),
+            ..Default::default()
+        };
+        // 2 prompt tokens at $1/1K plus 1000 completion tokens at $2/1K
+        let cost = manager.estimate_cost(&request, "mock").await.unwrap().unwrap();
+        assert!((cost - 2.002).abs() < 1e-9);
+
+        // Provider pricing wins over the profile
+        let request = CompletionRequest { model: "mock-llama-2".to_string(), ..request };
+        let cost = manager.estimate_cost(&request, "mock").await.unwrap().unwrap();
+        assert!(cost < 0.01);
+
+        // Requests larger than the context window are rejected up front
+        let request = CompletionRequest { max_tokens: Some(4096), ..request };
+        assert!(matches!(
+            manager.complete_with_provider("mock", request.clone()).await,
+            Err(LLMError::TokenLimitExceeded { limit: 2048, .. })
+        ));
+
+        // The prompt counts towards the window too
+        let request = CompletionRequest {
+            messages: vec![Message::user("x".repeat(8000))],
+            max_tokens: Some(100),
+            ..request
+        };
+        assert!(matches!(
+            manager.complete_with_provider("mock", request).await,
+            Err(LLMError::TokenLimitExceeded { prompt_tokens: 2004, max_tokens: 100, limit: 2048 })
+        ));
+    }
+
+    #[test]
+    fn test_llm_usage_aggregation() {
+        let response = CompletionResponse {
+            id: "r1".to_string(),
+            model: "mock-gpt-4".to_string(),
+            choices: Vec::new(),
+            usage: TokenUsage::new(100, 50).with_cost(0.021),
+            metadata: HashMap::new(),
+            timestamp: SystemTime::now(),
+        };
+
+        let mut usage = LLMUsage::default();
+        assert!(usage.is_empty());
+        usage.record(&response, Duration::from_millis(1500));
+        usage.record(&response, Duration::from_millis(1600));
+
+        assert_eq!(usage.calls, 2);
+        assert_eq!(usage.total_tokens, 300);
+        assert!((usage.cost - 0.042).abs() < 1e-9);
+        assert_eq!(usage.latency_ms(), 3100);
+
+        let restored = LLMUsage::from_metadata(&usage.to_metadata()).unwrap();
+        assert_eq!(restored, usage);
+        assert!(LLMUsage::from_metadata(&HashMap::new()).is_none());
     }
 }
@@ -0,0 +1,272 @@
+// Per-model configuration profiles for AgentGraph LLM framework
+// Profiles are bundled with the crate and can be overridden from a user TOML file
+
+#![allow(missing_docs)]
+
+use super::{LLMError, ModelPricing};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Profiles shipped with the crate
+const BUNDLED_PROFILES: &str = include_str!("profiles.toml");
+
+/// Rough latency class of a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyClass {
+    /// Interactive latency
+    Fast,
+    /// Typical latency
+    Standard,
+    /// Large models with long response times
+    Slow,
+}
+
+impl Default for LatencyClass {
+    fn default() -> Self {
+        Self::Standard
+    }
+}
+
+/// Configuration profile for a single model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelProfile {
+    /// Model name as passed to the provider
+    pub model: String,
+    /// Provider serving the model
+    pub provider: String,
+    /// Context window in tokens
+    pub context_window: u32,
+    /// Whether the model supports tool/function calling
+    #[serde(default)]
+    pub supports_tools: bool,
+    /// Temperature applied when a request does not set one
+    #[serde(default)]
+    pub default_temperature: Option<f32>,
+    /// Cost per 1K prompt tokens in USD
+    #[serde(default)]
+    pub prompt_cost_per_1k: f64,
+    /// Cost per 1K completion tokens in USD
+    #[serde(default)]
+    pub completion_cost_per_1k: f64,
+    /// Latency class
+    #[serde(default)]
+    pub latency_class: LatencyClass,
+}
+
+impl ModelProfile {
+    /// Create a profile with defaults for the optional fields
+    pub fn new<M: Into<String>, P: Into<String>>(model: M, provider: P, context_window: u32) -> Self {
+        Self {
+            model: model.into(),
+            provider: provider.into(),
+            context_window,
+            supports_tools: false,
+            default_temperature: None,
+            prompt_cost_per_1k: 0.0,
+            completion_cost_per_1k: 0.0,
+            latency_class: LatencyClass::default(),
+        }
+    }
+
+    /// Set tool support
+    pub fn with_tools(mut self, supports_tools: bool) -> Self {
+        self.supports_tools = supports_tools;
+        self
+    }
+
+    /// Set per-1K token costs
+    pub fn with_cost(mut self, prompt_cost_per_1k: f64, completion_cost_per_1k: f64) -> Self {
+        self.prompt_cost_per_1k = prompt_cost_per_1k;
+        self.completion_cost_per_1k = completion_cost_per_1k;
+        self
+    }
+
+    /// Set latency class
+    pub fn with_latency_class(mut self, latency_class: LatencyClass) -> Self {
+        self.latency_class = latency_class;
+        self
+    }
+
+    /// Pricing derived from the profile costs
+    pub fn pricing(&self) -> ModelPricing {
+        ModelPricing {
+            prompt_cost_per_1k: self.prompt_cost_per_1k,
+            completion_cost_per_1k: self.completion_cost_per_1k,
+            currency: "USD".to_string(),
+        }
+    }
+
+    /// Check whether a prompt plus completion budget fits the context window
+    pub fn fits_context(&self, prompt_tokens: u32, max_completion_tokens: u32) -> bool {
+        prompt_tokens.saturating_add(max_completion_tokens) <= self.context_window
+    }
+}
+
+/// On-disk layout of a profile file
+#[derive(Debug, Default, Deserialize)]
+struct ProfileFile {
+    #[serde(default)]
+    profiles: Vec<ModelProfile>,
+}
+
+/// Registry of model profiles keyed by model name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelProfileRegistry {
+    /// Profiles by model name
+    profiles: HashMap<String, ModelProfile>,
+}
+
+impl ModelProfileRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry containing the bundled profiles
+    pub fn bundled() -> Self {
+        Self::from_toml_str(BUNDLED_PROFILES).expect("bundled model profiles must parse")
+    }
+
+    /// Load bundled profiles, then apply overrides from a user file if given
+    pub fn load(user_file: Option<&Path>) -> Result<Self, LLMError> {
+        let mut registry = Self::bundled();
+        if let Some(path) = user_file {
+            registry.merge(Self::from_file(path)?);
+        }
+        Ok(registry)
+    }
+
+    /// Parse profiles from TOML
+    pub fn from_toml_str(content: &str) -> Result<Self, LLMError> {
+        let file: ProfileFile = toml::from_str(content)
+            .map_err(|e| LLMError::ConfigurationError {
+                message: format!("Invalid model profile file: {}", e),
+            })?;
+
+        let mut registry = Self::new();
+        for profile in file.profiles {
+            registry.register(profile);
+        }
+        Ok(registry)
+    }
+
+    /// Parse profiles from a TOML file
+    pub fn from_file(path: &Path) -> Result<Self, LLMError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| LLMError::ConfigurationError {
+                message: format!("Failed to read model profiles from {}: {}", path.display(), e),
+            })?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Register or replace a profile
+    pub fn register(&mut self, profile: ModelProfile) {
+        self.profiles.insert(profile.model.clone(), profile);
+    }
+
+    /// Merge another registry, replacing profiles with the same model name
+    pub fn merge(&mut self, other: ModelProfileRegistry) {
+        self.profiles.extend(other.profiles);
+    }
+
+    /// Get profile for a model
+    pub fn get(&self, model: &str) -> Option<&ModelProfile> {
+        self.profiles.get(model)
+    }
+
+    /// Profiles served by a provider
+    pub fn for_provider(&self, provider: &str) -> Vec<&ModelProfile> {
+        self.profiles.values().filter(|p| p.provider == provider).collect()
+    }
+
+    /// Cheapest model whose context window and tool support satisfy the requirements
+    pub fn cheapest_matching(&self, min_context: u32, needs_tools: bool) -> Option<&ModelProfile> {
+        self.profiles
+            .values()
+            .filter(|p| p.context_window >= min_context && (!needs_tools || p.supports_tools))
+            .min_by(|a, b| {
+                let cost_a = a.prompt_cost_per_1k + a.completion_cost_per_1k;
+                let cost_b = b.prompt_cost_per_1k + b.completion_cost_per_1k;
+                cost_a.partial_cmp(&cost_b).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Number of registered profiles
+    pub fn len(&self) -> usize {
+        self.profiles.len()
+    }
+
+    /// Check if the registry is empty
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_profiles() {
+        let registry = ModelProfileRegistry::bundled();
+        let gpt4 = registry.get("gpt-4").unwrap();
+
+        assert_eq!(gpt4.provider, "openai");
+        assert_eq!(gpt4.context_window, 8192);
+        assert!(gpt4.supports_tools);
+        assert_eq!(gpt4.latency_class, LatencyClass::Slow);
+        assert!(!registry.for_provider("mock").is_empty());
+    }
+
+    #[test]
+    fn test_user_overrides() {
+        let mut registry = ModelProfileRegistry::bundled();
+        let overrides = ModelProfileRegistry::from_toml_str(r#"
+            [[profiles]]
+            model = "gpt-4"
+            provider = "openai"
+            context_window = 128000
+            latency_class = "standard"
+
+            [[profiles]]
+            model = "local-llama"
+            provider = "ollama"
+            context_window = 4096
+        "#).unwrap();
+
+        registry.merge(overrides);
+
+        assert_eq!(registry.get("gpt-4").unwrap().context_window, 128000);
+        assert!(!registry.get("gpt-4").unwrap().supports_tools);
+        assert_eq!(registry.get("local-llama").unwrap().latency_class, LatencyClass::Standard);
+    }
+
+    #[test]
+    fn test_load_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profiles.toml");
+        std::fs::write(&path, "[[profiles]]\nmodel = \"custom\"\nprovider = \"mock\"\ncontext_window = 1024\n").unwrap();
+
+        let registry = ModelProfileRegistry::load(Some(&path)).unwrap();
+        assert!(registry.get("custom").is_some());
+        assert!(registry.get("gpt-4").is_some());
+
+        assert!(ModelProfileRegistry::from_toml_str("profiles = 3").is_err());
+    }
+
+    #[test]
+    fn test_cheapest_matching() {
+        let mut registry = ModelProfileRegistry::new();
+        registry.register(ModelProfile::new("small", "mock", 4096).with_tools(true).with_cost(0.001, 0.002));
+        registry.register(ModelProfile::new("large", "mock", 100000).with_tools(true).with_cost(0.01, 0.03));
+        registry.register(ModelProfile::new("no-tools", "mock", 100000).with_cost(0.0001, 0.0001));
+
+        assert_eq!(registry.cheapest_matching(1000, true).unwrap().model, "small");
+        assert_eq!(registry.cheapest_matching(50000, true).unwrap().model, "large");
+        assert_eq!(registry.cheapest_matching(50000, false).unwrap().model, "no-tools");
+        assert!(registry.get("small").unwrap().fits_context(3000, 1000));
+        assert!(!registry.get("small").unwrap().fits_context(4000, 1000));
+    }
+}
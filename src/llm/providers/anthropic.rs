@@ -133,6 +133,7 @@ impl AnthropicProvider {
             index: 0,
             message,
             finish_reason,
+            logprobs: None,
         };
 
         // Parse usage information
@@ -178,6 +179,52 @@ impl LLMProvider for AnthropicProvider {
         true
     }
 
+    fn supports_vision(&self) -> bool {
+        true // Claude 3 models accept image content
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, LLMError> {
+        let url = format!("{}/models", self.base_url);
+        let response = self.client.get(&url).send().await
+            .map_err(|e| LLMError::NetworkError {
+                message: format!("Request failed: {}", e),
+            })?;
+
+        let status = response.status();
+        let response_text = response.text().await
+            .map_err(|e| LLMError::NetworkError {
+                message: format!("Failed to read response: {}", e),
+            })?;
+
+        if !status.is_success() {
+            return match status.as_u16() {
+                401 | 403 => Err(LLMError::AuthenticationError {
+                    provider: self.name().to_string(),
+                    message: "Invalid API key".to_string(),
+                }),
+                429 => Err(LLMError::RateLimitExceeded {
+                    provider: self.name().to_string(),
+                }),
+                _ => Err(LLMError::ServerError {
+                    provider: self.name().to_string(),
+                    message: format!("HTTP {}: {}", status, response_text),
+                }),
+            };
+        }
+
+        let response_json: serde_json::Value = serde_json::from_str(&response_text)
+            .map_err(|e| LLMError::ServerError {
+                provider: self.name().to_string(),
+                message: format!("Invalid JSON response: {}", e),
+            })?;
+
+        Ok(response_json["data"].as_array()
+            .map(|models| models.iter()
+                .filter_map(|model| model["id"].as_str().map(String::from))
+                .collect())
+            .unwrap_or_default())
+    }
+
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, LLMError> {
         if !self.supports_model(&request.model) {
             return Err(LLMError::ModelNotSupported {
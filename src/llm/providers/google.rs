@@ -119,6 +119,7 @@ impl GoogleProvider {
             index: 0,
             message,
             finish_reason,
+            logprobs: None,
         };
 
         // Parse usage information if available
@@ -164,6 +165,54 @@ impl LLMProvider for GoogleProvider {
         true // Gemini supports streaming
     }
 
+    fn supports_vision(&self) -> bool {
+        true // Gemini models are multimodal
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, LLMError> {
+        let url = format!("{}/models?key={}", self.base_url, self.api_key);
+        let response = self.client.get(&url).send().await
+            .map_err(|e| LLMError::NetworkError {
+                message: format!("Request failed: {}", e),
+            })?;
+
+        let status = response.status();
+        let response_text = response.text().await
+            .map_err(|e| LLMError::NetworkError {
+                message: format!("Failed to read response: {}", e),
+            })?;
+
+        if !status.is_success() {
+            return match status.as_u16() {
+                400 | 401 | 403 => Err(LLMError::AuthenticationError {
+                    provider: self.name().to_string(),
+                    message: "Invalid API key".to_string(),
+                }),
+                429 => Err(LLMError::RateLimitExceeded {
+                    provider: self.name().to_string(),
+                }),
+                _ => Err(LLMError::ServerError {
+                    provider: self.name().to_string(),
+                    message: format!("HTTP {}: {}", status, response_text),
+                }),
+            };
+        }
+
+        let response_json: serde_json::Value = serde_json::from_str(&response_text)
+            .map_err(|e| LLMError::ServerError {
+                provider: self.name().to_string(),
+                message: format!("Invalid JSON response: {}", e),
+            })?;
+
+        // Names are returned as "models/gemini-1.5-pro"
+        Ok(response_json["models"].as_array()
+            .map(|models| models.iter()
+                .filter_map(|model| model["name"].as_str())
+                .map(|name| name.trim_start_matches("models/").to_string())
+                .collect())
+            .unwrap_or_default())
+    }
+
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, LLMError> {
         if !self.supports_model(&request.model) {
             return Err(LLMError::ModelNotSupported {
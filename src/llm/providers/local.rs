@@ -0,0 +1,384 @@
+// Local inference provider for AgentGraph
+// Runs small instruct and embedding models in-process, for air-gapped deployments and cheap auxiliary calls
+
+#![allow(missing_docs)]
+
+use super::super::*;
+use crate::agents::memory::MemoryError;
+use crate::agents::rerank::Reranker;
+use crate::agents::vector::Embedder;
+use std::time::SystemTime;
+use tokio::sync::Semaphore;
+
+/// Sampling settings of one generation
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationOptions {
+    pub max_new_tokens: u32,
+    /// 0.0 decodes greedily
+    pub temperature: f32,
+    pub top_p: f32,
+    /// Generation ends before the first of these is produced
+    pub stop: Vec<String>,
+}
+
+impl Default for GenerationOptions {
+    fn default() -> Self {
+        Self {
+            max_new_tokens: 256,
+            temperature: 0.0,
+            top_p: 1.0,
+            stop: Vec::new(),
+        }
+    }
+}
+
+/// Text produced by a local model
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generation {
+    pub text: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub finish_reason: FinishReason,
+}
+
+/// A model loaded in this process
+///
+/// Calls are blocking and CPU- or GPU-bound; [`LocalProvider`] runs them on
+/// the blocking thread pool.
+pub trait LocalModel: Send + Sync + std::fmt::Debug {
+    fn generate(&self, prompt: &str, options: &GenerationOptions) -> Result<Generation, LLMError>;
+
+    /// Embed a batch of texts; only embedding models support it
+    fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>, LLMError> {
+        Err(LLMError::InvalidRequest {
+            message: "Model does not produce embeddings".to_string(),
+        })
+    }
+
+    /// Dimension of produced embeddings, if the model produces them
+    fn embedding_dimensions(&self) -> Option<usize> {
+        None
+    }
+
+    /// Relevance of each document to the query; only cross-encoders support it
+    fn score_pairs(&self, _query: &str, _documents: &[String]) -> Result<Vec<f32>, LLMError> {
+        Err(LLMError::InvalidRequest {
+            message: "Model does not score query-document pairs".to_string(),
+        })
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError>;
+}
+
+/// How chat messages are turned into the prompt a model was trained on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptFormat {
+    /// `<|im_start|>role ... <|im_end|>`, used by Qwen, SmolLM and most small instruct models
+    #[default]
+    ChatMl,
+    /// `<|user|> ... <|end|>`, used by Phi-3
+    Phi3,
+    /// `Role: content` lines
+    Plain,
+}
+
+impl PromptFormat {
+    pub fn render(&self, messages: &[Message]) -> String {
+        let role = |message: &Message| match message.role {
+            MessageRole::System => "system",
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::Function => "tool",
+        };
+        let mut prompt = String::new();
+        match self {
+            PromptFormat::ChatMl => {
+                for message in messages {
+                    prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role(message), message.content));
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            PromptFormat::Phi3 => {
+                for message in messages {
+                    prompt.push_str(&format!("<|{}|>\n{}<|end|>\n", role(message), message.content));
+                }
+                prompt.push_str("<|assistant|>\n");
+            }
+            PromptFormat::Plain => {
+                for message in messages {
+                    let role = role(message);
+                    let mut chars = role.chars();
+                    let title: String = chars.next().map(|c| c.to_ascii_uppercase()).into_iter().chain(chars).collect();
+                    prompt.push_str(&format!("{}: {}\n", title, message.content));
+                }
+                prompt.push_str("Assistant:");
+            }
+        }
+        prompt
+    }
+
+    /// Markers the model emits when its turn is over
+    fn end_markers(&self) -> &'static [&'static str] {
+        match self {
+            PromptFormat::ChatMl => &["<|im_end|>"],
+            PromptFormat::Phi3 => &["<|end|>"],
+            PromptFormat::Plain => &["\nUser:"],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LocalEntry {
+    model: Arc<dyn LocalModel>,
+    format: PromptFormat,
+}
+
+/// Provider serving models loaded in this process
+///
+/// Needs no network access or API key and costs nothing per call, which suits
+/// air-gapped deployments and auxiliary calls such as routing or scoring.
+/// Generations run one at a time unless raised with
+/// [`with_max_concurrency`](Self::with_max_concurrency), since each one
+/// saturates the CPU or GPU.
+#[derive(Debug)]
+pub struct LocalProvider {
+    models: HashMap<String, LocalEntry>,
+    permits: Arc<Semaphore>,
+}
+
+impl LocalProvider {
+    pub fn new() -> Self {
+        Self {
+            models: HashMap::new(),
+            permits: Arc::new(Semaphore::new(1)),
+        }
+    }
+
+    /// Serve `model` under `name`, prompting it in ChatML
+    pub fn with_model(self, name: impl Into<String>, model: Arc<dyn LocalModel>) -> Self {
+        self.with_formatted_model(name, model, PromptFormat::default())
+    }
+
+    pub fn with_formatted_model(mut self, name: impl Into<String>, model: Arc<dyn LocalModel>, format: PromptFormat) -> Self {
+        self.models.insert(name.into(), LocalEntry { model, format });
+        self
+    }
+
+    /// Run up to `max` generations at once
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.permits = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    pub fn model(&self, name: &str) -> Option<Arc<dyn LocalModel>> {
+        self.models.get(name).map(|entry| entry.model.clone())
+    }
+
+    fn entry(&self, name: &str) -> Result<&LocalEntry, LLMError> {
+        self.models.get(name).ok_or_else(|| LLMError::ModelNotSupported {
+            model: name.to_string(),
+            provider: self.name().to_string(),
+        })
+    }
+
+    /// Run blocking inference off the async runtime, one permit at a time
+    async fn run_blocking<T, F>(&self, work: F) -> Result<T, LLMError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, LLMError> + Send + 'static,
+    {
+        let _permit = self.permits.clone().acquire_owned().await.map_err(|e| LLMError::SystemError {
+            message: format!("Local inference unavailable: {}", e),
+        })?;
+        tokio::task::spawn_blocking(work).await.map_err(|e| LLMError::SystemError {
+            message: format!("Local inference task failed: {}", e),
+        })?
+    }
+}
+
+impl Default for LocalProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for LocalProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn supported_models(&self) -> Vec<String> {
+        let mut models: Vec<String> = self.models.keys().cloned().collect();
+        models.sort();
+        models
+    }
+
+    fn supports_function_calling(&self) -> bool {
+        false
+    }
+
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, LLMError> {
+        let entry = self.entry(&request.model)?.clone();
+        let prompt = entry.format.render(&request.messages);
+        let mut stop = request.stop.clone().unwrap_or_default();
+        stop.extend(entry.format.end_markers().iter().map(|marker| marker.to_string()));
+        let options = GenerationOptions {
+            max_new_tokens: request.max_tokens.unwrap_or(256),
+            temperature: request.temperature.unwrap_or(0.0),
+            top_p: request.top_p.unwrap_or(1.0),
+            stop,
+        };
+
+        let model = entry.model.clone();
+        let generation = self.run_blocking(move || model.generate(&prompt, &options)).await?;
+        Ok(CompletionResponse {
+            id: format!("local-{}", uuid::Uuid::new_v4()),
+            model: request.model,
+            choices: vec![Choice {
+                index: 0,
+                message: Message::assistant(generation.text.trim().to_string()),
+                finish_reason: generation.finish_reason,
+                logprobs: None,
+            }],
+            usage: TokenUsage::new(generation.prompt_tokens, generation.completion_tokens),
+            metadata: HashMap::new(),
+            timestamp: SystemTime::now(),
+        })
+    }
+
+    async fn count_tokens(&self, text: &str, model: &str) -> Result<u32, LLMError> {
+        self.entry(model)?.model.count_tokens(text)
+    }
+
+    /// Free for every served model
+    fn get_pricing(&self, model: &str) -> Option<ModelPricing> {
+        self.models.contains_key(model).then(|| ModelPricing {
+            prompt_cost_per_1k: 0.0,
+            completion_cost_per_1k: 0.0,
+            currency: "USD".to_string(),
+        })
+    }
+}
+
+/// [`Embedder`] backed by a local embedding model, for vector memory without an embeddings API
+#[derive(Debug, Clone)]
+pub struct LocalEmbedder {
+    model: Arc<dyn LocalModel>,
+    dimensions: usize,
+}
+
+impl LocalEmbedder {
+    /// Fails for models that do not produce embeddings
+    pub fn new(model: Arc<dyn LocalModel>) -> Result<Self, LLMError> {
+        let dimensions = model.embedding_dimensions().ok_or_else(|| LLMError::ConfigurationError {
+            message: "Model does not produce embeddings".to_string(),
+        })?;
+        Ok(Self { model, dimensions })
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for LocalEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, MemoryError> {
+        let model = self.model.clone();
+        let texts = texts.to_vec();
+        tokio::task::spawn_blocking(move || model.embed(&texts))
+            .await
+            .map_err(|e| MemoryError::SystemError { message: e.to_string() })?
+            .map_err(|e| MemoryError::SystemError { message: e.to_string() })
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+}
+
+/// [`Reranker`] backed by a local cross-encoder, e.g. an ONNX export of `ms-marco-MiniLM-L-6-v2`
+#[derive(Debug, Clone)]
+pub struct LocalReranker {
+    model: Arc<dyn LocalModel>,
+}
+
+impl LocalReranker {
+    pub fn new(model: Arc<dyn LocalModel>) -> Self {
+        Self { model }
+    }
+}
+
+#[async_trait::async_trait]
+impl Reranker for LocalReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, MemoryError> {
+        let model = self.model.clone();
+        let query = query.to_string();
+        let documents = documents.to_vec();
+        tokio::task::spawn_blocking(move || model.score_pairs(&query, &documents))
+            .await
+            .map_err(|e| MemoryError::SystemError { message: e.to_string() })?
+            .map_err(|e| MemoryError::RetrievalError { message: e.to_string() })
+    }
+}
+
+/// Cut `text` before the first stop sequence, if it contains one
+pub fn truncate_at_stop(text: &str, stop: &[String]) -> Option<usize> {
+    stop.iter().filter(|sequence| !sequence.is_empty()).filter_map(|sequence| text.find(sequence.as_str())).min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes the last prompt line and embeds by text length
+    #[derive(Debug)]
+    struct EchoModel;
+
+    impl LocalModel for EchoModel {
+        fn generate(&self, prompt: &str, options: &GenerationOptions) -> Result<Generation, LLMError> {
+            let user = prompt.split("<|im_start|>user\n").last().unwrap_or_default();
+            let text = format!("{}<|im_end|>more", user.split("<|im_end|>").next().unwrap_or_default());
+            let end = truncate_at_stop(&text, &options.stop).unwrap_or(text.len());
+            Ok(Generation {
+                text: text[..end].to_string(),
+                prompt_tokens: self.count_tokens(prompt)?,
+                completion_tokens: 2,
+                finish_reason: FinishReason::Stop,
+            })
+        }
+
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, LLMError> {
+            Ok(texts.iter().map(|text| vec![text.len() as f32, 1.0]).collect())
+        }
+
+        fn embedding_dimensions(&self) -> Option<usize> {
+            Some(2)
+        }
+
+        fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+            Ok(text.split_whitespace().count() as u32)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_provider_serves_loaded_models_for_free() {
+        let provider = LocalProvider::new().with_model("echo", Arc::new(EchoModel));
+        let request = CompletionRequest {
+            model: "echo".to_string(),
+            messages: vec![Message::system("Route the ticket".to_string()), Message::user("billing question".to_string())],
+            ..Default::default()
+        };
+        let response = provider.complete(request.clone()).await.unwrap();
+        assert_eq!(response.choices[0].message.content, "billing question");
+        assert_eq!(provider.get_pricing("echo").unwrap().calculate_cost(&response.usage), 0.0);
+
+        let unknown = CompletionRequest { model: "gpt-4".to_string(), ..request };
+        assert!(matches!(provider.complete(unknown).await, Err(LLMError::ModelNotSupported { .. })));
+
+        let embedder = LocalEmbedder::new(provider.model("echo").unwrap()).unwrap();
+        assert_eq!(embedder.embed(&["abc".to_string()]).await.unwrap(), vec![vec![3.0, 1.0]]);
+    }
+}
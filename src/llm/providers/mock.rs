@@ -14,6 +14,8 @@ pub struct MockProvider {
     responses: Vec<String>,
     /// Current response index
     response_index: std::sync::Arc<std::sync::Mutex<usize>>,
+    /// Simulated per-token confidence used when logprobs are requested
+    confidence: f64,
 }
 
 impl MockProvider {
@@ -27,6 +29,7 @@ impl MockProvider {
                 "Mock provider generating test content.".to_string(),
             ],
             response_index: std::sync::Arc::new(std::sync::Mutex::new(0)),
+            confidence: 0.9,
         }
     }
 
@@ -36,6 +39,7 @@ impl MockProvider {
             delay: std::time::Duration::from_millis(100),
             responses,
             response_index: std::sync::Arc::new(std::sync::Mutex::new(0)),
+            confidence: 0.9,
         }
     }
 
@@ -45,6 +49,23 @@ impl MockProvider {
         self
     }
 
+    /// Set simulated confidence reported through logprobs
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence.clamp(f64::MIN_POSITIVE, 1.0);
+        self
+    }
+
+    /// Build synthetic per-word logprobs for a response
+    fn create_logprobs(&self, content: &str) -> ChoiceLogprobs {
+        let logprob = self.confidence.ln();
+        ChoiceLogprobs::new(
+            content
+                .split_whitespace()
+                .map(|word| TokenLogprob::new(word.to_string(), logprob))
+                .collect(),
+        )
+    }
+
     /// Get next response
     fn get_next_response(&self) -> String {
         let mut index = self.response_index.lock().unwrap();
@@ -98,6 +119,10 @@ impl LLMProvider for MockProvider {
         true
     }
 
+    fn supports_logprobs(&self) -> bool {
+        true
+    }
+
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, LLMError> {
         // Simulate network delay
         tokio::time::sleep(self.delay).await;
@@ -114,13 +139,19 @@ impl LLMProvider for MockProvider {
         if let Some(max_tokens) = request.max_tokens {
             if max_tokens > 4000 {
                 return Err(LLMError::TokenLimitExceeded {
-                    tokens: max_tokens,
+                    prompt_tokens: request.estimated_prompt_tokens(),
+                    max_tokens,
                     limit: 4000,
                 });
             }
         }
 
         let content = self.get_next_response();
+        let logprobs = if request.logprobs {
+            Some(self.create_logprobs(&content))
+        } else {
+            None
+        };
         let mut message = Message::assistant(content);
 
         // Check for function calling
@@ -136,6 +167,7 @@ impl LLMProvider for MockProvider {
             index: 0,
             message,
             finish_reason,
+            logprobs,
         };
 
         // Simulate token usage
@@ -223,6 +255,7 @@ pub struct MockProviderBuilder {
     delay: std::time::Duration,
     should_fail: bool,
     failure_error: Option<LLMError>,
+    confidence: f64,
 }
 
 impl MockProviderBuilder {
@@ -233,6 +266,7 @@ impl MockProviderBuilder {
             delay: std::time::Duration::from_millis(10),
             should_fail: false,
             failure_error: None,
+            confidence: 0.9,
         }
     }
 
@@ -261,9 +295,17 @@ impl MockProviderBuilder {
         self
     }
 
+    /// Set simulated confidence
+    pub fn with_confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
     /// Build the mock provider
     pub fn build(self) -> MockProvider {
-        MockProvider::with_responses(self.responses).with_delay(self.delay)
+        MockProvider::with_responses(self.responses)
+            .with_delay(self.delay)
+            .with_confidence(self.confidence)
     }
 }
 
@@ -352,6 +394,28 @@ mod tests {
         assert_eq!(function_call.name, "test_function");
     }
 
+    #[tokio::test]
+    async fn test_logprobs_confidence() {
+        let provider = MockProvider::new().with_confidence(0.5);
+        let request = CompletionRequest {
+            model: "mock-gpt-4".to_string(),
+            messages: vec![Message::user("Hello".to_string())],
+            ..Default::default()
+        }
+        .with_logprobs(None);
+
+        let response = provider.complete(request).await.unwrap();
+        let confidence = response.confidence().unwrap();
+        assert!((confidence - 0.5).abs() < 1e-9);
+
+        // Logprobs are omitted unless requested
+        let plain = CompletionRequest {
+            model: "mock-gpt-4".to_string(),
+            ..Default::default()
+        };
+        assert!(provider.complete(plain).await.unwrap().confidence().is_none());
+    }
+
     #[tokio::test]
     async fn test_token_counting() {
         let provider = MockProvider::new();
@@ -7,12 +7,18 @@ pub mod anthropic;
 pub mod google;
 pub mod openrouter;
 pub mod mock;
+pub mod local;
+#[cfg(feature = "local-onnx")]
+pub mod onnx;
 
 pub use openai::OpenAIProvider;
 pub use anthropic::AnthropicProvider;
 pub use google::GoogleProvider;
 pub use openrouter::OpenRouterProvider;
 pub use mock::MockProvider;
+pub use local::{LocalProvider, LocalModel, LocalEmbedder, LocalReranker, PromptFormat};
+#[cfg(feature = "local-onnx")]
+pub use onnx::{OnnxModel, OnnxModelConfig, OnnxDevice};
 
 // Re-export from parent module
 pub use super::ProviderConfig;
@@ -30,6 +36,8 @@ pub fn create_provider(name: &str, config: ProviderConfig) -> Result<Arc<dyn LLM
             ..Default::default()
         }))),
         "mock" => Ok(Arc::new(MockProvider::new())),
+        #[cfg(feature = "local-onnx")]
+        "local" => Ok(Arc::new(onnx::provider_from_config(&config)?)),
         _ => Err(LLMError::ProviderNotFound {
             provider: name.to_string(),
         }),
@@ -38,7 +46,11 @@ pub fn create_provider(name: &str, config: ProviderConfig) -> Result<Arc<dyn LLM
 
 /// Get all available provider names
 pub fn available_providers() -> Vec<&'static str> {
-    vec!["openai", "anthropic", "google", "openrouter", "mock"]
+    let mut providers = vec!["openai", "anthropic", "google", "openrouter", "mock"];
+    if cfg!(feature = "local-onnx") {
+        providers.push("local");
+    }
+    providers
 }
 
 /// Provider capabilities
@@ -52,6 +64,10 @@ pub struct ProviderCapabilities {
     pub function_calling: bool,
     /// Supports streaming
     pub streaming: bool,
+    /// Supports image inputs
+    pub vision: bool,
+    /// Supports token log probabilities
+    pub logprobs: bool,
     /// Supports embeddings
     pub embeddings: bool,
     /// Maximum context length
@@ -67,12 +83,64 @@ pub fn get_provider_capabilities(provider: &dyn LLMProvider) -> ProviderCapabili
         models: provider.supported_models(),
         function_calling: provider.supports_function_calling(),
         streaming: provider.supports_streaming(),
+        vision: provider.supports_vision(),
+        logprobs: provider.supports_logprobs(),
         embeddings: false, // TODO: Add embedding support
         max_context_length: get_max_context_length(provider.name()),
         languages: get_supported_languages(provider.name()),
     }
 }
 
+/// Result of probing a single registered provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderVerification {
+    /// Name the provider is registered under
+    pub provider: String,
+    /// Static capabilities of the provider
+    pub capabilities: ProviderCapabilities,
+    /// Models reported by the provider's listing endpoint
+    pub available_models: Vec<String>,
+    /// Configured default model, if any
+    pub default_model: Option<String>,
+    /// Time taken by the probe
+    pub latency: Duration,
+    /// Probe failure, if any
+    pub error: Option<LLMError>,
+}
+
+impl ProviderVerification {
+    /// Whether the probe succeeded
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Capability report produced by `LLMManager::verify`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    /// Per-provider results, sorted by provider name
+    pub providers: Vec<ProviderVerification>,
+    /// When the report was generated
+    pub generated_at: SystemTime,
+}
+
+impl CapabilityReport {
+    /// Whether every provider passed verification
+    pub fn is_healthy(&self) -> bool {
+        self.providers.iter().all(|p| p.is_ok())
+    }
+
+    /// Get the result for a provider
+    pub fn provider(&self, name: &str) -> Option<&ProviderVerification> {
+        self.providers.iter().find(|p| p.provider == name)
+    }
+
+    /// Providers that failed verification
+    pub fn failures(&self) -> Vec<&ProviderVerification> {
+        self.providers.iter().filter(|p| !p.is_ok()).collect()
+    }
+}
+
 /// Get maximum context length for provider
 fn get_max_context_length(provider_name: &str) -> Option<u32> {
     match provider_name {
@@ -133,6 +201,8 @@ mod tests {
         assert_eq!(capabilities.name, "mock");
         assert!(capabilities.function_calling); // MockProvider supports function calling
         assert!(capabilities.streaming); // MockProvider supports streaming
+        assert!(capabilities.logprobs);
+        assert!(!capabilities.vision);
     }
 
     #[test]
@@ -0,0 +1,377 @@
+// ONNX Runtime backend for the local provider
+// Loads quantized or full-precision ONNX exports of small instruct and embedding models, on CPU or CUDA
+
+#![allow(missing_docs)]
+
+use super::local::{truncate_at_stop, Generation, GenerationOptions, LocalModel, LocalProvider, PromptFormat};
+use super::super::*;
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::Session;
+use ort::value::{DynValue, Tensor};
+use rand::Rng;
+use std::path::{Path, PathBuf};
+use tokenizers::Tokenizer;
+
+/// Tokens that end a turn in the common chat formats
+const DEFAULT_EOS_TOKENS: &[&str] = &["<|im_end|>", "<|endoftext|>", "<|end|>", "</s>", "<|eot_id|>"];
+
+/// Where inference runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnnxDevice {
+    #[default]
+    Cpu,
+    /// CUDA device by index; needs the `local-onnx-cuda` feature
+    Cuda(i32),
+}
+
+impl std::str::FromStr for OnnxDevice {
+    type Err = LLMError;
+
+    /// `cpu`, `cuda` or `cuda:<index>`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            None if value == "cpu" => Ok(Self::Cpu),
+            None if value == "cuda" => Ok(Self::Cuda(0)),
+            Some(("cuda", index)) => index.parse().map(Self::Cuda).map_err(|_| device_error(value)),
+            _ => Err(device_error(value)),
+        }
+    }
+}
+
+fn device_error(value: &str) -> LLMError {
+    LLMError::ConfigurationError {
+        message: format!("Unknown device '{}', expected cpu, cuda or cuda:<index>", value),
+    }
+}
+
+/// Files and runtime settings of one model
+///
+/// Decoder models must be exported without past key values, e.g. with
+/// `optimum-cli export onnx --task text-generation`; embedding models with
+/// `--task feature-extraction`. Quantized exports load the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnnxModelConfig {
+    pub model_path: PathBuf,
+    pub tokenizer_path: PathBuf,
+    #[serde(default)]
+    pub device: OnnxDevice,
+    /// Threads used within an operator; ONNX Runtime picks when unset
+    #[serde(default)]
+    pub intra_threads: Option<usize>,
+    /// Tokens that end generation, besides the common chat end markers
+    #[serde(default)]
+    pub eos_tokens: Vec<String>,
+}
+
+impl OnnxModelConfig {
+    /// `model.onnx` and `tokenizer.json` in `dir`
+    pub fn from_dir(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        Self {
+            model_path: dir.join("model.onnx"),
+            tokenizer_path: dir.join("tokenizer.json"),
+            device: OnnxDevice::Cpu,
+            intra_threads: None,
+            eos_tokens: Vec::new(),
+        }
+    }
+
+    /// Load another file of the directory, e.g. `model_quantized.onnx`
+    pub fn with_model_file(mut self, file: &str) -> Self {
+        self.model_path.set_file_name(file);
+        self
+    }
+
+    pub fn with_device(mut self, device: OnnxDevice) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn with_intra_threads(mut self, threads: usize) -> Self {
+        self.intra_threads = Some(threads);
+        self
+    }
+}
+
+/// A model run by ONNX Runtime
+#[derive(Debug)]
+pub struct OnnxModel {
+    session: Session,
+    tokenizer: Tokenizer,
+    eos_ids: Vec<u32>,
+    /// Whether the graph takes `position_ids` as well as `input_ids` and `attention_mask`
+    takes_positions: bool,
+    /// Whether the graph takes `token_type_ids`, as BERT-style encoders do
+    takes_token_types: bool,
+    /// Dimension of `last_hidden_state` or `sentence_embedding`, for embedding models
+    hidden_size: Option<usize>,
+}
+
+fn runtime_error(context: &str) -> impl Fn(ort::Error) -> LLMError + '_ {
+    move |e| LLMError::SystemError {
+        message: format!("{}: {}", context, e),
+    }
+}
+
+impl OnnxModel {
+    pub fn load(config: &OnnxModelConfig) -> Result<Self, LLMError> {
+        let load_error = runtime_error("Failed to load ONNX model");
+        let mut builder = Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .map_err(&load_error)?;
+        if let Some(threads) = config.intra_threads {
+            builder = builder.with_intra_threads(threads).map_err(&load_error)?;
+        }
+        match config.device {
+            OnnxDevice::Cpu => {}
+            #[cfg(feature = "local-onnx-cuda")]
+            OnnxDevice::Cuda(device_id) => {
+                let cuda = ort::execution_providers::CUDAExecutionProvider::default().with_device_id(device_id).build();
+                builder = builder.with_execution_providers([cuda.error_on_failure()]).map_err(&load_error)?;
+            }
+            #[cfg(not(feature = "local-onnx-cuda"))]
+            OnnxDevice::Cuda(_) => {
+                return Err(LLMError::ConfigurationError {
+                    message: "CUDA inference needs the local-onnx-cuda feature".to_string(),
+                });
+            }
+        }
+        let session = builder.commit_from_file(&config.model_path).map_err(&load_error)?;
+        let tokenizer = Tokenizer::from_file(&config.tokenizer_path).map_err(|e| LLMError::ConfigurationError {
+            message: format!("Failed to load tokenizer {}: {}", config.tokenizer_path.display(), e),
+        })?;
+
+        let eos_ids = DEFAULT_EOS_TOKENS
+            .iter()
+            .copied()
+            .chain(config.eos_tokens.iter().map(String::as_str))
+            .filter_map(|token| tokenizer.token_to_id(token))
+            .collect();
+        let takes = |name: &str| session.inputs.iter().any(|input| input.name == name);
+        let takes_positions = takes("position_ids");
+        let takes_token_types = takes("token_type_ids");
+        let hidden_size = session
+            .outputs
+            .iter()
+            .find(|output| output.name == "sentence_embedding" || output.name == "last_hidden_state")
+            .and_then(|output| output.output_type.tensor_dimensions())
+            .and_then(|dimensions| dimensions.last().copied())
+            .filter(|size| *size > 0)
+            .map(|size| size as usize);
+        Ok(Self {
+            session,
+            tokenizer,
+            eos_ids,
+            takes_positions,
+            takes_token_types,
+            hidden_size,
+        })
+    }
+
+    fn encode(&self, text: &str) -> Result<Vec<u32>, LLMError> {
+        self.tokenizer
+            .encode(text, false)
+            .map(|encoding| encoding.get_ids().to_vec())
+            .map_err(|e| LLMError::InvalidRequest {
+                message: format!("Failed to tokenize input: {}", e),
+            })
+    }
+
+    /// Run the graph on one sequence and return the named output with its shape
+    ///
+    /// `type_ids` tell the segments of a sentence pair apart; single sequences are all segment 0.
+    fn forward(&self, ids: &[u32], type_ids: Option<&[u32]>, output: &str) -> Result<(Vec<i64>, Vec<f32>), LLMError> {
+        let run_error = runtime_error("ONNX inference failed");
+        let len = ids.len();
+        let tensor = |values: Vec<i64>| Tensor::from_array(([1, len], values)).map(DynValue::from).map_err(&run_error);
+        let mut inputs = vec![
+            ("input_ids", tensor(ids.iter().map(|&id| id as i64).collect())?),
+            ("attention_mask", tensor(vec![1; len])?),
+        ];
+        if self.takes_positions {
+            inputs.push(("position_ids", tensor((0..len as i64).collect())?));
+        }
+        if self.takes_token_types {
+            let type_ids = type_ids.map_or_else(|| vec![0; len], |type_ids| type_ids.iter().map(|&id| id as i64).collect());
+            inputs.push(("token_type_ids", tensor(type_ids)?));
+        }
+        let outputs = self.session.run(inputs).map_err(&run_error)?;
+        let value = outputs.get(output).ok_or_else(|| LLMError::ConfigurationError {
+            message: format!("Model has no '{}' output", output),
+        })?;
+        let (shape, data) = value.try_extract_raw_tensor::<f32>().map_err(&run_error)?;
+        Ok((shape, data.to_vec()))
+    }
+
+    /// Pick the next token from the logits of the last position
+    fn sample(logits: &[f32], options: &GenerationOptions) -> u32 {
+        let argmax = || {
+            logits
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map_or(0, |(index, _)| index as u32)
+        };
+        if options.temperature <= f32::EPSILON {
+            return argmax();
+        }
+        let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let mut candidates: Vec<(u32, f32)> = logits
+            .iter()
+            .enumerate()
+            .map(|(index, logit)| (index as u32, ((logit - max) / options.temperature).exp()))
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let total: f32 = candidates.iter().map(|(_, weight)| weight).sum();
+        // Smallest set of likeliest tokens holding `top_p` of the probability mass
+        let mut kept = 0.0;
+        let mut nucleus = 0;
+        for (_, weight) in &candidates {
+            nucleus += 1;
+            kept += weight;
+            if kept >= options.top_p * total {
+                break;
+            }
+        }
+        let mut pick = rand::thread_rng().gen::<f32>() * kept;
+        for (id, weight) in &candidates[..nucleus] {
+            if pick < *weight {
+                return *id;
+            }
+            pick -= weight;
+        }
+        candidates[0].0
+    }
+}
+
+impl LocalModel for OnnxModel {
+    fn generate(&self, prompt: &str, options: &GenerationOptions) -> Result<Generation, LLMError> {
+        let mut ids = self.encode(prompt)?;
+        let prompt_tokens = ids.len();
+        let mut finish_reason = FinishReason::Length;
+        let mut text = String::new();
+        // Without a KV cache each step reruns the whole sequence, which small models afford
+        for _ in 0..options.max_new_tokens {
+            let (shape, logits) = self.forward(&ids, None, "logits")?;
+            let vocab = shape.last().copied().unwrap_or_default() as usize;
+            if vocab == 0 || logits.len() < vocab {
+                return Err(LLMError::SystemError {
+                    message: format!("Unexpected logits shape {:?}", shape),
+                });
+            }
+            let next = Self::sample(&logits[logits.len() - vocab..], options);
+            if self.eos_ids.contains(&next) {
+                finish_reason = FinishReason::Stop;
+                break;
+            }
+            ids.push(next);
+            text = self.tokenizer.decode(&ids[prompt_tokens..], true).map_err(|e| LLMError::SystemError {
+                message: format!("Failed to decode output: {}", e),
+            })?;
+            if let Some(end) = truncate_at_stop(&text, &options.stop) {
+                text.truncate(end);
+                finish_reason = FinishReason::Stop;
+                break;
+            }
+        }
+        Ok(Generation {
+            text,
+            prompt_tokens: prompt_tokens as u32,
+            completion_tokens: (ids.len() - prompt_tokens) as u32,
+            finish_reason,
+        })
+    }
+
+    /// Mean of the token states, or the model's own pooled output, normalized
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, LLMError> {
+        let Some(hidden_size) = self.hidden_size else {
+            return Err(LLMError::InvalidRequest {
+                message: "Model does not produce embeddings".to_string(),
+            });
+        };
+        let pooled = self.session.outputs.iter().any(|output| output.name == "sentence_embedding");
+        texts
+            .iter()
+            .map(|text| {
+                let ids = self.tokenizer.encode(text.as_str(), true).map_err(|e| LLMError::InvalidRequest {
+                    message: format!("Failed to tokenize input: {}", e),
+                })?;
+                let ids = ids.get_ids();
+                let (_, states) = self.forward(ids, None, if pooled { "sentence_embedding" } else { "last_hidden_state" })?;
+                let mut embedding = vec![0.0; hidden_size];
+                let rows = states.chunks(hidden_size);
+                let count = rows.len().max(1) as f32;
+                for row in rows {
+                    for (sum, value) in embedding.iter_mut().zip(row) {
+                        *sum += value / count;
+                    }
+                }
+                let norm = embedding.iter().map(|value| value * value).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    embedding.iter_mut().for_each(|value| *value /= norm);
+                }
+                Ok(embedding)
+            })
+            .collect()
+    }
+
+    fn embedding_dimensions(&self) -> Option<usize> {
+        self.hidden_size
+    }
+
+    /// Relevance logit of each query-document pair, for cross-encoders with a single `logits` output
+    fn score_pairs(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, LLMError> {
+        documents
+            .iter()
+            .map(|document| {
+                let encoding = self.tokenizer.encode((query, document.as_str()), true).map_err(|e| LLMError::InvalidRequest {
+                    message: format!("Failed to tokenize input: {}", e),
+                })?;
+                let (_, logits) = self.forward(encoding.get_ids(), Some(encoding.get_type_ids()), "logits")?;
+                logits.first().copied().ok_or_else(|| LLMError::SystemError {
+                    message: "Cross-encoder returned no score".to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<u32, LLMError> {
+        Ok(self.encode(text)?.len() as u32)
+    }
+}
+
+/// Local provider serving the models named in `config.settings`
+///
+/// `models` maps model names to directories holding `model.onnx` and
+/// `tokenizer.json`; `device` (`cpu`, `cuda:<index>`), `format` (`chat_ml`,
+/// `phi3`, `plain`) and `max_concurrency` apply to all of them.
+pub fn provider_from_config(config: &ProviderConfig) -> Result<LocalProvider, LLMError> {
+    let setting = |key: &str| config.settings.get(key);
+    let device = match setting("device").and_then(|value| value.as_str()) {
+        Some(device) => device.parse()?,
+        None => OnnxDevice::Cpu,
+    };
+    let format: PromptFormat = match setting("format") {
+        Some(format) => serde_json::from_value(format.clone()).map_err(|e| LLMError::ConfigurationError {
+            message: format!("Invalid prompt format: {}", e),
+        })?,
+        None => PromptFormat::default(),
+    };
+    let models = setting("models").and_then(|models| models.as_object()).ok_or_else(|| LLMError::ConfigurationError {
+        message: "The local provider needs a 'models' setting mapping model names to directories".to_string(),
+    })?;
+
+    let mut provider = LocalProvider::new();
+    if let Some(max) = setting("max_concurrency").and_then(|value| value.as_u64()) {
+        provider = provider.with_max_concurrency(max as usize);
+    }
+    for (name, dir) in models {
+        let dir = dir.as_str().ok_or_else(|| LLMError::ConfigurationError {
+            message: format!("Directory of local model '{}' must be a string", name),
+        })?;
+        let model = OnnxModel::load(&OnnxModelConfig::from_dir(dir).with_device(device))?;
+        provider = provider.with_formatted_model(name.clone(), Arc::new(model), format);
+    }
+    Ok(provider)
+}
@@ -157,10 +157,16 @@ impl OpenAIProvider {
                 _ => FinishReason::Stop,
             };
 
+            // Logprobs follow the `{"content": [{"token", "logprob", "top_logprobs"}]}` shape
+            let logprobs = choice.get("logprobs")
+                .filter(|value| !value.is_null())
+                .and_then(|value| serde_json::from_value::<ChoiceLogprobs>(value.clone()).ok());
+
             parsed_choices.push(Choice {
                 index: index as u32,
                 message,
                 finish_reason,
+                logprobs,
             });
         }
 
@@ -212,6 +218,62 @@ impl LLMProvider for OpenAIProvider {
         true
     }
 
+    fn supports_logprobs(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, LLMError> {
+        let url = format!("{}/models", self.base_url);
+        let mut req_builder = self.client.get(&url);
+
+        if let Some(org) = &self.organization {
+            req_builder = req_builder.header("OpenAI-Organization", org);
+        }
+
+        let response = req_builder.send().await
+            .map_err(|e| LLMError::NetworkError {
+                message: format!("Request failed: {}", e),
+            })?;
+
+        let status = response.status();
+        let response_text = response.text().await
+            .map_err(|e| LLMError::NetworkError {
+                message: format!("Failed to read response: {}", e),
+            })?;
+
+        if !status.is_success() {
+            return match status.as_u16() {
+                401 | 403 => Err(LLMError::AuthenticationError {
+                    provider: self.name().to_string(),
+                    message: "Invalid API key".to_string(),
+                }),
+                429 => Err(LLMError::RateLimitExceeded {
+                    provider: self.name().to_string(),
+                }),
+                _ => Err(LLMError::ServerError {
+                    provider: self.name().to_string(),
+                    message: format!("HTTP {}: {}", status, response_text),
+                }),
+            };
+        }
+
+        let response_json: serde_json::Value = serde_json::from_str(&response_text)
+            .map_err(|e| LLMError::ServerError {
+                provider: self.name().to_string(),
+                message: format!("Invalid JSON response: {}", e),
+            })?;
+
+        Ok(response_json["data"].as_array()
+            .map(|models| models.iter()
+                .filter_map(|model| model["id"].as_str().map(String::from))
+                .collect())
+            .unwrap_or_default())
+    }
+
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse, LLMError> {
         if !self.supports_model(&request.model) {
             return Err(LLMError::ModelNotSupported {
@@ -246,6 +308,13 @@ impl LLMProvider for OpenAIProvider {
             body["stream"] = json!(true);
         }
 
+        if request.logprobs {
+            body["logprobs"] = json!(true);
+            if let Some(top_logprobs) = request.top_logprobs {
+                body["top_logprobs"] = json!(top_logprobs);
+            }
+        }
+
         // Add function calling if specified
         if let Some(functions) = &request.functions {
             body["functions"] = json!(functions.iter().map(|f| self.convert_function(f)).collect::<Vec<_>>());
@@ -399,6 +468,37 @@ mod tests {
         assert!(provider.get_pricing("invalid-model").is_none());
     }
 
+    #[test]
+    fn test_parse_response_with_logprobs() {
+        let provider = OpenAIProvider::new("test-key".to_string()).unwrap();
+        let response = json!({
+            "id": "chatcmpl-1",
+            "model": "gpt-4",
+            "choices": [{
+                "message": {"role": "assistant", "content": "Yes"},
+                "finish_reason": "stop",
+                "logprobs": {
+                    "content": [{
+                        "token": "Yes",
+                        "logprob": -0.01,
+                        "bytes": [89, 101, 115],
+                        "top_logprobs": [
+                            {"token": "Yes", "logprob": -0.01},
+                            {"token": "No", "logprob": -4.6}
+                        ]
+                    }]
+                }
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1}
+        });
+
+        let parsed = provider.parse_response(response).unwrap();
+        let logprobs = parsed.choices[0].logprobs.as_ref().unwrap();
+        assert_eq!(logprobs.content.len(), 1);
+        assert_eq!(logprobs.content[0].top_logprobs.len(), 2);
+        assert!(parsed.confidence().unwrap() > 0.98);
+    }
+
     #[tokio::test]
     async fn test_token_counting() {
         let provider = OpenAIProvider::new("test-key".to_string()).unwrap();
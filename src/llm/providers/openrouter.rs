@@ -5,7 +5,7 @@
 
 use super::{LLMProvider, LLMError};
 use crate::llm::{
-    CompletionRequest, CompletionResponse, Choice, Message, MessageRole,
+    CompletionRequest, CompletionResponse, Choice, ChoiceLogprobs, Message, MessageRole,
     FunctionCall, TokenUsage
 };
 use async_trait::async_trait;
@@ -154,6 +154,7 @@ impl OpenRouterProvider {
                         timestamp: std::time::SystemTime::now(),
                     },
                     finish_reason: crate::llm::FinishReason::Stop, // Default, should be mapped properly
+                    logprobs: choice.logprobs,
                 }
             })
             .collect();
@@ -318,6 +319,14 @@ impl LLMProvider for OpenRouterProvider {
             body["top_p"] = json!(top_p);
         }
         
+        // Passed through to upstream models that support it
+        if request.logprobs {
+            body["logprobs"] = json!(true);
+            if let Some(top_logprobs) = request.top_logprobs {
+                body["top_logprobs"] = json!(top_logprobs);
+            }
+        }
+        
         if let Some(functions) = request.functions {
             let openrouter_functions: Vec<Value> = functions
                 .iter()
@@ -370,6 +379,20 @@ impl LLMProvider for OpenRouterProvider {
         false // Will be true once streaming is implemented
     }
 
+    fn supports_logprobs(&self) -> bool {
+        true // Depends on the upstream model; unsupported models omit the field
+    }
+
+    fn supports_vision(&self) -> bool {
+        true // Available on multimodal upstream models
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, LLMError> {
+        // The models endpoint is public, so this confirms reachability rather than the key
+        let models = self.get_models().await?;
+        Ok(models.into_iter().map(|model| model.id).collect())
+    }
+
     
     async fn stream(&self, _request: CompletionRequest) -> Result<Box<dyn futures::Stream<Item = Result<CompletionResponse, LLMError>> + Unpin + Send>, LLMError> {
         // For now, return an error as streaming implementation is complex
@@ -416,6 +439,8 @@ struct OpenRouterChoice {
     index: u32,
     message: OpenRouterResponseMessage,
     finish_reason: Option<String>,
+    #[serde(default)]
+    logprobs: Option<ChoiceLogprobs>,
 }
 
 /// OpenRouter response message format
@@ -0,0 +1,757 @@
+//! Application manifests (`agentgraph.toml`) declaring the graphs of an application
+//! together with the providers, tools, personas and per-environment overrides they share.
+
+use crate::agents::roles::{RoleTemplate, RoleTemplates};
+use crate::error::{GraphError, GraphResult};
+use crate::graph::cost::{CostEstimate, TokenHistory};
+use crate::graph::templates::{GraphTemplate, TemplateContext, Topology};
+use crate::graph::{ExecutionConfig, Graph};
+use crate::llm::providers::create_provider;
+use crate::llm::{LLMConfig, LLMManager, ProviderConfig};
+use crate::state::State;
+use crate::tools::common::create_common_tools_registry;
+use crate::enterprise::secrets::{CredentialVault, SecretStore};
+use crate::tools::{ToolDefinition, ToolExecutor, ToolRegistry};
+use crate::visualization::VisualizationConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub mod lint;
+
+/// File name looked for by [`AppManifest::discover`]
+pub const MANIFEST_FILE: &str = "agentgraph.toml";
+
+/// Environment variable selecting the environment when none is requested
+pub const ENVIRONMENT_VAR: &str = "AGENTGRAPH_ENV";
+
+/// The `[app]` table
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppSection {
+    /// Application name
+    pub name: String,
+    /// Application version
+    pub version: Option<String>,
+    /// Provider used by graphs that do not name one
+    pub default_provider: Option<String>,
+    /// Environment applied when none is requested
+    pub default_environment: Option<String>,
+}
+
+/// An LLM provider shared by the graphs of the application
+///
+/// API keys are never written in the manifest; `api_key_env` names the
+/// environment variable holding the key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderManifest {
+    /// Provider implementation, defaulting to the table's name
+    pub kind: Option<String>,
+    /// Environment variable holding the API key
+    pub api_key_env: Option<String>,
+    /// API base URL
+    pub base_url: Option<String>,
+    /// Organization ID
+    pub organization: Option<String>,
+    /// Model used when a request does not name one
+    pub default_model: Option<String>,
+    /// Additional headers
+    pub headers: HashMap<String, String>,
+    /// Provider-specific settings
+    pub settings: HashMap<String, serde_json::Value>,
+}
+
+impl ProviderManifest {
+    /// Provider configuration, reading the API key from the environment
+    pub fn provider_config(&self) -> ProviderConfig {
+        ProviderConfig {
+            api_key: self.api_key_env.as_ref().and_then(|var| std::env::var(var).ok()),
+            base_url: self.base_url.clone(),
+            organization: self.organization.clone(),
+            headers: self.headers.clone(),
+            settings: self.settings.clone(),
+        }
+    }
+}
+
+/// The `[tools]` table
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsManifest {
+    /// Built-in tools to register by ID, or `["*"]` for all of them
+    pub builtin: Vec<String>,
+    /// HTTP, SQL and shell tools by ID
+    pub declared: BTreeMap<String, ToolDefinition>,
+    /// YAML files declaring more tools by ID, relative to the manifest
+    pub files: Vec<PathBuf>,
+}
+
+/// A named agent persona, usable wherever a role name is expected
+///
+/// A persona either `extends` a built-in role or another persona and
+/// overrides some of its fields, or stands alone with at least a system
+/// prompt and a model.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersonaManifest {
+    /// Built-in role or persona this one starts from
+    pub extends: Option<String>,
+    /// Description of the persona
+    pub description: Option<String>,
+    /// System prompt
+    pub system_prompt: Option<String>,
+    /// Model to use
+    pub model: Option<String>,
+    /// Temperature setting
+    pub temperature: Option<f32>,
+    /// Max tokens
+    pub max_tokens: Option<u32>,
+    /// Tools the persona may use
+    pub tools: Option<Vec<String>>,
+}
+
+/// A graph of the application, built from a template
+///
+/// Exactly one of `template` (a bundled template), `path` (a template file,
+/// relative to the manifest) or `topology` (an inline template) is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphManifest {
+    /// Bundled template name
+    pub template: Option<String>,
+    /// Template file
+    pub path: Option<PathBuf>,
+    /// Inline template topology
+    pub topology: Option<Topology>,
+    /// Description, replacing the template's
+    pub description: Option<String>,
+    /// Provider the graph's agents run on
+    pub provider: Option<String>,
+    /// Execution limits for the graph
+    pub execution: Option<ExecutionConfig>,
+    /// Settings of individual nodes, by node ID
+    pub nodes: HashMap<String, NodeManifest>,
+}
+
+/// Settings of one node of a graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeManifest {
+    /// Time the node may run, overriding the graph's node timeout
+    pub timeout_ms: Option<u64>,
+}
+
+/// A resolved application manifest
+///
+/// ```toml
+/// [app]
+/// name = "support"
+/// default_provider = "openai"
+/// default_environment = "dev"
+///
+/// [providers.openai]
+/// api_key_env = "OPENAI_API_KEY"
+/// default_model = "gpt-4o-mini"
+///
+/// [tools]
+/// builtin = ["calculator"]
+/// files = ["tools.yaml"]
+///
+/// [tools.declared.get_weather]
+/// kind = "http"
+/// description = "Current weather for a city"
+/// url = "https://api.weather.test/v1/current?city={city}"
+///
+/// [personas.triager]
+/// extends = "customer_support"
+/// temperature = 0.2
+///
+/// [graphs.research]
+/// template = "research_pipeline"
+///
+/// [graphs.research.nodes.researcher]
+/// timeout_ms = 30000
+///
+/// [graphs.research.execution]
+/// history = "full"
+///
+/// [environments.prod.providers.openai]
+/// default_model = "gpt-4o"
+///
+/// [environments.prod.graphs.research.execution]
+/// history = "metadata"
+/// ```
+///
+/// The table under `environments.<name>` is merged over the rest of the file
+/// when that environment is selected, so any setting can be overridden per
+/// environment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppManifest {
+    /// Application settings
+    pub app: AppSection,
+    /// LLM providers by name
+    pub providers: BTreeMap<String, ProviderManifest>,
+    /// Tools shared by all graphs
+    pub tools: ToolsManifest,
+    /// Personas by name
+    pub personas: BTreeMap<String, PersonaManifest>,
+    /// Graphs by name
+    pub graphs: BTreeMap<String, GraphManifest>,
+    /// Studio and tracing settings used by the server
+    pub studio: VisualizationConfig,
+    /// Environment overrides, already applied
+    #[serde(skip_serializing)]
+    #[schemars(with = "BTreeMap<String, serde_json::Value>")]
+    environments: BTreeMap<String, toml::Value>,
+    #[serde(skip)]
+    environment: Option<String>,
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl AppManifest {
+    /// Parse a manifest, applying `environment`
+    ///
+    /// Without an explicit environment, the one named by `AGENTGRAPH_ENV` or
+    /// `app.default_environment` is applied, if any.
+    pub fn from_toml_str(content: &str, environment: Option<&str>) -> GraphResult<Self> {
+        let mut document: toml::Table = toml::from_str(content)
+            .map_err(|e| GraphError::ConfigurationError(format!("Invalid application manifest: {}", e)))?;
+
+        let overrides = match document.remove("environments") {
+            Some(toml::Value::Table(overrides)) => overrides,
+            Some(_) => {
+                return Err(GraphError::ConfigurationError(
+                    "`environments` must be a table of environment overrides".to_string(),
+                ))
+            }
+            None => toml::Table::new(),
+        };
+
+        let default_environment = document
+            .get("app")
+            .and_then(|app| app.get("default_environment"))
+            .and_then(|name| name.as_str())
+            .map(str::to_string);
+        let selected = environment
+            .map(str::to_string)
+            .or_else(|| std::env::var(ENVIRONMENT_VAR).ok().filter(|name| !name.is_empty()))
+            .or(default_environment);
+
+        if let Some(name) = &selected {
+            match overrides.get(name) {
+                Some(toml::Value::Table(overlay)) => merge_tables(&mut document, overlay.clone()),
+                Some(_) => {
+                    return Err(GraphError::ConfigurationError(format!(
+                        "Environment '{}' must be a table",
+                        name
+                    )))
+                }
+                None => {
+                    let known: Vec<&str> = overrides.keys().map(String::as_str).collect();
+                    return Err(GraphError::ConfigurationError(format!(
+                        "Unknown environment '{}' (defined: {})",
+                        name,
+                        if known.is_empty() { "none".to_string() } else { known.join(", ") }
+                    )));
+                }
+            }
+        }
+
+        let mut manifest: Self = toml::Value::Table(document)
+            .try_into()
+            .map_err(|e| GraphError::ConfigurationError(format!("Invalid application manifest: {}", e)))?;
+        manifest.environments = overrides.into_iter().collect();
+        manifest.environment = selected;
+        Ok(manifest)
+    }
+
+    /// Load a manifest file; relative graph paths resolve against its directory
+    pub fn load(path: &Path, environment: Option<&str>) -> GraphResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| GraphError::ConfigurationError(format!(
+            "Failed to read application manifest {}: {}",
+            path.display(),
+            e
+        )))?;
+        let mut manifest = Self::from_toml_str(&content, environment)?;
+        manifest.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(manifest)
+    }
+
+    /// Find `agentgraph.toml` in `start` or the closest directory above it
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start.ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Environment that was applied
+    pub fn environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    /// Environments defined in the manifest
+    pub fn environments(&self) -> Vec<&str> {
+        self.environments.keys().map(String::as_str).collect()
+    }
+
+    /// Names of the declared graphs
+    pub fn graph_names(&self) -> Vec<&str> {
+        self.graphs.keys().map(String::as_str).collect()
+    }
+
+    /// LLM configuration for the declared providers
+    pub fn llm_config(&self) -> LLMConfig {
+        let mut config = LLMConfig::default();
+        if let Some(provider) = self.app.default_provider.clone().or_else(|| self.providers.keys().next().cloned()) {
+            config.default_provider = provider;
+        }
+        for (name, provider) in &self.providers {
+            config.providers.insert(name.clone(), provider.provider_config());
+            if let Some(model) = &provider.default_model {
+                config.default_models.insert(name.clone(), model.clone());
+            }
+        }
+        config
+    }
+
+    /// LLM manager with every declared provider registered
+    pub fn llm_manager(&self) -> GraphResult<LLMManager> {
+        let mut manager = LLMManager::new(self.llm_config());
+        for (name, provider) in &self.providers {
+            let kind = provider.kind.as_deref().unwrap_or(name);
+            let instance = create_provider(kind, provider.provider_config()).map_err(|e| {
+                GraphError::ConfigurationError(format!("Failed to create provider '{}': {}", name, e))
+            })?;
+            manager.register_provider(name.clone(), instance);
+        }
+        Ok(manager)
+    }
+
+    /// Registry holding the declared built-in tools and the tools declared in the manifest and its tool files
+    pub fn tool_registry(&self) -> GraphResult<ToolRegistry> {
+        let mut registry = self.builtin_tools()?;
+        for (id, definition) in self.tool_definitions()? {
+            registry.register_declared(&id, definition)
+                .map_err(|e| GraphError::ConfigurationError(e.to_string()))?;
+        }
+        Ok(registry)
+    }
+
+    /// Tools declared inline and in tool files, with paths resolved against the file declaring them
+    pub fn tool_definitions(&self) -> GraphResult<BTreeMap<String, ToolDefinition>> {
+        let mut definitions = BTreeMap::new();
+        for (id, definition) in &self.tools.declared {
+            let mut definition = definition.clone();
+            definition.resolve_paths(&self.base_dir);
+            definitions.insert(id.clone(), definition);
+        }
+
+        for file in &self.tools.files {
+            let path = self.base_dir.join(file);
+            let content = std::fs::read_to_string(&path).map_err(|e| GraphError::ConfigurationError(format!(
+                "Failed to read tools file {}: {}",
+                path.display(),
+                e
+            )))?;
+            let declared: BTreeMap<String, ToolDefinition> = serde_yaml::from_str(&content).map_err(|e| {
+                GraphError::ConfigurationError(format!("Invalid tools file {}: {}", path.display(), e))
+            })?;
+            let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            for (id, mut definition) in declared {
+                if definitions.contains_key(&id) {
+                    return Err(GraphError::ConfigurationError(format!(
+                        "Tool '{}' in {} is already declared",
+                        id,
+                        path.display()
+                    )));
+                }
+                definition.resolve_paths(&base_dir);
+                definitions.insert(id, definition);
+            }
+        }
+        Ok(definitions)
+    }
+
+    /// Vault injecting the credentials declared tools reference from `store`
+    pub fn credential_vault(&self, store: Arc<dyn SecretStore>) -> GraphResult<CredentialVault> {
+        let mut vault = CredentialVault::new(store);
+        for (id, definition) in self.tool_definitions()? {
+            for binding in definition.credentials {
+                vault = vault.with_binding(&id, binding);
+            }
+        }
+        Ok(vault)
+    }
+
+    /// Resolve a persona, following `extends` through personas and built-in roles
+    pub fn persona(&self, name: &str) -> GraphResult<RoleTemplate> {
+        self.resolve_persona(name, &mut Vec::new())
+    }
+
+    fn resolve_persona(&self, name: &str, seen: &mut Vec<String>) -> GraphResult<RoleTemplate> {
+        if seen.iter().any(|persona| persona == name) {
+            seen.push(name.to_string());
+            return Err(GraphError::ConfigurationError(format!(
+                "Persona inheritance cycle: {}",
+                seen.join(" -> ")
+            )));
+        }
+        let persona = self.personas.get(name).ok_or_else(|| {
+            GraphError::ConfigurationError(format!("Unknown persona '{}'", name))
+        })?;
+        seen.push(name.to_string());
+
+        let mut template = match persona.extends.as_deref() {
+            Some(base) if self.personas.contains_key(base) => self.resolve_persona(base, seen)?,
+            Some(base) => RoleTemplates::get_template(base).ok_or_else(|| GraphError::ConfigurationError(format!(
+                "Persona '{}' extends unknown role '{}'",
+                name, base
+            )))?,
+            None => {
+                let (Some(system_prompt), Some(model)) = (&persona.system_prompt, &persona.model) else {
+                    return Err(GraphError::ConfigurationError(format!(
+                        "Persona '{}' needs `extends`, or both `system_prompt` and `model`",
+                        name
+                    )));
+                };
+                RoleTemplate {
+                    name: name.to_string(),
+                    description: name.to_string(),
+                    system_prompt: system_prompt.clone(),
+                    tools: Vec::new(),
+                    model: model.clone(),
+                    temperature: 0.7,
+                    max_tokens: 2000,
+                    memory_config: Default::default(),
+                    collaboration_config: Default::default(),
+                }
+            }
+        };
+
+        template.name = name.to_string();
+        if let Some(description) = &persona.description {
+            template.description = description.clone();
+        }
+        if let Some(system_prompt) = &persona.system_prompt {
+            template.system_prompt = system_prompt.clone();
+        }
+        if let Some(model) = &persona.model {
+            template.model = model.clone();
+        }
+        if let Some(temperature) = persona.temperature {
+            template.temperature = temperature;
+        }
+        if let Some(max_tokens) = persona.max_tokens {
+            template.max_tokens = max_tokens;
+        }
+        if let Some(tools) = &persona.tools {
+            template.tools = tools.clone();
+        }
+        Ok(template)
+    }
+
+    /// Template of a declared graph
+    pub fn graph_template(&self, name: &str) -> GraphResult<GraphTemplate> {
+        let graph = self.graph_manifest(name)?;
+        let mut template = match (&graph.template, &graph.path, &graph.topology) {
+            (Some(bundled), None, None) => GraphTemplate::bundled_template(bundled).ok_or_else(|| {
+                GraphError::ConfigurationError(format!("Graph '{}' uses unknown template '{}'", name, bundled))
+            })?,
+            (None, Some(path), None) => GraphTemplate::load(&self.base_dir.join(path))?,
+            (None, None, Some(topology)) => GraphTemplate {
+                name: name.to_string(),
+                description: None,
+                topology: topology.clone(),
+            },
+            _ => {
+                return Err(GraphError::ConfigurationError(format!(
+                    "Graph '{}' must set exactly one of `template`, `path` or `topology`",
+                    name
+                )))
+            }
+        };
+        template.name = name.to_string();
+        if graph.description.is_some() {
+            template.description = graph.description.clone();
+        }
+        Ok(template)
+    }
+
+    /// Template context for a declared graph, on its provider and with the manifest's personas
+    ///
+    /// Add a vector store to the returned context for retrieval templates.
+    pub fn graph_context(
+        &self,
+        name: &str,
+        llm_manager: Arc<LLMManager>,
+        tool_registry: Arc<ToolRegistry>,
+        tool_executor: Arc<ToolExecutor>,
+    ) -> GraphResult<TemplateContext> {
+        let provider = self.graph_provider(name)?;
+        let personas = self.personas.keys()
+            .map(|persona| Ok((persona.clone(), self.persona(persona)?)))
+            .collect::<GraphResult<HashMap<_, _>>>()?;
+        Ok(TemplateContext::new(llm_manager, tool_registry, tool_executor, provider).with_personas(personas))
+    }
+
+    /// Build a declared graph
+    pub fn build_graph<S: State>(&self, name: &str, context: &TemplateContext) -> GraphResult<Graph<S>> {
+        let manifest = self.graph_manifest(name)?;
+        let mut graph = self.graph_template(name)?.build(context)?;
+        if let Some(execution) = &manifest.execution {
+            graph.set_config(execution.clone());
+        }
+        for (node_id, node) in &manifest.nodes {
+            let metadata = graph.node_registry_mut().get_metadata_mut(node_id).ok_or_else(|| {
+                GraphError::ConfigurationError(format!("graphs.{}.nodes: graph has no node '{}'", name, node_id))
+            })?;
+            if node.timeout_ms.is_some() {
+                metadata.timeout_ms = node.timeout_ms;
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Pre-flight cost estimate for a declared graph, without creating any provider
+    ///
+    /// The graph is built over a JSON state, so `input` can be any JSON document.
+    pub fn estimate_cost(&self, name: &str, input: &serde_json::Value, history: &TokenHistory) -> GraphResult<CostEstimate> {
+        let llm_manager = Arc::new(LLMManager::new(self.llm_config()));
+        let context = self.graph_context(
+            name,
+            llm_manager.clone(),
+            Arc::new(self.tool_registry()?),
+            Arc::new(ToolExecutor::new()),
+        )?;
+        let graph: Graph<serde_json::Value> = self.build_graph(name, &context)?;
+        graph.estimate_cost(input, llm_manager.profiles(), history)
+    }
+
+    /// Check references between sections without building anything
+    pub fn validate(&self) -> GraphResult<()> {
+        if let Some(provider) = &self.app.default_provider {
+            self.require_provider(provider, "app.default_provider")?;
+        }
+        for persona in self.personas.keys() {
+            self.persona(persona)?;
+        }
+        for name in self.graphs.keys() {
+            self.graph_template(name)?;
+            self.graph_provider(name)?;
+        }
+        self.tool_registry()?;
+        Ok(())
+    }
+
+    fn graph_manifest(&self, name: &str) -> GraphResult<&GraphManifest> {
+        self.graphs.get(name).ok_or_else(|| GraphError::ConfigurationError(format!(
+            "Unknown graph '{}' (declared: {})",
+            name,
+            self.graph_names().join(", ")
+        )))
+    }
+
+    fn graph_provider(&self, name: &str) -> GraphResult<String> {
+        let graph = self.graph_manifest(name)?;
+        let provider = graph.provider.clone()
+            .or_else(|| self.app.default_provider.clone())
+            .or_else(|| self.providers.keys().next().cloned())
+            .ok_or_else(|| GraphError::ConfigurationError(format!(
+                "Graph '{}' has no provider and the manifest declares none",
+                name
+            )))?;
+        self.require_provider(&provider, &format!("graphs.{}.provider", name))?;
+        Ok(provider)
+    }
+
+    fn require_provider(&self, provider: &str, key: &str) -> GraphResult<()> {
+        if self.providers.contains_key(provider) {
+            return Ok(());
+        }
+        Err(GraphError::ConfigurationError(format!(
+            "`{}` names undeclared provider '{}'",
+            key, provider
+        )))
+    }
+
+    fn builtin_tools(&self) -> GraphResult<ToolRegistry> {
+        if self.tools.builtin.is_empty() {
+            return Ok(ToolRegistry::new());
+        }
+        let mut registry = create_common_tools_registry()
+            .map_err(|e| GraphError::ConfigurationError(format!("Failed to create built-in tools: {}", e)))?;
+        if self.tools.builtin.iter().any(|tool| tool == "*") {
+            return Ok(registry);
+        }
+
+        let available = registry.list_tools();
+        if let Some(unknown) = self.tools.builtin.iter().find(|tool| !available.contains(tool)) {
+            return Err(GraphError::ConfigurationError(format!(
+                "Unknown built-in tool '{}' (available: {})",
+                unknown,
+                available.join(", ")
+            )));
+        }
+        for tool in available.iter().filter(|tool| !self.tools.builtin.contains(tool)) {
+            registry.unregister(tool)
+                .map_err(|e| GraphError::ConfigurationError(e.to_string()))?;
+        }
+        Ok(registry)
+    }
+}
+
+/// Merge `overlay` into `base`, replacing everything but nested tables
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(nested)) => merge_tables(existing, nested),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+        [app]
+        name = "support"
+        default_provider = "primary"
+        default_environment = "dev"
+
+        [providers.primary]
+        kind = "mock"
+        default_model = "small"
+
+        [personas.triager]
+        extends = "customer_support"
+        temperature = 0.1
+
+        [personas.senior_triager]
+        extends = "triager"
+        model = "big"
+
+        [graphs.research]
+        template = "research_pipeline"
+
+        [graphs.inline]
+        description = "Inline pipeline"
+        [graphs.inline.topology]
+        kind = "pipeline"
+        writer_role = "triager"
+
+        [environments.dev]
+
+        [environments.prod.providers.primary]
+        default_model = "big"
+
+        [environments.prod.graphs.research]
+        provider = "backup"
+    "#;
+
+    #[test]
+    fn test_environment_overrides_are_merged() {
+        let dev = AppManifest::from_toml_str(MANIFEST, None).unwrap();
+        assert_eq!(dev.environment(), Some("dev"));
+        assert_eq!(dev.environments(), vec!["dev", "prod"]);
+        assert_eq!(dev.llm_config().default_models["primary"], "small");
+        dev.validate().unwrap();
+
+        let prod = AppManifest::from_toml_str(MANIFEST, Some("prod")).unwrap();
+        assert_eq!(prod.llm_config().default_models["primary"], "big");
+        // Nested tables merge instead of replacing the base graph
+        assert_eq!(prod.graphs["research"].template.as_deref(), Some("research_pipeline"));
+        let error = prod.validate().unwrap_err();
+        assert!(error.to_string().contains("backup"));
+
+        assert!(AppManifest::from_toml_str(MANIFEST, Some("qa")).is_err());
+    }
+
+    #[test]
+    fn test_personas_and_graph_templates_resolve() {
+        let manifest = AppManifest::from_toml_str(MANIFEST, Some("dev")).unwrap();
+
+        let senior = manifest.persona("senior_triager").unwrap();
+        assert_eq!(senior.name, "senior_triager");
+        assert_eq!(senior.model, "big");
+        assert_eq!(senior.temperature, 0.1);
+        assert_eq!(senior.system_prompt, RoleTemplates::customer_support().system_prompt);
+
+        let inline = manifest.graph_template("inline").unwrap();
+        assert_eq!(inline.name, "inline");
+        assert_eq!(inline.description.as_deref(), Some("Inline pipeline"));
+        assert!(matches!(inline.topology, Topology::Pipeline(_)));
+        assert!(manifest.graph_template("missing").is_err());
+
+        let unknown_field = AppManifest::from_toml_str("[app]\nnmae = \"typo\"", None);
+        assert!(unknown_field.is_err());
+    }
+
+    #[test]
+    fn test_node_settings_apply_to_built_graph() {
+        let build = |nodes: &str| {
+            let manifest = AppManifest::from_toml_str(&format!("{}\n{}", MANIFEST, nodes), Some("dev")).unwrap();
+            let context = manifest.graph_context(
+                "research",
+                Arc::new(LLMManager::new(manifest.llm_config())),
+                Arc::new(manifest.tool_registry().unwrap()),
+                Arc::new(ToolExecutor::new()),
+            ).unwrap();
+            manifest.build_graph::<serde_json::Value>("research", &context)
+        };
+
+        let graph = build("[graphs.research.nodes.researcher]\ntimeout_ms = 30000").unwrap();
+        let timeout = |node: &str| graph.node_registry().get_metadata(&node.to_string()).unwrap().timeout_ms;
+        assert_eq!(timeout("researcher"), Some(30_000));
+        assert_eq!(timeout("writer"), None);
+
+        let error = build("[graphs.research.nodes.researchr]\ntimeout_ms = 30000").unwrap_err();
+        assert!(error.to_string().contains("researchr"));
+    }
+
+    #[test]
+    fn test_declared_tools_are_registered() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tools.yaml"), r#"
+create_ticket:
+  kind: http
+  method: POST
+  description: Open a support ticket
+  url: https://tickets.test/api/tickets
+  credentials:
+    - credential: tickets_token
+      injection: { type: header, name: Authorization, prefix: "Bearer " }
+      required: true
+"#).unwrap();
+        std::fs::write(dir.path().join(MANIFEST_FILE), format!("{}\n{}", MANIFEST, r#"
+            [tools]
+            files = ["tools.yaml"]
+
+            [tools.declared.get_weather]
+            kind = "http"
+            description = "Current weather for a city"
+            url = "https://api.weather.test/v1/current?city={city}"
+        "#)).unwrap();
+
+        let manifest = AppManifest::load(&dir.path().join(MANIFEST_FILE), Some("dev")).unwrap();
+        manifest.validate().unwrap();
+        let registry = manifest.tool_registry().unwrap();
+        assert!(registry.get("get_weather").is_some());
+        assert!(registry.get("create_ticket").unwrap().metadata().has_side_effects);
+
+        let vault = manifest.credential_vault(Arc::new(crate::enterprise::secrets::InMemorySecretStore::new())).unwrap();
+        assert_eq!(vault.bindings("create_ticket")[0].credential, "tickets_token");
+        assert!(vault.bindings("get_weather").is_empty());
+
+        std::fs::write(dir.path().join("tools.yaml"), "get_weather:\n  kind: http\n  description: Again\n  url: https://x.test\n").unwrap();
+        let manifest = AppManifest::load(&dir.path().join(MANIFEST_FILE), Some("dev")).unwrap();
+        assert!(manifest.validate().unwrap_err().to_string().contains("already declared"));
+    }
+}
@@ -0,0 +1,700 @@
+//! Static checks of declarative definitions: application manifests, graph
+//! template files and tool files.
+//!
+//! Every problem found is reported as a [`Diagnostic`] with a severity, a
+//! stable code and, where it can be located, the span in the source file, so
+//! editors and CI can consume the result of `agentgraph validate --json`.
+
+use super::{AppManifest, MANIFEST_FILE};
+use crate::agents::vector::{HashingEmbedder, InMemoryVectorStore};
+use crate::error::GraphError;
+use crate::graph::templates::{GraphTemplate, TemplateContext, Topology};
+use crate::graph::Graph;
+use crate::llm::providers::available_providers;
+use crate::llm::{LLMConfig, LLMManager};
+use crate::tools::{ToolDefinition, ToolExecutor, ToolRegistry};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Provider kinds that need an API key
+const KEYED_PROVIDERS: &[&str] = &["openai", "anthropic", "google", "openrouter"];
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The definition cannot be loaded or run as written
+    Error,
+    /// The definition loads but probably does not do what was meant
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => f.write_str("error"),
+            Self::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// Source range of a diagnostic; lines and columns are 1-based, the end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    /// First line
+    pub line: usize,
+    /// Column on the first line
+    pub column: usize,
+    /// Last line
+    pub end_line: usize,
+    /// Column on the last line
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Span of the byte range `range` of `source`
+    pub fn from_range(source: &str, range: Range<usize>) -> Self {
+        let (line, column) = position(source, range.start);
+        let (end_line, end_column) = position(source, range.end.max(range.start));
+        Self { line, column, end_line, end_column }
+    }
+
+    fn point(line: usize, column: usize) -> Self {
+        Self { line, column, end_line: line, end_column: column }
+    }
+}
+
+/// One problem in a declarative definition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Stable identifier of the check, e.g. `unknown-provider`
+    pub code: String,
+    /// What is wrong
+    pub message: String,
+    /// File the problem is in
+    pub file: PathBuf,
+    /// Dotted key the problem is about, e.g. `graphs.research.provider`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Environment the problem shows up in, when it is specific to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Where in the file the problem is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(span) = &self.span {
+            write!(f, ":{}:{}", span.line, span.column)?;
+        }
+        write!(f, ": {}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(environment) = &self.environment {
+            write!(f, " (environment {})", environment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Diagnostics of one or more files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LintReport {
+    /// Files checked
+    pub files: Vec<PathBuf>,
+    /// Problems found, in file order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// Add the diagnostics of `other`
+    pub fn merge(&mut self, other: LintReport) {
+        self.files.extend(other.files);
+        self.diagnostics.extend(other.diagnostics);
+    }
+
+    /// Diagnostics of severity `severity`
+    pub fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(move |diagnostic| diagnostic.severity == severity)
+    }
+
+    /// Number of errors
+    pub fn error_count(&self) -> usize {
+        self.with_severity(Severity::Error).count()
+    }
+
+    /// Number of warnings
+    pub fn warning_count(&self) -> usize {
+        self.with_severity(Severity::Warning).count()
+    }
+
+    /// Whether no errors were found
+    pub fn is_ok(&self) -> bool {
+        self.error_count() == 0
+    }
+}
+
+/// Check a manifest, graph template or tool file, telling them apart by extension and content
+///
+/// YAML files are tool files. A TOML file with a `topology` and no `[app]`
+/// table is a graph template; any other TOML file is an application manifest.
+/// Manifests are checked in `environment`, or in each environment they define.
+pub fn lint_path(path: &Path, environment: Option<&str>) -> LintReport {
+    let mut linter = Linter::new(path, "");
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            linter.push(Severity::Error, "io", format!("Failed to read {}: {}", path.display(), e), None, None);
+            return linter.finish();
+        }
+    };
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => lint_tool_file(path, &content),
+        Some("toml") => {
+            let is_template = path.file_name().is_some_and(|name| name != MANIFEST_FILE)
+                && content.parse::<toml::Table>().is_ok_and(|table| {
+                    table.contains_key("topology") && !table.contains_key("app")
+                });
+            if is_template {
+                lint_template(path, &content)
+            } else {
+                lint_manifest(path, &content, environment)
+            }
+        }
+        _ => {
+            linter.push(
+                Severity::Error,
+                "unsupported-file",
+                "Expected a TOML manifest or graph template, or a YAML tool file".to_string(),
+                None,
+                None,
+            );
+            linter.finish()
+        }
+    }
+}
+
+/// Check an application manifest read from `path`
+///
+/// The syntax and the fields of the file are checked first, then the
+/// references between providers, tools, personas and graphs, and finally
+/// every graph is built, without creating any provider, and its structure
+/// checked.
+pub fn lint_manifest(path: &Path, content: &str, environment: Option<&str>) -> LintReport {
+    let mut linter = Linter::new(path, content);
+    let Some(raw) = linter.parse_toml::<toml::Table>(content) else {
+        return linter.finish();
+    };
+    let Some(base) = linter.parse_toml::<AppManifest>(content) else {
+        return linter.finish();
+    };
+    linter.check_topology_keys(&raw);
+    if let Some(name) = base.app.default_environment.as_ref().filter(|name| !base.environments.contains_key(*name)) {
+        linter.push(
+            Severity::Error,
+            "unknown-environment",
+            format!("Default environment '{}' is not defined under `environments`", name),
+            Some("app.default_environment".to_string()),
+            None,
+        );
+    }
+
+    let environments: Vec<Option<String>> = match environment {
+        Some(name) => vec![Some(name.to_string())],
+        None if base.environments.is_empty() => vec![None],
+        None => base.environments.keys().cloned().map(Some).collect(),
+    };
+    for environment in environments {
+        let resolved = match environment.as_deref() {
+            Some(name) => AppManifest::from_toml_str(content, Some(name)),
+            // Without environments there is nothing to apply
+            None => Ok(base.clone()),
+        };
+        match resolved {
+            Ok(mut manifest) => {
+                manifest.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                linter.check_manifest(&manifest, environment.as_deref());
+            }
+            Err(e) => {
+                let key = environment.as_ref().map(|name| format!("environments.{}", name));
+                linter.push(Severity::Error, "schema", detail(&e), key, environment.clone());
+            }
+        }
+    }
+    linter.finish()
+}
+
+/// Check a graph template file read from `path`, building it on the built-in roles
+pub fn lint_template(path: &Path, content: &str) -> LintReport {
+    let mut linter = Linter::new(path, content);
+    let Some(raw) = linter.parse_toml::<toml::Table>(content) else {
+        return linter.finish();
+    };
+    let Some(template) = linter.parse_toml::<GraphTemplate>(content) else {
+        return linter.finish();
+    };
+    if let Some(topology) = raw.get("topology") {
+        linter.check_unknown_keys(topology, "topology");
+    }
+
+    let context = lint_context(LLMConfig::default(), Arc::new(ToolRegistry::new()), "default");
+    match template.build::<serde_json::Value>(&context) {
+        Ok(graph) => linter.check_graph(graph, "topology", None),
+        Err(e) => linter.push(Severity::Error, "graph-build", detail(&e), Some("topology".to_string()), None),
+    }
+    linter.finish()
+}
+
+/// Check a YAML file declaring tools by ID, read from `path`
+pub fn lint_tool_file(path: &Path, content: &str) -> LintReport {
+    let mut linter = Linter::new(path, content);
+    let declared: BTreeMap<String, ToolDefinition> = match serde_yaml::from_str(content) {
+        Ok(declared) => declared,
+        Err(e) => {
+            let span = e.location().map(|location| Span::point(location.line(), location.column()));
+            linter.push_at(Severity::Error, "schema", e.to_string(), None, None, span);
+            return linter.finish();
+        }
+    };
+
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut registry = ToolRegistry::new();
+    for (id, mut definition) in declared {
+        definition.resolve_paths(&base_dir);
+        if let Err(e) = registry.register_declared(&id, definition) {
+            let span = locate_yaml(content, &id);
+            linter.push_at(Severity::Error, "tool", e.to_string(), Some(id), None, span);
+        }
+    }
+    linter.finish()
+}
+
+/// Collects the diagnostics of one file
+struct Linter<'a> {
+    file: &'a Path,
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(file: &'a Path, source: &'a str) -> Self {
+        Self { file, source, diagnostics: Vec::new() }
+    }
+
+    fn finish(self) -> LintReport {
+        LintReport {
+            files: vec![self.file.to_path_buf()],
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Report a problem, locating `key` in the source
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: &str,
+        message: String,
+        key: Option<String>,
+        environment: Option<String>,
+    ) {
+        let span = key.as_deref().and_then(|key| locate_toml(self.source, key, environment.as_deref()));
+        self.push_at(severity, code, message, key, environment, span);
+    }
+
+    fn push_at(
+        &mut self,
+        severity: Severity,
+        code: &str,
+        message: String,
+        key: Option<String>,
+        environment: Option<String>,
+        span: Option<Span>,
+    ) {
+        // A problem found in every environment comes from the base of the file
+        if let Some(existing) = self.diagnostics.iter_mut().find(|existing| {
+            existing.code == code && existing.message == message && existing.key == key
+        }) {
+            if existing.environment != environment {
+                existing.environment = None;
+            }
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code: code.to_string(),
+            message,
+            file: self.file.to_path_buf(),
+            key,
+            environment,
+            span,
+        });
+    }
+
+    /// Parse the whole source, reporting syntax and field errors at their span
+    fn parse_toml<T: serde::de::DeserializeOwned>(&mut self, content: &str) -> Option<T> {
+        match toml::from_str(content) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let code = if content.parse::<toml::Table>().is_ok() { "schema" } else { "syntax" };
+                let span = e.span().map(|range| Span::from_range(content, range));
+                self.push_at(Severity::Error, code, e.message().trim().to_string(), None, None, span);
+                None
+            }
+        }
+    }
+
+    /// Warn about keys of inline topologies, in the base or an environment, that no parameter takes
+    fn check_topology_keys(&mut self, raw: &toml::Table) {
+        let mut tables = vec![(String::new(), raw)];
+        if let Some(toml::Value::Table(environments)) = raw.get("environments") {
+            for (name, overlay) in environments {
+                if let toml::Value::Table(overlay) = overlay {
+                    tables.push((format!("environments.{}.", name), overlay));
+                }
+            }
+        }
+        for (prefix, table) in tables {
+            let Some(toml::Value::Table(graphs)) = table.get("graphs") else {
+                continue;
+            };
+            for (name, graph) in graphs {
+                if let Some(topology) = graph.get("topology") {
+                    self.check_unknown_keys(topology, &format!("{}graphs.{}.topology", prefix, name));
+                }
+            }
+        }
+    }
+
+    /// Topology parameters default every field, so misspelt ones are otherwise silently ignored
+    fn check_unknown_keys(&mut self, raw: &toml::Value, key: &str) {
+        let Ok(topology) = raw.clone().try_into::<Topology>() else {
+            // Partial environment overrides, or errors reported when the file is parsed
+            return;
+        };
+        let (Some(raw), Ok(toml::Value::Table(known))) = (raw.as_table(), toml::Value::try_from(&topology)) else {
+            return;
+        };
+        for field in raw.keys().filter(|field| !known.contains_key(*field)) {
+            self.push(
+                Severity::Warning,
+                "unknown-key",
+                format!("`{}` is not a parameter of this topology and is ignored", field),
+                Some(format!("{}.{}", key, field)),
+                None,
+            );
+        }
+    }
+
+    fn check_manifest(&mut self, manifest: &AppManifest, environment: Option<&str>) {
+        let environment = environment.map(str::to_string);
+
+        if let Some(provider) = &manifest.app.default_provider {
+            if let Err(e) = manifest.require_provider(provider, "app.default_provider") {
+                self.push(Severity::Error, "unknown-provider", detail(&e), Some("app.default_provider".to_string()), environment.clone());
+            }
+        }
+        let available = available_providers();
+        for (name, provider) in &manifest.providers {
+            let kind = provider.kind.as_deref().unwrap_or(name);
+            if !available.contains(&kind) {
+                self.push(
+                    Severity::Error,
+                    "unknown-provider-kind",
+                    format!("Provider '{}' has unknown kind '{}' (available: {})", name, kind, available.join(", ")),
+                    Some(format!("providers.{}.kind", name)),
+                    environment.clone(),
+                );
+            } else if KEYED_PROVIDERS.contains(&kind) {
+                match &provider.api_key_env {
+                    None => self.push(
+                        Severity::Warning,
+                        "missing-api-key",
+                        format!("Provider '{}' sets no `api_key_env`, so it runs without an API key", name),
+                        Some(format!("providers.{}", name)),
+                        environment.clone(),
+                    ),
+                    Some(var) if std::env::var(var).unwrap_or_default().is_empty() => self.push(
+                        Severity::Warning,
+                        "missing-api-key",
+                        format!("Provider '{}' reads its API key from ${}, which is not set", name, var),
+                        Some(format!("providers.{}.api_key_env", name)),
+                        environment.clone(),
+                    ),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let registry = match manifest.tool_registry() {
+            Ok(registry) => Some(registry),
+            Err(e) => {
+                self.push(Severity::Error, "tools", detail(&e), Some("tools".to_string()), environment.clone());
+                None
+            }
+        };
+
+        let mut personas_ok = true;
+        for (name, persona) in &manifest.personas {
+            if let Err(e) = manifest.persona(name) {
+                personas_ok = false;
+                self.push(Severity::Error, "persona", detail(&e), Some(format!("personas.{}", name)), environment.clone());
+            }
+            let (Some(registry), Some(tools)) = (&registry, &persona.tools) else {
+                continue;
+            };
+            for tool in tools.iter().filter(|tool| !registry.contains(tool)) {
+                self.push(
+                    Severity::Warning,
+                    "unknown-tool",
+                    format!("Persona '{}' uses tool '{}', which the manifest does not declare", name, tool),
+                    Some(format!("personas.{}.tools", name)),
+                    environment.clone(),
+                );
+            }
+        }
+
+        let registry = Arc::new(registry.unwrap_or_default());
+        for name in manifest.graph_names() {
+            let key = format!("graphs.{}", name);
+            let mut buildable = personas_ok;
+            if let Err(e) = manifest.graph_template(name) {
+                buildable = false;
+                self.push(Severity::Error, "graph-template", detail(&e), Some(key.clone()), environment.clone());
+            }
+            if let Err(e) = manifest.graph_provider(name) {
+                buildable = false;
+                self.push(Severity::Error, "unknown-provider", detail(&e), Some(format!("{}.provider", key)), environment.clone());
+            }
+            if !buildable {
+                continue;
+            }
+
+            let built = manifest.graph_context(
+                name,
+                Arc::new(LLMManager::new(manifest.llm_config())),
+                registry.clone(),
+                Arc::new(ToolExecutor::new()),
+            ).map(|context| {
+                context.with_vector_store(Arc::new(InMemoryVectorStore::new()), Arc::new(HashingEmbedder::new(64)))
+            }).and_then(|context| manifest.build_graph::<serde_json::Value>(name, &context));
+            match built {
+                Ok(graph) => self.check_graph(graph, &key, environment.clone()),
+                Err(e) => self.push(Severity::Error, "graph-build", detail(&e), Some(key.clone()), environment.clone()),
+            }
+        }
+    }
+
+    /// Structural checks of a built graph
+    fn check_graph(&mut self, graph: Graph<serde_json::Value>, key: &str, environment: Option<String>) {
+        if let Err(e) = graph.validate() {
+            self.push(Severity::Error, "graph-structure", detail(&e), Some(key.to_string()), environment);
+            return;
+        }
+
+        let mut reached = HashSet::new();
+        let mut queue: VecDeque<&String> = graph.entry_point().into_iter().collect();
+        while let Some(node) = queue.pop_front() {
+            if !reached.insert(node) {
+                continue;
+            }
+            for edge in graph.edges().iter().filter(|edge| &edge.from == node) {
+                queue.extend(edge.possible_targets());
+            }
+        }
+        let mut nodes = graph.node_ids();
+        nodes.sort();
+        for node in nodes {
+            if !reached.contains(node) {
+                self.push(
+                    Severity::Warning,
+                    "unreachable-node",
+                    format!("Node '{}' cannot be reached from the entry point", node),
+                    Some(key.to_string()),
+                    environment.clone(),
+                );
+            } else if !graph.finish_points().contains(node) && !graph.edges().iter().any(|edge| &edge.from == node) {
+                self.push(
+                    Severity::Warning,
+                    "dead-end",
+                    format!("Node '{}' has no outgoing edges and is not a finish point", node),
+                    Some(key.to_string()),
+                    environment.clone(),
+                );
+            }
+        }
+
+        if let Err(e) = graph.compile() {
+            self.push(Severity::Error, "graph-structure", detail(&e), Some(key.to_string()), environment);
+        }
+    }
+}
+
+/// Template context that builds agents without creating any provider
+fn lint_context(config: LLMConfig, tool_registry: Arc<ToolRegistry>, provider: &str) -> TemplateContext {
+    TemplateContext::new(Arc::new(LLMManager::new(config)), tool_registry, Arc::new(ToolExecutor::new()), provider)
+        .with_vector_store(Arc::new(InMemoryVectorStore::new()), Arc::new(HashingEmbedder::new(64)))
+}
+
+/// Message of an error without the prefix naming its kind
+fn detail(error: &GraphError) -> String {
+    match error {
+        GraphError::ConfigurationError(message)
+        | GraphError::GraphStructure(message)
+        | GraphError::ValidationError(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// 1-based line and column of byte `offset` of `source`
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset.min(source.len())).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+/// Span of the line setting dotted `key` in a TOML source, or of its closest parent that is set
+///
+/// Keys are looked for in `environment`'s override table first.
+fn locate_toml(source: &str, key: &str, environment: Option<&str>) -> Option<Span> {
+    let segments: Vec<&str> = key.split('.').collect();
+    (1..=segments.len()).rev().find_map(|len| {
+        let key = &segments[..len];
+        let in_environment = environment.and_then(|name| {
+            let prefixed: Vec<&str> = ["environments", name].into_iter().chain(key.iter().copied()).collect();
+            find_toml_key(source, &prefixed)
+        });
+        in_environment.or_else(|| find_toml_key(source, key))
+    })
+}
+
+fn find_toml_key(source: &str, key: &[&str]) -> Option<Span> {
+    let split = |name: &str| -> Vec<String> {
+        name.split('.').map(|part| part.trim().trim_matches(|c| c == '"' || c == '\'').to_string()).collect()
+    };
+    let mut table: Vec<String> = Vec::new();
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let start = offset + (line.len() - line.trim_start().len());
+        offset += line.len();
+
+        let path = if let Some(header) = trimmed.strip_prefix('[') {
+            let name = header.trim_start_matches('[').split(']').next().unwrap_or_default();
+            table = split(name);
+            table.clone()
+        } else if let Some((name, _)) = trimmed.split_once('=').filter(|_| !trimmed.starts_with('#')) {
+            table.iter().cloned().chain(split(name)).collect()
+        } else {
+            continue;
+        };
+        if path.len() == key.len() && path.iter().zip(key).all(|(segment, wanted)| segment == wanted) {
+            return Some(Span::from_range(source, start..start + trimmed.len()));
+        }
+    }
+    None
+}
+
+/// Span of the line declaring top-level `key` in a YAML source
+fn locate_yaml(source: &str, key: &str) -> Option<Span> {
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let name = line.split(':').next().unwrap_or_default().trim_matches(|c| c == '"' || c == '\'');
+        if !line.starts_with(char::is_whitespace) && line.contains(':') && name == key {
+            let trimmed = line.trim_end();
+            return Some(Span::from_range(source, offset..offset + trimmed.len()));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"[app]
+name = "support"
+default_provider = "primary"
+
+[providers.primary]
+kind = "mock"
+
+[personas.triager]
+extends = "customer_support"
+tools = ["calculator"]
+
+[graphs.inline.topology]
+kind = "pipeline"
+writer_role = "triager"
+max_revisons = 1
+
+[environments.dev]
+
+[environments.prod.graphs.inline]
+provider = "backup"
+"#;
+
+    fn codes(report: &LintReport) -> Vec<&str> {
+        report.diagnostics.iter().map(|diagnostic| diagnostic.code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_manifest_references_are_reported_with_spans() {
+        let report = lint_manifest(Path::new(MANIFEST_FILE), MANIFEST, None);
+        assert_eq!(report.error_count(), 1);
+        assert_eq!(report.warning_count(), 2);
+
+        let typo = report.diagnostics.iter().find(|d| d.code == "unknown-key").unwrap();
+        assert_eq!(typo.key.as_deref(), Some("graphs.inline.topology.max_revisons"));
+        assert_eq!(typo.span.map(|span| (span.line, span.column)), Some((15, 1)));
+        // Found in both environments, so it belongs to the base
+        let tool = report.diagnostics.iter().find(|d| d.code == "unknown-tool").unwrap();
+        assert_eq!(tool.environment, None);
+
+        let provider = report.diagnostics.iter().find(|d| d.code == "unknown-provider").unwrap();
+        assert_eq!(provider.environment.as_deref(), Some("prod"));
+        assert_eq!(provider.span.map(|span| span.line), Some(20));
+        assert!(provider.to_string().starts_with("agentgraph.toml:20:1: error[unknown-provider]"));
+
+        let dev = lint_manifest(Path::new(MANIFEST_FILE), MANIFEST, Some("dev"));
+        assert!(dev.is_ok());
+    }
+
+    #[test]
+    fn test_syntax_and_schema_errors_have_spans() {
+        let syntax = lint_manifest(Path::new(MANIFEST_FILE), "[app]\nname = \"x", None);
+        assert_eq!(codes(&syntax), vec!["syntax"]);
+        assert_eq!(syntax.diagnostics[0].span.unwrap().line, 2);
+
+        let schema = lint_manifest(Path::new(MANIFEST_FILE), "[app]\nname = \"x\"\nnmae = \"typo\"\n", None);
+        assert_eq!(codes(&schema), vec!["schema"]);
+        assert_eq!(schema.diagnostics[0].span.unwrap().line, 3);
+
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["diagnostics"][0]["severity"], "error");
+        assert_eq!(json["diagnostics"][0]["span"]["line"], 3);
+    }
+
+    #[test]
+    fn test_templates_and_tool_files() {
+        let template = lint_template(
+            Path::new("digest.toml"),
+            "name = \"digest\"\n\n[topology]\nkind = \"pipeline\"\nwriter_role = \"astronaut\"\n",
+        );
+        assert_eq!(codes(&template), vec!["graph-build"]);
+        assert_eq!(template.diagnostics[0].span.unwrap().line, 3);
+
+        let tools = lint_tool_file(Path::new("tools.yaml"), "get_weather:\n  kind: http\n  description: [\n");
+        assert_eq!(codes(&tools), vec!["schema"]);
+        assert!(tools.diagnostics[0].span.is_some());
+    }
+}
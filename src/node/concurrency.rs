@@ -0,0 +1,147 @@
+//! Concurrency keys: nodes declaring the same key never run at the same time.
+//!
+//! Keys are held process-wide, so two parallel branches, two executions of a
+//! graph, or two different graphs writing to the same external resource are
+//! serialized alike. A key may be templated with `{name}` placeholders filled
+//! from the state or the execution's custom data, e.g. `crm-writer-{tenant_id}`.
+
+use crate::error::{GraphError, GraphResult};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, Weak};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Proof that a concurrency key is held; the key is released on drop
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    key: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl ConcurrencyPermit {
+    /// Key this permit holds
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// Locks behind concurrency keys
+///
+/// Entries live only while some node holds or waits for the key, so per-tenant
+/// keys do not accumulate.
+#[derive(Debug, Default)]
+pub struct ConcurrencyKeys {
+    locks: parking_lot::Mutex<HashMap<String, Weak<Mutex<()>>>>,
+}
+
+impl ConcurrencyKeys {
+    /// Create an empty set of keys
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys shared by every engine in the process
+    pub fn global() -> &'static ConcurrencyKeys {
+        static GLOBAL: OnceLock<ConcurrencyKeys> = OnceLock::new();
+        GLOBAL.get_or_init(ConcurrencyKeys::new)
+    }
+
+    /// Wait until `key` is free and take it
+    pub async fn acquire(&self, key: &str) -> ConcurrencyPermit {
+        let lock = {
+            let mut locks = self.locks.lock();
+            match locks.get(key).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    locks.retain(|_, lock| lock.strong_count() > 0);
+                    let lock = Arc::new(Mutex::new(()));
+                    locks.insert(key.to_string(), Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        ConcurrencyPermit {
+            key: key.to_string(),
+            _guard: lock.lock_owned().await,
+        }
+    }
+
+    /// Check whether a node currently holds `key`
+    pub fn is_held(&self, key: &str) -> bool {
+        self.locks
+            .lock()
+            .get(key)
+            .and_then(Weak::upgrade)
+            .is_some_and(|lock| lock.try_lock().is_err())
+    }
+}
+
+/// Fill the `{name}` placeholders of a key template using `lookup`
+///
+/// String values are inserted as-is and other values as JSON. A placeholder
+/// without a value is an error rather than an empty segment, which would
+/// silently share the key between unrelated tenants.
+pub fn resolve_key<F>(template: &str, lookup: F) -> GraphResult<String>
+where
+    F: Fn(&str) -> Option<serde_json::Value>,
+{
+    let mut key = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').map(|end| start + end).ok_or_else(|| {
+            GraphError::ConfigurationError(format!("Unclosed placeholder in concurrency key '{}'", template))
+        })?;
+        let name = &rest[start + 1..end];
+        let value = lookup(name).ok_or_else(|| {
+            GraphError::ConfigurationError(format!("No value for '{}' in concurrency key '{}'", name, template))
+        })?;
+        key.push_str(&rest[..start]);
+        match value {
+            serde_json::Value::String(value) => key.push_str(&value),
+            other => key.push_str(&other.to_string()),
+        }
+        rest = &rest[end + 1..];
+    }
+    key.push_str(rest);
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_resolve_key_templates() {
+        let lookup = |name: &str| match name {
+            "tenant_id" => Some(serde_json::json!("acme")),
+            "shard" => Some(serde_json::json!(3)),
+            _ => None,
+        };
+        assert_eq!(resolve_key("db-writer", lookup).unwrap(), "db-writer");
+        assert_eq!(resolve_key("crm-{tenant_id}-{shard}", lookup).unwrap(), "crm-acme-3");
+        assert!(resolve_key("crm-{region}", lookup).is_err());
+        assert!(resolve_key("crm-{tenant_id", lookup).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_same_key_is_serialized() {
+        let keys = Arc::new(ConcurrencyKeys::new());
+        let permit = keys.acquire("db-writer").await;
+        assert!(keys.is_held("db-writer"));
+
+        // Other keys are independent
+        drop(keys.acquire("cache-writer").await);
+
+        let waiter = tokio::spawn({
+            let keys = keys.clone();
+            async move { keys.acquire("db-writer").await.key().to_string() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(permit);
+        assert_eq!(waiter.await.unwrap(), "db-writer");
+        assert!(!keys.is_held("db-writer"));
+        assert!(keys.locks.lock().values().all(|lock| lock.strong_count() == 0));
+    }
+}
@@ -0,0 +1,189 @@
+//! Engine-managed node setup and teardown with shared resource pooling.
+
+use crate::error::{GraphError, GraphResult};
+use crate::node::{NodeId, SharedNode};
+use crate::state::State;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+type PooledResource = Arc<dyn Any + Send + Sync>;
+
+/// Expensive resources shared by the nodes of an engine
+///
+/// Nodes fetch clients, connection pools or warmed-up models from the pool in
+/// [`Node::setup_with`](crate::node::Node::setup_with). Each resource is keyed
+/// by its type and a name and initialized once, however many nodes ask for it.
+#[derive(Default)]
+pub struct ResourcePool {
+    resources: parking_lot::Mutex<HashMap<(TypeId, String), Arc<OnceCell<PooledResource>>>>,
+}
+
+impl ResourcePool {
+    /// Create an empty pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the resource named `name`, creating it with `init` if no node has yet
+    ///
+    /// Concurrent callers wait for the same initialization. If `init` fails
+    /// the next caller tries again.
+    pub async fn get_or_init<T, F, Fut>(&self, name: &str, init: F) -> GraphResult<Arc<T>>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = GraphResult<T>>,
+    {
+        let cell = self.resources
+            .lock()
+            .entry((TypeId::of::<T>(), name.to_string()))
+            .or_default()
+            .clone();
+
+        let resource = cell
+            .get_or_try_init(|| async { init().await.map(|value| Arc::new(value) as PooledResource) })
+            .await?
+            .clone();
+        resource.downcast::<T>().map_err(|_| {
+            GraphError::Internal(format!("Pooled resource '{}' has an unexpected type", name))
+        })
+    }
+
+    /// Get the resource named `name` if it has been created
+    pub fn get<T: Send + Sync + 'static>(&self, name: &str) -> Option<Arc<T>> {
+        let cell = self.resources.lock().get(&(TypeId::of::<T>(), name.to_string()))?.clone();
+        cell.get()?.clone().downcast::<T>().ok()
+    }
+
+    /// Number of resources created
+    pub fn len(&self) -> usize {
+        self.resources.lock().values().filter(|cell| cell.initialized()).count()
+    }
+
+    /// Check if no resources have been created
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every pooled resource
+    pub fn clear(&self) {
+        self.resources.lock().clear();
+    }
+}
+
+impl std::fmt::Debug for ResourcePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourcePool")
+            .field("resources", &self.len())
+            .finish()
+    }
+}
+
+/// Tracks which nodes an engine has set up
+///
+/// Nodes are identified by the graph they belong to and their ID, so the same
+/// engine can run several graphs. Setup runs at most once per node until the
+/// graph is torn down.
+#[derive(Debug, Default)]
+pub struct NodeLifecycle {
+    ready: parking_lot::Mutex<HashMap<(Uuid, NodeId), Arc<OnceCell<()>>>>,
+    resources: ResourcePool,
+}
+
+impl NodeLifecycle {
+    /// Create a lifecycle with an empty resource pool
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resources shared by the nodes
+    pub fn resources(&self) -> &ResourcePool {
+        &self.resources
+    }
+
+    /// Whether a node of a graph has been set up
+    pub fn is_ready(&self, graph_id: Uuid, node_id: &NodeId) -> bool {
+        self.ready
+            .lock()
+            .get(&(graph_id, node_id.clone()))
+            .is_some_and(|cell| cell.initialized())
+    }
+
+    /// Set up a node unless it already is
+    pub async fn ensure_setup<S: State>(
+        &self,
+        graph_id: Uuid,
+        node_id: &NodeId,
+        node: &SharedNode<S>,
+    ) -> GraphResult<()> {
+        let cell = self.ready
+            .lock()
+            .entry((graph_id, node_id.clone()))
+            .or_default()
+            .clone();
+
+        cell.get_or_try_init(|| async {
+            tracing::debug!(node_id = %node_id, "Setting up node");
+            node.setup_with(&self.resources).await.map_err(|e| GraphError::node_error(
+                node_id.clone(),
+                format!("Setup failed: {}", e),
+                Some(Box::new(e)),
+            ))
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Forget the nodes of a graph that were set up, returning their IDs
+    pub fn take_ready(&self, graph_id: Uuid) -> Vec<NodeId> {
+        let mut ready = self.ready.lock();
+        let keys: Vec<(Uuid, NodeId)> = ready.keys()
+            .filter(|(graph, _)| *graph == graph_id)
+            .cloned()
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let cell = ready.remove(&key)?;
+                cell.initialized().then_some(key.1)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_resource_initialized_once() {
+        let pool = ResourcePool::new();
+        let inits = AtomicUsize::new(0);
+        let init = || async {
+            inits.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, GraphError>(vec![1.0f32; 4])
+        };
+
+        let (a, b) = tokio::join!(
+            pool.get_or_init::<Vec<f32>, _, _>("embeddings", init),
+            pool.get_or_init::<Vec<f32>, _, _>("embeddings", init),
+        );
+        assert!(Arc::ptr_eq(&a.unwrap(), &b.unwrap()));
+        assert_eq!(inits.load(Ordering::SeqCst), 1);
+
+        // Same name, different type is a different resource
+        assert!(pool.get::<String>("embeddings").is_none());
+        assert_eq!(pool.len(), 1);
+
+        let failed = pool.get_or_init::<String, _, _>("client", || async {
+            Err(GraphError::Internal("unreachable".to_string()))
+        }).await;
+        assert!(failed.is_err());
+        let retried = pool.get_or_init::<String, _, _>("client", || async { Ok("ok".to_string()) }).await;
+        assert_eq!(retried.unwrap().as_str(), "ok");
+    }
+}
@@ -1,13 +1,17 @@
 //! Node definitions and traits for the AgentGraph framework.
 
+pub mod concurrency;
+pub mod lifecycle;
 pub mod traits;
 
-use crate::error::GraphResult;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::tuning::NodeWorkload;
 use crate::state::State;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Unique identifier for a node
@@ -30,8 +34,36 @@ pub struct NodeMetadata {
     pub parallel_safe: bool,
     /// Expected execution time in milliseconds (for scheduling)
     pub expected_duration_ms: Option<u64>,
+    /// Time the node may run before it fails, overriding the graph's limit
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
     /// Resource requirements
     pub resource_requirements: ResourceRequirements,
+    /// Model the node calls, for cost estimates
+    #[serde(default)]
+    pub llm: Option<LlmFootprint>,
+    /// Key serializing this node with every other node declaring it, see [`concurrency`]
+    #[serde(default)]
+    pub concurrency_key: Option<String>,
+    /// Whether the node waits on I/O or computes, see [`tuning`](crate::graph::tuning)
+    #[serde(default)]
+    pub workload: NodeWorkload,
+    /// Cores the node prefers to run on when it is compute-bound; a hint
+    #[serde(default)]
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Concurrency pools the node takes a permit from, e.g. `llm` or `db`,
+    /// see [`ConcurrencyPools`](crate::execution::ConcurrencyPools)
+    #[serde(default)]
+    pub concurrency_pools: Vec<String>,
+}
+
+/// LLM usage a node declares up front
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LlmFootprint {
+    /// Model the node calls
+    pub model: String,
+    /// Completion token limit per call
+    pub max_tokens: Option<u32>,
 }
 
 /// Resource requirements for a node
@@ -57,7 +89,13 @@ impl Default for NodeMetadata {
             version: "1.0.0".to_string(),
             parallel_safe: true,
             expected_duration_ms: None,
+            timeout_ms: None,
             resource_requirements: ResourceRequirements::default(),
+            llm: None,
+            concurrency_key: None,
+            workload: NodeWorkload::Io,
+            cpu_affinity: None,
+            concurrency_pools: Vec::new(),
         }
     }
 }
@@ -106,6 +144,51 @@ impl NodeMetadata {
         self
     }
 
+    /// Fail the node if it runs longer than `timeout`, whatever the graph's limit
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    /// Declare the model the node calls and its completion token limit
+    pub fn with_llm<S: Into<String>>(mut self, model: S, max_tokens: Option<u32>) -> Self {
+        self.llm = Some(LlmFootprint {
+            model: model.into(),
+            max_tokens,
+        });
+        self
+    }
+
+    /// Never run at the same time as other nodes declaring `key`
+    ///
+    /// `{name}` placeholders are filled from the state or the execution's
+    /// custom data, e.g. `"crm-writer-{tenant_id}"` for a per-tenant key.
+    pub fn with_concurrency_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.concurrency_key = Some(key.into());
+        self
+    }
+
+    /// Declare what the node spends its time on
+    pub fn with_workload(mut self, workload: NodeWorkload) -> Self {
+        self.workload = workload;
+        self
+    }
+
+    /// Prefer running on `cores` when the node is compute-bound
+    pub fn with_cpu_affinity(mut self, cores: Vec<usize>) -> Self {
+        self.cpu_affinity = Some(cores);
+        self
+    }
+
+    /// Take a permit from concurrency pool `pool` while running
+    pub fn with_concurrency_pool<S: Into<String>>(mut self, pool: S) -> Self {
+        let pool = pool.into();
+        if !self.concurrency_pools.contains(&pool) {
+            self.concurrency_pools.push(pool);
+        }
+        self
+    }
+
     /// Set custom metadata
     pub fn with_custom<K, V>(mut self, key: K, value: V) -> Self
     where
@@ -138,12 +221,30 @@ where
         Ok(())
     }
 
-    /// Called before the node is executed (setup phase)
+    /// Finish a node that suspended with `GraphError::Suspended`
+    ///
+    /// Called with the output of the operation the node was waiting on.
+    async fn resume(&self, _state: &mut S, _output: serde_json::Value) -> GraphResult<()> {
+        Err(GraphError::execution_error(format!(
+            "Node type {} does not support resuming",
+            self.node_type()
+        )))
+    }
+
+    /// Called once per engine before the node first runs (setup phase)
     async fn setup(&self) -> GraphResult<()> {
         Ok(())
     }
 
-    /// Called after the node is executed (cleanup phase)
+    /// Setup with access to resources shared across the engine's nodes
+    ///
+    /// Override this instead of [`setup`](Self::setup) to share clients or
+    /// warmed-up models between nodes. The default calls `setup`.
+    async fn setup_with(&self, _resources: &lifecycle::ResourcePool) -> GraphResult<()> {
+        self.setup().await
+    }
+
+    /// Called when the engine tears down a graph whose node was set up (cleanup phase)
     async fn cleanup(&self) -> GraphResult<()> {
         Ok(())
     }
@@ -162,8 +263,11 @@ where
 /// A wrapper for boxed nodes to enable dynamic dispatch
 pub type BoxedNode<S> = Box<dyn Node<S>>;
 
+/// A node shared between its registry and the tasks running it
+pub type SharedNode<S> = Arc<dyn Node<S>>;
+
 /// Node execution context with timing and metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct NodeExecutionContext {
     /// Unique execution ID
     pub execution_id: Uuid,
@@ -189,7 +293,7 @@ impl NodeExecutionContext {
         Self {
             execution_id: Uuid::new_v4(),
             node_id,
-            start_time: chrono::Utc::now(),
+            start_time: crate::graph::determinism::now(),
             end_time: None,
             duration_ms: None,
             success: None,
@@ -200,7 +304,7 @@ impl NodeExecutionContext {
 
     /// Mark the execution as completed successfully
     pub fn mark_success(&mut self) {
-        let now = chrono::Utc::now();
+        let now = crate::graph::determinism::now();
         self.end_time = Some(now);
         self.duration_ms = Some((now - self.start_time).num_milliseconds() as u64);
         self.success = Some(true);
@@ -208,7 +312,7 @@ impl NodeExecutionContext {
 
     /// Mark the execution as failed
     pub fn mark_failure<S: Into<String>>(&mut self, error: S) {
-        let now = chrono::Utc::now();
+        let now = crate::graph::determinism::now();
         self.end_time = Some(now);
         self.duration_ms = Some((now - self.start_time).num_milliseconds() as u64);
         self.success = Some(false);
@@ -243,7 +347,7 @@ pub struct NodeRegistry<S>
 where
     S: State,
 {
-    nodes: HashMap<NodeId, BoxedNode<S>>,
+    nodes: HashMap<NodeId, SharedNode<S>>,
     metadata: HashMap<NodeId, NodeMetadata>,
 }
 
@@ -272,13 +376,13 @@ where
         }
 
         let metadata = node.metadata();
-        self.nodes.insert(id.clone(), Box::new(node));
+        self.nodes.insert(id.clone(), Arc::new(node));
         self.metadata.insert(id, metadata);
         Ok(())
     }
 
     /// Get a node by ID
-    pub fn get(&self, id: &NodeId) -> Option<&BoxedNode<S>> {
+    pub fn get(&self, id: &NodeId) -> Option<&SharedNode<S>> {
         self.nodes.get(id)
     }
 
@@ -287,13 +391,18 @@ where
         self.metadata.get(id)
     }
 
+    /// Get mutable node metadata by ID
+    pub fn get_metadata_mut(&mut self, id: &NodeId) -> Option<&mut NodeMetadata> {
+        self.metadata.get_mut(id)
+    }
+
     /// List all registered node IDs
     pub fn list_nodes(&self) -> Vec<&NodeId> {
         self.nodes.keys().collect()
     }
 
     /// Remove a node from the registry
-    pub fn unregister(&mut self, id: &NodeId) -> Option<BoxedNode<S>> {
+    pub fn unregister(&mut self, id: &NodeId) -> Option<SharedNode<S>> {
         self.metadata.remove(id);
         self.nodes.remove(id)
     }
@@ -0,0 +1,190 @@
+//! JSON Schemas of the declarative formats, generated from their Rust types.
+//!
+//! Editors validate and autocomplete files against them: add
+//! `#:schema ./schemas/manifest.schema.json` at the top of `agentgraph.toml`
+//! for TOML extensions such as Even Better TOML, or
+//! `# yaml-language-server: $schema=./schemas/tools.schema.json` to a tools
+//! file for the YAML extension. `agentgraph schema dump` writes every schema.
+
+use crate::config::AppConfig;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::templates::GraphTemplate;
+use crate::manifest::{AppManifest, PersonaManifest};
+use crate::tools::ToolDefinition;
+use crate::visualization::dataset::DatasetExample;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A declarative format with a published schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// Application manifest, `agentgraph.toml`
+    Manifest,
+    /// Graph template file
+    Graph,
+    /// Persona, as under `[personas.<name>]` of a manifest
+    Persona,
+    /// Tools file declaring tools by ID
+    Tools,
+    /// One line of an eval dataset exported as JSONL
+    Dataset,
+    /// Layered configuration file read by `agentgraph config validate`
+    Config,
+}
+
+impl SchemaKind {
+    /// Every published schema
+    pub const ALL: [Self; 6] = [Self::Manifest, Self::Graph, Self::Persona, Self::Tools, Self::Dataset, Self::Config];
+
+    /// Short name, as accepted by `agentgraph schema dump`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Manifest => "manifest",
+            Self::Graph => "graph",
+            Self::Persona => "persona",
+            Self::Tools => "tools",
+            Self::Dataset => "dataset",
+            Self::Config => "config",
+        }
+    }
+
+    /// File the schema is written to
+    pub fn file_name(self) -> String {
+        format!("{}.schema.json", self.name())
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Manifest => "AgentGraph application manifest",
+            Self::Graph => "AgentGraph graph template",
+            Self::Persona => "AgentGraph persona",
+            Self::Tools => "AgentGraph tools file",
+            Self::Dataset => "AgentGraph eval dataset example",
+            Self::Config => "AgentGraph configuration",
+        }
+    }
+
+    /// JSON Schema of the format
+    pub fn schema(self) -> Value {
+        let mut root = match self {
+            Self::Manifest => schemars::schema_for!(AppManifest),
+            Self::Graph => schemars::schema_for!(GraphTemplate),
+            Self::Persona => schemars::schema_for!(PersonaManifest),
+            Self::Tools => schemars::schema_for!(BTreeMap<String, ToolDefinition>),
+            Self::Dataset => schemars::schema_for!(DatasetExample),
+            Self::Config => schemars::schema_for!(AppConfig),
+        };
+        let metadata = root.schema.metadata();
+        metadata.id = Some(format!("agent_graph/schemas/{}", self.file_name()));
+        metadata.title = Some(self.title().to_string());
+        serde_json::to_value(root).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for SchemaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SchemaKind {
+    type Err = GraphError;
+
+    fn from_str(name: &str) -> GraphResult<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name).ok_or_else(|| {
+            GraphError::validation_error(format!(
+                "Unknown schema '{}' (available: {})",
+                name,
+                Self::ALL.map(Self::name).join(", ")
+            ))
+        })
+    }
+}
+
+/// Write the schemas of `kinds` to `dir` as `<name>.schema.json`, returning the files written
+pub fn write_schemas(dir: &Path, kinds: &[SchemaKind]) -> GraphResult<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    kinds
+        .iter()
+        .map(|kind| {
+            let path = dir.join(kind.file_name());
+            let mut json = serde_json::to_string_pretty(&kind.schema())?;
+            json.push('\n');
+            std::fs::write(&path, json)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonschema::JSONSchema;
+
+    fn validates(kind: SchemaKind, document: &Value) -> bool {
+        JSONSchema::compile(&kind.schema()).unwrap().is_valid(document)
+    }
+
+    #[test]
+    fn test_schemas_accept_declarative_files() {
+        for kind in SchemaKind::ALL {
+            let schema = kind.schema();
+            assert_eq!(schema["$id"], format!("agent_graph/schemas/{}.schema.json", kind));
+            assert_eq!(kind.name().parse::<SchemaKind>().unwrap(), kind);
+        }
+        assert!("manifests".parse::<SchemaKind>().is_err());
+
+        let manifest: toml::Value = toml::from_str(r#"
+            [app]
+            name = "support"
+            default_provider = "openai"
+
+            [providers.openai]
+            api_key_env = "OPENAI_API_KEY"
+
+            [tools.declared.get_weather]
+            kind = "http"
+            description = "Current weather for a city"
+            url = "https://api.weather.test/v1/current?city={city}"
+
+            [graphs.digest.topology]
+            kind = "map_reduce"
+            parallelism = 2
+
+            [graphs.digest.execution]
+            history = "metadata"
+
+            [environments.prod.providers.openai]
+            default_model = "gpt-4o"
+        "#).unwrap();
+        let manifest = serde_json::to_value(manifest).unwrap();
+        assert!(validates(SchemaKind::Manifest, &manifest));
+
+        let mut typo = manifest.clone();
+        typo["app"]["nmae"] = Value::from("typo");
+        assert!(!validates(SchemaKind::Manifest, &typo));
+        let mut bad_history = manifest;
+        bad_history["graphs"]["digest"]["execution"]["history"] = Value::from("everything");
+        assert!(!validates(SchemaKind::Manifest, &bad_history));
+
+        let tools = serde_json::json!({
+            "list_files": { "kind": "shell", "description": "List files", "command": ["ls"] },
+        });
+        assert!(validates(SchemaKind::Tools, &tools));
+        assert!(!validates(SchemaKind::Tools, &serde_json::json!({ "list_files": { "kind": "ftp" } })));
+    }
+
+    #[test]
+    fn test_write_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let written = write_schemas(dir.path(), &SchemaKind::ALL).unwrap();
+        assert_eq!(written.len(), SchemaKind::ALL.len());
+
+        let graph: Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("graph.schema.json")).unwrap()).unwrap();
+        assert_eq!(graph["title"], "AgentGraph graph template");
+        assert!(graph["required"].as_array().unwrap().contains(&Value::from("topology")));
+    }
+}
@@ -0,0 +1,592 @@
+//! Storage for binary outputs of executions: PDFs, images, CSVs and the like.
+//!
+//! Nodes and tools save what they produce in an [`ArtifactStore`] and put the
+//! returned [`ArtifactRef`] in the state. Artifacts are grouped by execution so
+//! the Studio can list and serve everything an execution produced.
+
+use crate::error::{GraphError, GraphResult};
+use crate::{hex, sha256_hex};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Execution ID used for artifacts saved outside of any execution
+pub const UNATTACHED_EXECUTION: &str = "unattached";
+
+/// Typed reference to a stored artifact, kept in the state in place of its bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactRef {
+    /// Artifact ID, unique within its execution
+    pub id: String,
+    /// Execution that produced the artifact
+    pub execution_id: String,
+    /// File name, e.g. `report.pdf`
+    pub name: String,
+    /// MIME type the artifact is served with
+    pub content_type: String,
+    /// Size in bytes
+    pub size_bytes: u64,
+    /// Hex SHA-256 of the content
+    pub sha256: String,
+    /// When the artifact was saved
+    pub created_at: DateTime<Utc>,
+}
+
+impl ArtifactRef {
+    /// Describe new content; the content type is guessed from `name` if not given
+    pub fn new(execution_id: &str, name: &str, content_type: Option<&str>, bytes: &[u8]) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            execution_id: execution_id.to_string(),
+            name: name.to_string(),
+            content_type: content_type.map_or_else(|| content_type_for(name).to_string(), str::to_string),
+            size_bytes: bytes.len() as u64,
+            sha256: sha256_hex(bytes),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// MIME type for a file name, by extension
+pub fn content_type_for(name: &str) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("pdf") => "application/pdf",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("csv") => "text/csv",
+        Some("json") => "application/json",
+        Some("txt" | "log") => "text/plain",
+        Some("md") => "text/markdown",
+        Some("html" | "htm") => "text/html",
+        Some("xml") => "application/xml",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Storage for artifacts, grouped by execution
+#[async_trait]
+pub trait ArtifactStore: Send + Sync + fmt::Debug {
+    /// Name of the backend, for logs and errors
+    fn backend(&self) -> &'static str;
+
+    /// Store the content of an artifact described by `artifact`
+    async fn put(&self, artifact: &ArtifactRef, bytes: Vec<u8>) -> GraphResult<()>;
+
+    /// An artifact and its content
+    async fn get(&self, execution_id: &str, artifact_id: &str) -> GraphResult<Option<(ArtifactRef, Vec<u8>)>>;
+
+    /// Artifacts of an execution, oldest first
+    async fn list(&self, execution_id: &str) -> GraphResult<Vec<ArtifactRef>>;
+
+    /// Delete an artifact; returns whether it existed
+    async fn delete(&self, execution_id: &str, artifact_id: &str) -> GraphResult<bool>;
+}
+
+impl dyn ArtifactStore {
+    /// Save content for the execution the caller runs in
+    ///
+    /// Outside an execution the artifact is filed under [`UNATTACHED_EXECUTION`].
+    pub async fn save(&self, name: &str, content_type: Option<&str>, bytes: Vec<u8>) -> GraphResult<ArtifactRef> {
+        let execution_id = crate::graph::engine::current_execution_id()
+            .map_or_else(|| UNATTACHED_EXECUTION.to_string(), |id| id.to_string());
+        self.save_for(&execution_id, name, content_type, bytes).await
+    }
+
+    /// Save content for a given execution
+    pub async fn save_for(
+        &self,
+        execution_id: &str,
+        name: &str,
+        content_type: Option<&str>,
+        bytes: Vec<u8>,
+    ) -> GraphResult<ArtifactRef> {
+        check_segment(execution_id)?;
+        let artifact = ArtifactRef::new(execution_id, name, content_type, &bytes);
+        self.put(&artifact, bytes).await?;
+        Ok(artifact)
+    }
+
+    /// Content of a referenced artifact
+    pub async fn load(&self, artifact: &ArtifactRef) -> GraphResult<Vec<u8>> {
+        let (_, bytes) = self.get(&artifact.execution_id, &artifact.id).await?.ok_or_else(|| {
+            GraphError::state_error(format!(
+                "Artifact '{}' ({}) is missing from the {} artifact store",
+                artifact.name,
+                artifact.id,
+                self.backend()
+            ))
+        })?;
+        Ok(bytes)
+    }
+}
+
+/// Reject IDs that would escape their directory or prefix
+fn check_segment(segment: &str) -> GraphResult<()> {
+    let valid = !segment.is_empty()
+        && !segment.starts_with('.')
+        && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(GraphError::validation_error(format!("Invalid artifact or execution ID '{}'", segment)))
+    }
+}
+
+/// Artifacts kept in a local directory, one subdirectory per execution
+#[derive(Debug, Clone)]
+pub struct LocalArtifactStore {
+    dir: PathBuf,
+}
+
+impl LocalArtifactStore {
+    /// Store artifacts under `dir`, created on first use
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    fn paths(&self, execution_id: &str, artifact_id: &str) -> GraphResult<(PathBuf, PathBuf)> {
+        check_segment(execution_id)?;
+        check_segment(artifact_id)?;
+        let dir = self.dir.join(execution_id);
+        Ok((dir.join(format!("{}.json", artifact_id)), dir.join(format!("{}.bin", artifact_id))))
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for LocalArtifactStore {
+    fn backend(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(&self, artifact: &ArtifactRef, bytes: Vec<u8>) -> GraphResult<()> {
+        let (meta_path, data_path) = self.paths(&artifact.execution_id, &artifact.id)?;
+        fs::create_dir_all(self.dir.join(&artifact.execution_id)).await?;
+        fs::write(&data_path, bytes).await?;
+        // Metadata last, so listed artifacts always have their content
+        fs::write(&meta_path, serde_json::to_vec_pretty(artifact)?).await?;
+        Ok(())
+    }
+
+    async fn get(&self, execution_id: &str, artifact_id: &str) -> GraphResult<Option<(ArtifactRef, Vec<u8>)>> {
+        let (meta_path, data_path) = self.paths(execution_id, artifact_id)?;
+        if !meta_path.exists() {
+            return Ok(None);
+        }
+        let artifact = serde_json::from_slice(&fs::read(&meta_path).await?)?;
+        Ok(Some((artifact, fs::read(&data_path).await?)))
+    }
+
+    async fn list(&self, execution_id: &str) -> GraphResult<Vec<ArtifactRef>> {
+        check_segment(execution_id)?;
+        let dir = self.dir.join(execution_id);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut artifacts = Vec::new();
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                artifacts.push(serde_json::from_slice::<ArtifactRef>(&fs::read(&path).await?)?);
+            }
+        }
+        artifacts.sort_by_key(|artifact| artifact.created_at);
+        Ok(artifacts)
+    }
+
+    async fn delete(&self, execution_id: &str, artifact_id: &str) -> GraphResult<bool> {
+        let (meta_path, data_path) = self.paths(execution_id, artifact_id)?;
+        if !meta_path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&meta_path).await?;
+        if data_path.exists() {
+            fs::remove_file(&data_path).await?;
+        }
+        Ok(true)
+    }
+}
+
+/// Connection settings of an S3 bucket
+#[derive(Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    /// Endpoint URL, e.g. `https://s3.eu-west-1.amazonaws.com` or a MinIO server
+    pub endpoint: String,
+    /// AWS region the bucket is in
+    pub region: String,
+    /// Bucket name
+    pub bucket: String,
+    /// Key prefix for the artifacts
+    #[serde(default)]
+    pub prefix: String,
+    /// Access key ID
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+    /// Session token of temporary credentials
+    #[serde(default)]
+    pub session_token: Option<String>,
+}
+
+impl fmt::Debug for S3Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Config")
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Config {
+    /// Bucket on AWS, with credentials from `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`
+    pub fn from_env(region: &str, bucket: &str) -> GraphResult<Self> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| GraphError::ConfigurationError(format!("{} is not set", name)))
+        };
+        Ok(Self {
+            endpoint: format!("https://s3.{}.amazonaws.com", region),
+            region: region.to_string(),
+            bucket: bucket.to_string(),
+            prefix: String::new(),
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+/// Artifacts kept in an S3 bucket or an S3-compatible store
+///
+/// Each artifact is stored as two objects under
+/// `{prefix}/{execution_id}/`: its content, uploaded with its content type,
+/// and a JSON description used for listing. Requests are signed with AWS
+/// Signature Version 4 and use path-style URLs, which MinIO and most other
+/// S3-compatible stores accept.
+#[derive(Debug, Clone)]
+pub struct S3ArtifactStore {
+    config: S3Config,
+    client: reqwest::Client,
+}
+
+impl S3ArtifactStore {
+    /// Create a store for a bucket
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn object_key(&self, execution_id: &str, name: &str) -> String {
+        let prefix = self.config.prefix.trim_matches('/');
+        if prefix.is_empty() {
+            format!("{}/{}", execution_id, name)
+        } else {
+            format!("{}/{}/{}", prefix, execution_id, name)
+        }
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: Option<&str>,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> GraphResult<reqwest::Response> {
+        let mut path = format!("/{}", self.config.bucket);
+        if let Some(key) = key {
+            path.push('/');
+            path.push_str(&uri_encode(key, false));
+        }
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(name, value)| (uri_encode(name, true), uri_encode(value, true)))
+            .collect();
+        query.sort();
+        let query = query.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&");
+
+        let endpoint = self.config.endpoint.trim_end_matches('/');
+        let url = if query.is_empty() { format!("{}{}", endpoint, path) } else { format!("{}{}?{}", endpoint, path, query) };
+        let parsed = reqwest::Url::parse(&url)
+            .map_err(|e| GraphError::ConfigurationError(format!("Invalid S3 endpoint '{}': {}", endpoint, e)))?;
+        let host = match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(GraphError::ConfigurationError(format!("S3 endpoint '{}' has no host", endpoint))),
+        };
+
+        let now = Utc::now();
+        let signature = sign_v4(&self.config, method.as_str(), &host, &path, &query, &body, now);
+        let mut request = self.client
+            .request(method, parsed)
+            .header("x-amz-date", signature.amz_date)
+            .header("x-amz-content-sha256", signature.payload_hash)
+            .header("authorization", signature.authorization);
+        if let Some(token) = &self.config.session_token {
+            request = request.header("x-amz-security-token", token);
+        }
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        let response = request.body(body).send().await.map_err(|e| s3_error(&e.to_string()))?;
+        Ok(response)
+    }
+
+    pub(crate) async fn get_object(&self, key: &str) -> GraphResult<Option<Vec<u8>>> {
+        let response = self.send(reqwest::Method::GET, Some(key), &[], Vec::new(), None).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check_status(response).await?;
+        Ok(Some(response.bytes().await.map_err(|e| s3_error(&e.to_string()))?.to_vec()))
+    }
+
+    async fn put_object(&self, key: &str, body: Vec<u8>, content_type: &str) -> GraphResult<()> {
+        let response = self.send(reqwest::Method::PUT, Some(key), &[], body, Some(content_type)).await?;
+        check_status(response).await.map(|_| ())
+    }
+
+    async fn delete_object(&self, key: &str) -> GraphResult<()> {
+        let response = self.send(reqwest::Method::DELETE, Some(key), &[], Vec::new(), None).await?;
+        check_status(response).await.map(|_| ())
+    }
+
+    /// Keys under a prefix, following continuation tokens
+    async fn list_keys(&self, prefix: &str) -> GraphResult<Vec<String>> {
+        Ok(self.list_objects(prefix).await?.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// Keys and ETags under a prefix, following continuation tokens
+    pub(crate) async fn list_objects(&self, prefix: &str) -> GraphResult<Vec<(String, String)>> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let response = self.send(reqwest::Method::GET, None, &query, Vec::new(), None).await?;
+            let body = check_status(response).await?.text().await.map_err(|e| s3_error(&e.to_string()))?;
+            objects.extend(xml_values(&body, "Key").into_iter().zip(xml_values(&body, "ETag")));
+            token = xml_values(&body, "NextContinuationToken").into_iter().next();
+            if token.is_none() {
+                return Ok(objects);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for S3ArtifactStore {
+    fn backend(&self) -> &'static str {
+        "S3"
+    }
+
+    async fn put(&self, artifact: &ArtifactRef, bytes: Vec<u8>) -> GraphResult<()> {
+        check_segment(&artifact.execution_id)?;
+        check_segment(&artifact.id)?;
+        self.put_object(&self.object_key(&artifact.execution_id, &artifact.id), bytes, &artifact.content_type).await?;
+        let meta_key = self.object_key(&artifact.execution_id, &format!("{}.json", artifact.id));
+        self.put_object(&meta_key, serde_json::to_vec(artifact)?, "application/json").await
+    }
+
+    async fn get(&self, execution_id: &str, artifact_id: &str) -> GraphResult<Option<(ArtifactRef, Vec<u8>)>> {
+        check_segment(execution_id)?;
+        check_segment(artifact_id)?;
+        let meta_key = self.object_key(execution_id, &format!("{}.json", artifact_id));
+        let Some(meta) = self.get_object(&meta_key).await? else {
+            return Ok(None);
+        };
+        let artifact = serde_json::from_slice(&meta)?;
+        Ok(self.get_object(&self.object_key(execution_id, artifact_id)).await?.map(|bytes| (artifact, bytes)))
+    }
+
+    async fn list(&self, execution_id: &str) -> GraphResult<Vec<ArtifactRef>> {
+        check_segment(execution_id)?;
+        let mut artifacts = Vec::new();
+        for key in self.list_keys(&self.object_key(execution_id, "")).await? {
+            if key.ends_with(".json") {
+                if let Some(meta) = self.get_object(&key).await? {
+                    artifacts.push(serde_json::from_slice::<ArtifactRef>(&meta)?);
+                }
+            }
+        }
+        artifacts.sort_by_key(|artifact| artifact.created_at);
+        Ok(artifacts)
+    }
+
+    async fn delete(&self, execution_id: &str, artifact_id: &str) -> GraphResult<bool> {
+        check_segment(execution_id)?;
+        check_segment(artifact_id)?;
+        let meta_key = self.object_key(execution_id, &format!("{}.json", artifact_id));
+        if self.get_object(&meta_key).await?.is_none() {
+            return Ok(false);
+        }
+        self.delete_object(&self.object_key(execution_id, artifact_id)).await?;
+        self.delete_object(&meta_key).await?;
+        Ok(true)
+    }
+}
+
+fn s3_error(message: &str) -> GraphError {
+    GraphError::ExternalServiceError(format!("S3 artifact store: {}", message))
+}
+
+async fn check_status(response: reqwest::Response) -> GraphResult<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let code = xml_values(&body, "Code").into_iter().next().unwrap_or_default();
+    Err(s3_error(&format!("{} {}", status, code)))
+}
+
+/// Text of every `<tag>` element; enough for S3's flat list responses
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else { break };
+        values.push(
+            after[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &after[end + close.len()..];
+    }
+    values
+}
+
+/// Percent-encode as SigV4 requires; `/` is kept in paths
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+struct Signature {
+    amz_date: String,
+    payload_hash: String,
+    authorization: String,
+}
+
+/// AWS Signature Version 4 for an S3 request
+fn sign_v4(
+    config: &S3Config,
+    method: &str,
+    host: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Signature {
+    use hmac::{Hmac, Mac};
+
+    let hmac = |key: &[u8], data: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    };
+
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = sha256_hex(body);
+
+    let mut headers = vec![
+        ("host", host.to_string()),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &config.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method, path, query, canonical_headers, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical_request)
+    );
+
+    let key = hmac(format!("AWS4{}", config.secret_access_key).as_bytes(), &date);
+    let key = hmac(&key, &config.region);
+    let key = hmac(&key, "s3");
+    let key = hmac(&key, "aws4_request");
+    let signature = hex(&hmac(&key, &string_to_sign));
+
+    Signature {
+        amz_date,
+        payload_hash,
+        authorization: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            config.access_key_id, scope, signed_headers, signature
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_local_store_groups_artifacts_by_execution() {
+        let dir = std::env::temp_dir().join(format!("agentgraph-artifacts-{}", uuid::Uuid::new_v4()));
+        let store: Arc<dyn ArtifactStore> = Arc::new(LocalArtifactStore::new(&dir));
+
+        let report = store.save_for("exec-1", "report.PDF", None, b"%PDF-1.7".to_vec()).await.unwrap();
+        let table = store.save_for("exec-1", "rows", Some("text/csv"), b"a,b\n1,2\n".to_vec()).await.unwrap();
+        store.save_for("exec-2", "chart.png", None, vec![0x89, b'P', b'N', b'G']).await.unwrap();
+        assert_eq!(report.content_type, "application/pdf");
+        assert_eq!(table.content_type, "text/csv");
+        assert_eq!(report.size_bytes, 8);
+
+        let listed = store.list("exec-1").await.unwrap();
+        assert_eq!(listed, vec![report.clone(), table.clone()]);
+        assert_eq!(store.load(&report).await.unwrap(), b"%PDF-1.7");
+
+        assert!(store.delete("exec-1", &report.id).await.unwrap());
+        assert!(store.load(&report).await.is_err());
+        assert!(store.list("../exec-2").await.is_err());
+
+        // Outside an execution artifacts are filed apart
+        let loose = store.save("notes.txt", None, b"hi".to_vec()).await.unwrap();
+        assert_eq!(loose.execution_id, UNATTACHED_EXECUTION);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
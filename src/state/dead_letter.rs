@@ -0,0 +1,227 @@
+//! Dead-letter queue for executions that failed permanently.
+
+use crate::error::{GraphError, GraphResult};
+use crate::node::NodeId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Directory the CLI looks in when no dead-letter directory is given
+pub const DEFAULT_DEAD_LETTER_DIR: &str = ".agentgraph/dlq";
+
+/// What should happen to a dead-lettered execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterStatus {
+    /// Waiting for someone to look at it
+    Pending,
+    /// Marked for the next redrive
+    RetryRequested,
+}
+
+/// A failed execution with everything needed to inspect and rerun it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// ID of the execution that first failed
+    pub id: String,
+    /// Name of the graph that was running
+    pub graph: String,
+    /// When the execution last failed
+    pub failed_at: DateTime<Utc>,
+    /// Node running when it failed
+    pub node_id: Option<NodeId>,
+    /// Step it failed at
+    pub step: u64,
+    /// Nodes run before the failure
+    pub execution_path: Vec<NodeId>,
+    /// The error followed by its sources, outermost first
+    pub errors: Vec<String>,
+    /// State the execution started from
+    pub input_state: serde_json::Value,
+    /// State when it failed
+    pub failed_state: serde_json::Value,
+    /// Runs so far, including the first
+    pub attempts: u32,
+    /// What should happen next
+    pub status: DeadLetterStatus,
+}
+
+impl DeadLetter {
+    /// Error message followed by the messages of its sources
+    pub fn error_chain(error: &(dyn std::error::Error + 'static)) -> Vec<String> {
+        let mut chain = vec![error.to_string()];
+        let mut source = error.source();
+        while let Some(error) = source {
+            chain.push(error.to_string());
+            source = error.source();
+        }
+        chain
+    }
+}
+
+/// Storage for dead letters
+#[async_trait]
+pub trait DeadLetterQueue: Send + Sync + std::fmt::Debug {
+    /// Add a letter, replacing any with the same ID
+    async fn put(&self, letter: &DeadLetter) -> GraphResult<()>;
+
+    /// Get a letter by ID
+    async fn get(&self, id: &str) -> GraphResult<Option<DeadLetter>>;
+
+    /// All letters, oldest failure first
+    async fn list(&self) -> GraphResult<Vec<DeadLetter>>;
+
+    /// Remove a letter; returns whether it existed
+    async fn remove(&self, id: &str) -> GraphResult<bool>;
+
+    /// Mark a letter for the next redrive
+    async fn request_retry(&self, id: &str) -> GraphResult<DeadLetter> {
+        let mut letter = self.get(id).await?
+            .ok_or_else(|| GraphError::validation_error(format!("No dead letter '{}'", id)))?;
+        letter.status = DeadLetterStatus::RetryRequested;
+        self.put(&letter).await?;
+        Ok(letter)
+    }
+}
+
+/// Dead letters kept as one JSON file each, so they survive restarts
+#[derive(Debug, Clone)]
+pub struct FileDeadLetterQueue {
+    dir: PathBuf,
+}
+
+impl FileDeadLetterQueue {
+    /// Store letters in `dir`, created on first use
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    fn path(&self, id: &str) -> GraphResult<PathBuf> {
+        if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+            return Err(GraphError::validation_error(format!("Invalid dead letter ID '{}'", id)));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueue for FileDeadLetterQueue {
+    async fn put(&self, letter: &DeadLetter) -> GraphResult<()> {
+        let path = self.path(&letter.id)?;
+        fs::create_dir_all(&self.dir).await?;
+        fs::write(&path, serde_json::to_string_pretty(letter)?).await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> GraphResult<Option<DeadLetter>> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(&path).await?)?))
+    }
+
+    async fn list(&self) -> GraphResult<Vec<DeadLetter>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut letters = Vec::new();
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                letters.push(serde_json::from_str::<DeadLetter>(&fs::read_to_string(&path).await?)?);
+            }
+        }
+        letters.sort_by_key(|letter| letter.failed_at);
+        Ok(letters)
+    }
+
+    async fn remove(&self, id: &str) -> GraphResult<bool> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&path).await?;
+        Ok(true)
+    }
+}
+
+/// In-memory dead letters, for tests and short-lived processes
+#[derive(Debug, Default)]
+pub struct MemoryDeadLetterQueue {
+    letters: parking_lot::RwLock<HashMap<String, DeadLetter>>,
+}
+
+impl MemoryDeadLetterQueue {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueue for MemoryDeadLetterQueue {
+    async fn put(&self, letter: &DeadLetter) -> GraphResult<()> {
+        self.letters.write().insert(letter.id.clone(), letter.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> GraphResult<Option<DeadLetter>> {
+        Ok(self.letters.read().get(id).cloned())
+    }
+
+    async fn list(&self) -> GraphResult<Vec<DeadLetter>> {
+        let mut letters: Vec<_> = self.letters.read().values().cloned().collect();
+        letters.sort_by_key(|letter| letter.failed_at);
+        Ok(letters)
+    }
+
+    async fn remove(&self, id: &str) -> GraphResult<bool> {
+        Ok(self.letters.write().remove(id).is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(id: &str) -> DeadLetter {
+        DeadLetter {
+            id: id.to_string(),
+            graph: "billing".to_string(),
+            failed_at: Utc::now(),
+            node_id: Some("charge".to_string()),
+            step: 2,
+            execution_path: vec!["load".to_string(), "charge".to_string()],
+            errors: vec!["card declined".to_string()],
+            input_state: serde_json::json!({"amount": 10}),
+            failed_state: serde_json::json!({"amount": 10, "loaded": true}),
+            attempts: 1,
+            status: DeadLetterStatus::Pending,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_queue_survives_reopening() {
+        let dir = std::env::temp_dir().join(format!("agentgraph-dlq-{}", uuid::Uuid::new_v4()));
+        let queue = FileDeadLetterQueue::new(&dir);
+        queue.put(&letter("a")).await.unwrap();
+        queue.put(&letter("b")).await.unwrap();
+        queue.request_retry("b").await.unwrap();
+
+        let reopened = FileDeadLetterQueue::new(&dir);
+        let letters = reopened.list().await.unwrap();
+        assert_eq!(letters.len(), 2);
+        assert_eq!(reopened.get("b").await.unwrap().unwrap().status, DeadLetterStatus::RetryRequested);
+        assert_eq!(reopened.get("a").await.unwrap().unwrap(), letters[0]);
+
+        assert!(reopened.remove("a").await.unwrap());
+        assert!(!reopened.remove("a").await.unwrap());
+        assert!(reopened.get("../a").await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,482 @@
+//! Execution leases and node-completion records for instances sharing a store.
+
+use crate::error::{GraphError, GraphResult};
+use crate::node::NodeId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs;
+use uuid::Uuid;
+
+/// How long a lease lasts unless renewed
+const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(30);
+
+/// Lock files older than this are left over from a crashed instance
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+/// Which instance owns an execution, and until when
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    /// Execution leased
+    pub execution_id: Uuid,
+    /// Instance holding the lease
+    pub owner: String,
+    /// When the lease lapses unless renewed
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Lease {
+    /// Whether the lease has lapsed at `now`
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// A node run recorded as done, with the state it left behind
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeCompletion {
+    /// Execution the node ran in
+    pub execution_id: Uuid,
+    /// Step the node ran at
+    pub step: u64,
+    /// Node that completed
+    pub node_id: NodeId,
+    /// Instance that ran it
+    pub owner: String,
+    /// State after the node
+    pub state: serde_json::Value,
+    /// When it completed
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Storage for leases and completion records, shared by every instance
+#[async_trait]
+pub trait LeaseStore: Send + Sync + std::fmt::Debug {
+    /// Take the lease on an execution for `owner`, or extend it if `owner` holds it
+    ///
+    /// Returns the lease held afterwards, which belongs to someone else if
+    /// their lease has not expired.
+    async fn acquire(&self, execution_id: Uuid, owner: &str, ttl: Duration) -> GraphResult<Lease>;
+
+    /// Give up the lease on an execution, if `owner` holds it
+    async fn release(&self, execution_id: Uuid, owner: &str) -> GraphResult<()>;
+
+    /// Current lease on an execution, expired or not
+    async fn lease(&self, execution_id: Uuid) -> GraphResult<Option<Lease>>;
+
+    /// Record a node as completed, unless it already was at that step
+    ///
+    /// Returns the record kept, which is the earlier one if there was one.
+    async fn complete(&self, completion: NodeCompletion) -> GraphResult<NodeCompletion>;
+
+    /// Completion of `node_id` at `step` of an execution, if recorded
+    async fn completion(&self, execution_id: Uuid, step: u64, node_id: &str) -> GraphResult<Option<NodeCompletion>>;
+
+    /// Drop an execution's completion records once it has finished
+    async fn forget(&self, execution_id: Uuid) -> GraphResult<()>;
+}
+
+/// Leases and completions in memory, for tests and instances sharing a process
+#[derive(Debug, Default)]
+pub struct MemoryLeaseStore {
+    leases: parking_lot::Mutex<HashMap<Uuid, Lease>>,
+    completions: parking_lot::RwLock<HashMap<(Uuid, u64, NodeId), NodeCompletion>>,
+}
+
+impl MemoryLeaseStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LeaseStore for MemoryLeaseStore {
+    async fn acquire(&self, execution_id: Uuid, owner: &str, ttl: Duration) -> GraphResult<Lease> {
+        let now = Utc::now();
+        let mut leases = self.leases.lock();
+        match leases.get(&execution_id) {
+            Some(lease) if lease.owner != owner && !lease.is_expired(now) => Ok(lease.clone()),
+            _ => {
+                let lease = Lease { execution_id, owner: owner.to_string(), expires_at: expiry(now, ttl) };
+                leases.insert(execution_id, lease.clone());
+                Ok(lease)
+            }
+        }
+    }
+
+    async fn release(&self, execution_id: Uuid, owner: &str) -> GraphResult<()> {
+        let mut leases = self.leases.lock();
+        if leases.get(&execution_id).is_some_and(|lease| lease.owner == owner) {
+            leases.remove(&execution_id);
+        }
+        Ok(())
+    }
+
+    async fn lease(&self, execution_id: Uuid) -> GraphResult<Option<Lease>> {
+        Ok(self.leases.lock().get(&execution_id).cloned())
+    }
+
+    async fn complete(&self, completion: NodeCompletion) -> GraphResult<NodeCompletion> {
+        let key = (completion.execution_id, completion.step, completion.node_id.clone());
+        Ok(self.completions.write().entry(key).or_insert(completion).clone())
+    }
+
+    async fn completion(&self, execution_id: Uuid, step: u64, node_id: &str) -> GraphResult<Option<NodeCompletion>> {
+        Ok(self.completions.read().get(&(execution_id, step, node_id.to_string())).cloned())
+    }
+
+    async fn forget(&self, execution_id: Uuid) -> GraphResult<()> {
+        self.completions.write().retain(|(id, _, _), _| *id != execution_id);
+        Ok(())
+    }
+}
+
+/// Leases and completions as JSON files in a directory shared by every instance, e.g. a network volume
+///
+/// Leases are changed under a lock file created exclusively, and completion
+/// records are created exclusively, so the first instance to write wins.
+#[derive(Debug, Clone)]
+pub struct FileLeaseStore {
+    dir: PathBuf,
+}
+
+impl FileLeaseStore {
+    /// Store leases and completions under `dir`, created on first use
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self { dir: dir.as_ref().to_path_buf() }
+    }
+
+    fn lease_path(&self, execution_id: Uuid) -> PathBuf {
+        self.dir.join("leases").join(format!("{}.json", execution_id))
+    }
+
+    fn completions_dir(&self, execution_id: Uuid) -> PathBuf {
+        self.dir.join("completions").join(execution_id.to_string())
+    }
+
+    fn completion_path(&self, execution_id: Uuid, step: u64, node_id: &str) -> PathBuf {
+        self.completions_dir(execution_id)
+            .join(format!("{}-{}.json", step, utf8_percent_encode(node_id, NON_ALPHANUMERIC)))
+    }
+
+    /// A scratch path next to `path`, renamed or linked into place once written
+    fn scratch_path(path: &Path) -> PathBuf {
+        path.with_extension(format!("{}.tmp", Uuid::new_v4()))
+    }
+
+    async fn read_lease(&self, execution_id: Uuid) -> GraphResult<Option<Lease>> {
+        match fs::read_to_string(self.lease_path(execution_id)).await {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Run `change` on an execution's lease while holding its lock file
+    async fn with_lock<T>(
+        &self,
+        execution_id: Uuid,
+        change: impl FnOnce(Option<Lease>) -> (Option<Lease>, T),
+    ) -> GraphResult<T> {
+        let dir = self.dir.join("leases");
+        fs::create_dir_all(&dir).await?;
+        let lock = dir.join(format!("{}.lock", execution_id));
+        let mut attempts = 0;
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&lock).await {
+                Ok(_) => break,
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&lock).await.ok()
+                        .and_then(|metadata| metadata.modified().ok())
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK);
+                    if stale {
+                        let _ = fs::remove_file(&lock).await;
+                        continue;
+                    }
+                    attempts += 1;
+                    if attempts > 200 {
+                        return Err(GraphError::ConcurrencyError(format!("Lease on execution {} stayed locked", execution_id)));
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        let result = async {
+            let current = self.read_lease(execution_id).await?;
+            let unchanged = current.clone();
+            let (next, output) = change(current);
+            if next != unchanged {
+                let path = self.lease_path(execution_id);
+                match &next {
+                    // Renamed into place, so a reader never sees half a lease
+                    Some(lease) => {
+                        let scratch = Self::scratch_path(&path);
+                        fs::write(&scratch, serde_json::to_string_pretty(lease)?).await?;
+                        if let Err(error) = fs::rename(&scratch, &path).await {
+                            let _ = fs::remove_file(&scratch).await;
+                            return Err(error.into());
+                        }
+                    }
+                    None => fs::remove_file(&path).await?,
+                }
+            }
+            Ok(output)
+        }.await;
+        let _ = fs::remove_file(&lock).await;
+        result
+    }
+}
+
+#[async_trait]
+impl LeaseStore for FileLeaseStore {
+    async fn acquire(&self, execution_id: Uuid, owner: &str, ttl: Duration) -> GraphResult<Lease> {
+        let now = Utc::now();
+        self.with_lock(execution_id, |current| match current {
+            Some(lease) if lease.owner != owner && !lease.is_expired(now) => (Some(lease.clone()), lease),
+            _ => {
+                let lease = Lease { execution_id, owner: owner.to_string(), expires_at: expiry(now, ttl) };
+                (Some(lease.clone()), lease)
+            }
+        }).await
+    }
+
+    async fn release(&self, execution_id: Uuid, owner: &str) -> GraphResult<()> {
+        self.with_lock(execution_id, |current| match current {
+            Some(lease) if lease.owner == owner => (None, ()),
+            other => (other, ()),
+        }).await
+    }
+
+    async fn lease(&self, execution_id: Uuid) -> GraphResult<Option<Lease>> {
+        self.read_lease(execution_id).await
+    }
+
+    async fn complete(&self, completion: NodeCompletion) -> GraphResult<NodeCompletion> {
+        fs::create_dir_all(self.completions_dir(completion.execution_id)).await?;
+        let path = self.completion_path(completion.execution_id, completion.step, &completion.node_id);
+        // Written in full first, then linked into place: a reader never sees a
+        // partial record, and linking fails if another completion got there first
+        let scratch = Self::scratch_path(&path);
+        fs::write(&scratch, serde_json::to_string_pretty(&completion)?).await?;
+        let linked = fs::hard_link(&scratch, &path).await;
+        let _ = fs::remove_file(&scratch).await;
+        match linked {
+            Ok(()) => Ok(completion),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                Ok(serde_json::from_str(&fs::read_to_string(&path).await?)?)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn completion(&self, execution_id: Uuid, step: u64, node_id: &str) -> GraphResult<Option<NodeCompletion>> {
+        match fs::read_to_string(self.completion_path(execution_id, step, node_id)).await {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn forget(&self, execution_id: Uuid) -> GraphResult<()> {
+        match fs::remove_dir_all(self.completions_dir(execution_id)).await {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn expiry(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    now + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::zero())
+}
+
+/// Lease-based ownership of executions, for instances sharing a checkpoint store
+///
+/// A graph given leases with
+/// [`Graph::set_leases`](crate::graph::Graph::set_leases) takes the lease on
+/// an execution before each node and renews it while the node runs, so no
+/// two instances run an execution at once; an instance that finds the lease
+/// held elsewhere stops with a concurrency error. Every node that completes
+/// is recorded with the state it left behind, and a node found already
+/// completed at its step, e.g. by an instance that failed over before
+/// checkpointing, is not run again: its recorded state is taken instead.
+/// Nodes of a fan-out are recorded the same way, each with its own state.
+#[derive(Debug, Clone)]
+pub struct ExecutionLeases {
+    store: Arc<dyn LeaseStore>,
+    owner: String,
+    ttl: Duration,
+}
+
+impl ExecutionLeases {
+    /// Lease executions in `store` as this instance, see
+    /// [`ExecutionControls::instance_id`](crate::graph::control::ExecutionControls::instance_id)
+    pub fn new<L: LeaseStore + 'static>(store: L) -> Self {
+        Self::shared(Arc::new(store))
+    }
+
+    /// Lease executions in a store shared with other handles
+    pub fn shared(store: Arc<dyn LeaseStore>) -> Self {
+        Self {
+            store,
+            owner: crate::graph::control::default_instance_id(),
+            ttl: DEFAULT_LEASE_TTL,
+        }
+    }
+
+    /// Hold leases as `owner` rather than this instance's default ID
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = owner.into();
+        self
+    }
+
+    /// Let leases lapse after `ttl` without renewal [default: 30 seconds]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl.max(Duration::from_millis(30));
+        self
+    }
+
+    /// Instance the leases are held as
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Store the leases are kept in
+    pub fn store(&self) -> &Arc<dyn LeaseStore> {
+        &self.store
+    }
+
+    /// Take or renew the lease on an execution, failing if another instance holds it
+    pub async fn claim(&self, execution_id: Uuid) -> GraphResult<Lease> {
+        let lease = self.store.acquire(execution_id, &self.owner, self.ttl).await?;
+        if lease.owner != self.owner {
+            return Err(GraphError::ConcurrencyError(format!(
+                "Execution {} is leased by '{}' until {}",
+                execution_id,
+                lease.owner,
+                lease.expires_at.to_rfc3339()
+            )));
+        }
+        Ok(lease)
+    }
+
+    /// Run `future` under the lease on an execution, renewing it until the future is done
+    ///
+    /// The future is dropped, and the error returned, if the lease is lost.
+    pub(crate) async fn hold<T, F>(&self, execution_id: Uuid, future: F) -> GraphResult<T>
+    where
+        F: Future<Output = GraphResult<T>>,
+    {
+        self.claim(execution_id).await?;
+        tokio::pin!(future);
+        let mut renewal = tokio::time::interval(self.ttl / 3);
+        renewal.tick().await;
+        loop {
+            tokio::select! {
+                result = &mut future => return result,
+                _ = renewal.tick() => {
+                    self.claim(execution_id).await?;
+                }
+            }
+        }
+    }
+
+    /// Claim the lease on an execution, then look up the completion of `node_id` at `step`
+    ///
+    /// Claiming first means no other instance can be running the node, and
+    /// about to record it, while the record is read.
+    pub(crate) async fn completed(&self, execution_id: Uuid, step: u64, node_id: &NodeId) -> GraphResult<Option<NodeCompletion>> {
+        self.claim(execution_id).await?;
+        self.store.completion(execution_id, step, node_id).await
+    }
+
+    /// Record `node_id` as completed at `step` with `state`
+    ///
+    /// Returns the state recorded by another instance that got there first, if any.
+    pub(crate) async fn record(
+        &self,
+        execution_id: Uuid,
+        step: u64,
+        node_id: &NodeId,
+        state: serde_json::Value,
+    ) -> GraphResult<Option<serde_json::Value>> {
+        let completion = NodeCompletion {
+            execution_id,
+            step,
+            node_id: node_id.clone(),
+            owner: self.owner.clone(),
+            state,
+            completed_at: crate::graph::determinism::now(),
+        };
+        let kept = self.store.complete(completion).await?;
+        Ok((kept.owner != self.owner).then_some(kept.state))
+    }
+
+    /// Let go of an execution once it stops here, forgetting its completions if it finished
+    pub(crate) async fn finish(&self, execution_id: Uuid, finished: bool) {
+        if finished {
+            if let Err(error) = self.store.forget(execution_id).await {
+                tracing::warn!(execution_id = %execution_id, error = %error, "Failed to drop node completion records");
+            }
+        }
+        if let Err(error) = self.store.release(execution_id, &self.owner).await {
+            tracing::warn!(execution_id = %execution_id, error = %error, "Failed to release execution lease");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completion(owner: &str, value: i32) -> NodeCompletion {
+        NodeCompletion {
+            execution_id: Uuid::nil(),
+            step: 2,
+            node_id: "charge card".to_string(),
+            owner: owner.to_string(),
+            state: serde_json::json!({ "value": value }),
+            completed_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_store_leases_and_completions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileLeaseStore::new(dir.path());
+        let id = Uuid::nil();
+
+        let lease = store.acquire(id, "a", Duration::from_secs(60)).await.unwrap();
+        assert_eq!(lease.owner, "a");
+        assert_eq!(store.acquire(id, "b", Duration::from_secs(60)).await.unwrap().owner, "a");
+        store.release(id, "b").await.unwrap();
+        assert_eq!(store.lease(id).await.unwrap().unwrap().owner, "a");
+        store.release(id, "a").await.unwrap();
+        assert_eq!(store.acquire(id, "b", Duration::ZERO).await.unwrap().owner, "b");
+        // Lapsed at once, so it can be taken over
+        assert_eq!(store.acquire(id, "a", Duration::from_secs(60)).await.unwrap().owner, "a");
+
+        assert_eq!(store.complete(completion("a", 1)).await.unwrap().owner, "a");
+        assert_eq!(store.complete(completion("b", 2)).await.unwrap().state["value"], 1);
+        assert!(store.completion(id, 2, "charge card").await.unwrap().is_some());
+        // Scratch files are gone once records are in place
+        let written = std::fs::read_dir(store.completions_dir(id)).unwrap().count();
+        assert_eq!(written, 1);
+        store.forget(id).await.unwrap();
+        assert!(store.completion(id, 2, "charge card").await.unwrap().is_none());
+
+        let leases = ExecutionLeases::new(MemoryLeaseStore::new()).with_owner("a");
+        leases.claim(id).await.unwrap();
+        let error = ExecutionLeases::shared(leases.store().clone()).with_owner("b").claim(id).await.unwrap_err();
+        assert_eq!(error.category(), "concurrency");
+    }
+}
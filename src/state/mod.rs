@@ -1,10 +1,17 @@
 //! State management for the AgentGraph framework.
 
+pub mod artifacts;
 pub mod checkpointing;
+pub mod dead_letter;
+pub mod lease;
 pub mod management;
+pub mod store;
+pub mod tenant;
 
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::sync::Arc;
+use store::{StateHandle, StateStore};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
@@ -24,6 +31,20 @@ pub trait State: Debug + Clone + Send + Sync + 'static {
         Ok(())
     }
 
+    /// Remove a value from the state by key, returning it (optional for advanced state access)
+    ///
+    /// The default implementation can only go through `set_value`, so it sets
+    /// the key to `null`: the key stays in the serialized state, and a `null`
+    /// value counts as already removed. States backed by a map should override
+    /// this to drop the key.
+    fn remove_value(&mut self, key: &str) -> crate::error::GraphResult<Option<serde_json::Value>> {
+        let value = self.get_value(key).filter(|value| !value.is_null());
+        if value.is_some() {
+            self.set_value(key, serde_json::Value::Null)?;
+        }
+        Ok(value)
+    }
+
     /// Serialize the state to JSON (optional for advanced state access)
     fn to_json(&self) -> crate::error::GraphResult<serde_json::Value> {
         // Default implementation tries to serialize using serde
@@ -86,8 +107,8 @@ where
     /// Create a new state snapshot
     pub fn new(state: S) -> Self {
         Self {
-            id: Uuid::new_v4(),
-            timestamp: Utc::now(),
+            id: crate::graph::determinism::new_uuid(),
+            timestamp: crate::graph::determinism::now(),
             state,
             metadata: SnapshotMetadata::default(),
         }
@@ -96,8 +117,8 @@ where
     /// Create a new state snapshot with metadata
     pub fn with_metadata(state: S, metadata: SnapshotMetadata) -> Self {
         Self {
-            id: Uuid::new_v4(),
-            timestamp: Utc::now(),
+            id: crate::graph::determinism::new_uuid(),
+            timestamp: crate::graph::determinism::now(),
             state,
             metadata,
         }
@@ -140,6 +161,8 @@ pub struct StateManager<S> {
     snapshots: Vec<StateSnapshot<S>>,
     /// Maximum number of snapshots to keep
     max_snapshots: usize,
+    /// Where artifacts too large to keep in the state live
+    store: Option<Arc<dyn StateStore>>,
 }
 
 impl<S> StateManager<S>
@@ -152,6 +175,7 @@ where
             current_state: initial_state,
             snapshots: Vec::new(),
             max_snapshots: 100, // Default limit
+            store: None,
         }
     }
 
@@ -161,9 +185,40 @@ where
             current_state: initial_state,
             snapshots: Vec::new(),
             max_snapshots,
+            store: None,
         }
     }
 
+    /// Keep large artifacts in `store`, referenced from the state by handle
+    pub fn with_store(mut self, store: Arc<dyn StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Store large artifacts are kept in
+    pub fn store(&self) -> Option<&Arc<dyn StateStore>> {
+        self.store.as_ref()
+    }
+
+    /// Move an artifact out of the state into the store, returning its handle
+    ///
+    /// The handle is what the state keeps; snapshots then copy the handle
+    /// rather than the artifact.
+    pub async fn offload<T: Serialize + ?Sized>(&self, value: &T) -> crate::error::GraphResult<StateHandle> {
+        self.require_store()?.put(value).await
+    }
+
+    /// Load an artifact offloaded with [`offload`](Self::offload)
+    pub async fn load<T: for<'de> Deserialize<'de>>(&self, handle: &StateHandle) -> crate::error::GraphResult<T> {
+        self.require_store()?.get(handle).await
+    }
+
+    fn require_store(&self) -> crate::error::GraphResult<&Arc<dyn StateStore>> {
+        self.store.as_ref().ok_or_else(|| {
+            crate::error::GraphError::ConfigurationError("State manager has no state store".to_string())
+        })
+    }
+
     /// Get a reference to the current state
     pub fn current_state(&self) -> &S {
         &self.current_state
@@ -275,4 +330,34 @@ mod tests {
         assert!(snapshot.metadata.tags.contains(&"test".to_string()));
         assert_eq!(snapshot.get_custom_metadata::<i32>("step"), Some(5));
     }
+
+    /// Map-backed state relying on the default `remove_value`
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct FieldState {
+        values: std::collections::BTreeMap<String, serde_json::Value>,
+    }
+
+    impl State for FieldState {
+        fn get_value(&self, key: &str) -> Option<serde_json::Value> {
+            self.values.get(key).cloned()
+        }
+
+        fn set_value(&mut self, key: &str, value: serde_json::Value) -> crate::error::GraphResult<()> {
+            self.values.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_default_remove_value_nulls_the_key() {
+        let mut state = FieldState::default();
+        state.set_value("usage", serde_json::json!({ "calls": 1 })).unwrap();
+
+        assert_eq!(state.remove_value("usage").unwrap(), Some(serde_json::json!({ "calls": 1 })));
+        assert_eq!(state.get_value("usage"), Some(serde_json::Value::Null));
+        assert_eq!(state.to_json().unwrap(), serde_json::json!({ "values": { "usage": null } }));
+        assert_eq!(state.remove_value("usage").unwrap(), None);
+        assert_eq!(state.remove_value("missing").unwrap(), None);
+        assert!(state.get_value("missing").is_none());
+    }
 }
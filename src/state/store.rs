@@ -0,0 +1,308 @@
+//! Pluggable stores for large intermediate artifacts referenced from the state.
+//!
+//! Documents, embeddings and other bulky values do not have to travel inside
+//! the state, where every node, branch and checkpoint clones them. A node puts
+//! the artifact in a [`StateStore`] and keeps only the small [`StateHandle`] in
+//! the state; nodes that need the artifact load it by handle.
+//!
+//! The in-memory store is always available. A sled store (feature
+//! `state-sled`) keeps artifacts on local disk and a Redis store (feature
+//! `state-redis`) shares them between processes.
+
+use crate::error::{GraphError, GraphResult};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Reference to an artifact kept in a [`StateStore`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StateHandle {
+    /// Key of the artifact in its store
+    pub id: String,
+    /// Size of the stored artifact in bytes
+    pub size_bytes: u64,
+}
+
+impl StateHandle {
+    fn new(size_bytes: usize) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            size_bytes: size_bytes as u64,
+        }
+    }
+}
+
+impl fmt::Display for StateHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} bytes)", self.id, self.size_bytes)
+    }
+}
+
+/// Storage for artifacts referenced from the state by handle
+#[async_trait]
+pub trait StateStore: Send + Sync + fmt::Debug {
+    /// Name of the backend, for logs and errors
+    fn backend(&self) -> &'static str;
+
+    /// Store an artifact and return its handle
+    async fn put_bytes(&self, bytes: Vec<u8>) -> GraphResult<StateHandle>;
+
+    /// Artifact behind a handle, or `None` if it was deleted or expired
+    async fn get_bytes(&self, handle: &StateHandle) -> GraphResult<Option<Vec<u8>>>;
+
+    /// Delete an artifact; returns whether it existed
+    async fn delete(&self, handle: &StateHandle) -> GraphResult<bool>;
+}
+
+impl dyn StateStore {
+    /// Store a value as JSON
+    pub async fn put<T: Serialize + ?Sized>(&self, value: &T) -> GraphResult<StateHandle> {
+        self.put_bytes(serde_json::to_vec(value)?).await
+    }
+
+    /// Load a value stored with [`put`](Self::put)
+    ///
+    /// A handle whose artifact is gone is an error: the state still refers to it.
+    pub async fn get<T: DeserializeOwned>(&self, handle: &StateHandle) -> GraphResult<T> {
+        let bytes = self.get_bytes(handle).await?.ok_or_else(|| {
+            GraphError::state_error(format!("Artifact {} is missing from the {} state store", handle.id, self.backend()))
+        })?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+fn storage_error(backend: &str, error: impl fmt::Display) -> GraphError {
+    GraphError::ExternalServiceError(format!("{} state store: {}", backend, error))
+}
+
+/// Artifacts kept in process memory
+#[derive(Debug, Default)]
+pub struct MemoryStateStore {
+    artifacts: parking_lot::RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStateStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored artifacts
+    pub fn len(&self) -> usize {
+        self.artifacts.read().len()
+    }
+
+    /// Whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.artifacts.read().is_empty()
+    }
+}
+
+#[async_trait]
+impl StateStore for MemoryStateStore {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn put_bytes(&self, bytes: Vec<u8>) -> GraphResult<StateHandle> {
+        let handle = StateHandle::new(bytes.len());
+        self.artifacts.write().insert(handle.id.clone(), bytes);
+        Ok(handle)
+    }
+
+    async fn get_bytes(&self, handle: &StateHandle) -> GraphResult<Option<Vec<u8>>> {
+        Ok(self.artifacts.read().get(&handle.id).cloned())
+    }
+
+    async fn delete(&self, handle: &StateHandle) -> GraphResult<bool> {
+        Ok(self.artifacts.write().remove(&handle.id).is_some())
+    }
+}
+
+#[cfg(feature = "state-sled")]
+pub use self::sled_store::SledStateStore;
+
+#[cfg(feature = "state-sled")]
+mod sled_store {
+    use super::*;
+    use std::path::Path;
+
+    /// Artifacts kept in an embedded sled database on local disk
+    #[derive(Debug, Clone)]
+    pub struct SledStateStore {
+        tree: sled::Tree,
+    }
+
+    impl SledStateStore {
+        /// Open or create the database at `path`
+        pub fn open<P: AsRef<Path>>(path: P) -> GraphResult<Self> {
+            let db = sled::open(path).map_err(|e| storage_error("sled", e))?;
+            Self::from_db(&db)
+        }
+
+        /// Use the `state_store` tree of an open database
+        pub fn from_db(db: &sled::Db) -> GraphResult<Self> {
+            let tree = db.open_tree("state_store").map_err(|e| storage_error("sled", e))?;
+            Ok(Self { tree })
+        }
+    }
+
+    #[async_trait]
+    impl StateStore for SledStateStore {
+        fn backend(&self) -> &'static str {
+            "sled"
+        }
+
+        async fn put_bytes(&self, bytes: Vec<u8>) -> GraphResult<StateHandle> {
+            let handle = StateHandle::new(bytes.len());
+            let tree = self.tree.clone();
+            let id = handle.id.clone();
+            tokio::task::spawn_blocking(move || {
+                tree.insert(id.as_bytes(), bytes)?;
+                tree.flush().map(|_| ())
+            })
+            .await
+            .map_err(|e| GraphError::Internal(e.to_string()))?
+            .map_err(|e| storage_error("sled", e))?;
+            Ok(handle)
+        }
+
+        async fn get_bytes(&self, handle: &StateHandle) -> GraphResult<Option<Vec<u8>>> {
+            let value = self.tree.get(handle.id.as_bytes()).map_err(|e| storage_error("sled", e))?;
+            Ok(value.map(|value| value.to_vec()))
+        }
+
+        async fn delete(&self, handle: &StateHandle) -> GraphResult<bool> {
+            let removed = self.tree.remove(handle.id.as_bytes()).map_err(|e| storage_error("sled", e))?;
+            Ok(removed.is_some())
+        }
+    }
+}
+
+#[cfg(feature = "state-redis")]
+pub use self::redis_store::RedisStateStore;
+
+#[cfg(feature = "state-redis")]
+mod redis_store {
+    use super::*;
+    use redis::aio::MultiplexedConnection;
+    use redis::AsyncCommands;
+    use std::time::Duration;
+
+    /// Artifacts kept in Redis, shared by every process using the same server
+    #[derive(Clone)]
+    pub struct RedisStateStore {
+        connection: MultiplexedConnection,
+        prefix: String,
+        ttl: Option<Duration>,
+    }
+
+    impl fmt::Debug for RedisStateStore {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("RedisStateStore")
+                .field("prefix", &self.prefix)
+                .field("ttl", &self.ttl)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl RedisStateStore {
+        /// Connect to a server, e.g. `redis://127.0.0.1/`
+        pub async fn connect(url: &str) -> GraphResult<Self> {
+            let client = redis::Client::open(url).map_err(|e| storage_error("Redis", e))?;
+            let connection = client.get_multiplexed_tokio_connection().await
+                .map_err(|e| storage_error("Redis", e))?;
+            Ok(Self {
+                connection,
+                prefix: "agent_graph:state".to_string(),
+                ttl: None,
+            })
+        }
+
+        /// Namespace for the keys this store creates
+        pub fn with_prefix(mut self, prefix: &str) -> Self {
+            self.prefix = prefix.to_string();
+            self
+        }
+
+        /// Expire artifacts after `ttl`, so abandoned executions do not leak them
+        pub fn with_ttl(mut self, ttl: Duration) -> Self {
+            self.ttl = Some(ttl);
+            self
+        }
+
+        fn redis_key(&self, handle: &StateHandle) -> String {
+            format!("{}:{}", self.prefix, handle.id)
+        }
+    }
+
+    #[async_trait]
+    impl StateStore for RedisStateStore {
+        fn backend(&self) -> &'static str {
+            "Redis"
+        }
+
+        async fn put_bytes(&self, bytes: Vec<u8>) -> GraphResult<StateHandle> {
+            let handle = StateHandle::new(bytes.len());
+            let key = self.redis_key(&handle);
+            let mut connection = self.connection.clone();
+            match self.ttl {
+                Some(ttl) => connection.set_ex::<_, _, ()>(key, bytes, ttl.as_secs().max(1) as usize).await,
+                None => connection.set::<_, _, ()>(key, bytes).await,
+            }
+            .map_err(|e| storage_error("Redis", e))?;
+            Ok(handle)
+        }
+
+        async fn get_bytes(&self, handle: &StateHandle) -> GraphResult<Option<Vec<u8>>> {
+            let mut connection = self.connection.clone();
+            connection.get(self.redis_key(handle)).await.map_err(|e| storage_error("Redis", e))
+        }
+
+        async fn delete(&self, handle: &StateHandle) -> GraphResult<bool> {
+            let mut connection = self.connection.clone();
+            let removed: usize = connection.del(self.redis_key(handle)).await
+                .map_err(|e| storage_error("Redis", e))?;
+            Ok(removed > 0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateManager;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct DocState {
+        question: String,
+        corpus: Option<StateHandle>,
+    }
+
+    #[tokio::test]
+    async fn test_artifacts_travel_by_handle() {
+        let store = Arc::new(MemoryStateStore::new());
+        let manager = StateManager::new(DocState { question: "q".to_string(), corpus: None })
+            .with_store(store.clone());
+
+        let embeddings = vec![vec![0.25f32; 384]; 100];
+        let handle = manager.offload(&embeddings).await.unwrap();
+        assert!(handle.size_bytes > 1000);
+
+        // Only the handle goes into the state and its snapshots
+        let state = DocState { corpus: Some(handle.clone()), ..manager.current_state().clone() };
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.len() < 200);
+
+        let loaded: Vec<Vec<f32>> = manager.load(state.corpus.as_ref().unwrap()).await.unwrap();
+        assert_eq!(loaded, embeddings);
+
+        let store: Arc<dyn StateStore> = store;
+        assert!(store.delete(&handle).await.unwrap());
+        assert!(store.get::<Vec<Vec<f32>>>(&handle).await.is_err());
+        assert!(StateManager::new(0u8).offload(&1).await.is_err());
+    }
+}
@@ -0,0 +1,492 @@
+//! Tenant isolation for the storage layers.
+//!
+//! Backends are shared between tenants; a [`TenantScope`] keeps each tenant
+//! to its own data. Wrapped in a scope, a store only ever sees keys prefixed
+//! with the tenant ID (artifacts, dead letters, agent memory, vector
+//! collections), execution IDs derived from it (leases and completion
+//! records) or records tagged with it (checkpoints, state store entries), and
+//! treats anything else as missing. IDs of another tenant's data cannot be
+//! used to read, list, overwrite or delete it.
+
+use crate::error::{GraphError, GraphResult};
+use crate::state::artifacts::{ArtifactRef, ArtifactStore};
+use crate::state::checkpointing::Checkpointer;
+use crate::state::dead_letter::{DeadLetter, DeadLetterQueue};
+use crate::state::lease::{Lease, LeaseStore, NodeCompletion};
+use crate::state::store::{StateHandle, StateStore};
+use crate::state::{SnapshotMetadata, StateSnapshot};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Snapshot metadata field naming the tenant a checkpoint belongs to
+pub const TENANT_METADATA_KEY: &str = "tenant_id";
+
+/// The tenant whose data a store is confined to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TenantScope {
+    tenant_id: String,
+}
+
+impl TenantScope {
+    /// Scope for `tenant_id`, made of ASCII letters, digits, `-` and `_`
+    pub fn new(tenant_id: impl Into<String>) -> GraphResult<Self> {
+        let tenant_id = tenant_id.into();
+        let valid = !tenant_id.is_empty()
+            && tenant_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if !valid {
+            return Err(GraphError::validation_error(format!("Invalid tenant ID '{}'", tenant_id)));
+        }
+        Ok(Self { tenant_id })
+    }
+
+    /// ID of the tenant
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+
+    /// `key` as stored for this tenant
+    pub fn prefix(&self, key: &str) -> String {
+        format!("{}.{}", self.tenant_id, key)
+    }
+
+    /// `key` as the tenant knows it, if it was stored for this tenant
+    pub fn strip<'a>(&self, key: &'a str) -> Option<&'a str> {
+        key.strip_prefix(self.tenant_id.as_str())?.strip_prefix('.')
+    }
+
+    /// `id` as stored for this tenant, for stores keyed by UUID
+    pub fn scoped_id(&self, id: Uuid) -> Uuid {
+        let digest = Sha256::digest(self.prefix(&id.to_string()).as_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&digest[..16]);
+        Uuid::from_bytes(bytes)
+    }
+
+    /// Whether checkpoint metadata is tagged with this tenant
+    fn owns(&self, metadata: &SnapshotMetadata) -> bool {
+        metadata.custom.get(TENANT_METADATA_KEY).and_then(|tenant| tenant.as_str()) == Some(self.tenant_id.as_str())
+    }
+}
+
+impl fmt::Display for TenantScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tenant_id)
+    }
+}
+
+/// Checkpoints of one tenant, tagged in their metadata
+///
+/// Snapshots of other tenants, and untagged ones saved without a scope, are
+/// reported as not found.
+pub struct TenantCheckpointer<S> {
+    inner: Arc<dyn Checkpointer<S>>,
+    scope: TenantScope,
+}
+
+impl<S> fmt::Debug for TenantCheckpointer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TenantCheckpointer").field("scope", &self.scope).finish_non_exhaustive()
+    }
+}
+
+impl<S> TenantCheckpointer<S>
+where
+    S: Serialize + for<'de> Deserialize<'de> + Send + Sync,
+{
+    /// Confine `inner` to the checkpoints of `scope`
+    pub fn new(inner: Arc<dyn Checkpointer<S>>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    /// Tenant the checkpoints belong to
+    pub fn scope(&self) -> &TenantScope {
+        &self.scope
+    }
+
+    fn not_found(snapshot_id: Uuid) -> GraphError {
+        GraphError::CheckpointError(format!("Snapshot {} not found", snapshot_id))
+    }
+
+    async fn check_owner(&self, snapshot_id: Uuid) -> GraphResult<()> {
+        if !self.inner.exists(snapshot_id).await? {
+            return Err(Self::not_found(snapshot_id));
+        }
+        let metadata = self.inner.get_metadata(snapshot_id).await?;
+        if self.scope.owns(&metadata) {
+            Ok(())
+        } else {
+            Err(Self::not_found(snapshot_id))
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Checkpointer<S> for TenantCheckpointer<S>
+where
+    S: Serialize + for<'de> Deserialize<'de> + Send + Sync + Clone,
+{
+    async fn save(&self, snapshot: &StateSnapshot<S>) -> GraphResult<()> {
+        // Saving under another tenant's snapshot ID must not overwrite it
+        if self.inner.exists(snapshot.id).await? {
+            self.check_owner(snapshot.id).await?;
+        }
+        let mut snapshot = snapshot.clone();
+        snapshot.metadata.custom.insert(TENANT_METADATA_KEY.to_string(), serde_json::json!(self.scope.tenant_id));
+        self.inner.save(&snapshot).await
+    }
+
+    async fn load(&self, snapshot_id: Uuid) -> GraphResult<StateSnapshot<S>> {
+        if !self.inner.exists(snapshot_id).await? {
+            return Err(Self::not_found(snapshot_id));
+        }
+        // Anything else going wrong with the backend is reported as it is
+        let snapshot = self.inner.load(snapshot_id).await?;
+        if self.scope.owns(&snapshot.metadata) {
+            Ok(snapshot)
+        } else {
+            Err(Self::not_found(snapshot_id))
+        }
+    }
+
+    async fn list_snapshots(&self) -> GraphResult<Vec<Uuid>> {
+        let mut owned = Vec::new();
+        for snapshot_id in self.inner.list_snapshots().await? {
+            if self.inner.get_metadata(snapshot_id).await.is_ok_and(|metadata| self.scope.owns(&metadata)) {
+                owned.push(snapshot_id);
+            }
+        }
+        Ok(owned)
+    }
+
+    async fn delete(&self, snapshot_id: Uuid) -> GraphResult<()> {
+        match self.check_owner(snapshot_id).await {
+            Ok(()) => self.inner.delete(snapshot_id).await,
+            // Deleting a missing snapshot is not an error for the backends either
+            Err(_) => Ok(()),
+        }
+    }
+
+    async fn exists(&self, snapshot_id: Uuid) -> GraphResult<bool> {
+        Ok(self.check_owner(snapshot_id).await.is_ok())
+    }
+
+    async fn get_metadata(&self, snapshot_id: Uuid) -> GraphResult<SnapshotMetadata> {
+        self.check_owner(snapshot_id).await?;
+        self.inner.get_metadata(snapshot_id).await
+    }
+}
+
+/// Artifacts of one tenant, filed under tenant-prefixed execution IDs
+///
+/// References handed out carry the execution ID the tenant used, so they
+/// only resolve through a store scoped to the same tenant.
+#[derive(Debug, Clone)]
+pub struct TenantArtifactStore {
+    inner: Arc<dyn ArtifactStore>,
+    scope: TenantScope,
+}
+
+impl TenantArtifactStore {
+    /// Confine `inner` to the artifacts of `scope`
+    pub fn new(inner: Arc<dyn ArtifactStore>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    fn unscoped(&self, mut artifact: ArtifactRef) -> ArtifactRef {
+        if let Some(execution_id) = self.scope.strip(&artifact.execution_id) {
+            artifact.execution_id = execution_id.to_string();
+        }
+        artifact
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for TenantArtifactStore {
+    fn backend(&self) -> &'static str {
+        self.inner.backend()
+    }
+
+    async fn put(&self, artifact: &ArtifactRef, bytes: Vec<u8>) -> GraphResult<()> {
+        let mut scoped = artifact.clone();
+        scoped.execution_id = self.scope.prefix(&artifact.execution_id);
+        self.inner.put(&scoped, bytes).await
+    }
+
+    async fn get(&self, execution_id: &str, artifact_id: &str) -> GraphResult<Option<(ArtifactRef, Vec<u8>)>> {
+        let found = self.inner.get(&self.scope.prefix(execution_id), artifact_id).await?;
+        Ok(found.map(|(artifact, bytes)| (self.unscoped(artifact), bytes)))
+    }
+
+    async fn list(&self, execution_id: &str) -> GraphResult<Vec<ArtifactRef>> {
+        let listed = self.inner.list(&self.scope.prefix(execution_id)).await?;
+        Ok(listed.into_iter().map(|artifact| self.unscoped(artifact)).collect())
+    }
+
+    async fn delete(&self, execution_id: &str, artifact_id: &str) -> GraphResult<bool> {
+        self.inner.delete(&self.scope.prefix(execution_id), artifact_id).await
+    }
+}
+
+/// State store entries of one tenant, each stored with the tenant ID in front
+///
+/// Handles are generated by the backend, so a handle of another tenant's
+/// entry can be presented; its content is then reported as missing.
+#[derive(Debug, Clone)]
+pub struct TenantStateStore {
+    inner: Arc<dyn StateStore>,
+    scope: TenantScope,
+}
+
+impl TenantStateStore {
+    /// Confine `inner` to the entries of `scope`
+    pub fn new(inner: Arc<dyn StateStore>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    /// Bytes in front of every entry of the tenant
+    fn header(&self) -> Vec<u8> {
+        let mut header = self.scope.tenant_id.as_bytes().to_vec();
+        header.push(0);
+        header
+    }
+}
+
+#[async_trait]
+impl StateStore for TenantStateStore {
+    fn backend(&self) -> &'static str {
+        self.inner.backend()
+    }
+
+    async fn put_bytes(&self, bytes: Vec<u8>) -> GraphResult<StateHandle> {
+        let mut stored = self.header();
+        let size = bytes.len();
+        stored.extend(bytes);
+        let mut handle = self.inner.put_bytes(stored).await?;
+        handle.size_bytes = size as u64;
+        Ok(handle)
+    }
+
+    async fn get_bytes(&self, handle: &StateHandle) -> GraphResult<Option<Vec<u8>>> {
+        let header = self.header();
+        Ok(self.inner.get_bytes(handle).await?
+            .filter(|stored| stored.starts_with(&header))
+            .map(|stored| stored[header.len()..].to_vec()))
+    }
+
+    async fn delete(&self, handle: &StateHandle) -> GraphResult<bool> {
+        if self.get_bytes(handle).await?.is_none() {
+            return Ok(false);
+        }
+        self.inner.delete(handle).await
+    }
+}
+
+/// Dead letters of one tenant, filed under tenant-prefixed IDs
+///
+/// Letters are handed out with the ID the tenant knows them by.
+#[derive(Debug, Clone)]
+pub struct TenantDeadLetterQueue {
+    inner: Arc<dyn DeadLetterQueue>,
+    scope: TenantScope,
+}
+
+impl TenantDeadLetterQueue {
+    /// Confine `inner` to the dead letters of `scope`
+    pub fn new(inner: Arc<dyn DeadLetterQueue>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+
+    fn unscoped(&self, mut letter: DeadLetter) -> Option<DeadLetter> {
+        letter.id = self.scope.strip(&letter.id)?.to_string();
+        Some(letter)
+    }
+}
+
+#[async_trait]
+impl DeadLetterQueue for TenantDeadLetterQueue {
+    async fn put(&self, letter: &DeadLetter) -> GraphResult<()> {
+        let mut scoped = letter.clone();
+        scoped.id = self.scope.prefix(&letter.id);
+        self.inner.put(&scoped).await
+    }
+
+    async fn get(&self, id: &str) -> GraphResult<Option<DeadLetter>> {
+        let found = self.inner.get(&self.scope.prefix(id)).await?;
+        Ok(found.and_then(|letter| self.unscoped(letter)))
+    }
+
+    async fn list(&self) -> GraphResult<Vec<DeadLetter>> {
+        let listed = self.inner.list().await?;
+        Ok(listed.into_iter().filter_map(|letter| self.unscoped(letter)).collect())
+    }
+
+    async fn remove(&self, id: &str) -> GraphResult<bool> {
+        self.inner.remove(&self.scope.prefix(id)).await
+    }
+}
+
+/// Leases and completion records of one tenant's executions
+///
+/// Each execution ID is mapped to one derived from the tenant, so another
+/// tenant using the same ID neither reads the states recorded for it nor
+/// holds up its lease. Records are handed out with the ID the tenant used.
+#[derive(Debug, Clone)]
+pub struct TenantLeaseStore {
+    inner: Arc<dyn LeaseStore>,
+    scope: TenantScope,
+}
+
+impl TenantLeaseStore {
+    /// Confine `inner` to the executions of `scope`
+    pub fn new(inner: Arc<dyn LeaseStore>, scope: TenantScope) -> Self {
+        Self { inner, scope }
+    }
+}
+
+#[async_trait]
+impl LeaseStore for TenantLeaseStore {
+    async fn acquire(&self, execution_id: Uuid, owner: &str, ttl: Duration) -> GraphResult<Lease> {
+        let lease = self.inner.acquire(self.scope.scoped_id(execution_id), owner, ttl).await?;
+        Ok(Lease { execution_id, ..lease })
+    }
+
+    async fn release(&self, execution_id: Uuid, owner: &str) -> GraphResult<()> {
+        self.inner.release(self.scope.scoped_id(execution_id), owner).await
+    }
+
+    async fn lease(&self, execution_id: Uuid) -> GraphResult<Option<Lease>> {
+        let lease = self.inner.lease(self.scope.scoped_id(execution_id)).await?;
+        Ok(lease.map(|lease| Lease { execution_id, ..lease }))
+    }
+
+    async fn complete(&self, completion: NodeCompletion) -> GraphResult<NodeCompletion> {
+        let execution_id = completion.execution_id;
+        let scoped = NodeCompletion { execution_id: self.scope.scoped_id(execution_id), ..completion };
+        let kept = self.inner.complete(scoped).await?;
+        Ok(NodeCompletion { execution_id, ..kept })
+    }
+
+    async fn completion(&self, execution_id: Uuid, step: u64, node_id: &str) -> GraphResult<Option<NodeCompletion>> {
+        let found = self.inner.completion(self.scope.scoped_id(execution_id), step, node_id).await?;
+        Ok(found.map(|completion| NodeCompletion { execution_id, ..completion }))
+    }
+
+    async fn forget(&self, execution_id: Uuid) -> GraphResult<()> {
+        self.inner.forget(self.scope.scoped_id(execution_id)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::artifacts::LocalArtifactStore;
+    use crate::state::checkpointing::MemoryCheckpointer;
+    use crate::state::dead_letter::{DeadLetterStatus, MemoryDeadLetterQueue};
+    use crate::state::lease::MemoryLeaseStore;
+    use crate::state::store::MemoryStateStore;
+
+    #[tokio::test]
+    async fn test_tenants_cannot_read_each_others_data() {
+        let acme = TenantScope::new("acme").unwrap();
+        let globex = TenantScope::new("globex").unwrap();
+        assert!(TenantScope::new("../acme").is_err());
+        assert_eq!(acme.strip("acme.run-1"), Some("run-1"));
+        assert_eq!(acme.strip("acmex.run-1"), None);
+
+        // Checkpoints
+        let shared: Arc<dyn Checkpointer<i32>> = Arc::new(MemoryCheckpointer::new());
+        let acme_checkpoints = TenantCheckpointer::new(shared.clone(), acme.clone());
+        let globex_checkpoints = TenantCheckpointer::new(shared.clone(), globex.clone());
+        let snapshot = StateSnapshot::new(42);
+        acme_checkpoints.save(&snapshot).await.unwrap();
+        assert_eq!(acme_checkpoints.load(snapshot.id).await.unwrap().state, 42);
+        assert!(globex_checkpoints.load(snapshot.id).await.is_err());
+        assert!(globex_checkpoints.get_metadata(snapshot.id).await.is_err());
+        assert!(!globex_checkpoints.exists(snapshot.id).await.unwrap());
+        assert!(globex_checkpoints.list_snapshots().await.unwrap().is_empty());
+        let mut overwrite = StateSnapshot::new(0);
+        overwrite.id = snapshot.id;
+        assert!(globex_checkpoints.save(&overwrite).await.is_err());
+        globex_checkpoints.delete(snapshot.id).await.unwrap();
+        assert_eq!(acme_checkpoints.list_snapshots().await.unwrap(), vec![snapshot.id]);
+
+        // Artifacts
+        let dir = tempfile::TempDir::new().unwrap();
+        let shared: Arc<dyn ArtifactStore> = Arc::new(LocalArtifactStore::new(dir.path()));
+        let acme_artifacts: Arc<dyn ArtifactStore> = Arc::new(TenantArtifactStore::new(shared.clone(), acme.clone()));
+        let globex_artifacts: Arc<dyn ArtifactStore> = Arc::new(TenantArtifactStore::new(shared.clone(), globex.clone()));
+        let artifact = acme_artifacts.save_for("run-1", "report.txt", None, b"q3 numbers".to_vec()).await.unwrap();
+        assert_eq!(artifact.execution_id, "run-1");
+        assert_eq!(acme_artifacts.load(&artifact).await.unwrap(), b"q3 numbers");
+        assert_eq!(acme_artifacts.list("run-1").await.unwrap()[0].execution_id, "run-1");
+        assert!(globex_artifacts.load(&artifact).await.is_err());
+        assert!(globex_artifacts.list("run-1").await.unwrap().is_empty());
+        assert!(!globex_artifacts.delete("run-1", &artifact.id).await.unwrap());
+        // Without a scope the tenant's execution is not found under its own ID either
+        assert!(shared.list("run-1").await.unwrap().is_empty());
+
+        // State store entries
+        let shared: Arc<dyn StateStore> = Arc::new(MemoryStateStore::new());
+        let acme_store: Arc<dyn StateStore> = Arc::new(TenantStateStore::new(shared.clone(), acme.clone()));
+        let globex_store: Arc<dyn StateStore> = Arc::new(TenantStateStore::new(shared.clone(), globex.clone()));
+        let handle = acme_store.put(&"embedding").await.unwrap();
+        assert_eq!(acme_store.get::<String>(&handle).await.unwrap(), "embedding");
+        assert!(globex_store.get::<String>(&handle).await.is_err());
+        assert!(!globex_store.delete(&handle).await.unwrap());
+        assert!(acme_store.delete(&handle).await.unwrap());
+
+        // Dead letters
+        let shared: Arc<dyn DeadLetterQueue> = Arc::new(MemoryDeadLetterQueue::new());
+        let acme_letters = TenantDeadLetterQueue::new(shared.clone(), acme.clone());
+        let globex_letters = TenantDeadLetterQueue::new(shared.clone(), globex.clone());
+        let letter = DeadLetter {
+            id: "run-1".to_string(),
+            graph: "billing".to_string(),
+            failed_at: chrono::Utc::now(),
+            node_id: None,
+            step: 1,
+            execution_path: Vec::new(),
+            errors: vec!["card declined".to_string()],
+            input_state: serde_json::json!({"card": "4242"}),
+            failed_state: serde_json::json!({}),
+            attempts: 1,
+            status: DeadLetterStatus::Pending,
+        };
+        acme_letters.put(&letter).await.unwrap();
+        assert_eq!(acme_letters.get("run-1").await.unwrap(), Some(letter.clone()));
+        assert_eq!(acme_letters.list().await.unwrap(), vec![letter]);
+        assert_eq!(globex_letters.get("run-1").await.unwrap(), None);
+        assert!(globex_letters.list().await.unwrap().is_empty());
+        assert!(globex_letters.request_retry("run-1").await.is_err());
+        assert!(!globex_letters.remove("run-1").await.unwrap());
+        assert!(acme_letters.remove("run-1").await.unwrap());
+
+        // Leases and completion records
+        let shared: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::new());
+        let acme_leases = TenantLeaseStore::new(shared.clone(), acme.clone());
+        let globex_leases = TenantLeaseStore::new(shared.clone(), globex.clone());
+        let execution_id = Uuid::new_v4();
+        let ttl = Duration::from_secs(30);
+        assert_eq!(acme_leases.acquire(execution_id, "a", ttl).await.unwrap().execution_id, execution_id);
+        assert_eq!(globex_leases.acquire(execution_id, "b", ttl).await.unwrap().owner, "b");
+        let completion = NodeCompletion {
+            execution_id,
+            step: 1,
+            node_id: "charge".to_string(),
+            owner: "a".to_string(),
+            state: serde_json::json!({"card": "4242"}),
+            completed_at: chrono::Utc::now(),
+        };
+        acme_leases.complete(completion.clone()).await.unwrap();
+        assert_eq!(acme_leases.completion(execution_id, 1, "charge").await.unwrap(), Some(completion));
+        assert_eq!(globex_leases.completion(execution_id, 1, "charge").await.unwrap(), None);
+        assert_eq!(shared.completion(execution_id, 1, "charge").await.unwrap(), None);
+        globex_leases.forget(execution_id).await.unwrap();
+        assert!(acme_leases.completion(execution_id, 1, "charge").await.unwrap().is_some());
+    }
+}
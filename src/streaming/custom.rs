@@ -0,0 +1,318 @@
+//! Typed custom events.
+//!
+//! Domain events such as a quality score or a moderation flag travel as
+//! [`ExecutionEvent::Custom`], tagged with a string and carrying JSON data.
+//! Declaring the event as a type implementing [`CustomEvent`] ties the tag to
+//! one Rust type: emitters build the event from a value, and consumers get the
+//! value back with [`ExecutionEvent::downcast`] or a [`subscribe`]d stream
+//! instead of reading fields out of the JSON. The wire format is the same as
+//! for an untyped custom event, so existing consumers keep working. Until a
+//! consumer needs the JSON, the event travels unserialized; see
+//! [`EventData`](super::encoding::EventData).
+//!
+//! A [`CustomEventRegistry`] lists the custom events a deployment emits. It
+//! refuses two types under one tag, publishes the events' JSON Schemas and
+//! decodes any registered event into a value that can be downcast to its type.
+
+use super::encoding::EventData;
+use super::{ExecutionEvent, ExecutionStream, NODE_EVENT_SCOPE};
+use crate::error::{GraphError, GraphResult};
+use crate::node::NodeId;
+
+use async_stream::stream;
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use uuid::Uuid;
+
+/// A domain event published as [`ExecutionEvent::Custom`]
+///
+/// The serde form of the type is the event's `data`.
+///
+/// ```
+/// use agent_graph::streaming::CustomEvent;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct QualityScored {
+///     node_id: String,
+///     score: f64,
+/// }
+///
+/// impl CustomEvent for QualityScored {
+///     const EVENT_TYPE: &'static str = "quality_scored";
+/// }
+/// ```
+pub trait CustomEvent: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Tag carried in the `event_type` of the custom event
+    const EVENT_TYPE: &'static str;
+}
+
+/// A typed custom event taken from an execution stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedEvent<E> {
+    /// Execution that emitted the event
+    pub execution_id: Uuid,
+    /// When the event was emitted
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// The decoded event
+    pub event: E,
+}
+
+/// Stream of the custom events of one type
+pub type TypedEventStream<E> = Pin<Box<dyn Stream<Item = TypedEvent<E>> + Send>>;
+
+impl ExecutionEvent {
+    /// Wrap a typed event as a custom event, leaving it unserialized
+    pub fn custom<E: CustomEvent>(execution_id: Uuid, event: E) -> Self {
+        ExecutionEvent::Custom {
+            execution_id,
+            event_type: E::EVENT_TYPE.to_string(),
+            data: EventData::typed(event),
+            timestamp: crate::graph::determinism::now(),
+        }
+    }
+
+    /// Check if this is a custom event tagged as `E`
+    pub fn is_custom<E: CustomEvent>(&self) -> bool {
+        self.custom_type() == Some(E::EVENT_TYPE)
+    }
+
+    /// Tag of a custom event
+    pub fn custom_type(&self) -> Option<&str> {
+        match self {
+            ExecutionEvent::Custom { event_type, .. } => Some(event_type),
+            _ => None,
+        }
+    }
+
+    /// Decode a custom event tagged as `E`
+    ///
+    /// Returns `None` for any other event, and an error if the data under the
+    /// tag is not an `E`.
+    pub fn downcast<E: CustomEvent>(&self) -> Option<GraphResult<E>> {
+        match self {
+            ExecutionEvent::Custom { event_type, data, .. } if event_type == E::EVENT_TYPE => {
+                Some(E::deserialize(data.json()).map_err(GraphError::from))
+            }
+            _ => None,
+        }
+    }
+
+    /// Borrow a custom event emitted as an `E` in this process, without serializing it
+    ///
+    /// Returns `None` for any other event, including an `E` decoded from the
+    /// wire; [`downcast`](Self::downcast) reads those.
+    pub fn downcast_ref<E: CustomEvent>(&self) -> Option<&E> {
+        match self {
+            ExecutionEvent::Custom { event_type, data, .. } if event_type == E::EVENT_TYPE => data.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl super::EventEmitter {
+    /// Emit a typed custom event
+    pub fn emit_typed<E: CustomEvent>(&self, execution_id: Uuid, event: E) -> GraphResult<()> {
+        self.emit(ExecutionEvent::custom(execution_id, event))
+    }
+}
+
+/// Emit a typed custom event from inside a running node
+///
+/// `build` receives the node's ID. Returns false when the node is not running
+/// under an emitter, in which case nothing is emitted.
+pub fn emit_node_custom<E, F>(build: F) -> bool
+where
+    E: CustomEvent,
+    F: FnOnce(NodeId) -> E,
+{
+    NODE_EVENT_SCOPE
+        .try_with(|scope| scope.emitter.emit(ExecutionEvent::custom(scope.execution_id, build(scope.node_id.clone()))).is_ok())
+        .unwrap_or(false)
+}
+
+/// Keep only the custom events tagged as `E`, decoded
+///
+/// Events whose data is not an `E` are logged and skipped.
+pub fn subscribe<E: CustomEvent>(stream: ExecutionStream) -> TypedEventStream<E> {
+    Box::pin(stream! {
+        futures::pin_mut!(stream);
+        while let Some(event) = futures::StreamExt::next(&mut stream).await {
+            match event.downcast::<E>() {
+                Some(Ok(decoded)) => yield TypedEvent {
+                    execution_id: event.execution_id(),
+                    timestamp: event.timestamp(),
+                    event: decoded,
+                },
+                Some(Err(error)) => {
+                    tracing::warn!(event_type = E::EVENT_TYPE, %error, "Skipped malformed custom event");
+                }
+                None => {}
+            }
+        }
+    })
+}
+
+type Decoder = fn(&Value) -> GraphResult<Box<dyn Any + Send>>;
+
+#[derive(Clone)]
+struct Registration {
+    type_id: TypeId,
+    type_name: &'static str,
+    schema: Option<Value>,
+    decode: Decoder,
+}
+
+fn decode_as<E: CustomEvent>(data: &Value) -> GraphResult<Box<dyn Any + Send>> {
+    Ok(Box::new(E::deserialize(data)?))
+}
+
+/// Custom event types known to a deployment, by tag
+#[derive(Clone, Default)]
+pub struct CustomEventRegistry {
+    events: BTreeMap<&'static str, Registration>,
+}
+
+impl CustomEventRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `E` under its tag
+    ///
+    /// Registering a type again is a no-op; registering a different type
+    /// under a tag already taken is an error.
+    pub fn register<E: CustomEvent>(&mut self) -> GraphResult<()> {
+        self.insert::<E>(None)
+    }
+
+    /// Register `E` under its tag and publish its JSON Schema
+    pub fn register_with_schema<E: CustomEvent + schemars::JsonSchema>(&mut self) -> GraphResult<()> {
+        self.insert::<E>(Some(serde_json::to_value(schemars::schema_for!(E))?))
+    }
+
+    fn insert<E: CustomEvent>(&mut self, schema: Option<Value>) -> GraphResult<()> {
+        let type_name = std::any::type_name::<E>();
+        if let Some(existing) = self.events.get(E::EVENT_TYPE) {
+            if existing.type_id != TypeId::of::<E>() {
+                return Err(GraphError::ConfigurationError(format!(
+                    "Custom event '{}' is already registered as {}, not {}",
+                    E::EVENT_TYPE,
+                    existing.type_name,
+                    type_name
+                )));
+            }
+        }
+        let schema = schema.or_else(|| self.events.get(E::EVENT_TYPE).and_then(|existing| existing.schema.clone()));
+        self.events.insert(E::EVENT_TYPE, Registration { type_id: TypeId::of::<E>(), type_name, schema, decode: decode_as::<E> });
+        Ok(())
+    }
+
+    /// Check if a type is registered under `event_type`
+    pub fn contains(&self, event_type: &str) -> bool {
+        self.events.contains_key(event_type)
+    }
+
+    /// Registered tags, sorted
+    pub fn event_types(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.events.keys().copied()
+    }
+
+    /// JSON Schema of each event registered with one, by tag
+    pub fn schemas(&self) -> Vec<(String, Value)> {
+        self.events
+            .iter()
+            .filter_map(|(event_type, registration)| Some((event_type.to_string(), registration.schema.clone()?)))
+            .collect()
+    }
+
+    /// Decode a custom event of any registered type
+    ///
+    /// The value can be downcast to the registered type. Returns `None` for
+    /// events that are not custom or whose tag is not registered.
+    pub fn decode(&self, event: &ExecutionEvent) -> Option<GraphResult<Box<dyn Any + Send>>> {
+        let ExecutionEvent::Custom { event_type, data, .. } = event else {
+            return None;
+        };
+        self.events.get(event_type.as_str()).map(|registration| (registration.decode)(data.json()))
+    }
+}
+
+impl std::fmt::Debug for CustomEventRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.events.iter().map(|(event_type, registration)| (event_type, registration.type_name)))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::{create_execution_stream, with_node_events, EventEmitter, EventFilter};
+    use futures::StreamExt;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+    struct QualityScored {
+        node_id: String,
+        score: f64,
+    }
+
+    impl CustomEvent for QualityScored {
+        const EVENT_TYPE: &'static str = "quality_scored";
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Impostor {
+        score: String,
+    }
+
+    impl CustomEvent for Impostor {
+        const EVENT_TYPE: &'static str = "quality_scored";
+    }
+
+    #[tokio::test]
+    async fn test_typed_custom_events_round_trip() {
+        let (emitter, receiver) = EventEmitter::new();
+        let execution_id = Uuid::new_v4();
+
+        assert!(!emit_node_custom(|node_id| QualityScored { node_id, score: 0.0 }));
+        let emitted = with_node_events(emitter.clone(), execution_id, "judge".to_string(), async {
+            emit_node_custom(|node_id| QualityScored { node_id, score: 0.9 })
+        })
+        .await;
+        assert!(emitted);
+        emitter.emit_custom(execution_id, "quality_scored".to_string(), serde_json::json!({"score": "high"})).unwrap();
+        emitter.emit_graph_started(execution_id, "judge".to_string()).unwrap();
+        drop(emitter);
+
+        let events: Vec<ExecutionEvent> = create_execution_stream(receiver).collect().await;
+        let scored = &events[0];
+        assert!(scored.is_custom::<QualityScored>());
+        assert!(EventFilter::new().with_custom_event::<QualityScored>().matches(scored));
+        assert!(!EventFilter::new().with_custom_event::<QualityScored>().matches(&events[2]));
+        assert_eq!(scored.downcast::<QualityScored>().unwrap().unwrap(), QualityScored { node_id: "judge".to_string(), score: 0.9 });
+        assert!(events[1].downcast::<QualityScored>().unwrap().is_err());
+        assert!(events[2].downcast::<QualityScored>().is_none());
+
+        let typed: Vec<TypedEvent<QualityScored>> = subscribe(Box::pin(futures::stream::iter(events.clone()))).collect().await;
+        assert_eq!(typed.len(), 1);
+        assert_eq!(typed[0].execution_id, execution_id);
+
+        let mut registry = CustomEventRegistry::new();
+        registry.register_with_schema::<QualityScored>().unwrap();
+        registry.register::<QualityScored>().unwrap();
+        assert!(registry.register::<Impostor>().is_err());
+        assert_eq!(registry.schemas().len(), 1);
+        let decoded = registry.decode(scored).unwrap().unwrap();
+        assert_eq!(decoded.downcast_ref::<QualityScored>().map(|event| event.score), Some(0.9));
+        assert!(registry.decode(&events[2]).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+//! Lazy event payloads and binary event encoding.
+//!
+//! A typed custom event is emitted as [`EventData`] holding the event itself;
+//! it is rendered as JSON the first time a consumer reads
+//! [`EventData::json`] or serializes the event, and at most once however many
+//! consumers do. In-process consumers borrow the typed event with
+//! [`ExecutionEvent::downcast_ref`] and never serialize it, so a run whose
+//! events only feed in-process sinks pays nothing for JSON.
+//!
+//! Sinks that store or forward events inside a deployment, e.g. an event log
+//! or a queue between workers, can write them with
+//! [`EventEncoding::MessagePack`], which is smaller and cheaper to produce
+//! than JSON. Event payloads carry free-form JSON values, so the binary
+//! encoding is self-describing; formats that are not, such as bincode, cannot
+//! decode them. Consumers outside the deployment should keep receiving the
+//! versioned JSON [`EventEnvelope`](super::EventEnvelope).
+
+use crate::error::{GraphError, GraphResult};
+use crate::streaming::{CustomEvent, ExecutionEvent};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::any::Any;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// Typed payload that renders itself as JSON on demand
+trait TypedPayload: Any + Send + Sync {
+    fn to_json(&self) -> serde_json::Result<Value>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<E: CustomEvent> TypedPayload for E {
+    fn to_json(&self) -> serde_json::Result<Value> {
+        serde_json::to_value(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct Payload {
+    typed: Option<Box<dyn TypedPayload>>,
+    type_name: &'static str,
+    json: OnceLock<Value>,
+}
+
+/// Data of a custom event, serialized to JSON only when first read
+///
+/// Cloning shares the payload and its JSON, so events fanned out to several
+/// consumers are serialized once.
+#[derive(Clone)]
+pub struct EventData(Arc<Payload>);
+
+impl EventData {
+    /// Data that is already JSON
+    pub fn json_value(value: Value) -> Self {
+        Self(Arc::new(Payload { typed: None, type_name: "serde_json::Value", json: OnceLock::from(value) }))
+    }
+
+    /// Data holding a typed event, rendered as JSON when first read
+    pub fn typed<E: CustomEvent>(event: E) -> Self {
+        Self(Arc::new(Payload {
+            typed: Some(Box::new(event)),
+            type_name: std::any::type_name::<E>(),
+            json: OnceLock::new(),
+        }))
+    }
+
+    /// The data as JSON
+    ///
+    /// A typed event that fails to serialize reads as null; the failure is
+    /// logged once.
+    pub fn json(&self) -> &Value {
+        self.0.json.get_or_init(|| match self.0.typed.as_ref().map(|typed| typed.to_json()) {
+            Some(Ok(value)) => value,
+            Some(Err(error)) => {
+                tracing::warn!(event_type = self.0.type_name, %error, "Custom event does not serialize");
+                Value::Null
+            }
+            None => Value::Null,
+        })
+    }
+
+    /// The typed event, if the data holds an `E` that was never serialized across a process boundary
+    pub fn downcast_ref<E: 'static>(&self) -> Option<&E> {
+        self.0.typed.as_ref()?.as_any().downcast_ref()
+    }
+
+    /// Check if the data has been rendered as JSON
+    pub fn is_serialized(&self) -> bool {
+        self.0.json.get().is_some()
+    }
+}
+
+impl From<Value> for EventData {
+    fn from(value: Value) -> Self {
+        Self::json_value(value)
+    }
+}
+
+impl PartialEq for EventData {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.json() == other.json()
+    }
+}
+
+impl fmt::Debug for EventData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.json.get() {
+            Some(value) => fmt::Debug::fmt(value, f),
+            None => write!(f, "<{}>", self.0.type_name),
+        }
+    }
+}
+
+impl Serialize for EventData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EventData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(deserializer).map(Self::json_value)
+    }
+}
+
+impl schemars::JsonSchema for EventData {
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    fn schema_name() -> String {
+        Value::schema_name()
+    }
+
+    fn json_schema(generator: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        Value::json_schema(generator)
+    }
+}
+
+/// How an internal sink writes execution events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventEncoding {
+    /// JSON text, readable by any consumer
+    #[default]
+    Json,
+    /// MessagePack, smaller and faster to encode than JSON
+    MessagePack,
+}
+
+impl EventEncoding {
+    /// Encode an event
+    pub fn encode(&self, event: &ExecutionEvent) -> GraphResult<Vec<u8>> {
+        match self {
+            EventEncoding::Json => Ok(serde_json::to_vec(event)?),
+            // Named fields, so optional fields left out by a producer still decode
+            EventEncoding::MessagePack => rmp_serde::to_vec_named(event).map_err(|error| encoding_error("encode", error)),
+        }
+    }
+
+    /// Decode an event written with this encoding
+    pub fn decode(&self, bytes: &[u8]) -> GraphResult<ExecutionEvent> {
+        match self {
+            EventEncoding::Json => Ok(serde_json::from_slice(bytes)?),
+            EventEncoding::MessagePack => rmp_serde::from_slice(bytes).map_err(|error| encoding_error("decode", error)),
+        }
+    }
+
+    /// MIME type of encoded events
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            EventEncoding::Json => "application/json",
+            EventEncoding::MessagePack => "application/msgpack",
+        }
+    }
+}
+
+fn encoding_error(operation: &str, error: impl fmt::Display) -> GraphError {
+    GraphError::SerializationError(serde::ser::Error::custom(format!("failed to {} MessagePack event: {}", operation, error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::EventEmitter;
+    use uuid::Uuid;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Scored {
+        score: f64,
+    }
+
+    impl CustomEvent for Scored {
+        const EVENT_TYPE: &'static str = "scored";
+    }
+
+    #[test]
+    fn test_custom_data_serialized_only_on_demand() {
+        let (emitter, mut receiver) = EventEmitter::new();
+        let execution_id = Uuid::new_v4();
+        emitter.emit_typed(execution_id, Scored { score: 0.5 }).unwrap();
+        let event = receiver.try_recv().unwrap();
+        let ExecutionEvent::Custom { data, .. } = &event else { panic!("expected a custom event") };
+
+        assert_eq!(event.downcast_ref::<Scored>(), Some(&Scored { score: 0.5 }));
+        assert!(!data.is_serialized());
+        assert_eq!(event.downcast::<Scored>().unwrap().unwrap(), Scored { score: 0.5 });
+        assert!(data.is_serialized());
+
+        for encoding in [EventEncoding::Json, EventEncoding::MessagePack] {
+            let decoded = encoding.decode(&encoding.encode(&event).unwrap()).unwrap();
+            assert_eq!(decoded.downcast::<Scored>().unwrap().unwrap(), Scored { score: 0.5 });
+            assert!(decoded.downcast_ref::<Scored>().is_none());
+        }
+        let json = EventEncoding::Json.encode(&event).unwrap();
+        assert!(EventEncoding::MessagePack.encode(&event).unwrap().len() < json.len());
+    }
+}
@@ -1,17 +1,31 @@
 //! Streaming execution and real-time event handling.
 
 use crate::error::GraphResult;
+use crate::llm::LLMUsage;
 use crate::node::{NodeExecutionContext, NodeId};
 
 use async_stream::stream;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
 use std::pin::Pin;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+pub mod custom;
+pub mod encoding;
+pub mod output;
+pub mod schema;
+
+pub use custom::{emit_node_custom, subscribe, CustomEvent, CustomEventRegistry, TypedEvent, TypedEventStream};
+pub use encoding::{EventData, EventEncoding};
+pub use schema::{EventEnvelope, EVENT_SCHEMA_VERSION};
+
 /// Events that can be emitted during graph execution
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Consumers outside the process receive them as a versioned
+/// [`EventEnvelope`](schema::EventEnvelope).
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub enum ExecutionEvent {
     /// Graph execution started
     GraphStarted {
@@ -63,6 +77,12 @@ pub enum ExecutionEvent {
         success: bool,
         /// Error message if failed
         error: Option<String>,
+        /// LLM usage the node reported, if it called a model
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        llm_usage: Option<LLMUsage>,
+        /// Candidates an aggregating node chose from, such as a debate's answers
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        candidates: Option<serde_json::Value>,
     },
 
     /// State updated
@@ -127,14 +147,30 @@ pub enum ExecutionEvent {
         category: String,
     },
 
+    /// Incremental output from a streaming tool
+    ToolOutputChunk {
+        /// Execution ID
+        execution_id: Uuid,
+        /// Node running the tool
+        node_id: NodeId,
+        /// Tool that produced the chunk
+        tool_id: String,
+        /// Position of the chunk within the tool's output
+        sequence: u64,
+        /// Chunk payload
+        data: serde_json::Value,
+        /// Timestamp
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+
     /// Custom event
     Custom {
         /// Execution ID
         execution_id: Uuid,
         /// Event type
         event_type: String,
-        /// Event data
-        data: serde_json::Value,
+        /// Event data, serialized when first read
+        data: EventData,
         /// Timestamp
         timestamp: chrono::DateTime<chrono::Utc>,
     },
@@ -153,6 +189,7 @@ impl ExecutionEvent {
             | ExecutionEvent::ParallelStarted { execution_id, .. }
             | ExecutionEvent::ParallelCompleted { execution_id, .. }
             | ExecutionEvent::Error { execution_id, .. }
+            | ExecutionEvent::ToolOutputChunk { execution_id, .. }
             | ExecutionEvent::Custom { execution_id, .. } => *execution_id,
         }
     }
@@ -169,6 +206,7 @@ impl ExecutionEvent {
             | ExecutionEvent::ParallelStarted { timestamp, .. }
             | ExecutionEvent::ParallelCompleted { timestamp, .. }
             | ExecutionEvent::Error { timestamp, .. }
+            | ExecutionEvent::ToolOutputChunk { timestamp, .. }
             | ExecutionEvent::Custom { timestamp, .. } => *timestamp,
         }
     }
@@ -185,6 +223,7 @@ impl ExecutionEvent {
             ExecutionEvent::ParallelStarted { .. } => "parallel_started",
             ExecutionEvent::ParallelCompleted { .. } => "parallel_completed",
             ExecutionEvent::Error { .. } => "error",
+            ExecutionEvent::ToolOutputChunk { .. } => "tool_output_chunk",
             ExecutionEvent::Custom { .. } => "custom",
         }
     }
@@ -209,7 +248,7 @@ impl ExecutionEvent {
 pub type ExecutionStream = Pin<Box<dyn Stream<Item = ExecutionEvent> + Send>>;
 
 /// Event emitter for streaming execution events
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EventEmitter {
     sender: mpsc::UnboundedSender<ExecutionEvent>,
 }
@@ -233,7 +272,7 @@ impl EventEmitter {
     pub fn emit_graph_started(&self, execution_id: Uuid, entry_point: NodeId) -> GraphResult<()> {
         self.emit(ExecutionEvent::GraphStarted {
             execution_id,
-            timestamp: chrono::Utc::now(),
+            timestamp: crate::graph::determinism::now(),
             entry_point,
         })
     }
@@ -248,7 +287,7 @@ impl EventEmitter {
     ) -> GraphResult<()> {
         self.emit(ExecutionEvent::GraphCompleted {
             execution_id,
-            timestamp: chrono::Utc::now(),
+            timestamp: crate::graph::determinism::now(),
             final_node,
             duration_ms,
             success,
@@ -265,7 +304,7 @@ impl EventEmitter {
         self.emit(ExecutionEvent::NodeStarted {
             execution_id,
             node_id,
-            timestamp: chrono::Utc::now(),
+            timestamp: crate::graph::determinism::now(),
             context,
         })
     }
@@ -278,14 +317,18 @@ impl EventEmitter {
         duration_ms: u64,
         success: bool,
         error: Option<String>,
+        llm_usage: Option<LLMUsage>,
+        candidates: Option<serde_json::Value>,
     ) -> GraphResult<()> {
         self.emit(ExecutionEvent::NodeCompleted {
             execution_id,
             node_id,
-            timestamp: chrono::Utc::now(),
+            timestamp: crate::graph::determinism::now(),
             duration_ms,
             success,
             error,
+            llm_usage,
+            candidates,
         })
     }
 
@@ -299,7 +342,7 @@ impl EventEmitter {
         self.emit(ExecutionEvent::StateUpdated {
             execution_id,
             node_id,
-            timestamp: chrono::Utc::now(),
+            timestamp: crate::graph::determinism::now(),
             snapshot_id,
         })
     }
@@ -315,7 +358,7 @@ impl EventEmitter {
         self.emit(ExecutionEvent::Error {
             execution_id,
             node_id,
-            timestamp: chrono::Utc::now(),
+            timestamp: crate::graph::determinism::now(),
             error,
             category,
         })
@@ -331,8 +374,8 @@ impl EventEmitter {
         self.emit(ExecutionEvent::Custom {
             execution_id,
             event_type,
-            data,
-            timestamp: chrono::Utc::now(),
+            data: data.into(),
+            timestamp: crate::graph::determinism::now(),
         })
     }
 }
@@ -344,6 +387,43 @@ impl Default for EventEmitter {
     }
 }
 
+tokio::task_local! {
+    static NODE_EVENT_SCOPE: NodeEventScope;
+}
+
+/// Emitter and identifiers available to a node while it runs
+#[derive(Debug, Clone)]
+struct NodeEventScope {
+    emitter: EventEmitter,
+    execution_id: Uuid,
+    node_id: NodeId,
+}
+
+/// Run a node's future so that it can emit events with [`emit_node_event`]
+pub async fn with_node_events<F: Future>(
+    emitter: EventEmitter,
+    execution_id: Uuid,
+    node_id: NodeId,
+    future: F,
+) -> F::Output {
+    NODE_EVENT_SCOPE
+        .scope(NodeEventScope { emitter, execution_id, node_id }, future)
+        .await
+}
+
+/// Emit an event from inside a running node
+///
+/// `build` receives the execution and node IDs. Returns false when the node
+/// is not running under an emitter, in which case nothing is emitted.
+pub fn emit_node_event<F>(build: F) -> bool
+where
+    F: FnOnce(Uuid, NodeId) -> ExecutionEvent,
+{
+    NODE_EVENT_SCOPE
+        .try_with(|scope| scope.emitter.emit(build(scope.execution_id, scope.node_id.clone())).is_ok())
+        .unwrap_or(false)
+}
+
 /// Stream adapter for converting receiver to stream
 pub fn create_execution_stream(
     mut receiver: mpsc::UnboundedReceiver<ExecutionEvent>,
@@ -360,6 +440,8 @@ pub fn create_execution_stream(
 pub struct EventFilter {
     /// Filter by event types
     pub event_types: Option<Vec<String>>,
+    /// Filter by custom event tags; only custom events carrying one pass
+    pub custom_types: Option<Vec<String>>,
     /// Filter by execution ID
     pub execution_id: Option<Uuid>,
     /// Filter by node ID
@@ -375,6 +457,7 @@ impl EventFilter {
     pub fn new() -> Self {
         Self {
             event_types: None,
+            custom_types: None,
             execution_id: None,
             node_id: None,
             errors_only: false,
@@ -388,6 +471,12 @@ impl EventFilter {
         self
     }
 
+    /// Filter by custom event type, keeping only events tagged as `E`
+    pub fn with_custom_event<E: CustomEvent>(mut self) -> Self {
+        self.custom_types.get_or_insert_with(Vec::new).push(E::EVENT_TYPE.to_string());
+        self
+    }
+
     /// Filter by execution ID
     pub fn with_execution_id(mut self, id: Uuid) -> Self {
         self.execution_id = Some(id);
@@ -428,12 +517,20 @@ impl EventFilter {
             }
         }
 
+        // Check custom event tag filter
+        if let Some(ref tags) = self.custom_types {
+            if !event.custom_type().is_some_and(|tag| tags.iter().any(|t| t == tag)) {
+                return false;
+            }
+        }
+
         // Check node ID filter
         if let Some(ref node_id) = self.node_id {
             match event {
                 ExecutionEvent::NodeStarted { node_id: nid, .. }
                 | ExecutionEvent::NodeCompleted { node_id: nid, .. }
-                | ExecutionEvent::StateUpdated { node_id: nid, .. } => {
+                | ExecutionEvent::StateUpdated { node_id: nid, .. }
+                | ExecutionEvent::ToolOutputChunk { node_id: nid, .. } => {
                     if nid != node_id {
                         return false;
                     }
@@ -494,6 +591,7 @@ pub fn filter_stream(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::stream::ToolOutputChunk;
 
     #[test]
     fn test_event_creation() {
@@ -548,4 +646,40 @@ mod tests {
         assert_eq!(event.execution_id(), execution_id);
         assert_eq!(event.event_type(), "graph_started");
     }
+
+    #[tokio::test]
+    async fn test_node_event_scope() {
+        let (emitter, mut receiver) = EventEmitter::new();
+        let execution_id = Uuid::new_v4();
+        let chunk = ToolOutputChunk {
+            tool_id: "crawler".to_string(),
+            sequence: 0,
+            data: serde_json::json!({"page": 1}),
+            timestamp: chrono::Utc::now(),
+        };
+
+        assert!(!emit_node_event(|id, node| ExecutionEvent::Custom {
+            execution_id: id,
+            event_type: node,
+            data: serde_json::Value::Null.into(),
+            timestamp: chrono::Utc::now(),
+        }));
+
+        let emitted = with_node_events(emitter, execution_id, "crawl".to_string(), async {
+            emit_node_event(|id, node| ExecutionEvent::ToolOutputChunk {
+                execution_id: id,
+                node_id: node,
+                tool_id: chunk.tool_id.clone(),
+                sequence: chunk.sequence,
+                data: chunk.data.clone(),
+                timestamp: chunk.timestamp,
+            })
+        }).await;
+        assert!(emitted);
+
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.execution_id(), execution_id);
+        assert_eq!(event.event_type(), "tool_output_chunk");
+        assert!(EventFilter::new().with_node_id("crawl".to_string()).matches(&event));
+    }
 }
@@ -0,0 +1,318 @@
+//! Designated output fields of an execution streamed to HTTP clients.
+//!
+//! In server mode a graph's answer need not wait for the graph to finish.
+//! [`stream_outputs`] runs an execution in the background and yields an
+//! [`OutputUpdate`] each time one of the named state fields changes between
+//! steps, then a final update with every field. Nodes that build a field a
+//! piece at a time, e.g. from LLM tokens, can send the pieces as they arrive
+//! with [`publish`]. [`route`] serves all this over HTTP as NDJSON or
+//! Server-Sent Events.
+
+use crate::error::GraphError;
+use crate::graph::engine::{current_execution_id, GraphEngine};
+use crate::graph::Graph;
+use crate::node::NodeId;
+use crate::state::State;
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use warp::Filter;
+
+/// Largest input state [`route`] accepts
+const MAX_INPUT_BYTES: u64 = 1024 * 1024;
+
+/// One message of an output stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputUpdate {
+    /// An output field has a new value
+    Field {
+        /// Execution producing the field
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        execution_id: Option<Uuid>,
+        /// State field
+        field: String,
+        /// New value, or the next piece of it when `partial`
+        value: Value,
+        /// Node that produced the value
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        node_id: Option<NodeId>,
+        /// Whether `value` is a piece published while the node runs
+        #[serde(default)]
+        partial: bool,
+    },
+    /// The execution finished; the last message of a stream that succeeded
+    Completed {
+        /// Execution ID
+        execution_id: Uuid,
+        /// Final value of every output field the state has
+        outputs: Map<String, Value>,
+    },
+    /// The execution failed or was refused; the last message of a stream that did not succeed
+    Failed {
+        /// Why it failed
+        error: String,
+        /// Error category, see [`GraphError::category`]
+        category: String,
+        /// How long to wait before trying again, when the execution was throttled
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry_after_ms: Option<u64>,
+    },
+}
+
+impl OutputUpdate {
+    /// Name of the update, used as the SSE event name
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutputUpdate::Field { .. } => "field",
+            OutputUpdate::Completed { .. } => "completed",
+            OutputUpdate::Failed { .. } => "failed",
+        }
+    }
+
+    /// Whether no update follows this one
+    pub fn is_final(&self) -> bool {
+        !matches!(self, OutputUpdate::Field { .. })
+    }
+
+    fn failed(error: &GraphError) -> Self {
+        OutputUpdate::Failed {
+            error: error.to_string(),
+            category: error.category().to_string(),
+            retry_after_ms: error.retry_after().map(|wait| wait.as_millis() as u64),
+        }
+    }
+}
+
+/// Wire format of an output stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One JSON update per line
+    #[default]
+    Ndjson,
+    /// Server-Sent Events named after the update
+    Sse,
+}
+
+impl OutputFormat {
+    /// Pick a format from an explicit `format` parameter, then the `Accept` header
+    pub fn negotiate(format: Option<&str>, accept: Option<&str>) -> Self {
+        match format {
+            Some(format) if format.eq_ignore_ascii_case("sse") => OutputFormat::Sse,
+            Some(format) if format.eq_ignore_ascii_case("ndjson") => OutputFormat::Ndjson,
+            _ if accept.is_some_and(|accept| accept.contains("text/event-stream")) => OutputFormat::Sse,
+            _ => OutputFormat::Ndjson,
+        }
+    }
+
+    /// Content type of the response body
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Ndjson => "application/x-ndjson",
+            OutputFormat::Sse => "text/event-stream",
+        }
+    }
+
+    /// Encode one update, including its delimiter
+    pub fn encode(&self, update: &OutputUpdate) -> String {
+        let json = serde_json::to_string(update).unwrap_or_else(|_| "null".to_string());
+        match self {
+            OutputFormat::Ndjson => format!("{}\n", json),
+            OutputFormat::Sse => format!("event: {}\ndata: {}\n\n", update.kind(), json),
+        }
+    }
+}
+
+/// Stream of output updates
+pub type OutputStream = Pin<Box<dyn Stream<Item = OutputUpdate> + Send>>;
+
+/// Output fields of one execution and the values last sent for them
+#[derive(Debug)]
+struct OutputWatch {
+    fields: Vec<String>,
+    sent: Mutex<HashMap<String, Value>>,
+    sender: mpsc::UnboundedSender<OutputUpdate>,
+}
+
+impl OutputWatch {
+    fn observe<S: State>(&self, node_id: Option<&NodeId>, state: &S) {
+        let mut sent = self.sent.lock();
+        for field in &self.fields {
+            let Some(value) = state.get_value(field) else {
+                continue;
+            };
+            if sent.get(field) == Some(&value) {
+                continue;
+            }
+            sent.insert(field.clone(), value.clone());
+            let _ = self.sender.send(OutputUpdate::Field {
+                execution_id: current_execution_id(),
+                field: field.clone(),
+                value,
+                node_id: node_id.cloned(),
+                partial: false,
+            });
+        }
+    }
+}
+
+tokio::task_local! {
+    static OUTPUTS: Arc<OutputWatch>;
+}
+
+/// Send output fields that changed since they were last sent, if the current task streams outputs
+pub(crate) fn observe<S: State>(node_id: Option<&NodeId>, state: &S) {
+    let _ = OUTPUTS.try_with(|watch| watch.observe(node_id, state));
+}
+
+/// Carry the current task's output stream into `future`, for a node spawned on a task of its own
+pub(crate) fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let watch = OUTPUTS.try_with(Arc::clone).ok();
+    async move {
+        match watch {
+            Some(watch) => OUTPUTS.scope(watch, future).await,
+            None => future.await,
+        }
+    }
+}
+
+/// Send the next piece of an output field from inside a running node
+///
+/// The field's final value is still sent once the node has set it. Returns
+/// false when the execution is not streamed or `field` is not one of its
+/// outputs, in which case nothing is sent.
+pub fn publish(field: &str, value: Value) -> bool {
+    OUTPUTS
+        .try_with(|watch| {
+            watch.fields.iter().any(|f| f == field)
+                && watch.sender.send(OutputUpdate::Field {
+                    execution_id: current_execution_id(),
+                    field: field.to_string(),
+                    value,
+                    node_id: None,
+                    partial: true,
+                }).is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Run an execution of `graph` on `engine` in the background, streaming the state fields named in `fields`
+///
+/// Fields are sent when they differ from the input state, so values the
+/// caller passed in are not echoed back. The stream ends with
+/// [`OutputUpdate::Completed`] or [`OutputUpdate::Failed`]; dropping it does
+/// not stop the execution.
+pub fn stream_outputs<S>(graph: Arc<Graph<S>>, mut engine: GraphEngine<S>, mut state: S, fields: Vec<String>) -> OutputStream
+where
+    S: State + Clone + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let sent = fields.iter()
+        .filter_map(|field| Some((field.clone(), state.get_value(field)?)))
+        .collect();
+    let watch = Arc::new(OutputWatch { fields, sent: Mutex::new(sent), sender });
+
+    tokio::spawn(OUTPUTS.scope(watch.clone(), async move {
+        let result = engine.execute(&graph, &mut state).await;
+        let update = match result {
+            Ok(context) => {
+                watch.observe(context.current_node.as_ref(), &state);
+                let outputs = watch.fields.iter()
+                    .filter_map(|field| Some((field.clone(), state.get_value(field)?)))
+                    .collect();
+                OutputUpdate::Completed { execution_id: context.execution_id, outputs }
+            }
+            Err(error) => OutputUpdate::failed(&error),
+        };
+        let _ = watch.sender.send(update);
+    }));
+
+    Box::pin(stream! {
+        while let Some(update) = receiver.recv().await {
+            let last = update.is_final();
+            yield update;
+            if last {
+                break;
+            }
+        }
+    })
+}
+
+/// Stream `updates` as the body of an HTTP response in `format`
+pub fn into_response(updates: OutputStream, format: OutputFormat) -> warp::reply::Response {
+    let body = updates.map(move |update| Ok::<_, Infallible>(format.encode(&update)));
+    let mut response = warp::reply::Response::new(warp::hyper::Body::wrap_stream(body));
+    let headers = response.headers_mut();
+    headers.insert(warp::http::header::CONTENT_TYPE, warp::http::HeaderValue::from_static(format.content_type()));
+    headers.insert(warp::http::header::CACHE_CONTROL, warp::http::HeaderValue::from_static("no-cache"));
+    // Keep reverse proxies from holding back updates
+    headers.insert("x-accel-buffering", warp::http::HeaderValue::from_static("no"));
+    response
+}
+
+/// POST handler running `graph` on the JSON input state and streaming `fields` back
+///
+/// Each request runs on a sibling of `engine`. The response is NDJSON unless
+/// `?format=sse` is given or the client accepts `text/event-stream`. Mount it
+/// under a path of your choosing, e.g.
+/// `warp::path!("graphs" / "support" / "run").and(output::route(graph, engine, fields))`.
+pub fn route<S>(
+    graph: Arc<Graph<S>>,
+    engine: GraphEngine<S>,
+    fields: Vec<String>,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone
+where
+    S: State + Clone + Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    let engine = Arc::new(engine);
+    warp::post()
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::body::content_length_limit(MAX_INPUT_BYTES))
+        .and(warp::body::json::<S>())
+        .map(move |query: HashMap<String, String>, accept: Option<String>, state: S| {
+            let format = OutputFormat::negotiate(query.get("format").map(String::as_str), accept.as_deref());
+            into_response(stream_outputs(graph.clone(), engine.sibling(), state, fields.clone()), format)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_output_formats() {
+        let update = OutputUpdate::Field {
+            execution_id: None,
+            field: "answer".to_string(),
+            value: json!("Hel"),
+            node_id: Some("reply".to_string()),
+            partial: true,
+        };
+        assert_eq!(
+            OutputFormat::Ndjson.encode(&update),
+            "{\"type\":\"field\",\"field\":\"answer\",\"value\":\"Hel\",\"node_id\":\"reply\",\"partial\":true}\n"
+        );
+        assert!(OutputFormat::Sse.encode(&update).starts_with("event: field\ndata: {\"type\":\"field\""));
+        assert!(OutputFormat::Sse.encode(&update).ends_with("}\n\n"));
+
+        assert_eq!(OutputFormat::negotiate(None, Some("text/event-stream")), OutputFormat::Sse);
+        assert_eq!(OutputFormat::negotiate(Some("ndjson"), Some("text/event-stream")), OutputFormat::Ndjson);
+        assert_eq!(OutputFormat::negotiate(None, None), OutputFormat::Ndjson);
+
+        let throttled = OutputUpdate::failed(&GraphError::throttled("messages_per_minute", "chat-7", std::time::Duration::from_secs(12)));
+        assert!(throttled.is_final());
+        assert_eq!(serde_json::to_value(&throttled).unwrap()["retry_after_ms"], json!(12000));
+    }
+}
@@ -0,0 +1,367 @@
+//! Versioned wire format of execution events.
+//!
+//! Events leave the process wrapped in an [`EventEnvelope`] that names the
+//! schema version, the event type and the event's data. Adding an event type
+//! or an optional field keeps the version; removing, renaming or retyping a
+//! field bumps [`EVENT_SCHEMA_VERSION`], so consumers can tell an event they
+//! cannot read from one they can.
+//!
+//! [`event_schemas`] publishes a JSON Schema per event type, and
+//! [`typescript_client`] renders the TypeScript types of the client package in
+//! `clients/typescript`, which `cargo run --example generate_event_client`
+//! regenerates. A test fails while the package is out of date.
+
+use crate::error::{GraphError, GraphResult};
+use crate::streaming::ExecutionEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt::Write as _;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Version of the event wire format
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// An execution event as sent over the wire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    /// Version of the wire format the event was written in
+    pub schema_version: u32,
+    /// Event type, as reported by [`ExecutionEvent::event_type`]
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Execution the event belongs to
+    pub execution_id: Uuid,
+    /// When the event happened
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Fields of the event
+    pub data: Value,
+}
+
+impl EventEnvelope {
+    /// Wrap an event in the current wire format
+    pub fn new(event: &ExecutionEvent) -> GraphResult<Self> {
+        let data = match serde_json::to_value(event)? {
+            Value::Object(tagged) => tagged.into_iter().next().map(|(_, data)| data).unwrap_or_default(),
+            other => other,
+        };
+        Ok(Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            event_type: event.event_type().to_string(),
+            execution_id: event.execution_id(),
+            timestamp: event.timestamp(),
+            data,
+        })
+    }
+
+    /// Unwrap the event
+    ///
+    /// Returns `None` for event types this version does not know, which a
+    /// newer producer may send without bumping the schema version. Events
+    /// written in a newer schema version are an error.
+    pub fn decode(&self) -> GraphResult<Option<ExecutionEvent>> {
+        if self.schema_version > EVENT_SCHEMA_VERSION {
+            return Err(GraphError::validation_error(format!(
+                "Event schema version {} is newer than the supported version {}",
+                self.schema_version, EVENT_SCHEMA_VERSION
+            )));
+        }
+        if !event_types().any(|(event_type, _)| event_type == self.event_type) {
+            return Ok(None);
+        }
+        let tagged = json!({ pascal_case(&self.event_type): self.data });
+        Ok(Some(serde_json::from_value(tagged)?))
+    }
+}
+
+impl ExecutionEvent {
+    /// Wrap this event in the versioned wire format
+    pub fn to_envelope(&self) -> GraphResult<EventEnvelope> {
+        EventEnvelope::new(self)
+    }
+}
+
+/// Schema of every event, as generated from [`ExecutionEvent`]
+fn root_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(ExecutionEvent)).unwrap_or_default()
+}
+
+/// Event types with the schema of their data, in declaration order
+fn event_types() -> impl Iterator<Item = (String, Value)> {
+    let root = root_schema();
+    let variants = root["oneOf"].as_array().cloned().unwrap_or_default();
+    variants.into_iter().filter_map(|variant| {
+        let (name, mut data) = variant["properties"].as_object()?.iter().next().map(|(k, v)| (k.clone(), v.clone()))?;
+        if let (Some(description), Value::Object(data)) = (variant.get("description"), &mut data) {
+            data.insert("description".to_string(), description.clone());
+        }
+        Some((snake_case(&name), data))
+    })
+}
+
+/// JSON Schema of the envelope of each event type, by event type
+pub fn event_schemas() -> Vec<(String, Value)> {
+    let definitions = root_schema()["definitions"].clone();
+    event_types()
+        .map(|(event_type, data)| {
+            let schema = json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "$id": format!("agent_graph/events/v{}/{}.json", EVENT_SCHEMA_VERSION, event_type),
+                "title": pascal_case(&event_type),
+                "type": "object",
+                "required": ["schema_version", "type", "execution_id", "timestamp", "data"],
+                "properties": {
+                    "schema_version": { "type": "integer", "minimum": 1, "maximum": EVENT_SCHEMA_VERSION },
+                    "type": { "const": event_type },
+                    "execution_id": { "type": "string", "format": "uuid" },
+                    "timestamp": { "type": "string", "format": "date-time" },
+                    "data": data,
+                },
+                "definitions": definitions,
+            });
+            (event_type, schema)
+        })
+        .collect()
+}
+
+/// TypeScript types and parser of the event client package
+pub fn typescript_client() -> String {
+    let root = root_schema();
+    let mut out = String::new();
+    out.push_str("// Generated from the execution event JSON Schemas by\n");
+    out.push_str("// `cargo run --example generate_event_client`. Do not edit.\n\n");
+    let _ = writeln!(out, "export const EVENT_SCHEMA_VERSION = {}\n", EVENT_SCHEMA_VERSION);
+
+    if let Some(definitions) = root["definitions"].as_object() {
+        for (name, schema) in definitions {
+            write_declaration(&mut out, name, schema);
+        }
+    }
+
+    let types: Vec<(String, Value)> = event_types().collect();
+    for (event_type, data) in &types {
+        write_declaration(&mut out, &format!("{}Data", pascal_case(event_type)), data);
+    }
+
+    out.push_str("interface Envelope<T extends string, D> {\n");
+    out.push_str("  schema_version: number\n  type: T\n  execution_id: string\n  timestamp: string\n  data: D\n}\n\n");
+    out.push_str("export type ExecutionEvent =\n");
+    for (event_type, _) in &types {
+        let _ = writeln!(out, "  | Envelope<'{}', {}Data>", event_type, pascal_case(event_type));
+    }
+    out.push_str("\nexport type ExecutionEventType = ExecutionEvent['type']\n\n");
+
+    out.push_str("export const EXECUTION_EVENT_TYPES: readonly ExecutionEventType[] = [\n");
+    for (event_type, _) in &types {
+        let _ = writeln!(out, "  '{}',", event_type);
+    }
+    out.push_str("]\n\n");
+
+    out.push_str(PARSER);
+    out
+}
+
+const PARSER: &str = r#"/**
+ * Parse an event received from AgentGraph.
+ *
+ * Returns null for event types this client does not know yet, which newer
+ * servers may send without a version bump. Throws for events written in a
+ * newer schema version, whose fields may have changed.
+ */
+export function parseExecutionEvent(message: string | unknown): ExecutionEvent | null {
+  const event = (typeof message === 'string' ? JSON.parse(message) : message) as ExecutionEvent
+  if (typeof event !== 'object' || event === null || typeof event.schema_version !== 'number') {
+    throw new Error('Not an AgentGraph execution event')
+  }
+  if (event.schema_version > EVENT_SCHEMA_VERSION) {
+    throw new Error(
+      `Event schema version ${event.schema_version} is newer than the supported version ${EVENT_SCHEMA_VERSION}`
+    )
+  }
+  return EXECUTION_EVENT_TYPES.includes(event.type) ? event : null
+}
+"#;
+
+/// Write the generated files of the TypeScript client package in `dir`
+pub fn write_typescript_client(dir: &Path) -> GraphResult<()> {
+    let schemas = dir.join("schemas");
+    std::fs::create_dir_all(dir.join("src"))?;
+    std::fs::create_dir_all(&schemas)?;
+    std::fs::write(dir.join("src").join("events.ts"), typescript_client())?;
+    for (event_type, schema) in event_schemas() {
+        let mut json = serde_json::to_string_pretty(&schema)?;
+        json.push('\n');
+        std::fs::write(schemas.join(format!("{}.json", event_type)), json)?;
+    }
+    Ok(())
+}
+
+fn write_declaration(out: &mut String, name: &str, schema: &Value) {
+    write_doc(out, schema, "");
+    match schema["properties"].as_object() {
+        Some(properties) => {
+            let _ = writeln!(out, "export interface {} {{", name);
+            write_properties(out, schema, properties, "  ");
+            out.push_str("}\n\n");
+        }
+        None => {
+            let _ = writeln!(out, "export type {} = {}\n", name, ts_type(schema));
+        }
+    }
+}
+
+fn write_properties(out: &mut String, schema: &Value, properties: &Map<String, Value>, indent: &str) {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    for (field, field_schema) in properties {
+        write_doc(out, field_schema, indent);
+        let optional = if required.contains(&field.as_str()) { "" } else { "?" };
+        let _ = writeln!(out, "{}{}{}: {}", indent, field, optional, ts_type(field_schema));
+    }
+}
+
+fn write_doc(out: &mut String, schema: &Value, indent: &str) {
+    if let Some(description) = schema["description"].as_str() {
+        let _ = writeln!(out, "{}/** {} */", indent, description.replace('\n', " "));
+    }
+}
+
+/// TypeScript type of a JSON Schema, for the subset schemars generates
+fn ts_type(schema: &Value) -> String {
+    let Value::Object(schema) = schema else {
+        // `true` accepts anything
+        return "unknown".to_string();
+    };
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_string();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string().replace('"', "'");
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return union(values.iter().map(|value| value.to_string().replace('"', "'")));
+    }
+    for key in ["oneOf", "anyOf", "allOf"] {
+        if let Some(members) = schema.get(key).and_then(Value::as_array) {
+            let join = if key == "allOf" { " & " } else { " | " };
+            return members.iter().map(ts_type).collect::<Vec<_>>().join(join);
+        }
+    }
+    match schema.get("type") {
+        Some(Value::String(kind)) => primitive(kind, schema),
+        Some(Value::Array(kinds)) => union(kinds.iter().filter_map(Value::as_str).map(|kind| primitive(kind, schema))),
+        _ => "unknown".to_string(),
+    }
+}
+
+fn primitive(kind: &str, schema: &Map<String, Value>) -> String {
+    match kind {
+        "string" => "string".to_string(),
+        "integer" | "number" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => match schema.get("items") {
+            Some(Value::Array(items)) => format!("[{}]", items.iter().map(ts_type).collect::<Vec<_>>().join(", ")),
+            Some(items) => {
+                let item = ts_type(items);
+                if item.contains(" | ") || item.contains(" & ") { format!("({})[]", item) } else { format!("{}[]", item) }
+            }
+            None => "unknown[]".to_string(),
+        },
+        "object" => match (schema.get("properties").and_then(Value::as_object), schema.get("additionalProperties")) {
+            (Some(properties), _) => {
+                let mut out = String::from("{\n");
+                write_properties(&mut out, &Value::Object(schema.clone()), properties, "    ");
+                out.push_str("  }");
+                out
+            }
+            (None, Some(Value::Object(values))) => format!("Record<string, {}>", ts_type(&Value::Object(values.clone()))),
+            _ => "Record<string, unknown>".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+fn union(members: impl Iterator<Item = String>) -> String {
+    let mut members: Vec<String> = members.collect();
+    members.dedup();
+    members.join(" | ")
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelopes_round_trip_and_client_is_current() {
+        let execution_id = Uuid::new_v4();
+        let events = vec![
+            ExecutionEvent::GraphCompleted {
+                execution_id,
+                timestamp: chrono::Utc::now(),
+                final_node: Some("answer".to_string()),
+                duration_ms: 42,
+                success: true,
+            },
+            ExecutionEvent::ParallelCompleted {
+                execution_id,
+                results: vec![("a".to_string(), true), ("b".to_string(), false)],
+                timestamp: chrono::Utc::now(),
+                duration_ms: 7,
+            },
+        ];
+        for event in events {
+            let envelope = event.to_envelope().unwrap();
+            assert_eq!(envelope.event_type, event.event_type());
+            assert!(envelope.data.get("execution_id").is_some());
+            let wire = serde_json::to_string(&envelope).unwrap();
+            let decoded = serde_json::from_str::<EventEnvelope>(&wire).unwrap().decode().unwrap().unwrap();
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&event).unwrap());
+        }
+
+        let mut envelope = ExecutionEvent::Custom {
+            execution_id,
+            event_type: "progress".to_string(),
+            data: json!(0.5).into(),
+            timestamp: chrono::Utc::now(),
+        }
+        .to_envelope()
+        .unwrap();
+        envelope.event_type = "budget_exhausted".to_string();
+        assert!(envelope.decode().unwrap().is_none());
+        envelope.schema_version = EVENT_SCHEMA_VERSION + 1;
+        assert!(envelope.decode().is_err());
+
+        let schemas = event_schemas();
+        assert!(schemas.iter().any(|(event_type, _)| event_type == "tool_output_chunk"));
+        assert_eq!(schemas[0].1["properties"]["type"]["const"], "graph_started");
+
+        assert_eq!(
+            typescript_client(),
+            include_str!("../../clients/typescript/src/events.ts"),
+            "the TypeScript client is out of date: run `cargo run --example generate_event_client`"
+        );
+    }
+}
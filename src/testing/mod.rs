@@ -0,0 +1,16 @@
+//! Helpers for testing graphs built on AgentGraph.
+//!
+//! [`snapshot`] compares final states, traces and event sequences against
+//! stored snapshots with the values that vary between runs normalized away.
+//! [`properties`] generates states and routing decisions with proptest and
+//! checks the invariants custom routers, conditions and reducers must hold.
+
+pub mod properties;
+pub mod snapshot;
+
+pub use insta;
+pub use proptest;
+
+#[cfg(feature = "streaming")]
+pub use snapshot::normalize_events;
+pub use snapshot::{normalize_state, normalize_trace, Normalizer, DURATION_KEYS};
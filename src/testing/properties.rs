@@ -0,0 +1,290 @@
+//! Property-based checks for routers, conditions and reducers.
+//!
+//! Custom edge logic is easy to get right for the states a developer thinks
+//! of and wrong for the rest. The strategies here generate states and the
+//! target lists routers are offered, and the checks fail a proptest case when
+//! a router picks a node it was not offered, a condition errors or answers
+//! differently for the same state, or a reducer breaks a law it claims.
+//!
+//! ```ignore
+//! use agent_graph::testing::properties::{check_router, routing_targets, StateFields};
+//! use agent_graph::testing::proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn router_stays_on_the_graph(
+//!         state in StateFields::new().field("score", 0..100i64).states::<Ticket>(),
+//!         targets in routing_targets(&["triage", "escalate", "close"]),
+//!     ) {
+//!         check_router(&PriorityRouter, &state, &targets)?;
+//!     }
+//! }
+//! ```
+//!
+//! Routers and conditions are async; the checks run them to completion on a
+//! runtime of their own, so call them from synchronous tests such as the ones
+//! `proptest!` generates, not from inside `#[tokio::test]`.
+
+use crate::edge::{DynamicRouter, EdgeCondition};
+use crate::node::NodeId;
+use crate::state::State;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt::Debug;
+use std::future::Future;
+
+/// JSON values of bounded depth and size
+pub fn json_values() -> BoxedStrategy<Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        (-1.0e6..1.0e6f64).prop_map(Value::from),
+        "[a-zA-Z0-9 _-]{0,16}".prop_map(Value::String),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::btree_map("[a-z_]{1,8}", inner, 0..4).prop_map(|fields| Value::Object(fields.into_iter().collect())),
+        ]
+    })
+    .boxed()
+}
+
+/// Generates states field by field
+///
+/// Each field gets its own strategy, so states hold the values a router
+/// actually sees rather than arbitrary JSON that would not deserialize.
+#[derive(Debug, Default)]
+pub struct StateFields {
+    fields: Vec<(String, BoxedStrategy<Value>)>,
+}
+
+impl StateFields {
+    /// Generate states with no fields set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw the field `key` from `strategy`
+    pub fn field<T, St>(mut self, key: impl Into<String>, strategy: St) -> Self
+    where
+        T: Into<Value> + Debug + 'static,
+        St: Strategy<Value = T> + 'static,
+    {
+        self.fields.push((key.into(), strategy.prop_map(Into::<Value>::into).boxed()));
+        self
+    }
+
+    /// Draw the field `key` from [`json_values`]
+    pub fn any_field(self, key: impl Into<String>) -> Self {
+        self.field(key, json_values())
+    }
+
+    /// Draw the field `key` from `strategy`, or leave it out
+    pub fn optional_field<T, St>(mut self, key: impl Into<String>, strategy: St) -> Self
+    where
+        T: Into<Value> + Debug + 'static,
+        St: Strategy<Value = T> + 'static,
+    {
+        let strategy = prop::option::of(strategy.prop_map(Into::<Value>::into)).prop_map(|value| value.unwrap_or(Value::Null));
+        self.fields.push((key.into(), strategy.boxed()));
+        self
+    }
+
+    /// The generated states as JSON objects; left-out fields are absent
+    pub fn objects(self) -> BoxedStrategy<Value> {
+        let (keys, strategies): (Vec<String>, Vec<BoxedStrategy<Value>>) = self.fields.into_iter().unzip();
+        strategies
+            .prop_map(move |values| {
+                Value::Object(keys.iter().cloned().zip(values).filter(|(_, value)| !value.is_null()).collect())
+            })
+            .boxed()
+    }
+
+    /// The generated states, deserialized into `S`
+    ///
+    /// Objects that do not deserialize are rejected, and proptest gives up if
+    /// too many are, so fields the state requires must always be drawn.
+    pub fn states<S: State + DeserializeOwned>(self) -> BoxedStrategy<S> {
+        self.objects()
+            .prop_filter_map("generated fields do not deserialize into the state", |object| serde_json::from_value(object).ok())
+            .boxed()
+    }
+}
+
+/// Target lists a router may be offered: a non-empty selection of `nodes`, in any order
+pub fn routing_targets(nodes: &[&str]) -> BoxedStrategy<Vec<NodeId>> {
+    let nodes: Vec<NodeId> = nodes.iter().map(|node| node.to_string()).collect();
+    let count = nodes.len();
+    prop::sample::subsequence(nodes, 1..=count.max(1)).prop_shuffle().boxed()
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    thread_local! {
+        static RUNTIME: tokio::runtime::Runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build the runtime for property checks");
+    }
+    RUNTIME.with(|runtime| runtime.block_on(future))
+}
+
+/// Check that `router` picks one of `targets` for `state`, returning its pick
+pub fn check_router<S, R>(router: &R, state: &S, targets: &[NodeId]) -> Result<NodeId, TestCaseError>
+where
+    S: State,
+    R: DynamicRouter<S> + ?Sized,
+{
+    let target = block_on(router.route(state, targets))
+        .map_err(|error| TestCaseError::fail(format!("router '{}' failed: {}", router.router_id(), error)))?;
+    prop_assert!(
+        targets.contains(&target),
+        "router '{}' chose '{}', which is not one of {:?}",
+        router.router_id(),
+        target,
+        targets
+    );
+    Ok(target)
+}
+
+/// Check that `condition` evaluates without error and gives the same answer twice, returning it
+pub fn check_condition<S, C>(condition: &C, state: &S) -> Result<bool, TestCaseError>
+where
+    S: State,
+    C: EdgeCondition<S> + ?Sized,
+{
+    let evaluate = || {
+        block_on(condition.evaluate(state))
+            .map_err(|error| TestCaseError::fail(format!("condition '{}' failed: {}", condition.condition_id(), error)))
+    };
+    let first = evaluate()?;
+    prop_assert_eq!(first, evaluate()?, "condition '{}' is not deterministic", condition.condition_id());
+    Ok(first)
+}
+
+/// Check that `reduce(reduce(a, b), c) == reduce(a, reduce(b, c))`
+pub fn check_associative<T, F>(reduce: F, a: T, b: T, c: T) -> Result<(), TestCaseError>
+where
+    T: Clone + PartialEq + Debug,
+    F: Fn(T, T) -> T,
+{
+    let left = reduce(reduce(a.clone(), b.clone()), c.clone());
+    let right = reduce(a.clone(), reduce(b.clone(), c.clone()));
+    prop_assert_eq!(left, right, "reducer is not associative for {:?}, {:?}, {:?}", a, b, c);
+    Ok(())
+}
+
+/// Check that `reduce(a, b) == reduce(b, a)`
+pub fn check_commutative<T, F>(reduce: F, a: T, b: T) -> Result<(), TestCaseError>
+where
+    T: Clone + PartialEq + Debug,
+    F: Fn(T, T) -> T,
+{
+    let forward = reduce(a.clone(), b.clone());
+    let backward = reduce(b.clone(), a.clone());
+    prop_assert_eq!(forward, backward, "reducer is not commutative for {:?}, {:?}", a, b);
+    Ok(())
+}
+
+/// Check that `identity` leaves `a` unchanged on either side
+pub fn check_identity<T, F>(reduce: F, identity: T, a: T) -> Result<(), TestCaseError>
+where
+    T: Clone + PartialEq + Debug,
+    F: Fn(T, T) -> T,
+{
+    prop_assert_eq!(reduce(identity.clone(), a.clone()), a.clone(), "{:?} is not a left identity for {:?}", identity, a);
+    prop_assert_eq!(reduce(a.clone(), identity.clone()), a.clone(), "{:?} is not a right identity for {:?}", identity, a);
+    Ok(())
+}
+
+/// Laws a reducer claims to hold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReducerLaws {
+    /// The grouping of reductions does not matter
+    pub associative: bool,
+    /// The order of the reduced values does not matter, e.g. for parallel branches
+    pub commutative: bool,
+}
+
+impl ReducerLaws {
+    /// Associative and commutative, as reducers of parallel branches must be
+    pub fn parallel() -> Self {
+        Self { associative: true, commutative: true }
+    }
+}
+
+/// Check every law `laws` claims for `reduce`
+pub fn check_reducer<T, F>(reduce: F, laws: ReducerLaws, a: T, b: T, c: T) -> Result<(), TestCaseError>
+where
+    T: Clone + PartialEq + Debug,
+    F: Fn(T, T) -> T,
+{
+    if laws.associative {
+        check_associative(&reduce, a.clone(), b.clone(), c)?;
+    }
+    if laws.commutative {
+        check_commutative(&reduce, a, b)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GraphResult;
+    use async_trait::async_trait;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Ticket {
+        score: i64,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    /// Escalates high scores, and forgets to check what it was offered
+    #[derive(Debug)]
+    struct PriorityRouter;
+
+    #[async_trait]
+    impl DynamicRouter<Ticket> for PriorityRouter {
+        async fn route(&self, state: &Ticket, possible_targets: &[NodeId]) -> GraphResult<NodeId> {
+            if state.score > 90 {
+                return Ok("escalate".to_string());
+            }
+            Ok(possible_targets[state.tags.len() % possible_targets.len()].clone())
+        }
+
+        fn router_id(&self) -> String {
+            "priority".to_string()
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_properties_hold_for_valid_logic(
+            state in StateFields::new()
+                .field("score", 0..=90i64)
+                .optional_field("tags", prop::collection::vec("[a-z]{1,4}", 0..3))
+                .states::<Ticket>(),
+            targets in routing_targets(&["triage", "escalate", "close"]),
+            (a, b, c) in (any::<i32>(), any::<i32>(), any::<i32>()),
+        ) {
+            check_router(&PriorityRouter, &state, &targets)?;
+            check_reducer(|x: i32, y: i32| x.max(y), ReducerLaws::parallel(), a, b, c)?;
+            check_identity(|x: i32, y: i32| x.max(y), i32::MIN, a)?;
+        }
+    }
+
+    #[test]
+    fn test_properties_catch_broken_logic() {
+        let ticket = Ticket { score: 95, tags: Vec::new() };
+        assert!(check_router(&PriorityRouter, &ticket, &["triage".to_string()]).is_err());
+        let concat = |x: String, y: String| x + &y;
+        assert!(check_associative(concat, "a".to_string(), "b".to_string(), "c".to_string()).is_ok());
+        assert!(check_commutative(concat, "a".to_string(), "b".to_string()).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Snapshot assertions for graph state, traces and events.
+//!
+//! The final state of a graph run and the events or trace it produced make
+//! good snapshots, except for the values that change on every run: execution
+//! and checkpoint IDs, timestamps and durations. The macros here normalize
+//! those before handing the value to [insta](https://insta.rs), so a snapshot
+//! only changes when the behavior does.
+//!
+//! ```ignore
+//! use agent_graph::{assert_events_snapshot, assert_state_snapshot};
+//!
+//! let context = engine.execute(&graph, &mut state).await?;
+//! assert_state_snapshot!("refund_flow_state", state);
+//! assert_events_snapshot!("refund_flow_events", events);
+//! ```
+//!
+//! UUIDs are replaced with `[uuid-1]`, `[uuid-2]` and so on in the order they
+//! first appear, so a snapshot still shows which events belong to the same
+//! execution. Timestamps become `[timestamp]` and the values under duration
+//! keys such as `duration_ms` become `[duration]`. A [`Normalizer`] redacts
+//! further keys. Snapshots are reviewed and updated with `cargo insta review`.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+/// Keys whose values are replaced with `[duration]` by default
+pub const DURATION_KEYS: &[&str] = &["duration_ms", "latency", "latency_ms", "elapsed_ms"];
+
+fn uuid_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b").expect("valid UUID pattern")
+    })
+}
+
+fn timestamp_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2}| UTC)?").expect("valid timestamp pattern")
+    })
+}
+
+/// Replaces the values of a snapshot that differ between runs
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    redacted: HashMap<String, String>,
+    ignored: BTreeSet<String>,
+    uuids: bool,
+    timestamps: bool,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            redacted: DURATION_KEYS.iter().map(|key| (key.to_string(), "[duration]".to_string())).collect(),
+            ignored: BTreeSet::new(),
+            uuids: true,
+            timestamps: true,
+        }
+    }
+}
+
+impl Normalizer {
+    /// Normalize UUIDs, timestamps and durations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the value under `key`, wherever it appears, with `placeholder`
+    pub fn redact_key(mut self, key: impl Into<String>, placeholder: impl Into<String>) -> Self {
+        self.redacted.insert(key.into(), placeholder.into());
+        self
+    }
+
+    /// Leave `key` out of the snapshot wherever it appears
+    pub fn ignore_key(mut self, key: impl Into<String>) -> Self {
+        self.ignored.insert(key.into());
+        self
+    }
+
+    /// Keep UUIDs as they are
+    pub fn keep_uuids(mut self) -> Self {
+        self.uuids = false;
+        self
+    }
+
+    /// Keep timestamps as they are
+    pub fn keep_timestamps(mut self) -> Self {
+        self.timestamps = false;
+        self
+    }
+
+    /// Serialize `value` and normalize it
+    ///
+    /// Values that do not serialize become a string naming the error, which
+    /// then shows up in the snapshot.
+    pub fn normalize<T: Serialize + ?Sized>(&self, value: &T) -> Value {
+        match serde_json::to_value(value) {
+            Ok(value) => self.normalize_value(value),
+            Err(error) => Value::String(format!("[unserializable: {}]", error)),
+        }
+    }
+
+    /// Normalize a JSON value
+    pub fn normalize_value(&self, value: Value) -> Value {
+        let mut uuids = HashMap::new();
+        self.walk(value, &mut uuids)
+    }
+
+    fn walk(&self, value: Value, uuids: &mut HashMap<String, usize>) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.normalize_text(&text, uuids)),
+            Value::Array(items) => Value::Array(items.into_iter().map(|item| self.walk(item, uuids)).collect()),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .filter(|(key, _)| !self.ignored.contains(key))
+                    .map(|(key, value)| {
+                        let value = match self.redacted.get(&key) {
+                            Some(_) if value.is_null() => Value::Null,
+                            Some(placeholder) => Value::String(placeholder.clone()),
+                            None => self.walk(value, uuids),
+                        };
+                        // Keys can hold IDs too, e.g. maps by execution ID
+                        (self.normalize_text(&key, uuids), value)
+                    })
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    fn normalize_text(&self, text: &str, uuids: &mut HashMap<String, usize>) -> String {
+        let mut text = text.to_string();
+        if self.timestamps {
+            text = timestamp_pattern().replace_all(&text, "[timestamp]").into_owned();
+        }
+        if self.uuids {
+            text = uuid_pattern()
+                .replace_all(&text, |found: &regex::Captures<'_>| {
+                    let next = uuids.len() + 1;
+                    format!("[uuid-{}]", uuids.entry(found[0].to_lowercase()).or_insert(next))
+                })
+                .into_owned();
+        }
+        text
+    }
+}
+
+/// Normalize a graph state for a snapshot
+pub fn normalize_state<S: Serialize + ?Sized>(state: &S) -> Value {
+    Normalizer::new().normalize(state)
+}
+
+/// Normalize a trace, or any serializable record of an execution, for a snapshot
+pub fn normalize_trace<T: Serialize + ?Sized>(trace: &T) -> Value {
+    Normalizer::new().normalize(trace)
+}
+
+/// Normalize a sequence of execution events for a snapshot
+///
+/// Each event is written as its type and data, as in the wire format.
+#[cfg(feature = "streaming")]
+pub fn normalize_events(events: &[crate::streaming::ExecutionEvent]) -> Value {
+    let events: Vec<Value> = events
+        .iter()
+        .map(|event| {
+            let data = event.to_envelope().map(|envelope| envelope.data).unwrap_or_default();
+            serde_json::json!({ "type": event.event_type(), "data": data })
+        })
+        .collect();
+    Normalizer::new().normalize_value(Value::Array(events))
+}
+
+/// Assert that a graph state matches its snapshot, after normalization
+///
+/// Takes the state, optionally preceded by the snapshot name or followed by
+/// an inline snapshot, like insta's `assert_json_snapshot!`.
+#[macro_export]
+macro_rules! assert_state_snapshot {
+    ($state:expr $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($crate::testing::normalize_state(&$state))
+    };
+    ($state:expr, @$snapshot:literal $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($crate::testing::normalize_state(&$state), @$snapshot)
+    };
+    ($name:expr, $state:expr $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($name, $crate::testing::normalize_state(&$state))
+    };
+}
+
+/// Assert that a trace, or any serializable record of an execution, matches its snapshot
+#[macro_export]
+macro_rules! assert_trace_snapshot {
+    ($trace:expr $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($crate::testing::normalize_trace(&$trace))
+    };
+    ($trace:expr, @$snapshot:literal $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($crate::testing::normalize_trace(&$trace), @$snapshot)
+    };
+    ($name:expr, $trace:expr $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($name, $crate::testing::normalize_trace(&$trace))
+    };
+}
+
+/// Assert that a sequence of execution events matches its snapshot
+///
+/// Needs the `streaming` feature.
+#[macro_export]
+macro_rules! assert_events_snapshot {
+    ($events:expr $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($crate::testing::normalize_events(&$events))
+    };
+    ($events:expr, @$snapshot:literal $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($crate::testing::normalize_events(&$events), @$snapshot)
+    };
+    ($name:expr, $events:expr $(,)?) => {
+        $crate::testing::insta::assert_json_snapshot!($name, $crate::testing::normalize_events(&$events))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalizer_strips_ids_times_and_durations() {
+        let run = "4f1c2d3e-5a6b-4c7d-8e9f-0a1b2c3d4e5f";
+        let checkpoint = "9a8b7c6d-5e4f-4a3b-8c2d-1e0f9a8b7c6d";
+        let trace = json!({
+            "execution_id": run,
+            "events": [
+                {"at": "2026-10-16T09:30:12.345678Z", "node": "plan", "duration_ms": 12, "note": format!("run {} saved", run)},
+                {"at": "2026-10-16T09:30:13+02:00", "checkpoint": checkpoint, "duration_ms": null, "secret": "k"},
+            ],
+            "by_execution": {run: 1},
+        });
+
+        let normalizer = Normalizer::new().ignore_key("secret");
+        assert_eq!(
+            normalizer.normalize(&trace),
+            json!({
+                "execution_id": "[uuid-1]",
+                "events": [
+                    {"at": "[timestamp]", "node": "plan", "duration_ms": "[duration]", "note": "run [uuid-1] saved"},
+                    {"at": "[timestamp]", "checkpoint": "[uuid-2]", "duration_ms": null},
+                ],
+                "by_execution": {"[uuid-1]": 1},
+            })
+        );
+
+        assert_state_snapshot!(json!({"answer": 42, "trace_id": run}), @r###"
+        {
+          "answer": 42,
+          "trace_id": "[uuid-1]"
+        }
+        "###);
+    }
+}
@@ -0,0 +1,45 @@
+// Human approval for tools that act on the outside world
+// Shared by tools that send messages or invitations on a user's behalf
+
+use crate::human::traits::{HumanInput, HumanInteraction};
+use crate::human::{HumanConfig, HumanContext};
+use crate::tools::traits::{ToolError, ToolInput, ToolResult};
+use std::sync::Arc;
+
+/// Ask a human to approve `action` before the tool performs it
+///
+/// Fails with `PermissionDenied` when no approver is configured, the request
+/// fails, or the human declines.
+pub(crate) async fn require_approval(
+    approver: Option<&Arc<dyn HumanInteraction>>,
+    action: &str,
+    details: &[(&str, String)],
+    input: &ToolInput,
+) -> ToolResult<()> {
+    let approver = approver.ok_or_else(|| ToolError::PermissionDenied {
+        message: format!("'{}' requires human approval, but no approver is configured", action),
+    })?;
+
+    let mut prompt = HumanInput::approval(format!("Allow agent to {}?", action));
+    for (key, value) in details {
+        prompt = prompt.with_metadata(key, value);
+    }
+
+    let mut context = HumanContext::new(uuid::Uuid::new_v4().to_string());
+    if let Some(user_id) = input.get_context("user_id") {
+        context = context.with_user_id(user_id.clone());
+    }
+
+    let response = approver.request_input(prompt, &context, &HumanConfig::default()).await
+        .map_err(|e| ToolError::PermissionDenied {
+            message: format!("Approval to {} failed: {}", action, e),
+        })?;
+
+    if response.as_bool() == Some(true) {
+        Ok(())
+    } else {
+        Err(ToolError::PermissionDenied {
+            message: format!("Request to {} was not approved", action),
+        })
+    }
+}
@@ -0,0 +1,362 @@
+// Calendar tools for assistant and support agents
+// Creates events in a CalDAV calendar collection
+
+use super::approval::require_approval;
+use super::egress::check_sandbox_egress;
+use crate::human::traits::HumanInteraction;
+use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::sync::Arc;
+
+/// Calendar event to create
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    /// Event UID; generated when omitted
+    #[serde(default = "new_event_uid")]
+    pub uid: String,
+    /// Title
+    pub summary: String,
+    /// Longer description
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Where the event takes place
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Start time
+    pub start: DateTime<Utc>,
+    /// End time
+    pub end: DateTime<Utc>,
+    /// Attendee email addresses, who receive invitations
+    #[serde(default)]
+    pub attendees: Vec<String>,
+}
+
+fn new_event_uid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+impl CalendarEvent {
+    /// Create an event without attendees
+    pub fn new(summary: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            uid: new_event_uid(),
+            summary: summary.to_string(),
+            description: None,
+            location: None,
+            start,
+            end,
+            attendees: Vec::new(),
+        }
+    }
+
+    /// Check that the event is well formed
+    pub fn validate(&self) -> ToolResult<()> {
+        if self.summary.trim().is_empty() {
+            return Err(ToolError::ValidationError {
+                message: "Event summary is required".to_string(),
+            });
+        }
+        if self.end <= self.start {
+            return Err(ToolError::ValidationError {
+                message: "Event must end after it starts".to_string(),
+            });
+        }
+        if self.uid.is_empty() || !self.uid.chars().all(|c| c.is_ascii_alphanumeric() || "-_.@".contains(c)) {
+            return Err(ToolError::ValidationError {
+                message: format!("Invalid event UID '{}'", self.uid),
+            });
+        }
+        Ok(())
+    }
+
+    /// Render the event as an iCalendar (RFC 5545) document
+    pub fn to_ical(&self) -> String {
+        let timestamp = |t: &DateTime<Utc>| t.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//AgentGraph//Calendar Tool//EN".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", self.uid),
+            format!("DTSTAMP:{}", timestamp(&Utc::now())),
+            format!("DTSTART:{}", timestamp(&self.start)),
+            format!("DTEND:{}", timestamp(&self.end)),
+            format!("SUMMARY:{}", ical_escape(&self.summary)),
+        ];
+        if let Some(description) = &self.description {
+            lines.push(format!("DESCRIPTION:{}", ical_escape(description)));
+        }
+        if let Some(location) = &self.location {
+            lines.push(format!("LOCATION:{}", ical_escape(location)));
+        }
+        for attendee in &self.attendees {
+            lines.push(format!("ATTENDEE;RSVP=TRUE:mailto:{}", attendee));
+        }
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+        lines.iter().map(|line| fold_ical_line(line)).collect::<Vec<_>>().join("\r\n") + "\r\n"
+    }
+}
+
+/// Escape text property values
+fn ical_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold lines longer than 75 octets, never splitting a character
+fn fold_ical_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+/// Stores calendar events
+#[async_trait]
+pub trait CalendarClient: Send + Sync + fmt::Debug {
+    /// Create an event, returning its URL
+    async fn create_event(&self, event: &CalendarEvent) -> ToolResult<String>;
+}
+
+/// CalDAV calendar collection settings
+#[derive(Clone, Deserialize)]
+pub struct CalDavConfig {
+    /// Calendar collection URL, e.g. `https://dav.example.com/calendars/agent/work/`
+    pub collection_url: String,
+    /// Login user name
+    pub username: String,
+    /// Login password
+    pub password: String,
+}
+
+impl fmt::Debug for CalDavConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CalDavConfig")
+            .field("collection_url", &self.collection_url)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// CalDAV client
+#[derive(Debug, Clone)]
+pub struct CalDavClient {
+    config: CalDavConfig,
+    client: reqwest::Client,
+}
+
+impl CalDavClient {
+    /// Create a client for a calendar collection
+    pub fn new(config: CalDavConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn event_url(&self, uid: &str) -> String {
+        format!("{}/{}.ics", self.config.collection_url.trim_end_matches('/'), uid)
+    }
+}
+
+#[async_trait]
+impl CalendarClient for CalDavClient {
+    async fn create_event(&self, event: &CalendarEvent) -> ToolResult<String> {
+        let url = self.event_url(&event.uid);
+        check_sandbox_egress(&url).await?;
+        let response = self.client
+            .put(&url)
+            .basic_auth(&self.config.username, Some(&self.config.password))
+            .header(reqwest::header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+            // Never overwrite an existing event with the same UID
+            .header(reqwest::header::IF_NONE_MATCH, "*")
+            .body(event.to_ical())
+            .send()
+            .await
+            .map_err(|e| ToolError::NetworkError {
+                message: format!("CalDAV request failed: {}", e),
+            })?;
+
+        match response.status() {
+            status if status.is_success() => Ok(url),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Err(ToolError::AuthenticationError {
+                message: format!("CalDAV server rejected credentials for {}", self.config.username),
+            }),
+            reqwest::StatusCode::PRECONDITION_FAILED => Err(ToolError::ValidationError {
+                message: format!("An event with UID '{}' already exists", event.uid),
+            }),
+            status => Err(ToolError::ExecutionError {
+                message: format!("CalDAV server returned {}", status),
+            }),
+        }
+    }
+}
+
+/// Tool for creating calendar events
+///
+/// Events with attendees send invitations, so creating one needs human
+/// approval unless approval is explicitly turned off.
+#[derive(Debug)]
+pub struct CreateCalendarEventTool {
+    metadata: ToolMetadata,
+    client: Arc<dyn CalendarClient>,
+    approver: Option<Arc<dyn HumanInteraction>>,
+    require_approval: bool,
+}
+
+impl CreateCalendarEventTool {
+    /// Create a tool writing to a calendar
+    pub fn new(client: Arc<dyn CalendarClient>) -> Self {
+        let metadata = ToolMetadata::new(
+            "calendar_create_event",
+            "Create Calendar Event",
+            "Create a calendar event and invite attendees"
+        )
+        .with_namespace("calendar")
+        .with_tag("calendar")
+        .with_tag("communication")
+        .with_deterministic(false)
+        .with_side_effects(true)
+        .with_estimated_duration_ms(1500);
+
+        Self {
+            metadata,
+            client,
+            approver: None,
+            require_approval: true,
+        }
+    }
+
+    /// Ask a human before sending invitations
+    pub fn with_approver(mut self, approver: Arc<dyn HumanInteraction>) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+
+    /// Turn approval off
+    pub fn without_approval(mut self) -> Self {
+        self.require_approval = false;
+        self
+    }
+
+    fn parse_event(input: &ToolInput) -> ToolResult<CalendarEvent> {
+        let event: CalendarEvent = serde_json::from_value(input.data.clone()).map_err(|e| ToolError::ValidationError {
+            message: format!("Invalid calendar event: {}", e),
+        })?;
+        event.validate()?;
+        Ok(event)
+    }
+}
+
+#[async_trait]
+impl Tool for CreateCalendarEventTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let event = Self::parse_event(&input)?;
+
+        if self.require_approval && !event.attendees.is_empty() {
+            require_approval(
+                self.approver.as_ref(),
+                &format!("send invitations for '{}' to {}", event.summary, event.attendees.join(", ")),
+                &[
+                    ("summary", event.summary.clone()),
+                    ("start", event.start.to_rfc3339()),
+                    ("end", event.end.to_rfc3339()),
+                    ("attendees", event.attendees.join(", ")),
+                ],
+                &input,
+            ).await?;
+        }
+
+        let url = self.client.create_event(&event).await?;
+
+        Ok(ToolOutput::new(json!({
+            "uid": event.uid,
+            "url": url,
+            "summary": event.summary,
+            "start": event.start,
+            "end": event.end,
+            "attendees": event.attendees
+        }))
+        .with_metadata("url", &url)
+        .with_metric("attendees", event.attendees.len() as f64))
+    }
+
+    async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
+        Self::parse_event(input).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default)]
+    struct MemoryCalendar {
+        events: Mutex<Vec<CalendarEvent>>,
+    }
+
+    #[async_trait]
+    impl CalendarClient for MemoryCalendar {
+        async fn create_event(&self, event: &CalendarEvent) -> ToolResult<String> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(format!("memory://{}", event.uid))
+        }
+    }
+
+    #[test]
+    fn test_ical_rendering() {
+        let start = "2026-03-02T15:00:00Z".parse().unwrap();
+        let end = "2026-03-02T15:30:00Z".parse().unwrap();
+        let mut event = CalendarEvent::new("Sync; review, plan", start, end);
+        event.description = Some("x".repeat(100));
+        let ical = event.to_ical();
+
+        assert!(ical.contains("DTSTART:20260302T150000Z\r\n"));
+        assert!(ical.contains("SUMMARY:Sync\\; review\\, plan\r\n"));
+        assert!(ical.lines().all(|line| line.len() <= 75));
+        assert!(ical.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_invitations_need_approval() {
+        let calendar = Arc::new(MemoryCalendar::default());
+        let tool = CreateCalendarEventTool::new(calendar.clone());
+
+        let solo = json!({"summary": "Focus time", "start": "2026-03-02T09:00:00Z", "end": "2026-03-02T10:00:00Z"});
+        tool.execute(ToolInput::new(solo)).await.unwrap();
+
+        let meeting = json!({
+            "summary": "Onboarding call",
+            "start": "2026-03-02T11:00:00Z",
+            "end": "2026-03-02T11:30:00Z",
+            "attendees": ["customer@example.com"]
+        });
+        let result = tool.execute(ToolInput::new(meeting)).await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied { .. })));
+
+        let backwards = json!({"summary": "Oops", "start": "2026-03-02T11:00:00Z", "end": "2026-03-02T10:00:00Z"});
+        assert!(tool.validate_input(&ToolInput::new(backwards)).await.is_err());
+        assert_eq!(calendar.events.lock().unwrap().len(), 1);
+    }
+}
@@ -18,6 +18,7 @@ impl SqlQueryTool {
             "SQL Query",
             "Execute SQL queries against databases"
         )
+        .with_namespace("data")
         .with_tag("database")
         .with_tag("sql")
         .with_tag("query")
@@ -78,6 +79,7 @@ impl JsonQueryTool {
             "JSON Query",
             "Query and filter JSON data using JSONPath expressions"
         )
+        .with_namespace("data")
         .with_tag("database")
         .with_tag("json")
         .with_tag("query")
@@ -0,0 +1,592 @@
+// Document parsing tools for retrieval-augmented generation
+// Turn PDF, DOCX, HTML, Markdown and text files into normalized text with page and section metadata, ready to chunk
+
+use super::sandbox::FsSandbox;
+use crate::agents::vector::VectorRecord;
+use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Formats documents can be parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    /// Plain text; form feeds separate pages
+    Text,
+    /// Markdown; headings start sections
+    Markdown,
+    /// HTML; needs the `documents` feature
+    Html,
+    /// PDF; needs the `documents` feature, and `ocr` for scanned pages
+    Pdf,
+    /// Word documents; needs the `documents` feature
+    Docx,
+    /// PNG, JPEG or TIFF images; needs the `ocr` feature
+    Image,
+}
+
+impl DocumentFormat {
+    /// Format named by a file extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "txt" | "text" | "log" | "csv" => Some(Self::Text),
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" | "htm" | "xhtml" => Some(Self::Html),
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "png" | "jpg" | "jpeg" | "tif" | "tiff" => Some(Self::Image),
+            _ => None,
+        }
+    }
+
+    /// Format recognized from the first bytes of the content
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        let head = &bytes[..bytes.len().min(512)];
+        if head.starts_with(b"%PDF-") {
+            return Some(Self::Pdf);
+        }
+        // DOCX files are ZIP archives
+        if head.starts_with(b"PK\x03\x04") {
+            return Some(Self::Docx);
+        }
+        if head.starts_with(b"\x89PNG") || head.starts_with(b"\xFF\xD8\xFF") || head.starts_with(b"II*\x00") || head.starts_with(b"MM\x00*") {
+            return Some(Self::Image);
+        }
+        let text = String::from_utf8_lossy(head).trim_start().to_ascii_lowercase();
+        if text.starts_with("<!doctype html") || text.starts_with("<html") {
+            return Some(Self::Html);
+        }
+        std::str::from_utf8(head).is_ok().then_some(Self::Text)
+    }
+}
+
+/// A part of a document under one heading, on one page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentSection {
+    /// Heading the section starts with, if any
+    pub heading: Option<String>,
+    /// Heading level, 1 for top-level; 0 without a heading
+    pub level: u8,
+    /// Page number, starting at 1, for formats with pages
+    pub page: Option<u32>,
+    /// Normalized text of the section, heading excluded
+    pub text: String,
+}
+
+/// Text and structure extracted from a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedDocument {
+    /// Format the document was parsed as
+    pub format: DocumentFormat,
+    /// Sections in reading order
+    pub sections: Vec<DocumentSection>,
+    /// Title, author, page count and other properties found in the document
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl ParsedDocument {
+    /// Document made of `sections`, with empty ones dropped
+    pub fn new(format: DocumentFormat, sections: Vec<DocumentSection>) -> Self {
+        let sections = sections
+            .into_iter()
+            .filter(|section| !section.text.is_empty() || section.heading.is_some())
+            .collect();
+        Self { format, sections, metadata: HashMap::new() }
+    }
+
+    /// Add a property to the metadata
+    pub fn with_metadata<V: Serialize>(mut self, key: &str, value: V) -> Self {
+        self.metadata.insert(key.to_string(), serde_json::to_value(value).unwrap_or(serde_json::Value::Null));
+        self
+    }
+
+    /// Whole text, headings included, sections separated by blank lines
+    pub fn text(&self) -> String {
+        self.sections
+            .iter()
+            .map(|section| match &section.heading {
+                Some(heading) if section.text.is_empty() => heading.clone(),
+                Some(heading) => format!("{}\n\n{}", heading, section.text),
+                None => section.text.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Split into chunks that stay within one section and page
+    ///
+    /// Every chunk carries `source`, `format`, `chunk_index` and, when known,
+    /// `page` and `section` metadata, plus the document's `title`.
+    pub fn chunks(&self, source: &str, options: &ChunkOptions) -> Vec<DocumentChunk> {
+        let mut chunks = Vec::new();
+        for section in &self.sections {
+            for text in split_text(&section.text, options) {
+                let mut metadata = HashMap::from([
+                    ("source".to_string(), json!(source)),
+                    ("format".to_string(), json!(self.format)),
+                    ("chunk_index".to_string(), json!(chunks.len())),
+                ]);
+                if let Some(page) = section.page {
+                    metadata.insert("page".to_string(), json!(page));
+                }
+                if let Some(heading) = &section.heading {
+                    metadata.insert("section".to_string(), json!(heading));
+                }
+                if let Some(title) = self.metadata.get("title") {
+                    metadata.insert("title".to_string(), title.clone());
+                }
+                chunks.push(DocumentChunk {
+                    id: format!("{}#{}", source, chunks.len()),
+                    text,
+                    metadata,
+                });
+            }
+        }
+        chunks
+    }
+}
+
+/// How documents are split for embedding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkOptions {
+    /// Longest chunk, in characters
+    pub max_chars: usize,
+    /// Characters repeated from the end of one chunk at the start of the next
+    pub overlap: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self { max_chars: 1500, overlap: 150 }
+    }
+}
+
+/// A piece of a document sized for embedding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentChunk {
+    /// `<source>#<index>`
+    pub id: String,
+    /// Chunk text
+    pub text: String,
+    /// Source, format, chunk index, page, section and title
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl DocumentChunk {
+    /// Vector store record of the chunk
+    pub fn into_record(self, embedding: Vec<f32>) -> VectorRecord {
+        self.metadata
+            .into_iter()
+            .fold(VectorRecord::new(&self.id, &self.text, embedding), |record, (key, value)| record.with_metadata(&key, value))
+    }
+}
+
+/// Split `text` at paragraph, then sentence, then word boundaries into pieces of at most `max_chars`
+fn split_text(text: &str, options: &ChunkOptions) -> Vec<String> {
+    let max_chars = options.max_chars.max(1);
+    let overlap = options.overlap.min(max_chars / 2);
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for (piece, paragraph_start) in pieces(text, max_chars) {
+        let separator = if paragraph_start { "\n\n" } else { " " };
+        let fits = |current: &str| current.is_empty() || current.chars().count() + separator.len() + piece.chars().count() <= max_chars;
+        if !fits(&current) {
+            let carried = tail(&current, overlap);
+            chunks.push(std::mem::take(&mut current));
+            current = carried;
+            if !fits(&current) {
+                current.clear();
+            }
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(piece);
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Paragraphs, with those longer than `max_chars` broken into sentences and then words,
+/// each flagged when it starts a paragraph
+fn pieces(text: &str, max_chars: usize) -> Vec<(&str, bool)> {
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let start = pieces.len();
+        if paragraph.chars().count() <= max_chars {
+            pieces.push((paragraph, false));
+        } else {
+            for sentence in paragraph.split_inclusive(['.', '!', '?']).map(str::trim).filter(|s| !s.is_empty()) {
+                if sentence.chars().count() <= max_chars {
+                    pieces.push((sentence, false));
+                } else {
+                    pieces.extend(sentence.split_whitespace().map(|word| (word, false)));
+                }
+            }
+        }
+        if let Some(first) = pieces.get_mut(start) {
+            first.1 = true;
+        }
+    }
+    pieces
+}
+
+/// Last `chars` characters of `text`, starting at a word boundary
+fn tail(text: &str, chars: usize) -> String {
+    if chars == 0 {
+        return String::new();
+    }
+    let start = text.char_indices().rev().nth(chars - 1).map_or(0, |(index, _)| index);
+    let tail = &text[start..];
+    match tail.find(char::is_whitespace) {
+        Some(space) if start > 0 => tail[space..].trim_start().to_string(),
+        _ => tail.to_string(),
+    }
+}
+
+/// Clean up extracted text
+///
+/// Joins words hyphenated across line breaks, collapses runs of spaces,
+/// drops control characters and keeps at most one blank line in a row.
+pub fn normalize_text(text: &str) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<String> = Vec::new();
+    let mut blank = false;
+    for line in text.lines() {
+        let line: String = line
+            .chars()
+            .map(|c| if c == '\t' || c == '\u{a0}' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        let line = line.split(' ').filter(|word| !word.is_empty()).collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank = !lines.is_empty();
+            continue;
+        }
+        match lines.last_mut() {
+            // A word split over two lines
+            Some(previous) if !blank && previous.ends_with('-') && line.starts_with(char::is_lowercase) && previous.len() > 1 => {
+                previous.pop();
+                previous.push_str(&line);
+            }
+            _ => {
+                if blank {
+                    lines.push(String::new());
+                }
+                lines.push(line);
+            }
+        }
+        blank = false;
+    }
+    lines.join("\n")
+}
+
+/// Sections of Markdown-style text, started by `#` headings
+///
+/// Used for Markdown and for HTML, which is rendered with such headings.
+pub(crate) fn markdown_sections(text: &str, page: Option<u32>) -> Vec<DocumentSection> {
+    let mut sections = vec![DocumentSection { heading: None, level: 0, page, text: String::new() }];
+    let mut body = String::new();
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let level = line.chars().take_while(|c| *c == '#').count();
+        let heading = line[level..].strip_prefix(' ').map(str::trim).filter(|heading| !heading.is_empty());
+        match heading {
+            Some(heading) if !in_code && (1..=6).contains(&level) => {
+                if let Some(section) = sections.last_mut() {
+                    section.text = normalize_text(&std::mem::take(&mut body));
+                }
+                sections.push(DocumentSection { heading: Some(heading.to_string()), level: level as u8, page, text: String::new() });
+            }
+            _ => {
+                body.push_str(line);
+                body.push('\n');
+            }
+        }
+    }
+    if let Some(section) = sections.last_mut() {
+        section.text = normalize_text(&body);
+    }
+    sections
+}
+
+/// Parse `bytes` as `format`
+///
+/// Formats other than text and Markdown need the `documents` feature; images
+/// and scanned PDF pages need `ocr`, which runs the `tesseract` and
+/// `pdftoppm` programs.
+pub fn parse_document(bytes: &[u8], format: DocumentFormat) -> ToolResult<ParsedDocument> {
+    match format {
+        DocumentFormat::Text => {
+            let text = String::from_utf8_lossy(bytes);
+            let pages: Vec<&str> = text.split('\x0c').collect();
+            let paged = pages.len() > 1;
+            let sections = pages
+                .iter()
+                .enumerate()
+                .map(|(index, page)| DocumentSection {
+                    heading: None,
+                    level: 0,
+                    page: paged.then_some(index as u32 + 1),
+                    text: normalize_text(page),
+                })
+                .collect();
+            Ok(ParsedDocument::new(format, sections))
+        }
+        DocumentFormat::Markdown => {
+            let document = ParsedDocument::new(format, markdown_sections(&String::from_utf8_lossy(bytes), None));
+            let title = document.sections.iter().find(|section| section.level == 1).and_then(|section| section.heading.clone());
+            Ok(match title {
+                Some(title) => document.with_metadata("title", title),
+                None => document,
+            })
+        }
+        #[cfg(feature = "documents")]
+        DocumentFormat::Html => super::document_formats::parse_html(bytes),
+        #[cfg(feature = "documents")]
+        DocumentFormat::Pdf => super::document_formats::parse_pdf(bytes),
+        #[cfg(feature = "documents")]
+        DocumentFormat::Docx => super::document_formats::parse_docx(bytes),
+        #[cfg(feature = "ocr")]
+        DocumentFormat::Image => super::document_formats::parse_image(bytes),
+        #[allow(unreachable_patterns)]
+        _ => Err(ToolError::ConfigurationError {
+            message: format!(
+                "Parsing {:?} documents needs the `{}` feature",
+                format,
+                if format == DocumentFormat::Image { "ocr" } else { "documents" }
+            ),
+        }),
+    }
+}
+
+/// Tool turning documents into text and chunks for a RAG pipeline
+///
+/// Input is a file path or an object with `path`, optional `format` and
+/// optional `chunk` (`true` or `{"max_chars", "overlap"}`). Output holds the
+/// text, the sections with their pages and headings, the document's
+/// metadata and, when asked for, the chunks.
+#[derive(Debug)]
+pub struct ParseDocumentTool {
+    metadata: ToolMetadata,
+    sandbox: Option<Arc<FsSandbox>>,
+    /// Largest file parsed without a sandbox, whose own limit applies otherwise
+    max_bytes: u64,
+}
+
+impl ParseDocumentTool {
+    /// Create a document parsing tool
+    pub fn new() -> Self {
+        let metadata = ToolMetadata::new(
+            "parse_document",
+            "Document Parser",
+            "Extract text with page and section metadata from PDF, DOCX, HTML, Markdown and text files"
+        )
+        .with_namespace("fs")
+        .with_tag("file")
+        .with_tag("io")
+        .with_tag("document")
+        .with_deterministic(true)
+        .with_side_effects(false)
+        .with_estimated_duration_ms(500)
+        .with_input_schema(json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string", "description": "File to parse"},
+                "format": {"type": "string", "enum": ["text", "markdown", "html", "pdf", "docx", "image"]},
+                "chunk": {
+                    "description": "Also split the text into chunks",
+                    "oneOf": [
+                        {"type": "boolean"},
+                        {"type": "object", "properties": {
+                            "max_chars": {"type": "integer", "minimum": 1},
+                            "overlap": {"type": "integer", "minimum": 0}
+                        }}
+                    ]
+                }
+            },
+            "required": ["path"]
+        }));
+
+        Self { metadata, sandbox: None, max_bytes: 50 * 1024 * 1024 }
+    }
+
+    /// Create a document parsing tool confined to a sandbox root
+    pub fn sandboxed(sandbox: Arc<FsSandbox>) -> Self {
+        Self { sandbox: Some(sandbox), ..Self::new() }
+    }
+
+    fn path(input: &ToolInput) -> ToolResult<&str> {
+        input.data.as_str()
+            .or_else(|| input.data.get("path").and_then(|v| v.as_str()))
+            .ok_or_else(|| ToolError::ValidationError {
+                message: "Document path is required".to_string(),
+            })
+    }
+
+    fn chunk_options(input: &ToolInput) -> ToolResult<Option<ChunkOptions>> {
+        match input.data.get("chunk") {
+            None | Some(serde_json::Value::Bool(false)) | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::Bool(true)) => Ok(Some(ChunkOptions::default())),
+            Some(options) => {
+                let defaults = ChunkOptions::default();
+                let field = |key: &str, default: usize| options.get(key).and_then(|v| v.as_u64()).map_or(default, |v| v as usize);
+                Ok(Some(ChunkOptions {
+                    max_chars: field("max_chars", defaults.max_chars).max(1),
+                    overlap: field("overlap", defaults.overlap),
+                }))
+            }
+        }
+    }
+
+    async fn read(&self, path: &str) -> ToolResult<(Vec<u8>, Option<DocumentFormat>)> {
+        let (resolved, max_bytes) = match &self.sandbox {
+            Some(sandbox) => {
+                let resolved = sandbox.resolve(path)?;
+                sandbox.check_extension(&resolved)?;
+                (resolved, sandbox.config().max_read_bytes)
+            }
+            None => (Path::new(path).to_path_buf(), self.max_bytes),
+        };
+        let io_error = |e: std::io::Error| ToolError::IoError {
+            message: format!("Failed to read document '{}': {}", path, e),
+        };
+        let size = tokio::fs::metadata(&resolved).await.map_err(io_error)?.len();
+        if size > max_bytes {
+            return Err(ToolError::PermissionDenied {
+                message: format!("Document '{}' is {} bytes, exceeding the {} byte limit", path, size, max_bytes),
+            });
+        }
+        let bytes = tokio::fs::read(&resolved).await.map_err(io_error)?;
+        let format = resolved.extension().and_then(|e| e.to_str()).and_then(DocumentFormat::from_extension);
+        Ok((bytes, format))
+    }
+
+    async fn parse(&self, input: &ToolInput) -> ToolResult<ToolOutput> {
+        let path = Self::path(input)?;
+        let chunk_options = Self::chunk_options(input)?;
+        let (bytes, extension_format) = self.read(path).await?;
+        let format = match input.data.get("format") {
+            Some(format) => serde_json::from_value(format.clone()).map_err(|e| ToolError::ValidationError {
+                message: format!("Unknown document format: {}", e),
+            })?,
+            None => extension_format.or_else(|| DocumentFormat::sniff(&bytes)).ok_or_else(|| ToolError::ValidationError {
+                message: format!("Cannot tell the format of '{}'", path),
+            })?,
+        };
+
+        // Parsing is CPU-bound and OCR runs external programs
+        let document = crate::graph::tuning::spawn_blocking(move || parse_document(&bytes, format))
+            .await
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Document parsing failed: {}", e),
+            })??;
+
+        let text = document.text();
+        let mut data = json!({
+            "path": path,
+            "format": document.format,
+            "text": text,
+            "metadata": document.metadata,
+            "sections": document.sections.iter().map(|section| json!({
+                "heading": section.heading,
+                "level": section.level,
+                "page": section.page,
+                "chars": section.text.chars().count()
+            })).collect::<Vec<_>>(),
+        });
+        let mut chunk_count = 0;
+        if let Some(options) = chunk_options {
+            let chunks = document.chunks(path, &options);
+            chunk_count = chunks.len();
+            data["chunks"] = json!(chunks);
+        }
+        Ok(ToolOutput::new(data)
+            .with_metadata("format", document.format)
+            .with_metric("chars", text.chars().count() as f64)
+            .with_metric("sections", document.sections.len() as f64)
+            .with_metric("chunks", chunk_count as f64))
+    }
+}
+
+impl Default for ParseDocumentTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for ParseDocumentTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let result = self.parse(&input).await;
+        if let Some(sandbox) = &self.sandbox {
+            let denied = matches!(result, Err(ToolError::PermissionDenied { .. }));
+            sandbox.audit(&self.metadata.id, Self::path(&input).unwrap_or_default(), &input, !denied).await;
+        }
+        result
+    }
+
+    async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
+        Self::path(input)?;
+        Self::chunk_options(input)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_sections_and_chunks() {
+        let markdown = "# Handbook\n\nWelcome.\n\n## Remote work\n\nEmployees may work remotely\nthree days a week. Core hours are 10-4.\n\n```\n# not a heading\n```\n";
+        let document = parse_document(markdown.as_bytes(), DocumentFormat::Markdown).unwrap();
+        let headings: Vec<_> = document.sections.iter().map(|s| (s.heading.as_deref(), s.level)).collect();
+        assert_eq!(headings, [(Some("Handbook"), 1), (Some("Remote work"), 2)]);
+        assert_eq!(document.metadata["title"], "Handbook");
+        assert!(document.sections[1].text.contains("# not a heading"));
+
+        let chunks = document.chunks("handbook.md", &ChunkOptions { max_chars: 40, overlap: 10 });
+        assert!(chunks.iter().all(|chunk| chunk.text.chars().count() <= 40));
+        assert_eq!(chunks[0].metadata["section"], "Handbook");
+        assert_eq!(chunks[1].metadata["section"], "Remote work");
+        assert_eq!(chunks[1].id, "handbook.md#1");
+        assert_eq!(chunks[1].clone().into_record(vec![1.0]).metadata["title"], "Handbook");
+    }
+
+    #[test]
+    fn test_text_pages_are_normalized() {
+        let text = "First  page with a hyphen-\nated word.\r\n\n\n\nNext paragraph.\x0cSecond\tpage.";
+        let document = parse_document(text.as_bytes(), DocumentFormat::Text).unwrap();
+        assert_eq!(document.sections[0].text, "First page with a hyphenated word.\n\nNext paragraph.");
+        assert_eq!((document.sections[1].page, document.sections[1].text.as_str()), (Some(2), "Second page."));
+        assert_eq!(DocumentFormat::sniff(b"%PDF-1.7"), Some(DocumentFormat::Pdf));
+        assert_eq!(DocumentFormat::from_extension("DOCX"), Some(DocumentFormat::Docx));
+    }
+
+    #[tokio::test]
+    async fn test_parse_document_tool_in_sandbox() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("faq.md"), "# FAQ\n\nHow do I reset my password? Use the portal.").unwrap();
+        let tool = ParseDocumentTool::sandboxed(Arc::new(FsSandbox::new(root.path()).unwrap()));
+
+        let output = tool.execute(ToolInput::new(json!({"path": "faq.md", "chunk": true}))).await.unwrap();
+        assert_eq!(output.data["format"], "markdown");
+        assert_eq!(output.data["sections"][0]["heading"], "FAQ");
+        assert_eq!(output.data["chunks"][0]["metadata"]["source"], "faq.md");
+        assert!(tool.execute(ToolInput::new(json!("../etc/passwd"))).await.is_err());
+    }
+}
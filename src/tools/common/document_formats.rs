@@ -0,0 +1,359 @@
+// Parsers for binary and markup document formats
+// PDF through pdf-extract, DOCX through its WordprocessingML, HTML through html2text, and OCR through tesseract
+
+use super::document::{markdown_sections, normalize_text, DocumentFormat, DocumentSection, ParsedDocument};
+use crate::tools::traits::{ToolError, ToolResult};
+use quick_xml::events::Event;
+use std::io::{Cursor, Read};
+
+/// Width html2text wraps lines at; wide enough to leave paragraphs on one line
+const HTML_WIDTH: usize = 10_000;
+
+/// Largest DOCX part inflated, so a small archive cannot expand without bound
+const MAX_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+fn parse_error(format: DocumentFormat, message: impl std::fmt::Display) -> ToolError {
+    ToolError::ExecutionError {
+        message: format!("Failed to parse {:?} document: {}", format, message),
+    }
+}
+
+/// HTML rendered to text, with headings starting sections
+pub(crate) fn parse_html(bytes: &[u8]) -> ToolResult<ParsedDocument> {
+    let html = String::from_utf8_lossy(bytes);
+    let title = html_title(&html);
+    // html2text renders <h1>..<h6> as Markdown `#` headings
+    let text = html2text::from_read(bytes, HTML_WIDTH);
+    let document = ParsedDocument::new(DocumentFormat::Html, markdown_sections(&text, None));
+    Ok(match title {
+        Some(title) => document.with_metadata("title", title),
+        None => document,
+    })
+}
+
+fn html_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = normalize_text(&html[start..end]);
+    (!title.is_empty()).then_some(title)
+}
+
+/// PDF text, one section per page
+///
+/// With the `ocr` feature, pages without a text layer are rendered and run
+/// through OCR.
+pub(crate) fn parse_pdf(bytes: &[u8]) -> ToolResult<ParsedDocument> {
+    let pages = pdf_extract::extract_text_from_mem_by_pages(bytes).map_err(|e| parse_error(DocumentFormat::Pdf, e))?;
+    let page_count = pages.len();
+    let mut sections: Vec<DocumentSection> = pages
+        .iter()
+        .enumerate()
+        .map(|(index, text)| DocumentSection { heading: None, level: 0, page: Some(index as u32 + 1), text: normalize_text(text) })
+        .collect();
+    let ocr_pages = ocr_blank_pages(bytes, &mut sections)?;
+    let document = ParsedDocument::new(DocumentFormat::Pdf, sections).with_metadata("page_count", page_count);
+    Ok(if ocr_pages.is_empty() { document } else { document.with_metadata("ocr_pages", ocr_pages) })
+}
+
+/// Fill pages that have no text layer with OCR output, returning their numbers
+#[cfg(feature = "ocr")]
+fn ocr_blank_pages(pdf: &[u8], sections: &mut [DocumentSection]) -> ToolResult<Vec<u32>> {
+    let mut pages = Vec::new();
+    for section in sections.iter_mut().filter(|section| section.text.is_empty()) {
+        let page = section.page.unwrap_or(1);
+        section.text = ocr::pdf_page(pdf, page)?;
+        pages.push(page);
+    }
+    Ok(pages)
+}
+
+#[cfg(not(feature = "ocr"))]
+fn ocr_blank_pages(_pdf: &[u8], _sections: &mut [DocumentSection]) -> ToolResult<Vec<u32>> {
+    Ok(Vec::new())
+}
+
+/// Text of a Word document, sectioned by its heading styles
+///
+/// Pages follow the page breaks Word recorded when the file was last saved,
+/// so they match what the author saw rather than any particular printer.
+pub(crate) fn parse_docx(bytes: &[u8]) -> ToolResult<ParsedDocument> {
+    let failed = |e: &dyn std::fmt::Display| parse_error(DocumentFormat::Docx, e);
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| failed(&e))?;
+    let body = read_entry(&mut archive, "word/document.xml")?.ok_or_else(|| failed(&"missing word/document.xml"))?;
+
+    let mut sections = vec![DocumentSection { heading: None, level: 0, page: Some(1), text: String::new() }];
+    let mut page = 1;
+    // Word records a rendered break right after an explicit one; count them once
+    let mut explicit_break = false;
+    let mut paragraph = String::new();
+    let mut heading_level: Option<u8> = None;
+    let mut in_text = false;
+    let mut reader = quick_xml::Reader::from_str(&body);
+    loop {
+        let event = reader.read_event().map_err(|e| failed(&e))?;
+        match &event {
+            Event::Start(e) | Event::Empty(e) => match e.name().as_ref() {
+                b"w:p" => {
+                    paragraph.clear();
+                    heading_level = None;
+                }
+                b"w:pStyle" | b"w:outlineLvl" => {
+                    if let Some(value) = e.try_get_attribute("w:val").map_err(|e| failed(&e))? {
+                        let value = value.unescape_value().map_err(|e| failed(&e))?;
+                        heading_level = heading_level.or_else(|| docx_heading_level(e.name().as_ref(), &value));
+                    }
+                }
+                b"w:t" => in_text = matches!(event, Event::Start(_)),
+                b"w:tab" => paragraph.push('\t'),
+                b"w:br" | b"w:cr" => {
+                    let page_break = e
+                        .try_get_attribute("w:type")
+                        .ok()
+                        .flatten()
+                        .is_some_and(|kind| kind.value.as_ref() == b"page");
+                    if page_break {
+                        page += 1;
+                        explicit_break = true;
+                    } else {
+                        paragraph.push('\n');
+                    }
+                }
+                b"w:lastRenderedPageBreak" => {
+                    if !explicit_break {
+                        page += 1;
+                    }
+                    explicit_break = false;
+                }
+                _ => {}
+            },
+            Event::Text(e) if in_text => {
+                paragraph.push_str(&e.unescape().map_err(|e| failed(&e))?);
+                explicit_break = false;
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => {
+                    let text = normalize_text(&paragraph);
+                    let current_page = sections.last().and_then(|section| section.page);
+                    match heading_level {
+                        Some(level) if !text.is_empty() => sections.push(DocumentSection {
+                            heading: Some(text),
+                            level,
+                            page: Some(page),
+                            text: String::new(),
+                        }),
+                        _ if text.is_empty() => {}
+                        _ => {
+                            // Paragraphs past a page break continue the heading on a new page
+                            if current_page != Some(page) {
+                                let (heading, level) = sections
+                                    .last()
+                                    .map(|section| (section.heading.clone(), section.level))
+                                    .unwrap_or_default();
+                                sections.push(DocumentSection { heading, level, page: Some(page), text: String::new() });
+                            }
+                            if let Some(section) = sections.last_mut() {
+                                if !section.text.is_empty() {
+                                    section.text.push_str("\n\n");
+                                }
+                                section.text.push_str(&text);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let mut document = ParsedDocument::new(DocumentFormat::Docx, sections).with_metadata("page_count", page);
+    if let Some(core) = read_entry(&mut archive, "docProps/core.xml")? {
+        for (tag, key) in [("dc:title", "title"), ("dc:creator", "author"), ("dcterms:created", "created"), ("dcterms:modified", "modified")] {
+            if let Some(value) = xml_element_text(&core, tag) {
+                document = document.with_metadata(key, value);
+            }
+        }
+    }
+    Ok(document)
+}
+
+/// Heading level named by a `w:pStyle` such as `Heading2` or `Title`, or a zero-based `w:outlineLvl`
+fn docx_heading_level(element: &[u8], value: &str) -> Option<u8> {
+    if element == b"w:outlineLvl" {
+        return value.parse::<u8>().ok().filter(|level| *level < 9).map(|level| level + 1);
+    }
+    let style = value.to_ascii_lowercase();
+    if style == "title" {
+        return Some(1);
+    }
+    style.strip_prefix("heading").and_then(|level| level.trim().parse().ok())
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str) -> ToolResult<Option<String>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(parse_error(DocumentFormat::Docx, e)),
+    };
+    let too_large = || parse_error(DocumentFormat::Docx, format!("{} is larger than {} bytes", name, MAX_ENTRY_BYTES));
+    if entry.size() > MAX_ENTRY_BYTES {
+        return Err(too_large());
+    }
+    // The declared size is the archive's word; stop inflating past the limit regardless
+    let mut xml = String::new();
+    (&mut entry).take(MAX_ENTRY_BYTES + 1).read_to_string(&mut xml).map_err(|e| parse_error(DocumentFormat::Docx, e))?;
+    if xml.len() as u64 > MAX_ENTRY_BYTES {
+        return Err(too_large());
+    }
+    Ok(Some(xml))
+}
+
+/// Text of the first `tag` element in `xml`
+fn xml_element_text(xml: &str, tag: &str) -> Option<String> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut inside = false;
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) if e.name().as_ref() == tag.as_bytes() => inside = true,
+            Event::Text(e) if inside => {
+                let text = normalize_text(&e.unescape().ok()?);
+                return (!text.is_empty()).then_some(text);
+            }
+            Event::End(e) if e.name().as_ref() == tag.as_bytes() => return None,
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Text recognized in an image
+#[cfg(feature = "ocr")]
+pub(crate) fn parse_image(bytes: &[u8]) -> ToolResult<ParsedDocument> {
+    let text = ocr::image(bytes)?;
+    let section = DocumentSection { heading: None, level: 0, page: None, text };
+    Ok(ParsedDocument::new(DocumentFormat::Image, vec![section]).with_metadata("ocr", true))
+}
+
+/// OCR through the `tesseract` and `pdftoppm` (poppler) programs
+///
+/// `TESSERACT_LANG` picks the language models, `eng` by default. Each program
+/// is killed if it runs longer than two minutes.
+#[cfg(feature = "ocr")]
+mod ocr {
+    use super::normalize_text;
+    use crate::tools::traits::{ToolError, ToolResult};
+    use std::io::Read;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    /// Longest one program may run, per image or page
+    const TIMEOUT: Duration = Duration::from_secs(120);
+
+    /// Scratch directory removed when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new() -> ToolResult<Self> {
+            let dir = std::env::temp_dir().join(format!("agent-graph-ocr-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).map_err(|e| failed(format!("cannot create {}: {}", dir.display(), e)))?;
+            Ok(Self(dir))
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn failed(message: String) -> ToolError {
+        ToolError::ExecutionError {
+            message: format!("OCR failed: {}", message),
+        }
+    }
+
+    /// Everything `pipe` yields, read on a thread of its own
+    fn drain(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = pipe.read_to_end(&mut bytes);
+            bytes
+        })
+    }
+
+    fn run(command: &mut Command) -> ToolResult<Vec<u8>> {
+        let program = command.get_program().to_string_lossy().into_owned();
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| failed(format!("cannot run {}: {}", program, e)))?;
+        // Read the pipes while waiting, so a full pipe cannot stall the program
+        let stdout = child.stdout.take().map(drain);
+        let stderr = child.stderr.take().map(drain);
+        let deadline = Instant::now() + TIMEOUT;
+        let status = loop {
+            match child.try_wait().map_err(|e| failed(format!("cannot wait for {}: {}", program, e)))? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(failed(format!("{} did not finish within {} seconds", program, TIMEOUT.as_secs())));
+                }
+                None => std::thread::sleep(Duration::from_millis(20)),
+            }
+        };
+        let collect = |pipe: Option<std::thread::JoinHandle<Vec<u8>>>| pipe.and_then(|pipe| pipe.join().ok()).unwrap_or_default();
+        let (stdout, stderr) = (collect(stdout), collect(stderr));
+        if !status.success() {
+            return Err(failed(format!("{} exited with {}: {}", program, status, String::from_utf8_lossy(&stderr).trim())));
+        }
+        Ok(stdout)
+    }
+
+    fn recognize(image: &Path) -> ToolResult<String> {
+        let language = std::env::var("TESSERACT_LANG").unwrap_or_else(|_| "eng".to_string());
+        let text = run(Command::new("tesseract").arg(image).arg("stdout").args(["-l", &language]))?;
+        Ok(normalize_text(&String::from_utf8_lossy(&text)))
+    }
+
+    pub(super) fn image(bytes: &[u8]) -> ToolResult<String> {
+        let scratch = Scratch::new()?;
+        let image = scratch.0.join("image");
+        std::fs::write(&image, bytes).map_err(|e| failed(e.to_string()))?;
+        recognize(&image)
+    }
+
+    /// Render one page at 300 dpi and recognize it
+    pub(super) fn pdf_page(pdf: &[u8], page: u32) -> ToolResult<String> {
+        let scratch = Scratch::new()?;
+        let input = scratch.0.join("document.pdf");
+        std::fs::write(&input, pdf).map_err(|e| failed(e.to_string()))?;
+        let page = page.to_string();
+        run(Command::new("pdftoppm")
+            .args(["-f", &page, "-l", &page, "-r", "300", "-png", "-singlefile"])
+            .arg(&input)
+            .arg(scratch.0.join("page")))?;
+        recognize(&scratch.0.join("page.png"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docx_heading_levels() {
+        assert_eq!(docx_heading_level(b"w:pStyle", "Heading2"), Some(2));
+        assert_eq!(docx_heading_level(b"w:pStyle", "Title"), Some(1));
+        assert_eq!(docx_heading_level(b"w:pStyle", "BodyText"), None);
+        assert_eq!(docx_heading_level(b"w:outlineLvl", "0"), Some(1));
+        assert_eq!(html_title("<html><head><TITLE>Leave  policy</TITLE>").as_deref(), Some("Leave policy"));
+    }
+}
@@ -0,0 +1,965 @@
+// Email tools for assistant and support agents
+// Available with the `email` feature; sends over SMTP and reads inboxes over IMAP
+
+use super::approval::require_approval;
+use crate::human::traits::HumanInteraction;
+use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use base64::Engine;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Subject and body with `{{variable}}` placeholders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailTemplate {
+    /// Subject template
+    pub subject: String,
+    /// Body template
+    pub body: String,
+    /// Whether the body is HTML
+    #[serde(default)]
+    pub html: bool,
+}
+
+impl EmailTemplate {
+    /// Create a plain-text template
+    pub fn new(subject: &str, body: &str) -> Self {
+        Self {
+            subject: subject.to_string(),
+            body: body.to_string(),
+            html: false,
+        }
+    }
+
+    /// Render subject and body, leaving unknown placeholders in place
+    pub fn render(&self, variables: &serde_json::Map<String, serde_json::Value>) -> (String, String) {
+        (
+            render_placeholders(&self.subject, variables),
+            render_placeholders(&self.body, variables),
+        )
+    }
+}
+
+fn render_placeholders(template: &str, variables: &serde_json::Map<String, serde_json::Value>) -> String {
+    variables.iter().fold(template.to_string(), |text, (key, value)| {
+        let replacement = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        text.replace(&format!("{{{{{}}}}}", key), &replacement)
+    })
+}
+
+/// Email ready to be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingEmail {
+    /// Sender address, e.g. `Support <support@example.com>`
+    pub from: String,
+    /// Recipients
+    pub to: Vec<String>,
+    /// Carbon-copy recipients
+    pub cc: Vec<String>,
+    /// Reply-to address
+    pub reply_to: Option<String>,
+    /// Subject line
+    pub subject: String,
+    /// Message body
+    pub body: String,
+    /// Whether the body is HTML
+    pub html: bool,
+}
+
+/// Delivers outgoing email
+#[async_trait]
+pub trait MailTransport: Send + Sync + fmt::Debug {
+    /// Send an email, returning its Message-ID
+    async fn send(&self, email: &OutgoingEmail) -> ToolResult<String>;
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmtpSecurity {
+    /// TLS from the first byte, usually port 465
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    StartTls,
+}
+
+/// SMTP server settings
+#[derive(Clone, Deserialize)]
+pub struct SmtpConfig {
+    /// Server host name
+    pub host: String,
+    /// Server port
+    pub port: u16,
+    /// Connection security
+    pub security: SmtpSecurity,
+    /// Login user name
+    pub username: String,
+    /// Login password
+    pub password: String,
+}
+
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("security", &self.security)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// SMTP transport
+#[derive(Debug, Clone)]
+pub struct SmtpMailTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailTransport {
+    /// Create a transport for an SMTP server
+    pub fn new(config: &SmtpConfig) -> ToolResult<Self> {
+        let builder = match config.security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+        }
+        .map_err(|e| ToolError::ConfigurationError {
+            message: format!("Invalid SMTP server '{}': {}", config.host, e),
+        })?;
+
+        let transport = builder
+            .port(config.port)
+            .credentials(Credentials::new(config.username.clone(), config.password.clone()))
+            .build();
+        Ok(Self { transport })
+    }
+}
+
+fn parse_mailbox(address: &str) -> ToolResult<Mailbox> {
+    address.parse().map_err(|e| ToolError::ValidationError {
+        message: format!("Invalid email address '{}': {}", address, e),
+    })
+}
+
+#[async_trait]
+impl MailTransport for SmtpMailTransport {
+    async fn send(&self, email: &OutgoingEmail) -> ToolResult<String> {
+        let from = parse_mailbox(&email.from)?;
+        let message_id = format!("<{}@{}>", uuid::Uuid::new_v4(), from.email.domain());
+
+        let mut builder = Message::builder()
+            .from(from)
+            .subject(email.subject.as_str())
+            .message_id(Some(message_id.clone()));
+        for to in &email.to {
+            builder = builder.to(parse_mailbox(to)?);
+        }
+        for cc in &email.cc {
+            builder = builder.cc(parse_mailbox(cc)?);
+        }
+        if let Some(reply_to) = &email.reply_to {
+            builder = builder.reply_to(parse_mailbox(reply_to)?);
+        }
+        let content_type = if email.html { ContentType::TEXT_HTML } else { ContentType::TEXT_PLAIN };
+        let message = builder.header(content_type).body(email.body.clone())
+            .map_err(|e| ToolError::ValidationError {
+                message: format!("Invalid email: {}", e),
+            })?;
+
+        self.transport.send(message).await.map_err(|e| ToolError::NetworkError {
+            message: format!("SMTP delivery failed: {}", e),
+        })?;
+        Ok(message_id)
+    }
+}
+
+/// Which messages to read from a mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailFilter {
+    /// Mailbox to read; the reader's default when unset
+    pub mailbox: Option<String>,
+    /// Only messages not yet marked as seen
+    pub unseen_only: bool,
+    /// Sender address or name contains this text
+    pub from: Option<String>,
+    /// Subject contains this text
+    pub subject: Option<String>,
+    /// Messages received on or after this date
+    pub since: Option<chrono::NaiveDate>,
+    /// Maximum number of messages, newest first
+    pub limit: usize,
+}
+
+impl Default for MailFilter {
+    fn default() -> Self {
+        Self {
+            mailbox: None,
+            unseen_only: false,
+            from: None,
+            subject: None,
+            since: None,
+            limit: 20,
+        }
+    }
+}
+
+impl MailFilter {
+    /// IMAP SEARCH criteria for this filter
+    fn search_criteria(&self) -> ToolResult<String> {
+        let mut criteria = Vec::new();
+        if self.unseen_only {
+            criteria.push("UNSEEN".to_string());
+        }
+        if let Some(from) = &self.from {
+            criteria.push(format!("FROM {}", imap_quote(from)?));
+        }
+        if let Some(subject) = &self.subject {
+            criteria.push(format!("SUBJECT {}", imap_quote(subject)?));
+        }
+        if let Some(since) = self.since {
+            criteria.push(format!("SINCE {}", since.format("%-d-%b-%Y")));
+        }
+        if criteria.is_empty() {
+            Ok("ALL".to_string())
+        } else {
+            Ok(criteria.join(" "))
+        }
+    }
+}
+
+/// Message read from a mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailMessage {
+    /// IMAP UID within the mailbox
+    pub uid: u32,
+    /// Message-ID header
+    pub message_id: Option<String>,
+    /// Sender
+    pub from: String,
+    /// Recipients
+    pub to: String,
+    /// Subject line
+    pub subject: String,
+    /// Date header as sent
+    pub date: Option<String>,
+    /// Whether the message was already seen
+    pub seen: bool,
+    /// Start of the raw message body
+    pub body: String,
+}
+
+/// Reads messages from a mailbox
+#[async_trait]
+pub trait MailboxReader: Send + Sync + fmt::Debug {
+    /// Fetch messages matching a filter, newest first
+    async fn fetch(&self, filter: &MailFilter) -> ToolResult<Vec<EmailMessage>>;
+}
+
+/// IMAP server settings
+#[derive(Clone, Deserialize)]
+pub struct ImapConfig {
+    /// Server host name
+    pub host: String,
+    /// TLS port, usually 993
+    pub port: u16,
+    /// Login user name
+    pub username: String,
+    /// Login password
+    pub password: String,
+    /// Mailbox read when the filter names none
+    pub mailbox: String,
+    /// Bytes of each message body to fetch
+    pub max_body_bytes: usize,
+}
+
+impl fmt::Debug for ImapConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImapConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("mailbox", &self.mailbox)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ImapConfig {
+    /// Create settings for the INBOX on port 993
+    pub fn new(host: &str, username: &str, password: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: 993,
+            username: username.to_string(),
+            password: password.to_string(),
+            mailbox: "INBOX".to_string(),
+            max_body_bytes: 4096,
+        }
+    }
+}
+
+/// Mailbox reader over IMAP with TLS
+///
+/// Mailboxes are opened read-only, so reading never marks messages as seen.
+#[derive(Debug, Clone)]
+pub struct ImapMailbox {
+    config: ImapConfig,
+}
+
+impl ImapMailbox {
+    /// Create a reader for an IMAP account
+    pub fn new(config: ImapConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl MailboxReader for ImapMailbox {
+    async fn fetch(&self, filter: &MailFilter) -> ToolResult<Vec<EmailMessage>> {
+        let network_error = |e: &dyn fmt::Display| ToolError::NetworkError {
+            message: format!("IMAP connection to {} failed: {}", self.config.host, e),
+        };
+        let tcp = TcpStream::connect((self.config.host.as_str(), self.config.port)).await
+            .map_err(|e| network_error(&e))?;
+        let connector = tokio_native_tls::native_tls::TlsConnector::new().map_err(|e| network_error(&e))?;
+        let tls = tokio_native_tls::TlsConnector::from(connector)
+            .connect(&self.config.host, tcp)
+            .await
+            .map_err(|e| network_error(&e))?;
+
+        ImapSession::new(tls).fetch(&self.config, filter).await
+    }
+}
+
+/// Quote a string for use in an IMAP command
+///
+/// Line breaks and NUL cannot be quoted: they would end the command and let
+/// the rest of the value run as commands of its own.
+fn imap_quote(value: &str) -> ToolResult<String> {
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(ToolError::ValidationError {
+            message: "IMAP arguments may not contain line breaks or NUL".to_string(),
+        });
+    }
+    Ok(format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"")))
+}
+
+/// Response line with any literals it carried
+#[derive(Debug, Default)]
+struct ImapResponse {
+    /// Response text with literals removed
+    text: String,
+    /// Literals, each with the text offset it appeared at
+    literals: Vec<(usize, Vec<u8>)>,
+}
+
+/// Minimal IMAP client session
+struct ImapSession<S> {
+    stream: BufReader<S>,
+    next_tag: u32,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> ImapSession<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            next_tag: 1,
+        }
+    }
+
+    async fn read_response(&mut self) -> ToolResult<ImapResponse> {
+        let io_error = |e: std::io::Error| ToolError::NetworkError {
+            message: format!("IMAP read failed: {}", e),
+        };
+        let mut response = ImapResponse::default();
+        loop {
+            let mut line = Vec::new();
+            let read = self.stream.read_until(b'\n', &mut line).await.map_err(io_error)?;
+            if read == 0 {
+                return Err(ToolError::NetworkError {
+                    message: "IMAP server closed the connection".to_string(),
+                });
+            }
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            // A line ending in {n} is followed by an n-byte literal
+            let literal_len = line.strip_suffix('}')
+                .and_then(|rest| rest.rfind('{').map(|i| (i, &rest[i + 1..])))
+                .and_then(|(i, digits)| digits.parse::<usize>().ok().map(|n| (i, n)));
+            match literal_len {
+                Some((start, len)) => {
+                    response.text.push_str(&line[..start]);
+                    let mut literal = vec![0; len];
+                    self.stream.read_exact(&mut literal).await.map_err(io_error)?;
+                    response.literals.push((response.text.len(), literal));
+                }
+                None => {
+                    response.text.push_str(line);
+                    return Ok(response);
+                }
+            }
+        }
+    }
+
+    /// Send a command and collect untagged responses until its completion
+    async fn command(&mut self, command: &str) -> ToolResult<Vec<ImapResponse>> {
+        let tag = format!("A{:03}", self.next_tag);
+        self.next_tag += 1;
+        let stream = self.stream.get_mut();
+        stream.write_all(format!("{} {}\r\n", tag, command).as_bytes()).await
+            .and(stream.flush().await)
+            .map_err(|e| ToolError::NetworkError {
+                message: format!("IMAP write failed: {}", e),
+            })?;
+
+        let prefix = format!("{} ", tag);
+        let mut untagged = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(status) = response.text.strip_prefix(&prefix) {
+                return if status.starts_with("OK") {
+                    Ok(untagged)
+                } else {
+                    let verb = command.split_whitespace().next().unwrap_or_default();
+                    Err(ToolError::ExecutionError {
+                        message: format!("IMAP {} failed: {}", verb, status),
+                    })
+                };
+            }
+            untagged.push(response);
+        }
+    }
+
+    async fn fetch(mut self, config: &ImapConfig, filter: &MailFilter) -> ToolResult<Vec<EmailMessage>> {
+        let greeting = self.read_response().await?;
+        if !greeting.text.starts_with("* OK") {
+            return Err(ToolError::NetworkError {
+                message: format!("Unexpected IMAP greeting: {}", greeting.text),
+            });
+        }
+
+        // Quoted before anything is sent, so a rejected argument sends no command
+        let login = format!("LOGIN {} {}", imap_quote(&config.username)?, imap_quote(&config.password)?);
+        let mailbox = imap_quote(filter.mailbox.as_deref().unwrap_or(&config.mailbox))?;
+        let criteria = filter.search_criteria()?;
+
+        self.command(&login).await
+            .map_err(|_| ToolError::AuthenticationError {
+                message: format!("IMAP login failed for {}", config.username),
+            })?;
+        self.command(&format!("EXAMINE {}", mailbox)).await?;
+
+        let mut uids: Vec<u32> = self.command(&format!("UID SEARCH {}", criteria)).await?
+            .iter()
+            .filter_map(|r| r.text.strip_prefix("* SEARCH"))
+            .flat_map(|ids| ids.split_whitespace().filter_map(|id| id.parse().ok()))
+            .collect();
+        uids.sort_unstable();
+        let newest = &uids[uids.len().saturating_sub(filter.limit)..];
+
+        let mut messages = Vec::new();
+        if !newest.is_empty() {
+            let set = newest.iter().map(|uid| uid.to_string()).collect::<Vec<_>>().join(",");
+            let responses = self.command(&format!(
+                "UID FETCH {} (UID FLAGS BODY.PEEK[HEADER] BODY.PEEK[TEXT]<0.{}>)",
+                set, config.max_body_bytes
+            )).await?;
+            messages = responses.iter().filter_map(parse_fetch).collect();
+            messages.sort_by(|a: &EmailMessage, b| b.uid.cmp(&a.uid));
+        }
+
+        // Results are already in hand; a failed logout doesn't matter
+        let _ = self.command("LOGOUT").await;
+        Ok(messages)
+    }
+}
+
+/// Parse a `* n FETCH (...)` response
+fn parse_fetch(response: &ImapResponse) -> Option<EmailMessage> {
+    let text = &response.text;
+    if !text.starts_with("* ") || !text.contains(" FETCH ") {
+        return None;
+    }
+    let uid = text.split("UID ").nth(1)?
+        .split(|c: char| !c.is_ascii_digit()).next()?
+        .parse().ok()?;
+    let seen = text.split("FLAGS (").nth(1)
+        .and_then(|rest| rest.split(')').next())
+        .is_some_and(|flags| flags.split_whitespace().any(|f| f.eq_ignore_ascii_case("\\Seen")));
+
+    let mut headers = HashMap::new();
+    let mut body = String::new();
+    let mut previous = 0;
+    for (offset, literal) in &response.literals {
+        let label = &text[previous..*offset];
+        previous = *offset;
+        if label.contains("HEADER") {
+            headers = parse_headers(&String::from_utf8_lossy(literal));
+        } else if label.contains("TEXT") {
+            body = String::from_utf8_lossy(literal).into_owned();
+        }
+    }
+    let header = |name: &str| headers.get(name).map(|v| decode_encoded_words(v));
+
+    Some(EmailMessage {
+        uid,
+        message_id: header("message-id"),
+        from: header("from").unwrap_or_default(),
+        to: header("to").unwrap_or_default(),
+        subject: header("subject").unwrap_or_default(),
+        date: header("date"),
+        seen,
+        body,
+    })
+}
+
+/// Parse RFC 5322 headers, unfolding continuation lines; names are lowercased
+fn parse_headers(raw: &str) -> HashMap<String, String> {
+    let mut headers: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in raw.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = current.as_ref().and_then(|name| headers.get_mut(name)) {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_lowercase();
+            headers.insert(name.clone(), value.trim().to_string());
+            current = Some(name);
+        }
+    }
+    headers
+}
+
+/// Decode RFC 2047 encoded words such as `=?UTF-8?B?...?=`
+fn decode_encoded_words(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    let mut after_encoded_word = false;
+    while let Some(start) = rest.find("=?") {
+        let parts = rest[start + 2..].splitn(4, '?').collect::<Vec<_>>();
+        let word = match parts.as_slice() {
+            [charset, encoding, text, tail] if tail.starts_with('=') => {
+                let bytes = match encoding.to_ascii_uppercase().as_str() {
+                    "B" => base64::engine::general_purpose::STANDARD.decode(text).ok(),
+                    "Q" => Some(decode_q(text)),
+                    _ => None,
+                };
+                let consumed = charset.len() + encoding.len() + text.len() + 6;
+                bytes.map(|b| (String::from_utf8_lossy(&b).into_owned(), consumed))
+            }
+            _ => None,
+        };
+        let before = &rest[..start];
+        match word {
+            Some((text, consumed)) => {
+                // Whitespace between adjacent encoded words is not part of the text
+                if !(after_encoded_word && before.trim().is_empty()) {
+                    result.push_str(before);
+                }
+                result.push_str(&text);
+                rest = &rest[start + consumed..];
+                after_encoded_word = true;
+            }
+            None => {
+                result.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_encoded_word = false;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn decode_q(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'_' => decoded.push(b' '),
+            b'=' => {
+                let byte = bytes.get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match byte {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'='),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    decoded
+}
+
+/// Tool for sending email, optionally from named templates
+///
+/// Input is an object with `to`, optional `cc`, and either `subject` and
+/// `body` or a `template` name with `variables`. Every send needs human
+/// approval unless approval is explicitly turned off.
+#[derive(Debug)]
+pub struct SendEmailTool {
+    metadata: ToolMetadata,
+    transport: Arc<dyn MailTransport>,
+    from: String,
+    templates: HashMap<String, EmailTemplate>,
+    approver: Option<Arc<dyn HumanInteraction>>,
+    require_approval: bool,
+}
+
+impl SendEmailTool {
+    /// Create a tool sending as `from` through a transport
+    pub fn new(transport: Arc<dyn MailTransport>, from: &str) -> Self {
+        let metadata = ToolMetadata::new(
+            "email_send",
+            "Send Email",
+            "Send an email, either written directly or rendered from a template"
+        )
+        .with_namespace("email")
+        .with_tag("email")
+        .with_tag("communication")
+        .with_deterministic(false)
+        .with_side_effects(true)
+        .with_estimated_duration_ms(2000);
+
+        Self {
+            metadata,
+            transport,
+            from: from.to_string(),
+            templates: HashMap::new(),
+            approver: None,
+            require_approval: true,
+        }
+    }
+
+    /// Register a named template
+    pub fn with_template(mut self, name: &str, template: EmailTemplate) -> Self {
+        self.templates.insert(name.to_string(), template);
+        self
+    }
+
+    /// Ask a human before each send
+    pub fn with_approver(mut self, approver: Arc<dyn HumanInteraction>) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+
+    /// Turn approval off, e.g. for internal notifications
+    pub fn without_approval(mut self) -> Self {
+        self.require_approval = false;
+        self
+    }
+
+    fn addresses(value: Option<&serde_json::Value>) -> Vec<String> {
+        match value {
+            Some(serde_json::Value::String(address)) => vec![address.clone()],
+            Some(value) => serde_json::from_value(value.clone()).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    fn build_email(&self, input: &ToolInput) -> ToolResult<OutgoingEmail> {
+        let data = &input.data;
+        let to = Self::addresses(data.get("to"));
+        if to.is_empty() {
+            return Err(ToolError::ValidationError {
+                message: "At least one recipient is required in 'to'".to_string(),
+            });
+        }
+
+        let field = |name: &str| data.get(name).and_then(|v| v.as_str()).map(String::from);
+        let (subject, body, html) = match field("template") {
+            Some(name) => {
+                let template = self.templates.get(&name).ok_or_else(|| ToolError::ValidationError {
+                    message: format!("Unknown email template '{}'", name),
+                })?;
+                let variables = data.get("variables").and_then(|v| v.as_object()).cloned().unwrap_or_default();
+                let (subject, body) = template.render(&variables);
+                (subject, body, template.html)
+            }
+            None => {
+                let subject = field("subject").ok_or_else(|| ToolError::ValidationError {
+                    message: "Either 'template' or 'subject' and 'body' are required".to_string(),
+                })?;
+                let body = field("body").unwrap_or_default();
+                (subject, body, data.get("html").and_then(|v| v.as_bool()).unwrap_or(false))
+            }
+        };
+
+        Ok(OutgoingEmail {
+            from: self.from.clone(),
+            to,
+            cc: Self::addresses(data.get("cc")),
+            reply_to: field("reply_to"),
+            subject,
+            body,
+            html,
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for SendEmailTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let email = self.build_email(&input)?;
+
+        if self.require_approval {
+            let preview: String = email.body.chars().take(500).collect();
+            require_approval(
+                self.approver.as_ref(),
+                &format!("send email '{}' to {}", email.subject, email.to.join(", ")),
+                &[
+                    ("to", email.to.join(", ")),
+                    ("cc", email.cc.join(", ")),
+                    ("subject", email.subject.clone()),
+                    ("body", preview),
+                ],
+                &input,
+            ).await?;
+        }
+
+        let message_id = self.transport.send(&email).await?;
+        let recipients = email.to.len() + email.cc.len();
+
+        Ok(ToolOutput::new(json!({
+            "message_id": message_id,
+            "to": email.to,
+            "cc": email.cc,
+            "subject": email.subject
+        }))
+        .with_metadata("message_id", &message_id)
+        .with_metric("recipients", recipients as f64))
+    }
+
+    async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
+        self.build_email(input).map(|_| ())
+    }
+}
+
+/// Tool for reading recent messages from a mailbox
+///
+/// Input is a `MailFilter` object; null reads the newest messages.
+#[derive(Debug)]
+pub struct ReadInboxTool {
+    metadata: ToolMetadata,
+    reader: Arc<dyn MailboxReader>,
+}
+
+impl ReadInboxTool {
+    /// Create a tool reading through a mailbox reader
+    pub fn new(reader: Arc<dyn MailboxReader>) -> Self {
+        let metadata = ToolMetadata::new(
+            "email_read",
+            "Read Inbox",
+            "Read recent email, filtered by sender, subject, date, or unread status"
+        )
+        .with_namespace("email")
+        .with_tag("email")
+        .with_tag("communication")
+        .with_deterministic(false)
+        .with_side_effects(false)
+        .with_estimated_duration_ms(3000);
+
+        Self { metadata, reader }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadInboxTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let filter: MailFilter = if input.data.is_null() {
+            MailFilter::default()
+        } else {
+            serde_json::from_value(input.data.clone()).map_err(|e| ToolError::ValidationError {
+                message: format!("Invalid mail filter: {}", e),
+            })?
+        };
+
+        let messages = self.reader.fetch(&filter).await?;
+        let count = messages.len();
+
+        Ok(ToolOutput::new(json!({
+            "messages": messages,
+            "count": count
+        }))
+        .with_metric("messages", count as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::human::traits::{HumanInput, HumanResponse, HumanResult};
+    use crate::human::{HumanConfig, HumanContext};
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default)]
+    struct RecordingTransport {
+        sent: Mutex<Vec<OutgoingEmail>>,
+    }
+
+    #[async_trait]
+    impl MailTransport for RecordingTransport {
+        async fn send(&self, email: &OutgoingEmail) -> ToolResult<String> {
+            self.sent.lock().unwrap().push(email.clone());
+            Ok("<1@example.com>".to_string())
+        }
+    }
+
+    #[derive(Debug)]
+    struct FixedApprover(bool);
+
+    #[async_trait]
+    impl HumanInteraction for FixedApprover {
+        async fn request_input(&self, _input: HumanInput, _context: &HumanContext, _config: &HumanConfig) -> HumanResult<HumanResponse> {
+            Ok(HumanResponse::new(json!(self.0), 0, true))
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn cancel_interaction(&self, _interaction_id: &str) -> HumanResult<()> {
+            Ok(())
+        }
+
+        fn provider_name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_templated_email_requires_approval() {
+        let transport = Arc::new(RecordingTransport::default());
+        let template = EmailTemplate::new("Ticket {{ticket}} update", "Hi {{name}}, your ticket is {{status}}.");
+        let input = ToolInput::new(json!({
+            "to": "customer@example.com",
+            "template": "ticket_update",
+            "variables": {"ticket": 42, "name": "Sam", "status": "resolved"}
+        }));
+
+        let unapproved = SendEmailTool::new(transport.clone(), "support@example.com")
+            .with_template("ticket_update", template.clone());
+        assert!(matches!(unapproved.execute(input.clone()).await, Err(ToolError::PermissionDenied { .. })));
+
+        let declined = SendEmailTool::new(transport.clone(), "support@example.com")
+            .with_template("ticket_update", template.clone())
+            .with_approver(Arc::new(FixedApprover(false)));
+        assert!(declined.execute(input.clone()).await.is_err());
+        assert!(transport.sent.lock().unwrap().is_empty());
+
+        let approved = SendEmailTool::new(transport.clone(), "support@example.com")
+            .with_template("ticket_update", template)
+            .with_approver(Arc::new(FixedApprover(true)));
+        let output = approved.execute(input).await.unwrap();
+        assert_eq!(output.data["subject"], "Ticket 42 update");
+
+        let sent = transport.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].body, "Hi Sam, your ticket is resolved.");
+        assert_eq!(sent[0].to, vec!["customer@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_imap_session_fetches_filtered_messages() {
+        let (client, server) = tokio::io::duplex(16 * 1024);
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let mut commands = Vec::new();
+            server.get_mut().write_all(b"* OK IMAP4rev1 ready\r\n").await.unwrap();
+            loop {
+                let mut line = String::new();
+                if server.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let (tag, command) = line.trim_end().split_once(' ').unwrap();
+                commands.push(command.to_string());
+                let reply = if command.starts_with("UID SEARCH") {
+                    "* SEARCH 3 7 9\r\n".to_string()
+                } else if command.starts_with("UID FETCH") {
+                    let header = "From: Ann <ann@example.com>\r\nSubject: =?UTF-8?B?UmVmdW5kIHJlcXVlc3Q=?=\r\n\r\n";
+                    let body = "Please refund order 17.";
+                    format!(
+                        "* 2 FETCH (UID 9 FLAGS () BODY[HEADER] {{{}}}\r\n{} BODY[TEXT]<0> {{{}}}\r\n{})\r\n",
+                        header.len(), header, body.len(), body
+                    )
+                } else {
+                    String::new()
+                };
+                let done = command == "LOGOUT";
+                server.get_mut().write_all(format!("{}{} OK done\r\n", reply, tag).as_bytes()).await.unwrap();
+                if done {
+                    break;
+                }
+            }
+            commands
+        });
+
+        let config = ImapConfig::new("imap.example.com", "agent", "pw\"1");
+        let filter = MailFilter {
+            unseen_only: true,
+            from: Some("ann".to_string()),
+            limit: 1,
+            ..Default::default()
+        };
+        let messages = ImapSession::new(client).fetch(&config, &filter).await.unwrap();
+        let commands = server.await.unwrap();
+
+        assert_eq!(commands[0], r#"LOGIN "agent" "pw\"1""#);
+        assert_eq!(commands[1], r#"EXAMINE "INBOX""#);
+        assert_eq!(commands[2], r#"UID SEARCH UNSEEN FROM "ann""#);
+        assert!(commands[3].starts_with("UID FETCH 9 "));
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].uid, 9);
+        assert_eq!(messages[0].subject, "Refund request");
+        assert_eq!(messages[0].from, "Ann <ann@example.com>");
+        assert_eq!(messages[0].body, "Please refund order 17.");
+        assert!(!messages[0].seen);
+    }
+
+    #[test]
+    fn test_imap_arguments_with_line_breaks_are_rejected() {
+        assert_eq!(imap_quote(r#"a\b"c"#).unwrap(), r#""a\\b\"c""#);
+        assert!(imap_quote("INBOX\r\nA1 EXPUNGE").is_err());
+        assert!(imap_quote("INBOX\0").is_err());
+        let filter = MailFilter {
+            subject: Some("refund\nA2 DELETE INBOX".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.search_criteria().is_err());
+    }
+
+    #[test]
+    fn test_decode_encoded_words() {
+        assert_eq!(decode_encoded_words("=?utf-8?Q?Caf=C3=A9_menu?="), "Café menu");
+        assert_eq!(decode_encoded_words("Re: =?UTF-8?B?SGk=?= =?UTF-8?B?IHRoZXJl?="), "Re: Hi there");
+        assert_eq!(decode_encoded_words("plain"), "plain");
+    }
+}
@@ -1,15 +1,18 @@
 // File system tools for reading, writing, and managing files
 
+use super::sandbox::FsSandbox;
 use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
 use async_trait::async_trait;
 use serde_json::json;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
 
 /// Tool for reading files
 #[derive(Debug)]
 pub struct FileReadTool {
     metadata: ToolMetadata,
+    sandbox: Option<Arc<FsSandbox>>,
 }
 
 impl FileReadTool {
@@ -20,6 +23,7 @@ impl FileReadTool {
             "File Reader",
             "Read contents from files on the filesystem"
         )
+        .with_namespace("fs")
         .with_tag("file")
         .with_tag("io")
         .with_tag("utility")
@@ -27,7 +31,33 @@ impl FileReadTool {
         .with_side_effects(false)
         .with_estimated_duration_ms(100);
         
-        Self { metadata }
+        Self { metadata, sandbox: None }
+    }
+
+    /// Create a file read tool confined to a sandbox root
+    pub fn sandboxed(sandbox: Arc<FsSandbox>) -> Self {
+        Self { sandbox: Some(sandbox), ..Self::new() }
+    }
+
+    async fn read_sandboxed(&self, sandbox: &FsSandbox, path: &str) -> ToolResult<ToolOutput> {
+        let resolved = sandbox.resolve(path)?;
+        sandbox.check_extension(&resolved)?;
+
+        let size = fs::metadata(&resolved).await
+            .map_err(|e| ToolError::IoError {
+                message: format!("Failed to read file '{}': {}", path, e),
+            })?
+            .len();
+        if size > sandbox.config().max_read_bytes {
+            return Err(ToolError::PermissionDenied {
+                message: format!(
+                    "File '{}' is {} bytes, exceeding the {} byte read limit",
+                    path, size, sandbox.config().max_read_bytes
+                ),
+            });
+        }
+
+        read_file(&resolved, path).await
     }
 }
 
@@ -43,20 +73,14 @@ impl Tool for FileReadTool {
                 message: "File path is required".to_string(),
             })?;
 
-        let contents = fs::read_to_string(path).await
-            .map_err(|e| ToolError::IoError {
-                message: format!("Failed to read file '{}': {}", path, e),
-            })?;
-
-        let output = ToolOutput::new(json!({
-            "path": path,
-            "contents": contents,
-            "size": contents.len()
-        }))
-        .with_metadata("file_path", path)
-        .with_metric("file_size_bytes", contents.len() as f64);
-
-        Ok(output)
+        match &self.sandbox {
+            Some(sandbox) => {
+                let result = self.read_sandboxed(sandbox, path).await;
+                sandbox.audit(&self.metadata.id, path, &input, !is_denied(&result)).await;
+                result
+            }
+            None => read_file(Path::new(path), path).await,
+        }
     }
 
     async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
@@ -65,7 +89,8 @@ impl Tool for FileReadTool {
                 message: "File path is required".to_string(),
             })?;
 
-        if !Path::new(path).exists() {
+        // Sandboxed paths are resolved, checked, and audited in execute
+        if self.sandbox.is_none() && !Path::new(path).exists() {
             return Err(ToolError::ValidationError {
                 message: format!("File '{}' does not exist", path),
             });
@@ -79,6 +104,7 @@ impl Tool for FileReadTool {
 #[derive(Debug)]
 pub struct FileWriteTool {
     metadata: ToolMetadata,
+    sandbox: Option<Arc<FsSandbox>>,
 }
 
 impl FileWriteTool {
@@ -89,6 +115,7 @@ impl FileWriteTool {
             "File Writer",
             "Write contents to files on the filesystem"
         )
+        .with_namespace("fs")
         .with_tag("file")
         .with_tag("io")
         .with_tag("utility")
@@ -96,7 +123,33 @@ impl FileWriteTool {
         .with_side_effects(true)
         .with_estimated_duration_ms(200);
         
-        Self { metadata }
+        Self { metadata, sandbox: None }
+    }
+
+    /// Create a file write tool confined to a sandbox root
+    pub fn sandboxed(sandbox: Arc<FsSandbox>) -> Self {
+        Self { sandbox: Some(sandbox), ..Self::new() }
+    }
+
+    async fn write_sandboxed(&self, sandbox: &FsSandbox, path: &str, contents: &str) -> ToolResult<ToolOutput> {
+        if contents.len() as u64 > sandbox.config().max_write_bytes {
+            return Err(ToolError::PermissionDenied {
+                message: format!(
+                    "Contents are {} bytes, exceeding the {} byte write limit",
+                    contents.len(), sandbox.config().max_write_bytes
+                ),
+            });
+        }
+
+        let resolved = sandbox.resolve(path)?;
+        sandbox.check_extension(&resolved)?;
+        if resolved.is_dir() {
+            return Err(ToolError::ValidationError {
+                message: format!("Path '{}' is a directory", path),
+            });
+        }
+
+        write_file(&resolved, path, contents).await
     }
 }
 
@@ -117,20 +170,14 @@ impl Tool for FileWriteTool {
                 message: "File contents are required in data field".to_string(),
             })?;
 
-        fs::write(&path, contents).await
-            .map_err(|e| ToolError::IoError {
-                message: format!("Failed to write file '{}': {}", path, e),
-            })?;
-
-        let output = ToolOutput::new(json!({
-            "path": path,
-            "bytes_written": contents.len(),
-            "success": true
-        }))
-        .with_metadata("file_path", &path)
-        .with_metric("bytes_written", contents.len() as f64);
-
-        Ok(output)
+        match &self.sandbox {
+            Some(sandbox) => {
+                let result = self.write_sandboxed(sandbox, &path, contents).await;
+                sandbox.audit(&self.metadata.id, &path, &input, !is_denied(&result)).await;
+                result
+            }
+            None => write_file(Path::new(&path), &path, contents).await,
+        }
     }
 
     async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
@@ -145,6 +192,10 @@ impl Tool for FileWriteTool {
             });
         }
 
+        if self.sandbox.is_some() {
+            return Ok(());
+        }
+
         // Check if parent directory exists
         if let Some(parent) = Path::new(&path).parent() {
             if !parent.exists() {
@@ -162,6 +213,7 @@ impl Tool for FileWriteTool {
 #[derive(Debug)]
 pub struct DirectoryListTool {
     metadata: ToolMetadata,
+    sandbox: Option<Arc<FsSandbox>>,
 }
 
 impl DirectoryListTool {
@@ -172,6 +224,7 @@ impl DirectoryListTool {
             "Directory Lister",
             "List contents of directories on the filesystem"
         )
+        .with_namespace("fs")
         .with_tag("file")
         .with_tag("io")
         .with_tag("utility")
@@ -179,7 +232,12 @@ impl DirectoryListTool {
         .with_side_effects(false)
         .with_estimated_duration_ms(50);
         
-        Self { metadata }
+        Self { metadata, sandbox: None }
+    }
+
+    /// Create a directory list tool confined to a sandbox root
+    pub fn sandboxed(sandbox: Arc<FsSandbox>) -> Self {
+        Self { sandbox: Some(sandbox), ..Self::new() }
     }
 }
 
@@ -195,7 +253,16 @@ impl Tool for DirectoryListTool {
                 message: "Directory path is required".to_string(),
             })?;
 
-        let mut entries = fs::read_dir(path).await
+        let dir = match &self.sandbox {
+            Some(sandbox) => {
+                let resolved = sandbox.resolve(path);
+                sandbox.audit(&self.metadata.id, path, &input, resolved.is_ok()).await;
+                resolved?
+            }
+            None => PathBuf::from(path),
+        };
+
+        let mut entries = fs::read_dir(&dir).await
             .map_err(|e| ToolError::IoError {
                 message: format!("Failed to read directory '{}': {}", path, e),
             })?;
@@ -248,6 +315,10 @@ impl Tool for DirectoryListTool {
                 message: "Directory path is required".to_string(),
             })?;
 
+        if self.sandbox.is_some() {
+            return Ok(());
+        }
+
         let path_obj = Path::new(path);
         if !path_obj.exists() {
             return Err(ToolError::ValidationError {
@@ -264,3 +335,150 @@ impl Tool for DirectoryListTool {
         Ok(())
     }
 }
+
+/// Tool for recursively listing files inside a sandbox root
+#[derive(Debug)]
+pub struct FileListTool {
+    metadata: ToolMetadata,
+    sandbox: Arc<FsSandbox>,
+}
+
+impl FileListTool {
+    /// Create a new file list tool over a sandbox root
+    pub fn new(sandbox: Arc<FsSandbox>) -> Self {
+        let metadata = ToolMetadata::new(
+            "file_list",
+            "File Lister",
+            "Recursively list files under a directory in the sandbox"
+        )
+        .with_namespace("fs")
+        .with_tag("file")
+        .with_tag("io")
+        .with_tag("utility")
+        .with_deterministic(true)
+        .with_side_effects(false)
+        .with_estimated_duration_ms(100);
+
+        Self { metadata, sandbox }
+    }
+
+    async fn list(&self, path: &str, recursive: bool) -> ToolResult<ToolOutput> {
+        let config = self.sandbox.config();
+        let start = self.sandbox.resolve(path)?;
+        if !start.is_dir() {
+            return Err(ToolError::ValidationError {
+                message: format!("Path '{}' is not a directory", path),
+            });
+        }
+
+        let mut files = Vec::new();
+        let mut truncated = false;
+        let mut pending = vec![(start, 0usize)];
+
+        'walk: while let Some((dir, depth)) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await
+                .map_err(|e| ToolError::IoError {
+                    message: format!("Failed to read directory '{}': {}", self.sandbox.relative(&dir), e),
+                })?;
+
+            while let Some(entry) = entries.next_entry().await
+                .map_err(|e| ToolError::IoError {
+                    message: format!("Failed to read directory entry: {}", e),
+                })? {
+
+                // Symlinks are skipped so the walk never leaves the root
+                let file_type = match entry.file_type().await {
+                    Ok(file_type) if !file_type.is_symlink() => file_type,
+                    _ => continue,
+                };
+
+                let entry_path = entry.path();
+                if file_type.is_dir() {
+                    if recursive && depth + 1 < config.max_list_depth {
+                        pending.push((entry_path, depth + 1));
+                    }
+                    continue;
+                }
+                if self.sandbox.check_extension(&entry_path).is_err() {
+                    continue;
+                }
+
+                if files.len() >= config.max_list_entries {
+                    truncated = true;
+                    break 'walk;
+                }
+
+                let size = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+                files.push(json!({
+                    "path": self.sandbox.relative(&entry_path),
+                    "size": size
+                }));
+            }
+        }
+
+        let output = ToolOutput::new(json!({
+            "path": path,
+            "files": files,
+            "total_files": files.len(),
+            "truncated": truncated
+        }))
+        .with_metadata("directory_path", path)
+        .with_metric("file_count", files.len() as f64);
+
+        Ok(output)
+    }
+}
+
+#[async_trait]
+impl Tool for FileListTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let path = input.data.as_str().unwrap_or("/").to_string();
+        let recursive = input.get_parameter::<bool>("recursive").unwrap_or(true);
+
+        let result = self.list(&path, recursive).await;
+        self.sandbox.audit(&self.metadata.id, &path, &input, !is_denied(&result)).await;
+        result
+    }
+}
+
+async fn read_file(path: &Path, display_path: &str) -> ToolResult<ToolOutput> {
+    let contents = fs::read_to_string(path).await
+        .map_err(|e| ToolError::IoError {
+            message: format!("Failed to read file '{}': {}", display_path, e),
+        })?;
+
+    let output = ToolOutput::new(json!({
+        "path": display_path,
+        "contents": contents,
+        "size": contents.len()
+    }))
+    .with_metadata("file_path", display_path)
+    .with_metric("file_size_bytes", contents.len() as f64);
+
+    Ok(output)
+}
+
+async fn write_file(path: &Path, display_path: &str, contents: &str) -> ToolResult<ToolOutput> {
+    fs::write(path, contents).await
+        .map_err(|e| ToolError::IoError {
+            message: format!("Failed to write file '{}': {}", display_path, e),
+        })?;
+
+    let output = ToolOutput::new(json!({
+        "path": display_path,
+        "bytes_written": contents.len(),
+        "success": true
+    }))
+    .with_metadata("file_path", display_path)
+    .with_metric("bytes_written", contents.len() as f64);
+
+    Ok(output)
+}
+
+fn is_denied<T>(result: &ToolResult<T>) -> bool {
+    matches!(result, Err(ToolError::PermissionDenied { .. }))
+}
@@ -1,10 +1,13 @@
 // HTTP tools for making web requests
 
+use super::egress::{check_sandbox_egress, HttpGuard};
 use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult, CacheableTool};
 use async_trait::async_trait;
 // use serde::{Deserialize, Serialize};
+use reqwest::Method;
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// HTTP GET tool for making GET requests
@@ -12,6 +15,7 @@ use std::time::Duration;
 pub struct HttpGetTool {
     metadata: ToolMetadata,
     client: reqwest::Client,
+    guard: Option<Arc<HttpGuard>>,
 }
 
 impl HttpGetTool {
@@ -27,6 +31,7 @@ impl HttpGetTool {
             "HTTP GET",
             "Make HTTP GET requests to retrieve data from web APIs"
         )
+        .with_namespace("web")
         .with_tag("http")
         .with_tag("network")
         .with_tag("api")
@@ -34,7 +39,12 @@ impl HttpGetTool {
         .with_side_effects(false)
         .with_estimated_duration_ms(1000);
         
-        Self { metadata, client }
+        Self { metadata, client, guard: None }
+    }
+
+    /// Create an HTTP GET tool whose requests pass through an egress guard
+    pub fn guarded(guard: Arc<HttpGuard>) -> Self {
+        Self { guard: Some(guard), ..Self::new() }
     }
 }
 
@@ -56,41 +66,52 @@ impl Tool for HttpGetTool {
         let headers = input.get_parameter::<HashMap<String, String>>("headers")
             .unwrap_or_default();
 
-        // Build request
-        let mut request = self.client.get(url);
-        for (key, value) in headers {
-            request = request.header(&key, &value);
-        }
-
-        // Execute request
-        let response = request.send().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("HTTP request failed: {}", e),
-            })?;
-
-        let status = response.status();
-        let headers_map: HashMap<String, String> = response.headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
-
-        let body = response.text().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("Failed to read response body: {}", e),
-            })?;
+        let (status, headers_map, body) = match &self.guard {
+            Some(guard) => {
+                let response = guard.send(&self.metadata.id, Method::GET, url, &headers, None, &input).await?;
+                (response.status, response.headers, response.body)
+            }
+            None => {
+                check_sandbox_egress(url).await?;
+
+                // Build request
+                let mut request = self.client.get(url);
+                for (key, value) in headers {
+                    request = request.header(&key, &value);
+                }
+
+                // Execute request
+                let response = request.send().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("HTTP request failed: {}", e),
+                    })?;
+
+                let status = response.status().as_u16();
+                let headers_map: HashMap<String, String> = response.headers()
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+                    .collect();
+
+                let body = response.text().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("Failed to read response body: {}", e),
+                    })?;
+                (status, headers_map, body)
+            }
+        };
 
         // Try to parse as JSON, fallback to text
         let parsed_body = serde_json::from_str::<Value>(&body)
             .unwrap_or_else(|_| Value::String(body));
 
         let output = ToolOutput::new(json!({
-            "status": status.as_u16(),
+            "status": status,
             "headers": headers_map,
             "body": parsed_body
         }))
         .with_metadata("url", url)
         .with_metadata("method", "GET")
-        .with_metric("status_code", status.as_u16() as f64);
+        .with_metric("status_code", status as f64);
 
         Ok(output)
     }
@@ -122,7 +143,11 @@ impl CacheableTool for HttpGetTool {
         let headers = input.get_parameter::<HashMap<String, String>>("headers")
             .unwrap_or_default();
         
-        format!("http_get:{}:{:?}", url, headers)
+        // Guarded responses are scoped per tenant since allowlists differ between tenants
+        match (&self.guard, input.get_context("tenant_id")) {
+            (Some(_), Some(tenant_id)) => format!("http_get:{}:{}:{:?}", tenant_id, url, headers),
+            _ => format!("http_get:{}:{:?}", url, headers),
+        }
     }
 
     fn should_cache(&self, _input: &ToolInput) -> bool {
@@ -139,6 +164,7 @@ impl CacheableTool for HttpGetTool {
 pub struct HttpPostTool {
     metadata: ToolMetadata,
     client: reqwest::Client,
+    guard: Option<Arc<HttpGuard>>,
 }
 
 impl HttpPostTool {
@@ -154,6 +180,7 @@ impl HttpPostTool {
             "HTTP POST",
             "Make HTTP POST requests to send data to web APIs"
         )
+        .with_namespace("web")
         .with_tag("http")
         .with_tag("network")
         .with_tag("api")
@@ -161,7 +188,12 @@ impl HttpPostTool {
         .with_side_effects(true)
         .with_estimated_duration_ms(1500);
         
-        Self { metadata, client }
+        Self { metadata, client, guard: None }
+    }
+
+    /// Create an HTTP POST tool whose requests pass through an egress guard
+    pub fn guarded(guard: Arc<HttpGuard>) -> Self {
+        Self { guard: Some(guard), ..Self::new() }
     }
 }
 
@@ -182,46 +214,57 @@ impl Tool for HttpPostTool {
         let headers = input.get_parameter::<HashMap<String, String>>("headers")
             .unwrap_or_default();
 
-        // Build request
-        let mut request = self.client.post(&url);
-        
-        // Add headers
-        for (key, value) in headers {
-            request = request.header(&key, &value);
-        }
-
-        // Add body
-        request = request.json(&body);
-
-        // Execute request
-        let response = request.send().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("HTTP request failed: {}", e),
-            })?;
-
-        let status = response.status();
-        let headers_map: HashMap<String, String> = response.headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
-
-        let response_body = response.text().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("Failed to read response body: {}", e),
-            })?;
+        let (status, headers_map, response_body) = match &self.guard {
+            Some(guard) => {
+                let response = guard.send(&self.metadata.id, Method::POST, &url, &headers, Some(&body), &input).await?;
+                (response.status, response.headers, response.body)
+            }
+            None => {
+                check_sandbox_egress(&url).await?;
+
+                // Build request
+                let mut request = self.client.post(&url);
+                
+                // Add headers
+                for (key, value) in headers {
+                    request = request.header(&key, &value);
+                }
+
+                // Add body
+                request = request.json(&body);
+
+                // Execute request
+                let response = request.send().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("HTTP request failed: {}", e),
+                    })?;
+
+                let status = response.status().as_u16();
+                let headers_map: HashMap<String, String> = response.headers()
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+                    .collect();
+
+                let response_body = response.text().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("Failed to read response body: {}", e),
+                    })?;
+                (status, headers_map, response_body)
+            }
+        };
 
         // Try to parse as JSON, fallback to text
         let parsed_body = serde_json::from_str::<Value>(&response_body)
             .unwrap_or_else(|_| Value::String(response_body));
 
         let output = ToolOutput::new(json!({
-            "status": status.as_u16(),
+            "status": status,
             "headers": headers_map,
             "body": parsed_body
         }))
         .with_metadata("url", &url)
         .with_metadata("method", "POST")
-        .with_metric("status_code", status.as_u16() as f64);
+        .with_metric("status_code", status as f64);
 
         Ok(output)
     }
@@ -249,6 +292,7 @@ impl Tool for HttpPostTool {
 pub struct HttpPutTool {
     metadata: ToolMetadata,
     client: reqwest::Client,
+    guard: Option<Arc<HttpGuard>>,
 }
 
 impl HttpPutTool {
@@ -264,6 +308,7 @@ impl HttpPutTool {
             "HTTP PUT",
             "Make HTTP PUT requests to update data via web APIs"
         )
+        .with_namespace("web")
         .with_tag("http")
         .with_tag("network")
         .with_tag("api")
@@ -271,7 +316,12 @@ impl HttpPutTool {
         .with_side_effects(true)
         .with_estimated_duration_ms(1500);
         
-        Self { metadata, client }
+        Self { metadata, client, guard: None }
+    }
+
+    /// Create an HTTP PUT tool whose requests pass through an egress guard
+    pub fn guarded(guard: Arc<HttpGuard>) -> Self {
+        Self { guard: Some(guard), ..Self::new() }
     }
 }
 
@@ -292,41 +342,51 @@ impl Tool for HttpPutTool {
         let headers = input.get_parameter::<HashMap<String, String>>("headers")
             .unwrap_or_default();
 
-        let mut request = self.client.put(&url);
-        
-        for (key, value) in headers {
-            request = request.header(&key, &value);
-        }
-
-        request = request.json(&body);
-
-        let response = request.send().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("HTTP request failed: {}", e),
-            })?;
-
-        let status = response.status();
-        let headers_map: HashMap<String, String> = response.headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
-
-        let response_body = response.text().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("Failed to read response body: {}", e),
-            })?;
+        let (status, headers_map, response_body) = match &self.guard {
+            Some(guard) => {
+                let response = guard.send(&self.metadata.id, Method::PUT, &url, &headers, Some(&body), &input).await?;
+                (response.status, response.headers, response.body)
+            }
+            None => {
+                check_sandbox_egress(&url).await?;
+                let mut request = self.client.put(&url);
+
+                for (key, value) in headers {
+                    request = request.header(&key, &value);
+                }
+
+                request = request.json(&body);
+
+                let response = request.send().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("HTTP request failed: {}", e),
+                    })?;
+
+                let status = response.status().as_u16();
+                let headers_map: HashMap<String, String> = response.headers()
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+                    .collect();
+
+                let response_body = response.text().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("Failed to read response body: {}", e),
+                    })?;
+                (status, headers_map, response_body)
+            }
+        };
 
         let parsed_body = serde_json::from_str::<Value>(&response_body)
             .unwrap_or_else(|_| Value::String(response_body));
 
         let output = ToolOutput::new(json!({
-            "status": status.as_u16(),
+            "status": status,
             "headers": headers_map,
             "body": parsed_body
         }))
         .with_metadata("url", &url)
         .with_metadata("method", "PUT")
-        .with_metric("status_code", status.as_u16() as f64);
+        .with_metric("status_code", status as f64);
 
         Ok(output)
     }
@@ -352,6 +412,7 @@ impl Tool for HttpPutTool {
 pub struct HttpDeleteTool {
     metadata: ToolMetadata,
     client: reqwest::Client,
+    guard: Option<Arc<HttpGuard>>,
 }
 
 impl HttpDeleteTool {
@@ -367,6 +428,7 @@ impl HttpDeleteTool {
             "HTTP DELETE",
             "Make HTTP DELETE requests to remove data via web APIs"
         )
+        .with_namespace("web")
         .with_tag("http")
         .with_tag("network")
         .with_tag("api")
@@ -374,7 +436,12 @@ impl HttpDeleteTool {
         .with_side_effects(true)
         .with_estimated_duration_ms(1000);
         
-        Self { metadata, client }
+        Self { metadata, client, guard: None }
+    }
+
+    /// Create an HTTP DELETE tool whose requests pass through an egress guard
+    pub fn guarded(guard: Arc<HttpGuard>) -> Self {
+        Self { guard: Some(guard), ..Self::new() }
     }
 }
 
@@ -398,39 +465,49 @@ impl Tool for HttpDeleteTool {
         let headers = input.get_parameter::<HashMap<String, String>>("headers")
             .unwrap_or_default();
 
-        let mut request = self.client.delete(&url);
-        
-        for (key, value) in headers {
-            request = request.header(&key, &value);
-        }
-
-        let response = request.send().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("HTTP request failed: {}", e),
-            })?;
-
-        let status = response.status();
-        let headers_map: HashMap<String, String> = response.headers()
-            .iter()
-            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-            .collect();
-
-        let response_body = response.text().await
-            .map_err(|e| ToolError::NetworkError {
-                message: format!("Failed to read response body: {}", e),
-            })?;
+        let (status, headers_map, response_body) = match &self.guard {
+            Some(guard) => {
+                let response = guard.send(&self.metadata.id, Method::DELETE, &url, &headers, None, &input).await?;
+                (response.status, response.headers, response.body)
+            }
+            None => {
+                check_sandbox_egress(&url).await?;
+                let mut request = self.client.delete(&url);
+
+                for (key, value) in headers {
+                    request = request.header(&key, &value);
+                }
+
+                let response = request.send().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("HTTP request failed: {}", e),
+                    })?;
+
+                let status = response.status().as_u16();
+                let headers_map: HashMap<String, String> = response.headers()
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+                    .collect();
+
+                let response_body = response.text().await
+                    .map_err(|e| ToolError::NetworkError {
+                        message: format!("Failed to read response body: {}", e),
+                    })?;
+                (status, headers_map, response_body)
+            }
+        };
 
         let parsed_body = serde_json::from_str::<Value>(&response_body)
             .unwrap_or_else(|_| Value::String(response_body));
 
         let output = ToolOutput::new(json!({
-            "status": status.as_u16(),
+            "status": status,
             "headers": headers_map,
             "body": parsed_body
         }))
         .with_metadata("url", &url)
         .with_metadata("method", "DELETE")
-        .with_metric("status_code", status.as_u16() as f64);
+        .with_metric("status_code", status as f64);
 
         Ok(output)
     }
@@ -18,6 +18,7 @@ impl CalculatorTool {
             "Calculator",
             "Perform basic mathematical calculations"
         )
+        .with_namespace("math")
         .with_tag("math")
         .with_tag("calculation")
         .with_tag("utility")
@@ -154,6 +155,7 @@ impl StatisticsTool {
             "Statistics Calculator",
             "Calculate statistical measures for datasets"
         )
+        .with_namespace("math")
         .with_tag("math")
         .with_tag("statistics")
         .with_tag("analysis")
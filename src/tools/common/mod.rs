@@ -3,37 +3,88 @@
 
 /// HTTP tools for making web requests
 pub mod http;
+/// Egress guard for HTTP tools
+pub mod egress;
 /// File system tools for reading, writing, and managing files
 pub mod file;
+/// Root directory sandbox for file system tools
+pub mod sandbox;
 /// Database tools for querying and manipulating data
 pub mod database;
 /// Text processing tools
 pub mod text;
 /// Mathematical computation tools
 pub mod math;
+/// Document parsing into text, sections and chunks for RAG
+pub mod document;
+/// PDF, DOCX, HTML and OCR parsers behind the `documents` and `ocr` features
+#[cfg(feature = "documents")]
+mod document_formats;
+/// Vector search tool over memory collections
+pub mod vector;
+/// Sandboxed shell command tool
+#[cfg(feature = "shell")]
+pub mod shell;
+/// Python and JavaScript evaluation tools
+#[cfg(feature = "scripting")]
+pub mod script;
+/// Human approval for tools that send on a user's behalf
+mod approval;
+/// Calendar event tools over CalDAV
+pub mod calendar;
+/// Email tools over SMTP and IMAP
+#[cfg(feature = "email")]
+pub mod email;
 
 pub use http::{HttpGetTool, HttpPostTool, HttpPutTool, HttpDeleteTool};
-pub use file::{FileReadTool, FileWriteTool, DirectoryListTool};
+pub use file::{FileReadTool, FileWriteTool, DirectoryListTool, FileListTool};
+pub use sandbox::{FsSandbox, FsSandboxConfig};
+pub use egress::{HttpGuard, HttpGuardConfig, GuardedResponse};
+pub use vector::VectorSearchTool;
+pub use document::{
+    normalize_text, parse_document, ChunkOptions, DocumentChunk, DocumentFormat, DocumentSection,
+    ParseDocumentTool, ParsedDocument,
+};
+#[cfg(feature = "shell")]
+pub use shell::{ShellTool, ShellPolicy};
+#[cfg(feature = "scripting")]
+pub use script::{ScriptEvalTool, ScriptLanguage, ScriptLimits};
+pub use calendar::{CalDavClient, CalDavConfig, CalendarClient, CalendarEvent, CreateCalendarEventTool};
+#[cfg(feature = "email")]
+pub use email::{
+    EmailTemplate, ImapConfig, ImapMailbox, MailFilter, MailTransport, MailboxReader,
+    ReadInboxTool, SendEmailTool, SmtpConfig, SmtpMailTransport, SmtpSecurity,
+};
 pub use database::{SqlQueryTool, JsonQueryTool};
 pub use text::{TextProcessorTool, RegexTool, TemplateRenderTool};
 pub use math::{CalculatorTool, StatisticsTool};
 
+#[cfg(feature = "email")]
+use crate::human::traits::HumanInteraction;
 use crate::tools::registry::{ToolRegistry, ToolRegistryBuilder};
 use crate::tools::traits::ToolResult;
+use std::sync::Arc;
 
 /// Create a registry with all common tools
+///
+/// HTTP requests pass through a default egress guard, which rejects private,
+/// loopback and metadata addresses. File tools are confined to the current
+/// working directory.
 pub fn create_common_tools_registry() -> ToolResult<ToolRegistry> {
+    let guard = Arc::new(HttpGuard::default());
+    let sandbox = Arc::new(FsSandbox::new(".")?);
     let registry = ToolRegistryBuilder::new()
         // HTTP tools
-        .with_tool(HttpGetTool::new())?
-        .with_tool(HttpPostTool::new())?
-        .with_tool(HttpPutTool::new())?
-        .with_tool(HttpDeleteTool::new())?
+        .with_tool(HttpGetTool::guarded(guard.clone()))?
+        .with_tool(HttpPostTool::guarded(guard.clone()))?
+        .with_tool(HttpPutTool::guarded(guard.clone()))?
+        .with_tool(HttpDeleteTool::guarded(guard))?
 
         // File tools
-        .with_tool(FileReadTool::new())?
-        .with_tool(FileWriteTool::new())?
-        .with_tool(DirectoryListTool::new())?
+        .with_tool(FileReadTool::sandboxed(sandbox.clone()))?
+        .with_tool(FileWriteTool::sandboxed(sandbox.clone()))?
+        .with_tool(DirectoryListTool::sandboxed(sandbox.clone()))?
+        .with_tool(ParseDocumentTool::sandboxed(sandbox))?
 
         // Database tools
         .with_tool(SqlQueryTool::new())?
@@ -53,12 +104,58 @@ pub fn create_common_tools_registry() -> ToolResult<ToolRegistry> {
     Ok(registry)
 }
 
+/// Create a registry of file tools confined to a sandbox root
+pub fn create_sandboxed_file_registry(sandbox: Arc<FsSandbox>) -> ToolResult<ToolRegistry> {
+    let registry = ToolRegistryBuilder::new()
+        .with_tool(FileReadTool::sandboxed(sandbox.clone()))?
+        .with_tool(FileWriteTool::sandboxed(sandbox.clone()))?
+        .with_tool(ParseDocumentTool::sandboxed(sandbox.clone()))?
+        .with_tool(FileListTool::new(sandbox))?
+        .build();
+
+    Ok(registry)
+}
+
+/// Create a registry of HTTP tools whose requests pass through an egress guard
+pub fn create_guarded_http_registry(guard: Arc<HttpGuard>) -> ToolResult<ToolRegistry> {
+    let registry = ToolRegistryBuilder::new()
+        .with_tool(HttpGetTool::guarded(guard.clone()))?
+        .with_tool(HttpPostTool::guarded(guard.clone()))?
+        .with_tool(HttpPutTool::guarded(guard.clone()))?
+        .with_tool(HttpDeleteTool::guarded(guard))?
+        .build();
+
+    Ok(registry)
+}
+
+/// Create a registry of email and calendar tools for assistant and support agents
+///
+/// Sending email and calendar invitations goes through `approver` first.
+#[cfg(feature = "email")]
+pub fn create_communication_registry(
+    transport: Arc<dyn MailTransport>,
+    from: &str,
+    mailbox: Arc<dyn MailboxReader>,
+    calendar: Arc<dyn CalendarClient>,
+    approver: Arc<dyn HumanInteraction>,
+) -> ToolResult<ToolRegistry> {
+    let registry = ToolRegistryBuilder::new()
+        .with_tool(SendEmailTool::new(transport, from).with_approver(approver.clone()))?
+        .with_tool(ReadInboxTool::new(mailbox))?
+        .with_tool(CreateCalendarEventTool::new(calendar).with_approver(approver))?
+        .build();
+
+    Ok(registry)
+}
+
 /// Tool categories for organization
 pub mod categories {
     /// HTTP and web-related tools
     pub const HTTP: &str = "http";
     /// File system tools
     pub const FILE: &str = "file";
+    /// Document parsing tools
+    pub const DOCUMENT: &str = "document";
     /// Database and data query tools
     pub const DATABASE: &str = "database";
     /// Text processing tools
@@ -71,6 +168,8 @@ pub mod categories {
     pub const NETWORK: &str = "network";
     /// Input/output tools
     pub const IO: &str = "io";
+    /// Email, calendar, and messaging tools
+    pub const COMMUNICATION: &str = "communication";
 }
 
 #[cfg(test)]
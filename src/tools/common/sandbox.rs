@@ -0,0 +1,301 @@
+// Filesystem sandbox for file tools
+// Confines tool paths to a root directory, chroot-style, with size and extension limits
+
+use crate::enterprise::audit::{AuditEvent, AuditLogger};
+use crate::tools::traits::{ToolError, ToolInput, ToolResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Sandbox limits for filesystem tools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsSandboxConfig {
+    /// Maximum size of a file that may be read
+    pub max_read_bytes: u64,
+    /// Maximum size of contents that may be written
+    pub max_write_bytes: u64,
+    /// Allowed file extensions without the dot; `None` allows any
+    pub allowed_extensions: Option<Vec<String>>,
+    /// Maximum entries returned by a listing
+    pub max_list_entries: usize,
+    /// Maximum recursion depth for listings
+    pub max_list_depth: usize,
+}
+
+impl Default for FsSandboxConfig {
+    fn default() -> Self {
+        Self {
+            max_read_bytes: 10 * 1024 * 1024, // 10MB
+            max_write_bytes: 10 * 1024 * 1024,
+            allowed_extensions: None,
+            max_list_entries: 1000,
+            max_list_depth: 8,
+        }
+    }
+}
+
+/// Root directory jail shared by the filesystem tools
+///
+/// Every requested path is interpreted relative to the root: absolute paths
+/// are re-rooted, `..` may not climb above the root, and symlinks resolving
+/// outside the root are rejected.
+#[derive(Debug)]
+pub struct FsSandbox {
+    /// Canonical root directory
+    root: PathBuf,
+    /// Sandbox limits
+    config: FsSandboxConfig,
+    /// Audit logger receiving access events
+    audit_logger: Option<Arc<AuditLogger>>,
+}
+
+impl FsSandbox {
+    /// Create a sandbox rooted at an existing directory
+    pub fn new<P: AsRef<Path>>(root: P) -> ToolResult<Self> {
+        let root = root.as_ref().canonicalize()
+            .map_err(|e| ToolError::ConfigurationError {
+                message: format!("Invalid sandbox root '{}': {}", root.as_ref().display(), e),
+            })?;
+
+        if !root.is_dir() {
+            return Err(ToolError::ConfigurationError {
+                message: format!("Sandbox root '{}' is not a directory", root.display()),
+            });
+        }
+
+        Ok(Self {
+            root,
+            config: FsSandboxConfig::default(),
+            audit_logger: None,
+        })
+    }
+
+    /// Set sandbox limits
+    pub fn with_config(mut self, config: FsSandboxConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Restrict accessible files to the given extensions
+    pub fn with_allowed_extensions(mut self, extensions: Vec<&str>) -> Self {
+        self.config.allowed_extensions = Some(
+            extensions.into_iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect(),
+        );
+        self
+    }
+
+    /// Record file access in an audit log
+    pub fn with_audit_logger(mut self, audit_logger: Arc<AuditLogger>) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
+    /// Canonical root directory
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Sandbox limits
+    pub fn config(&self) -> &FsSandboxConfig {
+        &self.config
+    }
+
+    /// Resolve a requested path to a location inside the root
+    pub fn resolve(&self, requested: &str) -> ToolResult<PathBuf> {
+        let mut resolved = self.root.clone();
+        for component in Path::new(requested).components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+                Component::ParentDir => {
+                    if resolved == self.root {
+                        return Err(self.escape_error(requested));
+                    }
+                    resolved.pop();
+                }
+                Component::Normal(part) => resolved.push(part),
+            }
+        }
+
+        // Symlinks are followed by the OS, so check where the deepest existing ancestor really points.
+        // `symlink_metadata` also finds dangling symlinks, which `exists` skips but a write would follow.
+        let mut existing = resolved.as_path();
+        while existing.symlink_metadata().is_err() {
+            existing = match existing.parent() {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+        let canonical = existing.canonicalize()
+            .map_err(|e| match existing.is_symlink() {
+                true => ToolError::PermissionDenied {
+                    message: format!("Path '{}' goes through a dangling symlink", requested),
+                },
+                false => ToolError::IoError {
+                    message: format!("Failed to resolve '{}': {}", requested, e),
+                },
+            })?;
+        if !canonical.starts_with(&self.root) {
+            return Err(self.escape_error(requested));
+        }
+
+        Ok(canonical.join(resolved.strip_prefix(existing).unwrap_or(Path::new(""))))
+    }
+
+    /// Path relative to the root, as reported back to agents
+    pub fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Check a path against the extension allowlist
+    pub fn check_extension(&self, path: &Path) -> ToolResult<()> {
+        if let Some(allowed) = &self.config.allowed_extensions {
+            let extension = path.extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !allowed.contains(&extension) {
+                return Err(ToolError::PermissionDenied {
+                    message: format!("File extension '{}' is not allowed", extension),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an access attempt in the audit log, if configured
+    pub async fn audit(&self, tool_id: &str, requested: &str, input: &ToolInput, allowed: bool) {
+        if let Some(logger) = &self.audit_logger {
+            let mut event = AuditEvent::tool_execution(tool_id.to_string(), requested.to_string(), allowed)
+                .with_data("sandbox_root".to_string(), self.root.display().to_string());
+            if let Some(tenant_id) = input.get_context("tenant_id") {
+                event = event.with_tenant(tenant_id.clone());
+            }
+            if let Some(user_id) = input.get_context("user_id") {
+                event = event.with_user(user_id.clone());
+            }
+            if let Err(e) = logger.log_event(event).await {
+                tracing::warn!("Failed to record file access audit event: {}", e);
+            }
+        }
+    }
+
+    fn escape_error(&self, requested: &str) -> ToolError {
+        ToolError::PermissionDenied {
+            message: format!("Path '{}' escapes the sandbox root", requested),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enterprise::audit::AuditEventType;
+    use crate::tools::common::file::{FileListTool, FileReadTool, FileWriteTool};
+    use crate::tools::traits::Tool;
+
+    #[test]
+    fn test_resolve_stays_in_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        let sandbox = FsSandbox::new(dir.path()).unwrap();
+        let root = sandbox.root().to_path_buf();
+
+        assert_eq!(sandbox.resolve("docs/a.txt").unwrap(), root.join("docs/a.txt"));
+        assert_eq!(sandbox.resolve("/docs/../b.txt").unwrap(), root.join("b.txt"));
+        assert_eq!(sandbox.resolve("/etc/passwd").unwrap(), root.join("etc/passwd"));
+        assert!(matches!(sandbox.resolve("../outside"), Err(ToolError::PermissionDenied { .. })));
+        assert!(sandbox.resolve("docs/../../outside").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escape_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+        let sandbox = FsSandbox::new(dir.path()).unwrap();
+        assert!(matches!(sandbox.resolve("link/secret.txt"), Err(ToolError::PermissionDenied { .. })));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dangling_symlink_escape_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let target = outside.path().join("planted.txt");
+        std::os::unix::fs::symlink(&target, dir.path().join("report.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("missing"), dir.path().join("cache")).unwrap();
+
+        let sandbox = Arc::new(FsSandbox::new(dir.path()).unwrap());
+        assert!(matches!(sandbox.resolve("report.txt"), Err(ToolError::PermissionDenied { .. })));
+        assert!(matches!(sandbox.resolve("cache/entry.txt"), Err(ToolError::PermissionDenied { .. })));
+
+        let writer = FileWriteTool::sandboxed(sandbox);
+        let input = ToolInput::new(serde_json::json!("pwned")).with_parameter("path", "report.txt");
+        assert!(writer.execute(input).await.is_err());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_extension_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = FsSandbox::new(dir.path()).unwrap().with_allowed_extensions(vec!["md", ".TXT"]);
+
+        assert!(sandbox.check_extension(Path::new("notes.md")).is_ok());
+        assert!(sandbox.check_extension(Path::new("notes.txt")).is_ok());
+        assert!(sandbox.check_extension(Path::new("script.sh")).is_err());
+        assert!(sandbox.check_extension(Path::new("Makefile")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sandboxed_file_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("notes")).unwrap();
+        let config = FsSandboxConfig { max_write_bytes: 16, ..Default::default() };
+        let sandbox = Arc::new(FsSandbox::new(dir.path()).unwrap().with_config(config));
+
+        let writer = FileWriteTool::sandboxed(sandbox.clone());
+        let input = ToolInput::new(serde_json::json!("hello")).with_parameter("path", "/notes/a.txt");
+        writer.execute(input).await.unwrap();
+        assert!(dir.path().join("notes/a.txt").exists());
+
+        let input = ToolInput::new(serde_json::json!("x".repeat(17))).with_parameter("path", "notes/b.txt");
+        assert!(matches!(writer.execute(input).await, Err(ToolError::PermissionDenied { .. })));
+
+        let input = ToolInput::new(serde_json::json!("pwned")).with_parameter("path", "../escape.txt");
+        assert!(writer.execute(input).await.is_err());
+
+        let reader = FileReadTool::sandboxed(sandbox.clone());
+        let output = reader.execute(ToolInput::new(serde_json::json!("notes/a.txt"))).await.unwrap();
+        assert_eq!(output.data["contents"], "hello");
+
+        let lister = FileListTool::new(sandbox);
+        let output = lister.execute(ToolInput::new(serde_json::json!("/"))).await.unwrap();
+        assert_eq!(output.data["files"][0]["path"], "notes/a.txt");
+        assert_eq!(output.data["truncated"], false);
+    }
+
+    #[tokio::test]
+    async fn test_file_access_is_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data.csv"), "a,b").unwrap();
+
+        let logger = Arc::new(AuditLogger::in_memory());
+        let sandbox = Arc::new(
+            FsSandbox::new(dir.path()).unwrap()
+                .with_allowed_extensions(vec!["csv"])
+                .with_audit_logger(logger.clone()),
+        );
+
+        let reader = FileReadTool::sandboxed(sandbox);
+        let input = ToolInput::new(serde_json::json!("data.csv")).with_context("tenant_id", "acme");
+        reader.execute(input).await.unwrap();
+        assert!(reader.execute(ToolInput::new(serde_json::json!("../../etc/passwd"))).await.is_err());
+
+        let stats = logger.get_stats();
+        assert_eq!(stats.total_events, 2);
+        assert_eq!(stats.events_by_type.get(&AuditEventType::ToolExecution), Some(&2));
+    }
+}
@@ -0,0 +1,534 @@
+// Script evaluation tools for Python and JavaScript
+// Available with the `scripting` feature; code runs in a resource-limited interpreter subprocess
+
+use super::sandbox::FsSandbox;
+use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Python harness: runs the code with `input` bound and writes `result` as JSON to the result file
+///
+/// With a `jail`, an audit hook keeps the script's reads to the jail and the
+/// interpreter's own modules, its writes to the jail, and stops it starting
+/// processes or loading native libraries. The result file is opened first.
+const PYTHON_HARNESS: &str = r#"
+import contextlib, io, json, os, sys, traceback
+payload = json.loads(sys.stdin.read())
+channel = open(payload.pop("result_path"), "w")
+limit = payload["max_output"]
+jail = payload.get("jail")
+if jail:
+    # Reads stay in the jail and the interpreter's own modules, writes in the jail
+    readable = [jail] + [os.path.realpath(path) for path in sys.path if path]
+    def inside(path, roots):
+        try:
+            path = os.path.realpath(os.fsdecode(path))
+        except (TypeError, ValueError):
+            return False
+        return any(path == root or path.startswith(root.rstrip(os.sep) + os.sep) for root in roots)
+    def guard(event, args):
+        if event == "open":
+            path, mode, flags = args
+            writing = any(c in (mode or "") for c in "wax+") or flags & (os.O_WRONLY | os.O_RDWR | os.O_CREAT)
+            if not isinstance(path, int) and not inside(path, [jail] if writing else readable):
+                raise PermissionError("Access to '%s' is outside the sandbox" % (path,))
+        elif event in ("os.listdir", "os.scandir", "os.chdir") and args[0] is not None and not isinstance(args[0], int):
+            if not inside(args[0], readable):
+                raise PermissionError("Access to '%s' is outside the sandbox" % (args[0],))
+        elif event in ("os.mkdir", "os.remove", "os.rmdir", "os.rename", "os.link", "os.symlink", "os.truncate",
+                       "os.chmod", "os.chown", "os.utime", "shutil.rmtree"):
+            paths = [arg for arg in args[:2] if isinstance(arg, (str, bytes, os.PathLike))]
+            if not all(inside(path, [jail]) for path in paths):
+                raise PermissionError("Access to '%s' is outside the sandbox" % (paths,))
+        elif event in ("subprocess.Popen", "os.system", "os.exec", "os.posix_spawn", "os.spawn", "os.fork",
+                       "os.forkpty", "ctypes.dlopen"):
+            raise PermissionError("%s is not allowed in the sandbox" % event)
+    sys.addaudithook(guard)
+out, err = io.StringIO(), io.StringIO()
+scope = {"__name__": "__eval__", "input": payload.get("input")}
+error = None
+with contextlib.redirect_stdout(out), contextlib.redirect_stderr(err):
+    try:
+        exec(compile(payload["code"], "<eval>", "exec"), scope)
+    except BaseException:
+        error = traceback.format_exc()
+result = scope.get("result")
+try:
+    json.dumps(result)
+except (TypeError, ValueError):
+    result = repr(result)
+o, e = out.getvalue(), err.getvalue()
+channel.write(json.dumps({"result": result, "stdout": o[:limit], "stderr": e[:limit],
+                          "truncated": len(o) > limit or len(e) > limit, "error": error}))
+channel.close()
+"#;
+
+/// JavaScript harness: runs the code as an async function body and writes its return value to the result file
+const JS_HARNESS: &str = r#"
+const fs = require('fs');
+const chunks = [];
+process.stdin.on('data', c => chunks.push(c));
+process.stdin.on('end', async () => {
+  const p = JSON.parse(Buffer.concat(chunks).toString());
+  const out = [], err = [];
+  const fmt = a => a.map(x => typeof x === 'string' ? x : JSON.stringify(x)).join(' ');
+  console.log = console.info = (...a) => out.push(fmt(a));
+  console.error = console.warn = (...a) => err.push(fmt(a));
+  let result = null, error = null;
+  try {
+    const AsyncFunction = Object.getPrototypeOf(async function () {}).constructor;
+    result = await new AsyncFunction('input', p.code)(p.input);
+  } catch (e) {
+    error = String((e && e.stack) || e);
+  }
+  let value;
+  try { value = JSON.parse(JSON.stringify(result === undefined ? null : result)); } catch (_) { value = String(result); }
+  const o = out.join('\n'), e = err.join('\n');
+  fs.writeFileSync(p.result_path, JSON.stringify({ result: value, stdout: o.slice(0, p.max_output), stderr: e.slice(0, p.max_output),
+    truncated: o.length > p.max_output || e.length > p.max_output, error }));
+});
+"#;
+
+/// Supported scripting languages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptLanguage {
+    /// Python 3; the value assigned to `result` is returned
+    Python,
+    /// JavaScript on Node.js; the code's `return` value is returned
+    JavaScript,
+}
+
+impl ScriptLanguage {
+    /// Default interpreter binary
+    pub fn default_interpreter(&self) -> &'static str {
+        match self {
+            ScriptLanguage::Python => "python3",
+            ScriptLanguage::JavaScript => "node",
+        }
+    }
+}
+
+/// Resource limits for script evaluation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptLimits {
+    /// Wall-clock timeout
+    pub timeout: Duration,
+    /// Maximum interpreter memory in bytes; JavaScript's heap is capped to the same size
+    pub max_memory_bytes: u64,
+    /// Maximum characters kept from each of stdout and stderr
+    pub max_output_chars: usize,
+    /// Allow network access; otherwise scripts run in an empty network namespace
+    pub allow_network: bool,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_memory_bytes: 256 * 1024 * 1024, // 256MB
+            max_output_chars: 64 * 1024,
+            allow_network: false,
+        }
+    }
+}
+
+/// Tool evaluating Python or JavaScript code in an interpreter subprocess
+///
+/// Input is either the code as a string or an object with `code` and an
+/// optional JSON `input` value exposed to the script as `input`. Each run
+/// gets a scratch directory, inside the sandbox root when there is one, as
+/// its working directory, `HOME` and `TMPDIR`; it is removed afterwards. The
+/// result comes back through a file outside the scratch directory, so what
+/// the script prints cannot pass for it.
+///
+/// With a sandbox, scripts may only touch files under its root: Python
+/// through an audit hook installed before the code runs, JavaScript through
+/// Node's permission model, which also refuses child processes and addons.
+#[derive(Debug)]
+pub struct ScriptEvalTool {
+    metadata: ToolMetadata,
+    language: ScriptLanguage,
+    interpreter: String,
+    limits: ScriptLimits,
+    sandbox: Option<Arc<FsSandbox>>,
+    node_permission_flag: OnceLock<&'static str>,
+}
+
+impl ScriptEvalTool {
+    /// Create a `python_eval` tool
+    pub fn python() -> Self {
+        Self::new(
+            ScriptLanguage::Python,
+            ToolMetadata::new(
+                "python_eval",
+                "Python Evaluator",
+                "Run Python code; assign the value to return to `result`"
+            ),
+        )
+    }
+
+    /// Create a `js_eval` tool
+    pub fn javascript() -> Self {
+        Self::new(
+            ScriptLanguage::JavaScript,
+            ToolMetadata::new(
+                "js_eval",
+                "JavaScript Evaluator",
+                "Run JavaScript code as a function body; `return` the value to produce"
+            ),
+        )
+    }
+
+    fn new(language: ScriptLanguage, metadata: ToolMetadata) -> Self {
+        let metadata = metadata
+            .with_namespace("code")
+            .with_tag("code")
+            .with_deterministic(false)
+            .with_side_effects(true)
+            .with_estimated_duration_ms(500);
+
+        Self {
+            metadata,
+            language,
+            interpreter: language.default_interpreter().to_string(),
+            limits: ScriptLimits::default(),
+            sandbox: None,
+            node_permission_flag: OnceLock::new(),
+        }
+    }
+
+    /// Use a specific interpreter binary
+    pub fn with_interpreter<S: Into<String>>(mut self, interpreter: S) -> Self {
+        self.interpreter = interpreter.into();
+        self
+    }
+
+    /// Set resource limits
+    pub fn with_limits(mut self, limits: ScriptLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Run scripts in scratch directories inside the sandbox root, with no file access outside it
+    pub fn with_sandbox(mut self, sandbox: Arc<FsSandbox>) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Script language
+    pub fn language(&self) -> ScriptLanguage {
+        self.language
+    }
+
+    fn parse_input(input: &ToolInput) -> ToolResult<(String, Value)> {
+        if let Some(code) = input.data.as_str() {
+            return Ok((code.to_string(), Value::Null));
+        }
+
+        let code = input.data.get("code").and_then(|v| v.as_str())
+            .ok_or_else(|| ToolError::ValidationError {
+                message: "Code is required".to_string(),
+            })?;
+        Ok((code.to_string(), input.data.get("input").cloned().unwrap_or(Value::Null)))
+    }
+
+    /// Flag turning on Node's permission model, which older releases call experimental
+    fn node_permission_flag(&self) -> &'static str {
+        self.node_permission_flag.get_or_init(|| {
+            let stable = std::process::Command::new(&self.interpreter)
+                .args(["--permission", "-e", "0"])
+                .output()
+                .is_ok_and(|output| output.status.success());
+            if stable { "--permission" } else { "--experimental-permission" }
+        })
+    }
+
+    fn build_command(&self, scratch: &Path) -> Command {
+        let memory_mb = (self.limits.max_memory_bytes / (1024 * 1024)).max(16);
+        let mut argv: Vec<String> = Vec::new();
+
+        if !self.limits.allow_network {
+            argv.extend(["unshare", "--net", "--map-root-user", "--"].map(String::from));
+        }
+
+        match self.language {
+            ScriptLanguage::Python => {
+                argv.extend([
+                    "prlimit".to_string(),
+                    format!("--as={}", self.limits.max_memory_bytes),
+                    "--".to_string(),
+                    self.interpreter.clone(),
+                    "-I".to_string(),
+                    "-c".to_string(),
+                    PYTHON_HARNESS.to_string(),
+                ]);
+            }
+            // V8 reserves far more address space than it uses, so cap the data
+            // segment, which buffers count against, and the heap instead
+            ScriptLanguage::JavaScript => {
+                argv.extend([
+                    "prlimit".to_string(),
+                    format!("--data={}", memory_mb * 1024 * 1024),
+                    "--".to_string(),
+                    self.interpreter.clone(),
+                    format!("--max-old-space-size={}", memory_mb),
+                ]);
+                if let Some(sandbox) = &self.sandbox {
+                    let root = sandbox.root().display();
+                    argv.extend([
+                        self.node_permission_flag().to_string(),
+                        "--no-warnings".to_string(),
+                        format!("--allow-fs-read={}", root),
+                        format!("--allow-fs-write={}", root),
+                    ]);
+                }
+                argv.extend(["-e".to_string(), JS_HARNESS.to_string()]);
+            }
+        }
+
+        let mut command = Command::new(&argv[0]);
+        command
+            .args(&argv[1..])
+            .current_dir(scratch)
+            .env_clear()
+            .env("PATH", std::env::var("PATH").unwrap_or_else(|_| "/usr/bin:/bin".to_string()))
+            .env("HOME", scratch)
+            .env("TMPDIR", scratch)
+            .env("LANG", "C.UTF-8")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        command
+    }
+}
+
+#[async_trait]
+impl Tool for ScriptEvalTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let (code, script_input) = Self::parse_input(&input)?;
+        let base = self.sandbox.as_ref().map_or_else(std::env::temp_dir, |sandbox| sandbox.root().to_path_buf());
+        let run = RunDir::create(&base)?;
+        let payload = json!({
+            "code": code,
+            "input": script_input,
+            "max_output": self.limits.max_output_chars,
+            "result_path": run.result_path(),
+            "jail": self.sandbox.as_ref().map(|sandbox| sandbox.root())
+        });
+
+        let start = Instant::now();
+        let mut child = self.build_command(&run.scratch()).spawn()
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Failed to start {}: {}", self.interpreter, e),
+            })?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(payload.to_string().as_bytes()).await
+                .map_err(|e| ToolError::IoError {
+                    message: format!("Failed to send code to interpreter: {}", e),
+                })?;
+        }
+
+        let output = tokio::time::timeout(self.limits.timeout, child.wait_with_output()).await
+            .map_err(|_| ToolError::TimeoutError {
+                timeout_ms: self.limits.timeout.as_millis() as u64,
+            })?
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Interpreter failed: {}", e),
+            })?;
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        // A missing envelope means the interpreter died, e.g. from hitting the memory limit
+        let envelope: Value = std::fs::read(run.result_path()).ok()
+            .and_then(|envelope| serde_json::from_slice(&envelope).ok())
+            .ok_or_else(|| ToolError::ExecutionError {
+                message: format!(
+                    "Interpreter exited with {} without a result: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).chars().take(self.limits.max_output_chars).collect::<String>()
+                ),
+            })?;
+
+        if let Some(error) = envelope["error"].as_str() {
+            return Err(ToolError::ExecutionError {
+                message: format!("Script raised an error: {}", error),
+            });
+        }
+
+        let output = ToolOutput::new(json!({
+            "result": envelope["result"],
+            "stdout": envelope["stdout"],
+            "stderr": envelope["stderr"],
+            "truncated": envelope["truncated"]
+        }))
+        .with_metadata("language", format!("{:?}", self.language).to_lowercase())
+        .with_metric("duration_ms", duration_ms as f64)
+        .with_metric("code_length", code.len() as f64);
+
+        Ok(output)
+    }
+
+    async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
+        let (code, _) = Self::parse_input(input)?;
+        if code.trim().is_empty() {
+            return Err(ToolError::ValidationError {
+                message: "Code must not be empty".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Directory of one script run, removed when dropped
+///
+/// Holds the scratch directory the script runs in and, beside it, the file
+/// the harness writes the result to.
+struct RunDir(PathBuf);
+
+impl RunDir {
+    fn create(base: &Path) -> ToolResult<Self> {
+        let dir = base.join(format!(".script-eval-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("scratch"))
+            .map_err(|e| ToolError::IoError {
+                message: format!("Failed to create scratch directory: {}", e),
+            })?;
+        Ok(Self(dir))
+    }
+
+    fn scratch(&self) -> PathBuf {
+        self.0.join("scratch")
+    }
+
+    fn result_path(&self) -> PathBuf {
+        self.0.join("result.json")
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpreter_available(binary: &str) -> bool {
+        std::process::Command::new(binary).arg("--version").output().is_ok()
+    }
+
+    fn permissive() -> ScriptLimits {
+        ScriptLimits { allow_network: true, ..Default::default() }
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let (code, value) = ScriptEvalTool::parse_input(&ToolInput::new(json!("result = 1"))).unwrap();
+        assert_eq!(code, "result = 1");
+        assert_eq!(value, Value::Null);
+
+        let (_, value) = ScriptEvalTool::parse_input(&ToolInput::new(json!({"code": "x", "input": [1, 2]}))).unwrap();
+        assert_eq!(value, json!([1, 2]));
+
+        assert!(ScriptEvalTool::parse_input(&ToolInput::new(json!({"input": 1}))).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_python_eval() {
+        if !interpreter_available("python3") {
+            return;
+        }
+        let tool = ScriptEvalTool::python().with_limits(permissive());
+
+        let input = ToolInput::new(json!({"code": "print('sum')\nresult = {'total': sum(input)}", "input": [1, 2, 3]}));
+        let output = tool.execute(input).await.unwrap();
+        assert_eq!(output.data["result"], json!({"total": 6}));
+        assert_eq!(output.data["stdout"], "sum\n");
+
+        let error = tool.execute(ToolInput::new(json!("raise ValueError('bad')"))).await;
+        assert!(matches!(error, Err(ToolError::ExecutionError { .. })));
+
+        let slow = ScriptEvalTool::python()
+            .with_limits(ScriptLimits { timeout: Duration::from_millis(200), ..permissive() });
+        let result = slow.execute(ToolInput::new(json!("while True: pass"))).await;
+        assert!(matches!(result, Err(ToolError::TimeoutError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_python_runs_in_scratch_dir_and_result_cannot_be_printed() {
+        let tool = ScriptEvalTool::python();
+        assert!(tool.metadata().has_side_effects);
+        assert!(!tool.metadata().tags.iter().any(|tag| tag == "math"));
+        if !interpreter_available("python3") {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = Arc::new(FsSandbox::new(dir.path()).unwrap());
+        let tool = tool.with_limits(permissive()).with_sandbox(sandbox.clone());
+
+        let code = "import os, sys\n\
+                    sys.__stdout__.write('{\"result\": \"forged\", \"error\": null}')\n\
+                    sys.__stdout__.flush()\n\
+                    open('scratch.txt', 'w').write('x')\n\
+                    result = os.getcwd()";
+        let output = tool.execute(ToolInput::new(json!(code))).await.unwrap();
+        let cwd = PathBuf::from(output.data["result"].as_str().unwrap());
+        assert!(cwd.starts_with(sandbox.root()));
+        assert!(!cwd.exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let read = format!("result = open({:?}).read()", outside.path());
+        assert!(matches!(tool.execute(ToolInput::new(json!(read))).await, Err(ToolError::ExecutionError { .. })));
+        let spawn = "import subprocess\nresult = subprocess.run(['true']).returncode";
+        assert!(matches!(tool.execute(ToolInput::new(json!(spawn))).await, Err(ToolError::ExecutionError { .. })));
+        let imports = "import statistics\nresult = statistics.mean([1, 2])";
+        assert_eq!(tool.execute(ToolInput::new(json!(imports))).await.unwrap().data["result"], json!(1.5));
+    }
+
+    #[tokio::test]
+    async fn test_js_eval() {
+        if !interpreter_available("node") {
+            return;
+        }
+        let tool = ScriptEvalTool::javascript().with_limits(permissive());
+
+        let input = ToolInput::new(json!({"code": "console.log('hi'); return input.map(x => x * 2);", "input": [1, 2]}));
+        let output = tool.execute(input).await.unwrap();
+        assert_eq!(output.data["result"], json!([2, 4]));
+        assert_eq!(output.data["stdout"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_js_is_confined_to_the_sandbox() {
+        if !interpreter_available("node") {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data.json"), "[1, 2]").unwrap();
+        let tool = ScriptEvalTool::javascript()
+            .with_limits(permissive())
+            .with_sandbox(Arc::new(FsSandbox::new(dir.path()).unwrap()));
+
+        let read = format!("return require('fs').readFileSync({:?}, 'utf8');", dir.path().join("data.json"));
+        assert_eq!(tool.execute(ToolInput::new(json!(read))).await.unwrap().data["result"], "[1, 2]");
+
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let read = format!("return require('fs').readFileSync({:?}, 'utf8');", outside.path());
+        assert!(matches!(tool.execute(ToolInput::new(json!(read))).await, Err(ToolError::ExecutionError { .. })));
+    }
+}
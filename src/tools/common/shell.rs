@@ -0,0 +1,471 @@
+// Shell command tool with a policy-controlled sandbox
+// Available with the `shell` feature; commands run without a shell interpreter
+
+use super::sandbox::FsSandbox;
+use crate::human::traits::{HumanInput, HumanInteraction};
+use crate::human::{HumanConfig, HumanContext};
+use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+
+/// Characters that only make sense to a shell interpreter
+const SHELL_METACHARACTERS: &[char] = &[';', '|', '&', '$', '`', '>', '<', '\n'];
+
+/// Shells and binaries that run another command, which would escape the policy
+const EXEC_WRAPPERS: &[&str] = &[
+    "sh", "bash", "zsh", "dash", "ksh", "csh", "tcsh", "fish", "busybox",
+    "env", "xargs", "nice", "ionice", "nohup", "timeout", "stdbuf", "setsid",
+    "time", "watch", "chroot", "taskset", "flock", "strace", "unshare", "parallel",
+];
+
+/// Policy for the shell tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellPolicy {
+    /// Binaries that may run; `None` allows any binary not denied, and the
+    /// default allows none
+    pub allowed_binaries: Option<Vec<String>>,
+    /// Binaries that may never run
+    pub denied_binaries: Vec<String>,
+    /// Commands that need human approval, e.g. `git push`: the binary
+    /// followed by arguments that appear in that order anywhere after it
+    pub destructive_patterns: Vec<String>,
+    /// Allow network access; otherwise commands run in an empty network namespace
+    pub allow_network: bool,
+    /// Command timeout
+    pub timeout: Duration,
+    /// Maximum bytes kept from each of stdout and stderr
+    pub max_output_bytes: usize,
+}
+
+impl Default for ShellPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_binaries: Some(Vec::new()),
+            denied_binaries: [
+                "sudo", "su", "doas",
+                "curl", "wget", "nc", "ncat", "ssh", "scp", "rsync",
+                "mkfs", "mount", "umount", "shutdown", "reboot",
+            ].iter().chain(EXEC_WRAPPERS).map(|s| s.to_string()).collect(),
+            destructive_patterns: [
+                "rm", "rmdir", "mv", "dd", "chmod", "chown", "truncate", "shred", "ln",
+                "find -exec", "find -execdir", "find -ok", "find -okdir", "find -delete",
+                "python", "python3", "perl", "ruby", "node", "awk", "gawk",
+                "git push", "git reset --hard", "git clean", "git -c",
+            ].iter().map(|s| s.to_string()).collect(),
+            allow_network: false,
+            timeout: Duration::from_secs(30),
+            max_output_bytes: 64 * 1024, // 64KB
+        }
+    }
+}
+
+impl ShellPolicy {
+    /// Restrict commands to the given binaries
+    pub fn with_allowed_binaries(mut self, binaries: Vec<&str>) -> Self {
+        self.allowed_binaries = Some(binaries.into_iter().map(String::from).collect());
+        self
+    }
+
+    /// Allow any binary that is not denied
+    pub fn with_any_binary(mut self) -> Self {
+        self.allowed_binaries = None;
+        self
+    }
+
+    /// Deny a binary
+    pub fn with_denied_binary(mut self, binary: &str) -> Self {
+        self.denied_binaries.push(binary.to_string());
+        self
+    }
+
+    /// Require approval for commands matching the given words
+    pub fn with_destructive_pattern(mut self, pattern: &str) -> Self {
+        self.destructive_patterns.push(pattern.to_string());
+        self
+    }
+
+    /// Allow network access
+    pub fn with_network(mut self, allowed: bool) -> Self {
+        self.allow_network = allowed;
+        self
+    }
+
+    /// Set command timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the output truncation limit
+    pub fn with_max_output_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_output_bytes = max_bytes;
+        self
+    }
+
+    /// Check a binary against the allow and deny lists
+    pub fn check_binary(&self, binary: &str) -> ToolResult<()> {
+        if binary.contains('/') {
+            return Err(ToolError::PermissionDenied {
+                message: format!("Binary '{}' must be a bare name resolved from PATH", binary),
+            });
+        }
+        if self.denied_binaries.iter().any(|b| b == binary) {
+            return Err(ToolError::PermissionDenied {
+                message: format!("Binary '{}' is denied", binary),
+            });
+        }
+        if let Some(allowed) = &self.allowed_binaries {
+            if !allowed.iter().any(|b| b == binary) {
+                return Err(ToolError::PermissionDenied {
+                    message: format!("Binary '{}' is not in the allowlist", binary),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Destructive pattern matched by a command, if any
+    ///
+    /// The pattern's first word is the binary; the others may be separated by
+    /// other arguments, so `git -C repo push` matches `git push`.
+    pub fn destructive_match(&self, argv: &[String]) -> Option<&str> {
+        let (binary, args) = argv.split_first()?;
+        self.destructive_patterns.iter()
+            .find(|pattern| {
+                let mut words = pattern.split_whitespace();
+                let mut args = args.iter();
+                words.next() == Some(binary.as_str()) && words.all(|word| args.any(|arg| arg == word))
+            })
+            .map(|pattern| pattern.as_str())
+    }
+}
+
+/// Tool for running commands inside a working-directory jail
+///
+/// Input is either a command string split on whitespace or an object with
+/// `command` and `args`. Commands matching a destructive pattern run only
+/// after a human approves them; without an approver they are rejected.
+#[derive(Debug)]
+pub struct ShellTool {
+    metadata: ToolMetadata,
+    sandbox: Arc<FsSandbox>,
+    policy: ShellPolicy,
+    approver: Option<Arc<dyn HumanInteraction>>,
+}
+
+impl ShellTool {
+    /// Create a shell tool jailed to the sandbox root
+    pub fn new(sandbox: Arc<FsSandbox>, policy: ShellPolicy) -> Self {
+        let metadata = ToolMetadata::new(
+            "shell",
+            "Shell",
+            "Run a command-line program in a restricted working directory"
+        )
+        .with_namespace("system")
+        .with_tag("shell")
+        .with_tag("system")
+        .with_deterministic(false)
+        .with_side_effects(true)
+        .with_estimated_duration_ms(1000);
+
+        Self { metadata, sandbox, policy, approver: None }
+    }
+
+    /// Ask a human before running destructive commands
+    pub fn with_approver(mut self, approver: Arc<dyn HumanInteraction>) -> Self {
+        self.approver = Some(approver);
+        self
+    }
+
+    /// Shell policy
+    pub fn policy(&self) -> &ShellPolicy {
+        &self.policy
+    }
+
+    fn parse_command(input: &ToolInput) -> ToolResult<Vec<String>> {
+        let argv: Vec<String> = if let Some(command) = input.data.as_str() {
+            command.split_whitespace().map(String::from).collect()
+        } else {
+            let command = input.data.get("command").and_then(|v| v.as_str())
+                .ok_or_else(|| ToolError::ValidationError {
+                    message: "Command is required".to_string(),
+                })?;
+            let args: Vec<String> = input.data.get("args")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default();
+            std::iter::once(command.to_string()).chain(args).collect()
+        };
+
+        if argv.is_empty() {
+            return Err(ToolError::ValidationError {
+                message: "Command is required".to_string(),
+            });
+        }
+        if argv.iter().any(|arg| arg.contains(SHELL_METACHARACTERS)) {
+            return Err(ToolError::PermissionDenied {
+                message: "Shell metacharacters are not supported; pass a single command".to_string(),
+            });
+        }
+        Ok(argv)
+    }
+
+    /// Check that path-like arguments stay inside the jail
+    ///
+    /// Besides the argument itself, the value of `--opt=value` and of a short
+    /// option with its value attached, `-fvalue`, is checked. Paths that exist
+    /// are resolved, so a symlink cannot point out of the jail either.
+    fn check_arguments(&self, args: &[String], working_dir: &std::path::Path) -> ToolResult<()> {
+        let cwd = self.sandbox.relative(working_dir);
+        for arg in args {
+            let mut values = vec![arg.as_str()];
+            if let Some((_, value)) = arg.split_once('=') {
+                values.push(value);
+            }
+            if arg.starts_with('-') && !arg.starts_with("--") && arg.len() > 2 {
+                values.extend(arg.get(2..));
+            }
+            for value in values {
+                if value.starts_with('/') || value.starts_with('~') {
+                    return Err(ToolError::PermissionDenied {
+                        message: format!("Absolute path '{}' is not allowed; use paths relative to the working directory", value),
+                    });
+                }
+                let escapes = value.split('/').any(|part| part == "..");
+                if escapes || working_dir.join(value).symlink_metadata().is_ok() {
+                    self.sandbox.resolve(&format!("{}/{}", cwd, value))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn request_approval(&self, argv: &[String], pattern: &str, input: &ToolInput) -> ToolResult<()> {
+        let approver = self.approver.as_ref()
+            .ok_or_else(|| ToolError::PermissionDenied {
+                message: format!("Command matching '{}' requires human approval, but no approver is configured", pattern),
+            })?;
+
+        let command_line = argv.join(" ");
+        let prompt = HumanInput::approval(format!("Allow agent to run `{}`?", command_line))
+            .with_context(format!("The command matches the destructive pattern '{}'", pattern))
+            .with_metadata("command", &command_line)
+            .with_metadata("working_dir", self.sandbox.root().display().to_string());
+
+        let mut context = HumanContext::new(uuid::Uuid::new_v4().to_string());
+        if let Some(user_id) = input.get_context("user_id") {
+            context = context.with_user_id(user_id.clone());
+        }
+
+        let response = approver.request_input(prompt, &context, &HumanConfig::default()).await
+            .map_err(|e| ToolError::PermissionDenied {
+                message: format!("Approval for '{}' failed: {}", command_line, e),
+            })?;
+
+        if response.as_bool() == Some(true) {
+            Ok(())
+        } else {
+            Err(ToolError::PermissionDenied {
+                message: format!("Command '{}' was not approved", command_line),
+            })
+        }
+    }
+
+    fn build_command(&self, argv: &[String], working_dir: &std::path::Path) -> Command {
+        let mut command = if self.policy.allow_network {
+            Command::new(&argv[0])
+        } else {
+            // A fresh user and network namespace leaves only a downed loopback interface
+            let mut command = Command::new("unshare");
+            command.args(["--net", "--map-root-user", "--"]).arg(&argv[0]);
+            command
+        };
+
+        command
+            .args(&argv[1..])
+            .current_dir(working_dir)
+            .env_clear()
+            .env("PATH", std::env::var("PATH").unwrap_or_else(|_| "/usr/bin:/bin".to_string()))
+            .env("HOME", self.sandbox.root())
+            .env("LANG", "C.UTF-8")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        command
+    }
+}
+
+#[async_trait]
+impl Tool for ShellTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let argv = Self::parse_command(&input)?;
+        self.policy.check_binary(&argv[0])?;
+
+        let working_dir = match input.get_parameter::<String>("cwd") {
+            Some(cwd) => self.sandbox.resolve(&cwd)?,
+            None => self.sandbox.root().to_path_buf(),
+        };
+        if !working_dir.is_dir() {
+            return Err(ToolError::ValidationError {
+                message: format!("Working directory '{}' does not exist", self.sandbox.relative(&working_dir)),
+            });
+        }
+        self.check_arguments(&argv[1..], &working_dir)?;
+
+        if let Some(pattern) = self.policy.destructive_match(&argv) {
+            self.request_approval(&argv, pattern, &input).await?;
+        }
+
+        let start = Instant::now();
+        let mut child = self.build_command(&argv, &working_dir).spawn()
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Failed to start '{}': {}", argv[0], e),
+            })?;
+
+        let limit = self.policy.max_output_bytes;
+        let stdout = read_limited(child.stdout.take(), limit);
+        let stderr = read_limited(child.stderr.take(), limit);
+
+        let (status, (stdout, stdout_truncated), (stderr, stderr_truncated)) = tokio::time::timeout(
+            self.policy.timeout,
+            async { tokio::join!(child.wait(), stdout, stderr) },
+        )
+        .await
+        .map_err(|_| ToolError::TimeoutError {
+            timeout_ms: self.policy.timeout.as_millis() as u64,
+        })?;
+
+        let status = status.map_err(|e| ToolError::ExecutionError {
+            message: format!("Failed to wait for '{}': {}", argv[0], e),
+        })?;
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        let output = ToolOutput::new(json!({
+            "command": argv,
+            "exit_code": status.code(),
+            "success": status.success(),
+            "stdout": stdout,
+            "stderr": stderr,
+            "truncated": stdout_truncated || stderr_truncated
+        }))
+        .with_metadata("command", argv.join(" "))
+        .with_metric("exit_code", status.code().unwrap_or(-1) as f64)
+        .with_metric("duration_ms", duration_ms as f64);
+
+        Ok(output)
+    }
+
+    async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
+        let argv = Self::parse_command(input)?;
+        self.policy.check_binary(&argv[0])
+    }
+}
+
+/// Read a stream, keeping at most `limit` bytes but draining the rest
+async fn read_limited<R: tokio::io::AsyncRead + Unpin>(reader: Option<R>, limit: usize) -> (String, bool) {
+    let mut reader = match reader {
+        Some(reader) => reader,
+        None => return (String::new(), false),
+    };
+
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buffer = [0u8; 8192];
+    while let Ok(read) = reader.read(&mut buffer).await {
+        if read == 0 {
+            break;
+        }
+        let room = limit.saturating_sub(kept.len());
+        if read > room {
+            truncated = true;
+        }
+        kept.extend_from_slice(&buffer[..read.min(room)]);
+    }
+
+    (String::from_utf8_lossy(&kept).to_string(), truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(policy: ShellPolicy) -> (tempfile::TempDir, ShellTool) {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = Arc::new(FsSandbox::new(dir.path()).unwrap());
+        (dir, ShellTool::new(sandbox, policy))
+    }
+
+    #[test]
+    fn test_policy_checks() {
+        let policy = ShellPolicy::default();
+        assert!(policy.check_binary("ls").is_err());
+        assert!(policy.check_binary("/bin/ls").is_err());
+
+        let argv = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(policy.destructive_match(&argv("rm -rf build")), Some("rm"));
+        assert_eq!(policy.destructive_match(&argv("git push origin main")), Some("git push"));
+        assert_eq!(policy.destructive_match(&argv("git -C repo push")), Some("git push"));
+        assert_eq!(policy.destructive_match(&argv("find . -name x -delete")), Some("find -delete"));
+        assert_eq!(policy.destructive_match(&argv("git status")), None);
+        assert_eq!(policy.destructive_match(&[]), None);
+
+        let allowlist = ShellPolicy::default().with_allowed_binaries(vec!["echo", "curl", "xargs"]);
+        assert!(allowlist.check_binary("echo").is_ok());
+        assert!(allowlist.check_binary("ls").is_err());
+        assert!(allowlist.check_binary("curl").is_err());
+        assert!(allowlist.check_binary("xargs").is_err());
+
+        let open = ShellPolicy::default().with_any_binary();
+        assert!(open.check_binary("ls").is_ok());
+        for wrapper in ["env", "nice", "timeout", "xargs", "sh"] {
+            assert!(open.check_binary(wrapper).is_err(), "{} should be denied", wrapper);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_escapes_and_unapproved_commands() {
+        let (dir, tool) = shell(ShellPolicy::default().with_allowed_binaries(vec!["cat", "ls", "echo", "rm", "find", "git"]));
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/etc/passwd", dir.path().join("passwd")).unwrap();
+
+        for command in [
+            "cat /etc/passwd", "ls ../..", "echo hi; id", "rm -rf data", "id",
+            "find . -delete", "find . -exec cat {} +", "git -C . push", "sh -c id", "env rm -rf data",
+            "nice rm data", "timeout 5 rm data", "xargs rm", "cat --file=/etc/passwd", "cat -f/etc/passwd",
+        ] {
+            let result = tool.execute(ToolInput::new(json!(command))).await;
+            assert!(matches!(result, Err(ToolError::PermissionDenied { .. })), "{} should be denied", command);
+        }
+        #[cfg(unix)]
+        {
+            let result = tool.execute(ToolInput::new(json!("cat passwd"))).await;
+            assert!(matches!(result, Err(ToolError::PermissionDenied { .. })), "symlink out of the jail should be denied");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_runs_in_jail_with_truncation() {
+        let (dir, tool) = shell(ShellPolicy::default().with_allowed_binaries(vec!["cat", "sleep"]).with_network(true).with_max_output_bytes(4));
+        std::fs::write(dir.path().join("hello.txt"), "hello world").unwrap();
+
+        let output = tool.execute(ToolInput::new(json!({"command": "cat", "args": ["hello.txt"]}))).await.unwrap();
+        assert_eq!(output.data["exit_code"], 0);
+        assert_eq!(output.data["stdout"], "hell");
+        assert_eq!(output.data["truncated"], true);
+
+        let slow = ShellTool::new(tool.sandbox.clone(), tool.policy().clone().with_timeout(Duration::from_millis(50)));
+        let result = slow.execute(ToolInput::new(json!("sleep 5"))).await;
+        assert!(matches!(result, Err(ToolError::TimeoutError { .. })));
+    }
+}
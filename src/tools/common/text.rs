@@ -18,6 +18,7 @@ impl TextProcessorTool {
             "Text Processor",
             "Process and transform text data"
         )
+        .with_namespace("text")
         .with_tag("text")
         .with_tag("processing")
         .with_tag("utility")
@@ -89,6 +90,7 @@ impl RegexTool {
             "Regex Tool",
             "Perform regular expression operations on text"
         )
+        .with_namespace("text")
         .with_tag("text")
         .with_tag("regex")
         .with_tag("pattern")
@@ -161,6 +163,7 @@ impl TemplateRenderTool {
             "Template Renderer",
             "Render templates with variable substitution"
         )
+        .with_namespace("text")
         .with_tag("text")
         .with_tag("template")
         .with_tag("rendering")
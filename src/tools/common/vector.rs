@@ -0,0 +1,255 @@
+// Vector search tool for retrieval-augmented generation
+
+use crate::agents::rerank::{self, Reranker};
+use crate::agents::vector::{Embedder, MetadataFilter, VectorStore};
+use crate::tools::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Tool for querying vector collections
+///
+/// Input is either the query text or an object with `query` and optional
+/// `collection`, `top_k`, `filter` (metadata conditions), and `min_score`.
+/// With a reranker, `min_score` still applies to the vector similarity.
+#[derive(Debug)]
+pub struct VectorSearchTool {
+    metadata: ToolMetadata,
+    store: Arc<dyn VectorStore>,
+    embedder: Arc<dyn Embedder>,
+    collections: Vec<String>,
+    default_top_k: usize,
+    max_top_k: usize,
+    reranker: Option<(Arc<dyn Reranker>, usize)>,
+}
+
+impl VectorSearchTool {
+    /// Create a vector search tool over the given collections
+    ///
+    /// The first collection is searched when the input does not name one.
+    pub fn new(store: Arc<dyn VectorStore>, embedder: Arc<dyn Embedder>, collections: Vec<&str>) -> Self {
+        let metadata = ToolMetadata::new(
+            "vector_search",
+            "Vector Search",
+            "Search knowledge collections for passages relevant to a query"
+        )
+        .with_namespace("data")
+        .with_tag("search")
+        .with_tag("rag")
+        .with_tag("database")
+        .with_deterministic(true)
+        .with_side_effects(false)
+        .with_estimated_duration_ms(100)
+        .with_input_schema(json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "description": "Text to search for"},
+                "collection": {"type": "string", "enum": collections},
+                "top_k": {"type": "integer", "minimum": 1},
+                "filter": {"type": "object", "description": "Metadata key/value conditions"},
+                "min_score": {"type": "number"}
+            },
+            "required": ["query"]
+        }));
+
+        Self {
+            metadata,
+            store,
+            embedder,
+            collections: collections.into_iter().map(String::from).collect(),
+            default_top_k: 5,
+            max_top_k: 50,
+            reranker: None,
+        }
+    }
+
+    /// Search `candidates` passages and return the `top_k` the reranker rates most relevant
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>, candidates: usize) -> Self {
+        self.reranker = Some((reranker, candidates));
+        self
+    }
+
+    /// Set the number of results returned when the input does not specify one
+    pub fn with_default_top_k(mut self, top_k: usize) -> Self {
+        self.default_top_k = top_k.max(1);
+        self
+    }
+
+    /// Set the maximum number of results per query
+    pub fn with_max_top_k(mut self, max_top_k: usize) -> Self {
+        self.max_top_k = max_top_k.max(1);
+        self
+    }
+
+    fn query_text(input: &ToolInput) -> ToolResult<&str> {
+        input.data.as_str()
+            .or_else(|| input.data.get("query").and_then(|v| v.as_str()))
+            .filter(|q| !q.trim().is_empty())
+            .ok_or_else(|| ToolError::ValidationError {
+                message: "Query text is required".to_string(),
+            })
+    }
+
+    /// Metadata filter of the input; one that cannot be read is refused rather than searching everything
+    fn filter(input: &ToolInput) -> ToolResult<Option<MetadataFilter>> {
+        match input.data.get("filter") {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::Object(conditions)) => Ok(Some(MetadataFilter {
+                conditions: conditions.clone().into_iter().collect(),
+            })),
+            Some(other) => Err(ToolError::ValidationError {
+                message: format!("Filter must be an object of metadata conditions, got {}", other),
+            }),
+        }
+    }
+
+    fn collection<'a>(&'a self, input: &'a ToolInput) -> ToolResult<&'a str> {
+        match input.data.get("collection").and_then(|v| v.as_str()) {
+            Some(name) if self.collections.iter().any(|c| c == name) => Ok(name),
+            Some(name) => Err(ToolError::PermissionDenied {
+                message: format!("Collection '{}' is not available to this tool", name),
+            }),
+            None => self.collections.first().map(|c| c.as_str())
+                .ok_or_else(|| ToolError::ConfigurationError {
+                    message: "No collections configured for vector search".to_string(),
+                }),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for VectorSearchTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let query = Self::query_text(&input)?;
+        let collection = self.collection(&input)?;
+        let top_k = input.data.get("top_k")
+            .and_then(|v| v.as_u64())
+            .map_or(self.default_top_k, |k| k as usize)
+            .clamp(1, self.max_top_k);
+        let min_score = input.data.get("min_score").and_then(|v| v.as_f64()).map(|s| s as f32);
+        let filter = Self::filter(&input)?;
+
+        let embedding = self.embedder.embed(&[query.to_string()]).await
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Failed to embed query: {}", e),
+            })?
+            .pop()
+            .ok_or_else(|| ToolError::ExecutionError {
+                message: "Embedder returned no vector".to_string(),
+            })?;
+
+        let candidates = self.reranker.as_ref().map_or(top_k, |(_, candidates)| (*candidates).max(top_k));
+        let mut matches = self.store.search(collection, &embedding, candidates, filter.as_ref()).await
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Vector search failed: {}", e),
+            })?;
+        if let Some(min_score) = min_score {
+            matches.retain(|m| m.score >= min_score);
+        }
+        if let Some((reranker, _)) = &self.reranker {
+            matches = rerank::rerank(reranker.as_ref(), query, matches, top_k).await
+                .map_err(|e| ToolError::ExecutionError {
+                    message: format!("Reranking failed: {}", e),
+                })?;
+        }
+
+        let top_score = matches.first().map_or(0.0, |m| m.score);
+        let output = ToolOutput::new(json!({
+            "query": query,
+            "collection": collection,
+            "results": matches.iter().enumerate().map(|(rank, m)| json!({
+                "rank": rank + 1,
+                "id": m.id,
+                "content": m.content,
+                "score": m.score,
+                "rerank_score": m.rerank_score,
+                "metadata": m.metadata
+            })).collect::<Vec<_>>(),
+            "total_results": matches.len()
+        }))
+        .with_metadata("collection", collection)
+        .with_metric("result_count", matches.len() as f64)
+        .with_metric("top_score", top_score as f64);
+
+        Ok(output)
+    }
+
+    async fn validate_input(&self, input: &ToolInput) -> ToolResult<()> {
+        Self::query_text(input)?;
+        self.collection(input)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::vector::{HashingEmbedder, InMemoryVectorStore, VectorRecord};
+
+    async fn tool() -> VectorSearchTool {
+        let embedder = Arc::new(HashingEmbedder::default());
+        let store = Arc::new(InMemoryVectorStore::new());
+        let docs = [
+            ("policy", "Employees may work remotely three days a week", "hr"),
+            ("expenses", "Travel expenses need manager approval", "finance"),
+            ("laptops", "Laptops are refreshed every three years", "it"),
+        ];
+        let texts: Vec<String> = docs.iter().map(|d| d.1.to_string()).collect();
+        let embeddings = embedder.embed(&texts).await.unwrap();
+        let records = docs.iter().zip(embeddings)
+            .map(|(d, e)| VectorRecord::new(d.0, d.1, e).with_metadata("team", d.2))
+            .collect();
+        store.upsert("handbook", records).await.unwrap();
+
+        VectorSearchTool::new(store, embedder, vec!["handbook"])
+    }
+
+    #[tokio::test]
+    async fn test_vector_search() {
+        let tool = tool().await;
+
+        let output = tool.execute(ToolInput::new(json!("how many days can I work remotely"))).await.unwrap();
+        assert_eq!(output.data["results"][0]["id"], "policy");
+        assert_eq!(output.data["results"][0]["rank"], 1);
+
+        let input = ToolInput::new(json!({"query": "three", "filter": {"team": "it"}, "top_k": 10}));
+        let output = tool.execute(input).await.unwrap();
+        assert_eq!(output.data["total_results"], 1);
+        assert_eq!(output.data["results"][0]["id"], "laptops");
+
+        let input = ToolInput::new(json!({"query": "three", "filter": "team = it"}));
+        assert!(matches!(tool.execute(input).await, Err(ToolError::ValidationError { .. })));
+    }
+
+    /// Rates passages mentioning a word above everything else
+    #[derive(Debug)]
+    struct MentionReranker(&'static str);
+
+    #[async_trait]
+    impl Reranker for MentionReranker {
+        async fn score(&self, _query: &str, documents: &[String]) -> Result<Vec<f32>, crate::agents::memory::MemoryError> {
+            Ok(documents.iter().map(|d| if d.contains(self.0) { 1.0 } else { 0.0 }).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reranker_picks_from_candidates() {
+        let tool = tool().await.with_reranker(Arc::new(MentionReranker("Laptops")), 3);
+        let input = ToolInput::new(json!({"query": "how many days can I work remotely", "top_k": 1}));
+        let output = tool.execute(input).await.unwrap();
+        assert_eq!(output.data["total_results"], 1);
+        assert_eq!(output.data["results"][0]["id"], "laptops");
+        assert_eq!(output.data["results"][0]["rerank_score"], 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_collection_rejected() {
+        let tool = tool().await;
+        let input = ToolInput::new(json!({"query": "salaries", "collection": "payroll"}));
+        assert!(matches!(tool.validate_input(&input).await, Err(ToolError::PermissionDenied { .. })));
+    }
+}
@@ -0,0 +1,495 @@
+// Declarative tools
+// HTTP endpoints, SQL queries and command lines declared in configuration instead of written in Rust
+
+use super::common::egress::check_sandbox_egress;
+use super::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use crate::enterprise::secrets::CredentialBinding;
+use async_trait::async_trait;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Time a declared tool may run when its definition sets none
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+/// A tool declared in an application manifest or a tools file
+///
+/// ```yaml
+/// get_weather:
+///   kind: http
+///   description: Current weather for a city
+///   url: https://api.weather.test/v1/current?city={city}
+///   input_schema:
+///     type: object
+///     properties:
+///       city: { type: string }
+///     required: [city]
+///   credentials:
+///     - credential: weather_api_key
+///       injection: { type: header, name: X-Api-Key }
+///       required: true
+/// ```
+///
+/// `{field}` placeholders in URLs, headers and command arguments are filled
+/// from parameters such as credentials a vault injected, or else from the
+/// tool input, so input cannot replace a secret. SQL queries bind `:field`
+/// parameters the same way.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ToolDefinition {
+    /// What the tool does, as shown to agents
+    pub description: String,
+    /// Display name, defaulting to the tool ID
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// JSON Schema of the input
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+    /// Time a call may take
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Tenant credentials injected into each call by a
+    /// [`CredentialVault`](crate::enterprise::secrets::CredentialVault)
+    #[serde(default)]
+    pub credentials: Vec<CredentialBinding>,
+    /// What a call does
+    #[serde(flatten)]
+    pub action: ToolAction,
+}
+
+/// What a declared tool does when called
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolAction {
+    /// Send an HTTP request; the input is the JSON body of methods that take one
+    Http {
+        #[serde(default = "default_method")]
+        method: String,
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// Run a query against a SQLite database
+    Sql {
+        database: PathBuf,
+        query: String,
+        /// Allow statements that modify the database
+        #[serde(default)]
+        writable: bool,
+    },
+    /// Run a program with the sandboxed shell tool
+    Shell {
+        /// Program and arguments
+        command: Vec<String>,
+        /// Directory the command is jailed to, defaulting to the manifest's
+        #[serde(default)]
+        working_dir: Option<PathBuf>,
+        #[serde(default)]
+        allow_network: bool,
+    },
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl ToolDefinition {
+    /// Resolve relative database and working directory paths against `base_dir`
+    pub fn resolve_paths(&mut self, base_dir: &Path) {
+        match &mut self.action {
+            ToolAction::Http { .. } => {}
+            ToolAction::Sql { database, .. } => {
+                if database.is_relative() {
+                    *database = base_dir.join(&*database);
+                }
+            }
+            ToolAction::Shell { working_dir, .. } => {
+                let dir = working_dir.take().unwrap_or_default();
+                *working_dir = Some(if dir.is_relative() { base_dir.join(dir) } else { dir });
+            }
+        }
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS))
+    }
+}
+
+/// A tool instantiated from a [`ToolDefinition`]
+#[derive(Debug)]
+pub struct DeclaredTool {
+    metadata: ToolMetadata,
+    definition: ToolDefinition,
+    client: reqwest::Client,
+    #[cfg(feature = "shell")]
+    shell: Option<super::common::ShellTool>,
+}
+
+impl DeclaredTool {
+    /// Instantiate a definition under `id`, checking it can run in this build
+    pub fn new(id: &str, definition: ToolDefinition) -> ToolResult<Self> {
+        let invalid = |message: String| ToolError::ConfigurationError {
+            message: format!("Tool '{}': {}", id, message),
+        };
+
+        let side_effects = match &definition.action {
+            ToolAction::Http { method, url, .. } => {
+                let method = Method::from_bytes(method.to_uppercase().as_bytes())
+                    .map_err(|_| invalid(format!("unknown HTTP method '{}'", method)))?;
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(invalid("url must start with http:// or https://".to_string()));
+                }
+                !matches!(method, Method::GET | Method::HEAD)
+            }
+            ToolAction::Sql { writable, .. } => {
+                if !cfg!(feature = "memory-sqlite") {
+                    return Err(invalid("SQL tools need the memory-sqlite feature".to_string()));
+                }
+                *writable
+            }
+            ToolAction::Shell { command, .. } => {
+                if !cfg!(feature = "shell") {
+                    return Err(invalid("shell tools need the shell feature".to_string()));
+                }
+                match command.first() {
+                    Some(program) if !program.contains('{') => {}
+                    _ => return Err(invalid("command must start with a fixed program".to_string())),
+                }
+                true
+            }
+        };
+
+        let mut metadata = ToolMetadata::new(id, definition.name.as_deref().unwrap_or(id), &definition.description)
+            .with_deterministic(false)
+            .with_side_effects(side_effects)
+            .with_estimated_duration_ms(1000);
+        if let Some(namespace) = &definition.namespace {
+            metadata = metadata.with_namespace(namespace);
+        }
+        for tag in &definition.tags {
+            metadata = metadata.with_tag(tag);
+        }
+        if let Some(schema) = &definition.input_schema {
+            metadata = metadata.with_input_schema(schema.clone());
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(definition.timeout())
+            .build()
+            .map_err(|e| invalid(format!("failed to create HTTP client: {}", e)))?;
+
+        #[cfg(feature = "shell")]
+        let shell = match &definition.action {
+            ToolAction::Shell { command, working_dir, allow_network } => {
+                let sandbox = super::common::FsSandbox::new(working_dir.clone().unwrap_or_else(|| PathBuf::from(".")))?;
+                let policy = super::common::ShellPolicy::default()
+                    .with_allowed_binaries(vec![command[0].as_str()])
+                    .with_network(*allow_network)
+                    .with_timeout(definition.timeout());
+                Some(super::common::ShellTool::new(std::sync::Arc::new(sandbox), policy))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            metadata,
+            definition,
+            client,
+            #[cfg(feature = "shell")]
+            shell,
+        })
+    }
+
+    /// The definition the tool was created from
+    pub fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    async fn send_request(&self, method: &str, url: &str, headers: &BTreeMap<String, String>, input: &ToolInput) -> ToolResult<ToolOutput> {
+        let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap_or(Method::GET);
+        let url = render(url, input, |value| utf8_percent_encode(value, NON_ALPHANUMERIC).to_string())?;
+
+        check_sandbox_egress(&url).await?;
+        let mut request = self.client.request(method.clone(), &url);
+        for (name, value) in headers {
+            request = request.header(name, render(value, input, str::to_string)?);
+        }
+        // Headers a credential vault injected
+        for (name, value) in input.get_parameter::<HashMap<String, String>>("headers").unwrap_or_default() {
+            request = request.header(name, value);
+        }
+        if !matches!(method, Method::GET | Method::HEAD | Method::DELETE) {
+            request = request.json(&input.data);
+        }
+
+        let response = request.send().await.map_err(|e| ToolError::NetworkError {
+            message: format!("HTTP request to {} failed: {}", url, e),
+        })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| ToolError::NetworkError {
+            message: format!("Failed to read response body: {}", e),
+        })?;
+        if !status.is_success() {
+            return Err(ToolError::ExecutionError {
+                message: format!("{} {} returned {}: {}", method, url, status, body.chars().take(500).collect::<String>()),
+            });
+        }
+
+        let body = serde_json::from_str::<Value>(&body).unwrap_or(Value::String(body));
+        Ok(ToolOutput::new(json!({ "status": status.as_u16(), "body": body }))
+            .with_metadata("method", method.as_str())
+            .with_metric("status_code", status.as_u16() as f64))
+    }
+
+    #[cfg(feature = "memory-sqlite")]
+    async fn run_query(&self, database: &Path, query: &str, writable: bool, input: &ToolInput) -> ToolResult<ToolOutput> {
+        let (database, query, input) = (database.to_path_buf(), query.to_string(), input.clone());
+        let task = crate::graph::tuning::spawn_blocking(move || sql::run(&database, &query, writable, &input));
+        let data = tokio::time::timeout(self.definition.timeout(), task)
+            .await
+            .map_err(|_| ToolError::TimeoutError {
+                timeout_ms: self.definition.timeout().as_millis() as u64,
+            })?
+            .map_err(|e| ToolError::ExecutionError {
+                message: format!("Query task failed: {}", e),
+            })??;
+        Ok(ToolOutput::new(data))
+    }
+
+    #[cfg(feature = "shell")]
+    async fn run_command(&self, command: &[String], input: &ToolInput) -> ToolResult<ToolOutput> {
+        let shell = self.shell.as_ref().ok_or_else(|| ToolError::ConfigurationError {
+            message: format!("Tool '{}' has no shell", self.metadata.id),
+        })?;
+        let args = command[1..].iter()
+            .map(|arg| render(arg, input, str::to_string))
+            .collect::<ToolResult<Vec<_>>>()?;
+        let mut call = ToolInput::new(json!({ "command": command[0], "args": args }));
+        call.context = input.context.clone();
+        shell.execute(call).await
+    }
+}
+
+#[async_trait]
+impl Tool for DeclaredTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        match &self.definition.action {
+            ToolAction::Http { method, url, headers } => self.send_request(method, url, headers, &input).await,
+            #[cfg(feature = "memory-sqlite")]
+            ToolAction::Sql { database, query, writable } => self.run_query(database, query, *writable, &input).await,
+            #[cfg(feature = "shell")]
+            ToolAction::Shell { command, .. } => self.run_command(command, &input).await,
+            #[allow(unreachable_patterns)]
+            _ => Err(ToolError::ConfigurationError {
+                message: format!("Tool '{}' is not supported in this build", self.metadata.id),
+            }),
+        }
+    }
+}
+
+/// Parameter, or else input field, named `field`
+fn lookup<'a>(input: &'a ToolInput, field: &str) -> Option<&'a Value> {
+    input.parameters.get(field).or_else(|| input.data.get(field))
+}
+
+/// [`lookup`] as text, for substitution into a template
+fn field_value(input: &ToolInput, field: &str) -> Option<String> {
+    lookup(input, field)
+        .filter(|value| !value.is_null())
+        .map(|value| match value {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+}
+
+/// Replace `{field}` placeholders in `template`, encoding each value with `encode`
+fn render(template: &str, input: &ToolInput, encode: impl Fn(&str) -> String) -> ToolResult<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or_else(|| ToolError::ConfigurationError {
+            message: format!("Unclosed placeholder in '{}'", template),
+        })?;
+        let field = &after[..end];
+        let value = field_value(input, field).ok_or_else(|| ToolError::ValidationError {
+            message: format!("Input field '{}' is required", field),
+        })?;
+        rendered.push_str(&encode(&value));
+        rest = &after[end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+#[cfg(feature = "memory-sqlite")]
+mod sql {
+    use super::field_value;
+    use crate::tools::traits::{ToolError, ToolInput, ToolResult};
+    use base64::Engine as _;
+    use rusqlite::types::{Value as SqlValue, ValueRef};
+    use rusqlite::{Connection, OpenFlags};
+    use serde_json::{json, Map, Value};
+    use std::path::Path;
+
+    fn sql_error(e: rusqlite::Error) -> ToolError {
+        ToolError::ExecutionError { message: format!("Query failed: {}", e) }
+    }
+
+    /// Run `query` with its `:field` parameters bound from `input`
+    pub(super) fn run(database: &Path, query: &str, writable: bool, input: &ToolInput) -> ToolResult<Value> {
+        let flags = if writable {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        };
+        let connection = Connection::open_with_flags(database, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .map_err(|e| ToolError::ConfigurationError {
+                message: format!("Failed to open database {}: {}", database.display(), e),
+            })?;
+        let mut statement = connection.prepare(query).map_err(sql_error)?;
+        if !writable && !statement.readonly() {
+            return Err(ToolError::PermissionDenied {
+                message: "Query modifies the database but the tool is not writable".to_string(),
+            });
+        }
+
+        for index in 1..=statement.parameter_count() {
+            let name = statement.parameter_name(index).ok_or_else(|| ToolError::ConfigurationError {
+                message: "Queries must use named parameters, e.g. :city".to_string(),
+            })?;
+            let field = name.trim_start_matches([':', '@', '$']).to_string();
+            let value = match lookup(input, &field) {
+                Some(Value::Bool(flag)) => SqlValue::Integer(*flag as i64),
+                Some(Value::Number(number)) => match number.as_i64() {
+                    Some(integer) => SqlValue::Integer(integer),
+                    None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+                },
+                Some(Value::Null) => SqlValue::Null,
+                Some(_) => SqlValue::Text(field_value(input, &field).unwrap_or_default()),
+                None => {
+                    return Err(ToolError::ValidationError {
+                        message: format!("Input field '{}' is required", field),
+                    })
+                }
+            };
+            statement.raw_bind_parameter(index, value).map_err(sql_error)?;
+        }
+
+        if statement.column_count() == 0 {
+            let affected = statement.raw_execute().map_err(sql_error)?;
+            return Ok(json!({ "affected_rows": affected }));
+        }
+
+        let columns: Vec<String> = statement.column_names().into_iter().map(String::from).collect();
+        let mut rows = statement.raw_query();
+        let mut results = Vec::new();
+        while let Some(row) = rows.next().map_err(sql_error)? {
+            let mut object = Map::new();
+            for (index, column) in columns.iter().enumerate() {
+                let value = match row.get_ref(index).map_err(sql_error)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(integer) => json!(integer),
+                    ValueRef::Real(real) => json!(real),
+                    ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+                    ValueRef::Blob(blob) => Value::String(base64::engine::general_purpose::STANDARD.encode(blob)),
+                };
+                object.insert(column.clone(), value);
+            }
+            results.push(Value::Object(object));
+        }
+        Ok(json!({ "row_count": results.len(), "rows": results }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_definitions_parse_and_render() {
+        let definitions: BTreeMap<String, ToolDefinition> = serde_yaml::from_str(r#"
+            get_weather:
+              kind: http
+              description: Current weather for a city
+              url: https://api.weather.test/v1/current?city={city}
+              headers:
+                Authorization: "Token {weather_token}"
+              input_schema:
+                type: object
+                required: [city]
+            create_ticket:
+              kind: http
+              method: post
+              description: Open a support ticket
+              url: https://tickets.test/api/tickets
+        "#).unwrap();
+
+        let weather = DeclaredTool::new("get_weather", definitions["get_weather"].clone()).unwrap();
+        assert!(!weather.metadata().has_side_effects);
+        assert_eq!(weather.metadata().input_schema.as_ref().unwrap()["required"], json!(["city"]));
+        let ticket = DeclaredTool::new("create_ticket", definitions["create_ticket"].clone()).unwrap();
+        assert!(ticket.metadata().has_side_effects);
+
+        let input = ToolInput::new(json!({ "city": "São Paulo" })).with_parameter("weather_token", "secret");
+        let ToolAction::Http { url, headers, .. } = &definitions["get_weather"].action else {
+            panic!("expected an HTTP tool");
+        };
+        let url = render(url, &input, |value| utf8_percent_encode(value, NON_ALPHANUMERIC).to_string()).unwrap();
+        assert_eq!(url, "https://api.weather.test/v1/current?city=S%C3%A3o%20Paulo");
+        assert_eq!(render(&headers["Authorization"], &input, str::to_string).unwrap(), "Token secret");
+        assert!(render("{missing}", &input, str::to_string).is_err());
+
+        let spoofed = ToolInput::new(json!({ "weather_token": "mine" })).with_parameter("weather_token", "secret");
+        assert_eq!(render(&headers["Authorization"], &spoofed, str::to_string).unwrap(), "Token secret");
+
+        let mut bad = definitions["get_weather"].clone();
+        bad.action = ToolAction::Http { method: "GET".to_string(), url: "ftp://x".to_string(), headers: BTreeMap::new() };
+        assert!(DeclaredTool::new("bad", bad).is_err());
+    }
+
+    #[cfg(feature = "memory-sqlite")]
+    #[tokio::test]
+    async fn test_sql_tool_binds_named_parameters() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("orders.db");
+        let connection = rusqlite::Connection::open(&database).unwrap();
+        connection.execute_batch("
+            CREATE TABLE orders (id INTEGER, customer TEXT, total REAL);
+            INSERT INTO orders VALUES (1, 'ada', 12.5), (2, 'grace', 40.0), (3, 'ada', 7.25);
+        ").unwrap();
+
+        let mut definition: ToolDefinition = serde_yaml::from_str(r#"
+            kind: sql
+            description: Orders of a customer
+            database: orders.db
+            query: SELECT id, total FROM orders WHERE customer = :customer ORDER BY id
+        "#).unwrap();
+        definition.resolve_paths(dir.path());
+        let tool = DeclaredTool::new("customer_orders", definition.clone()).unwrap();
+
+        let output = tool.execute(ToolInput::new(json!({ "customer": "ada" }))).await.unwrap();
+        assert_eq!(output.data["rows"], json!([{ "id": 1, "total": 12.5 }, { "id": 3, "total": 7.25 }]));
+
+        definition.action = ToolAction::Sql {
+            database,
+            query: "DELETE FROM orders WHERE customer = :customer".to_string(),
+            writable: false,
+        };
+        let tool = DeclaredTool::new("delete_orders", definition).unwrap();
+        let error = tool.execute(ToolInput::new(json!({ "customer": "ada" }))).await.unwrap_err();
+        assert!(matches!(error, ToolError::PermissionDenied { .. }));
+    }
+}
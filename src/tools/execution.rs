@@ -2,7 +2,13 @@
 
 use super::traits::{Tool, ToolError, ToolInput, ToolOutput, ToolResult};
 use super::{ToolConfig, ToolStats};
-use std::collections::HashMap;
+use crate::enterprise::sandbox;
+use crate::enterprise::secrets::{CredentialVault, TENANT_CONTEXT_KEY};
+use crate::graph::context_vars::ContextVars;
+use crate::graph::dry_run::{self, SimulatedEffectKind};
+use crate::graph::retry::{self, IdempotencyLedger, IDEMPOTENCY_KEY_CONTEXT_KEY};
+use crate::visualization::metrics_collector::MetricsCollector;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::timeout;
@@ -49,6 +55,20 @@ impl ToolExecutionContext {
         self.context_data.insert(key, value);
         self
     }
+
+    /// Add an execution's context variables as context data
+    ///
+    /// The user and session IDs are taken from them unless already set.
+    pub fn with_context_vars(mut self, vars: &ContextVars) -> Self {
+        for (key, _) in vars.iter() {
+            if let Some(text) = vars.text(key) {
+                self.context_data.entry(key.to_string()).or_insert(text);
+            }
+        }
+        self.user_id = self.user_id.or_else(|| vars.user_id());
+        self.session_id = self.session_id.or_else(|| vars.session_id());
+        self
+    }
 }
 
 /// Result of tool execution with metadata
@@ -127,6 +147,8 @@ impl ToolCache {
 pub struct ToolExecutor {
     cache: Option<ToolCache>,
     stats: HashMap<String, ToolStats>,
+    metrics: Option<Arc<MetricsCollector>>,
+    credentials: Option<Arc<CredentialVault>>,
 }
 
 impl ToolExecutor {
@@ -135,6 +157,8 @@ impl ToolExecutor {
         Self {
             cache: None,
             stats: HashMap::new(),
+            metrics: None,
+            credentials: None,
         }
     }
     
@@ -143,23 +167,133 @@ impl ToolExecutor {
         self.cache = Some(ToolCache::new(ttl));
         self
     }
+
+    /// Report executions and cache lookups to a metrics collector
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Inject the calling tenant's credentials from a vault before each call
+    pub fn with_credentials(mut self, vault: Arc<CredentialVault>) -> Self {
+        self.credentials = Some(vault);
+        self
+    }
     
     /// Execute a tool with configuration and context
     pub async fn execute(
         &mut self,
         tool: Arc<dyn Tool>,
-        input: ToolInput,
+        mut input: ToolInput,
         config: &ToolConfig,
-        _context: &ToolExecutionContext,
+        context: &ToolExecutionContext,
     ) -> ToolResult<ToolExecutionResult> {
         let tool_id = tool.metadata().id.clone();
         let start_time = Instant::now();
         let mut retry_attempts;
         
-        // Check cache first if enabled (simplified for now)
-        // TODO: Implement proper caching with trait object downcasting
-        if config.cache_results {
-            // Cache implementation will be added in a future version
+        // Tools outside the execution's sandbox profile fail before anything runs, dry runs included
+        if let Some(sandbox) = sandbox::current() {
+            sandbox.check_tool(tool.metadata()).await
+                .map_err(|violation| ToolError::PermissionDenied { message: violation.to_string() })?;
+        }
+        
+        // Dry runs answer from the declared sample and never perform writes
+        if dry_run::is_active() {
+            if let Some(output) = Self::simulate(tool.as_ref()) {
+                return Ok(ToolExecutionResult {
+                    output,
+                    metadata: ExecutionMetadata {
+                        tool_id,
+                        duration_ms: 0,
+                        retry_attempts: 0,
+                        from_cache: false,
+                        timestamp: chrono::Utc::now(),
+                        success: true,
+                        error_message: None,
+                    },
+                });
+            }
+        }
+        
+        // A retried execution gets the recorded output of writes it already made
+        let mut effect: Option<(String, IdempotencyLedger)> = None;
+        if tool.metadata().has_side_effects {
+            if let Some((key, ledger)) = retry::next_call(&tool_id, &input.data) {
+                if let Some(output) = ledger.get(&key).and_then(|output| serde_json::from_value(output).ok()) {
+                    tracing::info!(tool_id = %tool_id, idempotency_key = %key, "Skipping side effect already performed");
+                    return Ok(ToolExecutionResult {
+                        output,
+                        metadata: ExecutionMetadata {
+                            tool_id,
+                            duration_ms: 0,
+                            retry_attempts: 0,
+                            from_cache: true,
+                            timestamp: chrono::Utc::now(),
+                            success: true,
+                            error_message: None,
+                        },
+                    });
+                }
+                input.context.insert(IDEMPOTENCY_KEY_CONTEXT_KEY.to_string(), key.clone());
+                effect = Some((key, ledger));
+            }
+        }
+        
+        // The call runs on behalf of the execution's tenant, never one the tool input claims
+        match (context.context_data.get(TENANT_CONTEXT_KEY), input.get_context(TENANT_CONTEXT_KEY)) {
+            (Some(tenant_id), Some(claimed)) if claimed != tenant_id => {
+                return Err(ToolError::PermissionDenied {
+                    message: format!("Tool input names tenant '{}' but the execution runs for '{}'", claimed, tenant_id),
+                });
+            }
+            (Some(tenant_id), _) => {
+                input.context.insert(TENANT_CONTEXT_KEY.to_string(), tenant_id.clone());
+            }
+            (None, _) => {
+                input.context.remove(TENANT_CONTEXT_KEY);
+            }
+        }
+        
+        // Results of tools with side effects are never reused. Parameters are
+        // keyed in a stable order, before credentials are injected into them
+        let cached_parameters = (config.cache_results && self.cache.is_some() && !tool.metadata().has_side_effects)
+            .then(|| serde_json::to_string(&input.parameters.iter().collect::<BTreeMap<_, _>>()).unwrap_or_default());
+        
+        // Resolve credentials for the tenant the call runs on behalf of
+        if let Some(vault) = &self.credentials {
+            vault.inject(&tool_id, &mut input).await?;
+        }
+        
+        // Check cache first if enabled; results are never shared across tenants
+        let cache_key = cached_parameters.map(|parameters| {
+            format!(
+                "{}:{}:{}:{}",
+                tool_id,
+                input.get_context(TENANT_CONTEXT_KEY).map(String::as_str).unwrap_or_default(),
+                serde_json::to_string(&input.data).unwrap_or_default(),
+                parameters
+            )
+        });
+        if let (Some(key), Some(cache)) = (&cache_key, &self.cache) {
+            let cached = cache.get(key);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_tool_cache_access(&tool_id, cached.is_some()).await;
+            }
+            if let Some(output) = cached {
+                return Ok(ToolExecutionResult {
+                    output,
+                    metadata: ExecutionMetadata {
+                        tool_id,
+                        duration_ms: start_time.elapsed().as_millis() as u64,
+                        retry_attempts: 0,
+                        from_cache: true,
+                        timestamp: chrono::Utc::now(),
+                        success: true,
+                        error_message: None,
+                    },
+                });
+            }
         }
         
         // Execute with retries
@@ -186,14 +320,21 @@ impl ToolExecutor {
                 Ok(Ok(output)) => {
                     let duration_ms = start_time.elapsed().as_millis() as u64;
                     
-                    // Cache result if enabled (simplified for now)
-                    // TODO: Implement proper caching with trait object downcasting
-                    if config.cache_results {
-                        // Cache implementation will be added in a future version
+                    // Cache result if enabled
+                    if let (Some(key), Some(cache)) = (cache_key, &mut self.cache) {
+                        cache.put(key, output.clone());
+                    }
+                    if let Some((key, ledger)) = effect {
+                        if let Ok(recorded) = serde_json::to_value(&output) {
+                            ledger.record(key, recorded);
+                        }
                     }
                     
                     // Update statistics
                     self.update_stats(&tool_id, duration_ms, true);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_tool_execution(&tool_id, duration_ms, true).await;
+                    }
                     
                     return Ok(ToolExecutionResult {
                         output,
@@ -235,10 +376,33 @@ impl ToolExecutor {
         
         // Update statistics
         self.update_stats(&tool_id, duration_ms, false);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_tool_execution(&tool_id, duration_ms, false).await;
+        }
         
         Err(error)
     }
     
+    /// Output of a tool during a dry run, or `None` if it is safe to run for real
+    fn simulate(tool: &dyn Tool) -> Option<ToolOutput> {
+        let metadata = tool.metadata();
+        let tool_id = metadata.id.clone();
+        if let Some(sample) = &metadata.sample_output {
+            dry_run::record(SimulatedEffectKind::ToolSampled { tool_id });
+            return Some(ToolOutput::new(sample.clone()).with_metadata("dry_run", true));
+        }
+        if metadata.has_side_effects {
+            dry_run::record(SimulatedEffectKind::WriteSkipped { tool_id });
+            return Some(
+                ToolOutput::new(serde_json::Value::Null)
+                    .with_metadata("dry_run", true)
+                    .with_metadata("skipped", true),
+            );
+        }
+        dry_run::record(SimulatedEffectKind::ToolExecuted { tool_id });
+        None
+    }
+    
     /// Get statistics for a tool
     pub fn get_stats(&self, tool_id: &str) -> Option<&ToolStats> {
         self.stats.get(tool_id)
@@ -401,6 +565,117 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_cached_execution_reports_metrics() {
+        let metrics = Arc::new(MetricsCollector::new(true, 1));
+        let mut executor = ToolExecutor::new()
+            .with_cache(Duration::from_secs(60))
+            .with_metrics(metrics.clone());
+        let tool = Arc::new(TestTool::new("test_tool", false));
+        let config = ToolConfig {
+            cache_results: true,
+            ..Default::default()
+        };
+        let context = ToolExecutionContext::new("exec_1".to_string());
+
+        let first = executor.execute(tool.clone(), ToolInput::new(json!({"q": 1})), &config, &context).await.unwrap();
+        let second = executor.execute(tool.clone(), ToolInput::new(json!({"q": 1})), &config, &context).await.unwrap();
+        assert!(!first.metadata.from_cache);
+        assert!(second.metadata.from_cache);
+        assert_eq!(tool.call_count(), 1);
+
+        let tool_metrics = metrics.get_tool_metrics("test_tool").await.unwrap();
+        assert_eq!(tool_metrics.total_executions, 1);
+        assert_eq!(tool_metrics.cache_hits, 1);
+        assert_eq!(tool_metrics.cache_misses, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_keys_on_parameters_and_skips_side_effects() {
+        let mut executor = ToolExecutor::new().with_cache(Duration::from_secs(60));
+        let tool = Arc::new(TestTool::new("test_tool", false));
+        let config = ToolConfig {
+            cache_results: true,
+            ..Default::default()
+        };
+        let context = ToolExecutionContext::new("exec_1".to_string());
+        let input = |url: &str| ToolInput::new(json!({"q": 1})).with_parameter("url", url).with_parameter("method", "GET");
+
+        executor.execute(tool.clone(), input("https://a.example"), &config, &context).await.unwrap();
+        let other = executor.execute(tool.clone(), input("https://b.example"), &config, &context).await.unwrap();
+        assert!(!other.metadata.from_cache);
+        let repeated = executor.execute(tool.clone(), input("https://b.example"), &config, &context).await.unwrap();
+        assert!(repeated.metadata.from_cache);
+        assert_eq!(tool.call_count(), 2);
+
+        let mut writer = TestTool::new("writer", false);
+        writer.metadata = writer.metadata.with_side_effects(true);
+        let writer = Arc::new(writer);
+        for _ in 0..2 {
+            let result = executor.execute(writer.clone(), input("https://a.example"), &config, &context).await.unwrap();
+            assert!(!result.metadata.from_cache);
+        }
+        assert_eq!(writer.call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_injects_tenant_credentials() {
+        use crate::enterprise::secrets::{Credential, CredentialBinding, InMemorySecretStore};
+
+        #[derive(Debug)]
+        struct EchoKeyTool {
+            metadata: ToolMetadata,
+        }
+
+        #[async_trait]
+        impl Tool for EchoKeyTool {
+            fn metadata(&self) -> &ToolMetadata {
+                &self.metadata
+            }
+
+            async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+                Ok(ToolOutput::new(json!(input.get_parameter::<String>("api_key"))))
+            }
+        }
+
+        let vault = CredentialVault::new(Arc::new(InMemorySecretStore::new()))
+            .with_binding("echo_key", CredentialBinding::parameter("service_key", "api_key"));
+        vault.store_credential("acme", Credential::api_key("service_key", "acme-key")).await.unwrap();
+        vault.store_credential("globex", Credential::api_key("service_key", "globex-key")).await.unwrap();
+
+        let mut executor = ToolExecutor::new()
+            .with_cache(Duration::from_secs(60))
+            .with_credentials(Arc::new(vault));
+        let tool: Arc<dyn Tool> = Arc::new(EchoKeyTool {
+            metadata: ToolMetadata::new("echo_key", "Echo Key", "Echo the injected key"),
+        });
+        let config = ToolConfig {
+            cache_results: true,
+            ..Default::default()
+        };
+
+        for (tenant, expected) in [("acme", "acme-key"), ("globex", "globex-key")] {
+            let context = ToolExecutionContext::new("exec_1".to_string())
+                .with_context_data(TENANT_CONTEXT_KEY.to_string(), tenant.to_string());
+            let result = executor.execute(tool.clone(), ToolInput::new(json!({})), &config, &context).await.unwrap();
+            assert_eq!(result.output.data, expected);
+            assert!(!result.metadata.from_cache);
+        }
+
+        let acme = ToolExecutionContext::new("exec_1".to_string())
+            .with_context_data(TENANT_CONTEXT_KEY.to_string(), "acme".to_string());
+        let spoofed = ToolInput::new(json!({})).with_context(TENANT_CONTEXT_KEY, "globex");
+        let result = executor.execute(tool.clone(), spoofed, &config, &acme).await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied { .. })));
+
+        let anonymous = ToolExecutionContext::new("exec_2".to_string());
+        let result = executor.execute(tool.clone(), ToolInput::new(json!({})), &config, &anonymous).await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied { .. })));
+        let claimed = ToolInput::new(json!({})).with_context(TENANT_CONTEXT_KEY, "globex");
+        let result = executor.execute(tool, claimed, &config, &anonymous).await;
+        assert!(matches!(result, Err(ToolError::PermissionDenied { .. })));
+    }
+
     #[test]
     fn test_cache_operations() {
         let mut cache = ToolCache::new(Duration::from_millis(100));
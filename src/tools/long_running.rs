@@ -0,0 +1,378 @@
+// Long-running tools that complete asynchronously
+// Execution returns an operation handle; completion arrives by polling or callback
+
+use super::traits::{ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// How a long-running operation reports completion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompletionMode {
+    /// The tracker polls the tool for status
+    Polling,
+    /// The tool's backend calls a webhook with the result
+    Callback,
+}
+
+/// Handle to an operation started by a long-running tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationHandle {
+    /// Unique operation ID
+    pub operation_id: String,
+    /// Tool that started the operation
+    pub tool_id: String,
+    /// How completion is reported
+    pub completion: CompletionMode,
+    /// Minimum time between polls
+    pub poll_interval: Duration,
+    /// Secret the callback must present to complete the operation
+    pub callback_token: String,
+    /// Tool-specific data needed to poll or cancel (e.g. a remote job ID)
+    pub data: serde_json::Value,
+    /// When the operation was started
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl OperationHandle {
+    /// Create a polled operation handle
+    pub fn new(tool_id: &str, data: serde_json::Value) -> Self {
+        Self {
+            operation_id: Uuid::new_v4().to_string(),
+            tool_id: tool_id.to_string(),
+            completion: CompletionMode::Polling,
+            poll_interval: Duration::from_secs(5),
+            callback_token: Uuid::new_v4().simple().to_string(),
+            data,
+            started_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Use callback completion instead of polling
+    pub fn with_callback(mut self) -> Self {
+        self.completion = CompletionMode::Callback;
+        self
+    }
+
+    /// Set the minimum time between polls
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+/// Status of a long-running operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum OperationStatus {
+    /// Still running, with optional progress (0.0 - 1.0)
+    Pending {
+        /// Fraction complete, if known
+        progress: Option<f64>,
+    },
+    /// Finished successfully
+    Completed {
+        /// Tool output
+        output: ToolOutput,
+    },
+    /// Finished with an error
+    Failed {
+        /// Failure description
+        message: String,
+    },
+}
+
+impl OperationStatus {
+    /// Whether the operation has finished
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, OperationStatus::Pending { .. })
+    }
+}
+
+/// Tool whose execution outlives a single call
+///
+/// `start` kicks off the work and returns immediately with a handle. The
+/// result is later obtained with `poll`, or delivered to an
+/// [`OperationTracker`] by a webhook when the handle uses callback completion.
+#[async_trait]
+pub trait LongRunningTool: Send + Sync + std::fmt::Debug {
+    /// Get tool metadata
+    fn metadata(&self) -> &ToolMetadata;
+
+    /// Start the operation
+    async fn start(&self, input: ToolInput) -> ToolResult<OperationHandle>;
+
+    /// Check the operation's status
+    async fn poll(&self, handle: &OperationHandle) -> ToolResult<OperationStatus>;
+
+    /// Cancel the operation
+    async fn cancel(&self, handle: &OperationHandle) -> ToolResult<()> {
+        let _ = handle;
+        Err(ToolError::ExecutionError {
+            message: format!("Tool '{}' does not support cancellation", self.metadata().id),
+        })
+    }
+}
+
+/// An operation and the latest status known for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedOperation {
+    /// Operation handle
+    pub handle: OperationHandle,
+    /// Latest status
+    pub status: OperationStatus,
+    /// When the tool was last polled
+    #[serde(skip)]
+    last_polled: Option<std::time::Instant>,
+}
+
+/// Keeps track of in-flight operations until they complete
+///
+/// Hosts call [`OperationTracker::poll_due`] periodically and route webhook
+/// requests to [`OperationTracker::complete_with_callback`]. Finished
+/// operations are returned so the suspended branch can be resumed.
+#[derive(Debug, Default)]
+pub struct OperationTracker {
+    tools: RwLock<HashMap<String, Arc<dyn LongRunningTool>>>,
+    operations: RwLock<HashMap<String, TrackedOperation>>,
+}
+
+impl OperationTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a tool so its operations can be polled and cancelled
+    pub async fn register_tool(&self, tool: Arc<dyn LongRunningTool>) {
+        let id = tool.metadata().id.clone();
+        self.tools.write().await.insert(id, tool);
+    }
+
+    /// Start tracking an operation
+    pub async fn track(&self, handle: OperationHandle) {
+        let operation = TrackedOperation {
+            handle,
+            status: OperationStatus::Pending { progress: None },
+            last_polled: None,
+        };
+        self.operations.write().await.insert(operation.handle.operation_id.clone(), operation);
+    }
+
+    /// Get the latest known state of an operation
+    pub async fn get(&self, operation_id: &str) -> Option<TrackedOperation> {
+        self.operations.read().await.get(operation_id).cloned()
+    }
+
+    /// Operations that have not finished yet
+    pub async fn pending(&self) -> Vec<OperationHandle> {
+        self.operations.read().await.values()
+            .filter(|op| !op.status.is_terminal())
+            .map(|op| op.handle.clone())
+            .collect()
+    }
+
+    /// Poll every polled operation whose interval has elapsed
+    ///
+    /// Returns operations that finished during this call.
+    pub async fn poll_due(&self) -> Vec<TrackedOperation> {
+        let due: Vec<OperationHandle> = self.operations.read().await.values()
+            .filter(|op| op.handle.completion == CompletionMode::Polling && !op.status.is_terminal())
+            .filter(|op| op.last_polled.is_none_or(|at| at.elapsed() >= op.handle.poll_interval))
+            .map(|op| op.handle.clone())
+            .collect();
+
+        let mut finished = Vec::new();
+        for handle in due {
+            match self.refresh(&handle).await {
+                Ok(Some(op)) if op.status.is_terminal() => finished.push(op),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Polling operation {} failed: {}", handle.operation_id, e),
+            }
+        }
+        finished
+    }
+
+    /// Poll a single operation now, ignoring its poll interval
+    ///
+    /// Callback operations are not polled; their latest status is returned.
+    pub async fn poll_operation(&self, operation_id: &str) -> ToolResult<OperationStatus> {
+        let op = self.get(operation_id).await.ok_or_else(|| ToolError::ValidationError {
+            message: format!("Unknown operation: {}", operation_id),
+        })?;
+        if op.handle.completion == CompletionMode::Callback || op.status.is_terminal() {
+            return Ok(op.status);
+        }
+        let op = self.refresh(&op.handle).await?.ok_or_else(|| ToolError::ValidationError {
+            message: format!("Operation {} was cancelled", operation_id),
+        })?;
+        Ok(op.status)
+    }
+
+    /// Poll the tool for an operation and store the new status
+    async fn refresh(&self, handle: &OperationHandle) -> ToolResult<Option<TrackedOperation>> {
+        let tool = self.tools.read().await.get(&handle.tool_id).cloned();
+        let status = match tool {
+            Some(tool) => tool.poll(handle).await?,
+            None => OperationStatus::Failed {
+                message: format!("Tool '{}' is not registered with the tracker", handle.tool_id),
+            },
+        };
+
+        let mut operations = self.operations.write().await;
+        Ok(operations.get_mut(&handle.operation_id).map(|op| {
+            op.last_polled = Some(std::time::Instant::now());
+            op.status = status;
+            op.clone()
+        }))
+    }
+
+    /// Complete an operation from a webhook callback
+    ///
+    /// The token must match the handle's `callback_token`. Completing an
+    /// operation twice is rejected.
+    pub async fn complete_with_callback(
+        &self,
+        operation_id: &str,
+        callback_token: &str,
+        status: OperationStatus,
+    ) -> ToolResult<TrackedOperation> {
+        let mut operations = self.operations.write().await;
+        let op = operations.get_mut(operation_id).ok_or_else(|| ToolError::ValidationError {
+            message: format!("Unknown operation: {}", operation_id),
+        })?;
+
+        // Compared in constant time, so the token cannot be guessed byte by byte
+        if !bool::from(op.handle.callback_token.as_bytes().ct_eq(callback_token.as_bytes())) {
+            return Err(ToolError::PermissionDenied {
+                message: format!("Invalid callback token for operation {}", operation_id),
+            });
+        }
+        if op.status.is_terminal() {
+            return Err(ToolError::ValidationError {
+                message: format!("Operation {} has already completed", operation_id),
+            });
+        }
+
+        op.status = status;
+        Ok(op.clone())
+    }
+
+    /// Cancel an operation and stop tracking it
+    pub async fn cancel(&self, operation_id: &str) -> ToolResult<()> {
+        let handle = self.operations.read().await.get(operation_id)
+            .map(|op| op.handle.clone())
+            .ok_or_else(|| ToolError::ValidationError {
+                message: format!("Unknown operation: {}", operation_id),
+            })?;
+
+        if let Some(tool) = self.tools.read().await.get(&handle.tool_id).cloned() {
+            tool.cancel(&handle).await?;
+        }
+        self.operations.write().await.remove(operation_id);
+        Ok(())
+    }
+
+    /// Stop tracking a finished operation and return its result
+    pub async fn take_result(&self, operation_id: &str) -> Option<ToolResult<ToolOutput>> {
+        let mut operations = self.operations.write().await;
+        match operations.get(operation_id).map(|op| &op.status) {
+            Some(OperationStatus::Completed { .. }) | Some(OperationStatus::Failed { .. }) => {}
+            _ => return None,
+        }
+        match operations.remove(operation_id)?.status {
+            OperationStatus::Completed { output } => Some(Ok(output)),
+            OperationStatus::Failed { message } => Some(Err(ToolError::ExecutionError { message })),
+            OperationStatus::Pending { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug)]
+    struct RenderTool {
+        metadata: ToolMetadata,
+        polls_until_done: u32,
+        polls: AtomicU32,
+    }
+
+    impl RenderTool {
+        fn new(polls_until_done: u32) -> Self {
+            Self {
+                metadata: ToolMetadata::new("render_video", "Render Video", "Render a video"),
+                polls_until_done,
+                polls: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LongRunningTool for RenderTool {
+        fn metadata(&self) -> &ToolMetadata {
+            &self.metadata
+        }
+
+        async fn start(&self, input: ToolInput) -> ToolResult<OperationHandle> {
+            Ok(OperationHandle::new(&self.metadata.id, input.data).with_poll_interval(Duration::ZERO))
+        }
+
+        async fn poll(&self, _handle: &OperationHandle) -> ToolResult<OperationStatus> {
+            let polls = self.polls.fetch_add(1, Ordering::SeqCst) + 1;
+            if polls >= self.polls_until_done {
+                Ok(OperationStatus::Completed { output: ToolOutput::new(json!({"url": "video.mp4"})) })
+            } else {
+                Ok(OperationStatus::Pending { progress: Some(polls as f64 / self.polls_until_done as f64) })
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polling_completion() {
+        let tool = Arc::new(RenderTool::new(2));
+        let tracker = OperationTracker::new();
+        tracker.register_tool(tool.clone()).await;
+
+        let handle = tool.start(ToolInput::new(json!({"scene": 1}))).await.unwrap();
+        tracker.track(handle.clone()).await;
+
+        assert!(tracker.poll_due().await.is_empty());
+        assert!(tracker.take_result(&handle.operation_id).await.is_none());
+
+        let finished = tracker.poll_due().await;
+        assert_eq!(finished.len(), 1);
+        let output = tracker.take_result(&handle.operation_id).await.unwrap().unwrap();
+        assert_eq!(output.data["url"], "video.mp4");
+        assert!(tracker.pending().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_callback_completion_requires_token() {
+        let tracker = OperationTracker::new();
+        let handle = OperationHandle::new("batch_job", json!({"job": "42"})).with_callback();
+        tracker.track(handle.clone()).await;
+
+        // Callback operations are never polled
+        assert!(tracker.poll_due().await.is_empty());
+
+        let done = OperationStatus::Completed { output: ToolOutput::new(json!({"rows": 10})) };
+        let wrong = tracker.complete_with_callback(&handle.operation_id, "bogus", done.clone()).await;
+        assert!(matches!(wrong, Err(ToolError::PermissionDenied { .. })));
+
+        tracker.complete_with_callback(&handle.operation_id, &handle.callback_token, done.clone()).await.unwrap();
+        let again = tracker.complete_with_callback(&handle.operation_id, &handle.callback_token, done).await;
+        assert!(matches!(again, Err(ToolError::ValidationError { .. })));
+
+        let output = tracker.take_result(&handle.operation_id).await.unwrap().unwrap();
+        assert_eq!(output.data["rows"], 10);
+    }
+}
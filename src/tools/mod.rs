@@ -5,14 +5,32 @@
 pub mod traits;
 /// Tool registry for managing and discovering tools
 pub mod registry;
+/// Policies controlling which tools agents may use
+pub mod policy;
 /// Tool execution engine with retry, timeout, and caching
 pub mod execution;
+/// Long-running tools with polling or callback completion
+pub mod long_running;
+/// Incremental output from streaming tools
+pub mod stream;
+/// Declarative pipelines chaining registered tools
+pub mod pipeline;
+/// HTTP, SQL and shell tools declared in configuration
+pub mod declarative;
+/// OAuth2 authorization for user-delegated tool access
+pub mod oauth;
 /// Common tools for various tasks
 pub mod common;
 
 pub use traits::{Tool, ToolMetadata, ToolInput, ToolOutput, ToolError, ToolResult};
-pub use registry::{ToolRegistry, ToolRegistryBuilder};
+pub use registry::{ToolRegistry, ToolRegistryBuilder, ResolvedTool, ToolSource};
+pub use policy::ToolPolicy;
 pub use execution::{ToolExecutor, ToolExecutionContext};
+pub use long_running::{CompletionMode, LongRunningTool, OperationHandle, OperationStatus, OperationTracker};
+pub use stream::{StreamingTool, ToolOutputChunk, ToolOutputStream};
+pub use pipeline::{JsonPath, PipelineDefinition, PipelineStep, PipelineTool};
+pub use declarative::{DeclaredTool, ToolAction, ToolDefinition};
+pub use oauth::{OAuth2Error, OAuth2Manager, OAuth2ProviderConfig, OAuth2Token, PendingAuthorization};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
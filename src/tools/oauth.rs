@@ -0,0 +1,830 @@
+// OAuth2 authorization for tools acting on a user's behalf
+// Runs authorization-code flows, stores tokens encrypted per user, and refreshes them on expiry
+
+use super::traits::{ToolError, ToolInput, ToolResult};
+use crate::enterprise::secrets::{
+    Credential, CredentialKind, EncryptedSecretStore, SecretStore, SecretString, SecretsError, TENANT_CONTEXT_KEY,
+};
+use crate::human::interrupt::{InterruptManager, InterruptPoint, InterruptType, ResumeToken};
+use crate::human::traits::HumanResult;
+use crate::state::State;
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rand::distributions::{Alphanumeric, DistString};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+
+/// Interrupt type raised when a user must authorize a provider
+pub const AUTHORIZATION_INTERRUPT: &str = "oauth2_authorization";
+
+/// OAuth2 provider registration
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OAuth2ProviderConfig {
+    /// Provider name tools refer to, e.g. `github`
+    pub name: String,
+    /// OAuth client ID
+    pub client_id: String,
+    /// OAuth client secret
+    pub client_secret: Option<String>,
+    /// Authorization endpoint
+    pub auth_url: String,
+    /// Token endpoint
+    pub token_url: String,
+    /// Redirect URI registered with the provider
+    pub redirect_uri: String,
+    /// Scopes to request
+    pub scopes: Vec<String>,
+    /// Use PKCE (S256) for the authorization-code exchange
+    pub use_pkce: bool,
+    /// Extra query parameters for the authorization URL
+    pub extra_auth_params: HashMap<String, String>,
+}
+
+impl fmt::Debug for OAuth2ProviderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2ProviderConfig")
+            .field("name", &self.name)
+            .field("client_id", &self.client_id)
+            .field("auth_url", &self.auth_url)
+            .field("token_url", &self.token_url)
+            .field("redirect_uri", &self.redirect_uri)
+            .field("scopes", &self.scopes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuth2ProviderConfig {
+    /// Create a provider configuration
+    pub fn new(name: &str, client_id: &str, auth_url: &str, token_url: &str, redirect_uri: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: None,
+            auth_url: auth_url.to_string(),
+            token_url: token_url.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            scopes: Vec::new(),
+            use_pkce: true,
+            extra_auth_params: HashMap::new(),
+        }
+    }
+
+    /// Google Drive with read-only access and offline refresh tokens
+    pub fn google_drive(client_id: &str, client_secret: &str, redirect_uri: &str) -> Self {
+        Self::new(
+            "google_drive",
+            client_id,
+            "https://accounts.google.com/o/oauth2/v2/auth",
+            "https://oauth2.googleapis.com/token",
+            redirect_uri,
+        )
+        .with_client_secret(client_secret)
+        .with_scope("https://www.googleapis.com/auth/drive.readonly")
+        .with_auth_param("access_type", "offline")
+        .with_auth_param("prompt", "consent")
+    }
+
+    /// GitHub with repository read access
+    pub fn github(client_id: &str, client_secret: &str, redirect_uri: &str) -> Self {
+        Self::new(
+            "github",
+            client_id,
+            "https://github.com/login/oauth/authorize",
+            "https://github.com/login/oauth/access_token",
+            redirect_uri,
+        )
+        .with_client_secret(client_secret)
+        .with_scope("repo")
+    }
+
+    /// Set the client secret
+    pub fn with_client_secret(mut self, client_secret: &str) -> Self {
+        self.client_secret = Some(client_secret.to_string());
+        self
+    }
+
+    /// Request an additional scope
+    pub fn with_scope(mut self, scope: &str) -> Self {
+        self.scopes.push(scope.to_string());
+        self
+    }
+
+    /// Add a query parameter to the authorization URL
+    pub fn with_auth_param(mut self, key: &str, value: &str) -> Self {
+        self.extra_auth_params.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Enable or disable PKCE
+    pub fn with_pkce(mut self, use_pkce: bool) -> Self {
+        self.use_pkce = use_pkce;
+        self
+    }
+}
+
+/// Tokens granted to a user
+#[derive(Debug, Clone)]
+pub struct OAuth2Token {
+    /// Access token sent to the provider's API
+    pub access_token: SecretString,
+    /// Refresh token for obtaining new access tokens
+    pub refresh_token: Option<SecretString>,
+    /// Token type, normally `Bearer`
+    pub token_type: String,
+    /// When the access token expires
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Granted scopes
+    pub scopes: Vec<String>,
+}
+
+impl OAuth2Token {
+    /// Check if the access token expires within `margin`
+    pub fn expires_within(&self, margin: chrono::Duration) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now() + margin)
+    }
+}
+
+/// Serialized form of a token inside the secret store
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    access_token: String,
+    refresh_token: Option<String>,
+    token_type: String,
+    expires_at: Option<DateTime<Utc>>,
+    scopes: Vec<String>,
+}
+
+impl From<&OAuth2Token> for StoredToken {
+    fn from(token: &OAuth2Token) -> Self {
+        Self {
+            access_token: token.access_token.expose().to_string(),
+            refresh_token: token.refresh_token.as_ref().map(|t| t.expose().to_string()),
+            token_type: token.token_type.clone(),
+            expires_at: token.expires_at,
+            scopes: token.scopes.clone(),
+        }
+    }
+}
+
+impl From<StoredToken> for OAuth2Token {
+    fn from(token: StoredToken) -> Self {
+        Self {
+            access_token: SecretString::new(token.access_token),
+            refresh_token: token.refresh_token.map(SecretString::new),
+            token_type: token.token_type,
+            expires_at: token.expires_at,
+            scopes: token.scopes,
+        }
+    }
+}
+
+/// Authorization waiting for the user to approve access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAuthorization {
+    /// Opaque `state` value identifying the flow on callback
+    pub state: String,
+    /// Provider being authorized
+    pub provider: String,
+    /// Tenant the user belongs to
+    pub tenant_id: String,
+    /// User granting access
+    pub user_id: String,
+    /// URL the user must visit
+    pub authorization_url: String,
+    /// When the flow was started
+    pub created_at: DateTime<Utc>,
+    /// When the flow can no longer be completed
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PendingAuthorization {
+    /// Interrupt point asking a human to complete the authorization
+    pub fn interrupt_point(&self, node_id: &str) -> InterruptPoint {
+        let timeout = (self.expires_at - Utc::now()).to_std().unwrap_or_default();
+        InterruptPoint::new(node_id.to_string(), InterruptType::Custom(AUTHORIZATION_INTERRUPT.to_string()))
+            .with_timeout(timeout)
+            .with_data("provider".to_string(), serde_json::json!(self.provider))
+            .with_data("tenant_id".to_string(), serde_json::json!(self.tenant_id))
+            .with_data("user_id".to_string(), serde_json::json!(self.user_id))
+            .with_data("state".to_string(), serde_json::json!(self.state))
+            .with_data("authorization_url".to_string(), serde_json::json!(self.authorization_url))
+    }
+
+    /// Interrupt the execution at `node_id` until the user has authorized
+    ///
+    /// Resume it after `OAuth2Manager::complete_authorization` succeeds.
+    pub fn raise_interrupt<S: State>(
+        &self,
+        interrupts: &InterruptManager<S>,
+        execution_id: &str,
+        node_id: &str,
+        state: S,
+    ) -> HumanResult<ResumeToken> {
+        interrupts.register_interrupt_point(self.interrupt_point(node_id))?;
+        interrupts.create_interrupt(
+            execution_id.to_string(),
+            node_id.to_string(),
+            state,
+            format!("Authorize {} access at {}", self.provider, self.authorization_url),
+        )
+    }
+}
+
+/// Exchanges codes and refresh tokens at a provider's token endpoint
+#[async_trait]
+pub trait TokenExchanger: Send + Sync + fmt::Debug {
+    /// Exchange an authorization code for tokens
+    async fn exchange_code(
+        &self,
+        provider: &OAuth2ProviderConfig,
+        code: &str,
+        code_verifier: Option<&str>,
+    ) -> Result<OAuth2Token, OAuth2Error>;
+
+    /// Obtain a new access token with a refresh token
+    async fn refresh(&self, provider: &OAuth2ProviderConfig, refresh_token: &str) -> Result<OAuth2Token, OAuth2Error>;
+}
+
+/// Token exchanger speaking the standard OAuth2 token endpoint protocol
+#[derive(Debug, Clone, Default)]
+pub struct HttpTokenExchanger {
+    client: reqwest::Client,
+}
+
+/// Token endpoint error response
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+}
+
+/// Token endpoint response
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+impl HttpTokenExchanger {
+    /// Create an exchanger with a default HTTP client
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn request(
+        &self,
+        provider: &OAuth2ProviderConfig,
+        mut form: Vec<(&str, String)>,
+    ) -> Result<OAuth2Token, OAuth2Error> {
+        form.push(("client_id", provider.client_id.clone()));
+        if let Some(secret) = &provider.client_secret {
+            form.push(("client_secret", secret.clone()));
+        }
+
+        let failed = |message: String| OAuth2Error::TokenRequestFailed {
+            provider: provider.name.clone(),
+            message,
+        };
+        let response = self.client
+            .post(&provider.token_url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let error = serde_json::from_str::<TokenErrorResponse>(&body).ok().map(|response| response.error);
+            if error.as_deref() == Some("invalid_grant") {
+                return Err(OAuth2Error::InvalidGrant {
+                    provider: provider.name.clone(),
+                    message: body,
+                });
+            }
+            return Err(failed(format!("HTTP {}: {}", status, body)));
+        }
+        let token: TokenResponse = response.json().await.map_err(|e| failed(e.to_string()))?;
+
+        Ok(OAuth2Token {
+            access_token: SecretString::new(token.access_token),
+            refresh_token: token.refresh_token.map(SecretString::new),
+            token_type: token.token_type.unwrap_or_else(|| "Bearer".to_string()),
+            expires_at: token.expires_in.map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+            scopes: token.scope
+                .map(|s| s.split([' ', ',']).filter(|s| !s.is_empty()).map(String::from).collect())
+                .unwrap_or_else(|| provider.scopes.clone()),
+        })
+    }
+}
+
+#[async_trait]
+impl TokenExchanger for HttpTokenExchanger {
+    async fn exchange_code(
+        &self,
+        provider: &OAuth2ProviderConfig,
+        code: &str,
+        code_verifier: Option<&str>,
+    ) -> Result<OAuth2Token, OAuth2Error> {
+        let mut form = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code.to_string()),
+            ("redirect_uri", provider.redirect_uri.clone()),
+        ];
+        if let Some(verifier) = code_verifier {
+            form.push(("code_verifier", verifier.to_string()));
+        }
+        self.request(provider, form).await
+    }
+
+    async fn refresh(&self, provider: &OAuth2ProviderConfig, refresh_token: &str) -> Result<OAuth2Token, OAuth2Error> {
+        self.request(provider, vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ]).await
+    }
+}
+
+/// Flow started by `begin_authorization`, kept until the callback arrives
+#[derive(Debug, Clone)]
+struct AuthorizationFlow {
+    pending: PendingAuthorization,
+    code_verifier: Option<String>,
+}
+
+/// Manages OAuth2 authorizations and tokens for tools
+///
+/// Tokens are kept in a secret store under the user's tenant, named
+/// `oauth2:<provider>:<user_id>`, and are always encrypted with the
+/// manager's key before reaching it. A user's tokens for a provider are
+/// refreshed by one caller at a time.
+#[derive(Debug)]
+pub struct OAuth2Manager {
+    providers: HashMap<String, OAuth2ProviderConfig>,
+    store: EncryptedSecretStore,
+    exchanger: Arc<dyn TokenExchanger>,
+    flows: RwLock<HashMap<String, AuthorizationFlow>>,
+    /// Held while a user's token for a provider is refreshed
+    refreshes: std::sync::Mutex<HashMap<(String, String, String), Arc<tokio::sync::Mutex<()>>>>,
+    refresh_margin: chrono::Duration,
+    authorization_ttl: chrono::Duration,
+}
+
+impl OAuth2Manager {
+    /// Create a manager storing tokens in `store`, encrypted with `encryption_key`
+    pub fn new(store: Arc<dyn SecretStore>, encryption_key: &[u8; 32]) -> Self {
+        Self {
+            providers: HashMap::new(),
+            store: EncryptedSecretStore::new(store, encryption_key),
+            exchanger: Arc::new(HttpTokenExchanger::new()),
+            flows: RwLock::new(HashMap::new()),
+            refreshes: std::sync::Mutex::new(HashMap::new()),
+            refresh_margin: chrono::Duration::seconds(60),
+            authorization_ttl: chrono::Duration::minutes(10),
+        }
+    }
+
+    /// Register a provider
+    pub fn with_provider(mut self, provider: OAuth2ProviderConfig) -> Self {
+        self.providers.insert(provider.name.clone(), provider);
+        self
+    }
+
+    /// Use a custom token exchanger
+    pub fn with_exchanger(mut self, exchanger: Arc<dyn TokenExchanger>) -> Self {
+        self.exchanger = exchanger;
+        self
+    }
+
+    /// Refresh tokens this long before they expire (default 60 seconds)
+    pub fn with_refresh_margin(mut self, margin: chrono::Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// How long users have to complete an authorization (default 10 minutes)
+    pub fn with_authorization_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.authorization_ttl = ttl;
+        self
+    }
+
+    fn provider(&self, name: &str) -> Result<&OAuth2ProviderConfig, OAuth2Error> {
+        self.providers.get(name).ok_or_else(|| OAuth2Error::UnknownProvider {
+            provider: name.to_string(),
+        })
+    }
+
+    fn credential_name(provider: &str, user_id: &str) -> String {
+        format!("oauth2:{}:{}", provider, user_id)
+    }
+
+    /// Start an authorization-code flow for a user of a tenant
+    pub fn begin_authorization(&self, tenant_id: &str, user_id: &str, provider: &str) -> Result<PendingAuthorization, OAuth2Error> {
+        let config = self.provider(provider)?;
+        let state = uuid::Uuid::new_v4().to_string();
+
+        let mut params = vec![
+            ("response_type".to_string(), "code".to_string()),
+            ("client_id".to_string(), config.client_id.clone()),
+            ("redirect_uri".to_string(), config.redirect_uri.clone()),
+            ("state".to_string(), state.clone()),
+        ];
+        if !config.scopes.is_empty() {
+            params.push(("scope".to_string(), config.scopes.join(" ")));
+        }
+        let code_verifier = config.use_pkce.then(|| Alphanumeric.sample_string(&mut rand::thread_rng(), 64));
+        if let Some(verifier) = &code_verifier {
+            let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+            params.push(("code_challenge".to_string(), challenge));
+            params.push(("code_challenge_method".to_string(), "S256".to_string()));
+        }
+        let mut extra: Vec<_> = config.extra_auth_params.iter().collect();
+        extra.sort();
+        params.extend(extra.into_iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let url = Url::parse_with_params(&config.auth_url, &params).map_err(|e| OAuth2Error::InvalidConfiguration {
+            message: format!("Invalid authorization URL for {}: {}", provider, e),
+        })?;
+
+        let now = Utc::now();
+        let pending = PendingAuthorization {
+            state: state.clone(),
+            provider: provider.to_string(),
+            tenant_id: tenant_id.to_string(),
+            user_id: user_id.to_string(),
+            authorization_url: url.to_string(),
+            created_at: now,
+            expires_at: now + self.authorization_ttl,
+        };
+
+        let mut flows = self.flows.write().unwrap();
+        flows.retain(|_, flow| flow.pending.expires_at > now);
+        flows.insert(state, AuthorizationFlow {
+            pending: pending.clone(),
+            code_verifier,
+        });
+        Ok(pending)
+    }
+
+    /// Finish a flow with the code the provider redirected back with
+    pub async fn complete_authorization(&self, state: &str, code: &str) -> Result<OAuth2Token, OAuth2Error> {
+        let flow = self.flows.write().unwrap().remove(state).ok_or_else(|| OAuth2Error::InvalidState {
+            state: state.to_string(),
+        })?;
+        if flow.pending.expires_at <= Utc::now() {
+            return Err(OAuth2Error::AuthorizationExpired {
+                provider: flow.pending.provider,
+                user_id: flow.pending.user_id,
+            });
+        }
+
+        let config = self.provider(&flow.pending.provider)?;
+        let token = self.exchanger.exchange_code(config, code, flow.code_verifier.as_deref()).await?;
+        self.save_token(&flow.pending.tenant_id, &flow.pending.user_id, &flow.pending.provider, &token).await?;
+        tracing::info!("User {} authorized {}", flow.pending.user_id, flow.pending.provider);
+        Ok(token)
+    }
+
+    /// Get a usable access token, refreshing it if it is about to expire
+    ///
+    /// Fails with `AuthorizationRequired`, carrying a freshly started flow,
+    /// when the user has not authorized the provider or the provider rejects
+    /// the refresh token. Other refresh failures, e.g. network errors, are
+    /// returned as they are and leave the stored tokens in place.
+    pub async fn access_token(&self, tenant_id: &str, user_id: &str, provider: &str) -> Result<OAuth2Token, OAuth2Error> {
+        let config = self.provider(provider)?;
+
+        if let Some(token) = self.load_token(tenant_id, user_id, provider).await? {
+            if !token.expires_within(self.refresh_margin) {
+                return Ok(token);
+            }
+
+            // Refreshed by one caller at a time, so a rotated refresh token is never used twice
+            let lock = self.refresh_lock(tenant_id, user_id, provider);
+            let _refreshing = lock.lock().await;
+            let token = match self.load_token(tenant_id, user_id, provider).await? {
+                Some(token) if !token.expires_within(self.refresh_margin) => return Ok(token),
+                Some(token) => token,
+                None => return self.authorization_required(tenant_id, user_id, provider),
+            };
+            if let Some(refresh_token) = &token.refresh_token {
+                match self.exchanger.refresh(config, refresh_token.expose()).await {
+                    Ok(mut refreshed) => {
+                        // Providers may omit the refresh token when it is unchanged
+                        if refreshed.refresh_token.is_none() {
+                            refreshed.refresh_token = token.refresh_token.clone();
+                        }
+                        self.save_token(tenant_id, user_id, provider, &refreshed).await?;
+                        return Ok(refreshed);
+                    }
+                    Err(e @ OAuth2Error::InvalidGrant { .. }) => {
+                        tracing::warn!("Refreshing {} token for user {} failed: {}", provider, user_id, e);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            self.revoke(tenant_id, user_id, provider).await?;
+        }
+
+        self.authorization_required(tenant_id, user_id, provider)
+    }
+
+    fn authorization_required(&self, tenant_id: &str, user_id: &str, provider: &str) -> Result<OAuth2Token, OAuth2Error> {
+        Err(OAuth2Error::AuthorizationRequired {
+            authorization: Box::new(self.begin_authorization(tenant_id, user_id, provider)?),
+        })
+    }
+
+    fn refresh_lock(&self, tenant_id: &str, user_id: &str, provider: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut refreshes = self.refreshes.lock().unwrap();
+        // Locks nobody waits on are dropped
+        refreshes.retain(|_, lock| Arc::strong_count(lock) > 1);
+        refreshes
+            .entry((tenant_id.to_string(), user_id.to_string(), provider.to_string()))
+            .or_default()
+            .clone()
+    }
+
+    /// Forget a user's tokens for a provider
+    pub async fn revoke(&self, tenant_id: &str, user_id: &str, provider: &str) -> Result<bool, OAuth2Error> {
+        Ok(self.store.delete(tenant_id, &Self::credential_name(provider, user_id)).await?)
+    }
+
+    /// Set `Authorization` for a tool call made on behalf of the input's `user_id` and `tenant_id`
+    pub async fn inject(&self, provider: &str, input: &mut ToolInput) -> ToolResult<()> {
+        let context = |key: &str| input.get_context(key).cloned().ok_or_else(|| ToolError::PermissionDenied {
+            message: format!("{} access requires a user and tenant context", provider),
+        });
+        let (user_id, tenant_id) = (context("user_id")?, context(TENANT_CONTEXT_KEY)?);
+        let token = self.access_token(&tenant_id, &user_id, provider).await.map_err(|e| ToolError::AuthenticationError {
+            message: e.to_string(),
+        })?;
+
+        let headers = input.parameters.entry("headers".to_string())
+            .or_insert_with(|| serde_json::json!({}));
+        if !headers.is_object() {
+            *headers = serde_json::json!({});
+        }
+        headers["Authorization"] = serde_json::Value::String(
+            format!("{} {}", token.token_type, token.access_token.expose()),
+        );
+        Ok(())
+    }
+
+    async fn load_token(&self, tenant_id: &str, user_id: &str, provider: &str) -> Result<Option<OAuth2Token>, OAuth2Error> {
+        let credential = self.store.get(tenant_id, &Self::credential_name(provider, user_id)).await?;
+        credential
+            .map(|c| serde_json::from_str::<StoredToken>(c.secret.expose()).map(OAuth2Token::from))
+            .transpose()
+            .map_err(|e| OAuth2Error::Storage(SecretsError::StorageError { message: e.to_string() }))
+    }
+
+    async fn save_token(&self, tenant_id: &str, user_id: &str, provider: &str, token: &OAuth2Token) -> Result<(), OAuth2Error> {
+        let serialized = serde_json::to_string(&StoredToken::from(token))
+            .map_err(|e| OAuth2Error::Storage(SecretsError::StorageError { message: e.to_string() }))?;
+        let credential = Credential::new(&Self::credential_name(provider, user_id), CredentialKind::BearerToken, serialized);
+        Ok(self.store.put(tenant_id, credential).await?)
+    }
+}
+
+/// Errors that can occur in OAuth2 flows
+#[derive(Debug, Error, Clone)]
+pub enum OAuth2Error {
+    /// Provider is not registered
+    #[error("Unknown OAuth2 provider: {provider}")]
+    UnknownProvider {
+        /// Provider name
+        provider: String,
+    },
+
+    /// User must authorize the provider first
+    #[error("Authorization required for {}: visit {}", authorization.provider, authorization.authorization_url)]
+    AuthorizationRequired {
+        /// Flow the user should complete
+        authorization: Box<PendingAuthorization>,
+    },
+
+    /// Callback state does not match a pending flow
+    #[error("Unknown or already used authorization state: {state}")]
+    InvalidState {
+        /// State received on callback
+        state: String,
+    },
+
+    /// User took too long to authorize
+    #[error("Authorization for {provider} by user {user_id} expired")]
+    AuthorizationExpired {
+        /// Provider name
+        provider: String,
+        /// User ID
+        user_id: String,
+    },
+
+    /// Token endpoint rejected the request
+    #[error("Token request to {provider} failed: {message}")]
+    TokenRequestFailed {
+        /// Provider name
+        provider: String,
+        /// Error message
+        message: String,
+    },
+
+    /// Provider no longer accepts the refresh token, e.g. because access was revoked
+    #[error("{provider} rejected the refresh token: {message}")]
+    InvalidGrant {
+        /// Provider name
+        provider: String,
+        /// Error response
+        message: String,
+    },
+
+    /// Provider configuration is invalid
+    #[error("Invalid OAuth2 configuration: {message}")]
+    InvalidConfiguration {
+        /// Error message
+        message: String,
+    },
+
+    /// Token storage failed
+    #[error(transparent)]
+    Storage(#[from] SecretsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enterprise::secrets::InMemorySecretStore;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Debug, Default)]
+    struct MockExchanger {
+        refreshes: AtomicU32,
+        /// Error returned by the next refresh
+        failure: std::sync::Mutex<Option<OAuth2Error>>,
+    }
+
+    #[async_trait]
+    impl TokenExchanger for MockExchanger {
+        async fn exchange_code(
+            &self,
+            _provider: &OAuth2ProviderConfig,
+            code: &str,
+            code_verifier: Option<&str>,
+        ) -> Result<OAuth2Token, OAuth2Error> {
+            assert!(code_verifier.is_some());
+            Ok(OAuth2Token {
+                access_token: SecretString::new(format!("access-{}", code)),
+                refresh_token: Some(SecretString::new("refresh-1")),
+                token_type: "Bearer".to_string(),
+                // Already inside the refresh margin
+                expires_at: Some(Utc::now() + chrono::Duration::seconds(5)),
+                scopes: vec!["repo".to_string()],
+            })
+        }
+
+        async fn refresh(&self, _provider: &OAuth2ProviderConfig, refresh_token: &str) -> Result<OAuth2Token, OAuth2Error> {
+            assert_eq!(refresh_token, "refresh-1");
+            if let Some(failure) = self.failure.lock().unwrap().take() {
+                return Err(failure);
+            }
+            // Lets concurrent callers pile up behind the refresh
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            let n = self.refreshes.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(OAuth2Token {
+                access_token: SecretString::new(format!("refreshed-{}", n)),
+                refresh_token: None,
+                token_type: "Bearer".to_string(),
+                expires_at: Some(Utc::now() + chrono::Duration::hours(1)),
+                scopes: vec!["repo".to_string()],
+            })
+        }
+    }
+
+    fn manager(backend: Arc<InMemorySecretStore>, exchanger: Arc<MockExchanger>) -> OAuth2Manager {
+        OAuth2Manager::new(backend, &[3u8; 32])
+            .with_provider(OAuth2ProviderConfig::github("client-1", "shh", "https://app.test/callback"))
+            .with_exchanger(exchanger)
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow_and_refresh() {
+        let backend = Arc::new(InMemorySecretStore::new());
+        let exchanger = Arc::new(MockExchanger::default());
+        let manager = manager(backend.clone(), exchanger.clone());
+
+        let pending = match manager.access_token("acme", "alice", "github").await {
+            Err(OAuth2Error::AuthorizationRequired { authorization }) => *authorization,
+            other => panic!("expected authorization request, got {:?}", other),
+        };
+        let url = Url::parse(&pending.authorization_url).unwrap();
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["client_id"], "client-1");
+        assert_eq!(query["state"], pending.state);
+        assert_eq!(query["code_challenge_method"], "S256");
+
+        manager.complete_authorization(&pending.state, "abc").await.unwrap();
+        assert!(matches!(
+            manager.complete_authorization(&pending.state, "abc").await,
+            Err(OAuth2Error::InvalidState { .. })
+        ));
+
+        // Stored token is encrypted at rest
+        let raw = backend.get("acme", "oauth2:github:alice").await.unwrap().unwrap();
+        assert!(!raw.secret.expose().contains("access-abc"));
+
+        // Expiring token is refreshed once and the refresh token kept
+        let token = manager.access_token("acme", "alice", "github").await.unwrap();
+        assert_eq!(token.access_token.expose(), "refreshed-1");
+        assert_eq!(token.refresh_token.as_ref().map(|t| t.expose()), Some("refresh-1"));
+        let again = manager.access_token("acme", "alice", "github").await.unwrap();
+        assert_eq!(again.access_token.expose(), "refreshed-1");
+        assert_eq!(exchanger.refreshes.load(Ordering::SeqCst), 1);
+
+        let mut input = ToolInput::new(serde_json::json!({}))
+            .with_context("user_id", "alice")
+            .with_context(TENANT_CONTEXT_KEY, "acme");
+        manager.inject("github", &mut input).await.unwrap();
+        assert_eq!(input.parameters["headers"]["Authorization"], "Bearer refreshed-1");
+    }
+
+    #[tokio::test]
+    async fn test_pending_authorization_raises_interrupt() {
+        let manager = manager(Arc::new(InMemorySecretStore::new()), Arc::new(MockExchanger::default()));
+        let pending = manager.begin_authorization("acme", "bob", "github").unwrap();
+
+        let interrupts = InterruptManager::<serde_json::Value>::new();
+        let token = pending.raise_interrupt(&interrupts, "exec-1", "fetch_repos", serde_json::json!({})).unwrap();
+        assert_eq!(token.node_id, "fetch_repos");
+        assert!(token.expires_at.is_some());
+
+        let resumed = interrupts.resume_execution(&token).unwrap();
+        assert!(resumed.reason.contains(&pending.authorization_url));
+
+        let mut input = ToolInput::new(serde_json::json!({}));
+        assert!(matches!(manager.inject("github", &mut input).await, Err(ToolError::PermissionDenied { .. })));
+        let mut input = ToolInput::new(serde_json::json!({})).with_context("user_id", "bob");
+        assert!(matches!(manager.inject("github", &mut input).await, Err(ToolError::PermissionDenied { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_revokes_only_on_invalid_grant() {
+        let backend = Arc::new(InMemorySecretStore::new());
+        let exchanger = Arc::new(MockExchanger::default());
+        let manager = Arc::new(manager(backend.clone(), exchanger.clone()));
+
+        let pending = manager.begin_authorization("acme", "alice", "github").unwrap();
+        manager.complete_authorization(&pending.state, "abc").await.unwrap();
+
+        // Tokens are scoped to the tenant
+        assert!(matches!(
+            manager.access_token("globex", "alice", "github").await,
+            Err(OAuth2Error::AuthorizationRequired { .. })
+        ));
+
+        // A transient failure keeps the tokens for the next attempt
+        *exchanger.failure.lock().unwrap() = Some(OAuth2Error::TokenRequestFailed {
+            provider: "github".to_string(),
+            message: "HTTP 503".to_string(),
+        });
+        assert!(matches!(
+            manager.access_token("acme", "alice", "github").await,
+            Err(OAuth2Error::TokenRequestFailed { .. })
+        ));
+        assert!(backend.get("acme", "oauth2:github:alice").await.unwrap().is_some());
+
+        // Concurrent callers share a single refresh
+        let calls = (0..4).map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.access_token("acme", "alice", "github").await })
+        });
+        for call in futures::future::join_all(calls).await {
+            assert_eq!(call.unwrap().unwrap().access_token.expose(), "refreshed-1");
+        }
+        assert_eq!(exchanger.refreshes.load(Ordering::SeqCst), 1);
+
+        // A rejected refresh token forgets the tokens and asks to authorize again
+        let pending = manager.begin_authorization("acme", "alice", "github").unwrap();
+        manager.complete_authorization(&pending.state, "def").await.unwrap();
+        *exchanger.failure.lock().unwrap() = Some(OAuth2Error::InvalidGrant {
+            provider: "github".to_string(),
+            message: "invalid_grant".to_string(),
+        });
+        assert!(matches!(
+            manager.access_token("acme", "alice", "github").await,
+            Err(OAuth2Error::AuthorizationRequired { .. })
+        ));
+        assert!(backend.get("acme", "oauth2:github:alice").await.unwrap().is_none());
+    }
+}
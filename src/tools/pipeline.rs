@@ -0,0 +1,525 @@
+// Declarative tool pipelines
+// Chains registered tools, mapping earlier outputs into later inputs with JSONPath
+
+use super::registry::ToolRegistry;
+use super::traits::{Tool, ToolError, ToolInput, ToolMetadata, ToolOutput, ToolResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// One segment of a JSONPath expression
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    /// Object member
+    Key(String),
+    /// Array element; negative indexes count from the end
+    Index(i64),
+    /// Every member or element
+    Wildcard,
+}
+
+/// JSONPath expression
+///
+/// Supports the subset needed for mapping tool outputs: the root `$`, member
+/// access (`.name`, `['name']`), array indexes (`[0]`, `[-1]`) and wildcards
+/// (`.*`, `[*]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    raw: String,
+    segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    /// Parse an expression
+    pub fn parse(expression: &str) -> ToolResult<Self> {
+        let invalid = |reason: &str| ToolError::ValidationError {
+            message: format!("Invalid JSONPath '{}': {}", expression, reason),
+        };
+
+        let mut chars = expression.trim().chars().peekable();
+        if chars.next() != Some('$') {
+            return Err(invalid("must start with '$'"));
+        }
+
+        let mut segments = Vec::new();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    if chars.peek() == Some(&'*') {
+                        chars.next();
+                        segments.push(PathSegment::Wildcard);
+                        continue;
+                    }
+                    let mut key = String::new();
+                    while let Some(&next) = chars.peek() {
+                        if next == '.' || next == '[' {
+                            break;
+                        }
+                        key.push(next);
+                        chars.next();
+                    }
+                    if key.is_empty() {
+                        return Err(invalid("empty member name"));
+                    }
+                    segments.push(PathSegment::Key(key));
+                }
+                '[' => {
+                    let mut inner = String::new();
+                    let mut quote = None;
+                    loop {
+                        let next = chars.next().ok_or_else(|| invalid("unclosed '['"))?;
+                        match (quote, next) {
+                            (None, ']') => break,
+                            (None, '\'' | '"') => quote = Some(next),
+                            (Some(q), c) if c == q => quote = None,
+                            _ => inner.push(next),
+                        }
+                    }
+                    let segment = if inner == "*" {
+                        PathSegment::Wildcard
+                    } else if let Ok(index) = inner.trim().parse::<i64>() {
+                        PathSegment::Index(index)
+                    } else if !inner.is_empty() {
+                        PathSegment::Key(inner)
+                    } else {
+                        return Err(invalid("empty brackets"));
+                    };
+                    segments.push(segment);
+                }
+                _ => return Err(invalid(&format!("unexpected '{}'", c))),
+            }
+        }
+
+        Ok(Self {
+            raw: expression.trim().to_string(),
+            segments,
+        })
+    }
+
+    /// Whether the path selects at most one value
+    pub fn is_definite(&self) -> bool {
+        !self.segments.contains(&PathSegment::Wildcard)
+    }
+
+    /// All values the path selects
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = current.into_iter().flat_map(|value| -> Vec<&'a Value> {
+                match (segment, value) {
+                    (PathSegment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                    (PathSegment::Index(index), Value::Array(items)) => {
+                        let position = if *index < 0 { items.len() as i64 + index } else { *index };
+                        usize::try_from(position).ok().and_then(|i| items.get(i)).into_iter().collect()
+                    }
+                    (PathSegment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    (PathSegment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    _ => Vec::new(),
+                }
+            }).collect();
+        }
+        current
+    }
+
+    /// Evaluate the path to a single JSON value
+    ///
+    /// A definite path yields the matched value and fails if nothing
+    /// matches; a wildcard path yields an array of all matches.
+    pub fn evaluate(&self, root: &Value) -> ToolResult<Value> {
+        let matches = self.select(root);
+        if self.is_definite() {
+            matches.first().map(|v| (*v).clone()).ok_or_else(|| ToolError::ValidationError {
+                message: format!("JSONPath '{}' matched nothing", self.raw),
+            })
+        } else {
+            Ok(Value::Array(matches.into_iter().cloned().collect()))
+        }
+    }
+}
+
+impl std::fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+/// One tool call in a pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStep {
+    /// Name other steps use to refer to this step's output; defaults to the tool ID
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Tool ID or namespaced name
+    pub tool: String,
+    /// Input data template; the previous step's output is used when omitted
+    #[serde(default)]
+    pub input: Option<Value>,
+    /// Input parameter templates
+    #[serde(default)]
+    pub parameters: HashMap<String, Value>,
+}
+
+impl PipelineStep {
+    /// Create a step that receives the previous step's output
+    pub fn new(tool: &str) -> Self {
+        Self {
+            name: None,
+            tool: tool.to_string(),
+            input: None,
+            parameters: HashMap::new(),
+        }
+    }
+
+    /// Set the step name
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set the input data template
+    pub fn with_input(mut self, input: Value) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Add an input parameter template
+    pub fn with_parameter(mut self, key: &str, value: Value) -> Self {
+        self.parameters.insert(key.to_string(), value);
+        self
+    }
+
+    /// Name of the step's output in the pipeline context
+    pub fn output_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.tool)
+    }
+}
+
+/// Declarative definition of a tool pipeline
+///
+/// Templates are JSON values in which any string starting with `$` is a
+/// JSONPath evaluated against the pipeline context:
+///
+/// - `$.input` – the pipeline's input data
+/// - `$.steps.<name>` – output data of an earlier step
+/// - `$.prev` – output data of the previous step
+///
+/// A leading `$$` escapes a literal dollar sign.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineDefinition {
+    /// Tool ID the pipeline is registered under
+    pub id: String,
+    /// Display name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// What the pipeline does
+    pub description: String,
+    /// Namespace for the pipeline tool
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Steps in execution order
+    pub steps: Vec<PipelineStep>,
+    /// Output template; defaults to the last step's output
+    #[serde(default)]
+    pub output: Option<Value>,
+}
+
+impl PipelineDefinition {
+    /// Create an empty pipeline definition
+    pub fn new(id: &str, description: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: None,
+            description: description.to_string(),
+            namespace: None,
+            steps: Vec::new(),
+            output: None,
+        }
+    }
+
+    /// Parse a definition from JSON
+    pub fn from_json(json: &str) -> ToolResult<Self> {
+        serde_json::from_str(json).map_err(|e| ToolError::ConfigurationError {
+            message: format!("Invalid pipeline definition: {}", e),
+        })
+    }
+
+    /// Append a step
+    pub fn with_step(mut self, step: PipelineStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Set the output template
+    pub fn with_output(mut self, output: Value) -> Self {
+        self.output = Some(output);
+        self
+    }
+}
+
+/// Tool that runs a pipeline of other tools as a single call
+#[derive(Debug)]
+pub struct PipelineTool {
+    metadata: ToolMetadata,
+    definition: PipelineDefinition,
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl PipelineTool {
+    /// Build a pipeline, resolving its steps against a registry
+    ///
+    /// Fails if a step names an unknown tool, two steps share an output
+    /// name, or a template contains an invalid JSONPath.
+    pub fn new(definition: PipelineDefinition, registry: &ToolRegistry) -> ToolResult<Self> {
+        if definition.steps.is_empty() {
+            return Err(ToolError::ConfigurationError {
+                message: format!("Pipeline '{}' has no steps", definition.id),
+            });
+        }
+
+        let mut tools = Vec::with_capacity(definition.steps.len());
+        let mut names = std::collections::HashSet::new();
+        for step in &definition.steps {
+            let tool = registry.resolve(&step.tool).ok_or_else(|| ToolError::ConfigurationError {
+                message: format!("Pipeline '{}' uses unknown tool '{}'", definition.id, step.tool),
+            })?;
+            if !names.insert(step.output_name().to_string()) {
+                return Err(ToolError::ConfigurationError {
+                    message: format!(
+                        "Pipeline '{}' has more than one step named '{}'",
+                        definition.id,
+                        step.output_name()
+                    ),
+                });
+            }
+            for template in step.input.iter().chain(step.parameters.values()) {
+                check_template(template)?;
+            }
+            tools.push(tool);
+        }
+        if let Some(output) = &definition.output {
+            check_template(output)?;
+        }
+
+        let mut metadata = ToolMetadata::new(
+            &definition.id,
+            definition.name.as_deref().unwrap_or(&definition.id),
+            &definition.description,
+        )
+        .with_namespace(definition.namespace.as_deref().unwrap_or("pipeline"))
+        .with_tag("pipeline")
+        .with_deterministic(tools.iter().all(|t| t.metadata().deterministic))
+        .with_side_effects(tools.iter().any(|t| t.metadata().has_side_effects));
+        let estimated: Option<u64> = tools.iter().map(|t| t.metadata().estimated_duration_ms).sum();
+        if let Some(estimated) = estimated {
+            metadata = metadata.with_estimated_duration_ms(estimated);
+        }
+
+        Ok(Self {
+            metadata,
+            definition,
+            tools,
+        })
+    }
+
+    /// Pipeline definition
+    pub fn definition(&self) -> &PipelineDefinition {
+        &self.definition
+    }
+}
+
+#[async_trait]
+impl Tool for PipelineTool {
+    fn metadata(&self) -> &ToolMetadata {
+        &self.metadata
+    }
+
+    async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+        let mut context = json!({
+            "input": input.data.clone(),
+            "steps": {},
+            "prev": input.data.clone(),
+        });
+        let mut step_durations = Map::new();
+
+        for (step, tool) in self.definition.steps.iter().zip(&self.tools) {
+            let data = match &step.input {
+                Some(template) => render_template(template, &context)?,
+                None => context["prev"].clone(),
+            };
+            let mut step_input = ToolInput::new(data);
+            step_input.context = input.context.clone();
+            for (key, template) in &step.parameters {
+                step_input.parameters.insert(key.clone(), render_template(template, &context)?);
+            }
+
+            let started = Instant::now();
+            let result = match tool.validate_input(&step_input).await {
+                Ok(()) => tool.execute(step_input).await,
+                Err(e) => Err(e),
+            };
+            let output = result.map_err(|e| ToolError::ExecutionError {
+                message: format!(
+                    "Pipeline '{}' step '{}' ({}) failed: {}",
+                    self.definition.id,
+                    step.output_name(),
+                    tool.metadata().id,
+                    e
+                ),
+            })?;
+            step_durations.insert(step.output_name().to_string(), json!(started.elapsed().as_millis() as u64));
+
+            context["steps"][step.output_name()] = output.data.clone();
+            context["prev"] = output.data;
+        }
+
+        let result = match &self.definition.output {
+            Some(template) => render_template(template, &context)?,
+            None => context["prev"].clone(),
+        };
+
+        Ok(ToolOutput::new(result)
+            .with_metadata("pipeline", &self.definition.id)
+            .with_metadata("step_durations_ms", Value::Object(step_durations))
+            .with_metric("steps_executed", self.definition.steps.len() as f64))
+    }
+}
+
+/// Substitute JSONPath expressions in a template
+fn render_template(template: &Value, context: &Value) -> ToolResult<Value> {
+    match template {
+        Value::String(s) if s.starts_with("$$") => Ok(Value::String(s[1..].to_string())),
+        Value::String(s) if s.starts_with('$') => JsonPath::parse(s)?.evaluate(context),
+        Value::Array(items) => items.iter().map(|item| render_template(item, context)).collect(),
+        Value::Object(map) => map.iter()
+            .map(|(key, value)| Ok((key.clone(), render_template(value, context)?)))
+            .collect::<ToolResult<Map<String, Value>>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+/// Check that every JSONPath in a template parses
+fn check_template(template: &Value) -> ToolResult<()> {
+    match template {
+        Value::String(s) if s.starts_with("$$") => Ok(()),
+        Value::String(s) if s.starts_with('$') => JsonPath::parse(s).map(|_| ()),
+        Value::Array(items) => items.iter().try_for_each(check_template),
+        Value::Object(map) => map.values().try_for_each(check_template),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct FnTool {
+        metadata: ToolMetadata,
+        run: fn(&ToolInput) -> ToolResult<Value>,
+    }
+
+    impl FnTool {
+        fn new(id: &str, run: fn(&ToolInput) -> ToolResult<Value>) -> Self {
+            Self {
+                metadata: ToolMetadata::new(id, id, "test tool").with_estimated_duration_ms(10),
+                run,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Tool for FnTool {
+        fn metadata(&self) -> &ToolMetadata {
+            &self.metadata
+        }
+
+        async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+            (self.run)(&input).map(ToolOutput::new)
+        }
+    }
+
+    fn registry() -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        registry.register(FnTool::new("search", |input| {
+            Ok(json!({"results": [
+                {"url": format!("https://a.test/{}", input.data["q"].as_str().unwrap_or_default()), "score": 0.9},
+                {"url": "https://b.test", "score": 0.4}
+            ]}))
+        })).unwrap();
+        registry.register(FnTool::new("fetch", |input| {
+            Ok(json!({"body": format!("contents of {}", input.data.as_str().unwrap_or_default())}))
+        })).unwrap();
+        registry.register(FnTool::new("summarize", |input| {
+            let max = input.get_parameter::<usize>("max_chars").unwrap_or(usize::MAX);
+            let text = input.data["text"].as_str().unwrap_or_default();
+            Ok(json!({"summary": text.chars().take(max).collect::<String>()}))
+        })).unwrap();
+        registry.register(FnTool::new("fail", |_| {
+            Err(ToolError::ExecutionError { message: "boom".to_string() })
+        })).unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_json_path() {
+        let value = json!({"a": {"b": [1, 2, {"c": "x"}]}, "key with space": true});
+
+        assert_eq!(JsonPath::parse("$.a.b[0]").unwrap().evaluate(&value).unwrap(), json!(1));
+        assert_eq!(JsonPath::parse("$.a.b[-1].c").unwrap().evaluate(&value).unwrap(), json!("x"));
+        assert_eq!(JsonPath::parse("$['key with space']").unwrap().evaluate(&value).unwrap(), json!(true));
+        assert_eq!(JsonPath::parse("$.a.b[*]").unwrap().evaluate(&value).unwrap(), json!([1, 2, {"c": "x"}]));
+        assert_eq!(JsonPath::parse("$").unwrap().evaluate(&value).unwrap(), value);
+        assert!(JsonPath::parse("$.a.missing").unwrap().evaluate(&value).is_err());
+        assert!(JsonPath::parse("a.b").is_err());
+        assert!(JsonPath::parse("$.a[1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_fetch_summarize_pipeline() {
+        let definition = PipelineDefinition::from_json(r#"{
+            "id": "research",
+            "description": "Search, fetch the top hit, and summarize it",
+            "steps": [
+                {"tool": "search", "input": {"q": "$.input.topic"}},
+                {"tool": "fetch", "input": "$.steps.search.results[0].url"},
+                {"name": "short", "tool": "summarize", "input": {"text": "$.prev.body"}, "parameters": {"max_chars": 11}}
+            ],
+            "output": {"summary": "$.steps.short.summary", "sources": "$.steps.search.results[*].url", "cost": "$$0"}
+        }"#).unwrap();
+
+        let mut registry = registry();
+        registry.register_pipeline(definition).unwrap();
+        let pipeline = registry.get("research").unwrap();
+        assert_eq!(pipeline.metadata().namespace.as_deref(), Some("pipeline"));
+        assert_eq!(pipeline.metadata().estimated_duration_ms, Some(30));
+
+        let output = pipeline.execute(ToolInput::new(json!({"topic": "rust"}))).await.unwrap();
+        assert_eq!(output.data["summary"], "contents of");
+        assert_eq!(output.data["sources"], json!(["https://a.test/rust", "https://b.test"]));
+        assert_eq!(output.data["cost"], "$0");
+        assert_eq!(output.metrics["steps_executed"], 3.0);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_errors() {
+        let registry = registry();
+
+        let unknown = PipelineDefinition::new("p", "unknown tool").with_step(PipelineStep::new("nope"));
+        assert!(matches!(PipelineTool::new(unknown, &registry), Err(ToolError::ConfigurationError { .. })));
+
+        let bad_path = PipelineDefinition::new("p", "bad path")
+            .with_step(PipelineStep::new("fetch").with_input(json!("$.input[")));
+        assert!(PipelineTool::new(bad_path, &registry).is_err());
+
+        let failing = PipelineDefinition::new("p", "failing step")
+            .with_step(PipelineStep::new("search"))
+            .with_step(PipelineStep::new("fail").with_name("explode"));
+        let pipeline = PipelineTool::new(failing, &registry).unwrap();
+        let error = pipeline.execute(ToolInput::new(json!({"q": "x"}))).await.unwrap_err();
+        assert!(error.to_string().contains("step 'explode'"));
+    }
+}
@@ -0,0 +1,157 @@
+// Tool exposure policies for AgentGraph
+// Decide which registered tools an agent may see, based on namespaces, tags, and capabilities
+
+use super::traits::ToolMetadata;
+use serde::{Deserialize, Serialize};
+
+/// Policy controlling which tools are exposed
+///
+/// Patterns match a tool by:
+/// - `*` for every tool
+/// - `ns:*` for every tool in a namespace
+/// - `ns:id` or `id` for a single tool
+/// - `#tag` for every tool carrying a tag
+///
+/// A tool is allowed when it matches at least one `allow` pattern and no `deny`
+/// pattern. Deny always wins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    /// Patterns of tools to expose, every tool by default
+    #[serde(default = "default_allow")]
+    pub allow: Vec<String>,
+    /// Patterns of tools to hide, taking precedence over `allow`
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Whether tools with side effects may be exposed
+    #[serde(default = "default_allow_side_effects")]
+    pub allow_side_effects: bool,
+}
+
+fn default_allow() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+fn default_allow_side_effects() -> bool {
+    true
+}
+
+impl Default for ToolPolicy {
+    fn default() -> Self {
+        Self::allow_all()
+    }
+}
+
+impl ToolPolicy {
+    /// Policy that exposes every tool
+    pub fn allow_all() -> Self {
+        Self {
+            allow: vec!["*".to_string()],
+            deny: Vec::new(),
+            allow_side_effects: true,
+        }
+    }
+
+    /// Policy that exposes nothing until patterns are allowed
+    pub fn deny_all() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            allow_side_effects: true,
+        }
+    }
+
+    /// Add an allow pattern
+    pub fn with_allow<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Add a deny pattern
+    pub fn with_deny<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// Set whether side-effecting tools may be exposed
+    pub fn with_side_effects(mut self, allowed: bool) -> Self {
+        self.allow_side_effects = allowed;
+        self
+    }
+
+    /// Check whether a tool is allowed by this policy
+    pub fn allows(&self, metadata: &ToolMetadata) -> bool {
+        if metadata.has_side_effects && !self.allow_side_effects {
+            return false;
+        }
+        if self.deny.iter().any(|pattern| pattern_matches(pattern, metadata)) {
+            return false;
+        }
+        self.allow.iter().any(|pattern| pattern_matches(pattern, metadata))
+    }
+}
+
+/// Check whether a single pattern matches a tool
+pub fn pattern_matches(pattern: &str, metadata: &ToolMetadata) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    if let Some(tag) = pattern.strip_prefix('#') {
+        return metadata.tags.iter().any(|t| t == tag);
+    }
+    if let Some(namespace) = pattern.strip_suffix(":*") {
+        return metadata.namespace.as_deref() == Some(namespace);
+    }
+    pattern == metadata.id || pattern == metadata.qualified_name()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fs_read() -> ToolMetadata {
+        ToolMetadata::new("file_read", "File Reader", "Read files")
+            .with_namespace("fs")
+            .with_tag("file")
+    }
+
+    fn fs_write() -> ToolMetadata {
+        ToolMetadata::new("file_write", "File Writer", "Write files")
+            .with_namespace("fs")
+            .with_tag("file")
+            .with_side_effects(true)
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        let tool = fs_read();
+        assert!(pattern_matches("*", &tool));
+        assert!(pattern_matches("fs:*", &tool));
+        assert!(pattern_matches("fs:file_read", &tool));
+        assert!(pattern_matches("file_read", &tool));
+        assert!(pattern_matches("#file", &tool));
+        assert!(!pattern_matches("web:*", &tool));
+        assert!(!pattern_matches("#network", &tool));
+    }
+
+    #[test]
+    fn test_deny_wins() {
+        let policy = ToolPolicy::deny_all()
+            .with_allow("fs:*")
+            .with_deny("fs:file_write");
+
+        assert!(policy.allows(&fs_read()));
+        assert!(!policy.allows(&fs_write()));
+        assert!(!ToolPolicy::deny_all().allows(&fs_read()));
+
+        let deny_only: ToolPolicy = serde_json::from_str(r#"{"deny": ["fs:file_write"]}"#).unwrap();
+        assert!(deny_only.allows(&fs_read()));
+        assert!(!deny_only.allows(&fs_write()));
+    }
+
+    #[test]
+    fn test_side_effect_filter() {
+        let policy = ToolPolicy::allow_all().with_side_effects(false);
+        assert!(policy.allows(&fs_read()));
+        assert!(!policy.allows(&fs_write()));
+    }
+}
@@ -1,5 +1,8 @@
 // Tool registry for managing and discovering tools
 
+use super::policy::{pattern_matches, ToolPolicy};
+use super::declarative::{DeclaredTool, ToolDefinition};
+use super::pipeline::{PipelineDefinition, PipelineTool};
 use super::traits::{Tool, ToolError, ToolResult};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -52,6 +55,92 @@ impl ToolRegistry {
         self.tools.get(tool_id).cloned()
     }
     
+    /// Get a tool by ID or namespaced name (`fs:file_read`)
+    pub fn resolve(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.get(name).or_else(|| {
+            self.tools
+                .values()
+                .find(|tool| tool.metadata().qualified_name() == name)
+                .cloned()
+        })
+    }
+    
+    /// Build a pipeline from tools already in this registry and register it
+    pub fn register_pipeline(&mut self, definition: PipelineDefinition) -> ToolResult<()> {
+        let pipeline = PipelineTool::new(definition, self)?;
+        self.register(pipeline)
+    }
+
+    /// Instantiate a declared tool and register it under `id`
+    pub fn register_declared(&mut self, id: &str, definition: ToolDefinition) -> ToolResult<()> {
+        self.register(DeclaredTool::new(id, definition)?)
+    }
+    
+    /// Get tools in a namespace
+    pub fn get_by_namespace(&self, namespace: &str) -> Vec<Arc<dyn Tool>> {
+        self.tools
+            .values()
+            .filter(|tool| tool.metadata().namespace.as_deref() == Some(namespace))
+            .cloned()
+            .collect()
+    }
+    
+    /// Get all namespaces in use
+    pub fn list_namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self.tools
+            .values()
+            .filter_map(|tool| tool.metadata().namespace.clone())
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        namespaces
+    }
+    
+    /// Tools matching a pattern (see `ToolPolicy` for pattern syntax)
+    pub fn matching(&self, pattern: &str) -> Vec<Arc<dyn Tool>> {
+        self.tools
+            .values()
+            .filter(|tool| pattern_matches(pattern, tool.metadata()))
+            .cloned()
+            .collect()
+    }
+    
+    /// Resolve the tools an agent may use.
+    ///
+    /// Role recommendations and explicitly configured tools (IDs, namespaced
+    /// names, or patterns) are merged, unknown names are skipped, and the result
+    /// is filtered through every policy, e.g. the agent's own and its tenant's.
+    pub fn resolve_tools(
+        &self,
+        role_tools: &[String],
+        explicit_tools: &[String],
+        policies: &[&ToolPolicy],
+    ) -> Vec<ResolvedTool> {
+        let mut resolved: HashMap<String, ResolvedTool> = HashMap::new();
+        
+        let sources = role_tools.iter().map(|name| (name, ToolSource::Role))
+            .chain(explicit_tools.iter().map(|name| (name, ToolSource::Explicit)));
+        
+        for (name, source) in sources {
+            for tool in self.matching(name) {
+                let metadata = tool.metadata();
+                if !policies.iter().all(|policy| policy.allows(metadata)) {
+                    continue;
+                }
+                resolved.insert(metadata.id.clone(), ResolvedTool {
+                    id: metadata.id.clone(),
+                    qualified_name: metadata.qualified_name(),
+                    source: source.clone(),
+                    tool,
+                });
+            }
+        }
+        
+        let mut tools: Vec<ResolvedTool> = resolved.into_values().collect();
+        tools.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+        tools
+    }
+    
     /// Check if a tool exists
     pub fn contains(&self, tool_id: &str) -> bool {
         self.tools.contains_key(tool_id)
@@ -152,6 +241,28 @@ pub struct RegistryStats {
     pub tools_by_category: HashMap<String, usize>,
 }
 
+/// Where a resolved tool came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolSource {
+    /// Recommended by the agent's role
+    Role,
+    /// Listed in the agent's configuration
+    Explicit,
+}
+
+/// A tool selected for an agent by `ToolRegistry::resolve_tools`
+#[derive(Debug, Clone)]
+pub struct ResolvedTool {
+    /// Tool ID, used as the function name exposed to the LLM
+    pub id: String,
+    /// Namespaced name
+    pub qualified_name: String,
+    /// Why the tool was included
+    pub source: ToolSource,
+    /// The tool itself
+    pub tool: Arc<dyn Tool>,
+}
+
 /// Builder for creating and configuring a tool registry
 #[derive(Debug)]
 pub struct ToolRegistryBuilder {
@@ -204,6 +315,12 @@ mod tests {
             }
             Self { metadata }
         }
+
+        fn namespaced(namespace: &str, id: &str, tags: Vec<&str>) -> Self {
+            let mut tool = Self::new(id, id, tags);
+            tool.metadata = tool.metadata.with_namespace(namespace);
+            tool
+        }
     }
 
     #[async_trait]
@@ -335,4 +452,41 @@ mod tests {
         assert!(registry.contains("test1"));
         assert!(registry.contains("test2"));
     }
+
+    #[test]
+    fn test_namespaces() {
+        let mut registry = ToolRegistry::new();
+        registry.register(TestTool::namespaced("fs", "read", vec!["file"])).unwrap();
+        registry.register(TestTool::namespaced("fs", "write", vec!["file"])).unwrap();
+        registry.register(TestTool::namespaced("web", "search", vec!["network"])).unwrap();
+
+        assert_eq!(registry.list_namespaces(), vec!["fs", "web"]);
+        assert_eq!(registry.get_by_namespace("fs").len(), 2);
+        assert!(registry.resolve("web:search").is_some());
+        assert!(registry.resolve("search").is_some());
+        assert!(registry.resolve("fs:search").is_none());
+    }
+
+    #[test]
+    fn test_resolve_tools_for_agent() {
+        let mut registry = ToolRegistry::new();
+        registry.register(TestTool::namespaced("fs", "read", vec!["file"])).unwrap();
+        registry.register(TestTool::namespaced("fs", "write", vec!["file"])).unwrap();
+        registry.register(TestTool::namespaced("web", "search", vec!["network"])).unwrap();
+
+        let role_tools = vec!["read".to_string(), "missing_tool".to_string()];
+        let explicit = vec!["web:*".to_string(), "fs:write".to_string()];
+
+        let resolved = registry.resolve_tools(&role_tools, &explicit, &[]);
+        let names: Vec<_> = resolved.iter().map(|t| t.qualified_name.as_str()).collect();
+        assert_eq!(names, vec!["fs:read", "fs:write", "web:search"]);
+        assert_eq!(resolved[0].source, ToolSource::Role);
+
+        // Tenant policy only permits file tools, agent policy hides writes
+        let tenant = ToolPolicy::deny_all().with_allow("#file");
+        let agent = ToolPolicy::allow_all().with_deny("fs:write");
+        let resolved = registry.resolve_tools(&role_tools, &explicit, &[&tenant, &agent]);
+        let names: Vec<_> = resolved.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(names, vec!["read"]);
+    }
 }
@@ -0,0 +1,179 @@
+// Incremental tool output
+// Streaming tools push chunks to subscribers while they are still running
+
+use super::traits::{Tool, ToolInput, ToolOutput, ToolResult};
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// A piece of output produced before the tool finishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolOutputChunk {
+    /// Tool that produced the chunk
+    pub tool_id: String,
+    /// Position of the chunk, starting at 0
+    pub sequence: u64,
+    /// Chunk payload
+    pub data: serde_json::Value,
+    /// When the chunk was produced
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Stream of chunks received from a tool
+pub type ToolChunkStream = Pin<Box<dyn Stream<Item = ToolOutputChunk> + Send>>;
+
+/// Channel a streaming tool writes its incremental output to
+///
+/// Cloning shares the same channel and sequence counter. Chunks sent while
+/// nobody is subscribed are dropped, so consumers subscribe before the tool
+/// starts.
+#[derive(Debug, Clone)]
+pub struct ToolOutputStream {
+    tool_id: String,
+    sender: broadcast::Sender<ToolOutputChunk>,
+    next_sequence: Arc<AtomicU64>,
+}
+
+impl ToolOutputStream {
+    /// Create a stream for a tool with the default buffer of 256 chunks
+    pub fn new(tool_id: &str) -> Self {
+        Self::with_capacity(tool_id, 256)
+    }
+
+    /// Create a stream buffering up to `capacity` unread chunks per subscriber
+    pub fn with_capacity(tool_id: &str, capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            tool_id: tool_id.to_string(),
+            sender,
+            next_sequence: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Tool this stream belongs to
+    pub fn tool_id(&self) -> &str {
+        &self.tool_id
+    }
+
+    /// Subscribe to chunks sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ToolOutputChunk> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribe and receive chunks as a `Stream`
+    ///
+    /// Chunks a slow consumer missed are skipped. The stream ends when every
+    /// clone of this `ToolOutputStream` has been dropped.
+    pub fn chunks(&self) -> ToolChunkStream {
+        let mut receiver = self.subscribe();
+        Box::pin(stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(chunk) => yield chunk,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Tool output consumer lagged, skipped {} chunks", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Send a chunk, returning its sequence number
+    pub fn send<T: Serialize>(&self, data: T) -> u64 {
+        let sequence = self.next_sequence.fetch_add(1, Ordering::SeqCst);
+        let chunk = ToolOutputChunk {
+            tool_id: self.tool_id.clone(),
+            sequence,
+            data: serde_json::to_value(data).unwrap_or(serde_json::Value::Null),
+            timestamp: chrono::Utc::now(),
+        };
+        // No subscribers is not an error; the chunk is simply not observed
+        let _ = self.sender.send(chunk);
+        sequence
+    }
+
+    /// Number of chunks sent so far
+    pub fn chunks_sent(&self) -> u64 {
+        self.next_sequence.load(Ordering::SeqCst)
+    }
+}
+
+/// Tool that can report output incrementally
+///
+/// Implementors usually forward `Tool::execute` to `execute_streaming` with a
+/// fresh `ToolOutputStream`, so the tool still works where nothing listens.
+#[async_trait]
+pub trait StreamingTool: Tool {
+    /// Execute the tool, sending chunks to `output` as they become available
+    ///
+    /// The returned output is the final result, which may aggregate the chunks.
+    async fn execute_streaming(&self, input: ToolInput, output: ToolOutputStream) -> ToolResult<ToolOutput>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::traits::ToolMetadata;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    #[derive(Debug)]
+    struct CrawlerTool {
+        metadata: ToolMetadata,
+    }
+
+    #[async_trait]
+    impl Tool for CrawlerTool {
+        fn metadata(&self) -> &ToolMetadata {
+            &self.metadata
+        }
+
+        async fn execute(&self, input: ToolInput) -> ToolResult<ToolOutput> {
+            self.execute_streaming(input, ToolOutputStream::new(&self.metadata.id)).await
+        }
+    }
+
+    #[async_trait]
+    impl StreamingTool for CrawlerTool {
+        async fn execute_streaming(&self, input: ToolInput, output: ToolOutputStream) -> ToolResult<ToolOutput> {
+            let pages = input.data["pages"].as_u64().unwrap_or(0);
+            for page in 0..pages {
+                output.send(json!({"page": page}));
+            }
+            Ok(ToolOutput::new(json!({"crawled": pages})))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chunks_arrive_in_order() {
+        let tool = CrawlerTool {
+            metadata: ToolMetadata::new("crawler", "Crawler", "Crawl pages"),
+        };
+        let output = ToolOutputStream::new("crawler");
+        let chunks = output.chunks();
+
+        let result = tool.execute_streaming(ToolInput::new(json!({"pages": 3})), output).await.unwrap();
+        let received: Vec<ToolOutputChunk> = chunks.collect().await;
+
+        assert_eq!(result.data["crawled"], 3);
+        assert_eq!(received.len(), 3);
+        assert_eq!(received.iter().map(|c| c.sequence).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(received[2].data["page"], 2);
+        assert_eq!(received[0].tool_id, "crawler");
+    }
+
+    #[tokio::test]
+    async fn test_plain_execute_without_subscribers() {
+        let tool = CrawlerTool {
+            metadata: ToolMetadata::new("crawler", "Crawler", "Crawl pages"),
+        };
+        let result = tool.execute(ToolInput::new(json!({"pages": 2}))).await.unwrap();
+        assert_eq!(result.data["crawled"], 2);
+    }
+}
@@ -67,6 +67,13 @@ pub enum ToolError {
         /// Error message
         message: String
     },
+
+    /// Tool operation blocked by a sandbox or policy
+    #[error("Tool permission denied: {message}")]
+    PermissionDenied {
+        /// Error message
+        message: String
+    },
 }
 
 /// Input data for tool execution
@@ -172,6 +179,9 @@ impl ToolOutput {
 pub struct ToolMetadata {
     /// Unique identifier for the tool
     pub id: String,
+    /// Namespace grouping related tools, e.g. `fs` or `web`
+    #[serde(default)]
+    pub namespace: Option<String>,
     /// Human-readable name
     pub name: String,
     /// Description of what the tool does
@@ -192,6 +202,12 @@ pub struct ToolMetadata {
     pub has_side_effects: bool,
     /// Estimated execution time in milliseconds
     pub estimated_duration_ms: Option<u64>,
+    /// Output returned instead of executing the tool during dry runs
+    #[serde(default)]
+    pub sample_output: Option<serde_json::Value>,
+    /// Concurrency pools nodes running the tool take a permit from
+    #[serde(default)]
+    pub concurrency_pools: Vec<String>,
 }
 
 impl ToolMetadata {
@@ -199,6 +215,7 @@ impl ToolMetadata {
     pub fn new(id: &str, name: &str, description: &str) -> Self {
         Self {
             id: id.to_string(),
+            namespace: None,
             name: name.to_string(),
             description: description.to_string(),
             version: "1.0.0".to_string(),
@@ -209,6 +226,28 @@ impl ToolMetadata {
             deterministic: true,
             has_side_effects: false,
             estimated_duration_ms: None,
+            sample_output: None,
+            concurrency_pools: Vec::new(),
+        }
+    }
+    
+    /// Set the input JSON Schema
+    pub fn with_input_schema(mut self, schema: serde_json::Value) -> Self {
+        self.input_schema = Some(schema);
+        self
+    }
+    
+    /// Set the namespace
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+    
+    /// Namespaced name, e.g. `fs:file_read`, or the bare ID without a namespace
+    pub fn qualified_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}:{}", namespace, self.id),
+            None => self.id.clone(),
         }
     }
     
@@ -247,6 +286,20 @@ impl ToolMetadata {
         self.estimated_duration_ms = Some(duration_ms);
         self
     }
+    
+    /// Set the output dry runs use in place of executing the tool
+    pub fn with_sample_output(mut self, output: serde_json::Value) -> Self {
+        self.sample_output = Some(output);
+        self
+    }
+    
+    /// Run the tool within concurrency pool `pool`
+    pub fn with_concurrency_pool(mut self, pool: &str) -> Self {
+        if !self.concurrency_pools.iter().any(|name| name == pool) {
+            self.concurrency_pools.push(pool.to_string());
+        }
+        self
+    }
 }
 
 /// Core trait that all tools must implement
@@ -348,5 +401,7 @@ mod tests {
         assert!(!metadata.deterministic);
         assert!(metadata.has_side_effects);
         assert_eq!(metadata.estimated_duration_ms, Some(1000));
+        assert_eq!(metadata.qualified_name(), "test_tool");
+        assert_eq!(metadata.with_namespace("test").qualified_name(), "test:test_tool");
     }
 }
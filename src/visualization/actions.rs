@@ -0,0 +1,244 @@
+//! Authorization and audit of the Studio actions that change executions
+//! Each action needs a permission granted to the user's role, and is logged with its before and after state
+
+use crate::enterprise::audit::{AuditEvent, AuditEventType, AuditLevel};
+use crate::enterprise::security::{Permission, Role};
+use crate::sha256_hex;
+use crate::visualization::auth::{StudioRole, StudioUser};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Entry of an approval request's graph context naming the graph it belongs to
+pub const APPROVAL_GRAPH_KEY: &str = "graph";
+
+/// An action that changes an execution or starts one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StudioAction {
+    /// Cancel a running execution
+    Cancel,
+    /// Pause a running execution
+    Pause,
+    /// Resume a paused execution
+    Resume,
+    /// Rerun a failed execution with the state it failed in
+    Retry,
+    /// Rerun a failed execution with an edited state
+    EditState,
+    /// Start a registered graph by hand
+    RunGraph,
+    /// Send a traced LLM call again, which is billed
+    ReplayLlmCall,
+    /// Answer an approval request
+    Approve,
+}
+
+impl StudioAction {
+    /// Name used in permissions and audit events
+    pub fn name(self) -> &'static str {
+        match self {
+            StudioAction::Cancel => "cancel",
+            StudioAction::Pause => "pause",
+            StudioAction::Resume => "resume",
+            StudioAction::Retry => "retry",
+            StudioAction::EditState => "edit_state",
+            StudioAction::RunGraph => "run",
+            StudioAction::ReplayLlmCall => "replay",
+            StudioAction::Approve => "approve",
+        }
+    }
+
+    /// Permission needed to take the action
+    pub fn permission(self) -> Permission {
+        let resource = match self {
+            StudioAction::RunGraph => "graph",
+            StudioAction::ReplayLlmCall => "llm",
+            StudioAction::Approve => "approval",
+            _ => "execution",
+        };
+        Permission::new(resource.to_string(), self.name().to_string())
+    }
+}
+
+/// Which Studio users may take which actions
+///
+/// Users get the permissions of their Studio role plus those of any roles
+/// assigned to them by ID. Permissions scoped to a tenant only apply to
+/// users of that tenant.
+#[derive(Debug, Clone)]
+pub struct StudioRbac {
+    role_permissions: HashMap<StudioRole, Vec<Permission>>,
+    user_roles: HashMap<String, Vec<Role>>,
+}
+
+impl Default for StudioRbac {
+    /// Operators may take every action; viewers none
+    fn default() -> Self {
+        let operator = ["execution", "graph", "llm", "approval"]
+            .into_iter()
+            .map(|resource| Permission::new(resource.to_string(), "*".to_string()))
+            .collect();
+        Self {
+            role_permissions: HashMap::from([(StudioRole::Viewer, Vec::new()), (StudioRole::Operator, operator)]),
+            user_roles: HashMap::new(),
+        }
+    }
+}
+
+impl StudioRbac {
+    /// No permissions for anyone
+    pub fn empty() -> Self {
+        Self {
+            role_permissions: HashMap::new(),
+            user_roles: HashMap::new(),
+        }
+    }
+
+    /// Grant `permission` to every user with `role`
+    pub fn grant(mut self, role: StudioRole, permission: Permission) -> Self {
+        self.role_permissions.entry(role).or_default().push(permission);
+        self
+    }
+
+    /// Give one user the permissions of an enterprise role
+    pub fn assign(mut self, user_id: impl Into<String>, role: Role) -> Self {
+        self.user_roles.entry(user_id.into()).or_default().push(role);
+        self
+    }
+
+    /// Whether `user` may take `action`
+    pub fn allows(&self, user: &StudioUser, action: StudioAction) -> bool {
+        let mut wanted = action.permission();
+        wanted.scope = user.tenant.clone();
+        let from_role = self.role_permissions.get(&user.role).into_iter().flatten();
+        let from_user = self.user_roles.get(&user.id).into_iter().flatten().flat_map(|role| &role.permissions);
+        from_role.chain(from_user).any(|granted| granted.matches(&wanted))
+    }
+}
+
+/// What an action was taken on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionTarget {
+    /// e.g. `execution:<id>` or `graph:<name>`
+    pub resource: String,
+    /// Graph the target belongs to, which decides its tenant
+    pub graph: Option<String>,
+}
+
+impl ActionTarget {
+    /// An execution of `graph`, if known
+    pub fn execution(execution_id: impl std::fmt::Display, graph: Option<&str>) -> Self {
+        Self {
+            resource: format!("execution:{}", execution_id),
+            graph: graph.map(String::from),
+        }
+    }
+
+    /// A registered graph
+    pub fn graph(name: &str) -> Self {
+        Self {
+            resource: format!("graph:{}", name),
+            graph: Some(name.to_string()),
+        }
+    }
+
+    /// An LLM call of a traced execution
+    pub fn llm_call(execution_id: &str, index: usize, graph: &str) -> Self {
+        Self {
+            resource: format!("execution:{}/llm_call:{}", execution_id, index),
+            graph: Some(graph.to_string()),
+        }
+    }
+
+    /// An approval request
+    pub fn approval(request_id: &str, graph: Option<&str>) -> Self {
+        Self {
+            resource: format!("approval:{}", request_id),
+            graph: graph.map(String::from),
+        }
+    }
+}
+
+/// Where an execution stood before or after an action
+///
+/// States are referenced by checkpoint or by hash, so the audit log does
+/// not copy their contents.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRef {
+    /// Execution the snapshot is of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_id: Option<String>,
+    /// Latest checkpoint the execution saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint_id: Option<uuid::Uuid>,
+    /// Status, e.g. `running` or `pending`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Steps the execution had taken
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<u64>,
+    /// Node the execution was at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    /// SHA-256 of the state as JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_sha256: Option<String>,
+}
+
+impl SnapshotRef {
+    /// Snapshot of an execution
+    pub fn execution(execution_id: impl std::fmt::Display) -> Self {
+        Self {
+            execution_id: Some(execution_id.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Reference the state by its hash
+    pub fn with_state(mut self, state: &serde_json::Value) -> Self {
+        self.state_sha256 = Some(sha256_hex(state.to_string()));
+        self
+    }
+}
+
+/// Audit event for an action taken, or attempted and failed
+pub(crate) fn action_event(
+    user: &StudioUser,
+    tenant: Option<String>,
+    action: StudioAction,
+    target: &ActionTarget,
+    before: Option<&SnapshotRef>,
+    outcome: Result<Option<&SnapshotRef>, &str>,
+) -> AuditEvent {
+    let description = match &outcome {
+        Ok(_) => format!("{} took action {} on {}", user.id, action.name(), target.resource),
+        Err(error) => format!("{} failed to take action {} on {}: {}", user.id, action.name(), target.resource, error),
+    };
+    let mut event = AuditEvent::new(AuditEventType::GraphExecution, format!("studio.{}", action.name()), description)
+        .with_user(user.id.clone())
+        .with_resource(target.resource.clone())
+        .with_level(if outcome.is_ok() { AuditLevel::Info } else { AuditLevel::Warning })
+        .with_data("role".to_string(), user.role)
+        .with_data("succeeded".to_string(), outcome.is_ok());
+    if let Some(tenant) = tenant {
+        event = event.with_tenant(tenant);
+    }
+    if let Some(before) = before {
+        event = event.with_data("before".to_string(), before);
+    }
+    match outcome {
+        Ok(Some(after)) => event.with_data("after".to_string(), after),
+        Ok(None) => event,
+        Err(error) => event.with_data("error".to_string(), error),
+    }
+}
+
+/// Audit event for an action refused for lack of permission
+pub(crate) fn denied_event(user: &StudioUser, tenant: Option<String>, action: StudioAction, target: &ActionTarget) -> AuditEvent {
+    let mut event = AuditEvent::permission_denied(user.id.clone(), target.resource.clone(), format!("studio.{}", action.name()))
+        .with_data("role".to_string(), user.role);
+    if let Some(tenant) = tenant {
+        event = event.with_tenant(tenant);
+    }
+    event
+}
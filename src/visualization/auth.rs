@@ -0,0 +1,563 @@
+//! Authentication and per-user views for the Studio API
+//! API keys or OIDC bearer tokens, tenant-scoped data and viewer/operator roles
+
+use crate::enterprise::api_keys::{ApiKeyError, ApiKeyManager};
+use crate::enterprise::audit::{AuditEvent, AuditLogger};
+use crate::sha256_hex;
+use crate::visualization::actions::{self, ActionTarget, SnapshotRef, StudioAction, StudioRbac};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// What a Studio user may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StudioRole {
+    /// Read traces, workflows, artifacts and metrics
+    Viewer,
+    /// Everything a viewer may, plus act on executions, e.g. cancel or resume them
+    Operator,
+}
+
+/// An authenticated Studio user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StudioUser {
+    /// User ID, or `api_key:<key id>` for keys issued by an `ApiKeyManager`
+    pub id: String,
+    /// Tenant whose data the user sees; `None` sees every tenant
+    pub tenant: Option<String>,
+    /// What the user may do
+    pub role: StudioRole,
+}
+
+impl StudioUser {
+    /// A user confined to one tenant's data
+    pub fn new(id: impl Into<String>, tenant: impl Into<String>, role: StudioRole) -> Self {
+        Self {
+            id: id.into(),
+            tenant: Some(tenant.into()),
+            role,
+        }
+    }
+
+    /// A user who sees every tenant's data
+    pub fn unscoped(id: impl Into<String>, role: StudioRole) -> Self {
+        Self {
+            id: id.into(),
+            tenant: None,
+            role,
+        }
+    }
+}
+
+/// OpenID Connect login for the Studio
+///
+/// Bearer tokens are checked against the provider's userinfo endpoint, so
+/// revoked tokens stop working once their cache entry expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OidcConfig {
+    /// Issuer URL, e.g. `https://login.example.com/realms/acme`
+    pub issuer: String,
+    /// Userinfo endpoint; discovered from the issuer when unset
+    pub userinfo_endpoint: Option<String>,
+    /// Claim naming the user's tenant; users without it are refused.
+    /// `None` lets every user see every tenant.
+    pub tenant_claim: Option<String>,
+    /// Claim listing the user's roles
+    pub role_claim: String,
+    /// Role claim values that make a user an operator; everyone else is a viewer
+    pub operator_roles: Vec<String>,
+    /// How long a checked token is trusted before it is checked again
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            issuer: String::new(),
+            userinfo_endpoint: None,
+            tenant_claim: Some("tenant".to_string()),
+            role_claim: "roles".to_string(),
+            operator_roles: vec!["studio-operator".to_string()],
+            cache_ttl_secs: 300,
+        }
+    }
+}
+
+impl OidcConfig {
+    /// Accept tokens issued by `issuer`, finding its userinfo endpoint by discovery
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            ..Self::default()
+        }
+    }
+
+    /// User described by a userinfo response
+    fn user(&self, claims: &serde_json::Value) -> Result<StudioUser, AuthRejection> {
+        let id = claims["sub"].as_str().ok_or_else(|| AuthRejection::unauthorized("Token has no subject"))?;
+        let tenant = match &self.tenant_claim {
+            Some(claim) => Some(
+                claims[claim.as_str()]
+                    .as_str()
+                    .ok_or_else(|| AuthRejection::forbidden("User belongs to no tenant"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let roles: Vec<&str> = match &claims[self.role_claim.as_str()] {
+            serde_json::Value::String(role) => role.split_whitespace().collect(),
+            serde_json::Value::Array(roles) => roles.iter().filter_map(|role| role.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        let operator = roles.iter().any(|role| self.operator_roles.iter().any(|op| op == role));
+        Ok(StudioUser {
+            id: id.to_string(),
+            tenant,
+            role: if operator { StudioRole::Operator } else { StudioRole::Viewer },
+        })
+    }
+}
+
+/// Who may use the Studio API
+#[derive(Debug, Default)]
+pub struct StudioAuth {
+    /// Users by SHA-256 of their API key, so keys are not kept in memory
+    api_keys: HashMap<String, StudioUser>,
+    /// Issued keys, checked before the static ones
+    key_manager: Option<Arc<ApiKeyManager>>,
+    oidc: Option<OidcConfig>,
+    client: reqwest::Client,
+    /// Users of recently checked OIDC tokens, by token hash
+    sessions: parking_lot::Mutex<HashMap<String, (StudioUser, Instant)>>,
+    /// Userinfo endpoint found by discovery
+    discovered_userinfo: tokio::sync::OnceCell<String>,
+}
+
+impl StudioAuth {
+    /// Create an authenticator accepting nothing until keys or OIDC are added
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let requests presenting `key` in as `user`
+    pub fn with_api_key(mut self, key: impl AsRef<str>, user: StudioUser) -> Self {
+        self.api_keys.insert(sha256_hex(key.as_ref()), user);
+        self
+    }
+
+    /// Accept keys issued by `manager`, with their tenant, role and rate limit
+    ///
+    /// A request with such a key acts as user `api_key:<key id>`, since key
+    /// names need not be unique.
+    pub fn with_api_key_manager(mut self, manager: Arc<ApiKeyManager>) -> Self {
+        self.key_manager = Some(manager);
+        self
+    }
+
+    /// Accept access tokens issued by an OIDC provider
+    pub fn with_oidc(mut self, config: OidcConfig) -> Self {
+        self.oidc = Some(config);
+        self
+    }
+
+    /// User presenting a bearer token
+    pub async fn authenticate(&self, token: &str) -> Result<StudioUser, AuthRejection> {
+        if let Some(manager) = &self.key_manager {
+            match manager.authenticate(token).await {
+                Ok(key) => {
+                    return Ok(StudioUser {
+                        id: format!("api_key:{}", key.id),
+                        tenant: key.tenant,
+                        role: key.role,
+                    })
+                }
+                Err(ApiKeyError::Unknown) => {}
+                Err(ApiKeyError::RateLimited { retry_after_secs, .. }) => {
+                    return Err(AuthRejection::too_many_requests(retry_after_secs));
+                }
+                Err(e) => return Err(AuthRejection::unauthorized(e.to_string())),
+            }
+        }
+        let hash = sha256_hex(token);
+        if let Some(user) = self.api_keys.get(&hash) {
+            return Ok(user.clone());
+        }
+        let Some(oidc) = &self.oidc else {
+            return Err(AuthRejection::unauthorized("Invalid API key"));
+        };
+
+        if let Some((user, checked)) = self.sessions.lock().get(&hash) {
+            if checked.elapsed() < Duration::from_secs(oidc.cache_ttl_secs) {
+                return Ok(user.clone());
+            }
+        }
+        let endpoint = self.userinfo_endpoint(oidc).await?;
+        let response = self.client
+            .get(endpoint)
+            .bearer_auth(token)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| AuthRejection::unavailable(format!("Identity provider unreachable: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(AuthRejection::unauthorized("Invalid or expired token"));
+        }
+        let claims: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| AuthRejection::unavailable(format!("Invalid userinfo response: {}", e)))?;
+        let user = oidc.user(&claims)?;
+
+        let mut sessions = self.sessions.lock();
+        let ttl = Duration::from_secs(oidc.cache_ttl_secs);
+        sessions.retain(|_, (_, checked)| checked.elapsed() < ttl);
+        sessions.insert(hash, (user.clone(), Instant::now()));
+        Ok(user)
+    }
+
+    async fn userinfo_endpoint(&self, oidc: &OidcConfig) -> Result<String, AuthRejection> {
+        if let Some(endpoint) = &oidc.userinfo_endpoint {
+            return Ok(endpoint.clone());
+        }
+        self.discovered_userinfo
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", oidc.issuer.trim_end_matches('/'));
+                let document: serde_json::Value = async { self.client.get(&url).send().await?.error_for_status()?.json().await }
+                    .await
+                    .map_err(|e| AuthRejection::unavailable(format!("OIDC discovery failed: {}", e)))?;
+                document["userinfo_endpoint"]
+                    .as_str()
+                    .map(String::from)
+                    .ok_or_else(|| AuthRejection::unavailable("Identity provider has no userinfo endpoint"))
+            })
+            .await
+            .cloned()
+    }
+}
+
+/// Access control shared by the Studio routes
+#[derive(Debug, Default)]
+pub struct StudioAccess {
+    /// `None` leaves the API open, for local development
+    auth: parking_lot::RwLock<Option<Arc<StudioAuth>>>,
+    /// Tenant each workflow belongs to
+    workflow_tenants: parking_lot::RwLock<HashMap<String, String>>,
+    /// Who may take which actions
+    rbac: parking_lot::RwLock<StudioRbac>,
+    /// Where actions and refused attempts are recorded
+    audit_logger: parking_lot::RwLock<Option<Arc<AuditLogger>>>,
+}
+
+impl StudioAccess {
+    /// Authenticate every request with `auth`
+    pub fn set_auth(&self, auth: Arc<StudioAuth>) {
+        *self.auth.write() = Some(auth);
+    }
+
+    /// Whether requests go unauthenticated
+    pub fn is_open(&self) -> bool {
+        self.auth.read().is_none()
+    }
+
+    /// Show a workflow's traces and artifacts to its tenant's users only
+    pub fn assign_tenant(&self, workflow_id: impl Into<String>, tenant: impl Into<String>) {
+        self.workflow_tenants.write().insert(workflow_id.into(), tenant.into());
+    }
+
+    /// Decide who may cancel, retry, run and approve; operators may do
+    /// everything by default
+    pub fn set_rbac(&self, rbac: StudioRbac) {
+        *self.rbac.write() = rbac;
+    }
+
+    /// Record every action, and every attempt refused for lack of permission
+    pub fn set_audit_logger(&self, logger: Arc<AuditLogger>) {
+        *self.audit_logger.write() = Some(logger);
+    }
+
+    /// Tenant an action is recorded under: the user's, else the target graph's
+    fn action_tenant(&self, user: &StudioUser, target: &ActionTarget) -> Option<String> {
+        user.tenant.clone().or_else(|| {
+            let graph = target.graph.as_ref()?;
+            self.workflow_tenants.read().get(graph).cloned()
+        })
+    }
+
+    async fn audit(&self, event: AuditEvent) {
+        let logger = self.audit_logger.read().clone();
+        if let Some(logger) = logger {
+            if let Err(e) = logger.log_event(event).await {
+                tracing::warn!("Failed to record Studio action audit event: {}", e);
+            }
+        }
+    }
+
+    /// Whether `user` may see the data of `workflow_id`
+    ///
+    /// Workflows not assigned to a tenant are only visible to unscoped users.
+    pub fn can_view(&self, user: &StudioUser, workflow_id: &str) -> bool {
+        match &user.tenant {
+            None => true,
+            Some(tenant) => self.workflow_tenants.read().get(workflow_id) == Some(tenant),
+        }
+    }
+
+    async fn session(self: Arc<Self>, authorization: Option<String>, access_token: Option<String>) -> Result<StudioSession, Rejection> {
+        let auth = self.auth.read().clone();
+        let Some(auth) = auth else {
+            let user = StudioUser::unscoped("anonymous", StudioRole::Operator);
+            return Ok(StudioSession { user, access: self });
+        };
+        let token = authorization
+            .as_deref()
+            .and_then(|header| header.strip_prefix("Bearer ").or_else(|| header.strip_prefix("bearer ")))
+            .map(str::trim)
+            .map(String::from)
+            .or(access_token)
+            .ok_or_else(|| warp::reject::custom(AuthRejection::unauthorized("Missing bearer token")))?;
+        let user = auth.authenticate(&token).await.map_err(warp::reject::custom)?;
+        Ok(StudioSession { user, access: self })
+    }
+}
+
+/// The user behind a Studio request
+#[derive(Debug, Clone)]
+pub struct StudioSession {
+    pub user: StudioUser,
+    access: Arc<StudioAccess>,
+}
+
+impl StudioSession {
+    /// Whether the user may see the data of `workflow_id`
+    pub fn can_view(&self, workflow_id: &str) -> bool {
+        self.access.can_view(&self.user, workflow_id)
+    }
+
+    /// Refuse users below `role`
+    pub fn require(&self, role: StudioRole) -> Result<(), Rejection> {
+        if self.user.role >= role {
+            Ok(())
+        } else {
+            Err(warp::reject::custom(AuthRejection::forbidden("Operator role required")))
+        }
+    }
+
+    /// Refuse users not allowed to take `action`, recording the refusal
+    pub async fn authorize(&self, action: StudioAction, target: &ActionTarget) -> Result<(), Rejection> {
+        if self.access.rbac.read().allows(&self.user, action) {
+            return Ok(());
+        }
+        let tenant = self.access.action_tenant(&self.user, target);
+        self.access.audit(actions::denied_event(&self.user, tenant, action, target)).await;
+        Err(warp::reject::custom(AuthRejection::forbidden(format!("Not allowed to {}", action.name()))))
+    }
+
+    /// Record an action taken on `target`, with where it stood before and after
+    pub async fn record(
+        &self,
+        action: StudioAction,
+        target: &ActionTarget,
+        before: Option<&SnapshotRef>,
+        outcome: Result<Option<&SnapshotRef>, &str>,
+    ) {
+        let tenant = self.access.action_tenant(&self.user, target);
+        self.access.audit(actions::action_event(&self.user, tenant, action, target, before, outcome)).await;
+    }
+
+    /// Refuse users confined to a tenant, for data spanning every tenant
+    pub fn require_unscoped(&self) -> Result<(), Rejection> {
+        match self.user.tenant {
+            None => Ok(()),
+            Some(_) => Err(warp::reject::custom(AuthRejection::forbidden("Not available to tenant users"))),
+        }
+    }
+}
+
+/// Authenticate a request from its `Authorization: Bearer` header, or its
+/// `access_token` query parameter for WebSocket clients, which cannot set headers
+pub fn with_session(access: Arc<StudioAccess>) -> impl Filter<Extract = (StudioSession,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(move |authorization: Option<String>, mut query: HashMap<String, String>| {
+            access.clone().session(authorization, query.remove("access_token"))
+        })
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone)]
+pub struct AuthRejection {
+    /// 401, 403, 429 or 503
+    pub status: StatusCode,
+    /// Shown to the client
+    pub message: String,
+    /// Seconds a rate-limited client should wait
+    pub retry_after: Option<u64>,
+}
+
+impl AuthRejection {
+    fn unauthorized(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::UNAUTHORIZED, message: message.into(), retry_after: None }
+    }
+
+    fn forbidden(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::FORBIDDEN, message: message.into(), retry_after: None }
+    }
+
+    fn unavailable(message: impl Into<String>) -> Self {
+        Self { status: StatusCode::SERVICE_UNAVAILABLE, message: message.into(), retry_after: None }
+    }
+
+    fn too_many_requests(retry_after: u64) -> Self {
+        Self {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            message: "API key rate limit exceeded".to_string(),
+            retry_after: Some(retry_after),
+        }
+    }
+}
+
+impl warp::reject::Reject for AuthRejection {}
+
+/// Turn authentication rejections into JSON errors with their status
+pub async fn handle_rejection(rejection: Rejection) -> Result<warp::reply::Response, Rejection> {
+    match rejection.find::<AuthRejection>() {
+        Some(refused) => {
+            let body = warp::reply::json(&serde_json::json!({"error": refused.message}));
+            let mut response = warp::reply::with_status(body, refused.status).into_response();
+            if refused.status == StatusCode::UNAUTHORIZED {
+                response.headers_mut().insert(
+                    warp::http::header::WWW_AUTHENTICATE,
+                    warp::http::HeaderValue::from_static("Bearer"),
+                );
+            }
+            if let Some(retry_after) = refused.retry_after {
+                response.headers_mut().insert(warp::http::header::RETRY_AFTER, retry_after.into());
+            }
+            Ok(response)
+        }
+        None => Err(rejection),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sessions_are_scoped_by_key_tenant_and_role() {
+        let auth = StudioAuth::new()
+            .with_api_key("acme-viewer-key", StudioUser::new("acme-dashboard", "acme", StudioRole::Viewer))
+            .with_api_key("ops-key", StudioUser::unscoped("ops", StudioRole::Operator));
+        let access = Arc::new(StudioAccess::default());
+        access.set_auth(Arc::new(auth));
+        access.assign_tenant("billing", "acme");
+        access.assign_tenant("search", "globex");
+
+        let route = warp::path("traces")
+            .and(with_session(access.clone()))
+            .map(|session: StudioSession| {
+                let visible: Vec<&str> = ["billing", "search", "unassigned"]
+                    .into_iter()
+                    .filter(|workflow| session.can_view(workflow))
+                    .collect();
+                warp::reply::json(&visible)
+            })
+            .recover(handle_rejection);
+
+        let response = warp::test::request().path("/traces").reply(&route).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = warp::test::request()
+            .path("/traces")
+            .header("authorization", "Bearer wrong")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = warp::test::request()
+            .path("/traces")
+            .header("authorization", "Bearer acme-viewer-key")
+            .reply(&route)
+            .await;
+        assert_eq!(response.body().as_ref(), br#"["billing"]"#);
+        let response = warp::test::request().path("/traces?access_token=ops-key").reply(&route).await;
+        assert_eq!(response.body().as_ref(), br#"["billing","search","unassigned"]"#);
+
+        let session = access.clone().session(Some("Bearer acme-viewer-key".to_string()), None).await.unwrap();
+        assert!(session.require(StudioRole::Viewer).is_ok());
+        assert!(session.require(StudioRole::Operator).is_err());
+        assert!(session.require_unscoped().is_err());
+
+        let oidc = OidcConfig::new("https://login.example.com");
+        let user = oidc.user(&serde_json::json!({"sub": "u1", "tenant": "acme", "roles": ["studio-operator"]})).unwrap();
+        assert_eq!(user, StudioUser::new("u1", "acme", StudioRole::Operator));
+        assert!(oidc.user(&serde_json::json!({"sub": "u2", "roles": []})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_issued_api_keys_carry_scope_and_rate_limit() {
+        use crate::enterprise::api_keys::ApiKeySpec;
+
+        let keys = Arc::new(ApiKeyManager::new());
+        let issued = keys.issue(ApiKeySpec::new("acme-ci", "acme", StudioRole::Operator).with_rate_limit(1)).await;
+        let access = Arc::new(StudioAccess::default());
+        access.set_auth(Arc::new(StudioAuth::new().with_api_key_manager(keys.clone())));
+        let route = warp::path("me")
+            .and(with_session(access))
+            .map(|session: StudioSession| warp::reply::json(&session.user))
+            .recover(handle_rejection);
+
+        let request = || warp::test::request().path("/me").header("authorization", format!("Bearer {}", issued.secret));
+        let user: StudioUser = serde_json::from_slice(request().reply(&route).await.body()).unwrap();
+        let id = format!("api_key:{}", issued.key.id);
+        assert_eq!(user, StudioUser::new(id, "acme", StudioRole::Operator));
+        let response = request().reply(&route).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key("retry-after"));
+
+        keys.revoke(&issued.key.id).await.unwrap();
+        assert_eq!(request().reply(&route).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_actions_are_authorized_and_audited() {
+        use crate::enterprise::security::Permission;
+        use std::time::SystemTime;
+
+        let logger = Arc::new(AuditLogger::in_memory());
+        let access = Arc::new(StudioAccess::default());
+        access.set_auth(Arc::new(
+            StudioAuth::new()
+                .with_api_key("viewer-key", StudioUser::new("acme-viewer", "acme", StudioRole::Viewer))
+                .with_api_key("operator-key", StudioUser::new("acme-operator", "acme", StudioRole::Operator)),
+        ));
+        access.assign_tenant("billing", "acme");
+        access.set_audit_logger(logger.clone());
+        access.set_rbac(
+            StudioRbac::default().grant(StudioRole::Viewer, Permission::new("approval".to_string(), "approve".to_string()).with_scope("acme".to_string())),
+        );
+        let session = |key: &str| access.clone().session(Some(format!("Bearer {}", key)), None);
+        let viewer = session("viewer-key").await.unwrap();
+        let operator = session("operator-key").await.unwrap();
+
+        let execution = ActionTarget::execution("e1", Some("billing"));
+        assert!(viewer.authorize(StudioAction::Cancel, &execution).await.is_err());
+        assert!(viewer.authorize(StudioAction::Approve, &ActionTarget::approval("a1", Some("billing"))).await.is_ok());
+        assert!(operator.authorize(StudioAction::Cancel, &execution).await.is_ok());
+        let before = SnapshotRef { status: Some("running".to_string()), ..SnapshotRef::execution("e1") };
+        let after = SnapshotRef { status: Some("stopping".to_string()), ..SnapshotRef::execution("e1") };
+        operator.record(StudioAction::Cancel, &execution, Some(&before), Ok(Some(&after))).await;
+
+        let events = logger.events(SystemTime::UNIX_EPOCH, SystemTime::now(), Some("acme")).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].user_id.as_deref(), Some("acme-viewer"));
+        assert_eq!(events[0].resource.as_deref(), Some("execution:e1"));
+        assert_eq!(events[1].action, "studio.cancel");
+        assert_eq!(events[1].data["before"]["status"], "running");
+        assert_eq!(events[1].data["after"]["status"], "stopping");
+    }
+}
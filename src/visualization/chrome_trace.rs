@@ -0,0 +1,404 @@
+//! Export of execution traces to the Chrome `trace_event` format
+//! The output opens in chrome://tracing, Perfetto and other profiling UIs
+
+use crate::error::{GraphError, GraphResult};
+use crate::visualization::{ExecutionTrace, VisualEventType, VisualExecutionEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Process ID used for every event; one trace is one execution
+const PID: u32 = 1;
+
+/// File formats a trace can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceExportFormat {
+    /// Chrome `trace_event` JSON
+    Chrome,
+    /// Perfetto, which reads the same JSON as Chrome
+    Perfetto,
+}
+
+impl FromStr for TraceExportFormat {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chrome" | "trace_event" => Ok(Self::Chrome),
+            "perfetto" => Ok(Self::Perfetto),
+            other => Err(GraphError::validation_error(format!(
+                "Unknown trace format '{}', expected 'chrome' or 'perfetto'",
+                other
+            ))),
+        }
+    }
+}
+
+/// One entry of a Chrome trace
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChromeTraceEvent {
+    /// Span or marker name
+    pub name: String,
+    /// Comma-separated categories
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub cat: String,
+    /// Phase: `X` complete span, `i` instant, `M` metadata
+    pub ph: String,
+    /// Start in microseconds since the execution started
+    pub ts: i64,
+    /// Span duration in microseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dur: Option<i64>,
+    /// Process ID
+    pub pid: u32,
+    /// Thread ID; concurrent spans are placed on separate lanes
+    pub tid: u32,
+    /// Extra data shown in the UI
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub args: serde_json::Value,
+}
+
+/// An execution trace in Chrome `trace_event` JSON object format
+///
+/// Node spans come from paired start and completion events. LLM time is
+/// reported per node rather than per call, so it is drawn as one span at the
+/// start of the node covering the node's total provider latency. Tool calls
+/// traced with a duration become spans ending when the call was recorded;
+/// others become instant markers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChromeTrace {
+    /// Trace entries
+    pub trace_events: Vec<ChromeTraceEvent>,
+    /// Unit the UI displays times in
+    pub display_time_unit: String,
+    /// Execution metadata
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+/// Time a node spent executing, in microseconds since its trace started
+pub(crate) struct NodeSpan<'a> {
+    pub(crate) node_id: &'a str,
+    pub(crate) start: i64,
+    pub(crate) end: i64,
+    /// Completion or failure event; `None` if the node was still running
+    pub(crate) completion: Option<&'a VisualExecutionEvent>,
+}
+
+impl NodeSpan<'_> {
+    /// Provider latency the node reported on completion
+    pub(crate) fn llm_micros(&self) -> Option<i64> {
+        self.completion
+            .and_then(|event| event.data.get("llm_latency_ms"))
+            .and_then(|v| v.as_f64())
+            .map(|ms| (ms * 1000.0) as i64)
+            .filter(|us| *us > 0)
+    }
+}
+
+/// Microseconds from the start of `trace` to `event`
+pub(crate) fn offset_micros(trace: &ExecutionTrace, event: &VisualExecutionEvent) -> i64 {
+    (event.timestamp - trace.start_time).num_microseconds().unwrap_or(0).max(0)
+}
+
+/// Node spans of a trace ordered by start time
+///
+/// Starts are paired with the next completion or failure of the same node.
+/// Nodes still running when the trace was taken end with the trace.
+pub(crate) fn node_spans(trace: &ExecutionTrace) -> Vec<NodeSpan<'_>> {
+    let mut open: HashMap<&str, Vec<i64>> = HashMap::new();
+    let mut spans = Vec::new();
+    for event in &trace.events {
+        let Some(node_id) = event.node_id.as_deref() else { continue };
+        match event.event_type {
+            VisualEventType::NodeStarted => {
+                open.entry(node_id).or_default().push(offset_micros(trace, event));
+            }
+            VisualEventType::NodeCompleted | VisualEventType::NodeFailed => {
+                let end = offset_micros(trace, event);
+                let start = open
+                    .get_mut(node_id)
+                    .and_then(|starts| starts.pop())
+                    .or_else(|| {
+                        // Completion without a start: fall back to the reported duration
+                        event.data.get("duration_ms")
+                            .and_then(|v| v.as_i64())
+                            .map(|ms| (end - ms * 1000).max(0))
+                    })
+                    .unwrap_or(end);
+                spans.push(NodeSpan { node_id, start, end, completion: Some(event) });
+            }
+            _ => {}
+        }
+    }
+
+    let trace_end = trace.end_time
+        .map(|end| (end - trace.start_time).num_microseconds().unwrap_or(0))
+        .or_else(|| trace.events.iter().map(|event| offset_micros(trace, event)).max())
+        .unwrap_or(0);
+    for (node_id, starts) in open {
+        for start in starts {
+            spans.push(NodeSpan { node_id, start, end: trace_end.max(start), completion: None });
+        }
+    }
+    spans.sort_by_key(|span| (span.start, span.end));
+    spans
+}
+
+impl ChromeTrace {
+    /// Build the timeline of an execution trace
+    pub fn from_execution(trace: &ExecutionTrace) -> Self {
+        let spans = node_spans(trace);
+
+        // Greedily assign lanes so overlapping spans never share a thread
+        let mut lane_ends: Vec<i64> = Vec::new();
+        let mut node_lanes: Vec<(&str, i64, i64, u32)> = Vec::new();
+        let mut events = Vec::new();
+        for span in &spans {
+            let lane = match lane_ends.iter().position(|end| *end <= span.start) {
+                Some(lane) => {
+                    lane_ends[lane] = span.end;
+                    lane
+                }
+                None => {
+                    lane_ends.push(span.end);
+                    lane_ends.len() - 1
+                }
+            } as u32;
+            node_lanes.push((span.node_id, span.start, span.end, lane));
+            events.extend(Self::node_events(span, lane));
+        }
+
+        // Tool calls and other markers go on the lane of the node they belong to
+        let lane_of = |node_id: Option<&str>, ts: i64| {
+            node_id
+                .and_then(|id| {
+                    node_lanes.iter()
+                        .find(|(node, start, end, _)| *node == id && *start <= ts && ts <= *end)
+                        .or_else(|| node_lanes.iter().find(|(node, ..)| *node == id))
+                })
+                .map(|(.., lane)| *lane)
+                .unwrap_or(0)
+        };
+        for event in &trace.events {
+            let ts = offset_micros(trace, event);
+            match &event.event_type {
+                VisualEventType::ToolExecution => {
+                    let name = event.data.get("tool_name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("tool");
+                    let dur = event.data.get("duration_ms")
+                        .and_then(|v| v.as_f64())
+                        .map(|ms| (ms * 1000.0) as i64);
+                    events.push(ChromeTraceEvent {
+                        name: name.to_string(),
+                        cat: "tool".to_string(),
+                        ph: if dur.is_some() { "X" } else { "i" }.to_string(),
+                        ts: dur.map(|d| (ts - d).max(0)).unwrap_or(ts),
+                        dur,
+                        pid: PID,
+                        tid: lane_of(event.node_id.as_deref(), ts),
+                        args: serde_json::json!({ "node_id": event.node_id }),
+                    });
+                }
+                VisualEventType::CommandRouting
+                | VisualEventType::EdgeThrottled
+                | VisualEventType::AgentResponse => {
+                    events.push(ChromeTraceEvent {
+                        name: format!("{:?}", event.event_type),
+                        cat: "marker".to_string(),
+                        ph: "i".to_string(),
+                        ts,
+                        dur: None,
+                        pid: PID,
+                        tid: lane_of(event.node_id.as_deref(), ts),
+                        args: event.data.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // Name the process and lanes
+        events.push(ChromeTraceEvent {
+            name: "process_name".to_string(),
+            cat: String::new(),
+            ph: "M".to_string(),
+            ts: 0,
+            dur: None,
+            pid: PID,
+            tid: 0,
+            args: serde_json::json!({ "name": format!("{} ({})", trace.workflow_id, trace.execution_id) }),
+        });
+        for lane in 0..lane_ends.len().max(1) as u32 {
+            events.push(ChromeTraceEvent {
+                name: "thread_name".to_string(),
+                cat: String::new(),
+                ph: "M".to_string(),
+                ts: 0,
+                dur: None,
+                pid: PID,
+                tid: lane,
+                args: serde_json::json!({ "name": format!("lane {}", lane) }),
+            });
+        }
+
+        let mut metadata = HashMap::new();
+        metadata.insert("execution_id".to_string(), serde_json::json!(trace.execution_id));
+        metadata.insert("workflow_id".to_string(), serde_json::json!(trace.workflow_id));
+        metadata.insert("start_time".to_string(), serde_json::json!(trace.start_time));
+        metadata.insert("status".to_string(), serde_json::json!(trace.status));
+        if let Some(lineage) = &trace.lineage {
+            metadata.insert("lineage".to_string(), serde_json::json!(lineage));
+        }
+
+        Self {
+            trace_events: events,
+            display_time_unit: "ms".to_string(),
+            metadata,
+        }
+    }
+
+    /// The node span and its LLM span
+    fn node_events(span: &NodeSpan<'_>, lane: u32) -> Vec<ChromeTraceEvent> {
+        let data = span.completion.map(|event| &event.data);
+        let failed = span.completion
+            .map(|event| matches!(event.event_type, VisualEventType::NodeFailed))
+            .unwrap_or(false);
+
+        let mut args = serde_json::json!({ "node_id": span.node_id });
+        if failed {
+            args["error"] = data.and_then(|d| d.get("error")).cloned().unwrap_or_default();
+        }
+        if let Some(usage) = data.and_then(|d| d.get("llm_usage")) {
+            args["llm_usage"] = usage.clone();
+        }
+
+        let mut events = vec![ChromeTraceEvent {
+            name: span.node_id.to_string(),
+            cat: if failed { "node,error" } else { "node" }.to_string(),
+            ph: "X".to_string(),
+            ts: span.start,
+            dur: Some(span.end - span.start),
+            pid: PID,
+            tid: lane,
+            args,
+        }];
+
+        if let Some(latency) = span.llm_micros() {
+            let usage = data.and_then(|d| d.get("llm_usage")).cloned().unwrap_or_default();
+            events.push(ChromeTraceEvent {
+                name: "llm".to_string(),
+                cat: "llm".to_string(),
+                ph: "X".to_string(),
+                ts: span.start,
+                dur: Some(latency.min(span.end - span.start)),
+                pid: PID,
+                tid: lane,
+                args: usage,
+            });
+        }
+        events
+    }
+
+    /// Serialize as trace_event JSON
+    pub fn to_json(&self) -> GraphResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Write the trace to a file
+    pub fn write_to<P: AsRef<std::path::Path>>(&self, path: P) -> GraphResult<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+impl ExecutionTrace {
+    /// Timeline of this execution in Chrome `trace_event` format
+    pub fn to_chrome_trace(&self) -> ChromeTrace {
+        ChromeTrace::from_execution(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::ExecutionStatus;
+    use chrono::{Duration, Utc};
+
+    fn event(
+        event_type: VisualEventType,
+        node_id: &str,
+        at_ms: i64,
+        data: serde_json::Value,
+        start: chrono::DateTime<Utc>,
+    ) -> VisualExecutionEvent {
+        VisualExecutionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            execution_id: "exec".to_string(),
+            event_type,
+            node_id: Some(node_id.to_string()),
+            timestamp: start + Duration::milliseconds(at_ms),
+            data,
+            context: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parallel_nodes_get_separate_lanes() {
+        let start = Utc::now();
+        let trace = ExecutionTrace {
+            id: "trace".to_string(),
+            execution_id: "exec".to_string(),
+            workflow_id: "wf".to_string(),
+            start_time: start,
+            end_time: Some(start + Duration::milliseconds(100)),
+            events: vec![
+                event(VisualEventType::NodeStarted, "a", 0, serde_json::json!({}), start),
+                event(VisualEventType::NodeStarted, "b", 10, serde_json::json!({}), start),
+                event(VisualEventType::ToolExecution, "b", 30,
+                    serde_json::json!({ "tool_name": "search", "duration_ms": 15 }), start),
+                event(VisualEventType::NodeCompleted, "a", 50,
+                    serde_json::json!({ "llm_latency_ms": 40 }), start),
+                event(VisualEventType::NodeCompleted, "b", 60, serde_json::json!({}), start),
+                event(VisualEventType::NodeStarted, "c", 70, serde_json::json!({}), start),
+                event(VisualEventType::NodeFailed, "c", 90,
+                    serde_json::json!({ "error": "boom" }), start),
+            ],
+            status: ExecutionStatus::Failed,
+            error: None,
+            lineage: None,
+        };
+
+        let chrome = trace.to_chrome_trace();
+        let span = |name: &str| chrome.trace_events.iter()
+            .find(|e| e.name == name && e.ph == "X")
+            .unwrap();
+
+        let (a, b, c) = (span("a"), span("b"), span("c"));
+        assert_eq!((a.ts, a.dur), (0, Some(50_000)));
+        assert_eq!((b.ts, b.dur), (10_000, Some(50_000)));
+        assert_ne!(a.tid, b.tid);
+        // The first lane is free again once "a" finishes
+        assert_eq!(c.tid, a.tid);
+        assert!(c.cat.contains("error"));
+
+        let llm = span("llm");
+        assert_eq!((llm.ts, llm.dur, llm.tid), (0, Some(40_000), a.tid));
+        let tool = span("search");
+        assert_eq!((tool.ts, tool.dur, tool.tid), (15_000, Some(15_000), b.tid));
+
+        let json: serde_json::Value = serde_json::from_str(&chrome.to_json().unwrap()).unwrap();
+        assert!(json["traceEvents"].is_array());
+        assert_eq!(json["displayTimeUnit"], "ms");
+    }
+
+    #[test]
+    fn test_format_parsing() {
+        assert_eq!("perfetto".parse::<TraceExportFormat>().unwrap(), TraceExportFormat::Perfetto);
+        assert_eq!("Chrome".parse::<TraceExportFormat>().unwrap(), TraceExportFormat::Chrome);
+        assert!("svg".parse::<TraceExportFormat>().is_err());
+    }
+}
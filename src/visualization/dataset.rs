@@ -0,0 +1,262 @@
+//! Eval datasets exported from production execution traces
+//! Node inputs and outputs become JSONL examples, with sensitive fields and personal data scrubbed
+
+use crate::enterprise::security::{PiiScrubber, RedactionPolicy};
+use crate::error::GraphResult;
+use crate::visualization::{ExecutionStatus, ExecutionTrace, VisualEventType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Which traces and nodes examples are taken from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatasetFilter {
+    /// Only executions that completed successfully
+    pub successful_only: bool,
+    /// Only these nodes; every node when empty
+    pub nodes: Vec<String>,
+    /// Only these workflows; every workflow when empty
+    pub workflows: Vec<String>,
+}
+
+impl Default for DatasetFilter {
+    fn default() -> Self {
+        Self {
+            successful_only: true,
+            nodes: Vec::new(),
+            workflows: Vec::new(),
+        }
+    }
+}
+
+impl DatasetFilter {
+    /// Every node of successful executions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also take examples from failed and cancelled executions
+    pub fn with_failed_runs(mut self) -> Self {
+        self.successful_only = false;
+        self
+    }
+
+    /// Take examples from `node`, in addition to other nodes named
+    pub fn with_node(mut self, node: impl Into<String>) -> Self {
+        self.nodes.push(node.into());
+        self
+    }
+
+    /// Take examples from executions of `workflow`, in addition to other workflows named
+    pub fn with_workflow(mut self, workflow: impl Into<String>) -> Self {
+        self.workflows.push(workflow.into());
+        self
+    }
+
+    /// Whether examples are taken from `trace`
+    pub fn matches_trace(&self, trace: &ExecutionTrace) -> bool {
+        (!self.successful_only || matches!(trace.status, ExecutionStatus::Completed))
+            && (self.workflows.is_empty() || self.workflows.contains(&trace.workflow_id))
+    }
+
+    /// Whether examples are taken from `node`
+    pub fn matches_node(&self, node: &str) -> bool {
+        self.nodes.is_empty() || self.nodes.iter().any(|n| n == node)
+    }
+}
+
+/// One input/output pair of an eval dataset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DatasetExample {
+    /// Stable ID: execution, node and the node's run within the execution
+    pub id: String,
+    /// Workflow the execution ran
+    pub workflow_id: String,
+    /// Execution the pair was taken from
+    pub execution_id: String,
+    /// Node that produced the output
+    pub node_id: String,
+    /// What the node was given
+    pub input: Value,
+    /// What the node produced
+    pub output: Value,
+    /// When the node completed
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Turns traces into scrubbed eval examples
+///
+/// A node run becomes an example when its start was traced with its input
+/// (see [`ExecutionTracer::trace_node_start_with_input`](crate::visualization::execution_tracer::ExecutionTracer::trace_node_start_with_input))
+/// and it completed with an output. Runs that failed are never exported.
+/// Sensitive fields are redacted and personal data in free text replaced
+/// before an example leaves the exporter.
+#[derive(Debug, Clone, Default)]
+pub struct DatasetExporter {
+    filter: DatasetFilter,
+    redaction: RedactionPolicy,
+    pii: PiiScrubber,
+}
+
+impl DatasetExporter {
+    /// Create an exporter with the default redaction and PII patterns
+    pub fn new(filter: DatasetFilter) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+
+    /// Redact fields per `redaction`
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Replace personal data found by `pii`
+    pub fn with_pii_scrubber(mut self, pii: PiiScrubber) -> Self {
+        self.pii = pii;
+        self
+    }
+
+    /// Examples from `traces`, in trace and event order
+    pub fn examples<'a>(&self, traces: impl IntoIterator<Item = &'a ExecutionTrace>) -> Vec<DatasetExample> {
+        let mut examples = Vec::new();
+        for trace in traces.into_iter().filter(|trace| self.filter.matches_trace(trace)) {
+            let mut inputs: HashMap<&str, &Value> = HashMap::new();
+            let mut runs: HashMap<&str, usize> = HashMap::new();
+            for event in &trace.events {
+                let Some(node_id) = event.node_id.as_deref().filter(|node| self.filter.matches_node(node)) else {
+                    continue;
+                };
+                match event.event_type {
+                    VisualEventType::NodeStarted => match event.data.get("input") {
+                        Some(input) => {
+                            inputs.insert(node_id, input);
+                        }
+                        None => {
+                            inputs.remove(node_id);
+                        }
+                    },
+                    VisualEventType::NodeCompleted => {
+                        let input = inputs.remove(node_id);
+                        let output = event.data.get("output").filter(|output| !output.is_null());
+                        let (Some(input), Some(output)) = (input, output) else {
+                            continue;
+                        };
+                        let run = runs.entry(node_id).or_default();
+                        *run += 1;
+                        examples.push(DatasetExample {
+                            id: format!("{}:{}:{}", trace.execution_id, node_id, run),
+                            workflow_id: trace.workflow_id.clone(),
+                            execution_id: trace.execution_id.clone(),
+                            node_id: node_id.to_string(),
+                            input: self.sanitize(input),
+                            output: self.sanitize(output),
+                            timestamp: event.timestamp,
+                        });
+                    }
+                    VisualEventType::NodeFailed => {
+                        inputs.remove(node_id);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        examples
+    }
+
+    /// Write examples from `traces` as JSON Lines, returning how many were written
+    pub fn write_jsonl<'a, W: Write>(
+        &self,
+        traces: impl IntoIterator<Item = &'a ExecutionTrace>,
+        mut writer: W,
+    ) -> GraphResult<usize> {
+        let examples = self.examples(traces);
+        for example in &examples {
+            serde_json::to_writer(&mut writer, example)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(examples.len())
+    }
+
+    fn sanitize(&self, value: &Value) -> Value {
+        let mut value = self.redaction.redacted(value);
+        self.pii.scrub(&mut value);
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::VisualExecutionEvent;
+    use serde_json::json;
+
+    fn event(event_type: VisualEventType, node: &str, data: Value) -> VisualExecutionEvent {
+        VisualExecutionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            execution_id: "exec-1".to_string(),
+            event_type,
+            node_id: Some(node.to_string()),
+            timestamp: chrono::Utc::now(),
+            data,
+            context: HashMap::new(),
+        }
+    }
+
+    fn trace(execution_id: &str, status: ExecutionStatus, events: Vec<VisualExecutionEvent>) -> ExecutionTrace {
+        ExecutionTrace {
+            id: uuid::Uuid::new_v4().to_string(),
+            execution_id: execution_id.to_string(),
+            workflow_id: "support".to_string(),
+            start_time: chrono::Utc::now(),
+            end_time: Some(chrono::Utc::now()),
+            events,
+            status,
+            error: None,
+            lineage: None,
+        }
+    }
+
+    #[test]
+    fn test_export_filters_and_scrubs() {
+        let succeeded = trace("exec-1", ExecutionStatus::Completed, vec![
+            event(VisualEventType::NodeStarted, "classify", json!({"input": {"message": "Reset my password", "api_key": "sk-1"}})),
+            event(VisualEventType::NodeCompleted, "classify", json!({"output": "account"})),
+            event(VisualEventType::NodeStarted, "reply", json!({"input": {"message": "Reset my password"}})),
+            event(VisualEventType::NodeCompleted, "reply", json!({"output": "We emailed ada@example.com a reset link"})),
+            // Started without its input traced
+            event(VisualEventType::NodeStarted, "reply", json!({"node_type": "AgentNode"})),
+            event(VisualEventType::NodeCompleted, "reply", json!({"output": "Anything else?"})),
+            event(VisualEventType::NodeStarted, "reply", json!({"input": {"message": "Thanks"}})),
+            event(VisualEventType::NodeFailed, "reply", json!({"error": "timeout"})),
+        ]);
+        let failed = trace("exec-2", ExecutionStatus::Failed, vec![
+            event(VisualEventType::NodeStarted, "classify", json!({"input": {"message": "Hi"}})),
+            event(VisualEventType::NodeCompleted, "classify", json!({"output": "greeting"})),
+        ]);
+        let traces = [succeeded, failed];
+
+        let examples = DatasetExporter::new(DatasetFilter::new()).examples(&traces);
+        assert_eq!(examples.len(), 2);
+        assert_eq!(examples[0].id, "exec-1:classify:1");
+        assert_eq!(examples[0].input, json!({"message": "Reset my password", "api_key": "[REDACTED]"}));
+        assert_eq!(examples[1].output, json!("We emailed [EMAIL] a reset link"));
+
+        let classify = DatasetExporter::new(DatasetFilter::new().with_failed_runs().with_node("classify"));
+        let mut jsonl = Vec::new();
+        assert_eq!(classify.write_jsonl(&traces, &mut jsonl).unwrap(), 2);
+        let lines: Vec<DatasetExample> = String::from_utf8(jsonl).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[1].execution_id, "exec-2");
+        assert_eq!(lines[1].output, json!("greeting"));
+
+        assert!(DatasetExporter::new(DatasetFilter::new().with_workflow("billing")).examples(&traces).is_empty());
+    }
+}
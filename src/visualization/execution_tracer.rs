@@ -1,8 +1,14 @@
 //! Real-time execution tracing for AgentGraph workflows
 //! Provides LangSmith-style execution monitoring and debugging
 
+use crate::edge::throttle::EdgeWait;
 use crate::error::GraphResult;
-use crate::visualization::{VisualExecutionEvent, VisualEventType, ExecutionTrace, ExecutionStatus};
+use crate::graph::ExecutionLineage;
+use crate::llm::{LLMUsage, LlmCallRecord};
+use crate::state::tenant::TenantScope;
+use crate::visualization::chrome_trace::ChromeTrace;
+use crate::visualization::latency_profile::LatencyProfile;
+use crate::visualization::{VisualExecutionEvent, VisualEventType, ExecutionTrace, ExecutionStatus, BranchSummary};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -14,6 +20,8 @@ use uuid::Uuid;
 pub struct ExecutionTracer {
     /// Active execution traces
     traces: Arc<RwLock<HashMap<String, ExecutionTrace>>>,
+    /// Tenants that executions were traced for, by execution ID
+    tenants: Arc<parking_lot::RwLock<HashMap<String, String>>>,
     /// Event broadcaster for real-time updates
     event_broadcaster: broadcast::Sender<VisualExecutionEvent>,
     /// Maximum number of traces to keep
@@ -29,6 +37,7 @@ impl ExecutionTracer {
         
         Self {
             traces: Arc::new(RwLock::new(HashMap::new())),
+            tenants: Arc::new(parking_lot::RwLock::new(HashMap::new())),
             event_broadcaster,
             max_traces,
             enabled,
@@ -37,6 +46,35 @@ impl ExecutionTracer {
 
     /// Start tracing a new execution
     pub async fn start_execution(&self, execution_id: String, workflow_id: String) -> GraphResult<()> {
+        self.start_trace(execution_id, workflow_id, None).await
+    }
+
+    /// Start tracing an execution of a tenant, whose trace is then only
+    /// returned by [`get_tenant_trace`](Self::get_tenant_trace) and
+    /// [`get_tenant_traces`](Self::get_tenant_traces) for that tenant
+    pub async fn start_tenant_execution(&self, execution_id: String, workflow_id: String, tenant: &TenantScope) -> GraphResult<()> {
+        if self.enabled {
+            self.tenants.write().insert(execution_id.clone(), tenant.tenant_id().to_string());
+        }
+        self.start_trace(execution_id, workflow_id, None).await
+    }
+
+    /// Start tracing an execution forked from a checkpoint of another execution
+    pub async fn start_forked_execution(
+        &self,
+        execution_id: String,
+        workflow_id: String,
+        lineage: ExecutionLineage,
+    ) -> GraphResult<()> {
+        self.start_trace(execution_id, workflow_id, Some(lineage)).await
+    }
+
+    async fn start_trace(
+        &self,
+        execution_id: String,
+        workflow_id: String,
+        lineage: Option<ExecutionLineage>,
+    ) -> GraphResult<()> {
         if !self.enabled {
             return Ok(());
         }
@@ -50,6 +88,7 @@ impl ExecutionTracer {
             events: Vec::new(),
             status: ExecutionStatus::Running,
             error: None,
+            lineage: lineage.clone(),
         };
 
         // Add to traces
@@ -61,6 +100,7 @@ impl ExecutionTracer {
             let oldest_key = traces.keys().next().cloned();
             if let Some(key) = oldest_key {
                 traces.remove(&key);
+                self.tenants.write().remove(&key);
             }
         }
 
@@ -71,7 +111,10 @@ impl ExecutionTracer {
             event_type: VisualEventType::ExecutionStarted,
             node_id: None,
             timestamp: chrono::Utc::now(),
-            data: serde_json::json!({}),
+            data: match &lineage {
+                Some(lineage) => serde_json::json!({ "lineage": lineage }),
+                None => serde_json::json!({}),
+            },
             context: HashMap::new(),
         };
 
@@ -120,19 +163,36 @@ impl ExecutionTracer {
 
     /// Trace node execution start
     pub async fn trace_node_start(&self, execution_id: &str, node_id: &str, node_type: &str) -> GraphResult<()> {
+        self.trace_node_start_with_input(execution_id, node_id, node_type, None).await
+    }
+
+    /// Trace node execution start along with the input the node was given,
+    /// which makes the node's runs exportable as eval examples
+    pub async fn trace_node_start_with_input(
+        &self,
+        execution_id: &str,
+        node_id: &str,
+        node_type: &str,
+        input: Option<&serde_json::Value>,
+    ) -> GraphResult<()> {
         if !self.enabled {
             return Ok(());
         }
 
+        let mut data = serde_json::json!({
+            "node_type": node_type
+        });
+        if let Some(input) = input {
+            data["input"] = input.clone();
+        }
+
         let event = VisualExecutionEvent {
             id: Uuid::new_v4().to_string(),
             execution_id: execution_id.to_string(),
             event_type: VisualEventType::NodeStarted,
             node_id: Some(node_id.to_string()),
             timestamp: chrono::Utc::now(),
-            data: serde_json::json!({
-                "node_type": node_type
-            }),
+            data,
             context: HashMap::new(),
         };
 
@@ -143,20 +203,39 @@ impl ExecutionTracer {
 
     /// Trace node execution completion
     pub async fn trace_node_complete(&self, execution_id: &str, node_id: &str, duration_ms: u64, output: Option<serde_json::Value>) -> GraphResult<()> {
+        self.trace_node_complete_with_usage(execution_id, node_id, duration_ms, output, None).await
+    }
+
+    /// Trace node execution completion along with the LLM usage it incurred
+    pub async fn trace_node_complete_with_usage(
+        &self,
+        execution_id: &str,
+        node_id: &str,
+        duration_ms: u64,
+        output: Option<serde_json::Value>,
+        llm_usage: Option<&LLMUsage>,
+    ) -> GraphResult<()> {
         if !self.enabled {
             return Ok(());
         }
 
+        let mut data = serde_json::json!({
+            "duration_ms": duration_ms,
+            "output": output
+        });
+        if let Some(usage) = llm_usage {
+            data["llm_usage"] = serde_json::to_value(usage).unwrap_or_default();
+            data["llm_cost_usd"] = serde_json::json!(usage.cost);
+            data["llm_latency_ms"] = serde_json::json!(usage.latency_ms());
+        }
+
         let event = VisualExecutionEvent {
             id: Uuid::new_v4().to_string(),
             execution_id: execution_id.to_string(),
             event_type: VisualEventType::NodeCompleted,
             node_id: Some(node_id.to_string()),
             timestamp: chrono::Utc::now(),
-            data: serde_json::json!({
-                "duration_ms": duration_ms,
-                "output": output
-            }),
+            data,
             context: HashMap::new(),
         };
 
@@ -216,21 +295,66 @@ impl ExecutionTracer {
 
     /// Trace tool execution
     pub async fn trace_tool_execution(&self, execution_id: &str, node_id: &str, tool_name: &str, input: &serde_json::Value, output: &serde_json::Value) -> GraphResult<()> {
+        self.trace_tool_execution_with_duration(execution_id, node_id, tool_name, input, output, None).await
+    }
+
+    /// Trace tool execution along with how long the call took
+    pub async fn trace_tool_execution_with_duration(
+        &self,
+        execution_id: &str,
+        node_id: &str,
+        tool_name: &str,
+        input: &serde_json::Value,
+        output: &serde_json::Value,
+        duration_ms: Option<u64>,
+    ) -> GraphResult<()> {
         if !self.enabled {
             return Ok(());
         }
 
+        let mut data = serde_json::json!({
+            "tool_name": tool_name,
+            "input": input,
+            "output": output
+        });
+        if let Some(duration_ms) = duration_ms {
+            data["duration_ms"] = serde_json::json!(duration_ms);
+        }
+
         let event = VisualExecutionEvent {
             id: Uuid::new_v4().to_string(),
             execution_id: execution_id.to_string(),
             event_type: VisualEventType::ToolExecution,
             node_id: Some(node_id.to_string()),
             timestamp: chrono::Utc::now(),
-            data: serde_json::json!({
-                "tool_name": tool_name,
-                "input": input,
-                "output": output
-            }),
+            data,
+            context: HashMap::new(),
+        };
+
+        self.add_event(execution_id, event.clone()).await?;
+        let _ = self.event_broadcaster.send(event);
+        Ok(())
+    }
+
+    /// Trace a call to an LLM provider with the request sent and the response
+    /// received, so it can be replayed in the playground
+    pub async fn trace_llm_call(
+        &self,
+        execution_id: &str,
+        node_id: &str,
+        call: &LlmCallRecord,
+    ) -> GraphResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let event = VisualExecutionEvent {
+            id: Uuid::new_v4().to_string(),
+            execution_id: execution_id.to_string(),
+            event_type: VisualEventType::LlmCall,
+            node_id: Some(node_id.to_string()),
+            timestamp: chrono::Utc::now(),
+            data: serde_json::to_value(call).unwrap_or_default(),
             context: HashMap::new(),
         };
 
@@ -263,6 +387,31 @@ impl ExecutionTracer {
         Ok(())
     }
 
+    /// Trace a wait imposed by a rate-limited edge
+    pub async fn trace_edge_wait(&self, execution_id: &str, wait: &EdgeWait) -> GraphResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let event = VisualExecutionEvent {
+            id: Uuid::new_v4().to_string(),
+            execution_id: execution_id.to_string(),
+            event_type: VisualEventType::EdgeThrottled,
+            node_id: None,
+            timestamp: chrono::Utc::now(),
+            data: serde_json::json!({
+                "edge": wait.edge,
+                "step": wait.step,
+                "waited_ms": wait.waited.as_millis() as u64
+            }),
+            context: HashMap::new(),
+        };
+
+        self.add_event(execution_id, event.clone()).await?;
+        let _ = self.event_broadcaster.send(event);
+        Ok(())
+    }
+
     /// Trace state update
     pub async fn trace_state_update(&self, execution_id: &str, node_id: Option<&str>, key: &str, value: &serde_json::Value) -> GraphResult<()> {
         if !self.enabled {
@@ -302,12 +451,77 @@ impl ExecutionTracer {
         traces.get(execution_id).cloned()
     }
 
+    /// Tenant an execution was traced for, if any
+    pub fn tenant_of(&self, execution_id: &str) -> Option<String> {
+        self.tenants.read().get(execution_id).cloned()
+    }
+
+    /// Execution trace, if it was traced for `tenant`
+    pub async fn get_tenant_trace(&self, tenant: &TenantScope, execution_id: &str) -> Option<ExecutionTrace> {
+        if self.tenant_of(execution_id).as_deref() != Some(tenant.tenant_id()) {
+            return None;
+        }
+        self.get_trace(execution_id).await
+    }
+
+    /// Traces of the executions traced for `tenant`
+    pub async fn get_tenant_traces(&self, tenant: &TenantScope) -> Vec<ExecutionTrace> {
+        let tenants = self.tenants.read().clone();
+        let traces = self.traces.read().await;
+        traces.values()
+            .filter(|trace| tenants.get(&trace.execution_id).map(String::as_str) == Some(tenant.tenant_id()))
+            .cloned()
+            .collect()
+    }
+
+    /// Export an execution's timeline in Chrome `trace_event` format
+    pub async fn export_chrome_trace(&self, execution_id: &str) -> Option<ChromeTrace> {
+        self.get_trace(execution_id).await.map(|trace| trace.to_chrome_trace())
+    }
+
+    /// Latency profile of the most recent `limit` finished executions of a workflow
+    pub async fn latency_profile(&self, workflow_id: &str, limit: usize) -> LatencyProfile {
+        let traces = self.traces.read().await;
+        let mut runs: Vec<&ExecutionTrace> = traces.values()
+            .filter(|trace| trace.workflow_id == workflow_id)
+            .filter(|trace| !matches!(trace.status, ExecutionStatus::Running))
+            .collect();
+        runs.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+        runs.truncate(limit);
+
+        LatencyProfile::from_traces(workflow_id, runs)
+    }
+
     /// Get all traces
     pub async fn get_all_traces(&self) -> Vec<ExecutionTrace> {
         let traces = self.traces.read().await;
         traces.values().cloned().collect()
     }
 
+    /// Traces of executions forked from `parent_execution_id`, oldest first
+    pub async fn get_branches(&self, parent_execution_id: &str) -> Vec<ExecutionTrace> {
+        let traces = self.traces.read().await;
+        let mut branches: Vec<ExecutionTrace> = traces
+            .values()
+            .filter(|trace| trace.lineage.as_ref()
+                .is_some_and(|lineage| lineage.parent_execution_id.to_string() == parent_execution_id))
+            .cloned()
+            .collect();
+        branches.sort_by_key(|trace| trace.start_time);
+        branches
+    }
+
+    /// Side-by-side summaries of an execution and the branches forked from it
+    ///
+    /// The parent comes first if it is still traced, followed by its branches.
+    pub async fn compare_branches(&self, parent_execution_id: &str) -> Vec<BranchSummary> {
+        let parent = self.get_trace(parent_execution_id).await;
+        parent.iter()
+            .chain(self.get_branches(parent_execution_id).await.iter())
+            .map(ExecutionTrace::branch_summary)
+            .collect()
+    }
+
     /// Subscribe to real-time events
     pub fn subscribe_events(&self) -> broadcast::Receiver<VisualExecutionEvent> {
         self.event_broadcaster.subscribe()
@@ -381,6 +595,78 @@ mod tests {
         assert!(!trace.events.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_node_llm_usage_in_trace() {
+        let tracer = ExecutionTracer::new(100, true);
+        let execution_id = "usage_execution".to_string();
+        tracer.start_execution(execution_id.clone(), "workflow".to_string()).await.unwrap();
+
+        let usage = LLMUsage {
+            calls: 2,
+            total_tokens: 900,
+            cost: 0.042,
+            latency: std::time::Duration::from_millis(3100),
+            ..Default::default()
+        };
+        tracer.trace_node_complete_with_usage(&execution_id, "writer", 3400, None, Some(&usage)).await.unwrap();
+        tracer.trace_node_complete(&execution_id, "formatter", 5, None).await.unwrap();
+
+        let trace = tracer.get_trace(&execution_id).await.unwrap();
+        let by_node = trace.llm_usage_by_node();
+        assert_eq!(by_node.len(), 1);
+        assert_eq!(by_node["writer"], usage);
+        assert_eq!(trace.total_llm_usage().latency_ms(), 3100);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_traces_are_isolated() {
+        let tracer = ExecutionTracer::new(100, true);
+        let acme = TenantScope::new("acme").unwrap();
+        let globex = TenantScope::new("globex").unwrap();
+        tracer.start_tenant_execution("acme-run".to_string(), "billing".to_string(), &acme).await.unwrap();
+        tracer.start_execution("untenanted".to_string(), "billing".to_string()).await.unwrap();
+
+        assert!(tracer.get_tenant_trace(&acme, "acme-run").await.is_some());
+        assert!(tracer.get_tenant_trace(&globex, "acme-run").await.is_none());
+        assert!(tracer.get_tenant_trace(&acme, "untenanted").await.is_none());
+        assert_eq!(tracer.get_tenant_traces(&acme).await.len(), 1);
+        assert!(tracer.get_tenant_traces(&globex).await.is_empty());
+        assert_eq!(tracer.tenant_of("acme-run").as_deref(), Some("acme"));
+    }
+
+    #[tokio::test]
+    async fn test_forked_execution_lineage() {
+        let tracer = ExecutionTracer::new(100, true);
+        let parent_id = Uuid::new_v4();
+        tracer.start_execution(parent_id.to_string(), "workflow".to_string()).await.unwrap();
+        tracer.trace_node_complete(&parent_id.to_string(), "plan", 10, None).await.unwrap();
+        tracer.end_execution(&parent_id.to_string(), ExecutionStatus::Completed, None).await.unwrap();
+
+        for branch in ["concise", "detailed"] {
+            let lineage = ExecutionLineage {
+                parent_execution_id: parent_id,
+                checkpoint_id: Uuid::new_v4(),
+                branch: branch.to_string(),
+            };
+            let execution_id = format!("{}-{}", parent_id, branch);
+            tracer.start_forked_execution(execution_id.clone(), "workflow".to_string(), lineage).await.unwrap();
+            tracer.trace_node_complete(&execution_id, "write", 20, None).await.unwrap();
+            tracer.end_execution(&execution_id, ExecutionStatus::Completed, None).await.unwrap();
+        }
+
+        let branches = tracer.get_branches(&parent_id.to_string()).await;
+        assert_eq!(branches.len(), 2);
+
+        let comparison = tracer.compare_branches(&parent_id.to_string()).await;
+        assert_eq!(comparison.len(), 3);
+        assert_eq!(comparison[0].branch, None);
+        assert_eq!(comparison[0].completed_nodes, vec!["plan".to_string()]);
+        let mut names: Vec<_> = comparison[1..].iter().filter_map(|summary| summary.branch.clone()).collect();
+        names.sort();
+        assert_eq!(names, vec!["concise".to_string(), "detailed".to_string()]);
+        assert!(comparison[1..].iter().all(|summary| summary.completed_nodes == vec!["write".to_string()]));
+    }
+
     #[tokio::test]
     async fn test_event_subscription() {
         let tracer = ExecutionTracer::new(100, true);
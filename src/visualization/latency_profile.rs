@@ -0,0 +1,331 @@
+//! Latency profiles aggregated across many executions of a workflow
+//! Node time is split into LLM, tool and framework overhead, flamegraph style
+
+use crate::visualization::chrome_trace::{node_spans, offset_micros};
+use crate::visualization::{ExecutionTrace, VisualEventType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One frame of a latency profile
+///
+/// A node's frame is nested under another when its span fell inside that
+/// node's span, as for nodes run by a subgraph node. Frames with the same
+/// stack are merged across runs. Times are totals over all runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileFrame {
+    /// Node ID, or the workflow ID for the root frame
+    pub name: String,
+    /// Times the frame was entered
+    pub calls: u64,
+    /// Wall time inside the frame
+    pub total_ms: f64,
+    /// Wall time not spent in child frames
+    pub self_ms: f64,
+    /// Time spent waiting on LLM providers
+    pub llm_ms: f64,
+    /// Time spent in tool calls
+    pub tool_ms: f64,
+    /// Self time not accounted for by LLM or tool calls
+    pub overhead_ms: f64,
+    /// Nested frames, slowest first
+    pub children: Vec<ProfileFrame>,
+}
+
+impl ProfileFrame {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn child_mut(&mut self, name: &str) -> &mut ProfileFrame {
+        match self.children.iter().position(|child| child.name == name) {
+            Some(index) => &mut self.children[index],
+            None => {
+                self.children.push(ProfileFrame::new(name));
+                self.children.last_mut().unwrap()
+            }
+        }
+    }
+
+    fn frame_mut(&mut self, path: &[&str]) -> &mut ProfileFrame {
+        path.iter().fold(self, |frame, name| frame.child_mut(name))
+    }
+
+    /// Derive self and overhead time once totals are known
+    fn finish(&mut self) {
+        for child in &mut self.children {
+            child.finish();
+        }
+        self.children.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+
+        let children_ms: f64 = self.children.iter().map(|child| child.total_ms).sum();
+        self.self_ms = (self.total_ms - children_ms).max(0.0);
+        self.overhead_ms = (self.self_ms - self.llm_ms - self.tool_ms).max(0.0);
+    }
+
+    /// This frame and all frames below it
+    pub fn descendants(&self) -> Vec<&ProfileFrame> {
+        let mut frames = vec![self];
+        for child in &self.children {
+            frames.extend(child.descendants());
+        }
+        frames
+    }
+}
+
+/// A node's aggregate latency across every stack it appeared in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HotNode {
+    /// Node ID
+    pub node_id: String,
+    /// Executions of the node
+    pub calls: u64,
+    /// Wall time inside the node
+    pub total_ms: f64,
+    /// Wall time not spent in nested nodes
+    pub self_ms: f64,
+    /// Mean wall time per call
+    pub mean_ms: f64,
+    /// Fraction of total profiled time spent in the node itself
+    pub share: f64,
+}
+
+/// Where profiled time went
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyBreakdown {
+    /// Time waiting on LLM providers
+    pub llm_ms: f64,
+    /// Time in tool calls
+    pub tool_ms: f64,
+    /// Node self time outside LLM and tool calls, plus time between nodes
+    pub overhead_ms: f64,
+}
+
+impl LatencyBreakdown {
+    /// Sum of all components
+    pub fn total_ms(&self) -> f64 {
+        self.llm_ms + self.tool_ms + self.overhead_ms
+    }
+
+    /// Fractions of the total spent in LLM, tool and overhead, in that order
+    pub fn fractions(&self) -> (f64, f64, f64) {
+        let total = self.total_ms();
+        if total <= 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        (self.llm_ms / total, self.tool_ms / total, self.overhead_ms / total)
+    }
+}
+
+/// Node latency of a workflow aggregated over many executions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyProfile {
+    /// Executions profiled
+    pub runs: usize,
+    /// Root frame covering whole executions; its self time is time between nodes
+    pub root: ProfileFrame,
+}
+
+impl LatencyProfile {
+    /// Aggregate the node spans of `traces` under a root frame named `name`
+    pub fn from_traces<'a, I>(name: &str, traces: I) -> Self
+    where
+        I: IntoIterator<Item = &'a ExecutionTrace>,
+    {
+        let mut root = ProfileFrame::new(name);
+        let mut runs = 0;
+
+        for trace in traces {
+            runs += 1;
+            Self::add_trace(&mut root, trace);
+        }
+        root.finish();
+
+        Self { runs, root }
+    }
+
+    fn add_trace(root: &mut ProfileFrame, trace: &ExecutionTrace) {
+        let mut spans = node_spans(trace);
+        // Parents before the children they contain
+        spans.sort_by_key(|span| (span.start, std::cmp::Reverse(span.end)));
+
+        let tool_calls: Vec<(&str, i64, f64)> = trace.events.iter()
+            .filter(|event| matches!(event.event_type, VisualEventType::ToolExecution))
+            .filter_map(|event| {
+                let node_id = event.node_id.as_deref()?;
+                let duration_ms = event.data.get("duration_ms")?.as_f64()?;
+                Some((node_id, offset_micros(trace, event), duration_ms))
+            })
+            .collect();
+
+        let mut run_end = trace.end_time
+            .map(|end| (end - trace.start_time).num_microseconds().unwrap_or(0))
+            .unwrap_or(0);
+
+        // Spans enclosing the current one, outermost first
+        let mut stack: Vec<(&str, i64)> = Vec::new();
+        for span in &spans {
+            while stack.last().is_some_and(|(_, end)| *end < span.end || *end <= span.start) {
+                stack.pop();
+            }
+            let mut path: Vec<&str> = stack.iter().map(|(name, _)| *name).collect();
+            path.push(span.node_id);
+
+            let frame = root.frame_mut(&path);
+            frame.calls += 1;
+            frame.total_ms += (span.end - span.start) as f64 / 1000.0;
+            frame.llm_ms += span.llm_micros().unwrap_or(0) as f64 / 1000.0;
+            frame.tool_ms += tool_calls.iter()
+                .filter(|(node, at, _)| *node == span.node_id && span.start <= *at && *at <= span.end)
+                .map(|(.., duration_ms)| duration_ms)
+                .sum::<f64>();
+
+            run_end = run_end.max(span.end);
+            stack.push((span.node_id, span.end));
+        }
+
+        root.calls += 1;
+        root.total_ms += run_end as f64 / 1000.0;
+    }
+
+    /// Nodes ranked by self time, slowest first
+    pub fn hottest_nodes(&self, limit: usize) -> Vec<HotNode> {
+        let mut by_node: HashMap<&str, HotNode> = HashMap::new();
+        for frame in self.root.descendants().into_iter().skip(1) {
+            let node = by_node.entry(frame.name.as_str()).or_insert_with(|| HotNode {
+                node_id: frame.name.clone(),
+                calls: 0,
+                total_ms: 0.0,
+                self_ms: 0.0,
+                mean_ms: 0.0,
+                share: 0.0,
+            });
+            node.calls += frame.calls;
+            node.total_ms += frame.total_ms;
+            node.self_ms += frame.self_ms;
+        }
+
+        let mut nodes: Vec<HotNode> = by_node.into_values()
+            .map(|mut node| {
+                node.mean_ms = node.total_ms / node.calls.max(1) as f64;
+                if self.root.total_ms > 0.0 {
+                    node.share = node.self_ms / self.root.total_ms;
+                }
+                node
+            })
+            .collect();
+        nodes.sort_by(|a, b| b.self_ms.total_cmp(&a.self_ms));
+        nodes.truncate(limit);
+        nodes
+    }
+
+    /// Split of profiled time between LLM, tools and framework overhead
+    pub fn breakdown(&self) -> LatencyBreakdown {
+        self.root.descendants().into_iter().fold(LatencyBreakdown::default(), |mut acc, frame| {
+            acc.llm_ms += frame.llm_ms;
+            acc.tool_ms += frame.tool_ms;
+            acc.overhead_ms += frame.overhead_ms;
+            acc
+        })
+    }
+
+    /// Folded stacks (`root;outer;inner <self microseconds>`) for flamegraph tools
+    pub fn folded_stacks(&self) -> String {
+        fn fold(frame: &ProfileFrame, prefix: &str, out: &mut String) {
+            let stack = if prefix.is_empty() {
+                frame.name.clone()
+            } else {
+                format!("{};{}", prefix, frame.name)
+            };
+            let self_us = (frame.self_ms * 1000.0).round() as u64;
+            if self_us > 0 {
+                out.push_str(&format!("{} {}\n", stack, self_us));
+            }
+            for child in &frame.children {
+                fold(child, &stack, out);
+            }
+        }
+
+        let mut out = String::new();
+        fold(&self.root, "", &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visualization::{ExecutionStatus, VisualExecutionEvent};
+    use chrono::{Duration, Utc};
+
+    fn run(events: &[(VisualEventType, &str, i64, serde_json::Value)], end_ms: i64) -> ExecutionTrace {
+        let start = Utc::now();
+        ExecutionTrace {
+            id: uuid::Uuid::new_v4().to_string(),
+            execution_id: uuid::Uuid::new_v4().to_string(),
+            workflow_id: "wf".to_string(),
+            start_time: start,
+            end_time: Some(start + Duration::milliseconds(end_ms)),
+            events: events.iter()
+                .map(|(event_type, node_id, at_ms, data)| VisualExecutionEvent {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    execution_id: "exec".to_string(),
+                    event_type: event_type.clone(),
+                    node_id: Some(node_id.to_string()),
+                    timestamp: start + Duration::milliseconds(*at_ms),
+                    data: data.clone(),
+                    context: HashMap::new(),
+                })
+                .collect(),
+            status: ExecutionStatus::Completed,
+            error: None,
+            lineage: None,
+        }
+    }
+
+    #[test]
+    fn test_profile_nests_and_splits_latency() {
+        use VisualEventType::*;
+        let none = serde_json::json!({});
+        // "sub" runs "inner" as a subgraph; "llm" spends 60ms of 80ms waiting on the model
+        let trace = run(&[
+            (NodeStarted, "llm", 0, none.clone()),
+            (NodeCompleted, "llm", 80, serde_json::json!({ "llm_latency_ms": 60 })),
+            (NodeStarted, "sub", 90, none.clone()),
+            (NodeStarted, "inner", 95, none.clone()),
+            (ToolExecution, "inner", 110, serde_json::json!({ "tool_name": "search", "duration_ms": 10 })),
+            (NodeCompleted, "inner", 115, none.clone()),
+            (NodeCompleted, "sub", 120, none.clone()),
+        ], 125);
+        let profile = LatencyProfile::from_traces("wf", [&trace, &trace]);
+
+        assert_eq!(profile.runs, 2);
+        assert_eq!(profile.root.calls, 2);
+        assert!((profile.root.total_ms - 250.0).abs() < 1e-6);
+
+        let sub = profile.root.children.iter().find(|f| f.name == "sub").unwrap();
+        assert_eq!(sub.calls, 2);
+        assert_eq!(sub.children.len(), 1);
+        let inner = &sub.children[0];
+        assert_eq!(inner.name, "inner");
+        assert!((inner.total_ms - 40.0).abs() < 1e-6);
+        assert!((inner.tool_ms - 20.0).abs() < 1e-6);
+        assert!((sub.self_ms - 20.0).abs() < 1e-6);
+
+        let hottest = profile.hottest_nodes(1);
+        assert_eq!(hottest[0].node_id, "llm");
+        assert!((hottest[0].mean_ms - 80.0).abs() < 1e-6);
+
+        let breakdown = profile.breakdown();
+        assert!((breakdown.llm_ms - 120.0).abs() < 1e-6);
+        assert!((breakdown.tool_ms - 20.0).abs() < 1e-6);
+        // Everything else, including the 15ms per run between and after nodes
+        assert!((breakdown.total_ms() - 250.0).abs() < 1e-6);
+
+        let folded = profile.folded_stacks();
+        assert!(folded.contains("wf;sub;inner 40000\n"));
+        assert!(folded.contains("wf;llm 160000\n"));
+    }
+}
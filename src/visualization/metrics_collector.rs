@@ -3,12 +3,15 @@
 
 use crate::error::GraphResult;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use tokio::time::interval;
 
+/// Number of recent tool latencies kept for percentile calculation
+const TOOL_LATENCY_SAMPLES: usize = 1000;
+
 /// Metrics collector for performance analytics
 #[derive(Debug)]
 pub struct MetricsCollector {
@@ -22,6 +25,10 @@ pub struct MetricsCollector {
     enabled: bool,
     /// Collection task handle
     collection_task: Option<tokio::task::JoinHandle<()>>,
+    /// Error-rate spike detection settings
+    tool_anomaly_config: ToolAnomalyConfig,
+    /// Broadcaster for tool anomaly events
+    anomaly_sender: broadcast::Sender<ToolAnomalyEvent>,
 }
 
 /// System-wide metrics
@@ -110,10 +117,124 @@ pub struct ToolMetrics {
     pub failed_executions: u64,
     /// Average execution time (ms)
     pub avg_execution_time_ms: f64,
+    /// Min execution time (ms)
+    pub min_execution_time_ms: f64,
+    /// Max execution time (ms)
+    pub max_execution_time_ms: f64,
+    /// Median execution time over recent calls (ms)
+    pub p50_execution_time_ms: f64,
+    /// 95th percentile execution time over recent calls (ms)
+    pub p95_execution_time_ms: f64,
+    /// 99th percentile execution time over recent calls (ms)
+    pub p99_execution_time_ms: f64,
     /// Success rate
     pub success_rate: f64,
+    /// Error rate (percentage)
+    pub error_rate: f64,
+    /// Calls answered from cache
+    pub cache_hits: u64,
+    /// Cacheable calls that missed the cache
+    pub cache_misses: u64,
+    /// Cache hit rate (percentage)
+    pub cache_hit_rate: f64,
     /// Last used
     pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+    /// Recent latencies for percentiles
+    #[serde(skip)]
+    recent_durations_ms: VecDeque<u64>,
+    /// Recent outcomes for spike detection
+    #[serde(skip)]
+    recent_outcomes: VecDeque<bool>,
+    /// Whether an error-rate spike is currently reported
+    #[serde(skip)]
+    anomaly_active: bool,
+}
+
+impl ToolMetrics {
+    /// Create empty metrics for a tool
+    pub fn new(tool_name: &str) -> Self {
+        Self {
+            tool_name: tool_name.to_string(),
+            total_executions: 0,
+            successful_executions: 0,
+            failed_executions: 0,
+            avg_execution_time_ms: 0.0,
+            min_execution_time_ms: 0.0,
+            max_execution_time_ms: 0.0,
+            p50_execution_time_ms: 0.0,
+            p95_execution_time_ms: 0.0,
+            p99_execution_time_ms: 0.0,
+            success_rate: 0.0,
+            error_rate: 0.0,
+            cache_hits: 0,
+            cache_misses: 0,
+            cache_hit_rate: 0.0,
+            last_used: None,
+            recent_durations_ms: VecDeque::new(),
+            recent_outcomes: VecDeque::new(),
+            anomaly_active: false,
+        }
+    }
+
+    fn update_percentiles(&mut self) {
+        let mut sorted: Vec<u64> = self.recent_durations_ms.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |p: f64| -> f64 {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+            let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1] as f64
+        };
+        self.p50_execution_time_ms = percentile(50.0);
+        self.p95_execution_time_ms = percentile(95.0);
+        self.p99_execution_time_ms = percentile(99.0);
+    }
+
+    /// Error rate over the recent outcome window (0.0 - 1.0)
+    pub fn window_error_rate(&self) -> f64 {
+        if self.recent_outcomes.is_empty() {
+            return 0.0;
+        }
+        let failures = self.recent_outcomes.iter().filter(|ok| !**ok).count();
+        failures as f64 / self.recent_outcomes.len() as f64
+    }
+}
+
+/// Settings for detecting tool error-rate spikes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolAnomalyConfig {
+    /// Number of recent calls in the detection window
+    pub window_size: usize,
+    /// Minimum calls in the window before alerting
+    pub min_samples: usize,
+    /// Window error rate (0.0 - 1.0) that counts as a spike
+    pub error_rate_threshold: f64,
+}
+
+impl Default for ToolAnomalyConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 20,
+            min_samples: 10,
+            error_rate_threshold: 0.5,
+        }
+    }
+}
+
+/// Event emitted when a tool's error rate spikes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolAnomalyEvent {
+    /// Tool name
+    pub tool_name: String,
+    /// Error rate over the detection window (0.0 - 1.0)
+    pub window_error_rate: f64,
+    /// Lifetime error rate before the triggering call (0.0 - 1.0)
+    pub baseline_error_rate: f64,
+    /// Calls in the detection window
+    pub window_size: usize,
+    /// Detection time
+    pub detected_at: chrono::DateTime<chrono::Utc>,
 }
 
 /// System resource metrics
@@ -176,15 +297,29 @@ impl Default for ResourceMetrics {
 impl MetricsCollector {
     /// Create a new metrics collector
     pub fn new(enabled: bool, collection_interval_seconds: u64) -> Self {
+        let (anomaly_sender, _) = broadcast::channel(100);
         Self {
             metrics: Arc::new(RwLock::new(SystemMetrics::default())),
             history: Arc::new(RwLock::new(Vec::new())),
             collection_interval: Duration::from_secs(collection_interval_seconds),
             enabled,
             collection_task: None,
+            tool_anomaly_config: ToolAnomalyConfig::default(),
+            anomaly_sender,
         }
     }
 
+    /// Set error-rate spike detection settings
+    pub fn with_tool_anomaly_config(mut self, config: ToolAnomalyConfig) -> Self {
+        self.tool_anomaly_config = config;
+        self
+    }
+
+    /// Subscribe to tool error-rate spike events
+    pub fn subscribe_tool_anomalies(&self) -> broadcast::Receiver<ToolAnomalyEvent> {
+        self.anomaly_sender.subscribe()
+    }
+
     /// Start metrics collection
     pub async fn start(&mut self) -> GraphResult<()> {
         if !self.enabled {
@@ -204,18 +339,20 @@ impl MetricsCollector {
                 // Collect current metrics
                 let current_metrics = Self::collect_system_metrics().await;
                 
-                // Update metrics
-                {
+                // Update resource metrics, keeping recorded execution metrics
+                let snapshot = {
                     let mut metrics_guard = metrics.write().await;
-                    *metrics_guard = current_metrics.clone();
-                }
+                    metrics_guard.resource_metrics = current_metrics.resource_metrics;
+                    metrics_guard.last_updated = current_metrics.last_updated;
+                    metrics_guard.clone()
+                };
                 
                 // Add to history
                 {
                     let mut history_guard = history.write().await;
                     history_guard.push(MetricsSnapshot {
                         timestamp: chrono::Utc::now(),
-                        metrics: current_metrics,
+                        metrics: snapshot,
                     });
                     
                     // Keep only last 1000 snapshots
@@ -377,28 +514,111 @@ impl MetricsCollector {
             return;
         }
 
+        let anomaly = {
+            let mut metrics = self.metrics.write().await;
+            let tool_metrics = metrics.tool_metrics.entry(tool_name.to_string())
+                .or_insert_with(|| ToolMetrics::new(tool_name));
+
+            let baseline_error_rate = if tool_metrics.total_executions > 0 {
+                tool_metrics.failed_executions as f64 / tool_metrics.total_executions as f64
+            } else {
+                0.0
+            };
+
+            tool_metrics.total_executions += 1;
+            if success {
+                tool_metrics.successful_executions += 1;
+            } else {
+                tool_metrics.failed_executions += 1;
+            }
+
+            let duration_f64 = duration_ms as f64;
+            tool_metrics.avg_execution_time_ms =
+                (tool_metrics.avg_execution_time_ms * (tool_metrics.total_executions - 1) as f64 + duration_f64) / tool_metrics.total_executions as f64;
+            tool_metrics.min_execution_time_ms = if tool_metrics.total_executions == 1 {
+                duration_f64
+            } else {
+                tool_metrics.min_execution_time_ms.min(duration_f64)
+            };
+            tool_metrics.max_execution_time_ms = tool_metrics.max_execution_time_ms.max(duration_f64);
+
+            tool_metrics.recent_durations_ms.push_back(duration_ms);
+            if tool_metrics.recent_durations_ms.len() > TOOL_LATENCY_SAMPLES {
+                tool_metrics.recent_durations_ms.pop_front();
+            }
+            tool_metrics.update_percentiles();
+
+            tool_metrics.success_rate = (tool_metrics.successful_executions as f64 / tool_metrics.total_executions as f64) * 100.0;
+            tool_metrics.error_rate = 100.0 - tool_metrics.success_rate;
+            tool_metrics.last_used = Some(chrono::Utc::now());
+
+            // Detect error-rate spikes over the recent window, reporting once per spike
+            let config = &self.tool_anomaly_config;
+            tool_metrics.recent_outcomes.push_back(success);
+            if tool_metrics.recent_outcomes.len() > config.window_size {
+                tool_metrics.recent_outcomes.pop_front();
+            }
+            let window_error_rate = tool_metrics.window_error_rate();
+            let spiking = tool_metrics.recent_outcomes.len() >= config.min_samples
+                && window_error_rate >= config.error_rate_threshold;
+
+            let anomaly = if spiking && !tool_metrics.anomaly_active {
+                Some(ToolAnomalyEvent {
+                    tool_name: tool_name.to_string(),
+                    window_error_rate,
+                    baseline_error_rate,
+                    window_size: tool_metrics.recent_outcomes.len(),
+                    detected_at: chrono::Utc::now(),
+                })
+            } else {
+                None
+            };
+            tool_metrics.anomaly_active = spiking;
+            anomaly
+        };
+
+        if let Some(event) = anomaly {
+            tracing::warn!(
+                "Tool '{}' error rate spiked to {:.0}% over the last {} calls",
+                event.tool_name,
+                event.window_error_rate * 100.0,
+                event.window_size
+            );
+            let _ = self.anomaly_sender.send(event);
+        }
+    }
+
+    /// Record whether a cacheable tool call was served from cache
+    pub async fn record_tool_cache_access(&self, tool_name: &str, hit: bool) {
+        if !self.enabled {
+            return;
+        }
+
         let mut metrics = self.metrics.write().await;
-        let tool_metrics = metrics.tool_metrics.entry(tool_name.to_string()).or_insert_with(|| ToolMetrics {
-            tool_name: tool_name.to_string(),
-            total_executions: 0,
-            successful_executions: 0,
-            failed_executions: 0,
-            avg_execution_time_ms: 0.0,
-            success_rate: 0.0,
-            last_used: None,
-        });
+        let tool_metrics = metrics.tool_metrics.entry(tool_name.to_string())
+            .or_insert_with(|| ToolMetrics::new(tool_name));
 
-        tool_metrics.total_executions += 1;
-        if success {
-            tool_metrics.successful_executions += 1;
+        if hit {
+            tool_metrics.cache_hits += 1;
         } else {
-            tool_metrics.failed_executions += 1;
+            tool_metrics.cache_misses += 1;
         }
+        let lookups = tool_metrics.cache_hits + tool_metrics.cache_misses;
+        tool_metrics.cache_hit_rate = (tool_metrics.cache_hits as f64 / lookups as f64) * 100.0;
+    }
 
-        tool_metrics.avg_execution_time_ms = 
-            (tool_metrics.avg_execution_time_ms * (tool_metrics.total_executions - 1) as f64 + duration_ms as f64) / tool_metrics.total_executions as f64;
-        tool_metrics.success_rate = (tool_metrics.successful_executions as f64 / tool_metrics.total_executions as f64) * 100.0;
-        tool_metrics.last_used = Some(chrono::Utc::now());
+    /// Get metrics for a single tool
+    pub async fn get_tool_metrics(&self, tool_name: &str) -> Option<ToolMetrics> {
+        let metrics = self.metrics.read().await;
+        metrics.tool_metrics.get(tool_name).cloned()
+    }
+
+    /// Get metrics for all tools, most used first
+    pub async fn get_all_tool_metrics(&self) -> Vec<ToolMetrics> {
+        let metrics = self.metrics.read().await;
+        let mut tools: Vec<_> = metrics.tool_metrics.values().cloned().collect();
+        tools.sort_by(|a, b| b.total_executions.cmp(&a.total_executions).then_with(|| a.tool_name.cmp(&b.tool_name)));
+        tools
     }
 
     /// Collect system metrics (simplified implementation)
@@ -484,4 +704,56 @@ mod tests {
         let summary = collector.get_performance_summary().await;
         assert!(!summary.top_performing_nodes.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_tool_metrics() {
+        let collector = MetricsCollector::new(true, 1);
+
+        for duration in 1..=100 {
+            collector.record_tool_execution("http_get", duration, duration % 10 != 0).await;
+        }
+        collector.record_tool_cache_access("http_get", true).await;
+        collector.record_tool_cache_access("http_get", false).await;
+        collector.record_tool_cache_access("http_get", true).await;
+        collector.record_tool_execution("calculator", 5, true).await;
+
+        let tool = collector.get_tool_metrics("http_get").await.unwrap();
+        assert_eq!(tool.total_executions, 100);
+        assert_eq!(tool.failed_executions, 10);
+        assert!((tool.error_rate - 10.0).abs() < 1e-9);
+        assert_eq!(tool.p50_execution_time_ms, 50.0);
+        assert_eq!(tool.p95_execution_time_ms, 95.0);
+        assert_eq!(tool.p99_execution_time_ms, 99.0);
+        assert_eq!(tool.min_execution_time_ms, 1.0);
+        assert_eq!(tool.cache_hits, 2);
+        assert!((tool.cache_hit_rate - 200.0 / 3.0).abs() < 1e-9);
+
+        let all = collector.get_all_tool_metrics().await;
+        assert_eq!(all[0].tool_name, "http_get");
+        assert_eq!(all.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tool_error_spike_emits_event_once() {
+        let collector = MetricsCollector::new(true, 1).with_tool_anomaly_config(ToolAnomalyConfig {
+            window_size: 10,
+            min_samples: 5,
+            error_rate_threshold: 0.6,
+        });
+        let mut anomalies = collector.subscribe_tool_anomalies();
+
+        for _ in 0..20 {
+            collector.record_tool_execution("flaky", 10, true).await;
+        }
+        assert!(anomalies.try_recv().is_err());
+
+        for _ in 0..10 {
+            collector.record_tool_execution("flaky", 10, false).await;
+        }
+        let event = anomalies.try_recv().unwrap();
+        assert_eq!(event.tool_name, "flaky");
+        assert!(event.window_error_rate >= 0.6);
+        assert!(event.baseline_error_rate < 0.1);
+        assert!(anomalies.try_recv().is_err());
+    }
 }
@@ -1,9 +1,16 @@
 //! Visual debugging and monitoring interface for AgentGraph
 //! Provides LangSmith and LangGraph Studio equivalent functionality
 
+pub mod actions;
+pub mod auth;
+pub mod chrome_trace;
+pub mod dataset;
 pub mod execution_tracer;
 pub mod graph_visualizer;
+pub mod latency_profile;
 pub mod metrics_collector;
+pub mod playground;
+pub mod state_inspector;
 pub mod web_interface;
 
 use crate::error::GraphResult;
@@ -28,7 +35,8 @@ pub struct VisualizationEngine {
 }
 
 /// Configuration for visualization engine
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
 pub struct VisualizationConfig {
     /// Enable real-time tracing
     pub enable_tracing: bool,
@@ -84,6 +92,11 @@ impl VisualizationEngine {
         })
     }
 
+    /// Create the engine configured by an application manifest's `[studio]` table
+    pub fn from_manifest(manifest: &crate::manifest::AppManifest) -> GraphResult<Self> {
+        Self::new(manifest.studio.clone())
+    }
+
     /// Start the visualization engine
     pub async fn start(&mut self) -> GraphResult<()> {
         tracing::info!("Starting AgentGraph Visualization Engine");
@@ -185,8 +198,12 @@ pub enum VisualEventType {
     AgentResponse,
     /// Tool execution
     ToolExecution,
+    /// Call to an LLM provider, with its request and response
+    LlmCall,
     /// Command routing
     CommandRouting,
+    /// Traversal delayed by an edge rate limit
+    EdgeThrottled,
     /// State update
     StateUpdate,
     /// Execution completed
@@ -311,6 +328,78 @@ pub struct ExecutionTrace {
     pub status: ExecutionStatus,
     /// Error information (if failed)
     pub error: Option<String>,
+    /// Parent execution and checkpoint if this execution was forked
+    #[serde(default)]
+    pub lineage: Option<crate::graph::ExecutionLineage>,
+}
+
+impl ExecutionTrace {
+    /// Nodes completed, in order
+    pub fn completed_nodes(&self) -> Vec<String> {
+        self.events
+            .iter()
+            .filter(|event| matches!(event.event_type, VisualEventType::NodeCompleted))
+            .filter_map(|event| event.node_id.clone())
+            .collect()
+    }
+
+    /// Summary for comparing this execution with its forks
+    pub fn branch_summary(&self) -> BranchSummary {
+        BranchSummary {
+            execution_id: self.execution_id.clone(),
+            branch: self.lineage.as_ref().map(|lineage| lineage.branch.clone()),
+            status: self.status.clone(),
+            duration_ms: self.end_time
+                .map(|end| (end - self.start_time).num_milliseconds().max(0) as u64),
+            completed_nodes: self.completed_nodes(),
+            llm_usage: self.total_llm_usage(),
+            error: self.error.clone(),
+        }
+    }
+
+    /// LLM usage reported by node completion events, keyed by node ID
+    pub fn llm_usage_by_node(&self) -> HashMap<String, crate::llm::LLMUsage> {
+        let mut usage_by_node: HashMap<String, crate::llm::LLMUsage> = HashMap::new();
+        for event in &self.events {
+            if !matches!(event.event_type, VisualEventType::NodeCompleted) {
+                continue;
+            }
+            if let (Some(node_id), Some(usage)) = (&event.node_id, event.data.get("llm_usage")) {
+                if let Ok(usage) = serde_json::from_value::<crate::llm::LLMUsage>(usage.clone()) {
+                    usage_by_node.entry(node_id.clone()).or_default().merge(&usage);
+                }
+            }
+        }
+        usage_by_node
+    }
+
+    /// Total LLM usage across the trace
+    pub fn total_llm_usage(&self) -> crate::llm::LLMUsage {
+        let mut total = crate::llm::LLMUsage::default();
+        for usage in self.llm_usage_by_node().values() {
+            total.merge(usage);
+        }
+        total
+    }
+}
+
+/// One execution in a side-by-side comparison of forked branches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchSummary {
+    /// Execution ID
+    pub execution_id: String,
+    /// Branch name, or `None` for the parent execution
+    pub branch: Option<String>,
+    /// Final status
+    pub status: ExecutionStatus,
+    /// Duration if the execution has ended
+    pub duration_ms: Option<u64>,
+    /// Nodes completed, in order
+    pub completed_nodes: Vec<String>,
+    /// Total LLM usage
+    pub llm_usage: crate::llm::LLMUsage,
+    /// Error information (if failed)
+    pub error: Option<String>,
 }
 
 /// Execution status
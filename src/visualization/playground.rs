@@ -0,0 +1,277 @@
+//! Prompt playground for LLM calls recorded in execution traces
+//! Re-runs one call with edited messages or parameters and diffs the new response against the original
+
+use crate::llm::{CompletionRequest, CompletionResponse, LLMError, LLMManager, LlmCallRecord, Message, MessageRole, TokenUsage};
+use crate::visualization::{ExecutionTrace, VisualEventType};
+use serde::{Deserialize, Serialize};
+
+/// An LLM call found in a trace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracedLlmCall {
+    /// Position among the trace's LLM calls, starting at 0
+    pub index: usize,
+    /// Node that made the call
+    pub node_id: Option<String>,
+    /// When the call was traced
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Request and response as recorded
+    #[serde(flatten)]
+    pub call: LlmCallRecord,
+}
+
+impl ExecutionTrace {
+    /// LLM calls recorded with
+    /// [`ExecutionTracer::trace_llm_call`](crate::visualization::execution_tracer::ExecutionTracer::trace_llm_call),
+    /// in the order they were made
+    pub fn llm_calls(&self) -> Vec<TracedLlmCall> {
+        self.events
+            .iter()
+            .filter(|event| matches!(event.event_type, VisualEventType::LlmCall))
+            .filter_map(|event| {
+                let call = serde_json::from_value(event.data.clone()).ok()?;
+                Some((event, call))
+            })
+            .enumerate()
+            .map(|(index, (event, call))| TracedLlmCall {
+                index,
+                node_id: event.node_id.clone(),
+                timestamp: event.timestamp,
+                call,
+            })
+            .collect()
+    }
+}
+
+/// A message of an edited conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaygroundMessage {
+    /// Who the message is from
+    pub role: MessageRole,
+    /// Text of the message
+    pub content: String,
+}
+
+/// Changes to a recorded call; anything left unset is sent as recorded
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmCallEdit {
+    /// Provider to send the call to instead
+    pub provider: Option<String>,
+    /// Model to use instead
+    pub model: Option<String>,
+    /// Conversation replacing the recorded one, without its function calls
+    pub messages: Option<Vec<PlaygroundMessage>>,
+    /// Sampling temperature
+    pub temperature: Option<f32>,
+    /// Most tokens to generate
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling cutoff
+    pub top_p: Option<f32>,
+    /// Stop sequences
+    pub stop: Option<Vec<String>>,
+}
+
+impl LlmCallEdit {
+    /// The recorded request with the changes applied
+    pub fn apply(&self, recorded: &CompletionRequest) -> CompletionRequest {
+        let mut request = recorded.clone();
+        if let Some(model) = &self.model {
+            request.model = model.clone();
+        }
+        if let Some(messages) = &self.messages {
+            request.messages = messages
+                .iter()
+                .map(|message| Message::new(message.role.clone(), message.content.clone()))
+                .collect();
+        }
+        if self.temperature.is_some() {
+            request.temperature = self.temperature;
+        }
+        if self.max_tokens.is_some() {
+            request.max_tokens = self.max_tokens;
+        }
+        if self.top_p.is_some() {
+            request.top_p = self.top_p;
+        }
+        if self.stop.is_some() {
+            request.stop = self.stop.clone();
+        }
+        // The playground compares whole responses
+        request.stream = false;
+        request
+    }
+}
+
+/// What a provider answered, reduced to what the playground compares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaygroundResponse {
+    /// Model that answered
+    pub model: String,
+    /// Text of the first choice
+    pub content: String,
+    /// Tokens used
+    pub usage: TokenUsage,
+    /// Time the provider took to answer
+    pub duration_ms: u64,
+}
+
+impl PlaygroundResponse {
+    fn new(response: &CompletionResponse, duration_ms: u64) -> Self {
+        Self {
+            model: response.model.clone(),
+            content: response.choices.first().map(|choice| choice.message.content.clone()).unwrap_or_default(),
+            usage: response.usage.clone(),
+            duration_ms,
+        }
+    }
+}
+
+/// A line of the diff between two responses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLine {
+    /// In both responses
+    Same(String),
+    /// Only in the original response
+    Removed(String),
+    /// Only in the new response
+    Added(String),
+}
+
+/// Lines of `new` compared with those of `old`, in order
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Longest common subsequence of lines, filled from the end
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff.extend(old[i..].iter().map(|line| DiffLine::Removed(line.to_string())));
+    diff.extend(new[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+    diff
+}
+
+/// A traced call run again with edits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaygroundRun {
+    /// Position of the call among the trace's LLM calls
+    pub index: usize,
+    /// Node that made the original call
+    pub node_id: Option<String>,
+    /// Provider the call was sent to this time
+    pub provider: String,
+    /// Request sent this time
+    pub request: CompletionRequest,
+    /// Response recorded in the trace
+    pub original: PlaygroundResponse,
+    /// Response to the edited request
+    pub replayed: PlaygroundResponse,
+    /// Whether the text of the responses differs
+    pub changed: bool,
+    /// Line by line comparison of the two responses' text
+    pub diff: Vec<DiffLine>,
+}
+
+/// Send `call` again through `manager` with `edit` applied
+///
+/// The call goes through the manager like any other, so it is subject to
+/// its retries, model profiles and cost limits, and it is billed.
+pub async fn replay(manager: &LLMManager, call: &TracedLlmCall, edit: &LlmCallEdit) -> Result<PlaygroundRun, LLMError> {
+    let provider = edit.provider.clone().unwrap_or_else(|| call.call.provider.clone());
+    let request = edit.apply(&call.call.request);
+
+    let started = std::time::Instant::now();
+    let response = manager.complete_with_provider(&provider, request.clone()).await?;
+    let replayed = PlaygroundResponse::new(&response, started.elapsed().as_millis() as u64);
+    let original = PlaygroundResponse::new(&call.call.response, call.call.duration_ms);
+
+    Ok(PlaygroundRun {
+        index: call.index,
+        node_id: call.node_id.clone(),
+        provider,
+        request,
+        changed: original.content != replayed.content,
+        diff: diff_lines(&original.content, &replayed.content),
+        original,
+        replayed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::providers::MockProvider;
+    use crate::llm::LLMConfig;
+    use crate::visualization::execution_tracer::ExecutionTracer;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_replay_traced_llm_call_with_edits() {
+        let mut manager = LLMManager::new(LLMConfig::default());
+        let mock = MockProvider::with_responses(vec!["Refund approved.\nThanks for waiting.".to_string()])
+            .with_delay(std::time::Duration::ZERO);
+        manager.register_provider("mock".to_string(), Arc::new(mock));
+
+        let request = CompletionRequest {
+            model: "mock-gpt-4".to_string(),
+            messages: vec![Message::user("Can I get a refund?".to_string())],
+            ..Default::default()
+        };
+        let response = manager.complete_with_provider("mock", request.clone()).await.unwrap();
+        let mut response_with_other_text = response.clone();
+        response_with_other_text.choices[0].message.content = "Refund denied.\nThanks for waiting.".to_string();
+
+        let tracer = ExecutionTracer::new(10, true);
+        tracer.start_execution("run".to_string(), "support".to_string()).await.unwrap();
+        let record = LlmCallRecord {
+            provider: "mock".to_string(),
+            request,
+            response: response_with_other_text,
+            duration_ms: 120,
+        };
+        tracer.trace_llm_call("run", "agent", &record).await.unwrap();
+
+        let calls = tracer.get_trace("run").await.unwrap().llm_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].node_id.as_deref(), Some("agent"));
+        assert_eq!(calls[0].call.content(), "Refund denied.\nThanks for waiting.");
+
+        let edit = LlmCallEdit {
+            messages: Some(vec![PlaygroundMessage { role: MessageRole::User, content: "Refund order 42".to_string() }]),
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        let run = replay(&manager, &calls[0], &edit).await.unwrap();
+        assert_eq!(run.request.messages[0].content, "Refund order 42");
+        assert_eq!(run.request.temperature, Some(0.0));
+        assert!(run.changed);
+        assert_eq!(run.diff, vec![
+            DiffLine::Removed("Refund denied.".to_string()),
+            DiffLine::Added("Refund approved.".to_string()),
+            DiffLine::Same("Thanks for waiting.".to_string()),
+        ]);
+    }
+}
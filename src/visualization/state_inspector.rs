@@ -0,0 +1,320 @@
+//! State inspection for the Studio: an execution's current and checkpointed state
+//! Sensitive fields are redacted per the enterprise policy before anything is shown
+
+use crate::enterprise::security::RedactionPolicy;
+use crate::error::{GraphError, GraphResult};
+use crate::graph::control::ExecutionControls;
+use crate::state::checkpointing::Checkpointer;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A checkpoint an execution saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointSummary {
+    /// Checkpoint ID
+    pub id: Uuid,
+    /// Node that had just run
+    pub node: Option<String>,
+    /// Step of the execution
+    pub step: u64,
+}
+
+/// Checkpoints of one graph, with its state type erased
+#[async_trait]
+pub trait StateSource: Send + Sync {
+    /// Checkpoints saved by an execution, in no particular order
+    async fn checkpoints(&self, execution_id: Uuid) -> GraphResult<Vec<CheckpointSummary>>;
+
+    /// State saved in a checkpoint, as JSON
+    async fn state(&self, checkpoint_id: Uuid) -> GraphResult<Value>;
+}
+
+/// [`StateSource`] reading from a graph's checkpointer
+pub struct CheckpointStateSource<S> {
+    checkpointer: Arc<dyn Checkpointer<S>>,
+}
+
+impl<S> CheckpointStateSource<S> {
+    /// Read from `checkpointer`, which must share its storage with the graph's
+    pub fn new(checkpointer: Arc<dyn Checkpointer<S>>) -> Self {
+        Self { checkpointer }
+    }
+}
+
+impl<S> std::fmt::Debug for CheckpointStateSource<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CheckpointStateSource").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<S> StateSource for CheckpointStateSource<S>
+where
+    S: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+{
+    async fn checkpoints(&self, execution_id: Uuid) -> GraphResult<Vec<CheckpointSummary>> {
+        let execution_id = Value::String(execution_id.to_string());
+        let mut checkpoints = Vec::new();
+        for id in self.checkpointer.list_snapshots().await? {
+            let metadata = self.checkpointer.get_metadata(id).await?;
+            if metadata.custom.get("execution_id") == Some(&execution_id) {
+                checkpoints.push(CheckpointSummary { id, node: metadata.current_node, step: metadata.step });
+            }
+        }
+        Ok(checkpoints)
+    }
+
+    async fn state(&self, checkpoint_id: Uuid) -> GraphResult<Value> {
+        let snapshot = self.checkpointer.load(checkpoint_id).await?;
+        Ok(serde_json::to_value(snapshot.state)?)
+    }
+}
+
+/// An execution's state as shown to a user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InspectedState {
+    /// Execution the state belongs to
+    pub execution_id: Uuid,
+    /// Checkpoint it was read from; `None` for the state a failed execution stopped in
+    pub checkpoint: Option<CheckpointSummary>,
+    /// The state, redacted
+    pub state: Value,
+}
+
+/// How a field differs between two checkpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Only in the later checkpoint
+    Added,
+    /// Only in the earlier checkpoint
+    Removed,
+    /// In both, with different values
+    Changed,
+}
+
+/// A field that differs between two checkpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// JSON pointer to the field
+    pub path: String,
+    /// How it differs
+    pub kind: ChangeKind,
+    /// Value in the earlier checkpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    /// Value in the later checkpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+/// Reads executions' state for the Studio, redacting it on the way out
+#[derive(Clone, Default)]
+pub struct StateInspector {
+    sources: Vec<Arc<dyn StateSource>>,
+    controls: Option<ExecutionControls>,
+    redaction: RedactionPolicy,
+}
+
+impl std::fmt::Debug for StateInspector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateInspector")
+            .field("sources", &self.sources.len())
+            .field("redaction", &self.redaction)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StateInspector {
+    /// Create an inspector redacting with `redaction`, usually
+    /// `EnterpriseConfig::security.redaction`
+    pub fn new(redaction: RedactionPolicy) -> Self {
+        Self { redaction, ..Self::default() }
+    }
+
+    /// Read checkpoints from `source`
+    pub fn with_source(mut self, source: Arc<dyn StateSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Read checkpoints from a graph's checkpointer
+    pub fn with_checkpointer<S>(self, checkpointer: Arc<dyn Checkpointer<S>>) -> Self
+    where
+        S: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        self.with_source(Arc::new(CheckpointStateSource::new(checkpointer)))
+    }
+
+    /// Show the state failed executions stopped in as their current state
+    pub fn with_controls(mut self, controls: ExecutionControls) -> Self {
+        self.controls = Some(controls);
+        self
+    }
+
+    /// Policy applied to every state shown
+    pub fn redaction(&self) -> &RedactionPolicy {
+        &self.redaction
+    }
+
+    /// Checkpoints an execution saved with where they are, oldest first
+    async fn located(&self, execution_id: Uuid) -> GraphResult<Vec<(Arc<dyn StateSource>, CheckpointSummary)>> {
+        let mut checkpoints = Vec::new();
+        for source in &self.sources {
+            for checkpoint in source.checkpoints(execution_id).await? {
+                checkpoints.push((source.clone(), checkpoint));
+            }
+        }
+        checkpoints.sort_by_key(|(_, checkpoint)| checkpoint.step);
+        Ok(checkpoints)
+    }
+
+    /// Summaries of the checkpoints an execution saved, oldest first
+    pub async fn list(&self, execution_id: Uuid) -> GraphResult<Vec<CheckpointSummary>> {
+        Ok(self.located(execution_id).await?.into_iter().map(|(_, checkpoint)| checkpoint).collect())
+    }
+
+    /// State of an execution at a checkpoint, or its current state
+    ///
+    /// The current state is the state a failed execution stopped in, else
+    /// the latest checkpoint. `None` when there is nothing to show.
+    pub async fn state(&self, execution_id: Uuid, checkpoint_id: Option<Uuid>) -> GraphResult<Option<InspectedState>> {
+        if checkpoint_id.is_none() {
+            if let Some(failed) = self.controls.as_ref().and_then(|controls| controls.failed_execution(execution_id)) {
+                return Ok(Some(InspectedState {
+                    execution_id,
+                    checkpoint: None,
+                    state: self.redaction.redacted(&failed.state),
+                }));
+            }
+        }
+
+        let checkpoints = self.located(execution_id).await?;
+        let found = match checkpoint_id {
+            Some(id) => checkpoints.into_iter().find(|(_, checkpoint)| checkpoint.id == id),
+            None => checkpoints.into_iter().last(),
+        };
+        let Some((source, checkpoint)) = found else {
+            return Ok(None);
+        };
+        let mut state = source.state(checkpoint.id).await?;
+        self.redaction.redact(&mut state);
+        Ok(Some(InspectedState { execution_id, checkpoint: Some(checkpoint), state }))
+    }
+
+    /// Fields that differ between two of an execution's checkpoints
+    ///
+    /// Both states are redacted first, so a redacted field only shows up
+    /// when it was added or removed.
+    pub async fn diff(&self, execution_id: Uuid, from: Uuid, to: Uuid) -> GraphResult<Vec<FieldChange>> {
+        let mut states = Vec::with_capacity(2);
+        for id in [from, to] {
+            let inspected = self.state(execution_id, Some(id)).await?
+                .ok_or_else(|| GraphError::validation_error(format!("Execution {} has no checkpoint {}", execution_id, id)))?;
+            states.push(inspected.state);
+        }
+        Ok(diff(&states[0], &states[1]))
+    }
+}
+
+/// Field-level differences between two JSON values
+///
+/// Objects are compared key by key, arrays index by index; anything else that
+/// differs is one change.
+pub fn diff(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    diff_at(before, after, &mut String::new(), &mut changes);
+    changes
+}
+
+fn diff_at(before: &Value, after: &Value, path: &mut String, changes: &mut Vec<FieldChange>) {
+    if before == after {
+        return;
+    }
+    let len = path.len();
+    let child = |path: &mut String, key: &str, before: Option<&Value>, after: Option<&Value>, changes: &mut Vec<FieldChange>| {
+        path.push('/');
+        path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+        match (before, after) {
+            (Some(before), Some(after)) => diff_at(before, after, path, changes),
+            (before, after) => changes.push(FieldChange {
+                path: path.clone(),
+                kind: if before.is_some() { ChangeKind::Removed } else { ChangeKind::Added },
+                before: before.cloned(),
+                after: after.cloned(),
+            }),
+        }
+        path.truncate(len);
+    };
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, value) in before {
+                child(path, key, Some(value), after.get(key), changes);
+            }
+            for (key, value) in after.iter().filter(|(key, _)| !before.contains_key(*key)) {
+                child(path, key, None, Some(value), changes);
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for index in 0..before.len().max(after.len()) {
+                child(path, &index.to_string(), before.get(index), after.get(index), changes);
+            }
+        }
+        _ => changes.push(FieldChange {
+            path: path.clone(),
+            kind: ChangeKind::Changed,
+            before: Some(before.clone()),
+            after: Some(after.clone()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::checkpointing::MemoryCheckpointer;
+    use crate::state::{SnapshotMetadata, StateSnapshot};
+
+    #[tokio::test]
+    async fn test_redacted_state_and_checkpoint_diff() {
+        let checkpointer: Arc<dyn Checkpointer<Value>> = Arc::new(MemoryCheckpointer::new());
+        let execution_id = Uuid::new_v4();
+        let mut ids = Vec::new();
+        let states = [
+            serde_json::json!({ "answer": null, "api_key": "sk-1", "messages": ["hi"] }),
+            serde_json::json!({ "answer": "hello", "api_key": "sk-2", "messages": ["hi", "hello"], "done": true }),
+        ];
+        for (step, state) in states.into_iter().enumerate() {
+            let metadata = SnapshotMetadata {
+                current_node: Some(format!("node{}", step)),
+                step: step as u64 + 1,
+                custom: [("execution_id".to_string(), serde_json::json!(execution_id))].into_iter().collect(),
+                ..SnapshotMetadata::default()
+            };
+            let snapshot = StateSnapshot::with_metadata(state, metadata);
+            checkpointer.save(&snapshot).await.unwrap();
+            ids.push(snapshot.id);
+        }
+        let inspector = StateInspector::new(RedactionPolicy::default()).with_checkpointer(checkpointer);
+
+        let listed = inspector.list(execution_id).await.unwrap();
+        assert_eq!(listed.iter().map(|checkpoint| checkpoint.id).collect::<Vec<_>>(), ids);
+        assert!(inspector.list(Uuid::new_v4()).await.unwrap().is_empty());
+
+        let current = inspector.state(execution_id, None).await.unwrap().unwrap();
+        assert_eq!(current.checkpoint.unwrap().id, ids[1]);
+        assert_eq!(current.state["api_key"], "[REDACTED]");
+
+        let changes = inspector.diff(execution_id, ids[0], ids[1]).await.unwrap();
+        let paths: Vec<_> = changes.iter().map(|change| (change.path.as_str(), change.kind)).collect();
+        assert_eq!(paths, vec![
+            ("/answer", ChangeKind::Changed),
+            ("/messages/1", ChangeKind::Added),
+            ("/done", ChangeKind::Added),
+        ]);
+        assert!(inspector.diff(execution_id, ids[0], Uuid::new_v4()).await.is_err());
+    }
+}
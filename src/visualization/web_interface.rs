@@ -2,7 +2,19 @@
 //! Provides LangGraph Studio and LangSmith equivalent web dashboard
 
 use crate::error::GraphResult;
-use crate::visualization::{execution_tracer::ExecutionTracer, graph_visualizer::GraphVisualizer, metrics_collector::MetricsCollector};
+use crate::enterprise::audit::AuditLogger;
+use crate::graph::control::{ExecutionControls, LiveExecution, RetryRequest};
+use crate::graph::cost::{CostEstimator, TokenHistory};
+use crate::graph::registry::GraphRegistry;
+use crate::human::approval::{ApprovalDecision, ApprovalManager, ApprovalResponse};
+use crate::llm::LLMManager;
+use crate::state::artifacts::ArtifactStore;
+use crate::visualization::dataset::{DatasetExporter, DatasetFilter};
+use crate::visualization::playground::{self, LlmCallEdit};
+use crate::visualization::actions::{ActionTarget, SnapshotRef, StudioAction, StudioRbac, APPROVAL_GRAPH_KEY};
+use crate::visualization::auth::{handle_rejection, with_session, StudioAccess, StudioAuth, StudioSession};
+use crate::visualization::state_inspector::StateInspector;
+use crate::visualization::{execution_tracer::ExecutionTracer, graph_visualizer::GraphVisualizer, metrics_collector::MetricsCollector, ExecutionTrace};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -24,6 +36,22 @@ pub struct WebServer {
     server_handle: Option<tokio::task::JoinHandle<()>>,
     /// Active workflows
     workflows: Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>,
+    /// Pre-flight cost estimators by workflow ID
+    cost_estimators: Arc<RwLock<HashMap<String, CostEstimator>>>,
+    /// Where executions keep their artifacts
+    artifacts: Option<Arc<dyn ArtifactStore>>,
+    /// Who may see and do what
+    access: Arc<StudioAccess>,
+    /// Handles for cancelling, pausing and retrying executions
+    controls: Option<ExecutionControls>,
+    /// Reads executions' state, redacted
+    inspector: Option<Arc<StateInspector>>,
+    /// Registered graph definitions
+    graphs: Option<Arc<GraphRegistry>>,
+    /// Providers traced LLM calls are replayed against
+    llm: Option<Arc<LLMManager>>,
+    /// Approval requests answered from the Studio
+    approvals: Option<Arc<ApprovalManager>>,
 }
 
 impl WebServer {
@@ -41,19 +69,109 @@ impl WebServer {
             metrics,
             server_handle: None,
             workflows: Arc::new(RwLock::new(HashMap::new())),
+            cost_estimators: Arc::new(RwLock::new(HashMap::new())),
+            artifacts: None,
+            access: Arc::new(StudioAccess::default()),
+            controls: None,
+            inspector: None,
+            graphs: None,
+            llm: None,
+            approvals: None,
         })
     }
 
+    /// Serve the artifacts of executions at `GET /api/executions/{id}/artifacts`
+    pub fn set_artifact_store(&mut self, store: Arc<dyn ArtifactStore>) {
+        self.artifacts = Some(store);
+    }
+
+    /// Serve live executions at `GET /api/executions` and let operators
+    /// cancel, pause, resume and retry them
+    ///
+    /// Give the same controls to the graphs with
+    /// [`Graph::set_controls`](crate::graph::Graph::set_controls).
+    pub fn set_execution_controls(&mut self, controls: ExecutionControls) {
+        self.controls = Some(controls);
+    }
+
+    /// Serve executions' redacted state at `GET /api/executions/{id}/state`,
+    /// their checkpoints and diffs between them
+    pub fn set_state_inspector(&mut self, inspector: StateInspector) {
+        self.inspector = Some(Arc::new(inspector));
+    }
+
+    /// Serve the gallery of registered graphs at `GET /api/graphs`, with
+    /// their Mermaid rendering, versions, run statistics and manual runs
+    pub fn set_graph_registry(&mut self, registry: Arc<GraphRegistry>) {
+        self.graphs = Some(registry);
+    }
+
+    /// Replay LLM calls recorded in traces with edits at
+    /// `POST /api/traces/{id}/llm_calls/{index}/replay`, diffing the new
+    /// response against the recorded one
+    pub fn set_llm_manager(&mut self, manager: Arc<LLMManager>) {
+        self.llm = Some(manager);
+    }
+
+    /// List pending approval requests at `GET /api/approvals` and answer
+    /// them at `POST /api/approvals/{id}`
+    ///
+    /// Requests are shown to users who may see the graph named by the
+    /// `graph` entry of their context; requests without one only to unscoped users.
+    pub fn set_approval_manager(&mut self, manager: Arc<ApprovalManager>) {
+        self.approvals = Some(manager);
+    }
+
+    /// Decide which users may cancel, pause, resume, retry, edit state,
+    /// run graphs, replay LLM calls and approve; operators may do all of it by default
+    pub fn set_rbac(&self, rbac: StudioRbac) {
+        self.access.set_rbac(rbac);
+    }
+
+    /// Record every action taken through the Studio, with its actor, target
+    /// and before/after snapshots, and every attempt refused
+    pub fn set_audit_logger(&self, logger: Arc<AuditLogger>) {
+        self.access.set_audit_logger(logger);
+    }
+
+    /// Require API keys or OIDC tokens on every route
+    ///
+    /// Without it the API is open to anyone who can reach the port.
+    pub fn set_auth(&self, auth: StudioAuth) {
+        self.access.set_auth(Arc::new(auth));
+    }
+
+    /// Show a workflow's traces and artifacts only to users of `tenant` and unscoped users
+    pub fn assign_tenant(&self, workflow_id: impl Into<String>, tenant: impl Into<String>) {
+        self.access.assign_tenant(workflow_id, tenant);
+    }
+
+    /// Serve cost estimates for a workflow at `POST /api/workflows/{id}/estimate`
+    pub async fn register_cost_estimator(&self, workflow_id: impl Into<String>, estimator: CostEstimator) {
+        self.cost_estimators.write().await.insert(workflow_id.into(), estimator);
+    }
+
     /// Start the web server
     pub async fn start(&mut self) -> GraphResult<()> {
         let tracer = self.tracer.clone();
         let visualizer = self.visualizer.clone();
         let metrics = self.metrics.clone();
         let workflows = self.workflows.clone();
+        let cost_estimators = self.cost_estimators.clone();
+        let artifacts = self.artifacts.clone();
+        let access = self.access.clone();
+        let controls = self.controls.clone();
+        let inspector = self.inspector.clone();
+        let graphs = self.graphs.clone();
+        let llm = self.llm.clone();
+        let approvals = self.approvals.clone();
         let port = self.port;
+        if access.is_open() {
+            tracing::warn!("Studio API authentication is disabled; set_auth before exposing the server");
+        }
 
         // Create routes
-        let routes = self.create_routes(tracer, visualizer, metrics, workflows).await;
+        let routes = self.create_routes(tracer, visualizer, metrics, workflows, cost_estimators, artifacts, access, controls, inspector, graphs, llm, approvals).await;
 
         // Start server
         let server = warp::serve(routes).run(([127, 0, 0, 1], port));
@@ -80,7 +198,17 @@ impl WebServer {
         visualizer: Arc<GraphVisualizer>,
         metrics: Arc<MetricsCollector>,
         workflows: Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>,
+        cost_estimators: Arc<RwLock<HashMap<String, CostEstimator>>>,
+        artifacts: Option<Arc<dyn ArtifactStore>>,
+        access: Arc<StudioAccess>,
+        controls: Option<ExecutionControls>,
+        inspector: Option<Arc<StateInspector>>,
+        graphs: Option<Arc<GraphRegistry>>,
+        llm: Option<Arc<LLMManager>>,
+        approvals: Option<Arc<ApprovalManager>>,
     ) -> impl Filter<Extract = impl Reply> + Clone {
+        let session = with_session(access);
+
         // API routes only - frontend is served by Next.js
         let api = warp::path("api");
 
@@ -89,6 +217,7 @@ impl WebServer {
             .and(warp::path("traces"))
             .and(warp::path::end())
             .and(warp::get())
+            .and(session.clone())
             .and(with_tracer(tracer.clone()))
             .and_then(get_traces);
 
@@ -98,47 +227,306 @@ impl WebServer {
             .and(warp::path::param::<String>())
             .and(warp::path::end())
             .and(warp::get())
+            .and(session.clone())
             .and(with_tracer(tracer.clone()))
             .and_then(get_trace);
 
+        // Re-run an LLM call of a trace with edited messages or parameters
+        let replay_route = api
+            .and(warp::path("traces"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("llm_calls"))
+            .and(warp::path::param::<usize>())
+            .and(warp::path("replay"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(session.clone())
+            .and(warp::body::json())
+            .and(with_tracer(tracer.clone()))
+            .and(warp::any().map(move || llm.clone()))
+            .and_then(replay_llm_call);
+
+        // Export traced node inputs and outputs as an eval dataset
+        let dataset_route = api
+            .and(warp::path("datasets"))
+            .and(warp::path("export"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(warp::query::<DatasetQuery>())
+            .and(with_tracer(tracer.clone()))
+            .and(with_inspector(inspector.clone()))
+            .and_then(export_dataset);
+
         // Get workflows
         let workflows_route = api
             .and(warp::path("workflows"))
             .and(warp::path::end())
             .and(warp::get())
+            .and(session.clone())
             .and(with_workflows(workflows.clone()))
             .and_then(get_workflows);
 
+        // Estimate the cost of running a workflow on the posted input state
+        let estimate_route = api
+            .and(warp::path("workflows"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("estimate"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(session.clone())
+            .and(warp::body::json())
+            .and(with_tracer(tracer.clone()))
+            .and(warp::any().map(move || cost_estimators.clone()))
+            .and_then(estimate_workflow_cost);
+
+        // List the artifacts an execution produced
+        let artifacts_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("artifacts"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_artifacts(artifacts.clone()))
+            .and(with_tracer(tracer.clone()))
+            .and_then(list_artifacts);
+
+        // Download an artifact with its content type
+        let artifact_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("artifacts"))
+            .and(warp::path::param::<String>())
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_artifacts(artifacts))
+            .and(with_tracer(tracer.clone()))
+            .and_then(get_artifact);
+
+        // List executions in progress
+        let executions_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_controls(controls.clone()))
+            .and(with_inspector(inspector.clone()))
+            .and_then(list_executions);
+
+        // Cancel, pause or resume an execution in progress
+        let control_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path::param::<String>())
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(session.clone())
+            .and(with_controls(controls.clone()))
+            .and(with_inspector(inspector.clone()))
+            .and_then(control_execution);
+
+        // Retry a failed execution from a node, optionally with an edited state
+        let retry_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("retry"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(session.clone())
+            .and(warp::body::json())
+            .and(with_controls(controls))
+            .and_then(retry_execution);
+
+        // Pending approval requests the user may answer
+        let approvals_route = api
+            .and(warp::path("approvals"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_approvals(approvals.clone()))
+            .and_then(list_approvals);
+
+        // Approve or reject a request
+        let approve_route = api
+            .and(warp::path("approvals"))
+            .and(warp::path::param::<String>())
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(session.clone())
+            .and(warp::body::json())
+            .and(with_approvals(approvals))
+            .and_then(answer_approval);
+
+        // List the checkpoints an execution saved
+        let checkpoints_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("checkpoints"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_inspector(inspector.clone()))
+            .and(with_tracer(tracer.clone()))
+            .and_then(list_checkpoints);
+
+        // Show an execution's current or checkpointed state, redacted
+        let state_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("state"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(warp::query::<StateQuery>())
+            .and(with_inspector(inspector.clone()))
+            .and(with_tracer(tracer.clone()))
+            .and_then(get_execution_state);
+
+        // Diff the state of two checkpoints field by field
+        let state_diff_route = api
+            .and(warp::path("executions"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("state"))
+            .and(warp::path("diff"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(warp::query::<StateDiffQuery>())
+            .and(with_inspector(inspector.clone()))
+            .and(with_tracer(tracer.clone()))
+            .and_then(diff_execution_state);
+
+        // Browse the registered graphs
+        let graphs_route = api
+            .and(warp::path("graphs"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_graphs(graphs.clone()))
+            .and_then(list_graphs);
+
+        // Show a graph with its Mermaid rendering, statistics and recent runs
+        let graph_route = api
+            .and(warp::path("graphs"))
+            .and(warp::path::param::<String>())
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_graphs(graphs.clone()))
+            .and_then(get_graph);
+
+        // Render a graph as Mermaid
+        let graph_mermaid_route = api
+            .and(warp::path("graphs"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("mermaid"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_graphs(graphs.clone()))
+            .and_then(get_graph_mermaid);
+
+        // Every version of a graph registered
+        let graph_versions_route = api
+            .and(warp::path("graphs"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("versions"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_graphs(graphs.clone()))
+            .and_then(get_graph_versions);
+
+        // Run a graph once on the posted input state
+        let graph_run_route = api
+            .and(warp::path("graphs"))
+            .and(warp::path::param::<String>())
+            .and(warp::path("run"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(session.clone())
+            .and(warp::body::json())
+            .and(with_graphs(graphs))
+            .and(with_inspector(inspector.clone()))
+            .and_then(run_graph);
+
         // Get metrics
         let metrics_route = api
             .and(warp::path("metrics"))
             .and(warp::path::end())
             .and(warp::get())
+            .and(session.clone())
             .and(with_metrics(metrics.clone()))
             .and_then(get_metrics);
 
+        // Get per-tool metrics
+        let tool_metrics_route = api
+            .and(warp::path("metrics"))
+            .and(warp::path("tools"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_metrics(metrics.clone()))
+            .and_then(get_tool_metrics);
+
+        // Get metrics for a specific tool
+        let single_tool_metrics_route = api
+            .and(warp::path("metrics"))
+            .and(warp::path("tools"))
+            .and(warp::path::param::<String>())
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(session.clone())
+            .and(with_metrics(metrics.clone()))
+            .and_then(get_single_tool_metrics);
+
         // WebSocket for real-time events
         let events_ws = api
             .and(warp::path("events"))
             .and(warp::path::end())
             .and(warp::ws())
+            .and(session)
             .and(with_tracer(tracer))
-            .map(|ws: warp::ws::Ws, tracer: Arc<ExecutionTracer>| {
-                ws.on_upgrade(move |socket| handle_websocket(socket, tracer))
+            .and(with_metrics(metrics))
+            .map(|ws: warp::ws::Ws, session: StudioSession, tracer: Arc<ExecutionTracer>, metrics: Arc<MetricsCollector>| {
+                ws.on_upgrade(move |socket| handle_websocket(socket, session, tracer, metrics))
             });
 
         // CORS
         let cors = warp::cors()
             .allow_any_origin()
-            .allow_headers(vec!["content-type"])
+            .allow_headers(vec!["content-type", "authorization"])
             .allow_methods(vec!["GET", "POST", "PUT", "DELETE"]);
 
         // Only API routes - no static files or dashboard
         traces_route
             .or(trace_route)
+            .or(replay_route)
+            .or(dataset_route)
             .or(workflows_route)
+            .or(estimate_route)
+            .or(artifacts_route)
+            .or(artifact_route)
+            .or(executions_route)
+            .or(retry_route)
+            .or(control_route)
+            .or(approvals_route)
+            .or(approve_route)
+            .or(checkpoints_route)
+            .or(state_route)
+            .or(state_diff_route)
+            .or(graphs_route)
+            .or(graph_route)
+            .or(graph_mermaid_route)
+            .or(graph_versions_route)
+            .or(graph_run_route)
             .or(metrics_route)
+            .or(tool_metrics_route)
+            .or(single_tool_metrics_route)
             .or(events_ws)
+            .recover(handle_rejection)
             .with(cors)
     }
 
@@ -146,51 +534,668 @@ impl WebServer {
 }
 
 // Helper functions for warp filters
-fn with_tracer(tracer: Arc<ExecutionTracer>) -> impl Filter<Extract = (Arc<ExecutionTracer>,)> + Clone {
+fn with_tracer(tracer: Arc<ExecutionTracer>) -> impl Filter<Extract = (Arc<ExecutionTracer>,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || tracer.clone())
 }
 
-fn with_workflows(workflows: Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>) -> impl Filter<Extract = (Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>,)> + Clone {
+fn with_workflows(workflows: Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>) -> impl Filter<Extract = (Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || workflows.clone())
 }
 
-fn with_metrics(metrics: Arc<MetricsCollector>) -> impl Filter<Extract = (Arc<MetricsCollector>,)> + Clone {
+fn with_artifacts(artifacts: Option<Arc<dyn ArtifactStore>>) -> impl Filter<Extract = (Option<Arc<dyn ArtifactStore>>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || artifacts.clone())
+}
+
+fn with_controls(controls: Option<ExecutionControls>) -> impl Filter<Extract = (Option<ExecutionControls>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || controls.clone())
+}
+
+fn with_inspector(inspector: Option<Arc<StateInspector>>) -> impl Filter<Extract = (Option<Arc<StateInspector>>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || inspector.clone())
+}
+
+fn with_approvals(approvals: Option<Arc<ApprovalManager>>) -> impl Filter<Extract = (Option<Arc<ApprovalManager>>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || approvals.clone())
+}
+
+fn with_graphs(graphs: Option<Arc<GraphRegistry>>) -> impl Filter<Extract = (Option<Arc<GraphRegistry>>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || graphs.clone())
+}
+
+fn with_metrics(metrics: Arc<MetricsCollector>) -> impl Filter<Extract = (Arc<MetricsCollector>,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || metrics.clone())
 }
 
+/// Whether the user may see a trace: traces recorded for a tenant belong to
+/// its users, others to the users who may see their workflow
+fn can_view_trace(session: &StudioSession, tracer: &ExecutionTracer, trace: &ExecutionTrace) -> bool {
+    match (tracer.tenant_of(&trace.execution_id), &session.user.tenant) {
+        (Some(owner), Some(tenant)) => owner == *tenant,
+        (Some(_), None) => true,
+        (None, _) => session.can_view(&trace.workflow_id),
+    }
+}
+
 // API handlers
-async fn get_traces(tracer: Arc<ExecutionTracer>) -> Result<impl Reply, warp::Rejection> {
-    let traces = tracer.get_all_traces().await;
+async fn get_traces(session: StudioSession, tracer: Arc<ExecutionTracer>) -> Result<impl Reply, warp::Rejection> {
+    let mut traces = tracer.get_all_traces().await;
+    traces.retain(|trace| can_view_trace(&session, &tracer, trace));
     Ok(warp::reply::json(&traces))
 }
 
-async fn get_trace(trace_id: String, tracer: Arc<ExecutionTracer>) -> Result<impl Reply, warp::Rejection> {
-    match tracer.get_trace(&trace_id).await {
+async fn replay_llm_call(
+    trace_id: String,
+    index: usize,
+    session: StudioSession,
+    edit: LlmCallEdit,
+    tracer: Arc<ExecutionTracer>,
+    llm: Option<Arc<LLMManager>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let Some(llm) = llm else {
+        return Err(warp::reject::not_found());
+    };
+    let trace = tracer.get_trace(&trace_id).await
+        .filter(|trace| can_view_trace(&session, &tracer, trace))
+        .ok_or_else(warp::reject::not_found)?;
+    // Replays are billed, so they take more than viewing the trace
+    let target = ActionTarget::llm_call(&trace_id, index, &trace.workflow_id);
+    session.authorize(StudioAction::ReplayLlmCall, &target).await?;
+    let call = trace.llm_calls().into_iter().nth(index).ok_or_else(warp::reject::not_found)?;
+    tracing::info!(execution_id = %trace_id, index, user = %session.user.id, "LLM call replay requested");
+    match playground::replay(&llm, &call, &edit).await {
+        Ok(run) => {
+            session.record(StudioAction::ReplayLlmCall, &target, None, Ok(None)).await;
+            Ok(warp::reply::json(&run).into_response())
+        }
+        Err(error) => {
+            session.record(StudioAction::ReplayLlmCall, &target, None, Err(&error.to_string())).await;
+            Ok(control_error(warp::http::StatusCode::BAD_GATEWAY, error))
+        }
+    }
+}
+
+async fn get_trace(trace_id: String, session: StudioSession, tracer: Arc<ExecutionTracer>) -> Result<impl Reply, warp::Rejection> {
+    // Traces of other tenants look like missing ones
+    match tracer.get_trace(&trace_id).await.filter(|trace| can_view_trace(&session, &tracer, trace)) {
         Some(trace) => Ok(warp::reply::json(&trace)),
         None => Ok(warp::reply::json(&serde_json::json!({"error": "Trace not found"}))),
     }
 }
 
-async fn get_workflows(workflows: Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>) -> Result<impl Reply, warp::Rejection> {
+#[derive(Debug, Deserialize)]
+struct DatasetQuery {
+    /// Also export from failed and cancelled executions
+    #[serde(default)]
+    include_failed: bool,
+    /// Comma-separated node IDs
+    nodes: Option<String>,
+    workflow: Option<String>,
+}
+
+async fn export_dataset(
+    session: StudioSession,
+    query: DatasetQuery,
+    tracer: Arc<ExecutionTracer>,
+    inspector: Option<Arc<StateInspector>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let mut filter = DatasetFilter::new();
+    if query.include_failed {
+        filter = filter.with_failed_runs();
+    }
+    for node in query.nodes.iter().flat_map(|nodes| nodes.split(',')).map(str::trim).filter(|node| !node.is_empty()) {
+        filter = filter.with_node(node);
+    }
+    if let Some(workflow) = query.workflow {
+        filter = filter.with_workflow(workflow);
+    }
+    // Redact what the inspector would, so exports show no more than the Studio does
+    let mut exporter = DatasetExporter::new(filter);
+    if let Some(inspector) = &inspector {
+        exporter = exporter.with_redaction(inspector.redaction().clone());
+    }
+
+    let mut traces = tracer.get_all_traces().await;
+    traces.retain(|trace| can_view_trace(&session, &tracer, trace));
+    let mut body = Vec::new();
+    match exporter.write_jsonl(&traces, &mut body) {
+        Ok(examples) => {
+            tracing::info!(user = %session.user.id, examples, "Eval dataset exported");
+            let mut response = warp::reply::Response::new(body.into());
+            let headers = response.headers_mut();
+            headers.insert(warp::http::header::CONTENT_TYPE, warp::http::HeaderValue::from_static("application/x-ndjson"));
+            headers.insert(
+                warp::http::header::CONTENT_DISPOSITION,
+                warp::http::HeaderValue::from_static("attachment; filename=\"dataset.jsonl\""),
+            );
+            Ok(response)
+        }
+        Err(error) => Ok(control_error(warp::http::StatusCode::INTERNAL_SERVER_ERROR, error)),
+    }
+}
+
+async fn get_workflows(
+    session: StudioSession,
+    workflows: Arc<RwLock<HashMap<String, crate::visualization::VisualWorkflow>>>,
+) -> Result<impl Reply, warp::Rejection> {
     let workflows = workflows.read().await;
-    let workflow_list: Vec<_> = workflows.values().collect();
+    let workflow_list: Vec<_> = workflows.values().filter(|workflow| session.can_view(&workflow.id)).collect();
     Ok(warp::reply::json(&workflow_list))
 }
 
-async fn get_metrics(metrics: Arc<MetricsCollector>) -> Result<impl Reply, warp::Rejection> {
+async fn estimate_workflow_cost(
+    workflow_id: String,
+    session: StudioSession,
+    input: serde_json::Value,
+    tracer: Arc<ExecutionTracer>,
+    cost_estimators: Arc<RwLock<HashMap<String, CostEstimator>>>,
+) -> Result<impl Reply, warp::Rejection> {
+    if !session.can_view(&workflow_id) {
+        return Ok(warp::reply::json(&serde_json::json!({"error": "No cost estimator for workflow"})));
+    }
+    let Some(estimator) = cost_estimators.read().await.get(&workflow_id).cloned() else {
+        return Ok(warp::reply::json(&serde_json::json!({"error": "No cost estimator for workflow"})));
+    };
+    let traces = tracer.get_all_traces().await;
+    let history = TokenHistory::from_traces(traces.iter().filter(|trace| trace.workflow_id == workflow_id));
+    match estimator.estimate(input, &history) {
+        Ok(estimate) => Ok(warp::reply::json(&estimate)),
+        Err(error) => Ok(warp::reply::json(&serde_json::json!({"error": error.to_string()}))),
+    }
+}
+
+/// Whether the user may see an execution's data; executions whose trace is
+/// gone are only visible to unscoped users
+async fn can_view_execution(session: &StudioSession, tracer: &ExecutionTracer, execution_id: &str) -> bool {
+    match tracer.get_trace(execution_id).await {
+        Some(trace) => can_view_trace(session, tracer, &trace),
+        None => session.user.tenant.is_none(),
+    }
+}
+
+async fn list_artifacts(
+    execution_id: String,
+    session: StudioSession,
+    artifacts: Option<Arc<dyn ArtifactStore>>,
+    tracer: Arc<ExecutionTracer>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if !can_view_execution(&session, &tracer, &execution_id).await {
+        return Err(warp::reject::not_found());
+    }
+    let Some(store) = artifacts else {
+        return Ok(warp::reply::json(&serde_json::json!({"error": "No artifact store configured"})).into_response());
+    };
+    match store.list(&execution_id).await {
+        Ok(listed) => Ok(warp::reply::json(&listed).into_response()),
+        Err(error) => Ok(warp::reply::json(&serde_json::json!({"error": error.to_string()})).into_response()),
+    }
+}
+
+async fn get_artifact(
+    execution_id: String,
+    artifact_id: String,
+    session: StudioSession,
+    artifacts: Option<Arc<dyn ArtifactStore>>,
+    tracer: Arc<ExecutionTracer>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if !can_view_execution(&session, &tracer, &execution_id).await {
+        return Err(warp::reject::not_found());
+    }
+    let Some(store) = artifacts else {
+        return Err(warp::reject::not_found());
+    };
+    match store.get(&execution_id, &artifact_id).await {
+        Ok(Some((artifact, bytes))) => {
+            let mut response = warp::reply::Response::new(bytes.into());
+            let headers = response.headers_mut();
+            if let Ok(content_type) = artifact.content_type.parse() {
+                headers.insert(warp::http::header::CONTENT_TYPE, content_type);
+            }
+            let disposition = format!("inline; filename=\"{}\"", artifact.name.replace(['"', '\\', '\r', '\n'], "_"));
+            if let Ok(disposition) = disposition.parse() {
+                headers.insert(warp::http::header::CONTENT_DISPOSITION, disposition);
+            }
+            // Stop browsers from sniffing uploaded HTML or SVG into something else
+            headers.insert(warp::http::header::X_CONTENT_TYPE_OPTIONS, warp::http::HeaderValue::from_static("nosniff"));
+            Ok(response)
+        }
+        Ok(None) => Err(warp::reject::not_found()),
+        Err(error) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"error": error.to_string()})),
+            warp::http::StatusCode::BAD_REQUEST,
+        ).into_response()),
+    }
+}
+
+async fn list_executions(
+    session: StudioSession,
+    controls: Option<ExecutionControls>,
+    inspector: Option<Arc<StateInspector>>,
+) -> Result<impl Reply, warp::Rejection> {
+    let Some(controls) = controls else {
+        return Err(warp::reject::not_found());
+    };
+    let live: Vec<_> = controls.list().into_iter().filter(|live| session.can_view(&live.graph)).collect();
+    let mut failed: Vec<_> = controls.failed().into_iter().filter(|failed| session.can_view(&failed.graph)).collect();
+    // Failed states are redacted as the state endpoints redact them, and left
+    // out when there is no inspector to redact them with
+    for failed in &mut failed {
+        match &inspector {
+            Some(inspector) => inspector.redaction().redact(&mut failed.state),
+            None => failed.state = serde_json::Value::Null,
+        }
+    }
+    Ok(warp::reply::json(&serde_json::json!({"running": live, "failed": failed})))
+}
+
+fn control_error(status: warp::http::StatusCode, error: impl std::fmt::Display) -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&serde_json::json!({"error": error.to_string()})), status).into_response()
+}
+
+/// Latest checkpoint an execution saved, if the inspector can see its checkpoints
+async fn latest_checkpoint(inspector: Option<&StateInspector>, execution_id: uuid::Uuid) -> Option<uuid::Uuid> {
+    let checkpoints = inspector?.list(execution_id).await.ok()?;
+    checkpoints.into_iter().max_by_key(|checkpoint| checkpoint.step).map(|checkpoint| checkpoint.id)
+}
+
+fn live_snapshot(live: &LiveExecution, checkpoint_id: Option<uuid::Uuid>) -> SnapshotRef {
+    SnapshotRef {
+        checkpoint_id,
+        status: serde_json::to_value(live.status).ok().and_then(|status| status.as_str().map(String::from)),
+        step: Some(live.step),
+        node: live.current_node.clone(),
+        ..SnapshotRef::execution(live.execution_id)
+    }
+}
+
+async fn control_execution(
+    execution_id: String,
+    action: String,
+    session: StudioSession,
+    controls: Option<ExecutionControls>,
+    inspector: Option<Arc<StateInspector>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (Some(controls), Ok(execution_id)) = (controls, execution_id.parse()) else {
+        return Err(warp::reject::not_found());
+    };
+    let action = match action.as_str() {
+        "cancel" => StudioAction::Cancel,
+        "pause" => StudioAction::Pause,
+        "resume" => StudioAction::Resume,
+        _ => return Err(warp::reject::not_found()),
+    };
+    let live = match controls.get(execution_id) {
+        Some(live) if session.can_view(&live.graph) => live,
+        _ => return Err(warp::reject::not_found()),
+    };
+    let target = ActionTarget::execution(execution_id, Some(&live.graph));
+    session.authorize(action, &target).await?;
+
+    let checkpoint = latest_checkpoint(inspector.as_deref(), execution_id).await;
+    let before = live_snapshot(&live, checkpoint);
+    let result = match action {
+        StudioAction::Cancel => controls.cancel(execution_id),
+        StudioAction::Pause => controls.pause(execution_id),
+        _ => controls.resume(execution_id),
+    };
+    tracing::info!(execution_id = %execution_id, action = action.name(), user = %session.user.id, "Execution control requested");
+    match result {
+        Ok(live) => {
+            let after = live_snapshot(&live, checkpoint);
+            session.record(action, &target, Some(&before), Ok(Some(&after))).await;
+            Ok(warp::reply::json(&live).into_response())
+        }
+        Err(error) => {
+            session.record(action, &target, Some(&before), Err(&error.to_string())).await;
+            Ok(control_error(warp::http::StatusCode::CONFLICT, error))
+        }
+    }
+}
+
+async fn retry_execution(
+    execution_id: String,
+    session: StudioSession,
+    request: RetryRequest,
+    controls: Option<ExecutionControls>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (Some(controls), Ok(execution_id)) = (controls, execution_id.parse()) else {
+        return Err(warp::reject::not_found());
+    };
+    let failed = match controls.failed_execution(execution_id) {
+        Some(failed) if session.can_view(&failed.graph) => failed,
+        _ => return Err(warp::reject::not_found()),
+    };
+    let action = if request.state.is_some() { StudioAction::EditState } else { StudioAction::Retry };
+    let target = ActionTarget::execution(execution_id, Some(&failed.graph));
+    session.authorize(action, &target).await?;
+
+    let before = SnapshotRef {
+        status: Some("failed".to_string()),
+        node: failed.node_id.clone(),
+        ..SnapshotRef::execution(execution_id)
+    }
+    .with_state(&failed.state);
+    let start_node = request.node_id.clone().or(failed.node_id.clone());
+    let start_state = request.state.clone().unwrap_or(failed.state);
+    tracing::info!(execution_id = %execution_id, user = %session.user.id, edited_state = request.state.is_some(), "Execution retry requested");
+    match controls.retry(execution_id, request) {
+        Ok(retry_id) => {
+            let after = SnapshotRef {
+                status: Some("retrying".to_string()),
+                node: start_node,
+                ..SnapshotRef::execution(retry_id)
+            }
+            .with_state(&start_state);
+            session.record(action, &target, Some(&before), Ok(Some(&after))).await;
+            Ok(warp::reply::json(&serde_json::json!({"execution_id": retry_id})).into_response())
+        }
+        Err(error) => {
+            session.record(action, &target, Some(&before), Err(&error.to_string())).await;
+            Ok(control_error(warp::http::StatusCode::BAD_REQUEST, error))
+        }
+    }
+}
+
+/// Whether the user may see an approval request of `graph`
+fn can_view_approval(session: &StudioSession, graph: Option<&String>) -> bool {
+    match graph {
+        Some(graph) => session.can_view(graph),
+        None => session.user.tenant.is_none(),
+    }
+}
+
+async fn list_approvals(session: StudioSession, approvals: Option<Arc<ApprovalManager>>) -> Result<warp::reply::Response, warp::Rejection> {
+    let Some(approvals) = approvals else {
+        return Err(warp::reject::not_found());
+    };
+    match approvals.list_pending_for_user(&session.user.id) {
+        Ok(mut pending) => {
+            pending.retain(|request| can_view_approval(&session, request.context.graph_context.get(APPROVAL_GRAPH_KEY)));
+            Ok(warp::reply::json(&pending).into_response())
+        }
+        Err(error) => Ok(control_error(warp::http::StatusCode::INTERNAL_SERVER_ERROR, error)),
+    }
+}
+
+/// An approver's answer to an approval request
+#[derive(Debug, Deserialize)]
+struct ApprovalAnswer {
+    decision: ApprovalDecision,
+    comments: Option<String>,
+}
+
+async fn answer_approval(
+    request_id: String,
+    session: StudioSession,
+    answer: ApprovalAnswer,
+    approvals: Option<Arc<ApprovalManager>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let Some(approvals) = approvals else {
+        return Err(warp::reject::not_found());
+    };
+    let pending = approvals.list_pending_for_user(&session.user.id).unwrap_or_default();
+    let request = pending
+        .into_iter()
+        .find(|request| request.request_id == request_id)
+        .filter(|request| can_view_approval(&session, request.context.graph_context.get(APPROVAL_GRAPH_KEY)))
+        .ok_or_else(warp::reject::not_found)?;
+    let target = ActionTarget::approval(&request_id, request.context.graph_context.get(APPROVAL_GRAPH_KEY).map(String::as_str));
+    session.authorize(StudioAction::Approve, &target).await?;
+
+    let before = SnapshotRef {
+        status: Some("pending".to_string()),
+        ..SnapshotRef::default()
+    };
+    let mut response = ApprovalResponse::new(request_id.clone(), session.user.id.clone(), answer.decision);
+    if let Some(comments) = answer.comments {
+        response = response.with_comments(comments);
+    }
+    tracing::info!(request_id = %request_id, user = %session.user.id, decision = ?answer.decision, "Approval answered");
+    match approvals.submit_response(response) {
+        Ok(status) => {
+            let after = SnapshotRef {
+                status: serde_json::to_value(status).ok().and_then(|status| status.as_str().map(String::from)),
+                ..SnapshotRef::default()
+            };
+            session.record(StudioAction::Approve, &target, Some(&before), Ok(Some(&after))).await;
+            Ok(warp::reply::json(&serde_json::json!({"request_id": request_id, "status": status})).into_response())
+        }
+        Err(error) => {
+            session.record(StudioAction::Approve, &target, Some(&before), Err(&error.to_string())).await;
+            Ok(control_error(warp::http::StatusCode::CONFLICT, error))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StateQuery {
+    /// Checkpoint to show instead of the current state
+    checkpoint: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StateDiffQuery {
+    from: uuid::Uuid,
+    to: uuid::Uuid,
+}
+
+/// JSON indented for reading in the inspector
+fn pretty_json<T: Serialize>(value: &T) -> warp::reply::Response {
+    match serde_json::to_string_pretty(value) {
+        Ok(body) => warp::reply::with_header(body, "content-type", "application/json").into_response(),
+        Err(error) => control_error(warp::http::StatusCode::INTERNAL_SERVER_ERROR, error),
+    }
+}
+
+/// The inspector and parsed execution ID, if the user may see the execution
+async fn inspect(
+    execution_id: &str,
+    session: &StudioSession,
+    inspector: Option<Arc<StateInspector>>,
+    tracer: &ExecutionTracer,
+) -> Result<(Arc<StateInspector>, uuid::Uuid), warp::Rejection> {
+    let (Some(inspector), Ok(id)) = (inspector, execution_id.parse()) else {
+        return Err(warp::reject::not_found());
+    };
+    if !can_view_execution(session, tracer, execution_id).await {
+        return Err(warp::reject::not_found());
+    }
+    Ok((inspector, id))
+}
+
+async fn list_checkpoints(
+    execution_id: String,
+    session: StudioSession,
+    inspector: Option<Arc<StateInspector>>,
+    tracer: Arc<ExecutionTracer>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (inspector, id) = inspect(&execution_id, &session, inspector, &tracer).await?;
+    match inspector.list(id).await {
+        Ok(checkpoints) => Ok(pretty_json(&checkpoints)),
+        Err(error) => Ok(control_error(warp::http::StatusCode::BAD_GATEWAY, error)),
+    }
+}
+
+async fn get_execution_state(
+    execution_id: String,
+    session: StudioSession,
+    query: StateQuery,
+    inspector: Option<Arc<StateInspector>>,
+    tracer: Arc<ExecutionTracer>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (inspector, id) = inspect(&execution_id, &session, inspector, &tracer).await?;
+    match inspector.state(id, query.checkpoint).await {
+        Ok(Some(state)) => Ok(pretty_json(&state)),
+        Ok(None) => Err(warp::reject::not_found()),
+        Err(error) => Ok(control_error(warp::http::StatusCode::BAD_GATEWAY, error)),
+    }
+}
+
+async fn diff_execution_state(
+    execution_id: String,
+    session: StudioSession,
+    query: StateDiffQuery,
+    inspector: Option<Arc<StateInspector>>,
+    tracer: Arc<ExecutionTracer>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (inspector, id) = inspect(&execution_id, &session, inspector, &tracer).await?;
+    match inspector.diff(id, query.from, query.to).await {
+        Ok(changes) => Ok(pretty_json(&serde_json::json!({"from": query.from, "to": query.to, "changes": changes}))),
+        Err(error) => Ok(control_error(warp::http::StatusCode::BAD_REQUEST, error)),
+    }
+}
+
+/// Recent runs shown with a graph
+const RECENT_RUNS: usize = 20;
+
+/// The registry and decoded graph name, if the user may see the graph
+fn registered_graph(
+    name: &str,
+    session: &StudioSession,
+    graphs: Option<Arc<GraphRegistry>>,
+) -> Result<(Arc<GraphRegistry>, String), warp::Rejection> {
+    let name = percent_encoding::percent_decode_str(name).decode_utf8_lossy().into_owned();
+    match graphs {
+        Some(graphs) if session.can_view(&name) && graphs.get(&name).is_some() => Ok((graphs, name)),
+        _ => Err(warp::reject::not_found()),
+    }
+}
+
+async fn list_graphs(session: StudioSession, graphs: Option<Arc<GraphRegistry>>) -> Result<impl Reply, warp::Rejection> {
+    let Some(graphs) = graphs else {
+        return Err(warp::reject::not_found());
+    };
+    let gallery: Vec<_> = graphs
+        .list()
+        .into_iter()
+        .filter(|definition| session.can_view(&definition.name))
+        .map(|definition| {
+            let stats = graphs.stats(&definition.name);
+            serde_json::json!({"definition": definition, "stats": stats})
+        })
+        .collect();
+    Ok(warp::reply::json(&gallery))
+}
+
+async fn get_graph(name: String, session: StudioSession, graphs: Option<Arc<GraphRegistry>>) -> Result<impl Reply, warp::Rejection> {
+    let (graphs, name) = registered_graph(&name, &session, graphs)?;
+    let definition = graphs.get(&name).ok_or_else(warp::reject::not_found)?;
+    Ok(warp::reply::json(&serde_json::json!({
+        "mermaid": definition.to_mermaid(),
+        "definition": definition,
+        "versions": graphs.versions(&name).len(),
+        "stats": graphs.stats(&name),
+        "recent_runs": graphs.recent_runs(&name, RECENT_RUNS),
+    })))
+}
+
+async fn get_graph_mermaid(name: String, session: StudioSession, graphs: Option<Arc<GraphRegistry>>) -> Result<impl Reply, warp::Rejection> {
+    let (graphs, name) = registered_graph(&name, &session, graphs)?;
+    let definition = graphs.get(&name).ok_or_else(warp::reject::not_found)?;
+    Ok(warp::reply::with_header(definition.to_mermaid(), "content-type", "text/plain; charset=utf-8"))
+}
+
+async fn get_graph_versions(name: String, session: StudioSession, graphs: Option<Arc<GraphRegistry>>) -> Result<impl Reply, warp::Rejection> {
+    let (graphs, name) = registered_graph(&name, &session, graphs)?;
+    Ok(warp::reply::json(&graphs.versions(&name)))
+}
+
+async fn run_graph(
+    name: String,
+    session: StudioSession,
+    input: serde_json::Value,
+    graphs: Option<Arc<GraphRegistry>>,
+    inspector: Option<Arc<StateInspector>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (graphs, name) = registered_graph(&name, &session, graphs)?;
+    let target = ActionTarget::graph(&name);
+    session.authorize(StudioAction::RunGraph, &target).await?;
+    tracing::info!(graph = %name, user = %session.user.id, "Manual run requested");
+    match graphs.run(&name, input).await {
+        Ok(mut run) => {
+            let after = SnapshotRef {
+                execution_id: run.execution_id.map(|id| id.to_string()),
+                status: Some(if run.succeeded { "succeeded" } else { "failed" }.to_string()),
+                ..SnapshotRef::default()
+            }
+            .with_state(&run.state);
+            session.record(StudioAction::RunGraph, &target, None, Ok(Some(&after))).await;
+            if let Some(inspector) = inspector {
+                inspector.redaction().redact(&mut run.state);
+            }
+            Ok(warp::reply::json(&run).into_response())
+        }
+        Err(error) => {
+            session.record(StudioAction::RunGraph, &target, None, Err(&error.to_string())).await;
+            Ok(control_error(warp::http::StatusCode::BAD_REQUEST, error))
+        }
+    }
+}
+
+// Metrics aggregate every tenant's executions
+async fn get_metrics(session: StudioSession, metrics: Arc<MetricsCollector>) -> Result<impl Reply, warp::Rejection> {
+    session.require_unscoped()?;
     let metrics_data = metrics.get_current_metrics().await;
     Ok(warp::reply::json(&metrics_data))
 }
 
+async fn get_tool_metrics(session: StudioSession, metrics: Arc<MetricsCollector>) -> Result<impl Reply, warp::Rejection> {
+    session.require_unscoped()?;
+    let tool_metrics = metrics.get_all_tool_metrics().await;
+    Ok(warp::reply::json(&tool_metrics))
+}
+
+async fn get_single_tool_metrics(
+    tool_name: String,
+    session: StudioSession,
+    metrics: Arc<MetricsCollector>,
+) -> Result<impl Reply, warp::Rejection> {
+    session.require_unscoped()?;
+    match metrics.get_tool_metrics(&tool_name).await {
+        Some(tool_metrics) => Ok(warp::reply::json(&tool_metrics)),
+        None => Ok(warp::reply::json(&serde_json::json!({"error": "Tool not found"}))),
+    }
+}
+
 // WebSocket handler for real-time events
-async fn handle_websocket(ws: warp::ws::WebSocket, tracer: Arc<ExecutionTracer>) {
+async fn handle_websocket(
+    ws: warp::ws::WebSocket,
+    session: StudioSession,
+    tracer: Arc<ExecutionTracer>,
+    metrics: Arc<MetricsCollector>,
+) {
     let mut event_receiver = tracer.subscribe_events();
+    let mut anomaly_receiver = metrics.subscribe_tool_anomalies();
     let (ws_tx, mut ws_rx) = ws.split();
+    let unscoped = session.user.tenant.is_none();
+    // Whether the user may see each execution's events, decided on its first event
+    let mut visible: HashMap<String, bool> = HashMap::new();
     
-    // Forward events to WebSocket
+    // Forward execution events and tool anomalies to WebSocket
     let forward_events = async {
-        while let Ok(event) = event_receiver.recv().await {
-            let message = serde_json::to_string(&event).unwrap_or_default();
+        loop {
+            let message = tokio::select! {
+                event = event_receiver.recv() => match event {
+                    Ok(event) => {
+                        let allowed = match visible.get(&event.execution_id) {
+                            Some(allowed) => *allowed,
+                            None => {
+                                let allowed = can_view_execution(&session, &tracer, &event.execution_id).await;
+                                visible.insert(event.execution_id.clone(), allowed);
+                                allowed
+                            }
+                        };
+                        if !allowed {
+                            continue;
+                        }
+                        serde_json::to_string(&event).unwrap_or_default()
+                    }
+                    Err(_) => break,
+                },
+                anomaly = anomaly_receiver.recv(), if unscoped => match anomaly {
+                    Ok(anomaly) => serde_json::json!({"type": "tool_anomaly", "data": anomaly}).to_string(),
+                    Err(_) => break,
+                },
+            };
             if ws_tx.send(warp::ws::Message::text(message)).await.is_err() {
                 break;
             }